@@ -39,7 +39,8 @@ from .services.stats import (
 from .services.proxy import (
     process_request_body,
     filter_response_headers,
-    prepare_forward_headers
+    prepare_forward_headers,
+    UPSTREAM_ELAPSED_HEADER,
 )
 
 # 导入编码工具
@@ -317,10 +318,14 @@ async def proxy(path: str, request: Request):
         )
 
         # 发送请求并开启流式模式 (不使用 async with)
+        upstream_start = time.time()
         resp = await http_client.send(req, stream=True)
+        upstream_elapsed = time.time() - upstream_start
 
         # 过滤响应头
         response_headers = filter_response_headers(resp.headers.items())
+        # 回传真实上游耗时（到收到响应头为止，毫秒），供 cc-switch 区分本地一跳与上游耗时
+        response_headers[UPSTREAM_ELAPSED_HEADER] = f"{upstream_elapsed * 1000:.0f}"
 
         # 统计响应时间
         response_time = time.time() - start_time
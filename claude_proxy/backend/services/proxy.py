@@ -29,6 +29,9 @@ def _env_flag(name: str, default: str = 'false') -> bool:
 # 仅在需要时输出 System Replacement 细节，避免污染 claude_proxy.log
 LOG_SYSTEM_REPLACEMENT = _env_flag('CLAUDE_PROXY_LOG_SYSTEM_REPLACEMENT', 'false') or DEBUG_MODE
 
+# 回传给 cc-switch 的真实上游耗时响应头（毫秒）
+UPSTREAM_ELAPSED_HEADER = "x-upstream-elapsed-ms"
+
 
 def filter_request_headers(headers: Iterable[tuple]) -> dict:
     """
@@ -77,6 +80,9 @@ def filter_response_headers(headers: Iterable[tuple]) -> dict:
         # httpx 会自动解压 gzip/deflate，去掉 Content-Encoding 避免客户端重复解压导致 ZlibError
         if lk == "content-encoding":
             continue
+        # 上游耗时由本代理重新计算，不透传上游同名头
+        if lk == UPSTREAM_ELAPSED_HEADER:
+            continue
         out[k] = v
     return out
 
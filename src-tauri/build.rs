@@ -1,3 +1,20 @@
 fn main() {
+    embed_git_hash();
     tauri_build::build()
 }
+
+/// 构建时嵌入 git 短哈希（`GET /version` 与 `csc version` 展示）；非 git 环境下跳过
+fn embed_git_hash() {
+    println!("cargo:rerun-if-changed=../.git/HEAD");
+    println!("cargo:rerun-if-changed=../.git/refs/heads");
+
+    let output = std::process::Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output();
+    if let Ok(output) = output {
+        let hash = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if output.status.success() && !hash.is_empty() {
+            println!("cargo:rustc-env=CC_SWITCH_GIT_HASH={hash}");
+        }
+    }
+}
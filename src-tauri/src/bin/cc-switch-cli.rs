@@ -2,12 +2,43 @@
 //!
 //! 提供终端命令行控制功能，用于无GUI环境
 
-use cc_switch_lib::{AppError, Database, Provider};
-use clap::{Parser, Subcommand};
+use cc_switch_lib::i18n::{char_display_width, tr, Msg};
+use cc_switch_lib::proxy::bandwidth::format_bytes;
+use cc_switch_lib::proxy::env_expand::MissingEnvVar;
+use cc_switch_lib::proxy::ShadowConfig;
+use cc_switch_lib::{AppError, Database, EventKind, EventLogEntry, Provider, DEFAULT_PRIORITY};
+use clap::{Args, Parser, Subcommand};
 use serde_json::json;
 use std::path::PathBuf;
 use std::sync::Arc;
 
+#[path = "cli/audit.rs"]
+mod audit;
+#[path = "cli/compare.rs"]
+mod compare;
+#[path = "cli/completions.rs"]
+mod completions;
+#[path = "cli/daemon.rs"]
+mod daemon;
+#[path = "cli/dashboard.rs"]
+mod dashboard;
+#[path = "cli/doctor.rs"]
+mod doctor;
+#[path = "cli/import_live.rs"]
+mod import_live;
+#[path = "cli/service.rs"]
+mod service;
+#[path = "cli/shadow.rs"]
+mod shadow;
+#[path = "cli/stats.rs"]
+mod stats;
+#[path = "cli/status.rs"]
+mod status;
+#[path = "cli/takeover.rs"]
+mod takeover;
+#[path = "cli/version.rs"]
+mod version;
+
 #[derive(Parser)]
 #[command(name = "csc")]
 #[command(about = "CC-Switch 命令行工具", long_about = None)]
@@ -29,6 +60,21 @@ enum Commands {
     List {
         /// 应用类型 (claude/codex/gemini)
         app_type: Option<String>,
+        /// 显示 base_url 与模型映射
+        #[arg(short, long)]
+        verbose: bool,
+        /// 以 JSON 格式输出
+        #[arg(long)]
+        json: bool,
+        /// 附带请求日志统计：成功率、请求数与平均延迟
+        #[arg(long)]
+        stats: bool,
+        /// 统计窗口，如 1h、24h、7d（配合 --stats）
+        #[arg(long, default_value = "24h")]
+        window: String,
+        /// 排序: success（成功率高在前）、latency（平均延迟低在前）、requests（请求多在前），隐含 --stats
+        #[arg(long)]
+        sort: Option<String>,
     },
     /// 添加供应商 (别名: a)
     #[command(alias = "a")]
@@ -37,18 +83,24 @@ enum Commands {
         app_type: String,
         /// 供应商ID
         id: String,
-        /// 供应商名称
-        #[arg(long)]
-        name: String,
+        /// 供应商名称（使用 --template 时默认为模板名称）
+        #[arg(long, required_unless_present = "template")]
+        name: Option<String>,
         /// API Key
         #[arg(long)]
         api_key: String,
-        /// Base URL
+        /// Base URL（使用 --template 时默认为模板的第一个地址）
+        #[arg(long, required_unless_present = "template")]
+        base_url: Option<String>,
+        /// 供应商模板（如 openrouter、packycode；可在 ~/.cc-switch/templates.json 中覆盖或新增）
         #[arg(long)]
-        base_url: String,
-        /// 优先级层级 (默认: 0)
-        #[arg(long, default_value = "0")]
+        template: Option<String>,
+        /// 优先级层级（默认与未设置层级的供应商相同，见 DEFAULT_PRIORITY）
+        #[arg(long, default_value_t = DEFAULT_PRIORITY)]
         priority: usize,
+        /// supplier 分组名（默认取名称中 '-' 之前的部分）
+        #[arg(long)]
+        supplier: Option<String>,
     },
     /// 删除供应商 (别名: rm)
     #[command(alias = "rm")]
@@ -65,12 +117,33 @@ enum Commands {
         app_type: String,
         /// 供应商ID
         id: String,
+        /// 切换前校验一次（默认请求模型列表，不产生费用），失败时取消切换
+        #[arg(long)]
+        verify: bool,
+        /// 校验时改为发送一次问答请求（max_tokens=1，会产生少量费用）
+        #[arg(long, requires = "verify")]
+        full: bool,
+        /// 校验失败时仍然切换（仅警告）
+        #[arg(long, requires = "verify")]
+        force: bool,
     },
-    /// 取消当前指定的供应商（回到层级轮询） (别名: dis)
+    /// 取消当前指定的供应商（回到层级轮询）；指定 ID 与 --for 时临时禁用该供应商 (别名: dis)
     #[command(alias = "dis")]
     Disable {
         /// 应用类型 (claude/codex/gemini)
         app_type: String,
+        /// 供应商ID（维护禁用，需配合 --for）
+        id: Option<String>,
+        /// 禁用时长，如 30m、4h、1d
+        #[arg(long = "for", value_name = "DURATION", requires = "id")]
+        duration: Option<String>,
+    },
+    /// 提前解除供应商的维护禁用
+    EnableProvider {
+        /// 应用类型 (claude/codex/gemini)
+        app_type: String,
+        /// 供应商ID
+        id: String,
     },
     /// 查看当前供应商 (别名: c)
     #[command(alias = "c")]
@@ -88,6 +161,14 @@ enum Commands {
         /// 优先级层级 (0为最高优先级，数字越大优先级越低)
         priority: usize,
     },
+    /// 规范化优先级层级：为未设置层级的供应商写入默认层级
+    NormalizePriorities {
+        /// 应用类型 (claude/codex/gemini)
+        app_type: String,
+        /// 同时把现有层级按相对顺序压缩为 0..n
+        #[arg(long)]
+        compact: bool,
+    },
     /// 添加供应商到故障转移队列 (别名: qa)
     #[command(alias = "qa")]
     AddToQueue {
@@ -104,6 +185,27 @@ enum Commands {
         /// 供应商ID
         id: String,
     },
+    /// 故障转移队列：按层级查看 / 批量调整顺序 (别名: q)
+    #[command(alias = "q")]
+    Queue {
+        #[command(subcommand)]
+        action: QueueAction,
+    },
+    /// 管理供应商的 supplier 分组
+    Supplier {
+        #[command(subcommand)]
+        action: SupplierAction,
+    },
+    /// 管理 supplier 的首选 URL 列表（测速选 URL 时优先命中）
+    UrlPriority {
+        #[command(subcommand)]
+        action: UrlPriorityAction,
+    },
+    /// 管理自定义模型家族规则（家族锚定时先于内置规则生效）
+    Family {
+        #[command(subcommand)]
+        action: FamilyAction,
+    },
     /// 测试供应商URL延迟 (别名: t)
     #[command(alias = "t")]
     TestLatency {
@@ -115,6 +217,114 @@ enum Commands {
         #[arg(long, default_value = "startup")]
         mode: String,
     },
+    /// 各供应商延迟分位数（p50/p95/p99）与错误率；`stats models` 查看模型映射
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+    Stats {
+        #[command(subcommand)]
+        action: Option<StatsAction>,
+        /// 应用类型 (claude/codex/gemini)
+        #[arg(required = true)]
+        app_type: Option<String>,
+        /// 统计窗口，如 1h、24h、7d
+        #[arg(long, default_value = "24h")]
+        window: String,
+        /// 以 JSON 格式输出
+        #[arg(long)]
+        json: bool,
+    },
+    /// 审计供应商配置：重复 API Key、缺少 base_url / key、跨家族模型映射
+    Audit {
+        /// 应用类型 (claude/codex/gemini)
+        app_type: String,
+        /// 以 JSON 格式输出
+        #[arg(long)]
+        json: bool,
+    },
+    /// 检查配置中的 ${ENV_VAR} 占位符在当前环境下能否解析
+    CheckEnv {
+        /// 应用类型 (claude/codex/gemini)
+        app_type: String,
+    },
+    /// 环境自检：数据库、供应商、Python 代理、端口、上游连通性、熔断状态与 Live 接管（不消耗 token）
+    Doctor {
+        /// 应用类型 (claude/codex/gemini)，缺省检查全部
+        app_type: Option<String>,
+        /// 以 JSON 格式输出
+        #[arg(long)]
+        json: bool,
+    },
+    /// 将捕获的请求固定到指定供应商重放（需代理运行中）
+    Replay {
+        /// 应用类型 (claude/codex/gemini)
+        app_type: String,
+        /// 供应商ID（重放期间忽略故障转移队列/冷却/熔断，固定使用该供应商）
+        #[arg(long)]
+        provider: String,
+        /// 请求日志 ID（需开启 captureBodies<应用>）
+        #[arg(long, conflicts_with = "file", required_unless_present = "file")]
+        from_log: Option<String>,
+        /// 请求 JSON 文件（请求捕获记录或原始请求体）
+        #[arg(long)]
+        file: Option<PathBuf>,
+        /// 输出响应体的前 N 字节
+        #[arg(long, default_value_t = 2048)]
+        bytes: usize,
+    },
+    /// 向多个供应商发送同一提示词，并排对比耗时、状态、token 用量与回复（需代理运行中）
+    Compare {
+        /// 应用类型 (claude/codex/gemini)
+        app_type: String,
+        /// 参与对比的供应商ID，逗号分隔（依次固定到每个供应商，忽略故障转移）
+        #[arg(long, value_delimiter = ',', num_args = 1.., required = true)]
+        providers: Vec<String>,
+        /// 提示词
+        #[arg(long)]
+        prompt: String,
+        /// 模型（Gemini 必填；其它应用默认由代理按默认模型补全）
+        #[arg(long)]
+        model: Option<String>,
+        /// 每个回复显示的前 N 个字符
+        #[arg(long, default_value_t = 200)]
+        chars: usize,
+        /// 以 JSON 格式输出
+        #[arg(long)]
+        json: bool,
+    },
+    /// 实时监控面板（需代理运行中） (别名: d)
+    #[command(alias = "d")]
+    Dashboard,
+    /// 手动强制熔断 / 解除熔断供应商（需代理运行中）
+    Breaker {
+        #[command(subcommand)]
+        action: BreakerAction,
+    },
+    /// 临时暂停 / 恢复故障转移：维护窗口内只使用当前供应商（需代理运行中）
+    Failover {
+        #[command(subcommand)]
+        action: FailoverAction,
+    },
+    /// 影子流量：按比例把非流式请求镜像到评估中的供应商，对比成功率与延迟
+    Shadow {
+        #[command(subcommand)]
+        action: ShadowAction,
+    },
+    /// 查看路由事件日志：熔断切换、冷却、疑似失效、故障转移切换等
+    Events {
+        /// 应用类型 (claude/codex/gemini)
+        app_type: String,
+        /// 只显示指定类型（如 breaker_open、cooldown、failover_switch）
+        #[arg(long)]
+        kind: Option<String>,
+        /// 时间范围，如 30m、1h、7d
+        #[arg(long, default_value = "24h")]
+        since: String,
+        /// 最多显示条数
+        #[arg(long, default_value_t = 100)]
+        limit: usize,
+        /// 以 JSON 格式输出
+        #[arg(long)]
+        json: bool,
+    },
     /// 导出配置到 SQL 文件 (别名: ex)
     #[command(alias = "ex")]
     Export {
@@ -127,57 +337,565 @@ enum Commands {
         /// 导入文件路径
         file_path: String,
     },
+    /// 从 Claude/Codex/Gemini 现有的 live 配置导入供应商（不修改 live 文件，可重复运行）
+    ImportLive {
+        /// 应用类型 (claude/codex/gemini/all)
+        #[arg(long, default_value = "all")]
+        app: String,
+        /// 以 JSON 格式输出
+        #[arg(long)]
+        json: bool,
+    },
+    /// Live 接管：开启/关闭、检查 live 配置是否仍指向代理、查看快照并回滚
+    Takeover {
+        #[command(subcommand)]
+        action: TakeoverAction,
+    },
+    /// 导出/导入代理与应用运行配置（JSON，不含供应商）
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// 数据库维护
+    Db {
+        #[command(subcommand)]
+        action: DbAction,
+    },
+    /// 开机自启：生成并注册 systemd 单元 / launchd plist / Windows 计划任务
+    Service {
+        #[command(subcommand)]
+        action: ServiceAction,
+    },
+    /// 测速历史
+    Benchmark {
+        #[command(subcommand)]
+        action: BenchmarkAction,
+    },
+    /// 查看 CLI 与运行中代理的版本、git hash 与 schema 版本；检测新版本（只检测，不下载）
+    Version {
+        /// 立即查询 GitHub Releases 最新版本
+        #[arg(long)]
+        check: bool,
+        /// 开关代理的每日新版本检测（on/off，默认关闭）
+        #[arg(long, value_name = "on|off")]
+        auto_check: Option<String>,
+        /// 以 JSON 格式输出
+        #[arg(long)]
+        json: bool,
+    },
+    /// 生成 Shell 补全脚本（bash/zsh/fish/powershell/elvish）
+    Completions {
+        /// Shell 类型
+        shell: clap_complete::Shell,
+    },
+    /// 生成 man 页
+    Docs {
+        /// 输出目录（不指定则输出到标准输出）
+        #[arg(long)]
+        out_dir: Option<String>,
+    },
+    /// 补全脚本内部使用：列出供应商ID
+    #[command(name = "__complete-providers", hide = true)]
+    CompleteProviders {
+        /// 应用类型 (claude/codex/gemini)
+        app_type: String,
+    },
 }
 
 #[derive(Subcommand)]
 enum ProxyAction {
-    /// 启动代理服务器(前台模式) (别名: s)
+    /// 启动代理服务器(默认前台模式) (别名: s)
     #[command(alias = "s")]
-    Start,
+    Start(StartArgs),
     /// 停止代理服务器 (别名: x)
     #[command(alias = "x")]
     Stop,
     /// 重启代理服务器 (别名: r)
     #[command(alias = "r")]
-    Restart,
+    Restart(StartArgs),
     /// 查看代理服务器状态 (别名: st)
     #[command(alias = "st")]
-    Status,
+    Status {
+        /// 输出 JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum SupplierAction {
+    /// 为供应商设置显式 supplier（改名后分组不变）
+    Set {
+        /// 应用类型 (claude/codex/gemini)
+        app_type: String,
+        /// 供应商ID
+        id: String,
+        /// supplier 名称
+        supplier: String,
+    },
+    /// 清除显式 supplier（回到按名称推断） (别名: rm)
+    #[command(alias = "rm")]
+    Clear {
+        /// 应用类型 (claude/codex/gemini)
+        app_type: String,
+        /// 供应商ID
+        id: String,
+    },
+    /// 按当前名称为未设置 supplier 的供应商回填显式 supplier
+    Backfill {
+        /// 应用类型 (claude/codex/gemini)，不指定则处理全部
+        app_type: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum QueueAction {
+    /// 按层级分组显示队列（标记当前供应商与健康状态） (别名: ls)
+    #[command(alias = "ls")]
+    Show {
+        /// 应用类型 (claude/codex/gemini)
+        app_type: String,
+    },
+    /// 按给定顺序把层级依次设为 0,1,2,...（单个事务；未列出的供应商保持相对顺序排在其后）
+    Reorder {
+        /// 应用类型 (claude/codex/gemini)
+        app_type: String,
+        /// 供应商ID列表，例如 id1,id2,id3
+        ids: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum UrlPriorityAction {
+    /// 列出所有 supplier 的首选 URL (别名: ls)
+    #[command(alias = "ls")]
+    List,
+    /// 设置 supplier 的首选 URL（按顺序，逗号分隔）
+    Set {
+        /// supplier 名称（显式 supplier，未设置时为供应商名称中 '-' 之前的部分）
+        supplier: String,
+        /// URL 列表，例如 https://a.com,https://b.com
+        urls: String,
+    },
+    /// 清除 supplier 的首选 URL (别名: rm)
+    #[command(alias = "rm")]
+    Clear {
+        /// supplier 名称
+        supplier: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum FamilyAction {
+    /// 列出自定义规则与内置家族 (别名: ls)
+    #[command(alias = "ls")]
+    List,
+    /// 添加规则：模型名包含 pattern 时归入 family（内置家族名或新的自定义家族）
+    Add {
+        /// 模型名模式（子串，不区分大小写），例如 acme-coder
+        pattern: String,
+        /// 家族名，例如 claude；不是内置家族名时声明一个新家族
+        family: String,
+    },
+    /// 删除规则 (别名: rm)
+    #[command(alias = "rm")]
+    Remove {
+        /// 模型名模式
+        pattern: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum TakeoverAction {
+    /// 开启接管：备份 live 配置并改写为代理地址（需代理运行中）
+    Enable {
+        /// 应用类型 (claude/codex/gemini)
+        app_type: String,
+        /// 代理未运行时也强制接管（启动代理前应用请求会失败）
+        #[arg(long)]
+        force: bool,
+    },
+    /// 关闭接管：从接管前的备份恢复 live 配置
+    Disable {
+        /// 应用类型 (claude/codex/gemini)
+        app_type: String,
+    },
+    /// 各应用的接管状态，并检查 live 配置是否仍指向代理
+    Status {
+        /// 应用类型 (claude/codex/gemini)，缺省显示全部
+        app_type: Option<String>,
+        /// 以 JSON 格式输出
+        #[arg(long)]
+        json: bool,
+    },
+    /// 列出 live 配置快照（每次接管写入前自动保存） (别名: ls)
+    #[command(alias = "ls")]
+    List {
+        /// 应用类型 (claude/codex/gemini)
+        app_type: String,
+        /// 以 JSON 格式输出
+        #[arg(long)]
+        json: bool,
+    },
+    /// 显示快照与当前 live 配置的 unified diff（默认最新快照）
+    Diff {
+        /// 应用类型 (claude/codex/gemini)
+        app_type: String,
+        /// 快照 ID（见 takeover list）
+        #[arg(long)]
+        backup_id: Option<i64>,
+    },
+    /// 将 live 配置回滚到快照（默认最新快照）
+    Restore {
+        /// 应用类型 (claude/codex/gemini)
+        app_type: String,
+        /// 快照 ID（见 takeover list）
+        #[arg(long)]
+        backup_id: Option<i64>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// 导出全局/应用代理配置、当前供应商、故障转移队列与首选 URL
+    Export {
+        /// 输出文件路径（不指定则输出到标准输出）
+        #[arg(long)]
+        out: Option<String>,
+    },
+    /// 从 JSON 文件导入配置（代理运行中时自动热更新）
+    Import {
+        /// 配置文件路径
+        file: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum DbAction {
+    /// 完整性检查 + 清理过期请求日志与测速历史 + 压缩（需先停止代理）
+    Maintain {
+        /// 请求日志保留天数（默认读取 requestLogRetentionDays，未设置为 30）
+        #[arg(long)]
+        retention_days: Option<u32>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ServiceAction {
+    /// 安装并启动开机自启（默认系统级，需要 root/管理员权限）
+    Install {
+        /// 用户级（systemd 用户单元 / LaunchAgent / 登录时启动的计划任务）
+        #[arg(long)]
+        user: bool,
+    },
+    /// 停止并移除开机自启
+    Uninstall {
+        /// 用户级
+        #[arg(long)]
+        user: bool,
+    },
+    /// 查看服务定义与注册状态
+    Status {
+        /// 用户级
+        #[arg(long)]
+        user: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum StatsAction {
+    /// 各供应商的请求模型 → 实际模型使用计数
+    Models {
+        /// 应用类型 (claude/codex/gemini)
+        app_type: String,
+        /// 统计窗口，如 1h、24h、7d
+        #[arg(long, default_value = "24h")]
+        window: String,
+        /// 以 JSON 格式输出
+        #[arg(long)]
+        json: bool,
+    },
+    /// 各供应商的请求/响应流量（字节数）
+    Bandwidth {
+        /// 应用类型 (claude/codex/gemini)
+        app_type: String,
+        /// 统计窗口，如 1h、24h、7d
+        #[arg(long, default_value = "24h")]
+        window: String,
+        /// 以 JSON 格式输出
+        #[arg(long)]
+        json: bool,
+    },
+    /// 设置供应商当日（UTC）流量告警阈值，超过时记录 bandwidth_alert 事件
+    BandwidthAlert {
+        /// 应用类型 (claude/codex/gemini)
+        app_type: String,
+        /// 供应商ID
+        id: String,
+        /// 阈值，如 500MB、2GB；off 表示关闭
+        threshold: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum BreakerAction {
+    /// 强制熔断供应商：期间所有请求跳过它，不修改供应商配置
+    Open {
+        /// 应用类型 (claude/codex/gemini)
+        app_type: String,
+        /// 供应商ID
+        id: String,
+        /// 熔断时长，如 30m、2h（到期后按半开探测自动恢复）
+        #[arg(long = "for")]
+        duration: String,
+    },
+    /// 解除熔断（重置为闭合状态）
+    Close {
+        /// 应用类型 (claude/codex/gemini)
+        app_type: String,
+        /// 供应商ID
+        id: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum FailoverAction {
+    /// 暂停故障转移，到期自动恢复（仅影响运行中的代理，重启后失效）
+    Pause {
+        /// 应用类型 (claude/codex/gemini)
+        app_type: String,
+        /// 暂停时长，如 30m、2h
+        #[arg(long = "for")]
+        duration: String,
+    },
+    /// 立即恢复故障转移
+    Resume {
+        /// 应用类型 (claude/codex/gemini)
+        app_type: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ShadowAction {
+    /// 开启影子流量：按比例把非流式请求异步镜像到指定供应商（不影响响应、熔断与故障转移）
+    Set {
+        /// 应用类型 (claude/codex/gemini)
+        app_type: String,
+        /// 影子供应商ID
+        provider_id: String,
+        /// 采样比例（1-100）
+        #[arg(long, value_parser = clap::value_parser!(u8).range(1..=100))]
+        percent: u8,
+    },
+    /// 关闭影子流量
+    Off {
+        /// 应用类型 (claude/codex/gemini)
+        app_type: String,
+    },
+    /// 对比影子请求与主请求的成功率与延迟分位数
+    Stats {
+        /// 应用类型 (claude/codex/gemini)
+        app_type: String,
+        /// 影子供应商ID（默认取当前配置）
+        #[arg(long)]
+        provider: Option<String>,
+        /// 统计窗口，如 1h、24h、7d
+        #[arg(long, default_value = "24h")]
+        window: String,
+        /// 以 JSON 格式输出
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum BenchmarkAction {
+    /// 查看测速历史（不指定 supplier 时列出各 supplier 最近一次结果）
+    History {
+        /// 应用类型 (claude/codex/gemini)
+        app_type: String,
+        /// 只看该 supplier 的测速序列
+        #[arg(long)]
+        supplier: Option<String>,
+        /// 时间范围，如 24h、7d
+        #[arg(long, default_value = "7d")]
+        since: String,
+        /// 以 JSON 格式输出
+        #[arg(long)]
+        json: bool,
+    },
+    /// 对比各 supplier 最近的测速（延迟变化、结果变化、新增/消失的 URL）
+    Diff {
+        /// 应用类型 (claude/codex/gemini)
+        app_type: String,
+        /// 只对比该 supplier
+        #[arg(long)]
+        supplier: Option<String>,
+        /// 与最近第几次测速对比（2 即上一次）
+        #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(u32).range(2..))]
+        runs: u32,
+        /// 以 JSON 格式输出
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Args, Clone, Default)]
+struct StartArgs {
+    /// 后台运行（守护进程模式，日志写入 ~/.cc-switch/logs/rust_proxy.log）
+    #[arg(short, long)]
+    daemon: bool,
+    /// 监听端口（覆盖数据库配置）
+    #[arg(long)]
+    port: Option<u16>,
+    /// 监听地址（覆盖数据库配置）
+    #[arg(long)]
+    address: Option<String>,
+    /// 停止时等待进行中请求完成的最长时间（秒，默认 30）
+    #[arg(long)]
+    drain_timeout: Option<u64>,
 }
 
 #[tokio::main]
 async fn main() {
     let cli = Cli::parse();
 
+    if is_mutating_command(&cli.command) {
+        warn_if_read_only().await;
+    }
+
     let result = match cli.command {
         Commands::Proxy { action } => handle_proxy(action).await,
-        Commands::List { app_type } => handle_list(app_type),
+        Commands::List {
+            app_type,
+            verbose,
+            json,
+            stats,
+            window,
+            sort,
+        } => handle_list(app_type, verbose, json, stats, &window, sort.as_deref()).await,
         Commands::Add {
             app_type,
             id,
             name,
             api_key,
             base_url,
+            template,
             priority,
-        } => handle_add(&app_type, &id, &name, &api_key, &base_url, priority),
+            supplier,
+        } => handle_add(
+            &app_type,
+            &id,
+            name.as_deref(),
+            &api_key,
+            base_url.as_deref(),
+            template.as_deref(),
+            priority,
+            supplier.as_deref(),
+        )
+        .await,
         Commands::Remove { app_type, id } => handle_remove(&app_type, &id),
-        Commands::Enable { app_type, id } => handle_enable(&app_type, &id),
-        Commands::Disable { app_type } => handle_disable(&app_type),
+        Commands::Enable {
+            app_type,
+            id,
+            verify,
+            full,
+            force,
+        } => handle_enable(&app_type, &id, verify, full, force).await,
+        Commands::Disable {
+            app_type, id: None, ..
+        } => handle_disable(&app_type),
+        Commands::Disable {
+            app_type,
+            id: Some(id),
+            duration,
+        } => handle_disable_provider(&app_type, &id, duration.as_deref()),
+        Commands::EnableProvider { app_type, id } => handle_enable_provider(&app_type, &id),
         Commands::Current { app_type } => handle_current(app_type),
         Commands::SetPriority {
             app_type,
             id,
             priority,
         } => handle_set_priority(&app_type, &id, priority),
+        Commands::NormalizePriorities { app_type, compact } => {
+            handle_normalize_priorities(&app_type, compact)
+        }
         Commands::AddToQueue { app_type, id } => handle_add_to_queue(&app_type, &id),
         Commands::RemoveFromQueue { app_type, id } => handle_remove_from_queue(&app_type, &id),
+        Commands::Queue { action } => handle_queue(action).await,
+        Commands::Supplier { action } => handle_supplier(action),
+        Commands::UrlPriority { action } => handle_url_priority(action),
+        Commands::Family { action } => handle_family(action),
         Commands::TestLatency { app_type, id, mode } => handle_test_latency(&app_type, id, &mode).await,
-        Commands::Export { file_path } => handle_export(&file_path),
-        Commands::Import { file_path } => handle_import(&file_path),
+        Commands::Stats {
+            action: Some(action),
+            ..
+        } => handle_stats_action(action),
+        Commands::Stats {
+            action: None,
+            app_type,
+            window,
+            json,
+        } => handle_stats(&app_type.unwrap_or_default(), &window, json).await,
+        Commands::Audit { app_type, json } => handle_audit(&app_type, json).await,
+        Commands::CheckEnv { app_type } => handle_check_env(&app_type),
+        Commands::Doctor { app_type, json } => handle_doctor(app_type.as_deref(), json).await,
+        Commands::Replay {
+            app_type,
+            provider,
+            from_log,
+            file,
+            bytes,
+        } => handle_replay(&app_type, &provider, from_log, file, bytes).await,
+        Commands::Compare {
+            app_type,
+            providers,
+            prompt,
+            model,
+            chars,
+            json,
+        } => {
+            handle_compare(
+                &app_type,
+                &providers,
+                &prompt,
+                model.as_deref(),
+                chars,
+                json,
+            )
+            .await
+        }
+        Commands::Dashboard => handle_dashboard().await,
+        Commands::Breaker { action } => handle_breaker(action).await,
+        Commands::Failover { action } => handle_failover(action).await,
+        Commands::Shadow { action } => handle_shadow(action).await,
+        Commands::Events {
+            app_type,
+            kind,
+            since,
+            limit,
+            json,
+        } => handle_events(&app_type, kind.as_deref(), &since, limit, json),
+        Commands::Export { file_path } => handle_export(&file_path),
+        Commands::Import { file_path } => handle_import(&file_path),
+        Commands::ImportLive { app, json } => handle_import_live(&app, json),
+        Commands::Takeover { action } => handle_takeover(action).await,
+        Commands::Config { action } => handle_config(action).await,
+        Commands::Db { action } => handle_db(action).await,
+        Commands::Service { action } => handle_service(action),
+        Commands::Benchmark { action } => handle_benchmark(action),
+        Commands::Version {
+            check,
+            auto_check,
+            json,
+        } => handle_version(check, auto_check.as_deref(), json).await,
+        Commands::Completions { shell } => handle_completions(shell),
+        Commands::Docs { out_dir } => handle_docs(out_dir),
+        Commands::CompleteProviders { app_type } => handle_complete_providers(&app_type),
     };
 
     if let Err(e) = result {
-        eprintln!("错误: {}", e);
+        eprintln!("{}", tr(Msg::CliError, &[&e]));
         std::process::exit(1);
     }
 }
@@ -188,21 +906,28 @@ async fn main() {
 
 async fn handle_proxy(action: ProxyAction) -> Result<(), AppError> {
     match action {
-        ProxyAction::Start => proxy_start().await,
+        ProxyAction::Start(args) => proxy_start(args).await,
         ProxyAction::Stop => proxy_stop().await,
-        ProxyAction::Restart => {
+        ProxyAction::Restart(args) => {
             let _ = proxy_stop().await; // 忽略停止错误
             tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
-            proxy_start().await
+            proxy_start(args).await
         }
-        ProxyAction::Status => proxy_status().await,
+        ProxyAction::Status { json } => proxy_status(json).await,
     }
 }
 
-async fn proxy_start() -> Result<(), AppError> {
+/// 守护进程启动后等待 PID 文件出现的最长时间
+const DAEMON_START_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(15);
+
+async fn proxy_start(args: StartArgs) -> Result<(), AppError> {
     use cc_switch_lib::proxy::{ProxyConfig, ProxyServer};
     use std::io::Write;
 
+    if args.daemon {
+        return proxy_start_daemon(&args).await;
+    }
+
     // 初始化日志系统
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
         // 统一格式：
@@ -222,8 +947,13 @@ async fn proxy_start() -> Result<(), AppError> {
         })
         .init();
 
-    println!("正在启动代理服务器（前台模式）...");
-    println!("按 Ctrl+C 停止\n");
+    if daemon::is_daemon_child() {
+        println!("{}", tr(Msg::CliStartingDaemon, &[]));
+        println!("{}\n", tr(Msg::CliStopHint, &[]));
+    } else {
+        println!("{}", tr(Msg::CliStartingForeground, &[]));
+        println!("{}\n", tr(Msg::CliCtrlCHint, &[]));
+    }
 
     // 初始化数据库
     let db = Arc::new(Database::init()?);
@@ -249,7 +979,18 @@ async fn proxy_start() -> Result<(), AppError> {
         }
     }
 
-    // 若通过环境变量覆盖了端口/地址，则同步写回 DB，确保后续 `csc` 能发现实际运行端口
+    // 命令行参数优先于环境变量
+    if let Some(address) = args.address.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+        config.listen_address = address.to_string();
+    }
+    if let Some(port) = args.port.filter(|p| *p > 0) {
+        config.listen_port = port;
+    }
+    if let Some(secs) = args.drain_timeout {
+        config.drain_timeout = secs;
+    }
+
+    // 若通过环境变量/命令行覆盖了端口/地址，则同步写回 DB，确保后续 `csc` 能发现实际运行端口
     //（proxy_config 为三行镜像结构，更新一次即可覆盖三行公共字段）
     let _ = db.update_proxy_config(config.clone()).await;
 
@@ -260,7 +1001,7 @@ async fn proxy_start() -> Result<(), AppError> {
     server.start().await
         .map_err(|e| AppError::Message(format!("启动服务器失败: {}", e)))?;
 
-    println!("✓ 代理服务器已启动");
+    println!("{}", tr(Msg::CliProxyStarted, &[]));
     println!("  地址: {}:{}", config.listen_address, config.listen_port);
     println!("  启动时间: {}\n", chrono::Utc::now().to_rfc3339());
     println!("  日志级别: INFO");
@@ -271,26 +1012,127 @@ async fn proxy_start() -> Result<(), AppError> {
     std::fs::write(&pid_file, std::process::id().to_string())
         .map_err(|e| AppError::Message(format!("写入PID文件失败: {}", e)))?;
 
-    // 等待Ctrl+C信号
-    match tokio::signal::ctrl_c().await {
+    // 等待 Ctrl+C / SIGTERM 信号（守护进程由 `csc proxy stop` 发送 SIGTERM）
+    match wait_for_shutdown_signal().await {
         Ok(()) => {
-            println!("\n正在停止...");
+            println!(
+                "\n正在停止（等待进行中请求完成，最长 {} 秒）...",
+                config.drain_timeout
+            );
             server.stop().await
                 .map_err(|e| AppError::Message(format!("停止服务器失败: {}", e)))?;
             std::fs::remove_file(&pid_file).ok();
-            println!("✓ 代理服务器已停止");
+            println!("{}", tr(Msg::CliProxyStopped, &[]));
             Ok(())
         }
         Err(e) => Err(AppError::Message(format!("信号处理失败: {}", e))),
     }
 }
 
+/// 等待停止信号：Ctrl+C，unix 下同时监听 SIGTERM
+async fn wait_for_shutdown_signal() -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut terminate = signal(SignalKind::terminate())?;
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result,
+            _ = terminate.recv() => Ok(()),
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        tokio::signal::ctrl_c().await
+    }
+}
+
+/// 以守护进程方式启动：重新执行 `csc proxy start`（前台逻辑）并脱离终端，
+/// 等待子进程写入 PID 文件后返回
+async fn proxy_start_daemon(args: &StartArgs) -> Result<(), AppError> {
+    let pid_file = get_config_dir().join("proxy.pid");
+    let log_path = get_config_dir().join("logs").join("rust_proxy.log");
+
+    if let Some(pid) = daemon::read_pid_file(&pid_file) {
+        if daemon::is_process_alive(pid) {
+            return Err(AppError::Message(format!(
+                "代理服务器已在运行（PID {}），如需重启请使用 csc proxy restart --daemon",
+                pid
+            )));
+        }
+        std::fs::remove_file(&pid_file).ok();
+    }
+
+    let mut child_args = vec!["proxy".to_string(), "start".to_string()];
+    if let Some(port) = args.port {
+        child_args.push("--port".to_string());
+        child_args.push(port.to_string());
+    }
+    if let Some(address) = &args.address {
+        child_args.push("--address".to_string());
+        child_args.push(address.clone());
+    }
+    if let Some(secs) = args.drain_timeout {
+        child_args.push("--drain-timeout".to_string());
+        child_args.push(secs.to_string());
+    }
+
+    println!("{}", tr(Msg::CliStartingDaemon, &[]));
+    daemon::spawn_detached(&child_args, &log_path)
+        .map_err(|e| AppError::Message(format!("启动守护进程失败: {}", e)))?;
+
+    // 子进程在监听成功后才写入 PID 文件，以此作为启动成功的信号
+    let deadline = std::time::Instant::now() + DAEMON_START_TIMEOUT;
+    while std::time::Instant::now() < deadline {
+        if let Some(pid) = daemon::read_pid_file(&pid_file) {
+            if daemon::is_process_alive(pid) {
+                println!("✓ 代理服务器已在后台启动");
+                println!("  PID: {}", pid);
+                println!("  日志: {}", log_path.display());
+                println!("  停止: csc proxy stop");
+                return Ok(());
+            }
+        }
+        tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+    }
+
+    Err(AppError::Message(format!(
+        "守护进程在 {} 秒内未就绪，请查看日志: {}",
+        DAEMON_START_TIMEOUT.as_secs(),
+        log_path.display()
+    )))
+}
+
+/// `proxy stop` 在排空超时之外额外等待的余量（停止监听、写回状态、删除 PID 文件）
+const PROXY_STOP_MARGIN_SECS: u64 = 10;
+
+/// `proxy stop` 等待进程退出的最长时间：运行中代理的排空超时 + 停止余量
+fn proxy_stop_wait(drain_timeout_secs: Option<u64>) -> std::time::Duration {
+    let drain = drain_timeout_secs
+        .filter(|secs| *secs > 0)
+        .unwrap_or(cc_switch_lib::proxy::inflight::DEFAULT_DRAIN_TIMEOUT_SECS);
+    std::time::Duration::from_secs(drain.saturating_add(PROXY_STOP_MARGIN_SECS))
+}
+
+/// 查询 PID 对应的运行中代理的排空超时（`/admin/status`）；不可达或不是同一进程时为 None
+async fn running_proxy_drain_timeout(pid: i32) -> Option<u64> {
+    let db = open_db_readonly().ok()?;
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(2))
+        .build()
+        .ok()?;
+    let base = find_running_proxy_base(&db, &client).await.ok()?;
+    let admin = status::fetch_admin_status(&client, &base).await.ok()?;
+    (i64::from(admin.pid) == i64::from(pid)).then_some(admin.status.drain_timeout_secs)
+}
+
 async fn proxy_stop() -> Result<(), AppError> {
     // 读取 PID 文件
     let pid_file = get_config_dir().join("proxy.pid");
 
     if !pid_file.exists() {
-        return Err(AppError::Message("代理服务器未运行（PID文件不存在）".to_string()));
+        return Err(AppError::Message(tr(Msg::CliProxyNotRunning, &[])));
     }
 
     let pid_str = std::fs::read_to_string(&pid_file)
@@ -298,6 +1140,9 @@ async fn proxy_stop() -> Result<(), AppError> {
     let pid: i32 = pid_str.trim().parse()
         .map_err(|e| AppError::Message(format!("解析PID失败: {}", e)))?;
 
+    // 按运行中代理的 --drain-timeout 决定等待时长（需在发送信号前查询）
+    let stop_wait = proxy_stop_wait(running_proxy_drain_timeout(pid).await);
+
     // 发送 SIGTERM 信号
     #[cfg(unix)]
     {
@@ -317,183 +1162,579 @@ async fn proxy_stop() -> Result<(), AppError> {
             .map_err(|e| AppError::Message(format!("停止进程失败: {}", e)))?;
     }
 
+    // 等待进程退出（收到 SIGTERM 后会先排空进行中的请求，再停止服务并删除 PID 文件）
+    let deadline = std::time::Instant::now() + stop_wait;
+    while daemon::is_process_alive(pid) && std::time::Instant::now() < deadline {
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    }
+    if daemon::is_process_alive(pid) {
+        println!(
+            "⚠ 进程 {} 在 {} 秒内未退出（可能仍在排空请求）",
+            pid,
+            stop_wait.as_secs()
+        );
+        return Ok(());
+    }
+
     // 删除 PID 文件
     std::fs::remove_file(&pid_file).ok();
 
-    println!("✓ 代理服务器已停止");
+    println!("{}", tr(Msg::CliProxyStopped, &[]));
     Ok(())
 }
 
-async fn proxy_status() -> Result<(), AppError> {
+/// 查询代理状态：优先请求运行中代理的 `/admin/status`，不可达时回退到 PID 检查
+async fn proxy_status(json: bool) -> Result<(), AppError> {
     let pid_file = get_config_dir().join("proxy.pid");
+    let pid = daemon::read_pid_file(&pid_file).map(|pid| (pid, daemon::is_process_alive(pid)));
 
-    if !pid_file.exists() {
-        println!("代理服务器状态: 未运行");
-        return Ok(());
+    let db = open_db_readonly()?;
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(2))
+        .build()
+        .map_err(|e| AppError::Message(format!("创建HTTP客户端失败: {e}")))?;
+    let base = find_running_proxy_base(&db, &client).await.ok();
+
+    let state = status::detect(&client, base.as_deref(), pid).await;
+
+    // PID 文件指向的进程已不存在：清理失效的 PID 文件
+    if let status::ProxyRunState::Stopped { stale_pid: Some(_) } = state {
+        std::fs::remove_file(&pid_file).ok();
     }
 
-    let pid_str = std::fs::read_to_string(&pid_file)
-        .map_err(|e| AppError::Message(format!("读取PID文件失败: {}", e)))?;
-    let pid: i32 = pid_str.trim().parse()
-        .map_err(|e| AppError::Message(format!("解析PID失败: {}", e)))?;
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&state.to_json())
+                .map_err(|e| AppError::Message(format!("序列化失败: {e}")))?
+        );
+    } else {
+        for line in state.format_lines() {
+            println!("{line}");
+        }
+    }
 
-    // 检查进程是否存在
-    #[cfg(unix)]
-    {
-        use nix::sys::signal::kill;
-        use nix::unistd::Pid;
+    Ok(())
+}
 
-        match kill(Pid::from_raw(pid), None) {
-            Ok(()) => {
-                println!("代理服务器状态: 运行中");
-                println!("  PID: {}", pid);
+// ============================================================================
+// 供应商管理
+// ============================================================================
+
+/// 最近错误在列表中的最大显示宽度
+const LIST_LAST_ERROR_WIDTH: usize = 60;
+
+/// 各应用用于模型映射的配置键
+const CLAUDE_MODEL_ENV_KEYS: &[&str] = &[
+    "ANTHROPIC_MODEL",
+    "ANTHROPIC_DEFAULT_HAIKU_MODEL",
+    "ANTHROPIC_DEFAULT_SONNET_MODEL",
+    "ANTHROPIC_DEFAULT_OPUS_MODEL",
+    "ANTHROPIC_REASONING_MODEL",
+    "ANTHROPIC_SMALL_FAST_MODEL",
+];
+const GEMINI_MODEL_ENV_KEYS: &[&str] = &["GEMINI_MODEL"];
+
+/// `list` 的单行视图（文本与 JSON 输出共用）
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProviderListRow {
+    id: String,
+    name: String,
+    priority: Option<usize>,
+    in_failover_queue: bool,
+    is_current: bool,
+    is_healthy: bool,
+    consecutive_failures: u32,
+    last_error: Option<String>,
+    notes: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    base_url: Option<String>,
+    #[serde(skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+    model_mappings: std::collections::BTreeMap<String, String>,
+    /// 维护禁用截止时间（Unix 秒）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    disabled_until: Option<i64>,
+    /// 余额耗尽时的上游提示（请求成功或手动重置后清除）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    depleted: Option<String>,
+    /// 统计窗口内的成功率（`list --stats`）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stats: Option<cc_switch_lib::ProviderSuccessRate>,
+}
+
+fn build_list_row(
+    app_type: &str,
+    provider: &Provider,
+    health: Option<&cc_switch_lib::proxy::ProviderHealth>,
+    is_current: bool,
+    verbose: bool,
+) -> ProviderListRow {
+    let mut model_mappings = std::collections::BTreeMap::new();
+    let mut base_url = None;
+
+    if verbose {
+        let env = provider.settings_config.get("env");
+        let env_keys: &[&str] = match app_type {
+            "claude" => CLAUDE_MODEL_ENV_KEYS,
+            "gemini" => GEMINI_MODEL_ENV_KEYS,
+            _ => &[],
+        };
+        for key in env_keys {
+            if let Some(v) = env.and_then(|e| e.get(*key)).and_then(|v| v.as_str()) {
+                if !v.trim().is_empty() {
+                    model_mappings.insert(key.to_string(), v.to_string());
+                }
             }
-            Err(_) => {
-                println!("代理服务器状态: 未运行（PID {} 不存在）", pid);
-                std::fs::remove_file(&pid_file).ok();
+        }
+        // Codex 的模型写在根级 model 字段
+        if app_type == "codex" {
+            if let Some(v) = provider.settings_config.get("model").and_then(|v| v.as_str()) {
+                model_mappings.insert("model".to_string(), v.to_string());
             }
         }
+
+        if let Ok(t) = app_type.parse::<cc_switch_lib::AppType>() {
+            use cc_switch_lib::proxy::providers::{get_adapter, ProviderAdapter};
+            base_url = get_adapter(&t).extract_base_url(provider).ok();
+        }
     }
 
-    #[cfg(windows)]
-    {
-        use std::process::Command;
-        let output = Command::new("tasklist")
-            .args(&["/FI", &format!("PID eq {}", pid)])
-            .output()
-            .map_err(|e| AppError::Message(format!("检查进程失败: {}", e)))?;
+    ProviderListRow {
+        id: provider.id.clone(),
+        name: provider.name.clone(),
+        priority: provider.sort_index,
+        in_failover_queue: provider.in_failover_queue,
+        is_current,
+        is_healthy: health.map(|h| h.is_healthy).unwrap_or(true),
+        consecutive_failures: health.map(|h| h.consecutive_failures).unwrap_or(0),
+        last_error: health
+            .and_then(|h| h.last_error.as_deref())
+            .map(|e| truncate_display(e.trim(), LIST_LAST_ERROR_WIDTH)),
+        notes: provider.notes.clone().filter(|n| !n.trim().is_empty()),
+        base_url,
+        model_mappings,
+        disabled_until: None,
+        depleted: health.filter(|h| h.depleted_at.is_some()).map(|h| {
+            let message = h.depleted_message.as_deref().unwrap_or_default().trim();
+            truncate_display(message, LIST_LAST_ERROR_WIDTH)
+        }),
+        stats: None,
+    }
+}
 
-        let output_str = String::from_utf8_lossy(&output.stdout);
-        if output_str.contains(&pid.to_string()) {
-            println!("代理服务器状态: 运行中");
-            println!("  PID: {}", pid);
-        } else {
-            println!("代理服务器状态: 未运行（PID {} 不存在）", pid);
-            std::fs::remove_file(&pid_file).ok();
+fn format_list_row(row: &ProviderListRow) -> Vec<String> {
+    let marker = if row.is_current { "  [当前]" } else { "" };
+    let in_queue = if row.in_failover_queue { " [队列]" } else { "" };
+    let priority = row.priority.map(|p| format!(" [层级:{}]", p)).unwrap_or_default();
+    let status = format_row_status(row);
+
+    let mut lines = vec![format!(
+        "  {} - {}{}{}{}{}",
+        row.id, row.name, priority, in_queue, status, marker
+    )];
+    if let Some(notes) = row.notes.as_deref() {
+        lines.push(format!("    备注: {}", notes));
+    }
+    if let Some(err) = row.last_error.as_deref() {
+        lines.push(format!("    最近错误: {}", err));
+    }
+    if let Some(message) = row.depleted.as_deref().filter(|m| !m.is_empty()) {
+        lines.push(format!("    余额耗尽: {}", message));
+    }
+    if let Some(stats) = row.stats.as_ref() {
+        // 无流量显示 "-"，避免误读为 100%
+        let rate = stats
+            .success_rate
+            .map(|r| format!("{:.1}%", r * 100.0))
+            .unwrap_or_else(|| "-".to_string());
+        let latency = stats
+            .avg_latency_ms
+            .map(|ms| format!("{ms}ms"))
+            .unwrap_or_else(|| "-".to_string());
+        lines.push(format!(
+            "    成功率 {}  请求 {}  平均延迟 {}",
+            rate, stats.requests, latency
+        ));
+    }
+    if let Some(url) = row.base_url.as_deref() {
+        lines.push(format!("    base_url: {}", url));
+    }
+    for (key, value) in &row.model_mappings {
+        lines.push(format!("    {} = {}", key, value));
+    }
+    lines
+}
+
+/// `list --sort` 的排序方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ListSort {
+    Success,
+    Latency,
+    Requests,
+}
+
+fn parse_list_sort(s: &str) -> Result<ListSort, AppError> {
+    match s.trim().to_lowercase().as_str() {
+        "success" => Ok(ListSort::Success),
+        "latency" => Ok(ListSort::Latency),
+        "requests" => Ok(ListSort::Requests),
+        _ => Err(AppError::InvalidInput(format!(
+            "无效的排序方式: {s}，支持: success, latency, requests"
+        ))),
+    }
+}
+
+/// 按统计排序（稳定排序，相同时保持原有顺序）；无流量的供应商排在最后
+fn sort_list_rows(rows: &mut [ProviderListRow], sort: ListSort) {
+    let requests = |row: &ProviderListRow| row.stats.as_ref().map_or(0, |s| s.requests);
+    rows.sort_by(|a, b| match sort {
+        ListSort::Success => {
+            let rate = |row: &ProviderListRow| row.stats.as_ref().and_then(|s| s.success_rate);
+            match (rate(a), rate(b)) {
+                (Some(x), Some(y)) => y.total_cmp(&x).then(requests(b).cmp(&requests(a))),
+                (x, y) => y.is_some().cmp(&x.is_some()),
+            }
+        }
+        ListSort::Latency => {
+            let latency = |row: &ProviderListRow| row.stats.as_ref().and_then(|s| s.avg_latency_ms);
+            match (latency(a), latency(b)) {
+                (Some(x), Some(y)) => x.cmp(&y),
+                (x, y) => y.is_some().cmp(&x.is_some()),
+            }
         }
+        ListSort::Requests => requests(b).cmp(&requests(a)),
+    });
+}
+
+/// 健康、维护禁用与余额耗尽标记（`list` 与 `queue show` 共用）
+fn format_row_status(row: &ProviderListRow) -> String {
+    let health = if !row.is_healthy {
+        format!(" [异常 连续失败:{}]", row.consecutive_failures)
+    } else if row.consecutive_failures > 0 {
+        format!(" [连续失败:{}]", row.consecutive_failures)
+    } else {
+        String::new()
+    };
+
+    let disabled = row
+        .disabled_until
+        .map(|until| {
+            let remaining = until - chrono::Utc::now().timestamp();
+            format!(" [禁用 剩余{}]", format_remaining(remaining))
+        })
+        .unwrap_or_default();
+
+    let depleted = if row.depleted.is_some() { " [余额耗尽]" } else { "" };
+
+    format!("{health}{disabled}{depleted}")
+}
+
+/// `queue show`：按层级分组（未设置层级的供应商归入 DEFAULT_PRIORITY）
+fn format_queue_tiers(rows: &[ProviderListRow]) -> Vec<String> {
+    if rows.is_empty() {
+        return vec!["  故障转移队列为空".to_string()];
+    }
+    let mut tiers: std::collections::BTreeMap<usize, Vec<&ProviderListRow>> =
+        std::collections::BTreeMap::new();
+    for row in rows {
+        tiers
+            .entry(row.priority.unwrap_or(DEFAULT_PRIORITY))
+            .or_default()
+            .push(row);
     }
 
-    Ok(())
+    let mut lines = Vec::new();
+    for (level, rows) in tiers {
+        lines.push(format!("  层级 {level}:"));
+        for row in rows {
+            let marker = if row.is_current { "  [当前]" } else { "" };
+            lines.push(format!(
+                "    {} - {}{}{}",
+                row.id,
+                row.name,
+                format_row_status(row),
+                marker
+            ));
+        }
+    }
+    lines
 }
 
-// ============================================================================
-// 供应商管理
-// ============================================================================
+/// 按显示宽度截断（超出时以 … 结尾，总宽度不超过 max_width）
+fn truncate_display(s: &str, max_width: usize) -> String {
+    let s = s.replace(['\n', '\r'], " ");
+    let total: usize = s.chars().map(char_display_width).sum();
+    if total <= max_width {
+        return s;
+    }
+    let mut out = String::new();
+    let mut width = 0;
+    for c in s.chars() {
+        let w = char_display_width(c);
+        if width + w + 1 > max_width {
+            break;
+        }
+        out.push(c);
+        width += w;
+    }
+    out.push('…');
+    out
+}
 
-fn handle_list(app_type: Option<String>) -> Result<(), AppError> {
-    let db = Arc::new(Database::init()?);
+async fn handle_list(
+    app_type: Option<String>,
+    verbose: bool,
+    json: bool,
+    stats: bool,
+    window: &str,
+    sort: Option<&str>,
+) -> Result<(), AppError> {
+    let db = Arc::new(open_db_readonly()?);
+    let sort = sort.map(parse_list_sort).transpose()?;
+    let window_secs = if stats || sort.is_some() {
+        Some(parse_duration_secs(window)?)
+    } else {
+        None
+    };
 
     let app_types = match app_type {
         Some(t) => vec![parse_app_type(&t)?],
         None => vec!["claude".to_string(), "codex".to_string(), "gemini".to_string()],
     };
 
-    for app_type_str in app_types {
-        println!("\n=== {} 供应商 ===", app_type_str);
+    let mut json_out = serde_json::Map::new();
 
+    for app_type_str in app_types {
         let providers = db.get_all_providers(&app_type_str)?;
         let current_id = db.get_current_provider(&app_type_str)?;
+        let health = db.get_all_provider_health(&app_type_str).await?;
+        // 只读：查询时过滤已到期的禁用，到期记录由代理的后台清理任务清除
+        let disabled = db.get_disabled_providers(&app_type_str, chrono::Utc::now().timestamp())?;
+        let success_rates = window_secs
+            .map(|secs| db.get_provider_success_rates(&app_type_str, secs))
+            .transpose()?;
+
+        let mut rows: Vec<ProviderListRow> = providers
+            .values()
+            .map(|provider| {
+                let is_current = current_id.as_ref().map(|id| id == &provider.id).unwrap_or(false);
+                let mut row = build_list_row(
+                    &app_type_str,
+                    provider,
+                    health.get(&provider.id),
+                    is_current,
+                    verbose,
+                );
+                row.disabled_until = disabled.get(&provider.id).copied();
+                row.stats = success_rates
+                    .as_ref()
+                    .map(|rates| rates.get(&provider.id).cloned().unwrap_or_default());
+                row
+            })
+            .collect();
+        if let Some(sort) = sort {
+            sort_list_rows(&mut rows, sort);
+        }
 
-        if providers.is_empty() {
-            println!("  (无供应商)");
+        if json {
+            json_out.insert(
+                app_type_str.clone(),
+                serde_json::to_value(&rows).unwrap_or_default(),
+            );
             continue;
         }
 
-        for (_, provider) in providers {
-            let is_current = current_id.as_ref().map(|id| id == &provider.id).unwrap_or(false);
-            let marker = if is_current { "  [当前]" } else { "" };
-            let in_queue = if provider.in_failover_queue { " [队列]" } else { "" };
-            let priority = provider.sort_index.map(|p| format!(" [层级:{}]", p)).unwrap_or_default();
+        if window_secs.is_some() {
+            println!("\n=== {} 供应商（统计窗口 {}） ===", app_type_str, window);
+        } else {
+            println!("\n=== {} 供应商 ===", app_type_str);
+        }
 
-            println!("  {} - {}{}{}{}",
-                provider.id,
-                provider.name,
-                priority,
-                in_queue,
-                marker
-            );
+        if rows.is_empty() {
+            println!("  {}", tr(Msg::CliNoProviders, &[]));
+            continue;
+        }
+
+        for row in &rows {
+            for line in format_list_row(row) {
+                println!("{}", line);
+            }
 
             // Debug: 输出settingsConfig
             if std::env::var("DEBUG_CONFIG").is_ok() {
-                println!("    settingsConfig: {}", serde_json::to_string_pretty(&provider.settings_config).unwrap_or_default());
+                if let Some(provider) = providers.get(&row.id) {
+                    println!(
+                        "    settingsConfig: {}",
+                        serde_json::to_string_pretty(&provider.settings_config).unwrap_or_default()
+                    );
+                }
             }
         }
     }
 
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::Value::Object(json_out)).unwrap_or_default()
+        );
+    }
+
     Ok(())
 }
 
-fn handle_add(
+async fn handle_add(
     app_type: &str,
     id: &str,
-    name: &str,
+    name: Option<&str>,
     api_key: &str,
-    base_url: &str,
+    base_url: Option<&str>,
+    template: Option<&str>,
     priority: usize,
+    supplier: Option<&str>,
 ) -> Result<(), AppError> {
+    use cc_switch_lib::proxy::ProviderRouter;
+
     let db = Arc::new(Database::init()?);
     let app_type_str = parse_app_type(app_type)?;
 
-    // 构建 settings_config - 根据app_type使用不同的字段名
-    let settings_config = match app_type {
-        "codex" => json!({
-            "env": {
-                "OPENAI_API_KEY": api_key,
-            },
-            "base_url": base_url,
-        }),
-        "gemini" => json!({
-            "apiKey": api_key,
-            "baseUrl": base_url,
-        }),
-        _ => json!({
-            "env": {
-                "ANTHROPIC_API_KEY": api_key,
-                "ANTHROPIC_BASE_URL": base_url,
-            }
-        }),
-    };
+    let mut provider = if let Some(template_id) = template {
+        let template = cc_switch_lib::find_provider_template(template_id)?;
+        template.build_provider(&app_type_str, id, name, api_key, base_url)?
+    } else {
+        let (Some(name), Some(base_url)) = (name, base_url) else {
+            return Err(AppError::InvalidInput(
+                "未使用 --template 时需要指定 --name 与 --base-url".to_string(),
+            ));
+        };
 
-    let provider = Provider {
-        id: id.to_string(),
-        name: name.to_string(),
-        settings_config,
-        website_url: None,
-        category: None,
-        created_at: None,
-        sort_index: Some(priority),
-        notes: None,
-        meta: None,
-        icon: None,
-        icon_color: None,
-        in_failover_queue: false,
+        // 构建 settings_config - 根据app_type使用不同的字段名
+        let settings_config = match app_type {
+            "codex" => json!({
+                "env": {
+                    "OPENAI_API_KEY": api_key,
+                },
+                "base_url": base_url,
+            }),
+            "gemini" => json!({
+                "apiKey": api_key,
+                "baseUrl": base_url,
+            }),
+            _ => json!({
+                "env": {
+                    "ANTHROPIC_API_KEY": api_key,
+                    "ANTHROPIC_BASE_URL": base_url,
+                }
+            }),
+        };
+        Provider::with_id(id.to_string(), name.to_string(), settings_config, None)
     };
+    provider.sort_index = Some(priority);
+    provider.set_supplier(supplier);
+    if let Some(url) = ProviderRouter::extract_base_url(&provider, &app_type_str) {
+        ProviderRouter::validate_base_url(&url)
+            .map_err(|e| AppError::InvalidInput(format!("base_url 无效: {e}")))?;
+    }
 
     db.save_provider(&app_type_str, &provider)?;
-    println!("✓ 已添加供应商: {} ({})", name, id);
+    println!("{}", tr(Msg::CliProviderAdded, &[&provider.name, &id]));
+    if let Some(template_id) = template {
+        println!("  模板: {}", template_id);
+    }
     println!("  优先级层级: {}", priority);
+    println!("  supplier: {}", provider.supplier());
+    warm_up_models_after_save(&db, &app_type_str, id).await;
 
     Ok(())
 }
 
+/// 保存供应商后预热模型列表（顺带校验 key）：代理运行中时在代理进程内预热，缓存对后续请求生效；
+/// 否则本地拉取一次。失败只提示并记录事件，不影响保存结果
+async fn warm_up_models_after_save(db: &Arc<Database>, app_type: &str, provider_id: &str) {
+    use cc_switch_lib::proxy::model_warmup::{ModelWarmup, WarmupOutcome};
+
+    let outcome = match request_proxy_model_warmup(db, app_type, provider_id).await {
+        Some(outcome) => outcome,
+        None => {
+            ModelWarmup::standalone(db.clone())
+                .await
+                .run(app_type, provider_id)
+                .await
+        }
+    };
+    match outcome {
+        WarmupOutcome::Warmed { models } => println!("✓ 模型列表已预热（{models} 个模型）"),
+        WarmupOutcome::Failed { error } => {
+            println!("⚠ 模型列表预热失败（key 或 base_url 可能有误）: {error}")
+        }
+        WarmupOutcome::Skipped { .. } => {}
+    }
+}
+
+/// 请求运行中的代理预热模型列表；代理未运行或请求失败时返回 None
+async fn request_proxy_model_warmup(
+    db: &Database,
+    app_type: &str,
+    provider_id: &str,
+) -> Option<cc_switch_lib::proxy::model_warmup::WarmupOutcome> {
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(2))
+        .build()
+        .ok()?;
+    let base = find_running_proxy_base(db, &client).await.ok()?;
+    let resp = client
+        .post(format!("{base}/__cc_switch/models/warmup"))
+        .timeout(std::time::Duration::from_secs(30))
+        .json(&json!({ "app_type": app_type, "provider_id": provider_id }))
+        .send()
+        .await
+        .ok()?;
+    if !resp.status().is_success() {
+        return None;
+    }
+    resp.json().await.ok()
+}
+
 fn handle_remove(app_type: &str, id: &str) -> Result<(), AppError> {
     let db = Arc::new(Database::init()?);
     let app_type_str = parse_app_type(app_type)?;
 
     db.delete_provider(&app_type_str, id)?;
-    println!("✓ 已删除供应商: {}", id);
+    println!("{}", tr(Msg::CliProviderDeleted, &[&id]));
 
     Ok(())
 }
 
-fn handle_enable(app_type: &str, id: &str) -> Result<(), AppError> {
+async fn handle_enable(
+    app_type: &str,
+    id: &str,
+    verify: bool,
+    full: bool,
+    force: bool,
+) -> Result<(), AppError> {
     let db = Arc::new(Database::init()?);
     let app_type_str = parse_app_type(app_type)?;
 
+    if verify {
+        let provider = db
+            .get_provider_by_id(id, &app_type_str)?
+            .ok_or_else(|| AppError::InvalidInput(format!("供应商 {id} 不存在")))?;
+        let router = cc_switch_lib::proxy::ProviderRouter::new(db.clone());
+        let verification = router.verify_provider(&provider, &app_type_str, full).await;
+        match verification.check(force)? {
+            Some(warning) => println!("⚠ {}", warning),
+            None => println!("✓ 校验通过: {}", verification.describe()),
+        }
+    }
+
     db.set_current_provider(&app_type_str, id)?;
-    println!("✓ 已启用供应商: {}", id);
+    // 手动切换：取消待切回的首选供应商
+    if let Ok(app) = app_type_str.parse::<cc_switch_lib::AppType>() {
+        cc_switch_lib::set_restore_primary(&app, None)?;
+    }
+    println!("{}", tr(Msg::CliProviderEnabled, &[&id]));
     println!("\n提示: 该供应商将被优先使用（优先于故障转移队列）");
-    println!("      如需应用更改，请重启代理服务器: csc p r");
+    println!("      {}", tr(Msg::CliRestartHint, &[]));
 
     Ok(())
 }
@@ -505,13 +1746,60 @@ fn handle_disable(app_type: &str) -> Result<(), AppError> {
     db.clear_current_provider(&app_type_str)?;
     println!("✓ 已取消 {} 的当前指定供应商", app_type_str);
     println!("\n提示: 系统将自动使用故障转移队列中最优先层级的供应商");
-    println!("      如需应用更改，请重启代理服务器: csc p r");
+    println!("      {}", tr(Msg::CliRestartHint, &[]));
 
     Ok(())
 }
 
-fn handle_current(app_type: Option<String>) -> Result<(), AppError> {
+fn handle_disable_provider(
+    app_type: &str,
+    id: &str,
+    duration: Option<&str>,
+) -> Result<(), AppError> {
+    let Some(duration) = duration else {
+        return Err(AppError::InvalidInput(
+            "维护禁用需要指定时长，如: disable claude p1 --for 4h".to_string(),
+        ));
+    };
+    let secs = parse_duration_secs(duration)?;
+
+    let db = Arc::new(Database::init()?);
+    let app_type_str = parse_app_type(app_type)?;
+
+    let until = chrono::Utc::now().timestamp() + secs;
+    db.set_provider_disabled_until(&app_type_str, id, Some(until))?;
+
+    let until_local = chrono::DateTime::from_timestamp(until, 0)
+        .map(|t| {
+            t.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        })
+        .unwrap_or_default();
+    println!(
+        "✓ 已禁用 {} 的供应商 {}，{} 后自动恢复（{}）",
+        app_type_str,
+        id,
+        format_remaining(secs),
+        until_local
+    );
+    println!("\n提示: 禁用期间故障转移会跳过该供应商，可用 enable-provider 提前恢复");
+
+    Ok(())
+}
+
+fn handle_enable_provider(app_type: &str, id: &str) -> Result<(), AppError> {
     let db = Arc::new(Database::init()?);
+    let app_type_str = parse_app_type(app_type)?;
+
+    db.set_provider_disabled_until(&app_type_str, id, None)?;
+    println!("✓ 已解除 {} 的供应商 {} 的维护禁用", app_type_str, id);
+
+    Ok(())
+}
+
+fn handle_current(app_type: Option<String>) -> Result<(), AppError> {
+    let db = Arc::new(open_db_readonly()?);
 
     let app_types = match app_type {
         Some(t) => vec![parse_app_type(&t)?],
@@ -551,7 +1839,7 @@ fn handle_set_priority(app_type: &str, id: &str, priority: usize) -> Result<(),
 
     // 获取现有供应商
     let mut provider = db.get_provider_by_id(id, &app_type_str)?
-        .ok_or_else(|| AppError::Message(format!("供应商不存在: {}", id)))?;
+        .ok_or_else(|| AppError::Message(tr(Msg::CliProviderNotFound, &[&id])))?;
 
     // 更新优先级
     provider.sort_index = Some(priority);
@@ -564,6 +1852,129 @@ fn handle_set_priority(app_type: &str, id: &str, priority: usize) -> Result<(),
     Ok(())
 }
 
+fn handle_normalize_priorities(app_type: &str, compact: bool) -> Result<(), AppError> {
+    let db = Database::init()?;
+    let app_type_str = parse_app_type(app_type)?;
+
+    let changes = db.normalize_priorities(&app_type_str, compact)?;
+    for line in format_priority_changes(&changes) {
+        println!("{line}");
+    }
+    Ok(())
+}
+
+fn format_priority_changes(changes: &[cc_switch_lib::PriorityChange]) -> Vec<String> {
+    if changes.is_empty() {
+        return vec!["✓ 优先级层级无需调整".to_string()];
+    }
+    let mut lines = vec![format!("✓ 已调整 {} 个供应商的优先级层级:", changes.len())];
+    for c in changes {
+        let from = c
+            .from
+            .map(|p| p.to_string())
+            .unwrap_or_else(|| "-".to_string());
+        lines.push(format!("  {} - {}  {} → {}", c.id, c.name, from, c.to));
+    }
+    lines
+}
+
+async fn handle_queue(action: QueueAction) -> Result<(), AppError> {
+    let db = Database::init()?;
+
+    match action {
+        QueueAction::Show { app_type } => {
+            let app_type_str = parse_app_type(&app_type)?;
+            let providers = db.get_failover_providers(&app_type_str)?;
+            let current_id = db.get_current_provider(&app_type_str)?;
+            let health = db.get_all_provider_health(&app_type_str).await?;
+            let disabled =
+                db.get_disabled_providers(&app_type_str, chrono::Utc::now().timestamp())?;
+
+            let rows: Vec<ProviderListRow> = providers
+                .iter()
+                .map(|provider| {
+                    let is_current = current_id.as_deref() == Some(provider.id.as_str());
+                    let mut row = build_list_row(
+                        &app_type_str,
+                        provider,
+                        health.get(&provider.id),
+                        is_current,
+                        false,
+                    );
+                    row.disabled_until = disabled.get(&provider.id).copied();
+                    row
+                })
+                .collect();
+
+            println!("\n=== {} 故障转移队列 ===", app_type_str);
+            for line in format_queue_tiers(&rows) {
+                println!("{line}");
+            }
+        }
+        QueueAction::Reorder { app_type, ids } => {
+            let app_type_str = parse_app_type(&app_type)?;
+            let ids: Vec<String> = ids
+                .split(',')
+                .map(|id| id.trim().to_string())
+                .filter(|id| !id.is_empty())
+                .collect();
+            if ids.is_empty() {
+                return Err(AppError::Message("供应商ID列表不能为空".to_string()));
+            }
+
+            let providers = db.get_failover_providers(&app_type_str)?;
+            let changes = db.set_priorities_bulk(&app_type_str, &ids)?;
+            for line in format_priority_changes(&changes) {
+                println!("{line}");
+            }
+            if changes.is_empty() {
+                return Ok(());
+            }
+
+            // 运行中的代理：清除受影响 supplier 的选路缓存，使新顺序立即生效
+            let mut suppliers: Vec<String> = providers
+                .iter()
+                .filter(|p| changes.iter().any(|c| c.id == p.id))
+                .map(|p| p.supplier())
+                .collect();
+            suppliers.sort();
+            suppliers.dedup();
+            notify_queue_reordered(&db, &app_type_str, &suppliers).await;
+        }
+    }
+
+    Ok(())
+}
+
+async fn notify_queue_reordered(db: &Database, app_type: &str, suppliers: &[String]) {
+    let Ok(client) = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(2))
+        .build()
+    else {
+        return;
+    };
+    let Ok(base) = find_running_proxy_base(db, &client).await else {
+        println!("  代理未运行，新顺序将在下次启动时生效");
+        return;
+    };
+    let cleared = match client
+        .post(format!("{base}/__cc_switch/queue/invalidate"))
+        .json(&json!({ "app_type": app_type, "suppliers": suppliers }))
+        .send()
+        .await
+    {
+        Ok(resp) if resp.status().is_success() => resp.json::<serde_json::Value>().await.ok(),
+        _ => None,
+    };
+    match cleared {
+        Some(v) => println!(
+            "✓ 已通知运行中的代理（清除选路缓存 {} 条）",
+            v.get("cleared").and_then(|n| n.as_u64()).unwrap_or(0)
+        ),
+        None => println!("⚠ 通知运行中的代理失败，请重启代理: csc p r"),
+    }
+}
+
 fn handle_add_to_queue(app_type: &str, id: &str) -> Result<(), AppError> {
     let db = Arc::new(Database::init()?);
     let app_type_str = parse_app_type(app_type)?;
@@ -584,10 +1995,142 @@ fn handle_remove_from_queue(app_type: &str, id: &str) -> Result<(), AppError> {
     Ok(())
 }
 
-async fn handle_test_latency(app_type: &str, id: Option<String>, mode: &str) -> Result<(), AppError> {
-    use cc_switch_lib::proxy::provider_router::ProviderRouter;
+fn handle_supplier(action: SupplierAction) -> Result<(), AppError> {
+    let db = Database::init()?;
 
-    let db = Arc::new(Database::init()?);
+    match action {
+        SupplierAction::Set {
+            app_type,
+            id,
+            supplier,
+        } => {
+            let app_type_str = parse_app_type(&app_type)?;
+            db.set_provider_supplier(&app_type_str, &id, Some(&supplier))?;
+            println!("✓ 已设置供应商 {} 的 supplier: {}", id, supplier.trim());
+            println!("  {}", tr(Msg::CliRestartHint, &[]));
+        }
+        SupplierAction::Clear { app_type, id } => {
+            let app_type_str = parse_app_type(&app_type)?;
+            db.set_provider_supplier(&app_type_str, &id, None)?;
+            println!("✓ 已清除供应商 {} 的 supplier（按名称推断）", id);
+            println!("  {}", tr(Msg::CliRestartHint, &[]));
+        }
+        SupplierAction::Backfill { app_type } => {
+            let app_types = match app_type {
+                Some(t) => vec![parse_app_type(&t)?],
+                None => vec!["claude".to_string(), "codex".to_string(), "gemini".to_string()],
+            };
+            for app_type_str in app_types {
+                let count = db.backfill_provider_suppliers(&app_type_str)?;
+                println!("✓ {}: 已回填 {} 个供应商的 supplier", app_type_str, count);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_url_priority(action: UrlPriorityAction) -> Result<(), AppError> {
+    let db = Database::init()?;
+
+    match action {
+        UrlPriorityAction::List => {
+            let items = db.list_supplier_url_priorities()?;
+            if items.is_empty() {
+                println!("未配置任何 supplier 的首选 URL");
+                return Ok(());
+            }
+            for item in items {
+                println!("{}", item.supplier);
+                for (i, url) in item.urls.iter().enumerate() {
+                    println!("  {}. {}", i + 1, url);
+                }
+            }
+        }
+        UrlPriorityAction::Set { supplier, urls } => {
+            let urls: Vec<String> = urls
+                .split(',')
+                .map(|u| u.trim().to_string())
+                .filter(|u| !u.is_empty())
+                .collect();
+            if urls.is_empty() {
+                return Err(AppError::Message(
+                    "URL 列表不能为空（如需清除请使用 csc url-priority clear）".to_string(),
+                ));
+            }
+            db.set_supplier_url_priority(&supplier, &urls)?;
+            println!("✓ 已设置 supplier {} 的首选 URL: {}", supplier, urls.join(", "));
+            println!("  提示: 运行中的代理会在下次测速选 URL 时生效");
+        }
+        UrlPriorityAction::Clear { supplier } => {
+            db.set_supplier_url_priority(&supplier, &[])?;
+            println!("✓ 已清除 supplier {} 的首选 URL", supplier);
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_family(action: FamilyAction) -> Result<(), AppError> {
+    use cc_switch_lib::proxy::ModelFamily;
+
+    match action {
+        FamilyAction::List => {
+            let overrides = cc_switch_lib::get_model_family_overrides();
+            if overrides.is_empty() {
+                println!("未配置自定义模型家族规则");
+            } else {
+                println!("自定义规则（先于内置规则生效）:");
+                for (pattern, family) in overrides {
+                    if ModelFamily::is_builtin_name(&family) {
+                        println!("  {} -> {}", pattern, family);
+                    } else {
+                        println!("  {} -> {}（自定义家族）", pattern, family);
+                    }
+                }
+            }
+            println!(
+                "内置家族: {}",
+                ModelFamily::builtin_names().collect::<Vec<_>>().join(", ")
+            );
+        }
+        FamilyAction::Add { pattern, family } => {
+            let (pattern, family) = (pattern.trim(), family.trim());
+            if pattern.is_empty() || family.is_empty() {
+                return Err(AppError::Message("模式与家族名不能为空".to_string()));
+            }
+            cc_switch_lib::set_model_family_override(pattern, family)?;
+            let family = ModelFamily::from_name(family);
+            let kind = if ModelFamily::is_builtin_name(family.name()) {
+                ""
+            } else {
+                "（自定义家族）"
+            };
+            println!(
+                "✓ 已添加规则: {} -> {}{}",
+                pattern.to_lowercase(),
+                family.name(),
+                kind
+            );
+            println!("  {}", tr(Msg::CliRestartHint, &[]));
+        }
+        FamilyAction::Remove { pattern } => {
+            if cc_switch_lib::remove_model_family_override(&pattern)? {
+                println!("✓ 已删除规则: {}", pattern.trim().to_lowercase());
+                println!("  {}", tr(Msg::CliRestartHint, &[]));
+            } else {
+                println!("未找到规则: {}", pattern.trim());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn handle_test_latency(app_type: &str, id: Option<String>, mode: &str) -> Result<(), AppError> {
+    use cc_switch_lib::proxy::provider_router::ProviderRouter;
+
+    let db = Arc::new(Database::init()?);
     let app_type_str = parse_app_type(app_type)?;
 
     let mode = mode.trim().to_lowercase();
@@ -626,42 +2169,6 @@ async fn handle_test_latency(app_type: &str, id: Option<String>, mode: &str) ->
             .build()
             .map_err(|e| AppError::Message(format!("创建HTTP客户端失败: {e}")))?;
 
-        async fn find_running_proxy_base(
-            db: &Database,
-            client: &reqwest::Client,
-        ) -> Result<String, AppError> {
-            let mut ports: Vec<u16> = Vec::new();
-
-            // 真实运行默认端口（当前安装脚本与运行日志均以此为主）
-            ports.push(15721);
-
-            // 兼容历史配置/数据库记录
-            if let Ok(cfg) = db.get_proxy_config().await {
-                ports.push(cfg.listen_port);
-            }
-
-            // 常见端口兜底
-            ports.push(5000);
-            ports.push(8080);
-
-            // 去重保序
-            let mut seen = std::collections::HashMap::<u16, ()>::new();
-            ports.retain(|p| seen.insert(*p, ()).is_none());
-
-            for port in ports {
-                let base = format!("http://127.0.0.1:{port}");
-                if let Ok(resp) = client.get(format!("{base}/health")).send().await {
-                    if resp.status().is_success() {
-                        return Ok(base);
-                    }
-                }
-            }
-
-            Err(AppError::Message(
-                "代理服务未运行或不可达，请先启动 cc-switch 代理（默认端口 15721）".to_string(),
-            ))
-        }
-
         let base = find_running_proxy_base(db.as_ref(), &client).await?;
         println!("✓ 已检测到代理服务: {base}");
 
@@ -1365,8 +2872,8 @@ async fn handle_test_latency(app_type: &str, id: Option<String>, mode: &str) ->
                 } else {
                     let payload = serde_json::json!({
                         "model": model,
-                        "max_tokens": 64,
-                        "temperature": 0.7,
+                        "max_tokens": 16,
+                        "temperature": 0.0,
                         "stream": false,
                         "messages": [{"role":"user","content":"ping"}],
                     });
@@ -1820,31 +3327,24 @@ async fn handle_test_latency(app_type: &str, id: Option<String>, mode: &str) ->
                 .get_all_providers(&app_type_str)
                 .map_err(|e| AppError::Message(format!("读取供应商失败: {e}")))?;
             let Some(p) = all.get(provider_id) else {
-                return Err(AppError::Message(format!("供应商不存在: {}", provider_id)));
+                return Err(AppError::Message(tr(
+                    Msg::CliProviderNotFound,
+                    &[&provider_id],
+                )));
             };
-            let supplier = p
-                .name
-                .split('-')
-                .next()
-                .unwrap_or(&p.name)
-                .to_string();
-            let priority = p.sort_index.unwrap_or(999999) as usize;
+            let supplier = p.supplier();
+            let priority = p.priority();
             targets.push(Target { priority, supplier });
         } else {
             let providers = db.get_failover_providers(&app_type_str)?;
             if providers.is_empty() {
-                return Err(AppError::Message("没有可测试的供应商（队列为空）".to_string()));
+                return Err(AppError::Message(tr(Msg::CliQueueEmpty, &[])));
             }
 
             let mut grouped: BTreeMap<usize, BTreeMap<String, ()>> = BTreeMap::new();
             for p in providers {
-                let priority = p.sort_index.unwrap_or(999999) as usize;
-                let supplier = p
-                    .name
-                    .split('-')
-                    .next()
-                    .unwrap_or(&p.name)
-                    .to_string();
+                let priority = p.priority();
+                let supplier = p.supplier();
                 grouped.entry(priority).or_default().insert(supplier, ());
             }
 
@@ -1859,7 +3359,7 @@ async fn handle_test_latency(app_type: &str, id: Option<String>, mode: &str) ->
         }
 
         if targets.is_empty() {
-            return Err(AppError::Message("没有可测试的供应商".to_string()));
+            return Err(AppError::Message(tr(Msg::CliNoTestableProviders, &[])));
         }
 
         // 4) 启动/退出方式逐个测试（每个 supplier 一次启动 Claude Code）
@@ -1983,16 +3483,11 @@ async fn handle_test_latency(app_type: &str, id: Option<String>, mode: &str) ->
                 let mut url_to_key: BTreeMap<String, String> = BTreeMap::new();
                 let mut url_to_provider_ids: BTreeMap<String, Vec<String>> = BTreeMap::new();
                 for p in providers.iter() {
-                    let p_priority = p.sort_index.unwrap_or(999999) as usize;
+                    let p_priority = p.priority();
                     if p_priority != *priority {
                         continue;
                     }
-                    let p_supplier = p
-                        .name
-                        .split('-')
-                        .next()
-                        .unwrap_or(&p.name)
-                        .to_string();
+                    let p_supplier = p.supplier();
                     if &p_supplier != supplier {
                         continue;
                     }
@@ -2247,6 +3742,7 @@ async fn handle_test_latency(app_type: &str, id: Option<String>, mode: &str) ->
                                         chosen_kind: "OK".to_string(),
                                         metric_ms: Some(s.median_latency_ms),
                                         urls: vec![url_result],
+                                        cooldown_reason: None,
                                     },
                                 );
                             }
@@ -2598,13 +4094,13 @@ async fn handle_test_latency(app_type: &str, id: Option<String>, mode: &str) ->
         }
 
         if summary.is_empty() {
-            println!("\n=== 汇总 ===");
-            println!("全部测试失败（没有任何可用URL）");
+            println!("\n{}", tr(Msg::CliSummary, &[]));
+            println!("{}", tr(Msg::CliAllTestsFailed, &[]));
             return Ok(());
         }
 
         summary.sort_by_key(|(_, _, _, _, ms)| *ms);
-        println!("\n=== 汇总（按最优URL延迟排序）===");
+        println!("\n{}", tr(Msg::CliSummarySorted, &[]));
         for (i, (priority, supplier, url, kind, metric_ms)) in summary.iter().enumerate() {
             match kind.as_str() {
                 "OK" => println!(
@@ -2645,46 +4141,56 @@ async fn handle_test_latency(app_type: &str, id: Option<String>, mode: &str) ->
         return Ok(());
     }
 
-    let test_model = match app_type_str.as_str() {
-        "claude" => "claude-sonnet-4-5-20250929",
-        "codex" => "gpt-5.2",
-        "gemini" => "gemini-2.0-flash",
-        _ => "unknown",
-    };
-
     let (only_priority, only_supplier) = if let Some(provider_id) = id.as_deref() {
         let all = db
             .get_all_providers(&app_type_str)
             .map_err(|e| AppError::Message(format!("读取供应商失败: {e}")))?;
         let Some(p) = all.get(provider_id) else {
-            return Err(AppError::Message(format!("供应商不存在: {}", provider_id)));
+            return Err(AppError::Message(tr(
+                Msg::CliProviderNotFound,
+                &[&provider_id],
+            )));
         };
-        let supplier = p
-            .name
-            .split('-')
-            .next()
-            .unwrap_or(&p.name)
-            .to_string();
+        let supplier = p.supplier();
         (p.sort_index.map(|v| v as usize), Some(supplier))
     } else {
         (None, None)
     };
 
     let router = ProviderRouter::new(db);
-
+    let test_model = router.app_probe_model(&app_type_str).await;
+
+    // Ctrl+C：不再开始新的 supplier，中止进行中的探测，只展示已完成的结果
+    let cancel = tokio_util::sync::CancellationToken::new();
+    let ctrl_c = {
+        let cancel = cancel.clone();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                cancel.cancel();
+            }
+        })
+    };
+    let progress = BenchmarkProgressBar::new();
     let results = router
         .benchmark_all_suppliers(
             app_type_str.as_str(),
-            test_model,
+            &test_model,
             only_priority,
             only_supplier.as_deref(),
+            &cancel,
+            &|event| progress.update(&event),
         )
-        .await
-        .map_err(|e| AppError::Message(format!("测速失败: {e}")))?;
+        .await;
+    ctrl_c.abort();
+    progress.finish();
+    let results = results.map_err(|e| AppError::Message(format!("测速失败: {e}")))?;
+    if cancel.is_cancelled() {
+        println!("\n测速已取消，仅显示已完成的 {} 个供应商", results.len());
+    }
 
     if results.is_empty() {
-        println!("\n=== 汇总 ===");
-        println!("全部测试失败（没有任何可用URL）");
+        println!("\n{}", tr(Msg::CliSummary, &[]));
+        println!("{}", tr(Msg::CliAllTestsFailed, &[]));
         return Ok(());
     }
 
@@ -2707,6 +4213,14 @@ async fn handle_test_latency(app_type: &str, id: Option<String>, mode: &str) ->
 
         for s in list.into_iter() {
             println!("\n供应商: {}", s.supplier);
+            if s.chosen_kind == "COOLDOWN" {
+                let reason = s.cooldown_reason.as_deref().unwrap_or("-");
+                let reason =
+                    cc_switch_lib::proxy::provider_router::CooldownReason::from_label(reason)
+                        .map(|r| r.describe())
+                        .unwrap_or(reason);
+                println!("  冷却中（{}）", reason);
+            }
             for (i, u) in s.urls.iter().enumerate() {
                 match u.kind.as_str() {
                     "OK" => println!(
@@ -2715,6 +4229,12 @@ async fn handle_test_latency(app_type: &str, id: Option<String>, mode: &str) ->
                         u.url,
                         u.latency_ms.unwrap_or(0)
                     ),
+                    "MO" => println!(
+                        "  {}. {} - MO {}ms (仅确认可列出模型)",
+                        i + 1,
+                        u.url,
+                        u.latency_ms.unwrap_or(0)
+                    ),
                     "OV" => println!(
                         "  {}. {} - OV {}ms ({})",
                         i + 1,
@@ -2746,14 +4266,14 @@ async fn handle_test_latency(app_type: &str, id: Option<String>, mode: &str) ->
     }
 
     if summary.is_empty() {
-        println!("\n=== 汇总 ===");
-        println!("全部测试失败（没有任何可用URL）");
+        println!("\n{}", tr(Msg::CliSummary, &[]));
+        println!("{}", tr(Msg::CliAllTestsFailed, &[]));
         return Ok(());
     }
 
     summary.sort_by_key(|(_, _, _, _, latency)| *latency);
 
-    println!("\n=== 汇总（按最优URL延迟排序）===");
+    println!("\n{}", tr(Msg::CliSummarySorted, &[]));
     for (i, (priority, supplier, url, kind, metric_ms)) in summary.iter().enumerate() {
         match kind.as_str() {
             "OK" => println!(
@@ -2764,6 +4284,14 @@ async fn handle_test_latency(app_type: &str, id: Option<String>, mode: &str) ->
                 url,
                 metric_ms
             ),
+            "MO" => println!(
+                "{}. [层级 {}] {} -> {} - MO {}ms",
+                i + 1,
+                priority,
+                supplier,
+                url,
+                metric_ms
+            ),
             "OV" => println!(
                 "{}. [层级 {}] {} -> {} - OV ~{}ms",
                 i + 1,
@@ -2794,6 +4322,364 @@ async fn handle_test_latency(app_type: &str, id: Option<String>, mode: &str) ->
     Ok(())
 }
 
+/// 测速进度条：输出到 stderr，仅在终端中显示
+struct BenchmarkProgressBar {
+    enabled: bool,
+    /// (已完成数, 进行中的 supplier)
+    state: std::sync::Mutex<(usize, Vec<String>)>,
+}
+
+impl BenchmarkProgressBar {
+    fn new() -> Self {
+        use std::io::IsTerminal;
+        Self {
+            enabled: std::io::stderr().is_terminal(),
+            state: std::sync::Mutex::new((0, Vec::new())),
+        }
+    }
+
+    fn update(&self, event: &cc_switch_lib::proxy::provider_router::BenchmarkProgress) {
+        use cc_switch_lib::proxy::provider_router::BenchmarkProgress;
+
+        if !self.enabled {
+            return;
+        }
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let total = match event {
+            BenchmarkProgress::SupplierStarted {
+                total, supplier, ..
+            } => {
+                state.1.push(supplier.clone());
+                *total
+            }
+            BenchmarkProgress::SupplierFinished { total, result, .. } => {
+                state.0 += 1;
+                state.1.retain(|s| s != &result.supplier);
+                *total
+            }
+        };
+        eprint!(
+            "\r\x1b[2K{}",
+            format_benchmark_progress(state.0, total, &state.1)
+        );
+    }
+
+    /// 清除进度行，避免与后续输出混在一起
+    fn finish(&self) {
+        if self.enabled {
+            eprint!("\r\x1b[2K");
+        }
+    }
+}
+
+fn format_benchmark_progress(done: usize, total: usize, running: &[String]) -> String {
+    const WIDTH: usize = 20;
+    let filled = (done * WIDTH).checked_div(total).unwrap_or(WIDTH);
+    let mut line = format!(
+        "测速中 [{}{}] {done}/{total}",
+        "#".repeat(filled),
+        "-".repeat(WIDTH - filled)
+    );
+    if !running.is_empty() {
+        line.push_str(&format!(" 进行中: {}", running.join(", ")));
+    }
+    line
+}
+
+// ============================================================================
+// 监控面板
+// ============================================================================
+
+async fn handle_dashboard() -> Result<(), AppError> {
+    use std::time::Duration;
+
+    let db = Arc::new(Database::init()?);
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(2))
+        .build()
+        .map_err(|e| AppError::Message(format!("创建HTTP客户端失败: {e}")))?;
+
+    let base = match find_running_proxy_base(db.as_ref(), &client).await {
+        Ok(base) => base,
+        Err(_) => {
+            println!("代理服务器未运行，无法打开监控面板");
+            println!("  请先启动代理: csc p s");
+            return Ok(());
+        }
+    };
+
+    dashboard::run(db, client, base).await
+}
+
+// ============================================================================
+// 熔断器
+// ============================================================================
+
+async fn handle_shadow(action: ShadowAction) -> Result<(), AppError> {
+    match action {
+        ShadowAction::Set {
+            app_type,
+            provider_id,
+            percent,
+        } => {
+            let app_type_str = parse_app_type(&app_type)?;
+            let db = Database::init()?;
+            let provider = db
+                .get_provider_by_id(&provider_id, &app_type_str)?
+                .ok_or_else(|| {
+                    AppError::Message(format!("供应商 {provider_id} 不存在（{app_type_str}）"))
+                })?;
+            let mut config = db.get_proxy_config_for_app(&app_type_str).await?;
+            config.shadow = Some(ShadowConfig {
+                provider_id: provider.id.clone(),
+                sample_percent: percent,
+            });
+            db.update_proxy_config_for_app(config).await?;
+            println!(
+                "✓ {app_type_str} 已开启影子流量: {} - {}（采样 {percent}%，仅非流式请求）",
+                provider.id, provider.name
+            );
+            println!("  对比结果: csc shadow stats {app_type_str}");
+            Ok(())
+        }
+        ShadowAction::Off { app_type } => {
+            let app_type_str = parse_app_type(&app_type)?;
+            let db = Database::init()?;
+            let mut config = db.get_proxy_config_for_app(&app_type_str).await?;
+            if config.shadow.take().is_none() {
+                println!("{app_type_str} 未开启影子流量");
+                return Ok(());
+            }
+            db.update_proxy_config_for_app(config).await?;
+            println!("✓ {app_type_str} 已关闭影子流量（历史影子请求日志保留）");
+            Ok(())
+        }
+        ShadowAction::Stats {
+            app_type,
+            provider,
+            window,
+            json,
+        } => {
+            let app_type_str = parse_app_type(&app_type)?;
+            let window_secs = parse_duration_secs(&window)?;
+            let db = open_db_readonly()?;
+            let configured = db
+                .get_proxy_config_for_app(&app_type_str)
+                .await
+                .ok()
+                .and_then(|c| c.shadow);
+            let provider_id = provider
+                .or_else(|| configured.as_ref().map(|s| s.provider_id.clone()))
+                .ok_or_else(|| {
+                    AppError::Message(format!(
+                        "{app_type_str} 未配置影子供应商，请使用 --provider 指定或先执行 csc shadow set"
+                    ))
+                })?;
+            let sample_percent = configured
+                .filter(|s| s.provider_id == provider_id)
+                .map(|s| s.sample_percent);
+            let cmp = db.get_shadow_comparison(&app_type_str, &provider_id, window_secs)?;
+
+            if json {
+                let value = json!({
+                    "appType": app_type_str,
+                    "windowSecs": window_secs,
+                    "providerId": provider_id,
+                    "samplePercent": sample_percent,
+                    "primary": cmp.primary,
+                    "shadow": cmp.shadow,
+                });
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&value).unwrap_or_default()
+                );
+                return Ok(());
+            }
+
+            for line in
+                shadow::format_lines(&app_type_str, &window, &provider_id, sample_percent, &cmp)
+            {
+                println!("{}", line);
+            }
+            Ok(())
+        }
+    }
+}
+
+async fn handle_breaker(action: BreakerAction) -> Result<(), AppError> {
+    let db = Database::init()?;
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(5))
+        .build()
+        .map_err(|e| AppError::Message(format!("创建HTTP客户端失败: {e}")))?;
+
+    let (app_type, id, path, body, secs) = match action {
+        BreakerAction::Open {
+            app_type,
+            id,
+            duration,
+        } => {
+            let app_type = parse_app_type(&app_type)?;
+            let secs = parse_duration_secs(&duration)?;
+            let body = json!({ "app_type": app_type, "provider_id": id, "secs": secs });
+            (app_type, id, "force-open", body, Some(secs))
+        }
+        BreakerAction::Close { app_type, id } => {
+            let app_type = parse_app_type(&app_type)?;
+            let body = json!({ "app_type": app_type, "provider_id": id });
+            (app_type, id, "reset", body, None)
+        }
+    };
+
+    let base = find_running_proxy_base(&db, &client).await?;
+    let resp = client
+        .post(format!("{base}/__cc_switch/breaker/{path}"))
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| AppError::Message(format!("请求代理失败: {e}")))?;
+    if !resp.status().is_success() {
+        let status = resp.status();
+        let text = resp.text().await.unwrap_or_default();
+        return Err(AppError::Message(format!(
+            "代理返回错误 ({status}): {text}"
+        )));
+    }
+
+    match secs {
+        Some(secs) => {
+            println!(
+                "✓ 已强制熔断 {app_type}/{id}，持续 {}",
+                format_remaining(secs)
+            );
+            println!("  提前解除: csc breaker close {app_type} {id}");
+        }
+        None => println!("✓ 已解除 {app_type}/{id} 的熔断"),
+    }
+    Ok(())
+}
+
+async fn handle_failover(action: FailoverAction) -> Result<(), AppError> {
+    let db = Database::init()?;
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(5))
+        .build()
+        .map_err(|e| AppError::Message(format!("创建HTTP客户端失败: {e}")))?;
+
+    let (app_type, path, body, secs) = match action {
+        FailoverAction::Pause { app_type, duration } => {
+            let app_type = parse_app_type(&app_type)?;
+            let secs = parse_duration_secs(&duration)?;
+            let body = json!({ "app_type": app_type, "secs": secs });
+            (app_type, "pause", body, Some(secs))
+        }
+        FailoverAction::Resume { app_type } => {
+            let app_type = parse_app_type(&app_type)?;
+            let body = json!({ "app_type": app_type });
+            (app_type, "resume", body, None)
+        }
+    };
+
+    let base = find_running_proxy_base(&db, &client).await?;
+    let resp = client
+        .post(format!("{base}/__cc_switch/failover/{path}"))
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| AppError::Message(format!("请求代理失败: {e}")))?;
+    if !resp.status().is_success() {
+        let status = resp.status();
+        let text = resp.text().await.unwrap_or_default();
+        return Err(AppError::Message(format!(
+            "代理返回错误 ({status}): {text}"
+        )));
+    }
+    let data: serde_json::Value = resp
+        .json()
+        .await
+        .map_err(|e| AppError::Message(format!("解析代理响应失败: {e}")))?;
+
+    match secs {
+        Some(secs) => {
+            println!(
+                "✓ 已暂停 {app_type} 故障转移 {}，期间只使用当前供应商",
+                format_remaining(secs)
+            );
+            if data.get("auto_failover_enabled").and_then(|v| v.as_bool()) == Some(false) {
+                println!("  注意: {app_type} 当前未开启故障转移，暂停暂无实际效果");
+            }
+            println!("  提前恢复: csc failover resume {app_type}");
+        }
+        None => {
+            if data.get("was_paused").and_then(|v| v.as_bool()) == Some(true) {
+                println!("✓ 已恢复 {app_type} 故障转移");
+            } else {
+                println!("{app_type} 故障转移未处于暂停状态，无需恢复");
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn handle_version(check: bool, auto_check: Option<&str>, json: bool) -> Result<(), AppError> {
+    use cc_switch_lib::proxy::update_check::{self, VersionInfo};
+
+    // 只查看时不写数据库
+    let db = if check || auto_check.is_some() {
+        Database::init()?
+    } else {
+        open_db_readonly()?
+    };
+    if let Some(value) = auto_check {
+        let enabled = version::parse_switch(value)
+            .ok_or_else(|| AppError::InvalidInput(format!("无效的取值: {value}，支持: on, off")))?;
+        db.set_update_check_enabled(enabled)?;
+        if !json {
+            println!(
+                "✓ 每日新版本检测已{}（代理运行中时下一轮调度生效）",
+                if enabled { "开启" } else { "关闭" }
+            );
+        }
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(2))
+        .build()
+        .map_err(|e| AppError::Message(format!("创建HTTP客户端失败: {e}")))?;
+    let proxy = match find_running_proxy_base(&db, &client).await {
+        Ok(base) => match client.get(format!("{base}/version")).send().await {
+            Ok(resp) if resp.status().is_success() => resp.json::<VersionInfo>().await.ok(),
+            _ => None,
+        },
+        Err(_) => None,
+    };
+    let update = if check {
+        Some(update_check::check_now(&db, update_check::RELEASES_API_URL).await?)
+    } else {
+        update_check::load_stored(&db)
+    };
+    let auto_check = db.get_update_check_enabled()?;
+    let cli = VersionInfo::current();
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&json!({
+                "cli": cli,
+                "proxy": proxy,
+                "updateCheck": update,
+                "autoCheck": auto_check,
+            }))
+            .unwrap_or_default()
+        );
+        return Ok(());
+    }
+    for line in version::format_lines(&cli, proxy.as_ref(), update.as_ref(), auto_check) {
+        println!("{line}");
+    }
+    Ok(())
+}
+
 // ============================================================================
 // 辅助函数
 // ============================================================================
@@ -2809,12 +4695,107 @@ fn parse_app_type(s: &str) -> Result<String, AppError> {
     }
 }
 
+/// 探测正在运行的代理服务地址（依次尝试默认端口 / DB 配置端口 / 常见端口）
+async fn find_running_proxy_base(
+    db: &Database,
+    client: &reqwest::Client,
+) -> Result<String, AppError> {
+    let mut ports: Vec<u16> = Vec::new();
+
+    // 真实运行默认端口（当前安装脚本与运行日志均以此为主）
+    ports.push(15721);
+
+    // 兼容历史配置/数据库记录
+    if let Ok(cfg) = db.get_proxy_config().await {
+        ports.push(cfg.listen_port);
+    }
+
+    // 常见端口兜底
+    ports.push(5000);
+    ports.push(8080);
+
+    // 去重保序
+    let mut seen = std::collections::HashMap::<u16, ()>::new();
+    ports.retain(|p| seen.insert(*p, ()).is_none());
+
+    for port in ports {
+        let base = format!("http://127.0.0.1:{port}");
+        if let Ok(resp) = client.get(format!("{base}/health")).send().await {
+            if resp.status().is_success() {
+                return Ok(base);
+            }
+        }
+    }
+
+    Err(AppError::Message(
+        "代理服务未运行或不可达，请先启动 cc-switch 代理（默认端口 15721）".to_string(),
+    ))
+}
+
 fn get_config_dir() -> PathBuf {
     dirs::home_dir()
         .unwrap_or_else(|| PathBuf::from("."))
         .join(".cc-switch")
 }
 
+/// 会写入数据库的命令（只读模式下给出提示）
+fn is_mutating_command(command: &Commands) -> bool {
+    matches!(
+        command,
+        Commands::Add { .. }
+            | Commands::Remove { .. }
+            | Commands::Enable { .. }
+            | Commands::Disable { .. }
+            | Commands::EnableProvider { .. }
+            | Commands::SetPriority { .. }
+            | Commands::NormalizePriorities { .. }
+            | Commands::AddToQueue { .. }
+            | Commands::RemoveFromQueue { .. }
+            | Commands::Queue {
+                action: QueueAction::Reorder { .. }
+            }
+            | Commands::Supplier { .. }
+            | Commands::UrlPriority {
+                action: UrlPriorityAction::Set { .. } | UrlPriorityAction::Clear { .. }
+            }
+            | Commands::Import { .. }
+            | Commands::ImportLive { .. }
+            | Commands::Takeover {
+                action: TakeoverAction::Enable { .. }
+                    | TakeoverAction::Disable { .. }
+                    | TakeoverAction::Restore { .. }
+            }
+            | Commands::Config {
+                action: ConfigAction::Import { .. }
+            }
+            | Commands::Db {
+                action: DbAction::Maintain { .. }
+            }
+    )
+}
+
+/// 数据库配置为只读模式时提示：修改仍会写入，但运行中的代理不会写回任何状态
+async fn warn_if_read_only() {
+    // 仅用只读连接探测，避免为了提示而触发建表/迁移
+    let Ok(db) = Database::open_readonly(&Database::file_path()) else {
+        return;
+    };
+    if db
+        .get_global_proxy_config()
+        .await
+        .map(|c| c.read_only)
+        .unwrap_or(false)
+    {
+        eprintln!("⚠ 当前数据库已配置为只读模式（globalProxy.readOnly）：本命令的修改会写入数据库，但代理运行期间不会写回任何状态");
+    }
+}
+
+/// 只读命令打开数据库：只读连接不建表、不迁移、不写入；
+/// 数据库不存在或版本不一致（升级后首次运行）时回退到完整初始化
+fn open_db_readonly() -> Result<Database, AppError> {
+    Database::open_readonly(&Database::file_path()).or_else(|_| Database::init())
+}
+
 // ============================================================================
 // 配置导出/导入
 // ============================================================================
@@ -2848,3 +4829,1580 @@ fn handle_import(file_path: &str) -> Result<(), AppError> {
 
     Ok(())
 }
+
+fn handle_import_live(app: &str, json: bool) -> Result<(), AppError> {
+    use cc_switch_lib::{AppType, LiveImportService, LivePaths};
+    use std::str::FromStr;
+
+    let app_types = if app.eq_ignore_ascii_case("all") {
+        vec![AppType::Claude, AppType::Codex, AppType::Gemini]
+    } else {
+        vec![AppType::from_str(&parse_app_type(app)?)?]
+    };
+
+    let db = Database::init()?;
+    let items = LiveImportService::import(&db, &app_types, &LivePaths::current())?;
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&items).unwrap_or_default()
+        );
+        return Ok(());
+    }
+
+    for line in import_live::format_lines(&items) {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+async fn handle_takeover(action: TakeoverAction) -> Result<(), AppError> {
+    use cc_switch_lib::{AppType, ProxyService};
+    use std::str::FromStr;
+
+    let db = Arc::new(Database::init()?);
+    let service = ProxyService::new(db.clone());
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(2))
+        .build()
+        .map_err(|e| AppError::Message(format!("创建HTTP客户端失败: {e}")))?;
+
+    match action {
+        TakeoverAction::Enable { app_type, force } => {
+            let app = AppType::from_str(&parse_app_type(&app_type)?)?;
+            let base = find_running_proxy_base(&db, &client).await.ok();
+            if base.is_none() && !force {
+                return Err(AppError::Message(
+                    "代理未运行，接管后应用请求会失败。请先启动代理（csc proxy start --daemon），或加 --force 强制接管"
+                        .to_string(),
+                ));
+            }
+
+            let detail = service
+                .get_takeover_detail(&app)
+                .await
+                .map_err(AppError::Message)?;
+            let before = takeover::read_files(&detail.files);
+            if service
+                .apply_takeover_for_app(&app)
+                .await
+                .map_err(AppError::Message)?
+            {
+                println!("✓ {} 已接管，代理地址: {}", app.as_str(), detail.proxy_url);
+            } else {
+                println!("  {} 已处于接管状态", app.as_str());
+                if detail.points_to_proxy != Some(true) {
+                    println!(
+                        "⚠ live 配置已不再指向代理：先 csc takeover disable {0} 再重新开启即可恢复",
+                        app.as_str()
+                    );
+                }
+            }
+            for line in takeover::format_changed(&takeover::changed_files(&before)) {
+                println!("{}", line);
+            }
+
+            match base {
+                Some(base) => reload_running_proxy(&client, &base).await,
+                None => println!("⚠ 代理未运行，请尽快启动: csc proxy start --daemon"),
+            }
+        }
+        TakeoverAction::Disable { app_type } => {
+            let app = AppType::from_str(&parse_app_type(&app_type)?)?;
+            let detail = service
+                .get_takeover_detail(&app)
+                .await
+                .map_err(AppError::Message)?;
+            let before = takeover::read_files(&detail.files);
+            if !service
+                .release_takeover_for_app(&app)
+                .await
+                .map_err(AppError::Message)?
+            {
+                println!("  {} 未处于接管状态", app.as_str());
+                return Ok(());
+            }
+
+            if detail.has_backup {
+                println!(
+                    "✓ {} 已关闭接管，live 配置已从接管前的备份恢复",
+                    app.as_str()
+                );
+            } else {
+                println!(
+                    "✓ {} 已关闭接管（没有接管前的备份，live 配置未恢复）",
+                    app.as_str()
+                );
+            }
+            for line in takeover::format_changed(&takeover::changed_files(&before)) {
+                println!("{}", line);
+            }
+            if service.live_config_taken_over(&app) == Some(true) {
+                println!(
+                    "⚠ live 配置仍是代理占位符，可用 csc takeover restore {} 回滚到接管前的快照",
+                    app.as_str()
+                );
+            }
+
+            if let Ok(base) = find_running_proxy_base(&db, &client).await {
+                reload_running_proxy(&client, &base).await;
+            }
+        }
+        TakeoverAction::Status { app_type, json } => {
+            let apps = match app_type {
+                Some(app_type) => vec![AppType::from_str(&parse_app_type(&app_type)?)?],
+                None => vec![AppType::Claude, AppType::Codex, AppType::Gemini],
+            };
+            let mut details = Vec::new();
+            for app in &apps {
+                details.push(
+                    service
+                        .get_takeover_detail(app)
+                        .await
+                        .map_err(AppError::Message)?,
+                );
+            }
+
+            if json {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&details).unwrap_or_default()
+                );
+                return Ok(());
+            }
+            let proxy_running = find_running_proxy_base(&db, &client).await.is_ok();
+            let (lines, _) = takeover::format_status(&details, proxy_running);
+            for line in lines {
+                println!("{}", line);
+            }
+        }
+        TakeoverAction::List { app_type, json } => {
+            let app_type_str = parse_app_type(&app_type)?;
+            let snapshots = service
+                .list_live_snapshots(&app_type_str)
+                .await
+                .map_err(AppError::Message)?;
+
+            if json {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&snapshots).unwrap_or_default()
+                );
+                return Ok(());
+            }
+            for line in takeover::format_list(&app_type_str, &snapshots) {
+                println!("{}", line);
+            }
+        }
+        TakeoverAction::Diff {
+            app_type,
+            backup_id,
+        } => {
+            let app_type_str = parse_app_type(&app_type)?;
+            let (snapshot, diff) = service
+                .diff_live_snapshot(&app_type_str, backup_id)
+                .await
+                .map_err(AppError::Message)?;
+            for line in takeover::format_diff(&snapshot, &diff) {
+                println!("{}", line);
+            }
+        }
+        TakeoverAction::Restore {
+            app_type,
+            backup_id,
+        } => {
+            let app_type_str = parse_app_type(&app_type)?;
+            let snapshot = service
+                .restore_live_snapshot(&app_type_str, backup_id)
+                .await
+                .map_err(AppError::Message)?;
+            println!(
+                "✓ {} live 配置已回滚到快照 #{}（回滚前的状态已另存为新快照）",
+                app_type_str, snapshot.id
+            );
+
+            let still_taken_over = db
+                .get_proxy_config_for_app(&app_type_str)
+                .await
+                .map(|c| c.enabled)
+                .unwrap_or(false);
+            if still_taken_over {
+                println!(
+                    "⚠ {} 仍处于接管状态：桌面端的 live 配置守护可能重新写入代理地址，关闭接管时也会按接管前的备份再次改写 live 配置",
+                    app_type_str
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 通知运行中的代理重新加载按应用的代理开关（接管开关即 proxy_config.enabled）
+async fn reload_running_proxy(client: &reqwest::Client, base: &str) {
+    match client
+        .post(format!("{base}/__cc_switch/config/reload"))
+        .send()
+        .await
+    {
+        Ok(resp) if resp.status().is_success() => println!("✓ 已通知运行中的代理刷新应用开关"),
+        _ => println!("⚠ 通知运行中的代理刷新配置失败，请重启代理: csc p r"),
+    }
+}
+
+async fn handle_config(action: ConfigAction) -> Result<(), AppError> {
+    use cc_switch_lib::ConfigBundle;
+    use std::time::Duration;
+
+    let db = Database::init()?;
+
+    match action {
+        ConfigAction::Export { out } => {
+            let bundle = db.export_config_bundle().await?;
+            let text = serde_json::to_string_pretty(&bundle)
+                .map_err(|e| AppError::Message(format!("序列化配置失败: {e}")))?;
+            match out {
+                Some(path) => {
+                    std::fs::write(&path, format!("{text}\n"))
+                        .map_err(|e| AppError::io(&path, e))?;
+                    println!("✓ 配置已导出到: {}", path);
+                }
+                None => println!("{text}"),
+            }
+        }
+        ConfigAction::Import { file } => {
+            let text = std::fs::read_to_string(&file).map_err(|e| AppError::io(&file, e))?;
+            let (bundle, warnings) = ConfigBundle::parse(&text)?;
+            for warning in &warnings {
+                println!("⚠ {}", warning);
+            }
+
+            let report = db.import_config_bundle(&bundle).await?;
+            for warning in &report.warnings {
+                println!("⚠ {}", warning);
+            }
+            println!("✓ 配置已从文件导入: {}", file);
+
+            // 代理运行中：通知其刷新熔断器阈值，其余配置按请求读取数据库，立即生效
+            let client = reqwest::Client::builder()
+                .timeout(Duration::from_secs(2))
+                .build()
+                .map_err(|e| AppError::Message(format!("创建HTTP客户端失败: {e}")))?;
+            match find_running_proxy_base(&db, &client).await {
+                Ok(base) => {
+                    let reloaded = match client
+                        .post(format!("{base}/__cc_switch/config/reload"))
+                        .send()
+                        .await
+                    {
+                        Ok(resp) if resp.status().is_success() => {
+                            resp.json::<serde_json::Value>().await.ok()
+                        }
+                        _ => None,
+                    };
+                    match reloaded {
+                        Some(v) => println!(
+                            "✓ 已热更新运行中的代理（熔断器 {} 个）",
+                            v.get("breakers_updated")
+                                .and_then(|n| n.as_u64())
+                                .unwrap_or(0)
+                        ),
+                        None => println!("⚠ 通知运行中的代理刷新配置失败，请重启代理: csc p r"),
+                    }
+                }
+                Err(_) => println!("  代理未运行，配置将在下次启动时生效"),
+            }
+
+            if !report.restart_required.is_empty() {
+                println!("\n以下变更需要重启代理才能生效（csc p r）:");
+                for item in &report.restart_required {
+                    println!("  - {}", item);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// ============================================================================
+// 开机自启
+// ============================================================================
+
+fn handle_service(action: ServiceAction) -> Result<(), AppError> {
+    match action {
+        ServiceAction::Install { user } => service::install(&service::ServiceSpec::detect(user)?),
+        ServiceAction::Uninstall { user } => {
+            service::uninstall(&service::ServiceSpec::detect(user)?)
+        }
+        ServiceAction::Status { user } => service::status(&service::ServiceSpec::detect(user)?),
+    }
+}
+
+// ============================================================================
+// 数据库维护
+// ============================================================================
+
+async fn handle_db(action: DbAction) -> Result<(), AppError> {
+    use std::time::Duration;
+
+    match action {
+        DbAction::Maintain { retention_days } => {
+            let retention_days = retention_days
+                .filter(|d| *d > 0)
+                .unwrap_or_else(cc_switch_lib::get_request_log_retention_days);
+            let db = Database::init()?;
+
+            // 压缩会替换数据库文件，代理运行期间的写入会丢失
+            let client = reqwest::Client::builder()
+                .timeout(Duration::from_secs(2))
+                .build()
+                .map_err(|e| AppError::Message(format!("创建HTTP客户端失败: {e}")))?;
+            if find_running_proxy_base(&db, &client).await.is_ok() {
+                return Err(AppError::Message(
+                    "代理服务运行中，维护期间需暂停数据库写入。请先停止代理（csc p x），完成后再启动（csc p s -d）"
+                        .to_string(),
+                ));
+            }
+
+            let db_path = Database::file_path();
+            println!("正在维护数据库: {}", db_path.display());
+            let report = db.maintain(&db_path, retention_days)?;
+
+            if !report.integrity_errors.is_empty() {
+                println!("✗ 完整性检查未通过，未做任何修改:");
+                for err in report.integrity_errors.iter().take(20) {
+                    println!("  - {}", err);
+                }
+                println!("  可从 ~/.cc-switch/backups/ 恢复备份");
+                return Err(AppError::Database("数据库完整性检查失败".to_string()));
+            }
+
+            println!("✓ 完整性检查通过");
+            println!(
+                "✓ 已清理 {} 条超过 {} 天的请求日志",
+                report.pruned_request_logs, retention_days
+            );
+            println!(
+                "✓ 已清理 {} 条超过 {} 天的测速历史",
+                report.pruned_benchmark_history, retention_days
+            );
+            println!(
+                "✓ 已清理 {} 条超过 {} 天的事件日志",
+                report.pruned_events, retention_days
+            );
+            println!(
+                "✓ 已压缩: {} → {}",
+                format_size(report.size_before),
+                format_size(report.size_after)
+            );
+        }
+    }
+
+    Ok(())
+}
+
+async fn handle_stats(app_type: &str, window: &str, json: bool) -> Result<(), AppError> {
+    let app_type_str = parse_app_type(app_type)?;
+    let window_secs = parse_duration_secs(window)?;
+    let db = open_db_readonly()?;
+    let rows = stats::collect(&db, &app_type_str, window_secs)?;
+
+    // 代理运行中时附带本次运行的累计请求数
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(2))
+        .build()
+        .map_err(|e| AppError::Message(format!("创建HTTP客户端失败: {e}")))?;
+    let proxy = match find_running_proxy_base(&db, &client).await {
+        Ok(base) => status::fetch_admin_status(&client, &base)
+            .await
+            .ok()
+            .and_then(|admin| admin.apps.into_iter().find(|a| a.app_type == app_type_str)),
+        Err(_) => None,
+    };
+
+    if json {
+        let value = json!({
+            "appType": app_type_str,
+            "windowSecs": window_secs,
+            "providers": rows,
+            "proxy": proxy,
+        });
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&value).unwrap_or_default()
+        );
+        return Ok(());
+    }
+
+    for line in stats::format_lines(&app_type_str, window, &rows, proxy.as_ref()) {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+fn handle_stats_action(action: StatsAction) -> Result<(), AppError> {
+    match action {
+        StatsAction::Models {
+            app_type,
+            window,
+            json,
+        } => {
+            let app_type_str = parse_app_type(&app_type)?;
+            let window_secs = parse_duration_secs(&window)?;
+            let since = chrono::Utc::now().timestamp() - window_secs;
+            let db = open_db_readonly()?;
+            let rows = db.get_model_mapping_stats(&app_type_str, since)?;
+
+            if json {
+                let value = json!({
+                    "appType": app_type_str,
+                    "windowSecs": window_secs,
+                    "mappings": rows,
+                });
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&value).unwrap_or_default()
+                );
+                return Ok(());
+            }
+
+            for line in stats::format_model_lines(&app_type_str, &window, &rows) {
+                println!("{}", line);
+            }
+            Ok(())
+        }
+        StatsAction::Bandwidth {
+            app_type,
+            window,
+            json,
+        } => {
+            let app_type_str = parse_app_type(&app_type)?;
+            let window_secs = parse_duration_secs(&window)?;
+            let since = chrono::Utc::now().timestamp() - window_secs;
+            let db = open_db_readonly()?;
+            let rows = db.get_bandwidth_stats(&app_type_str, since)?;
+
+            if json {
+                let value = json!({
+                    "appType": app_type_str,
+                    "windowSecs": window_secs,
+                    "providers": rows,
+                });
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&value).unwrap_or_default()
+                );
+                return Ok(());
+            }
+
+            for line in stats::format_bandwidth_lines(&app_type_str, &window, &rows) {
+                println!("{}", line);
+            }
+            Ok(())
+        }
+        StatsAction::BandwidthAlert {
+            app_type,
+            id,
+            threshold,
+        } => {
+            let app_type_str = parse_app_type(&app_type)?;
+            let threshold = if threshold.trim().eq_ignore_ascii_case("off") {
+                None
+            } else {
+                Some(stats::parse_size(&threshold)?)
+            };
+            let db = Database::init()?;
+            let mut provider = db.get_provider_by_id(&id, &app_type_str)?.ok_or_else(|| {
+                AppError::Message(format!("供应商 {id} 不存在（{app_type_str}）"))
+            })?;
+            provider
+                .meta
+                .get_or_insert_with(Default::default)
+                .bandwidth_daily_alert_bytes = threshold;
+            db.save_provider(&app_type_str, &provider)?;
+            match threshold {
+                Some(bytes) => println!(
+                    "✓ {} 当日流量告警阈值: {}（UTC 自然日）",
+                    provider.name,
+                    format_bytes(bytes)
+                ),
+                None => println!("✓ {} 已关闭流量告警", provider.name),
+            }
+            Ok(())
+        }
+    }
+}
+
+async fn handle_audit(app_type: &str, json: bool) -> Result<(), AppError> {
+    let app_type_str = parse_app_type(app_type)?;
+    let db = Database::init()?;
+
+    // 优先在代理进程内审计（可给出跨家族映射的修正建议）
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(2))
+        .build()
+        .map_err(|e| AppError::Message(format!("创建HTTP客户端失败: {e}")))?;
+    let remote = match find_running_proxy_base(&db, &client).await {
+        Ok(base) => match audit::fetch_admin_audit(&client, &base, &app_type_str).await {
+            Ok(report) => Some(report),
+            Err(e) => {
+                log::debug!("查询 /admin/audit 失败: {e}");
+                None
+            }
+        },
+        Err(_) => None,
+    };
+    let from_proxy = remote.is_some();
+    let report = match remote {
+        Some(report) => report,
+        None => db.audit_providers(&app_type_str)?,
+    };
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report).unwrap_or_default()
+        );
+        return Ok(());
+    }
+
+    for line in audit::format_lines(&report, from_proxy) {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+fn handle_check_env(app_type: &str) -> Result<(), AppError> {
+    use cc_switch_lib::proxy::env_expand::{count_placeholders, unresolved_placeholders};
+
+    let app_type_str = parse_app_type(app_type)?;
+    let db = Database::init()?;
+    let providers = db.get_all_providers(&app_type_str)?;
+
+    let mut referenced = 0;
+    let mut missing = Vec::new();
+    for provider in providers.values() {
+        referenced += count_placeholders(&provider.settings_config);
+        for var in unresolved_placeholders(&provider.settings_config) {
+            missing.push((provider.id.clone(), provider.name.clone(), var));
+        }
+    }
+
+    for line in format_env_check(referenced, &missing) {
+        println!("{}", line);
+    }
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::Config(format!(
+            "{} 个占位符未解析",
+            missing.len()
+        )))
+    }
+}
+
+/// `check-env` 输出；missing 为 (供应商ID, 名称, 未解析的变量)
+fn format_env_check(referenced: usize, missing: &[(String, String, MissingEnvVar)]) -> Vec<String> {
+    if referenced == 0 {
+        return vec!["  未发现 ${ENV_VAR} 占位符".to_string()];
+    }
+    if missing.is_empty() {
+        return vec![format!("✓ {} 个占位符均已解析", referenced)];
+    }
+
+    let mut lines = vec![format!(
+        "⚠ {}/{} 个占位符未解析:",
+        missing.len(),
+        referenced
+    )];
+    for (id, name, var) in missing {
+        lines.push(format!(
+            "  {} - {}  {} → ${{{}}}",
+            id, name, var.path, var.var
+        ));
+    }
+    lines.push(String::new());
+    lines.push(
+        "提示: 检查的是当前 shell 的环境变量，代理需在设置了这些变量的环境中启动".to_string(),
+    );
+    lines
+}
+
+async fn handle_doctor(app_type: Option<&str>, json: bool) -> Result<(), AppError> {
+    let app_types = match app_type {
+        Some(app_type) => vec![parse_app_type(app_type)?],
+        None => vec![
+            "claude".to_string(),
+            "codex".to_string(),
+            "gemini".to_string(),
+        ],
+    };
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(3))
+        .build()
+        .map_err(|e| AppError::Message(format!("创建HTTP客户端失败: {e}")))?;
+
+    let checks = doctor::run(&Database::file_path(), &app_types, &client).await?;
+    if json {
+        let (passed, warnings, failures) = doctor::tally(&checks);
+        let value = json!({
+            "checks": checks,
+            "passed": passed,
+            "warnings": warnings,
+            "failures": failures,
+        });
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&value).unwrap_or_default()
+        );
+    } else {
+        for line in doctor::format_lines(&checks) {
+            println!("{}", line);
+        }
+    }
+
+    let (_, _, failures) = doctor::tally(&checks);
+    if failures > 0 {
+        return Err(AppError::Message(format!("自检发现 {failures} 项失败")));
+    }
+    Ok(())
+}
+
+async fn handle_replay(
+    app_type: &str,
+    provider_id: &str,
+    from_log: Option<String>,
+    file: Option<PathBuf>,
+    bytes: usize,
+) -> Result<(), AppError> {
+    use cc_switch_lib::proxy::replay;
+
+    let app_type_str = parse_app_type(app_type)?;
+    let app = app_type_str.parse::<cc_switch_lib::AppType>()?;
+    let db = Database::init()?;
+    if db.get_provider_by_id(provider_id, &app_type_str)?.is_none() {
+        return Err(AppError::Message(format!("供应商不存在: {provider_id}")));
+    }
+
+    let capture = match (from_log, file) {
+        (Some(request_id), _) => db
+            .get_request_capture(&app_type_str, &request_id)?
+            .ok_or_else(|| {
+                AppError::Message(format!(
+                    "未找到请求 {request_id} 的捕获记录（需在 settings.json 开启 captureBodies{}）",
+                    match app {
+                        cc_switch_lib::AppType::Claude => "Claude",
+                        cc_switch_lib::AppType::Codex => "Codex",
+                        cc_switch_lib::AppType::Gemini => "Gemini",
+                    }
+                ))
+            })?,
+        (None, Some(path)) => {
+            let text = std::fs::read_to_string(&path)
+                .map_err(|e| AppError::Message(format!("读取 {} 失败: {e}", path.display())))?;
+            let raw: serde_json::Value = serde_json::from_str(&text)
+                .map_err(|e| AppError::Message(format!("解析 {} 失败: {e}", path.display())))?;
+            replay::reconstruct(&app, raw)?
+        }
+        (None, None) => {
+            return Err(AppError::Message("需指定 --from-log 或 --file".to_string()));
+        }
+    };
+    if capture.truncated {
+        println!("⚠ 捕获时部分字段已截断，重放结果可能与原请求不同");
+    }
+
+    // 重放会等待完整的上游响应头，超时放宽
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(300))
+        .build()
+        .map_err(|e| AppError::Message(format!("创建HTTP客户端失败: {e}")))?;
+    let base = find_running_proxy_base(&db, &client).await?;
+
+    println!("重放 {} → 供应商 {}", capture.endpoint, provider_id);
+    let outcome = replay::replay(&client, &base, &app, provider_id, &capture, bytes).await?;
+    println!("状态: {}  耗时: {}ms", outcome.status, outcome.latency_ms);
+    println!("{}", outcome.preview);
+    if outcome.preview_truncated {
+        println!("…（仅显示前 {bytes} 字节）");
+    }
+    Ok(())
+}
+
+async fn handle_compare(
+    app_type: &str,
+    providers: &[String],
+    prompt: &str,
+    model: Option<&str>,
+    chars: usize,
+    json: bool,
+) -> Result<(), AppError> {
+    use cc_switch_lib::proxy::compare;
+
+    let app_type_str = parse_app_type(app_type)?;
+    let app = app_type_str.parse::<cc_switch_lib::AppType>()?;
+    let providers: Vec<String> = providers
+        .iter()
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .collect();
+    if providers.len() < 2 {
+        return Err(AppError::Message("至少需要两个供应商参与对比".to_string()));
+    }
+    let request = compare::build_prompt_request(&app, prompt, model)?;
+
+    let db = Database::init()?;
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(300))
+        .build()
+        .map_err(|e| AppError::Message(format!("创建HTTP客户端失败: {e}")))?;
+    let base = find_running_proxy_base(&db, &client).await?;
+
+    let sides = compare::compare(&client, &base, &app, &providers, &request, chars).await;
+    if json {
+        let value = json!({
+            "appType": app_type_str,
+            "endpoint": request.endpoint,
+            "results": sides,
+        });
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&value).unwrap_or_default()
+        );
+        return Ok(());
+    }
+
+    for line in self::compare::format_lines(&app_type_str, &sides) {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+fn handle_benchmark(action: BenchmarkAction) -> Result<(), AppError> {
+    match action {
+        BenchmarkAction::History {
+            app_type,
+            supplier,
+            since,
+            json,
+        } => {
+            let app_type_str = parse_app_type(&app_type)?;
+            let since_ts = chrono::Utc::now().timestamp() - parse_duration_secs(&since)?;
+            let db = Database::init()?;
+
+            let (lines, value) = match supplier.as_deref() {
+                Some(supplier) => {
+                    let points =
+                        db.get_benchmark_series(&app_type_str, supplier, None, since_ts)?;
+                    (
+                        format_benchmark_series(&points),
+                        serde_json::to_value(&points),
+                    )
+                }
+                None => {
+                    let entries = db.get_latest_benchmarks(&app_type_str, since_ts)?;
+                    (
+                        format_benchmark_latest(&entries),
+                        serde_json::to_value(&entries),
+                    )
+                }
+            };
+
+            if json {
+                let value = value.map_err(|e| AppError::JsonSerialize { source: e })?;
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&value).unwrap_or_default()
+                );
+                return Ok(());
+            }
+
+            println!("\n=== {} 测速历史（最近 {}） ===", app_type_str, since);
+            if lines.is_empty() {
+                println!("  暂无测速记录");
+            }
+            for line in lines {
+                println!("{}", line);
+            }
+        }
+        BenchmarkAction::Diff {
+            app_type,
+            supplier,
+            runs,
+            json,
+        } => {
+            let app_type_str = parse_app_type(&app_type)?;
+            let db = Database::init()?;
+            let diffs =
+                db.get_benchmark_diffs(&app_type_str, supplier.as_deref(), runs as usize)?;
+
+            if json {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&diffs)
+                        .map_err(|e| AppError::JsonSerialize { source: e })?
+                );
+                return Ok(());
+            }
+
+            println!("\n=== {} 测速对比 ===", app_type_str);
+            if diffs.is_empty() {
+                println!("  暂无可对比的测速记录（每个 supplier 至少需要测速两次）");
+            }
+            let color = use_color();
+            for line in format_benchmark_diff(&diffs, color) {
+                println!("{}", line);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// 终端输出且未设置 NO_COLOR 时启用颜色
+fn use_color() -> bool {
+    use std::io::IsTerminal;
+    std::env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal()
+}
+
+const ANSI_RED: &str = "31";
+const ANSI_GREEN: &str = "32";
+const ANSI_YELLOW: &str = "33";
+
+fn paint(text: &str, code: &str, color: bool) -> String {
+    if color {
+        format!("\x1b[{code}m{text}\x1b[0m")
+    } else {
+        text.to_string()
+    }
+}
+
+/// 结果类型变化的颜色：变为 OK 为绿，OK 变为其它为红，其余为黄
+fn kind_transition_color(before: &str, after: &str) -> &'static str {
+    match (before, after) {
+        (_, "OK") => ANSI_GREEN,
+        ("OK", _) => ANSI_RED,
+        _ => ANSI_YELLOW,
+    }
+}
+
+fn format_kind_transition(before: &str, after: &str, color: bool) -> String {
+    if before == after {
+        after.to_string()
+    } else {
+        paint(
+            &format!("{before} → {after}"),
+            kind_transition_color(before, after),
+            color,
+        )
+    }
+}
+
+/// 延迟变化：变快为绿，变慢为红
+fn format_delta_ms(delta: Option<i64>, color: bool) -> String {
+    match delta {
+        Some(d) if d < 0 => paint(&format!("（{d}ms）"), ANSI_GREEN, color),
+        Some(d) if d > 0 => paint(&format!("（+{d}ms）"), ANSI_RED, color),
+        Some(_) => "（±0ms）".to_string(),
+        None => String::new(),
+    }
+}
+
+/// 各 supplier 两次测速的对比：首行为选中结果，其后每个 URL 一行（`+` 新增，`-` 消失）
+fn format_benchmark_diff(
+    diffs: &[cc_switch_lib::BenchmarkSupplierDiff],
+    color: bool,
+) -> Vec<String> {
+    use cc_switch_lib::BenchmarkUrlChange;
+
+    let mut lines = Vec::new();
+    for d in diffs {
+        lines.push(format!(
+            "  [层级:{}] {:<16} {} → {}  {} {} → {}{}",
+            d.priority,
+            d.supplier,
+            format_history_time(d.before_at),
+            format_history_time(d.after_at),
+            format_kind_transition(&d.before_kind, &d.after_kind, color),
+            format_history_ms(d.before_metric_ms),
+            format_history_ms(d.after_metric_ms),
+            format_delta_ms(
+                match (d.before_metric_ms, d.after_metric_ms) {
+                    (Some(b), Some(a)) => Some(a as i64 - b as i64),
+                    _ => None,
+                },
+                color
+            )
+        ));
+        for u in &d.urls {
+            let line = match u.change {
+                BenchmarkUrlChange::Kept => format!(
+                    "      {}  {}  {} → {}{}",
+                    u.url,
+                    format_kind_transition(
+                        u.before_kind.as_deref().unwrap_or("-"),
+                        u.after_kind.as_deref().unwrap_or("-"),
+                        color
+                    ),
+                    format_history_ms(u.before_ms),
+                    format_history_ms(u.after_ms),
+                    format_delta_ms(u.delta_ms, color)
+                ),
+                BenchmarkUrlChange::Appeared => paint(
+                    &format!(
+                        "    + {}  {} {}（新增）",
+                        u.url,
+                        u.after_kind.as_deref().unwrap_or("-"),
+                        format_history_ms(u.after_ms)
+                    ),
+                    ANSI_GREEN,
+                    color,
+                ),
+                BenchmarkUrlChange::Disappeared => paint(
+                    &format!(
+                        "    - {}  {} {}（消失）",
+                        u.url,
+                        u.before_kind.as_deref().unwrap_or("-"),
+                        format_history_ms(u.before_ms)
+                    ),
+                    ANSI_RED,
+                    color,
+                ),
+            };
+            lines.push(line);
+        }
+    }
+    lines
+}
+
+fn handle_events(
+    app_type: &str,
+    kind: Option<&str>,
+    since: &str,
+    limit: usize,
+    json: bool,
+) -> Result<(), AppError> {
+    let app_type = parse_app_type(app_type)?;
+    let kind = kind.map(str::parse::<EventKind>).transpose()?;
+    let since = chrono::Utc::now().timestamp() - parse_duration_secs(since)?;
+    let db = Database::init()?;
+    let events = db.query_events(&app_type, kind, since, limit)?;
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&events).unwrap_or_default()
+        );
+        return Ok(());
+    }
+    if events.is_empty() {
+        println!("暂无事件（代理每 2 秒批量写入一次）");
+        return Ok(());
+    }
+    // 按时间正序输出，最新的在最后
+    for event in events.iter().rev() {
+        println!("{}", format_event_line(event));
+    }
+    Ok(())
+}
+
+fn format_event_line(event: &EventLogEntry) -> String {
+    let target = match (&event.provider_id, &event.supplier) {
+        (Some(id), Some(supplier)) => format!("{id} ({supplier})"),
+        (Some(id), None) => id.clone(),
+        (None, Some(supplier)) => supplier.clone(),
+        (None, None) => "-".to_string(),
+    };
+    let mut line = format!(
+        "{}  {:<18} {}",
+        format_history_time(event.created_at),
+        event.kind.as_str(),
+        target
+    );
+    if let Some(detail) = event.detail.as_object().filter(|d| !d.is_empty()) {
+        let parts: Vec<String> = detail
+            .iter()
+            .filter(|(_, v)| !v.is_null())
+            .map(|(k, v)| match v {
+                serde_json::Value::String(s) => format!("{k}={s}"),
+                other => format!("{k}={other}"),
+            })
+            .collect();
+        if !parts.is_empty() {
+            line.push_str("  ");
+            line.push_str(&parts.join(" "));
+        }
+    }
+    line
+}
+
+fn format_history_time(ts: i64) -> String {
+    chrono::DateTime::from_timestamp(ts, 0)
+        .map(|t| {
+            t.with_timezone(&chrono::Local)
+                .format("%m-%d %H:%M")
+                .to_string()
+        })
+        .unwrap_or_else(|| ts.to_string())
+}
+
+fn format_history_ms(ms: Option<u64>) -> String {
+    ms.map(|v| format!("{v}ms"))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+/// 各 supplier 最近一次测速
+fn format_benchmark_latest(entries: &[cc_switch_lib::BenchmarkHistoryEntry]) -> Vec<String> {
+    entries
+        .iter()
+        .map(|e| {
+            let r = &e.result;
+            format!(
+                "  {}  [层级:{}] {:<16} {:<8} {:>8}  {}",
+                format_history_time(e.created_at),
+                r.priority,
+                r.supplier,
+                r.chosen_kind,
+                format_history_ms(r.metric_ms),
+                r.chosen_url.as_deref().unwrap_or("-")
+            )
+        })
+        .collect()
+}
+
+/// 单个 supplier 的测速序列，末尾附各 URL 的首末对比（`*` 为当次选中的 URL）
+fn format_benchmark_series(points: &[cc_switch_lib::BenchmarkSeriesPoint]) -> Vec<String> {
+    let mut lines: Vec<String> = points
+        .iter()
+        .map(|p| {
+            format!(
+                "  {}  {:<4} {:>8}{} {}",
+                format_history_time(p.created_at),
+                p.kind,
+                format_history_ms(p.latency_ms),
+                if p.chosen { "*" } else { " " },
+                p.url
+            )
+        })
+        .collect();
+
+    let mut by_url: std::collections::BTreeMap<&str, Vec<&cc_switch_lib::BenchmarkSeriesPoint>> =
+        std::collections::BTreeMap::new();
+    for p in points {
+        by_url.entry(p.url.as_str()).or_default().push(p);
+    }
+    if !by_url.is_empty() {
+        lines.push(String::new());
+    }
+    for (url, pts) in by_url {
+        let ok: Vec<u64> = pts
+            .iter()
+            .filter(|p| p.kind == "OK")
+            .filter_map(|p| p.latency_ms)
+            .collect();
+        let trend = match (ok.first(), ok.last()) {
+            (Some(first), Some(last)) => format!(
+                "平均 {}ms，{}ms → {}ms",
+                ok.iter().sum::<u64>() / ok.len() as u64,
+                first,
+                last
+            ),
+            _ => "无成功记录".to_string(),
+        };
+        lines.push(format!(
+            "  {}: OK {}/{}，{}",
+            url,
+            ok.len(),
+            pts.len(),
+            trend
+        ));
+    }
+    lines
+}
+
+/// 解析 `30m`/`4h`/`1d`/`90s` 形式的时长，返回秒数
+fn parse_duration_secs(s: &str) -> Result<i64, AppError> {
+    let s = s.trim();
+    let invalid = || AppError::InvalidInput(format!("无效的时长: {s}（示例: 30m、4h、1d）"));
+    let (num, unit) = s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()));
+    let value: i64 = num.parse().map_err(|_| invalid())?;
+    let factor = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        _ => return Err(invalid()),
+    };
+    match value.checked_mul(factor) {
+        Some(secs) if secs > 0 => Ok(secs),
+        _ => Err(invalid()),
+    }
+}
+
+/// 剩余时长的简短显示（如 `3h20m`）
+fn format_remaining(secs: i64) -> String {
+    let secs = secs.max(0);
+    let (d, h, m) = (secs / 86_400, secs % 86_400 / 3600, secs % 3600 / 60);
+    if d > 0 {
+        format!("{d}d{h}h")
+    } else if h > 0 {
+        format!("{h}h{m}m")
+    } else if m > 0 {
+        format!("{m}m")
+    } else {
+        format!("{secs}s")
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    const MB: f64 = 1024.0 * 1024.0;
+    if bytes as f64 >= MB {
+        format!("{:.1} MB", bytes as f64 / MB)
+    } else {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    }
+}
+
+// ============================================================================
+// 补全与文档
+// ============================================================================
+
+fn handle_completions(shell: clap_complete::Shell) -> Result<(), AppError> {
+    use clap::CommandFactory;
+
+    print!("{}", completions::render_completions(&mut Cli::command(), shell));
+    Ok(())
+}
+
+fn handle_docs(out_dir: Option<String>) -> Result<(), AppError> {
+    use clap::CommandFactory;
+    use std::io::Write;
+
+    let page = completions::render_man_page(Cli::command())
+        .map_err(|e| AppError::Message(format!("生成 man 页失败: {}", e)))?;
+
+    match out_dir {
+        Some(dir) => {
+            let dir = PathBuf::from(dir);
+            std::fs::create_dir_all(&dir)
+                .map_err(|e| AppError::Message(format!("创建目录失败: {}", e)))?;
+            let path = dir.join("csc.1");
+            std::fs::write(&path, page)
+                .map_err(|e| AppError::Message(format!("写入 man 页失败: {}", e)))?;
+            println!("✓ man 页已生成: {}", path.display());
+        }
+        None => {
+            std::io::stdout()
+                .write_all(&page)
+                .map_err(|e| AppError::Message(format!("输出 man 页失败: {}", e)))?;
+        }
+    }
+    Ok(())
+}
+
+fn handle_complete_providers(app_type: &str) -> Result<(), AppError> {
+    // 补全场景下静默失败：数据库不可用时不输出任何内容
+    let Ok(db) = Database::init() else {
+        return Ok(());
+    };
+    for id in completions::provider_ids_for_completion(&db, app_type) {
+        println!("{}", id);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claude_provider() -> Provider {
+        Provider::with_id(
+            "p1".to_string(),
+            "anyrouter-key1".to_string(),
+            json!({
+                "env": {
+                    "ANTHROPIC_BASE_URL": "https://api.example.com/",
+                    "ANTHROPIC_API_KEY": "sk-test",
+                    "ANTHROPIC_MODEL": "claude-sonnet-4-5",
+                    "ANTHROPIC_DEFAULT_HAIKU_MODEL": "claude-haiku-4-5-2cc"
+                }
+            }),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_proxy_stop_wait_follows_drain_timeout() {
+        use std::time::Duration;
+        assert_eq!(proxy_stop_wait(Some(120)), Duration::from_secs(130));
+        // 查询不到（或旧版本代理未返回）时按默认排空超时
+        let default_wait = Duration::from_secs(
+            cc_switch_lib::proxy::inflight::DEFAULT_DRAIN_TIMEOUT_SECS + PROXY_STOP_MARGIN_SECS,
+        );
+        assert_eq!(proxy_stop_wait(None), default_wait);
+        assert_eq!(proxy_stop_wait(Some(0)), default_wait);
+    }
+
+    #[tokio::test]
+    async fn test_verbose_list_row_includes_model_mappings_and_health() {
+        let db = Database::memory().expect("memory db");
+        let mut provider = claude_provider();
+        provider.notes = Some("备用线路".to_string());
+        db.save_provider("claude", &provider).expect("save provider");
+
+        for _ in 0..5 {
+            db.update_provider_health("p1", "claude", false, Some("HTTP 429: quota exceeded".to_string()))
+                .await
+                .expect("update health");
+        }
+
+        let health = db.get_all_provider_health("claude").await.expect("health");
+        let row = build_list_row("claude", &provider, health.get("p1"), false, true);
+        let output = format_list_row(&row).join("\n");
+
+        assert!(output.contains("ANTHROPIC_MODEL = claude-sonnet-4-5"));
+        assert!(output.contains("ANTHROPIC_DEFAULT_HAIKU_MODEL = claude-haiku-4-5-2cc"));
+        assert!(output.contains("base_url: https://api.example.com"));
+        assert!(output.contains("[异常 连续失败:5]"));
+        assert!(output.contains("最近错误: HTTP 429: quota exceeded"));
+        assert!(output.contains("备注: 备用线路"));
+    }
+
+    #[test]
+    fn test_event_line_shows_target_and_detail() {
+        let event = EventLogEntry {
+            id: 1,
+            created_at: 0,
+            app_type: "codex".to_string(),
+            kind: EventKind::Cooldown,
+            provider_id: None,
+            supplier: Some("mycorp".to_string()),
+            detail: json!({ "priority": 1, "reason": "overloaded", "note": null }),
+        };
+        let line = format_event_line(&event);
+        assert!(line.contains("cooldown"));
+        assert!(line.contains("mycorp"));
+        assert!(line.ends_with("priority=1 reason=overloaded"));
+    }
+
+    #[test]
+    fn test_priority_changes_output() {
+        let changes = vec![
+            cc_switch_lib::PriorityChange {
+                id: "p1".to_string(),
+                name: "acme-p1".to_string(),
+                from: None,
+                to: DEFAULT_PRIORITY,
+            },
+            cc_switch_lib::PriorityChange {
+                id: "p2".to_string(),
+                name: "acme-p2".to_string(),
+                from: Some(20),
+                to: 1,
+            },
+        ];
+        let output = format_priority_changes(&changes).join("\n");
+        assert!(output.contains("已调整 2 个供应商"));
+        assert!(output.contains("p1 - acme-p1  - → 100"));
+        assert!(output.contains("p2 - acme-p2  20 → 1"));
+        assert_eq!(format_priority_changes(&[]), vec!["✓ 优先级层级无需调整"]);
+    }
+
+    #[test]
+    fn test_list_stats_sorting_and_display() {
+        use cc_switch_lib::ProviderSuccessRate;
+
+        let stats = |requests: u64, success: u64, latency: Option<u64>| ProviderSuccessRate {
+            requests,
+            success,
+            success_rate: (requests > 0).then(|| success as f64 / requests as f64),
+            avg_latency_ms: latency,
+        };
+        let mut rows = Vec::new();
+        for (id, s) in [
+            ("idle", stats(0, 0, None)),
+            ("flaky", stats(10, 5, Some(300))),
+            ("solid", stats(4, 4, Some(900))),
+            ("busy", stats(20, 19, Some(600))),
+            ("broken", stats(3, 0, None)),
+        ] {
+            let mut provider = claude_provider();
+            provider.id = id.to_string();
+            let mut row = build_list_row("claude", &provider, None, false, false);
+            row.stats = Some(s);
+            rows.push(row);
+        }
+        let order = |rows: &[ProviderListRow]| {
+            rows.iter()
+                .map(|r| r.id.as_str())
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+
+        sort_list_rows(&mut rows, ListSort::Success);
+        assert_eq!(order(&rows), "solid,busy,flaky,broken,idle");
+        sort_list_rows(&mut rows, ListSort::Latency);
+        assert_eq!(order(&rows), "flaky,busy,solid,broken,idle");
+        sort_list_rows(&mut rows, ListSort::Requests);
+        assert_eq!(order(&rows), "busy,flaky,solid,broken,idle");
+
+        let output = format_list_row(&rows[0]).join("\n");
+        assert!(output.contains("成功率 95.0%  请求 20  平均延迟 600ms"));
+        // 无流量显示 "-" 而不是 100%
+        let output = format_list_row(&rows[4]).join("\n");
+        assert!(output.contains("成功率 -  请求 0  平均延迟 -"));
+
+        assert_eq!(parse_list_sort("Latency").unwrap(), ListSort::Latency);
+        assert!(parse_list_sort("fastest").is_err());
+    }
+
+    #[test]
+    fn test_queue_tiers_group_by_priority() {
+        let mut rows = Vec::new();
+        for (id, priority, current) in [
+            ("p1", Some(1), false),
+            ("p2", None, false),
+            ("p3", Some(1), true),
+        ] {
+            let mut provider = claude_provider();
+            provider.id = id.to_string();
+            provider.name = format!("acme-{id}");
+            provider.sort_index = priority;
+            rows.push(build_list_row("claude", &provider, None, current, false));
+        }
+        rows[0].is_healthy = false;
+        rows[0].consecutive_failures = 3;
+
+        let output = format_queue_tiers(&rows);
+        assert_eq!(
+            output,
+            vec![
+                "  层级 1:".to_string(),
+                "    p1 - acme-p1 [异常 连续失败:3]".to_string(),
+                "    p3 - acme-p3  [当前]".to_string(),
+                format!("  层级 {}:", DEFAULT_PRIORITY),
+                "    p2 - acme-p2".to_string(),
+            ]
+        );
+        assert_eq!(format_queue_tiers(&[]), vec!["  故障转移队列为空"]);
+    }
+
+    #[test]
+    fn test_env_check_lists_unresolved_placeholders() {
+        let missing = vec![(
+            "p1".to_string(),
+            "acme-p1".to_string(),
+            MissingEnvVar {
+                var: "ACME_KEY".to_string(),
+                path: "env.ANTHROPIC_AUTH_TOKEN".to_string(),
+            },
+        )];
+        let output = format_env_check(2, &missing);
+        assert_eq!(output[0], "⚠ 1/2 个占位符未解析:");
+        assert_eq!(
+            output[1],
+            "  p1 - acme-p1  env.ANTHROPIC_AUTH_TOKEN → ${ACME_KEY}"
+        );
+
+        assert_eq!(format_env_check(2, &[]), vec!["✓ 2 个占位符均已解析"]);
+        assert_eq!(format_env_check(0, &[]), vec!["  未发现 ${ENV_VAR} 占位符"]);
+    }
+
+    #[test]
+    fn test_non_verbose_row_hides_mappings() {
+        let provider = claude_provider();
+        let row = build_list_row("claude", &provider, None, true, false);
+        let output = format_list_row(&row).join("\n");
+
+        assert!(row.is_healthy);
+        assert!(output.contains("[当前]"));
+        assert!(!output.contains("ANTHROPIC_MODEL"));
+        assert!(!output.contains("base_url"));
+    }
+
+    #[tokio::test]
+    async fn test_depleted_row_shows_upstream_message() {
+        let db = Database::memory().expect("memory db");
+        let provider = claude_provider();
+        db.save_provider("claude", &provider).expect("save provider");
+        db.mark_provider_depleted("p1", "claude", "Insufficient Balance")
+            .await
+            .expect("mark depleted");
+
+        let health = db.get_all_provider_health("claude").await.expect("health");
+        let row = build_list_row("claude", &provider, health.get("p1"), false, false);
+        let output = format_list_row(&row).join("\n");
+        assert!(output.contains("[余额耗尽]"), "{output}");
+        assert!(output.contains("余额耗尽: Insufficient Balance"), "{output}");
+
+        // 手动重置（记录一次成功）后清除
+        db.update_provider_health("p1", "claude", true, None)
+            .await
+            .expect("reset");
+        let health = db.get_all_provider_health("claude").await.expect("health");
+        let row = build_list_row("claude", &provider, health.get("p1"), false, false);
+        assert!(!format_list_row(&row).join("\n").contains("余额耗尽"));
+    }
+
+    #[test]
+    fn test_disabled_row_shows_remaining_time() {
+        let provider = claude_provider();
+        let mut row = build_list_row("claude", &provider, None, false, false);
+        row.disabled_until = Some(chrono::Utc::now().timestamp() + 2 * 3600 + 1800 + 30);
+        let output = format_list_row(&row).join("\n");
+        assert!(output.contains("[禁用 剩余2h30m]"), "{output}");
+
+        assert_eq!(parse_duration_secs("4h").unwrap(), 4 * 3600);
+        assert_eq!(parse_duration_secs("30m").unwrap(), 1800);
+        assert_eq!(parse_duration_secs("1d").unwrap(), 86_400);
+        assert!(parse_duration_secs("0h").is_err());
+        assert!(parse_duration_secs("4w").is_err());
+        assert!(parse_duration_secs("h").is_err());
+    }
+
+    #[test]
+    fn test_benchmark_history_output() {
+        use cc_switch_lib::proxy::provider_router::{BenchmarkSupplierResult, BenchmarkUrlResult};
+
+        let db = Database::memory().expect("memory db");
+        let now = chrono::Utc::now().timestamp();
+        let url = "https://a.example.com";
+        for (days_ago, latency) in [(2, 400u64), (1, 800)] {
+            let result = BenchmarkSupplierResult {
+                priority: 0,
+                supplier: "acme".to_string(),
+                request_model: None,
+                effective_model: None,
+                chosen_url: Some(url.to_string()),
+                chosen_kind: "OK".to_string(),
+                metric_ms: Some(latency),
+                urls: vec![BenchmarkUrlResult {
+                    url: url.to_string(),
+                    kind: "OK".to_string(),
+                    latency_ms: Some(latency),
+                    penalty_ms: None,
+                    message: None,
+                    reason: None,
+                }],
+                cooldown_reason: None,
+            };
+            db.save_benchmark_results("claude", &[result], now - days_ago * 86_400)
+                .expect("save history");
+        }
+
+        let since = now - parse_duration_secs("7d").unwrap();
+        let latest = format_benchmark_latest(&db.get_latest_benchmarks("claude", since).unwrap());
+        assert_eq!(latest.len(), 1);
+        assert!(latest[0].contains("[层级:0] acme"), "{latest:?}");
+        assert!(latest[0].contains("800ms"));
+
+        let series = db
+            .get_benchmark_series("claude", "acme", None, since)
+            .unwrap();
+        let output = format_benchmark_series(&series).join("\n");
+        assert!(output.contains("400ms* https://a.example.com"), "{output}");
+        assert!(output.contains("https://a.example.com: OK 2/2，平均 600ms，400ms → 800ms"));
+
+        let json = serde_json::to_value(&series).unwrap();
+        assert_eq!(json[1]["latencyMs"], 800);
+    }
+
+    #[test]
+    fn test_benchmark_progress_line() {
+        assert_eq!(
+            format_benchmark_progress(0, 4, &[]),
+            "测速中 [--------------------] 0/4"
+        );
+        let running = vec!["acme".to_string(), "beta".to_string()];
+        assert_eq!(
+            format_benchmark_progress(1, 4, &running),
+            "测速中 [#####---------------] 1/4 进行中: acme, beta"
+        );
+        assert_eq!(
+            format_benchmark_progress(0, 0, &[]),
+            "测速中 [####################] 0/0"
+        );
+    }
+
+    #[test]
+    fn test_benchmark_diff_output() {
+        use cc_switch_lib::proxy::provider_router::{BenchmarkSupplierResult, BenchmarkUrlResult};
+
+        let db = Database::memory().expect("memory db");
+        let now = chrono::Utc::now().timestamp();
+        let (a, b, c) = (
+            "https://a.example.com",
+            "https://b.example.com",
+            "https://c.example.com",
+        );
+        let runs = [
+            (2, vec![(a, "OK", Some(400u64)), (b, "OK", Some(300))]),
+            (
+                1,
+                vec![(a, "OK", Some(250)), (b, "FAIL", None), (c, "FB", Some(20))],
+            ),
+        ];
+        for (hours_ago, urls) in runs {
+            let result = BenchmarkSupplierResult {
+                priority: 0,
+                supplier: "acme".to_string(),
+                request_model: None,
+                effective_model: None,
+                chosen_url: Some(a.to_string()),
+                chosen_kind: "OK".to_string(),
+                metric_ms: urls[0].2,
+                urls: urls
+                    .iter()
+                    .map(|(url, kind, latency_ms)| BenchmarkUrlResult {
+                        url: url.to_string(),
+                        kind: kind.to_string(),
+                        latency_ms: *latency_ms,
+                        penalty_ms: None,
+                        message: None,
+                        reason: None,
+                    })
+                    .collect(),
+                cooldown_reason: None,
+            };
+            db.save_benchmark_results("claude", &[result], now - hours_ago * 3600)
+                .expect("save history");
+        }
+
+        let diffs = db.get_benchmark_diffs("claude", None, 2).unwrap();
+        let output = format_benchmark_diff(&diffs, false).join("\n");
+        assert!(output.contains("[层级:0] acme"), "{output}");
+        assert!(output.contains("OK 400ms → 250ms（-150ms）"), "{output}");
+        assert!(
+            output.contains("https://b.example.com  OK → FAIL  300ms → -"),
+            "{output}"
+        );
+        assert!(
+            output.contains("+ https://c.example.com  FB 20ms（新增）"),
+            "{output}"
+        );
+
+        let colored = format_benchmark_diff(&diffs, true).join("\n");
+        assert!(colored.contains("\x1b[32m（-150ms）\x1b[0m"), "{colored}");
+        assert!(colored.contains("\x1b[31mOK → FAIL\x1b[0m"), "{colored}");
+
+        let json = serde_json::to_value(&diffs).unwrap();
+        assert_eq!(json[0]["urls"][0]["deltaMs"], -150);
+        assert_eq!(json[0]["urls"][2]["change"], "appeared");
+    }
+
+    #[test]
+    fn test_truncate_display_is_width_aware() {
+        let ascii = "x".repeat(100);
+        let truncated = truncate_display(&ascii, 60);
+        assert_eq!(truncated.chars().count(), 60);
+        assert!(truncated.ends_with('…'));
+
+        // 中文按 2 列计算：59 列内最多放 29 个字符，再加省略号
+        let cjk = "错".repeat(40);
+        let truncated = truncate_display(&cjk, 60);
+        assert_eq!(truncated.chars().count(), 30);
+
+        assert_eq!(truncate_display("短错误", 60), "短错误");
+    }
+}
@@ -0,0 +1,218 @@
+//! 供应商配置审计（`csc audit`）
+//!
+//! 列出共享同一 API Key 的供应商分组（只显示 key 指纹）以及缺少 base_url / key /
+//! 优先级层级、跨家族模型映射等问题。
+//!
+//! 代理运行中时通过 `GET /admin/audit/:app_type` 在代理进程内审计，跨家族映射可基于
+//! 已缓存的 `/v1/models` 列表给出修正建议；否则在本地审计（无建议）。
+
+use cc_switch_lib::{KeyHolder, ProviderAuditReport, ProviderIssue, DEFAULT_PRIORITY};
+
+fn holder_line(holder: &KeyHolder) -> String {
+    let level = match (holder.in_failover_queue, holder.sort_index) {
+        (false, _) => "不在队列".to_string(),
+        (true, Some(p)) => format!("层级 {p}"),
+        (true, None) => "层级 -".to_string(),
+    };
+    format!(
+        "    {} - {}  {}  {}",
+        holder.id,
+        holder.name,
+        level,
+        holder.base_url.as_deref().unwrap_or("(无 base_url)")
+    )
+}
+
+fn issue_line(issue: &ProviderIssue) -> String {
+    match issue {
+        ProviderIssue::MissingBaseUrl {
+            provider_id,
+            provider_name,
+        } => format!(
+            "  {provider_id} - {provider_name}  在故障转移队列中但未配置 base_url，会被跳过"
+        ),
+        ProviderIssue::UnresolvedBaseUrl {
+            provider_id,
+            provider_name,
+            error,
+        } => format!("  {provider_id} - {provider_name}  base_url 无法展开（{error}），会被跳过"),
+        ProviderIssue::MissingApiKey {
+            provider_id,
+            provider_name,
+        } => {
+            format!("  {provider_id} - {provider_name}  在故障转移队列中但未配置 API Key，会被跳过")
+        }
+        ProviderIssue::MissingSortIndex {
+            provider_id,
+            provider_name,
+        } => format!(
+            "  {provider_id} - {provider_name}  未设置优先级层级，按默认层级 {DEFAULT_PRIORITY} 参与路由（csc normalize-priorities 可写入）"
+        ),
+        ProviderIssue::CrossFamilyMapping {
+            provider_id,
+            provider_name,
+            env_key,
+            value,
+            suggestion,
+        } => {
+            let line = format!(
+                "  {provider_id} - {provider_name}  {env_key}={value} 跨模型家族，请求时会被忽略"
+            );
+            match suggestion {
+                Some(s) => format!("{line}，建议改为 {s}"),
+                None => line,
+            }
+        }
+    }
+}
+
+/// 从运行中的代理读取审计结果
+pub async fn fetch_admin_audit(
+    client: &reqwest::Client,
+    base: &str,
+    app_type: &str,
+) -> Result<ProviderAuditReport, String> {
+    let resp = client
+        .get(format!(
+            "{}/admin/audit/{}",
+            base.trim_end_matches('/'),
+            app_type
+        ))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !resp.status().is_success() {
+        return Err(format!("HTTP {}", resp.status().as_u16()));
+    }
+    resp.json::<ProviderAuditReport>()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 可读输出（逐行），`from_proxy` 表示结果来自运行中的代理
+pub fn format_lines(report: &ProviderAuditReport, from_proxy: bool) -> Vec<String> {
+    let mut lines = vec![format!("\n=== {} 配置审计 ===", report.app_type)];
+
+    if report.is_clean() {
+        lines.push("✓ 未发现问题".to_string());
+        return lines;
+    }
+
+    if !report.duplicate_keys.is_empty() {
+        lines.push("重复 API Key:".to_string());
+        for group in &report.duplicate_keys {
+            lines.push(format!(
+                "  [指纹 {}] {} 个供应商",
+                group.fingerprint,
+                group.providers.len()
+            ));
+            lines.extend(group.providers.iter().map(holder_line));
+            for (priority, holders) in group.same_priority_sets() {
+                lines.push(format!(
+                    "    ⚠ 层级 {} 内 {} 个供应商共享该 key，相互之间的故障转移不会生效",
+                    priority,
+                    holders.len()
+                ));
+            }
+        }
+    }
+
+    if !report.issues.is_empty() {
+        lines.push("其他问题:".to_string());
+        lines.extend(report.issues.iter().map(issue_line));
+
+        let cross_family = report
+            .issues
+            .iter()
+            .any(|i| matches!(i, ProviderIssue::CrossFamilyMapping { .. }));
+        if cross_family && !from_proxy {
+            lines.push("  代理未运行，无法基于模型列表给出修正建议".to_string());
+        }
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cc_switch_lib::DuplicateKeyGroup;
+
+    fn holder(id: &str, sort_index: Option<usize>, in_queue: bool) -> KeyHolder {
+        KeyHolder {
+            id: id.to_string(),
+            name: format!("{id}-name"),
+            sort_index,
+            in_failover_queue: in_queue,
+            base_url: Some(format!("https://{id}.example.com")),
+        }
+    }
+
+    #[test]
+    fn test_format_lines_lists_groups_and_issues() {
+        let report = ProviderAuditReport {
+            app_type: "claude".to_string(),
+            duplicate_keys: vec![DuplicateKeyGroup {
+                fingerprint: "1a2b3c4d".to_string(),
+                providers: vec![
+                    holder("p1", Some(1), true),
+                    holder("p2", Some(1), true),
+                    holder("p3", Some(2), false),
+                ],
+            }],
+            issues: vec![
+                ProviderIssue::MissingBaseUrl {
+                    provider_id: "p4".to_string(),
+                    provider_name: "p4-name".to_string(),
+                },
+                ProviderIssue::UnresolvedBaseUrl {
+                    provider_id: "p7".to_string(),
+                    provider_name: "p7-name".to_string(),
+                    error: "环境变量 RELAY_URL 未设置".to_string(),
+                },
+                ProviderIssue::MissingSortIndex {
+                    provider_id: "p6".to_string(),
+                    provider_name: "p6-name".to_string(),
+                },
+                ProviderIssue::CrossFamilyMapping {
+                    provider_id: "p5".to_string(),
+                    provider_name: "p5-name".to_string(),
+                    env_key: "ANTHROPIC_DEFAULT_HAIKU_MODEL".to_string(),
+                    value: "glm-4.5".to_string(),
+                    suggestion: None,
+                },
+                ProviderIssue::CrossFamilyMapping {
+                    provider_id: "p5".to_string(),
+                    provider_name: "p5-name".to_string(),
+                    env_key: "ANTHROPIC_DEFAULT_OPUS_MODEL".to_string(),
+                    value: "gpt-5".to_string(),
+                    suggestion: Some("claude-opus-4-5".to_string()),
+                },
+            ],
+        };
+
+        let text = format_lines(&report, false).join("\n");
+        assert!(text.contains("[指纹 1a2b3c4d] 3 个供应商"));
+        assert!(text.contains("    p1 - p1-name  层级 1  https://p1.example.com"));
+        assert!(text.contains("    p3 - p3-name  不在队列"));
+        assert!(text.contains("⚠ 层级 1 内 2 个供应商共享该 key"));
+        assert!(text.contains("p4 - p4-name  在故障转移队列中但未配置 base_url"));
+        assert!(text.contains("p7 - p7-name  base_url 无法展开（环境变量 RELAY_URL 未设置"));
+        assert!(text.contains("p6 - p6-name  未设置优先级层级，按默认层级 100 参与路由"));
+        assert!(text.contains("ANTHROPIC_DEFAULT_HAIKU_MODEL=glm-4.5 跨模型家族，请求时会被忽略\n"));
+        assert!(text.contains("ANTHROPIC_DEFAULT_OPUS_MODEL=gpt-5 跨模型家族，请求时会被忽略，建议改为 claude-opus-4-5"));
+        assert!(text.contains("代理未运行，无法基于模型列表给出修正建议"));
+        assert!(!format_lines(&report, true)
+            .join("\n")
+            .contains("代理未运行"));
+
+        let clean = ProviderAuditReport {
+            app_type: "codex".to_string(),
+            duplicate_keys: Vec::new(),
+            issues: Vec::new(),
+        };
+        assert!(format_lines(&clean, false)
+            .join("\n")
+            .contains("✓ 未发现问题"));
+    }
+}
@@ -0,0 +1,133 @@
+//! 供应商 A/B 对比输出（`csc compare`）
+//!
+//! 指标按列并排显示（每个供应商一列），回复开头逐个供应商列在表格下方。
+
+use cc_switch_lib::i18n::{display_width, pad_display};
+use cc_switch_lib::proxy::compare::CompareSide;
+
+fn or_dash(value: Option<String>) -> String {
+    value.unwrap_or_else(|| "-".to_string())
+}
+
+/// 表格行：(指标名, 每个供应商的取值)
+fn metric_rows(sides: &[CompareSide]) -> Vec<(&'static str, Vec<String>)> {
+    let column = |f: &dyn Fn(&CompareSide) -> String| sides.iter().map(f).collect::<Vec<_>>();
+    vec![
+        ("供应商", column(&|s| s.provider_id.clone())),
+        (
+            "状态",
+            column(&|s| or_dash(s.status.map(|v| v.to_string()))),
+        ),
+        (
+            "耗时",
+            column(&|s| or_dash(s.latency_ms.map(|v| format!("{v}ms")))),
+        ),
+        (
+            "输入 token",
+            column(&|s| or_dash(s.input_tokens.map(|v| v.to_string()))),
+        ),
+        (
+            "输出 token",
+            column(&|s| or_dash(s.output_tokens.map(|v| v.to_string()))),
+        ),
+        ("实际模型", column(&|s| or_dash(s.effective_model.clone()))),
+        ("错误", column(&|s| or_dash(s.error.clone()))),
+    ]
+}
+
+/// 可读输出（逐行）
+pub fn format_lines(app_type: &str, sides: &[CompareSide]) -> Vec<String> {
+    let rows = metric_rows(sides);
+    let label_width = rows
+        .iter()
+        .map(|(l, _)| display_width(l))
+        .max()
+        .unwrap_or(0);
+    let widths: Vec<usize> = (0..sides.len())
+        .map(|i| {
+            rows.iter()
+                .map(|(_, values)| display_width(&values[i]))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let mut lines = vec![format!("\n=== {app_type} 供应商对比 ===")];
+    for (label, values) in &rows {
+        let cells: Vec<String> = values
+            .iter()
+            .zip(&widths)
+            .map(|(v, w)| pad_display(v, *w))
+            .collect();
+        lines.push(
+            format!(
+                "  {}  {}",
+                pad_display(label, label_width),
+                cells.join("  ")
+            )
+            .trim_end()
+            .to_string(),
+        );
+    }
+
+    lines.push(String::new());
+    for side in sides {
+        lines.push(format!("  [{}]", side.provider_id));
+        if side.preview.is_empty() {
+            lines.push("    （无回复）".to_string());
+            continue;
+        }
+        for text in side.preview.lines() {
+            lines.push(format!("    {text}"));
+        }
+        if side.preview_truncated {
+            lines.push("    …".to_string());
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_lines_puts_providers_side_by_side() {
+        let sides = vec![
+            CompareSide {
+                provider_id: "fast".to_string(),
+                status: Some(200),
+                latency_ms: Some(850),
+                input_tokens: Some(12),
+                output_tokens: Some(40),
+                effective_model: Some("gpt-5".to_string()),
+                preview: "第一行\n第二行".to_string(),
+                preview_truncated: true,
+                error: None,
+            },
+            CompareSide {
+                provider_id: "down".to_string(),
+                error: Some("连接代理失败".to_string()),
+                ..Default::default()
+            },
+        ];
+        let lines = format_lines("codex", &sides);
+        assert_eq!(lines[0], "\n=== codex 供应商对比 ===");
+        assert_eq!(lines[1], "  供应商      fast   down");
+        assert_eq!(lines[2], "  状态        200    -");
+        assert_eq!(lines[3], "  耗时        850ms  -");
+        assert_eq!(lines[6], "  实际模型    gpt-5  -");
+        assert_eq!(lines[7], "  错误        -      连接代理失败");
+        assert_eq!(
+            &lines[9..],
+            [
+                "  [fast]",
+                "    第一行",
+                "    第二行",
+                "    …",
+                "  [down]",
+                "    （无回复）"
+            ]
+        );
+    }
+}
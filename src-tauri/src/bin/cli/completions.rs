@@ -0,0 +1,136 @@
+//! Shell 补全脚本与 man 页生成
+//!
+//! - `csc completions <shell>`：基于 clap_complete 生成静态补全；bash/fish 额外追加
+//!   供应商 ID 的动态补全（调用隐藏子命令 `csc __complete-providers <app_type>` 读取本地数据库）
+//! - `csc docs`：基于 clap_mangen 生成 man 页
+
+use cc_switch_lib::Database;
+use clap::Command;
+use clap_complete::{generate, Shell};
+
+/// 第 2 个位置参数为供应商 ID 的子命令（含别名）
+const PROVIDER_ID_SUBCOMMANDS: &[&str] = &[
+    "remove",
+    "rm",
+    "enable",
+    "en",
+    "set-priority",
+    "sp",
+    "add-to-queue",
+    "qa",
+    "remove-from-queue",
+    "qr",
+    "test-latency",
+    "t",
+];
+
+const BASH_PROVIDER_HOOK: &str = r#"
+# 动态补全供应商 ID（读取本地数据库）
+___BIN___providers() {
+    local cur="${COMP_WORDS[COMP_CWORD]}"
+    if [[ ${COMP_CWORD} -eq 3 ]]; then
+        case "${COMP_WORDS[1]}" in
+            __SUBCOMMANDS__)
+                COMPREPLY=( $(compgen -W "$(__BIN__ __complete-providers "${COMP_WORDS[2]}" 2>/dev/null)" -- "${cur}") )
+                return 0
+                ;;
+        esac
+    fi
+    ___BIN__ "$@"
+}
+complete -F ___BIN___providers -o bashdefault -o default __BIN__
+"#;
+
+const FISH_PROVIDER_HOOK: &str = r#"
+# 动态补全供应商 ID（读取本地数据库）
+complete -c __BIN__ -n "__fish_seen_subcommand_from __SUBCOMMANDS__; and test (count (commandline -opc)) -eq 3" -f -a "(__BIN__ __complete-providers (commandline -opc)[3])"
+"#;
+
+/// 生成补全脚本
+pub fn render_completions(cmd: &mut Command, shell: Shell) -> String {
+    let bin_name = cmd.get_name().to_string();
+    let mut buf = Vec::new();
+    generate(shell, cmd, &bin_name, &mut buf);
+    let mut script = String::from_utf8_lossy(&buf).into_owned();
+
+    let hook = match shell {
+        Shell::Bash => Some((BASH_PROVIDER_HOOK, "|")),
+        Shell::Fish => Some((FISH_PROVIDER_HOOK, " ")),
+        _ => None,
+    };
+    if let Some((template, sep)) = hook {
+        script.push_str(
+            &template
+                .replace("__SUBCOMMANDS__", &PROVIDER_ID_SUBCOMMANDS.join(sep))
+                .replace("__BIN__", &bin_name),
+        );
+    }
+
+    script
+}
+
+/// 生成 man 页（roff 格式）
+pub fn render_man_page(cmd: Command) -> std::io::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    clap_mangen::Man::new(cmd).render(&mut buf)?;
+    Ok(buf)
+}
+
+/// 供补全脚本调用：列出指定应用下的供应商 ID（出错时返回空列表，避免干扰补全）
+pub fn provider_ids_for_completion(db: &Database, app_type: &str) -> Vec<String> {
+    let app_type = app_type.trim().to_lowercase();
+    if !matches!(app_type.as_str(), "claude" | "codex" | "gemini") {
+        return Vec::new();
+    }
+    db.get_all_providers(&app_type)
+        .map(|providers| providers.into_keys().collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cc_switch_lib::Provider;
+    use clap::CommandFactory;
+    use serde_json::json;
+
+    const BASH_GOLDEN_PATH: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/src/bin/cli/testdata/csc.bash");
+
+    /// 命令结构变化时，使用 `UPDATE_GOLDEN=1 cargo test` 重新生成
+    #[test]
+    fn test_bash_completions_match_golden() {
+        let script = render_completions(&mut crate::Cli::command(), Shell::Bash);
+
+        if std::env::var("UPDATE_GOLDEN").is_ok() {
+            std::fs::write(BASH_GOLDEN_PATH, &script).expect("write golden");
+        }
+
+        let golden = std::fs::read_to_string(BASH_GOLDEN_PATH).expect("read golden");
+        assert_eq!(script, golden);
+        assert!(script.contains("csc __complete-providers"));
+    }
+
+    #[test]
+    fn test_man_page_renders() {
+        let page = render_man_page(crate::Cli::command()).expect("render man page");
+        let page = String::from_utf8(page).expect("utf8");
+        assert!(page.contains(".TH csc"));
+    }
+
+    #[test]
+    fn test_provider_ids_from_db() {
+        let db = Database::memory().expect("memory db");
+        for id in ["p1", "p2"] {
+            let provider = Provider::with_id(id.to_string(), id.to_string(), json!({}), None);
+            db.save_provider("claude", &provider)
+                .expect("save provider");
+        }
+
+        let mut ids = provider_ids_for_completion(&db, "Claude");
+        ids.sort();
+        assert_eq!(ids, vec!["p1".to_string(), "p2".to_string()]);
+        assert!(provider_ids_for_completion(&db, "codex").is_empty());
+        assert!(provider_ids_for_completion(&db, "unknown").is_empty());
+    }
+}
@@ -0,0 +1,203 @@
+//! 代理守护进程（`csc proxy start --daemon`）
+//!
+//! - unix：重新执行自身，子进程在 exec 前 `setsid` + 二次 fork，彻底脱离终端与会话
+//! - Windows：以 `DETACHED_PROCESS | CREATE_NO_WINDOW` 启动分离进程
+//!
+//! 子进程的 stdout/stderr 追加写入 `~/.cc-switch/logs/rust_proxy.log`（启动前按大小轮转），
+//! PID 仍由子进程在监听成功后写入 `~/.cc-switch/proxy.pid`，stop/status/restart 无需区分前台或后台。
+
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// 守护子进程标记（子进程据此调整启动提示）
+pub const DAEMON_ENV: &str = "CC_SWITCH_DAEMON";
+
+/// 日志轮转阈值（与 install-ccs.sh 的 rotate_log_if_needed 保持一致）
+const LOG_MAX_BYTES: u64 = 10 * 1024 * 1024;
+const LOG_MAX_BACKUPS: usize = 5;
+
+/// 当前进程是否为守护子进程
+pub fn is_daemon_child() -> bool {
+    std::env::var(DAEMON_ENV).map(|v| v == "1").unwrap_or(false)
+}
+
+/// 以分离模式重新启动自身，stdout/stderr 写入日志文件
+///
+/// 返回时子进程已脱离当前终端；是否启动成功由调用方通过 PID 文件确认。
+pub fn spawn_detached(args: &[String], log_path: &Path) -> io::Result<()> {
+    let exe = std::env::current_exe()?;
+
+    if let Some(dir) = log_path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    rotate_log_if_needed(log_path)?;
+    let log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_path)?;
+
+    let mut cmd = Command::new(exe);
+    cmd.args(args)
+        .env(DAEMON_ENV, "1")
+        .stdin(Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        // SAFETY: pre_exec 闭包只调用 async-signal-safe 的 setsid/fork/_exit
+        unsafe {
+            cmd.pre_exec(detach_session);
+        }
+        // 中间进程在二次 fork 后立即退出，这里回收它，避免留下僵尸进程
+        cmd.spawn()?.wait()?;
+    }
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const DETACHED_PROCESS: u32 = 0x0000_0008;
+        const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        cmd.creation_flags(DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP | CREATE_NO_WINDOW);
+        cmd.spawn()?;
+    }
+
+    Ok(())
+}
+
+/// 在子进程 exec 之前执行：新建会话后再 fork 一次，
+/// 使最终进程不再是会话首进程，无法重新获取控制终端
+#[cfg(unix)]
+fn detach_session() -> io::Result<()> {
+    use nix::unistd::{fork, setsid, ForkResult};
+
+    setsid().map_err(io::Error::from)?;
+    match unsafe { fork() }.map_err(io::Error::from)? {
+        ForkResult::Parent { .. } => unsafe { nix::libc::_exit(0) },
+        ForkResult::Child => Ok(()),
+    }
+}
+
+/// 日志超过阈值时轮转：rust_proxy.log -> .1 -> ... -> .5，最旧的一份归档保留
+pub fn rotate_log_if_needed(log_path: &Path) -> io::Result<()> {
+    let size = match fs::metadata(log_path) {
+        Ok(meta) => meta.len(),
+        Err(_) => return Ok(()),
+    };
+    if size < LOG_MAX_BYTES {
+        return Ok(());
+    }
+
+    let backup = |i: usize| log_path.with_file_name(format!("{}.{}", file_name(log_path), i));
+
+    let oldest = backup(LOG_MAX_BACKUPS);
+    if oldest.exists() {
+        let ts = chrono::Local::now().format("%Y%m%d_%H%M%S");
+        let archived = log_path.with_file_name(format!(
+            "{}.archive.{}.{}",
+            file_name(log_path),
+            ts,
+            LOG_MAX_BACKUPS
+        ));
+        fs::rename(&oldest, archived)?;
+    }
+    for i in (1..LOG_MAX_BACKUPS).rev() {
+        let from = backup(i);
+        if from.exists() {
+            fs::rename(&from, backup(i + 1))?;
+        }
+    }
+    fs::rename(log_path, backup(1))?;
+    Ok(())
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// 读取 PID 文件（不存在或内容非法时返回 None）
+pub fn read_pid_file(pid_file: &Path) -> Option<i32> {
+    fs::read_to_string(pid_file).ok()?.trim().parse().ok()
+}
+
+/// 检查进程是否存在
+pub fn is_process_alive(pid: i32) -> bool {
+    #[cfg(unix)]
+    {
+        use nix::sys::signal::kill;
+        use nix::unistd::Pid;
+
+        kill(Pid::from_raw(pid), None).is_ok()
+    }
+
+    #[cfg(windows)]
+    {
+        Command::new("tasklist")
+            .args(["/FI", &format!("PID eq {}", pid)])
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).contains(&pid.to_string()))
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_small_log_is_not_rotated() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("rust_proxy.log");
+        fs::write(&log, "hello").unwrap();
+
+        rotate_log_if_needed(&log).unwrap();
+        assert!(log.exists());
+        assert!(!dir.path().join("rust_proxy.log.1").exists());
+    }
+
+    #[test]
+    fn test_large_log_is_shifted() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("rust_proxy.log");
+        fs::write(&log, vec![b'x'; LOG_MAX_BYTES as usize]).unwrap();
+        fs::write(dir.path().join("rust_proxy.log.1"), "old-1").unwrap();
+
+        rotate_log_if_needed(&log).unwrap();
+        assert!(!log.exists());
+        assert_eq!(
+            fs::metadata(dir.path().join("rust_proxy.log.1"))
+                .unwrap()
+                .len(),
+            LOG_MAX_BYTES
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("rust_proxy.log.2")).unwrap(),
+            "old-1"
+        );
+    }
+
+    #[test]
+    fn test_read_pid_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let pid_file = dir.path().join("proxy.pid");
+        assert_eq!(read_pid_file(&pid_file), None);
+
+        fs::write(&pid_file, "1234\n").unwrap();
+        assert_eq!(read_pid_file(&pid_file), Some(1234));
+
+        fs::write(&pid_file, "garbage").unwrap();
+        assert_eq!(read_pid_file(&pid_file), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_current_process_is_alive() {
+        assert!(is_process_alive(std::process::id() as i32));
+    }
+}
@@ -0,0 +1,835 @@
+//! `csc dashboard`：终端实时监控面板
+//!
+//! 分层：
+//! - 数据层：每秒轮询代理 `/status`，并从数据库读取健康状态 / 请求日志，组成 `DashboardSnapshot`；
+//!   请求日志按 rowid 游标增量读取，读取失败时保留上一次的数据并在状态栏提示
+//! - 视图模型：`DashboardView::build` 为纯函数（便于测试）
+//! - 渲染层：ratatui 仅负责布局与绘制
+
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use cc_switch_lib::proxy::{ProviderHealth, ProxyStatus};
+use cc_switch_lib::{
+    AppError, AppRequestCount, Database, Provider, RequestLogDelta, RequestLogLine,
+};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+
+const APP_TYPES: [&str; 3] = ["claude", "codex", "gemini"];
+
+/// 轮询间隔
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 日志尾部最多保留行数
+const LOG_TAIL_LINES: usize = 200;
+
+/// 代理日志文件只读取末尾这么多字节，避免每次刷新都读完整个文件
+const LOG_TAIL_BYTES: u64 = 64 * 1024;
+
+/// 代理启动时间由 `now - uptime` 推算，容许轮询之间的秒级抖动
+const SINCE_JITTER_SECS: i64 = 5;
+
+/// 最近切换最多展示条数
+const MAX_FAILOVER_EVENTS: usize = 5;
+
+// ============================================================================
+// 数据层
+// ============================================================================
+
+/// 单个 app 的供应商快照
+#[derive(Debug, Clone, Default)]
+pub struct AppSnapshot {
+    pub app_type: String,
+    pub providers: Vec<Provider>,
+    pub current_id: Option<String>,
+    pub health: HashMap<String, ProviderHealth>,
+}
+
+/// 一次轮询得到的完整数据
+#[derive(Debug, Clone, Default)]
+pub struct DashboardSnapshot {
+    /// None 表示代理不可达
+    pub status: Option<ProxyStatus>,
+    pub apps: Vec<AppSnapshot>,
+    pub counts: Vec<AppRequestCount>,
+    /// 请求日志（按时间倒序）
+    pub request_logs: Vec<RequestLogLine>,
+    /// 代理日志文件尾部（按时间正序）；为空时回退为请求日志
+    pub log_tail: Vec<String>,
+    /// 最近一次读取数据库失败的原因（其余数据沿用上一次的结果）
+    pub error: Option<String>,
+}
+
+/// 跨轮询保留的数据源状态
+#[derive(Debug, Default)]
+struct SnapshotLoader {
+    snapshot: DashboardSnapshot,
+    /// 请求计数的统计起点（代理启动时间）；变化即代理重启，需要重建基线
+    log_since: Option<i64>,
+    /// 已读取的请求日志最大 rowid
+    log_cursor: i64,
+}
+
+impl SnapshotLoader {
+    async fn refresh(&mut self, db: &Database, client: &reqwest::Client, base: &str) {
+        let status = match client.get(format!("{base}/status")).send().await {
+            Ok(resp) if resp.status().is_success() => resp.json::<ProxyStatus>().await.ok(),
+            _ => None,
+        };
+        let since = status
+            .as_ref()
+            .map(|s| chrono::Utc::now().timestamp() - s.uptime_seconds as i64)
+            .unwrap_or(0);
+
+        let mut errors = Vec::new();
+        match load_apps(db).await {
+            Ok(apps) => self.snapshot.apps = apps,
+            Err(e) => errors.push(format!("读取供应商失败: {e}")),
+        }
+        if let Err(e) = self.refresh_request_logs(db, since) {
+            errors.push(format!("读取请求日志失败: {e}"));
+        }
+
+        self.snapshot.status = status;
+        self.snapshot.error = (!errors.is_empty()).then(|| errors.join("; "));
+        self.snapshot.log_tail =
+            read_log_tail(&super::get_config_dir().join("logs/rust_proxy.log"));
+    }
+
+    /// 只查询游标之后新增的请求日志；代理重启或日志被清空时重建基线
+    fn refresh_request_logs(&mut self, db: &Database, since: i64) -> Result<(), AppError> {
+        let restarted = match self.log_since {
+            Some(prev) => (prev - since).abs() > SINCE_JITTER_SECS,
+            None => true,
+        };
+        if restarted {
+            self.reset_request_logs(since);
+        }
+        let since = self.log_since.unwrap_or(since);
+
+        let mut delta = db.get_request_log_delta(self.log_cursor, since, LOG_TAIL_LINES)?;
+        if delta.last_id < self.log_cursor {
+            self.reset_request_logs(since);
+            delta = db.get_request_log_delta(0, since, LOG_TAIL_LINES)?;
+        }
+        self.log_cursor = delta.last_id;
+        apply_log_delta(&mut self.snapshot, delta);
+        Ok(())
+    }
+
+    fn reset_request_logs(&mut self, since: i64) {
+        self.log_since = Some(since);
+        self.log_cursor = 0;
+        self.snapshot.counts.clear();
+        self.snapshot.request_logs.clear();
+    }
+}
+
+async fn load_apps(db: &Database) -> Result<Vec<AppSnapshot>, AppError> {
+    let mut apps = Vec::new();
+    for app_type in APP_TYPES {
+        apps.push(AppSnapshot {
+            app_type: app_type.to_string(),
+            providers: db.get_all_providers(app_type)?.into_values().collect(),
+            current_id: db.get_current_provider(app_type)?,
+            health: db.get_all_provider_health(app_type).await?,
+        });
+    }
+    Ok(apps)
+}
+
+/// 把增量日志合并进快照：计数累加，日志插到最前并截断到 `LOG_TAIL_LINES`
+fn apply_log_delta(snapshot: &mut DashboardSnapshot, delta: RequestLogDelta) {
+    for count in delta.counts {
+        match snapshot
+            .counts
+            .iter_mut()
+            .find(|c| c.app_type == count.app_type)
+        {
+            Some(c) => {
+                c.total += count.total;
+                c.success += count.success;
+            }
+            None => snapshot.counts.push(count),
+        }
+    }
+
+    if !delta.logs.is_empty() {
+        let mut logs = delta.logs;
+        logs.append(&mut snapshot.request_logs);
+        logs.truncate(LOG_TAIL_LINES);
+        snapshot.request_logs = logs;
+    }
+}
+
+fn read_log_tail(path: &Path) -> Vec<String> {
+    let Ok(mut file) = std::fs::File::open(path) else {
+        return Vec::new();
+    };
+    let len = file.metadata().map(|m| m.len()).unwrap_or(0);
+    let offset = len.saturating_sub(LOG_TAIL_BYTES);
+    let mut buf = Vec::new();
+    if file.seek(SeekFrom::Start(offset)).is_err() || file.read_to_end(&mut buf).is_err() {
+        return Vec::new();
+    }
+
+    let content = String::from_utf8_lossy(&buf);
+    let mut lines: Vec<&str> = content.lines().collect();
+    // 从文件中间开始读时，第一行可能不完整
+    if offset > 0 && !lines.is_empty() {
+        lines.remove(0);
+    }
+    let start = lines.len().saturating_sub(LOG_TAIL_LINES);
+    lines[start..].iter().map(|s| s.to_string()).collect()
+}
+
+// ============================================================================
+// 视图模型
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppSummaryView {
+    pub app_type: String,
+    pub total: u64,
+    pub success_rate: Option<f32>,
+    pub current_provider: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProviderRowView {
+    pub app_type: String,
+    pub provider_id: String,
+    pub label: String,
+    pub breaker: &'static str,
+    pub is_current: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DashboardView {
+    pub proxy_running: bool,
+    pub header: String,
+    pub apps: Vec<AppSummaryView>,
+    pub providers: Vec<ProviderRowView>,
+    pub failovers: Vec<String>,
+    pub log_lines: Vec<String>,
+    /// 数据库读取失败时展示在状态栏
+    pub error: Option<String>,
+}
+
+/// 熔断/健康状态标签（基于 provider_health 表）
+fn breaker_label(health: Option<&ProviderHealth>) -> &'static str {
+    match health {
+        Some(h) if h.depleted_at.is_some() => "耗尽",
+        Some(h) if !h.is_healthy => "熔断",
+        Some(h) if h.consecutive_failures > 0 => "降级",
+        _ => "正常",
+    }
+}
+
+fn format_uptime(secs: u64) -> String {
+    let h = secs / 3600;
+    let m = (secs % 3600) / 60;
+    let s = secs % 60;
+    if h > 0 {
+        format!("{h}h{m:02}m")
+    } else {
+        format!("{m}m{s:02}s")
+    }
+}
+
+fn format_clock(ts: i64) -> String {
+    let tz = chrono::FixedOffset::east_opt(8 * 3600).unwrap();
+    chrono::DateTime::from_timestamp(ts, 0)
+        .map(|t| t.with_timezone(&tz).format("%H:%M:%S").to_string())
+        .unwrap_or_else(|| "--:--:--".to_string())
+}
+
+/// 从请求日志推导最近的供应商切换（同一 app 相邻两次请求的 provider 不同即视为一次切换）
+fn derive_failovers(
+    logs_newest_first: &[RequestLogLine],
+    names: &HashMap<String, String>,
+) -> Vec<String> {
+    let name_of = |id: &str| names.get(id).cloned().unwrap_or_else(|| id.to_string());
+    let mut last_by_app: HashMap<&str, &str> = HashMap::new();
+    let mut events = Vec::new();
+
+    for log in logs_newest_first.iter().rev() {
+        if let Some(prev) = last_by_app.insert(log.app_type.as_str(), log.provider_id.as_str()) {
+            if prev != log.provider_id {
+                events.push(format!(
+                    "{} [{}] {} → {}",
+                    format_clock(log.created_at),
+                    log.app_type,
+                    name_of(prev),
+                    name_of(&log.provider_id)
+                ));
+            }
+        }
+    }
+
+    let start = events.len().saturating_sub(MAX_FAILOVER_EVENTS);
+    events.split_off(start).into_iter().rev().collect()
+}
+
+impl DashboardView {
+    pub fn build(snapshot: &DashboardSnapshot) -> Self {
+        let header = match snapshot.status.as_ref() {
+            Some(s) => {
+                let mut header = format!(
+                    "运行中 {}:{} | 运行 {} | 总请求 {} | 成功率 {:.1}% | 故障转移 {}",
+                    s.address,
+                    s.port,
+                    format_uptime(s.uptime_seconds),
+                    s.total_requests,
+                    s.success_rate,
+                    s.failover_count
+                );
+                if let Some(err) = s.last_error.as_deref() {
+                    header.push_str(&format!(" | 最近错误: {err}"));
+                }
+                header
+            }
+            None => "代理服务未响应（可能已停止），等待重连...".to_string(),
+        };
+
+        let mut names = HashMap::new();
+        let mut apps = Vec::new();
+        let mut providers = Vec::new();
+
+        for app in &snapshot.apps {
+            for p in &app.providers {
+                names.insert(p.id.clone(), p.name.clone());
+            }
+
+            let count = snapshot.counts.iter().find(|c| c.app_type == app.app_type);
+            let total = count.map(|c| c.total).unwrap_or(0);
+            let success_rate = count
+                .filter(|c| c.total > 0)
+                .map(|c| c.success as f32 / c.total as f32 * 100.0);
+
+            // 优先展示代理实际使用的目标，其次为数据库中的当前供应商
+            let active = snapshot.status.as_ref().and_then(|s| {
+                s.active_targets
+                    .iter()
+                    .find(|t| t.app_type.eq_ignore_ascii_case(&app.app_type))
+                    .map(|t| t.provider_name.clone())
+            });
+            let current_provider = active
+                .or_else(|| {
+                    app.current_id.as_ref().and_then(|id| {
+                        app.providers
+                            .iter()
+                            .find(|p| &p.id == id)
+                            .map(|p| p.name.clone())
+                    })
+                })
+                .unwrap_or_else(|| "-".to_string());
+
+            apps.push(AppSummaryView {
+                app_type: app.app_type.clone(),
+                total,
+                success_rate,
+                current_provider,
+            });
+
+            for p in &app.providers {
+                let health = app.health.get(&p.id);
+                let failures = health.map(|h| h.consecutive_failures).unwrap_or(0);
+                let mut label = format!("{} ({})", p.name, p.id);
+                if failures > 0 {
+                    label.push_str(&format!(" 连续失败:{failures}"));
+                }
+                providers.push(ProviderRowView {
+                    app_type: app.app_type.clone(),
+                    provider_id: p.id.clone(),
+                    label,
+                    breaker: breaker_label(health),
+                    is_current: app.current_id.as_deref() == Some(p.id.as_str()),
+                });
+            }
+        }
+
+        let log_lines = if !snapshot.log_tail.is_empty() {
+            snapshot.log_tail.clone()
+        } else {
+            snapshot
+                .request_logs
+                .iter()
+                .rev()
+                .map(|l| {
+                    let mut line = format!(
+                        "{} [{}] {} {} {} {}ms",
+                        format_clock(l.created_at),
+                        l.app_type,
+                        names.get(&l.provider_id).unwrap_or(&l.provider_id),
+                        l.model,
+                        l.status_code,
+                        l.latency_ms
+                    );
+                    if let Some(err) = l.error_message.as_deref() {
+                        line.push_str(&format!(" {err}"));
+                    }
+                    line
+                })
+                .collect()
+        };
+
+        Self {
+            proxy_running: snapshot.status.is_some(),
+            header,
+            apps,
+            providers,
+            failovers: derive_failovers(&snapshot.request_logs, &names),
+            log_lines,
+            error: snapshot.error.clone(),
+        }
+    }
+}
+
+// ============================================================================
+// 渲染层
+// ============================================================================
+
+fn render(frame: &mut Frame, view: &DashboardView, list_state: &mut ListState, message: &str) {
+    let [header_area, apps_area, body_area, log_area, footer_area] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Length(5),
+        Constraint::Min(8),
+        Constraint::Length(10),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    let header_style = if view.proxy_running {
+        Style::new().fg(Color::Green)
+    } else {
+        Style::new().fg(Color::Red)
+    };
+    frame.render_widget(
+        Paragraph::new(view.header.as_str())
+            .style(header_style)
+            .block(Block::bordered().title(" CC-Switch 代理 ")),
+        header_area,
+    );
+
+    let app_lines: Vec<Line> = view
+        .apps
+        .iter()
+        .map(|a| {
+            let rate = a
+                .success_rate
+                .map(|r| format!("{r:.1}%"))
+                .unwrap_or_else(|| "-".to_string());
+            Line::from(format!(
+                "{:<7} 请求 {:>6}  成功率 {:>6}  当前: {}",
+                a.app_type, a.total, rate, a.current_provider
+            ))
+        })
+        .collect();
+    frame.render_widget(
+        Paragraph::new(app_lines).block(Block::bordered().title(" 应用 ")),
+        apps_area,
+    );
+
+    let [providers_area, failover_area] =
+        Layout::horizontal([Constraint::Percentage(65), Constraint::Percentage(35)])
+            .areas(body_area);
+
+    let items: Vec<ListItem> = view
+        .providers
+        .iter()
+        .map(|p| {
+            let color = match p.breaker {
+                "熔断" => Color::Red,
+                "降级" => Color::Yellow,
+                _ => Color::Reset,
+            };
+            let marker = if p.is_current { " [当前]" } else { "" };
+            ListItem::new(format!(
+                "[{}] {} {}{}",
+                p.breaker, p.app_type, p.label, marker
+            ))
+            .style(Style::new().fg(color))
+        })
+        .collect();
+    frame.render_stateful_widget(
+        List::new(items)
+            .block(Block::bordered().title(" 供应商 (Enter 切换 / r 重置熔断 / b 测速) "))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> "),
+        providers_area,
+        list_state,
+    );
+
+    let failover_lines: Vec<Line> = view
+        .failovers
+        .iter()
+        .map(|s| Line::from(s.as_str()))
+        .collect();
+    frame.render_widget(
+        Paragraph::new(failover_lines).block(Block::bordered().title(" 最近切换 ")),
+        failover_area,
+    );
+
+    let visible = log_area.height.saturating_sub(2) as usize;
+    let start = view.log_lines.len().saturating_sub(visible);
+    let log_lines: Vec<Line> = view.log_lines[start..]
+        .iter()
+        .map(|s| Line::from(s.as_str()))
+        .collect();
+    frame.render_widget(
+        Paragraph::new(log_lines).block(Block::bordered().title(" 日志 ")),
+        log_area,
+    );
+
+    let footer = if !message.is_empty() {
+        Paragraph::new(message)
+    } else if let Some(err) = view.error.as_deref() {
+        Paragraph::new(format!("⚠ {err}")).style(Style::new().fg(Color::Red))
+    } else {
+        Paragraph::new("q 退出 | ↑/↓ 选择")
+    };
+    frame.render_widget(footer, footer_area);
+}
+
+// ============================================================================
+// 入口与按键处理
+// ============================================================================
+
+pub async fn run(db: Arc<Database>, client: reqwest::Client, base: String) -> Result<(), AppError> {
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, db, client, base).await;
+    ratatui::restore();
+    result
+}
+
+async fn event_loop(
+    terminal: &mut DefaultTerminal,
+    db: Arc<Database>,
+    client: reqwest::Client,
+    base: String,
+) -> Result<(), AppError> {
+    let mut list_state = ListState::default();
+    let mut message = String::new();
+    let mut loader = SnapshotLoader::default();
+    loader.refresh(&db, &client, &base).await;
+    let mut view = DashboardView::build(&loader.snapshot);
+    let mut last_refresh = Instant::now();
+    let mut force_refresh = false;
+
+    loop {
+        if !view.providers.is_empty() && list_state.selected().is_none() {
+            list_state.select(Some(0));
+        }
+
+        terminal
+            .draw(|frame| render(frame, &view, &mut list_state, &message))
+            .map_err(|e| AppError::Message(format!("绘制终端界面失败: {e}")))?;
+
+        let has_event = event::poll(Duration::from_millis(200))
+            .map_err(|e| AppError::Message(format!("读取终端事件失败: {e}")))?;
+        if has_event {
+            if let Event::Key(key) =
+                event::read().map_err(|e| AppError::Message(format!("读取终端事件失败: {e}")))?
+            {
+                if key.kind == KeyEventKind::Press {
+                    let selected = list_state
+                        .selected()
+                        .and_then(|i| view.providers.get(i))
+                        .cloned();
+                    match key.code {
+                        KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                        KeyCode::Down | KeyCode::Char('j') => list_state.select_next(),
+                        KeyCode::Up | KeyCode::Char('k') => list_state.select_previous(),
+                        KeyCode::Char('r') => {
+                            if let Some(p) = selected {
+                                message = reset_breaker(&client, &base, &p).await;
+                            }
+                        }
+                        KeyCode::Char('b') => {
+                            if let Some(p) = selected {
+                                message = trigger_benchmark(&client, &base, &p.app_type);
+                            }
+                        }
+                        KeyCode::Enter => {
+                            if let Some(p) = selected {
+                                let switched = db
+                                    .set_current_provider(&p.app_type, &p.provider_id)
+                                    .and_then(|()| match p.app_type.parse() {
+                                        // 手动切换：取消待切回的首选供应商
+                                        Ok(app) => cc_switch_lib::set_restore_primary(&app, None),
+                                        Err(_) => Ok(()),
+                                    });
+                                message = match switched {
+                                    Ok(()) => format!(
+                                        "✓ 已将 {} 当前供应商切换为 {}（重启代理后生效: csc p r）",
+                                        p.app_type, p.provider_id
+                                    ),
+                                    Err(e) => format!("切换失败: {e}"),
+                                };
+                                force_refresh = true;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        if force_refresh || last_refresh.elapsed() >= REFRESH_INTERVAL {
+            loader.refresh(&db, &client, &base).await;
+            view = DashboardView::build(&loader.snapshot);
+            last_refresh = Instant::now();
+            force_refresh = false;
+        }
+    }
+}
+
+async fn reset_breaker(client: &reqwest::Client, base: &str, p: &ProviderRowView) -> String {
+    let resp = client
+        .post(format!("{base}/__cc_switch/breaker/reset"))
+        .json(&serde_json::json!({
+            "app_type": p.app_type,
+            "provider_id": p.provider_id,
+        }))
+        .send()
+        .await;
+    match resp {
+        Ok(r) if r.status().is_success() => {
+            format!("✓ 已重置熔断器: {} ({})", p.provider_id, p.app_type)
+        }
+        Ok(r) => format!("重置熔断器失败: HTTP {}", r.status().as_u16()),
+        Err(e) => format!("重置熔断器失败: {e}"),
+    }
+}
+
+/// 测速耗时较长：后台触发，结果写入代理日志
+fn trigger_benchmark(client: &reqwest::Client, base: &str, app_type: &str) -> String {
+    let client = client.clone();
+    let url = format!("{base}/__cc_switch/benchmark");
+    let body = serde_json::json!({ "app_type": app_type });
+    tokio::spawn(async move {
+        let _ = client
+            .post(url)
+            .timeout(Duration::from_secs(120))
+            .json(&body)
+            .send()
+            .await;
+    });
+    format!("已触发 {app_type} 测速（结果见日志）")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cc_switch_lib::proxy::ActiveTarget;
+    use serde_json::json;
+
+    fn provider(id: &str, name: &str) -> Provider {
+        Provider::with_id(id.to_string(), name.to_string(), json!({}), None)
+    }
+
+    fn health(id: &str, healthy: bool, failures: u32) -> ProviderHealth {
+        ProviderHealth {
+            provider_id: id.to_string(),
+            app_type: "claude".to_string(),
+            is_healthy: healthy,
+            consecutive_failures: failures,
+            last_success_at: None,
+            last_failure_at: None,
+            last_error: None,
+            updated_at: String::new(),
+            depleted_at: None,
+            depleted_message: None,
+        }
+    }
+
+    fn log(ts: i64, app: &str, provider_id: &str, status: u16) -> RequestLogLine {
+        RequestLogLine {
+            created_at: ts,
+            app_type: app.to_string(),
+            provider_id: provider_id.to_string(),
+            model: "m".to_string(),
+            status_code: status,
+            latency_ms: 100,
+            error_message: None,
+        }
+    }
+
+    fn snapshot() -> DashboardSnapshot {
+        let mut health_map = HashMap::new();
+        health_map.insert("p1".to_string(), health("p1", false, 5));
+        health_map.insert("p2".to_string(), health("p2", true, 1));
+
+        DashboardSnapshot {
+            status: Some(ProxyStatus {
+                running: true,
+                address: "127.0.0.1".to_string(),
+                port: 15721,
+                total_requests: 10,
+                success_rate: 90.0,
+                uptime_seconds: 3725,
+                failover_count: 2,
+                active_targets: vec![ActiveTarget {
+                    app_type: "Claude".to_string(),
+                    provider_name: "hyb-key2".to_string(),
+                    provider_id: "p2".to_string(),
+                }],
+                ..Default::default()
+            }),
+            apps: vec![AppSnapshot {
+                app_type: "claude".to_string(),
+                providers: vec![
+                    provider("p1", "anyrouter-key1"),
+                    provider("p2", "hyb-key2"),
+                    provider("p3", "foo"),
+                ],
+                current_id: Some("p1".to_string()),
+                health: health_map,
+            }],
+            counts: vec![AppRequestCount {
+                app_type: "claude".to_string(),
+                total: 4,
+                success: 3,
+            }],
+            // 倒序：最新在前
+            request_logs: vec![
+                log(400, "claude", "p2", 200),
+                log(300, "claude", "p2", 200),
+                log(200, "claude", "p1", 502),
+                log(100, "claude", "p1", 200),
+            ],
+            log_tail: Vec::new(),
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_view_from_running_status() {
+        let view = DashboardView::build(&snapshot());
+        assert!(view.proxy_running);
+        assert!(view.header.contains("127.0.0.1:15721"));
+        assert!(view.header.contains("1h02m"));
+        assert!(view.header.contains("故障转移 2"));
+
+        assert_eq!(
+            view.apps,
+            vec![AppSummaryView {
+                app_type: "claude".to_string(),
+                total: 4,
+                success_rate: Some(75.0),
+                current_provider: "hyb-key2".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_breaker_states_from_health() {
+        let view = DashboardView::build(&snapshot());
+        let states: Vec<(&str, &str, bool)> = view
+            .providers
+            .iter()
+            .map(|p| (p.provider_id.as_str(), p.breaker, p.is_current))
+            .collect();
+        assert_eq!(
+            states,
+            vec![
+                ("p1", "熔断", true),
+                ("p2", "降级", false),
+                ("p3", "正常", false)
+            ]
+        );
+        assert!(view.providers[0].label.contains("连续失败:5"));
+    }
+
+    #[test]
+    fn test_failovers_and_log_fallback() {
+        let view = DashboardView::build(&snapshot());
+        assert_eq!(view.failovers.len(), 1);
+        assert!(view.failovers[0].contains("anyrouter-key1 → hyb-key2"));
+
+        // 无日志文件时回退为请求日志（时间正序）
+        assert_eq!(view.log_lines.len(), 4);
+        assert!(view.log_lines[0].contains("anyrouter-key1"));
+        assert!(view.log_lines[3].contains("hyb-key2"));
+    }
+
+    #[test]
+    fn test_view_when_proxy_unreachable() {
+        let mut snap = snapshot();
+        snap.status = None;
+        snap.log_tail = vec!["[INFO] line".to_string()];
+        let view = DashboardView::build(&snap);
+        assert!(!view.proxy_running);
+        assert!(view.header.contains("未响应"));
+        // 代理不可达时回退到数据库中的当前供应商
+        assert_eq!(view.apps[0].current_provider, "anyrouter-key1");
+        assert_eq!(view.log_lines, vec!["[INFO] line".to_string()]);
+    }
+
+    #[test]
+    fn test_apply_log_delta_accumulates() {
+        let mut snap = snapshot();
+        apply_log_delta(
+            &mut snap,
+            RequestLogDelta {
+                last_id: 9,
+                logs: vec![log(600, "codex", "c1", 500), log(500, "claude", "p2", 200)],
+                counts: vec![
+                    AppRequestCount {
+                        app_type: "claude".to_string(),
+                        total: 1,
+                        success: 1,
+                    },
+                    AppRequestCount {
+                        app_type: "codex".to_string(),
+                        total: 1,
+                        success: 0,
+                    },
+                ],
+            },
+        );
+
+        // 计数在已有基线上累加，新 app 追加
+        assert_eq!(snap.counts[0].total, 5);
+        assert_eq!(snap.counts[0].success, 4);
+        assert_eq!(snap.counts[1].app_type, "codex");
+        // 新日志插到最前，仍按时间倒序
+        let times: Vec<i64> = snap.request_logs.iter().map(|l| l.created_at).collect();
+        assert_eq!(times, vec![600, 500, 400, 300, 200, 100]);
+
+        // 超出上限时丢弃最旧的日志
+        let burst: Vec<RequestLogLine> = (0..LOG_TAIL_LINES as i64)
+            .map(|i| log(10_000 - i, "claude", "p1", 200))
+            .collect();
+        apply_log_delta(
+            &mut snap,
+            RequestLogDelta {
+                last_id: 500,
+                logs: burst,
+                counts: Vec::new(),
+            },
+        );
+        assert_eq!(snap.request_logs.len(), LOG_TAIL_LINES);
+        assert_eq!(snap.request_logs[0].created_at, 10_000);
+    }
+
+    #[test]
+    fn test_db_error_shown_in_status_line() {
+        let mut snap = snapshot();
+        snap.error = Some("读取请求日志失败: database is locked".to_string());
+        let view = DashboardView::build(&snap);
+        assert_eq!(view.error.as_deref(), snap.error.as_deref());
+        // 其余数据仍正常展示
+        assert_eq!(view.providers.len(), 3);
+        assert_eq!(view.apps[0].total, 4);
+    }
+}
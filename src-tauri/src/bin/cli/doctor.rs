@@ -0,0 +1,933 @@
+//! 环境自检（`csc doctor`）
+//!
+//! 按顺序检查数据库、供应商配置、路由一致性、Python 代理、代理端口、上游连通性、熔断/冷却状态、模型列表预热与 Live 接管，
+//! 逐项给出通过/警告/失败与修复提示，便于定位问题出在哪一层。
+//!
+//! 只读：数据库以只读方式打开，连通性只对 base URL 发 HEAD 请求，不发送任何模型请求。
+
+use cc_switch_lib::proxy::env_expand::unresolved_placeholders;
+use cc_switch_lib::proxy::{ProviderHealth, ProviderRouter};
+use cc_switch_lib::{
+    AppError, AppType, ConsistencyReport, ConsistencySeverity, Database, EventKind, EventLogEntry,
+    Provider, ProxyService,
+};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 熔断/冷却事件的回看窗口
+const EVENT_WINDOW_SECS: i64 = 24 * 3600;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+/// 单项检查结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckResult {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// 修复提示（通过时为空）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+}
+
+impl CheckResult {
+    fn pass(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: CheckStatus::Pass,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn warn(name: impl Into<String>, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: CheckStatus::Warn,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+
+    fn fail(name: impl Into<String>, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: CheckStatus::Fail,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+}
+
+// ============================================================================
+// 各项检查
+// ============================================================================
+
+/// 数据库能否打开、schema 是否为当前版本（只读打开，不迁移）
+pub fn check_database(db_path: &Path) -> (CheckResult, Option<Database>) {
+    const NAME: &str = "数据库";
+    if !db_path.exists() {
+        return (
+            CheckResult::warn(
+                NAME,
+                format!("{} 不存在", db_path.display()),
+                "首次运行写命令（如 csc add）或启动代理时自动创建",
+            ),
+            None,
+        );
+    }
+    match Database::open_readonly(db_path) {
+        Ok(db) => (
+            CheckResult::pass(NAME, format!("{}，schema 为当前版本", db_path.display())),
+            Some(db),
+        ),
+        Err(e) if e.is_database_unavailable() => (
+            CheckResult::fail(
+                NAME,
+                format!("无法打开: {e}"),
+                "确认没有其它进程长时间占用数据库；文件损坏时运行 csc db maintain 检查，或从 ~/.cc-switch/backups/ 恢复",
+            ),
+            None,
+        ),
+        Err(e) => (
+            CheckResult::warn(
+                NAME,
+                e.to_string(),
+                "启动代理（csc proxy start）或运行任意写命令会自动迁移；版本过新时请升级 csc",
+            ),
+            None,
+        ),
+    }
+}
+
+/// 供应商配置：是否配置了供应商、审计问题与未解析的 `${ENV}` 占位符
+pub fn check_providers(db: &Database, app_type: &str) -> Result<CheckResult, AppError> {
+    let name = format!("供应商配置 ({app_type})");
+    let providers = db.get_all_providers(app_type)?;
+    if providers.is_empty() {
+        return Ok(CheckResult::fail(
+            name,
+            "未配置供应商",
+            format!("csc add {app_type} <id> --name <名称> --api-key <key> --base-url <url>"),
+        ));
+    }
+
+    let report = db.audit_providers(app_type)?;
+    let missing_env: usize = providers
+        .values()
+        .map(|p| unresolved_placeholders(&p.settings_config).len())
+        .sum();
+    let queued = providers.values().filter(|p| p.in_failover_queue).count();
+    let summary = format!("{} 个供应商，队列中 {} 个", providers.len(), queued);
+
+    let mut problems = Vec::new();
+    if !report.issues.is_empty() {
+        problems.push(format!("{} 个配置问题", report.issues.len()));
+    }
+    if !report.duplicate_keys.is_empty() {
+        problems.push(format!("{} 组共享 API Key", report.duplicate_keys.len()));
+    }
+    if missing_env > 0 {
+        problems.push(format!("{missing_env} 个环境变量占位符未解析"));
+    }
+    if problems.is_empty() {
+        return Ok(CheckResult::pass(name, summary));
+    }
+    Ok(CheckResult::warn(
+        name,
+        format!("{summary}；{}", problems.join("，")),
+        format!("csc audit {app_type} / csc check-env {app_type} 查看详情"),
+    ))
+}
+
+/// 路由一致性：当前供应商、故障转移队列与故障转移开关是否相互矛盾（只读，不自动修复）
+pub fn check_consistency(report: &ConsistencyReport) -> CheckResult {
+    let name = format!("路由一致性 ({})", report.app_type);
+    let findings: Vec<_> = report.unresolved().collect();
+    if findings.is_empty() {
+        return CheckResult::pass(name, "当前供应商、故障转移队列与开关一致");
+    }
+    let detail = findings
+        .iter()
+        .map(|f| f.message.as_str())
+        .collect::<Vec<_>>()
+        .join("；");
+    let hint = findings
+        .iter()
+        .map(|f| f.suggestion.as_str())
+        .collect::<Vec<_>>()
+        .join("；");
+    if findings
+        .iter()
+        .any(|f| f.severity == ConsistencySeverity::Error)
+    {
+        CheckResult::fail(name, detail, hint)
+    } else {
+        CheckResult::warn(name, detail, hint)
+    }
+}
+
+/// Claude 请求经 Python 透明代理转发：收到任意 HTTP 响应即视为可达
+pub async fn check_python_proxy(client: &reqwest::Client, base: &str) -> CheckResult {
+    const NAME: &str = "Python 代理";
+    match client.get(format!("{}/", base.trim_end_matches('/'))).send().await {
+        Ok(resp) => CheckResult::pass(NAME, format!("{base} 可达（HTTP {}）", resp.status().as_u16())),
+        Err(e) => CheckResult::fail(
+            NAME,
+            format!("{base} 不可达: {e}"),
+            "Claude 请求经 Python 代理转发：请先启动 Python 代理，或用 CC_SWITCH_PYTHON_PROXY_BASE 指向其地址",
+        ),
+    }
+}
+
+/// 代理端口：已有代理在响应，或端口空闲可启动；返回 (结果, 代理是否运行中)
+pub async fn check_proxy_port(
+    client: &reqwest::Client,
+    address: &str,
+    port: u16,
+) -> (CheckResult, bool) {
+    const NAME: &str = "代理端口";
+    // 监听全部地址时经本机回环访问
+    let host = match address {
+        "0.0.0.0" | "" => "127.0.0.1".to_string(),
+        "::" => "[::1]".to_string(),
+        a if a.contains(':') => format!("[{a}]"),
+        a => a.to_string(),
+    };
+    if let Ok(resp) = client
+        .get(format!("http://{host}:{port}/health"))
+        .send()
+        .await
+    {
+        if resp.status().is_success() {
+            return (
+                CheckResult::pass(NAME, format!("代理运行中（{host}:{port}）")),
+                true,
+            );
+        }
+    }
+    match std::net::TcpListener::bind((address, port)) {
+        Ok(_) => (
+            CheckResult::pass(NAME, format!("代理未运行，{address}:{port} 空闲")),
+            false,
+        ),
+        Err(e) => (
+            CheckResult::fail(
+                NAME,
+                format!("{address}:{port} 被其它程序占用或不可绑定: {e}"),
+                "csc proxy start --port <端口>，或设置 CC_SWITCH_LISTEN_PORT",
+            ),
+            false,
+        ),
+    }
+}
+
+/// 上游连通性：targets 为 (供应商, base URL)，相同 URL 只探测一次
+///
+/// 与测速的连通性探测相同（HTTP HEAD，经过 HTTP(S)_PROXY 与 TLS 握手），拿到任意响应即可连通
+pub async fn check_connectivity(
+    app_type: &str,
+    targets: &[(String, String)],
+    timeout: Duration,
+) -> CheckResult {
+    let name = format!("上游连通性 ({app_type})");
+    let mut urls: Vec<&str> = targets.iter().map(|(_, url)| url.as_str()).collect();
+    urls.sort_unstable();
+    urls.dedup();
+    if urls.is_empty() {
+        return CheckResult::warn(
+            name,
+            "故障转移队列与当前供应商均未配置 base URL",
+            format!("csc add-to-queue {app_type} <id> 将供应商加入队列"),
+        );
+    }
+
+    let results = futures::future::join_all(
+        urls.iter()
+            .map(|url| ProviderRouter::connectivity_latency(url, timeout)),
+    )
+    .await;
+    let mut failures = Vec::new();
+    let mut slowest = 0;
+    for (url, result) in urls.iter().zip(results) {
+        match result {
+            Ok(ms) => slowest = slowest.max(ms),
+            Err(e) => {
+                let providers: Vec<&str> = targets
+                    .iter()
+                    .filter(|(_, u)| u == url)
+                    .map(|(p, _)| p.as_str())
+                    .collect();
+                failures.push(format!("{url}（{}）: {e}", providers.join(", ")));
+            }
+        }
+    }
+
+    let hint = "检查网络/代理设置与 base URL 拼写；csc test-latency 查看完整测速";
+    if failures.is_empty() {
+        CheckResult::pass(
+            name,
+            format!("{} 个地址可连通（最慢 {slowest}ms）", urls.len()),
+        )
+    } else if failures.len() == urls.len() {
+        CheckResult::fail(name, failures.join("；"), hint)
+    } else {
+        CheckResult::warn(
+            name,
+            format!(
+                "{}/{} 个地址不可达: {}",
+                failures.len(),
+                urls.len(),
+                failures.join("；")
+            ),
+            hint,
+        )
+    }
+}
+
+/// 熔断/冷却/禁用/余额耗尽汇总；熔断与冷却只存在于运行中的代理，未运行时只看持久化状态
+pub fn check_breakers(
+    app_type: &str,
+    providers: &[Provider],
+    events: &[EventLogEntry],
+    health: &HashMap<String, ProviderHealth>,
+    disabled: &HashMap<String, i64>,
+    proxy_running: bool,
+    now: i64,
+) -> CheckResult {
+    let name = format!("熔断与冷却 ({app_type})");
+    let mut notes = Vec::new();
+    let mut unavailable = 0;
+
+    for provider in providers {
+        let mut reasons = Vec::new();
+        if disabled.get(&provider.id).is_some_and(|until| *until > now) {
+            reasons.push("维护禁用");
+        }
+        let provider_health = health.get(&provider.id);
+        if provider_health.is_some_and(|h| h.depleted_at.is_some()) {
+            reasons.push("余额耗尽");
+        }
+        if proxy_running {
+            // events 按时间倒序，第一条熔断事件即当前状态
+            let latest = events.iter().find(|e| {
+                e.provider_id.as_deref() == Some(provider.id.as_str())
+                    && matches!(
+                        e.kind,
+                        EventKind::BreakerOpen
+                            | EventKind::BreakerHalfOpen
+                            | EventKind::BreakerClose
+                            | EventKind::BreakerForced
+                    )
+            });
+            match latest.map(|e| e.kind) {
+                Some(EventKind::BreakerOpen | EventKind::BreakerForced) => reasons.push("熔断中"),
+                Some(EventKind::BreakerHalfOpen) => reasons.push("半开探测中"),
+                _ => {}
+            }
+        }
+        if !reasons.is_empty() {
+            unavailable += 1;
+            notes.push(format!("{}: {}", provider.id, reasons.join("、")));
+        } else if let Some(h) = provider_health.filter(|h| !h.is_healthy) {
+            notes.push(format!(
+                "{}: 连续失败 {} 次",
+                provider.id, h.consecutive_failures
+            ));
+        }
+    }
+
+    if proxy_running {
+        let mut seen = Vec::new();
+        for event in events.iter().filter(|e| e.kind == EventKind::Cooldown) {
+            let supplier = event.supplier.clone().unwrap_or_default();
+            if seen.contains(&supplier) {
+                continue;
+            }
+            seen.push(supplier.clone());
+            let secs = event
+                .detail
+                .get("secs")
+                .and_then(|v| v.as_i64())
+                .unwrap_or(0);
+            let remaining = event.created_at + secs - now;
+            if remaining > 0 {
+                notes.push(format!("supplier {supplier}: 冷却中，剩余 {remaining}s"));
+            }
+        }
+    }
+
+    let hint = format!(
+        "csc events {app_type} 查看路由事件；csc breaker close {app_type} <id> 手动解除熔断；csc enable {app_type} <id> 解除维护禁用"
+    );
+    if !providers.is_empty() && unavailable == providers.len() {
+        return CheckResult::fail(
+            name,
+            format!("所有供应商均不可用: {}", notes.join("；")),
+            hint,
+        );
+    }
+    if !notes.is_empty() {
+        return CheckResult::warn(name, notes.join("；"), hint);
+    }
+    CheckResult::pass(
+        name,
+        if proxy_running {
+            "无熔断、冷却或禁用"
+        } else {
+            "无禁用或余额耗尽（代理未运行，熔断与冷却在启动后重新计算）"
+        },
+    )
+}
+
+/// 保存供应商后的模型列表预热：窗口内预热失败（key 或 base_url 可能有误）的现存供应商
+pub fn check_model_warmup(
+    app_type: &str,
+    provider_ids: &[String],
+    events: &[EventLogEntry],
+) -> CheckResult {
+    let name = format!("模型列表预热 ({app_type})");
+    let mut seen: Vec<&str> = Vec::new();
+    let mut failed: Vec<String> = Vec::new();
+    for event in events
+        .iter()
+        .filter(|e| e.kind == EventKind::ModelWarmupFailed)
+    {
+        let Some(id) = event.provider_id.as_deref() else {
+            continue;
+        };
+        if !provider_ids.iter().any(|p| p == id) || seen.contains(&id) {
+            continue;
+        }
+        seen.push(id);
+        let error = event
+            .detail
+            .get("error")
+            .and_then(|v| v.as_str())
+            .unwrap_or("未知错误");
+        failed.push(format!("{id}: {error}"));
+    }
+    if failed.is_empty() {
+        return CheckResult::pass(name, "最近 24 小时无预热失败");
+    }
+    CheckResult::warn(
+        name,
+        format!("{} 个供应商预热失败: {}", failed.len(), failed.join("；")),
+        format!("检查 key 与 base_url 后重新保存；csc events {app_type} 查看详情"),
+    )
+}
+
+/// Live 接管一致性：接管开关与 live 配置是否指向代理相符
+///
+/// `live_taken_over` 为 None 表示 live 配置文件不存在或无法读取。
+pub fn check_live_takeover(
+    app_type: &str,
+    takeover_enabled: bool,
+    live_taken_over: Option<bool>,
+    proxy_running: bool,
+) -> CheckResult {
+    let name = format!("Live 接管 ({app_type})");
+    match (takeover_enabled, live_taken_over) {
+        (true, Some(true)) if proxy_running => CheckResult::pass(name, "已接管，live 配置指向代理"),
+        (true, Some(true)) => CheckResult::fail(
+            name,
+            "已接管但代理未运行，应用请求会失败",
+            "csc proxy start --daemon 启动代理，或关闭接管恢复原配置",
+        ),
+        (true, Some(false)) => CheckResult::warn(
+            name,
+            "接管已开启，但 live 配置未指向代理（可能被其它工具改写）",
+            format!("csc takeover diff {app_type} 查看差异；重新开启接管即可恢复"),
+        ),
+        (true, None) => CheckResult::warn(
+            name,
+            "接管已开启，但 live 配置文件不存在",
+            "重新开启接管以写入 live 配置",
+        ),
+        (false, Some(true)) => CheckResult::fail(
+            name,
+            "未接管，但 live 配置仍是代理占位符（上次退出时未恢复）",
+            format!("csc takeover restore {app_type} 恢复接管前的配置"),
+        ),
+        (false, _) => CheckResult::pass(name, "未接管"),
+    }
+}
+
+// ============================================================================
+// 汇总
+// ============================================================================
+
+/// 连通性检查的目标：队列中的供应商，队列为空时退回当前供应商
+fn connectivity_targets(db: &Database, app_type: &str) -> Result<Vec<(String, String)>, AppError> {
+    let mut providers = db.get_failover_providers(app_type)?;
+    if providers.is_empty() {
+        if let Some(id) = db.get_current_provider(app_type)? {
+            providers.extend(db.get_provider_by_id(&id, app_type)?);
+        }
+    }
+    Ok(providers
+        .iter()
+        .filter_map(|p| {
+            ProviderRouter::extract_base_url(p, app_type).map(|url| (p.id.clone(), url))
+        })
+        .collect())
+}
+
+/// 按顺序执行全部检查；数据库不可用时跳过依赖数据库的检查
+pub async fn run(
+    db_path: &Path,
+    app_types: &[String],
+    client: &reqwest::Client,
+) -> Result<Vec<CheckResult>, AppError> {
+    let mut checks = Vec::new();
+
+    let (db_check, db) = check_database(db_path);
+    checks.push(db_check);
+    let db = db.map(Arc::new);
+
+    if let Some(db) = &db {
+        for app_type in app_types {
+            checks.push(check_providers(db, app_type)?);
+            let report = db.check_consistency(app_type, false).await?;
+            checks.push(check_consistency(&report));
+        }
+    }
+
+    if app_types.iter().any(|a| a == "claude") {
+        let base = cc_switch_lib::proxy::python_proxy::python_proxy_base();
+        checks.push(check_python_proxy(client, &base).await);
+    }
+
+    let config = match &db {
+        Some(db) => db.get_proxy_config().await.unwrap_or_default(),
+        None => Default::default(),
+    };
+    let (port_check, proxy_running) =
+        check_proxy_port(client, &config.listen_address, config.listen_port).await;
+    checks.push(port_check);
+
+    let Some(db) = db else {
+        return Ok(checks);
+    };
+
+    let mut app_configs = HashMap::new();
+    for app_type in app_types {
+        app_configs.insert(
+            app_type.clone(),
+            db.get_proxy_config_for_app(app_type).await?,
+        );
+    }
+
+    for app_type in app_types {
+        let targets = connectivity_targets(&db, app_type)?;
+        let timeout = Duration::from_secs(app_configs[app_type].connectivity_timeout_secs.max(1));
+        checks.push(check_connectivity(app_type, &targets, timeout).await);
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    for app_type in app_types {
+        let providers = db.get_failover_providers(app_type)?;
+        let events = if proxy_running {
+            db.query_events(app_type, None, now - EVENT_WINDOW_SECS, 1000)?
+        } else {
+            Vec::new()
+        };
+        let health = db.get_all_provider_health(app_type).await?;
+        let disabled = db.get_disabled_providers(app_type, now)?;
+        checks.push(check_breakers(
+            app_type,
+            &providers,
+            &events,
+            &health,
+            &disabled,
+            proxy_running,
+            now,
+        ));
+    }
+
+    for app_type in app_types {
+        let provider_ids: Vec<String> = db.get_all_providers(app_type)?.into_keys().collect();
+        let events = db.query_events(
+            app_type,
+            Some(EventKind::ModelWarmupFailed),
+            now - EVENT_WINDOW_SECS,
+            100,
+        )?;
+        checks.push(check_model_warmup(app_type, &provider_ids, &events));
+    }
+
+    let service = ProxyService::new(db.clone());
+    for app_type in app_types {
+        let live = service.live_config_taken_over(&AppType::from_str(app_type)?);
+        checks.push(check_live_takeover(
+            app_type,
+            app_configs[app_type].enabled,
+            live,
+            proxy_running,
+        ));
+    }
+
+    Ok(checks)
+}
+
+/// (通过, 警告, 失败) 数量
+pub fn tally(checks: &[CheckResult]) -> (usize, usize, usize) {
+    let count = |s: CheckStatus| checks.iter().filter(|c| c.status == s).count();
+    (
+        count(CheckStatus::Pass),
+        count(CheckStatus::Warn),
+        count(CheckStatus::Fail),
+    )
+}
+
+pub fn format_lines(checks: &[CheckResult]) -> Vec<String> {
+    let mut lines = vec!["\n=== 环境自检 ===".to_string()];
+    for check in checks {
+        let mark = match check.status {
+            CheckStatus::Pass => "✓",
+            CheckStatus::Warn => "⚠",
+            CheckStatus::Fail => "✗",
+        };
+        lines.push(format!("  {mark} {}: {}", check.name, check.detail));
+        if let Some(hint) = &check.hint {
+            lines.push(format!("      → {hint}"));
+        }
+    }
+    let (pass, warn, fail) = tally(checks);
+    lines.push(format!(
+        "\n共 {} 项：{pass} 通过，{warn} 警告，{fail} 失败",
+        checks.len()
+    ));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Router};
+    use serde_json::json;
+
+    async fn spawn_health_stub() -> std::net::SocketAddr {
+        let app = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .route("/health", get(|| async { "ok" }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+        addr
+    }
+
+    /// 绑定后立即释放的端口
+    fn closed_port() -> u16 {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap().port()
+    }
+
+    fn provider(id: &str, base_url: &str) -> Provider {
+        let mut p = Provider::with_id(
+            id.to_string(),
+            id.to_string(),
+            json!({
+                "env": {
+                    "ANTHROPIC_API_KEY": format!("sk-{id}"),
+                    "ANTHROPIC_BASE_URL": base_url
+                }
+            }),
+            None,
+        );
+        p.sort_index = Some(1);
+        p
+    }
+
+    fn event(
+        kind: EventKind,
+        provider: Option<&str>,
+        created_at: i64,
+        detail: serde_json::Value,
+    ) -> EventLogEntry {
+        EventLogEntry {
+            id: 0,
+            created_at,
+            app_type: "claude".to_string(),
+            kind,
+            provider_id: provider.map(str::to_string),
+            supplier: Some("acme".to_string()),
+            detail,
+        }
+    }
+
+    #[test]
+    fn test_check_database_states() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cc-switch.db");
+
+        let (check, db) = check_database(&path);
+        assert_eq!(check.status, CheckStatus::Warn);
+        assert!(db.is_none());
+        assert!(!path.exists(), "自检不应创建数据库");
+
+        drop(Database::open(&path).unwrap());
+        let (check, db) = check_database(&path);
+        assert_eq!(check.status, CheckStatus::Pass, "{check:?}");
+        assert!(db.is_some());
+
+        rusqlite::Connection::open(&path)
+            .unwrap()
+            .execute_batch("PRAGMA user_version = 1;")
+            .unwrap();
+        assert_eq!(check_database(&path).0.status, CheckStatus::Warn);
+
+        std::fs::write(&path, vec![b'x'; 4096]).unwrap();
+        let (check, _) = check_database(&path);
+        assert_eq!(check.status, CheckStatus::Fail, "{check:?}");
+        assert!(check.hint.unwrap().contains("csc db maintain"));
+    }
+
+    #[test]
+    fn test_check_providers_reports_empty_and_issues() {
+        let db = Database::memory().unwrap();
+        assert_eq!(
+            check_providers(&db, "claude").unwrap().status,
+            CheckStatus::Fail
+        );
+
+        db.save_provider("claude", &provider("a", "https://a.example.com"))
+            .unwrap();
+        let check = check_providers(&db, "claude").unwrap();
+        assert_eq!(check.status, CheckStatus::Pass, "{check:?}");
+
+        let mut broken = provider("b", "https://b.example.com");
+        broken.settings_config["env"]["ANTHROPIC_API_KEY"] = json!("${DOCTOR_TEST_UNSET_KEY}");
+        db.save_provider("claude", &broken).unwrap();
+        let check = check_providers(&db, "claude").unwrap();
+        assert_eq!(check.status, CheckStatus::Warn);
+        assert!(
+            check.detail.contains("1 个环境变量占位符未解析"),
+            "{}",
+            check.detail
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_consistency_grades_findings() {
+        let db = Database::memory().unwrap();
+        db.save_provider("claude", &provider("a", "https://a.example.com"))
+            .unwrap();
+        db.set_current_provider("claude", "a").unwrap();
+        let check = check_consistency(&db.check_consistency("claude", false).await.unwrap());
+        assert_eq!(check.status, CheckStatus::Pass, "{check:?}");
+
+        let mut config = db.get_proxy_config_for_app("claude").await.unwrap();
+        config.auto_failover_enabled = true;
+        db.update_proxy_config_for_app(config).await.unwrap();
+        let check = check_consistency(&db.check_consistency("claude", false).await.unwrap());
+        assert_eq!(check.status, CheckStatus::Fail, "{check:?}");
+        assert!(check.detail.contains("队列为空"), "{}", check.detail);
+        assert!(check.hint.unwrap().contains("csc qa claude a"));
+
+        db.save_provider("claude", &provider("b", "https://b.example.com"))
+            .unwrap();
+        db.add_to_failover_queue("claude", "b").unwrap();
+        let check = check_consistency(&db.check_consistency("claude", false).await.unwrap());
+        assert_eq!(check.status, CheckStatus::Warn, "{check:?}");
+    }
+
+    #[tokio::test]
+    async fn test_check_python_proxy_reachability() {
+        let client = reqwest::Client::new();
+        let addr = spawn_health_stub().await;
+        let check = check_python_proxy(&client, &format!("http://{addr}")).await;
+        assert_eq!(check.status, CheckStatus::Pass);
+
+        let check =
+            check_python_proxy(&client, &format!("http://127.0.0.1:{}", closed_port())).await;
+        assert_eq!(check.status, CheckStatus::Fail);
+        assert!(check.hint.unwrap().contains("CC_SWITCH_PYTHON_PROXY_BASE"));
+    }
+
+    #[tokio::test]
+    async fn test_check_proxy_port_running_free_and_occupied() {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(1))
+            .build()
+            .unwrap();
+
+        let addr = spawn_health_stub().await;
+        let (check, running) = check_proxy_port(&client, "127.0.0.1", addr.port()).await;
+        assert!(running);
+        assert_eq!(check.status, CheckStatus::Pass);
+
+        let (check, running) = check_proxy_port(&client, "127.0.0.1", closed_port()).await;
+        assert!(!running);
+        assert_eq!(check.status, CheckStatus::Pass);
+        assert!(check.detail.contains("空闲"));
+
+        // 被非代理程序占用：不响应 /health，且无法绑定
+        let occupied = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = occupied.local_addr().unwrap().port();
+        let (check, running) = check_proxy_port(&client, "127.0.0.1", port).await;
+        assert!(!running);
+        assert_eq!(check.status, CheckStatus::Fail);
+    }
+
+    #[tokio::test]
+    async fn test_check_connectivity_dedupes_and_grades() {
+        let timeout = Duration::from_secs(2);
+        let up = format!("http://{}", spawn_health_stub().await);
+        let down = format!("http://127.0.0.1:{}", closed_port());
+
+        let check = check_connectivity(
+            "claude",
+            &[("a".to_string(), up.clone()), ("b".to_string(), up.clone())],
+            timeout,
+        )
+        .await;
+        assert_eq!(check.status, CheckStatus::Pass);
+        assert!(
+            check.detail.starts_with("1 个地址可连通"),
+            "{}",
+            check.detail
+        );
+
+        let check = check_connectivity(
+            "claude",
+            &[("a".to_string(), up), ("c".to_string(), down.clone())],
+            timeout,
+        )
+        .await;
+        assert_eq!(check.status, CheckStatus::Warn);
+        assert!(
+            check.detail.contains(&format!("{down}（c）")),
+            "{}",
+            check.detail
+        );
+
+        let check = check_connectivity("claude", &[("c".to_string(), down)], timeout).await;
+        assert_eq!(check.status, CheckStatus::Fail);
+        assert_eq!(
+            check_connectivity("claude", &[], timeout).await.status,
+            CheckStatus::Warn
+        );
+        let invalid = [("d".to_string(), "not a url".to_string())];
+        let check = check_connectivity("claude", &invalid, timeout).await;
+        assert_eq!(check.status, CheckStatus::Fail);
+    }
+
+    #[test]
+    fn test_check_breakers_summarizes_state() {
+        let now = 1_000_000;
+        let providers = vec![provider("a", "https://a"), provider("b", "https://b")];
+        let healthy = HashMap::new();
+        let none = HashMap::new();
+
+        let check = check_breakers("claude", &providers, &[], &healthy, &none, false, now);
+        assert_eq!(check.status, CheckStatus::Pass);
+        assert!(check.detail.contains("代理未运行"));
+
+        // 最新事件为准：a 已恢复，b 熔断中；supplier 冷却尚未结束
+        let events = vec![
+            event(EventKind::Cooldown, None, now - 10, json!({ "secs": 60 })),
+            event(EventKind::BreakerOpen, Some("b"), now - 20, json!({})),
+            event(EventKind::BreakerClose, Some("a"), now - 30, json!({})),
+            event(EventKind::BreakerOpen, Some("a"), now - 40, json!({})),
+        ];
+        let check = check_breakers("claude", &providers, &events, &healthy, &none, true, now);
+        assert_eq!(check.status, CheckStatus::Warn);
+        assert_eq!(check.detail, "b: 熔断中；supplier acme: 冷却中，剩余 50s");
+        // 熔断状态只存在于运行中的代理
+        assert_eq!(
+            check_breakers("claude", &providers, &events, &healthy, &none, false, now).status,
+            CheckStatus::Pass
+        );
+
+        let disabled = HashMap::from([("a".to_string(), now + 3600)]);
+        let check = check_breakers(
+            "claude", &providers, &events, &healthy, &disabled, true, now,
+        );
+        assert_eq!(check.status, CheckStatus::Fail);
+        assert!(check.detail.starts_with("所有供应商均不可用"));
+    }
+
+    #[test]
+    fn test_check_model_warmup_reports_latest_failure_per_provider() {
+        let now = 1_000_000;
+        let ids = vec!["a".to_string(), "b".to_string()];
+
+        let check = check_model_warmup("claude", &ids, &[]);
+        assert_eq!(check.status, CheckStatus::Pass);
+
+        // 每个供应商只取最新一条；已删除的供应商忽略
+        let events = vec![
+            event(
+                EventKind::ModelWarmupFailed,
+                Some("a"),
+                now - 10,
+                json!({ "error": "401 invalid key" }),
+            ),
+            event(
+                EventKind::ModelWarmupFailed,
+                Some("a"),
+                now - 20,
+                json!({ "error": "timeout" }),
+            ),
+            event(
+                EventKind::ModelWarmupFailed,
+                Some("gone"),
+                now - 30,
+                json!({ "error": "404" }),
+            ),
+        ];
+        let check = check_model_warmup("claude", &ids, &events);
+        assert_eq!(check.status, CheckStatus::Warn);
+        assert_eq!(check.detail, "1 个供应商预热失败: a: 401 invalid key");
+    }
+
+    #[test]
+    fn test_check_live_takeover_consistency() {
+        let status =
+            |enabled, live, running| check_live_takeover("codex", enabled, live, running).status;
+        assert_eq!(status(true, Some(true), true), CheckStatus::Pass);
+        assert_eq!(status(true, Some(true), false), CheckStatus::Fail);
+        assert_eq!(status(true, Some(false), true), CheckStatus::Warn);
+        assert_eq!(status(true, None, true), CheckStatus::Warn);
+        assert_eq!(status(false, Some(true), false), CheckStatus::Fail);
+        assert_eq!(status(false, Some(false), false), CheckStatus::Pass);
+        assert_eq!(status(false, None, false), CheckStatus::Pass);
+        assert!(check_live_takeover("codex", false, Some(true), false)
+            .hint
+            .unwrap()
+            .contains("csc takeover restore codex"));
+    }
+
+    #[test]
+    fn test_format_lines_and_tally() {
+        let checks = vec![
+            CheckResult::pass("数据库", "ok"),
+            CheckResult::warn("代理端口", "busy", "换端口"),
+            CheckResult::fail("Python 代理", "down", "启动"),
+        ];
+        assert_eq!(tally(&checks), (1, 1, 1));
+        let text = format_lines(&checks).join("\n");
+        assert!(text.contains("  ✓ 数据库: ok"));
+        assert!(text.contains("  ⚠ 代理端口: busy\n      → 换端口"));
+        assert!(text.ends_with("共 3 项：1 通过，1 警告，1 失败"));
+        let value = serde_json::to_value(&checks[0]).unwrap();
+        assert_eq!(
+            value,
+            json!({ "name": "数据库", "status": "pass", "detail": "ok" })
+        );
+    }
+}
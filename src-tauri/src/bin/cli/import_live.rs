@@ -0,0 +1,75 @@
+//! 从现有 live 配置导入供应商（`csc import-live`）
+//!
+//! 逐个应用列出新建 / 更新 / 未变化 / 跳过的结果；live 文件不会被修改。
+
+use cc_switch_lib::{LiveImportAction, LiveImportItem};
+
+fn item_line(item: &LiveImportItem) -> String {
+    let provider = format!(
+        "{} - {}  {}",
+        item.provider_id.as_deref().unwrap_or("-"),
+        item.name.as_deref().unwrap_or("-"),
+        item.base_url.as_deref().unwrap_or("-")
+    );
+    match item.action {
+        LiveImportAction::Created => format!("✓ [{}] 已新建 {}", item.app_type, provider),
+        LiveImportAction::Updated => format!("✓ [{}] 已更新 {}", item.app_type, provider),
+        LiveImportAction::Unchanged => format!("  [{}] 无变化 {}", item.app_type, provider),
+        LiveImportAction::Skipped => format!(
+            "⚠ [{}] 跳过: {}（{}）",
+            item.app_type,
+            item.reason.as_deref().unwrap_or("-"),
+            item.source
+        ),
+    }
+}
+
+/// 可读输出（逐行）
+pub fn format_lines(items: &[LiveImportItem]) -> Vec<String> {
+    let mut lines: Vec<String> = items.iter().map(item_line).collect();
+
+    let count = |action: LiveImportAction| items.iter().filter(|i| i.action == action).count();
+    lines.push(format!(
+        "\n新建 {}，更新 {}，无变化 {}，跳过 {}",
+        count(LiveImportAction::Created),
+        count(LiveImportAction::Updated),
+        count(LiveImportAction::Unchanged),
+        count(LiveImportAction::Skipped)
+    ));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(app: &str, action: LiveImportAction) -> LiveImportItem {
+        let imported = action != LiveImportAction::Skipped;
+        LiveImportItem {
+            app_type: app.to_string(),
+            action,
+            source: format!("/home/u/.{app}/settings.json"),
+            provider_id: imported.then(|| "live-0123456789ab".to_string()),
+            name: imported.then(|| "relay.example.com".to_string()),
+            base_url: imported.then(|| "https://relay.example.com".to_string()),
+            reason: (!imported).then(|| "未配置 API Key".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_format_lines_reports_each_app_and_totals() {
+        let items = vec![
+            item("claude", LiveImportAction::Created),
+            item("codex", LiveImportAction::Unchanged),
+            item("gemini", LiveImportAction::Skipped),
+        ];
+
+        let text = format_lines(&items).join("\n");
+        assert!(text.contains(
+            "✓ [claude] 已新建 live-0123456789ab - relay.example.com  https://relay.example.com"
+        ));
+        assert!(text.contains("  [codex] 无变化 live-0123456789ab"));
+        assert!(text.contains("⚠ [gemini] 跳过: 未配置 API Key（/home/u/.gemini/settings.json）"));
+        assert!(text.contains("新建 1，更新 0，无变化 1，跳过 1"));
+    }
+}
@@ -0,0 +1,661 @@
+//! 开机自启（`csc service install|uninstall|status`）
+//!
+//! 为无界面主机生成并注册服务定义，不需要手写单元文件：
+//! - Linux：systemd 单元。`--user` 安装到 `~/.config/systemd/user`，否则安装到 `/etc/systemd/system` 并以当前用户运行
+//! - macOS：launchd plist。`--user` 安装到 `~/Library/LaunchAgents`，否则安装到 `/Library/LaunchDaemons`
+//! - Windows：计划任务。`--user` 在登录时启动，否则开机时启动（无需登录）
+//!
+//! systemd 执行 `proxy start --daemon`，通过 PID 文件跟踪守护进程；launchd 与计划任务要求被托管的进程
+//! 不自行脱离，改为前台 `proxy start`，输出追加到 `~/.cc-switch/logs/rust_proxy.log`。
+//! 异常退出时自动重启；`csc proxy stop` 正常退出后不再拉起。
+
+use cc_switch_lib::proxy::inflight::DEFAULT_DRAIN_TIMEOUT_SECS;
+use cc_switch_lib::AppError;
+use std::path::{Path, PathBuf};
+
+pub const SYSTEMD_UNIT: &str = "cc-switch-proxy.service";
+pub const LAUNCHD_LABEL: &str = "com.cc-switch.proxy";
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub const WINDOWS_TASK: &str = "CC-Switch Proxy";
+
+const DESCRIPTION: &str = "CC-Switch 代理服务器";
+/// 异常退出后重新拉起前的等待时间（秒）
+const RESTART_DELAY_SECS: u64 = 5;
+/// 停止时等待排空进行中请求的时间（默认排空超时 + 余量）
+const STOP_TIMEOUT_SECS: u64 = DEFAULT_DRAIN_TIMEOUT_SECS + 15;
+
+/// 服务定义所需的运行环境
+#[derive(Debug, Clone)]
+pub struct ServiceSpec {
+    /// csc 可执行文件的绝对路径
+    pub exe: PathBuf,
+    /// 运行用户的主目录（决定 `~/.cc-switch` 的位置）
+    pub home: PathBuf,
+    /// 运行用户（Windows 为 `域\用户名`）
+    pub user: String,
+    /// 用户级服务（否则为系统级）
+    pub user_scope: bool,
+}
+
+impl ServiceSpec {
+    /// 按当前进程推断：可执行文件取规范化后的绝对路径；sudo 安装系统级服务时以调用 sudo 的用户运行
+    pub fn detect(user_scope: bool) -> Result<Self, AppError> {
+        let exe = std::env::current_exe()
+            .and_then(|p| p.canonicalize())
+            .map_err(|e| AppError::Message(format!("无法获取 csc 的绝对路径: {e}")))?;
+        let (user, home) = run_as(user_scope)?;
+        Ok(Self {
+            exe,
+            home,
+            user,
+            user_scope,
+        })
+    }
+
+    fn config_dir(&self) -> PathBuf {
+        self.home.join(".cc-switch")
+    }
+
+    fn log_path(&self) -> PathBuf {
+        self.config_dir().join("logs").join("rust_proxy.log")
+    }
+
+    fn pid_path(&self) -> PathBuf {
+        self.config_dir().join("proxy.pid")
+    }
+
+    fn scope_label(&self) -> &'static str {
+        if self.user_scope {
+            "用户级"
+        } else {
+            "系统级"
+        }
+    }
+}
+
+#[cfg(unix)]
+fn run_as(user_scope: bool) -> Result<(String, PathBuf), AppError> {
+    use nix::unistd::{geteuid, User};
+
+    let sudo_user = std::env::var("SUDO_USER")
+        .ok()
+        .filter(|u| !u.is_empty() && u != "root");
+    if let (true, Some(name)) = (geteuid().is_root(), sudo_user) {
+        if user_scope {
+            return Err(AppError::Message(
+                "用户级服务请不要通过 sudo 安装，直接运行 csc service install --user".to_string(),
+            ));
+        }
+        let user = User::from_name(&name)
+            .ok()
+            .flatten()
+            .ok_or_else(|| AppError::Message(format!("找不到用户 {name}")))?;
+        return Ok((user.name, user.dir));
+    }
+
+    let user = User::from_uid(geteuid())
+        .ok()
+        .flatten()
+        .ok_or_else(|| AppError::Message("无法获取当前用户".to_string()))?;
+    let home = dirs::home_dir().unwrap_or(user.dir);
+    Ok((user.name, home))
+}
+
+#[cfg(windows)]
+fn run_as(_user_scope: bool) -> Result<(String, PathBuf), AppError> {
+    let name =
+        std::env::var("USERNAME").map_err(|_| AppError::Message("无法获取当前用户".to_string()))?;
+    let user = match std::env::var("USERDOMAIN") {
+        Ok(domain) if !domain.is_empty() => format!("{domain}\\{name}"),
+        _ => name,
+    };
+    let home =
+        dirs::home_dir().ok_or_else(|| AppError::Message("无法获取用户主目录".to_string()))?;
+    Ok((user, home))
+}
+
+// ============================================================================
+// 服务定义生成
+// ============================================================================
+
+/// systemd 单元：`Type=forking` + `PIDFile` 跟踪守护进程，异常退出 5 秒后重启
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub fn systemd_unit(spec: &ServiceSpec) -> String {
+    let mut lines = vec!["[Unit]".to_string(), format!("Description={DESCRIPTION}")];
+    // 用户实例中没有 network-online.target
+    if !spec.user_scope {
+        lines.push("After=network-online.target".to_string());
+        lines.push("Wants=network-online.target".to_string());
+    }
+    lines.extend([
+        String::new(),
+        "[Service]".to_string(),
+        "Type=forking".to_string(),
+    ]);
+    if !spec.user_scope {
+        lines.push(format!("User={}", spec.user));
+    }
+    lines.extend([
+        format!(
+            "Environment={}",
+            systemd_quote(&format!("HOME={}", spec.home.display()))
+        ),
+        format!(
+            "PIDFile={}",
+            systemd_escape(&spec.pid_path().display().to_string())
+        ),
+        format!(
+            "ExecStart={} proxy start --daemon",
+            systemd_quote(&spec.exe.display().to_string())
+        ),
+        "Restart=on-failure".to_string(),
+        format!("RestartSec={RESTART_DELAY_SECS}"),
+        format!("TimeoutStopSec={STOP_TIMEOUT_SECS}"),
+        String::new(),
+        "[Install]".to_string(),
+        format!(
+            "WantedBy={}",
+            if spec.user_scope {
+                "default.target"
+            } else {
+                "multi-user.target"
+            }
+        ),
+    ]);
+    lines.join("\n") + "\n"
+}
+
+/// launchd plist：前台运行，非正常退出时重新拉起
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub fn launchd_plist(spec: &ServiceSpec) -> String {
+    let string = |s: &str| format!("<string>{}</string>", xml_escape(s));
+    let log = spec.log_path().display().to_string();
+    let mut lines = vec![
+        r#"<?xml version="1.0" encoding="UTF-8"?>"#.to_string(),
+        r#"<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">"#.to_string(),
+        r#"<plist version="1.0">"#.to_string(),
+        "<dict>".to_string(),
+        "  <key>Label</key>".to_string(),
+        format!("  {}", string(LAUNCHD_LABEL)),
+        "  <key>ProgramArguments</key>".to_string(),
+        "  <array>".to_string(),
+        format!("    {}", string(&spec.exe.display().to_string())),
+        format!("    {}", string("proxy")),
+        format!("    {}", string("start")),
+        "  </array>".to_string(),
+        "  <key>EnvironmentVariables</key>".to_string(),
+        "  <dict>".to_string(),
+        "    <key>HOME</key>".to_string(),
+        format!("    {}", string(&spec.home.display().to_string())),
+        "  </dict>".to_string(),
+    ];
+    if !spec.user_scope {
+        lines.push("  <key>UserName</key>".to_string());
+        lines.push(format!("  {}", string(&spec.user)));
+    }
+    lines.extend([
+        "  <key>WorkingDirectory</key>".to_string(),
+        format!("  {}", string(&spec.home.display().to_string())),
+        "  <key>RunAtLoad</key>".to_string(),
+        "  <true/>".to_string(),
+        "  <key>KeepAlive</key>".to_string(),
+        "  <dict>".to_string(),
+        "    <key>SuccessfulExit</key>".to_string(),
+        "    <false/>".to_string(),
+        "  </dict>".to_string(),
+        "  <key>ThrottleInterval</key>".to_string(),
+        format!("  <integer>{RESTART_DELAY_SECS}</integer>"),
+        "  <key>ExitTimeOut</key>".to_string(),
+        format!("  <integer>{STOP_TIMEOUT_SECS}</integer>"),
+        "  <key>StandardOutPath</key>".to_string(),
+        format!("  {}", string(&log)),
+        "  <key>StandardErrorPath</key>".to_string(),
+        format!("  {}", string(&log)),
+        "</dict>".to_string(),
+        "</plist>".to_string(),
+    ]);
+    lines.join("\n") + "\n"
+}
+
+/// 计划任务 XML：开机（系统级，S4U 无需保存密码）或登录（用户级）时前台运行，失败后每分钟重试
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub fn windows_task_xml(spec: &ServiceSpec) -> String {
+    let user = xml_escape(&spec.user);
+    let (trigger, logon_type) = if spec.user_scope {
+        (
+            format!("    <LogonTrigger>\n      <Enabled>true</Enabled>\n      <UserId>{user}</UserId>\n    </LogonTrigger>"),
+            "InteractiveToken",
+        )
+    } else {
+        (
+            "    <BootTrigger>\n      <Enabled>true</Enabled>\n    </BootTrigger>".to_string(),
+            "S4U",
+        )
+    };
+    // 前台进程的输出由 cmd 重定向到日志文件；/c 后整体再包一层引号，cmd 只剥掉最外层
+    let arguments = format!(
+        "/c \"\"{}\" proxy start >> \"{}\" 2>&1\"",
+        spec.exe.display(),
+        spec.log_path().display()
+    );
+    [
+        r#"<?xml version="1.0" encoding="UTF-16"?>"#.to_string(),
+        r#"<Task version="1.2" xmlns="http://schemas.microsoft.com/windows/2004/02/mit/task">"#
+            .to_string(),
+        "  <RegistrationInfo>".to_string(),
+        format!("    <Description>{DESCRIPTION}</Description>"),
+        "  </RegistrationInfo>".to_string(),
+        "  <Triggers>".to_string(),
+        trigger,
+        "  </Triggers>".to_string(),
+        "  <Principals>".to_string(),
+        r#"    <Principal id="Author">"#.to_string(),
+        format!("      <UserId>{user}</UserId>"),
+        format!("      <LogonType>{logon_type}</LogonType>"),
+        "      <RunLevel>LeastPrivilege</RunLevel>".to_string(),
+        "    </Principal>".to_string(),
+        "  </Principals>".to_string(),
+        "  <Settings>".to_string(),
+        "    <MultipleInstancesPolicy>IgnoreNew</MultipleInstancesPolicy>".to_string(),
+        "    <DisallowStartIfOnBatteries>false</DisallowStartIfOnBatteries>".to_string(),
+        "    <StopIfGoingOnBatteries>false</StopIfGoingOnBatteries>".to_string(),
+        "    <StartWhenAvailable>true</StartWhenAvailable>".to_string(),
+        "    <ExecutionTimeLimit>PT0S</ExecutionTimeLimit>".to_string(),
+        "    <RestartOnFailure>".to_string(),
+        "      <Interval>PT1M</Interval>".to_string(),
+        "      <Count>999</Count>".to_string(),
+        "    </RestartOnFailure>".to_string(),
+        "  </Settings>".to_string(),
+        r#"  <Actions Context="Author">"#.to_string(),
+        "    <Exec>".to_string(),
+        "      <Command>cmd.exe</Command>".to_string(),
+        format!("      <Arguments>{}</Arguments>", xml_escape(&arguments)),
+        format!(
+            "      <WorkingDirectory>{}</WorkingDirectory>",
+            xml_escape(&spec.home.display().to_string())
+        ),
+        "    </Exec>".to_string(),
+        "  </Actions>".to_string(),
+        "</Task>".to_string(),
+    ]
+    .join("\r\n")
+        + "\r\n"
+}
+
+/// systemd 中 `%` 为说明符前缀
+fn systemd_escape(s: &str) -> String {
+    s.replace('%', "%%")
+}
+
+/// systemd 双引号参数（路径含空格时仍为一个参数）
+fn systemd_quote(s: &str) -> String {
+    format!(
+        "\"{}\"",
+        systemd_escape(s).replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+// ============================================================================
+// 注册 / 移除 / 状态
+// ============================================================================
+
+/// 服务定义文件的安装位置
+pub fn definition_path(spec: &ServiceSpec) -> PathBuf {
+    #[cfg(target_os = "linux")]
+    {
+        if spec.user_scope {
+            dirs::config_dir()
+                .unwrap_or_else(|| spec.home.join(".config"))
+                .join("systemd")
+                .join("user")
+                .join(SYSTEMD_UNIT)
+        } else {
+            PathBuf::from("/etc/systemd/system").join(SYSTEMD_UNIT)
+        }
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let dir = if spec.user_scope {
+            spec.home.join("Library").join("LaunchAgents")
+        } else {
+            PathBuf::from("/Library/LaunchDaemons")
+        };
+        dir.join(format!("{LAUNCHD_LABEL}.plist"))
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        // 计划任务由 schtasks 注册，这里保留一份 XML 便于查看
+        spec.config_dir()
+            .join("service")
+            .join("cc-switch-proxy.xml")
+    }
+}
+
+fn run(program: &str, args: &[&str]) -> Result<String, AppError> {
+    let output = std::process::Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| AppError::Message(format!("执行 {program} 失败: {e}")))?;
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if output.status.success() {
+        Ok(stdout)
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        Err(AppError::Message(format!(
+            "{program} {} 失败: {}",
+            args.join(" "),
+            if stderr.is_empty() { stdout } else { stderr }
+        )))
+    }
+}
+
+fn write_definition(path: &Path, content: &[u8]) -> Result<(), AppError> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| AppError::io(dir, e))?;
+    }
+    std::fs::write(path, content).map_err(|e| AppError::io(path, e))
+}
+
+/// 代理已在运行（手动启动的守护进程）时只安装定义，避免服务立即启动失败
+fn proxy_running(spec: &ServiceSpec) -> Option<i32> {
+    crate::daemon::read_pid_file(&spec.pid_path())
+        .filter(|pid| crate::daemon::is_process_alive(*pid))
+}
+
+/// 生成服务定义并注册为开机自启
+pub fn install(spec: &ServiceSpec) -> Result<(), AppError> {
+    let path = definition_path(spec);
+    let running = proxy_running(spec);
+    // 前台运行时输出直接追加到日志文件，目录需提前存在
+    if let Some(dir) = spec.log_path().parent() {
+        std::fs::create_dir_all(dir).map_err(|e| AppError::io(dir, e))?;
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        write_definition(&path, systemd_unit(spec).as_bytes())?;
+        let scope: &[&str] = if spec.user_scope { &["--user"] } else { &[] };
+        run("systemctl", &[scope, &["daemon-reload"]].concat())?;
+        let enable: &[&str] = if running.is_some() {
+            &["enable", SYSTEMD_UNIT]
+        } else {
+            &["enable", "--now", SYSTEMD_UNIT]
+        };
+        run("systemctl", &[scope, enable].concat())?;
+    }
+    #[cfg(target_os = "macos")]
+    {
+        write_definition(&path, launchd_plist(spec).as_bytes())?;
+        // 放入 LaunchAgents/LaunchDaemons 即会在下次登录/开机时加载；未运行时立即加载
+        if running.is_none() {
+            let domain = launchd_domain(spec);
+            let target = format!("{domain}/{LAUNCHD_LABEL}");
+            let _ = run("launchctl", &["bootout", &target]);
+            run(
+                "launchctl",
+                &["bootstrap", &domain, &path.display().to_string()],
+            )?;
+        }
+    }
+    #[cfg(target_os = "windows")]
+    {
+        // schtasks /XML 要求 UTF-16 编码
+        let mut bytes = vec![0xFF, 0xFE];
+        for unit in windows_task_xml(spec).encode_utf16() {
+            bytes.extend_from_slice(&unit.to_le_bytes());
+        }
+        write_definition(&path, &bytes)?;
+        run(
+            "schtasks",
+            &[
+                "/Create",
+                "/TN",
+                WINDOWS_TASK,
+                "/XML",
+                &path.display().to_string(),
+                "/F",
+            ],
+        )?;
+        if running.is_none() {
+            run("schtasks", &["/Run", "/TN", WINDOWS_TASK])?;
+        }
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+    {
+        let _ = (&path, running);
+        return Err(AppError::Message("当前平台不支持 csc service".to_string()));
+    }
+
+    println!("✓ 已安装{}开机自启", spec.scope_label());
+    println!("  定义文件: {}", path.display());
+    println!("  可执行文件: {}", spec.exe.display());
+    println!("  日志: {}", spec.log_path().display());
+    match running {
+        Some(pid) => println!(
+            "  代理已在运行（PID {pid}），本次未启动服务；csc proxy stop 后运行 csc service install 立即由服务接管"
+        ),
+        None => println!("  代理已由服务启动，查看状态: csc service status"),
+    }
+    if cfg!(target_os = "linux") && spec.user_scope {
+        println!(
+            "  提示: 用户单元默认在登录后启动，未登录也需随开机启动请执行 loginctl enable-linger {}",
+            spec.user
+        );
+    }
+    Ok(())
+}
+
+/// 停止服务并移除定义
+pub fn uninstall(spec: &ServiceSpec) -> Result<(), AppError> {
+    let path = definition_path(spec);
+
+    #[cfg(target_os = "linux")]
+    {
+        let scope: &[&str] = if spec.user_scope { &["--user"] } else { &[] };
+        let _ = run(
+            "systemctl",
+            &[scope, &["disable", "--now", SYSTEMD_UNIT]].concat(),
+        );
+        remove_definition(&path)?;
+        run("systemctl", &[scope, &["daemon-reload"]].concat())?;
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let _ = run(
+            "launchctl",
+            &[
+                "bootout",
+                &format!("{}/{LAUNCHD_LABEL}", launchd_domain(spec)),
+            ],
+        );
+        remove_definition(&path)?;
+    }
+    #[cfg(target_os = "windows")]
+    {
+        let _ = run("schtasks", &["/End", "/TN", WINDOWS_TASK]);
+        let _ = run("schtasks", &["/Delete", "/TN", WINDOWS_TASK, "/F"]);
+        remove_definition(&path)?;
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+    {
+        let _ = &path;
+        return Err(AppError::Message("当前平台不支持 csc service".to_string()));
+    }
+
+    println!("✓ 已移除{}开机自启", spec.scope_label());
+    Ok(())
+}
+
+/// 定义文件是否存在 + 服务管理器中的注册/运行状态
+pub fn status(spec: &ServiceSpec) -> Result<(), AppError> {
+    let path = definition_path(spec);
+    println!("\n=== {}开机自启 ===", spec.scope_label());
+    println!(
+        "  定义文件: {}（{}）",
+        path.display(),
+        if path.exists() {
+            "已安装"
+        } else {
+            "未安装"
+        }
+    );
+
+    #[cfg(target_os = "linux")]
+    {
+        let scope: &[&str] = if spec.user_scope { &["--user"] } else { &[] };
+        // is-enabled / is-active 在未启用或未运行时返回非零，输出仍是状态本身
+        let state = |action: &str| {
+            run("systemctl", &[scope, &[action, SYSTEMD_UNIT]].concat())
+                .unwrap_or_else(|e| e.to_string())
+        };
+        println!("  启用: {}", state("is-enabled"));
+        println!("  运行: {}", state("is-active"));
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let target = format!("{}/{LAUNCHD_LABEL}", launchd_domain(spec));
+        match run("launchctl", &["print", &target]) {
+            Ok(out) => {
+                for line in out.lines().filter(|l| {
+                    let l = l.trim_start();
+                    l.starts_with("state =")
+                        || l.starts_with("pid =")
+                        || l.starts_with("last exit code")
+                }) {
+                    println!("  {}", line.trim());
+                }
+            }
+            Err(_) => println!("  launchd: 未加载"),
+        }
+    }
+    #[cfg(target_os = "windows")]
+    {
+        match run("schtasks", &["/Query", "/TN", WINDOWS_TASK, "/FO", "LIST"]) {
+            Ok(out) => {
+                for line in out.lines().filter(|l| !l.trim().is_empty()) {
+                    println!("  {}", line.trim());
+                }
+            }
+            Err(_) => println!("  计划任务: 未注册"),
+        }
+    }
+
+    match proxy_running(spec) {
+        Some(pid) => println!("  代理: 运行中（PID {pid}）"),
+        None => println!("  代理: 未运行"),
+    }
+    Ok(())
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+fn remove_definition(path: &Path) -> Result<(), AppError> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(AppError::io(path, e)),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn launchd_domain(spec: &ServiceSpec) -> String {
+    if spec.user_scope {
+        format!("gui/{}", nix::unistd::getuid())
+    } else {
+        "system".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(user_scope: bool) -> ServiceSpec {
+        ServiceSpec {
+            exe: PathBuf::from("/opt/cc switch/bin/csc"),
+            home: PathBuf::from("/home/alice"),
+            user: "alice".to_string(),
+            user_scope,
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_systemd_unit_tracks_daemon_pid_and_restarts_on_failure() {
+        let unit = systemd_unit(&spec(false));
+        for line in [
+            "Type=forking",
+            "User=alice",
+            "Environment=\"HOME=/home/alice\"",
+            "PIDFile=/home/alice/.cc-switch/proxy.pid",
+            "ExecStart=\"/opt/cc switch/bin/csc\" proxy start --daemon",
+            "Restart=on-failure",
+            "RestartSec=5",
+            "TimeoutStopSec=45",
+            "After=network-online.target",
+            "WantedBy=multi-user.target",
+        ] {
+            assert!(unit.lines().any(|l| l == line), "缺少 {line}:\n{unit}");
+        }
+
+        let user = systemd_unit(&spec(true));
+        assert!(user.lines().any(|l| l == "WantedBy=default.target"));
+        assert!(!user.contains("User="));
+        assert!(!user.contains("network-online.target"));
+
+        let mut odd = spec(true);
+        odd.home = PathBuf::from("/home/50%\"x");
+        assert!(systemd_unit(&odd).contains("Environment=\"HOME=/home/50%%\\\"x\""));
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn test_launchd_plist_runs_in_foreground_with_keepalive() {
+        let plist = launchd_plist(&spec(false));
+        assert!(plist.contains("<string>com.cc-switch.proxy</string>"));
+        assert!(plist.contains(
+            "<array>\n    <string>/opt/cc switch/bin/csc</string>\n    <string>proxy</string>\n    <string>start</string>\n  </array>"
+        ));
+        assert!(!plist.contains("--daemon"));
+        assert!(plist.contains("<key>HOME</key>\n    <string>/home/alice</string>"));
+        assert!(plist.contains("<key>UserName</key>\n  <string>alice</string>"));
+        assert!(plist.contains("<key>SuccessfulExit</key>\n    <false/>"));
+        assert!(plist.contains(
+            "<key>StandardErrorPath</key>\n  <string>/home/alice/.cc-switch/logs/rust_proxy.log</string>"
+        ));
+        assert!(!launchd_plist(&spec(true)).contains("UserName"));
+
+        let mut odd = spec(true);
+        odd.home = PathBuf::from("/Users/a&b");
+        assert!(launchd_plist(&odd).contains("<string>/Users/a&amp;b</string>"));
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn test_windows_task_restarts_on_failure() {
+        let mut system = spec(false);
+        system.exe = PathBuf::from(r"C:\Program Files\cc-switch\csc.exe");
+        system.home = PathBuf::from(r"C:\Users\alice");
+        system.user = r"HOST\alice".to_string();
+        let xml = windows_task_xml(&system);
+        assert!(xml.contains("<BootTrigger>"));
+        assert!(xml.contains(r"<UserId>HOST\alice</UserId>"));
+        assert!(xml.contains("<LogonType>S4U</LogonType>"));
+        assert!(xml.contains("<RestartOnFailure>\r\n      <Interval>PT1M</Interval>"));
+        assert!(xml.contains("<ExecutionTimeLimit>PT0S</ExecutionTimeLimit>"));
+        assert!(xml.contains(
+            r"<Arguments>/c &quot;&quot;C:\Program Files\cc-switch\csc.exe&quot; proxy start &gt;&gt; &quot;C:\Users\alice\.cc-switch\logs\rust_proxy.log&quot; 2&gt;&amp;1&quot;</Arguments>"
+        ));
+
+        system.user_scope = true;
+        let xml = windows_task_xml(&system);
+        assert!(xml.contains("<LogonTrigger>"));
+        assert!(xml.contains("<LogonType>InteractiveToken</LogonType>"));
+    }
+}
@@ -0,0 +1,101 @@
+//! 影子流量对比（`csc shadow stats`）
+//!
+//! 按比例镜像到影子供应商的非流式请求只写入请求日志（`shadow = 1`），
+//! 这里把同一窗口内的主请求（所有供应商合计）与影子请求并排对比成功率与延迟分位数。
+
+use cc_switch_lib::{LatencyPercentiles, ShadowComparison};
+
+fn format_ms(ms: Option<u64>) -> String {
+    ms.map(|v| format!("{v}ms"))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+fn format_side(label: &str, stats: &LatencyPercentiles) -> String {
+    if stats.count == 0 {
+        return format!("  {label}  无请求");
+    }
+    format!(
+        "  {label}  请求 {}  成功率 {:.1}%  p50 {}  p95 {}  p99 {}",
+        stats.count,
+        (1.0 - stats.error_rate) * 100.0,
+        format_ms(stats.p50_ms),
+        format_ms(stats.p95_ms),
+        format_ms(stats.p99_ms)
+    )
+}
+
+/// 可读输出（逐行）；`sample_percent` 为 None 表示当前未启用影子流量
+pub fn format_lines(
+    app_type: &str,
+    window: &str,
+    provider: &str,
+    sample_percent: Option<u8>,
+    cmp: &ShadowComparison,
+) -> Vec<String> {
+    let mut lines = vec![format!(
+        "\n=== {} 影子流量对比（最近 {}） ===",
+        app_type, window
+    )];
+    match sample_percent {
+        Some(p) => lines.push(format!("  影子供应商: {provider}（采样 {p}%）")),
+        None => lines.push(format!("  影子供应商: {provider}（当前未启用）")),
+    }
+    lines.push(format_side("主请求", &cmp.primary));
+    lines.push(format_side("影子  ", &cmp.shadow));
+
+    if let (Some(primary), Some(shadow)) = (cmp.primary.p50_ms, cmp.shadow.p50_ms) {
+        let diff = shadow as i64 - primary as i64;
+        lines.push(format!(
+            "  p50 差值: {}{}ms（影子 - 主请求）",
+            if diff > 0 { "+" } else { "" },
+            diff
+        ));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(count: u64, error_rate: f64, p50: Option<u64>) -> LatencyPercentiles {
+        LatencyPercentiles {
+            count,
+            error_rate,
+            p50_ms: p50,
+            p95_ms: p50.map(|v| v * 2),
+            p99_ms: p50.map(|v| v * 3),
+        }
+    }
+
+    #[test]
+    fn format_lines_compares_both_sides() {
+        let cmp = ShadowComparison {
+            primary: stats(200, 0.05, Some(1200)),
+            shadow: stats(20, 0.1, Some(900)),
+        };
+        let lines = format_lines("claude", "24h", "cheap", Some(10), &cmp);
+        assert_eq!(lines[1], "  影子供应商: cheap（采样 10%）");
+        assert_eq!(
+            lines[2],
+            "  主请求  请求 200  成功率 95.0%  p50 1200ms  p95 2400ms  p99 3600ms"
+        );
+        assert_eq!(
+            lines[3],
+            "  影子    请求 20  成功率 90.0%  p50 900ms  p95 1800ms  p99 2700ms"
+        );
+        assert_eq!(lines[4], "  p50 差值: -300ms（影子 - 主请求）");
+    }
+
+    #[test]
+    fn format_lines_without_shadow_requests() {
+        let cmp = ShadowComparison {
+            primary: stats(3, 0.0, Some(100)),
+            shadow: LatencyPercentiles::default(),
+        };
+        let lines = format_lines("codex", "1h", "cheap", None, &cmp);
+        assert_eq!(lines[1], "  影子供应商: cheap（当前未启用）");
+        assert_eq!(lines[3], "  影子    无请求");
+        assert_eq!(lines.len(), 4);
+    }
+}
@@ -0,0 +1,359 @@
+//! 供应商延迟分位数统计（`csc stats`）
+//!
+//! 从请求日志计算时间窗口内各供应商的 p50/p95/p99（仅 2xx）与错误率；
+//! 代理运行中时附带 `GET /admin/status` 中该应用自启动以来的累计请求数。
+//!
+//! `csc stats models` 按供应商列出请求模型 → 实际模型的使用计数，用于发现意外的映射结果。
+//!
+//! `csc stats bandwidth` 按供应商列出窗口内的请求/响应字节数，按总流量倒序；
+//! `csc stats bandwidth-alert` 设置供应商当日流量告警阈值。
+
+use cc_switch_lib::proxy::bandwidth::format_bytes;
+use cc_switch_lib::proxy::AppStatusSummary;
+use cc_switch_lib::{AppError, BandwidthStat, Database, LatencyPercentiles, ModelMappingStat};
+use serde::Serialize;
+
+/// 单个供应商的统计行
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderStatsRow {
+    pub id: String,
+    pub name: String,
+    #[serde(flatten)]
+    pub stats: LatencyPercentiles,
+}
+
+/// 逐个供应商查询窗口内的分位数，按请求数倒序（无请求的排在最后）
+pub fn collect(
+    db: &Database,
+    app_type: &str,
+    window_secs: i64,
+) -> Result<Vec<ProviderStatsRow>, AppError> {
+    let mut rows = Vec::new();
+    for provider in db.get_all_providers(app_type)?.values() {
+        let stats =
+            db.get_latency_percentiles(std::slice::from_ref(&provider.id), app_type, window_secs)?;
+        rows.push(ProviderStatsRow {
+            id: provider.id.clone(),
+            name: provider.name.clone(),
+            stats,
+        });
+    }
+    rows.sort_by(|a, b| b.stats.count.cmp(&a.stats.count));
+    Ok(rows)
+}
+
+fn format_ms(ms: Option<u64>) -> String {
+    ms.map(|v| format!("{v}ms"))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+/// 可读输出（逐行）
+pub fn format_lines(
+    app_type: &str,
+    window: &str,
+    rows: &[ProviderStatsRow],
+    proxy: Option<&AppStatusSummary>,
+) -> Vec<String> {
+    let mut lines = vec![format!(
+        "\n=== {} 延迟统计（最近 {}） ===",
+        app_type, window
+    )];
+
+    if rows.is_empty() {
+        lines.push("  暂无供应商".to_string());
+    }
+    for row in rows {
+        let s = &row.stats;
+        if s.count == 0 {
+            lines.push(format!("  {} - {}  无请求", row.id, row.name));
+            continue;
+        }
+        lines.push(format!(
+            "  {} - {}  请求 {}  错误率 {:.1}%  p50 {}  p95 {}  p99 {}",
+            row.id,
+            row.name,
+            s.count,
+            s.error_rate * 100.0,
+            format_ms(s.p50_ms),
+            format_ms(s.p95_ms),
+            format_ms(s.p99_ms)
+        ));
+    }
+
+    let total: u64 = rows.iter().map(|r| r.stats.count).sum();
+    if total > 0 {
+        let errors: f64 = rows
+            .iter()
+            .map(|r| r.stats.error_rate * r.stats.count as f64)
+            .sum();
+        lines.push(format!(
+            "  窗口合计: 请求 {}  错误率 {:.1}%",
+            total,
+            errors / total as f64 * 100.0
+        ));
+    }
+
+    match proxy {
+        Some(app) => lines.push(format!(
+            "  代理本次运行: 请求 {} / 成功 {}（成功率 {:.1}%）",
+            app.total_requests, app.success_requests, app.success_rate
+        )),
+        None => lines.push("  代理未运行（仅显示请求日志统计）".to_string()),
+    }
+
+    lines
+}
+
+/// 模型映射可读输出：按供应商分组，映射过的组合显示 `请求 → 实际`
+pub fn format_model_lines(app_type: &str, window: &str, rows: &[ModelMappingStat]) -> Vec<String> {
+    let mut lines = vec![format!(
+        "\n=== {} 模型映射（最近 {}） ===",
+        app_type, window
+    )];
+
+    if rows.is_empty() {
+        lines.push("  暂无记录了模型映射的请求".to_string());
+        return lines;
+    }
+
+    let pair = |row: &ModelMappingStat| {
+        let requested = row.requested_model.as_deref().unwrap_or("-");
+        match row.effective_model.as_deref() {
+            Some(effective) if row.is_mapped() => format!("{requested} → {effective}"),
+            Some(_) => requested.to_string(),
+            None => format!("{requested} → ?"),
+        }
+    };
+    let width = rows
+        .iter()
+        .map(|r| pair(r).chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut current: Option<&str> = None;
+    for row in rows {
+        if current != Some(row.provider_id.as_str()) {
+            current = Some(row.provider_id.as_str());
+            let name = row.provider_name.as_deref().unwrap_or("(已删除)");
+            lines.push(format!("  {} - {}", row.provider_id, name));
+        }
+        let text = pair(row);
+        let pad = width - text.chars().count();
+        lines.push(format!(
+            "    {}{}  请求 {}（成功 {}）",
+            text,
+            " ".repeat(pad),
+            row.count,
+            row.success
+        ));
+    }
+
+    let mapped = rows.iter().filter(|r| r.is_mapped()).count();
+    lines.push(format!(
+        "  共 {} 组，其中 {} 组经过映射",
+        rows.len(),
+        mapped
+    ));
+    lines
+}
+
+/// 解析流量阈值（1024 进制）：`500MB`、`2GB`、`1.5GB`、`1048576`
+pub fn parse_size(s: &str) -> Result<u64, AppError> {
+    let s = s.trim();
+    let invalid = || AppError::InvalidInput(format!("无效的流量大小: {s}（示例: 500MB、2GB）"));
+    let split = s
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let value: f64 = num.parse().map_err(|_| invalid())?;
+    let factor: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1 << 10,
+        "M" | "MB" => 1 << 20,
+        "G" | "GB" => 1 << 30,
+        "T" | "TB" => 1 << 40,
+        _ => return Err(invalid()),
+    };
+    let bytes = (value * factor as f64).round();
+    if bytes < 1.0 || bytes >= u64::MAX as f64 {
+        return Err(invalid());
+    }
+    Ok(bytes as u64)
+}
+
+/// 流量可读输出：每个供应商一行，末尾附窗口合计
+pub fn format_bandwidth_lines(app_type: &str, window: &str, rows: &[BandwidthStat]) -> Vec<String> {
+    let mut lines = vec![format!("\n=== {} 流量（最近 {}） ===", app_type, window)];
+
+    if rows.is_empty() {
+        lines.push("  暂无请求记录".to_string());
+        return lines;
+    }
+
+    for row in rows {
+        let name = row.provider_name.as_deref().unwrap_or("(已删除)");
+        lines.push(format!(
+            "  {} - {}  请求 {}  上行 {}  下行 {}  合计 {}",
+            row.provider_id,
+            name,
+            row.requests,
+            format_bytes(row.request_bytes),
+            format_bytes(row.response_bytes),
+            format_bytes(row.total_bytes())
+        ));
+    }
+
+    let total: u64 = rows.iter().map(BandwidthStat::total_bytes).sum();
+    let requests: u64 = rows.iter().map(|r| r.requests).sum();
+    lines.push(format!(
+        "  窗口合计: 请求 {}  流量 {}",
+        requests,
+        format_bytes(total)
+    ));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(id: &str, count: u64, error_rate: f64, p50: Option<u64>) -> ProviderStatsRow {
+        ProviderStatsRow {
+            id: id.to_string(),
+            name: format!("{id}-name"),
+            stats: LatencyPercentiles {
+                count,
+                error_rate,
+                p50_ms: p50,
+                p95_ms: p50.map(|v| v * 3),
+                p99_ms: p50.map(|v| v * 5),
+            },
+        }
+    }
+
+    #[test]
+    fn test_format_lines_with_and_without_proxy() {
+        let rows = vec![
+            row("p1", 40, 0.25, Some(800)),
+            row("p2", 10, 1.0, None),
+            row("p3", 0, 0.0, None),
+        ];
+        let summary = AppStatusSummary {
+            app_type: "claude".to_string(),
+            total_requests: 120,
+            success_requests: 100,
+            success_rate: 83.3,
+            current_provider_id: None,
+            current_provider_name: None,
+            disabled: false,
+            rejected_disabled: 0,
+        };
+
+        let text = format_lines("claude", "24h", &rows, Some(&summary)).join("\n");
+        assert!(text.contains("最近 24h"));
+        assert!(
+            text.contains("p1 - p1-name  请求 40  错误率 25.0%  p50 800ms  p95 2400ms  p99 4000ms")
+        );
+        assert!(text.contains("p2 - p2-name  请求 10  错误率 100.0%  p50 -  p95 -  p99 -"));
+        assert!(text.contains("p3 - p3-name  无请求"));
+        // (10 + 10) / 50
+        assert!(text.contains("窗口合计: 请求 50  错误率 40.0%"));
+        assert!(text.contains("代理本次运行: 请求 120 / 成功 100（成功率 83.3%）"));
+
+        let text = format_lines("claude", "1h", &rows[2..], None).join("\n");
+        assert!(!text.contains("窗口合计"));
+        assert!(text.contains("代理未运行"));
+
+        let value = serde_json::to_value(&rows[0]).unwrap();
+        assert_eq!(value["p95Ms"], 2400);
+        assert_eq!(value["errorRate"], 0.25);
+    }
+
+    fn mapping(
+        provider: &str,
+        requested: Option<&str>,
+        effective: Option<&str>,
+        count: u64,
+    ) -> ModelMappingStat {
+        ModelMappingStat {
+            provider_id: provider.to_string(),
+            provider_name: Some(format!("{provider}-name")),
+            requested_model: requested.map(str::to_string),
+            effective_model: effective.map(str::to_string),
+            count,
+            success: count,
+            last_seen: 0,
+        }
+    }
+
+    #[test]
+    fn test_format_model_lines_groups_by_provider() {
+        let mut deleted = mapping("p2", Some("gpt-5"), None, 1);
+        deleted.provider_name = None;
+        let rows = vec![
+            mapping("p1", Some("claude-sonnet-4-5"), Some("glm-4.6"), 12),
+            mapping("p1", Some("claude-haiku-4-5"), Some("claude-haiku-4-5"), 3),
+            deleted,
+        ];
+
+        let lines = format_model_lines("claude", "24h", &rows);
+        let text = lines.join("\n");
+        assert!(text.contains("模型映射（最近 24h）"));
+        assert!(text.contains("  p1 - p1-name"));
+        assert!(text.contains("    claude-sonnet-4-5 → glm-4.6  请求 12（成功 12）"));
+        // 直通的组合只显示一个模型名，并与上一行对齐
+        assert!(text.contains("    claude-haiku-4-5             请求 3（成功 3）"));
+        assert!(text.contains("  p2 - (已删除)"));
+        assert!(text.contains("    gpt-5 → ?"));
+        assert!(text.contains("共 3 组，其中 1 组经过映射"));
+        // 供应商标题只出现一次
+        assert_eq!(lines.iter().filter(|l| *l == "  p1 - p1-name").count(), 1);
+
+        let text = format_model_lines("codex", "1h", &[]).join("\n");
+        assert!(text.contains("暂无记录了模型映射的请求"));
+    }
+
+    #[test]
+    fn test_format_bandwidth_lines() {
+        let rows = vec![
+            BandwidthStat {
+                provider_id: "p1".to_string(),
+                provider_name: Some("p1-name".to_string()),
+                requests: 3,
+                request_bytes: 2048,
+                response_bytes: 1024 * 1024,
+            },
+            BandwidthStat {
+                provider_id: "p2".to_string(),
+                provider_name: None,
+                requests: 1,
+                request_bytes: 100,
+                response_bytes: 400,
+            },
+        ];
+
+        let text = format_bandwidth_lines("codex", "24h", &rows).join("\n");
+        assert!(text.contains("流量（最近 24h）"));
+        assert!(text.contains("p1 - p1-name  请求 3  上行 2.0KB  下行 1.0MB  合计 1.0MB"));
+        assert!(text.contains("p2 - (已删除)  请求 1  上行 100B  下行 400B  合计 500B"));
+        assert!(text.contains("窗口合计: 请求 4  流量 1.0MB"));
+
+        let value = serde_json::to_value(&rows[0]).unwrap();
+        assert_eq!(value["requestBytes"], 2048);
+
+        let text = format_bandwidth_lines("claude", "1h", &[]).join("\n");
+        assert!(text.contains("暂无请求记录"));
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512").unwrap(), 512);
+        assert_eq!(parse_size("500MB").unwrap(), 500 * 1024 * 1024);
+        assert_eq!(parse_size("1.5gb").unwrap(), 1536 * 1024 * 1024);
+        assert_eq!(parse_size("2 G").unwrap(), 2 * 1024 * 1024 * 1024);
+        assert!(parse_size("0").is_err());
+        assert!(parse_size("10PB").is_err());
+        assert!(parse_size("MB").is_err());
+    }
+}
@@ -0,0 +1,346 @@
+//! 代理运行状态查询（`csc proxy status`）
+//!
+//! 优先请求运行中代理的 `GET /admin/status` 获取实时统计（运行时间、各应用请求数、成功率、
+//! 当前供应商、故障转移次数）；HTTP 不可达时回退到 PID 检查，区分三种状态：
+//! 运行中 / 进程存在但无响应 / 未运行。
+
+use cc_switch_lib::proxy::AdminStatus;
+use serde_json::{json, Value};
+
+/// 代理运行状态
+#[derive(Debug)]
+pub enum ProxyRunState {
+    Running(Box<AdminStatus>),
+    /// PID 文件指向的进程存在，但 HTTP 无响应
+    Unresponsive {
+        pid: i32,
+    },
+    /// 未运行（`stale_pid` 为已失效的 PID 文件内容）
+    Stopped {
+        stale_pid: Option<i32>,
+    },
+}
+
+/// 请求 `GET {base}/admin/status`
+pub async fn fetch_admin_status(
+    client: &reqwest::Client,
+    base: &str,
+) -> Result<AdminStatus, String> {
+    let resp = client
+        .get(format!("{}/admin/status", base.trim_end_matches('/')))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !resp.status().is_success() {
+        return Err(format!("HTTP {}", resp.status().as_u16()));
+    }
+    resp.json::<AdminStatus>().await.map_err(|e| e.to_string())
+}
+
+/// 判定运行状态
+///
+/// - `base`：探测到的代理地址（None 表示 HTTP 不可达）
+/// - `pid`：PID 文件内容与进程是否存活
+pub async fn detect(
+    client: &reqwest::Client,
+    base: Option<&str>,
+    pid: Option<(i32, bool)>,
+) -> ProxyRunState {
+    if let Some(base) = base {
+        match fetch_admin_status(client, base).await {
+            Ok(status) => return ProxyRunState::Running(Box::new(status)),
+            Err(e) => log::debug!("查询 /admin/status 失败: {e}"),
+        }
+    }
+
+    match pid {
+        Some((pid, true)) => ProxyRunState::Unresponsive { pid },
+        Some((pid, false)) => ProxyRunState::Stopped {
+            stale_pid: Some(pid),
+        },
+        None => ProxyRunState::Stopped { stale_pid: None },
+    }
+}
+
+impl ProxyRunState {
+    pub fn state_name(&self) -> &'static str {
+        match self {
+            ProxyRunState::Running(_) => "running",
+            ProxyRunState::Unresponsive { .. } => "stale",
+            ProxyRunState::Stopped { .. } => "stopped",
+        }
+    }
+
+    /// `--json` 输出：运行中时为完整的管理状态，并附加 `state` 字段
+    pub fn to_json(&self) -> Value {
+        match self {
+            ProxyRunState::Running(status) => {
+                let mut value = serde_json::to_value(status.as_ref()).unwrap_or_else(|_| json!({}));
+                if let Some(obj) = value.as_object_mut() {
+                    obj.insert("state".to_string(), json!(self.state_name()));
+                }
+                value
+            }
+            ProxyRunState::Unresponsive { pid } => json!({
+                "state": self.state_name(),
+                "pid": pid,
+            }),
+            ProxyRunState::Stopped { stale_pid } => json!({
+                "state": self.state_name(),
+                "stale_pid": stale_pid,
+            }),
+        }
+    }
+
+    /// 可读摘要（逐行）
+    pub fn format_lines(&self) -> Vec<String> {
+        match self {
+            ProxyRunState::Running(admin) => format_running(admin),
+            ProxyRunState::Unresponsive { pid } => vec![
+                "代理服务器状态: 无响应（进程存在但未响应 HTTP 请求）".to_string(),
+                format!("  PID: {}", pid),
+                "  可尝试: csc proxy restart".to_string(),
+            ],
+            ProxyRunState::Stopped {
+                stale_pid: Some(pid),
+            } => {
+                vec![format!("代理服务器状态: 未运行（PID {} 不存在）", pid)]
+            }
+            ProxyRunState::Stopped { stale_pid: None } => {
+                vec!["代理服务器状态: 未运行".to_string()]
+            }
+        }
+    }
+}
+
+fn format_running(admin: &AdminStatus) -> Vec<String> {
+    let s = &admin.status;
+    let mut lines = vec![
+        if s.read_only {
+            "代理服务器状态: 运行中（只读模式，不写数据库）".to_string()
+        } else {
+            "代理服务器状态: 运行中".to_string()
+        },
+        format!("  PID: {}  版本: {}", admin.pid, admin.version),
+        format!("  地址: {}:{}", s.address, s.port),
+        format!("  运行时间: {}", format_uptime(s.uptime_seconds)),
+        format!(
+            "  请求: 总计 {} / 成功 {} / 失败 {}（成功率 {:.1}%）",
+            s.total_requests, s.success_requests, s.failed_requests, s.success_rate
+        ),
+        format!("  故障转移: {} 次", s.failover_count),
+    ];
+    if let Some(at) = &s.last_request_at {
+        lines.push(format!("  最近请求: {}", at));
+    }
+    if let Some(err) = &s.last_error {
+        lines.push(format!("  最近错误: {}", err));
+    }
+    for pause in &s.failover_paused {
+        lines.push(format!(
+            "  故障转移已暂停: {}（剩余 {}，到期自动恢复）",
+            pause.app_type,
+            format_uptime(pause.remaining_secs)
+        ));
+    }
+    if let Some(update) = s.update_check.as_ref().filter(|u| u.update_available) {
+        lines.push(format!(
+            "  发现新版本: {}（当前 {}）{}",
+            update.latest_version,
+            update.current_version,
+            update.release_url.as_deref().unwrap_or_default()
+        ));
+    }
+
+    if !admin.apps.is_empty() {
+        lines.push("  各应用:".to_string());
+        for app in &admin.apps {
+            let current = match (&app.current_provider_name, &app.current_provider_id) {
+                (Some(name), Some(id)) => format!("{} ({})", name, id),
+                (None, Some(id)) => id.clone(),
+                _ => "-".to_string(),
+            };
+            let mut line = format!(
+                "    {:<7} 请求 {:>6}  成功率 {:>5.1}%  当前: {}",
+                app.app_type, app.total_requests, app.success_rate, current
+            );
+            if app.disabled {
+                line.push_str(&format!("  [已关闭，已拒绝 {}]", app.rejected_disabled));
+            } else if app.rejected_disabled > 0 {
+                line.push_str(&format!("  [已拒绝 {}]", app.rejected_disabled));
+            }
+            lines.push(line);
+        }
+    }
+
+    lines
+}
+
+fn format_uptime(secs: u64) -> String {
+    let d = secs / 86_400;
+    let h = (secs % 86_400) / 3600;
+    let m = (secs % 3600) / 60;
+    let s = secs % 60;
+    if d > 0 {
+        format!("{d}d {h}h{m:02}m")
+    } else if h > 0 {
+        format!("{h}h{m:02}m{s:02}s")
+    } else {
+        format!("{m}m{s:02}s")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Json, Router};
+    use cc_switch_lib::proxy::update_check::UpdateCheckResult;
+    use cc_switch_lib::proxy::{AppStatusSummary, FailoverPause, ProxyStatus};
+
+    fn sample_admin_status() -> AdminStatus {
+        AdminStatus {
+            status: ProxyStatus {
+                running: true,
+                address: "127.0.0.1".to_string(),
+                port: 15721,
+                total_requests: 10,
+                success_requests: 9,
+                failed_requests: 1,
+                success_rate: 90.0,
+                uptime_seconds: 3725,
+                failover_count: 2,
+                last_error: Some("HTTP 502".to_string()),
+                ..ProxyStatus::default()
+            },
+            pid: 4242,
+            version: "3.9.0".to_string(),
+            apps: vec![AppStatusSummary {
+                app_type: "claude".to_string(),
+                total_requests: 10,
+                success_requests: 9,
+                success_rate: 90.0,
+                current_provider_id: Some("p1".to_string()),
+                current_provider_name: Some("anyrouter".to_string()),
+                disabled: false,
+                rejected_disabled: 0,
+            }],
+            ..AdminStatus::default()
+        }
+    }
+
+    /// 启动返回固定 `/admin/status` 的本地桩服务，返回 base URL
+    async fn spawn_stub() -> String {
+        let app = Router::new().route(
+            "/admin/status",
+            get(|| async { Json(sample_admin_status()) }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+        format!("http://{addr}")
+    }
+
+    /// 绑定后立即释放的端口，用于模拟 HTTP 不可达
+    fn closed_base() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        format!("http://{addr}")
+    }
+
+    #[tokio::test]
+    async fn test_running_state_from_admin_endpoint() {
+        let base = spawn_stub().await;
+        let client = reqwest::Client::new();
+
+        let state = detect(&client, Some(&base), Some((4242, true))).await;
+        assert_eq!(state.state_name(), "running");
+
+        let text = state.format_lines().join("\n");
+        assert!(text.contains("代理服务器状态: 运行中"));
+        assert!(text.contains("PID: 4242"));
+        assert!(text.contains("运行时间: 1h02m05s"));
+        assert!(text.contains("成功率 90.0%"));
+        assert!(text.contains("故障转移: 2 次"));
+        assert!(text.contains("当前: anyrouter (p1)"));
+
+        let value = state.to_json();
+        assert_eq!(value["state"], "running");
+        assert_eq!(value["pid"], 4242);
+        assert_eq!(value["total_requests"], 10);
+        assert_eq!(value["apps"][0]["app_type"], "claude");
+    }
+
+    #[test]
+    fn test_running_read_only_mode_is_shown() {
+        let mut admin = sample_admin_status();
+        assert!(!format_running(&admin)[0].contains("只读模式"));
+
+        admin.status.read_only = true;
+        assert_eq!(
+            format_running(&admin)[0],
+            "代理服务器状态: 运行中（只读模式，不写数据库）"
+        );
+    }
+
+    #[test]
+    fn test_failover_pause_is_shown() {
+        let mut admin = sample_admin_status();
+        admin.status.failover_paused = vec![FailoverPause {
+            app_type: "codex".to_string(),
+            until: 1_700_000_000,
+            remaining_secs: 1499,
+        }];
+        let text = format_running(&admin).join("\n");
+        assert!(text.contains("故障转移已暂停: codex（剩余 24m59s，到期自动恢复）"));
+    }
+
+    #[test]
+    fn test_available_update_is_shown() {
+        let mut admin = sample_admin_status();
+        let mut update = UpdateCheckResult {
+            current_version: "3.9.0".to_string(),
+            latest_version: "v3.10.0".to_string(),
+            update_available: false,
+            release_url: Some("https://example.com/v3.10.0".to_string()),
+            checked_at: 1_700_000_000,
+        };
+        admin.status.update_check = Some(update.clone());
+        assert!(!format_running(&admin).join("\n").contains("发现新版本"));
+
+        update.update_available = true;
+        admin.status.update_check = Some(update);
+        let text = format_running(&admin).join("\n");
+        assert!(text.contains("发现新版本: v3.10.0（当前 3.9.0）https://example.com/v3.10.0"));
+    }
+
+    #[tokio::test]
+    async fn test_stale_when_process_alive_but_unreachable() {
+        let client = reqwest::Client::new();
+        let base = closed_base();
+
+        let state = detect(&client, Some(&base), Some((4242, true))).await;
+        assert_eq!(state.state_name(), "stale");
+        assert!(state.format_lines()[0].contains("进程存在但未响应"));
+        assert_eq!(state.to_json()["pid"], 4242);
+    }
+
+    #[tokio::test]
+    async fn test_stopped_states() {
+        let client = reqwest::Client::new();
+
+        let state = detect(&client, None, None).await;
+        assert_eq!(state.state_name(), "stopped");
+        assert_eq!(
+            state.format_lines(),
+            vec!["代理服务器状态: 未运行".to_string()]
+        );
+
+        let state = detect(&client, None, Some((4242, false))).await;
+        assert_eq!(state.state_name(), "stopped");
+        assert_eq!(state.to_json()["stale_pid"], 4242);
+        assert!(state.format_lines()[0].contains("PID 4242 不存在"));
+    }
+}
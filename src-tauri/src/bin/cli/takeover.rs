@@ -0,0 +1,244 @@
+//! Live 接管（`csc takeover enable|disable|status|list|diff|restore`）
+//!
+//! 开启/关闭接管与桌面端共用同一套备份与改写逻辑；每次接管写入 live 配置前都会保存一份快照，
+//! 这里还负责列出快照、显示快照与当前 live 文件的 unified diff，以及回滚到指定快照。
+
+use cc_switch_lib::proxy::{AppTakeoverDetail, LiveSnapshot};
+use std::path::{Path, PathBuf};
+
+/// 改写前的 live 文件内容（文件不存在为 None），用于报告实际改动了哪些文件
+pub fn read_files(paths: &[PathBuf]) -> Vec<(PathBuf, Option<Vec<u8>>)> {
+    paths
+        .iter()
+        .map(|path| (path.clone(), std::fs::read(path).ok()))
+        .collect()
+}
+
+/// 与改写前相比内容有变化（含新建/删除）的文件
+pub fn changed_files(before: &[(PathBuf, Option<Vec<u8>>)]) -> Vec<&Path> {
+    before
+        .iter()
+        .filter(|(path, content)| std::fs::read(path).ok() != *content)
+        .map(|(path, _)| path.as_path())
+        .collect()
+}
+
+/// 已改动的文件列表
+pub fn format_changed(changed: &[&Path]) -> Vec<String> {
+    if changed.is_empty() {
+        return vec!["  未改动任何文件".to_string()];
+    }
+    let mut lines = vec!["  已改动的文件:".to_string()];
+    lines.extend(changed.iter().map(|path| format!("    {}", path.display())));
+    lines
+}
+
+/// 接管状态；第二项为是否存在与开关不一致的 live 配置
+pub fn format_status(details: &[AppTakeoverDetail], proxy_running: bool) -> (Vec<String>, bool) {
+    let mut lines = vec![
+        "\n=== Live 接管状态 ===".to_string(),
+        format!(
+            "  代理: {}",
+            if proxy_running {
+                "运行中"
+            } else {
+                "未运行"
+            }
+        ),
+    ];
+    let mut drifted = false;
+
+    for detail in details {
+        let app = &detail.app_type;
+        let (mark, text) = match (detail.enabled, detail.points_to_proxy) {
+            (true, Some(true)) if proxy_running => ("✓", format!("已接管 → {}", detail.proxy_url)),
+            (true, Some(true)) => (
+                "⚠",
+                format!("已接管 → {}（代理未运行，请求会失败）", detail.proxy_url),
+            ),
+            (true, Some(false)) => {
+                drifted = true;
+                (
+                    "⚠",
+                    "已接管，但 live 配置已不再指向代理（被其它工具改写）".to_string(),
+                )
+            }
+            (true, None) => {
+                drifted = true;
+                ("⚠", "已接管，但 live 配置文件不存在".to_string())
+            }
+            (false, Some(true)) => {
+                drifted = true;
+                ("⚠", "未接管，但 live 配置仍指向代理".to_string())
+            }
+            (false, _) => ("○", "未接管".to_string()),
+        };
+        lines.push(format!("  {mark} {app:<7} {text}"));
+        if detail.enabled && !detail.has_backup {
+            lines.push("      缺少接管前的备份，关闭接管时无法自动恢复".to_string());
+        }
+        for path in &detail.files {
+            lines.push(format!("      {}", path.display()));
+        }
+    }
+
+    if drifted {
+        lines.push(String::new());
+        lines.push(
+            "提示: csc takeover diff <app> 查看改动；csc takeover disable <app> 后重新 enable 即可恢复接管"
+                .to_string(),
+        );
+    }
+    (lines, drifted)
+}
+
+fn snapshot_time(created_at: &str) -> String {
+    chrono::DateTime::parse_from_rfc3339(created_at)
+        .map(|t| {
+            t.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        })
+        .unwrap_or_else(|_| created_at.to_string())
+}
+
+/// 快照列表（最新在前）
+pub fn format_list(app_type: &str, snapshots: &[LiveSnapshot]) -> Vec<String> {
+    let mut lines = vec![format!("\n=== {app_type} Live 快照 ===")];
+    if snapshots.is_empty() {
+        lines.push("  暂无快照（接管写入 live 配置前自动生成）".to_string());
+        return lines;
+    }
+
+    for (idx, snapshot) in snapshots.iter().enumerate() {
+        lines.push(format!(
+            "  #{:<6} {}{}",
+            snapshot.id,
+            snapshot_time(&snapshot.created_at),
+            if idx == 0 { "  (最新)" } else { "" }
+        ));
+    }
+    lines
+}
+
+/// 快照与当前 live 配置的 diff
+pub fn format_diff(snapshot: &LiveSnapshot, diff: &str) -> Vec<String> {
+    let title = format!(
+        "快照 #{}（{}）",
+        snapshot.id,
+        snapshot_time(&snapshot.created_at)
+    );
+    if diff.is_empty() {
+        return vec![format!("✓ 当前 live 配置与{title}一致")];
+    }
+
+    let mut lines = vec![format!("{title} → 当前 live 配置:")];
+    lines.extend(diff.lines().map(str::to_string));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detail(app_type: &str, enabled: bool, points_to_proxy: Option<bool>) -> AppTakeoverDetail {
+        AppTakeoverDetail {
+            app_type: app_type.to_string(),
+            enabled,
+            has_backup: enabled,
+            proxy_url: "http://127.0.0.1:15721".to_string(),
+            points_to_proxy,
+            files: vec![PathBuf::from(format!("/home/u/.{app_type}/settings.json"))],
+        }
+    }
+
+    fn snapshot(id: i64) -> LiveSnapshot {
+        LiveSnapshot {
+            id,
+            app_type: "claude".to_string(),
+            config: "{}".to_string(),
+            created_at: "not-a-timestamp".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_format_list_marks_latest_snapshot() {
+        let text = format_list("claude", &[snapshot(7), snapshot(3)]).join("\n");
+        assert!(text.contains("=== claude Live 快照 ==="));
+        assert!(text.contains("  #7      not-a-timestamp  (最新)"));
+        assert!(text.ends_with("  #3      not-a-timestamp"));
+        assert!(format_list("codex", &[]).join("\n").contains("暂无快照"));
+    }
+
+    #[test]
+    fn test_format_diff_prints_unified_diff_or_match() {
+        let diff =
+            "--- settings.json (快照 #7)\n+++ settings.json (当前)\n@@ -1,1 +1,1 @@\n-a\n+b\n";
+        let lines = format_diff(&snapshot(7), diff);
+        assert_eq!(lines[0], "快照 #7（not-a-timestamp） → 当前 live 配置:");
+        assert_eq!(lines.last().map(String::as_str), Some("+b"));
+
+        assert_eq!(
+            format_diff(&snapshot(7), ""),
+            vec!["✓ 当前 live 配置与快照 #7（not-a-timestamp）一致".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_changed_files_reports_modified_created_and_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let kept = dir.path().join("auth.json");
+        let modified = dir.path().join("config.toml");
+        let created = dir.path().join(".env");
+        let removed = dir.path().join("settings.json");
+        std::fs::write(&kept, "{}").unwrap();
+        std::fs::write(&modified, "a = 1").unwrap();
+        std::fs::write(&removed, "{}").unwrap();
+
+        let before = read_files(&[kept, modified.clone(), created.clone(), removed.clone()]);
+        std::fs::write(&modified, "a = 2").unwrap();
+        std::fs::write(&created, "KEY=1").unwrap();
+        std::fs::remove_file(&removed).unwrap();
+
+        let changed = changed_files(&before);
+        assert_eq!(
+            changed,
+            vec![modified.as_path(), created.as_path(), removed.as_path()]
+        );
+        assert_eq!(format_changed(&[])[0], "  未改动任何文件");
+        assert_eq!(
+            format_changed(&changed)[1],
+            format!("    {}", modified.display())
+        );
+    }
+
+    #[test]
+    fn test_format_status_flags_drift() {
+        let (lines, drifted) = format_status(
+            &[
+                detail("claude", true, Some(true)),
+                detail("codex", false, None),
+            ],
+            true,
+        );
+        assert!(!drifted);
+        let text = lines.join("\n");
+        assert!(text.contains("  ✓ claude  已接管 → http://127.0.0.1:15721"));
+        assert!(text.contains("  ○ codex   未接管"));
+        assert!(!text.contains("提示"));
+
+        let (lines, drifted) = format_status(&[detail("claude", true, Some(true))], false);
+        assert!(!drifted);
+        assert!(lines.join("\n").contains("代理未运行，请求会失败"));
+
+        for stale in [
+            detail("gemini", true, Some(false)),
+            detail("gemini", true, None),
+            detail("gemini", false, Some(true)),
+        ] {
+            let (lines, drifted) = format_status(&[stale], true);
+            assert!(drifted);
+            assert!(lines.last().unwrap().starts_with("提示"));
+        }
+    }
+}
@@ -0,0 +1,134 @@
+//! 版本信息与新版本检测（`csc version`）
+//!
+//! 显示 CLI 与运行中代理（`GET /version`）的版本、git hash 与 schema 版本，
+//! 以及最近一次新版本检测结果；`--check` 立即查询 GitHub Releases，`--auto-check` 开关代理的每日检测。
+
+use cc_switch_lib::proxy::update_check::{UpdateCheckResult, VersionInfo};
+
+fn describe(info: &VersionInfo) -> String {
+    let hash = info.git_hash.as_deref().unwrap_or("unknown");
+    format!(
+        "{}（git {}，schema v{}）",
+        info.version, hash, info.schema_version
+    )
+}
+
+/// 可读输出（逐行）
+pub fn format_lines(
+    cli: &VersionInfo,
+    proxy: Option<&VersionInfo>,
+    update: Option<&UpdateCheckResult>,
+    auto_check: bool,
+) -> Vec<String> {
+    let mut lines = vec![format!("CLI:  {}", describe(cli))];
+    match proxy {
+        Some(proxy) => {
+            lines.push(format!("代理: {}", describe(proxy)));
+            if proxy.version != cli.version || proxy.git_hash != cli.git_hash {
+                lines.push("  ⚠ 运行中的代理与 CLI 版本不一致，重启代理后生效".to_string());
+            }
+        }
+        None => lines.push("代理: 未运行".to_string()),
+    }
+
+    match update {
+        Some(update) => {
+            let checked_at = chrono::DateTime::from_timestamp(update.checked_at, 0)
+                .map(|t| {
+                    t.with_timezone(&chrono::Local)
+                        .format("%Y-%m-%d %H:%M")
+                        .to_string()
+                })
+                .unwrap_or_else(|| "-".to_string());
+            if update.update_available {
+                lines.push(format!(
+                    "发现新版本: {}（检测于 {}）",
+                    update.latest_version, checked_at
+                ));
+                if let Some(url) = update.release_url.as_deref() {
+                    lines.push(format!("  下载: {url}"));
+                }
+            } else {
+                lines.push(format!(
+                    "已是最新版本（最新发布 {}，检测于 {}）",
+                    update.latest_version, checked_at
+                ));
+            }
+        }
+        None => lines.push("尚未检测新版本（csc version --check）".to_string()),
+    }
+    lines.push(format!(
+        "每日检测: {}",
+        if auto_check {
+            "已开启"
+        } else {
+            "已关闭（csc version --auto-check on）"
+        }
+    ));
+    lines
+}
+
+/// 解析 `--auto-check` 的取值
+pub fn parse_switch(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "on" | "true" => Some(true),
+        "off" | "false" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(version: &str, hash: Option<&str>) -> VersionInfo {
+        VersionInfo {
+            version: version.to_string(),
+            git_hash: hash.map(str::to_string),
+            schema_version: 25,
+        }
+    }
+
+    #[test]
+    fn test_format_lines() {
+        let cli = info("3.9.0-2", Some("abc123def456"));
+        let update = UpdateCheckResult {
+            current_version: "3.9.0-2".to_string(),
+            latest_version: "v3.10.0".to_string(),
+            update_available: true,
+            release_url: Some("https://example.com/v3.10.0".to_string()),
+            checked_at: 1_700_000_000,
+        };
+
+        let text = format_lines(&cli, Some(&cli), Some(&update), true).join("\n");
+        assert!(text.contains("CLI:  3.9.0-2（git abc123def456，schema v25）"));
+        assert!(text.contains("代理: 3.9.0-2"));
+        assert!(!text.contains("不一致"));
+        assert!(text.contains("发现新版本: v3.10.0"));
+        assert!(text.contains("  下载: https://example.com/v3.10.0"));
+        assert!(text.contains("每日检测: 已开启"));
+
+        let old_proxy = info("3.8.0", None);
+        let text = format_lines(&cli, Some(&old_proxy), None, false).join("\n");
+        assert!(text.contains("代理: 3.8.0（git unknown，schema v25）"));
+        assert!(text.contains("不一致"));
+        assert!(text.contains("尚未检测新版本"));
+        assert!(text.contains("每日检测: 已关闭"));
+
+        let up_to_date = UpdateCheckResult {
+            update_available: false,
+            latest_version: "v3.9.0-2".to_string(),
+            ..update
+        };
+        let text = format_lines(&cli, None, Some(&up_to_date), false).join("\n");
+        assert!(text.contains("代理: 未运行"));
+        assert!(text.contains("已是最新版本（最新发布 v3.9.0-2"));
+    }
+
+    #[test]
+    fn test_parse_switch() {
+        assert_eq!(parse_switch("ON"), Some(true));
+        assert_eq!(parse_switch("off"), Some(false));
+        assert_eq!(parse_switch("maybe"), None);
+    }
+}
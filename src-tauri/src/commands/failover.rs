@@ -2,8 +2,10 @@
 //!
 //! 管理代理模式下的故障转移队列（基于 providers 表的 in_failover_queue 字段）
 
-use crate::database::FailoverQueueItem;
+use crate::database::{FailoverQueueItem, FailoverQueueTier, SupplierUrlPriority};
 use crate::provider::Provider;
+use crate::proxy::provider_router::MAX_FAILOVER_PAUSE_SECS;
+use crate::proxy::FailoverPause;
 use crate::store::AppState;
 
 /// 获取故障转移队列
@@ -56,6 +58,73 @@ pub async fn remove_from_failover_queue(
         .map_err(|e| e.to_string())
 }
 
+/// 按优先级层级分组获取故障转移队列
+#[tauri::command]
+pub async fn get_failover_queue_grouped(
+    state: tauri::State<'_, AppState>,
+    app_type: String,
+) -> Result<Vec<FailoverQueueTier>, String> {
+    state
+        .db
+        .get_failover_queue_grouped(&app_type)
+        .map_err(|e| e.to_string())
+}
+
+/// 设置供应商是否在故障转移队列中（幂等），返回成员关系是否发生变化
+#[tauri::command]
+pub async fn set_failover_queue_membership(
+    state: tauri::State<'_, AppState>,
+    app_type: String,
+    provider_id: String,
+    in_queue: bool,
+) -> Result<bool, String> {
+    let changed = state
+        .db
+        .set_queue_membership(&app_type, &provider_id, in_queue)
+        .map_err(|e| e.to_string())?;
+    if changed {
+        invalidate_provider_supplier(&state, &app_type, &provider_id).await;
+    }
+    Ok(changed)
+}
+
+/// 把供应商移到指定优先级层级，返回是否发生变化
+#[tauri::command]
+pub async fn move_provider_priority(
+    state: tauri::State<'_, AppState>,
+    app_type: String,
+    provider_id: String,
+    priority: usize,
+) -> Result<bool, String> {
+    let change = state
+        .db
+        .move_provider_priority(&app_type, &provider_id, priority)
+        .map_err(|e| e.to_string())?;
+    let Some(change) = change else {
+        return Ok(false);
+    };
+    log::info!(
+        "[Failover] {app_type} 供应商 {} 层级 {:?} -> {}",
+        change.name,
+        change.from,
+        change.to
+    );
+    invalidate_provider_supplier(&state, &app_type, &provider_id).await;
+    Ok(true)
+}
+
+/// 清除供应商所属 supplier 的选路缓存，使新的队列配置在下一次请求时生效
+async fn invalidate_provider_supplier(state: &AppState, app_type: &str, provider_id: &str) {
+    let supplier = match state.db.get_provider_by_id(provider_id, app_type) {
+        Ok(Some(provider)) => provider.supplier(),
+        _ => return,
+    };
+    state
+        .proxy_service
+        .invalidate_suppliers(app_type, &[supplier])
+        .await;
+}
+
 /// 获取指定应用的自动故障转移开关状态（从 proxy_config 表读取）
 #[tauri::command]
 pub async fn get_auto_failover_enabled(
@@ -100,3 +169,61 @@ pub async fn set_auto_failover_enabled(
         .await
         .map_err(|e| e.to_string())
 }
+
+/// 临时暂停故障转移 `secs` 秒：期间只使用当前供应商，到期自动恢复（需代理运行中）
+#[tauri::command]
+pub async fn pause_failover(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    app_type: String,
+    secs: u64,
+) -> Result<FailoverPause, String> {
+    if secs == 0 {
+        return Err("暂停时长必须大于 0".to_string());
+    }
+    if secs > MAX_FAILOVER_PAUSE_SECS {
+        return Err(format!(
+            "暂停时长不能超过 {MAX_FAILOVER_PAUSE_SECS} 秒（7 天）"
+        ));
+    }
+    let pause = state.proxy_service.pause_failover(&app_type, secs).await?;
+    // 到期后的托盘刷新由 tray::spawn_failover_pause_watcher 负责
+    crate::tray::refresh_tray_menu(&app);
+    Ok(pause)
+}
+
+/// 提前恢复故障转移；返回此前是否处于暂停中
+#[tauri::command]
+pub async fn resume_failover(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    app_type: String,
+) -> Result<bool, String> {
+    let was_paused = state.proxy_service.resume_failover(&app_type).await;
+    crate::tray::refresh_tray_menu(&app);
+    Ok(was_paused)
+}
+
+/// 获取所有 supplier 的首选 URL 列表
+#[tauri::command]
+pub async fn get_supplier_url_priorities(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<SupplierUrlPriority>, String> {
+    state
+        .db
+        .list_supplier_url_priorities()
+        .map_err(|e| e.to_string())
+}
+
+/// 设置 supplier 的首选 URL 列表（空列表表示清除）
+#[tauri::command]
+pub async fn set_supplier_url_priority(
+    state: tauri::State<'_, AppState>,
+    supplier: String,
+    urls: Vec<String>,
+) -> Result<(), String> {
+    state
+        .db
+        .set_supplier_url_priority(&supplier, &urls)
+        .map_err(|e| e.to_string())
+}
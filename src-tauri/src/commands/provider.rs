@@ -1,21 +1,71 @@
 use indexmap::IndexMap;
+use serde::Serialize;
 use tauri::State;
 
 use crate::app_config::AppType;
+use crate::database::ProviderSuccessRate;
 use crate::error::AppError;
 use crate::provider::Provider;
+use crate::proxy::CircuitBreakerStats;
 use crate::services::{EndpointLatency, ProviderService, ProviderSortUpdate, SpeedtestService};
 use crate::store::AppState;
 use std::str::FromStr;
 
-/// 获取所有供应商
+/// 供应商列表成功率统计的默认时间窗口
+const DEFAULT_SUCCESS_RATE_WINDOW_SECS: i64 = 24 * 3600;
+
+/// 供应商列表项：供应商本身 + 代理运行时的熔断器状态（前端据此渲染徽标）
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderListItem {
+    #[serde(flatten)]
+    pub provider: Provider,
+    #[serde(rename = "circuitBreaker", skip_serializing_if = "Option::is_none")]
+    pub circuit_breaker: Option<CircuitBreakerStats>,
+    /// 窗口内的请求数与成功率；无流量时省略（前端显示 "-"）
+    #[serde(rename = "successStats", skip_serializing_if = "Option::is_none")]
+    pub success_stats: Option<ProviderSuccessRate>,
+}
+
+/// 获取所有供应商（代理运行中时附带各供应商的熔断器状态）
+///
+/// `window_secs` 为成功率统计窗口，默认最近 24 小时
 #[tauri::command]
-pub fn get_providers(
+pub async fn get_providers(
     state: State<'_, AppState>,
     app: String,
-) -> Result<IndexMap<String, Provider>, String> {
+    window_secs: Option<i64>,
+) -> Result<IndexMap<String, ProviderListItem>, String> {
     let app_type = AppType::from_str(&app).map_err(|e| e.to_string())?;
-    ProviderService::list(state.inner(), app_type).map_err(|e| e.to_string())
+    let mut breakers: HashMap<String, CircuitBreakerStats> = state
+        .proxy_service
+        .get_all_circuit_breaker_stats(app_type.as_str())
+        .await
+        .into_iter()
+        .map(|s| (s.provider_id, s.stats))
+        .collect();
+    let mut success_rates = state
+        .db
+        .get_provider_success_rates(
+            app_type.as_str(),
+            window_secs.unwrap_or(DEFAULT_SUCCESS_RATE_WINDOW_SECS),
+        )
+        .map_err(|e| e.to_string())?;
+    let providers = ProviderService::list(state.inner(), app_type).map_err(|e| e.to_string())?;
+    Ok(providers
+        .into_iter()
+        .map(|(id, provider)| {
+            let circuit_breaker = breakers.remove(&id);
+            let success_stats = success_rates.remove(&id);
+            (
+                id,
+                ProviderListItem {
+                    provider,
+                    circuit_breaker,
+                    success_stats,
+                },
+            )
+        })
+        .collect())
 }
 
 /// 获取当前供应商ID
@@ -33,7 +83,11 @@ pub fn add_provider(
     provider: Provider,
 ) -> Result<bool, String> {
     let app_type = AppType::from_str(&app).map_err(|e| e.to_string())?;
-    ProviderService::add(state.inner(), app_type, provider).map_err(|e| e.to_string())
+    let provider_id = provider.id.clone();
+    let saved = ProviderService::add(state.inner(), app_type.clone(), provider)
+        .map_err(|e| e.to_string())?;
+    spawn_model_warmup(&state, app_type, provider_id);
+    Ok(saved)
 }
 
 /// 更新供应商
@@ -44,7 +98,21 @@ pub fn update_provider(
     provider: Provider,
 ) -> Result<bool, String> {
     let app_type = AppType::from_str(&app).map_err(|e| e.to_string())?;
-    ProviderService::update(state.inner(), app_type, provider).map_err(|e| e.to_string())
+    let provider_id = provider.id.clone();
+    let saved = ProviderService::update(state.inner(), app_type.clone(), provider)
+        .map_err(|e| e.to_string())?;
+    spawn_model_warmup(&state, app_type, provider_id);
+    Ok(saved)
+}
+
+/// 保存成功后在后台预热模型列表（尽力而为，失败记录为事件，不影响保存结果）
+fn spawn_model_warmup(state: &State<'_, AppState>, app_type: AppType, provider_id: String) {
+    let proxy_service = state.proxy_service.clone();
+    tauri::async_runtime::spawn(async move {
+        proxy_service
+            .warm_up_models(app_type.as_str(), &provider_id)
+            .await;
+    });
 }
 
 /// 删除供应商
@@ -74,16 +142,49 @@ pub fn switch_provider_test_hook(
     switch_provider_internal(state, app_type, id)
 }
 
+/// `verify` 为 true 时先校验一次（默认请求模型列表，`full` 为 true 时发送问答请求），
+/// 失败则取消切换（`force` 为 true 时仅记录警告）
 #[tauri::command]
-pub fn switch_provider(
+pub async fn switch_provider(
     state: State<'_, AppState>,
     app: String,
     id: String,
+    verify: Option<bool>,
+    full: Option<bool>,
+    force: Option<bool>,
 ) -> Result<bool, String> {
     let app_type = AppType::from_str(&app).map_err(|e| e.to_string())?;
-    switch_provider_internal(&state, app_type, &id)
-        .map(|_| true)
-        .map_err(|e| e.to_string())
+
+    if verify.unwrap_or(false) {
+        let provider = state
+            .db
+            .get_provider_by_id(&id, app_type.as_str())
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("供应商 {id} 不存在"))?;
+        let router = crate::proxy::ProviderRouter::new(state.db.clone());
+        let verification = router
+            .verify_provider(&provider, app_type.as_str(), full.unwrap_or(false))
+            .await;
+        if let Some(warning) = verification
+            .check(force.unwrap_or(false))
+            .map_err(|e| e.to_string())?
+        {
+            log::warn!("[{}] {}", app_type.as_str(), warning);
+        }
+    }
+
+    // 切换流程内部会同步等待代理状态，放到阻塞线程执行
+    let app_state = AppState {
+        db: state.db.clone(),
+        proxy_service: state.proxy_service.clone(),
+    };
+    tauri::async_runtime::spawn_blocking(move || {
+        switch_provider_internal(&app_state, app_type, &id)
+    })
+    .await
+    .map_err(|e| format!("切换供应商失败: {e}"))?
+    .map(|_| true)
+    .map_err(|e| e.to_string())
 }
 
 fn import_default_config_internal(state: &AppState, app_type: AppType) -> Result<bool, AppError> {
@@ -230,6 +331,41 @@ pub fn update_providers_sort_order(
     ProviderService::update_sort_order(state.inner(), app_type, updates).map_err(|e| e.to_string())
 }
 
+/// 审计供应商配置（重复 API Key、缺少 base_url / key、跨家族模型映射及修正建议）
+#[tauri::command]
+pub fn audit_providers(
+    state: State<'_, AppState>,
+    app: String,
+) -> Result<crate::database::ProviderAuditReport, String> {
+    let app_type = AppType::from_str(&app).map_err(|e| e.to_string())?;
+    state
+        .db
+        .audit_providers(app_type.as_str())
+        .map_err(|e| e.to_string())
+}
+
+/// 列出供应商模板（内置目录 + ~/.cc-switch/templates.json 覆盖）；指定 app 时只返回支持该应用的模板
+#[tauri::command]
+pub fn list_provider_templates(
+    app: Option<String>,
+) -> Result<Vec<crate::provider_templates::ProviderTemplate>, String> {
+    let app_type = app
+        .as_deref()
+        .map(AppType::from_str)
+        .transpose()
+        .map_err(|e| e.to_string())?;
+    let templates =
+        crate::provider_templates::load_provider_templates().map_err(|e| e.to_string())?;
+    Ok(templates
+        .into_iter()
+        .filter(|t| {
+            app_type
+                .as_ref()
+                .is_none_or(|a| t.apps.contains_key(a.as_str()))
+        })
+        .collect())
+}
+
 // ============================================================================
 // 统一供应商（Universal Provider）命令
 // ============================================================================
@@ -3,7 +3,9 @@
 //! 提供前端调用的 API 接口
 
 use crate::proxy::types::*;
-use crate::proxy::{CircuitBreakerConfig, CircuitBreakerStats};
+use crate::proxy::{
+    CircuitBreakerConfig, CircuitBreakerStats, ProviderCircuitBreakerStats, RecentRequest,
+};
 use crate::store::AppState;
 
 /// 启动代理服务器（仅启动服务，不接管 Live 配置）
@@ -47,6 +49,14 @@ pub async fn get_proxy_status(state: tauri::State<'_, AppState>) -> Result<Proxy
     state.proxy_service.get_status().await
 }
 
+/// 获取最近的代理请求（最新在前）
+#[tauri::command]
+pub async fn get_recent_proxy_requests(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<RecentRequest>, String> {
+    Ok(state.proxy_service.get_recent_requests().await)
+}
+
 /// 获取代理配置
 #[tauri::command]
 pub async fn get_proxy_config(state: tauri::State<'_, AppState>) -> Result<ProxyConfig, String> {
@@ -116,7 +126,10 @@ pub async fn update_proxy_config_for_app(
     let db = &state.db;
     db.update_proxy_config_for_app(config)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+    // enabled 可能变化：推送到运行中的代理
+    state.proxy_service.refresh_app_gate().await;
+    Ok(())
 }
 
 /// 检查代理服务器是否正在运行
@@ -248,6 +261,44 @@ pub async fn reset_circuit_breaker(
     Ok(())
 }
 
+/// 强制打开熔断器 `secs` 秒（需代理运行中；解除使用 `reset_circuit_breaker`）
+#[tauri::command]
+pub async fn force_open_circuit_breaker(
+    state: tauri::State<'_, AppState>,
+    app_type: String,
+    provider_id: String,
+    secs: u64,
+) -> Result<(), String> {
+    if secs == 0 {
+        return Err("强制熔断时长必须大于 0".to_string());
+    }
+    state
+        .proxy_service
+        .force_open_provider_circuit_breaker(&app_type, &provider_id, secs)
+        .await
+}
+
+/// 查询路由事件日志（按时间倒序；`since` 为 Unix 秒，缺省 24 小时前）
+#[tauri::command]
+pub async fn get_routing_events(
+    state: tauri::State<'_, AppState>,
+    app_type: String,
+    kind: Option<String>,
+    since: Option<i64>,
+    limit: Option<usize>,
+) -> Result<Vec<crate::database::EventLogEntry>, String> {
+    let kind = kind
+        .as_deref()
+        .map(str::parse::<crate::database::EventKind>)
+        .transpose()
+        .map_err(|e| e.to_string())?;
+    let since = since.unwrap_or_else(|| chrono::Utc::now().timestamp() - 24 * 3600);
+    state
+        .db
+        .query_events(&app_type, kind, since, limit.unwrap_or(200))
+        .map_err(|e| e.to_string())
+}
+
 /// 获取熔断器配置
 #[tauri::command]
 pub async fn get_circuit_breaker_config(
@@ -288,8 +339,28 @@ pub async fn get_circuit_breaker_stats(
     provider_id: String,
     app_type: String,
 ) -> Result<Option<CircuitBreakerStats>, String> {
-    // 这个功能需要访问运行中的代理服务器的内存状态
-    // 目前先返回 None，后续可以通过 ProxyService 暴露接口来实现
-    let _ = (state, provider_id, app_type);
-    Ok(None)
+    Ok(state
+        .proxy_service
+        .get_circuit_breaker_stats(&provider_id, &app_type)
+        .await)
+}
+
+/// 获取该应用下所有供应商的熔断器统计信息（仅当代理服务器运行时，否则为空）
+#[tauri::command]
+pub async fn get_all_circuit_breaker_stats(
+    state: tauri::State<'_, AppState>,
+    app_type: String,
+) -> Result<Vec<ProviderCircuitBreakerStats>, String> {
+    Ok(state
+        .proxy_service
+        .get_all_circuit_breaker_stats(&app_type)
+        .await)
+}
+
+/// 获取各应用的路由快照：激活层级、各供应商锁定的 URL、缓存延迟与冷却状态（代理未运行时为空）
+#[tauri::command]
+pub async fn get_routing_snapshot(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<AppRoutingSnapshot>, String> {
+    Ok(state.proxy_service.get_routing_snapshot().await)
 }
@@ -0,0 +1,226 @@
+//! 供应商配置审计：重复 API Key、缺少（或无法展开）base_url / key / 优先级层级、跨家族模型映射
+//!
+//! 路由在同一 URL 上按 key 值去重轮询，多个供应商共享同一 key 时相互之间的故障转移
+//! 实际不会生效。结果中的 key 只以指纹（SHA-256 前 8 位）出现，不包含明文。
+//!
+//! 跨家族映射的修正建议来自代理进程内缓存的 `/v1/models` 列表，因此只有在代理进程内
+//! 执行审计（`GET /admin/audit/:app_type`）时才可能给出。
+
+use super::Database;
+use crate::error::AppError;
+use crate::provider::{Provider, DEFAULT_PRIORITY};
+use crate::proxy::model_mapper::{cross_family_mappings, suggest_mapping_value};
+use crate::proxy::provider_router::ProviderRouter;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+
+/// 共享 key 的供应商
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyHolder {
+    pub id: String,
+    pub name: String,
+    pub sort_index: Option<usize>,
+    pub in_failover_queue: bool,
+    pub base_url: Option<String>,
+}
+
+/// 一组共享同一 API Key 的供应商
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateKeyGroup {
+    /// key 指纹（哈希前 8 位十六进制，不可还原明文）
+    pub fingerprint: String,
+    pub providers: Vec<KeyHolder>,
+}
+
+impl DuplicateKeyGroup {
+    /// 故障转移队列中同一层级内共享该 key 的供应商（每个层级至少 2 个）
+    pub fn same_priority_sets(&self) -> Vec<(usize, Vec<&KeyHolder>)> {
+        let mut levels: BTreeMap<usize, Vec<&KeyHolder>> = BTreeMap::new();
+        for holder in self.providers.iter().filter(|h| h.in_failover_queue) {
+            levels
+                .entry(holder.sort_index.unwrap_or(DEFAULT_PRIORITY))
+                .or_default()
+                .push(holder);
+        }
+        levels.into_iter().filter(|(_, h)| h.len() > 1).collect()
+    }
+}
+
+/// 单个供应商的配置问题
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ProviderIssue {
+    /// 在故障转移队列中但未配置 base_url（路由会跳过）
+    #[serde(rename_all = "camelCase")]
+    MissingBaseUrl {
+        provider_id: String,
+        provider_name: String,
+    },
+    /// 在故障转移队列中，base_url 引用了未设置的环境变量（路由会跳过）
+    #[serde(rename_all = "camelCase")]
+    UnresolvedBaseUrl {
+        provider_id: String,
+        provider_name: String,
+        /// 展开失败的原因（变量名与引用路径）
+        error: String,
+    },
+    /// 在故障转移队列中但未配置 API Key（路由会跳过）
+    #[serde(rename_all = "camelCase")]
+    MissingApiKey {
+        provider_id: String,
+        provider_name: String,
+    },
+    /// 在故障转移队列中但未设置 sort_index（按默认层级 DEFAULT_PRIORITY 参与路由）
+    #[serde(rename_all = "camelCase")]
+    MissingSortIndex {
+        provider_id: String,
+        provider_name: String,
+    },
+    /// 映射到其他模型家族（请求时会被忽略，保持原模型）
+    #[serde(rename_all = "camelCase")]
+    CrossFamilyMapping {
+        provider_id: String,
+        provider_name: String,
+        env_key: String,
+        value: String,
+        /// 基于已缓存模型列表建议的同家族值
+        #[serde(default)]
+        suggestion: Option<String>,
+    },
+}
+
+/// 某个应用的审计结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderAuditReport {
+    pub app_type: String,
+    pub duplicate_keys: Vec<DuplicateKeyGroup>,
+    pub issues: Vec<ProviderIssue>,
+}
+
+impl ProviderAuditReport {
+    pub fn is_clean(&self) -> bool {
+        self.duplicate_keys.is_empty() && self.issues.is_empty()
+    }
+}
+
+/// key 指纹：SHA-256 前 8 位十六进制，跨版本与进程稳定，便于对照不同时间的审计结果
+fn key_fingerprint(key: &str) -> String {
+    Sha256::digest(key.as_bytes())
+        .iter()
+        .take(4)
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+impl Database {
+    /// 查找共享同一 API Key 的供应商分组（按 key 原值比较，与路由去重规则一致）
+    pub fn find_duplicate_keys(&self, app_type: &str) -> Result<Vec<DuplicateKeyGroup>, AppError> {
+        let providers = self.get_all_providers(app_type)?;
+        Ok(Self::group_duplicate_keys(providers.values(), app_type))
+    }
+
+    /// 故障转移队列中未设置 sort_index 的供应商（按 [`DEFAULT_PRIORITY`] 层级参与路由）
+    pub fn find_missing_sort_index(&self, app_type: &str) -> Result<Vec<Provider>, AppError> {
+        Ok(self
+            .get_failover_providers(app_type)?
+            .into_iter()
+            .filter(|p| p.sort_index.is_none())
+            .collect())
+    }
+
+    /// 审计该应用下的全部供应商
+    pub fn audit_providers(&self, app_type: &str) -> Result<ProviderAuditReport, AppError> {
+        let providers = self.get_all_providers(app_type)?;
+
+        let mut issues = Vec::new();
+        for provider in providers.values() {
+            if provider.in_failover_queue {
+                match ProviderRouter::try_extract_base_url(provider, app_type) {
+                    Ok(Some(_)) => {}
+                    Ok(None) => issues.push(ProviderIssue::MissingBaseUrl {
+                        provider_id: provider.id.clone(),
+                        provider_name: provider.name.clone(),
+                    }),
+                    Err(e) => issues.push(ProviderIssue::UnresolvedBaseUrl {
+                        provider_id: provider.id.clone(),
+                        provider_name: provider.name.clone(),
+                        error: e.to_string(),
+                    }),
+                }
+                if Self::api_key_of(provider, app_type).is_none() {
+                    issues.push(ProviderIssue::MissingApiKey {
+                        provider_id: provider.id.clone(),
+                        provider_name: provider.name.clone(),
+                    });
+                }
+                if provider.sort_index.is_none() {
+                    issues.push(ProviderIssue::MissingSortIndex {
+                        provider_id: provider.id.clone(),
+                        provider_name: provider.name.clone(),
+                    });
+                }
+            }
+            if app_type == "claude" {
+                for (env_key, value) in cross_family_mappings(provider) {
+                    issues.push(ProviderIssue::CrossFamilyMapping {
+                        provider_id: provider.id.clone(),
+                        provider_name: provider.name.clone(),
+                        env_key: env_key.to_string(),
+                        value,
+                        suggestion: suggest_mapping_value(provider, env_key),
+                    });
+                }
+            }
+        }
+
+        Ok(ProviderAuditReport {
+            app_type: app_type.to_string(),
+            duplicate_keys: Self::group_duplicate_keys(providers.values(), app_type),
+            issues,
+        })
+    }
+
+    fn api_key_of(provider: &Provider, app_type: &str) -> Option<String> {
+        ProviderRouter::extract_api_key_value(provider, app_type).filter(|k| !k.trim().is_empty())
+    }
+
+    fn group_duplicate_keys<'a>(
+        providers: impl Iterator<Item = &'a Provider>,
+        app_type: &str,
+    ) -> Vec<DuplicateKeyGroup> {
+        let mut by_key: HashMap<String, Vec<KeyHolder>> = HashMap::new();
+        for provider in providers {
+            let Some(key) = Self::api_key_of(provider, app_type) else {
+                continue;
+            };
+            by_key.entry(key).or_default().push(KeyHolder {
+                id: provider.id.clone(),
+                name: provider.name.clone(),
+                sort_index: provider.sort_index,
+                in_failover_queue: provider.in_failover_queue,
+                base_url: ProviderRouter::extract_base_url(provider, app_type),
+            });
+        }
+
+        let mut groups: Vec<DuplicateKeyGroup> = by_key
+            .into_iter()
+            .filter(|(_, holders)| holders.len() > 1)
+            .map(|(key, mut holders)| {
+                holders.sort_by(|a, b| {
+                    (a.sort_index.unwrap_or(DEFAULT_PRIORITY), &a.id)
+                        .cmp(&(b.sort_index.unwrap_or(DEFAULT_PRIORITY), &b.id))
+                });
+                DuplicateKeyGroup {
+                    fingerprint: key_fingerprint(&key),
+                    providers: holders,
+                }
+            })
+            .collect();
+        groups.sort_by(|a, b| a.providers[0].id.cmp(&b.providers[0].id));
+        groups
+    }
+}
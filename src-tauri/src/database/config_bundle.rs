@@ -0,0 +1,331 @@
+//! 代理/应用配置整体导入导出
+//!
+//! 与 SQL 导出（backup.rs）不同，这里只导出“运行配置”，用于在另一台机器上复刻同一套代理行为：
+//! - 全局代理配置（GlobalProxyConfig）
+//! - 每个应用的代理配置（AppProxyConfig）、当前供应商、故障转移队列顺序
+//! - supplier 首选 URL
+//!
+//! 供应商本身不在其中（使用 SQL 导出或 `csc add` 同步）；导入时引用不存在的供应商只告警不报错。
+
+use super::dao::SupplierUrlPriority;
+use super::{lock_conn, Database};
+use crate::app_config::AppType;
+use crate::error::AppError;
+use crate::proxy::types::{AppProxyConfig, GlobalProxyConfig};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// 配置包格式版本
+pub const CONFIG_BUNDLE_VERSION: u32 = 1;
+
+const APP_TYPES: [&str; 3] = ["claude", "codex", "gemini"];
+
+/// 配置包
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigBundle {
+    pub schema_version: u32,
+    pub global_proxy: GlobalProxyConfig,
+    pub apps: Vec<AppConfigBundle>,
+    #[serde(default)]
+    pub supplier_url_priorities: Vec<SupplierUrlPriority>,
+}
+
+/// 单个应用的配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppConfigBundle {
+    pub proxy: AppProxyConfig,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_provider: Option<String>,
+    #[serde(default)]
+    pub failover_queue: Vec<FailoverQueueEntry>,
+}
+
+/// 故障转移队列条目（按导出顺序排列）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FailoverQueueEntry {
+    pub provider_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort_index: Option<usize>,
+}
+
+/// 导入结果
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigImportReport {
+    /// 非致命问题（未知字段、引用了不存在的供应商等）
+    pub warnings: Vec<String>,
+    /// 需要重启代理才能生效的变更
+    pub restart_required: Vec<String>,
+}
+
+impl ConfigBundle {
+    /// 解析并校验配置包：未知字段告警，非法值报错
+    pub fn parse(text: &str) -> Result<(Self, Vec<String>), AppError> {
+        let raw: Value = serde_json::from_str(text)
+            .map_err(|e| AppError::InvalidInput(format!("配置文件不是有效的 JSON: {e}")))?;
+        let bundle: ConfigBundle = serde_json::from_value(raw.clone())
+            .map_err(|e| AppError::InvalidInput(format!("配置文件格式错误: {e}")))?;
+        bundle.validate()?;
+
+        // 反序列化会静默丢弃未知字段：与重新序列化的结果对比找出它们
+        let known = serde_json::to_value(&bundle)
+            .map_err(|e| AppError::Config(format!("JSON serialization failed: {e}")))?;
+        let mut warnings = Vec::new();
+        collect_unknown_fields(&raw, &known, "", &mut warnings);
+
+        Ok((bundle, warnings))
+    }
+
+    /// 校验取值
+    pub fn validate(&self) -> Result<(), AppError> {
+        let invalid = |msg: String| Err(AppError::InvalidInput(msg));
+
+        if self.schema_version == 0 || self.schema_version > CONFIG_BUNDLE_VERSION {
+            return invalid(format!(
+                "不支持的配置版本: {}（当前支持 {}）",
+                self.schema_version, CONFIG_BUNDLE_VERSION
+            ));
+        }
+
+        if self.global_proxy.listen_address.trim().is_empty() {
+            return invalid("globalProxy.listenAddress 不能为空".to_string());
+        }
+        if self.global_proxy.listen_port == 0 {
+            return invalid("globalProxy.listenPort 不能为 0".to_string());
+        }
+
+        let mut seen: Vec<&str> = Vec::new();
+        for app in &self.apps {
+            let cfg = &app.proxy;
+            let app_type = cfg.app_type.as_str();
+            if !APP_TYPES.contains(&app_type) {
+                return invalid(format!("无效的应用类型: {app_type}"));
+            }
+            if seen.contains(&app_type) {
+                return invalid(format!("应用 {app_type} 重复出现"));
+            }
+            seen.push(app_type);
+
+            if !(0.0..=1.0).contains(&cfg.circuit_error_rate_threshold) {
+                return invalid(format!(
+                    "[{app_type}] circuitErrorRateThreshold 必须在 0~1 之间: {}",
+                    cfg.circuit_error_rate_threshold
+                ));
+            }
+            if cfg.circuit_failure_threshold == 0 || cfg.circuit_success_threshold == 0 {
+                return invalid(format!("[{app_type}] 熔断失败/恢复阈值必须大于 0"));
+            }
+            if let Some(id) = &app.current_provider {
+                if id.trim().is_empty() {
+                    return invalid(format!("[{app_type}] currentProvider 不能为空字符串"));
+                }
+            }
+            for entry in &app.failover_queue {
+                if entry.provider_id.trim().is_empty() {
+                    return invalid(format!("[{app_type}] failoverQueue 中存在空的 providerId"));
+                }
+            }
+        }
+
+        for item in &self.supplier_url_priorities {
+            if item.supplier.trim().is_empty() {
+                return invalid("supplierUrlPriorities 中存在空的 supplier".to_string());
+            }
+            for url in &item.urls {
+                let url = url.trim();
+                if !(url.starts_with("http://") || url.starts_with("https://")) {
+                    return invalid(format!("[{}] 无效的 URL: {url}", item.supplier));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// 递归收集 `raw` 中存在、但 `known` 中没有的字段（值为 null 的字段忽略）
+fn collect_unknown_fields(raw: &Value, known: &Value, path: &str, out: &mut Vec<String>) {
+    match (raw, known) {
+        (Value::Object(raw), Value::Object(known)) => {
+            for (key, value) in raw {
+                let child = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{path}.{key}")
+                };
+                match known.get(key) {
+                    Some(known_value) => collect_unknown_fields(value, known_value, &child, out),
+                    None if value.is_null() => {}
+                    None => out.push(format!("未知字段已忽略: {child}")),
+                }
+            }
+        }
+        (Value::Array(raw), Value::Array(known)) => {
+            for (i, (raw, known)) in raw.iter().zip(known).enumerate() {
+                collect_unknown_fields(raw, known, &format!("{path}[{i}]"), out);
+            }
+        }
+        _ => {}
+    }
+}
+
+impl Database {
+    /// 导出全部代理/应用配置
+    pub async fn export_config_bundle(&self) -> Result<ConfigBundle, AppError> {
+        let global_proxy = self.get_global_proxy_config().await?;
+
+        let mut apps = Vec::new();
+        for app_type in APP_TYPES {
+            let proxy = self.get_proxy_config_for_app(app_type).await?;
+            // 与界面、托盘一致：设备级当前供应商（settings.json）优先，其次数据库
+            let app: AppType = app_type.parse()?;
+            let current_provider = crate::settings::get_effective_current_provider(self, &app)?;
+            let failover_queue = self
+                .get_failover_queue(app_type)?
+                .into_iter()
+                .map(|item| FailoverQueueEntry {
+                    provider_id: item.provider_id,
+                    sort_index: item.sort_index,
+                })
+                .collect();
+            apps.push(AppConfigBundle {
+                proxy,
+                current_provider,
+                failover_queue,
+            });
+        }
+
+        Ok(ConfigBundle {
+            schema_version: CONFIG_BUNDLE_VERSION,
+            global_proxy,
+            apps,
+            supplier_url_priorities: self.list_supplier_url_priorities()?,
+        })
+    }
+
+    /// 导入配置包（调用方应先通过 [`ConfigBundle::parse`] 校验）
+    ///
+    /// 包中出现的应用会整体替换（代理配置、当前供应商、故障转移队列），未出现的应用保持不变；
+    /// supplier 首选 URL 整体替换。数据库写入在同一事务内完成，任一步失败整体回滚；
+    /// 当前供应商同时写入数据库与设备级设置（settings.json），与切换供应商一致。
+    pub async fn import_config_bundle(
+        &self,
+        bundle: &ConfigBundle,
+    ) -> Result<ConfigImportReport, AppError> {
+        bundle.validate()?;
+        let mut report = ConfigImportReport::default();
+
+        let previous = self.get_global_proxy_config().await?;
+        let next = &bundle.global_proxy;
+        if previous.listen_address != next.listen_address
+            || previous.listen_port != next.listen_port
+        {
+            report.restart_required.push(format!(
+                "监听地址 {}:{} -> {}:{}",
+                previous.listen_address,
+                previous.listen_port,
+                next.listen_address,
+                next.listen_port
+            ));
+        }
+        if previous.enable_logging != next.enable_logging {
+            report
+                .restart_required
+                .push(format!("日志开关 -> {}", next.enable_logging));
+        }
+        if previous.read_only != next.read_only {
+            report
+                .restart_required
+                .push(format!("只读模式 -> {}", next.read_only));
+        }
+
+        // 事务外预读：确保 proxy_config 行存在，取得各应用的供应商
+        let mut app_providers = Vec::with_capacity(bundle.apps.len());
+        for app in &bundle.apps {
+            let app_type = app.proxy.app_type.as_str();
+            self.get_proxy_config_for_app(app_type).await?;
+            app_providers.push(self.get_all_providers(app_type)?);
+        }
+        let previous_priorities = self.list_supplier_url_priorities()?;
+
+        // 需要同步到设备级设置的当前供应商（None 表示清除）
+        let mut current_changes: Vec<(AppType, Option<String>)> = Vec::new();
+        {
+            let mut conn = lock_conn!(self.conn);
+            let tx = conn.transaction().map_err(AppError::from)?;
+
+            Self::write_global_proxy_config(&tx, next)?;
+
+            for (app, providers) in bundle.apps.iter().zip(&app_providers) {
+                let app_type = app.proxy.app_type.as_str();
+                Self::write_app_proxy_config(&tx, &app.proxy)?;
+
+                let current = match &app.current_provider {
+                    Some(id) if providers.contains_key(id) => Some(Some(id.clone())),
+                    Some(id) => {
+                        report
+                            .warnings
+                            .push(format!("[{app_type}] 当前供应商 {id} 不存在，已跳过"));
+                        None
+                    }
+                    None => Some(None),
+                };
+                if let Some(current) = current {
+                    tx.execute(
+                        "UPDATE providers SET is_current = 0 WHERE app_type = ?1",
+                        params![app_type],
+                    )
+                    .map_err(AppError::from)?;
+                    if let Some(id) = current.as_deref() {
+                        tx.execute(
+                            "UPDATE providers SET is_current = 1 WHERE id = ?1 AND app_type = ?2",
+                            params![id, app_type],
+                        )
+                        .map_err(AppError::from)?;
+                    }
+                    current_changes.push((app_type.parse()?, current));
+                }
+
+                tx.execute(
+                    "UPDATE providers SET in_failover_queue = 0 WHERE app_type = ?1",
+                    params![app_type],
+                )
+                .map_err(AppError::from)?;
+                for entry in &app.failover_queue {
+                    if !providers.contains_key(&entry.provider_id) {
+                        report.warnings.push(format!(
+                            "[{app_type}] 队列中的供应商 {} 不存在，已跳过",
+                            entry.provider_id
+                        ));
+                        continue;
+                    }
+                    tx.execute(
+                        "UPDATE providers SET in_failover_queue = 1, sort_index = ?1
+                         WHERE id = ?2 AND app_type = ?3",
+                        params![entry.sort_index, entry.provider_id, app_type],
+                    )
+                    .map_err(AppError::from)?;
+                }
+            }
+
+            for existing in &previous_priorities {
+                Self::write_supplier_url_priority(&tx, &existing.supplier, &[])?;
+            }
+            for item in &bundle.supplier_url_priorities {
+                Self::write_supplier_url_priority(&tx, &item.supplier, &item.urls)?;
+            }
+
+            tx.commit().map_err(AppError::from)?;
+        }
+
+        for (app_type, current) in current_changes {
+            crate::settings::set_current_provider(&app_type, current.as_deref())?;
+        }
+
+        Ok(report)
+    }
+}
@@ -0,0 +1,229 @@
+//! 启动一致性检查：当前供应商、故障转移队列与应用代理配置是否相互矛盾
+//!
+//! 这类问题（当前供应商不在队列、故障转移开启但队列为空、队列中的供应商缺 key）
+//! 平时不报错，直到请求时才表现为 "No available providers"。代理启动与 `csc doctor`
+//! 都会执行检查；把当前供应商加入队列这类简单问题可按设置自动修复。
+
+use super::Database;
+use crate::error::AppError;
+use crate::provider::Provider;
+use crate::proxy::provider_router::ProviderRouter;
+use serde::{Deserialize, Serialize};
+
+/// 问题严重程度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConsistencySeverity {
+    /// 部分供应商不会参与路由
+    Warning,
+    /// 请求必然失败（No available providers）
+    Error,
+}
+
+/// 不一致的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConsistencyKind {
+    /// 故障转移开启，但当前供应商不在队列中（不会被使用）
+    CurrentNotInQueue,
+    /// 故障转移开启，但队列为空
+    EmptyQueue,
+    /// 队列中的供应商缺少 base_url 或 API Key（路由会跳过）
+    QueuedProviderIncomplete,
+    /// 故障转移开启，但队列中没有可用的供应商
+    NoUsableQueuedProvider,
+    /// 故障转移关闭，且未指定当前供应商
+    NoCurrentProvider,
+    /// 故障转移关闭，当前供应商缺少 base_url 或 API Key
+    CurrentProviderIncomplete,
+}
+
+/// 单条检查结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsistencyFinding {
+    pub kind: ConsistencyKind,
+    pub severity: ConsistencySeverity,
+    pub message: String,
+    /// 修复建议
+    pub suggestion: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider_id: Option<String>,
+    /// 已自动修复
+    #[serde(default)]
+    pub fixed: bool,
+}
+
+/// 某个应用的一致性检查结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsistencyReport {
+    pub app_type: String,
+    pub findings: Vec<ConsistencyFinding>,
+}
+
+impl ConsistencyReport {
+    /// 仍未解决的问题（不含已自动修复的）
+    pub fn unresolved(&self) -> impl Iterator<Item = &ConsistencyFinding> {
+        self.findings.iter().filter(|f| !f.fixed)
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.unresolved()
+            .any(|f| f.severity == ConsistencySeverity::Error)
+    }
+}
+
+impl Database {
+    /// 检查当前供应商、故障转移队列与应用代理配置的一致性
+    ///
+    /// `auto_fix` 为 true 时先修复简单问题（目前只有“当前供应商不在队列中”），
+    /// 修复项以 `fixed = true` 保留在结果中，其余问题按修复后的状态检查。
+    pub async fn check_consistency(
+        &self,
+        app_type: &str,
+        auto_fix: bool,
+    ) -> Result<ConsistencyReport, AppError> {
+        let config = self.get_proxy_config_for_app(app_type).await?;
+        let providers = self.get_all_providers(app_type)?;
+        let mut findings = Vec::new();
+        // 未配置任何供应商的应用不经过代理，不算不一致
+        if providers.is_empty() {
+            return Ok(ConsistencyReport {
+                app_type: app_type.to_string(),
+                findings,
+            });
+        }
+        let current = self
+            .get_current_provider(app_type)?
+            .and_then(|id| providers.get(&id));
+
+        if !config.auto_failover_enabled {
+            match current {
+                None => findings.push(ConsistencyFinding {
+                    kind: ConsistencyKind::NoCurrentProvider,
+                    severity: ConsistencySeverity::Error,
+                    message: "故障转移已关闭，但未指定当前供应商".to_string(),
+                    suggestion: format!(
+                        "csc en {app_type} <ID> 指定供应商，或开启故障转移并把供应商加入队列"
+                    ),
+                    provider_id: None,
+                    fixed: false,
+                }),
+                Some(provider) => {
+                    if let Some(missing) = missing_fields(provider, app_type) {
+                        findings.push(ConsistencyFinding {
+                            kind: ConsistencyKind::CurrentProviderIncomplete,
+                            severity: ConsistencySeverity::Error,
+                            message: format!(
+                                "当前供应商 {} ({}) 缺少 {missing}",
+                                provider.name, provider.id
+                            ),
+                            suggestion: format!("补全该供应商的 {missing}，或 csc en {app_type} <ID> 换用其他供应商"),
+                            provider_id: Some(provider.id.clone()),
+                            fixed: false,
+                        });
+                    }
+                }
+            }
+            return Ok(ConsistencyReport {
+                app_type: app_type.to_string(),
+                findings,
+            });
+        }
+
+        let mut queued: Vec<&Provider> =
+            providers.values().filter(|p| p.in_failover_queue).collect();
+
+        if let Some(provider) = current.filter(|p| !p.in_failover_queue) {
+            if auto_fix {
+                self.add_to_failover_queue(app_type, &provider.id)?;
+                log::info!(
+                    "[{app_type}] 一致性检查：已将当前供应商 {} ({}) 加入故障转移队列",
+                    provider.name,
+                    provider.id
+                );
+                queued.push(provider);
+            }
+            findings.push(ConsistencyFinding {
+                kind: ConsistencyKind::CurrentNotInQueue,
+                severity: ConsistencySeverity::Warning,
+                message: format!(
+                    "故障转移已开启，当前供应商 {} ({}) 不在队列中，不会被使用",
+                    provider.name, provider.id
+                ),
+                suggestion: format!("csc qa {app_type} {}", provider.id),
+                provider_id: Some(provider.id.clone()),
+                fixed: auto_fix,
+            });
+        }
+
+        if queued.is_empty() {
+            findings.push(ConsistencyFinding {
+                kind: ConsistencyKind::EmptyQueue,
+                severity: ConsistencySeverity::Error,
+                message: "故障转移已开启，但队列为空".to_string(),
+                suggestion: format!("csc qa {app_type} <ID> 加入供应商，或关闭故障转移"),
+                provider_id: None,
+                fixed: false,
+            });
+            return Ok(ConsistencyReport {
+                app_type: app_type.to_string(),
+                findings,
+            });
+        }
+
+        queued.sort_by(|a, b| a.id.cmp(&b.id));
+        let mut usable = 0;
+        for provider in &queued {
+            match missing_fields(provider, app_type) {
+                Some(missing) => findings.push(ConsistencyFinding {
+                    kind: ConsistencyKind::QueuedProviderIncomplete,
+                    severity: ConsistencySeverity::Warning,
+                    message: format!(
+                        "队列中的供应商 {} ({}) 缺少 {missing}，路由会跳过",
+                        provider.name, provider.id
+                    ),
+                    suggestion: format!(
+                        "补全该供应商的 {missing}，或 csc qr {app_type} {} 移出队列",
+                        provider.id
+                    ),
+                    provider_id: Some(provider.id.clone()),
+                    fixed: false,
+                }),
+                None => usable += 1,
+            }
+        }
+        if usable == 0 {
+            findings.push(ConsistencyFinding {
+                kind: ConsistencyKind::NoUsableQueuedProvider,
+                severity: ConsistencySeverity::Error,
+                message: format!(
+                    "队列中的 {} 个供应商均缺少 base_url 或 API Key",
+                    queued.len()
+                ),
+                suggestion: "补全队列中供应商的 base_url 与 API Key".to_string(),
+                provider_id: None,
+                fixed: false,
+            });
+        }
+
+        Ok(ConsistencyReport {
+            app_type: app_type.to_string(),
+            findings,
+        })
+    }
+}
+
+/// 路由必需但缺失的字段（与选路时跳过供应商的条件一致）
+fn missing_fields(provider: &Provider, app_type: &str) -> Option<&'static str> {
+    let has_url = ProviderRouter::extract_base_url(provider, app_type).is_some();
+    let has_key = ProviderRouter::extract_api_key_value(provider, app_type)
+        .is_some_and(|k| !k.trim().is_empty());
+    match (has_url, has_key) {
+        (true, true) => None,
+        (false, true) => Some("base_url"),
+        (true, false) => Some("API Key"),
+        (false, false) => Some("base_url 与 API Key"),
+    }
+}
@@ -0,0 +1,385 @@
+//! 测速历史 DAO
+//!
+//! 持久化每次 `BenchmarkSupplierResult`（含 URL 明细），用于观察 supplier 延迟趋势。
+//! 同一次写入（一次测速调用）的结果共享 `run_id`，`benchmark diff` 据此对比两次测速。
+//! 与请求日志使用同一保留天数，由 `db maintain` 清理。
+
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use crate::proxy::provider_router::{BenchmarkSupplierResult, BenchmarkUrlResult};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// 一次 supplier 测速记录
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BenchmarkHistoryEntry {
+    pub id: i64,
+    pub app_type: String,
+    /// 测速批次标识（同一次测速调用写入的结果相同）
+    pub run_id: String,
+    pub created_at: i64,
+    pub result: BenchmarkSupplierResult,
+}
+
+/// 某个 supplier（可选限定 URL）的单个测速点
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BenchmarkSeriesPoint {
+    pub created_at: i64,
+    pub priority: usize,
+    pub supplier: String,
+    pub url: String,
+    /// OK / OV / FB / FAIL
+    pub kind: String,
+    pub latency_ms: Option<u64>,
+    pub penalty_ms: Option<u64>,
+    /// 该 URL 是否为本次测速选中的 URL
+    pub chosen: bool,
+}
+
+/// 两次测速之间单个 URL 的变化
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BenchmarkUrlChange {
+    /// 仅出现在较新的一次测速中
+    Appeared,
+    /// 仅出现在较早的一次测速中
+    Disappeared,
+    /// 两次测速都有
+    Kept,
+}
+
+/// 单个 URL 的测速对比
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BenchmarkUrlDiff {
+    pub url: String,
+    pub change: BenchmarkUrlChange,
+    /// OK / OV / FB / FAIL
+    pub before_kind: Option<String>,
+    pub after_kind: Option<String>,
+    pub before_ms: Option<u64>,
+    pub after_ms: Option<u64>,
+    /// 延迟变化（新 - 旧），任一侧无延迟时为空
+    pub delta_ms: Option<i64>,
+}
+
+impl BenchmarkUrlDiff {
+    /// 两次测速结果类型不同（如 OK → FAIL）
+    pub fn kind_changed(&self) -> bool {
+        self.change == BenchmarkUrlChange::Kept && self.before_kind != self.after_kind
+    }
+}
+
+/// 某个 supplier 两次测速的对比
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BenchmarkSupplierDiff {
+    pub priority: usize,
+    pub supplier: String,
+    pub before_run_id: String,
+    pub before_at: i64,
+    pub after_run_id: String,
+    pub after_at: i64,
+    pub before_kind: String,
+    pub after_kind: String,
+    pub before_metric_ms: Option<u64>,
+    pub after_metric_ms: Option<u64>,
+    pub before_url: Option<String>,
+    pub after_url: Option<String>,
+    pub urls: Vec<BenchmarkUrlDiff>,
+}
+
+impl BenchmarkSupplierDiff {
+    /// 对比同一 supplier 的两次测速（`before` 较早）
+    pub fn between(before: &BenchmarkHistoryEntry, after: &BenchmarkHistoryEntry) -> Self {
+        let mut by_url: BTreeMap<&str, (Option<&BenchmarkUrlResult>, Option<&BenchmarkUrlResult>)> =
+            BTreeMap::new();
+        for u in &before.result.urls {
+            by_url.entry(u.url.as_str()).or_default().0 = Some(u);
+        }
+        for u in &after.result.urls {
+            by_url.entry(u.url.as_str()).or_default().1 = Some(u);
+        }
+
+        let urls = by_url
+            .into_iter()
+            .map(|(url, (old, new))| {
+                let change = match (old, new) {
+                    (Some(_), Some(_)) => BenchmarkUrlChange::Kept,
+                    (None, _) => BenchmarkUrlChange::Appeared,
+                    (_, None) => BenchmarkUrlChange::Disappeared,
+                };
+                let before_ms = old.and_then(|u| u.latency_ms);
+                let after_ms = new.and_then(|u| u.latency_ms);
+                BenchmarkUrlDiff {
+                    url: url.to_string(),
+                    change,
+                    before_kind: old.map(|u| u.kind.clone()),
+                    after_kind: new.map(|u| u.kind.clone()),
+                    before_ms,
+                    after_ms,
+                    delta_ms: match (before_ms, after_ms) {
+                        (Some(b), Some(a)) => Some(a as i64 - b as i64),
+                        _ => None,
+                    },
+                }
+            })
+            .collect();
+
+        Self {
+            priority: after.result.priority,
+            supplier: after.result.supplier.clone(),
+            before_run_id: before.run_id.clone(),
+            before_at: before.created_at,
+            after_run_id: after.run_id.clone(),
+            after_at: after.created_at,
+            before_kind: before.result.chosen_kind.clone(),
+            after_kind: after.result.chosen_kind.clone(),
+            before_metric_ms: before.result.metric_ms,
+            after_metric_ms: after.result.metric_ms,
+            before_url: before.result.chosen_url.clone(),
+            after_url: after.result.chosen_url.clone(),
+            urls,
+        }
+    }
+}
+
+impl Database {
+    /// 写入一批测速结果（同一时间戳、同一批次标识）
+    pub fn save_benchmark_results(
+        &self,
+        app_type: &str,
+        results: &[BenchmarkSupplierResult],
+        created_at: i64,
+    ) -> Result<(), AppError> {
+        if results.is_empty() {
+            return Ok(());
+        }
+
+        let run_id = uuid::Uuid::new_v4().to_string();
+        let mut conn = lock_conn!(self.conn);
+        let tx = conn.transaction().map_err(AppError::from)?;
+
+        for r in results {
+            tx.execute(
+                "INSERT INTO benchmark_history
+                 (app_type, priority, supplier, request_model, chosen_url, chosen_kind, metric_ms, created_at, run_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    app_type,
+                    r.priority as i64,
+                    r.supplier,
+                    r.request_model,
+                    r.chosen_url,
+                    r.chosen_kind,
+                    r.metric_ms.map(|v| v as i64),
+                    created_at,
+                    run_id,
+                ],
+            )
+            .map_err(AppError::from)?;
+            let history_id = tx.last_insert_rowid();
+
+            for u in &r.urls {
+                tx.execute(
+                    "INSERT INTO benchmark_history_urls
+                     (history_id, url, kind, latency_ms, penalty_ms, message, reason)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    params![
+                        history_id,
+                        u.url,
+                        u.kind,
+                        u.latency_ms.map(|v| v as i64),
+                        u.penalty_ms.map(|v| v as i64),
+                        u.message,
+                        u.reason,
+                    ],
+                )
+                .map_err(AppError::from)?;
+            }
+        }
+
+        tx.commit().map_err(AppError::from)
+    }
+
+    /// 每个 supplier（按层级区分）在 `since` 之后的最近一次测速
+    pub fn get_latest_benchmarks(
+        &self,
+        app_type: &str,
+        since: i64,
+    ) -> Result<Vec<BenchmarkHistoryEntry>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, priority, supplier, request_model, chosen_url, chosen_kind, metric_ms, created_at, run_id
+                 FROM benchmark_history
+                 WHERE id IN (
+                     SELECT MAX(id) FROM benchmark_history
+                     WHERE app_type = ?1 AND created_at >= ?2
+                     GROUP BY priority, supplier
+                 )
+                 ORDER BY priority, supplier",
+            )
+            .map_err(AppError::from)?;
+
+        let mut entries = stmt
+            .query_map(params![app_type, since], |row| {
+                Self::benchmark_entry_from_row(app_type, row)
+            })
+            .map_err(AppError::from)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(AppError::from)?;
+
+        for entry in entries.iter_mut() {
+            entry.result.urls = Self::load_benchmark_urls(&conn, entry.id)?;
+        }
+
+        Ok(entries)
+    }
+
+    /// 各 supplier（按层级区分）最近两次测速的对比；`runs` 指定与倒数第几次对比（至少 2）
+    ///
+    /// 每个 supplier 独立取其最近的测速记录，只测过一次的 supplier 不参与对比。
+    pub fn get_benchmark_diffs(
+        &self,
+        app_type: &str,
+        supplier: Option<&str>,
+        runs: usize,
+    ) -> Result<Vec<BenchmarkSupplierDiff>, AppError> {
+        let runs = runs.max(2);
+        let conn = lock_conn!(self.conn);
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, priority, supplier, request_model, chosen_url, chosen_kind, metric_ms, created_at, run_id
+                 FROM (
+                     SELECT *, ROW_NUMBER() OVER (
+                         PARTITION BY priority, supplier ORDER BY created_at DESC, id DESC
+                     ) AS rn
+                     FROM benchmark_history
+                     WHERE app_type = ?1 AND (?2 IS NULL OR supplier = ?2)
+                 )
+                 WHERE rn <= ?3
+                 ORDER BY priority, supplier, created_at, id",
+            )
+            .map_err(AppError::from)?;
+
+        let mut entries = stmt
+            .query_map(params![app_type, supplier, runs as i64], |row| {
+                Self::benchmark_entry_from_row(app_type, row)
+            })
+            .map_err(AppError::from)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(AppError::from)?;
+
+        for entry in entries.iter_mut() {
+            entry.result.urls = Self::load_benchmark_urls(&conn, entry.id)?;
+        }
+
+        // 按 (priority, supplier) 分组后对比最早与最新的一次
+        let mut diffs = Vec::new();
+        for group in entries.chunk_by(|a, b| {
+            a.result.priority == b.result.priority && a.result.supplier == b.result.supplier
+        }) {
+            if let [before, .., after] = group {
+                diffs.push(BenchmarkSupplierDiff::between(before, after));
+            }
+        }
+        Ok(diffs)
+    }
+
+    fn benchmark_entry_from_row(
+        app_type: &str,
+        row: &rusqlite::Row<'_>,
+    ) -> rusqlite::Result<BenchmarkHistoryEntry> {
+        Ok(BenchmarkHistoryEntry {
+            id: row.get(0)?,
+            app_type: app_type.to_string(),
+            run_id: row.get(8)?,
+            created_at: row.get(7)?,
+            result: BenchmarkSupplierResult {
+                priority: row.get::<_, i64>(1)? as usize,
+                supplier: row.get(2)?,
+                request_model: row.get(3)?,
+                effective_model: None,
+                chosen_url: row.get(4)?,
+                chosen_kind: row.get(5)?,
+                metric_ms: row.get::<_, Option<i64>>(6)?.map(|v| v as u64),
+                urls: Vec::new(),
+                cooldown_reason: None,
+            },
+        })
+    }
+
+    /// supplier 在 `since` 之后的 URL 测速序列（按时间升序），`url` 为空时返回全部 URL
+    pub fn get_benchmark_series(
+        &self,
+        app_type: &str,
+        supplier: &str,
+        url: Option<&str>,
+        since: i64,
+    ) -> Result<Vec<BenchmarkSeriesPoint>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let mut stmt = conn
+            .prepare(
+                "SELECT h.created_at, h.priority, h.supplier, u.url, u.kind, u.latency_ms,
+                        u.penalty_ms, COALESCE(h.chosen_url = u.url, 0)
+                 FROM benchmark_history h
+                 JOIN benchmark_history_urls u ON u.history_id = h.id
+                 WHERE h.app_type = ?1 AND h.supplier = ?2 AND h.created_at >= ?3
+                   AND (?4 IS NULL OR u.url = ?4)
+                 ORDER BY h.created_at, h.id, u.url",
+            )
+            .map_err(AppError::from)?;
+
+        let points = stmt
+            .query_map(params![app_type, supplier, since, url], |row| {
+                Ok(BenchmarkSeriesPoint {
+                    created_at: row.get(0)?,
+                    priority: row.get::<_, i64>(1)? as usize,
+                    supplier: row.get(2)?,
+                    url: row.get(3)?,
+                    kind: row.get(4)?,
+                    latency_ms: row.get::<_, Option<i64>>(5)?.map(|v| v as u64),
+                    penalty_ms: row.get::<_, Option<i64>>(6)?.map(|v| v as u64),
+                    chosen: row.get(7)?,
+                })
+            })
+            .map_err(AppError::from)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(AppError::from)?;
+
+        Ok(points)
+    }
+
+    fn load_benchmark_urls(
+        conn: &Connection,
+        history_id: i64,
+    ) -> Result<Vec<BenchmarkUrlResult>, AppError> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT url, kind, latency_ms, penalty_ms, message, reason
+                 FROM benchmark_history_urls WHERE history_id = ?1 ORDER BY rowid",
+            )
+            .map_err(AppError::from)?;
+
+        let urls = stmt
+            .query_map([history_id], |row| {
+                Ok(BenchmarkUrlResult {
+                    url: row.get(0)?,
+                    kind: row.get(1)?,
+                    latency_ms: row.get::<_, Option<i64>>(2)?.map(|v| v as u64),
+                    penalty_ms: row.get::<_, Option<i64>>(3)?.map(|v| v as u64),
+                    message: row.get(4)?,
+                    reason: row.get(5)?,
+                })
+            })
+            .map_err(AppError::from)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(AppError::from)?;
+
+        Ok(urls)
+    }
+}
@@ -0,0 +1,188 @@
+//! 路由事件日志 DAO
+//!
+//! 记录代理的路由决策与状态切换（熔断、冷却、疑似失效、故障转移切换、余额耗尽跳过），
+//! 用于事后查询“cc-switch 做了什么决定、为什么”。写入由 `EventRecorder` 在后台批量完成。
+
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::str::FromStr;
+
+/// 事件类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    /// 熔断器打开
+    BreakerOpen,
+    /// 熔断器进入半开（开始探测）
+    BreakerHalfOpen,
+    /// 熔断器闭合（恢复或手动重置）
+    BreakerClose,
+    /// 熔断器被手动强制打开
+    BreakerForced,
+    /// supplier 进入冷却期
+    Cooldown,
+    /// URL 被标记为疑似失效
+    Suspect,
+    /// 故障转移切换了当前供应商
+    FailoverSwitch,
+    /// 供应商余额耗尽，暂停参与选路
+    QuotaExclusion,
+    /// 所有供应商均失败
+    AllFailed,
+    /// 保存供应商后预热模型列表失败（key 或 base_url 可能有误）
+    ModelWarmupFailed,
+    /// 供应商当日流量超过告警阈值
+    BandwidthAlert,
+}
+
+impl EventKind {
+    pub const ALL: [EventKind; 11] = [
+        EventKind::BreakerOpen,
+        EventKind::BreakerHalfOpen,
+        EventKind::BreakerClose,
+        EventKind::BreakerForced,
+        EventKind::Cooldown,
+        EventKind::Suspect,
+        EventKind::FailoverSwitch,
+        EventKind::QuotaExclusion,
+        EventKind::AllFailed,
+        EventKind::ModelWarmupFailed,
+        EventKind::BandwidthAlert,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventKind::BreakerOpen => "breaker_open",
+            EventKind::BreakerHalfOpen => "breaker_half_open",
+            EventKind::BreakerClose => "breaker_close",
+            EventKind::BreakerForced => "breaker_forced",
+            EventKind::Cooldown => "cooldown",
+            EventKind::Suspect => "suspect",
+            EventKind::FailoverSwitch => "failover_switch",
+            EventKind::QuotaExclusion => "quota_exclusion",
+            EventKind::AllFailed => "all_failed",
+            EventKind::ModelWarmupFailed => "model_warmup_failed",
+            EventKind::BandwidthAlert => "bandwidth_alert",
+        }
+    }
+}
+
+impl FromStr for EventKind {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.as_str() == s)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Self::ALL.iter().map(|k| k.as_str()).collect();
+                AppError::InvalidInput(format!("无效的事件类型: {s}，支持: {}", names.join(", ")))
+            })
+    }
+}
+
+/// 一条事件日志
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventLogEntry {
+    #[serde(default)]
+    pub id: i64,
+    /// Unix 秒
+    pub created_at: i64,
+    pub app_type: String,
+    pub kind: EventKind,
+    pub provider_id: Option<String>,
+    pub supplier: Option<String>,
+    /// 事件细节（原因、时长、URL 等，按事件类型不同）
+    #[serde(default)]
+    pub detail: Value,
+}
+
+impl Database {
+    /// 批量写入事件（单个事务），返回写入条数
+    pub fn insert_events(&self, events: &[EventLogEntry]) -> Result<usize, AppError> {
+        if events.is_empty() {
+            return Ok(0);
+        }
+
+        let mut conn = lock_conn!(self.conn);
+        let tx = conn.transaction().map_err(AppError::from)?;
+        for event in events {
+            tx.execute(
+                "INSERT INTO events (created_at, app_type, kind, provider_id, supplier, detail)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    event.created_at,
+                    event.app_type,
+                    event.kind.as_str(),
+                    event.provider_id,
+                    event.supplier,
+                    event.detail.to_string(),
+                ],
+            )
+            .map_err(AppError::from)?;
+        }
+        tx.commit().map_err(AppError::from)?;
+        Ok(events.len())
+    }
+
+    /// 查询 `since`（Unix 秒）之后的事件，按时间倒序，最多 `limit` 条
+    pub fn query_events(
+        &self,
+        app_type: &str,
+        kind: Option<EventKind>,
+        since: i64,
+        limit: usize,
+    ) -> Result<Vec<EventLogEntry>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, created_at, app_type, kind, provider_id, supplier, detail
+                 FROM events
+                 WHERE app_type = ?1 AND created_at >= ?2 AND (?3 IS NULL OR kind = ?3)
+                 ORDER BY created_at DESC, id DESC
+                 LIMIT ?4",
+            )
+            .map_err(AppError::from)?;
+        let rows = stmt
+            .query_map(
+                params![app_type, since, kind.map(|k| k.as_str()), limit as i64],
+                |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, i64>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, String>(3)?,
+                        row.get::<_, Option<String>>(4)?,
+                        row.get::<_, Option<String>>(5)?,
+                        row.get::<_, String>(6)?,
+                    ))
+                },
+            )
+            .map_err(AppError::from)?;
+
+        let mut out = Vec::new();
+        for row in rows {
+            let (id, created_at, app_type, kind, provider_id, supplier, detail) =
+                row.map_err(AppError::from)?;
+            // 未知类型（新版本写入）直接跳过
+            let Ok(kind) = kind.parse::<EventKind>() else {
+                continue;
+            };
+            out.push(EventLogEntry {
+                id,
+                created_at,
+                app_type,
+                kind,
+                provider_id,
+                supplier,
+                detail: serde_json::from_str(&detail).unwrap_or(Value::Null),
+            });
+        }
+        Ok(out)
+    }
+}
@@ -2,10 +2,11 @@
 //!
 //! 管理代理模式下的故障转移队列（基于 providers 表的 in_failover_queue 字段）
 
-use crate::database::{lock_conn, Database};
+use crate::database::{lock_conn, Database, PriorityChange};
 use crate::error::AppError;
-use crate::provider::Provider;
+use crate::provider::{Provider, DEFAULT_PRIORITY};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 /// 故障转移队列条目（简化版，用于前端展示）
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,19 +17,27 @@ pub struct FailoverQueueItem {
     pub sort_index: Option<usize>,
 }
 
+/// 故障转移队列中的一个优先级层级（层级内按 ID 排序）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FailoverQueueTier {
+    pub priority: usize,
+    pub providers: Vec<FailoverQueueItem>,
+}
+
 impl Database {
     /// 获取故障转移队列（按 sort_index 排序）
     pub fn get_failover_queue(&self, app_type: &str) -> Result<Vec<FailoverQueueItem>, AppError> {
         let conn = lock_conn!(self.conn);
 
         let mut stmt = conn
-            .prepare(
+            .prepare(&format!(
                 "SELECT id, name, sort_index
                  FROM providers
                  WHERE app_type = ?1 AND in_failover_queue = 1
-                 ORDER BY COALESCE(sort_index, 999999), id ASC",
-            )
-            .map_err(|e| AppError::Database(e.to_string()))?;
+                 ORDER BY COALESCE(sort_index, {DEFAULT_PRIORITY}), id ASC"
+            ))
+            .map_err(AppError::from)?;
 
         let items = stmt
             .query_map([app_type], |row| {
@@ -38,13 +47,32 @@ impl Database {
                     sort_index: row.get(2)?,
                 })
             })
-            .map_err(|e| AppError::Database(e.to_string()))?
+            .map_err(AppError::from)?
             .collect::<Result<Vec<_>, _>>()
-            .map_err(|e| AppError::Database(e.to_string()))?;
+            .map_err(AppError::from)?;
 
         Ok(items)
     }
 
+    /// 按优先级层级分组获取故障转移队列（层级从高到低，即 sort_index 从小到大）
+    pub fn get_failover_queue_grouped(
+        &self,
+        app_type: &str,
+    ) -> Result<Vec<FailoverQueueTier>, AppError> {
+        let mut tiers: Vec<FailoverQueueTier> = Vec::new();
+        for item in self.get_failover_queue(app_type)? {
+            let priority = item.sort_index.unwrap_or(DEFAULT_PRIORITY);
+            match tiers.last_mut() {
+                Some(tier) if tier.priority == priority => tier.providers.push(item),
+                _ => tiers.push(FailoverQueueTier {
+                    priority,
+                    providers: vec![item],
+                }),
+            }
+        }
+        Ok(tiers)
+    }
+
     /// 获取故障转移队列中的供应商（完整 Provider 信息，按 sort_index 排序）
     pub fn get_failover_providers(&self, app_type: &str) -> Result<Vec<Provider>, AppError> {
         let all_providers = self.get_all_providers(app_type)?;
@@ -54,22 +82,128 @@ impl Database {
             .filter(|p| p.in_failover_queue)
             .collect();
 
-        // 按 sort_index 排序（越小优先级越高，None 排最后）
+        // 按 sort_index 排序（越小优先级越高，None 视为 DEFAULT_PRIORITY）
         result.sort_by(|a, b| {
-            let a_index = a.sort_index.unwrap_or(999999);
-            let b_index = b.sort_index.unwrap_or(999999);
-            a_index.cmp(&b_index).then_with(|| a.id.cmp(&b.id))
+            a.priority()
+                .cmp(&b.priority())
+                .then_with(|| a.id.cmp(&b.id))
         });
 
         log::debug!(
             "[{app_type}] Failover providers sorted by priority: {}",
-            result.iter().map(|p| format!("{}({})", p.name, p.sort_index.unwrap_or(999999))).collect::<Vec<_>>().join(", ")
+            result.iter().map(|p| format!("{}({})", p.name, p.priority())).collect::<Vec<_>>().join(", ")
         );
 
         Ok(result)
     }
 
-    /// 添加供应商到故障转移队列
+    /// 按给定顺序批量设置队列层级（单个事务）
+    ///
+    /// `ordered_ids` 依次设为层级 0,1,2,...；队列中未列出的供应商保持原有相对顺序
+    /// （同层级仍在同一层级）排在其后。任一 ID 不在队列中或重复时不做任何修改。
+    pub fn set_priorities_bulk(
+        &self,
+        app_type: &str,
+        ordered_ids: &[String],
+    ) -> Result<Vec<PriorityChange>, AppError> {
+        let queue = self.get_failover_providers(app_type)?;
+
+        let mut targets: HashMap<&str, usize> = HashMap::new();
+        for (level, id) in ordered_ids.iter().enumerate() {
+            if !queue.iter().any(|p| &p.id == id) {
+                return Err(AppError::InvalidInput(format!(
+                    "供应商不在故障转移队列中: {id}"
+                )));
+            }
+            if targets.insert(id.as_str(), level).is_some() {
+                return Err(AppError::InvalidInput(format!("供应商ID重复: {id}")));
+            }
+        }
+
+        // 未列出的供应商：按原层级顺序依次接在后面
+        let mut next_level = ordered_ids.len();
+        let mut last_priority: Option<usize> = None;
+        let unlisted: Vec<&Provider> = queue
+            .iter()
+            .filter(|p| !targets.contains_key(p.id.as_str()))
+            .collect();
+        for p in unlisted {
+            if last_priority.is_some_and(|l| l != p.priority()) {
+                next_level += 1;
+            }
+            last_priority = Some(p.priority());
+            targets.insert(p.id.as_str(), next_level);
+        }
+
+        let changes: Vec<PriorityChange> = queue
+            .iter()
+            .filter_map(|p| {
+                let to = targets[p.id.as_str()];
+                (p.sort_index != Some(to)).then(|| PriorityChange {
+                    id: p.id.clone(),
+                    name: p.name.clone(),
+                    from: p.sort_index,
+                    to,
+                })
+            })
+            .collect();
+
+        self.write_priorities(app_type, &changes)?;
+        Ok(changes)
+    }
+
+    /// 把单个供应商移到指定层级（只改 sort_index，不影响队列成员关系）
+    ///
+    /// 层级未变化时返回 `None`。
+    pub fn move_provider_priority(
+        &self,
+        app_type: &str,
+        provider_id: &str,
+        new_priority: usize,
+    ) -> Result<Option<PriorityChange>, AppError> {
+        let provider = self
+            .get_provider_by_id(provider_id, app_type)?
+            .ok_or_else(|| AppError::InvalidInput(format!("供应商不存在: {provider_id}")))?;
+        if provider.sort_index == Some(new_priority) {
+            return Ok(None);
+        }
+
+        let change = PriorityChange {
+            id: provider.id,
+            name: provider.name,
+            from: provider.sort_index,
+            to: new_priority,
+        };
+        self.write_priorities(app_type, std::slice::from_ref(&change))?;
+        Ok(Some(change))
+    }
+
+    /// 设置供应商是否在故障转移队列中（幂等），返回成员关系是否发生变化
+    ///
+    /// 队列成员关系是 providers 表上的布尔列，同一供应商不会出现重复的队列条目；
+    /// 重复加入或重复移除均不做修改。
+    pub fn set_queue_membership(
+        &self,
+        app_type: &str,
+        provider_id: &str,
+        in_queue: bool,
+    ) -> Result<bool, AppError> {
+        let provider = self
+            .get_provider_by_id(provider_id, app_type)?
+            .ok_or_else(|| AppError::InvalidInput(format!("供应商不存在: {provider_id}")))?;
+        if provider.in_failover_queue == in_queue {
+            return Ok(false);
+        }
+
+        if in_queue {
+            self.add_to_failover_queue(app_type, provider_id)?;
+        } else {
+            self.remove_from_failover_queue(app_type, provider_id)?;
+        }
+        Ok(true)
+    }
+
+    /// 添加供应商到故障转移队列（已在队列中时不做修改）
     pub fn add_to_failover_queue(&self, app_type: &str, provider_id: &str) -> Result<(), AppError> {
         let conn = lock_conn!(self.conn);
 
@@ -77,7 +211,7 @@ impl Database {
             "UPDATE providers SET in_failover_queue = 1 WHERE id = ?1 AND app_type = ?2",
             rusqlite::params![provider_id, app_type],
         )
-        .map_err(|e| AppError::Database(e.to_string()))?;
+        .map_err(AppError::from)?;
 
         Ok(())
     }
@@ -95,14 +229,14 @@ impl Database {
             "UPDATE providers SET in_failover_queue = 0 WHERE id = ?1 AND app_type = ?2",
             rusqlite::params![provider_id, app_type],
         )
-        .map_err(|e| AppError::Database(e.to_string()))?;
+        .map_err(AppError::from)?;
 
         // 2. 清除该供应商的健康状态（退出队列后不再需要健康监控）
         conn.execute(
             "DELETE FROM provider_health WHERE provider_id = ?1 AND app_type = ?2",
             rusqlite::params![provider_id, app_type],
         )
-        .map_err(|e| AppError::Database(e.to_string()))?;
+        .map_err(AppError::from)?;
 
         log::info!("已从故障转移队列移除供应商 {provider_id} ({app_type}), 并清除其健康状态");
 
@@ -117,7 +251,7 @@ impl Database {
             "UPDATE providers SET in_failover_queue = 0 WHERE app_type = ?1",
             [app_type],
         )
-        .map_err(|e| AppError::Database(e.to_string()))?;
+        .map_err(AppError::from)?;
 
         Ok(())
     }
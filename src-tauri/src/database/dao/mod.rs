@@ -2,17 +2,23 @@
 //!
 //! Database access operations for each domain
 
+pub mod benchmark_history;
+pub mod events;
 pub mod failover;
 pub mod mcp;
 pub mod prompts;
 pub mod providers;
 pub mod proxy;
+pub mod request_captures;
 pub mod request_logs;
 pub mod settings;
 pub mod skills;
 pub mod stream_check;
 pub mod universal_providers;
+pub mod url_priority;
 
 // 所有 DAO 方法都通过 Database impl 提供，无需单独导出
 // 导出 FailoverQueueItem 供外部使用
-pub use failover::FailoverQueueItem;
+pub use failover::{FailoverQueueItem, FailoverQueueTier};
+pub use providers::PriorityChange;
+pub use url_priority::SupplierUrlPriority;
@@ -4,10 +4,19 @@
 
 use crate::database::{lock_conn, Database};
 use crate::error::AppError;
-use crate::provider::{Provider, ProviderMeta};
+use crate::provider::{Provider, ProviderMeta, DEFAULT_PRIORITY};
 use indexmap::IndexMap;
 use rusqlite::params;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+
+/// 优先级规范化中被改写的供应商
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PriorityChange {
+    pub id: String,
+    pub name: String,
+    pub from: Option<usize>,
+    pub to: usize,
+}
 
 impl Database {
     /// 获取指定应用类型的所有供应商
@@ -16,11 +25,11 @@ impl Database {
         app_type: &str,
     ) -> Result<IndexMap<String, Provider>, AppError> {
         let conn = lock_conn!(self.conn);
-        let mut stmt = conn.prepare(
+        let mut stmt = conn.prepare(&format!(
             "SELECT id, name, settings_config, website_url, category, created_at, sort_index, notes, icon, icon_color, meta, in_failover_queue
              FROM providers WHERE app_type = ?1
-             ORDER BY COALESCE(sort_index, 999999), created_at ASC, id ASC"
-        ).map_err(|e| AppError::Database(e.to_string()))?;
+             ORDER BY COALESCE(sort_index, {DEFAULT_PRIORITY}), created_at ASC, id ASC"
+        )).map_err(AppError::from)?;
 
         let provider_iter = stmt
             .query_map(params![app_type], |row| {
@@ -59,17 +68,17 @@ impl Database {
                     },
                 ))
             })
-            .map_err(|e| AppError::Database(e.to_string()))?;
+            .map_err(AppError::from)?;
 
         let mut providers = IndexMap::new();
         for provider_res in provider_iter {
-            let (id, mut provider) = provider_res.map_err(|e| AppError::Database(e.to_string()))?;
+            let (id, mut provider) = provider_res.map_err(AppError::from)?;
             provider.id = id.clone();
 
             // 加载 endpoints
             let mut stmt_endpoints = conn.prepare(
                 "SELECT url, added_at FROM provider_endpoints WHERE provider_id = ?1 AND app_type = ?2 ORDER BY added_at ASC, url ASC"
-            ).map_err(|e| AppError::Database(e.to_string()))?;
+            ).map_err(AppError::from)?;
 
             let endpoints_iter = stmt_endpoints
                 .query_map(params![id, app_type], |row| {
@@ -84,11 +93,11 @@ impl Database {
                         },
                     ))
                 })
-                .map_err(|e| AppError::Database(e.to_string()))?;
+                .map_err(AppError::from)?;
 
             let mut custom_endpoints = HashMap::new();
             for ep_res in endpoints_iter {
-                let (url, mut ep) = ep_res.map_err(|e| AppError::Database(e.to_string()))?;
+                let (url, mut ep) = ep_res.map_err(AppError::from)?;
                 ep.url = url.clone();
                 custom_endpoints.insert(url, ep);
             }
@@ -105,19 +114,17 @@ impl Database {
 
     /// 获取当前激活的供应商 ID
     pub fn get_current_provider(&self, app_type: &str) -> Result<Option<String>, AppError> {
+        #[cfg(test)]
+        self.count_dao_read("get_current_provider");
         let conn = lock_conn!(self.conn);
         let mut stmt = conn
             .prepare("SELECT id FROM providers WHERE app_type = ?1 AND is_current = 1 LIMIT 1")
-            .map_err(|e| AppError::Database(e.to_string()))?;
+            .map_err(AppError::from)?;
 
-        let mut rows = stmt
-            .query(params![app_type])
-            .map_err(|e| AppError::Database(e.to_string()))?;
+        let mut rows = stmt.query(params![app_type]).map_err(AppError::from)?;
 
-        if let Some(row) = rows.next().map_err(|e| AppError::Database(e.to_string()))? {
-            Ok(Some(
-                row.get(0).map_err(|e| AppError::Database(e.to_string()))?,
-            ))
+        if let Some(row) = rows.next().map_err(AppError::from)? {
+            Ok(Some(row.get(0).map_err(AppError::from)?))
         } else {
             Ok(None)
         }
@@ -170,7 +177,7 @@ impl Database {
         match result {
             Ok(provider) => Ok(Some(provider)),
             Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-            Err(e) => Err(AppError::Database(e.to_string())),
+            Err(e) => Err(AppError::from(e)),
         }
     }
 
@@ -180,9 +187,7 @@ impl Database {
     /// （add_custom_endpoint / remove_custom_endpoint），避免覆盖用户的修改。
     pub fn save_provider(&self, app_type: &str, provider: &Provider) -> Result<(), AppError> {
         let mut conn = lock_conn!(self.conn);
-        let tx = conn
-            .transaction()
-            .map_err(|e| AppError::Database(e.to_string()))?;
+        let tx = conn.transaction().map_err(AppError::from)?;
 
         // 处理 meta：取出 endpoints 以便单独处理
         let mut meta_clone = provider.meta.clone().unwrap_or_default();
@@ -235,7 +240,7 @@ impl Database {
                     app_type,
                 ],
             )
-            .map_err(|e| AppError::Database(e.to_string()))?;
+            .map_err(AppError::from)?;
         } else {
             // 新增模式：使用 INSERT
             tx.execute(
@@ -260,7 +265,7 @@ impl Database {
                     in_failover_queue,
                 ],
             )
-            .map_err(|e| AppError::Database(e.to_string()))?;
+            .map_err(AppError::from)?;
 
             // 只有新增时才同步 endpoints
             for (url, endpoint) in endpoints {
@@ -269,11 +274,11 @@ impl Database {
                      VALUES (?1, ?2, ?3, ?4)",
                     params![provider.id, app_type, url, endpoint.added_at],
                 )
-                .map_err(|e| AppError::Database(e.to_string()))?;
+                .map_err(AppError::from)?;
             }
         }
 
-        tx.commit().map_err(|e| AppError::Database(e.to_string()))?;
+        tx.commit().map_err(AppError::from)?;
         Ok(())
     }
 
@@ -284,32 +289,30 @@ impl Database {
             "DELETE FROM providers WHERE id = ?1 AND app_type = ?2",
             params![id, app_type],
         )
-        .map_err(|e| AppError::Database(e.to_string()))?;
+        .map_err(AppError::from)?;
         Ok(())
     }
 
     /// 设置当前供应商
     pub fn set_current_provider(&self, app_type: &str, id: &str) -> Result<(), AppError> {
         let mut conn = lock_conn!(self.conn);
-        let tx = conn
-            .transaction()
-            .map_err(|e| AppError::Database(e.to_string()))?;
+        let tx = conn.transaction().map_err(AppError::from)?;
 
         // 重置所有为 0
         tx.execute(
             "UPDATE providers SET is_current = 0 WHERE app_type = ?1",
             params![app_type],
         )
-        .map_err(|e| AppError::Database(e.to_string()))?;
+        .map_err(AppError::from)?;
 
         // 设置新的当前供应商
         tx.execute(
             "UPDATE providers SET is_current = 1 WHERE id = ?1 AND app_type = ?2",
             params![id, app_type],
         )
-        .map_err(|e| AppError::Database(e.to_string()))?;
+        .map_err(AppError::from)?;
 
-        tx.commit().map_err(|e| AppError::Database(e.to_string()))?;
+        tx.commit().map_err(AppError::from)?;
         Ok(())
     }
 
@@ -322,7 +325,7 @@ impl Database {
             "UPDATE providers SET is_current = 0 WHERE app_type = ?1",
             params![app_type],
         )
-        .map_err(|e| AppError::Database(e.to_string()))?;
+        .map_err(AppError::from)?;
 
         Ok(())
     }
@@ -343,10 +346,164 @@ impl Database {
                 app_type
             ],
         )
-        .map_err(|e| AppError::Database(e.to_string()))?;
+        .map_err(AppError::from)?;
         Ok(())
     }
 
+    /// 设置或清除供应商的显式 supplier（写入 settingsConfig.supplier）
+    pub fn set_provider_supplier(
+        &self,
+        app_type: &str,
+        provider_id: &str,
+        supplier: Option<&str>,
+    ) -> Result<(), AppError> {
+        let mut provider = self
+            .get_provider_by_id(provider_id, app_type)?
+            .ok_or_else(|| AppError::InvalidInput(format!("供应商不存在: {provider_id}")))?;
+        provider.set_supplier(supplier);
+        self.update_provider_settings_config(app_type, provider_id, &provider.settings_config)
+    }
+
+    /// 为未设置显式 supplier 的供应商按当前名称回填，返回回填数量
+    ///
+    /// 回填后再改名不会改变分组（冷却、当前 URL、测速锁等均按 supplier 分组）。
+    pub fn backfill_provider_suppliers(&self, app_type: &str) -> Result<usize, AppError> {
+        let providers = self.get_all_providers(app_type)?;
+        let mut count = 0;
+        for (id, mut provider) in providers {
+            if provider.explicit_supplier().is_some() || !provider.settings_config.is_object() {
+                continue;
+            }
+            let supplier = Provider::supplier_from_name(&provider.name);
+            provider.set_supplier(Some(&supplier));
+            if provider.explicit_supplier().is_none() {
+                continue;
+            }
+            self.update_provider_settings_config(app_type, &id, &provider.settings_config)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// 规范化优先级层级，返回被改写的供应商
+    ///
+    /// - 未设置 sort_index 的供应商写入 [`DEFAULT_PRIORITY`]
+    /// - `compact` 时再把现有层级按相对顺序压缩为 0..n（同层级仍在同一层级）
+    pub fn normalize_priorities(
+        &self,
+        app_type: &str,
+        compact: bool,
+    ) -> Result<Vec<PriorityChange>, AppError> {
+        let providers = self.get_all_providers(app_type)?;
+
+        let tiers: BTreeMap<usize, usize> = if compact {
+            let mut levels: Vec<usize> = providers.values().map(Provider::priority).collect();
+            levels.sort_unstable();
+            levels.dedup();
+            levels
+                .into_iter()
+                .enumerate()
+                .map(|(i, p)| (p, i))
+                .collect()
+        } else {
+            BTreeMap::new()
+        };
+
+        let changes: Vec<PriorityChange> = providers
+            .values()
+            .filter_map(|p| {
+                let to = tiers.get(&p.priority()).copied().unwrap_or(p.priority());
+                (p.sort_index != Some(to)).then(|| PriorityChange {
+                    id: p.id.clone(),
+                    name: p.name.clone(),
+                    from: p.sort_index,
+                    to,
+                })
+            })
+            .collect();
+
+        self.write_priorities(app_type, &changes)?;
+        Ok(changes)
+    }
+
+    /// 在单个事务中写入一组层级调整（任一失败则全部回滚）
+    pub(crate) fn write_priorities(
+        &self,
+        app_type: &str,
+        changes: &[PriorityChange],
+    ) -> Result<(), AppError> {
+        let mut conn = lock_conn!(self.conn);
+        let tx = conn.transaction().map_err(AppError::from)?;
+        for change in changes {
+            tx.execute(
+                "UPDATE providers SET sort_index = ?1 WHERE id = ?2 AND app_type = ?3",
+                params![change.to, change.id, app_type],
+            )
+            .map_err(AppError::from)?;
+        }
+        tx.commit().map_err(AppError::from)?;
+        Ok(())
+    }
+
+    /// 设置供应商维护截止时间（Unix 秒，`None` 表示取消禁用）
+    pub fn set_provider_disabled_until(
+        &self,
+        app_type: &str,
+        provider_id: &str,
+        disabled_until: Option<i64>,
+    ) -> Result<(), AppError> {
+        let conn = lock_conn!(self.conn);
+        let updated = conn
+            .execute(
+                "UPDATE providers SET disabled_until = ?1 WHERE id = ?2 AND app_type = ?3",
+                params![disabled_until, provider_id, app_type],
+            )
+            .map_err(AppError::from)?;
+        if updated == 0 {
+            return Err(AppError::InvalidInput(format!(
+                "供应商不存在: {provider_id}"
+            )));
+        }
+        Ok(())
+    }
+
+    /// 获取 `now` 时刻仍处于维护禁用中的供应商（id -> 截止时间）
+    ///
+    /// 只读：已到期的禁用在查询中过滤掉，不做清除（见 [`Self::clear_expired_disables`]）。
+    pub fn get_disabled_providers(
+        &self,
+        app_type: &str,
+        now: i64,
+    ) -> Result<HashMap<String, i64>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, disabled_until FROM providers
+                 WHERE app_type = ?1 AND disabled_until IS NOT NULL AND disabled_until > ?2",
+            )
+            .map_err(AppError::from)?;
+        let rows = stmt
+            .query_map(params![app_type, now], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+            })
+            .map_err(AppError::from)?
+            .collect::<Result<HashMap<_, _>, _>>()
+            .map_err(AppError::from)?;
+
+        Ok(rows)
+    }
+
+    /// 清除 `now` 时刻已到期的维护禁用（所有应用），返回清除条数
+    pub fn clear_expired_disables(&self, now: i64) -> Result<usize, AppError> {
+        let conn = lock_conn!(self.conn);
+        conn.execute(
+            "UPDATE providers SET disabled_until = NULL
+             WHERE disabled_until IS NOT NULL AND disabled_until <= ?1",
+            params![now],
+        )
+        .map_err(AppError::from)
+    }
+
     /// 添加自定义端点
     pub fn add_custom_endpoint(
         &self,
@@ -359,7 +516,7 @@ impl Database {
         conn.execute(
             "INSERT INTO provider_endpoints (provider_id, app_type, url, added_at) VALUES (?1, ?2, ?3, ?4)",
             params![provider_id, app_type, url, added_at],
-        ).map_err(|e| AppError::Database(e.to_string()))?;
+        ).map_err(AppError::from)?;
         Ok(())
     }
 
@@ -375,7 +532,7 @@ impl Database {
             "DELETE FROM provider_endpoints WHERE provider_id = ?1 AND app_type = ?2 AND url = ?3",
             params![provider_id, app_type, url],
         )
-        .map_err(|e| AppError::Database(e.to_string()))?;
+        .map_err(AppError::from)?;
         Ok(())
     }
 }
@@ -3,10 +3,15 @@
 //! 处理代理配置、Provider健康状态和使用统计的数据库操作
 
 use crate::error::AppError;
+use crate::proxy::selection_policy::SelectionPolicyKind;
 use crate::proxy::types::*;
+use std::collections::HashMap;
 
 use super::super::{lock_conn, Database};
 
+/// 每个应用保留的 Live 配置快照数量上限
+pub const MAX_LIVE_SNAPSHOTS_PER_APP: i64 = 20;
+
 impl Database {
     // ==================== Global Proxy Config ====================
 
@@ -18,7 +23,7 @@ impl Database {
         let result = {
             let conn = lock_conn!(self.conn);
             conn.query_row(
-                "SELECT proxy_enabled, listen_address, listen_port, enable_logging
+                "SELECT proxy_enabled, listen_address, listen_port, enable_logging, read_only
                  FROM proxy_config WHERE app_type = 'claude'",
                 [],
                 |row| {
@@ -27,6 +32,7 @@ impl Database {
                         listen_address: row.get(1)?,
                         listen_port: row.get::<_, i32>(2)? as u16,
                         enable_logging: row.get::<_, i32>(3)? != 0,
+                        read_only: row.get::<_, i32>(4)? != 0,
                     })
                 },
             )
@@ -43,9 +49,10 @@ impl Database {
                     listen_address: "127.0.0.1".to_string(),
                     listen_port: 5000,
                     enable_logging: true,
+                    read_only: false,
                 })
             }
-            Err(e) => Err(AppError::Database(e.to_string())),
+            Err(e) => Err(AppError::from(e)),
         }
     }
 
@@ -55,22 +62,31 @@ impl Database {
         config: GlobalProxyConfig,
     ) -> Result<(), AppError> {
         let conn = lock_conn!(self.conn);
+        Self::write_global_proxy_config(&conn, &config)
+    }
 
+    /// 写入全局代理配置（供调用方在自己的事务内使用）
+    pub(crate) fn write_global_proxy_config(
+        conn: &rusqlite::Connection,
+        config: &GlobalProxyConfig,
+    ) -> Result<(), AppError> {
         conn.execute(
             "UPDATE proxy_config SET
                 proxy_enabled = ?1,
                 listen_address = ?2,
                 listen_port = ?3,
                 enable_logging = ?4,
+                read_only = ?5,
                 updated_at = datetime('now')",
             rusqlite::params![
                 if config.proxy_enabled { 1 } else { 0 },
-                config.listen_address,
+                &config.listen_address,
                 config.listen_port as i32,
                 if config.enable_logging { 1 } else { 0 },
+                if config.read_only { 1 } else { 0 },
             ],
         )
-        .map_err(|e| AppError::Database(e.to_string()))?;
+        .map_err(AppError::from)?;
 
         Ok(())
     }
@@ -80,6 +96,8 @@ impl Database {
         &self,
         app_type: &str,
     ) -> Result<AppProxyConfig, AppError> {
+        #[cfg(test)]
+        self.count_dao_read("get_proxy_config_for_app");
         // 使用 block 限制 conn 的作用域，避免跨 await 持有锁
         let app_type_owned = app_type.to_string();
         let result = {
@@ -88,7 +106,13 @@ impl Database {
                 "SELECT app_type, enabled, auto_failover_enabled,
                         max_retries, streaming_first_byte_timeout, streaming_idle_timeout, non_streaming_timeout,
                         circuit_failure_threshold, circuit_success_threshold, circuit_timeout_seconds,
-                        circuit_error_rate_threshold, circuit_min_requests
+                        circuit_error_rate_threshold, circuit_min_requests, default_model,
+                        benchmark_max_keys_per_url, depleted_recheck_secs, overload_cooldown_secs,
+                        connectivity_penalty_ms, connectivity_timeout_secs, probe_timeout_secs,
+                        circuit_half_open_max_probes, shadow_provider_id, shadow_sample_percent,
+                        probe_min_interval_ms, probe_skip_recent_success_secs, probe_model,
+                        benchmark_concurrency, keep_warm, keep_warm_interval_secs,
+                        selection_policy
                  FROM proxy_config WHERE app_type = ?1",
                 [app_type],
                 |row| {
@@ -105,6 +129,32 @@ impl Database {
                         circuit_timeout_seconds: row.get::<_, i32>(9)? as u32,
                         circuit_error_rate_threshold: row.get(10)?,
                         circuit_min_requests: row.get::<_, i32>(11)? as u32,
+                        default_model: row.get(12)?,
+                        benchmark_max_keys_per_url: row.get::<_, i32>(13)?.max(1) as u32,
+                        depleted_recheck_secs: row.get::<_, i64>(14)?.max(0) as u64,
+                        overload_cooldown_secs: row.get::<_, i64>(15)?.max(0) as u64,
+                        connectivity_penalty_ms: row.get::<_, i64>(16)?.max(0) as u64,
+                        connectivity_timeout_secs: row.get::<_, i64>(17)?.max(1) as u64,
+                        probe_timeout_secs: row.get::<_, i64>(18)?.max(1) as u64,
+                        circuit_half_open_max_probes: row.get::<_, i32>(19)?.max(1) as u32,
+                        shadow: row
+                            .get::<_, Option<String>>(20)?
+                            .filter(|id| !id.is_empty())
+                            .map(|provider_id| ShadowConfig {
+                            provider_id,
+                            sample_percent: row.get::<_, i64>(21).unwrap_or(0).clamp(0, 100) as u8,
+                        }),
+                        probe_min_interval_ms: row.get::<_, i64>(22)?.max(0) as u64,
+                        probe_skip_recent_success_secs: row.get::<_, i64>(23)?.max(0) as u64,
+                        probe_model: row.get(24)?,
+                        benchmark_concurrency: row.get::<_, i32>(25)?.max(1) as u32,
+                        keep_warm: row.get::<_, i32>(26)? != 0,
+                        keep_warm_interval_secs: row.get::<_, i64>(27)?.max(1) as u64,
+                        selection_policy: row
+                            .get::<_, String>(28)
+                            .ok()
+                            .and_then(|name| SelectionPolicyKind::from_name(&name))
+                            .unwrap_or_default(),
                     })
                 },
             )
@@ -129,9 +179,25 @@ impl Database {
                     circuit_timeout_seconds: 600,
                     circuit_error_rate_threshold: 0.5,
                     circuit_min_requests: 10,
+                    default_model: None,
+                    benchmark_max_keys_per_url: 2,
+                    depleted_recheck_secs: 6 * 3600,
+                    overload_cooldown_secs: 5,
+                    connectivity_penalty_ms: 30_000,
+                    connectivity_timeout_secs: 5,
+                    probe_timeout_secs: 10,
+                    circuit_half_open_max_probes: 1,
+                    shadow: None,
+                    probe_min_interval_ms: 500,
+                    probe_skip_recent_success_secs: 120,
+                    probe_model: None,
+                    benchmark_concurrency: 3,
+                    keep_warm: false,
+                    keep_warm_interval_secs: 55,
+                    selection_policy: SelectionPolicyKind::Default,
                 })
             }
-            Err(e) => Err(AppError::Database(e.to_string())),
+            Err(e) => Err(AppError::from(e)),
         }
     }
 
@@ -141,7 +207,14 @@ impl Database {
         config: AppProxyConfig,
     ) -> Result<(), AppError> {
         let conn = lock_conn!(self.conn);
+        Self::write_app_proxy_config(&conn, &config)
+    }
 
+    /// 写入应用级代理配置（供调用方在自己的事务内使用）
+    pub(crate) fn write_app_proxy_config(
+        conn: &rusqlite::Connection,
+        config: &AppProxyConfig,
+    ) -> Result<(), AppError> {
         conn.execute(
             "UPDATE proxy_config SET
                 enabled = ?2,
@@ -155,10 +228,27 @@ impl Database {
                 circuit_timeout_seconds = ?10,
                 circuit_error_rate_threshold = ?11,
                 circuit_min_requests = ?12,
+                default_model = ?13,
+                benchmark_max_keys_per_url = ?14,
+                depleted_recheck_secs = ?15,
+                overload_cooldown_secs = ?16,
+                connectivity_penalty_ms = ?17,
+                connectivity_timeout_secs = ?18,
+                probe_timeout_secs = ?19,
+                circuit_half_open_max_probes = ?20,
+                shadow_provider_id = ?21,
+                shadow_sample_percent = ?22,
+                probe_min_interval_ms = ?23,
+                probe_skip_recent_success_secs = ?24,
+                probe_model = ?25,
+                benchmark_concurrency = ?26,
+                keep_warm = ?27,
+                keep_warm_interval_secs = ?28,
+                selection_policy = ?29,
                 updated_at = datetime('now')
              WHERE app_type = ?1",
             rusqlite::params![
-                config.app_type,
+                &config.app_type,
                 if config.enabled { 1 } else { 0 },
                 if config.auto_failover_enabled { 1 } else { 0 },
                 config.max_retries as i32,
@@ -170,9 +260,42 @@ impl Database {
                 config.circuit_timeout_seconds as i32,
                 config.circuit_error_rate_threshold,
                 config.circuit_min_requests as i32,
+                config
+                    .default_model
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|m| !m.is_empty()),
+                config.benchmark_max_keys_per_url.max(1) as i32,
+                config.depleted_recheck_secs as i64,
+                config.overload_cooldown_secs as i64,
+                config.connectivity_penalty_ms as i64,
+                config.connectivity_timeout_secs.max(1) as i64,
+                config.probe_timeout_secs.max(1) as i64,
+                config.circuit_half_open_max_probes.max(1) as i32,
+                config
+                    .shadow
+                    .as_ref()
+                    .map(|s| s.provider_id.trim())
+                    .filter(|id| !id.is_empty()),
+                config
+                    .shadow
+                    .as_ref()
+                    .map(|s| s.sample_percent.min(100) as i32)
+                    .unwrap_or(0),
+                config.probe_min_interval_ms as i64,
+                config.probe_skip_recent_success_secs as i64,
+                config
+                    .probe_model
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|m| !m.is_empty()),
+                config.benchmark_concurrency.max(1) as i32,
+                if config.keep_warm { 1 } else { 0 },
+                config.keep_warm_interval_secs.max(1) as i64,
+                config.selection_policy.as_str(),
             ],
         )
-        .map_err(|e| AppError::Database(e.to_string()))?;
+        .map_err(AppError::from)?;
 
         Ok(())
     }
@@ -186,7 +309,7 @@ impl Database {
                 "INSERT OR IGNORE INTO proxy_config (app_type) VALUES (?1)",
                 [app_type],
             )
-            .map_err(|e| AppError::Database(e.to_string()))?;
+            .map_err(AppError::from)?;
         }
 
         Ok(())
@@ -202,7 +325,8 @@ impl Database {
             conn.query_row(
                 "SELECT listen_address, listen_port, max_retries,
                         enable_logging,
-                        streaming_first_byte_timeout, streaming_idle_timeout, non_streaming_timeout
+                        streaming_first_byte_timeout, streaming_idle_timeout, non_streaming_timeout,
+                        read_only
                  FROM proxy_config WHERE app_type = 'claude'",
                 [],
                 |row| {
@@ -216,6 +340,8 @@ impl Database {
                         streaming_first_byte_timeout: row.get::<_, i32>(4).unwrap_or(30) as u64,
                         streaming_idle_timeout: row.get::<_, i32>(5).unwrap_or(60) as u64,
                         non_streaming_timeout: row.get::<_, i32>(6).unwrap_or(300) as u64,
+                        drain_timeout: crate::proxy::inflight::DEFAULT_DRAIN_TIMEOUT_SECS,
+                        read_only: row.get::<_, i32>(7)? != 0,
                     })
                 },
             )
@@ -229,7 +355,7 @@ impl Database {
                 self.init_proxy_config_rows().await?;
                 Ok(ProxyConfig::default())
             }
-            Err(e) => Err(AppError::Database(e.to_string())),
+            Err(e) => Err(AppError::from(e)),
         }
     }
 
@@ -258,7 +384,7 @@ impl Database {
                 config.non_streaming_timeout as i32,
             ],
         )
-        .map_err(|e| AppError::Database(e.to_string()))?;
+        .map_err(AppError::from)?;
 
         Ok(())
     }
@@ -281,7 +407,7 @@ impl Database {
                 [],
                 |row| row.get(0),
             )
-            .map_err(|e| AppError::Database(e.to_string()))?;
+            .map_err(AppError::from)?;
         Ok(count > 0)
     }
 
@@ -298,7 +424,8 @@ impl Database {
 
             conn.query_row(
                 "SELECT provider_id, app_type, is_healthy, consecutive_failures,
-                        last_success_at, last_failure_at, last_error, updated_at
+                        last_success_at, last_failure_at, last_error, updated_at,
+                        depleted_at, depleted_message
                  FROM provider_health
                  WHERE provider_id = ?1 AND app_type = ?2",
                 rusqlite::params![provider_id, app_type],
@@ -312,6 +439,8 @@ impl Database {
                         last_failure_at: row.get(5)?,
                         last_error: row.get(6)?,
                         updated_at: row.get(7)?,
+                        depleted_at: row.get(8)?,
+                        depleted_message: row.get(9)?,
                     })
                 },
             )
@@ -329,9 +458,55 @@ impl Database {
                 last_failure_at: None,
                 last_error: None,
                 updated_at: chrono::Utc::now().to_rfc3339(),
+                depleted_at: None,
+                depleted_message: None,
             }),
-            Err(e) => Err(AppError::Database(e.to_string())),
+            Err(e) => Err(AppError::from(e)),
+        }
+    }
+
+    /// 获取指定应用下所有已记录的Provider健康状态（按 provider_id 索引）
+    ///
+    /// 未出现在结果中的 Provider 视为健康（与 `get_provider_health` 的缺省语义一致）
+    pub async fn get_all_provider_health(
+        &self,
+        app_type: &str,
+    ) -> Result<HashMap<String, ProviderHealth>, AppError> {
+        let conn = lock_conn!(self.conn);
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT provider_id, app_type, is_healthy, consecutive_failures,
+                        last_success_at, last_failure_at, last_error, updated_at,
+                        depleted_at, depleted_message
+                 FROM provider_health
+                 WHERE app_type = ?1",
+            )
+            .map_err(AppError::from)?;
+
+        let rows = stmt
+            .query_map([app_type], |row| {
+                Ok(ProviderHealth {
+                    provider_id: row.get(0)?,
+                    app_type: row.get(1)?,
+                    is_healthy: row.get::<_, i64>(2)? != 0,
+                    consecutive_failures: row.get::<_, i64>(3)? as u32,
+                    last_success_at: row.get(4)?,
+                    last_failure_at: row.get(5)?,
+                    last_error: row.get(6)?,
+                    updated_at: row.get(7)?,
+                    depleted_at: row.get(8)?,
+                    depleted_message: row.get(9)?,
+                })
+            })
+            .map_err(AppError::from)?;
+
+        let mut out = HashMap::new();
+        for row in rows {
+            let health = row.map_err(AppError::from)?;
+            out.insert(health.provider_id.clone(), health);
         }
+        Ok(out)
     }
 
     /// 更新Provider健康状态
@@ -390,17 +565,22 @@ impl Database {
             (None, Some(now.clone()))
         };
 
-        // UPSERT
+        // UPSERT（成功时清除余额耗尽状态，失败时保留）
         conn.execute(
             "INSERT OR REPLACE INTO provider_health
              (provider_id, app_type, is_healthy, consecutive_failures,
-              last_success_at, last_failure_at, last_error, updated_at)
+              last_success_at, last_failure_at, last_error, updated_at,
+              depleted_at, depleted_message)
              VALUES (?1, ?2, ?3, ?4,
                      COALESCE(?5, (SELECT last_success_at FROM provider_health
                                    WHERE provider_id = ?1 AND app_type = ?2)),
                      COALESCE(?6, (SELECT last_failure_at FROM provider_health
                                    WHERE provider_id = ?1 AND app_type = ?2)),
-                     ?7, ?8)",
+                     ?7, ?8,
+                     CASE WHEN ?9 THEN NULL ELSE (SELECT depleted_at FROM provider_health
+                                   WHERE provider_id = ?1 AND app_type = ?2) END,
+                     CASE WHEN ?9 THEN NULL ELSE (SELECT depleted_message FROM provider_health
+                                   WHERE provider_id = ?1 AND app_type = ?2) END)",
             rusqlite::params![
                 provider_id,
                 app_type,
@@ -410,13 +590,77 @@ impl Database {
                 last_failure_at,
                 error_msg,
                 &now,
+                success,
             ],
         )
-        .map_err(|e| AppError::Database(e.to_string()))?;
+        .map_err(AppError::from)?;
+
+        Ok(())
+    }
+
+    /// 标记Provider余额耗尽（HTTP 402 / insufficient balance）
+    ///
+    /// 在重新检查间隔内选路会跳过该 Provider；成功请求或 `reset_provider_health` 清除。
+    pub async fn mark_provider_depleted(
+        &self,
+        provider_id: &str,
+        app_type: &str,
+        message: &str,
+    ) -> Result<(), AppError> {
+        let conn = lock_conn!(self.conn);
+        let now = chrono::Utc::now().to_rfc3339();
+
+        conn.execute(
+            "INSERT INTO provider_health
+             (provider_id, app_type, is_healthy, consecutive_failures, last_failure_at,
+              last_error, updated_at, depleted_at, depleted_message)
+             VALUES (?1, ?2, 1, 0, ?3, ?4, ?3, ?3, ?4)
+             ON CONFLICT(provider_id, app_type) DO UPDATE SET
+                depleted_at = ?3, depleted_message = ?4, updated_at = ?3",
+            rusqlite::params![provider_id, app_type, &now, message],
+        )
+        .map_err(AppError::from)?;
 
         Ok(())
     }
 
+    /// 获取处于余额耗尽跳过期内的Provider（provider_id -> 重新检查时间，Unix 秒）
+    ///
+    /// 超过 `recheck_secs` 的记录不返回（重新参与选路），但保留在表中，直到请求成功或手动重置。
+    pub async fn get_depleted_providers(
+        &self,
+        app_type: &str,
+        recheck_secs: u64,
+    ) -> Result<HashMap<String, i64>, AppError> {
+        let conn = lock_conn!(self.conn);
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT provider_id, depleted_at FROM provider_health
+                 WHERE app_type = ?1 AND depleted_at IS NOT NULL",
+            )
+            .map_err(AppError::from)?;
+        let rows = stmt
+            .query_map([app_type], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(AppError::from)?;
+
+        let now = chrono::Utc::now().timestamp();
+        let mut out = HashMap::new();
+        for row in rows {
+            let (provider_id, depleted_at) = row.map_err(AppError::from)?;
+            let Ok(at) = chrono::DateTime::parse_from_rfc3339(&depleted_at) else {
+                continue;
+            };
+            let recheck_at = at.timestamp().saturating_add(recheck_secs as i64);
+            if recheck_at > now {
+                out.insert(provider_id, recheck_at);
+            }
+        }
+        Ok(out)
+    }
+
     /// 重置Provider健康状态
     pub async fn reset_provider_health(
         &self,
@@ -429,7 +673,7 @@ impl Database {
             "DELETE FROM provider_health WHERE provider_id = ?1 AND app_type = ?2",
             rusqlite::params![provider_id, app_type],
         )
-        .map_err(|e| AppError::Database(e.to_string()))?;
+        .map_err(AppError::from)?;
 
         log::debug!("Reset health status for provider {provider_id} (app: {app_type})");
 
@@ -444,7 +688,7 @@ impl Database {
             "DELETE FROM provider_health WHERE app_type = ?1",
             [app_type],
         )
-        .map_err(|e| AppError::Database(e.to_string()))?;
+        .map_err(AppError::from)?;
 
         log::debug!("Cleared provider health records for app {app_type}");
         Ok(())
@@ -455,7 +699,7 @@ impl Database {
         let conn = lock_conn!(self.conn);
 
         conn.execute("DELETE FROM provider_health", [])
-            .map_err(|e| AppError::Database(e.to_string()))?;
+            .map_err(AppError::from)?;
 
         log::debug!("Cleared all provider health records");
         Ok(())
@@ -485,6 +729,7 @@ impl Database {
                         timeout_seconds: row.get::<_, i64>(2)? as u64,
                         error_rate_threshold: row.get(3)?,
                         min_requests: row.get::<_, i32>(4)? as u32,
+                        ..Default::default()
                     })
                 },
             )
@@ -498,7 +743,7 @@ impl Database {
                 self.init_proxy_config_rows().await?;
                 Ok(crate::proxy::circuit_breaker::CircuitBreakerConfig::default())
             }
-            Err(e) => Err(AppError::Database(e.to_string())),
+            Err(e) => Err(AppError::from(e)),
         }
     }
 
@@ -529,7 +774,7 @@ impl Database {
                 config.min_requests as i32,
             ],
         )
-        .map_err(|e| AppError::Database(e.to_string()))?;
+        .map_err(AppError::from)?;
 
         Ok(())
     }
@@ -550,7 +795,7 @@ impl Database {
              VALUES (?1, ?2, ?3)",
             rusqlite::params![app_type, config_json, now],
         )
-        .map_err(|e| AppError::Database(e.to_string()))?;
+        .map_err(AppError::from)?;
 
         log::info!("已备份 {app_type} Live 配置");
         Ok(())
@@ -563,7 +808,7 @@ impl Database {
             .query_row("SELECT COUNT(*) FROM proxy_live_backup", [], |row| {
                 row.get(0)
             })
-            .map_err(|e| AppError::Database(e.to_string()))?;
+            .map_err(AppError::from)?;
         Ok(count > 0)
     }
 
@@ -586,7 +831,7 @@ impl Database {
         match result {
             Ok(backup) => Ok(Some(backup)),
             Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-            Err(e) => Err(AppError::Database(e.to_string())),
+            Err(e) => Err(AppError::from(e)),
         }
     }
 
@@ -598,7 +843,7 @@ impl Database {
             "DELETE FROM proxy_live_backup WHERE app_type = ?1",
             rusqlite::params![app_type],
         )
-        .map_err(|e| AppError::Database(e.to_string()))?;
+        .map_err(AppError::from)?;
 
         log::info!("已删除 {app_type} Live 配置备份");
         Ok(())
@@ -609,9 +854,114 @@ impl Database {
         let conn = lock_conn!(self.conn);
 
         conn.execute("DELETE FROM proxy_live_backup", [])
-            .map_err(|e| AppError::Database(e.to_string()))?;
+            .map_err(AppError::from)?;
 
         log::info!("已删除所有 Live 配置备份");
         Ok(())
     }
+
+    // ==================== Live Snapshot ====================
+
+    /// 保存 Live 配置快照，返回快照 ID
+    ///
+    /// 与最近一条快照内容相同时不重复保存（返回已有 ID）；
+    /// 每个应用只保留最近 [`MAX_LIVE_SNAPSHOTS_PER_APP`] 条。
+    pub async fn save_live_snapshot(
+        &self,
+        app_type: &str,
+        config_json: &str,
+    ) -> Result<i64, AppError> {
+        let conn = lock_conn!(self.conn);
+
+        let latest: Option<(i64, String)> = match conn.query_row(
+            "SELECT id, config FROM proxy_live_snapshot WHERE app_type = ?1
+             ORDER BY id DESC LIMIT 1",
+            rusqlite::params![app_type],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ) {
+            Ok(row) => Some(row),
+            Err(rusqlite::Error::QueryReturnedNoRows) => None,
+            Err(e) => return Err(AppError::from(e)),
+        };
+        if let Some((id, config)) = latest {
+            if config == config_json {
+                return Ok(id);
+            }
+        }
+
+        let now = chrono::Utc::now().to_rfc3339();
+        conn.execute(
+            "INSERT INTO proxy_live_snapshot (app_type, config, created_at) VALUES (?1, ?2, ?3)",
+            rusqlite::params![app_type, config_json, now],
+        )
+        .map_err(AppError::from)?;
+        let id = conn.last_insert_rowid();
+
+        conn.execute(
+            "DELETE FROM proxy_live_snapshot WHERE app_type = ?1 AND id NOT IN (
+                SELECT id FROM proxy_live_snapshot WHERE app_type = ?1 ORDER BY id DESC LIMIT ?2
+            )",
+            rusqlite::params![app_type, MAX_LIVE_SNAPSHOTS_PER_APP],
+        )
+        .map_err(AppError::from)?;
+
+        log::info!("已保存 {app_type} Live 配置快照 #{id}");
+        Ok(id)
+    }
+
+    /// 列出 Live 配置快照（最新在前）
+    pub async fn list_live_snapshots(&self, app_type: &str) -> Result<Vec<LiveSnapshot>, AppError> {
+        let conn = lock_conn!(self.conn);
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, app_type, config, created_at FROM proxy_live_snapshot
+                 WHERE app_type = ?1 ORDER BY id DESC",
+            )
+            .map_err(AppError::from)?;
+        let rows = stmt
+            .query_map(rusqlite::params![app_type], Self::live_snapshot_from_row)
+            .map_err(AppError::from)?;
+
+        rows.collect::<Result<Vec<_>, _>>().map_err(AppError::from)
+    }
+
+    /// 获取 Live 配置快照（`id` 为 None 时取最新一条）
+    pub async fn get_live_snapshot(
+        &self,
+        app_type: &str,
+        id: Option<i64>,
+    ) -> Result<Option<LiveSnapshot>, AppError> {
+        let conn = lock_conn!(self.conn);
+
+        let result = match id {
+            Some(id) => conn.query_row(
+                "SELECT id, app_type, config, created_at FROM proxy_live_snapshot
+                 WHERE app_type = ?1 AND id = ?2",
+                rusqlite::params![app_type, id],
+                Self::live_snapshot_from_row,
+            ),
+            None => conn.query_row(
+                "SELECT id, app_type, config, created_at FROM proxy_live_snapshot
+                 WHERE app_type = ?1 ORDER BY id DESC LIMIT 1",
+                rusqlite::params![app_type],
+                Self::live_snapshot_from_row,
+            ),
+        };
+
+        match result {
+            Ok(snapshot) => Ok(Some(snapshot)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(AppError::from(e)),
+        }
+    }
+
+    fn live_snapshot_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<LiveSnapshot> {
+        Ok(LiveSnapshot {
+            id: row.get(0)?,
+            app_type: row.get(1)?,
+            config: row.get(2)?,
+            created_at: row.get(3)?,
+        })
+    }
 }
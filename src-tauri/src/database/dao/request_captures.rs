@@ -0,0 +1,115 @@
+//! 请求捕获 DAO
+//!
+//! 开启 `captureBodies<应用>` 后，代理把脱敏/截断后的请求（端点、请求头、请求体）
+//! 按 request_id 写入此表，与请求日志同一 ID，供 `csc replay --from-log` 重放。
+
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// 每个应用保留的请求捕获数量上限
+pub const MAX_REQUEST_CAPTURES_PER_APP: i64 = 200;
+
+/// 一条请求捕获（已脱敏）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestCapture {
+    #[serde(default)]
+    pub request_id: String,
+    #[serde(default)]
+    pub app_type: String,
+    /// 代理收到的端点（含查询参数，已去除 key）
+    pub endpoint: String,
+    /// 白名单内的客户端请求头（不含认证信息）
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    pub body: Value,
+    /// 请求体中是否有字段被截断
+    #[serde(default)]
+    pub truncated: bool,
+    #[serde(default)]
+    pub created_at: i64,
+}
+
+impl Database {
+    /// 保存请求捕获；每个应用只保留最近 [`MAX_REQUEST_CAPTURES_PER_APP`] 条
+    pub fn save_request_capture(&self, capture: &RequestCapture) -> Result<(), AppError> {
+        let headers = serde_json::to_string(&capture.headers)
+            .map_err(|e| AppError::Database(format!("序列化请求头失败: {e}")))?;
+        let body = serde_json::to_string(&capture.body)
+            .map_err(|e| AppError::Database(format!("序列化请求体失败: {e}")))?;
+
+        let conn = lock_conn!(self.conn);
+        conn.execute(
+            "INSERT OR REPLACE INTO proxy_request_captures
+             (request_id, app_type, endpoint, headers, body, truncated, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                capture.request_id,
+                capture.app_type,
+                capture.endpoint,
+                headers,
+                body,
+                capture.truncated as i64,
+                capture.created_at,
+            ],
+        )
+        .map_err(AppError::from)?;
+
+        conn.execute(
+            "DELETE FROM proxy_request_captures WHERE app_type = ?1 AND request_id NOT IN (
+                SELECT request_id FROM proxy_request_captures WHERE app_type = ?1
+                ORDER BY created_at DESC, rowid DESC LIMIT ?2
+            )",
+            params![capture.app_type, MAX_REQUEST_CAPTURES_PER_APP],
+        )
+        .map_err(AppError::from)?;
+
+        Ok(())
+    }
+
+    /// 按请求日志 ID 读取请求捕获
+    pub fn get_request_capture(
+        &self,
+        app_type: &str,
+        request_id: &str,
+    ) -> Result<Option<RequestCapture>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let row = conn.query_row(
+            "SELECT request_id, app_type, endpoint, headers, body, truncated, created_at
+             FROM proxy_request_captures WHERE app_type = ?1 AND request_id = ?2",
+            params![app_type, request_id],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, String>(4)?,
+                    row.get::<_, i64>(5)? != 0,
+                    row.get::<_, i64>(6)?,
+                ))
+            },
+        );
+
+        let (request_id, app_type, endpoint, headers, body, truncated, created_at) = match row {
+            Ok(row) => row,
+            Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(None),
+            Err(e) => return Err(AppError::from(e)),
+        };
+
+        Ok(Some(RequestCapture {
+            request_id,
+            app_type,
+            endpoint,
+            headers: serde_json::from_str(&headers).unwrap_or_default(),
+            body: serde_json::from_str(&body)
+                .map_err(|e| AppError::Database(format!("解析捕获的请求体失败: {e}")))?,
+            truncated,
+            created_at,
+        }))
+    }
+}
@@ -4,6 +4,8 @@
 
 use crate::error::AppError;
 use rusqlite::params_from_iter;
+use serde::Serialize;
+use std::collections::HashMap;
 
 use super::super::{lock_conn, Database};
 
@@ -16,6 +18,162 @@ pub struct RecentSuccessStats {
     pub last_model: Option<String>,
 }
 
+/// 单条请求日志摘要（用于 CLI 实时面板的日志尾部）
+#[derive(Debug, Clone)]
+pub struct RequestLogLine {
+    pub created_at: i64,
+    pub app_type: String,
+    pub provider_id: String,
+    pub model: String,
+    pub status_code: u16,
+    pub latency_ms: u64,
+    pub error_message: Option<String>,
+}
+
+/// 按 app 聚合的请求计数
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AppRequestCount {
+    pub app_type: String,
+    pub total: u64,
+    pub success: u64,
+}
+
+/// 某个游标之后新增的请求日志（用于 CLI 面板增量刷新）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RequestLogDelta {
+    /// 本次读取覆盖到的最大 rowid，作为下一次查询的游标
+    pub last_id: i64,
+    /// 新增请求日志（按时间倒序，最多 `limit` 条）
+    pub logs: Vec<RequestLogLine>,
+    /// 新增请求按 app 的计数（不受 `limit` 限制）
+    pub counts: Vec<AppRequestCount>,
+}
+
+/// 请求模型 → 实际模型的使用计数（按供应商区分）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelMappingStat {
+    pub provider_id: String,
+    /// 供应商已删除时为 None
+    pub provider_name: Option<String>,
+    pub requested_model: Option<String>,
+    pub effective_model: Option<String>,
+    pub count: u64,
+    /// 其中 2xx 的请求数
+    pub success: u64,
+    pub last_seen: i64,
+}
+
+impl ModelMappingStat {
+    /// 实际模型与请求模型不同（经过映射或智能解析）
+    pub fn is_mapped(&self) -> bool {
+        match (&self.requested_model, &self.effective_model) {
+            (Some(req), Some(eff)) => !req.eq_ignore_ascii_case(eff),
+            _ => false,
+        }
+    }
+}
+
+/// 时间窗口内单个供应商的流量（请求体 / 响应体字节数）
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BandwidthStat {
+    pub provider_id: String,
+    /// 供应商已删除时为 None
+    pub provider_name: Option<String>,
+    pub requests: u64,
+    pub request_bytes: u64,
+    pub response_bytes: u64,
+}
+
+impl BandwidthStat {
+    pub fn total_bytes(&self) -> u64 {
+        self.request_bytes + self.response_bytes
+    }
+}
+
+/// 时间窗口内单个供应商的成功率（供应商列表展示与排序）
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderSuccessRate {
+    pub requests: u64,
+    /// 其中 2xx 的请求数
+    pub success: u64,
+    /// 成功占比（0~1）；窗口内无请求时为 None（显示为 "-"，而不是 100%）
+    pub success_rate: Option<f64>,
+    /// 成功请求的平均延迟（无成功请求时为 None）
+    pub avg_latency_ms: Option<u64>,
+}
+
+/// 时间窗口内的延迟分位数与错误率（跨多个 provider_id 聚合）
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyPercentiles {
+    /// 窗口内扫描到的请求数（含失败，最多 `LATENCY_STATS_MAX_ROWS` 条）
+    pub count: u64,
+    /// 非 2xx 请求占比（0~1，无请求时为 0）
+    pub error_rate: f64,
+    /// 成功请求的延迟分位数（无成功请求时为 None）
+    pub p50_ms: Option<u64>,
+    pub p95_ms: Option<u64>,
+    pub p99_ms: Option<u64>,
+}
+
+/// 影子流量对比（同一时间窗口）
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShadowComparison {
+    /// 主请求（客户端实际收到的响应，所有供应商合计）
+    pub primary: LatencyPercentiles,
+    /// 镜像到影子供应商的请求
+    pub shadow: LatencyPercentiles,
+}
+
+/// 分位数统计最多扫描的请求日志条数（取窗口内最近的记录）
+pub const LATENCY_STATS_MAX_ROWS: usize = 20_000;
+
+/// 最近秩法分位数：`sorted` 需已升序，`p` 取 0~100
+/// 汇总 `(latency_ms, status_code)` 行：非 2xx 计为错误，成功请求计算延迟分位数
+fn summarize_latency_rows(mut rows: rusqlite::Rows<'_>) -> Result<LatencyPercentiles, AppError> {
+    let mut count: u64 = 0;
+    let mut errors: u64 = 0;
+    let mut latencies: Vec<u64> = Vec::new();
+
+    while let Some(r) = rows.next().map_err(AppError::from)? {
+        let latency_ms: i64 = r.get(0).map_err(AppError::from)?;
+        let status_code: i64 = r.get(1).map_err(AppError::from)?;
+
+        count += 1;
+        if (200..300).contains(&status_code) {
+            latencies.push(latency_ms.max(0) as u64);
+        } else {
+            errors += 1;
+        }
+    }
+
+    latencies.sort_unstable();
+
+    Ok(LatencyPercentiles {
+        count,
+        error_rate: if count == 0 {
+            0.0
+        } else {
+            errors as f64 / count as f64
+        },
+        p50_ms: nearest_rank_percentile(&latencies, 50.0),
+        p95_ms: nearest_rank_percentile(&latencies, 95.0),
+        p99_ms: nearest_rank_percentile(&latencies, 99.0),
+    })
+}
+
+pub(crate) fn nearest_rank_percentile(sorted: &[u64], p: f64) -> Option<u64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
 impl Database {
     fn sanitize_gpt_model_name_for_display(id: &str) -> String {
         let trimmed = id.trim();
@@ -109,6 +267,7 @@ impl Database {
                AND provider_id IN ({placeholders})
                AND status_code >= 200 AND status_code < 300
                AND created_at >= ?
+               AND shadow = 0
              ORDER BY created_at DESC
              LIMIT ?"
         );
@@ -124,22 +283,20 @@ impl Database {
         all_params.push(rusqlite::types::Value::from(min_created_at));
         all_params.push(rusqlite::types::Value::from(max_rows as i64));
 
-        let mut stmt = conn
-            .prepare(&sql)
-            .map_err(|e| AppError::Database(e.to_string()))?;
+        let mut stmt = conn.prepare(&sql).map_err(AppError::from)?;
 
         let mut rows = stmt
             .query(params_from_iter(all_params.iter()))
-            .map_err(|e| AppError::Database(e.to_string()))?;
+            .map_err(AppError::from)?;
 
         let mut latencies: Vec<u64> = Vec::new();
         let mut last_success_at: i64 = 0;
         let mut last_model: Option<String> = None;
 
-        while let Some(r) = rows.next().map_err(|e| AppError::Database(e.to_string()))? {
-            let latency_ms: i64 = r.get(0).map_err(|e| AppError::Database(e.to_string()))?;
-            let model: String = r.get(1).map_err(|e| AppError::Database(e.to_string()))?;
-            let created_at: i64 = r.get(2).map_err(|e| AppError::Database(e.to_string()))?;
+        while let Some(r) = rows.next().map_err(AppError::from)? {
+            let latency_ms: i64 = r.get(0).map_err(AppError::from)?;
+            let model: String = r.get(1).map_err(AppError::from)?;
+            let created_at: i64 = r.get(2).map_err(AppError::from)?;
 
             if latency_ms >= 0 {
                 latencies.push(latency_ms as u64);
@@ -164,4 +321,401 @@ impl Database {
             last_model,
         }))
     }
+
+    /// 时间窗口内的延迟分位数（p50/p95/p99，仅统计 2xx）与错误率
+    ///
+    /// 按 created_at 索引倒序扫描，最多 `LATENCY_STATS_MAX_ROWS` 条。
+    pub fn get_latency_percentiles(
+        &self,
+        provider_ids: &[String],
+        app_type: &str,
+        window_secs: i64,
+    ) -> Result<LatencyPercentiles, AppError> {
+        if provider_ids.is_empty() {
+            return Ok(LatencyPercentiles::default());
+        }
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::SystemTime::UNIX_EPOCH)
+            .map_err(|e| AppError::Database(format!("读取系统时间失败: {e}")))?
+            .as_secs() as i64;
+        let min_created_at = now.saturating_sub(window_secs.max(0));
+
+        let placeholders = std::iter::repeat("?")
+            .take(provider_ids.len())
+            .collect::<Vec<_>>()
+            .join(",");
+
+        let sql = format!(
+            "SELECT latency_ms, status_code
+             FROM proxy_request_logs INDEXED BY idx_request_logs_created_at
+             WHERE created_at >= ?
+               AND app_type = ?
+               AND provider_id IN ({placeholders})
+               AND shadow = 0
+             ORDER BY created_at DESC
+             LIMIT ?"
+        );
+
+        let conn = lock_conn!(self.conn);
+
+        // params: min_created_at + app_type + provider_ids... + limit
+        let mut all_params: Vec<rusqlite::types::Value> =
+            Vec::with_capacity(provider_ids.len() + 3);
+        all_params.push(rusqlite::types::Value::from(min_created_at));
+        all_params.push(rusqlite::types::Value::from(app_type.to_string()));
+        for pid in provider_ids {
+            all_params.push(rusqlite::types::Value::from(pid.to_string()));
+        }
+        all_params.push(rusqlite::types::Value::from(LATENCY_STATS_MAX_ROWS as i64));
+
+        let mut stmt = conn.prepare(&sql).map_err(AppError::from)?;
+
+        let rows = stmt
+            .query(params_from_iter(all_params.iter()))
+            .map_err(AppError::from)?;
+        summarize_latency_rows(rows)
+    }
+
+    /// 影子流量对比：时间窗口内该应用的主请求（所有供应商）与影子供应商的镜像请求
+    ///
+    /// 两侧各按 created_at 倒序最多扫描 `LATENCY_STATS_MAX_ROWS` 条。
+    pub fn get_shadow_comparison(
+        &self,
+        app_type: &str,
+        shadow_provider_id: &str,
+        window_secs: i64,
+    ) -> Result<ShadowComparison, AppError> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::SystemTime::UNIX_EPOCH)
+            .map_err(|e| AppError::Database(format!("读取系统时间失败: {e}")))?
+            .as_secs() as i64;
+        let min_created_at = now.saturating_sub(window_secs.max(0));
+        let limit = LATENCY_STATS_MAX_ROWS as i64;
+
+        let conn = lock_conn!(self.conn);
+        let mut primary_stmt = conn
+            .prepare(
+                "SELECT latency_ms, status_code
+                 FROM proxy_request_logs INDEXED BY idx_request_logs_created_at
+                 WHERE created_at >= ?1 AND app_type = ?2 AND shadow = 0
+                 ORDER BY created_at DESC
+                 LIMIT ?3",
+            )
+            .map_err(AppError::from)?;
+        let primary = summarize_latency_rows(
+            primary_stmt
+                .query(rusqlite::params![min_created_at, app_type, limit])
+                .map_err(AppError::from)?,
+        )?;
+
+        let mut shadow_stmt = conn
+            .prepare(
+                "SELECT latency_ms, status_code
+                 FROM proxy_request_logs INDEXED BY idx_request_logs_created_at
+                 WHERE created_at >= ?1 AND app_type = ?2 AND shadow = 1 AND provider_id = ?3
+                 ORDER BY created_at DESC
+                 LIMIT ?4",
+            )
+            .map_err(AppError::from)?;
+        let shadow = summarize_latency_rows(
+            shadow_stmt
+                .query(rusqlite::params![
+                    min_created_at,
+                    app_type,
+                    shadow_provider_id,
+                    limit
+                ])
+                .map_err(AppError::from)?,
+        )?;
+
+        Ok(ShadowComparison { primary, shadow })
+    }
+
+    /// 获取最近的请求日志（按时间倒序，最多 `limit` 条）
+    pub fn get_recent_request_logs(&self, limit: usize) -> Result<Vec<RequestLogLine>, AppError> {
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+
+        let conn = lock_conn!(self.conn);
+        let mut stmt = conn
+            .prepare(
+                "SELECT created_at, app_type, provider_id, model, status_code, latency_ms, error_message
+                 FROM proxy_request_logs
+                 WHERE shadow = 0
+                 ORDER BY created_at DESC, rowid DESC
+                 LIMIT ?1",
+            )
+            .map_err(AppError::from)?;
+
+        let rows = stmt
+            .query_map([limit.min(500) as i64], |r| {
+                Ok(RequestLogLine {
+                    created_at: r.get(0)?,
+                    app_type: r.get(1)?,
+                    provider_id: r.get(2)?,
+                    model: r.get(3)?,
+                    status_code: r.get::<_, i64>(4)?.clamp(0, u16::MAX as i64) as u16,
+                    latency_ms: r.get::<_, i64>(5)?.max(0) as u64,
+                    error_message: r.get(6)?,
+                })
+            })
+            .map_err(AppError::from)?;
+
+        rows.collect::<Result<Vec<_>, _>>().map_err(AppError::from)
+    }
+
+    /// 统计 `since`（epoch 秒）之后每个（供应商, 请求模型, 实际模型）组合的请求数
+    ///
+    /// 仅包含记录了模型映射的日志（v6 之前写入的日志两列均为空，不参与统计）。
+    /// 按供应商分组，组内按请求数倒序。
+    pub fn get_model_mapping_stats(
+        &self,
+        app_type: &str,
+        since: i64,
+    ) -> Result<Vec<ModelMappingStat>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let mut stmt = conn
+            .prepare(
+                "SELECT l.provider_id, p.name, l.requested_model, l.effective_model,
+                        COUNT(*),
+                        SUM(CASE WHEN l.status_code >= 200 AND l.status_code < 300 THEN 1 ELSE 0 END),
+                        MAX(l.created_at)
+                 FROM proxy_request_logs l
+                 LEFT JOIN providers p ON p.id = l.provider_id AND p.app_type = l.app_type
+                 WHERE l.app_type = ?1 AND l.created_at >= ?2 AND l.shadow = 0
+                   AND (l.requested_model IS NOT NULL OR l.effective_model IS NOT NULL)
+                 GROUP BY l.provider_id, l.requested_model, l.effective_model
+                 ORDER BY l.provider_id, COUNT(*) DESC, l.requested_model, l.effective_model",
+            )
+            .map_err(AppError::from)?;
+
+        let rows = stmt
+            .query_map(rusqlite::params![app_type, since], |r| {
+                Ok(ModelMappingStat {
+                    provider_id: r.get(0)?,
+                    provider_name: r.get(1)?,
+                    requested_model: r.get(2)?,
+                    effective_model: r.get(3)?,
+                    count: r.get::<_, i64>(4)?.max(0) as u64,
+                    success: r.get::<_, i64>(5)?.max(0) as u64,
+                    last_seen: r.get(6)?,
+                })
+            })
+            .map_err(AppError::from)?;
+
+        rows.collect::<Result<Vec<_>, _>>().map_err(AppError::from)
+    }
+
+    /// 统计 `since`（epoch 秒）之后各供应商的请求体/响应体字节数，按总流量倒序
+    pub fn get_bandwidth_stats(
+        &self,
+        app_type: &str,
+        since: i64,
+    ) -> Result<Vec<BandwidthStat>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let mut stmt = conn
+            .prepare(
+                "SELECT l.provider_id, p.name, COUNT(*),
+                        COALESCE(SUM(l.request_bytes), 0), COALESCE(SUM(l.response_bytes), 0)
+                 FROM proxy_request_logs l
+                 LEFT JOIN providers p ON p.id = l.provider_id AND p.app_type = l.app_type
+                 WHERE l.app_type = ?1 AND l.created_at >= ?2 AND l.shadow = 0
+                 GROUP BY l.provider_id
+                 ORDER BY SUM(l.request_bytes + l.response_bytes) DESC, l.provider_id",
+            )
+            .map_err(AppError::from)?;
+
+        let rows = stmt
+            .query_map(rusqlite::params![app_type, since], |r| {
+                Ok(BandwidthStat {
+                    provider_id: r.get(0)?,
+                    provider_name: r.get(1)?,
+                    requests: r.get::<_, i64>(2)?.max(0) as u64,
+                    request_bytes: r.get::<_, i64>(3)?.max(0) as u64,
+                    response_bytes: r.get::<_, i64>(4)?.max(0) as u64,
+                })
+            })
+            .map_err(AppError::from)?;
+
+        rows.collect::<Result<Vec<_>, _>>().map_err(AppError::from)
+    }
+
+    /// 统计最近 `window_secs` 秒内各供应商的请求数、成功率与成功请求的平均延迟
+    ///
+    /// 只返回窗口内有请求的供应商；调用方对缺失的供应商按“无流量”处理
+    pub fn get_provider_success_rates(
+        &self,
+        app_type: &str,
+        window_secs: i64,
+    ) -> Result<HashMap<String, ProviderSuccessRate>, AppError> {
+        let since = chrono::Utc::now().timestamp() - window_secs.max(0);
+        let conn = lock_conn!(self.conn);
+        let mut stmt = conn
+            .prepare(
+                "SELECT provider_id,
+                        COUNT(*),
+                        SUM(CASE WHEN status_code >= 200 AND status_code < 300 THEN 1 ELSE 0 END),
+                        AVG(CASE WHEN status_code >= 200 AND status_code < 300 THEN latency_ms END)
+                 FROM proxy_request_logs
+                 WHERE app_type = ?1 AND created_at >= ?2 AND shadow = 0
+                 GROUP BY provider_id",
+            )
+            .map_err(AppError::from)?;
+
+        let rows = stmt
+            .query_map(rusqlite::params![app_type, since], |r| {
+                let requests = r.get::<_, i64>(1)?.max(0) as u64;
+                let success = r.get::<_, i64>(2)?.max(0) as u64;
+                Ok((
+                    r.get::<_, String>(0)?,
+                    ProviderSuccessRate {
+                        requests,
+                        success,
+                        success_rate: (requests > 0).then(|| success as f64 / requests as f64),
+                        avg_latency_ms: r
+                            .get::<_, Option<f64>>(3)?
+                            .map(|v| v.max(0.0).round() as u64),
+                    },
+                ))
+            })
+            .map_err(AppError::from)?;
+
+        rows.collect::<Result<HashMap<_, _>, _>>()
+            .map_err(AppError::from)
+    }
+
+    /// 单个供应商 `since`（epoch 秒）之后的总流量（请求体 + 响应体字节数）
+    pub fn get_provider_bytes_since(
+        &self,
+        app_type: &str,
+        provider_id: &str,
+        since: i64,
+    ) -> Result<u64, AppError> {
+        let conn = lock_conn!(self.conn);
+        let total: i64 = conn
+            .query_row(
+                "SELECT COALESCE(SUM(request_bytes + response_bytes), 0)
+                 FROM proxy_request_logs
+                 WHERE app_type = ?1 AND provider_id = ?2 AND created_at >= ?3 AND shadow = 0",
+                rusqlite::params![app_type, provider_id, since],
+                |r| r.get(0),
+            )
+            .map_err(AppError::from)?;
+        Ok(total.max(0) as u64)
+    }
+
+    /// 统计 `since`（epoch 秒）之后各 app 的请求数与成功数（2xx）
+    pub fn get_request_counts_by_app(&self, since: i64) -> Result<Vec<AppRequestCount>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let mut stmt = conn
+            .prepare(
+                "SELECT app_type,
+                        COUNT(*),
+                        SUM(CASE WHEN status_code >= 200 AND status_code < 300 THEN 1 ELSE 0 END)
+                 FROM proxy_request_logs
+                 WHERE created_at >= ?1 AND shadow = 0
+                 GROUP BY app_type
+                 ORDER BY app_type",
+            )
+            .map_err(AppError::from)?;
+
+        let rows = stmt
+            .query_map([since], |r| {
+                Ok(AppRequestCount {
+                    app_type: r.get(0)?,
+                    total: r.get::<_, i64>(1)?.max(0) as u64,
+                    success: r.get::<_, i64>(2)?.max(0) as u64,
+                })
+            })
+            .map_err(AppError::from)?;
+
+        rows.collect::<Result<Vec<_>, _>>().map_err(AppError::from)
+    }
+
+    /// 读取 rowid 大于 `after_id` 且 `created_at >= since` 的请求日志
+    ///
+    /// 先确定本次的上界 rowid，日志与计数都只统计 `(after_id, last_id]` 区间，
+    /// 并发写入的新行留给下一次读取，不会重复或遗漏计数。
+    /// `after_id = 0` 即为全量基线；之后只需带上返回的 `last_id` 增量查询。
+    /// 返回的 `last_id` 小于 `after_id` 说明日志已被清空重建，调用方应重新建立基线。
+    pub fn get_request_log_delta(
+        &self,
+        after_id: i64,
+        since: i64,
+        limit: usize,
+    ) -> Result<RequestLogDelta, AppError> {
+        let conn = lock_conn!(self.conn);
+        let last_id: i64 = conn
+            .query_row(
+                "SELECT COALESCE(MAX(rowid), 0) FROM proxy_request_logs",
+                [],
+                |r| r.get(0),
+            )
+            .map_err(AppError::from)?;
+        if last_id <= after_id {
+            return Ok(RequestLogDelta {
+                last_id,
+                ..Default::default()
+            });
+        }
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT created_at, app_type, provider_id, model, status_code, latency_ms, error_message
+                 FROM proxy_request_logs
+                 WHERE rowid > ?1 AND rowid <= ?2 AND created_at >= ?3 AND shadow = 0
+                 ORDER BY created_at DESC, rowid DESC
+                 LIMIT ?4",
+            )
+            .map_err(AppError::from)?;
+        let logs = stmt
+            .query_map(
+                rusqlite::params![after_id, last_id, since, limit.min(500) as i64],
+                |r| {
+                    Ok(RequestLogLine {
+                        created_at: r.get(0)?,
+                        app_type: r.get(1)?,
+                        provider_id: r.get(2)?,
+                        model: r.get(3)?,
+                        status_code: r.get::<_, i64>(4)?.clamp(0, u16::MAX as i64) as u16,
+                        latency_ms: r.get::<_, i64>(5)?.max(0) as u64,
+                        error_message: r.get(6)?,
+                    })
+                },
+            )
+            .map_err(AppError::from)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(AppError::from)?;
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT app_type,
+                        COUNT(*),
+                        SUM(CASE WHEN status_code >= 200 AND status_code < 300 THEN 1 ELSE 0 END)
+                 FROM proxy_request_logs
+                 WHERE rowid > ?1 AND rowid <= ?2 AND created_at >= ?3 AND shadow = 0
+                 GROUP BY app_type
+                 ORDER BY app_type",
+            )
+            .map_err(AppError::from)?;
+        let counts = stmt
+            .query_map(rusqlite::params![after_id, last_id, since], |r| {
+                Ok(AppRequestCount {
+                    app_type: r.get(0)?,
+                    total: r.get::<_, i64>(1)?.max(0) as u64,
+                    success: r.get::<_, i64>(2)?.max(0) as u64,
+                })
+            })
+            .map_err(AppError::from)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(AppError::from)?;
+
+        Ok(RequestLogDelta {
+            last_id,
+            logs,
+            counts,
+        })
+    }
 }
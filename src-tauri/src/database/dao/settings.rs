@@ -4,6 +4,7 @@
 
 use crate::database::{lock_conn, Database};
 use crate::error::AppError;
+use crate::proxy::update_check::UpdateCheckResult;
 use rusqlite::params;
 
 impl Database {
@@ -12,16 +13,12 @@ impl Database {
         let conn = lock_conn!(self.conn);
         let mut stmt = conn
             .prepare("SELECT value FROM settings WHERE key = ?1")
-            .map_err(|e| AppError::Database(e.to_string()))?;
+            .map_err(AppError::from)?;
 
-        let mut rows = stmt
-            .query(params![key])
-            .map_err(|e| AppError::Database(e.to_string()))?;
+        let mut rows = stmt.query(params![key]).map_err(AppError::from)?;
 
-        if let Some(row) = rows.next().map_err(|e| AppError::Database(e.to_string()))? {
-            Ok(Some(
-                row.get(0).map_err(|e| AppError::Database(e.to_string()))?,
-            ))
+        if let Some(row) = rows.next().map_err(AppError::from)? {
+            Ok(Some(row.get(0).map_err(AppError::from)?))
         } else {
             Ok(None)
         }
@@ -34,7 +31,7 @@ impl Database {
             "INSERT OR REPLACE INTO settings (key, value) VALUES (?1, ?2)",
             params![key, value],
         )
-        .map_err(|e| AppError::Database(e.to_string()))?;
+        .map_err(AppError::from)?;
         Ok(())
     }
 
@@ -58,11 +55,41 @@ impl Database {
             // 如果为 None 则删除
             let conn = lock_conn!(self.conn);
             conn.execute("DELETE FROM settings WHERE key = ?1", params![key])
-                .map_err(|e| AppError::Database(e.to_string()))?;
+                .map_err(AppError::from)?;
             Ok(())
         }
     }
 
+    // --- 新版本检测 ---
+
+    /// 是否开启每日新版本检测（默认关闭）
+    pub fn get_update_check_enabled(&self) -> Result<bool, AppError> {
+        Ok(self.get_setting("update_check_enabled")?.as_deref() == Some("true"))
+    }
+
+    /// 开启/关闭每日新版本检测
+    pub fn set_update_check_enabled(&self, enabled: bool) -> Result<(), AppError> {
+        let value = if enabled { "true" } else { "false" };
+        self.set_setting("update_check_enabled", value)
+    }
+
+    /// 获取最近一次新版本检测结果
+    pub fn get_update_check_result(&self) -> Result<Option<UpdateCheckResult>, AppError> {
+        match self.get_setting("update_check_result")? {
+            Some(json) => serde_json::from_str(&json)
+                .map(Some)
+                .map_err(|e| AppError::Message(format!("解析检测结果失败: {e}"))),
+            None => Ok(None),
+        }
+    }
+
+    /// 保存新版本检测结果
+    pub fn save_update_check_result(&self, result: &UpdateCheckResult) -> Result<(), AppError> {
+        let json = serde_json::to_string(result)
+            .map_err(|e| AppError::Message(format!("序列化检测结果失败: {e}")))?;
+        self.set_setting("update_check_result", &json)
+    }
+
     // --- 代理接管状态管理（已废弃，使用 proxy_config.enabled 替代）---
 
     /// 获取指定应用的代理接管状态
@@ -107,7 +134,7 @@ impl Database {
                 [],
                 |row| row.get(0),
             )
-            .map_err(|e| AppError::Database(e.to_string()))?;
+            .map_err(AppError::from)?;
         Ok(count > 0)
     }
 
@@ -124,7 +151,7 @@ impl Database {
             "UPDATE settings SET value = 'false' WHERE key LIKE 'proxy_takeover_%'",
             [],
         )
-        .map_err(|e| AppError::Database(e.to_string()))?;
+        .map_err(AppError::from)?;
         log::info!("已清除所有代理接管状态");
         Ok(())
     }
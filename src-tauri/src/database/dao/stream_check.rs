@@ -34,7 +34,7 @@ impl Database {
                 result.tested_at,
             ],
         )
-        .map_err(|e| AppError::Database(e.to_string()))?;
+        .map_err(AppError::from)?;
 
         Ok(conn.last_insert_rowid())
     }
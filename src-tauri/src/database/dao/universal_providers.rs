@@ -17,7 +17,7 @@ impl Database {
         
         let mut stmt = conn
             .prepare("SELECT value FROM settings WHERE key = ?")
-            .map_err(|e| AppError::Database(e.to_string()))?;
+            .map_err(AppError::from)?;
         
         let result: Option<String> = stmt
             .query_row([UNIVERSAL_PROVIDERS_KEY], |row| row.get(0))
@@ -67,7 +67,7 @@ impl Database {
             "INSERT OR REPLACE INTO settings (key, value) VALUES (?, ?)",
             [UNIVERSAL_PROVIDERS_KEY, &json],
         )
-        .map_err(|e| AppError::Database(e.to_string()))?;
+        .map_err(AppError::from)?;
         
         Ok(())
     }
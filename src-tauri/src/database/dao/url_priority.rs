@@ -0,0 +1,119 @@
+//! 供应商 URL 优先级 DAO
+//!
+//! 按 supplier 名称（不区分大小写）保存首选 URL 列表，测速选 URL 时优先命中。
+//! 单个供应商的 `baseUrlPriority` / `BASE_URL_PRIORITY` 配置仍然叠加在其之上。
+
+use crate::database::{lock_conn, to_json_string, Database};
+use crate::error::AppError;
+use crate::proxy::provider_router::ProviderRouter;
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+/// 供应商 URL 优先级条目
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SupplierUrlPriority {
+    pub supplier: String,
+    pub urls: Vec<String>,
+}
+
+/// supplier 统一按小写存储
+fn normalize_supplier(supplier: &str) -> String {
+    supplier.trim().to_lowercase()
+}
+
+impl Database {
+    /// 获取指定 supplier 的首选 URL 列表（未配置时为空）
+    pub fn get_supplier_url_priority(&self, supplier: &str) -> Result<Vec<String>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let raw: Option<String> = conn
+            .query_row(
+                "SELECT urls FROM supplier_url_priority WHERE supplier = ?1",
+                params![normalize_supplier(supplier)],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(AppError::from)?;
+
+        match raw {
+            Some(raw) => serde_json::from_str(&raw)
+                .map_err(|e| AppError::Database(format!("解析 URL 优先级失败: {e}"))),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// 获取全部 supplier 的 URL 优先级（按 supplier 排序）
+    pub fn list_supplier_url_priorities(&self) -> Result<Vec<SupplierUrlPriority>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let mut stmt = conn
+            .prepare("SELECT supplier, urls FROM supplier_url_priority ORDER BY supplier ASC")
+            .map_err(AppError::from)?;
+
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(AppError::from)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(AppError::from)?;
+
+        rows.into_iter()
+            .map(|(supplier, raw)| {
+                let urls = serde_json::from_str(&raw)
+                    .map_err(|e| AppError::Database(format!("解析 URL 优先级失败: {e}")))?;
+                Ok(SupplierUrlPriority { supplier, urls })
+            })
+            .collect()
+    }
+
+    /// 设置指定 supplier 的首选 URL 列表（空列表表示删除）
+    pub fn set_supplier_url_priority(
+        &self,
+        supplier: &str,
+        urls: &[String],
+    ) -> Result<(), AppError> {
+        let conn = lock_conn!(self.conn);
+        Self::write_supplier_url_priority(&conn, supplier, urls)
+    }
+
+    /// 写入首选 URL 列表（供调用方在自己的事务内使用）
+    pub(crate) fn write_supplier_url_priority(
+        conn: &rusqlite::Connection,
+        supplier: &str,
+        urls: &[String],
+    ) -> Result<(), AppError> {
+        let supplier = normalize_supplier(supplier);
+        if supplier.is_empty() {
+            return Err(AppError::InvalidInput("supplier 不能为空".to_string()));
+        }
+
+        let mut cleaned: Vec<String> = Vec::new();
+        for url in urls {
+            let url = ProviderRouter::normalize_base_url(url);
+            if !url.is_empty() && !cleaned.contains(&url) {
+                cleaned.push(url);
+            }
+        }
+
+        if cleaned.is_empty() {
+            conn.execute(
+                "DELETE FROM supplier_url_priority WHERE supplier = ?1",
+                params![supplier],
+            )
+            .map_err(AppError::from)?;
+            return Ok(());
+        }
+
+        conn.execute(
+            "INSERT OR REPLACE INTO supplier_url_priority (supplier, urls, updated_at)
+             VALUES (?1, ?2, ?3)",
+            params![
+                supplier,
+                to_json_string(&cleaned)?,
+                chrono::Utc::now().timestamp()
+            ],
+        )
+        .map_err(AppError::from)?;
+        Ok(())
+    }
+}
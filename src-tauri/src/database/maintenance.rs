@@ -0,0 +1,175 @@
+//! 数据库维护：完整性检查、请求日志 / 测速历史 / 事件日志清理、压缩
+//!
+//! 压缩使用 `VACUUM INTO` 先写出临时文件再替换主库：期间主库只持有读锁，
+//! 失败时主库保持原样。替换文件会丢失其他进程在压缩期间写入的数据，
+//! 因此调用方需确保代理服务未运行（CLI 会检查并拒绝）。
+
+use super::{lock_conn, Database};
+use crate::error::AppError;
+use rusqlite::Connection;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 维护结果
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MaintenanceReport {
+    /// `PRAGMA integrity_check` 报告的问题（为空表示通过）
+    pub integrity_errors: Vec<String>,
+    /// 清理的请求日志条数
+    pub pruned_request_logs: usize,
+    /// 清理的测速历史条数（supplier 级记录）
+    pub pruned_benchmark_history: usize,
+    /// 清理的事件日志条数
+    pub pruned_events: usize,
+    /// 维护前数据库文件大小（字节，含 WAL）
+    pub size_before: u64,
+    /// 维护后数据库文件大小（字节，含 WAL）
+    pub size_after: u64,
+}
+
+impl Database {
+    /// 执行 `PRAGMA integrity_check`，返回发现的问题（通过时为空）
+    pub fn integrity_check(&self) -> Result<Vec<String>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let mut stmt = conn
+            .prepare("PRAGMA integrity_check")
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        let rows = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(rows.into_iter().filter(|r| r != "ok").collect())
+    }
+
+    /// 删除早于 `retention_days` 天的请求日志，返回删除条数
+    pub fn prune_request_logs(&self, retention_days: u32) -> Result<usize, AppError> {
+        let cutoff = Self::retention_cutoff(retention_days)?;
+
+        let conn = lock_conn!(self.conn);
+        conn.execute(
+            "DELETE FROM proxy_request_logs WHERE created_at < ?1",
+            [cutoff],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    /// 删除早于 `retention_days` 天的测速历史（含 URL 明细），返回删除的 supplier 级记录数
+    pub fn prune_benchmark_history(&self, retention_days: u32) -> Result<usize, AppError> {
+        let cutoff = Self::retention_cutoff(retention_days)?;
+
+        let conn = lock_conn!(self.conn);
+        conn.execute(
+            "DELETE FROM benchmark_history_urls WHERE history_id IN
+             (SELECT id FROM benchmark_history WHERE created_at < ?1)",
+            [cutoff],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+        conn.execute(
+            "DELETE FROM benchmark_history WHERE created_at < ?1",
+            [cutoff],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    /// 删除早于 `retention_days` 天的事件日志（与请求日志共用保留天数），返回删除条数
+    pub fn prune_events(&self, retention_days: u32) -> Result<usize, AppError> {
+        let cutoff = Self::retention_cutoff(retention_days)?;
+
+        let conn = lock_conn!(self.conn);
+        conn.execute("DELETE FROM events WHERE created_at < ?1", [cutoff])
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    fn retention_cutoff(retention_days: u32) -> Result<i64, AppError> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::SystemTime::UNIX_EPOCH)
+            .map_err(|e| AppError::Database(format!("读取系统时间失败: {e}")))?
+            .as_secs() as i64;
+        Ok(now.saturating_sub(i64::from(retention_days) * 86_400))
+    }
+
+    /// 完整性检查 → 清理过期请求日志、测速历史与事件日志 → 压缩
+    ///
+    /// 完整性检查未通过时不做任何修改，直接返回报告。
+    pub fn maintain(
+        &self,
+        db_path: &Path,
+        retention_days: u32,
+    ) -> Result<MaintenanceReport, AppError> {
+        let mut report = MaintenanceReport {
+            size_before: Self::file_size_with_wal(db_path),
+            ..Default::default()
+        };
+
+        report.integrity_errors = self.integrity_check()?;
+        if !report.integrity_errors.is_empty() {
+            report.size_after = report.size_before;
+            return Ok(report);
+        }
+
+        report.pruned_request_logs = self.prune_request_logs(retention_days)?;
+        report.pruned_benchmark_history = self.prune_benchmark_history(retention_days)?;
+        report.pruned_events = self.prune_events(retention_days)?;
+        self.compact_in_place(db_path)?;
+        report.size_after = Self::file_size_with_wal(db_path);
+
+        Ok(report)
+    }
+
+    /// `VACUUM INTO` 临时文件后替换主库，并重新打开连接
+    fn compact_in_place(&self, db_path: &Path) -> Result<(), AppError> {
+        // 与主库同目录，保证 rename 是原子操作
+        let tmp_path = Self::sidecar_path(db_path, "compact");
+        let _ = fs::remove_file(&tmp_path);
+
+        let mut conn = lock_conn!(self.conn);
+        conn.execute("VACUUM INTO ?1", [tmp_path.to_string_lossy().to_string()])
+            .map_err(|e| {
+                let _ = fs::remove_file(&tmp_path);
+                AppError::Database(format!("压缩数据库失败: {e}"))
+            })?;
+
+        // 关闭旧连接后再替换文件，避免连接继续指向旧文件
+        let placeholder =
+            Connection::open_in_memory().map_err(|e| AppError::Database(e.to_string()))?;
+        let old = std::mem::replace(&mut *conn, placeholder);
+        if let Err((_, e)) = old.close() {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(AppError::Database(format!("关闭数据库连接失败: {e}")));
+        }
+
+        let swapped = fs::rename(&tmp_path, db_path).map_err(|e| AppError::io(db_path, e));
+        if swapped.is_ok() {
+            // 旧库的 WAL/SHM 不再适用于新文件
+            for suffix in ["wal", "shm"] {
+                let _ = fs::remove_file(Self::sidecar_path(db_path, suffix));
+            }
+        } else {
+            let _ = fs::remove_file(&tmp_path);
+        }
+
+        // 无论替换是否成功都要恢复连接（与 Database::open 使用相同的 pragma）
+        *conn = Self::open_file_connection(db_path)?;
+
+        swapped
+    }
+
+    /// `cc-switch.db` → `cc-switch.db-<suffix>`
+    fn sidecar_path(db_path: &Path, suffix: &str) -> PathBuf {
+        let mut name = db_path.as_os_str().to_os_string();
+        name.push(format!("-{suffix}"));
+        PathBuf::from(name)
+    }
+
+    fn file_size_with_wal(db_path: &Path) -> u64 {
+        [db_path.to_path_buf(), Self::sidecar_path(db_path, "wal")]
+            .iter()
+            .filter_map(|p| fs::metadata(p).ok())
+            .map(|m| m.len())
+            .sum()
+    }
+}
@@ -12,8 +12,12 @@
 //! ```text
 //! database/
 //! ├── mod.rs        - Database 结构体 + 初始化
+//! ├── audit.rs      - 供应商配置审计（重复 key、缺失字段、跨家族映射）
 //! ├── schema.rs     - 表结构定义 + Schema 迁移
 //! ├── backup.rs     - SQL 导入导出 + 快照备份
+//! ├── config_bundle.rs - 代理/应用配置整体导入导出（JSON）
+//! ├── consistency.rs - 当前供应商/故障转移队列/代理配置一致性检查
+//! ├── maintenance.rs - 完整性检查 + 日志清理 + 压缩
 //! ├── migration.rs  - JSON → SQLite 数据迁移
 //! └── dao/          - 数据访问对象
 //!     ├── providers.rs
@@ -23,23 +27,51 @@
 //!     └── settings.rs
 //! ```
 
+mod audit;
 mod backup;
+mod config_bundle;
+mod consistency;
 mod dao;
+mod maintenance;
 mod migration;
 mod schema;
 
 #[cfg(test)]
 mod tests;
 
+// 配置包类型导出（CLI 导入导出使用）
+pub use config_bundle::{
+    AppConfigBundle, ConfigBundle, ConfigImportReport, FailoverQueueEntry, CONFIG_BUNDLE_VERSION,
+};
+
+pub use audit::{DuplicateKeyGroup, KeyHolder, ProviderAuditReport, ProviderIssue};
+pub use consistency::{
+    ConsistencyFinding, ConsistencyKind, ConsistencyReport, ConsistencySeverity,
+};
+pub use maintenance::MaintenanceReport;
+
 // DAO 类型导出供外部使用
-pub use dao::FailoverQueueItem;
-pub use dao::request_logs::RecentSuccessStats;
+pub use dao::benchmark_history::{
+    BenchmarkHistoryEntry, BenchmarkSeriesPoint, BenchmarkSupplierDiff, BenchmarkUrlChange,
+    BenchmarkUrlDiff,
+};
+pub use dao::events::{EventKind, EventLogEntry};
+pub use dao::{FailoverQueueItem, FailoverQueueTier};
+pub use dao::PriorityChange;
+pub use dao::SupplierUrlPriority;
+pub use dao::request_captures::RequestCapture;
+pub use dao::request_logs::{
+    AppRequestCount, BandwidthStat, LatencyPercentiles, ModelMappingStat, ProviderSuccessRate,
+    RecentSuccessStats, RequestLogDelta, RequestLogLine, ShadowComparison,
+};
 
 use crate::config::get_app_config_dir;
 use crate::error::AppError;
 use rusqlite::Connection;
 use serde::Serialize;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
+use std::time::Duration;
 
 // DAO 方法通过 impl Database 提供，无需额外导出
 
@@ -48,7 +80,7 @@ const DB_BACKUP_RETAIN: usize = 10;
 
 /// 当前 Schema 版本号
 /// 每次修改表结构时递增，并在 schema.rs 中添加相应的迁移逻辑
-pub(crate) const SCHEMA_VERSION: i32 = 2;
+pub(crate) const SCHEMA_VERSION: i32 = 26;
 
 /// 安全地序列化 JSON，避免 unwrap panic
 pub(crate) fn to_json_string<T: Serialize>(value: &T) -> Result<String, AppError> {
@@ -68,34 +100,48 @@ macro_rules! lock_conn {
 // 导出宏供子模块使用
 pub(crate) use lock_conn;
 
+/// 数据库被其它进程锁定时的最长等待时间（期间按退避间隔重试）
+const BUSY_TIMEOUT: Duration = Duration::from_secs(3);
+
 /// 数据库连接封装
 ///
 /// 使用 Mutex 包装 Connection 以支持在多线程环境（如 Tauri State）中共享。
 /// rusqlite::Connection 本身不是 Sync 的，因此需要这层包装。
 pub struct Database {
     pub(crate) conn: Mutex<Connection>,
+    /// 测试用：按 DAO 方法统计读取次数（断言单请求内的读库次数）
+    #[cfg(test)]
+    dao_reads: Mutex<std::collections::HashMap<&'static str, usize>>,
 }
 
 impl Database {
+    /// 数据库文件路径（`~/.cc-switch/cc-switch.db`）
+    pub fn file_path() -> PathBuf {
+        get_app_config_dir().join("cc-switch.db")
+    }
+
     /// 初始化数据库连接并创建表
     ///
     /// 数据库文件位于 `~/.cc-switch/cc-switch.db`
     pub fn init() -> Result<Self, AppError> {
-        let db_path = get_app_config_dir().join("cc-switch.db");
+        let db_path = Self::file_path();
 
         // 确保父目录存在
         if let Some(parent) = db_path.parent() {
             std::fs::create_dir_all(parent).map_err(|e| AppError::io(parent, e))?;
         }
 
-        let conn = Connection::open(&db_path).map_err(|e| AppError::Database(e.to_string()))?;
+        Self::open(&db_path)
+    }
 
-        // 启用外键约束
-        conn.execute("PRAGMA foreign_keys = ON;", [])
-            .map_err(|e| AppError::Database(e.to_string()))?;
+    /// 打开指定路径的数据库文件并创建表、执行迁移
+    pub fn open(db_path: &Path) -> Result<Self, AppError> {
+        let conn = Self::open_file_connection(db_path)?;
 
         let db = Self {
             conn: Mutex::new(conn),
+            #[cfg(test)]
+            dao_reads: Mutex::default(),
         };
         db.create_tables()?;
         db.apply_schema_migrations()?;
@@ -104,6 +150,53 @@ impl Database {
         Ok(db)
     }
 
+    /// 打开读写连接并应用连接级 pragma（压缩后重新打开连接时复用）
+    pub(crate) fn open_file_connection(db_path: &Path) -> Result<Connection, AppError> {
+        let conn = Connection::open(db_path).map_err(|e| AppError::Database(e.to_string()))?;
+
+        // 启用外键约束
+        conn.execute("PRAGMA foreign_keys = ON;", [])
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        // 其它进程持有锁时由 SQLite 按退避间隔重试，超时后才返回 SQLITE_BUSY
+        conn.busy_timeout(BUSY_TIMEOUT)
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(conn)
+    }
+
+    /// 以只读方式打开数据库文件（供 list/current/stats 等只读命令使用）
+    ///
+    /// 不建表、不迁移、不写入种子数据，连接本身只读；数据库不存在或版本与当前应用不一致时直接报错，
+    /// 由调用方回退到 [`Database::init`]。
+    pub fn open_readonly(db_path: &Path) -> Result<Self, AppError> {
+        use rusqlite::OpenFlags;
+
+        let conn = Connection::open_with_flags(
+            db_path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY
+                | OpenFlags::SQLITE_OPEN_URI
+                | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .map_err(AppError::from)?;
+        conn.busy_timeout(BUSY_TIMEOUT).map_err(AppError::from)?;
+
+        // 只读打开不读文件头，文件损坏/被锁定在首次查询时才暴露，需保留为“数据库不可用”
+        let version: i32 = conn
+            .query_row("PRAGMA user_version;", [], |row| row.get(0))
+            .map_err(AppError::from)?;
+        if version != SCHEMA_VERSION {
+            return Err(AppError::Database(format!(
+                "数据库版本（{version}）与当前应用（{SCHEMA_VERSION}）不一致，需要完整初始化"
+            )));
+        }
+
+        Ok(Self {
+            conn: Mutex::new(conn),
+            #[cfg(test)]
+            dao_reads: Mutex::default(),
+        })
+    }
+
     /// 创建内存数据库（用于测试）
     pub fn memory() -> Result<Self, AppError> {
         let conn = Connection::open_in_memory().map_err(|e| AppError::Database(e.to_string()))?;
@@ -114,6 +207,8 @@ impl Database {
 
         let db = Self {
             conn: Mutex::new(conn),
+            #[cfg(test)]
+            dao_reads: Mutex::default(),
         };
         db.create_tables()?;
         db.ensure_model_pricing_seeded()?;
@@ -121,6 +216,30 @@ impl Database {
         Ok(db)
     }
 
+    /// 测试用：缩短锁等待时间，模拟长时间锁定时不必等满 [`BUSY_TIMEOUT`]
+    #[cfg(test)]
+    pub(crate) fn set_busy_timeout(&self, timeout: Duration) {
+        if let Ok(conn) = self.conn.lock() {
+            let _ = conn.busy_timeout(timeout);
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn count_dao_read(&self, method: &'static str) {
+        if let Ok(mut reads) = self.dao_reads.lock() {
+            *reads.entry(method).or_default() += 1;
+        }
+    }
+
+    /// 测试用：某个 DAO 方法至今的读取次数
+    #[cfg(test)]
+    pub(crate) fn dao_reads(&self, method: &str) -> usize {
+        self.dao_reads
+            .lock()
+            .map(|reads| reads.get(method).copied().unwrap_or(0))
+            .unwrap_or(0)
+    }
+
     /// 检查 MCP 服务器表是否为空
     pub fn is_mcp_table_empty(&self) -> Result<bool, AppError> {
         let conn = lock_conn!(self.conn);
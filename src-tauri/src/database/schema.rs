@@ -6,6 +6,9 @@ use super::{lock_conn, Database, SCHEMA_VERSION};
 use crate::error::AppError;
 use rusqlite::Connection;
 
+/// v26 之前未设置 sort_index 的供应商的有效层级（排在所有层级之后）
+const LEGACY_UNSET_PRIORITY: i64 = 999999;
+
 impl Database {
     /// 创建所有数据库表
     pub(crate) fn create_tables(&self) -> Result<(), AppError> {
@@ -32,6 +35,7 @@ impl Database {
                 meta TEXT NOT NULL DEFAULT '{}',
                 is_current BOOLEAN NOT NULL DEFAULT 0,
                 in_failover_queue BOOLEAN NOT NULL DEFAULT 0,
+                disabled_until INTEGER,
                 PRIMARY KEY (id, app_type)
             )",
             [],
@@ -108,7 +112,23 @@ impl Database {
             streaming_idle_timeout INTEGER NOT NULL DEFAULT 60, non_streaming_timeout INTEGER NOT NULL DEFAULT 300,
             circuit_failure_threshold INTEGER NOT NULL DEFAULT 5, circuit_success_threshold INTEGER NOT NULL DEFAULT 2,
             circuit_timeout_seconds INTEGER NOT NULL DEFAULT 600, circuit_error_rate_threshold REAL NOT NULL DEFAULT 0.5,
-            circuit_min_requests INTEGER NOT NULL DEFAULT 10,
+            circuit_min_requests INTEGER NOT NULL DEFAULT 10, default_model TEXT,
+            benchmark_max_keys_per_url INTEGER NOT NULL DEFAULT 2,
+            depleted_recheck_secs INTEGER NOT NULL DEFAULT 21600,
+            overload_cooldown_secs INTEGER NOT NULL DEFAULT 5,
+            connectivity_penalty_ms INTEGER NOT NULL DEFAULT 30000,
+            connectivity_timeout_secs INTEGER NOT NULL DEFAULT 5,
+            probe_timeout_secs INTEGER NOT NULL DEFAULT 10,
+            circuit_half_open_max_probes INTEGER NOT NULL DEFAULT 1,
+            shadow_provider_id TEXT, shadow_sample_percent INTEGER NOT NULL DEFAULT 0,
+            probe_min_interval_ms INTEGER NOT NULL DEFAULT 500,
+            probe_skip_recent_success_secs INTEGER NOT NULL DEFAULT 120,
+            probe_model TEXT,
+            benchmark_concurrency INTEGER NOT NULL DEFAULT 3,
+            keep_warm INTEGER NOT NULL DEFAULT 0,
+            keep_warm_interval_secs INTEGER NOT NULL DEFAULT 55,
+            selection_policy TEXT NOT NULL DEFAULT 'default',
+            read_only INTEGER NOT NULL DEFAULT 0,
             created_at TEXT NOT NULL DEFAULT (datetime('now')), updated_at TEXT NOT NULL DEFAULT (datetime('now'))
         )", []).map_err(|e| AppError::Database(e.to_string()))?;
 
@@ -151,7 +171,7 @@ impl Database {
         conn.execute("CREATE TABLE IF NOT EXISTS provider_health (
             provider_id TEXT NOT NULL, app_type TEXT NOT NULL, is_healthy INTEGER NOT NULL DEFAULT 1,
             consecutive_failures INTEGER NOT NULL DEFAULT 0, last_success_at TEXT, last_failure_at TEXT,
-            last_error TEXT, updated_at TEXT NOT NULL,
+            last_error TEXT, updated_at TEXT NOT NULL, depleted_at TEXT, depleted_message TEXT,
             PRIMARY KEY (provider_id, app_type),
             FOREIGN KEY (provider_id, app_type) REFERENCES providers(id, app_type) ON DELETE CASCADE
         )", []).map_err(|e| AppError::Database(e.to_string()))?;
@@ -166,7 +186,10 @@ impl Database {
             total_cost_usd TEXT NOT NULL DEFAULT '0', latency_ms INTEGER NOT NULL, first_token_ms INTEGER,
             duration_ms INTEGER, status_code INTEGER NOT NULL, error_message TEXT, session_id TEXT,
             provider_type TEXT, is_streaming INTEGER NOT NULL DEFAULT 0,
-            cost_multiplier TEXT NOT NULL DEFAULT '1.0', created_at INTEGER NOT NULL
+            cost_multiplier TEXT NOT NULL DEFAULT '1.0', created_at INTEGER NOT NULL,
+            requested_model TEXT, effective_model TEXT, shadow INTEGER NOT NULL DEFAULT 0,
+            upstream_ms INTEGER, request_bytes INTEGER NOT NULL DEFAULT 0,
+            response_bytes INTEGER NOT NULL DEFAULT 0
         )", []).map_err(|e| AppError::Database(e.to_string()))?;
 
         conn.execute("CREATE INDEX IF NOT EXISTS idx_request_logs_provider ON proxy_request_logs(provider_id, app_type)", [])
@@ -227,6 +250,21 @@ impl Database {
         )
         .map_err(|e| AppError::Database(e.to_string()))?;
 
+        // 17. Supplier URL Priority 表（supplier 首选 URL 列表）
+        Self::create_supplier_url_priority_table(conn)?;
+
+        // 18. Benchmark History 表（测速结果历史）
+        Self::create_benchmark_history_tables(conn)?;
+
+        // 19. Proxy Live Snapshot 表（每次接管写入前的 Live 配置快照）
+        Self::create_live_snapshot_table(conn)?;
+
+        // 20. Proxy Request Captures 表（脱敏后的请求体，供 `csc replay` 重放）
+        Self::create_request_capture_table(conn)?;
+
+        // 21. Events 表（路由决策与状态切换事件日志）
+        Self::create_events_table(conn)?;
+
         // 尝试添加 live_takeover_active 列到 proxy_config 表
         let _ = conn.execute(
             "ALTER TABLE proxy_config ADD COLUMN live_takeover_active INTEGER NOT NULL DEFAULT 0",
@@ -305,6 +343,126 @@ impl Database {
                         Self::migrate_v1_to_v2(conn)?;
                         Self::set_user_version(conn, 2)?;
                     }
+                    2 => {
+                        log::info!("迁移数据库从 v2 到 v3（添加 supplier URL 优先级表）");
+                        Self::migrate_v2_to_v3(conn)?;
+                        Self::set_user_version(conn, 3)?;
+                    }
+                    3 => {
+                        log::info!("迁移数据库从 v3 到 v4（添加供应商维护禁用字段）");
+                        Self::migrate_v3_to_v4(conn)?;
+                        Self::set_user_version(conn, 4)?;
+                    }
+                    4 => {
+                        log::info!("迁移数据库从 v4 到 v5（添加测速历史表）");
+                        Self::create_benchmark_history_tables(conn)?;
+                        Self::set_user_version(conn, 5)?;
+                    }
+                    5 => {
+                        log::info!("迁移数据库从 v5 到 v6（请求日志记录请求模型与实际模型）");
+                        Self::migrate_v5_to_v6(conn)?;
+                        Self::set_user_version(conn, 6)?;
+                    }
+                    6 => {
+                        log::info!("迁移数据库从 v6 到 v7（添加 Live 配置快照表）");
+                        Self::create_live_snapshot_table(conn)?;
+                        Self::set_user_version(conn, 7)?;
+                    }
+                    7 => {
+                        log::info!("迁移数据库从 v7 到 v8（添加请求捕获表）");
+                        Self::create_request_capture_table(conn)?;
+                        Self::set_user_version(conn, 8)?;
+                    }
+                    8 => {
+                        log::info!("迁移数据库从 v8 到 v9（应用级默认模型）");
+                        Self::migrate_v8_to_v9(conn)?;
+                        Self::set_user_version(conn, 9)?;
+                    }
+                    9 => {
+                        log::info!("迁移数据库从 v9 到 v10（测速每个 URL 尝试的 key 数）");
+                        Self::migrate_v9_to_v10(conn)?;
+                        Self::set_user_version(conn, 10)?;
+                    }
+                    10 => {
+                        log::info!("迁移数据库从 v10 到 v11（供应商余额耗尽状态）");
+                        Self::migrate_v10_to_v11(conn)?;
+                        Self::set_user_version(conn, 11)?;
+                    }
+                    11 => {
+                        log::info!("迁移数据库从 v11 到 v12（满载冷却时长）");
+                        Self::migrate_v11_to_v12(conn)?;
+                        Self::set_user_version(conn, 12)?;
+                    }
+                    12 => {
+                        log::info!("迁移数据库从 v12 到 v13（连通性惩罚与探测超时）");
+                        Self::migrate_v12_to_v13(conn)?;
+                        Self::set_user_version(conn, 13)?;
+                    }
+                    13 => {
+                        log::info!("迁移数据库从 v13 到 v14（测速历史批次标识）");
+                        Self::migrate_v13_to_v14(conn)?;
+                        Self::set_user_version(conn, 14)?;
+                    }
+                    14 => {
+                        log::info!("迁移数据库从 v14 到 v15（半开探测并发数）");
+                        Self::migrate_v14_to_v15(conn)?;
+                        Self::set_user_version(conn, 15)?;
+                    }
+                    15 => {
+                        log::info!("迁移数据库从 v15 到 v16（路由事件日志表）");
+                        Self::create_events_table(conn)?;
+                        Self::set_user_version(conn, 16)?;
+                    }
+                    16 => {
+                        log::info!("迁移数据库从 v16 到 v17（影子流量配置与日志标记）");
+                        Self::migrate_v16_to_v17(conn)?;
+                        Self::set_user_version(conn, 17)?;
+                    }
+                    17 => {
+                        log::info!("迁移数据库从 v17 到 v18（探测节流配置）");
+                        Self::migrate_v17_to_v18(conn)?;
+                        Self::set_user_version(conn, 18)?;
+                    }
+                    18 => {
+                        log::info!("迁移数据库从 v18 到 v19（代理只读模式）");
+                        Self::migrate_v18_to_v19(conn)?;
+                        Self::set_user_version(conn, 19)?;
+                    }
+                    19 => {
+                        log::info!("迁移数据库从 v19 到 v20（探测模型配置）");
+                        Self::migrate_v19_to_v20(conn)?;
+                        Self::set_user_version(conn, 20)?;
+                    }
+                    20 => {
+                        log::info!("迁移数据库从 v20 到 v21（测速并发数）");
+                        Self::migrate_v20_to_v21(conn)?;
+                        Self::set_user_version(conn, 21)?;
+                    }
+                    21 => {
+                        log::info!("迁移数据库从 v21 到 v22（连接保温）");
+                        Self::migrate_v21_to_v22(conn)?;
+                        Self::set_user_version(conn, 22)?;
+                    }
+                    22 => {
+                        log::info!("迁移数据库从 v22 到 v23（选路策略）");
+                        Self::migrate_v22_to_v23(conn)?;
+                        Self::set_user_version(conn, 23)?;
+                    }
+                    23 => {
+                        log::info!("迁移数据库从 v23 到 v24（请求日志上游耗时）");
+                        Self::migrate_v23_to_v24(conn)?;
+                        Self::set_user_version(conn, 24)?;
+                    }
+                    24 => {
+                        log::info!("迁移数据库从 v24 到 v25（请求日志流量字节数）");
+                        Self::migrate_v24_to_v25(conn)?;
+                        Self::set_user_version(conn, 25)?;
+                    }
+                    25 => {
+                        log::info!("迁移数据库从 v25 到 v26（固化未设置的优先级层级）");
+                        Self::migrate_v25_to_v26(conn)?;
+                        Self::set_user_version(conn, 26)?;
+                    }
                     _ => {
                         return Err(AppError::Database(format!(
                             "未知的数据库版本 {version}，无法迁移到 {SCHEMA_VERSION}"
@@ -603,7 +761,23 @@ impl Database {
             streaming_idle_timeout INTEGER NOT NULL DEFAULT 60, non_streaming_timeout INTEGER NOT NULL DEFAULT 300,
             circuit_failure_threshold INTEGER NOT NULL DEFAULT 5, circuit_success_threshold INTEGER NOT NULL DEFAULT 2,
             circuit_timeout_seconds INTEGER NOT NULL DEFAULT 600, circuit_error_rate_threshold REAL NOT NULL DEFAULT 0.5,
-            circuit_min_requests INTEGER NOT NULL DEFAULT 10,
+            circuit_min_requests INTEGER NOT NULL DEFAULT 10, default_model TEXT,
+            benchmark_max_keys_per_url INTEGER NOT NULL DEFAULT 2,
+            depleted_recheck_secs INTEGER NOT NULL DEFAULT 21600,
+            overload_cooldown_secs INTEGER NOT NULL DEFAULT 5,
+            connectivity_penalty_ms INTEGER NOT NULL DEFAULT 30000,
+            connectivity_timeout_secs INTEGER NOT NULL DEFAULT 5,
+            probe_timeout_secs INTEGER NOT NULL DEFAULT 10,
+            circuit_half_open_max_probes INTEGER NOT NULL DEFAULT 1,
+            shadow_provider_id TEXT, shadow_sample_percent INTEGER NOT NULL DEFAULT 0,
+            probe_min_interval_ms INTEGER NOT NULL DEFAULT 500,
+            probe_skip_recent_success_secs INTEGER NOT NULL DEFAULT 120,
+            probe_model TEXT,
+            benchmark_concurrency INTEGER NOT NULL DEFAULT 3,
+            keep_warm INTEGER NOT NULL DEFAULT 0,
+            keep_warm_interval_secs INTEGER NOT NULL DEFAULT 55,
+            selection_policy TEXT NOT NULL DEFAULT 'default',
+            read_only INTEGER NOT NULL DEFAULT 0,
             created_at TEXT NOT NULL DEFAULT (datetime('now')), updated_at TEXT NOT NULL DEFAULT (datetime('now'))
         )", [])?;
 
@@ -708,6 +882,394 @@ impl Database {
         Ok(())
     }
 
+    /// v2 -> v3 迁移：添加 supplier URL 优先级表，并写入原先内置的默认规则
+    fn migrate_v2_to_v3(conn: &Connection) -> Result<(), AppError> {
+        Self::create_supplier_url_priority_table(conn)?;
+
+        // 原 default_url_priority_for_supplier 中的内置规则
+        conn.execute(
+            "INSERT OR IGNORE INTO supplier_url_priority (supplier, urls, updated_at)
+             VALUES ('anyrouter', '[\"https://anyrouter.top\"]', strftime('%s','now'))",
+            [],
+        )
+        .map_err(|e| AppError::Database(format!("写入默认 URL 优先级失败: {e}")))?;
+
+        Ok(())
+    }
+
+    /// v3 -> v4 迁移：供应商维护禁用截止时间
+    fn migrate_v3_to_v4(conn: &Connection) -> Result<(), AppError> {
+        Self::add_column_if_missing(conn, "providers", "disabled_until", "INTEGER")?;
+        Ok(())
+    }
+
+    /// v5 -> v6 迁移：请求日志记录 CLI 请求的模型与实际发往供应商的模型
+    fn migrate_v5_to_v6(conn: &Connection) -> Result<(), AppError> {
+        Self::add_column_if_missing(conn, "proxy_request_logs", "requested_model", "TEXT")?;
+        Self::add_column_if_missing(conn, "proxy_request_logs", "effective_model", "TEXT")?;
+        Ok(())
+    }
+
+    /// v8 -> v9 迁移：proxy_config 添加 default_model（客户端未指定模型时使用）
+    fn migrate_v8_to_v9(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_config")? {
+            Self::add_column_if_missing(conn, "proxy_config", "default_model", "TEXT")?;
+        }
+        Ok(())
+    }
+
+    /// v9 -> v10 迁移：proxy_config 添加 benchmark_max_keys_per_url（测速时每个 URL 最多尝试的 key 数）
+    fn migrate_v9_to_v10(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_config")? {
+            Self::add_column_if_missing(
+                conn,
+                "proxy_config",
+                "benchmark_max_keys_per_url",
+                "INTEGER NOT NULL DEFAULT 2",
+            )?;
+        }
+        Ok(())
+    }
+
+    /// v10 -> v11 迁移：provider_health 添加余额耗尽状态，proxy_config 添加耗尽后的重新检查间隔
+    fn migrate_v10_to_v11(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "provider_health")? {
+            Self::add_column_if_missing(conn, "provider_health", "depleted_at", "TEXT")?;
+            Self::add_column_if_missing(conn, "provider_health", "depleted_message", "TEXT")?;
+        }
+        if Self::table_exists(conn, "proxy_config")? {
+            Self::add_column_if_missing(
+                conn,
+                "proxy_config",
+                "depleted_recheck_secs",
+                "INTEGER NOT NULL DEFAULT 21600",
+            )?;
+        }
+        Ok(())
+    }
+
+    /// v11 -> v12 迁移：proxy_config 添加满载冷却时长
+    fn migrate_v11_to_v12(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_config")? {
+            Self::add_column_if_missing(
+                conn,
+                "proxy_config",
+                "overload_cooldown_secs",
+                "INTEGER NOT NULL DEFAULT 5",
+            )?;
+        }
+        Ok(())
+    }
+
+    /// v12 -> v13 迁移：proxy_config 添加连通性惩罚与探测超时
+    fn migrate_v12_to_v13(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_config")? {
+            for (column, definition) in [
+                ("connectivity_penalty_ms", "INTEGER NOT NULL DEFAULT 30000"),
+                ("connectivity_timeout_secs", "INTEGER NOT NULL DEFAULT 5"),
+                ("probe_timeout_secs", "INTEGER NOT NULL DEFAULT 10"),
+            ] {
+                Self::add_column_if_missing(conn, "proxy_config", column, definition)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Live 配置快照：每次接管写入前保存一份，按应用保留最近若干条
+    fn create_live_snapshot_table(conn: &Connection) -> Result<(), AppError> {
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS proxy_live_snapshot (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            app_type TEXT NOT NULL,
+            config TEXT NOT NULL,
+            created_at TEXT NOT NULL
+        )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_proxy_live_snapshot_app
+             ON proxy_live_snapshot(app_type, id)",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// 请求捕获：按 request_id 关联请求日志，每个应用保留最近若干条
+    fn create_request_capture_table(conn: &Connection) -> Result<(), AppError> {
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS proxy_request_captures (
+            request_id TEXT PRIMARY KEY, app_type TEXT NOT NULL, endpoint TEXT NOT NULL,
+            headers TEXT NOT NULL DEFAULT '{}', body TEXT NOT NULL,
+            truncated INTEGER NOT NULL DEFAULT 0, created_at INTEGER NOT NULL
+        )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_proxy_request_captures_app
+             ON proxy_request_captures(app_type, created_at)",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// v13 -> v14 迁移：测速历史添加批次标识（旧记录按写入时间归为同一批次）
+    fn migrate_v13_to_v14(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "benchmark_history")? {
+            Self::add_column_if_missing(
+                conn,
+                "benchmark_history",
+                "run_id",
+                "TEXT NOT NULL DEFAULT ''",
+            )?;
+            conn.execute(
+                "UPDATE benchmark_history SET run_id = 'legacy-' || app_type || '-' || created_at
+                 WHERE run_id = ''",
+                [],
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// v14 -> v15 迁移：proxy_config 添加熔断器半开状态的并发探测数
+    fn migrate_v14_to_v15(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_config")? {
+            Self::add_column_if_missing(
+                conn,
+                "proxy_config",
+                "circuit_half_open_max_probes",
+                "INTEGER NOT NULL DEFAULT 1",
+            )?;
+        }
+        Ok(())
+    }
+
+    /// v16 -> v17 迁移：proxy_config 添加影子供应商与采样比例，请求日志添加影子标记
+    fn migrate_v16_to_v17(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_config")? {
+            Self::add_column_if_missing(conn, "proxy_config", "shadow_provider_id", "TEXT")?;
+            Self::add_column_if_missing(
+                conn,
+                "proxy_config",
+                "shadow_sample_percent",
+                "INTEGER NOT NULL DEFAULT 0",
+            )?;
+        }
+        if Self::table_exists(conn, "proxy_request_logs")? {
+            Self::add_column_if_missing(
+                conn,
+                "proxy_request_logs",
+                "shadow",
+                "INTEGER NOT NULL DEFAULT 0",
+            )?;
+        }
+        Ok(())
+    }
+
+    /// v17 -> v18 迁移：proxy_config 添加探测节流（同一供应商最小间隔、近期成功跳过探测）
+    fn migrate_v17_to_v18(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_config")? {
+            Self::add_column_if_missing(
+                conn,
+                "proxy_config",
+                "probe_min_interval_ms",
+                "INTEGER NOT NULL DEFAULT 500",
+            )?;
+            Self::add_column_if_missing(
+                conn,
+                "proxy_config",
+                "probe_skip_recent_success_secs",
+                "INTEGER NOT NULL DEFAULT 120",
+            )?;
+        }
+        Ok(())
+    }
+
+    /// v18 -> v19 迁移：proxy_config 添加只读模式开关（全局字段，三行镜像）
+    fn migrate_v18_to_v19(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_config")? {
+            Self::add_column_if_missing(
+                conn,
+                "proxy_config",
+                "read_only",
+                "INTEGER NOT NULL DEFAULT 0",
+            )?;
+        }
+        Ok(())
+    }
+
+    /// v19 -> v20 迁移：proxy_config 添加应用级探测模型
+    fn migrate_v19_to_v20(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_config")? {
+            Self::add_column_if_missing(conn, "proxy_config", "probe_model", "TEXT")?;
+        }
+        Ok(())
+    }
+
+    /// v20 -> v21 迁移：proxy_config 添加测速并发数
+    fn migrate_v20_to_v21(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_config")? {
+            Self::add_column_if_missing(
+                conn,
+                "proxy_config",
+                "benchmark_concurrency",
+                "INTEGER NOT NULL DEFAULT 3",
+            )?;
+        }
+        Ok(())
+    }
+
+    /// v21 -> v22 迁移：proxy_config 添加连接保温开关与间隔
+    fn migrate_v21_to_v22(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_config")? {
+            Self::add_column_if_missing(
+                conn,
+                "proxy_config",
+                "keep_warm",
+                "INTEGER NOT NULL DEFAULT 0",
+            )?;
+            Self::add_column_if_missing(
+                conn,
+                "proxy_config",
+                "keep_warm_interval_secs",
+                "INTEGER NOT NULL DEFAULT 55",
+            )?;
+        }
+        Ok(())
+    }
+
+    /// v22 -> v23 迁移：proxy_config 添加选路策略
+    fn migrate_v22_to_v23(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_config")? {
+            Self::add_column_if_missing(
+                conn,
+                "proxy_config",
+                "selection_policy",
+                "TEXT NOT NULL DEFAULT 'default'",
+            )?;
+        }
+        Ok(())
+    }
+
+    /// v23 -> v24 迁移：请求日志记录 Python 代理回传的真实上游耗时
+    fn migrate_v23_to_v24(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_request_logs")? {
+            Self::add_column_if_missing(conn, "proxy_request_logs", "upstream_ms", "INTEGER")?;
+        }
+        Ok(())
+    }
+
+    /// v24 -> v25 迁移：请求日志记录请求/响应体字节数（按供应商统计流量）
+    fn migrate_v24_to_v25(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_request_logs")? {
+            for column in ["request_bytes", "response_bytes"] {
+                Self::add_column_if_missing(
+                    conn,
+                    "proxy_request_logs",
+                    column,
+                    "INTEGER NOT NULL DEFAULT 0",
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// v25 -> v26 迁移：未设置 sort_index 的供应商此前按最低层级（999999）参与路由，
+    /// 默认层级改为 DEFAULT_PRIORITY 后写入旧的有效层级，保持已有队列顺序不变
+    fn migrate_v25_to_v26(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "providers")? {
+            conn.execute(
+                "UPDATE providers SET sort_index = ?1 WHERE sort_index IS NULL",
+                [LEGACY_UNSET_PRIORITY],
+            )
+            .map_err(|e| AppError::Database(format!("固化优先级层级失败: {e}")))?;
+        }
+        Ok(())
+    }
+
+    /// 路由事件日志：熔断切换、冷却、疑似失效、故障转移切换等决策
+    fn create_events_table(conn: &Connection) -> Result<(), AppError> {
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS events (
+            id INTEGER PRIMARY KEY AUTOINCREMENT, created_at INTEGER NOT NULL,
+            app_type TEXT NOT NULL, kind TEXT NOT NULL, provider_id TEXT, supplier TEXT,
+            detail TEXT NOT NULL DEFAULT '{}'
+        )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_events_app_time ON events(app_type, created_at)",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// 测速历史：每个 supplier 结果一行，URL 明细在子表中
+    fn create_benchmark_history_tables(conn: &Connection) -> Result<(), AppError> {
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS benchmark_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT, app_type TEXT NOT NULL,
+            priority INTEGER NOT NULL, supplier TEXT NOT NULL, request_model TEXT,
+            chosen_url TEXT, chosen_kind TEXT NOT NULL, metric_ms INTEGER,
+            created_at INTEGER NOT NULL, run_id TEXT NOT NULL DEFAULT ''
+        )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_benchmark_history_supplier
+             ON benchmark_history(app_type, supplier, created_at)",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_benchmark_history_created_at
+             ON benchmark_history(created_at)",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS benchmark_history_urls (
+            history_id INTEGER NOT NULL, url TEXT NOT NULL, kind TEXT NOT NULL,
+            latency_ms INTEGER, penalty_ms INTEGER, message TEXT, reason TEXT,
+            FOREIGN KEY (history_id) REFERENCES benchmark_history(id) ON DELETE CASCADE
+        )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_benchmark_history_urls_history
+             ON benchmark_history_urls(history_id)",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    fn create_supplier_url_priority_table(conn: &Connection) -> Result<(), AppError> {
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS supplier_url_priority (
+            supplier TEXT PRIMARY KEY, urls TEXT NOT NULL DEFAULT '[]', updated_at INTEGER
+        )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(())
+    }
+
     /// 插入默认模型定价数据
     /// 格式: (model_id, display_name, input, output, cache_read, cache_creation)
     /// 注意: model_id 使用短横线格式（如 claude-haiku-4-5），与 API 返回的模型名称标准化后一致
@@ -5,6 +5,7 @@
 use super::*;
 use crate::app_config::MultiAppConfig;
 use crate::provider::{Provider, ProviderManager};
+use crate::proxy::selection_policy::SelectionPolicyKind;
 use indexmap::IndexMap;
 use rusqlite::Connection;
 use serde_json::json;
@@ -332,3 +333,1627 @@ fn model_pricing_is_seeded_on_init() {
         gemini_count
     );
 }
+
+#[test]
+fn migration_seeds_default_supplier_url_priority() {
+    let conn = Connection::open_in_memory().expect("open memory db");
+    Database::create_tables_on_conn(&conn).expect("create tables");
+    Database::set_user_version(&conn, 2).expect("set v2");
+
+    Database::apply_schema_migrations_on_conn(&conn).expect("apply migration");
+
+    let db = Database {
+        conn: std::sync::Mutex::new(conn),
+        dao_reads: Default::default(),
+    };
+    assert_eq!(
+        db.get_supplier_url_priority("AnyRouter")
+            .expect("read priority"),
+        vec!["https://anyrouter.top".to_string()]
+    );
+}
+
+#[test]
+fn migration_keeps_legacy_tier_for_unset_sort_index() {
+    let conn = Connection::open_in_memory().expect("open memory db");
+    Database::create_tables_on_conn(&conn).expect("create tables");
+    for (id, sort_index) in [("a", Some(1)), ("b", None)] {
+        conn.execute(
+            "INSERT INTO providers (id, app_type, name, settings_config, sort_index, in_failover_queue)
+             VALUES (?1, 'claude', ?1, '{}', ?2, 1)",
+            rusqlite::params![id, sort_index],
+        )
+        .expect("insert provider");
+    }
+    Database::set_user_version(&conn, 25).expect("set v25");
+
+    Database::apply_schema_migrations_on_conn(&conn).expect("apply migration");
+
+    let db = Database {
+        conn: std::sync::Mutex::new(conn),
+        dao_reads: Default::default(),
+    };
+    // 旧版本中未设置层级的供应商排在最后，迁移后顺序不变
+    let order: Vec<(String, Option<usize>)> = db
+        .get_failover_providers("claude")
+        .expect("read queue")
+        .into_iter()
+        .map(|p| (p.id, p.sort_index))
+        .collect();
+    assert_eq!(
+        order,
+        vec![("a".to_string(), Some(1)), ("b".to_string(), Some(999999))]
+    );
+}
+
+#[test]
+fn migration_groups_legacy_benchmark_history_by_timestamp() {
+    let conn = Connection::open_in_memory().expect("open memory db");
+    Database::create_tables_on_conn(&conn).expect("create tables");
+    conn.execute("ALTER TABLE benchmark_history DROP COLUMN run_id", [])
+        .expect("drop run_id");
+    for (supplier, created_at) in [("acme", 100), ("other", 100), ("acme", 200)] {
+        conn.execute(
+            "INSERT INTO benchmark_history (app_type, priority, supplier, chosen_kind, created_at)
+             VALUES ('claude', 1, ?1, 'OK', ?2)",
+            rusqlite::params![supplier, created_at],
+        )
+        .expect("insert legacy history");
+    }
+    Database::set_user_version(&conn, 13).expect("set v13");
+
+    Database::apply_schema_migrations_on_conn(&conn).expect("apply migration");
+
+    let run_ids: Vec<String> = conn
+        .prepare("SELECT run_id FROM benchmark_history ORDER BY id")
+        .expect("prepare")
+        .query_map([], |row| row.get(0))
+        .expect("query")
+        .collect::<Result<_, _>>()
+        .expect("collect");
+    assert_eq!(
+        run_ids,
+        vec![
+            "legacy-claude-100",
+            "legacy-claude-100",
+            "legacy-claude-200"
+        ]
+    );
+}
+
+#[test]
+fn supplier_url_priority_roundtrip() {
+    let db = Database::memory().expect("create memory db");
+    assert!(db
+        .get_supplier_url_priority("acme")
+        .expect("read empty")
+        .is_empty());
+
+    db.set_supplier_url_priority(
+        " Acme ",
+        &[
+            "https://b.example.com/".to_string(),
+            "https://a.example.com".to_string(),
+            "https://b.example.com".to_string(),
+        ],
+    )
+    .expect("set priority");
+    assert_eq!(
+        db.list_supplier_url_priorities().expect("list"),
+        vec![SupplierUrlPriority {
+            supplier: "acme".to_string(),
+            urls: vec![
+                "https://b.example.com".to_string(),
+                "https://a.example.com".to_string()
+            ],
+        }]
+    );
+
+    db.set_supplier_url_priority("acme", &[])
+        .expect("clear priority");
+    assert!(db.list_supplier_url_priorities().expect("list").is_empty());
+}
+
+#[test]
+fn backfill_provider_suppliers_pins_grouping() {
+    let db = Database::memory().expect("create memory db");
+    for (id, name, config) in [
+        ("a", "acme-key1", json!({ "env": {} })),
+        ("b", "other", json!({ "env": {}, "supplier": "pinned" })),
+    ] {
+        db.save_provider(
+            "claude",
+            &Provider::with_id(id.to_string(), name.to_string(), config, None),
+        )
+        .expect("save provider");
+    }
+
+    assert_eq!(
+        db.backfill_provider_suppliers("claude").expect("backfill"),
+        1
+    );
+    assert_eq!(
+        db.backfill_provider_suppliers("claude").expect("backfill"),
+        0
+    );
+
+    let mut a = db
+        .get_provider_by_id("a", "claude")
+        .expect("read provider")
+        .expect("provider exists");
+    assert_eq!(a.explicit_supplier(), Some("acme"));
+
+    // 改名后 supplier 不变
+    a.name = "renamed-key1".to_string();
+    db.save_provider("claude", &a).expect("rename provider");
+    let a = db
+        .get_provider_by_id("a", "claude")
+        .expect("read provider")
+        .expect("provider exists");
+    assert_eq!(a.supplier(), "acme");
+
+    db.set_provider_supplier("claude", "b", None)
+        .expect("clear supplier");
+    let b = db
+        .get_provider_by_id("b", "claude")
+        .expect("read provider")
+        .expect("provider exists");
+    assert_eq!(b.explicit_supplier(), None);
+    assert_eq!(b.supplier(), "other");
+}
+
+/// 导入配置包会写设备级设置（settings.json），测试期间将 HOME 指向临时目录
+struct TempHome {
+    #[allow(dead_code)] // 字段通过 Drop trait 管理临时目录生命周期
+    dir: tempfile::TempDir,
+    original_home: Option<String>,
+    original_userprofile: Option<String>,
+}
+
+impl TempHome {
+    fn new() -> Self {
+        let dir = tempfile::TempDir::new().expect("failed to create temp home");
+        let original_home = std::env::var("HOME").ok();
+        let original_userprofile = std::env::var("USERPROFILE").ok();
+
+        std::env::set_var("HOME", dir.path());
+        std::env::set_var("USERPROFILE", dir.path());
+
+        Self {
+            dir,
+            original_home,
+            original_userprofile,
+        }
+    }
+}
+
+impl Drop for TempHome {
+    fn drop(&mut self) {
+        match &self.original_home {
+            Some(value) => std::env::set_var("HOME", value),
+            None => std::env::remove_var("HOME"),
+        }
+
+        match &self.original_userprofile {
+            Some(value) => std::env::set_var("USERPROFILE", value),
+            None => std::env::remove_var("USERPROFILE"),
+        }
+    }
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn config_bundle_roundtrip_after_reset() {
+    fn seed_providers(db: &Database) {
+        for id in ["a", "b"] {
+            db.save_provider(
+                "codex",
+                &Provider::with_id(id.to_string(), format!("acme-{id}"), json!({}), None),
+            )
+            .expect("save provider");
+        }
+    }
+
+    let _home = TempHome::new();
+    let db = Database::memory().expect("create memory db");
+    seed_providers(&db);
+
+    let mut global = db.get_global_proxy_config().await.expect("global config");
+    global.listen_port = 15721;
+    global.read_only = true;
+    db.update_global_proxy_config(global)
+        .await
+        .expect("update global");
+    let mut codex = db.get_proxy_config_for_app("codex").await.expect("codex");
+    codex.auto_failover_enabled = true;
+    codex.max_retries = 5;
+    codex.circuit_error_rate_threshold = 0.3;
+    codex.default_model = Some(" gpt-5 ".to_string());
+    codex.benchmark_max_keys_per_url = 3;
+    codex.probe_min_interval_ms = 1500;
+    codex.probe_model = Some(" gpt-5-mini ".to_string());
+    codex.benchmark_concurrency = 5;
+    codex.keep_warm = true;
+    codex.keep_warm_interval_secs = 30;
+    codex.selection_policy = SelectionPolicyKind::StrictPrimary;
+    db.update_proxy_config_for_app(codex)
+        .await
+        .expect("update codex");
+    let saved = db.get_proxy_config_for_app("codex").await.expect("codex");
+    assert_eq!(saved.default_model.as_deref(), Some("gpt-5"));
+    assert_eq!(saved.benchmark_max_keys_per_url, 3);
+    assert_eq!(saved.probe_min_interval_ms, 1500);
+    assert_eq!(saved.probe_skip_recent_success_secs, 120);
+    assert_eq!(saved.probe_model.as_deref(), Some("gpt-5-mini"));
+    assert_eq!(saved.benchmark_concurrency, 5);
+    assert!(saved.keep_warm);
+    assert_eq!(saved.keep_warm_interval_secs, 30);
+    assert_eq!(saved.selection_policy, SelectionPolicyKind::StrictPrimary);
+    // 数据库默认值为 b，设备级设置为 a：导出以设备级设置为准
+    db.set_current_provider("codex", "b").expect("set current");
+    crate::settings::set_current_provider(&crate::app_config::AppType::Codex, Some("a"))
+        .expect("set device current");
+    db.add_to_failover_queue("codex", "b").expect("queue b");
+    db.add_to_failover_queue("codex", "a").expect("queue a");
+    db.set_supplier_url_priority("acme", &["https://b.example.com".to_string()])
+        .expect("set url priority");
+
+    let exported = serde_json::to_string_pretty(&db.export_config_bundle().await.expect("export"))
+        .expect("serialize bundle");
+    assert!(exported.contains(r#""currentProvider": "a""#), "{exported}");
+
+    // “重置”：全新数据库，仅保留供应商本身；设备级设置指向另一个供应商
+    crate::settings::set_current_provider(&crate::app_config::AppType::Codex, Some("b"))
+        .expect("reset device current");
+    let fresh = Database::memory().expect("create memory db");
+    seed_providers(&fresh);
+    let (bundle, warnings) = ConfigBundle::parse(&exported).expect("parse bundle");
+    assert!(warnings.is_empty(), "{warnings:?}");
+    let report = fresh
+        .import_config_bundle(&bundle)
+        .await
+        .expect("import bundle");
+    assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+    assert_eq!(
+        report.restart_required.len(),
+        2,
+        "{:?}",
+        report.restart_required
+    );
+    let global = fresh.get_global_proxy_config().await.expect("global");
+    assert!(global.read_only);
+    // 当前供应商同时写入数据库与设备级设置
+    assert_eq!(
+        fresh.get_current_provider("codex").unwrap().as_deref(),
+        Some("a")
+    );
+    assert_eq!(
+        crate::settings::get_current_provider(&crate::app_config::AppType::Codex).as_deref(),
+        Some("a")
+    );
+
+    let reimported =
+        serde_json::to_string_pretty(&fresh.export_config_bundle().await.expect("export again"))
+            .expect("serialize bundle");
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&exported).unwrap(),
+        serde_json::from_str::<serde_json::Value>(&reimported).unwrap()
+    );
+}
+
+#[test]
+fn config_bundle_parse_warns_unknown_and_rejects_invalid() {
+    let mut value = json!({
+        "schemaVersion": 1,
+        "globalProxy": {
+            "proxyEnabled": false,
+            "listenAddress": "127.0.0.1",
+            "listenPort": 15721,
+            "enableLogging": true,
+            "legacyField": 1
+        },
+        "apps": [],
+        "extra": "x"
+    });
+    let (_, mut warnings) = ConfigBundle::parse(&value.to_string()).expect("parse bundle");
+    warnings.sort();
+    assert_eq!(
+        warnings,
+        vec![
+            "未知字段已忽略: extra".to_string(),
+            "未知字段已忽略: globalProxy.legacyField".to_string()
+        ]
+    );
+
+    value["schemaVersion"] = json!(99);
+    assert!(ConfigBundle::parse(&value.to_string()).is_err());
+
+    value["schemaVersion"] = json!(1);
+    value["globalProxy"]["listenPort"] = json!(0);
+    assert!(ConfigBundle::parse(&value.to_string()).is_err());
+}
+
+#[test]
+fn readonly_open_reads_without_writing() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let db_path = dir.path().join("cc-switch.db");
+    let now = chrono::Utc::now().timestamp();
+    {
+        let db = Database::open(&db_path).expect("init db file");
+        for id in ["p1", "p2"] {
+            let provider = Provider::with_id(id.to_string(), id.to_string(), json!({}), None);
+            db.save_provider("claude", &provider)
+                .expect("save provider");
+        }
+        db.set_current_provider("claude", "p1")
+            .expect("set current");
+        // p2 的维护禁用已过期：查询时过滤，不写库
+        db.set_provider_disabled_until("claude", "p2", Some(now - 60))
+            .expect("disable p2");
+    }
+    let before = std::fs::read(&db_path).expect("read db file");
+
+    let db = Database::open_readonly(&db_path).expect("open readonly");
+    assert_eq!(db.get_all_providers("claude").expect("providers").len(), 2);
+    assert_eq!(
+        db.get_current_provider("claude").expect("current"),
+        Some("p1".to_string())
+    );
+    assert!(db
+        .get_disabled_providers("claude", now)
+        .expect("disabled")
+        .is_empty());
+    db.get_latency_percentiles(&["p1".to_string()], "claude", 3600)
+        .expect("percentiles");
+    db.get_model_mapping_stats("claude", now - 3600)
+        .expect("mapping stats");
+    // 连接本身只读，写命令走不到这里
+    assert!(db.set_current_provider("claude", "p2").is_err());
+    drop(db);
+
+    assert_eq!(std::fs::read(&db_path).expect("read db file"), before);
+    let files: Vec<_> = std::fs::read_dir(dir.path())
+        .expect("list dir")
+        .map(|e| e.expect("dir entry").file_name())
+        .collect();
+    assert_eq!(files, vec![std::ffi::OsString::from("cc-switch.db")]);
+
+    // 版本落后时不迁移，直接失败（由调用方回退到完整初始化）
+    Connection::open(&db_path)
+        .expect("open db file")
+        .execute_batch(&format!("PRAGMA user_version = {};", SCHEMA_VERSION - 1))
+        .expect("downgrade user_version");
+    assert!(Database::open_readonly(&db_path).is_err());
+    assert!(Database::open_readonly(&dir.path().join("missing.db")).is_err());
+    assert!(!dir.path().join("missing.db").exists());
+}
+
+#[test]
+fn maintain_prunes_logs_and_shrinks_file() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let db_path = dir.path().join("cc-switch.db");
+    let db = Database {
+        conn: std::sync::Mutex::new(Connection::open(&db_path).expect("open db file")),
+        dao_reads: Default::default(),
+    };
+    db.create_tables().expect("create tables");
+
+    let now = chrono::Utc::now().timestamp();
+    {
+        let conn = db.conn.lock().expect("lock conn");
+        let padding = "x".repeat(4096);
+        for i in 0..2000 {
+            conn.execute(
+                "INSERT INTO proxy_request_logs (request_id, provider_id, app_type, model,
+                 latency_ms, status_code, error_message, created_at)
+                 VALUES (?1, 'p', 'claude', 'm', 100, 500, ?2, ?3)",
+                rusqlite::params![format!("old-{i}"), padding, now - 90 * 86_400],
+            )
+            .expect("insert old log");
+        }
+        conn.execute(
+            "INSERT INTO proxy_request_logs (request_id, provider_id, app_type, model,
+             latency_ms, status_code, created_at)
+             VALUES ('recent', 'p', 'claude', 'm', 100, 200, ?1)",
+            [now],
+        )
+        .expect("insert recent log");
+    }
+
+    let report = db.maintain(&db_path, 30).expect("maintain");
+    assert!(report.integrity_errors.is_empty(), "{report:?}");
+    assert_eq!(report.pruned_request_logs, 2000);
+    assert!(
+        report.size_after * 10 < report.size_before,
+        "file should shrink: {report:?}"
+    );
+    assert_eq!(
+        std::fs::metadata(&db_path).expect("db metadata").len(),
+        report.size_after
+    );
+
+    // 连接已指向压缩后的文件，数据与完整性保持
+    let remaining: i64 = db
+        .conn
+        .lock()
+        .expect("lock conn")
+        .query_row("SELECT COUNT(*) FROM proxy_request_logs", [], |row| {
+            row.get(0)
+        })
+        .expect("count logs");
+    assert_eq!(remaining, 1);
+    assert!(db.integrity_check().expect("integrity check").is_empty());
+
+    // 重新打开的连接保留锁等待与外键约束
+    let conn = db.conn.lock().expect("lock conn");
+    let busy_timeout: i64 = conn
+        .query_row("PRAGMA busy_timeout", [], |row| row.get(0))
+        .expect("read busy_timeout");
+    assert_eq!(busy_timeout, 3000);
+    let foreign_keys: i64 = conn
+        .query_row("PRAGMA foreign_keys", [], |row| row.get(0))
+        .expect("read foreign_keys");
+    assert_eq!(foreign_keys, 1);
+}
+
+fn benchmark_result(
+    supplier: &str,
+    chosen: &str,
+    urls: &[(&str, &str, Option<u64>)],
+) -> crate::proxy::provider_router::BenchmarkSupplierResult {
+    use crate::proxy::provider_router::{BenchmarkSupplierResult, BenchmarkUrlResult};
+
+    let chosen_latency = urls.iter().find(|u| u.0 == chosen).and_then(|u| u.2);
+    BenchmarkSupplierResult {
+        priority: 1,
+        supplier: supplier.to_string(),
+        request_model: Some("claude-sonnet-4-5".to_string()),
+        effective_model: None,
+        chosen_url: Some(chosen.to_string()),
+        chosen_kind: "OK".to_string(),
+        metric_ms: chosen_latency,
+        urls: urls
+            .iter()
+            .map(|(url, kind, latency_ms)| BenchmarkUrlResult {
+                url: url.to_string(),
+                kind: kind.to_string(),
+                latency_ms: *latency_ms,
+                penalty_ms: None,
+                message: None,
+                reason: None,
+            })
+            .collect(),
+        cooldown_reason: None,
+    }
+}
+
+#[test]
+fn events_query_filters_and_prune() {
+    let db = Database::memory().expect("create memory db");
+    let now = chrono::Utc::now().timestamp();
+    let event = |created_at, app_type: &str, kind| EventLogEntry {
+        id: 0,
+        created_at,
+        app_type: app_type.to_string(),
+        kind,
+        provider_id: Some("p1".to_string()),
+        supplier: None,
+        detail: json!({ "secs": 60 }),
+    };
+    let written = db
+        .insert_events(&[
+            event(now - 60 * 86_400, "codex", EventKind::Cooldown),
+            event(now - 3600, "codex", EventKind::BreakerOpen),
+            event(now - 60, "codex", EventKind::BreakerClose),
+            event(now - 60, "claude", EventKind::BreakerOpen),
+        ])
+        .expect("insert events");
+    assert_eq!(written, 4);
+
+    let recent = db
+        .query_events("codex", None, now - 86_400, 100)
+        .expect("query events");
+    let kinds: Vec<_> = recent.iter().map(|e| e.kind).collect();
+    assert_eq!(kinds, vec![EventKind::BreakerClose, EventKind::BreakerOpen]);
+    assert_eq!(recent[0].detail, json!({ "secs": 60 }));
+
+    let opened = db
+        .query_events("codex", Some(EventKind::BreakerOpen), 0, 100)
+        .expect("query by kind");
+    assert_eq!(opened.len(), 1);
+    assert_eq!(
+        db.query_events("codex", None, 0, 1).expect("limit").len(),
+        1
+    );
+
+    assert_eq!(db.prune_events(30).expect("prune events"), 1);
+    assert_eq!(
+        db.query_events("codex", None, 0, 100).expect("after").len(),
+        2
+    );
+    assert!("breaker-open".parse::<EventKind>().is_err());
+}
+
+#[test]
+fn benchmark_history_latest_series_and_prune() {
+    let db = Database::memory().expect("create memory db");
+    let now = chrono::Utc::now().timestamp();
+    let (a, b) = ("https://a.example.com", "https://b.example.com");
+
+    // 三天内 a 逐渐变慢，b 一直失败
+    for (days_ago, latency) in [(3, 300), (2, 600), (1, 900)] {
+        db.save_benchmark_results(
+            "claude",
+            &[
+                benchmark_result("acme", a, &[(a, "OK", Some(latency)), (b, "FAIL", None)]),
+                benchmark_result("other", b, &[(b, "OK", Some(100))]),
+            ],
+            now - days_ago * 86_400,
+        )
+        .expect("save history");
+    }
+    // 60 天前的旧记录
+    db.save_benchmark_results(
+        "claude",
+        &[benchmark_result("acme", a, &[(a, "OK", Some(50))])],
+        now - 60 * 86_400,
+    )
+    .expect("save old history");
+
+    let latest = db
+        .get_latest_benchmarks("claude", now - 7 * 86_400)
+        .expect("latest");
+    assert_eq!(latest.len(), 2);
+    assert_eq!(latest[0].result.supplier, "acme");
+    assert_eq!(latest[0].result.metric_ms, Some(900));
+    assert_eq!(latest[0].result.urls.len(), 2);
+    assert_eq!(latest[1].result.supplier, "other");
+    assert!(db
+        .get_latest_benchmarks("codex", 0)
+        .expect("latest codex")
+        .is_empty());
+
+    let series = db
+        .get_benchmark_series("claude", "acme", Some(a), now - 7 * 86_400)
+        .expect("series");
+    let latencies: Vec<_> = series.iter().map(|p| p.latency_ms).collect();
+    assert_eq!(latencies, vec![Some(300), Some(600), Some(900)]);
+    assert!(series.iter().all(|p| p.chosen));
+
+    let all_urls = db
+        .get_benchmark_series("claude", "acme", None, now - 7 * 86_400)
+        .expect("series all urls");
+    assert_eq!(all_urls.len(), 6);
+    assert!(all_urls.iter().any(|p| p.url == b && !p.chosen));
+
+    // 保留 30 天：只删除 60 天前的一条（及其 URL 明细）
+    assert_eq!(db.prune_benchmark_history(30).expect("prune"), 1);
+    let since_epoch = db
+        .get_benchmark_series("claude", "acme", Some(a), 0)
+        .expect("series after prune");
+    assert_eq!(since_epoch.len(), 3);
+    let orphan_urls: i64 = db
+        .conn
+        .lock()
+        .expect("lock conn")
+        .query_row(
+            "SELECT COUNT(*) FROM benchmark_history_urls
+             WHERE history_id NOT IN (SELECT id FROM benchmark_history)",
+            [],
+            |row| row.get(0),
+        )
+        .expect("count orphan urls");
+    assert_eq!(orphan_urls, 0);
+}
+
+#[test]
+fn benchmark_diff_compares_recent_runs_per_supplier() {
+    use crate::database::BenchmarkUrlChange;
+
+    let db = Database::memory().expect("create memory db");
+    let now = chrono::Utc::now().timestamp();
+    let (a, b, c, d) = (
+        "https://a.example.com",
+        "https://b.example.com",
+        "https://c.example.com",
+        "https://d.example.com",
+    );
+
+    let runs: [(i64, Vec<_>); 3] = [
+        (
+            3,
+            vec![benchmark_result("acme", a, &[(a, "OK", Some(900))])],
+        ),
+        (
+            2,
+            vec![
+                benchmark_result(
+                    "acme",
+                    a,
+                    &[
+                        (a, "OK", Some(500)),
+                        (b, "OK", Some(300)),
+                        (c, "FB", Some(20)),
+                    ],
+                ),
+                benchmark_result("other", d, &[(d, "OK", Some(100))]),
+            ],
+        ),
+        (
+            1,
+            vec![benchmark_result(
+                "acme",
+                a,
+                &[
+                    (a, "OK", Some(350)),
+                    (b, "FAIL", None),
+                    (d, "OK", Some(200)),
+                ],
+            )],
+        ),
+    ];
+    for (hours_ago, results) in runs {
+        db.save_benchmark_results("claude", &results, now - hours_ago * 3600)
+            .expect("save history");
+    }
+
+    // 同一次写入共享 run_id
+    let latest = db.get_latest_benchmarks("claude", 0).expect("latest");
+    assert_eq!(latest.len(), 2);
+    assert_ne!(latest[0].run_id, latest[1].run_id);
+
+    // other 只测过一次，不参与对比；acme 对比最近两次
+    let diffs = db.get_benchmark_diffs("claude", None, 2).expect("diffs");
+    assert_eq!(diffs.len(), 1);
+    let diff = &diffs[0];
+    assert_eq!(diff.supplier, "acme");
+    assert_eq!(diff.before_at, now - 2 * 3600);
+    assert_eq!(diff.after_run_id, latest[0].run_id);
+    assert_eq!(
+        (diff.before_metric_ms, diff.after_metric_ms),
+        (Some(500), Some(350))
+    );
+
+    let urls: Vec<_> = diff
+        .urls
+        .iter()
+        .map(|u| {
+            (
+                u.url.as_str(),
+                u.change.clone(),
+                u.delta_ms,
+                u.kind_changed(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        urls,
+        vec![
+            (a, BenchmarkUrlChange::Kept, Some(-150), false),
+            (b, BenchmarkUrlChange::Kept, None, true),
+            (c, BenchmarkUrlChange::Disappeared, None, false),
+            (d, BenchmarkUrlChange::Appeared, None, false),
+        ]
+    );
+    assert_eq!(diff.urls[1].before_kind.as_deref(), Some("OK"));
+    assert_eq!(diff.urls[1].after_kind.as_deref(), Some("FAIL"));
+
+    // runs=3：与最早一次对比
+    let diffs = db
+        .get_benchmark_diffs("claude", Some("acme"), 3)
+        .expect("diffs over 3 runs");
+    assert_eq!(diffs[0].before_at, now - 3 * 3600);
+    assert_eq!(diffs[0].urls[0].delta_ms, Some(-550));
+}
+
+fn insert_request_log(
+    db: &Database,
+    id: &str,
+    provider_id: &str,
+    latency_ms: i64,
+    status: i64,
+    created_at: i64,
+) {
+    db.conn
+        .lock()
+        .expect("lock conn")
+        .execute(
+            "INSERT INTO proxy_request_logs (request_id, provider_id, app_type, model,
+             latency_ms, status_code, created_at)
+             VALUES (?1, ?2, 'claude', 'm', ?3, ?4, ?5)",
+            rusqlite::params![id, provider_id, latency_ms, status, created_at],
+        )
+        .expect("insert request log");
+}
+
+#[test]
+fn latency_percentiles_over_window() {
+    use super::dao::request_logs::nearest_rank_percentile;
+
+    // 最近秩法的小样本边界
+    assert_eq!(nearest_rank_percentile(&[], 50.0), None);
+    assert_eq!(nearest_rank_percentile(&[7], 99.0), Some(7));
+    assert_eq!(nearest_rank_percentile(&[10, 20], 50.0), Some(10));
+    assert_eq!(nearest_rank_percentile(&[10, 20], 95.0), Some(20));
+    assert_eq!(nearest_rank_percentile(&[10, 20, 30, 40], 0.0), Some(10));
+
+    let db = Database::memory().expect("create memory db");
+    let now = chrono::Utc::now().timestamp();
+    let p1 = vec!["p1".to_string()];
+
+    // 1..=100ms 的成功请求 + 25 个失败请求（失败延迟不计入分位数）
+    for i in 1..=100 {
+        insert_request_log(&db, &format!("ok-{i}"), "p1", i, 200, now - i);
+    }
+    for i in 0..25 {
+        insert_request_log(&db, &format!("err-{i}"), "p1", 99_999, 502, now - i);
+    }
+    // 窗口外与其他 provider 的记录不计入
+    insert_request_log(&db, "old", "p1", 1, 200, now - 7200);
+    insert_request_log(&db, "other", "p2", 5_000, 200, now);
+
+    let stats = db
+        .get_latency_percentiles(&p1, "claude", 3600)
+        .expect("percentiles");
+    assert_eq!(stats.count, 125);
+    assert!((stats.error_rate - 0.2).abs() < 1e-9);
+    assert_eq!(stats.p50_ms, Some(50));
+    assert_eq!(stats.p95_ms, Some(95));
+    assert_eq!(stats.p99_ms, Some(99));
+
+    // 只有失败请求：没有分位数，错误率 100%
+    insert_request_log(&db, "p3-err", "p3", 10, 500, now);
+    let stats = db
+        .get_latency_percentiles(&["p3".to_string()], "claude", 3600)
+        .expect("percentiles p3");
+    assert_eq!(stats.count, 1);
+    assert_eq!(stats.error_rate, 1.0);
+    assert_eq!(stats.p50_ms, None);
+
+    // 单个样本：所有分位数相同
+    let stats = db
+        .get_latency_percentiles(&["p2".to_string()], "claude", 3600)
+        .expect("percentiles p2");
+    assert_eq!((stats.p50_ms, stats.p99_ms), (Some(5_000), Some(5_000)));
+
+    // 无记录
+    let stats = db
+        .get_latency_percentiles(&["missing".to_string()], "claude", 3600)
+        .expect("percentiles missing");
+    assert_eq!(stats, LatencyPercentiles::default());
+}
+
+#[test]
+fn shadow_rows_are_compared_separately_and_excluded_from_stats() {
+    let db = Database::memory().expect("create memory db");
+    let now = chrono::Utc::now().timestamp();
+
+    for i in 1..=4 {
+        insert_request_log(&db, &format!("primary-{i}"), "p1", i * 100, 200, now - i);
+    }
+    insert_request_log(&db, "primary-err", "p1", 50, 502, now);
+    for (id, latency, status) in [("s1", 40, 200), ("s2", 60, 200), ("s3", 10, 500)] {
+        insert_request_log(&db, id, "cheap", latency, status, now);
+    }
+    // 影子供应商平时也承接主请求：只有 shadow = 1 的行计入影子侧
+    insert_request_log(&db, "cheap-primary", "cheap", 900, 200, now);
+    db.conn
+        .lock()
+        .expect("lock conn")
+        .execute(
+            "UPDATE proxy_request_logs SET shadow = 1 WHERE request_id IN ('s1', 's2', 's3')",
+            [],
+        )
+        .expect("flag shadow rows");
+
+    let cmp = db
+        .get_shadow_comparison("claude", "cheap", 3600)
+        .expect("shadow comparison");
+    assert_eq!(cmp.primary.count, 6);
+    assert_eq!(cmp.primary.p50_ms, Some(300));
+    assert_eq!(cmp.shadow.count, 3);
+    assert!((cmp.shadow.error_rate - 1.0 / 3.0).abs() < 1e-9);
+    assert_eq!(cmp.shadow.p50_ms, Some(40));
+
+    // 常规统计不包含影子请求
+    let stats = db
+        .get_latency_percentiles(&["cheap".to_string()], "claude", 3600)
+        .expect("percentiles");
+    assert_eq!(stats.count, 1);
+    let counts = db.get_request_counts_by_app(now - 3600).expect("counts");
+    assert_eq!(counts[0].total, 6);
+    assert_eq!(
+        db.get_usage_summary(None, None)
+            .expect("usage summary")
+            .total_requests,
+        6
+    );
+}
+
+#[test]
+fn request_log_delta_reads_only_new_rows() {
+    let db = Database::memory().expect("create memory db");
+    let now = chrono::Utc::now().timestamp();
+
+    insert_request_log(&db, "old", "p1", 100, 200, now - 7200);
+    insert_request_log(&db, "a", "p1", 100, 200, now - 20);
+    insert_request_log(&db, "b", "p1", 100, 502, now - 10);
+
+    // 基线：只统计 since 之后的行
+    let base = db
+        .get_request_log_delta(0, now - 3600, 200)
+        .expect("baseline delta");
+    assert_eq!(base.last_id, 3);
+    assert_eq!(base.logs.len(), 2);
+    assert_eq!(base.logs[0].status_code, 502);
+    assert_eq!(base.counts[0].total, 2);
+    assert_eq!(base.counts[0].success, 1);
+
+    // 无新行时游标不变
+    let idle = db
+        .get_request_log_delta(base.last_id, now - 3600, 200)
+        .expect("idle delta");
+    assert_eq!(idle.last_id, base.last_id);
+    assert!(idle.logs.is_empty() && idle.counts.is_empty());
+
+    // 只返回游标之后的新行；计数不受 limit 限制
+    for i in 0..3 {
+        insert_request_log(&db, &format!("c{i}"), "p2", 100, 200, now);
+    }
+    let delta = db
+        .get_request_log_delta(base.last_id, now - 3600, 2)
+        .expect("incremental delta");
+    assert_eq!(delta.last_id, 6);
+    assert_eq!(delta.logs.len(), 2);
+    assert!(delta.logs.iter().all(|l| l.provider_id == "p2"));
+    assert_eq!(delta.counts[0].total, 3);
+
+    // 日志被清空后游标回退，提示调用方重建基线
+    db.conn
+        .lock()
+        .expect("lock conn")
+        .execute("DELETE FROM proxy_request_logs", [])
+        .expect("clear logs");
+    let cleared = db
+        .get_request_log_delta(delta.last_id, now - 3600, 200)
+        .expect("delta after clear");
+    assert!(cleared.last_id < delta.last_id);
+}
+
+#[test]
+fn provider_success_rates_over_window() {
+    let db = Database::memory().expect("create memory db");
+    let now = chrono::Utc::now().timestamp();
+
+    // p1: 3 成功 + 1 失败；失败请求的延迟不计入平均值
+    for (i, latency) in [100, 200, 300].into_iter().enumerate() {
+        insert_request_log(&db, &format!("p1-ok-{i}"), "p1", latency, 200, now - 10);
+    }
+    insert_request_log(&db, "p1-err", "p1", 30_000, 502, now - 10);
+    // p2: 只有失败请求
+    insert_request_log(&db, "p2-err", "p2", 50, 429, now - 10);
+    // 窗口外与影子请求不计入
+    insert_request_log(&db, "p3-old", "p3", 100, 200, now - 7200);
+    insert_request_log(&db, "p1-shadow", "p1", 100, 500, now);
+    db.conn
+        .lock()
+        .expect("lock conn")
+        .execute(
+            "UPDATE proxy_request_logs SET shadow = 1 WHERE request_id = 'p1-shadow'",
+            [],
+        )
+        .expect("flag shadow row");
+
+    let rates = db
+        .get_provider_success_rates("claude", 3600)
+        .expect("success rates");
+    assert_eq!(rates.len(), 2);
+
+    let p1 = &rates["p1"];
+    assert_eq!((p1.requests, p1.success), (4, 3));
+    assert_eq!(p1.success_rate, Some(0.75));
+    assert_eq!(p1.avg_latency_ms, Some(200));
+
+    let p2 = &rates["p2"];
+    assert_eq!((p2.requests, p2.success), (1, 0));
+    assert_eq!(p2.success_rate, Some(0.0));
+    assert_eq!(p2.avg_latency_ms, None);
+
+    // 无流量的供应商不出现在结果中
+    assert!(!rates.contains_key("p3"));
+    assert!(db
+        .get_provider_success_rates("codex", 3600)
+        .expect("codex rates")
+        .is_empty());
+}
+
+fn insert_mapped_log(
+    db: &Database,
+    id: &str,
+    provider_id: &str,
+    requested: Option<&str>,
+    effective: Option<&str>,
+    status: i64,
+    created_at: i64,
+) {
+    db.conn
+        .lock()
+        .expect("lock conn")
+        .execute(
+            "INSERT INTO proxy_request_logs (request_id, provider_id, app_type, model,
+             latency_ms, status_code, created_at, requested_model, effective_model)
+             VALUES (?1, ?2, 'claude', 'm', 100, ?3, ?4, ?5, ?6)",
+            rusqlite::params![id, provider_id, status, created_at, requested, effective],
+        )
+        .expect("insert mapped request log");
+}
+
+#[test]
+fn model_mapping_stats_group_by_provider_and_pair() {
+    let db = Database::memory().expect("create memory db");
+    db.save_provider(
+        "claude",
+        &Provider::with_id("p1".to_string(), "Acme".to_string(), json!({}), None),
+    )
+    .expect("save provider");
+    let now = chrono::Utc::now().timestamp();
+
+    for i in 0..3 {
+        insert_mapped_log(
+            &db,
+            &format!("glm-{i}"),
+            "p1",
+            Some("claude-sonnet-4-5"),
+            Some("glm-4.6"),
+            if i == 0 { 502 } else { 200 },
+            now - i,
+        );
+    }
+    insert_mapped_log(
+        &db,
+        "haiku",
+        "p1",
+        Some("claude-haiku-4-5"),
+        Some("claude-haiku-4-5"),
+        200,
+        now,
+    );
+    // 已删除的供应商、窗口外与 v6 之前（无映射列）的日志
+    insert_mapped_log(&db, "gone", "p0", Some("x"), None, 500, now);
+    insert_mapped_log(&db, "old", "p1", Some("a"), Some("b"), 200, now - 7200);
+    insert_request_log(&db, "legacy", "p1", 10, 200, now);
+
+    let rows = db
+        .get_model_mapping_stats("claude", now - 3600)
+        .expect("mapping stats");
+    let summary: Vec<_> = rows
+        .iter()
+        .map(|r| {
+            (
+                r.provider_id.as_str(),
+                r.requested_model.as_deref(),
+                r.effective_model.as_deref(),
+                r.count,
+                r.success,
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("p0", Some("x"), None, 1, 0),
+            ("p1", Some("claude-sonnet-4-5"), Some("glm-4.6"), 3, 2),
+            (
+                "p1",
+                Some("claude-haiku-4-5"),
+                Some("claude-haiku-4-5"),
+                1,
+                1
+            ),
+        ]
+    );
+    assert_eq!(rows[0].provider_name, None);
+    assert_eq!(rows[1].provider_name.as_deref(), Some("Acme"));
+    assert_eq!(rows[1].last_seen, now);
+    assert!(rows[1].is_mapped());
+    assert!(!rows[2].is_mapped());
+    assert!(!rows[0].is_mapped());
+
+    assert!(db
+        .get_model_mapping_stats("codex", now - 3600)
+        .expect("codex stats")
+        .is_empty());
+}
+
+#[test]
+fn audit_detects_duplicate_keys_without_exposing_them() {
+    let db = Database::memory().expect("create memory db");
+    let secret = "sk-ant-shared-secret-0001";
+    let save = |id: &str, key: &str, base_url: Option<&str>, sort_index: usize, in_queue: bool| {
+        let mut env = json!({ "ANTHROPIC_AUTH_TOKEN": key });
+        if let Some(url) = base_url {
+            env["ANTHROPIC_BASE_URL"] = json!(url);
+        }
+        let mut provider = Provider::with_id(
+            id.to_string(),
+            format!("acme-{id}"),
+            json!({ "env": env }),
+            None,
+        );
+        provider.sort_index = Some(sort_index);
+        provider.in_failover_queue = in_queue;
+        db.save_provider("claude", &provider)
+            .expect("save provider");
+    };
+
+    save("a", secret, Some("https://a.example.com"), 1, true);
+    save("b", secret, Some("https://b.example.com"), 1, true);
+    save("c", secret, Some("https://c.example.com"), 2, false);
+    save("d", "sk-unique", None, 1, true);
+    save("e", "", Some("https://e.example.com"), 3, true);
+    save("f", "sk-f", Some("${AUDIT_TEST_UNSET_URL}"), 1, true);
+
+    let groups = db.find_duplicate_keys("claude").expect("find duplicates");
+    assert_eq!(groups.len(), 1);
+    let ids: Vec<&str> = groups[0].providers.iter().map(|h| h.id.as_str()).collect();
+    assert_eq!(ids, vec!["a", "b", "c"]);
+    // SHA-256 前 8 位，跨版本稳定
+    assert_eq!(groups[0].fingerprint, "c08d35cd");
+
+    // 只有队列中同一层级的 a/b 计入层级冲突
+    let sets = groups[0].same_priority_sets();
+    assert_eq!(sets.len(), 1);
+    assert_eq!(sets[0].0, 1);
+    assert_eq!(sets[0].1.len(), 2);
+
+    let report = db.audit_providers("claude").expect("audit");
+    assert!(!report.is_clean());
+    assert!(report.issues.contains(&ProviderIssue::MissingBaseUrl {
+        provider_id: "d".to_string(),
+        provider_name: "acme-d".to_string(),
+    }));
+    assert!(report.issues.contains(&ProviderIssue::MissingApiKey {
+        provider_id: "e".to_string(),
+        provider_name: "acme-e".to_string(),
+    }));
+    // 占位符未解析时报告具体变量，而不是当作未配置
+    assert!(report.issues.iter().any(|issue| matches!(
+        issue,
+        ProviderIssue::UnresolvedBaseUrl { provider_id, error, .. }
+            if provider_id == "f" && error.contains("AUDIT_TEST_UNSET_URL")
+    )));
+    assert!(!report.issues.contains(&ProviderIssue::MissingBaseUrl {
+        provider_id: "f".to_string(),
+        provider_name: "acme-f".to_string(),
+    }));
+
+    // 输出中不包含 key 明文
+    let serialized = serde_json::to_string(&report).expect("serialize report");
+    assert!(!serialized.contains(secret));
+    assert!(!serialized.contains("sk-unique"));
+
+    assert!(db.audit_providers("codex").expect("audit codex").is_clean());
+}
+
+#[test]
+fn audit_reports_cross_family_mapping_with_cached_suggestion() {
+    let db = Database::memory().expect("create memory db");
+    let provider = Provider::with_id(
+        "mapped".to_string(),
+        "acme-mapped".to_string(),
+        json!({
+            "env": {
+                "ANTHROPIC_AUTH_TOKEN": "sk-mapped",
+                "ANTHROPIC_BASE_URL": "https://mapped.example.com",
+                "ANTHROPIC_DEFAULT_HAIKU_MODEL": "glm-4.5",
+                "ANTHROPIC_DEFAULT_OPUS_MODEL": "gpt-5"
+            }
+        }),
+        None,
+    );
+    db.save_provider("claude", &provider)
+        .expect("save provider");
+
+    let cross_family = |report: &ProviderAuditReport| -> Vec<(String, Option<String>)> {
+        let mut found: Vec<_> = report
+            .issues
+            .iter()
+            .filter_map(|issue| match issue {
+                ProviderIssue::CrossFamilyMapping {
+                    env_key,
+                    suggestion,
+                    ..
+                } => Some((env_key.clone(), suggestion.clone())),
+                _ => None,
+            })
+            .collect();
+        found.sort();
+        found
+    };
+
+    // 没有缓存的模型列表：只报告问题，不给建议
+    let report = db.audit_providers("claude").expect("audit");
+    assert_eq!(
+        cross_family(&report),
+        vec![
+            ("ANTHROPIC_DEFAULT_HAIKU_MODEL".to_string(), None),
+            ("ANTHROPIC_DEFAULT_OPUS_MODEL".to_string(), None),
+        ]
+    );
+
+    crate::proxy::model_resolver::seed_model_list_cache(
+        &provider,
+        &["glm-4.5", "claude-haiku-4-5-20251001", "claude-opus-4-5"],
+    );
+    let report = db.audit_providers("claude").expect("audit");
+    assert_eq!(
+        cross_family(&report),
+        vec![
+            (
+                "ANTHROPIC_DEFAULT_HAIKU_MODEL".to_string(),
+                Some("claude-haiku-4-5-20251001".to_string())
+            ),
+            (
+                "ANTHROPIC_DEFAULT_OPUS_MODEL".to_string(),
+                Some("claude-opus-4-5".to_string())
+            ),
+        ]
+    );
+
+    // 报告可经管理接口往返（CLI 从运行中的代理读取）
+    let value = serde_json::to_value(&report).expect("serialize report");
+    let parsed: ProviderAuditReport = serde_json::from_value(value).expect("parse report");
+    assert_eq!(parsed.issues, report.issues);
+}
+
+#[tokio::test]
+async fn consistency_check_detects_each_scenario() {
+    let db = Database::memory().expect("create memory db");
+    let kinds = |report: &ConsistencyReport| -> Vec<(ConsistencyKind, bool)> {
+        report.findings.iter().map(|f| (f.kind, f.fixed)).collect()
+    };
+    let save = |id: &str, key: &str, in_queue: bool| {
+        let mut provider = Provider::with_id(
+            id.to_string(),
+            format!("acme-{id}"),
+            json!({ "env": {
+                "ANTHROPIC_AUTH_TOKEN": key,
+                "ANTHROPIC_BASE_URL": format!("https://{id}.example.com")
+            } }),
+            None,
+        );
+        provider.sort_index = Some(1);
+        provider.in_failover_queue = in_queue;
+        db.save_provider("claude", &provider)
+            .expect("save provider");
+    };
+
+    // 未配置供应商的应用不算不一致
+    let report = db.check_consistency("claude", false).await.expect("check");
+    assert!(report.findings.is_empty());
+
+    // 故障转移关闭：未指定当前供应商 / 当前供应商缺 key
+    save("a", "", false);
+    let report = db.check_consistency("claude", false).await.expect("check");
+    assert_eq!(
+        kinds(&report),
+        vec![(ConsistencyKind::NoCurrentProvider, false)]
+    );
+    assert!(report.has_errors());
+    db.set_current_provider("claude", "a").expect("set current");
+    let report = db.check_consistency("claude", false).await.expect("check");
+    assert_eq!(
+        kinds(&report),
+        vec![(ConsistencyKind::CurrentProviderIncomplete, false)]
+    );
+    assert!(report.findings[0].message.contains("API Key"));
+
+    // 故障转移开启：当前供应商不在队列 + 队列为空
+    save("a", "sk-a", false);
+    let mut config = db.get_proxy_config_for_app("claude").await.expect("config");
+    config.auto_failover_enabled = true;
+    db.update_proxy_config_for_app(config)
+        .await
+        .expect("update config");
+    let report = db.check_consistency("claude", false).await.expect("check");
+    assert_eq!(
+        kinds(&report),
+        vec![
+            (ConsistencyKind::CurrentNotInQueue, false),
+            (ConsistencyKind::EmptyQueue, false),
+        ]
+    );
+    assert_eq!(report.findings[0].suggestion, "csc qa claude a");
+
+    // 队列中的供应商缺 key：逐个告警，全部不可用时报错
+    save("b", "", true);
+    let report = db.check_consistency("claude", false).await.expect("check");
+    assert_eq!(
+        kinds(&report),
+        vec![
+            (ConsistencyKind::CurrentNotInQueue, false),
+            (ConsistencyKind::QueuedProviderIncomplete, false),
+            (ConsistencyKind::NoUsableQueuedProvider, false),
+        ]
+    );
+    assert_eq!(report.findings[1].provider_id.as_deref(), Some("b"));
+    let a = db
+        .get_provider_by_id("a", "claude")
+        .expect("get a")
+        .expect("a exists");
+    assert!(!a.in_failover_queue);
+}
+
+#[tokio::test]
+async fn consistency_auto_fix_adds_current_provider_to_queue() {
+    let db = Database::memory().expect("create memory db");
+    let provider = Provider::with_id(
+        "a".to_string(),
+        "acme-a".to_string(),
+        json!({ "env": {
+            "ANTHROPIC_AUTH_TOKEN": "sk-a",
+            "ANTHROPIC_BASE_URL": "https://a.example.com"
+        } }),
+        None,
+    );
+    db.save_provider("claude", &provider)
+        .expect("save provider");
+    db.set_current_provider("claude", "a").expect("set current");
+    let mut config = db.get_proxy_config_for_app("claude").await.expect("config");
+    config.auto_failover_enabled = true;
+    db.update_proxy_config_for_app(config)
+        .await
+        .expect("update config");
+
+    let report = db.check_consistency("claude", true).await.expect("check");
+    assert_eq!(report.findings.len(), 1);
+    assert_eq!(report.findings[0].kind, ConsistencyKind::CurrentNotInQueue);
+    assert!(report.findings[0].fixed);
+    assert!(!report.has_errors());
+    assert_eq!(report.unresolved().count(), 0);
+    let queue: Vec<String> = db
+        .get_failover_providers("claude")
+        .expect("queue")
+        .into_iter()
+        .map(|p| p.id)
+        .collect();
+    assert_eq!(queue, vec!["a".to_string()]);
+
+    let report = db.check_consistency("claude", true).await.expect("check");
+    assert!(report.findings.is_empty());
+}
+
+#[test]
+fn missing_sort_index_is_audited_and_normalized() {
+    let db = Database::memory().expect("create memory db");
+    for (id, sort_index, in_queue) in [
+        ("a", Some(5), true),
+        ("b", None, true),
+        ("c", Some(20), true),
+        ("d", Some(5), false),
+        ("e", None, false),
+    ] {
+        let mut provider = Provider::with_id(
+            id.to_string(),
+            format!("acme-{id}"),
+            json!({
+                "env": {
+                    "ANTHROPIC_AUTH_TOKEN": format!("sk-{id}"),
+                    "ANTHROPIC_BASE_URL": "https://a.example.com"
+                }
+            }),
+            None,
+        );
+        provider.sort_index = sort_index;
+        provider.in_failover_queue = in_queue;
+        db.save_provider("claude", &provider)
+            .expect("save provider");
+    }
+
+    // 只列出队列中的供应商
+    let missing: Vec<String> = db
+        .find_missing_sort_index("claude")
+        .expect("find missing")
+        .into_iter()
+        .map(|p| p.id)
+        .collect();
+    assert_eq!(missing, vec!["b"]);
+    let report = db.audit_providers("claude").expect("audit");
+    assert!(report.issues.contains(&ProviderIssue::MissingSortIndex {
+        provider_id: "b".to_string(),
+        provider_name: "acme-b".to_string(),
+    }));
+
+    // 默认：只为未设置的供应商写入 DEFAULT_PRIORITY
+    let changes = db.normalize_priorities("claude", false).expect("normalize");
+    let mut changed: Vec<(&str, Option<usize>, usize)> = changes
+        .iter()
+        .map(|c| (c.id.as_str(), c.from, c.to))
+        .collect();
+    changed.sort();
+    assert_eq!(
+        changed,
+        vec![
+            ("b", None, crate::provider::DEFAULT_PRIORITY),
+            ("e", None, crate::provider::DEFAULT_PRIORITY),
+        ]
+    );
+    assert!(db
+        .find_missing_sort_index("claude")
+        .expect("find")
+        .is_empty());
+    assert!(db
+        .normalize_priorities("claude", false)
+        .expect("normalize again")
+        .is_empty());
+
+    // compact：5 / 20 / 100 → 0 / 1 / 2，同层级仍在同一层级
+    db.normalize_priorities("claude", true).expect("compact");
+    let levels: HashMap<String, Option<usize>> = db
+        .get_all_providers("claude")
+        .expect("read providers")
+        .into_iter()
+        .map(|(id, p)| (id, p.sort_index))
+        .collect();
+    assert_eq!(levels["a"], Some(0));
+    assert_eq!(levels["d"], Some(0));
+    assert_eq!(levels["c"], Some(1));
+    assert_eq!(levels["b"], Some(2));
+    assert_eq!(levels["e"], Some(2));
+}
+
+#[test]
+fn set_priorities_bulk_reorders_queue_in_one_transaction() {
+    let db = Database::memory().expect("create memory db");
+    for (id, sort_index, in_queue) in [
+        ("a", Some(0), true),
+        ("b", Some(1), true),
+        ("c", Some(1), true),
+        ("d", Some(3), true),
+        ("e", None, true),
+        ("x", Some(0), false),
+    ] {
+        let mut provider = Provider::with_id(id.to_string(), format!("acme-{id}"), json!({}), None);
+        provider.sort_index = sort_index;
+        provider.in_failover_queue = in_queue;
+        db.save_provider("claude", &provider)
+            .expect("save provider");
+    }
+    let levels = |db: &Database| -> Vec<(String, Option<usize>)> {
+        let mut out: Vec<_> = db
+            .get_all_providers("claude")
+            .expect("read providers")
+            .into_values()
+            .map(|p| (p.id, p.sort_index))
+            .collect();
+        out.sort();
+        out
+    };
+    let ids = |list: &[&str]| -> Vec<String> { list.iter().map(|s| s.to_string()).collect() };
+
+    // 不在队列中 / 重复：整体拒绝，不做任何修改
+    let before = levels(&db);
+    assert!(db.set_priorities_bulk("claude", &ids(&["d", "x"])).is_err());
+    assert!(db.set_priorities_bulk("claude", &ids(&["d", "d"])).is_err());
+    assert_eq!(levels(&db), before);
+
+    // 列出的依次为 0,1；未列出的保持相对顺序（b/c 同层级仍同层级）排在其后
+    db.set_priorities_bulk("claude", &ids(&["d", "a"]))
+        .expect("reorder");
+    assert_eq!(
+        levels(&db),
+        vec![
+            ("a".to_string(), Some(1)),
+            ("b".to_string(), Some(2)),
+            ("c".to_string(), Some(2)),
+            ("d".to_string(), Some(0)),
+            ("e".to_string(), Some(3)),
+            ("x".to_string(), Some(0)),
+        ]
+    );
+
+    // 中途写入失败：已写入的层级全部回滚
+    {
+        let conn = db.conn.lock().expect("lock conn");
+        conn.execute_batch(
+            "CREATE TRIGGER fail_on_c BEFORE UPDATE OF sort_index ON providers
+             WHEN NEW.id = 'c'
+             BEGIN SELECT RAISE(ABORT, 'simulated failure'); END;",
+        )
+        .expect("create trigger");
+    }
+    let before = levels(&db);
+    assert!(db.set_priorities_bulk("claude", &ids(&["e", "c"])).is_err());
+    assert_eq!(levels(&db), before);
+}
+
+#[test]
+fn queue_membership_is_idempotent() {
+    let db = Database::memory().expect("create memory db");
+    let provider = Provider::with_id("a".to_string(), "acme-a".to_string(), json!({}), None);
+    db.save_provider("claude", &provider)
+        .expect("save provider");
+
+    // 重复加入/移除不改变成员关系，也不会产生重复条目
+    assert!(db.set_queue_membership("claude", "a", true).expect("add"));
+    assert!(!db
+        .set_queue_membership("claude", "a", true)
+        .expect("add again"));
+    db.add_to_failover_queue("claude", "a").expect("legacy add");
+    assert_eq!(db.get_failover_queue("claude").expect("queue").len(), 1);
+
+    assert!(db
+        .set_queue_membership("claude", "a", false)
+        .expect("remove"));
+    assert!(!db
+        .set_queue_membership("claude", "a", false)
+        .expect("remove again"));
+    assert!(db.get_failover_queue("claude").expect("queue").is_empty());
+
+    assert!(db.set_queue_membership("claude", "missing", true).is_err());
+}
+
+#[test]
+fn move_provider_priority_and_grouped_queue_order() {
+    let db = Database::memory().expect("create memory db");
+    for (id, sort_index, in_queue) in [
+        ("b", Some(2), true),
+        ("a", Some(2), true),
+        ("c", Some(0), true),
+        ("d", None, true),
+        ("x", Some(1), false),
+    ] {
+        let mut provider = Provider::with_id(id.to_string(), format!("acme-{id}"), json!({}), None);
+        provider.sort_index = sort_index;
+        provider.in_failover_queue = in_queue;
+        db.save_provider("claude", &provider)
+            .expect("save provider");
+    }
+    let grouped = |db: &Database| -> Vec<(usize, Vec<String>)> {
+        db.get_failover_queue_grouped("claude")
+            .expect("grouped queue")
+            .into_iter()
+            .map(|tier| {
+                let ids = tier.providers.into_iter().map(|p| p.provider_id).collect();
+                (tier.priority, ids)
+            })
+            .collect()
+    };
+
+    // 层级从小到大，层级内按 ID；未设置 sort_index 的归入默认层级；不在队列中的不出现
+    assert_eq!(
+        grouped(&db),
+        vec![
+            (0, vec!["c".to_string()]),
+            (2, vec!["a".to_string(), "b".to_string()]),
+            (crate::provider::DEFAULT_PRIORITY, vec!["d".to_string()]),
+        ]
+    );
+
+    // 移动到已有层级 / 新层级；层级不变时不做修改
+    let change = db
+        .move_provider_priority("claude", "b", 0)
+        .expect("move")
+        .expect("changed");
+    assert_eq!((change.from, change.to), (Some(2), 0));
+    assert!(db
+        .move_provider_priority("claude", "b", 0)
+        .expect("move again")
+        .is_none());
+    db.move_provider_priority("claude", "d", 1).expect("move d");
+    assert_eq!(
+        grouped(&db),
+        vec![
+            (0, vec!["b".to_string(), "c".to_string()]),
+            (1, vec!["d".to_string()]),
+            (2, vec!["a".to_string()]),
+        ]
+    );
+
+    assert!(db.move_provider_priority("claude", "missing", 0).is_err());
+}
+
+#[tokio::test]
+async fn live_snapshots_dedupe_and_keep_latest_per_app() {
+    use crate::database::dao::proxy::MAX_LIVE_SNAPSHOTS_PER_APP;
+
+    let db = Database::memory().expect("create memory db");
+
+    let first = db
+        .save_live_snapshot("claude", r#"{"v":0}"#)
+        .await
+        .expect("save snapshot");
+    // 与最近一条相同则不重复保存
+    assert_eq!(
+        db.save_live_snapshot("claude", r#"{"v":0}"#)
+            .await
+            .expect("save duplicate"),
+        first
+    );
+    db.save_live_snapshot("codex", r#"{"auth":{}}"#)
+        .await
+        .expect("save codex snapshot");
+
+    let total = MAX_LIVE_SNAPSHOTS_PER_APP + 5;
+    let mut last = first;
+    for v in 1..total {
+        last = db
+            .save_live_snapshot("claude", &format!(r#"{{"v":{v}}}"#))
+            .await
+            .expect("save snapshot");
+    }
+
+    let snapshots = db.list_live_snapshots("claude").await.expect("list");
+    assert_eq!(snapshots.len() as i64, MAX_LIVE_SNAPSHOTS_PER_APP);
+    assert_eq!(snapshots[0].id, last);
+    assert_eq!(
+        snapshots.last().map(|s| s.config.as_str()),
+        Some(r#"{"v":5}"#)
+    );
+    assert!(db
+        .get_live_snapshot("claude", Some(first))
+        .await
+        .expect("get pruned")
+        .is_none());
+    assert_eq!(
+        db.get_live_snapshot("claude", None)
+            .await
+            .expect("get latest")
+            .map(|s| s.id),
+        Some(last)
+    );
+    // 其他应用的快照不受裁剪影响
+    assert_eq!(
+        db.list_live_snapshots("codex").await.expect("list").len(),
+        1
+    );
+}
+
+#[test]
+fn request_captures_roundtrip_and_trim_per_app() {
+    use crate::database::dao::request_captures::MAX_REQUEST_CAPTURES_PER_APP;
+
+    let db = Database::memory().expect("create memory db");
+    let capture = |app_type: &str, id: &str, created_at: i64| RequestCapture {
+        request_id: id.to_string(),
+        app_type: app_type.to_string(),
+        endpoint: "/v1/messages".to_string(),
+        headers: [("anthropic-version".to_string(), "2023-06-01".to_string())]
+            .into_iter()
+            .collect(),
+        body: json!({ "model": "claude-sonnet-4-5", "max_tokens": 16 }),
+        truncated: false,
+        created_at,
+    };
+
+    let first = capture("claude", "req-0", 0);
+    db.save_request_capture(&first).expect("save capture");
+    db.save_request_capture(&capture("codex", "req-codex", 0))
+        .expect("save codex capture");
+    assert_eq!(
+        db.get_request_capture("claude", "req-0").expect("get"),
+        Some(first)
+    );
+    // 按应用隔离
+    assert!(db
+        .get_request_capture("codex", "req-0")
+        .expect("get other app")
+        .is_none());
+
+    for i in 1..=MAX_REQUEST_CAPTURES_PER_APP {
+        db.save_request_capture(&capture("claude", &format!("req-{i}"), i))
+            .expect("save capture");
+    }
+    assert!(db
+        .get_request_capture("claude", "req-0")
+        .expect("get pruned")
+        .is_none());
+    assert!(db
+        .get_request_capture("claude", &format!("req-{MAX_REQUEST_CAPTURES_PER_APP}"))
+        .expect("get latest")
+        .is_some());
+    assert!(db
+        .get_request_capture("codex", "req-codex")
+        .expect("get codex")
+        .is_some());
+}
@@ -52,6 +52,33 @@ pub enum AppError {
     },
     #[error("数据库错误: {0}")]
     Database(String),
+    /// 数据库被锁定（其它进程长时间持有写锁）、损坏或读写失败：属于环境问题，而非数据本身的错误
+    #[error("数据库暂不可用（可能被其它进程锁定或文件损坏，可运行 csc db maintain 检查）: {0}")]
+    DatabaseUnavailable(String),
+    /// 该应用没有配置任何供应商（新用户最常见的情况）
+    #[error("{app_type} 未配置任何供应商，请先运行 csc add {app_type} ... 添加")]
+    NoProvidersConfigured { app_type: String },
+    /// 故障转移关闭，但当前供应商未设置或已被删除
+    #[error("{}", current_provider_missing_message(app_type, provider_id.as_deref()))]
+    CurrentProviderMissing {
+        app_type: String,
+        provider_id: Option<String>,
+    },
+}
+
+/// 当前供应商缺失的提示（与 `ProxyError::CurrentProviderMissing` 共用）
+pub(crate) fn current_provider_missing_message(
+    app_type: &str,
+    provider_id: Option<&str>,
+) -> String {
+    match provider_id {
+        Some(id) => format!(
+            "{app_type} 的当前供应商 {id} 已被删除，请运行 csc enable {app_type} <供应商ID> 重新选择"
+        ),
+        None => format!(
+            "{app_type} 未设置当前供应商（可能已被删除），请运行 csc enable {app_type} <供应商ID> 选择"
+        ),
+    }
 }
 
 impl AppError {
@@ -76,6 +103,19 @@ impl AppError {
         }
     }
 
+    /// 数据库暂不可用（锁定/损坏/IO）：代理可用最近一次成功读取的数据兜底
+    pub fn is_database_unavailable(&self) -> bool {
+        matches!(self, Self::DatabaseUnavailable(_))
+    }
+
+    /// 供应商配置缺失（未配置任何供应商 / 当前供应商缺失）：需要用户处理，重试无意义
+    pub fn is_provider_setup_missing(&self) -> bool {
+        matches!(
+            self,
+            Self::NoProvidersConfigured { .. } | Self::CurrentProviderMissing { .. }
+        )
+    }
+
     pub fn localized(key: &'static str, zh: impl Into<String>, en: impl Into<String>) -> Self {
         Self::Localized {
             key,
@@ -93,7 +133,19 @@ impl<T> From<PoisonError<T>> for AppError {
 
 impl From<rusqlite::Error> for AppError {
     fn from(err: rusqlite::Error) -> Self {
-        Self::Database(err.to_string())
+        use rusqlite::ErrorCode;
+        match err.sqlite_error_code() {
+            Some(
+                ErrorCode::DatabaseBusy
+                | ErrorCode::DatabaseLocked
+                | ErrorCode::DatabaseCorrupt
+                | ErrorCode::NotADatabase
+                | ErrorCode::SystemIoFailure
+                | ErrorCode::CannotOpen
+                | ErrorCode::DiskFull,
+            ) => Self::DatabaseUnavailable(err.to_string()),
+            _ => Self::Database(err.to_string()),
+        }
     }
 }
 
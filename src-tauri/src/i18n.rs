@@ -0,0 +1,339 @@
+//! 运行日志与 CLI 输出的消息目录
+//!
+//! 高频日志（转发成功/失败、测速摘要）与 CLI 输出统一通过消息 ID 取文案：
+//! - 语言优先级：环境变量 `CC_SWITCH_LANG` > 设置项 `logLanguage` > 默认中文
+//! - 模板使用顺序占位符 `{}`，两种语言的占位符数量必须一致（见测试）
+
+use std::fmt::{self, Write as _};
+
+/// 日志语言环境变量
+pub const LANG_ENV: &str = "CC_SWITCH_LANG";
+
+/// 日志/CLI 语言
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+    #[default]
+    Zh,
+    En,
+}
+
+impl Locale {
+    /// 解析语言标识（兼容 `en_US.UTF-8`、`zh-CN` 等写法）
+    pub fn parse(raw: &str) -> Option<Self> {
+        let raw = raw.trim().to_ascii_lowercase();
+        if raw.starts_with("en") {
+            Some(Locale::En)
+        } else if raw.starts_with("zh") {
+            Some(Locale::Zh)
+        } else {
+            None
+        }
+    }
+
+    /// 当前生效语言（每次读取，修改设置后无需重启）
+    pub fn current() -> Self {
+        std::env::var(LANG_ENV)
+            .ok()
+            .and_then(|v| Self::parse(&v))
+            .or_else(|| crate::settings::get_log_language().and_then(|v| Self::parse(&v)))
+            .unwrap_or_default()
+    }
+}
+
+/// 消息 ID
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Msg {
+    // ===== 转发器 =====
+    ForwardOk,
+    ForwardLocal,
+    ForwardUpstream,
+    ForwardNonRetryable,
+    ForwardTierNoProvider,
+    ForwardTierExhausted,
+    ForwardAllFailed,
+    ForwardRequestFailed,
+    ForwardRetryFailed,
+    ForwardUpstreamError,
+    FailoverSwitchFailed,
+
+    // ===== 测速摘要 =====
+    BenchmarkAllFailed,
+    BenchmarkSummaryWithReason,
+    BenchmarkSummary,
+
+    // ===== CLI =====
+    CliError,
+    CliStartingDaemon,
+    CliStartingForeground,
+    CliStopHint,
+    CliCtrlCHint,
+    CliProxyStarted,
+    CliProxyStopped,
+    CliProxyNotRunning,
+    CliProviderNotFound,
+    CliProviderAdded,
+    CliProviderDeleted,
+    CliProviderEnabled,
+    CliNoProviders,
+    CliNoTestableProviders,
+    CliQueueEmpty,
+    CliSummary,
+    CliSummarySorted,
+    CliAllTestsFailed,
+    CliRestartHint,
+}
+
+impl Msg {
+    #[cfg(test)]
+    const ALL: [Msg; 33] = [
+        Msg::ForwardOk,
+        Msg::ForwardLocal,
+        Msg::ForwardUpstream,
+        Msg::ForwardNonRetryable,
+        Msg::ForwardTierNoProvider,
+        Msg::ForwardTierExhausted,
+        Msg::ForwardAllFailed,
+        Msg::ForwardRequestFailed,
+        Msg::ForwardRetryFailed,
+        Msg::ForwardUpstreamError,
+        Msg::FailoverSwitchFailed,
+        Msg::BenchmarkAllFailed,
+        Msg::BenchmarkSummaryWithReason,
+        Msg::BenchmarkSummary,
+        Msg::CliError,
+        Msg::CliStartingDaemon,
+        Msg::CliStartingForeground,
+        Msg::CliStopHint,
+        Msg::CliCtrlCHint,
+        Msg::CliProxyStarted,
+        Msg::CliProxyStopped,
+        Msg::CliProxyNotRunning,
+        Msg::CliProviderNotFound,
+        Msg::CliProviderAdded,
+        Msg::CliProviderDeleted,
+        Msg::CliProviderEnabled,
+        Msg::CliNoProviders,
+        Msg::CliNoTestableProviders,
+        Msg::CliQueueEmpty,
+        Msg::CliSummary,
+        Msg::CliSummarySorted,
+        Msg::CliAllTestsFailed,
+        Msg::CliRestartHint,
+    ];
+
+    /// 指定语言的模板
+    pub fn template(self, locale: Locale) -> &'static str {
+        match locale {
+            Locale::Zh => self.zh(),
+            Locale::En => self.en(),
+        }
+    }
+
+    fn zh(self) -> &'static str {
+        match self {
+            Msg::ForwardOk => "正常",
+            Msg::ForwardLocal => "本地",
+            Msg::ForwardUpstream => "上游",
+            Msg::ForwardNonRetryable => "[{}] Provider {} 失败（不可重试）: {}",
+            Msg::ForwardTierNoProvider => {
+                "[{}] 层级 {} 无可用供应商（可能被熔断器限制），尝试下一层级"
+            }
+            Msg::ForwardTierExhausted => "[{}] 层级 {} 已用尽尝试轮次（{} 轮），切换到下一层级",
+            Msg::ForwardAllFailed => "[{}] 所有 {} 个供应商都失败了",
+            Msg::ForwardRequestFailed => "错误 - {} - target={} base_url={} - 详情: 请求失败 {}",
+            Msg::ForwardRetryFailed => {
+                "错误 - {} - target={} base_url={} - 详情: 重试请求失败 {}"
+            }
+            Msg::ForwardUpstreamError => "错误 {} - {} - base_url={} - 详情: {}",
+            Msg::FailoverSwitchFailed => "[Failover] 切换供应商失败: {}",
+            Msg::BenchmarkAllFailed => {
+                "[{}:{}] 测速结束 supplier={} model={} 结果: 全失败(ok=0 ov=0 fb=0 fail={}) 选用={} 详情: {}"
+            }
+            Msg::BenchmarkSummaryWithReason => {
+                "[{}:{}] 测速结束 supplier={} model={} reason={} 结果: ok={} ov={} fb={} fail={} 选用={} 详情: {}"
+            }
+            Msg::BenchmarkSummary => {
+                "[{}:{}] 测速结束 supplier={} model={} 结果: ok={} ov={} fb={} fail={} 选用={} 详情: {}"
+            }
+            Msg::CliError => "错误: {}",
+            Msg::CliStartingDaemon => "正在启动代理服务器（守护进程模式）...",
+            Msg::CliStartingForeground => "正在启动代理服务器（前台模式）...",
+            Msg::CliStopHint => "使用 csc proxy stop 停止",
+            Msg::CliCtrlCHint => "按 Ctrl+C 停止",
+            Msg::CliProxyStarted => "✓ 代理服务器已启动",
+            Msg::CliProxyStopped => "✓ 代理服务器已停止",
+            Msg::CliProxyNotRunning => "代理服务器未运行（PID文件不存在）",
+            Msg::CliProviderNotFound => "供应商不存在: {}",
+            Msg::CliProviderAdded => "✓ 已添加供应商: {} ({})",
+            Msg::CliProviderDeleted => "✓ 已删除供应商: {}",
+            Msg::CliProviderEnabled => "✓ 已启用供应商: {}",
+            Msg::CliNoProviders => "(无供应商)",
+            Msg::CliNoTestableProviders => "没有可测试的供应商",
+            Msg::CliQueueEmpty => "没有可测试的供应商（队列为空）",
+            Msg::CliSummary => "=== 汇总 ===",
+            Msg::CliSummarySorted => "=== 汇总（按最优URL延迟排序）===",
+            Msg::CliAllTestsFailed => "全部测试失败（没有任何可用URL）",
+            Msg::CliRestartHint => "如需应用更改，请重启代理服务器: csc p r",
+        }
+    }
+
+    fn en(self) -> &'static str {
+        match self {
+            Msg::ForwardOk => "OK",
+            Msg::ForwardLocal => "local",
+            Msg::ForwardUpstream => "upstream",
+            Msg::ForwardNonRetryable => "[{}] Provider {} failed (not retryable): {}",
+            Msg::ForwardTierNoProvider => {
+                "[{}] Tier {} has no available provider (possibly blocked by circuit breaker), trying next tier"
+            }
+            Msg::ForwardTierExhausted => {
+                "[{}] Tier {} exhausted its attempts ({} rounds), moving to next tier"
+            }
+            Msg::ForwardAllFailed => "[{}] All {} providers failed",
+            Msg::ForwardRequestFailed => {
+                "Error - {} - target={} base_url={} - detail: request failed {}"
+            }
+            Msg::ForwardRetryFailed => {
+                "Error - {} - target={} base_url={} - detail: retry request failed {}"
+            }
+            Msg::ForwardUpstreamError => "Error {} - {} - base_url={} - detail: {}",
+            Msg::FailoverSwitchFailed => "[Failover] Failed to switch provider: {}",
+            Msg::BenchmarkAllFailed => {
+                "[{}:{}] Benchmark done supplier={} model={} result: all failed(ok=0 ov=0 fb=0 fail={}) selected={} detail: {}"
+            }
+            Msg::BenchmarkSummaryWithReason => {
+                "[{}:{}] Benchmark done supplier={} model={} reason={} result: ok={} ov={} fb={} fail={} selected={} detail: {}"
+            }
+            Msg::BenchmarkSummary => {
+                "[{}:{}] Benchmark done supplier={} model={} result: ok={} ov={} fb={} fail={} selected={} detail: {}"
+            }
+            Msg::CliError => "Error: {}",
+            Msg::CliStartingDaemon => "Starting proxy server (daemon mode)...",
+            Msg::CliStartingForeground => "Starting proxy server (foreground mode)...",
+            Msg::CliStopHint => "Use csc proxy stop to stop",
+            Msg::CliCtrlCHint => "Press Ctrl+C to stop",
+            Msg::CliProxyStarted => "✓ Proxy server started",
+            Msg::CliProxyStopped => "✓ Proxy server stopped",
+            Msg::CliProxyNotRunning => "Proxy server is not running (PID file not found)",
+            Msg::CliProviderNotFound => "Provider not found: {}",
+            Msg::CliProviderAdded => "✓ Provider added: {} ({})",
+            Msg::CliProviderDeleted => "✓ Provider deleted: {}",
+            Msg::CliProviderEnabled => "✓ Provider enabled: {}",
+            Msg::CliNoProviders => "(no providers)",
+            Msg::CliNoTestableProviders => "No providers to test",
+            Msg::CliQueueEmpty => "No providers to test (queue is empty)",
+            Msg::CliSummary => "=== Summary ===",
+            Msg::CliSummarySorted => "=== Summary (sorted by best URL latency) ===",
+            Msg::CliAllTestsFailed => "All tests failed (no usable URL)",
+            Msg::CliRestartHint => "To apply the change, restart the proxy server: csc p r",
+        }
+    }
+}
+
+/// 按指定语言渲染消息：依次替换模板中的 `{}`，多余参数忽略，缺少的参数保留原样
+pub fn render(locale: Locale, msg: Msg, args: &[&dyn fmt::Display]) -> String {
+    let mut rest = msg.template(locale);
+    let mut out = String::with_capacity(rest.len());
+    let mut args = args.iter();
+    while let Some(pos) = rest.find("{}") {
+        out.push_str(&rest[..pos]);
+        match args.next() {
+            Some(arg) => {
+                let _ = write!(out, "{arg}");
+            }
+            None => out.push_str("{}"),
+        }
+        rest = &rest[pos + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// 按当前语言渲染消息
+pub fn tr(msg: Msg, args: &[&dyn fmt::Display]) -> String {
+    render(Locale::current(), msg, args)
+}
+
+/// 字符显示宽度（CJK/全角字符按 2 列计）
+pub fn char_display_width(c: char) -> usize {
+    let cp = c as u32;
+    let wide = matches!(cp,
+        0x1100..=0x115F
+        | 0x2E80..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x20000..=0x3FFFD
+    );
+    if wide {
+        2
+    } else {
+        1
+    }
+}
+
+/// 字符串显示宽度
+pub fn display_width(s: &str) -> usize {
+    s.chars().map(char_display_width).sum()
+}
+
+/// 按显示宽度右侧补空格（不截断）
+pub fn pad_display(s: &str, width: usize) -> String {
+    let w = display_width(s);
+    if w >= width {
+        s.to_string()
+    } else {
+        format!("{s}{}", " ".repeat(width - w))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_locale() {
+        assert_eq!(Locale::parse("en"), Some(Locale::En));
+        assert_eq!(Locale::parse("en_US.UTF-8"), Some(Locale::En));
+        assert_eq!(Locale::parse(" zh-CN "), Some(Locale::Zh));
+        assert_eq!(Locale::parse("ja"), None);
+        assert_eq!(Locale::default(), Locale::Zh);
+    }
+
+    #[test]
+    fn test_catalog_placeholders_match() {
+        for msg in Msg::ALL {
+            assert_eq!(
+                msg.template(Locale::Zh).matches("{}").count(),
+                msg.template(Locale::En).matches("{}").count(),
+                "{msg:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        assert_eq!(
+            render(Locale::Zh, Msg::ForwardAllFailed, &[&"codex", &3]),
+            "[codex] 所有 3 个供应商都失败了"
+        );
+        assert_eq!(
+            render(Locale::En, Msg::ForwardAllFailed, &[&"codex", &3]),
+            "[codex] All 3 providers failed"
+        );
+        assert_eq!(
+            render(Locale::En, Msg::CliProviderNotFound, &[]),
+            "Provider not found: {}"
+        );
+    }
+
+    #[test]
+    fn test_pad_display() {
+        assert_eq!(display_width("正常"), 4);
+        assert_eq!(pad_display("OK", 4), "OK  ");
+        assert_eq!(pad_display("正常", 4), "正常");
+    }
+}
@@ -11,12 +11,14 @@ mod deeplink;
 mod error;
 mod gemini_config;
 mod gemini_mcp;
+pub mod i18n;
 mod init_status;
 mod mcp;
 mod prompt;
 mod prompt_files;
 mod provider;
 mod provider_defaults;
+mod provider_templates;
 pub mod proxy;
 mod services;
 mod settings;
@@ -29,7 +31,19 @@ pub use codex_config::{get_codex_auth_path, get_codex_config_path, write_codex_l
 pub use commands::*;
 pub use config::{get_claude_mcp_path, get_claude_settings_path, read_json_file};
 pub use database::Database;
-pub use database::RecentSuccessStats;
+pub use database::{
+    AppRequestCount, BandwidthStat, LatencyPercentiles, ModelMappingStat, ProviderSuccessRate,
+    RecentSuccessStats, RequestLogDelta, RequestLogLine, ShadowComparison,
+};
+pub use database::{
+    BenchmarkHistoryEntry, BenchmarkSeriesPoint, BenchmarkSupplierDiff, BenchmarkUrlChange,
+    BenchmarkUrlDiff,
+};
+pub use database::{ConfigBundle, ConfigImportReport};
+pub use database::{ConsistencyFinding, ConsistencyKind, ConsistencyReport, ConsistencySeverity};
+pub use database::{EventKind, EventLogEntry};
+pub use database::RequestCapture;
+pub use database::{DuplicateKeyGroup, KeyHolder, PriorityChange, ProviderAuditReport, ProviderIssue};
 pub use deeplink::{import_provider_from_deeplink, parse_deeplink_url, DeepLinkImportRequest};
 pub use error::AppError;
 pub use mcp::{
@@ -38,12 +52,17 @@ pub use mcp::{
     sync_enabled_to_codex, sync_enabled_to_gemini, sync_single_server_to_claude,
     sync_single_server_to_codex, sync_single_server_to_gemini,
 };
-pub use provider::{Provider, ProviderMeta};
+pub use provider::{Provider, ProviderMeta, DEFAULT_PRIORITY};
+pub use provider_templates::{find_provider_template, load_provider_templates, ProviderTemplate};
 pub use services::{
-    ConfigService, EndpointLatency, McpService, PromptService, ProviderService, ProxyService,
-    SkillService, SpeedtestService,
+    ConfigService, EndpointLatency, LiveImportAction, LiveImportItem, LiveImportService,
+    LivePaths, McpService, PromptService, ProviderService, ProxyService, SkillService,
+    SpeedtestService,
+};
+pub use settings::{
+    get_model_family_overrides, get_request_log_retention_days, remove_model_family_override,
+    set_model_family_override, set_restore_primary, update_settings, AppSettings,
 };
-pub use settings::{update_settings, AppSettings};
 pub use store::AppState;
 use tauri_plugin_deep_link::DeepLinkExt;
 use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
@@ -212,7 +231,11 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_store::Builder::new().build())
+        .plugin(tauri_plugin_notification::init())
         .setup(|app| {
+            // 故障通知需要 AppHandle 发送桌面通知
+            crate::proxy::notifications::init(app.handle().clone());
+
             // 注册 Updater 插件（桌面端）
             #[cfg(desktop)]
             {
@@ -504,6 +527,7 @@ pub fn run() {
             }
 
             let _tray = tray_builder.build(app)?;
+            tray::spawn_failover_pause_watcher(app.handle().clone());
             // 将同一个实例注入到全局状态，避免重复创建导致的不一致
             app.manage(app_state);
 
@@ -620,6 +644,8 @@ pub fn run() {
             commands::set_app_config_dir_override,
             // provider sort order management
             commands::update_providers_sort_order,
+            commands::audit_providers,
+            commands::list_provider_templates,
             // theirs: config import/export and dialogs
             commands::export_config_to_file,
             commands::import_config_from_file,
@@ -655,6 +681,7 @@ pub fn run() {
             commands::get_proxy_takeover_status,
             commands::set_proxy_takeover_for_app,
             commands::get_proxy_status,
+            commands::get_recent_proxy_requests,
             commands::get_proxy_config,
             commands::update_proxy_config,
             // Global & Per-App Config
@@ -668,16 +695,27 @@ pub fn run() {
             // Proxy failover commands
             commands::get_provider_health,
             commands::reset_circuit_breaker,
+            commands::force_open_circuit_breaker,
+            commands::get_routing_events,
             commands::get_circuit_breaker_config,
             commands::update_circuit_breaker_config,
             commands::get_circuit_breaker_stats,
+            commands::get_all_circuit_breaker_stats,
+            commands::get_routing_snapshot,
             // Failover queue management
             commands::get_failover_queue,
             commands::get_available_providers_for_failover,
             commands::add_to_failover_queue,
             commands::remove_from_failover_queue,
+            commands::get_failover_queue_grouped,
+            commands::set_failover_queue_membership,
+            commands::move_provider_priority,
             commands::get_auto_failover_enabled,
             commands::set_auto_failover_enabled,
+            commands::pause_failover,
+            commands::resume_failover,
+            commands::get_supplier_url_priorities,
+            commands::set_supplier_url_priority,
             // Usage statistics
             commands::get_usage_summary,
             commands::get_usage_trends,
@@ -5,6 +5,40 @@ use std::collections::HashMap;
 
 // SSOT 模式：不再写供应商副本文件
 
+/// 未设置 sort_index 的供应商所在的默认优先级层级（路由、排序、审计统一使用）
+pub const DEFAULT_PRIORITY: usize = 100;
+
+/// settingsConfig 中的供应商分组字段
+pub const SUPPLIER_KEY: &str = "supplier";
+/// settingsConfig 中的本地模型后端标记
+pub const LOCAL_BACKEND_KEY: &str = "localBackend";
+/// settingsConfig 中的模型锁定开关
+pub const MODEL_LOCK_KEY: &str = "modelLock";
+/// settingsConfig 中的家族锚定严格程度
+pub const FAMILY_GUARD_KEY: &str = "familyGuard";
+/// settingsConfig 中的测速/探测模型
+pub const PROBE_MODEL_KEY: &str = "probeModel";
+
+/// Claude 供应商 settingsConfig 中仅供代理使用的根字段
+///
+/// Claude Code 不认识这些字段（`bedrock` 还包含 AWS 凭据），写入 `~/.claude/settings.json`
+/// 前会去掉、从 live 回填时再补回。新增代理专用字段时在此登记。
+pub const CLAUDE_PROXY_ONLY_KEYS: &[&str] = &[
+    SUPPLIER_KEY,
+    LOCAL_BACKEND_KEY,
+    MODEL_LOCK_KEY,
+    FAMILY_GUARD_KEY,
+    PROBE_MODEL_KEY,
+    crate::proxy::providers::AUTH_KEY,
+    crate::proxy::providers::BEDROCK_KEY,
+    crate::proxy::thinking_capability::SUPPORTS_THINKING_KEY,
+    crate::proxy::max_tokens::MAX_TOKENS_CAP_KEY,
+    crate::proxy::body_transforms::BODY_TRANSFORMS_KEY,
+    crate::proxy::system_prompt::SYSTEM_PROMPT_KEY,
+    crate::proxy::sse_filter::SSE_FILTER_KEY,
+    crate::proxy::model_fallbacks::MODEL_FALLBACKS_KEY,
+];
+
 /// 供应商结构体
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Provider {
@@ -65,6 +99,102 @@ impl Provider {
             in_failover_queue: false,
         }
     }
+
+    /// 优先级层级（sort_index；未设置时为 [`DEFAULT_PRIORITY`]）
+    pub fn priority(&self) -> usize {
+        self.sort_index.unwrap_or(DEFAULT_PRIORITY)
+    }
+
+    /// 显式配置的 supplier（settingsConfig.supplier，去除首尾空白；空字符串视为未设置）
+    pub fn explicit_supplier(&self) -> Option<&str> {
+        self.settings_config
+            .get(SUPPLIER_KEY)
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+    }
+
+    /// 供应商分组名（supplier）
+    ///
+    /// 优先使用显式配置的 `settingsConfig.supplier`；未设置时回退为名称中第一个 '-' 之前的部分。
+    /// 路由器的冷却、当前 URL、测速锁等分组键均以此为准。
+    pub fn supplier(&self) -> String {
+        match self.explicit_supplier() {
+            Some(s) => s.to_string(),
+            None => Self::supplier_from_name(&self.name),
+        }
+    }
+
+    /// 按名称推断 supplier（第一个 '-' 之前的部分）
+    pub fn supplier_from_name(name: &str) -> String {
+        name.split('-').next().unwrap_or(name).to_string()
+    }
+
+    /// 设置或清除显式 supplier（None/空字符串表示清除）
+    pub fn set_supplier(&mut self, supplier: Option<&str>) {
+        let supplier = supplier.map(str::trim).filter(|s| !s.is_empty());
+        match (supplier, self.settings_config.as_object_mut()) {
+            (Some(s), Some(obj)) => {
+                obj.insert(SUPPLIER_KEY.to_string(), Value::String(s.to_string()));
+            }
+            (None, Some(obj)) => {
+                obj.remove(SUPPLIER_KEY);
+            }
+            (Some(s), None) => {
+                self.settings_config = serde_json::json!({ "supplier": s });
+            }
+            (None, None) => {}
+        }
+    }
+
+    /// 是否为本地模型后端（settingsConfig.localBackend，如 Ollama 的 OpenAI 兼容端点）
+    pub fn is_local_backend(&self) -> bool {
+        self.settings_config
+            .get(LOCAL_BACKEND_KEY)
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false)
+    }
+
+    /// 是否锁定模型（settingsConfig.modelLock）：跳过模型映射、智能解析与名称清洗，原样转发客户端模型
+    ///
+    /// 用于按模型全名计费、必须收到精确模型名（如带日期后缀）的供应商。
+    pub fn model_locked(&self) -> bool {
+        self.settings_config
+            .get(MODEL_LOCK_KEY)
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false)
+    }
+
+    /// Codex 配置中的默认模型（settingsConfig.config TOML 顶层的 `model`）
+    pub fn codex_default_model(&self) -> Option<String> {
+        let config = self.settings_config.get("config")?.as_str()?;
+        let table: toml::Table = toml::from_str(config).ok()?;
+        table
+            .get("model")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|m| !m.is_empty())
+            .map(str::to_string)
+    }
+
+    /// 家族锚定严格程度（settingsConfig.familyGuard：strict / prefer / off），未设置或无法识别时为 strict
+    pub fn family_guard(&self) -> crate::proxy::model_catalog::FamilyGuard {
+        self.settings_config
+            .get(FAMILY_GUARD_KEY)
+            .and_then(|v| v.as_str())
+            .and_then(crate::proxy::model_catalog::FamilyGuard::from_name)
+            .unwrap_or_default()
+    }
+
+    /// 测速/探测请求使用的模型（settingsConfig.probeModel），优先于应用级配置
+    pub fn probe_model(&self) -> Option<String> {
+        self.settings_config
+            .get(PROBE_MODEL_KEY)
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|m| !m.is_empty())
+            .map(str::to_string)
+    }
 }
 
 /// 供应商管理器
@@ -165,6 +295,12 @@ pub struct ProviderMeta {
     /// 每月消费限额（USD）
     #[serde(rename = "limitMonthlyUsd", skip_serializing_if = "Option::is_none")]
     pub limit_monthly_usd: Option<String>,
+    /// 每日流量告警阈值（请求体 + 响应体字节数，UTC 自然日）
+    #[serde(
+        rename = "bandwidthDailyAlertBytes",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub bandwidth_daily_alert_bytes: Option<u64>,
 }
 
 impl ProviderManager {
@@ -0,0 +1,339 @@
+//! 内置供应商模板目录
+//!
+//! 常见服务（anyrouter、openrouter、packycode、官方 Anthropic/OpenAI）的 base_url、鉴权方式、
+//! 推荐模型映射与探测模型。内置目录嵌入于 `defaults/provider_templates.json`，
+//! 用户可在 `~/.cc-switch/templates.json` 中以相同结构覆盖同 ID 的模板或新增模板。
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::error::AppError;
+use crate::provider::{Provider, ProviderMeta};
+use crate::settings::CustomEndpoint;
+
+const BUILTIN_PROVIDER_TEMPLATES_JSON: &str = include_str!("../defaults/provider_templates.json");
+
+/// 用户模板文件名（位于应用配置目录下）
+pub const USER_TEMPLATES_FILE: &str = "templates.json";
+
+/// Claude 的 API Key 写入方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TemplateAuthStyle {
+    /// `ANTHROPIC_AUTH_TOKEN`（Authorization: Bearer）
+    #[default]
+    AuthToken,
+    /// `ANTHROPIC_API_KEY`（x-api-key）
+    ApiKey,
+}
+
+/// 模板在单个应用下的配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateAppConfig {
+    /// 候选 base_url（第一个为默认地址，其余写入自定义端点供测速/切换）
+    pub base_urls: Vec<String>,
+    /// 鉴权方式（仅 Claude 区分；Codex/Gemini 固定使用各自的 API Key 字段）
+    #[serde(default)]
+    pub auth_style: TemplateAuthStyle,
+    /// 推荐模型映射（Claude/Gemini 为 env 键，Codex 使用 `model`）
+    #[serde(default)]
+    pub models: BTreeMap<String, String>,
+    /// 探测使用的模型（写入 settingsConfig.probeModel）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub probe_model: Option<String>,
+}
+
+/// 供应商模板
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderTemplate {
+    /// 模板 ID（取自目录中的键名）
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub website_url: Option<String>,
+    /// 各应用下的配置（claude/codex/gemini）
+    pub apps: BTreeMap<String, TemplateAppConfig>,
+    /// 是否来自内置目录（被用户文件覆盖或新增的模板为 false）
+    #[serde(default)]
+    pub builtin: bool,
+}
+
+impl ProviderTemplate {
+    /// 按模板生成供应商
+    ///
+    /// `base_url` 为空时使用模板的第一个候选地址；`name` 为空时使用模板名称。
+    pub fn build_provider(
+        &self,
+        app_type: &str,
+        id: &str,
+        name: Option<&str>,
+        api_key: &str,
+        base_url: Option<&str>,
+    ) -> Result<Provider, AppError> {
+        let app = self.apps.get(app_type).ok_or_else(|| {
+            AppError::InvalidInput(format!(
+                "模板 {} 不支持应用 {}（支持: {}）",
+                self.id,
+                app_type,
+                self.apps.keys().cloned().collect::<Vec<_>>().join(", ")
+            ))
+        })?;
+
+        let api_key = api_key.trim();
+        if api_key.is_empty() {
+            return Err(AppError::InvalidInput("API Key 不能为空".to_string()));
+        }
+
+        let base_url = base_url
+            .map(str::trim)
+            .filter(|u| !u.is_empty())
+            .or_else(|| app.base_urls.first().map(String::as_str))
+            .map(|u| u.trim_end_matches('/').to_string())
+            .ok_or_else(|| {
+                AppError::InvalidInput(format!(
+                    "模板 {} 未提供 base_url，请通过 --base-url 指定",
+                    self.id
+                ))
+            })?;
+
+        let mut settings_config = match app_type {
+            "codex" => {
+                let model = app
+                    .models
+                    .get("model")
+                    .map(String::as_str)
+                    .unwrap_or("gpt-5.1-codex");
+                let config_toml = format!(
+                    r#"model_provider = "{id}"
+model = "{model}"
+disable_response_storage = true
+
+[model_providers.{id}]
+name = "{id}"
+base_url = "{base_url}"
+wire_api = "responses"
+requires_openai_auth = true"#,
+                    id = self.id,
+                );
+                json!({
+                    "auth": {
+                        "OPENAI_API_KEY": api_key
+                    },
+                    "config": config_toml
+                })
+            }
+            "gemini" => {
+                let mut env = serde_json::Map::new();
+                env.insert("GOOGLE_GEMINI_BASE_URL".to_string(), json!(base_url));
+                env.insert("GEMINI_API_KEY".to_string(), json!(api_key));
+                for (key, value) in &app.models {
+                    env.insert(key.clone(), json!(value));
+                }
+                json!({ "env": env })
+            }
+            _ => {
+                let key_field = match app.auth_style {
+                    TemplateAuthStyle::AuthToken => "ANTHROPIC_AUTH_TOKEN",
+                    TemplateAuthStyle::ApiKey => "ANTHROPIC_API_KEY",
+                };
+                let mut env = serde_json::Map::new();
+                env.insert("ANTHROPIC_BASE_URL".to_string(), json!(base_url));
+                env.insert(key_field.to_string(), json!(api_key));
+                for (key, value) in &app.models {
+                    env.insert(key.clone(), json!(value));
+                }
+                json!({ "env": env })
+            }
+        };
+        if let Some(probe_model) = app.probe_model.as_deref() {
+            settings_config["probeModel"] = Value::String(probe_model.to_string());
+        }
+
+        // 全部候选地址写入自定义端点，便于在地址管理中测速与切换
+        let now = chrono::Utc::now().timestamp_millis();
+        let mut custom_endpoints = HashMap::new();
+        for url in app.base_urls.iter().map(|u| u.trim_end_matches('/')) {
+            custom_endpoints.insert(
+                url.to_string(),
+                CustomEndpoint {
+                    url: url.to_string(),
+                    added_at: now,
+                    last_used: None,
+                },
+            );
+        }
+
+        let mut provider = Provider::with_id(
+            id.to_string(),
+            name.unwrap_or(&self.name).to_string(),
+            settings_config,
+            self.website_url.clone(),
+        );
+        if custom_endpoints.len() > 1 {
+            provider.meta = Some(ProviderMeta {
+                custom_endpoints,
+                ..Default::default()
+            });
+        }
+        Ok(provider)
+    }
+}
+
+fn parse_templates(
+    json: &str,
+    builtin: bool,
+) -> Result<BTreeMap<String, ProviderTemplate>, serde_json::Error> {
+    let mut templates: BTreeMap<String, ProviderTemplate> = serde_json::from_str(json)?;
+    for (id, template) in templates.iter_mut() {
+        template.id = id.clone();
+        template.builtin = builtin;
+    }
+    Ok(templates)
+}
+
+/// 内置模板目录
+pub fn builtin_provider_templates() -> Result<BTreeMap<String, ProviderTemplate>, AppError> {
+    parse_templates(BUILTIN_PROVIDER_TEMPLATES_JSON, true)
+        .map_err(|e| AppError::Config(format!("内置供应商模板解析失败: {e}")))
+}
+
+fn load_provider_templates_from(user_file: &Path) -> Result<Vec<ProviderTemplate>, AppError> {
+    let mut templates = builtin_provider_templates()?;
+    if user_file.exists() {
+        let content = std::fs::read_to_string(user_file).map_err(|e| AppError::io(user_file, e))?;
+        let user = parse_templates(&content, false).map_err(|e| AppError::json(user_file, e))?;
+        templates.extend(user);
+    }
+    Ok(templates.into_values().collect())
+}
+
+/// 加载模板目录（内置目录 + `~/.cc-switch/templates.json` 覆盖），按 ID 排序
+pub fn load_provider_templates() -> Result<Vec<ProviderTemplate>, AppError> {
+    load_provider_templates_from(&crate::config::get_app_config_dir().join(USER_TEMPLATES_FILE))
+}
+
+/// 按 ID 查找模板
+pub fn find_provider_template(id: &str) -> Result<ProviderTemplate, AppError> {
+    let templates = load_provider_templates()?;
+    let ids = templates
+        .iter()
+        .map(|t| t.id.clone())
+        .collect::<Vec<_>>()
+        .join(", ");
+    templates
+        .into_iter()
+        .find(|t| t.id == id)
+        .ok_or_else(|| AppError::InvalidInput(format!("未知的供应商模板: {id}（可用: {ids}）")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_catalog_parses_and_has_base_urls() {
+        let templates = builtin_provider_templates().expect("builtin catalog");
+        for id in [
+            "anthropic",
+            "openai",
+            "openrouter",
+            "packycode",
+            "anyrouter",
+        ] {
+            let template = templates.get(id).expect(id);
+            assert_eq!(template.id, id);
+            assert!(template.builtin);
+            assert!(!template.apps.is_empty(), "{id} has no apps");
+            for (app, config) in &template.apps {
+                assert!(
+                    ["claude", "codex", "gemini"].contains(&app.as_str()),
+                    "{id}: unknown app {app}"
+                );
+                assert!(!config.base_urls.is_empty(), "{id}/{app} has no base_url");
+            }
+        }
+    }
+
+    #[test]
+    fn user_file_overrides_and_extends_builtin() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join(USER_TEMPLATES_FILE);
+        std::fs::write(
+            &path,
+            r#"{
+                "openrouter": {
+                    "name": "OpenRouter 自建",
+                    "apps": { "claude": { "baseUrls": ["https://or.example.com/api"] } }
+                },
+                "acme": {
+                    "name": "Acme",
+                    "apps": { "codex": { "baseUrls": ["https://acme.example.com/v1"] } }
+                }
+            }"#,
+        )
+        .expect("write user templates");
+
+        let templates = load_provider_templates_from(&path).expect("load templates");
+        let openrouter = templates.iter().find(|t| t.id == "openrouter").unwrap();
+        assert!(!openrouter.builtin);
+        assert_eq!(
+            openrouter.apps["claude"].base_urls,
+            vec!["https://or.example.com/api"]
+        );
+        assert!(templates.iter().any(|t| t.id == "acme" && !t.builtin));
+        assert!(templates.iter().any(|t| t.id == "anthropic" && t.builtin));
+
+        std::fs::write(&path, "{ not json").unwrap();
+        assert!(load_provider_templates_from(&path).is_err());
+    }
+
+    #[test]
+    fn build_provider_uses_auth_style_and_models() {
+        let templates = builtin_provider_templates().unwrap();
+
+        let provider = templates["anthropic"]
+            .build_provider("claude", "official", None, "sk-ant", None)
+            .unwrap();
+        let env = &provider.settings_config["env"];
+        assert_eq!(env["ANTHROPIC_API_KEY"], "sk-ant");
+        assert!(env.get("ANTHROPIC_AUTH_TOKEN").is_none());
+        assert_eq!(env["ANTHROPIC_BASE_URL"], "https://api.anthropic.com");
+
+        let provider = templates["openrouter"]
+            .build_provider("claude", "or", Some("or-main"), "sk-or", None)
+            .unwrap();
+        assert_eq!(provider.name, "or-main");
+        let env = &provider.settings_config["env"];
+        assert_eq!(env["ANTHROPIC_AUTH_TOKEN"], "sk-or");
+        assert_eq!(env["ANTHROPIC_MODEL"], "anthropic/claude-sonnet-4.5");
+        assert_eq!(
+            provider.settings_config["probeModel"],
+            "anthropic/claude-haiku-4.5"
+        );
+
+        let provider = templates["packycode"]
+            .build_provider("codex", "packy", None, "sk-packy", None)
+            .unwrap();
+        assert_eq!(
+            provider.codex_default_model().as_deref(),
+            Some("gpt-5.1-codex")
+        );
+        assert_eq!(
+            provider.meta.as_ref().unwrap().custom_endpoints.len(),
+            2,
+            "all candidate URLs become custom endpoints"
+        );
+
+        assert!(templates["anyrouter"]
+            .build_provider("codex", "x", None, "sk", None)
+            .is_err());
+        assert!(templates["anyrouter"]
+            .build_provider("claude", "x", None, "  ", None)
+            .is_err());
+    }
+}
@@ -0,0 +1,253 @@
+//! 按应用的代理开关
+//!
+//! `AppProxyConfig.enabled` 同时是该应用的 Live 接管标志。只要有任一应用开启，
+//! 未开启应用的路由直接返回 503（不经过熔断器、请求统计与请求日志，只累加
+//! `rejected_disabled`）；三个应用都未开启时（纯 CLI 运行、未使用接管）不做拦截。
+//!
+//! 开关通过 watch 通道热更新：切换接管与 `POST /__cc_switch/config/reload` 都会刷新。
+
+use super::inflight::app_type_from_path;
+use crate::database::Database;
+use crate::error::AppError;
+use axum::extract::{Request, State};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::watch;
+
+const APP_TYPES: [&str; 3] = ["claude", "codex", "gemini"];
+
+/// 各应用的代理开关快照
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AppGateState {
+    pub claude: bool,
+    pub codex: bool,
+    pub gemini: bool,
+}
+
+impl AppGateState {
+    /// 该应用的请求是否放行
+    pub fn allows(&self, app_type: &str) -> bool {
+        if !(self.claude || self.codex || self.gemini) {
+            return true;
+        }
+        match app_type {
+            "claude" => self.claude,
+            "codex" => self.codex,
+            "gemini" => self.gemini,
+            _ => true,
+        }
+    }
+}
+
+/// 应用开关 + 拒绝计数
+pub struct AppGate {
+    state: watch::Sender<AppGateState>,
+    rejected: [AtomicU64; 3],
+}
+
+impl Default for AppGate {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AppGate {
+    pub fn new() -> Self {
+        let (state, _) = watch::channel(AppGateState::default());
+        Self {
+            state,
+            rejected: Default::default(),
+        }
+    }
+
+    pub fn current(&self) -> AppGateState {
+        *self.state.borrow()
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<AppGateState> {
+        self.state.subscribe()
+    }
+
+    pub fn set(&self, next: AppGateState) {
+        let prev = self.state.send_replace(next);
+        if prev != next {
+            log::info!(
+                "应用代理开关已更新: claude={} codex={} gemini={}",
+                next.claude,
+                next.codex,
+                next.gemini
+            );
+        }
+    }
+
+    /// 从数据库重新读取各应用的 `enabled`
+    pub async fn reload(&self, db: &Database) -> Result<AppGateState, AppError> {
+        let next = AppGateState {
+            claude: db.get_proxy_config_for_app("claude").await?.enabled,
+            codex: db.get_proxy_config_for_app("codex").await?.enabled,
+            gemini: db.get_proxy_config_for_app("gemini").await?.enabled,
+        };
+        self.set(next);
+        Ok(next)
+    }
+
+    /// 因应用关闭而被拒绝的请求数
+    pub fn rejected_disabled(&self, app_type: &str) -> u64 {
+        APP_TYPES
+            .iter()
+            .position(|t| *t == app_type)
+            .map(|i| self.rejected[i].load(Ordering::Relaxed))
+            .unwrap_or(0)
+    }
+
+    fn record_rejected(&self, app_type: &str) {
+        if let Some(i) = APP_TYPES.iter().position(|t| *t == app_type) {
+            self.rejected[i].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// 拦截已关闭应用的代理请求（需位于进行中请求追踪之外，避免计入统计）
+pub async fn gate_disabled_apps(
+    State(gate): State<Arc<AppGate>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let app_type = app_type_from_path(req.uri().path());
+    if gate.current().allows(app_type) {
+        return next.run(req).await;
+    }
+
+    gate.record_rejected(app_type);
+    log::debug!(
+        "[{app_type}] 应用代理已关闭，拒绝请求: {}",
+        req.uri().path()
+    );
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(json!({
+            "error": {
+                "type": "app_disabled",
+                "message": format!("{app_type} 的代理已在 CC Switch 中关闭"),
+            }
+        })),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::post;
+    use axum::Router;
+
+    async fn spawn_server(gate: Arc<AppGate>) -> String {
+        let app = Router::new()
+            .route("/v1/messages", post(|| async { "claude" }))
+            .route("/v1/responses", post(|| async { "codex" }))
+            .layer(axum::middleware::from_fn_with_state(
+                gate,
+                gate_disabled_apps,
+            ));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+        base
+    }
+
+    #[test]
+    fn test_all_disabled_means_no_gating() {
+        let state = AppGateState::default();
+        assert!(state.allows("claude"));
+        assert!(state.allows("codex"));
+
+        let state = AppGateState {
+            claude: true,
+            ..Default::default()
+        };
+        assert!(state.allows("claude"));
+        assert!(!state.allows("codex"));
+        assert!(!state.allows("gemini"));
+        assert!(state.allows("-"));
+    }
+
+    #[tokio::test]
+    async fn test_toggle_is_applied_at_runtime() {
+        let gate = Arc::new(AppGate::new());
+        gate.set(AppGateState {
+            claude: true,
+            codex: true,
+            gemini: false,
+        });
+        let mut rx = gate.subscribe();
+        let base = spawn_server(gate.clone()).await;
+        let client = reqwest::Client::new();
+
+        let resp = client
+            .post(format!("{base}/v1/responses"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.text().await.unwrap(), "codex");
+
+        // 运行中关闭 codex
+        gate.set(AppGateState {
+            claude: true,
+            codex: false,
+            gemini: false,
+        });
+        assert!(rx.has_changed().unwrap());
+        assert!(!rx.borrow_and_update().codex);
+
+        let resp = client
+            .post(format!("{base}/v1/responses"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+        let body: serde_json::Value = resp.json().await.unwrap();
+        assert_eq!(body["error"]["type"], "app_disabled");
+        assert_eq!(gate.rejected_disabled("codex"), 1);
+
+        // 其他应用不受影响
+        let resp = client
+            .post(format!("{base}/v1/messages"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.text().await.unwrap(), "claude");
+        assert_eq!(gate.rejected_disabled("claude"), 0);
+
+        // 重新开启后恢复放行
+        gate.set(AppGateState {
+            claude: true,
+            codex: true,
+            gemini: false,
+        });
+        let resp = client
+            .post(format!("{base}/v1/responses"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.text().await.unwrap(), "codex");
+        assert_eq!(gate.rejected_disabled("codex"), 1);
+
+        // 全部关闭后回到不拦截（未使用接管的纯 CLI 场景）
+        gate.set(AppGateState::default());
+        let resp = client
+            .post(format!("{base}/v1/responses"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.text().await.unwrap(), "codex");
+        assert_eq!(gate.rejected_disabled("codex"), 1);
+    }
+}
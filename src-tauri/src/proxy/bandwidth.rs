@@ -0,0 +1,212 @@
+//! 流量计量
+//!
+//! 按供应商累计请求体与响应体字节数，写入请求日志的 `request_bytes` / `response_bytes` 列：
+//! - 请求体：客户端请求 JSON 的序列化长度（模型映射等改写之前），只计数不分配缓冲
+//! - 响应体：非流式为响应体长度；流式由 [`count_stream`] 在透传时逐块累加，不缓冲
+//! - 供应商 meta 设置 `bandwidthDailyAlertBytes` 后，本次请求使当日（UTC）累计流量越过阈值时
+//!   记录一条 `bandwidth_alert` 事件（每个供应商每天至多一次）
+//!
+//! 只统计记录了请求日志的请求（成功请求）；影子流量不计入。
+
+use super::event_log::EventRecorder;
+use super::usage::TransferBytes;
+use crate::database::{Database, EventKind};
+use crate::provider::Provider;
+use bytes::Bytes;
+use futures::stream::{Stream, StreamExt};
+use serde_json::Value;
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+const SECS_PER_DAY: i64 = 86_400;
+
+/// JSON 请求体序列化后的字节数（写入计数器，不生成中间字符串）
+pub fn json_body_size(body: &Value) -> u64 {
+    let mut writer = CountingWriter(0);
+    // 写入计数器不会失败
+    let _ = serde_json::to_writer(&mut writer, body);
+    writer.0
+}
+
+struct CountingWriter(u64);
+
+impl io::Write for CountingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// 跨任务共享的字节计数器
+#[derive(Debug, Clone, Default)]
+pub struct ByteCounter(Arc<AtomicU64>);
+
+impl ByteCounter {
+    pub fn add(&self, n: u64) {
+        self.0.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// 透传包装：原样转发每个分块，同时把分块大小累加到 `counter`
+pub fn count_stream<S, E>(stream: S, counter: ByteCounter) -> impl Stream<Item = Result<Bytes, E>>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+{
+    stream.inspect(move |chunk| {
+        if let Ok(bytes) = chunk {
+            counter.add(bytes.len() as u64);
+        }
+    })
+}
+
+/// 可读的字节数（1024 进制）
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{bytes}B");
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1}{}", UNITS[unit])
+}
+
+/// 请求日志写入后调用：本次请求使供应商当日累计流量越过告警阈值时记录事件
+pub(crate) fn check_daily_alert(
+    db: &Database,
+    events: &EventRecorder,
+    app_type: &str,
+    provider: &Provider,
+    bytes: TransferBytes,
+    now: i64,
+) {
+    let Some(threshold) = provider
+        .meta
+        .as_ref()
+        .and_then(|meta| meta.bandwidth_daily_alert_bytes)
+        .filter(|t| *t > 0)
+    else {
+        return;
+    };
+    let day_start = now - now.rem_euclid(SECS_PER_DAY);
+    let total = match db.get_provider_bytes_since(app_type, &provider.id, day_start) {
+        Ok(total) => total,
+        Err(e) => {
+            log::debug!("[{app_type}] 读取供应商 {} 当日流量失败: {e}", provider.id);
+            return;
+        }
+    };
+    let before = total.saturating_sub(bytes.total());
+    if before > threshold || total <= threshold {
+        return;
+    }
+
+    log::warn!(
+        "[{app_type}] 供应商 {} 今日流量 {} 已超过告警阈值 {}",
+        provider.name,
+        format_bytes(total),
+        format_bytes(threshold)
+    );
+    events.record(
+        app_type,
+        EventKind::BandwidthAlert,
+        Some(&provider.id),
+        Some(&provider.supplier()),
+        serde_json::json!({ "bytes": total, "threshold": threshold }),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::ProviderMeta;
+    use serde_json::json;
+
+    #[test]
+    fn json_body_size_matches_serialized_length() {
+        let body = json!({ "model": "gpt-5", "input": "你好", "n": [1, 2, 3] });
+        assert_eq!(
+            json_body_size(&body),
+            serde_json::to_vec(&body).unwrap().len() as u64
+        );
+    }
+
+    #[tokio::test]
+    async fn count_stream_passes_chunks_through() {
+        let counter = ByteCounter::default();
+        let chunks = vec![
+            Ok::<_, io::Error>(Bytes::from_static(b"data: a\n\n")),
+            Ok(Bytes::from_static(b"data: bc\n\n")),
+        ];
+        let out: Vec<_> = count_stream(futures::stream::iter(chunks), counter.clone())
+            .collect()
+            .await;
+        assert_eq!(out.len(), 2);
+        assert_eq!(counter.get(), 19);
+    }
+
+    #[test]
+    fn format_bytes_picks_unit() {
+        assert_eq!(format_bytes(512), "512B");
+        assert_eq!(format_bytes(1536), "1.5KB");
+        assert_eq!(format_bytes(3 * 1024 * 1024 * 1024), "3.0GB");
+    }
+
+    fn insert_log(db: &Database, id: &str, bytes: i64, created_at: i64) {
+        db.conn
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT INTO proxy_request_logs (request_id, provider_id, app_type, model,
+                    latency_ms, status_code, created_at, request_bytes, response_bytes)
+                 VALUES (?1, 'p1', 'codex', 'gpt-5', 10, 200, ?2, 0, ?3)",
+                rusqlite::params![id, created_at, bytes],
+            )
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn daily_alert_fires_once_when_threshold_is_crossed() {
+        let db = Arc::new(Database::memory().unwrap());
+        let events = EventRecorder::spawn(db.clone());
+        let mut provider = Provider::with_id("p1".into(), "acme-p1".into(), json!({}), None);
+        provider.meta = Some(ProviderMeta {
+            bandwidth_daily_alert_bytes: Some(1000),
+            ..Default::default()
+        });
+        let now = 10 * SECS_PER_DAY + 3600;
+        let sent = |response| TransferBytes {
+            request: 0,
+            response,
+        };
+
+        // 昨天的流量不计入
+        insert_log(&db, "old", 5000, now - SECS_PER_DAY);
+        insert_log(&db, "r1", 600, now);
+        check_daily_alert(&db, &events, "codex", &provider, sent(600), now);
+        insert_log(&db, "r2", 600, now);
+        check_daily_alert(&db, &events, "codex", &provider, sent(600), now);
+        insert_log(&db, "r3", 600, now);
+        check_daily_alert(&db, &events, "codex", &provider, sent(600), now);
+        events.flush().await;
+
+        let alerts = db
+            .query_events("codex", Some(EventKind::BandwidthAlert), 0, 10)
+            .unwrap();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].detail["bytes"], 1200);
+        assert_eq!(alerts[0].detail["threshold"], 1000);
+    }
+}
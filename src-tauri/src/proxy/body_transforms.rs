@@ -0,0 +1,195 @@
+//! 供应商请求体变换
+//!
+//! 各家网关接受的 Responses/Chat 字段不尽相同（例如 `store: true`、`parallel_tool_calls` 会被部分网关拒绝）。
+//! 供应商 `settings_config.bodyTransforms` 声明式地删除、设置、重命名字段：
+//!
+//! ```json
+//! {
+//!   "bodyTransforms": {
+//!     "remove": ["store", "include"],
+//!     "set": { "stream_options.include_usage": true },
+//!     "rename": { "max_tokens": "max_output_tokens" }
+//!   }
+//! }
+//! ```
+//!
+//! 路径用 `.` 分隔，只穿过对象，不进入数组。执行顺序：rename → remove → set。
+//! 转发时在模型映射/智能解析之后、发送之前应用；测速探测同样应用，使测速结果与真实请求一致。
+
+use crate::provider::Provider;
+use indexmap::IndexMap;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+/// settings_config 中的变换字段
+pub const BODY_TRANSFORMS_KEY: &str = "bodyTransforms";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct BodyTransforms {
+    pub remove: Vec<String>,
+    pub set: IndexMap<String, Value>,
+    pub rename: IndexMap<String, String>,
+}
+
+impl BodyTransforms {
+    /// 读取供应商配置的变换（未配置、格式错误或为空时返回 None）
+    pub fn from_provider(provider: &Provider) -> Option<Self> {
+        let raw = provider.settings_config.get(BODY_TRANSFORMS_KEY)?;
+        match serde_json::from_value::<Self>(raw.clone()) {
+            Ok(transforms) if !transforms.is_empty() => Some(transforms),
+            Ok(_) => None,
+            Err(e) => {
+                log::warn!(
+                    "[{}] {BODY_TRANSFORMS_KEY} 配置无效，已忽略: {e}",
+                    provider.id
+                );
+                None
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.remove.is_empty() && self.set.is_empty() && self.rename.is_empty()
+    }
+
+    /// 就地应用变换（请求体不是对象时不做处理）
+    pub fn apply(&self, body: &mut Value) {
+        let Some(root) = body.as_object_mut() else {
+            return;
+        };
+        for (from, to) in &self.rename {
+            if let Some(value) = take_path(root, from) {
+                set_path(root, to, value);
+            }
+        }
+        for path in &self.remove {
+            take_path(root, path);
+        }
+        for (path, value) in &self.set {
+            set_path(root, path, value.clone());
+        }
+    }
+}
+
+/// 应用供应商配置的变换（未配置时原样返回）
+pub fn apply_body_transforms(provider: &Provider, mut body: Value) -> Value {
+    if let Some(transforms) = BodyTransforms::from_provider(provider) {
+        transforms.apply(&mut body);
+    }
+    body
+}
+
+/// 取出并删除路径上的值（中间节点缺失或不是对象时返回 None）
+fn take_path(root: &mut Map<String, Value>, path: &str) -> Option<Value> {
+    let (parent, key) = match path.rsplit_once('.') {
+        Some((parent, key)) => (
+            parent
+                .split('.')
+                .try_fold(root, |obj, k| obj.get_mut(k)?.as_object_mut())?,
+            key,
+        ),
+        None => (root, path),
+    };
+    parent.remove(key)
+}
+
+/// 写入路径上的值（缺失的中间对象自动创建；中间节点存在但不是对象时跳过）
+fn set_path(root: &mut Map<String, Value>, path: &str, value: Value) {
+    let mut segments: Vec<&str> = path.split('.').collect();
+    let Some(key) = segments.pop() else {
+        return;
+    };
+    let mut obj = root;
+    for segment in segments {
+        let next = obj
+            .entry(segment)
+            .or_insert_with(|| Value::Object(Map::new()));
+        let Some(next) = next.as_object_mut() else {
+            return;
+        };
+        obj = next;
+    }
+    obj.insert(key.to_string(), value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn provider(transforms: Value) -> Provider {
+        Provider::with_id(
+            "p".to_string(),
+            "p".to_string(),
+            json!({ "bodyTransforms": transforms }),
+            None,
+        )
+    }
+
+    #[test]
+    fn applies_remove_set_and_rename() {
+        let p = provider(json!({
+            "remove": ["store", "include", "missing"],
+            "set": { "stream_options.include_usage": true, "parallel_tool_calls": false },
+            "rename": { "max_tokens": "max_output_tokens" }
+        }));
+        let body = apply_body_transforms(
+            &p,
+            json!({
+                "model": "gpt-5",
+                "store": true,
+                "include": ["reasoning.encrypted_content"],
+                "max_tokens": 512,
+                "parallel_tool_calls": true
+            }),
+        );
+        assert_eq!(
+            body,
+            json!({
+                "model": "gpt-5",
+                "max_output_tokens": 512,
+                "parallel_tool_calls": false,
+                "stream_options": { "include_usage": true }
+            })
+        );
+    }
+
+    #[test]
+    fn nested_paths_and_arrays() {
+        let p = provider(json!({
+            "remove": ["reasoning.summary", "input.0"],
+            "set": { "text.format.type": "text", "input.role": "user" },
+            "rename": { "reasoning.effort": "reasoning_effort" }
+        }));
+        let input = json!([{ "role": "user", "content": "hi" }]);
+        let body = apply_body_transforms(
+            &p,
+            json!({
+                "input": input.clone(),
+                "reasoning": { "effort": "high", "summary": "auto" },
+                "text": { "verbosity": "low" }
+            }),
+        );
+        assert_eq!(body["reasoning_effort"], "high");
+        assert_eq!(body["reasoning"], json!({}));
+        assert_eq!(
+            body["text"],
+            json!({ "verbosity": "low", "format": { "type": "text" } })
+        );
+        // 路径不进入数组：数组原样保留
+        assert_eq!(body["input"], input);
+    }
+
+    #[test]
+    fn absent_or_invalid_config_leaves_body_untouched() {
+        let body = json!({ "store": true });
+        let none = Provider::with_id("p".to_string(), "p".to_string(), json!({}), None);
+        assert_eq!(apply_body_transforms(&none, body.clone()), body);
+        assert!(BodyTransforms::from_provider(&provider(json!({}))).is_none());
+        assert_eq!(
+            apply_body_transforms(&provider(json!({ "remove": "store" })), body.clone()),
+            body
+        );
+    }
+}
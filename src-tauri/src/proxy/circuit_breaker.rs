@@ -1,13 +1,94 @@
 //! 熔断器模块
 //!
 //! 实现熔断器模式，用于防止向不健康的供应商发送请求
+//!
+//! 打开条件（Closed 状态下任一满足）：
+//! - 连续失败次数达到 `failure_threshold`
+//! - 最近 60 秒滑动窗口（10 个 6 秒的桶）内请求数 ≥ `min_requests` 且错误率 ≥ `error_rate_threshold`
+//!   （成功请求穿插在失败之间时连续失败计数会被重置，需靠窗口错误率识别）
 
 use serde::{Deserialize, Serialize};
-use std::sync::atomic::{AtomicU32, Ordering};
-use std::sync::Arc;
-use std::time::Instant;
+use std::sync::atomic::{AtomicI64, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 
+/// 滑动窗口的桶数与每个桶的时长（秒）
+const WINDOW_BUCKETS: usize = 10;
+const WINDOW_BUCKET_SECS: u64 = 6;
+/// 手动强制打开的最长时长（7 天），超出时按上限处理
+pub const MAX_FORCE_OPEN_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// 时钟（测试中注入可手动推进的时钟）
+pub(crate) type Clock = Arc<dyn Fn() -> Instant + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct WindowBucket {
+    /// 桶对应的时间片序号（自窗口起点按 6 秒计）
+    slot: u64,
+    successes: u32,
+    failures: u32,
+}
+
+/// 按时间分桶的请求结果滑动窗口
+#[derive(Debug)]
+struct SlidingWindow {
+    origin: Instant,
+    buckets: [WindowBucket; WINDOW_BUCKETS],
+}
+
+impl SlidingWindow {
+    fn new(origin: Instant) -> Self {
+        Self {
+            origin,
+            buckets: [WindowBucket::default(); WINDOW_BUCKETS],
+        }
+    }
+
+    fn slot(&self, now: Instant) -> u64 {
+        now.saturating_duration_since(self.origin).as_secs() / WINDOW_BUCKET_SECS
+    }
+
+    fn record(&mut self, now: Instant, success: bool) {
+        let slot = self.slot(now);
+        let bucket = &mut self.buckets[(slot % WINDOW_BUCKETS as u64) as usize];
+        if bucket.slot != slot {
+            *bucket = WindowBucket {
+                slot,
+                ..Default::default()
+            };
+        }
+        if success {
+            bucket.successes += 1;
+        } else {
+            bucket.failures += 1;
+        }
+    }
+
+    /// 窗口内的（请求数, 失败数）
+    fn totals(&self, now: Instant) -> (u32, u32) {
+        let current = self.slot(now);
+        self.buckets
+            .iter()
+            .filter(|b| b.slot <= current && b.slot + WINDOW_BUCKETS as u64 > current)
+            .fold((0, 0), |(total, failed), b| {
+                (total + b.successes + b.failures, failed + b.failures)
+            })
+    }
+
+    fn clear(&mut self) {
+        self.buckets = [WindowBucket::default(); WINDOW_BUCKETS];
+    }
+}
+
+fn ratio(failed: u32, total: u32) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        failed as f64 / total as f64
+    }
+}
+
 /// 熔断器状态
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -44,6 +125,13 @@ pub struct CircuitBreakerConfig {
     pub error_rate_threshold: f64,
     /// 最小请求数 - 计算错误率前的最小请求数
     pub min_requests: u32,
+    /// 半开状态下允许同时进行的探测请求数
+    #[serde(default = "default_half_open_max_probes")]
+    pub half_open_max_probes: u32,
+}
+
+fn default_half_open_max_probes() -> u32 {
+    1
 }
 
 impl Default for CircuitBreakerConfig {
@@ -54,6 +142,7 @@ impl Default for CircuitBreakerConfig {
             timeout_seconds: 60,
             error_rate_threshold: 0.5,
             min_requests: 10,
+            half_open_max_probes: default_half_open_max_probes(),
         }
     }
 }
@@ -72,10 +161,21 @@ pub struct CircuitBreaker {
     failed_requests: Arc<AtomicU32>,
     /// 上次打开时间
     last_opened_at: Arc<RwLock<Option<Instant>>>,
+    /// 手动强制打开的截止时间（期间保持 Open，不因冷静期或请求成功而恢复）
+    forced_until: Arc<RwLock<Option<Instant>>>,
     /// 配置（支持热更新）
     config: Arc<RwLock<CircuitBreakerConfig>>,
     /// 半开状态已放行的请求数（用于限流）
     half_open_requests: Arc<AtomicU32>,
+    /// 因熔断器打开而拒绝的请求数（累计，不随状态切换清零）
+    rejected_open: Arc<AtomicU64>,
+    /// 因半开探测名额用尽而拒绝的请求数（累计）
+    rejected_half_open: Arc<AtomicU64>,
+    /// 最近一次状态切换时间（Unix 秒，0 表示从未切换）
+    last_transition_at: Arc<AtomicI64>,
+    /// 最近 60 秒的请求结果（用于错误率判断）
+    window: Arc<Mutex<SlidingWindow>>,
+    clock: Clock,
 }
 
 /// 熔断器放行结果
@@ -86,11 +186,28 @@ pub struct CircuitBreaker {
 pub struct AllowResult {
     pub allowed: bool,
     pub used_half_open_permit: bool,
+    /// 被拒绝时的原因（放行时为 None）
+    pub rejection: Option<BreakerRejection>,
+}
+
+/// 熔断器拒绝放行的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerRejection {
+    /// 熔断器打开（冷静期未到）
+    Open,
+    /// 半开状态探测名额已用尽
+    HalfOpenExhausted,
 }
 
 impl CircuitBreaker {
     /// 创建新的熔断器
     pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self::with_clock(config, Arc::new(Instant::now))
+    }
+
+    /// 使用指定时钟创建熔断器（测试用）
+    pub(crate) fn with_clock(config: CircuitBreakerConfig, clock: Clock) -> Self {
+        let origin = clock();
         Self {
             state: Arc::new(RwLock::new(CircuitState::Closed)),
             consecutive_failures: Arc::new(AtomicU32::new(0)),
@@ -98,11 +215,46 @@ impl CircuitBreaker {
             total_requests: Arc::new(AtomicU32::new(0)),
             failed_requests: Arc::new(AtomicU32::new(0)),
             last_opened_at: Arc::new(RwLock::new(None)),
+            forced_until: Arc::new(RwLock::new(None)),
             config: Arc::new(RwLock::new(config)),
             half_open_requests: Arc::new(AtomicU32::new(0)),
+            rejected_open: Arc::new(AtomicU64::new(0)),
+            rejected_half_open: Arc::new(AtomicU64::new(0)),
+            last_transition_at: Arc::new(AtomicI64::new(0)),
+            window: Arc::new(Mutex::new(SlidingWindow::new(origin))),
+            clock,
         }
     }
 
+    fn now(&self) -> Instant {
+        (self.clock)()
+    }
+
+    /// Open 状态是否已过冷静期（强制打开时以截止时间为准）
+    async fn open_timeout_elapsed(&self, timeout_seconds: u64) -> bool {
+        if let Some(until) = *self.forced_until.read().await {
+            return self.now() >= until;
+        }
+        match *self.last_opened_at.read().await {
+            Some(opened_at) => {
+                self.now().saturating_duration_since(opened_at).as_secs() >= timeout_seconds
+            }
+            None => false,
+        }
+    }
+
+    fn record_in_window(&self, success: bool) -> (u32, u32) {
+        let now = self.now();
+        let mut window = self.window.lock().unwrap_or_else(|e| e.into_inner());
+        window.record(now, success);
+        window.totals(now)
+    }
+
+    fn window_totals(&self) -> (u32, u32) {
+        let window = self.window.lock().unwrap_or_else(|e| e.into_inner());
+        window.totals(self.now())
+    }
+
     /// 更新熔断器配置（热更新，不重置状态）
     pub async fn update_config(&self, new_config: CircuitBreakerConfig) {
         *self.config.write().await = new_config;
@@ -124,15 +276,14 @@ impl CircuitBreaker {
         match state {
             CircuitState::Closed | CircuitState::HalfOpen => true,
             CircuitState::Open => {
-                if let Some(opened_at) = *self.last_opened_at.read().await {
-                    if opened_at.elapsed().as_secs() >= config.timeout_seconds {
-                        drop(config); // 释放读锁再转换状态
-                        log::info!(
-                            "Circuit breaker transitioning from Open to HalfOpen (timeout reached)"
-                        );
-                        self.transition_to_half_open().await;
-                        return true;
-                    }
+                let timeout_seconds = config.timeout_seconds;
+                drop(config); // 释放读锁再转换状态
+                if self.open_timeout_elapsed(timeout_seconds).await {
+                    log::info!(
+                        "Circuit breaker transitioning from Open to HalfOpen (timeout reached)"
+                    );
+                    self.transition_to_half_open().await;
+                    return true;
                 }
                 false
             }
@@ -147,40 +298,33 @@ impl CircuitBreaker {
             CircuitState::Closed => AllowResult {
                 allowed: true,
                 used_half_open_permit: false,
+                rejection: None,
             },
             CircuitState::Open => {
-                let config = self.config.read().await;
+                let timeout_seconds = self.config.read().await.timeout_seconds;
                 // 检查是否应该尝试半开
-                if let Some(opened_at) = *self.last_opened_at.read().await {
-                    if opened_at.elapsed().as_secs() >= config.timeout_seconds {
-                        drop(config); // 释放读锁再转换状态
-                        log::info!(
-                            "Circuit breaker transitioning from Open to HalfOpen (timeout reached)"
-                        );
-                        self.transition_to_half_open().await;
-
-                        // 转换后按当前状态决定是否需要获取 HalfOpen 探测名额
-                        let current_state = *self.state.read().await;
-                        return match current_state {
-                            CircuitState::Closed => AllowResult {
-                                allowed: true,
-                                used_half_open_permit: false,
-                            },
-                            CircuitState::HalfOpen => self.allow_half_open_probe(),
-                            CircuitState::Open => AllowResult {
-                                allowed: false,
-                                used_half_open_permit: false,
-                            },
-                        };
-                    }
-                }
+                if self.open_timeout_elapsed(timeout_seconds).await {
+                    log::info!(
+                        "Circuit breaker transitioning from Open to HalfOpen (timeout reached)"
+                    );
+                    self.transition_to_half_open().await;
 
-                AllowResult {
-                    allowed: false,
-                    used_half_open_permit: false,
+                    // 转换后按当前状态决定是否需要获取 HalfOpen 探测名额
+                    let current_state = *self.state.read().await;
+                    return match current_state {
+                        CircuitState::Closed => AllowResult {
+                            allowed: true,
+                            used_half_open_permit: false,
+                            rejection: None,
+                        },
+                        CircuitState::HalfOpen => self.allow_half_open_probe().await,
+                        CircuitState::Open => self.reject(BreakerRejection::Open),
+                    };
                 }
+
+                self.reject(BreakerRejection::Open)
             }
-            CircuitState::HalfOpen => self.allow_half_open_probe(),
+            CircuitState::HalfOpen => self.allow_half_open_probe().await,
         }
     }
 
@@ -196,6 +340,7 @@ impl CircuitBreaker {
         // 重置失败计数
         self.consecutive_failures.store(0, Ordering::SeqCst);
         self.total_requests.fetch_add(1, Ordering::SeqCst);
+        self.record_in_window(true);
 
         match state {
             CircuitState::HalfOpen => {
@@ -232,6 +377,7 @@ impl CircuitBreaker {
         let failures = self.consecutive_failures.fetch_add(1, Ordering::SeqCst) + 1;
         self.total_requests.fetch_add(1, Ordering::SeqCst);
         self.failed_requests.fetch_add(1, Ordering::SeqCst);
+        let (window_total, window_failed) = self.record_in_window(false);
 
         // 重置成功计数
         self.consecutive_successes.store(0, Ordering::SeqCst);
@@ -262,17 +408,14 @@ impl CircuitBreaker {
                     drop(config); // 释放读锁再转换状态
                     self.transition_to_open().await;
                 } else {
-                    // 检查错误率
-                    let total = self.total_requests.load(Ordering::SeqCst);
-                    let failed = self.failed_requests.load(Ordering::SeqCst);
-
-                    if total >= config.min_requests {
-                        let error_rate = failed as f64 / total as f64;
+                    // 检查滑动窗口错误率
+                    if window_total >= config.min_requests {
+                        let error_rate = ratio(window_failed, window_total);
                         log::debug!(
-                            "Circuit breaker error rate: {:.2}% ({}/{} requests)",
+                            "Circuit breaker window error rate: {:.2}% ({}/{} requests)",
                             error_rate * 100.0,
-                            failed,
-                            total
+                            window_failed,
+                            window_total
                         );
 
                         if error_rate >= config.error_rate_threshold {
@@ -298,14 +441,32 @@ impl CircuitBreaker {
     }
 
     /// 获取统计信息
-    #[allow(dead_code)]
     pub async fn get_stats(&self) -> CircuitBreakerStats {
+        let total_requests = self.total_requests.load(Ordering::SeqCst);
+        let failed_requests = self.failed_requests.load(Ordering::SeqCst);
+        let last_transition_at = self.last_transition_at.load(Ordering::SeqCst);
+        let (window_requests, window_failed) = self.window_totals();
+        // 已到期但尚未有请求触发 HalfOpen 转换时不再视为强制打开
+        let now = self.now();
+        let forced_until = (*self.forced_until.read().await)
+            .filter(|until| *until > now)
+            .map(|until| {
+                chrono::Utc::now().timestamp() + until.duration_since(now).as_secs() as i64
+            });
         CircuitBreakerStats {
             state: *self.state.read().await,
             consecutive_failures: self.consecutive_failures.load(Ordering::SeqCst),
             consecutive_successes: self.consecutive_successes.load(Ordering::SeqCst),
-            total_requests: self.total_requests.load(Ordering::SeqCst),
-            failed_requests: self.failed_requests.load(Ordering::SeqCst),
+            total_requests,
+            failed_requests,
+            error_rate: ratio(failed_requests, total_requests),
+            window_requests,
+            window_error_rate: ratio(window_failed, window_requests),
+            rejected_open: self.rejected_open.load(Ordering::Relaxed),
+            rejected_half_open: self.rejected_half_open.load(Ordering::Relaxed),
+            forced: forced_until.is_some(),
+            forced_until,
+            last_transition_at: (last_transition_at > 0).then_some(last_transition_at),
         }
     }
 
@@ -316,10 +477,24 @@ impl CircuitBreaker {
         self.transition_to_closed().await;
     }
 
-    fn allow_half_open_probe(&self) -> AllowResult {
-        // 半开状态限流：只允许有限请求通过进行探测
-        // 默认最多允许 1 个请求（可在配置中扩展）
-        let max_half_open_requests = 1u32;
+    /// 手动强制打开熔断器，持续 `duration`
+    ///
+    /// 到期后进入 HalfOpen 按常规探测恢复；`reset()` 可提前解除。
+    /// 时长超过 [`MAX_FORCE_OPEN_SECS`] 时按上限处理。
+    pub async fn force_open(&self, duration: Duration) {
+        let duration = duration.min(Duration::from_secs(MAX_FORCE_OPEN_SECS));
+        let Some(until) = self.now().checked_add(duration) else {
+            log::warn!("Circuit breaker force open ignored: duration out of range");
+            return;
+        };
+        *self.forced_until.write().await = Some(until);
+        self.transition_to_open().await;
+        log::warn!("Circuit breaker forced open for {}s", duration.as_secs());
+    }
+
+    async fn allow_half_open_probe(&self) -> AllowResult {
+        // 半开状态限流：只允许有限请求通过进行探测（默认 1 个，可按应用配置）
+        let max_half_open_requests = self.config.read().await.half_open_max_probes.max(1);
         let current = self.half_open_requests.fetch_add(1, Ordering::SeqCst);
 
         if current < max_half_open_requests {
@@ -331,6 +506,7 @@ impl CircuitBreaker {
             AllowResult {
                 allowed: true,
                 used_half_open_permit: true,
+                rejection: None,
             }
         } else {
             // 超过限额，回退计数，拒绝请求
@@ -338,10 +514,21 @@ impl CircuitBreaker {
             log::debug!(
                 "Circuit breaker HalfOpen: rejecting request (limit reached: {max_half_open_requests})"
             );
-            AllowResult {
-                allowed: false,
-                used_half_open_permit: false,
-            }
+            self.reject(BreakerRejection::HalfOpenExhausted)
+        }
+    }
+
+    /// 拒绝放行并按原因计数
+    fn reject(&self, reason: BreakerRejection) -> AllowResult {
+        let counter = match reason {
+            BreakerRejection::Open => &self.rejected_open,
+            BreakerRejection::HalfOpenExhausted => &self.rejected_half_open,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        AllowResult {
+            allowed: false,
+            used_half_open_permit: false,
+            rejection: Some(reason),
         }
     }
 
@@ -366,10 +553,16 @@ impl CircuitBreaker {
         }
     }
 
+    fn mark_transition(&self) {
+        self.last_transition_at
+            .store(chrono::Utc::now().timestamp(), Ordering::SeqCst);
+    }
+
     /// 转换到打开状态
     async fn transition_to_open(&self) {
         *self.state.write().await = CircuitState::Open;
-        *self.last_opened_at.write().await = Some(Instant::now());
+        self.mark_transition();
+        *self.last_opened_at.write().await = Some(self.now());
         self.consecutive_failures.store(0, Ordering::SeqCst);
         self.consecutive_successes.store(0, Ordering::SeqCst);
     }
@@ -382,6 +575,8 @@ impl CircuitBreaker {
         }
 
         *state = CircuitState::HalfOpen;
+        self.mark_transition();
+        *self.forced_until.write().await = None;
         self.consecutive_successes.store(0, Ordering::SeqCst);
         // 重置半开状态的请求限流计数
         self.half_open_requests.store(0, Ordering::SeqCst);
@@ -390,11 +585,18 @@ impl CircuitBreaker {
     /// 转换到关闭状态
     async fn transition_to_closed(&self) {
         *self.state.write().await = CircuitState::Closed;
+        self.mark_transition();
+        *self.forced_until.write().await = None;
         self.consecutive_failures.store(0, Ordering::SeqCst);
         self.consecutive_successes.store(0, Ordering::SeqCst);
         // 重置计数器
         self.total_requests.store(0, Ordering::SeqCst);
         self.failed_requests.store(0, Ordering::SeqCst);
+        // 恢复后重新统计窗口，避免打开前的失败立即再次触发熔断
+        self.window
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
     }
 }
 
@@ -407,6 +609,100 @@ pub struct CircuitBreakerStats {
     pub consecutive_successes: u32,
     pub total_requests: u32,
     pub failed_requests: u32,
+    /// 自上次关闭以来的错误率（0.0-1.0，关闭时计数清零）
+    #[serde(default)]
+    pub error_rate: f64,
+    /// 最近 60 秒滑动窗口内的请求数
+    #[serde(default)]
+    pub window_requests: u32,
+    /// 最近 60 秒滑动窗口内的错误率（0.0-1.0，用于判断是否打开熔断器）
+    #[serde(default)]
+    pub window_error_rate: f64,
+    /// 因熔断器打开而拒绝的请求数（累计）
+    #[serde(default)]
+    pub rejected_open: u64,
+    /// 因半开探测名额用尽而拒绝的请求数（累计）
+    #[serde(default)]
+    pub rejected_half_open: u64,
+    /// 是否处于手动强制打开状态
+    #[serde(default)]
+    pub forced: bool,
+    /// 强制打开的截止时间（Unix 秒）
+    #[serde(default)]
+    pub forced_until: Option<i64>,
+    /// 最近一次状态切换时间（Unix 秒）
+    #[serde(default)]
+    pub last_transition_at: Option<i64>,
+}
+
+/// 某个供应商的熔断器统计（批量查询用）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderCircuitBreakerStats {
+    pub provider_id: String,
+    #[serde(flatten)]
+    pub stats: CircuitBreakerStats,
+}
+
+/// 将各供应商熔断器统计渲染为 Prometheus 文本格式（`/metrics`）
+///
+/// `entries` 为（应用, 供应商 id, 统计）列表
+pub(crate) fn render_prometheus_metrics(
+    entries: &[(String, String, CircuitBreakerStats)],
+) -> String {
+    fn label(value: &str) -> String {
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    }
+
+    let mut out = String::new();
+    out.push_str(
+        "# HELP cc_switch_circuit_breaker_state 熔断器状态（0=closed，1=half_open，2=open）\n",
+    );
+    out.push_str("# TYPE cc_switch_circuit_breaker_state gauge\n");
+    for (app, provider, stats) in entries {
+        let value = match stats.state {
+            CircuitState::Closed => 0,
+            CircuitState::HalfOpen => 1,
+            CircuitState::Open => 2,
+        };
+        out.push_str(&format!(
+            "cc_switch_circuit_breaker_state{{app=\"{}\",provider=\"{}\"}} {value}\n",
+            label(app),
+            label(provider)
+        ));
+    }
+
+    out.push_str("# HELP cc_switch_circuit_breaker_window_error_rate 最近 60 秒错误率\n");
+    out.push_str("# TYPE cc_switch_circuit_breaker_window_error_rate gauge\n");
+    for (app, provider, stats) in entries {
+        out.push_str(&format!(
+            "cc_switch_circuit_breaker_window_error_rate{{app=\"{}\",provider=\"{}\"}} {}\n",
+            label(app),
+            label(provider),
+            stats.window_error_rate
+        ));
+    }
+
+    out.push_str(
+        "# HELP cc_switch_circuit_breaker_rejections_total 熔断器拒绝的请求数（按原因）\n",
+    );
+    out.push_str("# TYPE cc_switch_circuit_breaker_rejections_total counter\n");
+    for (app, provider, stats) in entries {
+        for (reason, count) in [
+            ("open", stats.rejected_open),
+            ("half_open", stats.rejected_half_open),
+        ] {
+            out.push_str(&format!(
+                "cc_switch_circuit_breaker_rejections_total{{app=\"{}\",provider=\"{}\",reason=\"{reason}\"}} {count}\n",
+                label(app),
+                label(provider)
+            ));
+        }
+    }
+    out
 }
 
 #[cfg(test)]
@@ -503,4 +799,228 @@ mod tests {
         assert_eq!(breaker.get_state().await, CircuitState::Closed);
         assert!(breaker.allow_request().await.allowed);
     }
+
+    /// 可手动推进的测试时钟
+    fn manual_clock() -> (Arc<Mutex<Instant>>, Clock) {
+        let now = Arc::new(Mutex::new(Instant::now()));
+        let handle = now.clone();
+        (now, Arc::new(move || *handle.lock().unwrap()))
+    }
+
+    fn advance(now: &Mutex<Instant>, secs: u64) {
+        *now.lock().unwrap() += std::time::Duration::from_secs(secs);
+    }
+
+    fn window_config() -> CircuitBreakerConfig {
+        CircuitBreakerConfig {
+            failure_threshold: 5,
+            success_threshold: 2,
+            timeout_seconds: 30,
+            error_rate_threshold: 0.5,
+            min_requests: 10,
+            half_open_max_probes: 1,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_window_error_rate_opens_with_interleaved_successes() {
+        let (now, clock) = manual_clock();
+        let breaker = CircuitBreaker::with_clock(window_config(), clock);
+
+        // 成功/失败交替（50% 失败），连续失败从未超过 1
+        for i in 0..9 {
+            advance(&now, 1);
+            if i % 2 == 0 {
+                breaker.record_success(false).await;
+            } else {
+                breaker.record_failure(false).await;
+            }
+        }
+        // 9 次请求未达到 min_requests
+        assert_eq!(breaker.get_state().await, CircuitState::Closed);
+
+        advance(&now, 1);
+        breaker.record_failure(false).await;
+        assert_eq!(breaker.get_state().await, CircuitState::Open);
+
+        let stats = breaker.get_stats().await;
+        assert_eq!(stats.window_requests, 10);
+        assert!((stats.window_error_rate - 0.5).abs() < f64::EPSILON);
+        assert!(!breaker.allow_request().await.allowed);
+
+        // 冷静期后进入 HalfOpen，探测成功后恢复 Closed 并清空窗口
+        advance(&now, 30);
+        let probe = breaker.allow_request().await;
+        assert!(probe.allowed && probe.used_half_open_permit);
+        assert_eq!(breaker.get_state().await, CircuitState::HalfOpen);
+        breaker.record_success(true).await;
+        let probe = breaker.allow_request().await;
+        assert!(probe.allowed);
+        breaker.record_success(probe.used_half_open_permit).await;
+        assert_eq!(breaker.get_state().await, CircuitState::Closed);
+
+        let stats = breaker.get_stats().await;
+        assert_eq!(stats.window_requests, 0);
+        assert_eq!(stats.window_error_rate, 0.0);
+
+        // 恢复后的单次失败不会因旧窗口数据再次熔断
+        breaker.record_failure(false).await;
+        assert_eq!(breaker.get_state().await, CircuitState::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_window_expires_old_failures() {
+        let (now, clock) = manual_clock();
+        let breaker = CircuitBreaker::with_clock(window_config(), clock);
+
+        // 前 60 秒内累积 8 次请求（4 次失败）
+        for i in 0..8 {
+            if i % 2 == 0 {
+                breaker.record_success(false).await;
+            } else {
+                breaker.record_failure(false).await;
+            }
+        }
+        assert_eq!(breaker.get_stats().await.window_requests, 8);
+
+        // 窗口滑过后旧数据失效
+        advance(&now, 60);
+        assert_eq!(breaker.get_stats().await.window_requests, 0);
+
+        // 新窗口内仅 2 次失败，不满足 min_requests，不应熔断
+        breaker.record_failure(false).await;
+        breaker.record_success(false).await;
+        breaker.record_failure(false).await;
+        assert_eq!(breaker.get_state().await, CircuitState::Closed);
+
+        let stats = breaker.get_stats().await;
+        assert_eq!(stats.window_requests, 3);
+        // 累计错误率仍包含窗口外的请求
+        assert_eq!(stats.total_requests, 11);
+    }
+
+    #[tokio::test]
+    async fn test_half_open_allows_configured_probe_count() {
+        let config = CircuitBreakerConfig {
+            failure_threshold: 1,
+            timeout_seconds: 0,
+            half_open_max_probes: 3,
+            ..Default::default()
+        };
+        let breaker = CircuitBreaker::new(config);
+        breaker.record_failure(false).await;
+
+        // 冷静期为 0：首次请求即切到 HalfOpen 并占用名额
+        let permits: Vec<_> =
+            futures::future::join_all((0..3).map(|_| breaker.allow_request())).await;
+        assert!(permits.iter().all(|p| p.allowed && p.used_half_open_permit));
+        assert_eq!(breaker.get_state().await, CircuitState::HalfOpen);
+
+        let rejected = breaker.allow_request().await;
+        assert!(!rejected.allowed);
+        assert_eq!(
+            rejected.rejection,
+            Some(BreakerRejection::HalfOpenExhausted)
+        );
+
+        // 释放一个名额后可再放行一个探测
+        breaker.record_success(true).await;
+        let again = breaker.allow_request().await;
+        assert!(again.allowed && again.used_half_open_permit);
+
+        let stats = breaker.get_stats().await;
+        assert_eq!(stats.rejected_half_open, 1);
+        assert_eq!(stats.rejected_open, 0);
+    }
+
+    #[tokio::test]
+    async fn test_rejection_counters_by_reason() {
+        let (now, clock) = manual_clock();
+        let config = CircuitBreakerConfig {
+            failure_threshold: 1,
+            timeout_seconds: 30,
+            ..Default::default()
+        };
+        let breaker = CircuitBreaker::with_clock(config, clock);
+        breaker.record_failure(false).await;
+        assert_eq!(breaker.get_state().await, CircuitState::Open);
+
+        for _ in 0..2 {
+            let result = breaker.allow_request().await;
+            assert!(!result.allowed);
+            assert_eq!(result.rejection, Some(BreakerRejection::Open));
+        }
+
+        // 进入 HalfOpen 后默认仅 1 个探测名额
+        advance(&now, 30);
+        let probe = breaker.allow_request().await;
+        assert!(probe.allowed && probe.rejection.is_none());
+        let rejected = breaker.allow_request().await;
+        assert_eq!(
+            rejected.rejection,
+            Some(BreakerRejection::HalfOpenExhausted)
+        );
+
+        // 计数累计保留，不随恢复清零
+        breaker.record_success(true).await;
+        breaker.record_success(false).await;
+        assert_eq!(breaker.get_state().await, CircuitState::Closed);
+        let stats = breaker.get_stats().await;
+        assert_eq!(stats.rejected_open, 2);
+        assert_eq!(stats.rejected_half_open, 1);
+
+        let metrics =
+            render_prometheus_metrics(&[("claude".to_string(), "p\"1".to_string(), stats)]);
+        assert!(metrics
+            .contains("cc_switch_circuit_breaker_state{app=\"claude\",provider=\"p\\\"1\"} 0\n"));
+        assert!(metrics.contains(
+            "cc_switch_circuit_breaker_rejections_total{app=\"claude\",provider=\"p\\\"1\",reason=\"open\"} 2\n"
+        ));
+        assert!(metrics.contains(
+            "cc_switch_circuit_breaker_rejections_total{app=\"claude\",provider=\"p\\\"1\",reason=\"half_open\"} 1\n"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_force_open_holds_until_deadline() {
+        let (now, clock) = manual_clock();
+        let config = CircuitBreakerConfig {
+            timeout_seconds: 0,
+            success_threshold: 1,
+            ..Default::default()
+        };
+        let breaker = CircuitBreaker::with_clock(config, clock);
+
+        breaker.force_open(Duration::from_secs(60)).await;
+        assert_eq!(breaker.get_state().await, CircuitState::Open);
+        assert!(breaker.get_stats().await.forced);
+
+        // 冷静期为 0 也不会提前进入 HalfOpen，请求成功也不会关闭
+        advance(&now, 59);
+        breaker.record_success(false).await;
+        assert!(!breaker.is_available().await);
+        assert!(!breaker.allow_request().await.allowed);
+        assert_eq!(breaker.get_state().await, CircuitState::Open);
+
+        // 到期后按常规半开探测恢复
+        advance(&now, 1);
+        assert!(!breaker.get_stats().await.forced);
+        let probe = breaker.allow_request().await;
+        assert!(probe.allowed && probe.used_half_open_permit);
+        breaker.record_success(true).await;
+        assert_eq!(breaker.get_state().await, CircuitState::Closed);
+
+        // reset 可提前解除强制熔断
+        breaker.force_open(Duration::from_secs(60)).await;
+        breaker.reset().await;
+        let stats = breaker.get_stats().await;
+        assert_eq!(stats.state, CircuitState::Closed);
+        assert!(!stats.forced);
+        // 超长时长按上限处理，不会溢出 panic
+        breaker.force_open(Duration::MAX).await;
+        advance(&now, MAX_FORCE_OPEN_SECS - 1);
+        assert!(breaker.get_stats().await.forced);
+        advance(&now, 1);
+        assert!(!breaker.get_stats().await.forced);
+    }
 }
@@ -0,0 +1,393 @@
+//! 供应商 A/B 对比（`csc compare`）
+//!
+//! 把同一个提示词构造成该应用的标准请求，依次固定到每个供应商后经运行中的代理发出
+//! （与 `csc replay` 共用按请求 ID 的供应商固定，绕过故障转移且不影响正常流量），汇总状态码、耗时、usage、
+//! 实际模型与回复开头。某个供应商失败不影响其余供应商。
+
+use super::replay;
+use super::usage::parser::TokenUsage;
+use crate::app_config::AppType;
+use crate::database::RequestCapture;
+use crate::error::AppError;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// 读取响应体的上限（字节）：足够容纳非流式回复，避免异常响应占满内存
+const MAX_RESPONSE_BYTES: usize = 1024 * 1024;
+
+/// Claude 请求未指定输出上限时使用的 max_tokens
+const DEFAULT_MAX_TOKENS: u32 = 1024;
+
+/// 单个供应商的对比结果
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompareSide {
+    pub provider_id: String,
+    /// 未能发出请求（代理不可达、固定供应商失败等）时为 None
+    pub status: Option<u16>,
+    pub latency_ms: Option<u64>,
+    pub input_tokens: Option<u32>,
+    pub output_tokens: Option<u32>,
+    /// 响应中的实际模型
+    pub effective_model: Option<String>,
+    /// 回复正文开头（无法提取正文时为响应体开头）
+    pub preview: String,
+    /// 回复是否超过预览长度
+    pub preview_truncated: bool,
+    pub error: Option<String>,
+}
+
+/// 由提示词构造该应用的非流式请求；Gemini 的模型在 URI 中，必须指定模型
+pub fn build_prompt_request(
+    app_type: &AppType,
+    prompt: &str,
+    model: Option<&str>,
+) -> Result<RequestCapture, AppError> {
+    let model = model.map(str::trim).filter(|m| !m.is_empty());
+    let (endpoint, mut body) = match app_type {
+        AppType::Claude => (
+            "/v1/messages".to_string(),
+            json!({
+                "max_tokens": DEFAULT_MAX_TOKENS,
+                "messages": [{ "role": "user", "content": prompt }],
+                "stream": false,
+            }),
+        ),
+        AppType::Codex => (
+            "/v1/responses".to_string(),
+            json!({ "input": prompt, "stream": false }),
+        ),
+        AppType::Gemini => {
+            let model = model.ok_or_else(|| {
+                AppError::Message("Gemini 对比需使用 --model 指定模型".to_string())
+            })?;
+            return Ok(capture(
+                app_type,
+                format!("/v1beta/models/{model}:generateContent"),
+                json!({ "contents": [{ "role": "user", "parts": [{ "text": prompt }] }] }),
+            ));
+        }
+    };
+    // 未指定模型时由代理按默认模型补全
+    if let Some(model) = model {
+        body["model"] = Value::String(model.to_string());
+    }
+    Ok(capture(app_type, endpoint, body))
+}
+
+fn capture(app_type: &AppType, endpoint: String, body: Value) -> RequestCapture {
+    RequestCapture {
+        request_id: String::new(),
+        app_type: app_type.as_str().to_string(),
+        endpoint,
+        headers: Default::default(),
+        body,
+        truncated: false,
+        created_at: 0,
+    }
+}
+
+/// 提取回复正文（Claude content、Responses output、Chat choices、Gemini candidates）
+pub fn extract_text(body: &Value) -> Option<String> {
+    let join = |parts: Vec<&str>| {
+        let text = parts.concat();
+        (!text.is_empty()).then_some(text)
+    };
+
+    if let Some(content) = body.get("content").and_then(Value::as_array) {
+        return join(content.iter().filter_map(|c| c["text"].as_str()).collect());
+    }
+    if let Some(text) = body.get("output_text").and_then(Value::as_str) {
+        return Some(text.to_string());
+    }
+    if let Some(output) = body.get("output").and_then(Value::as_array) {
+        return join(
+            output
+                .iter()
+                .filter_map(|item| item["content"].as_array())
+                .flatten()
+                .filter_map(|c| c["text"].as_str())
+                .collect(),
+        );
+    }
+    if let Some(choices) = body.get("choices").and_then(Value::as_array) {
+        return choices
+            .first()
+            .and_then(|c| c["message"]["content"].as_str())
+            .map(str::to_string);
+    }
+    if let Some(candidates) = body.get("candidates").and_then(Value::as_array) {
+        return join(
+            candidates
+                .first()
+                .and_then(|c| c["content"]["parts"].as_array())
+                .map(|parts| parts.iter().filter_map(|p| p["text"].as_str()).collect())
+                .unwrap_or_default(),
+        );
+    }
+    None
+}
+
+/// 按字符截断（不截断多字节字符）
+fn truncate_chars(text: &str, max_chars: usize) -> (String, bool) {
+    match text.char_indices().nth(max_chars) {
+        Some((idx, _)) => (text[..idx].to_string(), true),
+        None => (text.to_string(), false),
+    }
+}
+
+/// 由重放结果生成对比行
+fn side_from_outcome(
+    app_type: &AppType,
+    provider_id: &str,
+    outcome: replay::ReplayOutcome,
+    preview_chars: usize,
+) -> CompareSide {
+    let parsed = serde_json::from_str::<Value>(&outcome.preview).ok();
+    let usage = parsed
+        .as_ref()
+        .and_then(|body| TokenUsage::from_app_response(app_type, body));
+    let effective_model = usage.as_ref().and_then(|u| u.model.clone()).or_else(|| {
+        parsed.as_ref().and_then(|body| {
+            body.get("model")
+                .or_else(|| body.get("modelVersion"))
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        })
+    });
+    let text = parsed
+        .as_ref()
+        .and_then(extract_text)
+        .unwrap_or_else(|| outcome.preview.clone());
+    let (preview, truncated) = truncate_chars(&text, preview_chars);
+    let success = (200..300).contains(&outcome.status);
+
+    CompareSide {
+        provider_id: provider_id.to_string(),
+        status: Some(outcome.status),
+        latency_ms: Some(outcome.latency_ms),
+        input_tokens: usage.as_ref().map(|u| u.input_tokens),
+        output_tokens: usage.as_ref().map(|u| u.output_tokens),
+        effective_model,
+        preview,
+        preview_truncated: truncated || outcome.preview_truncated,
+        error: (!success).then(|| format!("HTTP {}", outcome.status)),
+    }
+}
+
+/// 依次把同一请求固定到每个供应商，经运行中的代理（`base`）发出
+pub async fn compare(
+    client: &reqwest::Client,
+    base: &str,
+    app_type: &AppType,
+    provider_ids: &[String],
+    request: &RequestCapture,
+    preview_chars: usize,
+) -> Vec<CompareSide> {
+    let mut sides = Vec::with_capacity(provider_ids.len());
+    for provider_id in provider_ids {
+        let side = match replay::replay(
+            client,
+            base,
+            app_type,
+            provider_id,
+            request,
+            MAX_RESPONSE_BYTES,
+        )
+        .await
+        {
+            Ok(outcome) => side_from_outcome(app_type, provider_id, outcome, preview_chars),
+            Err(e) => CompareSide {
+                provider_id: provider_id.clone(),
+                error: Some(e.to_string()),
+                ..Default::default()
+            },
+        };
+        sides.push(side);
+    }
+    sides
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+    use crate::provider::Provider;
+    use crate::proxy::server::ProxyServer;
+    use crate::proxy::types::ProxyConfig;
+    use axum::{routing::post, Json, Router};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn build_prompt_request_per_app() {
+        let claude =
+            build_prompt_request(&AppType::Claude, "hi", Some("claude-sonnet-4-5")).unwrap();
+        assert_eq!(claude.endpoint, "/v1/messages");
+        assert_eq!(claude.body["model"], "claude-sonnet-4-5");
+        assert_eq!(claude.body["messages"][0]["content"], "hi");
+        assert_eq!(claude.body["stream"], false);
+
+        let codex = build_prompt_request(&AppType::Codex, "hi", None).unwrap();
+        assert_eq!(codex.endpoint, "/v1/responses");
+        assert!(codex.body.get("model").is_none());
+
+        let gemini = build_prompt_request(&AppType::Gemini, "hi", Some("gemini-2.5-pro")).unwrap();
+        assert_eq!(
+            gemini.endpoint,
+            "/v1beta/models/gemini-2.5-pro:generateContent"
+        );
+        assert!(build_prompt_request(&AppType::Gemini, "hi", None).is_err());
+    }
+
+    #[test]
+    fn extract_text_handles_each_response_shape() {
+        assert_eq!(
+            extract_text(&json!({"content": [{"type": "text", "text": "a"}, {"text": "b"}]})),
+            Some("ab".to_string())
+        );
+        assert_eq!(
+            extract_text(&json!({"output": [{"content": [{"type": "output_text", "text": "r"}]}]})),
+            Some("r".to_string())
+        );
+        assert_eq!(
+            extract_text(&json!({"choices": [{"message": {"content": "c"}}]})),
+            Some("c".to_string())
+        );
+        assert_eq!(
+            extract_text(&json!({"candidates": [{"content": {"parts": [{"text": "g"}]}}]})),
+            Some("g".to_string())
+        );
+        assert_eq!(extract_text(&json!({"error": "x"})), None);
+        assert_eq!(truncate_chars("你好世界", 2), ("你好".to_string(), true));
+    }
+
+    fn codex_provider(id: &str, base_url: &str) -> Provider {
+        Provider::with_id(
+            id.to_string(),
+            id.to_string(),
+            json!({ "base_url": base_url, "env": { "OPENAI_API_KEY": format!("sk-{id}") } }),
+            None,
+        )
+    }
+
+    #[tokio::test]
+    async fn compare_reports_each_provider_independently() {
+        // fast 立即回复；slow 延迟 300ms 且使用不同模型；broken 返回 500
+        let app = Router::new()
+            .route(
+                "/fast/v1/responses",
+                post(|Json(body): Json<Value>| async move {
+                    Json(json!({
+                        "model": body["model"],
+                        "output": [{ "content": [{ "type": "output_text", "text": "fast answer" }] }],
+                        "usage": { "input_tokens": 5, "output_tokens": 2 }
+                    }))
+                }),
+            )
+            .route(
+                "/slow/v1/responses",
+                post(|| async {
+                    tokio::time::sleep(Duration::from_millis(300)).await;
+                    Json(json!({
+                        "model": "gpt-5-mini",
+                        "output": [{ "content": [{ "type": "output_text", "text": "a much slower answer" }] }],
+                        "usage": { "input_tokens": 5, "output_tokens": 9 }
+                    }))
+                }),
+            )
+            .route(
+                "/broken/v1/responses",
+                post(|| async {
+                    (
+                        axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(json!({ "error": { "message": "boom" } })),
+                    )
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let upstream = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+
+        let db = Arc::new(Database::memory().unwrap());
+        for id in ["fast", "slow", "broken"] {
+            db.save_provider("codex", &codex_provider(id, &format!("{upstream}/{id}/v1")))
+                .unwrap();
+        }
+        db.set_current_provider("codex", "fast").unwrap();
+
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let server = ProxyServer::new(
+            ProxyConfig {
+                listen_port: port,
+                ..Default::default()
+            },
+            db,
+            None,
+        );
+        server.start().await.unwrap();
+        let base = format!("http://127.0.0.1:{port}");
+
+        let request = build_prompt_request(&AppType::Codex, "hello", Some("gpt-5")).unwrap();
+        let ids: Vec<String> = ["broken", "fast", "slow", "missing"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let sides = compare(
+            &reqwest::Client::new(),
+            &base,
+            &AppType::Codex,
+            &ids,
+            &request,
+            6,
+        )
+        .await;
+        server.stop().await.unwrap();
+
+        assert_eq!(sides.len(), 4);
+        let [broken, fast, slow, missing] = &sides[..] else {
+            unreachable!()
+        };
+
+        assert_eq!(broken.status, Some(500));
+        assert_eq!(broken.error.as_deref(), Some("HTTP 500"));
+
+        assert_eq!(fast.status, Some(200), "{fast:?}");
+        assert_eq!(fast.error, None);
+        assert_eq!(fast.effective_model.as_deref(), Some("gpt-5"));
+        assert_eq!((fast.input_tokens, fast.output_tokens), (Some(5), Some(2)));
+        assert_eq!(fast.preview, "fast a");
+        assert!(fast.preview_truncated);
+
+        assert_eq!(slow.status, Some(200), "{slow:?}");
+        assert_eq!(slow.effective_model.as_deref(), Some("gpt-5-mini"));
+        assert_eq!(slow.output_tokens, Some(9));
+        assert!(slow.latency_ms.unwrap() >= 300);
+        assert!(slow.latency_ms > fast.latency_ms);
+
+        // 供应商不存在：记录错误，不影响其它供应商
+        assert_eq!(missing.status, None);
+        assert!(missing.error.as_deref().unwrap().contains("固定供应商失败"));
+
+        let value = serde_json::to_value(fast).unwrap();
+        for key in [
+            "providerId",
+            "status",
+            "latencyMs",
+            "inputTokens",
+            "outputTokens",
+            "effectiveModel",
+            "preview",
+            "previewTruncated",
+            "error",
+        ] {
+            assert!(value.get(key).is_some(), "missing {key}");
+        }
+    }
+}
@@ -0,0 +1,837 @@
+//! 演练模式（dry-run）
+//!
+//! 请求头带 `x-cc-switch-dry-run: 1` 时，handler 在选路之前直接返回一份 JSON 报告，便于在 CI 中校验供应商配置：
+//! - 故障转移链与每个候选选中的 URL（只读选路预览，不测速）
+//! - 模型映射 + 智能解析后的模型（只使用已缓存的模型列表，不请求 /v1/models）及逐步的模型解析追踪
+//! - 供应商 `systemPrompt` 注入后的 system / instructions
+//! - 将要发送的请求头（密钥已遮蔽）
+//! - 当前疑似失效的 URL（解除时间与连续标记次数）
+//! - 校验错误
+//!
+//! 演练不请求上游，也不改动轮询计数、熔断器、当前 URL 与使用统计。
+
+use std::collections::BTreeMap;
+
+use axum::http::HeaderMap;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::{
+    env_expand::expand_provider,
+    forwarder::FORWARDED_HEADERS,
+    model_mapper::{apply_default_model, apply_model_mapping},
+    model_resolver::resolve_claude_model_cached,
+    model_trace::{
+        classify_resolution, ModelResolutionTrace, STEP_BEDROCK, STEP_DEFAULT, STEP_LOCKED,
+        STEP_MAPPING,
+    },
+    openai_model_resolver::resolve_openai_model_cached,
+    provider_router::{ProviderRouter, SelectionPreview, SuspectUrlEntry},
+    providers::{
+        get_adapter, python_proxy_auth_header, to_bedrock_body, AuthInfo, AzureConfig,
+        BedrockConfig, ProviderAdapter, VertexConfig,
+    },
+    python_proxy::python_proxy_base,
+    server::ProxyState,
+    system_prompt::{apply_system_prompt, injected_prompt},
+};
+use crate::app_config::AppType;
+use crate::provider::Provider;
+
+/// 开启演练模式的请求头（值为 `1` / `true`）
+pub const DRY_RUN_HEADER: &str = "x-cc-switch-dry-run";
+
+/// 请求是否要求演练
+pub fn is_dry_run(headers: &HeaderMap) -> bool {
+    headers
+        .get(DRY_RUN_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true"))
+        .unwrap_or(false)
+}
+
+/// 演练报告
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DryRunReport {
+    pub dry_run: bool,
+    pub app_type: String,
+    pub endpoint: String,
+    pub request_model: Option<String>,
+    pub failover_enabled: bool,
+    /// 链非空且所有候选均无错误
+    pub ok: bool,
+    /// 故障转移链（按实际尝试顺序）
+    pub chain: Vec<DryRunAttempt>,
+    /// 当前疑似失效的 URL（选路时跳过）
+    pub suspect_urls: Vec<SuspectUrlEntry>,
+    /// 与具体供应商无关的错误（请求体校验、选路失败）
+    pub errors: Vec<String>,
+}
+
+/// 故障转移链中的一个候选
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DryRunAttempt {
+    pub provider_id: String,
+    pub provider_name: String,
+    pub priority: Option<usize>,
+    pub supplier: String,
+    /// 选中的 base_url
+    pub base_url: Option<String>,
+    /// base_url 来源（见 [`SelectionPreview::url_source`]）
+    pub url_source: String,
+    /// 请求实际发往的地址（Claude 为本地 Python 代理，上游地址见 `x-target-base-url` 头）
+    pub target_url: Option<String>,
+    /// 客户端未指定模型时补上的默认模型（供应商 ANTHROPIC_MODEL 优先，其次为应用级默认模型）
+    pub default_model: Option<String>,
+    /// 注入供应商 systemPrompt 之后的 system（Claude）/ instructions 或 system 消息（Codex）
+    pub system_prompt: Option<Value>,
+    /// 显式映射之后的模型（仅 Claude）
+    pub mapped_model: Option<String>,
+    /// 最终发往上游的模型
+    pub effective_model: Option<String>,
+    /// 模型解析依据：model-list / no-cache / alias / azure-deployments / bedrock / locked / skipped
+    pub model_source: Option<String>,
+    /// 供应商锁定模型（modelLock）：跳过映射与智能解析，原样转发客户端模型
+    pub model_locked: bool,
+    /// 模型解析追踪：每一步对模型的改写（步骤、改写前、改写后、依据）
+    pub model_trace: ModelResolutionTrace,
+    /// 将要发送的请求头（密钥已遮蔽）
+    pub headers: BTreeMap<String, String>,
+    pub errors: Vec<String>,
+}
+
+impl DryRunAttempt {
+    fn from_preview(preview: &SelectionPreview) -> Self {
+        Self {
+            provider_id: preview.provider.id.clone(),
+            provider_name: preview.provider.name.clone(),
+            priority: preview.priority,
+            supplier: preview.supplier.clone(),
+            base_url: preview.base_url.clone(),
+            url_source: preview.url_source.to_string(),
+            target_url: None,
+            default_model: None,
+            system_prompt: None,
+            mapped_model: None,
+            effective_model: None,
+            model_source: None,
+            model_locked: preview.provider.model_locked(),
+            model_trace: ModelResolutionTrace::default(),
+            headers: BTreeMap::new(),
+            errors: Vec::new(),
+        }
+    }
+}
+
+/// handler 中的提前分支：生成报告并以 200 返回
+pub async fn respond(
+    state: &ProxyState,
+    app_type: &AppType,
+    endpoint: &str,
+    body: &Value,
+    headers: &HeaderMap,
+) -> Response {
+    let default_model = state
+        .db
+        .get_proxy_config_for_app(app_type.as_str())
+        .await
+        .ok()
+        .and_then(|config| config.default_model);
+    let report = build_report(
+        &state.provider_router,
+        app_type,
+        endpoint,
+        body,
+        headers,
+        default_model.as_deref(),
+    )
+    .await;
+    log::info!(
+        "[DryRun] app={} endpoint={} ok={} chain={}",
+        report.app_type,
+        report.endpoint,
+        report.ok,
+        report.chain.len()
+    );
+    Json(report).into_response()
+}
+
+/// 生成演练报告（不请求上游、不改动路由状态）
+///
+/// `default_model` 为应用级默认模型，客户端未指定模型时与转发器一样补上。
+pub async fn build_report(
+    router: &ProviderRouter,
+    app_type: &AppType,
+    endpoint: &str,
+    body: &Value,
+    headers: &HeaderMap,
+    default_model: Option<&str>,
+) -> DryRunReport {
+    let app_type_str = app_type.as_str();
+    let mut errors = Vec::new();
+
+    let request_model = match app_type {
+        AppType::Gemini => model_from_endpoint(endpoint),
+        _ => body
+            .get("model")
+            .and_then(|m| m.as_str())
+            .map(|m| m.to_string()),
+    };
+
+    let (failover_enabled, chain) = match router.preview_providers(app_type_str).await {
+        Ok((failover_enabled, previews)) => {
+            let chain = previews
+                .iter()
+                .map(|preview| {
+                    evaluate_attempt(app_type, preview, endpoint, body, headers, default_model)
+                })
+                .collect();
+            (failover_enabled, chain)
+        }
+        Err(e) => {
+            errors.push(e.to_string());
+            (false, Vec::new())
+        }
+    };
+
+    // 未指定模型且没有任何候选补上默认模型（应用级 default_model / 供应商 ANTHROPIC_MODEL）
+    let defaulted = chain
+        .iter()
+        .any(|a: &DryRunAttempt| a.default_model.is_some());
+    if request_model.is_none() && !defaulted {
+        errors.insert(0, "请求中缺少 model".to_string());
+    }
+
+    let suspect_urls = router.suspect_url_entries(app_type_str).await;
+
+    let ok = errors.is_empty()
+        && !chain.is_empty()
+        && chain.iter().all(|a: &DryRunAttempt| a.errors.is_empty());
+    DryRunReport {
+        dry_run: true,
+        app_type: app_type_str.to_string(),
+        endpoint: endpoint.to_string(),
+        request_model,
+        failover_enabled,
+        ok,
+        chain,
+        suspect_urls,
+        errors,
+    }
+}
+
+/// Gemini 的模型在 URI 中：`/v1beta/models/gemini-pro:generateContent`
+fn model_from_endpoint(endpoint: &str) -> Option<String> {
+    let path = endpoint.split('?').next().unwrap_or(endpoint);
+    let (_, rest) = path.split_once("models/")?;
+    let model = rest.split(':').next().unwrap_or(rest);
+    (!model.is_empty()).then(|| model.to_string())
+}
+
+fn body_model(body: &Value) -> Option<&str> {
+    body.get("model").and_then(|m| m.as_str())
+}
+
+/// 与转发器一致地记录智能解析对模型的改写
+fn trace_resolution(
+    model_trace: &mut ModelResolutionTrace,
+    provider: &Provider,
+    is_claude: bool,
+    request_model: &str,
+    from: Option<&str>,
+    resolved_body: &Value,
+) {
+    if let (Some(from_model), Some(to_model)) = (from, body_model(resolved_body)) {
+        let (step, reason) =
+            classify_resolution(provider, is_claude, request_model, from_model, to_model);
+        model_trace.record_change(step, from, Some(to_model), reason);
+    }
+}
+
+/// 按转发器的流程演练单个候选：展开占位符 → 认证 → 目标 URL → 模型映射/解析 → 请求头
+fn evaluate_attempt(
+    app_type: &AppType,
+    preview: &SelectionPreview,
+    endpoint: &str,
+    body: &Value,
+    headers: &HeaderMap,
+    default_model: Option<&str>,
+) -> DryRunAttempt {
+    let mut attempt = DryRunAttempt::from_preview(preview);
+    let provider = match expand_provider(&preview.provider) {
+        Ok(provider) => provider,
+        Err(e) => {
+            attempt.errors.push(e.to_string());
+            return attempt;
+        }
+    };
+    let provider = provider.as_ref();
+
+    // 客户端未指定模型：与转发器一致，补上默认模型（Gemini 的模型在 URI 中）
+    let defaulted = if *app_type == AppType::Gemini {
+        None
+    } else {
+        apply_default_model(body, provider, default_model)
+    };
+    attempt.default_model = defaulted
+        .as_ref()
+        .and_then(|b| b.get("model"))
+        .and_then(|m| m.as_str())
+        .map(|m| m.to_string());
+    if defaulted.is_some() {
+        attempt.model_trace.record(
+            STEP_DEFAULT,
+            body_model(body),
+            attempt.default_model.as_deref(),
+            "unspecified",
+        );
+    }
+    let body = defaulted.as_ref().unwrap_or(body);
+
+    // 供应商 system prompt 注入
+    let injected = apply_system_prompt(body, provider, get_adapter(app_type).name());
+    attempt.system_prompt = injected.as_ref().and_then(injected_prompt);
+    let body = injected.as_ref().unwrap_or(body);
+    let request_model = body
+        .get("model")
+        .and_then(|m| m.as_str())
+        .unwrap_or_default();
+
+    // AWS Bedrock：直连签名，签名在发送时生成
+    if *app_type == AppType::Claude {
+        if let Some(bedrock) = BedrockConfig::from_provider(provider) {
+            if endpoint != "/v1/messages" {
+                attempt
+                    .errors
+                    .push(format!("Bedrock 供应商不支持 {endpoint}"));
+                return attempt;
+            }
+            let mapped_body = if attempt.model_locked {
+                let model = body_model(body);
+                attempt
+                    .model_trace
+                    .record(STEP_LOCKED, model, model, "modelLock");
+                body.clone()
+            } else {
+                let mapped = apply_model_mapping(body.clone(), provider).0;
+                attempt.model_trace.record_change(
+                    STEP_MAPPING,
+                    body_model(body),
+                    body_model(&mapped),
+                    "env",
+                );
+                mapped
+            };
+            let is_stream = mapped_body
+                .get("stream")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            let (model, _) = to_bedrock_body(&mapped_body);
+            let model_id = bedrock.model_id(&model);
+            attempt.model_trace.record_change(
+                STEP_BEDROCK,
+                Some(&model),
+                Some(&model_id),
+                "model-id",
+            );
+            attempt.target_url = Some(bedrock.invoke_url(&model_id, is_stream));
+            attempt.mapped_model = Some(model);
+            attempt.effective_model = Some(model_id);
+            attempt.model_source = Some("bedrock".to_string());
+            attempt.headers.insert(
+                "authorization".to_string(),
+                "AWS4-HMAC-SHA256 ***（发送时签名）".to_string(),
+            );
+            return attempt;
+        }
+    }
+
+    let adapter = get_adapter(app_type);
+
+    // Google Vertex AI：access token 在发送时签发，这里不请求令牌端点
+    let vertex = if *app_type == AppType::Gemini {
+        VertexConfig::from_provider(provider)
+    } else {
+        None
+    };
+    let auth = match &vertex {
+        Some(_) => None,
+        None => {
+            let auth = adapter.extract_auth(provider);
+            if auth.is_none() {
+                attempt
+                    .errors
+                    .push(format!("Provider {} 缺少认证信息", provider.id));
+            }
+            auth
+        }
+    };
+
+    // 目标 URL
+    let mut upstream_base_url = None;
+    if *app_type == AppType::Claude {
+        attempt.target_url = Some(format!("{}{}", python_proxy_base(), endpoint));
+        upstream_base_url = provider
+            .settings_config
+            .get("env")
+            .and_then(|env| env.get("ANTHROPIC_BASE_URL"))
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
+        if upstream_base_url.is_none() {
+            attempt.errors.push(format!(
+                "Provider {} 缺少ANTHROPIC_BASE_URL配置",
+                provider.id
+            ));
+        }
+    } else if let Some(vertex) = &vertex {
+        match vertex.request_url(endpoint) {
+            Ok(url) => attempt.target_url = Some(url),
+            Err(e) => attempt.errors.push(e.to_string()),
+        }
+    } else {
+        match adapter.extract_base_url(provider) {
+            Ok(base_url) => {
+                attempt.target_url = Some(adapter.build_url(&base_url, endpoint));
+                upstream_base_url = Some(base_url);
+            }
+            Err(e) => attempt.errors.push(e.to_string()),
+        }
+    }
+
+    // 模型：Claude 先显式映射再智能解析；Codex 走别名/模型列表解析；只使用缓存
+    let final_body = if attempt.model_locked && !request_model.is_empty() {
+        attempt.model_source = Some("locked".to_string());
+        attempt.model_trace.record(
+            STEP_LOCKED,
+            Some(request_model),
+            Some(request_model),
+            "modelLock",
+        );
+        body.clone()
+    } else if *app_type == AppType::Claude
+        && endpoint == "/v1/messages"
+        && !request_model.is_empty()
+    {
+        let (mapped_body, _, _) = apply_model_mapping(body.clone(), provider);
+        attempt.mapped_model = mapped_body
+            .get("model")
+            .and_then(|m| m.as_str())
+            .map(|m| m.to_string());
+        attempt.model_trace.record_change(
+            STEP_MAPPING,
+            Some(request_model),
+            attempt.mapped_model.as_deref(),
+            "env",
+        );
+        let (resolved, source) = resolve_claude_model_cached(provider, request_model, mapped_body);
+        attempt.model_source = Some(source.to_string());
+        trace_resolution(
+            &mut attempt.model_trace,
+            provider,
+            true,
+            request_model,
+            attempt.mapped_model.as_deref(),
+            &resolved,
+        );
+        resolved
+    } else if *app_type == AppType::Codex
+        && (endpoint == "/v1/responses" || endpoint == "/v1/chat/completions")
+        && !request_model.is_empty()
+    {
+        let (resolved, source) = resolve_openai_model_cached(provider, request_model, body.clone());
+        attempt.model_source = Some(source.to_string());
+        trace_resolution(
+            &mut attempt.model_trace,
+            provider,
+            false,
+            request_model,
+            Some(request_model),
+            &resolved,
+        );
+        resolved
+    } else {
+        body.clone()
+    };
+    attempt.effective_model = final_body
+        .get("model")
+        .and_then(|m| m.as_str())
+        .map(|m| m.to_string());
+
+    // Azure OpenAI：部署由解析后的模型决定
+    if *app_type == AppType::Codex {
+        if let (Some(azure), Some(base_url)) =
+            (AzureConfig::from_provider(provider), &upstream_base_url)
+        {
+            match azure.request_url(base_url, endpoint, attempt.effective_model.as_deref()) {
+                Ok(url) => attempt.target_url = Some(url),
+                Err(e) => attempt.errors.push(e.to_string()),
+            }
+        }
+    }
+
+    attempt.headers = outgoing_headers(
+        app_type,
+        adapter.as_ref(),
+        auth.as_ref(),
+        upstream_base_url.as_deref(),
+        vertex.is_some(),
+        headers,
+    );
+    attempt
+}
+
+/// 与转发器一致的出站请求头（白名单透传 + Content-Type + 认证/路由头），密钥遮蔽后返回
+fn outgoing_headers(
+    app_type: &AppType,
+    adapter: &dyn ProviderAdapter,
+    auth: Option<&AuthInfo>,
+    upstream_base_url: Option<&str>,
+    is_vertex: bool,
+    headers: &HeaderMap,
+) -> BTreeMap<String, String> {
+    let mut out = BTreeMap::new();
+    for (key, value) in headers {
+        let key = key.as_str().to_lowercase();
+        if FORWARDED_HEADERS.contains(&key.as_str()) {
+            if let Ok(value) = value.to_str() {
+                out.insert(key, value.to_string());
+            }
+        }
+    }
+    out.insert("content-type".to_string(), "application/json".to_string());
+
+    if is_vertex {
+        out.insert(
+            "authorization".to_string(),
+            "Bearer ***（服务账号令牌，发送时签发）".to_string(),
+        );
+        return out;
+    }
+    let Some(auth) = auth else {
+        return out;
+    };
+
+    if *app_type == AppType::Claude {
+        let (name, value) = python_proxy_auth_header(auth.scheme.as_ref(), &auth.api_key);
+        out.insert(name.to_lowercase(), mask_secrets(&value, auth));
+        if let Some(base_url) = upstream_base_url {
+            out.insert("x-target-base-url".to_string(), base_url.to_string());
+        }
+        return out;
+    }
+
+    // 其它应用的认证头由适配器决定：构造一个不发送的请求读取其请求头
+    let request = adapter
+        .add_auth_headers(reqwest::Client::new().post("http://localhost/"), auth)
+        .build();
+    if let Ok(request) = request {
+        for (key, value) in request.headers() {
+            if let Ok(value) = value.to_str() {
+                out.insert(key.as_str().to_string(), mask_secrets(value, auth));
+            }
+        }
+    }
+    out
+}
+
+/// 把请求头值中的 API Key / access token 替换为遮蔽形式
+fn mask_secrets(value: &str, auth: &AuthInfo) -> String {
+    let mut value = value.to_string();
+    if !auth.api_key.is_empty() {
+        value = value.replace(&auth.api_key, &auth.masked_key());
+    }
+    if let (Some(token), Some(masked)) = (&auth.access_token, auth.masked_access_token()) {
+        if !token.is_empty() {
+            value = value.replace(token, &masked);
+        }
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+    use crate::provider::Provider;
+    use axum::http::HeaderValue;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    fn client_headers() -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(DRY_RUN_HEADER, HeaderValue::from_static("1"));
+        headers.insert("user-agent", HeaderValue::from_static("claude-cli/2.0"));
+        headers.insert("cookie", HeaderValue::from_static("session=abc"));
+        headers
+    }
+
+    #[tokio::test]
+    async fn claude_report_shows_mapping_masked_headers_and_target() {
+        let db = Arc::new(Database::memory().unwrap());
+        let provider = Provider::with_id(
+            "c1".to_string(),
+            "acme-c1".to_string(),
+            json!({
+                "env": {
+                    "ANTHROPIC_BASE_URL": "https://claude.example.com",
+                    "ANTHROPIC_AUTH_TOKEN": "sk-claude-secret-1234",
+                    "ANTHROPIC_DEFAULT_SONNET_MODEL": "claude-sonnet-4-5-cc"
+                },
+                "systemPrompt": { "prepend": "VENDOR-PREAMBLE" }
+            }),
+            None,
+        );
+        db.save_provider("claude", &provider).unwrap();
+        db.set_current_provider("claude", "c1").unwrap();
+        let router = ProviderRouter::new(db);
+
+        let headers = client_headers();
+        assert!(is_dry_run(&headers));
+        let body = json!({ "model": "claude-sonnet-4-5-20250929", "max_tokens": 16 });
+        let report = build_report(
+            &router,
+            &AppType::Claude,
+            "/v1/messages",
+            &body,
+            &headers,
+            None,
+        )
+        .await;
+
+        assert!(report.dry_run);
+        assert!(report.ok, "{:?}", report.errors);
+        assert!(!report.failover_enabled);
+        assert_eq!(
+            report.request_model.as_deref(),
+            Some("claude-sonnet-4-5-20250929")
+        );
+        assert_eq!(report.chain.len(), 1);
+
+        let attempt = &report.chain[0];
+        assert_eq!(attempt.provider_id, "c1");
+        assert_eq!(attempt.url_source, "current-provider");
+        assert_eq!(
+            attempt.base_url.as_deref(),
+            Some("https://claude.example.com")
+        );
+        assert_eq!(
+            attempt.target_url,
+            Some(format!("{}/v1/messages", python_proxy_base()))
+        );
+        assert_eq!(
+            attempt.mapped_model.as_deref(),
+            Some("claude-sonnet-4-5-cc")
+        );
+        assert_eq!(
+            attempt.effective_model.as_deref(),
+            Some("claude-sonnet-4-5-cc")
+        );
+        assert_eq!(attempt.model_source.as_deref(), Some("no-cache"));
+        assert_eq!(
+            serde_json::to_value(&attempt.model_trace).unwrap(),
+            json!([{
+                "step": "mapping",
+                "from": "claude-sonnet-4-5-20250929",
+                "to": "claude-sonnet-4-5-cc",
+                "reason": "env"
+            }])
+        );
+        assert_eq!(attempt.system_prompt, Some(json!("VENDOR-PREAMBLE")));
+
+        assert_eq!(attempt.headers["x-api-key"], "sk-c...1234");
+        assert_eq!(
+            attempt.headers["x-target-base-url"],
+            "https://claude.example.com"
+        );
+        assert_eq!(attempt.headers["user-agent"], "claude-cli/2.0");
+        assert!(!attempt.headers.contains_key("cookie"));
+        assert!(!attempt.headers.contains_key(DRY_RUN_HEADER));
+        assert!(!serde_json::to_string(&report)
+            .unwrap()
+            .contains("sk-claude-secret-1234"));
+    }
+
+    #[tokio::test]
+    async fn claude_report_shows_model_lock() {
+        let db = Arc::new(Database::memory().unwrap());
+        let provider = Provider::with_id(
+            "c1".to_string(),
+            "acme-c1".to_string(),
+            json!({
+                "env": {
+                    "ANTHROPIC_BASE_URL": "https://claude.example.com",
+                    "ANTHROPIC_AUTH_TOKEN": "sk-claude",
+                    "ANTHROPIC_DEFAULT_SONNET_MODEL": "claude-sonnet-4-5-cc"
+                },
+                "modelLock": true
+            }),
+            None,
+        );
+        db.save_provider("claude", &provider).unwrap();
+        db.set_current_provider("claude", "c1").unwrap();
+        let router = ProviderRouter::new(db);
+
+        let body = json!({ "model": "claude-sonnet-4-5-20250929", "max_tokens": 16 });
+        let report = build_report(
+            &router,
+            &AppType::Claude,
+            "/v1/messages",
+            &body,
+            &client_headers(),
+            None,
+        )
+        .await;
+
+        let attempt = &report.chain[0];
+        assert!(attempt.model_locked);
+        assert_eq!(attempt.mapped_model, None);
+        assert_eq!(
+            attempt.effective_model.as_deref(),
+            Some("claude-sonnet-4-5-20250929")
+        );
+        assert_eq!(attempt.model_source.as_deref(), Some("locked"));
+        assert_eq!(
+            attempt.model_trace.render(),
+            "locked:claude-sonnet-4-5-20250929>claude-sonnet-4-5-20250929(modelLock)"
+        );
+    }
+
+    #[tokio::test]
+    async fn codex_report_uses_alias_and_does_not_advance_round_robin() {
+        let db = Arc::new(Database::memory().unwrap());
+        for (id, name, url, key) in [
+            (
+                "p1",
+                "acme-p1",
+                "https://acme.example.com/v1",
+                "sk-acme-secret-5678",
+            ),
+            (
+                "p2",
+                "beta-p2",
+                "https://beta.example.com/v1",
+                "sk-beta-secret-9012",
+            ),
+        ] {
+            let mut provider = Provider::with_id(
+                id.to_string(),
+                name.to_string(),
+                json!({
+                    "base_url": url,
+                    "env": {
+                        "OPENAI_API_KEY": key,
+                        "CC_SWITCH_CODEX_MODEL_ALIASES": json!({ "gpt-5": "gpt-5-codex" }).to_string()
+                    }
+                }),
+                None,
+            );
+            provider.sort_index = Some(1);
+            db.save_provider("codex", &provider).unwrap();
+            db.add_to_failover_queue("codex", id).unwrap();
+        }
+        let mut config = db.get_proxy_config_for_app("codex").await.unwrap();
+        config.auto_failover_enabled = true;
+        db.update_proxy_config_for_app(config).await.unwrap();
+        let router = ProviderRouter::new(db);
+
+        let body = json!({ "model": "gpt-5", "input": "hi" });
+        let headers = client_headers();
+        let first = build_report(
+            &router,
+            &AppType::Codex,
+            "/v1/responses",
+            &body,
+            &headers,
+            None,
+        )
+        .await;
+        let again = build_report(
+            &router,
+            &AppType::Codex,
+            "/v1/responses",
+            &body,
+            &headers,
+            None,
+        )
+        .await;
+        // 真实选路会推进轮询计数，之后的演练反映下一次请求的顺序
+        let selected = router.select_providers("codex", None, false).await.unwrap();
+        let after = build_report(
+            &router,
+            &AppType::Codex,
+            "/v1/responses",
+            &body,
+            &headers,
+            None,
+        )
+        .await;
+
+        assert!(first.ok, "{:?}", first.errors);
+        assert!(first.failover_enabled);
+        let ids = |r: &DryRunReport| {
+            r.chain
+                .iter()
+                .map(|a| a.provider_id.clone())
+                .collect::<Vec<_>>()
+        };
+        // 演练不推进轮询计数：连续两次报告与随后的真实选路顺序相同
+        assert_eq!(ids(&first), vec!["p1", "p2"]);
+        assert_eq!(ids(&again), ids(&first));
+        assert_eq!(
+            selected.iter().map(|p| p.id.as_str()).collect::<Vec<_>>(),
+            vec!["p1", "p2"]
+        );
+        assert_eq!(ids(&after), vec!["p2", "p1"]);
+
+        let attempt = &first.chain[0];
+        assert_eq!(attempt.priority, Some(1));
+        assert_eq!(attempt.supplier, "acme");
+        assert_eq!(attempt.url_source, "only");
+        assert_eq!(
+            attempt.target_url.as_deref(),
+            Some("https://acme.example.com/v1/responses")
+        );
+        assert_eq!(attempt.mapped_model, None);
+        assert_eq!(attempt.effective_model.as_deref(), Some("gpt-5-codex"));
+        assert_eq!(attempt.model_source.as_deref(), Some("alias"));
+        assert_eq!(
+            attempt.model_trace.render(),
+            "alias:gpt-5>gpt-5-codex(alias-map)"
+        );
+        assert_eq!(attempt.headers["authorization"], "Bearer sk-a...5678");
+
+        // 请求体缺少 model：报告校验错误
+        let report = build_report(
+            &router,
+            &AppType::Codex,
+            "/v1/responses",
+            &json!({ "input": "hi" }),
+            &headers,
+            None,
+        )
+        .await;
+        assert!(!report.ok);
+        assert_eq!(report.errors, vec!["请求中缺少 model"]);
+
+        // 配置了应用级默认模型：补上后照常解析
+        let report = build_report(
+            &router,
+            &AppType::Codex,
+            "/v1/responses",
+            &json!({ "model": "auto", "input": "hi" }),
+            &headers,
+            Some("gpt-5"),
+        )
+        .await;
+        assert!(report.ok, "{:?}", report.errors);
+        assert_eq!(report.chain[0].default_model.as_deref(), Some("gpt-5"));
+        assert_eq!(
+            report.chain[0].effective_model.as_deref(),
+            Some("gpt-5-codex")
+        );
+    }
+}
@@ -0,0 +1,552 @@
+//! 端到端测试：客户端 → 代理（handler → 路由 → 转发）→ 模拟上游
+
+use super::attribution::PROVIDER_ID_HEADER;
+use super::circuit_breaker::CircuitState;
+use super::error_schema::ORIGINAL_ERROR_KEY;
+use super::python_proxy::UPSTREAM_ELAPSED_HEADER;
+use super::request_trace::{DEBUG_HEADER, TRACE_HEADER};
+use super::testkit::{MockResponse, MockUpstream, TestProxy};
+use serde_json::json;
+use std::time::Duration;
+
+fn completion() -> serde_json::Value {
+    json!({
+        "model": "gpt-5",
+        "usage": { "input_tokens": 7, "output_tokens": 3, "total_tokens": 10 }
+    })
+}
+
+#[tokio::test]
+async fn failover_moves_to_second_provider() {
+    let upstream = MockUpstream::start().await;
+    upstream.route("/a/v1/responses", [MockResponse::status(500)]);
+    upstream.route("/b/v1/responses", [MockResponse::json(200, completion())]);
+
+    let proxy = TestProxy::builder()
+        .codex_provider("a", &upstream.url("/a/v1"))
+        .codex_provider("b", &upstream.url("/b/v1"))
+        .app_config("codex", |c| c.auto_failover_enabled = true)
+        .start()
+        .await;
+
+    let response = proxy
+        .post_json(
+            "/v1/responses",
+            &json!({ "model": "gpt-5", "input": "hi", "stream": false }),
+        )
+        .await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()[PROVIDER_ID_HEADER], "b");
+    assert!(upstream.hits("/a/v1/responses") >= 1);
+    assert_eq!(upstream.hits("/b/v1/responses"), 1);
+
+    // 上游收到的是供应商自己的 key，而不是客户端的
+    let to_b = upstream
+        .requests()
+        .into_iter()
+        .find(|r| r.path == "/b/v1/responses")
+        .unwrap();
+    assert_eq!(to_b.headers["authorization"], "Bearer sk-b");
+    assert_eq!(to_b.json()["input"], "hi");
+
+    proxy.stop().await;
+}
+
+#[tokio::test]
+async fn debug_trace_records_failover_across_providers() {
+    let upstream = MockUpstream::start().await;
+    upstream.route("/a/v1/responses", [MockResponse::status(502)]);
+    upstream.route("/b/v1/responses", [MockResponse::json(200, completion())]);
+
+    let proxy = TestProxy::builder()
+        .codex_provider("a", &upstream.url("/a/v1"))
+        .codex_provider("b", &upstream.url("/b/v1"))
+        .app_config("codex", |c| c.auto_failover_enabled = true)
+        .start()
+        .await;
+    let body = json!({ "model": "gpt-5", "input": "hi", "stream": false });
+
+    let response = proxy
+        .post_json_with_headers("/v1/responses", &body, &[(DEBUG_HEADER, "1")])
+        .await;
+    assert_eq!(response.status(), 200);
+    let trace = response.headers()[TRACE_HEADER]
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    // 同一条追踪里依次记录 a 的失败尝试与 b 的成功尝试，胜出者为 b
+    let (attempts, rest) = trace.split_once(';').expect("trace has winner section");
+    let entries: Vec<&str> = attempts.split(',').collect();
+    let first_b = entries.iter().position(|e| e.starts_with("b=")).unwrap();
+    assert!(first_b > 0, "{trace}");
+    assert!(
+        entries[..first_b]
+            .iter()
+            .all(|e| e.starts_with("a=502@") && e.ends_with("ms")),
+        "{trace}"
+    );
+    assert!(entries[first_b].starts_with("b=200@"), "{trace}");
+    assert_eq!(entries.len(), first_b + 1, "{trace}");
+    assert!(rest.starts_with("winner=b"), "{trace}");
+
+    // 未携带调试头时不返回追踪
+    let response = proxy.post_json("/v1/responses", &body).await;
+    assert!(response.headers().get(TRACE_HEADER).is_none());
+
+    proxy.stop().await;
+}
+
+#[tokio::test]
+async fn breaker_opens_after_consecutive_failures() {
+    let upstream = MockUpstream::start().await;
+    upstream.route("/a/v1/responses", [MockResponse::status(500)]);
+    upstream.route("/b/v1/responses", [MockResponse::json(200, completion())]);
+
+    let proxy = TestProxy::builder()
+        .codex_provider("a", &upstream.url("/a/v1"))
+        .codex_provider("b", &upstream.url("/b/v1"))
+        .app_config("codex", |c| {
+            c.auto_failover_enabled = true;
+            c.circuit_failure_threshold = 2;
+        })
+        .start()
+        .await;
+    let body = json!({ "model": "gpt-5", "input": "hi", "stream": false });
+
+    for _ in 0..2 {
+        assert_eq!(proxy.post_json("/v1/responses", &body).await.status(), 200);
+        let opened = proxy
+            .server
+            .get_circuit_breaker_stats("a", "codex")
+            .await
+            .is_some_and(|stats| stats.state == CircuitState::Open);
+        if opened {
+            break;
+        }
+    }
+    let stats = proxy
+        .server
+        .get_circuit_breaker_stats("a", "codex")
+        .await
+        .unwrap();
+    assert_eq!(stats.state, CircuitState::Open);
+
+    // 熔断后不再请求 a
+    let hits_a = upstream.hits("/a/v1/responses");
+    assert_eq!(proxy.post_json("/v1/responses", &body).await.status(), 200);
+    assert_eq!(upstream.hits("/a/v1/responses"), hits_a);
+
+    proxy.stop().await;
+}
+
+#[tokio::test]
+async fn streaming_passes_events_through_in_order() {
+    let upstream = MockUpstream::start().await;
+    let events: Vec<String> = ["Hel", "lo", "!"]
+        .iter()
+        .map(|delta| {
+            format!(
+                "event: response.output_text.delta\ndata: {}",
+                json!({ "type": "response.output_text.delta", "delta": delta })
+            )
+        })
+        .chain(std::iter::once(format!(
+            "event: response.completed\ndata: {}",
+            json!({ "type": "response.completed", "response": completion() })
+        )))
+        .collect();
+    upstream.route(
+        "/a/v1/responses",
+        [MockResponse::sse(events).with_event_interval(Duration::from_millis(20))],
+    );
+
+    let proxy = TestProxy::builder()
+        .codex_provider("a", &upstream.url("/a/v1"))
+        .start()
+        .await;
+
+    let response = proxy
+        .post_json(
+            "/v1/responses",
+            &json!({ "model": "gpt-5", "input": "hi", "stream": true }),
+        )
+        .await;
+    assert_eq!(response.status(), 200);
+    let content_type = response.headers()["content-type"].to_str().unwrap();
+    assert!(
+        content_type.starts_with("text/event-stream"),
+        "{content_type}"
+    );
+
+    let text = response.text().await.unwrap();
+    let positions: Vec<_> = ["\"Hel\"", "\"lo\"", "\"!\"", "response.completed"]
+        .iter()
+        .map(|needle| {
+            text.find(needle)
+                .unwrap_or_else(|| panic!("{needle}: {text}"))
+        })
+        .collect();
+    assert!(positions.windows(2).all(|w| w[0] < w[1]), "{text}");
+
+    proxy.stop().await;
+}
+
+#[tokio::test]
+async fn streamed_response_bytes_are_counted() {
+    let upstream = MockUpstream::start().await;
+    let events: Vec<String> = ["x".repeat(100), "y".repeat(300)]
+        .iter()
+        .map(|delta| {
+            format!(
+                "event: response.output_text.delta\ndata: {}",
+                json!({ "type": "response.output_text.delta", "delta": delta })
+            )
+        })
+        .chain(std::iter::once(format!(
+            "event: response.completed\ndata: {}",
+            json!({ "type": "response.completed", "response": completion() })
+        )))
+        .collect();
+    // 模拟上游每个事件后追加一个空行
+    let sent_bytes: usize = events.iter().map(|e| e.len() + 2).sum();
+    upstream.route("/a/v1/responses", [MockResponse::sse(events)]);
+
+    let proxy = TestProxy::builder()
+        .codex_provider("a", &upstream.url("/a/v1"))
+        .start()
+        .await;
+
+    let body = json!({ "model": "gpt-5", "input": "hi", "stream": true });
+    let response = proxy.post_json("/v1/responses", &body).await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.bytes().await.unwrap().len(), sent_bytes);
+
+    let mut stored = None;
+    for _ in 0..50 {
+        let row = proxy
+            .db
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT request_bytes, response_bytes FROM proxy_request_logs WHERE app_type = 'codex'",
+                [],
+                |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)),
+            )
+            .ok();
+        if row.is_some() {
+            stored = row;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    let (request_bytes, response_bytes) = stored.expect("request log written");
+    assert_eq!(
+        request_bytes as usize,
+        serde_json::to_vec(&body).unwrap().len()
+    );
+    assert_eq!(response_bytes as usize, sent_bytes);
+
+    proxy.stop().await;
+}
+
+#[tokio::test]
+async fn model_is_resolved_against_provider_model_list() {
+    let upstream = MockUpstream::start().await;
+    upstream.route(
+        "/m/v1/models",
+        [MockResponse::json(
+            200,
+            json!({ "data": [{ "id": "gpt-5-mini" }] }),
+        )],
+    );
+    upstream.route("/m/v1/responses", [MockResponse::json(200, completion())]);
+
+    let proxy = TestProxy::builder()
+        .codex_provider("m", &upstream.url("/m/v1"))
+        .start()
+        .await;
+
+    let response = proxy
+        .post_json(
+            "/v1/responses",
+            &json!({ "model": "gpt-5", "input": "hi", "stream": false }),
+        )
+        .await;
+    assert_eq!(response.status(), 200);
+
+    // 供应商只开放 gpt-5-mini：转发时改写模型名
+    let forwarded = upstream
+        .requests()
+        .into_iter()
+        .find(|r| r.path == "/m/v1/responses")
+        .unwrap();
+    assert_eq!(forwarded.json()["model"], "gpt-5-mini");
+
+    proxy.stop().await;
+}
+
+#[tokio::test]
+async fn oversized_upstream_error_body_is_truncated() {
+    let upstream = MockUpstream::start().await;
+    let page = format!("<html>{}</html>", "x".repeat(2 * 1024 * 1024));
+    upstream.route("/a/v1/responses", [MockResponse::text(502, &page)]);
+
+    let proxy = TestProxy::builder()
+        .codex_provider("a", &upstream.url("/a/v1"))
+        .start()
+        .await;
+
+    let response = proxy
+        .post_json(
+            "/v1/responses",
+            &json!({ "model": "gpt-5", "input": "hi", "stream": false }),
+        )
+        .await;
+    assert!(!response.status().is_success());
+    let text = response.text().await.unwrap();
+    let max = crate::settings::DEFAULT_ERROR_BODY_MAX_BYTES;
+    assert!(text.len() < max + 1024, "{}", text.len());
+    assert!(
+        text.contains("已截断，共 2.0MB"),
+        "{}",
+        &text[text.len() - 200..]
+    );
+
+    proxy.stop().await;
+}
+
+#[tokio::test]
+async fn upstream_errors_use_client_native_schema() {
+    // 故障转移链最后失败的是 Anthropic 形态的网关：Codex 客户端仍收到 OpenAI 结构
+    let upstream = MockUpstream::start().await;
+    let anthropic = json!({
+        "type": "error",
+        "error": { "type": "overloaded_error", "message": "Overloaded" }
+    });
+    upstream.route(
+        "/a/v1/responses",
+        [MockResponse::json(529, anthropic.clone())],
+    );
+
+    let proxy = TestProxy::builder()
+        .codex_provider("a", &upstream.url("/a/v1"))
+        .start()
+        .await;
+
+    let response = proxy
+        .post_json(
+            "/v1/responses",
+            &json!({ "model": "gpt-5", "input": "hi", "stream": false }),
+        )
+        .await;
+    assert_eq!(response.status(), 529);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["error"]["message"], "Overloaded");
+    assert_eq!(body["error"]["type"], "overloaded_error");
+    assert!(body.get("type").is_none());
+    assert_eq!(body[ORIGINAL_ERROR_KEY], anthropic);
+
+    proxy.stop().await;
+}
+
+#[tokio::test]
+async fn proxy_errors_use_client_native_schema() {
+    // 未配置任何供应商：代理自身生成的错误同样按各应用协议返回
+    let proxy = TestProxy::builder().start().await;
+
+    let claude = proxy
+        .post_json(
+            "/v1/messages",
+            &json!({ "model": "claude-sonnet-4", "messages": [], "max_tokens": 1 }),
+        )
+        .await;
+    assert!(!claude.status().is_success());
+    let body: serde_json::Value = claude.json().await.unwrap();
+    assert_eq!(body["type"], "error", "{body}");
+    assert!(body["error"]["type"].is_string());
+    assert!(body["error"]["message"].is_string());
+
+    let gemini = proxy
+        .post_json(
+            "/v1beta/models/gemini-pro:generateContent",
+            &json!({ "contents": [] }),
+        )
+        .await;
+    let gemini_status = gemini.status().as_u16();
+    let body: serde_json::Value = gemini.json().await.unwrap();
+    assert_eq!(body["error"]["code"], gemini_status, "{body}");
+    assert!(body["error"]["status"].is_string());
+    assert!(body["error"]["message"].is_string());
+
+    let codex = proxy
+        .post_json("/v1/chat/completions", &json!({ "model": "gpt-5" }))
+        .await;
+    let body: serde_json::Value = codex.json().await.unwrap();
+    assert!(body["error"]["message"].is_string(), "{body}");
+    assert!(body["error"]["type"].is_string());
+    assert!(body.get("type").is_none());
+
+    // 客户端请求体无法解析（axum 的纯文本拒绝）同样被改写
+    let invalid = reqwest::Client::new()
+        .post(proxy.url("/v1/messages"))
+        .header("content-type", "application/json")
+        .body("{not json")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(invalid.status(), 400);
+    let body: serde_json::Value = invalid.json().await.unwrap();
+    assert_eq!(body["error"]["type"], "invalid_request_error");
+    assert!(body[ORIGINAL_ERROR_KEY].is_string());
+
+    proxy.stop().await;
+}
+
+#[tokio::test]
+async fn no_providers_configured_returns_503_with_add_hint() {
+    let proxy = TestProxy::builder().start().await;
+
+    let response = proxy
+        .post_json(
+            "/v1/responses",
+            &json!({ "model": "gpt-5", "input": "hi", "stream": false }),
+        )
+        .await;
+    assert_eq!(response.status(), 503);
+    let body: serde_json::Value = response.json().await.unwrap();
+    let message = body["error"]["message"].as_str().unwrap().to_string();
+    assert!(message.contains("未配置任何供应商"), "{body}");
+    assert!(message.contains("csc add codex"), "{body}");
+
+    let status = proxy.server.get_status().await;
+    assert_eq!(status.last_error.as_deref(), Some(message.as_str()));
+
+    proxy.stop().await;
+}
+
+#[tokio::test]
+async fn version_endpoint_reports_build_info() {
+    let proxy = TestProxy::builder().start().await;
+
+    let body: serde_json::Value = reqwest::get(proxy.url("/version"))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(body["schema_version"], crate::database::SCHEMA_VERSION);
+    assert!(body.get("git_hash").is_some());
+
+    // 默认未开启每日检测，状态中没有检测结果
+    assert!(proxy.server.get_status().await.update_check.is_none());
+
+    proxy.stop().await;
+}
+
+#[tokio::test]
+async fn deleted_current_provider_returns_503_with_enable_hint() {
+    let upstream = MockUpstream::start().await;
+    upstream.route("/b/v1/responses", [MockResponse::json(200, completion())]);
+
+    // 故障转移关闭（默认），当前供应商 a 被删除后只剩 b
+    let proxy = TestProxy::builder()
+        .codex_provider("a", &upstream.url("/a/v1"))
+        .codex_provider("b", &upstream.url("/b/v1"))
+        .start()
+        .await;
+    proxy.db.delete_provider("codex", "a").unwrap();
+
+    let response = proxy
+        .post_json(
+            "/v1/responses",
+            &json!({ "model": "gpt-5", "input": "hi", "stream": false }),
+        )
+        .await;
+    assert_eq!(response.status(), 503);
+    let body: serde_json::Value = response.json().await.unwrap();
+    let message = body["error"]["message"].as_str().unwrap();
+    assert!(message.contains("未设置当前供应商"), "{body}");
+    assert!(message.contains("csc enable codex"), "{body}");
+    assert!(!message.contains("csc add"), "{body}");
+    assert_eq!(upstream.hits("/b/v1/responses"), 0);
+
+    // 重新选择当前供应商后恢复
+    proxy.db.set_current_provider("codex", "b").unwrap();
+    let response = proxy
+        .post_json(
+            "/v1/responses",
+            &json!({ "model": "gpt-5", "input": "hi", "stream": false }),
+        )
+        .await;
+    assert_eq!(response.status(), 200);
+
+    proxy.stop().await;
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn python_hop_latency_is_split_in_request_log() {
+    // 模拟上游充当 Python 代理（15722）：回传真实上游耗时
+    let python = MockUpstream::start().await;
+    python.route(
+        "/v1/messages",
+        [MockResponse::json(
+            200,
+            json!({
+                "model": "claude-sonnet-4",
+                "stop_reason": "end_turn",
+                "usage": { "input_tokens": 7, "output_tokens": 3 }
+            }),
+        )
+        .with_header(UPSTREAM_ELAPSED_HEADER, "40")
+        .with_delay(Duration::from_millis(60))],
+    );
+    std::env::set_var("CC_SWITCH_PYTHON_PROXY_BASE", python.url(""));
+
+    let proxy = TestProxy::builder()
+        .claude_provider("c", "https://relay.example.com")
+        .start()
+        .await;
+    let response = proxy
+        .post_json(
+            "/v1/messages",
+            &json!({
+                "model": "claude-sonnet-4",
+                "max_tokens": 16,
+                "messages": [{ "role": "user", "content": "hi" }]
+            }),
+        )
+        .await;
+    std::env::remove_var("CC_SWITCH_PYTHON_PROXY_BASE");
+    assert_eq!(response.status(), 200);
+    response.bytes().await.unwrap();
+
+    // 请求日志异步写入：等待落库
+    let mut stored = None;
+    for _ in 0..50 {
+        let row = proxy
+            .db
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT latency_ms, upstream_ms FROM proxy_request_logs WHERE app_type = 'claude'",
+                [],
+                |row| Ok((row.get::<_, i64>(0)?, row.get::<_, Option<i64>>(1)?)),
+            )
+            .ok();
+        if row.is_some() {
+            stored = row;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    let (latency_ms, upstream_ms) = stored.expect("request log written");
+    assert_eq!(upstream_ms, Some(40));
+    assert!(latency_ms >= 60, "{latency_ms}");
+
+    proxy.stop().await;
+}
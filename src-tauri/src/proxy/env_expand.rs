@@ -0,0 +1,311 @@
+//! settings_config 中的 `${ENV_VAR}` 占位符展开
+//!
+//! - 展开只发生在读取时（路由器提取 base_url/key、转发前、测速与流式检查），得到的是本次使用的副本，
+//!   从不写回数据库；写回（模型别名/映射）总是重新读取数据库中的原始配置
+//! - 模型别名映射（env 中的 `*_MODEL_ALIASES`）是代理自己写回的 JSON 文本，不参与展开
+//! - 变量未设置时返回 [`MissingEnvVar`]，由调用方转为明确的错误（转发时为 `AuthError`）
+
+use std::borrow::Cow;
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::provider::Provider;
+
+/// 不参与展开的键（后缀匹配）
+const SKIPPED_KEY_SUFFIXES: &[&str] = &["_MODEL_ALIASES"];
+
+/// 未设置的环境变量
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingEnvVar {
+    /// 变量名
+    pub var: String,
+    /// 引用该变量的配置路径（如 `env.ANTHROPIC_AUTH_TOKEN`）
+    pub path: String,
+}
+
+impl fmt::Display for MissingEnvVar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "环境变量 {} 未设置（{} 引用了 ${{{}}}）",
+            self.var, self.path, self.var
+        )
+    }
+}
+
+fn is_var_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// 字符串中引用的变量名（只识别 `${NAME}`，NAME 为字母/数字/下划线且不以数字开头）
+pub fn placeholder_vars(s: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut rest = s;
+    while let Some(start) = rest.find("${") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find('}') else {
+            break;
+        };
+        let name = &after[..end];
+        if is_var_name(name) {
+            out.push(name.to_string());
+        }
+        rest = &after[end + 1..];
+    }
+    out
+}
+
+fn expand_str_with<'a>(
+    s: &'a str,
+    path: &str,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<Cow<'a, str>, MissingEnvVar> {
+    if !s.contains("${") {
+        return Ok(Cow::Borrowed(s));
+    }
+
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.find('}') {
+            Some(end) if is_var_name(&after[..end]) => {
+                let name = &after[..end];
+                let value = lookup(name).ok_or_else(|| MissingEnvVar {
+                    var: name.to_string(),
+                    path: path.to_string(),
+                })?;
+                out.push_str(&value);
+                rest = &after[end + 1..];
+            }
+            // 不是合法占位符：原样保留
+            _ => {
+                out.push_str("${");
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    Ok(Cow::Owned(out))
+}
+
+fn env_lookup(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+/// 展开单个字符串中的占位符（读取进程环境变量）
+pub fn expand_env_str<'a>(s: &'a str, path: &str) -> Result<Cow<'a, str>, MissingEnvVar> {
+    expand_str_with(s, path, &env_lookup)
+}
+
+fn is_skipped_key(key: &str) -> bool {
+    SKIPPED_KEY_SUFFIXES
+        .iter()
+        .any(|suffix| key.ends_with(suffix))
+}
+
+fn join_path(parent: &str, key: &str) -> String {
+    if parent.is_empty() {
+        key.to_string()
+    } else {
+        format!("{parent}.{key}")
+    }
+}
+
+fn visit_strings(value: &Value, path: &str, f: &mut dyn FnMut(&str, &str)) {
+    match value {
+        Value::String(s) => f(path, s),
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                visit_strings(item, &format!("{path}[{i}]"), f);
+            }
+        }
+        Value::Object(map) => {
+            for (key, item) in map {
+                if !is_skipped_key(key) {
+                    visit_strings(item, &join_path(path, key), f);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn expand_value_with(
+    value: &mut Value,
+    path: &str,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<(), MissingEnvVar> {
+    match value {
+        Value::String(s) => {
+            if let Cow::Owned(expanded) = expand_str_with(s, path, lookup)? {
+                *s = expanded;
+            }
+        }
+        Value::Array(items) => {
+            for (i, item) in items.iter_mut().enumerate() {
+                expand_value_with(item, &format!("{path}[{i}]"), lookup)?;
+            }
+        }
+        Value::Object(map) => {
+            for (key, item) in map.iter_mut() {
+                if !is_skipped_key(key) {
+                    expand_value_with(item, &join_path(path, key), lookup)?;
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// 配置中引用的占位符数量（不含模型别名映射）
+pub fn count_placeholders(value: &Value) -> usize {
+    let mut count = 0;
+    visit_strings(value, "", &mut |_, s| count += placeholder_vars(s).len());
+    count
+}
+
+fn expand_provider_with<'a>(
+    provider: &'a Provider,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<Cow<'a, Provider>, MissingEnvVar> {
+    if count_placeholders(&provider.settings_config) == 0 {
+        return Ok(Cow::Borrowed(provider));
+    }
+    let mut expanded = provider.clone();
+    expand_value_with(&mut expanded.settings_config, "", lookup)?;
+    Ok(Cow::Owned(expanded))
+}
+
+/// 返回展开占位符后的供应商副本（无占位符时直接借用原值）
+pub fn expand_provider(provider: &Provider) -> Result<Cow<'_, Provider>, MissingEnvVar> {
+    expand_provider_with(provider, &env_lookup)
+}
+
+/// 列出配置中当前无法解析的占位符（`check-env` 使用）
+pub fn unresolved_placeholders(value: &Value) -> Vec<MissingEnvVar> {
+    let mut out = Vec::new();
+    visit_strings(value, "", &mut |path, s| {
+        for var in placeholder_vars(s) {
+            if env_lookup(&var).is_none() {
+                out.push(MissingEnvVar {
+                    var,
+                    path: path.to_string(),
+                });
+            }
+        }
+    });
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "ACME_KEY" => Some("sk-acme".to_string()),
+            "ACME_HOST" => Some("acme.example.com".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn expands_placeholders_and_keeps_other_text() {
+        assert_eq!(
+            expand_str_with("https://${ACME_HOST}/v1", "p", &lookup).unwrap(),
+            "https://acme.example.com/v1"
+        );
+        assert_eq!(
+            expand_str_with("${ACME_KEY}", "p", &lookup).unwrap(),
+            "sk-acme"
+        );
+        // 非法占位符与普通 `$` 原样保留
+        assert_eq!(
+            expand_str_with("a$b ${1X} ${ not", "p", &lookup).unwrap(),
+            "a$b ${1X} ${ not"
+        );
+        assert!(matches!(
+            expand_str_with("plain", "p", &lookup).unwrap(),
+            Cow::Borrowed(_)
+        ));
+
+        let err = expand_str_with("${ACME_MISSING}", "env.OPENAI_API_KEY", &lookup).unwrap_err();
+        assert_eq!(err.var, "ACME_MISSING");
+        assert_eq!(err.path, "env.OPENAI_API_KEY");
+    }
+
+    #[test]
+    fn provider_expansion_skips_alias_blobs_and_leaves_original_untouched() {
+        let aliases = json!({ "gpt-5": "${ACME_KEY}" }).to_string();
+        let provider = Provider::with_id(
+            "p1".to_string(),
+            "acme-p1".to_string(),
+            json!({
+                "env": {
+                    "OPENAI_API_KEY": "${ACME_KEY}",
+                    "CC_SWITCH_CODEX_MODEL_ALIASES": aliases,
+                },
+                "base_url": "https://${ACME_HOST}/v1",
+                "baseUrlPriority": ["https://${ACME_HOST}"]
+            }),
+            None,
+        );
+
+        let expanded = expand_provider_with(&provider, &lookup).unwrap();
+        let env = &expanded.settings_config["env"];
+        assert_eq!(env["OPENAI_API_KEY"], "sk-acme");
+        assert_eq!(env["CC_SWITCH_CODEX_MODEL_ALIASES"], aliases);
+        assert_eq!(
+            expanded.settings_config["base_url"],
+            "https://acme.example.com/v1"
+        );
+        assert_eq!(
+            expanded.settings_config["baseUrlPriority"][0],
+            "https://acme.example.com"
+        );
+        // 原配置保持占位符
+        assert_eq!(
+            provider.settings_config["env"]["OPENAI_API_KEY"],
+            "${ACME_KEY}"
+        );
+
+        let no_placeholder = Provider::with_id(
+            "p2".to_string(),
+            "acme-p2".to_string(),
+            json!({ "env": { "OPENAI_API_KEY": "sk-plain" } }),
+            None,
+        );
+        assert!(matches!(
+            expand_provider_with(&no_placeholder, &lookup).unwrap(),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn unresolved_placeholders_are_listed_with_paths() {
+        let settings = json!({
+            "env": {
+                "ANTHROPIC_AUTH_TOKEN": "${CC_SWITCH_TEST_SURELY_UNSET_VAR}",
+                "ANTHROPIC_BASE_URL": "https://example.com"
+            }
+        });
+        let missing = unresolved_placeholders(&settings);
+        assert_eq!(
+            missing,
+            vec![MissingEnvVar {
+                var: "CC_SWITCH_TEST_SURELY_UNSET_VAR".to_string(),
+                path: "env.ANTHROPIC_AUTH_TOKEN".to_string(),
+            }]
+        );
+        assert!(missing[0]
+            .to_string()
+            .contains("CC_SWITCH_TEST_SURELY_UNSET_VAR"));
+    }
+}
@@ -6,6 +6,9 @@ use axum::{
 use serde_json::json;
 use thiserror::Error;
 
+use super::failure_report::FailureReport;
+use crate::error::{current_provider_missing_message, AppError};
+
 #[derive(Debug, Error)]
 pub enum ProxyError {
     #[error("服务器已在运行")]
@@ -23,6 +26,17 @@ pub enum ProxyError {
     #[error("无可用的Provider")]
     NoAvailableProvider,
 
+    /// 该应用未配置任何供应商
+    #[error("{app_type} 未配置任何供应商，请先运行 csc add {app_type} ... 添加")]
+    NoProvidersConfigured { app_type: String },
+
+    /// 故障转移关闭，但当前供应商未设置或已被删除
+    #[error("{}", current_provider_missing_message(app_type, provider_id.as_deref()))]
+    CurrentProviderMissing {
+        app_type: String,
+        provider_id: Option<String>,
+    },
+
     #[allow(dead_code)]
     #[error("Provider不健康: {0}")]
     ProviderUnhealthy(String),
@@ -33,6 +47,10 @@ pub enum ProxyError {
     #[error("超过最大重试次数")]
     MaxRetriesExceeded,
 
+    /// 故障转移链全部失败（附带逐次尝试报告）
+    #[error("{}", .0.summary())]
+    AllProvidersFailed(Box<FailureReport>),
+
     #[error("数据库错误: {0}")]
     DatabaseError(String),
 
@@ -100,6 +118,9 @@ impl IntoResponse for ProxyError {
 
                 (http_status, error_body)
             }
+            ProxyError::AllProvidersFailed(report) => {
+                (StatusCode::BAD_GATEWAY, report.to_response_body())
+            }
             _ => {
                 let (http_status, message) = match &self {
                     ProxyError::AlreadyRunning => (StatusCode::CONFLICT, self.to_string()),
@@ -111,6 +132,10 @@ impl IntoResponse for ProxyError {
                     ProxyError::NoAvailableProvider => {
                         (StatusCode::SERVICE_UNAVAILABLE, self.to_string())
                     }
+                    ProxyError::NoProvidersConfigured { .. }
+                    | ProxyError::CurrentProviderMissing { .. } => {
+                        (StatusCode::SERVICE_UNAVAILABLE, self.to_string())
+                    }
                     ProxyError::ProviderUnhealthy(_) => {
                         (StatusCode::SERVICE_UNAVAILABLE, self.to_string())
                     }
@@ -133,7 +158,9 @@ impl IntoResponse for ProxyError {
                     ProxyError::Internal(_) => {
                         (StatusCode::INTERNAL_SERVER_ERROR, self.to_string())
                     }
-                    ProxyError::UpstreamError { .. } => unreachable!(),
+                    ProxyError::UpstreamError { .. } | ProxyError::AllProvidersFailed(_) => {
+                        unreachable!()
+                    }
                 };
 
                 let error_body = json!({
@@ -151,6 +178,25 @@ impl IntoResponse for ProxyError {
     }
 }
 
+impl ProxyError {
+    /// 选路失败时的错误转换：供应商配置缺失保留为独立错误（503 + 操作提示），其余按数据库错误处理
+    pub fn from_selection_error(error: AppError) -> Self {
+        match error {
+            AppError::NoProvidersConfigured { app_type } => {
+                Self::NoProvidersConfigured { app_type }
+            }
+            AppError::CurrentProviderMissing {
+                app_type,
+                provider_id,
+            } => Self::CurrentProviderMissing {
+                app_type,
+                provider_id,
+            },
+            other => Self::DatabaseError(other.to_string()),
+        }
+    }
+}
+
 /// 错误分类
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ErrorCategory {
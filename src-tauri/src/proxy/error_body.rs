@@ -0,0 +1,157 @@
+//! 上游错误响应体的有界读取
+//!
+//! 错误路径上直接 `response.text()` 会把数 MB 的 HTML 错误页整个读进内存和日志，
+//! 而非 UTF-8 的响应体会变成 `None`，排查线索随之丢失。这里统一：
+//! - 最多读取 `errorBodyMaxBytes`（默认 64KB）字节，不再继续消费响应流
+//! - 非 UTF-8 内容按有损方式解码（无效字节替换为 U+FFFD）
+//! - 截断时在末尾注明总大小（未知时注明“超过”读取上限）
+
+use reqwest::Response;
+
+/// 读取错误响应体（上限取自设置）
+///
+/// 读取失败且没有读到任何内容时返回 `None`。
+pub async fn read_error_body(response: Response) -> Option<String> {
+    read_error_body_with_limit(response, crate::settings::get_error_body_max_bytes()).await
+}
+
+/// 读取错误响应体，最多 `limit` 字节
+pub async fn read_error_body_with_limit(mut response: Response, limit: usize) -> Option<String> {
+    let content_length = response.content_length();
+    let mut buf: Vec<u8> = Vec::new();
+    let mut truncated = false;
+    let mut failed = false;
+
+    loop {
+        match response.chunk().await {
+            Ok(Some(chunk)) => {
+                let room = limit - buf.len();
+                if chunk.len() > room {
+                    buf.extend_from_slice(&chunk[..room]);
+                    truncated = true;
+                    break;
+                }
+                buf.extend_from_slice(&chunk);
+                if buf.len() == limit && content_length.is_some_and(|n| n > limit as u64) {
+                    truncated = true;
+                    break;
+                }
+            }
+            Ok(None) => break,
+            Err(e) => {
+                log::debug!("读取上游错误响应体中断: {e}");
+                failed = true;
+                break;
+            }
+        }
+    }
+
+    if failed && buf.is_empty() {
+        return None;
+    }
+    let mut text = decode_lossy(&buf, truncated);
+    if truncated {
+        text.push_str(&truncation_note(content_length, limit));
+    }
+    Some(text)
+}
+
+/// 有损解码；截断时丢弃末尾被切断的不完整字符，避免多出一个替换符
+fn decode_lossy(bytes: &[u8], truncated: bool) -> String {
+    let bytes = match std::str::from_utf8(bytes) {
+        Err(e) if truncated && e.error_len().is_none() => &bytes[..e.valid_up_to()],
+        _ => bytes,
+    };
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+fn truncation_note(content_length: Option<u64>, limit: usize) -> String {
+    match content_length {
+        Some(total) => format!("...（已截断，共 {}）", format_size(total)),
+        None => format!("...（已截断，超过 {}）", format_size(limit as u64)),
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    const KB: f64 = 1024.0;
+    const MB: f64 = 1024.0 * 1024.0;
+    let n = bytes as f64;
+    if n >= MB {
+        format!("{:.1}MB", n / MB)
+    } else if n >= KB {
+        format!("{:.1}KB", n / KB)
+    } else {
+        format!("{bytes}B")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::testkit::{MockBody, MockResponse, MockUpstream};
+    use axum::body::Bytes;
+
+    async fn fetch(upstream: &MockUpstream, path: &str) -> Response {
+        reqwest::get(upstream.url(path)).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn huge_body_is_capped_and_annotated() {
+        let upstream = MockUpstream::start().await;
+        let page = format!("<html>{}</html>", "x".repeat(3 * 1024 * 1024));
+        upstream.route("/huge", [MockResponse::text(502, &page)]);
+
+        let body = read_error_body_with_limit(fetch(&upstream, "/huge").await, 1024)
+            .await
+            .unwrap();
+        assert!(body.starts_with("<html>xxx"));
+        assert!(body.len() < 1024 + 64, "{}", body.len());
+        assert!(body.ends_with("...（已截断，共 3.0MB）"), "{body}");
+    }
+
+    #[tokio::test]
+    async fn chunked_body_without_length_notes_limit() {
+        let upstream = MockUpstream::start().await;
+        let mut response = MockResponse::sse(vec!["y".repeat(4096); 64]);
+        response.headers.clear();
+        upstream.route("/chunked", [response]);
+
+        let body = read_error_body_with_limit(fetch(&upstream, "/chunked").await, 2048)
+            .await
+            .unwrap();
+        assert_eq!(body.matches('y').count(), 2048);
+        assert!(body.ends_with("...（已截断，超过 2.0KB）"), "{body}");
+    }
+
+    #[tokio::test]
+    async fn non_utf8_body_is_decoded_lossily() {
+        let upstream = MockUpstream::start().await;
+        // latin-1 编码的 "café: erreur"
+        let mut latin1 = MockResponse::status(500);
+        latin1.body = MockBody::Full(Bytes::from_static(b"caf\xe9: erreur"));
+        upstream.route("/latin1", [latin1]);
+
+        let body = read_error_body_with_limit(fetch(&upstream, "/latin1").await, 1024)
+            .await
+            .unwrap();
+        assert_eq!(body, "caf\u{FFFD}: erreur");
+    }
+
+    #[tokio::test]
+    async fn small_body_is_returned_whole() {
+        let upstream = MockUpstream::start().await;
+        upstream.route("/small", [MockResponse::text(400, "bad request")]);
+
+        let body = read_error_body_with_limit(fetch(&upstream, "/small").await, 11)
+            .await
+            .unwrap();
+        assert_eq!(body, "bad request");
+    }
+
+    #[test]
+    fn truncation_drops_split_multibyte_char() {
+        let bytes = "错误".as_bytes();
+        assert_eq!(decode_lossy(&bytes[..4], true), "错");
+        assert_eq!(decode_lossy(&bytes[..4], false), "错\u{FFFD}");
+    }
+}
@@ -12,6 +12,7 @@ use super::ProxyError;
 /// - 连接失败：502 Bad Gateway
 /// - 无可用 Provider：503 Service Unavailable
 /// - 重试耗尽：503 Service Unavailable
+/// - 全链路失败：502 Bad Gateway
 /// - 其他错误：500 Internal Server Error
 pub fn map_proxy_error_to_status(error: &ProxyError) -> u16 {
     match error {
@@ -24,12 +25,17 @@ pub fn map_proxy_error_to_status(error: &ProxyError) -> u16 {
         // 转发失败/连接失败：502 Bad Gateway
         ProxyError::ForwardFailed(_) => 502,
 
-        // 无可用 Provider：503 Service Unavailable
-        ProxyError::NoAvailableProvider => 503,
+        // 无可用 Provider / 供应商配置缺失：503 Service Unavailable
+        ProxyError::NoAvailableProvider
+        | ProxyError::NoProvidersConfigured { .. }
+        | ProxyError::CurrentProviderMissing { .. } => 503,
 
         // 重试耗尽：503 Service Unavailable
         ProxyError::MaxRetriesExceeded => 503,
 
+        // 故障转移链全部失败：502 Bad Gateway
+        ProxyError::AllProvidersFailed(_) => 502,
+
         // Provider 不健康：503 Service Unavailable
         ProxyError::ProviderUnhealthy(_) => 503,
 
@@ -58,6 +64,7 @@ pub fn get_error_message(error: &ProxyError) -> String {
         ProxyError::ForwardFailed(msg) => format!("转发失败: {msg}"),
         ProxyError::NoAvailableProvider => "无可用 Provider".to_string(),
         ProxyError::MaxRetriesExceeded => "所有 Provider 都失败，重试耗尽".to_string(),
+        ProxyError::AllProvidersFailed(report) => report.summary(),
         ProxyError::ProviderUnhealthy(msg) => format!("Provider 不健康: {msg}"),
         ProxyError::DatabaseError(msg) => format!("数据库错误: {msg}"),
         ProxyError::TransformError(msg) => format!("请求/响应转换错误: {msg}"),
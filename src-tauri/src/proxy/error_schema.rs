@@ -0,0 +1,310 @@
+//! 错误响应格式适配：客户端总是收到自己协议的错误结构
+//!
+//! 故障转移链最后失败的供应商可能与客户端协议不同（如 Codex 请求最终落在 Anthropic 形态的网关），
+//! 代理自身生成的错误也只有一种通用结构，客户端解析失败时只会显示空白错误。
+//! 本中间件在响应边界把所有非 2xx 的 API 响应改写为请求所属应用的原生结构：
+//! - Codex（OpenAI）：`{"error":{"message","type","code"}}`
+//! - Claude（Anthropic）：`{"type":"error","error":{"type","message"}}`
+//! - Gemini（Google）：`{"error":{"code","message","status"}}`
+//!
+//! 状态码与原始错误信息保持不变；改写时原始响应体放在 [`ORIGINAL_ERROR_KEY`] 下便于排查。
+//! 已经是原生结构的响应（包括故障转移报告）原样返回。
+
+use super::inflight::app_type_from_path;
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::{header, HeaderValue};
+use axum::middleware::Next;
+use axum::response::Response;
+use serde_json::{json, Map, Value};
+
+/// 改写后保存原始错误响应体的扩展字段
+pub const ORIGINAL_ERROR_KEY: &str = "x_cc_switch_original_error";
+
+/// 改写时最多读取的错误响应体大小（上游错误体已在读取时截断，这里只做兜底）
+const MAX_ERROR_BODY_BYTES: usize = 1024 * 1024;
+
+/// 中间件：把 API 路由的非 2xx 响应改写为请求所属应用的原生错误结构
+pub async fn translate_errors(request: Request, next: Next) -> Response {
+    let app_type = app_type_from_path(request.uri().path());
+    let response = next.run(request).await;
+    if app_type == "-" || response.status().is_success() {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, MAX_ERROR_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            log::debug!("[{app_type}] 读取错误响应体失败，无法改写: {e}");
+            Default::default()
+        }
+    };
+    let Some(native) = to_native_error(app_type, parts.status.as_u16(), &bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    Response::from_parts(parts, Body::from(native.to_string()))
+}
+
+/// 把错误响应体转换为 `app_type` 的原生结构；已是原生结构时返回 `None`
+pub fn to_native_error(app_type: &str, status: u16, body: &[u8]) -> Option<Value> {
+    let text = String::from_utf8_lossy(body);
+    let parsed = serde_json::from_str::<Value>(text.trim()).ok();
+    if parsed
+        .as_ref()
+        .is_some_and(|value| is_native(app_type, value))
+    {
+        return None;
+    }
+
+    let fields = ErrorFields::extract(parsed.as_ref(), text.trim(), status);
+    let mut native = match app_type {
+        "claude" => json!({
+            "type": "error",
+            "error": {
+                "type": fields.anthropic_type(status),
+                "message": fields.message,
+            }
+        }),
+        "gemini" => json!({
+            "error": {
+                "code": status,
+                "message": fields.message,
+                "status": fields.google_status(status),
+            }
+        }),
+        _ => json!({
+            "error": {
+                "message": fields.message,
+                "type": fields.openai_type(status),
+                "code": fields.code,
+            }
+        }),
+    };
+
+    let original = match parsed {
+        Some(value) => value,
+        None if text.trim().is_empty() => Value::Null,
+        None => Value::String(text.into_owned()),
+    };
+    if !original.is_null() {
+        native[ORIGINAL_ERROR_KEY] = original;
+    }
+    Some(native)
+}
+
+/// 是否已经是该应用的原生错误结构
+fn is_native(app_type: &str, value: &Value) -> bool {
+    let Some(error) = value.get("error").and_then(Value::as_object) else {
+        return false;
+    };
+    let has_str = |map: &Map<String, Value>, key: &str| map.get(key).is_some_and(Value::is_string);
+    match app_type {
+        "claude" => {
+            value.get("type").and_then(Value::as_str) == Some("error")
+                && has_str(error, "type")
+                && has_str(error, "message")
+        }
+        "gemini" => {
+            error.get("code").is_some_and(Value::is_u64)
+                && has_str(error, "message")
+                && has_str(error, "status")
+        }
+        _ => {
+            value.get("type").is_none()
+                && has_str(error, "message")
+                && has_str(error, "type")
+                && !has_str(error, "status")
+        }
+    }
+}
+
+/// 从任意已知结构中提取的错误信息
+struct ErrorFields {
+    message: String,
+    /// 来源结构中的错误类型（OpenAI/Anthropic 的 `error.type`）
+    kind: Option<String>,
+    /// 来源结构中的 Google 状态（`error.status`）
+    google_status: Option<String>,
+    /// 来源结构中的错误码（OpenAI 的 `error.code`）
+    code: Value,
+}
+
+impl ErrorFields {
+    fn extract(parsed: Option<&Value>, text: &str, status: u16) -> Self {
+        let error = parsed.and_then(|v| v.get("error"));
+        let str_at = |v: Option<&Value>, key: &str| {
+            v.and_then(|v| v.get(key))
+                .and_then(Value::as_str)
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+        };
+
+        let message = str_at(error, "message")
+            .or_else(|| error.and_then(Value::as_str).map(str::to_string))
+            .or_else(|| str_at(parsed, "message"))
+            .or_else(|| str_at(parsed, "detail"))
+            .or_else(|| (parsed.is_none() && !text.is_empty()).then(|| text.to_string()))
+            .unwrap_or_else(|| format!("Upstream error (status {status})"));
+
+        let code = match error.and_then(|e| e.get("code")) {
+            Some(Value::String(s)) => Value::String(s.clone()),
+            Some(Value::Number(n)) if !error.is_some_and(|e| e.get("status").is_some()) => {
+                Value::String(n.to_string())
+            }
+            _ => Value::Null,
+        };
+
+        Self {
+            message,
+            kind: str_at(error, "type"),
+            google_status: str_at(error, "status"),
+            code,
+        }
+    }
+
+    fn openai_type(&self, status: u16) -> String {
+        if let Some(kind) = &self.kind {
+            return kind.clone();
+        }
+        match status {
+            401 => "authentication_error",
+            403 => "permission_error",
+            429 => "rate_limit_error",
+            400..=499 => "invalid_request_error",
+            _ => "server_error",
+        }
+        .to_string()
+    }
+
+    fn anthropic_type(&self, status: u16) -> String {
+        const ANTHROPIC_TYPES: [&str; 9] = [
+            "invalid_request_error",
+            "authentication_error",
+            "permission_error",
+            "not_found_error",
+            "request_too_large",
+            "rate_limit_error",
+            "api_error",
+            "overloaded_error",
+            "billing_error",
+        ];
+        if let Some(kind) = self.kind.as_deref().filter(|k| ANTHROPIC_TYPES.contains(k)) {
+            return kind.to_string();
+        }
+        match status {
+            401 => "authentication_error",
+            402 => "billing_error",
+            403 => "permission_error",
+            404 => "not_found_error",
+            413 => "request_too_large",
+            429 => "rate_limit_error",
+            529 => "overloaded_error",
+            400..=499 => "invalid_request_error",
+            _ => "api_error",
+        }
+        .to_string()
+    }
+
+    fn google_status(&self, status: u16) -> String {
+        if let Some(s) = &self.google_status {
+            return s.clone();
+        }
+        match status {
+            400 | 422 => "INVALID_ARGUMENT",
+            401 => "UNAUTHENTICATED",
+            403 => "PERMISSION_DENIED",
+            404 => "NOT_FOUND",
+            409 => "ABORTED",
+            429 => "RESOURCE_EXHAUSTED",
+            499 => "CANCELLED",
+            501 => "UNIMPLEMENTED",
+            502 | 503 | 529 => "UNAVAILABLE",
+            504 => "DEADLINE_EXCEEDED",
+            400..=499 => "FAILED_PRECONDITION",
+            _ => "INTERNAL",
+        }
+        .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ANTHROPIC_OVERLOADED: &str =
+        r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#;
+    const OPENAI_RATE_LIMIT: &str =
+        r#"{"error":{"message":"Slow down","type":"requests","code":"rate_limit_exceeded"}}"#;
+    const GOOGLE_NOT_FOUND: &str =
+        r#"{"error":{"code":404,"message":"model not found","status":"NOT_FOUND"}}"#;
+
+    #[test]
+    fn codex_receives_openai_shape() {
+        let native = to_native_error("codex", 529, ANTHROPIC_OVERLOADED.as_bytes()).unwrap();
+        assert_eq!(native["error"]["message"], "Overloaded");
+        assert_eq!(native["error"]["type"], "overloaded_error");
+        assert_eq!(native["error"]["code"], Value::Null);
+        assert!(native.get("type").is_none());
+        assert_eq!(
+            native[ORIGINAL_ERROR_KEY]["error"]["type"],
+            "overloaded_error"
+        );
+
+        let native = to_native_error("codex", 404, GOOGLE_NOT_FOUND.as_bytes()).unwrap();
+        assert_eq!(native["error"]["message"], "model not found");
+        assert_eq!(native["error"]["type"], "invalid_request_error");
+
+        assert!(to_native_error("codex", 429, OPENAI_RATE_LIMIT.as_bytes()).is_none());
+    }
+
+    #[test]
+    fn claude_receives_anthropic_shape() {
+        let native = to_native_error("claude", 429, OPENAI_RATE_LIMIT.as_bytes()).unwrap();
+        assert_eq!(native["type"], "error");
+        assert_eq!(native["error"]["type"], "rate_limit_error");
+        assert_eq!(native["error"]["message"], "Slow down");
+        assert_eq!(
+            native[ORIGINAL_ERROR_KEY]["error"]["code"],
+            "rate_limit_exceeded"
+        );
+
+        assert!(to_native_error("claude", 529, ANTHROPIC_OVERLOADED.as_bytes()).is_none());
+    }
+
+    #[test]
+    fn gemini_receives_google_shape() {
+        let native = to_native_error("gemini", 502, b"<html>Bad Gateway</html>").unwrap();
+        assert_eq!(native["error"]["code"], 502);
+        assert_eq!(native["error"]["status"], "UNAVAILABLE");
+        assert_eq!(native["error"]["message"], "<html>Bad Gateway</html>");
+        assert_eq!(native[ORIGINAL_ERROR_KEY], "<html>Bad Gateway</html>");
+
+        assert!(to_native_error("gemini", 404, GOOGLE_NOT_FOUND.as_bytes()).is_none());
+    }
+
+    #[test]
+    fn proxy_errors_and_empty_bodies_are_translated() {
+        let proxy_error =
+            json!({ "error": { "message": "无可用的Provider", "type": "proxy_error" } });
+        let body = proxy_error.to_string();
+
+        let claude = to_native_error("claude", 503, body.as_bytes()).unwrap();
+        assert_eq!(claude["error"]["type"], "api_error");
+        assert_eq!(claude["error"]["message"], "无可用的Provider");
+        let gemini = to_native_error("gemini", 503, body.as_bytes()).unwrap();
+        assert_eq!(gemini["error"]["status"], "UNAVAILABLE");
+        assert!(to_native_error("codex", 503, body.as_bytes()).is_none());
+
+        let empty = to_native_error("claude", 500, b"").unwrap();
+        assert_eq!(empty["error"]["message"], "Upstream error (status 500)");
+        assert!(empty.get(ORIGINAL_ERROR_KEY).is_none());
+    }
+}
@@ -0,0 +1,206 @@
+//! 路由事件记录器
+//!
+//! 熔断状态切换、supplier 冷却、URL 疑似失效、故障转移切换、余额耗尽跳过等决策
+//! 通过 [`EventRecorder`] 写入 `events` 表。记录只是把事件放入通道，
+//! 由后台任务按批（满 [`BATCH_SIZE`] 条或每隔 [`FLUSH_INTERVAL`]）在单个事务中写入，不阻塞请求路径。
+
+use crate::database::{Database, EventKind, EventLogEntry};
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+
+/// 单批最多写入的事件数（达到后立即写入）
+const BATCH_SIZE: usize = 64;
+/// 未满一批时的最长等待时间
+const FLUSH_INTERVAL: Duration = Duration::from_secs(2);
+
+enum Command {
+    Record(EventLogEntry),
+    Flush(oneshot::Sender<()>),
+}
+
+/// 事件记录句柄（可廉价克隆；未启用时记录为空操作）
+#[derive(Clone, Default)]
+pub struct EventRecorder {
+    tx: Option<mpsc::UnboundedSender<Command>>,
+}
+
+impl EventRecorder {
+    /// 不记录事件（单元测试与 CLI 临时路由器使用）
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    /// 启动后台写入任务；所有句柄释放后写完剩余事件并退出
+    pub fn spawn(db: Arc<Database>) -> Self {
+        Self::spawn_with(db, BATCH_SIZE, FLUSH_INTERVAL)
+    }
+
+    fn spawn_with(db: Arc<Database>, batch_size: usize, interval: Duration) -> Self {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            log::warn!("[Events] 当前不在异步运行时中，事件日志未启用");
+            return Self::disabled();
+        };
+        let (tx, rx) = mpsc::unbounded_channel();
+        runtime.spawn(run_writer(db, rx, batch_size.max(1), interval));
+        Self { tx: Some(tx) }
+    }
+
+    /// 记录一条事件（立即返回）
+    pub fn record(
+        &self,
+        app_type: &str,
+        kind: EventKind,
+        provider_id: Option<&str>,
+        supplier: Option<&str>,
+        detail: Value,
+    ) {
+        let Some(tx) = &self.tx else {
+            return;
+        };
+        let _ = tx.send(Command::Record(EventLogEntry {
+            id: 0,
+            created_at: chrono::Utc::now().timestamp(),
+            app_type: app_type.to_string(),
+            kind,
+            provider_id: provider_id.map(str::to_string),
+            supplier: supplier.map(str::to_string),
+            detail,
+        }));
+    }
+
+    /// 立即写入已排队的事件并等待完成
+    pub async fn flush(&self) {
+        let Some(tx) = &self.tx else {
+            return;
+        };
+        let (ack_tx, ack_rx) = oneshot::channel();
+        if tx.send(Command::Flush(ack_tx)).is_ok() {
+            let _ = ack_rx.await;
+        }
+    }
+}
+
+async fn run_writer(
+    db: Arc<Database>,
+    mut rx: mpsc::UnboundedReceiver<Command>,
+    batch_size: usize,
+    interval: Duration,
+) {
+    let mut pending: Vec<EventLogEntry> = Vec::new();
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    // 首个 tick 立即触发：此时没有待写入事件
+    ticker.tick().await;
+
+    loop {
+        tokio::select! {
+            command = rx.recv() => match command {
+                Some(Command::Record(event)) => {
+                    pending.push(event);
+                    if pending.len() >= batch_size {
+                        write_batch(&db, &mut pending);
+                    }
+                }
+                Some(Command::Flush(ack)) => {
+                    write_batch(&db, &mut pending);
+                    let _ = ack.send(());
+                }
+                None => {
+                    write_batch(&db, &mut pending);
+                    break;
+                }
+            },
+            _ = ticker.tick() => write_batch(&db, &mut pending),
+        }
+    }
+}
+
+fn write_batch(db: &Database, pending: &mut Vec<EventLogEntry>) {
+    if pending.is_empty() {
+        return;
+    }
+    if let Err(e) = db.insert_events(pending) {
+        log::warn!(
+            "[Events] 写入事件日志失败（丢弃 {} 条）: {e}",
+            pending.len()
+        );
+    }
+    pending.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn count(db: &Database) -> usize {
+        db.query_events("codex", None, 0, 100).unwrap().len()
+    }
+
+    #[tokio::test]
+    async fn test_batches_until_full_or_flushed() {
+        let db = Arc::new(Database::memory().unwrap());
+        let recorder = EventRecorder::spawn_with(db.clone(), 3, Duration::from_secs(3600));
+
+        for i in 0..2 {
+            recorder.record(
+                "codex",
+                EventKind::Cooldown,
+                None,
+                Some("mycorp"),
+                json!({ "seq": i }),
+            );
+        }
+        tokio::task::yield_now().await;
+        assert_eq!(count(&db), 0, "未满一批时不应写入");
+
+        // 第三条凑满一批后立即写入（定时写入间隔很长，不会是定时触发）
+        recorder.record(
+            "codex",
+            EventKind::Suspect,
+            Some("p1"),
+            Some("mycorp"),
+            json!({}),
+        );
+        for _ in 0..100 {
+            if count(&db) == 3 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(count(&db), 3);
+
+        // 未满一批时 flush 立即写入
+        recorder.record("codex", EventKind::AllFailed, None, None, json!({}));
+        recorder.flush().await;
+        let events = db.query_events("codex", None, 0, 100).unwrap();
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[0].kind, EventKind::AllFailed);
+        assert_eq!(events[3].detail, json!({ "seq": 0 }));
+    }
+
+    #[tokio::test]
+    async fn test_flushes_partial_batch_on_interval() {
+        let db = Arc::new(Database::memory().unwrap());
+        let recorder = EventRecorder::spawn_with(db.clone(), 100, Duration::from_millis(50));
+
+        recorder.record("codex", EventKind::BreakerOpen, Some("p1"), None, json!({}));
+        // 不调用 flush：等待定时写入
+        for _ in 0..100 {
+            if count(&db) == 1 {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("未满一批的事件应在定时写入时落库");
+    }
+
+    #[tokio::test]
+    async fn test_disabled_recorder_is_noop() {
+        let recorder = EventRecorder::disabled();
+        recorder.record("codex", EventKind::Cooldown, None, None, json!({}));
+        recorder.flush().await;
+    }
+}
@@ -2,19 +2,169 @@
 //!
 //! 处理故障转移成功后的供应商切换逻辑，包括：
 //! - 去重控制（避免多个请求同时触发）
+//! - 防抖（两个供应商交替成功时不反复持久化"当前供应商"）
+//! - 首选供应商恢复后自动切回（`auto_restore_primary`）
 //! - 数据库更新
 //! - 托盘菜单更新
 //! - 前端事件发射
 //! - Live 备份更新
 
-use crate::database::Database;
+use super::circuit_breaker::CircuitState;
+use super::event_log::EventRecorder;
+use crate::database::{Database, EventKind};
 use crate::error::AppError;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::str::FromStr;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tauri::{Emitter, Manager};
 use tokio::sync::RwLock;
 
+/// 默认：同一供应商连续胜出 3 次才持久化切换
+pub const DEFAULT_MIN_CONSECUTIVE_WINS: u32 = 3;
+/// 默认：两次持久化切换之间至少间隔 60 秒
+pub const DEFAULT_MIN_DWELL_SECS: u64 = 60;
+
+/// 切换防抖配置
+///
+/// 满足任一条件即持久化切换：
+/// - 同一供应商连续胜出 `min_consecutive_wins` 次
+/// - 距上次持久化切换已超过 `min_dwell`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlapSuppressionConfig {
+    pub min_consecutive_wins: u32,
+    pub min_dwell: Duration,
+}
+
+impl Default for FlapSuppressionConfig {
+    fn default() -> Self {
+        Self {
+            min_consecutive_wins: DEFAULT_MIN_CONSECUTIVE_WINS,
+            min_dwell: Duration::from_secs(DEFAULT_MIN_DWELL_SECS),
+        }
+    }
+}
+
+impl FlapSuppressionConfig {
+    /// 从设备级设置读取（未设置的项使用默认值）
+    pub fn from_settings(settings: &crate::settings::AppSettings) -> Self {
+        Self {
+            min_consecutive_wins: settings
+                .failover_switch_min_wins
+                .unwrap_or(DEFAULT_MIN_CONSECUTIVE_WINS)
+                .max(1),
+            min_dwell: Duration::from_secs(
+                settings
+                    .failover_switch_dwell_secs
+                    .unwrap_or(DEFAULT_MIN_DWELL_SECS),
+            ),
+        }
+    }
+}
+
+/// UI 事件发射计划
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EmitPlan {
+    /// 立即发射
+    Now,
+    /// 窗口内已发射过，延迟到窗口结束时发射一次
+    After(Duration),
+    /// 已有延迟发射在排队，合并到该次发射
+    Coalesced,
+}
+
+/// 单个应用的防抖状态
+#[derive(Debug, Default)]
+struct AppFlapState {
+    /// 上次持久化切换的时间
+    last_switch_at: Option<Instant>,
+    /// 正在累计连续胜出次数的候选供应商
+    candidate: Option<String>,
+    consecutive_wins: u32,
+    /// 上次发射 UI 事件的时间
+    last_emit_at: Option<Instant>,
+    /// 是否已有延迟发射在排队
+    emit_pending: bool,
+}
+
+/// 切换防抖器（纯状态机，时间由调用方传入，便于测试）
+#[derive(Debug)]
+struct FlapSuppressor {
+    config: FlapSuppressionConfig,
+    apps: HashMap<String, AppFlapState>,
+}
+
+impl FlapSuppressor {
+    fn new(config: FlapSuppressionConfig) -> Self {
+        Self {
+            config,
+            apps: HashMap::new(),
+        }
+    }
+
+    /// 记录一次成功请求，返回是否应持久化切换到 `winner_id`
+    fn observe(&mut self, app_type: &str, winner_id: &str, current_id: &str, now: Instant) -> bool {
+        let config = self.config;
+        let state = self.apps.entry(app_type.to_string()).or_default();
+
+        // 胜出者就是当前供应商：打断候选的连续胜出
+        if winner_id == current_id {
+            state.candidate = None;
+            state.consecutive_wins = 0;
+            return false;
+        }
+
+        if state.candidate.as_deref() == Some(winner_id) {
+            state.consecutive_wins += 1;
+        } else {
+            state.candidate = Some(winner_id.to_string());
+            state.consecutive_wins = 1;
+        }
+
+        let dwell_elapsed = state
+            .last_switch_at
+            .map(|at| now.saturating_duration_since(at) >= config.min_dwell)
+            .unwrap_or(true);
+
+        if dwell_elapsed || state.consecutive_wins >= config.min_consecutive_wins {
+            state.last_switch_at = Some(now);
+            state.candidate = None;
+            state.consecutive_wins = 0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// 决定 UI 事件的发射时机：每个驻留窗口最多发射一次
+    fn plan_emit(&mut self, app_type: &str, now: Instant) -> EmitPlan {
+        let min_dwell = self.config.min_dwell;
+        let state = self.apps.entry(app_type.to_string()).or_default();
+
+        if state.emit_pending {
+            return EmitPlan::Coalesced;
+        }
+
+        match state.last_emit_at {
+            Some(at) if now.saturating_duration_since(at) < min_dwell => {
+                state.emit_pending = true;
+                EmitPlan::After(min_dwell - now.saturating_duration_since(at))
+            }
+            _ => {
+                state.last_emit_at = Some(now);
+                EmitPlan::Now
+            }
+        }
+    }
+
+    /// 延迟发射完成
+    fn finish_deferred_emit(&mut self, app_type: &str, now: Instant) {
+        let state = self.apps.entry(app_type.to_string()).or_default();
+        state.emit_pending = false;
+        state.last_emit_at = Some(now);
+    }
+}
+
 /// 故障转移切换管理器
 ///
 /// 负责处理故障转移成功后的供应商切换，确保 UI 能够直观反映当前使用的供应商。
@@ -22,24 +172,68 @@ use tokio::sync::RwLock;
 pub struct FailoverSwitchManager {
     /// 正在处理中的切换（key = "app_type:provider_id"）
     pending_switches: Arc<RwLock<HashSet<String>>>,
+    /// 切换防抖状态
+    flap: Arc<Mutex<FlapSuppressor>>,
     db: Arc<Database>,
+    /// 路由事件记录
+    events: EventRecorder,
+    /// 只读模式：不持久化切换（数据库 is_current、设备级设置与 Live 备份）
+    read_only: bool,
 }
 
 impl FailoverSwitchManager {
     pub fn new(db: Arc<Database>) -> Self {
+        Self::with_config(
+            db,
+            FlapSuppressionConfig::from_settings(&crate::settings::get_settings()),
+        )
+    }
+
+    pub fn with_config(db: Arc<Database>, config: FlapSuppressionConfig) -> Self {
         Self {
             pending_switches: Arc::new(RwLock::new(HashSet::new())),
+            flap: Arc::new(Mutex::new(FlapSuppressor::new(config))),
             db,
+            events: EventRecorder::disabled(),
+            read_only: false,
         }
     }
 
+    /// 设置路由事件记录器（代理服务启动时注入）
+    pub fn with_event_recorder(mut self, events: EventRecorder) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// 只读模式（代理服务启动时按全局配置设置）
+    pub fn with_read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// 记录一次成功请求，判断是否应持久化切换
+    ///
+    /// 每次成功请求都应调用（包括胜出者即当前供应商的情况，用于打断连续计数）。
+    /// 内存中的"当前使用"指示由调用方即时更新，不受此处防抖影响。
+    pub fn should_persist_switch(&self, app_type: &str, winner_id: &str, current_id: &str) -> bool {
+        let persist = self
+            .flap
+            .lock()
+            .map(|mut flap| flap.observe(app_type, winner_id, current_id, Instant::now()))
+            .unwrap_or(true);
+        if !persist && winner_id != current_id {
+            log::debug!("[Failover] 切换防抖中，暂不持久化: {app_type} -> {winner_id}");
+        }
+        persist
+    }
+
     /// 尝试执行故障转移切换
     ///
     /// 如果相同的切换已在进行中，则跳过；否则执行切换逻辑。
     ///
     /// # Returns
     /// - `Ok(true)` - 切换成功执行
-    /// - `Ok(false)` - 切换已在进行中，跳过
+    /// - `Ok(false)` - 切换已在进行中或处于只读模式，跳过
     /// - `Err(e)` - 切换过程中发生错误
     pub async fn try_switch(
         &self,
@@ -81,21 +275,43 @@ impl FailoverSwitchManager {
         provider_id: &str,
         provider_name: &str,
     ) -> Result<bool, AppError> {
+        if self.read_only {
+            log::debug!("[Failover] 只读模式，不持久化切换: {app_type} -> {provider_id}");
+            return Ok(false);
+        }
         log::debug!("[Failover] 开始切换供应商: {app_type} -> {provider_name} ({provider_id})");
 
+        let app_type_enum = crate::app_config::AppType::from_str(app_type)
+            .map_err(|_| AppError::Message(format!("无效的应用类型: {app_type}")))?;
+
+        // 0. 记录/清除待切回的首选供应商
+        self.track_restore_primary(&app_type_enum, provider_id)?;
+
         // 1. 更新数据库 is_current
+        let previous = self.db.get_current_provider(app_type).ok().flatten();
         self.db.set_current_provider(app_type, provider_id)?;
+        self.events.record(
+            app_type,
+            EventKind::FailoverSwitch,
+            Some(provider_id),
+            None,
+            serde_json::json!({ "from": previous, "toName": provider_name }),
+        );
 
         // 2. 更新本地 settings（设备级）
-        let app_type_enum = crate::app_config::AppType::from_str(app_type)
-            .map_err(|_| AppError::Message(format!("无效的应用类型: {app_type}")))?;
         crate::settings::set_current_provider(&app_type_enum, Some(provider_id))?;
 
-        // 3. 更新托盘菜单和发射事件
+        super::notifications::notify(
+            super::notifications::NotificationPayload::new(
+                super::notifications::NotificationEvent::FailoverSwitch,
+                app_type,
+            )
+            .with_provider(provider_id, provider_name),
+        );
+
+        // 3. 更新 Live 备份（确保代理停止时恢复正确配置），托盘与前端事件按窗口合并发射
         if let Some(app) = app_handle {
-            // 更新托盘菜单
             if let Some(app_state) = app.try_state::<crate::store::AppState>() {
-                // 更新 Live 备份（确保代理停止时恢复正确配置）
                 if let Ok(Some(provider)) = self.db.get_provider_by_id(provider_id, app_type) {
                     if let Err(e) = app_state
                         .proxy_service
@@ -105,30 +321,422 @@ impl FailoverSwitchManager {
                         log::warn!("[Failover] 更新 Live 备份失败: {e}");
                     }
                 }
+            }
+
+            self.notify_ui(app, app_type, provider_id);
+        }
+
+        log::debug!("[Failover] 供应商切换完成: {app_type} -> {provider_name} ({provider_id})");
+
+        Ok(true)
+    }
+
+    /// 自动切换前记录首选供应商：仅在首次自动切走时记录，切回首选时清除
+    fn track_restore_primary(
+        &self,
+        app_type: &crate::app_config::AppType,
+        target_id: &str,
+    ) -> Result<(), AppError> {
+        let remembered = crate::settings::get_restore_primary(app_type);
+        if remembered.as_deref() == Some(target_id) {
+            log::info!(
+                "[Failover] 已切回首选供应商: {} -> {target_id}",
+                app_type.as_str()
+            );
+            return crate::settings::set_restore_primary(app_type, None);
+        }
+
+        if remembered.is_some() || !crate::settings::get_auto_restore_primary(app_type) {
+            return Ok(());
+        }
+
+        let previous = match crate::settings::get_current_provider(app_type) {
+            Some(id) => Some(id),
+            None => self.db.get_current_provider(app_type.as_str())?,
+        };
+        if let Some(previous) = previous.filter(|id| id != target_id) {
+            log::info!(
+                "[Failover] 记录首选供应商，恢复后自动切回: {} -> {previous}",
+                app_type.as_str()
+            );
+            crate::settings::set_restore_primary(app_type, Some(&previous))?;
+        }
+        Ok(())
+    }
+
+    /// `provider_id` 是否为等待切回的首选供应商（轻量检查，供请求路径调用）
+    pub fn is_restore_candidate(&self, app_type: &str, provider_id: &str) -> bool {
+        crate::app_config::AppType::from_str(app_type)
+            .ok()
+            .filter(crate::settings::get_auto_restore_primary)
+            .and_then(|t| crate::settings::get_restore_primary(&t))
+            .is_some_and(|id| id == provider_id)
+    }
+
+    /// 首选供应商恢复后切回
+    ///
+    /// 条件：开启 `auto_restore_primary`、`provider_id` 为记录的首选供应商、熔断器已闭合，
+    /// 且调用方已观察到一次成功的探测或真实请求。切回后清除记录。
+    ///
+    /// # Returns
+    /// - `Ok(true)` - 已切回
+    /// - `Ok(false)` - 条件不满足，未切换
+    pub async fn try_restore_primary(
+        &self,
+        app_handle: Option<&tauri::AppHandle>,
+        app_type: &str,
+        provider_id: &str,
+        breaker_state: CircuitState,
+    ) -> Result<bool, AppError> {
+        if breaker_state != CircuitState::Closed
+            || !self.is_restore_candidate(app_type, provider_id)
+        {
+            return Ok(false);
+        }
 
-                // 重建托盘菜单
-                if let Ok(new_menu) = crate::tray::create_tray_menu(app, app_state.inner()) {
-                    if let Some(tray) = app.tray_by_id("main") {
-                        if let Err(e) = tray.set_menu(Some(new_menu)) {
-                            log::error!("[Failover] 更新托盘菜单失败: {e}");
-                        }
+        let app_type_enum = crate::app_config::AppType::from_str(app_type)
+            .map_err(|_| AppError::Message(format!("无效的应用类型: {app_type}")))?;
+        let current = crate::settings::get_effective_current_provider(&self.db, &app_type_enum)?;
+        if current.as_deref() == Some(provider_id) {
+            crate::settings::set_restore_primary(&app_type_enum, None)?;
+            return Ok(false);
+        }
+
+        let provider_name = self
+            .db
+            .get_provider_by_id(provider_id, app_type)?
+            .map(|p| p.name)
+            .unwrap_or_else(|| provider_id.to_string());
+        log::info!(
+            "[Recovery] 首选供应商已恢复，自动切回: {app_type} -> {provider_name} ({provider_id})"
+        );
+
+        self.try_switch(app_handle, app_type, provider_id, &provider_name)
+            .await
+    }
+
+    /// 合并托盘/前端更新：每个驻留窗口最多发射一次，窗口内的后续切换在窗口结束时以最新状态发射
+    fn notify_ui(&self, app: &tauri::AppHandle, app_type: &str, provider_id: &str) {
+        let plan = self
+            .flap
+            .lock()
+            .map(|mut flap| flap.plan_emit(app_type, Instant::now()))
+            .unwrap_or(EmitPlan::Now);
+
+        match plan {
+            EmitPlan::Now => emit_switch_event(app, app_type, provider_id),
+            EmitPlan::After(delay) => {
+                let app = app.clone();
+                let flap = self.flap.clone();
+                let app_type = app_type.to_string();
+                tokio::spawn(async move {
+                    tokio::time::sleep(delay).await;
+                    if let Ok(mut flap) = flap.lock() {
+                        flap.finish_deferred_emit(&app_type, Instant::now());
                     }
+                    let latest = crate::app_config::AppType::from_str(&app_type)
+                        .ok()
+                        .and_then(|t| crate::settings::get_current_provider(&t));
+                    if let Some(id) = latest {
+                        emit_switch_event(&app, &app_type, &id);
+                    }
+                });
+            }
+            EmitPlan::Coalesced => {
+                log::debug!("[Failover] 合并托盘/前端更新: {app_type} -> {provider_id}");
+            }
+        }
+    }
+}
+
+/// 重建托盘菜单并发射 `provider-switched` 事件
+fn emit_switch_event(app: &tauri::AppHandle, app_type: &str, provider_id: &str) {
+    if let Some(app_state) = app.try_state::<crate::store::AppState>() {
+        if let Ok(new_menu) = crate::tray::create_tray_menu(app, app_state.inner()) {
+            if let Some(tray) = app.tray_by_id("main") {
+                if let Err(e) = tray.set_menu(Some(new_menu)) {
+                    log::error!("[Failover] 更新托盘菜单失败: {e}");
                 }
             }
+        }
+    }
+
+    let event_data = serde_json::json!({
+        "appType": app_type,
+        "providerId": provider_id,
+        "source": "failover"  // 标识来源是故障转移
+    });
+    if let Err(e) = app.emit("provider-switched", event_data) {
+        log::error!("[Failover] 发射供应商切换事件失败: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app_config::AppType;
+    use crate::provider::Provider;
+    use serde_json::json;
+    use serial_test::serial;
+    use std::env;
+    use tempfile::TempDir;
+
+    struct TempHome {
+        #[allow(dead_code)]
+        dir: TempDir,
+        original_home: Option<String>,
+        original_userprofile: Option<String>,
+    }
+
+    impl TempHome {
+        fn new() -> Self {
+            let dir = TempDir::new().expect("failed to create temp home");
+            let original_home = env::var("HOME").ok();
+            let original_userprofile = env::var("USERPROFILE").ok();
 
-            // 发射事件到前端
-            let event_data = serde_json::json!({
-                "appType": app_type,
-                "providerId": provider_id,
-                "source": "failover"  // 标识来源是故障转移
-            });
-            if let Err(e) = app.emit("provider-switched", event_data) {
-                log::error!("[Failover] 发射供应商切换事件失败: {e}");
+            env::set_var("HOME", dir.path());
+            env::set_var("USERPROFILE", dir.path());
+
+            Self {
+                dir,
+                original_home,
+                original_userprofile,
             }
         }
+    }
 
-        log::debug!("[Failover] 供应商切换完成: {app_type} -> {provider_name} ({provider_id})");
+    impl Drop for TempHome {
+        fn drop(&mut self) {
+            match &self.original_home {
+                Some(value) => env::set_var("HOME", value),
+                None => env::remove_var("HOME"),
+            }
 
-        Ok(true)
+            match &self.original_userprofile {
+                Some(value) => env::set_var("USERPROFILE", value),
+                None => env::remove_var("USERPROFILE"),
+            }
+        }
+    }
+
+    /// 初始化 primary（当前）与 backup 两个供应商，返回切换管理器
+    fn setup_restore(auto_restore: bool) -> (Arc<Database>, FailoverSwitchManager) {
+        crate::settings::reload_settings().expect("reload settings");
+        let mut settings = crate::settings::get_settings();
+        settings.auto_restore_primary_claude = auto_restore;
+        crate::settings::update_settings(settings).expect("update settings");
+
+        let db = Arc::new(Database::memory().expect("init db"));
+        for (id, name) in [("primary", "Primary"), ("backup", "Backup")] {
+            let provider = Provider::with_id(id.to_string(), name.to_string(), json!({}), None);
+            db.save_provider("claude", &provider)
+                .expect("save provider");
+        }
+        db.set_current_provider("claude", "primary")
+            .expect("set current provider");
+        crate::settings::set_current_provider(&AppType::Claude, Some("primary"))
+            .expect("set local current provider");
+
+        let manager = FailoverSwitchManager::with_config(db.clone(), Default::default());
+        (db, manager)
+    }
+
+    fn suppressor() -> FlapSuppressor {
+        FlapSuppressor::new(FlapSuppressionConfig::default())
+    }
+
+    /// 模拟 A/B 交替胜出，返回每次持久化切换的时间（相对起点的秒数）与目标
+    fn simulate_alternating(
+        flap: &mut FlapSuppressor,
+        start: Instant,
+        total_secs: u64,
+    ) -> Vec<(u64, String)> {
+        let mut current = "a".to_string();
+        let mut persisted = Vec::new();
+        for sec in 0..total_secs {
+            let winner = if sec % 2 == 0 { "b" } else { "a" };
+            if flap.observe("claude", winner, &current, start + Duration::from_secs(sec)) {
+                current = winner.to_string();
+                persisted.push((sec, current.clone()));
+            }
+        }
+        persisted
+    }
+
+    #[test]
+    fn test_alternating_winners_persist_at_most_once_per_window() {
+        let mut flap = suppressor();
+        let start = Instant::now();
+
+        let persisted = simulate_alternating(&mut flap, start, 300);
+
+        assert!(!persisted.is_empty());
+        for pair in persisted.windows(2) {
+            assert!(
+                pair[1].0 - pair[0].0 >= DEFAULT_MIN_DWELL_SECS,
+                "两次持久化切换间隔过短: {pair:?}"
+            );
+        }
+        // 300 秒内最多 5 个窗口
+        assert!(persisted.len() <= 5, "{persisted:?}");
+    }
+
+    #[test]
+    fn test_consecutive_wins_persist_before_dwell() {
+        let mut flap = suppressor();
+        let start = Instant::now();
+
+        // 首次切换不受驻留时间限制
+        assert!(flap.observe("claude", "b", "a", start));
+
+        // 窗口内：c 连续胜出 3 次后持久化
+        let t = start + Duration::from_secs(1);
+        assert!(!flap.observe("claude", "c", "b", t));
+        assert!(!flap.observe("claude", "c", "b", t));
+        assert!(flap.observe("claude", "c", "b", t));
+    }
+
+    #[test]
+    fn test_current_provider_win_resets_streak() {
+        let mut flap = suppressor();
+        let start = Instant::now();
+        assert!(flap.observe("claude", "b", "a", start));
+
+        let t = start + Duration::from_secs(1);
+        assert!(!flap.observe("claude", "a", "b", t));
+        assert!(!flap.observe("claude", "a", "b", t));
+        // 当前供应商 b 胜出，打断 a 的连续计数
+        assert!(!flap.observe("claude", "b", "b", t));
+        assert!(!flap.observe("claude", "a", "b", t));
+        assert!(!flap.observe("claude", "a", "b", t));
+        assert!(flap.observe("claude", "a", "b", t));
+    }
+
+    #[test]
+    fn test_apps_are_independent() {
+        let mut flap = suppressor();
+        let now = Instant::now();
+        assert!(flap.observe("claude", "b", "a", now));
+        assert!(flap.observe("codex", "y", "x", now));
+    }
+
+    #[test]
+    fn test_emit_coalesced_within_window() {
+        let mut flap = suppressor();
+        let start = Instant::now();
+
+        assert_eq!(flap.plan_emit("claude", start), EmitPlan::Now);
+        assert_eq!(
+            flap.plan_emit("claude", start + Duration::from_secs(10)),
+            EmitPlan::After(Duration::from_secs(50))
+        );
+        assert_eq!(
+            flap.plan_emit("claude", start + Duration::from_secs(20)),
+            EmitPlan::Coalesced
+        );
+
+        flap.finish_deferred_emit("claude", start + Duration::from_secs(60));
+        assert_eq!(
+            flap.plan_emit("claude", start + Duration::from_secs(130)),
+            EmitPlan::Now
+        );
+    }
+
+    #[test]
+    fn test_config_from_settings() {
+        let mut settings = crate::settings::AppSettings::default();
+        assert_eq!(
+            FlapSuppressionConfig::from_settings(&settings),
+            FlapSuppressionConfig::default()
+        );
+
+        settings.failover_switch_min_wins = Some(0);
+        settings.failover_switch_dwell_secs = Some(5);
+        let config = FlapSuppressionConfig::from_settings(&settings);
+        assert_eq!(config.min_consecutive_wins, 1);
+        assert_eq!(config.min_dwell, Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_restore_primary_after_recovery() {
+        let _home = TempHome::new();
+        let (db, manager) = setup_restore(true);
+
+        // 故障转移切走：记录首选供应商
+        assert!(manager
+            .try_switch(None, "claude", "backup", "Backup")
+            .await
+            .unwrap());
+        assert_eq!(
+            crate::settings::get_restore_primary(&AppType::Claude).as_deref(),
+            Some("primary")
+        );
+        assert!(manager.is_restore_candidate("claude", "primary"));
+        assert!(!manager.is_restore_candidate("claude", "backup"));
+
+        // 熔断器未闭合：不切回
+        assert!(!manager
+            .try_restore_primary(None, "claude", "primary", CircuitState::HalfOpen)
+            .await
+            .unwrap());
+
+        // 恢复：熔断器闭合 + 请求成功，切回并清除记录
+        assert!(manager
+            .try_restore_primary(None, "claude", "primary", CircuitState::Closed)
+            .await
+            .unwrap());
+        assert_eq!(
+            crate::settings::get_current_provider(&AppType::Claude).as_deref(),
+            Some("primary")
+        );
+        assert_eq!(
+            db.get_current_provider("claude").unwrap().as_deref(),
+            Some("primary")
+        );
+        assert_eq!(crate::settings::get_restore_primary(&AppType::Claude), None);
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_manual_switch_cancels_restore() {
+        let _home = TempHome::new();
+        let (db, manager) = setup_restore(true);
+
+        manager
+            .try_switch(None, "claude", "backup", "Backup")
+            .await
+            .unwrap();
+        assert!(manager.is_restore_candidate("claude", "primary"));
+
+        // 用户手动切换
+        crate::services::ProxyService::new(db.clone())
+            .switch_proxy_target("claude", "backup")
+            .await
+            .unwrap();
+        assert_eq!(crate::settings::get_restore_primary(&AppType::Claude), None);
+
+        assert!(!manager
+            .try_restore_primary(None, "claude", "primary", CircuitState::Closed)
+            .await
+            .unwrap());
+        assert_eq!(
+            db.get_current_provider("claude").unwrap().as_deref(),
+            Some("backup")
+        );
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_restore_disabled_does_not_remember() {
+        let _home = TempHome::new();
+        let (_db, manager) = setup_restore(false);
+
+        manager
+            .try_switch(None, "claude", "backup", "Backup")
+            .await
+            .unwrap();
+        assert_eq!(crate::settings::get_restore_primary(&AppType::Claude), None);
+        assert!(!manager.is_restore_candidate("claude", "primary"));
     }
 }
@@ -0,0 +1,275 @@
+//! 供应商请求失败类型
+//!
+//! 转发器根据 `ProxyError` 变体得出结构化的失败类型（超时/连接失败在转发时已由
+//! reqwest 的 `is_timeout` / `is_connect` 判定），路由器据此决定是否标记 URL suspect，
+//! 不再依赖日志文本匹配。只有以纯文本形式到达的错误才回退到文本识别。
+
+use super::error::ProxyError;
+
+/// 失败类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    /// 连接失败（拒绝连接、DNS 解析失败、连接中断、读取响应失败等）
+    Network,
+    /// 请求超时（含流式响应空闲超时）
+    Timeout,
+    /// 上游返回非 2xx
+    UpstreamHttp(u16),
+    /// 上游满载/限流（可达但暂不可用）
+    Overloaded,
+    /// key 余额/额度耗尽（HTTP 402 或 insufficient balance 等提示），短时间内不会恢复
+    Depleted,
+    /// 其他（配置、认证、格式转换等）
+    Other,
+}
+
+impl FailureKind {
+    /// 从转发错误得出失败类型
+    pub fn from_proxy_error(err: &ProxyError) -> Self {
+        match err {
+            ProxyError::Timeout(_) | ProxyError::StreamIdleTimeout(_) => FailureKind::Timeout,
+            ProxyError::ForwardFailed(_) => FailureKind::Network,
+            ProxyError::UpstreamError { status, body } => {
+                let depleted = *status == 402 || body.as_deref().is_some_and(is_depleted_text);
+                let overloaded = *status == 429 || body.as_deref().is_some_and(is_overloaded_text);
+                if depleted {
+                    FailureKind::Depleted
+                } else if overloaded {
+                    FailureKind::Overloaded
+                } else {
+                    FailureKind::UpstreamHttp(*status)
+                }
+            }
+            _ => FailureKind::Other,
+        }
+    }
+
+    /// 文本兜底：仅用于没有结构化错误、只有错误文本的场景
+    pub fn from_error_text(text: &str) -> Self {
+        if text.contains("超时") || text.contains("timed out") {
+            FailureKind::Timeout
+        } else if text.contains("连接失败")
+            || text.contains("Connection refused")
+            || text.contains("connection refused")
+            || text.contains("dns")
+            || text.contains("DNS")
+            || text.contains("error sending request")
+            || text.contains("connection closed")
+            || text.contains("Upstream request failed")
+            || text.contains("请求转发失败: error")
+        {
+            FailureKind::Network
+        } else if is_depleted_text(text) {
+            FailureKind::Depleted
+        } else if is_overloaded_text(text) {
+            FailureKind::Overloaded
+        } else {
+            FailureKind::Other
+        }
+    }
+
+    /// 是否为链路层失败（URL 本身可能不可用，应标记 suspect 并在同 supplier 内换 URL）
+    pub fn is_link_failure(self) -> bool {
+        matches!(self, FailureKind::Network | FailureKind::Timeout)
+    }
+}
+
+/// 常见“可达但不可用”的提示（满载/限流/暂不可用）
+pub(crate) fn is_overloaded_text(text: &str) -> bool {
+    text.contains("负载已经达到上限")
+        || text.contains("满载")
+        || text.contains("rate limit")
+        || text.contains("Rate limit")
+        || text.contains("Too Many Requests")
+        || text.contains("temporarily unavailable")
+}
+
+/// 常见“余额/额度耗尽”的提示
+pub(crate) fn is_depleted_text(text: &str) -> bool {
+    let lower = text.to_lowercase();
+    lower.contains("insufficient balance")
+        || lower.contains("insufficient_balance")
+        || lower.contains("insufficient quota")
+        || lower.contains("insufficient_quota")
+        || lower.contains("credit balance is too low")
+        || lower.contains("payment required")
+        || text.contains("余额不足")
+        || text.contains("额度不足")
+        || text.contains("额度已用尽")
+}
+
+/// 从上游错误响应体中提取错误信息（兼容 `error.message` 与 `message` 两种结构）
+pub(crate) fn upstream_error_message(body: &str) -> Option<String> {
+    let v = serde_json::from_str::<serde_json::Value>(body).ok()?;
+    v.get("error")
+        .and_then(|e| e.get("message"))
+        .or_else(|| v.get("message"))
+        .and_then(|m| m.as_str())
+        .map(|m| m.to_string())
+}
+
+/// 余额耗尽错误的描述（`余额不足: <上游提示>`），用于健康记录与 CLI 展示；其它错误返回 None
+pub fn depleted_summary(err: &ProxyError) -> Option<String> {
+    if FailureKind::from_proxy_error(err) != FailureKind::Depleted {
+        return None;
+    }
+    let ProxyError::UpstreamError { status, body } = err else {
+        return None;
+    };
+    let message = body
+        .as_deref()
+        .map(|b| upstream_error_message(b).unwrap_or_else(|| b.trim().to_string()))
+        .filter(|m| !m.is_empty())
+        .unwrap_or_else(|| format!("HTTP {status}"));
+    Some(format!(
+        "余额不足: {}",
+        message.chars().take(200).collect::<String>()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::failure_report::FailureReport;
+
+    #[test]
+    fn test_proxy_error_variants_map_to_kinds() {
+        let cases = [
+            (
+                ProxyError::Timeout("请求超时: operation timed out".into()),
+                FailureKind::Timeout,
+            ),
+            (ProxyError::StreamIdleTimeout(30), FailureKind::Timeout),
+            (
+                ProxyError::ForwardFailed("连接失败: dns error".into()),
+                FailureKind::Network,
+            ),
+            (
+                ProxyError::ForwardFailed("Failed to read response body: eof".into()),
+                FailureKind::Network,
+            ),
+            (
+                ProxyError::UpstreamError {
+                    status: 502,
+                    body: Some("bad gateway".into()),
+                },
+                FailureKind::UpstreamHttp(502),
+            ),
+            (
+                ProxyError::UpstreamError {
+                    status: 429,
+                    body: None,
+                },
+                FailureKind::Overloaded,
+            ),
+            (
+                ProxyError::UpstreamError {
+                    status: 503,
+                    body: Some(r#"{"error":{"message":"当前分组负载已经达到上限"}}"#.into()),
+                },
+                FailureKind::Overloaded,
+            ),
+            (
+                ProxyError::UpstreamError {
+                    status: 402,
+                    body: None,
+                },
+                FailureKind::Depleted,
+            ),
+            (
+                ProxyError::UpstreamError {
+                    status: 429,
+                    body: Some(
+                        r#"{"error":{"message":"You exceeded your current quota","code":"insufficient_quota"}}"#
+                            .into(),
+                    ),
+                },
+                FailureKind::Depleted,
+            ),
+            (
+                ProxyError::UpstreamError {
+                    status: 400,
+                    body: Some(r#"{"message":"账户余额不足，请充值"}"#.into()),
+                },
+                FailureKind::Depleted,
+            ),
+            (
+                ProxyError::AuthError("缺少认证信息".into()),
+                FailureKind::Other,
+            ),
+            (ProxyError::ConfigError("bad".into()), FailureKind::Other),
+            (ProxyError::TransformError("bad".into()), FailureKind::Other),
+            (ProxyError::NoAvailableProvider, FailureKind::Other),
+            (ProxyError::MaxRetriesExceeded, FailureKind::Other),
+            (
+                ProxyError::AllProvidersFailed(Box::new(FailureReport::new("claude"))),
+                FailureKind::Other,
+            ),
+        ];
+
+        for (err, expected) in cases {
+            assert_eq!(FailureKind::from_proxy_error(&err), expected, "{err:?}");
+        }
+    }
+
+    #[test]
+    fn test_only_link_failures_are_suspect() {
+        assert!(FailureKind::Network.is_link_failure());
+        assert!(FailureKind::Timeout.is_link_failure());
+        assert!(!FailureKind::UpstreamHttp(500).is_link_failure());
+        assert!(!FailureKind::Overloaded.is_link_failure());
+        assert!(!FailureKind::Depleted.is_link_failure());
+        assert!(!FailureKind::Other.is_link_failure());
+    }
+
+    #[test]
+    fn test_text_fallback() {
+        assert_eq!(
+            FailureKind::from_error_text("请求转发失败: 连接失败: Connection refused"),
+            FailureKind::Network
+        );
+        assert_eq!(
+            FailureKind::from_error_text("超时: 请求超时"),
+            FailureKind::Timeout
+        );
+        assert_eq!(
+            FailureKind::from_error_text("Too Many Requests"),
+            FailureKind::Overloaded
+        );
+        assert_eq!(
+            FailureKind::from_error_text("余额不足: Insufficient Balance"),
+            FailureKind::Depleted
+        );
+        assert_eq!(
+            FailureKind::from_error_text("上游错误 (状态码 500)"),
+            FailureKind::Other
+        );
+    }
+
+    #[test]
+    fn test_depleted_summary_uses_upstream_message() {
+        let err = ProxyError::UpstreamError {
+            status: 402,
+            body: Some(r#"{"error":{"message":"Insufficient Balance"}}"#.into()),
+        };
+        assert_eq!(
+            depleted_summary(&err).as_deref(),
+            Some("余额不足: Insufficient Balance")
+        );
+
+        let err = ProxyError::UpstreamError {
+            status: 402,
+            body: None,
+        };
+        assert_eq!(
+            depleted_summary(&err).as_deref(),
+            Some("余额不足: HTTP 402")
+        );
+
+        let err = ProxyError::UpstreamError {
+            status: 429,
+            body: Some("Rate limit exceeded".into()),
+        };
+        assert!(depleted_summary(&err).is_none());
+    }
+}
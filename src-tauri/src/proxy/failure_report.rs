@@ -0,0 +1,240 @@
+//! 全链路失败报告
+//!
+//! 故障转移链中所有供应商都失败时，汇总每一次尝试（供应商、URL、状态码/错误、耗时），
+//! 并按客户端原生错误格式（Anthropic / OpenAI / Gemini）返回给调用方，
+//! 避免客户端只看到“最后一个供应商”的错误而丢失真正有用的信息（例如首个供应商的配额提示）。
+
+use super::ProxyError;
+use crate::provider::Provider;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// 单次尝试错误信息的最大字符数（保持响应体精简）
+const MAX_ATTEMPT_ERROR_CHARS: usize = 200;
+
+/// 摘要中最多展开的尝试条数（其余仅计数）
+const MAX_SUMMARY_ATTEMPTS: usize = 3;
+
+/// 单次失败尝试
+#[derive(Debug, Clone, Serialize)]
+pub struct FailedAttempt {
+    pub provider_id: String,
+    pub provider_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// 上游 HTTP 状态码（网络错误/超时等为 None）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    pub error: String,
+    pub latency_ms: u64,
+}
+
+/// 全链路失败报告（按尝试顺序记录）
+#[derive(Debug, Clone, Serialize)]
+pub struct FailureReport {
+    pub app_type: String,
+    pub attempts: Vec<FailedAttempt>,
+}
+
+impl FailureReport {
+    pub fn new(app_type: &str) -> Self {
+        Self {
+            app_type: app_type.to_string(),
+            attempts: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.attempts.is_empty()
+    }
+
+    /// 记录一次失败尝试
+    pub fn push(
+        &mut self,
+        provider: &Provider,
+        url: Option<String>,
+        error: &ProxyError,
+        latency_ms: u64,
+    ) {
+        let (status, error_text) = match error {
+            ProxyError::UpstreamError { status, body } => {
+                let text = body
+                    .as_deref()
+                    .map(extract_error_message)
+                    .filter(|s| !s.trim().is_empty())
+                    .unwrap_or_else(|| format!("HTTP {status}"));
+                (Some(*status), text)
+            }
+            other => (None, other.to_string()),
+        };
+
+        self.attempts.push(FailedAttempt {
+            provider_id: provider.id.clone(),
+            provider_name: provider.name.clone(),
+            url,
+            status,
+            error: truncate_chars(&error_text, MAX_ATTEMPT_ERROR_CHARS),
+            latency_ms,
+        });
+    }
+
+    /// 可读摘要（用于响应 message 与 status.last_error）
+    pub fn summary(&self) -> String {
+        let total = self.attempts.len();
+        let mut parts: Vec<String> = self
+            .attempts
+            .iter()
+            .take(MAX_SUMMARY_ATTEMPTS)
+            .map(|a| match a.status {
+                Some(code) => format!("{} HTTP {}: {}", a.provider_name, code, a.error),
+                None => format!("{}: {}", a.provider_name, a.error),
+            })
+            .collect();
+        if total > MAX_SUMMARY_ATTEMPTS {
+            parts.push(format!("其余 {} 次省略", total - MAX_SUMMARY_ATTEMPTS));
+        }
+        format!(
+            "所有供应商均失败（共 {total} 次尝试）: {}",
+            parts.join("; ")
+        )
+    }
+
+    /// 按 app 的原生错误格式构造响应体，并附带 `attempts` 数组
+    pub fn to_response_body(&self) -> Value {
+        let message = self.summary();
+        let attempts = serde_json::to_value(&self.attempts).unwrap_or(Value::Array(Vec::new()));
+
+        match self.app_type.as_str() {
+            "claude" => json!({
+                "type": "error",
+                "error": {
+                    "type": "api_error",
+                    "message": message,
+                },
+                "attempts": attempts,
+            }),
+            "gemini" => json!({
+                "error": {
+                    "code": 502,
+                    "message": message,
+                    "status": "UNAVAILABLE",
+                },
+                "attempts": attempts,
+            }),
+            _ => json!({
+                "error": {
+                    "message": message,
+                    "type": "upstream_error",
+                    "code": "all_providers_failed",
+                },
+                "attempts": attempts,
+            }),
+        }
+    }
+}
+
+/// 从上游错误体中提取可读信息（兼容 `error.message` / `message`），否则返回原文
+fn extract_error_message(body: &str) -> String {
+    if let Ok(v) = serde_json::from_str::<Value>(body) {
+        if let Some(msg) = v
+            .get("error")
+            .and_then(|e| e.get("message"))
+            .and_then(|m| m.as_str())
+        {
+            return msg.to_string();
+        }
+        if let Some(msg) = v.get("message").and_then(|m| m.as_str()) {
+            return msg.to_string();
+        }
+    }
+    body.trim().to_string()
+}
+
+fn truncate_chars(s: &str, max_chars: usize) -> String {
+    if s.chars().count() <= max_chars {
+        return s.to_string();
+    }
+    let mut out: String = s.chars().take(max_chars).collect();
+    out.push('…');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider(id: &str, name: &str) -> Provider {
+        Provider::with_id(id.to_string(), name.to_string(), json!({}), None)
+    }
+
+    fn sample_report(app_type: &str) -> FailureReport {
+        let mut report = FailureReport::new(app_type);
+        report.push(
+            &provider("a", "anyrouter-key1"),
+            Some("https://a.example.com".to_string()),
+            &ProxyError::UpstreamError {
+                status: 429,
+                body: Some(r#"{"error":{"message":"quota exceeded"}}"#.to_string()),
+            },
+            120,
+        );
+        report.push(
+            &provider("b", "hyb-key2"),
+            Some("https://b.example.com".to_string()),
+            &ProxyError::ForwardFailed("连接失败: connection refused".to_string()),
+            15,
+        );
+        report
+    }
+
+    #[test]
+    fn test_claude_body_uses_anthropic_error_shape() {
+        let body = sample_report("claude").to_response_body();
+        assert_eq!(body["type"], "error");
+        assert_eq!(body["error"]["type"], "api_error");
+        let message = body["error"]["message"].as_str().unwrap();
+        assert!(message.contains("quota exceeded"));
+        assert!(message.contains("connection refused"));
+    }
+
+    #[test]
+    fn test_codex_body_uses_openai_error_shape() {
+        let body = sample_report("codex").to_response_body();
+        assert!(body.get("type").is_none());
+        assert_eq!(body["error"]["type"], "upstream_error");
+        assert_eq!(body["error"]["code"], "all_providers_failed");
+        assert!(body["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("2 次尝试"));
+    }
+
+    #[test]
+    fn test_attempts_listed_in_order() {
+        let body = sample_report("codex").to_response_body();
+        let attempts = body["attempts"].as_array().unwrap();
+        assert_eq!(attempts.len(), 2);
+        assert_eq!(attempts[0]["provider_id"], "a");
+        assert_eq!(attempts[0]["status"], 429);
+        assert_eq!(attempts[0]["error"], "quota exceeded");
+        assert_eq!(attempts[1]["provider_id"], "b");
+        assert!(attempts[1].get("status").is_none());
+    }
+
+    #[test]
+    fn test_long_upstream_body_is_truncated() {
+        let mut report = FailureReport::new("claude");
+        report.push(
+            &provider("a", "a"),
+            None,
+            &ProxyError::UpstreamError {
+                status: 500,
+                body: Some("x".repeat(5000)),
+            },
+            1,
+        );
+        let error = &report.attempts[0].error;
+        assert_eq!(error.chars().count(), MAX_ATTEMPT_ERROR_CHARS + 1);
+        assert!(error.ends_with('…'));
+    }
+}
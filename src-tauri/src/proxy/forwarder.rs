@@ -3,30 +3,112 @@
 //! 负责将请求转发到上游Provider，支持重试和故障转移
 
 use super::{
+    body_transforms::apply_body_transforms,
+    env_expand::expand_provider,
     error::*,
+    error_body::read_error_body,
     failover_switch::FailoverSwitchManager,
+    failure_kind::{depleted_summary, FailureKind},
+    failure_report::FailureReport,
+    max_tokens::clamp_max_tokens,
+    model_fallbacks::{fallback_chain, MAX_MODEL_FALLBACK_RETRIES},
+    model_mapper::{apply_default_model, has_thinking_enabled, is_unspecified_model},
+    model_resolver::ModelWriteback,
+    model_trace::{
+        classify_resolution, ModelResolutionTrace, STEP_BEDROCK, STEP_DEFAULT, STEP_LOCKED,
+        STEP_MAPPING, STEP_RETRY, STEP_TRANSFORM,
+    },
     provider_router::ProviderRouter,
-    providers::{get_adapter, ProviderAdapter},
-    types::{last_request_summary_setting_key, LastRequestSummary, ProxyStatus},
+    providers::{
+        create_bedrock_sse_stream, get_adapter, python_proxy_auth_header, to_bedrock_body,
+        AuthInfo, AzureConfig, BedrockConfig, ProviderAdapter, VertexConfig,
+    },
+    request_trace::{RequestTrace, SkipReason},
+    shadow,
+    sse_filter::filter_sse_response,
+    system_prompt::apply_system_prompt,
+    thinking_capability::{
+        configured_support, is_thinking_unsupported_error, SUPPORTS_THINKING_KEY,
+    },
+    types::{last_request_summary_setting_key, LastRequestSummary, ProxyStatus, ShadowConfig},
+    usage::parser::TokenUsage,
     ProxyError,
 };
 use crate::database::Database;
-use crate::proxy::circuit_breaker::AllowResult;
+use crate::i18n::{pad_display, tr, Locale, Msg};
+use crate::proxy::circuit_breaker::{AllowResult, BreakerRejection};
+use crate::proxy::handler_context::RequestScope;
 use crate::{app_config::AppType, provider::Provider};
 use reqwest::{Client, Response};
 use serde_json::Value;
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 
+/// 透传给上游的客户端请求头（白名单模式，小写）
+pub(crate) const FORWARDED_HEADERS: &[&str] = &[
+    "accept",
+    "user-agent",
+    "x-request-id",
+    "openai-beta",
+    "openai-version",
+    "openai-organization",
+    "openai-project",
+    "x-stainless-arch",
+    "x-stainless-lang",
+    "x-stainless-os",
+    "x-stainless-package-version",
+    "x-stainless-runtime",
+    "x-stainless-runtime-version",
+];
+
+/// 按非流式超时复用的 HTTP 客户端：同一超时配置的请求共享连接池，
+/// 避免每个请求重新建立 TLS 连接（连接保温也经由同一连接池）
+static SHARED_CLIENTS: OnceLock<Mutex<HashMap<u64, Client>>> = OnceLock::new();
+
+/// 获取（必要时创建）指定非流式超时（秒，0 表示禁用）对应的共享客户端
+pub(crate) fn shared_client(non_streaming_timeout: u64) -> Client {
+    // 全局超时设置为 1800 秒（30 分钟），确保业务层超时配置能正常工作
+    // 参考 Claude Code Hub 的 undici 全局超时设计
+    const GLOBAL_TIMEOUT_SECS: u64 = 1800;
+
+    let mut clients = SHARED_CLIENTS
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    clients
+        .entry(non_streaming_timeout)
+        .or_insert_with(|| {
+            let timeout = if non_streaming_timeout > 0 {
+                // 使用配置的非流式超时
+                non_streaming_timeout
+            } else {
+                // 禁用超时时使用全局超时作为保底
+                GLOBAL_TIMEOUT_SECS
+            };
+            Client::builder()
+                .timeout(Duration::from_secs(timeout))
+                .build()
+                .expect("Failed to create HTTP client")
+        })
+        .clone()
+}
+
 struct ForwardedResponse {
     response: Response,
     effective_model: Option<String>,
+    /// 该供应商上的模型解析追踪（默认模型 → 映射 → 解析 → 重试）
+    model_trace: ModelResolutionTrace,
 }
 
 pub struct ForwardResult {
     pub response: Response,
     pub provider: Provider,
+    /// 实际发往供应商的模型（模型映射/智能解析之后）
+    pub effective_model: Option<String>,
+    /// Python 代理回传的真实上游耗时（仅 Claude；响应头缺失时为 None）
+    pub upstream_ms: Option<u64>,
 }
 
 pub struct ForwardError {
@@ -34,6 +116,7 @@ pub struct ForwardError {
     pub provider: Option<Provider>,
 }
 
+#[derive(Clone)]
 pub struct RequestForwarder {
     client: Client,
     /// 共享的 ProviderRouter（持有熔断器状态）
@@ -52,6 +135,10 @@ pub struct RequestForwarder {
     app_handle: Option<tauri::AppHandle>,
     /// 请求开始时的"当前供应商 ID"（用于判断是否需要同步 UI/托盘）
     current_provider_id_at_start: String,
+    /// 应用级默认模型（客户端未指定模型时使用）
+    default_model: Option<String>,
+    /// 影子流量配置（按比例镜像非流式请求，仅用于评估）
+    shadow: Option<ShadowConfig>,
 }
 
 impl RequestForwarder {
@@ -62,6 +149,23 @@ impl RequestForwarder {
             .map(|s| s.to_string())
     }
 
+    /// 模型解析追踪：记录智能解析（别名 / 名称清洗 / 回退链 / 模型列表）对模型的改写
+    fn trace_resolution(
+        model_trace: &mut ModelResolutionTrace,
+        provider: &Provider,
+        is_claude: bool,
+        request_model: &str,
+        from: Option<&str>,
+        resolved_body: &Value,
+    ) {
+        let to = Self::extract_model_from_body(resolved_body);
+        if let (Some(from_model), Some(to_model)) = (from, to.as_deref()) {
+            let (step, reason) =
+                classify_resolution(provider, is_claude, request_model, from_model, to_model);
+            model_trace.record_change(step, from, to.as_deref(), reason);
+        }
+    }
+
     fn tool_tag(headers: &axum::http::HeaderMap, app_type_str: &str) -> &'static str {
         let ua = headers
             .get("user-agent")
@@ -88,19 +192,100 @@ impl RequestForwarder {
         }
     }
 
+    /// 异步检查熔断器状态，满足条件时切回记录的首选供应商
+    fn spawn_restore_primary(&self, app_type_str: &str, provider_id: &str) {
+        let fm = self.failover_manager.clone();
+        let router = self.router.clone();
+        let ah = self.app_handle.clone();
+        let at = app_type_str.to_string();
+        let pid = provider_id.to_string();
+
+        tokio::spawn(async move {
+            let breaker_state = router
+                .get_circuit_breaker_stats(&pid, &at)
+                .await
+                .map(|stats| stats.state)
+                .unwrap_or(super::circuit_breaker::CircuitState::Closed);
+            if let Err(e) = fm
+                .try_restore_primary(ah.as_ref(), &at, &pid, breaker_state)
+                .await
+            {
+                log::error!("[Recovery] 切回首选供应商失败: {e}");
+            }
+        });
+    }
+
+    /// 调试追踪：记录该供应商的输出上限钳制（实际钳制在 forward 中进行）
+    fn trace_max_tokens_clamp(
+        trace: &mut RequestTrace,
+        provider: &Provider,
+        body: &Value,
+        adapter: &dyn ProviderAdapter,
+    ) {
+        if !trace.is_enabled() {
+            return;
+        }
+        if let Some((_, clamp)) = clamp_max_tokens(body, provider, adapter.name()) {
+            trace.record_clamp(&provider.id, clamp.from, clamp.to);
+        }
+    }
+
+    /// 成功日志的模型列：发生映射时显示 `请求 → 实际`
+    ///
+    /// 客户端未指定模型（缺失或 "auto"）而使用了默认模型时显示 `auto → 实际`。
+    fn upstream_model_label(request_model: Option<&str>, effective_model: Option<&str>) -> String {
+        let eff_model = effective_model
+            .map(super::model_sanitizer::sanitize_gpt_model_name)
+            .unwrap_or_else(|| "unknown".to_string());
+        let req_model = match request_model {
+            Some(m) if !is_unspecified_model(Some(m)) => {
+                super::model_sanitizer::sanitize_gpt_model_name(m)
+            }
+            _ if !is_unspecified_model(Some(&eff_model)) => "auto".to_string(),
+            _ => return eff_model,
+        };
+        if req_model.eq_ignore_ascii_case(&eff_model) {
+            eff_model
+        } else {
+            format!("{req_model} → {eff_model}")
+        }
+    }
+
+    /// Claude 经 Python 代理转发：读取其回传的真实上游耗时
+    fn python_hop_upstream_ms(app_type_str: &str, response: &Response) -> Option<u64> {
+        if app_type_str != "claude" {
+            return None;
+        }
+        super::python_proxy::upstream_elapsed_ms(response.headers())
+    }
+
     fn format_success_log_line(
+        locale: Locale,
         tool: &str,
         status_code: u16,
         channel_key: &str,
         latency_ms: u64,
+        upstream_ms: Option<u64>,
         upstream: &str,
     ) -> String {
         // 对齐目标：
         // [codex ] 正常 200 - anyrouter-key1                      ( 2.770s) [上游: gpt-5.2]
+        // [codex ] OK   200 - anyrouter-key1                      ( 2.770s) [upstream: gpt-5.2]
+        // Python 代理回传上游耗时时追加拆分：( 2.740s = 0.040s local + 2.700s upstream)
         let channel_width: usize = 35;
         let secs = (latency_ms as f64) / 1000.0;
+        let split = upstream_ms
+            .map(|ms| {
+                format!(
+                    " = {}",
+                    super::python_proxy::format_hop_split(locale, latency_ms, ms)
+                )
+            })
+            .unwrap_or_default();
+        let ok = pad_display(Msg::ForwardOk.template(locale), 4);
+        let upstream_label = Msg::ForwardUpstream.template(locale);
         format!(
-            "{tool:<8} 正常 {status_code} - {channel_key:<channel_width$} ({secs:>6.3}s) [上游: {upstream}]",
+            "{tool:<8} {ok} {status_code} - {channel_key:<channel_width$} ({secs:>6.3}s{split}) [{upstream_label}: {upstream}]",
         )
     }
 
@@ -118,22 +303,7 @@ impl RequestForwarder {
         _streaming_first_byte_timeout: u64,
         _streaming_idle_timeout: u64,
     ) -> Self {
-        // 全局超时设置为 1800 秒（30 分钟），确保业务层超时配置能正常工作
-        // 参考 Claude Code Hub 的 undici 全局超时设计
-        const GLOBAL_TIMEOUT_SECS: u64 = 1800;
-
-        let mut client_builder = Client::builder();
-        if non_streaming_timeout > 0 {
-            // 使用配置的非流式超时
-            client_builder = client_builder.timeout(Duration::from_secs(non_streaming_timeout));
-        } else {
-            // 禁用超时时使用全局超时作为保底
-            client_builder = client_builder.timeout(Duration::from_secs(GLOBAL_TIMEOUT_SECS));
-        }
-
-        let client = client_builder
-            .build()
-            .expect("Failed to create HTTP client");
+        let client = shared_client(non_streaming_timeout);
 
         Self {
             client,
@@ -145,6 +315,174 @@ impl RequestForwarder {
             failover_manager,
             app_handle,
             current_provider_id_at_start,
+            default_model: None,
+            shadow: None,
+        }
+    }
+
+    /// 设置应用级默认模型（`AppProxyConfig.default_model`）
+    pub fn with_default_model(mut self, default_model: Option<String>) -> Self {
+        self.default_model = default_model;
+        self
+    }
+
+    /// 设置影子流量（`AppProxyConfig.shadow`）
+    pub fn with_shadow(mut self, shadow: Option<ShadowConfig>) -> Self {
+        self.shadow = shadow.filter(|s| s.sample_percent > 0 && !s.provider_id.is_empty());
+        self
+    }
+
+    /// 按采样比例把非流式请求镜像到影子供应商
+    ///
+    /// 在后台任务中执行，结果只写入请求日志（`shadow = 1`）：不等待、不影响本次响应，
+    /// 也不记录熔断器或触发故障转移。影子请求已达上限或处于只读模式时本次跳过。
+    fn spawn_shadow(
+        &self,
+        app_type: &AppType,
+        endpoint: &str,
+        body: &Value,
+        headers: &axum::http::HeaderMap,
+    ) {
+        let Some(config) = self.shadow.as_ref() else {
+            return;
+        };
+        if self.router.is_read_only() {
+            return;
+        }
+        if shadow::is_streaming_request(body, endpoint)
+            || !shadow::sampler_for(app_type).should_mirror(config.sample_percent)
+        {
+            return;
+        }
+        let Some(slot) = shadow::InFlightSlot::try_acquire() else {
+            log::debug!(
+                "[Shadow] 进行中的影子请求已达上限 {}，跳过本次镜像",
+                shadow::MAX_SHADOW_IN_FLIGHT
+            );
+            return;
+        };
+
+        let forwarder = self.clone();
+        let app_type = app_type.clone();
+        let provider_id = config.provider_id.clone();
+        let endpoint = endpoint.to_string();
+        let body = body.clone();
+        let headers = headers.clone();
+        tokio::spawn(async move {
+            let _slot = slot;
+            forwarder
+                .mirror_to_shadow(&app_type, &provider_id, &endpoint, &body, &headers)
+                .await;
+        });
+    }
+
+    /// 向影子供应商发送一次请求（不重试）并记录结果
+    async fn mirror_to_shadow(
+        &self,
+        app_type: &AppType,
+        provider_id: &str,
+        endpoint: &str,
+        body: &Value,
+        headers: &axum::http::HeaderMap,
+    ) {
+        let app_type_str = app_type.as_str();
+        let provider = match self.db.get_provider_by_id(provider_id, app_type_str) {
+            Ok(Some(provider)) => provider,
+            Ok(None) => {
+                log::warn!("[Shadow] 影子供应商 {provider_id} 不存在（{app_type_str}），跳过镜像");
+                return;
+            }
+            Err(e) => {
+                log::warn!("[Shadow] 读取影子供应商 {provider_id} 失败: {e}");
+                return;
+            }
+        };
+
+        let adapter = get_adapter(app_type);
+        let started = Instant::now();
+        let result = self
+            .forward(&provider, endpoint, body, headers, adapter.as_ref())
+            .await;
+        let (status_code, usage, error_message, effective_model) = match result {
+            Ok(forwarded) => {
+                let status = forwarded.response.status().as_u16();
+                match forwarded.response.bytes().await {
+                    Ok(bytes) => (
+                        status,
+                        serde_json::from_slice::<Value>(&bytes)
+                            .ok()
+                            .and_then(|v| TokenUsage::from_app_response(app_type, &v)),
+                        None,
+                        forwarded.effective_model,
+                    ),
+                    Err(e) => (
+                        502,
+                        None,
+                        Some(format!("读取影子响应失败: {e}")),
+                        forwarded.effective_model,
+                    ),
+                }
+            }
+            Err(e) => {
+                let message = e.to_string();
+                let status = axum::response::IntoResponse::into_response(e)
+                    .status()
+                    .as_u16();
+                (status, None, Some(message), None)
+            }
+        };
+        let latency_ms = started.elapsed().as_millis() as u64;
+        log::debug!(
+            "[Shadow] {app_type_str} → {} 状态 {status_code}，耗时 {latency_ms}ms",
+            provider.id
+        );
+
+        let outcome = shadow::ShadowOutcome {
+            status_code,
+            latency_ms,
+            usage,
+            error_message,
+            requested_model: Self::extract_model_from_body(body),
+            effective_model,
+        };
+        if let Err(e) = shadow::record_outcome(&self.db, app_type, &provider, outcome) {
+            log::warn!("[Shadow] 记录影子请求失败: {e}");
+        }
+    }
+
+    /// thinking 请求被上游以“不支持 thinking”拒绝：未显式配置能力的供应商写回
+    /// `supportsThinking: false`，之后的 thinking 请求选路时将其排到链尾
+    async fn learn_thinking_unsupported(
+        &self,
+        app_type_str: &str,
+        provider: &Provider,
+        body: &Value,
+        err: &ProxyError,
+    ) {
+        if !has_thinking_enabled(body)
+            || configured_support(provider).is_some()
+            || !is_thinking_unsupported_error(err)
+        {
+            return;
+        }
+        match self
+            .router
+            .writeback_provider_setting(
+                app_type_str,
+                &provider.id,
+                SUPPORTS_THINKING_KEY,
+                Value::Bool(false),
+            )
+            .await
+        {
+            Ok(()) => log::warn!(
+                "[{app_type_str}] 供应商 {} 不支持 thinking，已写回 {SUPPORTS_THINKING_KEY}=false",
+                provider.name
+            ),
+            Err(e) => log::warn!(
+                "[{app_type_str}] 写回 {SUPPORTS_THINKING_KEY} 失败 provider={}: {e}",
+                provider.id
+            ),
         }
     }
 
@@ -211,6 +549,7 @@ impl RequestForwarder {
     /// * `body` - 请求体
     /// * `headers` - 请求头
     /// * `providers` - 已选择的 Provider 列表（由 RequestContext 提供，避免重复调用 select_providers）
+    /// * `trace` - 单请求尝试追踪（仅在请求开启调试时实际记录）
     pub async fn forward_with_retry(
         &self,
         app_type: &AppType,
@@ -218,12 +557,16 @@ impl RequestForwarder {
         body: Value,
         headers: axum::http::HeaderMap,
         providers: Vec<Provider>,
+        trace: &mut RequestTrace,
     ) -> Result<ForwardResult, ForwardError> {
         // 获取适配器
         let adapter = get_adapter(app_type);
         let app_type_str = app_type.as_str();
         let request_model = Self::extract_model_from_body(&body);
 
+        // 影子流量：与主请求并行发出，结果只写入请求日志
+        self.spawn_shadow(app_type, endpoint, &body, &headers);
+
         fn header_value(headers: &axum::http::HeaderMap, name: &str) -> Option<String> {
             headers
                 .get(name)
@@ -390,11 +733,22 @@ impl RequestForwarder {
         );
 
         // startup 测试覆盖：一旦处于覆盖期，就应“首错即停”，避免同一请求在多 key/多轮中反复尝试刷屏
-        let is_startup_test = self.router.has_active_test_override(app_type_str).await;
+        // 按请求固定供应商的重放请求同样不重试、不影响熔断与请求指纹
+        let is_startup_test = RequestScope::pinned_provider_id(app_type_str).is_some()
+            || self.router.has_active_test_override(app_type_str).await;
+
+        // 冷却期供应商在 select_providers 阶段已被过滤，这里补记到追踪中
+        if trace.is_enabled() {
+            for (supplier, reason) in self.router.suppliers_in_cooldown(app_type_str).await {
+                trace.record_skip(&supplier, SkipReason::Cooldown(reason));
+            }
+        }
 
         let mut last_error = None;
         let mut last_provider = None;
         let mut attempted_providers = 0usize;
+        // 逐次尝试记录：全链路失败时汇总返回给客户端
+        let mut failure_report = FailureReport::new(app_type_str);
 
         // 单 Provider 场景下跳过熔断器检查（故障转移关闭时）
         let bypass_circuit_breaker = providers.len() == 1;
@@ -425,8 +779,8 @@ impl RequestForwarder {
                     .insert(app_type_str.to_string(), last_summary.clone());
             }
 
-            // startup 测速请求不应污染“真实请求指纹”（否则会在重启后导致测速自我污染）
-            if !is_startup_test {
+            // startup 测速请求不应污染“真实请求指纹”（否则会在重启后导致测速自我污染）；只读模式不持久化
+            if !is_startup_test && !self.router.is_read_only() {
                 persist_last_request_summary(
                     self.db.clone(),
                     last_request_summary_setting_key(app_type_str),
@@ -434,6 +788,7 @@ impl RequestForwarder {
                 );
             }
 
+            Self::trace_max_tokens_clamp(trace, provider, &body, adapter.as_ref());
             let start = Instant::now();
 
             let resp = if is_startup_test {
@@ -448,9 +803,13 @@ impl RequestForwarder {
                 Ok(forwarded) => {
                     let latency = start.elapsed().as_millis() as u64;
                     let response = forwarded.response;
+                    trace.record_attempt(&provider.id, Some(response.status().as_u16()), latency);
+                    trace.set_winner(&provider.id);
+                    trace.set_model_trace(&forwarded.model_trace);
                     let effective_model = forwarded.effective_model.map(|m| {
                         super::model_sanitizer::sanitize_gpt_model_name(&m)
                     });
+                    let upstream_ms = Self::python_hop_upstream_ms(app_type_str, &response);
 
                     // 成功：记录成功并更新熔断器（startup 测试不应污染熔断器状态）
                     if !is_startup_test {
@@ -462,6 +821,7 @@ impl RequestForwarder {
                                 false,
                                 true,
                                 None,
+                                None,
                             )
                             .await
                         {
@@ -487,7 +847,13 @@ impl RequestForwarder {
                             self.current_provider_id_at_start.as_str() != provider.id.as_str();
                         if should_switch {
                             status.failover_count += 1;
-
+                        }
+                        // 防抖：交替胜出时不反复持久化"当前供应商"
+                        if self.failover_manager.should_persist_switch(
+                            app_type_str,
+                            &provider.id,
+                            &self.current_provider_id_at_start,
+                        ) {
                             // 异步触发供应商切换，更新 UI/托盘，并把"当前供应商"同步为实际使用的 provider
                             let fm = self.failover_manager.clone();
                             let ah = self.app_handle.clone();
@@ -498,9 +864,15 @@ impl RequestForwarder {
                             tokio::spawn(async move {
                                 if let Err(e) = fm.try_switch(ah.as_ref(), &at, &pid, &pname).await
                                 {
-                                    log::error!("[Failover] 切换供应商失败: {e}");
+                                    log::error!("{}", tr(Msg::FailoverSwitchFailed, &[&e]));
                                 }
                             });
+                        } else if self
+                            .failover_manager
+                            .is_restore_candidate(app_type_str, &provider.id)
+                        {
+                            // 首选供应商真实请求成功：熔断器闭合时切回
+                            self.spawn_restore_primary(app_type_str, &provider.id);
                         }
                         // 重新计算成功率
                         if status.total_requests > 0 {
@@ -512,28 +884,18 @@ impl RequestForwarder {
 
                     // 统一日志：一次请求仅记录一条（包含映射关系）
                     let tool = Self::tool_tag(&headers, app_type_str);
-                    let req_model = request_model
-                        .as_deref()
-                        .map(super::model_sanitizer::sanitize_gpt_model_name)
-                        .unwrap_or_else(|| "unknown".to_string());
-                    let eff_model = effective_model
-                        .as_deref()
-                        .map(super::model_sanitizer::sanitize_gpt_model_name)
-                        .unwrap_or_else(|| "unknown".to_string());
-                    let upstream = if req_model.trim().is_empty()
-                        || req_model == "unknown"
-                        || req_model.eq_ignore_ascii_case(&eff_model)
-                    {
-                        eff_model
-                    } else {
-                        format!("{req_model} → {eff_model}")
-                    };
+                    let upstream = Self::upstream_model_label(
+                        request_model.as_deref(),
+                        effective_model.as_deref(),
+                    );
 
                     let line = Self::format_success_log_line(
+                        Locale::current(),
                         tool,
                         response.status().as_u16(),
                         provider.name.as_str(),
                         latency,
+                        upstream_ms,
                         upstream.as_str(),
                     );
                     log::info!("{line}");
@@ -554,11 +916,16 @@ impl RequestForwarder {
                     return Ok(ForwardResult {
                         response,
                         provider: provider.clone(),
+                        effective_model,
+                        upstream_ms,
                     });
                 }
                 Err(e) => {
                     let latency = start.elapsed().as_millis() as u64;
                     let e_text = e.to_string();
+                    trace.record_error(&provider.id, &e, latency);
+                    self.learn_thinking_unsupported(app_type_str, provider, &body, &e)
+                        .await;
 
                     // 失败：记录失败并更新熔断器（startup 测试不应污染熔断器状态）
                     if !is_startup_test {
@@ -569,7 +936,8 @@ impl RequestForwarder {
                                 app_type_str,
                                 false,
                                 false,
-                                Some(e_text.clone()),
+                                Some(depleted_summary(&e).unwrap_or_else(|| e_text.clone())),
+                                Some(FailureKind::from_proxy_error(&e)),
                             )
                             .await
                         {
@@ -655,10 +1023,11 @@ impl RequestForwarder {
                                     .await;
                             }
                             log::error!(
-                                "[{}] Provider {} 失败（不可重试）: {}",
-                                app_type_str,
-                                provider.name,
-                                e
+                                "{}",
+                                tr(
+                                    Msg::ForwardNonRetryable,
+                                    &[&app_type_str, &provider.name, &e]
+                                )
                             );
                             return Err(ForwardError {
                                 error: e,
@@ -675,7 +1044,7 @@ impl RequestForwarder {
             let mut by_priority: std::collections::BTreeMap<usize, Vec<Provider>> =
                 std::collections::BTreeMap::new();
             for p in providers.into_iter() {
-                let priority = p.sort_index.unwrap_or(999999);
+                let priority = p.priority();
                 by_priority.entry(priority).or_default().push(p);
             }
 
@@ -702,6 +1071,7 @@ impl RequestForwarder {
                             AllowResult {
                                 allowed: true,
                                 used_half_open_permit: false,
+                                rejection: None,
                             }
                         } else {
                             self.router
@@ -711,6 +1081,12 @@ impl RequestForwarder {
 
                         if !permit.allowed {
                             skipped_by_circuit += 1;
+                            trace.record_skip(
+                                &provider.id,
+                                SkipReason::Breaker(
+                                    permit.rejection.unwrap_or(BreakerRejection::Open),
+                                ),
+                            );
                             continue;
                         }
 
@@ -746,7 +1122,7 @@ impl RequestForwarder {
                                 .insert(app_type_str.to_string(), last_summary.clone());
                         }
 
-                        if !is_startup_test {
+                        if !is_startup_test && !self.router.is_read_only() {
                             persist_last_request_summary(
                                 self.db.clone(),
                                 last_request_summary_setting_key(app_type_str),
@@ -754,6 +1130,7 @@ impl RequestForwarder {
                             );
                         }
 
+                        Self::trace_max_tokens_clamp(trace, provider, &body, adapter.as_ref());
                         let start = Instant::now();
 
                         // 多 Provider：错误即切换，不做“同 Provider 内重试”
@@ -764,9 +1141,18 @@ impl RequestForwarder {
                             Ok(forwarded) => {
                                 let latency = start.elapsed().as_millis() as u64;
                                 let response = forwarded.response;
+                                trace.record_attempt(
+                                    &provider.id,
+                                    Some(response.status().as_u16()),
+                                    latency,
+                                );
+                                trace.set_winner(&provider.id);
+                                trace.set_model_trace(&forwarded.model_trace);
                                 let effective_model = forwarded.effective_model.map(|m| {
                                     super::model_sanitizer::sanitize_gpt_model_name(&m)
                                 });
+                                let upstream_ms =
+                                    Self::python_hop_upstream_ms(app_type_str, &response);
 
                                 // startup 测试不应污染熔断器状态
                                 if !is_startup_test {
@@ -778,6 +1164,7 @@ impl RequestForwarder {
                                             permit.used_half_open_permit,
                                             true,
                                             None,
+                                            None,
                                         )
                                         .await
                                     {
@@ -803,7 +1190,12 @@ impl RequestForwarder {
                                         != provider.id.as_str();
                                     if should_switch {
                                         status.failover_count += 1;
-
+                                    }
+                                    if self.failover_manager.should_persist_switch(
+                                        app_type_str,
+                                        &provider.id,
+                                        &self.current_provider_id_at_start,
+                                    ) {
                                         let fm = self.failover_manager.clone();
                                         let ah = self.app_handle.clone();
                                         let pid = provider.id.clone();
@@ -814,9 +1206,17 @@ impl RequestForwarder {
                                             if let Err(e) =
                                                 fm.try_switch(ah.as_ref(), &at, &pid, &pname).await
                                             {
-                                                log::error!("[Failover] 切换供应商失败: {e}");
+                                                log::error!(
+                                                    "{}",
+                                                    tr(Msg::FailoverSwitchFailed, &[&e])
+                                                );
                                             }
                                         });
+                                    } else if self
+                                        .failover_manager
+                                        .is_restore_candidate(app_type_str, &provider.id)
+                                    {
+                                        self.spawn_restore_primary(app_type_str, &provider.id);
                                     }
                                     if status.total_requests > 0 {
                                         status.success_rate = (status.success_requests as f32
@@ -827,28 +1227,18 @@ impl RequestForwarder {
 
                                 // 统一日志：一次请求仅记录一条（包含映射关系）
                                 let tool = Self::tool_tag(&headers, app_type_str);
-                                let req_model = request_model
-                                    .as_deref()
-                                    .map(super::model_sanitizer::sanitize_gpt_model_name)
-                                    .unwrap_or_else(|| "unknown".to_string());
-                                let eff_model = effective_model
-                                    .as_deref()
-                                    .map(super::model_sanitizer::sanitize_gpt_model_name)
-                                    .unwrap_or_else(|| "unknown".to_string());
-                                let upstream = if req_model.trim().is_empty()
-                                    || req_model == "unknown"
-                                    || req_model.eq_ignore_ascii_case(&eff_model)
-                                {
-                                    eff_model
-                                } else {
-                                    format!("{req_model} → {eff_model}")
-                                };
+                                let upstream = Self::upstream_model_label(
+                                    request_model.as_deref(),
+                                    effective_model.as_deref(),
+                                );
 
                                 let line = Self::format_success_log_line(
+                                    Locale::current(),
                                     tool,
                                     response.status().as_u16(),
                                     provider.name.as_str(),
                                     latency,
+                                    upstream_ms,
                                     upstream.as_str(),
                                 );
                                 log::info!("{line}");
@@ -869,10 +1259,15 @@ impl RequestForwarder {
                                 return Ok(ForwardResult {
                                     response,
                                     provider: provider.clone(),
+                                    effective_model,
+                                    upstream_ms,
                                 });
                             },
                             Err(e) => {
                                 let latency = start.elapsed().as_millis() as u64;
+                                trace.record_error(&provider.id, &e, latency);
+                                self.learn_thinking_unsupported(app_type_str, provider, &body, &e)
+                                    .await;
 
                                 // startup 测试不应污染熔断器状态
                                 if !is_startup_test {
@@ -883,7 +1278,11 @@ impl RequestForwarder {
                                             app_type_str,
                                             permit.used_half_open_permit,
                                             false,
-                                            Some(e.to_string()),
+                                            Some(
+                                                depleted_summary(&e)
+                                                    .unwrap_or_else(|| e.to_string()),
+                                            ),
+                                            Some(FailureKind::from_proxy_error(&e)),
                                         )
                                         .await
                                     {
@@ -929,6 +1328,12 @@ impl RequestForwarder {
                                             latency
                                         );
 
+                                        failure_report.push(
+                                            provider,
+                                            ProviderRouter::extract_base_url(provider, app_type_str),
+                                            &e,
+                                            latency,
+                                        );
                                         last_error = Some(e);
                                         last_provider = Some(provider.clone());
                                         continue;
@@ -957,10 +1362,11 @@ impl RequestForwarder {
                                             )
                                             .await;
                                         log::error!(
-                                            "[{}] Provider {} 失败（不可重试）: {}",
-                                            app_type_str,
-                                            provider.name,
-                                            e
+                                            "{}",
+                                            tr(
+                                                Msg::ForwardNonRetryable,
+                                                &[&app_type_str, &provider.name, &e],
+                                            )
                                         );
                                         return Err(ForwardError {
                                             error: e,
@@ -981,16 +1387,16 @@ impl RequestForwarder {
 
                 if attempts_executed == 0 {
                     log::debug!(
-                        "[{}] 层级 {} 无可用供应商（可能被熔断器限制），尝试下一层级",
-                        app_type_str,
-                        priority
+                        "{}",
+                        tr(Msg::ForwardTierNoProvider, &[&app_type_str, &priority])
                     );
                 } else {
                     log::warn!(
-                        "[{}] 层级 {} 已用尽尝试轮次（{} 轮），切换到下一层级",
-                        app_type_str,
-                        priority,
-                        rounds_per_priority
+                        "{}",
+                        tr(
+                            Msg::ForwardTierExhausted,
+                            &[&app_type_str, &priority, &rounds_per_priority],
+                        )
                     );
                 }
             }
@@ -1013,30 +1419,62 @@ impl RequestForwarder {
             });
         }
 
-        // 所有供应商都失败了
+        // 所有供应商都失败了：优先返回逐次尝试汇总，而不是“最后一个错误”
+        let error = if failure_report.is_empty() {
+            last_error.unwrap_or(ProxyError::MaxRetriesExceeded)
+        } else {
+            ProxyError::AllProvidersFailed(Box::new(failure_report))
+        };
+
         {
             let mut status = self.status.write().await;
             status.failed_requests += 1;
-            status.last_error = Some("所有供应商都失败".to_string());
+            status.last_error = Some(match &error {
+                ProxyError::AllProvidersFailed(report) => report.summary(),
+                _ => "所有供应商都失败".to_string(),
+            });
             if status.total_requests > 0 {
                 status.success_rate =
                     (status.success_requests as f32 / status.total_requests as f32) * 100.0;
             }
         }
 
-        log::error!(
-            "[{}] 所有 {} 个供应商都失败了",
+        let summary = match &error {
+            ProxyError::AllProvidersFailed(report) => report.summary(),
+            other => other.to_string(),
+        };
+        self.router.event_recorder().record(
             app_type_str,
-            total_provider_count
+            crate::database::EventKind::AllFailed,
+            None,
+            None,
+            serde_json::json!({ "attempted": attempted_providers, "error": summary }),
+        );
+        super::notifications::notify(
+            super::notifications::NotificationPayload::new(
+                super::notifications::NotificationEvent::AllProvidersFailed,
+                app_type_str,
+            )
+            .with_error(summary),
+        );
+
+        log::error!(
+            "{}",
+            tr(
+                Msg::ForwardAllFailed,
+                &[&app_type_str, &total_provider_count]
+            )
         );
 
         Err(ForwardError {
-            error: last_error.unwrap_or(ProxyError::MaxRetriesExceeded),
+            error,
             provider: last_provider,
         })
     }
 
     /// 转发单个请求（使用适配器）
+    ///
+    /// 成功的 SSE 响应按供应商 `sseFilter` 配置过滤注入的自定义事件。
     async fn forward(
         &self,
         provider: &Provider,
@@ -1045,10 +1483,98 @@ impl RequestForwarder {
         headers: &axum::http::HeaderMap,
         adapter: &dyn ProviderAdapter,
     ) -> Result<ForwardedResponse, ProxyError> {
-        // 提取 API Key
-        let auth = adapter.extract_auth(provider).ok_or_else(|| {
-            ProxyError::AuthError(format!("Provider {} 缺少认证信息", provider.id))
+        let forwarded = self
+            .forward_upstream(provider, endpoint, body, headers, adapter)
+            .await?;
+        if !forwarded.model_trace.is_empty() {
+            log::debug!(
+                "[ModelResolver] provider={} 模型解析 {}",
+                provider.id,
+                forwarded.model_trace.render()
+            );
+        }
+        Ok(ForwardedResponse {
+            response: filter_sse_response(provider, forwarded.response)?,
+            effective_model: forwarded.effective_model,
+            model_trace: forwarded.model_trace,
+        })
+    }
+
+    async fn forward_upstream(
+        &self,
+        provider: &Provider,
+        endpoint: &str,
+        body: &Value,
+        headers: &axum::http::HeaderMap,
+        adapter: &dyn ProviderAdapter,
+    ) -> Result<ForwardedResponse, ProxyError> {
+        // `${ENV_VAR}` 占位符：只展开到本次请求使用的副本（适配器、解析器均读取该副本），不写回数据库
+        let expanded = expand_provider(provider).map_err(|e| {
+            ProxyError::AuthError(format!("Provider {} 配置无法解析: {e}", provider.id))
         })?;
+        let provider: &Provider = &expanded;
+
+        // 客户端未指定模型（缺失或 "auto"）：供应商的 ANTHROPIC_MODEL 优先，其次为应用级默认模型
+        // Gemini 的模型在 URI 中，不处理
+        let defaulted = if adapter.name() == "Gemini" {
+            None
+        } else {
+            apply_default_model(body, provider, self.default_model.as_deref())
+        };
+        let mut model_trace = ModelResolutionTrace::default();
+        if let Some(defaulted) = &defaulted {
+            model_trace.record(
+                STEP_DEFAULT,
+                Self::extract_model_from_body(body).as_deref(),
+                Self::extract_model_from_body(defaulted).as_deref(),
+                "unspecified",
+            );
+        }
+        let body = defaulted.as_ref().unwrap_or(body);
+
+        // 供应商输出上限（maxTokensCap）：超出时压到上限，只降不升
+        let clamped = clamp_max_tokens(body, provider, adapter.name());
+        if let Some((_, clamp)) = &clamped {
+            log::debug!(
+                "[{}] {} 超出供应商上限，{} → {}",
+                provider.id,
+                clamp.field,
+                clamp.from,
+                clamp.to
+            );
+        }
+        let body = clamped.as_ref().map(|(b, _)| b).unwrap_or(body);
+
+        // 供应商 system prompt 注入（systemPrompt.prepend/append）：幂等，同供应商重试不会叠加
+        let injected = apply_system_prompt(body, provider, adapter.name());
+        let body = injected.as_ref().unwrap_or(body);
+
+        // AWS Bedrock：不经过 Python 代理，直接签名请求 Bedrock
+        if adapter.name() == "Claude" {
+            if let Some(bedrock) = BedrockConfig::from_provider(provider) {
+                return self
+                    .forward_bedrock(provider, endpoint, body, &bedrock, model_trace)
+                    .await;
+            }
+        }
+
+        // Google Vertex AI：使用服务账号签发的 access token（Bearer）
+        let vertex = if adapter.name() == "Gemini" {
+            VertexConfig::from_provider(provider)
+        } else {
+            None
+        };
+
+        // 提取 API Key
+        let auth = match &vertex {
+            Some(vertex) => {
+                let token = vertex.access_token(&self.client).await?;
+                AuthInfo::with_access_token(String::new(), token)
+            }
+            None => adapter.extract_auth(provider).ok_or_else(|| {
+                ProxyError::AuthError(format!("Provider {} 缺少认证信息", provider.id))
+            })?,
+        };
 
         let is_claude = adapter.name() == "Claude";
         let app_type_str: &'static str = if is_claude {
@@ -1078,34 +1604,24 @@ impl RequestForwarder {
             )
         } else {
             // 其它（Codex/Gemini 等）直接转发到目标 URL
-            let base_url = adapter.extract_base_url(provider)?;
-            let full_url = adapter.build_url(&base_url, endpoint);
+            let (base_url, full_url) = match &vertex {
+                Some(vertex) => (vertex.base_url(), vertex.request_url(endpoint)?),
+                None => {
+                    let base_url = adapter.extract_base_url(provider)?;
+                    let full_url = adapter.build_url(&base_url, endpoint);
+                    (base_url, full_url)
+                }
+            };
             (full_url, base_url.clone(), Some(base_url))
         };
 
-        // 只透传必要的 Headers（白名单模式）
-        let allowed_headers = [
-            "accept",
-            "user-agent",
-            "x-request-id",
-            "openai-beta",
-            "openai-version",
-            "openai-organization",
-            "openai-project",
-            "x-stainless-arch",
-            "x-stainless-lang",
-            "x-stainless-os",
-            "x-stainless-package-version",
-            "x-stainless-runtime",
-            "x-stainless-runtime-version",
-        ];
         let claude_target_base_url = if is_claude {
             provider
                 .settings_config
                 .get("env")
                 .and_then(|env| env.get("ANTHROPIC_BASE_URL"))
                 .and_then(|v| v.as_str())
-                .map(|s| s.to_string())
+                .map(ProviderRouter::normalize_base_url)
                 .ok_or_else(|| {
                     ProxyError::ConfigError(format!(
                         "Provider {} 缺少ANTHROPIC_BASE_URL配置",
@@ -1115,13 +1631,30 @@ impl RequestForwarder {
         } else {
             String::new()
         };
+        // Claude 经 Python 代理时的认证头（显式配置 settings_config.auth 时直接发送目标认证头）
+        let (claude_auth_header, claude_auth_value) =
+            python_proxy_auth_header(auth.scheme.as_ref(), &auth.api_key);
+        // Azure OpenAI：URL 由请求体中（解析后）的模型对应的部署决定
+        let azure = if app_type_str == "codex" {
+            AzureConfig::from_provider(provider)
+        } else {
+            None
+        };
 
-        let build_request = |json_body: &Value| {
-            let mut request = self.client.post(&url);
+        let build_request = |json_body: &Value| -> Result<reqwest::RequestBuilder, ProxyError> {
+            let target_url = match (&azure, &upstream_base_url) {
+                (Some(azure), Some(base_url)) => azure.request_url(
+                    base_url,
+                    endpoint,
+                    json_body.get("model").and_then(|m| m.as_str()),
+                )?,
+                _ => url.clone(),
+            };
+            let mut request = self.client.post(&target_url);
 
             for (key, value) in headers {
                 let key_str = key.as_str().to_lowercase();
-                if allowed_headers.contains(&key_str.as_str()) {
+                if FORWARDED_HEADERS.contains(&key_str.as_str()) {
                     request = request.header(key, value);
                 }
             }
@@ -1131,18 +1664,20 @@ impl RequestForwarder {
 
             // 根据转发目标添加认证/路由头部
             if is_claude {
-                // Claude 通过 Python 代理：需要 X-API-Key + x-target-base-url
-                request = request.header("X-API-Key", &auth.api_key);
+                // Claude 通过 Python 代理：认证头（默认 X-API-Key）+ x-target-base-url
+                request = request.header(&claude_auth_header, &claude_auth_value);
                 request = request.header("x-target-base-url", &claude_target_base_url);
             } else {
                 // 其它：使用 adapter 的认证策略
                 request = adapter.add_auth_headers(request, &auth);
             }
 
-            request.json(json_body)
+            Ok(request.json(json_body))
         };
 
         // 构造最终请求体（Claude/Codex：支持映射/智能解析；其它：原样透传）
+        // 模型锁定（modelLock）的供应商跳过映射、智能解析与名称清洗，原样转发客户端模型
+        let model_locked = provider.model_locked();
         let original_request_model = if is_claude && endpoint == "/v1/messages" {
             Self::extract_model_from_body(body).unwrap_or_default()
         } else if app_type_str == "codex"
@@ -1153,21 +1688,43 @@ impl RequestForwarder {
             String::new()
         };
 
-        let (final_body, mut pending_writeback) = if is_claude && endpoint == "/v1/messages" {
+        let (final_body, pending_writeback) = if model_locked {
+            if !original_request_model.is_empty() {
+                let model = Some(original_request_model.as_str());
+                model_trace.record(STEP_LOCKED, model, model, "modelLock");
+            }
+            (body.clone(), None)
+        } else if is_claude && endpoint == "/v1/messages" {
 
             // 1) 先应用显式映射（ANTHROPIC_DEFAULT_* / ANTHROPIC_MODEL / ANTHROPIC_REASONING_MODEL）
             let (mapped_body, _, _) = super::model_mapper::apply_model_mapping(body.clone(), provider);
+            let mapped_model = Self::extract_model_from_body(&mapped_body);
+            model_trace.record_change(
+                STEP_MAPPING,
+                Self::extract_model_from_body(body).as_deref(),
+                mapped_model.as_deref(),
+                "env",
+            );
 
             // 2) 默认开启智能解析：若 mapped model 不在 /v1/models 内，则自动选取最匹配的上游模型
             if !original_request_model.is_empty() {
-                super::model_resolver::resolve_claude_model_in_body(
+                let resolved = super::model_resolver::resolve_claude_model_in_body(
                     &self.client,
                     provider,
                     &auth.api_key,
                     &original_request_model,
                     mapped_body,
                 )
-                .await
+                .await;
+                Self::trace_resolution(
+                    &mut model_trace,
+                    provider,
+                    true,
+                    &original_request_model,
+                    mapped_model.as_deref(),
+                    &resolved.0,
+                );
+                resolved
             } else {
                 (mapped_body, None)
             }
@@ -1177,28 +1734,52 @@ impl RequestForwarder {
         {
             // Codex/OpenAI：默认开启智能解析：当供应商仅开放部分模型或别名不一致时，
             // 通过 /v1/models 选取最接近的真实可用模型，并在首次成功后写回到 provider env（避免重复匹配）。
-            super::openai_model_resolver::resolve_openai_model_in_body(
+            let resolved = super::openai_model_resolver::resolve_openai_model_in_body(
                 &self.client,
                 provider,
                 &auth.api_key,
                 &original_request_model,
                 body.clone(),
             )
-            .await
+            .await;
+            Self::trace_resolution(
+                &mut model_trace,
+                provider,
+                false,
+                &original_request_model,
+                Self::extract_model_from_body(body).as_deref(),
+                &resolved.0,
+            );
+            resolved
         } else {
             (body.clone(), None)
         };
 
-        let mut effective_model = Self::extract_model_from_body(&final_body);
+        // 供应商请求体变换（bodyTransforms）：在映射/解析之后、发送之前
+        let resolved_model = Self::extract_model_from_body(&final_body);
+        let final_body = apply_body_transforms(provider, final_body);
+
+        let effective_model = Self::extract_model_from_body(&final_body);
+        model_trace.record_change(
+            STEP_TRANSFORM,
+            resolved_model.as_deref(),
+            effective_model.as_deref(),
+            "bodyTransforms",
+        );
 
         // 发送请求
-        let response = build_request(&final_body).send().await.map_err(|e| {
+        let response = build_request(&final_body)?.send().await.map_err(|e| {
             log::error!(
-                "错误 - {} - target={} base_url={} - 详情: 请求失败 {}",
-                provider.name,
-                target_description,
-                upstream_base_url.as_deref().unwrap_or("-"),
-                e
+                "{}",
+                tr(
+                    Msg::ForwardRequestFailed,
+                    &[
+                        &provider.name,
+                        &target_description,
+                        &upstream_base_url.as_deref().unwrap_or("-"),
+                        &e,
+                    ],
+                )
             );
             if e.is_timeout() {
                 ProxyError::Timeout(format!("请求超时: {e}"))
@@ -1216,96 +1797,140 @@ impl RequestForwarder {
             // Claude/Codex：请求成功后写回映射（避免后续重复匹配）
             if let Some(wb) = pending_writeback {
                 if app_type_str == "claude" || app_type_str == "codex" {
-                    let router = self.router.clone();
-                    let provider_id = provider.id.clone();
-                    let env_key = wb.env_key;
-                    let env_value = wb.value.clone();
-                    let app_type = app_type_str.to_string();
-                    tokio::spawn(async move {
-                        if let Err(e) = router
-                            .writeback_provider_env(&app_type, &provider_id, env_key, &env_value)
-                            .await
-                        {
-                            log::warn!(
-                                "[ModelResolver] 写回失败 app={} provider={} key={} err={}",
-                                app_type,
-                                provider_id,
-                                env_key,
-                                e
-                            );
-                        } else {
-                            log::debug!(
-                                "[ModelResolver] 已写回 app={} provider={} {}={}",
-                                app_type,
-                                provider_id,
-                                env_key,
-                                env_value
-                            );
-                        }
-                    });
+                    self.spawn_model_writeback(app_type_str, &provider.id, wb);
                 }
             }
             Ok(ForwardedResponse {
                 response,
                 effective_model,
+                model_trace,
             })
         } else {
             let status_code = status.as_u16();
-            let body_text = response.text().await.ok();
+            let body_text = read_error_body(response).await;
+            // Vertex AI：令牌被拒绝时丢弃缓存，下次请求重新签发
+            if let (Some(vertex), 401) = (&vertex, status_code) {
+                vertex.invalidate_token().await;
+            }
             log::error!(
-                "错误 {} - {} - base_url={} - 详情: {:?}",
-                status_code,
-                provider.name,
-                upstream_base_url.as_deref().unwrap_or("-"),
-                body_text
+                "{}",
+                tr(
+                    Msg::ForwardUpstreamError,
+                    &[
+                        &status_code,
+                        &provider.name,
+                        &upstream_base_url.as_deref().unwrap_or("-"),
+                        &format!("{:?}", body_text),
+                    ],
+                )
             );
 
-            // Claude：若上游明确提示“模型不存在/无可用渠道”，则在同一 provider 上做一次“次优模型”重试，
+            // 上游明确提示“模型不存在/无可用渠道”：在同一 provider 上换模型重试（最多 MAX_MODEL_FALLBACK_RETRIES 次），
+            // 先按供应商回退链（modelFallbacks）顺序，耗尽后再由智能解析选取一次次优模型；
             // 用于处理“/v1/models 列表可用，但当前分组无 distributor / 别名不通用”等情况。
-            if is_claude
-                && endpoint == "/v1/messages"
+            let model_aware = (is_claude && endpoint == "/v1/messages")
+                || (app_type_str == "codex"
+                    && (endpoint == "/v1/responses" || endpoint == "/v1/chat/completions"));
+            let model_unavailable = body_text
+                .as_deref()
+                .is_some_and(|t| Self::is_model_unavailable_error(status_code, t));
+            if model_aware
+                && !model_locked
                 && !original_request_model.is_empty()
-                && body_text
-                    .as_deref()
-                    .map(|t| Self::is_model_unavailable_error(status_code, t))
-                    == Some(true)
+                && model_unavailable
             {
-                if let Some(current_model) = effective_model.clone() {
-                    let avoid = [current_model.as_str()];
-                    let (retry_body, retry_writeback) =
-                        super::model_resolver::resolve_claude_model_in_body_with_avoid(
-                            &self.client,
-                            provider,
-                            &auth.api_key,
-                            &original_request_model,
-                            final_body.clone(),
-                            &avoid,
-                        )
-                        .await;
-
-                    let retry_model = Self::extract_model_from_body(&retry_body);
-                    let should_retry = retry_model
-                        .as_deref()
-                        .map(|m| m != current_model)
-                        .unwrap_or(false);
+                if let Some(failed_model) = effective_model.clone() {
+                    let thinking = has_thinking_enabled(&final_body);
+                    let mut tried = vec![failed_model.clone()];
+                    let mut chain =
+                        fallback_chain(provider, &original_request_model, &failed_model, &tried)
+                            .into_iter();
+                    let mut resolver_used = false;
+                    let mut last_error = (status_code, body_text);
+
+                    for _ in 0..MAX_MODEL_FALLBACK_RETRIES {
+                        let mut retry_reason = "fallback-chain";
+                        let (retry_body, retry_writeback) = if let Some(next) = chain.next() {
+                            let mut retry_body = final_body.clone();
+                            retry_body["model"] = Value::String(next.clone());
+                            let writeback = if is_claude {
+                                super::model_resolver::claude_writeback(
+                                    provider,
+                                    &original_request_model,
+                                    thinking,
+                                    &failed_model,
+                                    &next,
+                                )
+                            } else {
+                                super::openai_model_resolver::openai_writeback(
+                                    provider,
+                                    &original_request_model,
+                                    &next,
+                                )
+                            };
+                            (retry_body, writeback)
+                        } else if !resolver_used {
+                            resolver_used = true;
+                            retry_reason = "next-best";
+                            let avoid: Vec<&str> = tried.iter().map(String::as_str).collect();
+                            if is_claude {
+                                super::model_resolver::resolve_claude_model_in_body_with_avoid(
+                                    &self.client,
+                                    provider,
+                                    &auth.api_key,
+                                    &original_request_model,
+                                    final_body.clone(),
+                                    &avoid,
+                                )
+                                .await
+                            } else {
+                                super::openai_model_resolver::resolve_openai_model_in_body_with_avoid(
+                                    &self.client,
+                                    provider,
+                                    &auth.api_key,
+                                    &original_request_model,
+                                    final_body.clone(),
+                                    &avoid,
+                                )
+                                .await
+                            }
+                        } else {
+                            break;
+                        };
 
-                    if should_retry {
+                        let Some(retry_model) = Self::extract_model_from_body(&retry_body)
+                            .filter(|m| !tried.iter().any(|t| t.eq_ignore_ascii_case(m)))
+                        else {
+                            continue;
+                        };
                         log::debug!(
                             "[ModelResolver] provider={} 上游提示模型不可用，尝试重试 {} → {}",
                             provider.id,
-                            current_model,
-                            retry_model.as_deref().unwrap_or("unknown")
+                            tried.last().map(String::as_str).unwrap_or("unknown"),
+                            retry_model
                         );
+                        model_trace.record(
+                            STEP_RETRY,
+                            tried.last().map(String::as_str),
+                            Some(&retry_model),
+                            retry_reason,
+                        );
+                        tried.push(retry_model.clone());
 
                         // 重试：使用同一 provider、同一路由、同一认证，仅替换 model
                         let retry_response =
-                            build_request(&retry_body).send().await.map_err(|e| {
+                            build_request(&retry_body)?.send().await.map_err(|e| {
                                 log::error!(
-                                    "错误 - {} - target={} base_url={} - 详情: 重试请求失败 {}",
-                                    provider.name,
-                                    target_description,
-                                    upstream_base_url.as_deref().unwrap_or("-"),
-                                    e
+                                    "{}",
+                                    tr(
+                                        Msg::ForwardRetryFailed,
+                                        &[
+                                            &provider.name,
+                                            &target_description,
+                                            &upstream_base_url.as_deref().unwrap_or("-"),
+                                            &e,
+                                        ],
+                                    )
                                 );
                                 if e.is_timeout() {
                                     ProxyError::Timeout(format!("请求超时: {e}"))
@@ -1318,176 +1943,43 @@ impl RequestForwarder {
 
                         let retry_status = retry_response.status();
                         if retry_status.is_success() {
-                            pending_writeback = retry_writeback;
-                            effective_model = retry_model;
-
-                            if let Some(wb) = pending_writeback {
-                                let router = self.router.clone();
-                                let provider_id = provider.id.clone();
-                                let env_key = wb.env_key;
-                                let env_value = wb.value.clone();
-                                tokio::spawn(async move {
-                                    if let Err(e) = router
-                                        .writeback_provider_env(
-                                            "claude",
-                                            &provider_id,
-                                            env_key,
-                                            &env_value,
-                                        )
-                                        .await
-                                    {
-                                        log::warn!(
-                                            "[ModelResolver] 写回失败 provider={} key={} err={}",
-                                            provider_id,
-                                            env_key,
-                                            e
-                                        );
-                                    } else {
-                                        log::debug!(
-                                            "[ModelResolver] 已写回 provider={} {}={}",
-                                            provider_id,
-                                            env_key,
-                                            env_value
-                                        );
-                                    }
-                                });
+                            if let Some(wb) = retry_writeback {
+                                self.spawn_model_writeback(app_type_str, &provider.id, wb);
                             }
-
                             return Ok(ForwardedResponse {
                                 response: retry_response,
-                                effective_model,
-                            });
-                        } else {
-                            let status_code2 = retry_status.as_u16();
-                            let body_text2 = retry_response.text().await.ok();
-                            log::error!(
-                                "错误 {} - {} - base_url={} - 详情: {:?}",
-                                status_code2,
-                                provider.name,
-                                upstream_base_url.as_deref().unwrap_or("-"),
-                                body_text2
-                            );
-                            return Err(ProxyError::UpstreamError {
-                                status: status_code2,
-                                body: body_text2,
+                                effective_model: Some(retry_model),
+                                model_trace,
                             });
                         }
-                    }
-                }
-            }
-
-            // Codex/OpenAI：若上游明确提示“模型不存在/无可用渠道”，则在同一 provider 上做一次“次优模型”重试，
-            // 用于处理“供应商暴露了 gpt-5.2 但实际分组无 distributor / 需要使用 codex 子型号”等情况。
-            if app_type_str == "codex"
-                && (endpoint == "/v1/responses" || endpoint == "/v1/chat/completions")
-                && !original_request_model.is_empty()
-                && body_text
-                    .as_deref()
-                    .map(|t| Self::is_model_unavailable_error(status_code, t))
-                    == Some(true)
-            {
-                if let Some(current_model) = effective_model.clone() {
-                    let avoid = [current_model.as_str()];
-                    let (retry_body, retry_writeback) =
-                        super::openai_model_resolver::resolve_openai_model_in_body_with_avoid(
-                            &self.client,
-                            provider,
-                            &auth.api_key,
-                            &original_request_model,
-                            final_body.clone(),
-                            &avoid,
-                        )
-                        .await;
-
-                    let retry_model = Self::extract_model_from_body(&retry_body);
-                    let should_retry = retry_model
-                        .as_deref()
-                        .map(|m| m != current_model)
-                        .unwrap_or(false);
 
-                    if should_retry {
-                        log::debug!(
-                            "[ModelResolver] provider={} 上游提示模型不可用，尝试重试 {} → {}",
-                            provider.id,
-                            current_model,
-                            retry_model.as_deref().unwrap_or("unknown")
+                        let status_code2 = retry_status.as_u16();
+                        let body_text2 = read_error_body(retry_response).await;
+                        log::error!(
+                            "{}",
+                            tr(
+                                Msg::ForwardUpstreamError,
+                                &[
+                                    &status_code2,
+                                    &provider.name,
+                                    &upstream_base_url.as_deref().unwrap_or("-"),
+                                    &format!("{:?}", body_text2),
+                                ],
+                            )
                         );
-
-                        let retry_response =
-                            build_request(&retry_body).send().await.map_err(|e| {
-                                log::error!(
-                                    "错误 - {} - target={} base_url={} - 详情: 重试请求失败 {}",
-                                    provider.name,
-                                    target_description,
-                                    upstream_base_url.as_deref().unwrap_or("-"),
-                                    e
-                                );
-                                if e.is_timeout() {
-                                    ProxyError::Timeout(format!("请求超时: {e}"))
-                                } else if e.is_connect() {
-                                    ProxyError::ForwardFailed(format!("连接失败: {e}"))
-                                } else {
-                                    ProxyError::ForwardFailed(e.to_string())
-                                }
-                            })?;
-
-                        let retry_status = retry_response.status();
-                        if retry_status.is_success() {
-                            pending_writeback = retry_writeback;
-                            effective_model = retry_model;
-
-                            if let Some(wb) = pending_writeback {
-                                let router = self.router.clone();
-                                let provider_id = provider.id.clone();
-                                let env_key = wb.env_key;
-                                let env_value = wb.value.clone();
-                                tokio::spawn(async move {
-                                    if let Err(e) = router
-                                        .writeback_provider_env(
-                                            "codex",
-                                            &provider_id,
-                                            env_key,
-                                            &env_value,
-                                        )
-                                        .await
-                                    {
-                                        log::warn!(
-                                            "[ModelResolver] 写回失败 provider={} key={} err={}",
-                                            provider_id,
-                                            env_key,
-                                            e
-                                        );
-                                    } else {
-                                        log::debug!(
-                                            "[ModelResolver] 已写回 provider={} {}={}",
-                                            provider_id,
-                                            env_key,
-                                            env_value
-                                        );
-                                    }
-                                });
-                            }
-
-                            return Ok(ForwardedResponse {
-                                response: retry_response,
-                                effective_model,
-                            });
-                        } else {
-                            let status_code2 = retry_status.as_u16();
-                            let body_text2 = retry_response.text().await.ok();
-                            log::error!(
-                                "错误 {} - {} - base_url={} - 详情: {:?}",
-                                status_code2,
-                                provider.name,
-                                upstream_base_url.as_deref().unwrap_or("-"),
-                                body_text2
-                            );
-                            return Err(ProxyError::UpstreamError {
-                                status: status_code2,
-                                body: body_text2,
-                            });
+                        let still_unavailable = body_text2
+                            .as_deref()
+                            .is_some_and(|t| Self::is_model_unavailable_error(status_code2, t));
+                        last_error = (status_code2, body_text2);
+                        if !still_unavailable {
+                            break;
                         }
                     }
+
+                    return Err(ProxyError::UpstreamError {
+                        status: last_error.0,
+                        body: last_error.1,
+                    });
                 }
             }
 
@@ -1498,6 +1990,158 @@ impl RequestForwarder {
         }
     }
 
+    /// 请求成功后在后台写回解析出的模型（避免后续重复匹配）
+    fn spawn_model_writeback(&self, app_type: &str, provider_id: &str, wb: ModelWriteback) {
+        let router = self.router.clone();
+        let app_type = app_type.to_string();
+        let provider_id = provider_id.to_string();
+        tokio::spawn(async move {
+            if let Err(e) = router
+                .writeback_provider_env(&app_type, &provider_id, wb.env_key, &wb.value)
+                .await
+            {
+                log::warn!(
+                    "[ModelResolver] 写回失败 app={} provider={} key={} err={}",
+                    app_type,
+                    provider_id,
+                    wb.env_key,
+                    e
+                );
+            } else {
+                log::debug!(
+                    "[ModelResolver] 已写回 app={} provider={} {}={}",
+                    app_type,
+                    provider_id,
+                    wb.env_key,
+                    wb.value
+                );
+            }
+        });
+    }
+
+    /// 转发到 AWS Bedrock（SigV4 签名；流式响应由 event-stream 转换为 SSE）
+    async fn forward_bedrock(
+        &self,
+        provider: &Provider,
+        endpoint: &str,
+        body: &Value,
+        bedrock: &BedrockConfig,
+        mut model_trace: ModelResolutionTrace,
+    ) -> Result<ForwardedResponse, ProxyError> {
+        if endpoint != "/v1/messages" {
+            return Err(ProxyError::ConfigError(format!(
+                "Bedrock 供应商不支持 {endpoint}"
+            )));
+        }
+
+        // 先应用显式映射（ANTHROPIC_DEFAULT_* 等；模型锁定时跳过），再换算为 Bedrock 模型 ID
+        let client_model = Self::extract_model_from_body(body);
+        let mapped_body = if provider.model_locked() {
+            model_trace.record(
+                STEP_LOCKED,
+                client_model.as_deref(),
+                client_model.as_deref(),
+                "modelLock",
+            );
+            body.clone()
+        } else {
+            let mapped = super::model_mapper::apply_model_mapping(body.clone(), provider).0;
+            model_trace.record_change(
+                STEP_MAPPING,
+                client_model.as_deref(),
+                Self::extract_model_from_body(&mapped).as_deref(),
+                "env",
+            );
+            mapped
+        };
+        let is_stream = mapped_body
+            .get("stream")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let mapped_body = apply_body_transforms(provider, mapped_body);
+        let (model, bedrock_body) = to_bedrock_body(&mapped_body);
+        let model_id = bedrock.model_id(&model);
+        model_trace.record_change(STEP_BEDROCK, Some(&model), Some(&model_id), "model-id");
+        let url = bedrock.invoke_url(&model_id, is_stream);
+        let payload = serde_json::to_vec(&bedrock_body)
+            .map_err(|e| ProxyError::TransformError(format!("序列化 Bedrock 请求体失败: {e}")))?;
+        let signed = bedrock
+            .sign(&url, &payload, chrono::Utc::now())
+            .map_err(ProxyError::ConfigError)?;
+
+        let mut request = self
+            .client
+            .post(&url)
+            .header("Content-Type", "application/json")
+            .header("X-Amzn-Bedrock-Accept", "application/json");
+        request = if is_stream {
+            request.header("Accept", "application/vnd.amazon.eventstream")
+        } else {
+            request.header("Accept", "application/json")
+        };
+        for (name, value) in signed {
+            request = request.header(name, value);
+        }
+
+        let base_url = bedrock.base_url();
+        let response = request.body(payload).send().await.map_err(|e| {
+            log::error!(
+                "{}",
+                tr(
+                    Msg::ForwardRequestFailed,
+                    &[&provider.name, &"Bedrock", &base_url, &e],
+                )
+            );
+            if e.is_timeout() {
+                ProxyError::Timeout(format!("请求超时: {e}"))
+            } else if e.is_connect() {
+                ProxyError::ForwardFailed(format!("连接失败: {e}"))
+            } else {
+                ProxyError::ForwardFailed(e.to_string())
+            }
+        })?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body_text = read_error_body(response).await;
+            log::error!(
+                "{}",
+                tr(
+                    Msg::ForwardUpstreamError,
+                    &[
+                        &status.as_u16(),
+                        &provider.name,
+                        &base_url,
+                        &format!("{:?}", body_text),
+                    ],
+                )
+            );
+            return Err(ProxyError::UpstreamError {
+                status: status.as_u16(),
+                body: body_text,
+            });
+        }
+
+        // 非流式响应体即 Anthropic Messages 格式；流式响应转换为 SSE 后交给通用处理
+        let response = if is_stream {
+            let sse = create_bedrock_sse_stream(response.bytes_stream());
+            let converted = axum::http::Response::builder()
+                .status(status)
+                .header("content-type", "text/event-stream")
+                .body(reqwest::Body::wrap_stream(sse))
+                .map_err(|e| ProxyError::Internal(format!("构造 Bedrock 流式响应失败: {e}")))?;
+            Response::from(converted)
+        } else {
+            response
+        };
+
+        Ok(ForwardedResponse {
+            response,
+            effective_model: Some(model_id),
+            model_trace,
+        })
+    }
+
     /// 分类ProxyError
     ///
     /// 决定哪些错误应该触发故障转移到下一个 Provider
@@ -1583,3 +2227,1159 @@ impl RequestForwarder {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::i18n::display_width;
+
+    #[test]
+    fn test_success_log_line_alignment_in_both_locales() {
+        let zh = RequestForwarder::format_success_log_line(
+            Locale::Zh,
+            "[codex ]",
+            200,
+            "anyrouter-key1",
+            2770,
+            None,
+            "gpt-5.2",
+        );
+        assert_eq!(
+            zh,
+            "[codex ] 正常 200 - anyrouter-key1                      ( 2.770s) [上游: gpt-5.2]"
+        );
+
+        let en = RequestForwarder::format_success_log_line(
+            Locale::En,
+            "[codex ]",
+            200,
+            "anyrouter-key1",
+            2770,
+            None,
+            "gpt-5.2",
+        );
+        assert_eq!(
+            en,
+            "[codex ] OK   200 - anyrouter-key1                      ( 2.770s) [upstream: gpt-5.2]"
+        );
+
+        // 状态码、渠道、耗时列在两种语言下位于相同的显示列
+        let column = |line: &str, pat: &str| display_width(&line[..line.find(pat).unwrap()]);
+        for pat in ["200", "anyrouter-key1", "( 2.770s)"] {
+            assert_eq!(column(&zh, pat), column(&en, pat), "{pat}");
+        }
+    }
+
+    #[test]
+    fn test_success_log_line_splits_python_hop() {
+        let line = RequestForwarder::format_success_log_line(
+            Locale::En,
+            "[claude]",
+            200,
+            "relay",
+            2740,
+            Some(2700),
+            "claude-sonnet-4",
+        );
+        assert!(
+            line.contains("( 2.740s = 0.040s local + 2.700s upstream)"),
+            "{line}"
+        );
+    }
+
+    #[test]
+    fn test_upstream_model_label_records_default_model_substitution() {
+        let label = RequestForwarder::upstream_model_label;
+        assert_eq!(label(Some("gpt-5"), Some("gpt-5")), "gpt-5");
+        assert_eq!(
+            label(Some("gpt-5"), Some("gpt-5-codex")),
+            "gpt-5 → gpt-5-codex"
+        );
+        // 未指定模型：使用默认模型时记录替换
+        assert_eq!(label(Some("auto"), Some("gpt-5")), "auto → gpt-5");
+        assert_eq!(
+            label(None, Some("claude-sonnet-4-5")),
+            "auto → claude-sonnet-4-5"
+        );
+        // 未配置默认模型：原样透传
+        assert_eq!(label(Some("auto"), Some("auto")), "auto");
+        assert_eq!(label(None, None), "unknown");
+    }
+
+    #[tokio::test]
+    async fn test_bedrock_forward_signs_and_converts_body() {
+        use axum::{extract::Path, http::HeaderMap, routing::post, Json, Router};
+
+        // Bedrock 形态的上游：非流式回显请求；流式返回空的 event-stream
+        let echo = |Path(model_id): Path<String>, headers: HeaderMap, Json(body): Json<Value>| async move {
+            let header = |name: &str| {
+                headers
+                    .get(name)
+                    .and_then(|v| v.to_str().ok())
+                    .map(str::to_string)
+            };
+            Json(serde_json::json!({
+                "model_id": model_id,
+                "authorization": header("authorization"),
+                "x_amz_date": header("x-amz-date"),
+                "body": body,
+            }))
+        };
+        let app = Router::new()
+            .route("/model/:model_id/invoke", post(echo))
+            .route(
+                "/model/:model_id/invoke-with-response-stream",
+                post(|| async { ([("content-type", "application/vnd.amazon.eventstream")], "") }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+
+        let db = Arc::new(Database::memory().unwrap());
+        let forwarder = RequestForwarder::new(
+            Arc::new(ProviderRouter::new(db.clone())),
+            db.clone(),
+            30,
+            0,
+            Arc::new(RwLock::new(ProxyStatus::default())),
+            Arc::new(RwLock::new(std::collections::HashMap::new())),
+            Arc::new(FailoverSwitchManager::new(db)),
+            None,
+            String::new(),
+            0,
+            0,
+        );
+        let provider = Provider::with_id(
+            "br".to_string(),
+            "br".to_string(),
+            serde_json::json!({
+                "bedrock": {
+                    "region": "us-east-1",
+                    "accessKeyId": "AKID",
+                    "secretAccessKey": "secret",
+                    "endpoint": format!("http://{addr}")
+                }
+            }),
+            None,
+        );
+        let adapter = get_adapter(&AppType::Claude);
+        let request = |stream: bool| {
+            serde_json::json!({
+                "model": "claude-3-5-haiku-20241022",
+                "stream": stream,
+                "max_tokens": 8,
+                "messages": [{"role": "user", "content": "hi"}]
+            })
+        };
+
+        let forwarded = forwarder
+            .forward(
+                &provider,
+                "/v1/messages",
+                &request(false),
+                &axum::http::HeaderMap::new(),
+                adapter.as_ref(),
+            )
+            .await
+            .map_err(|e| e.to_string())
+            .unwrap();
+        assert_eq!(
+            forwarded.effective_model.as_deref(),
+            Some("anthropic.claude-3-5-haiku-20241022-v1:0")
+        );
+        let echoed: Value = forwarded.response.json().await.unwrap();
+        assert_eq!(
+            echoed["model_id"],
+            "anthropic.claude-3-5-haiku-20241022-v1:0"
+        );
+        assert!(echoed["authorization"]
+            .as_str()
+            .unwrap()
+            .starts_with("AWS4-HMAC-SHA256 Credential=AKID/"));
+        assert!(echoed["x_amz_date"].is_string());
+        assert_eq!(
+            echoed["body"],
+            serde_json::json!({
+                "anthropic_version": "bedrock-2023-05-31",
+                "max_tokens": 8,
+                "messages": [{"role": "user", "content": "hi"}]
+            })
+        );
+
+        // 流式：响应被转换为 SSE
+        let forwarded = forwarder
+            .forward(
+                &provider,
+                "/v1/messages",
+                &request(true),
+                &axum::http::HeaderMap::new(),
+                adapter.as_ref(),
+            )
+            .await
+            .map_err(|e| e.to_string())
+            .unwrap();
+        assert_eq!(
+            forwarded.response.headers()["content-type"],
+            "text/event-stream"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_vertex_forward_uses_cached_bearer_token() {
+        use crate::proxy::providers::vertex_test_support::{
+            spawn_token_endpoint, write_service_account,
+        };
+        use axum::{extract::Path, http::HeaderMap, http::Uri, routing::post, Json, Router};
+        use std::sync::atomic::Ordering;
+
+        // Vertex 形态的上游：回显路径、查询参数与认证头
+        let echo = |Path((project, location, model)): Path<(String, String, String)>,
+                    uri: Uri,
+                    headers: HeaderMap| async move {
+            Json(serde_json::json!({
+                "project": project,
+                "location": location,
+                "model": model,
+                "query": uri.query(),
+                "authorization": headers
+                    .get("authorization")
+                    .and_then(|v| v.to_str().ok()),
+                "api_key": headers
+                    .get("x-goog-api-key")
+                    .and_then(|v| v.to_str().ok()),
+            }))
+        };
+        let app = Router::new().route(
+            "/v1/projects/:project/locations/:location/publishers/google/models/:model",
+            post(echo),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let (token_uri, hits) = spawn_token_endpoint(3600).await;
+        let provider = Provider::with_id(
+            "vx".to_string(),
+            "vx".to_string(),
+            serde_json::json!({
+                "vertex": {
+                    "serviceAccountJsonPath": write_service_account(dir.path(), &token_uri),
+                    "project": "test-project",
+                    "location": "us-central1",
+                    "endpoint": format!("http://{addr}")
+                }
+            }),
+            None,
+        );
+
+        let db = Arc::new(Database::memory().unwrap());
+        let forwarder = RequestForwarder::new(
+            Arc::new(ProviderRouter::new(db.clone())),
+            db.clone(),
+            30,
+            0,
+            Arc::new(RwLock::new(ProxyStatus::default())),
+            Arc::new(RwLock::new(std::collections::HashMap::new())),
+            Arc::new(FailoverSwitchManager::new(db)),
+            None,
+            String::new(),
+            0,
+            0,
+        );
+        let adapter = get_adapter(&AppType::Gemini);
+        let body = serde_json::json!({"contents": [{"role": "user", "parts": [{"text": "hi"}]}]});
+
+        for _ in 0..2 {
+            let forwarded = forwarder
+                .forward(
+                    &provider,
+                    "/v1beta/models/gemini-2.5-pro:streamGenerateContent?alt=sse",
+                    &body,
+                    &axum::http::HeaderMap::new(),
+                    adapter.as_ref(),
+                )
+                .await
+                .map_err(|e| e.to_string())
+                .unwrap();
+            let echoed: Value = forwarded.response.json().await.unwrap();
+            assert_eq!(echoed["project"], "test-project");
+            assert_eq!(echoed["location"], "us-central1");
+            assert_eq!(echoed["model"], "gemini-2.5-pro:streamGenerateContent");
+            assert_eq!(echoed["query"], "alt=sse");
+            assert_eq!(echoed["authorization"], "Bearer ya29.token-1");
+            assert!(echoed["api_key"].is_null());
+        }
+        // 第二次请求复用缓存的 token
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_env_placeholders_expand_at_request_time_only() {
+        use axum::{http::HeaderMap, routing::post, Json, Router};
+
+        let app = Router::new().route(
+            "/v1/responses",
+            post(|headers: HeaderMap| async move {
+                Json(serde_json::json!({
+                    "authorization": headers
+                        .get("authorization")
+                        .and_then(|v| v.to_str().ok()),
+                }))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+
+        std::env::set_var("CC_SWITCH_TEST_FWD_KEY", "sk-from-env");
+        std::env::set_var("CC_SWITCH_TEST_FWD_HOST", addr.to_string());
+        let db = Arc::new(Database::memory().unwrap());
+        let provider = Provider::with_id(
+            "envp".to_string(),
+            "envp".to_string(),
+            serde_json::json!({
+                "env": { "OPENAI_API_KEY": "${CC_SWITCH_TEST_FWD_KEY}" },
+                "base_url": "http://${CC_SWITCH_TEST_FWD_HOST}/v1"
+            }),
+            None,
+        );
+        db.save_provider("codex", &provider).unwrap();
+
+        let forwarder = RequestForwarder::new(
+            Arc::new(ProviderRouter::new(db.clone())),
+            db.clone(),
+            30,
+            0,
+            Arc::new(RwLock::new(ProxyStatus::default())),
+            Arc::new(RwLock::new(std::collections::HashMap::new())),
+            Arc::new(FailoverSwitchManager::new(db.clone())),
+            None,
+            String::new(),
+            0,
+            0,
+        );
+        let adapter = get_adapter(&AppType::Codex);
+        let body = serde_json::json!({"model": "gpt-5", "input": "hi"});
+
+        let forwarded = forwarder
+            .forward(
+                &provider,
+                "/v1/responses",
+                &body,
+                &axum::http::HeaderMap::new(),
+                adapter.as_ref(),
+            )
+            .await
+            .map_err(|e| e.to_string())
+            .unwrap();
+        let echoed: Value = forwarded.response.json().await.unwrap();
+        assert_eq!(echoed["authorization"], "Bearer sk-from-env");
+
+        // 数据库中仍是占位符
+        let stored = db.get_provider_by_id("envp", "codex").unwrap().unwrap();
+        assert_eq!(
+            stored.settings_config["env"]["OPENAI_API_KEY"],
+            "${CC_SWITCH_TEST_FWD_KEY}"
+        );
+
+        // 变量未设置：认证错误，不在同一供应商内重试，但会切换到下一个供应商
+        let mut missing = provider.clone();
+        missing.settings_config["env"]["OPENAI_API_KEY"] =
+            Value::String("${CC_SWITCH_TEST_FWD_UNSET}".to_string());
+        let err = forwarder
+            .forward(
+                &missing,
+                "/v1/responses",
+                &body,
+                &axum::http::HeaderMap::new(),
+                adapter.as_ref(),
+            )
+            .await
+            .err()
+            .expect("unset variable should fail");
+        assert!(matches!(err, ProxyError::AuthError(_)), "{err:?}");
+        assert!(err.to_string().contains("CC_SWITCH_TEST_FWD_UNSET"));
+        assert!(!forwarder.should_retry_same_provider(&err));
+        assert!(matches!(
+            forwarder.categorize_proxy_error(&err),
+            ErrorCategory::Retryable
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_default_model_fills_unspecified_request_model() {
+        use axum::{routing::post, Json, Router};
+
+        // 上游回显收到的模型
+        let app = Router::new().route(
+            "/v1/responses",
+            post(|Json(body): Json<Value>| async move {
+                Json(serde_json::json!({ "model": body.get("model") }))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+
+        let db = Arc::new(Database::memory().unwrap());
+        let provider = Provider::with_id(
+            "dm".to_string(),
+            "dm".to_string(),
+            serde_json::json!({
+                "env": { "OPENAI_API_KEY": "sk-dm" },
+                "base_url": format!("http://{addr}/v1")
+            }),
+            None,
+        );
+        let forwarder = RequestForwarder::new(
+            Arc::new(ProviderRouter::new(db.clone())),
+            db.clone(),
+            30,
+            0,
+            Arc::new(RwLock::new(ProxyStatus::default())),
+            Arc::new(RwLock::new(std::collections::HashMap::new())),
+            Arc::new(FailoverSwitchManager::new(db)),
+            None,
+            String::new(),
+            0,
+            0,
+        )
+        .with_default_model(Some("gpt-5".to_string()));
+        let adapter = get_adapter(&AppType::Codex);
+
+        for body in [
+            serde_json::json!({"input": "hi"}),
+            serde_json::json!({"model": "auto", "input": "hi"}),
+            serde_json::json!({"model": "gpt-4o", "input": "hi"}),
+        ] {
+            let expected = match body["model"].as_str() {
+                Some("gpt-4o") => "gpt-4o",
+                _ => "gpt-5",
+            };
+            let forwarded = forwarder
+                .forward(
+                    &provider,
+                    "/v1/responses",
+                    &body,
+                    &axum::http::HeaderMap::new(),
+                    adapter.as_ref(),
+                )
+                .await
+                .map_err(|e| e.to_string())
+                .unwrap();
+            assert_eq!(forwarded.effective_model.as_deref(), Some(expected));
+            let echoed: Value = forwarded.response.json().await.unwrap();
+            assert_eq!(echoed["model"], expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_model_lock_forwards_client_model_verbatim() {
+        use axum::{
+            routing::{get, post},
+            Json, Router,
+        };
+
+        // 上游只列出不带日期的模型，并回显收到的模型
+        let app = Router::new()
+            .route(
+                "/v1/models",
+                get(|| async { Json(serde_json::json!({ "data": [{ "id": "gpt-5.2" }] })) }),
+            )
+            .route(
+                "/v1/responses",
+                post(|Json(body): Json<Value>| async move {
+                    Json(serde_json::json!({ "model": body.get("model") }))
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+
+        let db = Arc::new(Database::memory().unwrap());
+        let forwarder = RequestForwarder::new(
+            Arc::new(ProviderRouter::new(db.clone())),
+            db.clone(),
+            30,
+            0,
+            Arc::new(RwLock::new(ProxyStatus::default())),
+            Arc::new(RwLock::new(std::collections::HashMap::new())),
+            Arc::new(FailoverSwitchManager::new(db)),
+            None,
+            String::new(),
+            0,
+            0,
+        );
+        let adapter = get_adapter(&AppType::Codex);
+        let body = serde_json::json!({ "model": "gpt-5.2-2025-12-11", "input": "hi" });
+
+        for (id, locked, expected) in [
+            ("lock-on", true, "gpt-5.2-2025-12-11"),
+            ("lock-off", false, "gpt-5.2"),
+        ] {
+            let provider = Provider::with_id(
+                id.to_string(),
+                id.to_string(),
+                serde_json::json!({
+                    "env": { "OPENAI_API_KEY": "sk-lock" },
+                    "base_url": format!("http://{addr}/v1"),
+                    "modelLock": locked
+                }),
+                None,
+            );
+            let forwarded = forwarder
+                .forward(
+                    &provider,
+                    "/v1/responses",
+                    &body,
+                    &axum::http::HeaderMap::new(),
+                    adapter.as_ref(),
+                )
+                .await
+                .map_err(|e| e.to_string())
+                .unwrap();
+            assert_eq!(forwarded.effective_model.as_deref(), Some(expected), "{id}");
+            let echoed: Value = forwarded.response.json().await.unwrap();
+            assert_eq!(echoed["model"], expected, "{id}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_model_fallback_chain_retries_until_accepted() {
+        use axum::{
+            http::StatusCode,
+            routing::{get, post},
+            Json, Router,
+        };
+
+        // 上游列出全部模型，但只有 gpt-5-nano 有可用渠道
+        let received = Arc::new(std::sync::Mutex::new(Vec::<String>::new()));
+        let seen = received.clone();
+        let app = Router::new()
+            .route(
+                "/v1/models",
+                get(|| async {
+                    Json(serde_json::json!({ "data": [
+                        { "id": "gpt-5" },
+                        { "id": "gpt-5-pro" },
+                        { "id": "gpt-5-mini" },
+                        { "id": "gpt-5-nano" }
+                    ] }))
+                }),
+            )
+            .route(
+                "/v1/responses",
+                post(move |Json(body): Json<Value>| async move {
+                    let model = body["model"].as_str().unwrap_or_default().to_string();
+                    seen.lock().unwrap().push(model.clone());
+                    if model == "gpt-5-nano" {
+                        (StatusCode::OK, Json(serde_json::json!({ "model": model })))
+                    } else {
+                        (
+                            StatusCode::NOT_FOUND,
+                            Json(serde_json::json!({
+                                "error": { "code": "model_not_found", "message": "no channel" }
+                            })),
+                        )
+                    }
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+
+        let db = Arc::new(Database::memory().unwrap());
+        let provider = Provider::with_id(
+            "fb".to_string(),
+            "fb".to_string(),
+            serde_json::json!({
+                "env": { "OPENAI_API_KEY": "sk-fb" },
+                "base_url": format!("http://{addr}/v1"),
+                "modelFallbacks": { "gpt-5": ["gpt-5-pro", "gpt-5-mini", "gpt-5-nano"] }
+            }),
+            None,
+        );
+        db.save_provider("codex", &provider).unwrap();
+        let forwarder = RequestForwarder::new(
+            Arc::new(ProviderRouter::new(db.clone())),
+            db.clone(),
+            30,
+            0,
+            Arc::new(RwLock::new(ProxyStatus::default())),
+            Arc::new(RwLock::new(std::collections::HashMap::new())),
+            Arc::new(FailoverSwitchManager::new(db.clone())),
+            None,
+            String::new(),
+            0,
+            0,
+        );
+        let adapter = get_adapter(&AppType::Codex);
+        let forwarded = forwarder
+            .forward(
+                &provider,
+                "/v1/responses",
+                &serde_json::json!({ "model": "gpt-5", "input": "hi" }),
+                &axum::http::HeaderMap::new(),
+                adapter.as_ref(),
+            )
+            .await
+            .map_err(|e| e.to_string())
+            .unwrap();
+
+        assert_eq!(forwarded.effective_model.as_deref(), Some("gpt-5-nano"));
+        assert_eq!(
+            *received.lock().unwrap(),
+            vec!["gpt-5", "gpt-5-pro", "gpt-5-mini", "gpt-5-nano"]
+        );
+
+        // 写回在后台执行：记录 gpt-5 → gpt-5-nano 别名
+        let mut aliases = Value::Null;
+        for _ in 0..100 {
+            let saved = db.get_provider_by_id("fb", "codex").unwrap().unwrap();
+            if let Some(raw) = saved.settings_config["env"]
+                [super::super::openai_model_resolver::CODEX_ALIASES_ENV_KEY]
+                .as_str()
+            {
+                aliases = serde_json::from_str(raw).unwrap();
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(aliases["gpt-5"], "gpt-5-nano");
+    }
+
+    #[tokio::test]
+    async fn test_codex_model_unavailable_retries_once_with_next_best_model() {
+        use axum::{
+            extract::Path,
+            http::StatusCode,
+            routing::{get, post},
+            Json, Router,
+        };
+
+        // ok 供应商只有 gpt-5.2-codex 可用；none 供应商全部模型都无可用渠道
+        let received = Arc::new(std::sync::Mutex::new(Vec::<(String, String)>::new()));
+        let seen = received.clone();
+        let app = Router::new()
+            .route(
+                "/:id/v1/models",
+                get(|| async {
+                    Json(serde_json::json!({ "data": [
+                        { "id": "gpt-5.2" },
+                        { "id": "gpt-5.2-codex" },
+                        { "id": "gpt-4o" }
+                    ] }))
+                }),
+            )
+            .route(
+                "/:id/v1/responses",
+                post(move |Path(id): Path<String>, Json(body): Json<Value>| async move {
+                    let model = body["model"].as_str().unwrap_or_default().to_string();
+                    seen.lock().unwrap().push((id.clone(), model.clone()));
+                    if id == "ok" && model == "gpt-5.2-codex" {
+                        (StatusCode::OK, Json(serde_json::json!({ "model": model })))
+                    } else {
+                        (
+                            StatusCode::BAD_REQUEST,
+                            Json(serde_json::json!({
+                                "error": { "code": "model_not_found", "message": "model not found" }
+                            })),
+                        )
+                    }
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+
+        let db = Arc::new(Database::memory().unwrap());
+        let forwarder = RequestForwarder::new(
+            Arc::new(ProviderRouter::new(db.clone())),
+            db.clone(),
+            30,
+            0,
+            Arc::new(RwLock::new(ProxyStatus::default())),
+            Arc::new(RwLock::new(std::collections::HashMap::new())),
+            Arc::new(FailoverSwitchManager::new(db.clone())),
+            None,
+            String::new(),
+            0,
+            0,
+        );
+        let adapter = get_adapter(&AppType::Codex);
+        let body = serde_json::json!({ "model": "gpt-5.2", "input": "hi" });
+        let mut providers = Vec::new();
+        for id in ["ok", "none"] {
+            let provider = Provider::with_id(
+                id.to_string(),
+                id.to_string(),
+                serde_json::json!({
+                    "env": { "OPENAI_API_KEY": "sk-retry" },
+                    "base_url": format!("http://{addr}/{id}/v1")
+                }),
+                None,
+            );
+            db.save_provider("codex", &provider).unwrap();
+            providers.push(provider);
+        }
+
+        let forwarded = forwarder
+            .forward(
+                &providers[0],
+                "/v1/responses",
+                &body,
+                &axum::http::HeaderMap::new(),
+                adapter.as_ref(),
+            )
+            .await
+            .map_err(|e| e.to_string())
+            .unwrap();
+        assert_eq!(forwarded.effective_model.as_deref(), Some("gpt-5.2-codex"));
+
+        // 次优模型同样不可用：只重试一次，返回重试的错误
+        let err = forwarder
+            .forward(
+                &providers[1],
+                "/v1/responses",
+                &body,
+                &axum::http::HeaderMap::new(),
+                adapter.as_ref(),
+            )
+            .await
+            .err()
+            .expect("all models unavailable");
+        assert!(matches!(err, ProxyError::UpstreamError { status: 400, .. }));
+
+        let sent: Vec<(String, String)> = received.lock().unwrap().clone();
+        let expected: Vec<(String, String)> = [
+            ("ok", "gpt-5.2"),
+            ("ok", "gpt-5.2-codex"),
+            ("none", "gpt-5.2"),
+            ("none", "gpt-5.2-codex"),
+        ]
+        .iter()
+        .map(|(id, m)| (id.to_string(), m.to_string()))
+        .collect();
+        assert_eq!(sent, expected);
+
+        // 成功的重试写回别名 gpt-5.2 → gpt-5.2-codex
+        let mut aliases = Value::Null;
+        for _ in 0..100 {
+            let saved = db.get_provider_by_id("ok", "codex").unwrap().unwrap();
+            if let Some(raw) = saved.settings_config["env"]
+                [super::super::openai_model_resolver::CODEX_ALIASES_ENV_KEY]
+                .as_str()
+            {
+                aliases = serde_json::from_str(raw).unwrap();
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(aliases["gpt-5.2"], "gpt-5.2-codex");
+    }
+
+    #[tokio::test]
+    async fn test_model_resolution_trace_covers_default_alias_and_retry() {
+        use axum::{http::StatusCode, routing::post, Json, Router};
+
+        // gpt-5.2-codex 无可用渠道，gpt-5.2-mini 可用
+        let app = Router::new().route(
+            "/v1/responses",
+            post(|Json(body): Json<Value>| async move {
+                let model = body["model"].as_str().unwrap_or_default().to_string();
+                if model == "gpt-5.2-mini" {
+                    (StatusCode::OK, Json(serde_json::json!({ "model": model })))
+                } else {
+                    (
+                        StatusCode::NOT_FOUND,
+                        Json(serde_json::json!({
+                            "error": { "code": "model_not_found", "message": "no channel" }
+                        })),
+                    )
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+
+        let db = Arc::new(Database::memory().unwrap());
+        let provider = Provider::with_id(
+            "mt".to_string(),
+            "mt".to_string(),
+            serde_json::json!({
+                "env": {
+                    "OPENAI_API_KEY": "sk-mt",
+                    "CC_SWITCH_CODEX_MODEL_ALIASES":
+                        serde_json::json!({ "gpt-5.2": "gpt-5.2-codex" }).to_string()
+                },
+                "base_url": format!("http://{addr}/v1"),
+                "modelFallbacks": { "gpt-5.2": ["gpt-5.2-mini"] }
+            }),
+            None,
+        );
+        db.save_provider("codex", &provider).unwrap();
+        let forwarder = RequestForwarder::new(
+            Arc::new(ProviderRouter::new(db.clone())),
+            db.clone(),
+            30,
+            0,
+            Arc::new(RwLock::new(ProxyStatus::default())),
+            Arc::new(RwLock::new(std::collections::HashMap::new())),
+            Arc::new(FailoverSwitchManager::new(db)),
+            None,
+            String::new(),
+            0,
+            0,
+        )
+        .with_default_model(Some("gpt-5.2".to_string()));
+        let adapter = get_adapter(&AppType::Codex);
+        let forwarded = forwarder
+            .forward(
+                &provider,
+                "/v1/responses",
+                &serde_json::json!({ "model": "auto", "input": "hi" }),
+                &axum::http::HeaderMap::new(),
+                adapter.as_ref(),
+            )
+            .await
+            .map_err(|e| e.to_string())
+            .unwrap();
+
+        assert_eq!(forwarded.effective_model.as_deref(), Some("gpt-5.2-mini"));
+        let steps: Vec<(&str, Option<&str>, Option<&str>, &str)> = forwarded
+            .model_trace
+            .steps()
+            .iter()
+            .map(|s| (s.step, s.from.as_deref(), s.to.as_deref(), s.reason))
+            .collect();
+        assert_eq!(
+            steps,
+            vec![
+                ("default", Some("auto"), Some("gpt-5.2"), "unspecified"),
+                ("alias", Some("gpt-5.2"), Some("gpt-5.2-codex"), "alias-map"),
+                (
+                    "retry",
+                    Some("gpt-5.2-codex"),
+                    Some("gpt-5.2-mini"),
+                    "fallback-chain"
+                ),
+            ]
+        );
+        assert_eq!(
+            forwarded.model_trace.render(),
+            "default:auto>gpt-5.2(unspecified)|alias:gpt-5.2>gpt-5.2-codex(alias-map)|retry:gpt-5.2-codex>gpt-5.2-mini(fallback-chain)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_thinking_rejection_writes_back_capability_and_fails_over() {
+        use axum::{http::StatusCode, routing::post, Json, Router};
+
+        // a 拒绝 thinking，b 正常
+        let app = Router::new()
+            .route(
+                "/a/v1/responses",
+                post(|| async {
+                    (
+                        StatusCode::BAD_REQUEST,
+                        Json(serde_json::json!({
+                            "error": { "message": "thinking is not supported for this model" }
+                        })),
+                    )
+                }),
+            )
+            .route(
+                "/b/v1/responses",
+                post(|| async { Json(serde_json::json!({ "ok": true })) }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+
+        let db = Arc::new(Database::memory().unwrap());
+        let make = |id: &str, sort_index: usize| {
+            let mut provider = Provider::with_id(
+                id.to_string(),
+                id.to_string(),
+                serde_json::json!({
+                    "env": { "OPENAI_API_KEY": format!("sk-{id}") },
+                    "base_url": format!("http://{addr}/{id}/v1")
+                }),
+                None,
+            );
+            provider.sort_index = Some(sort_index);
+            provider
+        };
+        let (provider_a, provider_b) = (make("a", 1), make("b", 2));
+        db.save_provider("codex", &provider_a).unwrap();
+        db.save_provider("codex", &provider_b).unwrap();
+        db.set_current_provider("codex", "a").unwrap();
+        db.add_to_failover_queue("codex", "a").unwrap();
+        db.add_to_failover_queue("codex", "b").unwrap();
+        let mut config = db.get_proxy_config_for_app("codex").await.unwrap();
+        config.auto_failover_enabled = true;
+        db.update_proxy_config_for_app(config).await.unwrap();
+
+        let forwarder = RequestForwarder::new(
+            Arc::new(ProviderRouter::new(db.clone())),
+            db.clone(),
+            30,
+            0,
+            Arc::new(RwLock::new(ProxyStatus::default())),
+            Arc::new(RwLock::new(std::collections::HashMap::new())),
+            Arc::new(FailoverSwitchManager::new(db.clone())),
+            None,
+            String::new(),
+            0,
+            0,
+        );
+        let body = serde_json::json!({
+            "model": "gpt-5",
+            "input": "hi",
+            "thinking": { "type": "enabled", "budget_tokens": 1024 }
+        });
+        let headers = axum::http::HeaderMap::new();
+        let result = forwarder
+            .forward_with_retry(
+                &AppType::Codex,
+                "/v1/responses",
+                body,
+                headers.clone(),
+                vec![provider_a, provider_b],
+                &mut RequestTrace::from_headers(&headers),
+            )
+            .await
+            .map_err(|e| e.error.to_string())
+            .unwrap();
+        assert_eq!(result.provider.id, "b");
+
+        let providers = db.get_all_providers("codex").unwrap();
+        assert_eq!(
+            providers["a"].settings_config[SUPPORTS_THINKING_KEY],
+            Value::Bool(false)
+        );
+        assert!(providers["b"]
+            .settings_config
+            .get(SUPPORTS_THINKING_KEY)
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_shadow_mirror_never_blocks_primary() {
+        use axum::{routing::post, Json, Router};
+
+        // primary 立即返回；hang 永不响应；cheap 正常返回 usage
+        let ok = || async {
+            Json(serde_json::json!({
+                "model": "gpt-5",
+                "usage": { "input_tokens": 7, "output_tokens": 3, "total_tokens": 10 }
+            }))
+        };
+        let app = Router::new()
+            .route("/primary/v1/responses", post(ok))
+            .route("/cheap/v1/responses", post(ok))
+            .route(
+                "/hang/v1/responses",
+                post(std::future::pending::<&'static str>),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+
+        let db = Arc::new(Database::memory().unwrap());
+        for id in ["primary", "hang", "cheap"] {
+            let provider = Provider::with_id(
+                id.to_string(),
+                id.to_string(),
+                serde_json::json!({
+                    "env": { "OPENAI_API_KEY": format!("sk-{id}") },
+                    "base_url": format!("http://{addr}/{id}/v1")
+                }),
+                None,
+            );
+            db.save_provider("codex", &provider).unwrap();
+        }
+        let primary = db.get_provider_by_id("primary", "codex").unwrap().unwrap();
+        let router = Arc::new(ProviderRouter::new(db.clone()));
+        let forwarder_with_shadow = |provider_id: &str| {
+            RequestForwarder::new(
+                router.clone(),
+                db.clone(),
+                30,
+                0,
+                Arc::new(RwLock::new(ProxyStatus::default())),
+                Arc::new(RwLock::new(std::collections::HashMap::new())),
+                Arc::new(FailoverSwitchManager::new(db.clone())),
+                None,
+                String::new(),
+                0,
+                0,
+            )
+            .with_shadow(Some(ShadowConfig {
+                provider_id: provider_id.to_string(),
+                sample_percent: 100,
+            }))
+        };
+        let headers = axum::http::HeaderMap::new();
+        let body = serde_json::json!({"model": "gpt-5", "input": "hi"});
+        let shadow_rows = |provider_id: &str| -> i64 {
+            let conn = db.conn.lock().unwrap();
+            conn.query_row(
+                "SELECT COUNT(*) FROM proxy_request_logs WHERE provider_id = ?1 AND shadow = 1",
+                [provider_id],
+                |r| r.get(0),
+            )
+            .unwrap()
+        };
+
+        // 影子上游挂起：主请求照常返回
+        let result = tokio::time::timeout(
+            Duration::from_secs(5),
+            forwarder_with_shadow("hang").forward_with_retry(
+                &AppType::Codex,
+                "/v1/responses",
+                body.clone(),
+                headers.clone(),
+                vec![primary.clone()],
+                &mut RequestTrace::from_headers(&headers),
+            ),
+        )
+        .await
+        .expect("影子请求挂起不应阻塞主请求")
+        .map_err(|e| e.error.to_string())
+        .unwrap();
+        assert_eq!(result.provider.id, "primary");
+        assert_eq!(result.response.status().as_u16(), 200);
+        assert_eq!(shadow_rows("hang"), 0);
+        assert!(shadow::in_flight() >= 1);
+        let hang_breaker = router.get_circuit_breaker_stats("hang", "codex").await;
+        assert!(hang_breaker.is_none_or(|stats| stats.total_requests == 0));
+
+        // 正常的影子上游：结果写入请求日志并标记 shadow
+        forwarder_with_shadow("cheap")
+            .forward_with_retry(
+                &AppType::Codex,
+                "/v1/responses",
+                body.clone(),
+                headers.clone(),
+                vec![primary.clone()],
+                &mut RequestTrace::from_headers(&headers),
+            )
+            .await
+            .map_err(|e| e.error.to_string())
+            .unwrap();
+        for _ in 0..100 {
+            if shadow_rows("cheap") > 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(shadow_rows("cheap"), 1);
+        let cheap_breaker = router.get_circuit_breaker_stats("cheap", "codex").await;
+        assert!(cheap_breaker.is_none_or(|stats| stats.total_requests == 0));
+
+        // 流式请求不镜像
+        let streaming = serde_json::json!({"model": "gpt-5", "input": "hi", "stream": true});
+        forwarder_with_shadow("cheap")
+            .forward_with_retry(
+                &AppType::Codex,
+                "/v1/responses",
+                streaming,
+                headers.clone(),
+                vec![primary],
+                &mut RequestTrace::from_headers(&headers),
+            )
+            .await
+            .map_err(|e| e.error.to_string())
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(shadow_rows("cheap"), 1);
+    }
+
+    #[tokio::test]
+    async fn test_max_tokens_cap_clamps_forwarded_body() {
+        use axum::{routing::post, Json, Router};
+
+        // 上游回显收到的输出上限
+        let app = Router::new().route(
+            "/v1/responses",
+            post(|Json(body): Json<Value>| async move {
+                Json(serde_json::json!({ "max_output_tokens": body.get("max_output_tokens") }))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+
+        let db = Arc::new(Database::memory().unwrap());
+        let provider = Provider::with_id(
+            "cap".to_string(),
+            "cap".to_string(),
+            serde_json::json!({
+                "env": { "OPENAI_API_KEY": "sk-cap" },
+                "base_url": format!("http://{addr}/v1"),
+                "maxTokensCap": 8192
+            }),
+            None,
+        );
+        let forwarder = RequestForwarder::new(
+            Arc::new(ProviderRouter::new(db.clone())),
+            db.clone(),
+            30,
+            0,
+            Arc::new(RwLock::new(ProxyStatus::default())),
+            Arc::new(RwLock::new(std::collections::HashMap::new())),
+            Arc::new(FailoverSwitchManager::new(db)),
+            None,
+            String::new(),
+            0,
+            0,
+        );
+        let adapter = get_adapter(&AppType::Codex);
+
+        for (requested, expected) in [(32000, 8192), (4096, 4096)] {
+            let body = serde_json::json!({
+                "model": "gpt-5",
+                "input": "hi",
+                "max_output_tokens": requested
+            });
+            let forwarded = forwarder
+                .forward(
+                    &provider,
+                    "/v1/responses",
+                    &body,
+                    &axum::http::HeaderMap::new(),
+                    adapter.as_ref(),
+                )
+                .await
+                .map_err(|e| e.to_string())
+                .unwrap();
+            let echoed: Value = forwarded.response.json().await.unwrap();
+            assert_eq!(echoed["max_output_tokens"], expected);
+        }
+    }
+}
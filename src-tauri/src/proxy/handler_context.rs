@@ -3,13 +3,90 @@
 //! 提供请求生命周期的上下文管理，封装通用初始化逻辑
 
 use crate::app_config::AppType;
+use crate::database::Database;
+use crate::error::AppError;
 use crate::provider::Provider;
 use crate::proxy::{
-    forwarder::RequestForwarder, server::ProxyState, types::AppProxyConfig, ProxyError,
+    bandwidth::json_body_size, forwarder::RequestForwarder, model_mapper::has_thinking_enabled,
+    provider_router::ProviderPinGuard, server::ProxyState, types::AppProxyConfig, ProxyError,
 };
 use crate::proxy::model_sanitizer::sanitize_gpt_model_name;
+use crate::proxy::usage::ModelMapping;
+use std::future::Future;
 use std::time::Instant;
 
+tokio::task_local! {
+    /// 当前请求的配置快照（仅在 [`RequestScope::run`] 内可见）
+    static REQUEST_SCOPE: RequestScope;
+}
+
+/// 单请求配置快照
+///
+/// 请求开始时读取一次应用级代理配置与当前供应商；请求内 ProviderRouter 的故障转移判断、
+/// 熔断阈值、熔断器创建等优先使用快照，不再反复读库。快照之外（测速、CLI、后台任务）照常读库。
+/// 每个请求重新加载，热重载后的配置从下一个请求起生效。
+#[derive(Debug, Clone)]
+pub struct RequestScope {
+    app_type: String,
+    app_config: AppProxyConfig,
+    current_provider_id: Option<String>,
+    /// 本请求被固定到的供应商（`csc replay` / `csc compare`，见 [`ProviderRouter::claim_provider_pin`]）
+    ///
+    /// [`ProviderRouter::claim_provider_pin`]: crate::proxy::provider_router::ProviderRouter::claim_provider_pin
+    pinned_provider_id: Option<String>,
+}
+
+impl RequestScope {
+    /// 从数据库加载快照
+    pub async fn load(db: &Database, app_type: &str) -> Result<Self, AppError> {
+        Ok(Self {
+            app_type: app_type.to_string(),
+            app_config: db.get_proxy_config_for_app(app_type).await?,
+            current_provider_id: db.get_current_provider(app_type)?,
+            pinned_provider_id: None,
+        })
+    }
+
+    /// 将本请求固定到指定供应商
+    pub fn with_pinned_provider(mut self, provider_id: Option<String>) -> Self {
+        self.pinned_provider_id = provider_id;
+        self
+    }
+
+    /// 在快照内执行（快照随 future 传递，跨 await 有效；spawn 出去的任务不继承）
+    pub async fn run<F: Future>(&self, fut: F) -> F::Output {
+        REQUEST_SCOPE.scope(self.clone(), fut).await
+    }
+
+    /// 当前快照中该应用的代理配置（不在快照内或应用不同时为 None）
+    pub fn app_config(app_type: &str) -> Option<AppProxyConfig> {
+        REQUEST_SCOPE
+            .try_with(|s| (s.app_type == app_type).then(|| s.app_config.clone()))
+            .ok()
+            .flatten()
+    }
+
+    /// 当前快照中该应用的当前供应商（外层 None 表示没有可用快照）
+    pub fn current_provider_id(app_type: &str) -> Option<Option<String>> {
+        REQUEST_SCOPE
+            .try_with(|s| (s.app_type == app_type).then(|| s.current_provider_id.clone()))
+            .ok()
+            .flatten()
+    }
+
+    /// 当前快照中本请求被固定到的供应商
+    pub fn pinned_provider_id(app_type: &str) -> Option<String> {
+        REQUEST_SCOPE
+            .try_with(|s| {
+                (s.app_type == app_type)
+                    .then(|| s.pinned_provider_id.clone())
+                    .flatten()
+            })
+            .ok()
+            .flatten()
+    }
+}
+
 /// 流式超时配置
 #[derive(Debug, Clone, Copy)]
 pub struct StreamingTimeoutConfig {
@@ -30,8 +107,12 @@ pub struct StreamingTimeoutConfig {
 pub struct RequestContext {
     /// 请求开始时间
     pub start_time: Instant,
+    /// 请求日志 ID（请求捕获使用同一 ID，供 `csc replay --from-log` 关联）
+    pub request_id: String,
     /// 应用级代理配置（per-app，包含重试次数和超时配置）
     pub app_config: AppProxyConfig,
+    /// 单请求配置快照（选路与转发在快照内执行，见 [`RequestScope`]）
+    pub scope: RequestScope,
     /// 选中的 Provider（故障转移链的第一个）
     pub provider: Provider,
     /// 完整的 Provider 列表（用于故障转移）
@@ -43,6 +124,12 @@ pub struct RequestContext {
     pub current_provider_id: String,
     /// 请求中的模型名称
     pub request_model: String,
+    /// 实际发往供应商的模型（模型映射/智能解析之后，转发成功后回填）
+    pub effective_model: Option<String>,
+    /// Python 代理回传的真实上游耗时（仅 Claude，转发成功后回填）
+    pub upstream_ms: Option<u64>,
+    /// 客户端请求体字节数（流量计量）
+    pub request_bytes: u64,
     /// 日志标签（如 "Claude"、"Codex"、"Gemini"）
     pub tag: &'static str,
     /// 应用类型字符串（如 "claude"、"codex"、"gemini"）
@@ -50,6 +137,10 @@ pub struct RequestContext {
     /// 应用类型（预留，目前通过 app_type_str 使用）
     #[allow(dead_code)]
     pub app_type: AppType,
+    /// 是否为启动测速流量（CLI 测试覆盖生效期间的请求，或按请求固定供应商的重放请求）
+    pub is_startup_test: bool,
+    /// 本请求认领的供应商固定，上下文析构时清理
+    _provider_pin: Option<ProviderPinGuard>,
 }
 
 impl RequestContext {
@@ -61,6 +152,7 @@ impl RequestContext {
     /// * `app_type` - 应用类型
     /// * `tag` - 日志标签
     /// * `app_type_str` - 应用类型字符串
+    /// * `request_id` - 入口确定的请求 ID（与响应头、日志一致）
     ///
     /// # Errors
     /// 返回 `ProxyError` 如果 Provider 选择失败
@@ -70,15 +162,24 @@ impl RequestContext {
         app_type: AppType,
         tag: &'static str,
         app_type_str: &'static str,
+        request_id: &str,
     ) -> Result<Self, ProxyError> {
         let start_time = Instant::now();
 
-        // 从数据库读取应用级代理配置（per-app）
-        let app_config = state
-            .db
-            .get_proxy_config_for_app(app_type_str)
+        // 针对本请求的供应商固定（`csc replay` / `csc compare`）；guard 随上下文析构，任一退出路径都会清理
+        let provider_pin = state
+            .provider_router
+            .claim_provider_pin(app_type_str, request_id);
+
+        // 从数据库读取应用级代理配置（per-app）与当前供应商，整个请求只读一次
+        // 数据库暂不可用（锁定/损坏）时使用最近一次成功加载的快照
+        let scope = state
+            .provider_router
+            .load_request_scope(app_type_str)
             .await
-            .map_err(|e| ProxyError::DatabaseError(e.to_string()))?;
+            .map_err(|e| ProxyError::DatabaseError(e.to_string()))?
+            .with_pinned_provider(provider_pin.as_ref().map(|p| p.provider_id().to_string()));
+        let app_config = scope.app_config.clone();
 
         let current_provider_id =
             crate::settings::get_current_provider(&app_type).unwrap_or_default();
@@ -90,20 +191,39 @@ impl RequestContext {
             .unwrap_or("unknown")
             .to_string();
         let request_model = sanitize_gpt_model_name(&request_model_raw);
+        let wants_thinking = has_thinking_enabled(body);
 
         // 使用共享的 ProviderRouter 选择 Provider（熔断器状态跨请求保持）
         // 注意：只在这里调用一次，结果传递给 forwarder，避免重复消耗 HalfOpen 名额
-        let providers = state
-            .provider_router
-            .select_providers(app_type_str, Some(&request_model))
+        let providers = match scope
+            .run(state.provider_router.select_providers(
+                app_type_str,
+                Some(&request_model),
+                wants_thinking,
+            ))
             .await
-            .map_err(|e| ProxyError::DatabaseError(e.to_string()))?;
+        {
+            Ok(providers) => providers,
+            Err(e) => {
+                // 供应商配置缺失需要用户处理：写入状态的 last_error，状态页与 `csc proxy status` 可见
+                if e.is_provider_setup_missing() {
+                    state.status.write().await.last_error = Some(e.to_string());
+                }
+                return Err(ProxyError::from_selection_error(e));
+            }
+        };
 
         let provider = providers
             .first()
             .cloned()
             .ok_or(ProxyError::NoAvailableProvider)?;
 
+        let is_startup_test = provider_pin.is_some()
+            || state
+                .provider_router
+                .has_active_test_override(app_type_str)
+                .await;
+
         log::debug!(
             "[{}] Provider: {}, model: {}, failover chain: {} providers",
             tag,
@@ -114,14 +234,21 @@ impl RequestContext {
 
         Ok(Self {
             start_time,
+            request_id: request_id.to_string(),
             app_config,
+            scope,
             provider,
             providers,
             current_provider_id,
             request_model,
+            effective_model: None,
+            upstream_ms: None,
+            request_bytes: json_body_size(body),
             tag,
             app_type_str,
             app_type,
+            is_startup_test,
+            _provider_pin: provider_pin,
         })
     }
 
@@ -164,6 +291,8 @@ impl RequestContext {
             self.app_config.streaming_first_byte_timeout as u64,
             self.app_config.streaming_idle_timeout as u64,
         )
+        .with_default_model(self.app_config.default_model.clone())
+        .with_shadow(self.app_config.shadow.clone())
     }
 
     /// 获取 Provider 列表（用于故障转移）
@@ -179,6 +308,14 @@ impl RequestContext {
         self.start_time.elapsed().as_millis() as u64
     }
 
+    /// 请求模型 → 实际模型（写入请求日志）
+    pub fn model_mapping(&self) -> ModelMapping {
+        ModelMapping {
+            requested: Some(self.request_model.clone()).filter(|m| m != "unknown"),
+            effective: self.effective_model.clone(),
+        }
+    }
+
     /// 获取流式超时配置
     #[inline]
     pub fn streaming_timeout_config(&self) -> StreamingTimeoutConfig {
@@ -8,16 +8,24 @@
 //! - Claude 的格式转换逻辑保留在此文件（用于 OpenRouter 旧接口回退）
 
 use super::{
+    attribution,
+    bandwidth::{self, ByteCounter},
+    capture, dry_run,
     error_mapper::{get_error_message, map_proxy_error_to_status},
     handler_config::{
         CLAUDE_PARSER_CONFIG, CODEX_PARSER_CONFIG, GEMINI_PARSER_CONFIG, OPENAI_PARSER_CONFIG,
     },
     handler_context::RequestContext,
+    inflight::RequestMeta,
+    model_warmup::{ModelWarmup, WarmupOutcome},
+    provider_router::{ProviderRouter, MAX_FAILOVER_PAUSE_SECS},
     providers::{get_adapter, streaming::create_anthropic_sse_stream, transform},
+    request_trace::RequestTrace,
     response_processor::{create_logged_passthrough_stream, process_response, SseUsageCollector},
     server::ProxyState,
     types::*,
-    usage::parser::TokenUsage,
+    update_check::VersionInfo,
+    usage::{parser::TokenUsage, ModelMapping, TransferBytes},
     ProxyError,
 };
 use crate::app_config::AppType;
@@ -182,18 +190,87 @@ pub async fn health_check() -> (StatusCode, Json<Value>) {
     )
 }
 
+/// 版本信息：当前版本、构建时的 git hash 与数据库 schema 版本
+pub async fn version() -> Json<VersionInfo> {
+    Json(VersionInfo::current())
+}
+
 /// 获取服务状态
 pub async fn get_status(State(state): State<ProxyState>) -> Result<Json<ProxyStatus>, ProxyError> {
     let status = state.status.read().await.clone();
     Ok(Json(status))
 }
 
+/// 最近请求（最新在前，最多 50 条）
+pub async fn recent_requests(
+    State(state): State<ProxyState>,
+) -> Json<Vec<super::inflight::RecentRequest>> {
+    Json(state.inflight.recent_requests())
+}
+
+/// 管理状态：状态快照 + 进程信息 + 各 app 自启动以来的请求统计
+pub async fn admin_status(State(state): State<ProxyState>) -> Result<Json<AdminStatus>, ProxyError> {
+    let status = state.snapshot_status().await;
+
+    let since = chrono::Utc::now().timestamp() - status.uptime_seconds as i64;
+    let counts = state.db.get_request_counts_by_app(since).unwrap_or_else(|e| {
+        log::warn!("读取请求统计失败: {e}");
+        Vec::new()
+    });
+
+    let gate = state.app_gate.current();
+    let mut apps: Vec<AppStatusSummary> = ["claude", "codex", "gemini"]
+        .iter()
+        .map(|app_type| {
+            let count = counts.iter().find(|c| c.app_type == *app_type);
+            let total_requests = count.map(|c| c.total).unwrap_or(0);
+            let success_requests = count.map(|c| c.success).unwrap_or(0);
+            let target = status
+                .active_targets
+                .iter()
+                .find(|t| t.app_type.eq_ignore_ascii_case(app_type));
+            AppStatusSummary {
+                app_type: app_type.to_string(),
+                total_requests,
+                success_requests,
+                success_rate: if total_requests > 0 {
+                    success_requests as f32 / total_requests as f32 * 100.0
+                } else {
+                    0.0
+                },
+                current_provider_id: target.map(|t| t.provider_id.clone()),
+                current_provider_name: target.map(|t| t.provider_name.clone()),
+                disabled: !gate.allows(app_type),
+                rejected_disabled: state.app_gate.rejected_disabled(app_type),
+            }
+        })
+        .collect();
+    apps.retain(|a| {
+        a.total_requests > 0
+            || a.current_provider_id.is_some()
+            || a.disabled
+            || a.rejected_disabled > 0
+    });
+
+    Ok(Json(AdminStatus {
+        status,
+        pid: std::process::id(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        apps,
+        router: state.provider_router.memory_stats().await,
+    }))
+}
+
 #[derive(Debug, Deserialize)]
 pub struct BenchmarkRequest {
     pub app_type: String,
     pub model: Option<String>,
     pub only_priority: Option<usize>,
     pub only_supplier: Option<String>,
+    /// 以 NDJSON 流式返回进度：每个 supplier 的 `supplier_started` / `supplier_finished`，
+    /// 最后一行为 `done`（或 `error`）；客户端断开连接即取消测速
+    #[serde(default)]
+    pub stream: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -206,7 +283,7 @@ pub struct BenchmarkResponse {
 pub async fn benchmark_all_suppliers(
     State(state): State<ProxyState>,
     Json(req): Json<BenchmarkRequest>,
-) -> Result<Json<BenchmarkResponse>, ProxyError> {
+) -> Result<axum::response::Response, ProxyError> {
     let app_type = req.app_type.trim().to_lowercase();
     if !matches!(app_type.as_str(), "claude" | "codex" | "gemini") {
         return Err(ProxyError::InvalidRequest(format!(
@@ -215,17 +292,16 @@ pub async fn benchmark_all_suppliers(
         )));
     }
 
-    let model = req
-        .model
-        .as_deref()
-        .unwrap_or_else(|| match app_type.as_str() {
-            "claude" => "claude-sonnet-4-5-20250929",
-            "codex" => "gpt-5.2",
-            "gemini" => "gemini-2.0-flash",
-            _ => "unknown",
-        })
-        .to_string();
+    let model = match req.model.as_deref().map(str::trim) {
+        Some(model) if !model.is_empty() => model.to_string(),
+        _ => state.provider_router.app_probe_model(&app_type).await,
+    };
+
+    if req.stream {
+        return Ok(stream_benchmark(state, app_type, model, req));
+    }
 
+    // 非流式：客户端断开时 handler future 被丢弃，进行中的探测随之中止
     let results = state
         .provider_router
         .benchmark_all_suppliers(
@@ -233,6 +309,8 @@ pub async fn benchmark_all_suppliers(
             &model,
             req.only_priority,
             req.only_supplier.as_deref(),
+            &tokio_util::sync::CancellationToken::new(),
+            &|_| {},
         )
         .await
         .map_err(|e| ProxyError::Internal(format!("测速失败: {e}")))?;
@@ -241,16 +319,75 @@ pub async fn benchmark_all_suppliers(
         app_type,
         model,
         results,
-    }))
+    })
+    .into_response())
+}
+
+/// 流式测速：后台任务执行测速并把进度事件逐行写出（NDJSON）
+///
+/// 响应体持有取消令牌的 drop guard，客户端断开（响应体被丢弃）时取消测速。
+fn stream_benchmark(
+    state: ProxyState,
+    app_type: String,
+    model: String,
+    req: BenchmarkRequest,
+) -> axum::response::Response {
+    let cancel = tokio_util::sync::CancellationToken::new();
+    let guard = cancel.clone().drop_guard();
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<Value>();
+
+    tokio::spawn(async move {
+        let progress_tx = tx.clone();
+        let result = state
+            .provider_router
+            .benchmark_all_suppliers(
+                &app_type,
+                &model,
+                req.only_priority,
+                req.only_supplier.as_deref(),
+                &cancel,
+                &move |event| {
+                    let _ = progress_tx.send(json!(event));
+                },
+            )
+            .await;
+        let last = match result {
+            Ok(results) => json!({
+                "event": "done",
+                "app_type": app_type,
+                "model": model,
+                "cancelled": cancel.is_cancelled(),
+                "results": results,
+            }),
+            Err(e) => json!({ "event": "error", "message": format!("测速失败: {e}") }),
+        };
+        let _ = tx.send(last);
+    });
+
+    let body = async_stream::stream! {
+        let _guard = guard;
+        while let Some(line) = rx.recv().await {
+            yield Ok::<_, std::io::Error>(bytes::Bytes::from(format!("{line}\n")));
+        }
+    };
+    (
+        [(axum::http::header::CONTENT_TYPE, "application/x-ndjson")],
+        axum::body::Body::from_stream(body),
+    )
+        .into_response()
 }
 
 #[derive(Debug, Deserialize)]
 pub struct TestOverrideStartRequest {
     pub app_type: String,
+    #[serde(default)]
     pub priority: usize,
+    #[serde(default)]
     pub supplier: String,
     /// 可选：固定到某个 base_url（用于逐 URL 做“真实启动链路”全链路测速）
     pub base_url: Option<String>,
+    /// 可选：固定到单个供应商（`csc replay`），指定后忽略 priority/supplier/base_url
+    pub provider_id: Option<String>,
     pub run_id: String,
     pub ttl_secs: Option<u64>,
 }
@@ -274,12 +411,32 @@ pub async fn start_test_override(
         )));
     }
 
+    let ttl_secs = req.ttl_secs.unwrap_or(20).clamp(5, 120);
+
+    if let Some(provider_id) = req.provider_id.as_deref().map(str::trim) {
+        let provider = state
+            .db
+            .get_provider_by_id(provider_id, &app_type)
+            .map_err(|e| ProxyError::DatabaseError(e.to_string()))?
+            .ok_or_else(|| ProxyError::InvalidRequest(format!("供应商不存在: {provider_id}")))?;
+        state.provider_router.pin_provider_for_request(
+            &app_type,
+            &provider.id,
+            &req.run_id,
+            ttl_secs,
+        );
+        return Ok(Json(TestOverrideStartResponse {
+            ok: true,
+            run_id: req.run_id,
+            ttl_secs,
+        }));
+    }
+
     let supplier = req.supplier.trim();
     if supplier.is_empty() {
         return Err(ProxyError::InvalidRequest("supplier不能为空".to_string()));
     }
 
-    let ttl_secs = req.ttl_secs.unwrap_or(20).clamp(5, 120);
     state
         .provider_router
         .set_test_override(
@@ -316,6 +473,383 @@ pub async fn get_test_result(
     }))
 }
 
+#[derive(Debug, Deserialize)]
+pub struct BreakerResetRequest {
+    pub app_type: String,
+    pub provider_id: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BreakerResetResponse {
+    pub ok: bool,
+}
+
+pub async fn reset_breaker(
+    State(state): State<ProxyState>,
+    Json(req): Json<BreakerResetRequest>,
+) -> Result<Json<BreakerResetResponse>, ProxyError> {
+    let app_type = req.app_type.trim().to_lowercase();
+    if !matches!(app_type.as_str(), "claude" | "codex" | "gemini") {
+        return Err(ProxyError::InvalidRequest(format!(
+            "无效app_type: {}",
+            req.app_type
+        )));
+    }
+
+    let provider_id = req.provider_id.trim();
+    if provider_id.is_empty() {
+        return Err(ProxyError::InvalidRequest("provider_id不能为空".to_string()));
+    }
+
+    state
+        .provider_router
+        .reset_provider_breaker(provider_id, &app_type)
+        .await;
+    if let Err(e) = state.db.reset_provider_health(provider_id, &app_type).await {
+        log::warn!("[{app_type}] 重置健康状态失败: {provider_id} - {e}");
+    }
+    log::info!("[{app_type}] 已手动重置熔断器: {provider_id}");
+
+    Ok(Json(BreakerResetResponse { ok: true }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BreakerForceOpenRequest {
+    pub app_type: String,
+    pub provider_id: String,
+    /// 强制熔断时长（秒）
+    pub secs: u64,
+}
+
+/// 手动强制打开熔断器（已知上游故障时预先下线供应商；解除使用 `/__cc_switch/breaker/reset`）
+pub async fn force_open_breaker(
+    State(state): State<ProxyState>,
+    Json(req): Json<BreakerForceOpenRequest>,
+) -> Result<Json<BreakerResetResponse>, ProxyError> {
+    let app_type = req.app_type.trim().to_lowercase();
+    if !matches!(app_type.as_str(), "claude" | "codex" | "gemini") {
+        return Err(ProxyError::InvalidRequest(format!(
+            "无效app_type: {}",
+            req.app_type
+        )));
+    }
+    if req.secs == 0 {
+        return Err(ProxyError::InvalidRequest("secs必须大于0".to_string()));
+    }
+
+    let provider_id = req.provider_id.trim();
+    let exists = state
+        .db
+        .get_provider_by_id(provider_id, &app_type)
+        .map_err(|e| ProxyError::DatabaseError(e.to_string()))?
+        .is_some();
+    if !exists {
+        return Err(ProxyError::InvalidRequest(format!(
+            "供应商不存在: {provider_id}"
+        )));
+    }
+
+    state
+        .provider_router
+        .force_open_provider(&app_type, provider_id, req.secs)
+        .await;
+
+    Ok(Json(BreakerResetResponse { ok: true }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FailoverPauseRequest {
+    pub app_type: String,
+    /// 暂停时长（秒）
+    pub secs: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FailoverPauseResponse {
+    pub ok: bool,
+    #[serde(flatten)]
+    pub pause: FailoverPause,
+    /// 持久化的故障转移开关；关闭时暂停没有实际效果
+    pub auto_failover_enabled: bool,
+}
+
+/// 临时暂停故障转移（维护窗口内只使用当前供应商，到期自动恢复）
+pub async fn pause_failover(
+    State(state): State<ProxyState>,
+    Json(req): Json<FailoverPauseRequest>,
+) -> Result<Json<FailoverPauseResponse>, ProxyError> {
+    let app_type = req.app_type.trim().to_lowercase();
+    if !matches!(app_type.as_str(), "claude" | "codex" | "gemini") {
+        return Err(ProxyError::InvalidRequest(format!(
+            "无效app_type: {}",
+            req.app_type
+        )));
+    }
+    if req.secs == 0 {
+        return Err(ProxyError::InvalidRequest("secs必须大于0".to_string()));
+    }
+    if req.secs > MAX_FAILOVER_PAUSE_SECS {
+        return Err(ProxyError::InvalidRequest(format!(
+            "secs不能超过{MAX_FAILOVER_PAUSE_SECS}（7 天）"
+        )));
+    }
+
+    let auto_failover_enabled = state
+        .db
+        .get_proxy_config_for_app(&app_type)
+        .await
+        .map_err(|e| ProxyError::DatabaseError(e.to_string()))?
+        .auto_failover_enabled;
+    let until = state
+        .provider_router
+        .pause_failover(&app_type, std::time::Duration::from_secs(req.secs));
+
+    Ok(Json(FailoverPauseResponse {
+        ok: true,
+        pause: FailoverPause {
+            app_type,
+            until,
+            remaining_secs: req.secs,
+        },
+        auto_failover_enabled,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FailoverResumeRequest {
+    pub app_type: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FailoverResumeResponse {
+    pub ok: bool,
+    /// 恢复前是否处于暂停中
+    pub was_paused: bool,
+}
+
+/// 提前恢复故障转移
+pub async fn resume_failover(
+    State(state): State<ProxyState>,
+    Json(req): Json<FailoverResumeRequest>,
+) -> Result<Json<FailoverResumeResponse>, ProxyError> {
+    let app_type = req.app_type.trim().to_lowercase();
+    if !matches!(app_type.as_str(), "claude" | "codex" | "gemini") {
+        return Err(ProxyError::InvalidRequest(format!(
+            "无效app_type: {}",
+            req.app_type
+        )));
+    }
+
+    let was_paused = state.provider_router.resume_failover(&app_type);
+    Ok(Json(FailoverResumeResponse {
+        ok: true,
+        was_paused,
+    }))
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConfigReloadResponse {
+    pub ok: bool,
+    /// 已热更新配置的熔断器数量
+    pub breakers_updated: usize,
+}
+
+/// 从数据库重新加载可热更新的配置（供 CLI 导入配置后调用）
+///
+/// 超时/重试/故障转移开关/队列/首选 URL 均在每次请求时读取数据库，无需处理；
+/// 已创建的熔断器需要主动刷新阈值，应用代理开关需要推送到 watch 通道。
+pub async fn reload_config(
+    State(state): State<ProxyState>,
+) -> Result<Json<ConfigReloadResponse>, ProxyError> {
+    let mut breakers_updated = 0;
+    for app_type in ["claude", "codex", "gemini"] {
+        breakers_updated += state
+            .provider_router
+            .reload_app_breaker_configs(app_type)
+            .await
+            .map_err(|e| ProxyError::DatabaseError(e.to_string()))?;
+    }
+    state
+        .app_gate
+        .reload(&state.db)
+        .await
+        .map_err(|e| ProxyError::DatabaseError(e.to_string()))?;
+    log::info!("已重新加载配置（熔断器 {breakers_updated} 个）");
+
+    Ok(Json(ConfigReloadResponse {
+        ok: true,
+        breakers_updated,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QueueInvalidateRequest {
+    pub app_type: String,
+    pub suppliers: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QueueInvalidateResponse {
+    pub ok: bool,
+    pub cleared: usize,
+}
+
+/// 队列层级调整后（`csc queue reorder`）：清除受影响 supplier 的“已测试”与当前 URL 状态
+pub async fn invalidate_queue(
+    State(state): State<ProxyState>,
+    Json(req): Json<QueueInvalidateRequest>,
+) -> Result<Json<QueueInvalidateResponse>, ProxyError> {
+    let app_type = req.app_type.trim().to_lowercase();
+    if !matches!(app_type.as_str(), "claude" | "codex" | "gemini") {
+        return Err(ProxyError::InvalidRequest(format!(
+            "无效app_type: {}",
+            req.app_type
+        )));
+    }
+
+    let cleared = state
+        .provider_router
+        .invalidate_suppliers(&app_type, &req.suppliers)
+        .await;
+    log::info!(
+        "[{app_type}] 队列层级已调整，清除 {} 个 supplier 的选路缓存 {cleared} 条",
+        req.suppliers.len()
+    );
+
+    Ok(Json(QueueInvalidateResponse { ok: true, cleared }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ModelWarmupRequest {
+    pub app_type: String,
+    pub provider_id: String,
+}
+
+/// 保存供应商后（`csc add`）：在代理进程内预热模型列表，使缓存对后续请求生效
+pub async fn warm_up_models(
+    State(state): State<ProxyState>,
+    Json(req): Json<ModelWarmupRequest>,
+) -> Result<Json<WarmupOutcome>, ProxyError> {
+    let app_type = req.app_type.trim().to_lowercase();
+    if !matches!(app_type.as_str(), "claude" | "codex" | "gemini") {
+        return Err(ProxyError::InvalidRequest(format!(
+            "无效app_type: {}",
+            req.app_type
+        )));
+    }
+
+    let outcome = ModelWarmup::for_proxy(&state)
+        .run(&app_type, &req.provider_id)
+        .await;
+    Ok(Json(outcome))
+}
+
+/// 配置审计（在代理进程内执行，跨家族映射可基于已缓存的模型列表给出修正建议）
+pub async fn admin_audit(
+    State(state): State<ProxyState>,
+    axum::extract::Path(app_type): axum::extract::Path<String>,
+) -> Result<Json<crate::database::ProviderAuditReport>, ProxyError> {
+    let app_type = app_type.trim().to_lowercase();
+    if !matches!(app_type.as_str(), "claude" | "codex" | "gemini") {
+        return Err(ProxyError::InvalidRequest(format!(
+            "无效app_type: {app_type}"
+        )));
+    }
+
+    let report = state
+        .db
+        .audit_providers(&app_type)
+        .map_err(|e| ProxyError::DatabaseError(e.to_string()))?;
+    Ok(Json(report))
+}
+
+/// 各供应商的熔断器状态（仅包含已创建熔断器的供应商）
+pub async fn admin_circuit_breakers(
+    State(state): State<ProxyState>,
+    axum::extract::Path(app_type): axum::extract::Path<String>,
+) -> Result<Json<Vec<crate::proxy::ProviderCircuitBreakerStats>>, ProxyError> {
+    let app_type = app_type.trim().to_lowercase();
+    if !matches!(app_type.as_str(), "claude" | "codex" | "gemini") {
+        return Err(ProxyError::InvalidRequest(format!(
+            "无效app_type: {app_type}"
+        )));
+    }
+
+    let stats = state
+        .provider_router
+        .get_all_circuit_breaker_stats(&app_type)
+        .await
+        .into_iter()
+        .map(
+            |(provider_id, stats)| crate::proxy::ProviderCircuitBreakerStats { provider_id, stats },
+        )
+        .collect();
+    Ok(Json(stats))
+}
+
+/// `/admin/events/:app_type` 查询参数
+#[derive(Debug, Default, Deserialize)]
+pub struct AdminEventsQuery {
+    /// 事件类型（如 `breaker_open`），缺省为全部
+    pub kind: Option<String>,
+    /// 起始时间（Unix 秒），缺省为 24 小时前
+    pub since: Option<i64>,
+    /// 最多返回条数，缺省 200
+    pub limit: Option<usize>,
+}
+
+/// 路由事件日志（按时间倒序，供 UI 时间线展示）
+pub async fn admin_events(
+    State(state): State<ProxyState>,
+    axum::extract::Path(app_type): axum::extract::Path<String>,
+    axum::extract::Query(query): axum::extract::Query<AdminEventsQuery>,
+) -> Result<Json<Vec<crate::database::EventLogEntry>>, ProxyError> {
+    let app_type = app_type.trim().to_lowercase();
+    if !matches!(app_type.as_str(), "claude" | "codex" | "gemini") {
+        return Err(ProxyError::InvalidRequest(format!(
+            "无效app_type: {app_type}"
+        )));
+    }
+    let kind = query
+        .kind
+        .as_deref()
+        .map(crate::database::EventKind::from_str)
+        .transpose()
+        .map_err(|e| ProxyError::InvalidRequest(e.to_string()))?;
+    let since = query
+        .since
+        .unwrap_or_else(|| chrono::Utc::now().timestamp() - 24 * 3600);
+
+    // 先写入排队中的事件，保证刚发生的切换可见
+    state.provider_router.event_recorder().flush().await;
+    let events = state
+        .db
+        .query_events(&app_type, kind, since, query.limit.unwrap_or(200).min(1000))
+        .map_err(|e| ProxyError::DatabaseError(e.to_string()))?;
+    Ok(Json(events))
+}
+
+/// Prometheus 指标：各应用供应商的熔断器状态、窗口错误率与拒绝计数
+pub async fn metrics(State(state): State<ProxyState>) -> impl IntoResponse {
+    let mut entries = Vec::new();
+    for app_type in ["claude", "codex", "gemini"] {
+        for (provider_id, stats) in state
+            .provider_router
+            .get_all_circuit_breaker_stats(app_type)
+            .await
+        {
+            entries.push((app_type.to_string(), provider_id, stats));
+        }
+    }
+    (
+        [(
+            axum::http::header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        )],
+        crate::proxy::circuit_breaker::render_prometheus_metrics(&entries),
+    )
+}
+
 // ============================================================================
 // Claude API 处理器（包含格式转换逻辑）
 // ============================================================================
@@ -330,12 +864,8 @@ pub async fn handle_messages(
     headers: axum::http::HeaderMap,
     Json(body): Json<Value>,
 ) -> Result<axum::response::Response, ProxyError> {
-    let request_id = headers
-        .get("x-request-id")
-        .and_then(|v| v.to_str().ok())
-        .filter(|s| !s.trim().is_empty())
-        .map(|s| s.to_string())
-        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    // 与响应头 x-cc-switch-request-id、请求日志行使用同一个 ID
+    let request_id = attribution::request_id_from_headers(&headers);
 
     let user_agent = headers
         .get("user-agent")
@@ -374,18 +904,37 @@ pub async fn handle_messages(
         );
     }
 
-    let mut ctx = RequestContext::new(&state, &body, AppType::Claude, "Claude", "claude").await?;
+    // 演练模式：选路之前返回报告，不请求上游
+    if dry_run::is_dry_run(&headers) {
+        return Ok(
+            dry_run::respond(&state, &AppType::Claude, "/v1/messages", &body, &headers).await,
+        );
+    }
+
+    let mut ctx = RequestContext::new(
+        &state,
+        &body,
+        AppType::Claude,
+        "Claude",
+        "claude",
+        &request_id,
+    )
+    .await?;
+    capture::spawn_capture(&state, &ctx, "/v1/messages", &headers, &body);
 
     // 转发请求
     let forwarder = ctx.create_forwarder(&state);
-    let result = match forwarder
-        .forward_with_retry(
+    let mut trace = RequestTrace::from_headers(&headers);
+    let result = match ctx
+        .scope
+        .run(forwarder.forward_with_retry(
             &AppType::Claude,
             "/v1/messages",
             body.clone(),
             headers,
             ctx.get_providers(),
-        )
+            &mut trace,
+        ))
         .await
     {
         Ok(result) => result,
@@ -394,11 +943,13 @@ pub async fn handle_messages(
                 ctx.provider = provider;
             }
             log_forward_error(&state, &ctx, is_stream, &err.error);
-            return Err(err.error);
+            return finish_response(Err(err.error), &trace, &ctx);
         }
     };
 
     ctx.provider = result.provider;
+    ctx.effective_model = result.effective_model;
+    ctx.upstream_ms = result.upstream_ms;
     let response = result.response;
 
     if trace_requests {
@@ -440,11 +991,19 @@ pub async fn handle_messages(
 
     // Claude 特有：格式转换处理
     if needs_transform {
-        return handle_claude_transform(response, &ctx, &state, &body, is_stream).await;
+        return finish_response(
+            handle_claude_transform(response, &ctx, &state, &body, is_stream).await,
+            &trace,
+            &ctx,
+        );
     }
 
     // 通用响应处理（透传模式）
-    process_response(response, &ctx, &state, &CLAUDE_PARSER_CONFIG).await
+    finish_response(
+        process_response(response, &ctx, &state, &CLAUDE_PARSER_CONFIG).await,
+        &trace,
+        &ctx,
+    )
 }
 
 /// Claude 格式转换处理（独有逻辑）
@@ -463,35 +1022,50 @@ async fn handle_claude_transform(
         // 流式响应转换 (OpenAI SSE → Anthropic SSE)
         log::debug!("[Claude] 开始流式响应转换 (OpenAI SSE → Anthropic SSE)");
 
-        let stream = response.bytes_stream();
+        let response_bytes = ByteCounter::default();
+        let stream = bandwidth::count_stream(response.bytes_stream(), response_bytes.clone());
         let sse_stream = create_anthropic_sse_stream(stream);
 
         // 创建使用量收集器
         let usage_collector = {
             let state = state.clone();
+            let request_id = ctx.request_id.clone();
             let provider_id = ctx.provider.id.clone();
             let model = ctx.request_model.clone();
+            let mapping = ctx.model_mapping();
             let status_code = status.as_u16();
             let start_time = ctx.start_time;
+            let upstream_ms = ctx.upstream_ms;
+            let request_bytes = ctx.request_bytes;
 
             SseUsageCollector::new(start_time, move |events, first_token_ms| {
                 if let Some(usage) = TokenUsage::from_claude_stream_events(&events) {
                     let latency_ms = start_time.elapsed().as_millis() as u64;
+                    let bytes = TransferBytes {
+                        request: request_bytes,
+                        response: response_bytes.get(),
+                    };
                     let state = state.clone();
+                    let request_id = request_id.clone();
                     let provider_id = provider_id.clone();
                     let model = model.clone();
+                    let mapping = mapping.clone();
 
                     tokio::spawn(async move {
                         log_usage(
                             &state,
+                            request_id,
                             &provider_id,
                             "claude",
                             &model,
                             usage,
                             latency_ms,
                             first_token_ms,
+                            upstream_ms,
+                            bytes,
                             true,
                             status_code,
+                            mapping,
                         )
                         .await;
                     });
@@ -596,22 +1170,33 @@ async fn handle_claude_transform(
             .and_then(|m| m.as_str())
             .unwrap_or("unknown");
         let latency_ms = ctx.latency_ms();
+        let upstream_ms = ctx.upstream_ms;
+        let bytes = TransferBytes {
+            request: ctx.request_bytes,
+            response: body_bytes.len() as u64,
+        };
 
         tokio::spawn({
             let state = state.clone();
+            let request_id = ctx.request_id.clone();
             let provider_id = ctx.provider.id.clone();
             let model = model.to_string();
+            let mapping = ctx.model_mapping();
             async move {
                 log_usage(
                     &state,
+                    request_id,
                     &provider_id,
                     "claude",
                     &model,
                     usage,
                     latency_ms,
                     None,
+                    upstream_ms,
+                    bytes,
                     false,
                     status.as_u16(),
+                    mapping,
                 )
                 .await;
             }
@@ -655,18 +1240,13 @@ async fn handle_claude_transform(
 pub async fn handle_chat_completions(
     State(state): State<ProxyState>,
     headers: axum::http::HeaderMap,
-    Json(mut body): Json<Value>,
+    Json(body): Json<Value>,
 ) -> Result<axum::response::Response, ProxyError> {
-    // 禁止使用带日期后缀的 gpt-* 模型名（例如 gpt-5.2-2025-12-11）
-    // 该清洗发生在代理内部，不影响“正常可用模型”的请求。
-    let _ = crate::proxy::model_sanitizer::sanitize_openai_model_in_body(&mut body);
+    // gpt-* 的日期/版本后缀（例如 gpt-5.2-2025-12-11）在转发时按所选供应商的模型列表清洗：
+    // 截断后的名称存在才截断，避免误伤 gpt-5.2-1120-preview 这类真实模型。
 
-    let request_id = headers
-        .get("x-request-id")
-        .and_then(|v| v.to_str().ok())
-        .filter(|s| !s.trim().is_empty())
-        .map(|s| s.to_string())
-        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    // 与响应头 x-cc-switch-request-id、请求日志行使用同一个 ID
+    let request_id = attribution::request_id_from_headers(&headers);
 
     let user_agent = headers
         .get("user-agent")
@@ -732,7 +1312,21 @@ pub async fn handle_chat_completions(
         );
     }
 
-    let mut ctx = RequestContext::new(&state, &body, AppType::Codex, "Codex", "codex").await?;
+    // 演练模式：选路之前返回报告，不请求上游
+    if dry_run::is_dry_run(&headers) {
+        return Ok(dry_run::respond(
+            &state,
+            &AppType::Codex,
+            "/v1/chat/completions",
+            &body,
+            &headers,
+        )
+        .await);
+    }
+
+    let mut ctx =
+        RequestContext::new(&state, &body, AppType::Codex, "Codex", "codex", &request_id).await?;
+    capture::spawn_capture(&state, &ctx, "/v1/chat/completions", &headers, &body);
 
     log::debug!(
         "[Codex] 请求模型: {}, 流式: {}",
@@ -741,14 +1335,17 @@ pub async fn handle_chat_completions(
     );
 
     let forwarder = ctx.create_forwarder(&state);
-    let result = match forwarder
-        .forward_with_retry(
+    let mut trace = RequestTrace::from_headers(&headers);
+    let result = match ctx
+        .scope
+        .run(forwarder.forward_with_retry(
             &AppType::Codex,
             "/v1/chat/completions",
             body,
             headers,
             ctx.get_providers(),
-        )
+            &mut trace,
+        ))
         .await
     {
         Ok(result) => result,
@@ -757,11 +1354,12 @@ pub async fn handle_chat_completions(
                 ctx.provider = provider;
             }
             log_forward_error(&state, &ctx, is_stream, &err.error);
-            return Err(err.error);
+            return finish_response(Err(err.error), &trace, &ctx);
         }
     };
 
     ctx.provider = result.provider;
+    ctx.effective_model = result.effective_model;
     let response = result.response;
 
     log::debug!("[Codex] 上游响应状态: {}", response.status());
@@ -781,24 +1379,23 @@ pub async fn handle_chat_completions(
         );
     }
 
-    process_response(response, &ctx, &state, &OPENAI_PARSER_CONFIG).await
+    finish_response(
+        process_response(response, &ctx, &state, &OPENAI_PARSER_CONFIG).await,
+        &trace,
+        &ctx,
+    )
 }
 
 /// 处理 /v1/responses 请求（OpenAI Responses API - Codex CLI 透传）
 pub async fn handle_responses(
     State(state): State<ProxyState>,
     headers: axum::http::HeaderMap,
-    Json(mut body): Json<Value>,
+    Json(body): Json<Value>,
 ) -> Result<axum::response::Response, ProxyError> {
-    // 禁止使用带日期后缀的 gpt-* 模型名（例如 gpt-5.2-2025-12-11）
-    let _ = crate::proxy::model_sanitizer::sanitize_openai_model_in_body(&mut body);
+    // gpt-* 的日期/版本后缀在转发时按所选供应商的模型列表清洗（见 openai_model_resolver）
 
-    let request_id = headers
-        .get("x-request-id")
-        .and_then(|v| v.to_str().ok())
-        .filter(|s| !s.trim().is_empty())
-        .map(|s| s.to_string())
-        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    // 与响应头 x-cc-switch-request-id、请求日志行使用同一个 ID
+    let request_id = attribution::request_id_from_headers(&headers);
 
     let user_agent = headers
         .get("user-agent")
@@ -864,17 +1461,29 @@ pub async fn handle_responses(
         );
     }
 
-    let mut ctx = RequestContext::new(&state, &body, AppType::Codex, "Codex", "codex").await?;
+    // 演练模式：选路之前返回报告，不请求上游
+    if dry_run::is_dry_run(&headers) {
+        return Ok(
+            dry_run::respond(&state, &AppType::Codex, "/v1/responses", &body, &headers).await,
+        );
+    }
+
+    let mut ctx =
+        RequestContext::new(&state, &body, AppType::Codex, "Codex", "codex", &request_id).await?;
+    capture::spawn_capture(&state, &ctx, "/v1/responses", &headers, &body);
 
     let forwarder = ctx.create_forwarder(&state);
-    let result = match forwarder
-        .forward_with_retry(
+    let mut trace = RequestTrace::from_headers(&headers);
+    let result = match ctx
+        .scope
+        .run(forwarder.forward_with_retry(
             &AppType::Codex,
             "/v1/responses",
             body,
             headers,
             ctx.get_providers(),
-        )
+            &mut trace,
+        ))
         .await
     {
         Ok(result) => result,
@@ -883,11 +1492,12 @@ pub async fn handle_responses(
                 ctx.provider = provider;
             }
             log_forward_error(&state, &ctx, is_stream, &err.error);
-            return Err(err.error);
+            return finish_response(Err(err.error), &trace, &ctx);
         }
     };
 
     ctx.provider = result.provider;
+    ctx.effective_model = result.effective_model;
     let response = result.response;
 
     log::debug!("[Codex] 上游响应状态: {}", response.status());
@@ -907,7 +1517,11 @@ pub async fn handle_responses(
         );
     }
 
-    process_response(response, &ctx, &state, &CODEX_PARSER_CONFIG).await
+    finish_response(
+        process_response(response, &ctx, &state, &CODEX_PARSER_CONFIG).await,
+        &trace,
+        &ctx,
+    )
 }
 
 // ============================================================================
@@ -921,12 +1535,8 @@ pub async fn handle_gemini(
     headers: axum::http::HeaderMap,
     Json(body): Json<Value>,
 ) -> Result<axum::response::Response, ProxyError> {
-    let request_id = headers
-        .get("x-request-id")
-        .and_then(|v| v.to_str().ok())
-        .filter(|s| !s.trim().is_empty())
-        .map(|s| s.to_string())
-        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    // 与响应头 x-cc-switch-request-id、请求日志行使用同一个 ID
+    let request_id = attribution::request_id_from_headers(&headers);
 
     let user_agent = headers
         .get("user-agent")
@@ -960,10 +1570,26 @@ pub async fn handle_gemini(
         );
     }
 
+    // 演练模式：选路之前返回报告，不请求上游
+    if dry_run::is_dry_run(&headers) {
+        let endpoint = uri
+            .path_and_query()
+            .map(|pq| pq.as_str())
+            .unwrap_or(uri.path());
+        return Ok(dry_run::respond(&state, &AppType::Gemini, endpoint, &body, &headers).await);
+    }
+
     // Gemini 的模型名称在 URI 中
-    let mut ctx = RequestContext::new(&state, &body, AppType::Gemini, "Gemini", "gemini")
-        .await?
-        .with_model_from_uri(&uri);
+    let mut ctx = RequestContext::new(
+        &state,
+        &body,
+        AppType::Gemini,
+        "Gemini",
+        "gemini",
+        &request_id,
+    )
+    .await?
+    .with_model_from_uri(&uri);
 
     // 提取完整的路径和查询参数
     let endpoint = uri
@@ -972,16 +1598,20 @@ pub async fn handle_gemini(
         .unwrap_or(uri.path());
 
     log::debug!("[Gemini] 请求端点: {endpoint}");
+    capture::spawn_capture(&state, &ctx, endpoint, &headers, &body);
 
     let forwarder = ctx.create_forwarder(&state);
-    let result = match forwarder
-        .forward_with_retry(
+    let mut trace = RequestTrace::from_headers(&headers);
+    let result = match ctx
+        .scope
+        .run(forwarder.forward_with_retry(
             &AppType::Gemini,
             endpoint,
             body,
             headers,
             ctx.get_providers(),
-        )
+            &mut trace,
+        ))
         .await
     {
         Ok(result) => result,
@@ -990,11 +1620,12 @@ pub async fn handle_gemini(
                 ctx.provider = provider;
             }
             log_forward_error(&state, &ctx, is_stream, &err.error);
-            return Err(err.error);
+            return finish_response(Err(err.error), &trace, &ctx);
         }
     };
 
     ctx.provider = result.provider;
+    ctx.effective_model = result.effective_model;
     let response = result.response;
 
     log::debug!("[Gemini] 上游响应状态: {}", response.status());
@@ -1014,7 +1645,34 @@ pub async fn handle_gemini(
         );
     }
 
-    process_response(response, &ctx, &state, &GEMINI_PARSER_CONFIG).await
+    finish_response(
+        process_response(response, &ctx, &state, &GEMINI_PARSER_CONFIG).await,
+        &trace,
+        &ctx,
+    )
+}
+
+/// 收尾响应：写入请求元信息（供 inflight 中间件记录最近请求）与供应商归属头，
+/// 请求开启调试时把尝试追踪写入响应头（错误响应同样携带）
+fn finish_response(
+    result: Result<axum::response::Response, ProxyError>,
+    trace: &RequestTrace,
+    ctx: &RequestContext,
+) -> Result<axum::response::Response, ProxyError> {
+    let mut response = match result {
+        Ok(response) => response,
+        Err(err) => err.into_response(),
+    };
+    response.extensions_mut().insert(RequestMeta {
+        app_type: ctx.app_type_str.to_string(),
+        provider_id: ctx.provider.id.clone(),
+        provider_name: ctx.provider.name.clone(),
+        model: ctx.request_model.clone(),
+        startup_test: ctx.is_startup_test,
+    });
+    let base_url = ProviderRouter::extract_base_url(&ctx.provider, ctx.app_type_str);
+    attribution::attach_provider(&mut response, &ctx.provider.id, base_url.as_deref());
+    Ok(trace.attach(response))
 }
 
 // ============================================================================
@@ -1029,10 +1687,14 @@ fn log_forward_error(
 ) {
     use super::usage::logger::UsageLogger;
 
+    // 只读模式不写请求日志
+    if state.provider_router.is_read_only() {
+        return;
+    }
     let logger = UsageLogger::new(&state.db);
     let status_code = map_proxy_error_to_status(error);
     let error_message = get_error_message(error);
-    let request_id = uuid::Uuid::new_v4().to_string();
+    let request_id = ctx.request_id.clone();
 
     if let Err(e) = logger.log_error_with_context(
         request_id.clone(),
@@ -1045,6 +1707,7 @@ fn log_forward_error(
         is_streaming,
         Some(request_id),
         None,
+        ctx.model_mapping(),
     ) {
         log::warn!("记录失败请求日志失败: {e}");
     }
@@ -1054,36 +1717,39 @@ fn log_forward_error(
 #[allow(clippy::too_many_arguments)]
 async fn log_usage(
     state: &ProxyState,
+    request_id: String,
     provider_id: &str,
     app_type: &str,
     model: &str,
     usage: TokenUsage,
     latency_ms: u64,
     first_token_ms: Option<u64>,
+    upstream_ms: Option<u64>,
+    bytes: TransferBytes,
     is_streaming: bool,
     status_code: u16,
+    mapping: ModelMapping,
 ) {
     use super::usage::logger::UsageLogger;
 
+    // 只读模式不写请求日志
+    if state.provider_router.is_read_only() {
+        return;
+    }
     let logger = UsageLogger::new(&state.db);
 
     // 获取 provider 的 cost_multiplier
-    let multiplier = match state.db.get_provider_by_id(provider_id, app_type) {
-        Ok(Some(p)) => {
-            if let Some(meta) = p.meta {
-                if let Some(cm) = meta.cost_multiplier {
-                    Decimal::from_str(&cm).unwrap_or(Decimal::from(1))
-                } else {
-                    Decimal::from(1)
-                }
-            } else {
-                Decimal::from(1)
-            }
-        }
-        _ => Decimal::from(1),
-    };
-
-    let request_id = uuid::Uuid::new_v4().to_string();
+    let provider = state
+        .db
+        .get_provider_by_id(provider_id, app_type)
+        .ok()
+        .flatten();
+    let multiplier = provider
+        .as_ref()
+        .and_then(|p| p.meta.as_ref())
+        .and_then(|meta| meta.cost_multiplier.as_deref())
+        .map(|cm| Decimal::from_str(cm).unwrap_or(Decimal::from(1)))
+        .unwrap_or(Decimal::from(1));
 
     if let Err(e) = logger.log_with_calculation(
         request_id,
@@ -1094,11 +1760,25 @@ async fn log_usage(
         multiplier,
         latency_ms,
         first_token_ms,
+        upstream_ms,
+        bytes,
         status_code,
         None,
         None, // provider_type
         is_streaming,
+        mapping,
     ) {
         log::warn!("记录使用量失败: {e}");
+        return;
+    }
+    if let Some(provider) = &provider {
+        bandwidth::check_daily_alert(
+            &state.db,
+            state.provider_router.event_recorder(),
+            app_type,
+            provider,
+            bytes,
+            chrono::Utc::now().timestamp(),
+        );
     }
 }
@@ -1,7 +1,539 @@
 //! 健康检查器
 //!
-//! 负责定期检查Provider健康状态（占位实现）
+//! 没有真实流量的供应商永远不会刷新健康状态，失效的 key 只能等真实请求撞上才发现。
+//! 这里按应用配置的间隔（默认关闭），在后台定期探测故障转移队列中的供应商：
+//! - 探测结果通过 `record_probe_result` 写入熔断器与 provider_health，不计入代理请求统计
+//! - 遵守熔断器与层级冷却期，处于冷却/熔断中的供应商跳过
+//! - 同一轮内的探测错开执行，避免瞬时并发
+//! - 等待切回的首选供应商（`auto_restore_primary`）也会被探测，探测成功且熔断器闭合时切回，
+//!   空闲的代理同样能在首选供应商恢复后切回
+//! - 随代理服务器启动/停止
 
-// 占位实现，稍后添加完整逻辑
-#[allow(dead_code)]
-pub struct HealthChecker;
+use super::circuit_breaker::CircuitState;
+use super::failover_switch::FailoverSwitchManager;
+use super::provider_router::ProviderRouter;
+use crate::app_config::AppType;
+use crate::database::Database;
+use crate::provider::Provider;
+use crate::settings::HealthCheckSettings;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{watch, Mutex};
+use tokio::task::JoinHandle;
+
+/// 调度循环的检查粒度
+const SCHEDULER_TICK: Duration = Duration::from_secs(5);
+/// 同一轮内相邻两次探测的间隔
+const PROBE_STAGGER: Duration = Duration::from_secs(2);
+/// 停止时等待调度任务结束的最长时间，超时后中止任务
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+const APP_TYPES: [&str; 3] = ["claude", "codex", "gemini"];
+
+/// 探测过程被停止
+#[derive(Debug)]
+struct Stopped;
+
+/// 后台健康检查器
+pub struct HealthChecker {
+    prober: Prober,
+    stop_tx: watch::Sender<bool>,
+    handle: Mutex<Option<JoinHandle<()>>>,
+    stop_timeout: Duration,
+}
+
+#[derive(Clone)]
+struct Prober {
+    router: Arc<ProviderRouter>,
+    db: Arc<Database>,
+    failover_manager: Arc<FailoverSwitchManager>,
+    app_handle: Option<tauri::AppHandle>,
+    stagger: Duration,
+}
+
+impl HealthChecker {
+    pub fn new(
+        router: Arc<ProviderRouter>,
+        db: Arc<Database>,
+        failover_manager: Arc<FailoverSwitchManager>,
+        app_handle: Option<tauri::AppHandle>,
+    ) -> Self {
+        let (stop_tx, _) = watch::channel(false);
+        Self {
+            prober: Prober {
+                router,
+                db,
+                failover_manager,
+                app_handle,
+                stagger: PROBE_STAGGER,
+            },
+            stop_tx,
+            handle: Mutex::new(None),
+            stop_timeout: STOP_TIMEOUT,
+        }
+    }
+
+    /// 启动调度循环（已在运行时忽略）
+    pub async fn start(&self) {
+        let mut handle = self.handle.lock().await;
+        if handle.as_ref().is_some_and(|h| !h.is_finished()) {
+            return;
+        }
+
+        self.stop_tx.send_replace(false);
+        let stop_rx = self.stop_tx.subscribe();
+        let prober = self.prober.clone();
+        *handle = Some(tokio::spawn(async move {
+            prober.run(stop_rx).await;
+        }));
+    }
+
+    /// 停止调度循环：先发出停止信号等待当前探测结束，超过 `STOP_TIMEOUT` 仍未退出则中止任务
+    pub async fn stop(&self) {
+        self.stop_tx.send_replace(true);
+        if let Some(mut handle) = self.handle.lock().await.take() {
+            if tokio::time::timeout(self.stop_timeout, &mut handle)
+                .await
+                .is_err()
+            {
+                handle.abort();
+                log::warn!("[Health] 健康检查停止超时，已中止调度任务");
+            }
+        }
+    }
+}
+
+/// 读取应用的健康检查设置（间隔为 0 视为关闭）
+fn enabled_settings(app_type: &str) -> Option<HealthCheckSettings> {
+    AppType::from_str(app_type)
+        .ok()
+        .and_then(|t| crate::settings::get_health_check_settings(&t))
+        .filter(|s| s.interval_secs > 0)
+}
+
+impl Prober {
+    async fn run(self, mut stop_rx: watch::Receiver<bool>) {
+        // 首轮在启动一个间隔之后执行，避免与启动测速重叠
+        let mut last_round: HashMap<&'static str, Instant> = HashMap::new();
+
+        loop {
+            for key in APP_TYPES {
+                let Some(settings) = enabled_settings(key) else {
+                    last_round.remove(key);
+                    continue;
+                };
+
+                let interval = Duration::from_secs(settings.interval_secs);
+                let last = *last_round.entry(key).or_insert_with(Instant::now);
+                if last.elapsed() < interval {
+                    continue;
+                }
+                last_round.insert(key, Instant::now());
+
+                if self
+                    .run_round(key, settings.zero_cost, &mut stop_rx)
+                    .await
+                    .is_err()
+                {
+                    return;
+                }
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(SCHEDULER_TICK) => {}
+                _ = stop_rx.changed() => {}
+            }
+            if *stop_rx.borrow() {
+                return;
+            }
+        }
+    }
+
+    /// 探测一个应用故障转移队列中的全部供应商（以及等待切回的首选供应商），返回实际探测的数量
+    async fn run_round(
+        &self,
+        app_type: &str,
+        zero_cost: bool,
+        stop_rx: &mut watch::Receiver<bool>,
+    ) -> Result<usize, Stopped> {
+        let mut providers = match self.db.get_failover_providers(app_type) {
+            Ok(providers) => providers,
+            Err(e) => {
+                log::warn!("[Health] 读取 {app_type} 故障转移队列失败: {e}");
+                return Ok(0);
+            }
+        };
+        if let Some(primary) = self.restore_primary_outside_queue(app_type, &providers) {
+            providers.push(primary);
+        }
+
+        let mut probed = 0;
+        for (index, provider) in providers.iter().enumerate() {
+            if index > 0 && !self.stagger.is_zero() {
+                tokio::select! {
+                    _ = tokio::time::sleep(self.stagger) => {}
+                    _ = stop_rx.changed() => {}
+                }
+            }
+            if *stop_rx.borrow() {
+                return Err(Stopped);
+            }
+
+            if self
+                .router
+                .is_provider_in_cooldown(provider, app_type)
+                .await
+            {
+                log::debug!("[Health] {app_type}/{} 处于冷却期，跳过", provider.name);
+                continue;
+            }
+            let permit = self
+                .router
+                .allow_provider_request(&provider.id, app_type)
+                .await;
+            if !permit.allowed {
+                log::debug!("[Health] {app_type}/{} 熔断中，跳过", provider.name);
+                continue;
+            }
+
+            let result = self
+                .router
+                .probe_provider_health(provider, app_type, zero_cost)
+                .await;
+            let (success, error) = match &result {
+                Ok(latency) => {
+                    log::debug!("[Health] {app_type}/{} 正常 ({latency}ms)", provider.name);
+                    (true, None)
+                }
+                Err(e) => {
+                    log::info!("[Health] {app_type}/{} 探测失败: {e}", provider.name);
+                    (false, Some(e.clone()))
+                }
+            };
+
+            if let Err(e) = self
+                .router
+                .record_probe_result(
+                    &provider.id,
+                    app_type,
+                    permit.used_half_open_permit,
+                    success,
+                    error,
+                )
+                .await
+            {
+                log::warn!(
+                    "[Health] 记录 {app_type}/{} 探测结果失败: {e}",
+                    provider.name
+                );
+            }
+            if success
+                && self
+                    .failover_manager
+                    .is_restore_candidate(app_type, &provider.id)
+            {
+                self.restore_primary(app_type, provider).await;
+            }
+            probed += 1;
+        }
+
+        Ok(probed)
+    }
+
+    /// 等待切回的首选供应商不在故障转移队列中时，返回该供应商以便一并探测
+    fn restore_primary_outside_queue(
+        &self,
+        app_type: &str,
+        queue: &[Provider],
+    ) -> Option<Provider> {
+        let primary_id = AppType::from_str(app_type)
+            .ok()
+            .filter(crate::settings::get_auto_restore_primary)
+            .and_then(|t| crate::settings::get_restore_primary(&t))?;
+        if queue.iter().any(|p| p.id == primary_id) {
+            return None;
+        }
+        self.db
+            .get_provider_by_id(&primary_id, app_type)
+            .ok()
+            .flatten()
+    }
+
+    /// 首选供应商探测成功：熔断器闭合时切回（与真实请求成功后的切回路径一致）
+    async fn restore_primary(&self, app_type: &str, provider: &Provider) {
+        let breaker_state = self
+            .router
+            .get_circuit_breaker_stats(&provider.id, app_type)
+            .await
+            .map(|stats| stats.state)
+            .unwrap_or(CircuitState::Closed);
+        if let Err(e) = self
+            .failover_manager
+            .try_restore_primary(
+                self.app_handle.as_ref(),
+                app_type,
+                &provider.id,
+                breaker_state,
+            )
+            .await
+        {
+            log::error!("[Health] 切回首选供应商失败: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::Provider;
+    use axum::{http::StatusCode, routing::post, Router};
+    use serde_json::json;
+    use std::env;
+    use tempfile::TempDir;
+
+    struct TempHome {
+        #[allow(dead_code)]
+        dir: TempDir,
+        original_home: Option<String>,
+        original_userprofile: Option<String>,
+    }
+
+    impl TempHome {
+        fn new() -> Self {
+            let dir = TempDir::new().expect("failed to create temp home");
+            let original_home = env::var("HOME").ok();
+            let original_userprofile = env::var("USERPROFILE").ok();
+
+            env::set_var("HOME", dir.path());
+            env::set_var("USERPROFILE", dir.path());
+
+            Self {
+                dir,
+                original_home,
+                original_userprofile,
+            }
+        }
+    }
+
+    impl Drop for TempHome {
+        fn drop(&mut self) {
+            match &self.original_home {
+                Some(value) => env::set_var("HOME", value),
+                None => env::remove_var("HOME"),
+            }
+
+            match &self.original_userprofile {
+                Some(value) => env::set_var("USERPROFILE", value),
+                None => env::remove_var("USERPROFILE"),
+            }
+        }
+    }
+
+    /// 启动模拟上游：`/v1/responses` 固定返回给定状态码
+    async fn spawn_upstream(status: StatusCode) -> String {
+        let app = Router::new().route("/v1/responses", post(move || async move { status }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+        format!("http://{addr}")
+    }
+
+    fn codex_provider(id: &str, base_url: &str) -> Provider {
+        Provider::with_id(
+            id.to_string(),
+            id.to_string(),
+            json!({
+                "base_url": base_url,
+                "env": { "OPENAI_API_KEY": "sk-test" }
+            }),
+            None,
+        )
+    }
+
+    fn prober(db: Arc<Database>) -> Prober {
+        Prober {
+            router: Arc::new(ProviderRouter::new(db.clone())),
+            failover_manager: Arc::new(FailoverSwitchManager::with_config(
+                db.clone(),
+                Default::default(),
+            )),
+            db,
+            app_handle: None,
+            stagger: Duration::ZERO,
+        }
+    }
+
+    fn checker(db: Arc<Database>) -> HealthChecker {
+        let failover_manager = Arc::new(FailoverSwitchManager::with_config(
+            db.clone(),
+            Default::default(),
+        ));
+        HealthChecker::new(
+            Arc::new(ProviderRouter::new(db.clone())),
+            db,
+            failover_manager,
+            None,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_round_updates_health_without_traffic() {
+        let ok_url = spawn_upstream(StatusCode::OK).await;
+        let bad_url = spawn_upstream(StatusCode::UNAUTHORIZED).await;
+
+        let db = Arc::new(Database::memory().unwrap());
+        db.save_provider("codex", &codex_provider("good", &ok_url))
+            .unwrap();
+        db.save_provider("codex", &codex_provider("dead", &bad_url))
+            .unwrap();
+        db.add_to_failover_queue("codex", "good").unwrap();
+        db.add_to_failover_queue("codex", "dead").unwrap();
+
+        let prober = prober(db.clone());
+        let (_stop_tx, mut stop_rx) = watch::channel(false);
+        let probed = prober
+            .run_round("codex", false, &mut stop_rx)
+            .await
+            .unwrap();
+        assert_eq!(probed, 2);
+
+        let good = db.get_provider_health("good", "codex").await.unwrap();
+        assert!(good.is_healthy);
+        assert!(good.last_success_at.is_some());
+
+        let dead = db.get_provider_health("dead", "codex").await.unwrap();
+        assert_eq!(dead.consecutive_failures, 1);
+        assert!(dead.last_error.unwrap().contains("HTTP 401"));
+
+        // 熔断器也记录了探测结果
+        let stats = prober
+            .router
+            .get_circuit_breaker_stats("dead", "codex")
+            .await
+            .unwrap();
+        assert_eq!(stats.consecutive_failures, 1);
+    }
+
+    #[tokio::test]
+    #[serial_test::serial]
+    async fn test_successful_probe_restores_primary() {
+        let _home = TempHome::new();
+        crate::settings::reload_settings().expect("reload settings");
+        let mut settings = crate::settings::get_settings();
+        settings.auto_restore_primary_codex = true;
+        crate::settings::update_settings(settings).expect("update settings");
+
+        let ok_url = spawn_upstream(StatusCode::OK).await;
+        let db = Arc::new(Database::memory().unwrap());
+        db.save_provider("codex", &codex_provider("primary", &ok_url))
+            .unwrap();
+        db.save_provider("codex", &codex_provider("backup", &ok_url))
+            .unwrap();
+        // 首选供应商不在故障转移队列中，同样会被探测
+        db.add_to_failover_queue("codex", "backup").unwrap();
+        db.set_current_provider("codex", "primary").unwrap();
+        crate::settings::set_current_provider(&AppType::Codex, Some("primary")).unwrap();
+
+        let prober = prober(db.clone());
+        assert!(prober
+            .failover_manager
+            .try_switch(None, "codex", "backup", "backup")
+            .await
+            .unwrap());
+        assert_eq!(
+            crate::settings::get_restore_primary(&AppType::Codex).as_deref(),
+            Some("primary")
+        );
+
+        // 没有真实请求：定时探测成功后切回首选供应商并清除记录
+        let (_stop_tx, mut stop_rx) = watch::channel(false);
+        assert_eq!(
+            prober
+                .run_round("codex", false, &mut stop_rx)
+                .await
+                .unwrap(),
+            2
+        );
+        assert_eq!(
+            db.get_current_provider("codex").unwrap().as_deref(),
+            Some("primary")
+        );
+        assert_eq!(crate::settings::get_restore_primary(&AppType::Codex), None);
+    }
+
+    #[tokio::test]
+    async fn test_zero_cost_round_uses_connectivity() {
+        // 模拟上游只提供 /v1/responses 且返回 500：零成本模式不发问答请求，
+        // 连通性探测的根路径由 axum 返回 404，能拿到响应即视为可连通
+        let url = spawn_upstream(StatusCode::INTERNAL_SERVER_ERROR).await;
+
+        let db = Arc::new(Database::memory().unwrap());
+        db.save_provider("codex", &codex_provider("p1", &url))
+            .unwrap();
+        db.add_to_failover_queue("codex", "p1").unwrap();
+
+        let prober = prober(db.clone());
+        let (_stop_tx, mut stop_rx) = watch::channel(false);
+        assert_eq!(
+            prober.run_round("codex", true, &mut stop_rx).await.unwrap(),
+            1
+        );
+        let health = db.get_provider_health("p1", "codex").await.unwrap();
+        assert!(health.last_success_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_round_stops_when_signalled() {
+        let db = Arc::new(Database::memory().unwrap());
+        db.save_provider("codex", &codex_provider("p1", "http://127.0.0.1:9"))
+            .unwrap();
+        db.add_to_failover_queue("codex", "p1").unwrap();
+
+        let prober = prober(db.clone());
+        let (stop_tx, mut stop_rx) = watch::channel(false);
+        stop_tx.send_replace(true);
+        assert!(prober
+            .run_round("codex", false, &mut stop_rx)
+            .await
+            .is_err());
+
+        let health = db.get_provider_health("p1", "codex").await.unwrap();
+        assert!(health.last_failure_at.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_checker_start_stop() {
+        let db = Arc::new(Database::memory().unwrap());
+        let checker = checker(db);
+
+        checker.start().await;
+        assert!(checker.handle.lock().await.is_some());
+        checker.stop().await;
+        assert!(checker.handle.lock().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_stop_aborts_scheduler_after_timeout() {
+        let db = Arc::new(Database::memory().unwrap());
+        let mut checker = checker(db);
+        checker.stop_timeout = Duration::from_millis(50);
+
+        // 模拟卡住、不响应停止信号的调度任务：任务被中止时持有的 sender 随之释放
+        let (alive_tx, alive_rx) = tokio::sync::oneshot::channel::<()>();
+        *checker.handle.lock().await = Some(tokio::spawn(async move {
+            let _alive = alive_tx;
+            std::future::pending::<()>().await;
+        }));
+
+        checker.stop().await;
+        assert!(checker.handle.lock().await.is_none());
+        assert!(tokio::time::timeout(Duration::from_secs(1), alive_rx)
+            .await
+            .expect("scheduler task must be aborted")
+            .is_err());
+    }
+}
@@ -0,0 +1,581 @@
+//! 进行中请求追踪、最近请求记录与优雅停机
+//!
+//! 每个代理请求在进入路由时登记，直到响应体（含 SSE 流）发送完毕才注销，
+//! 计数同时用于 `ProxyStatus.active_connections`；注销时写入最近请求环形缓冲
+//! （供 UI / `GET /admin/recent-requests` 查看）。
+//!
+//! 停止代理时：先停止接受新连接，再等待进行中请求在排空超时内自然结束，
+//! 超时后向剩余请求发出中止信号（未返回的请求直接返回 503，流式响应立即断开）。
+
+use axum::body::{Body, Bytes, HttpBody};
+use axum::extract::{Request, State};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use http_body::{Frame, SizeHint};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::sync::{watch, Notify};
+
+/// 默认排空超时（秒）
+pub const DEFAULT_DRAIN_TIMEOUT_SECS: u64 = 30;
+
+/// 最近请求环形缓冲容量
+pub const RECENT_REQUESTS_CAPACITY: usize = 50;
+
+/// 不计入进行中请求的路径（状态查询类接口）
+const UNTRACKED_PATHS: &[&str] = &["/health", "/status", "/version"];
+const UNTRACKED_PREFIXES: &[&str] = &["/admin/"];
+
+type AbortSignal = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// 排空结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DrainOutcome {
+    /// 开始排空时的进行中请求数
+    pub in_flight: usize,
+    /// 超时后被中止的请求数
+    pub aborted: usize,
+    pub elapsed_ms: u64,
+}
+
+/// 最近请求记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecentRequest {
+    /// 请求开始时间（RFC3339）
+    pub at: String,
+    pub app_type: String,
+    pub path: String,
+    pub provider_id: Option<String>,
+    pub provider_name: Option<String>,
+    pub model: Option<String>,
+    pub status: u16,
+    /// 从收到请求到响应体发送完毕的耗时
+    pub latency_ms: u64,
+    /// 启动测速流量（非真实请求）
+    pub startup_test: bool,
+}
+
+/// 请求元信息：由 handler 写入响应扩展，供中间件记录最近请求
+#[derive(Debug, Clone, Default)]
+pub struct RequestMeta {
+    pub app_type: String,
+    pub provider_id: String,
+    pub provider_name: String,
+    pub model: String,
+    pub startup_test: bool,
+}
+
+/// 待写入的最近请求记录（守卫释放时落盘到环形缓冲）
+struct PendingRecord {
+    started: Instant,
+    at: String,
+    path: String,
+    status: u16,
+    meta: Option<RequestMeta>,
+}
+
+impl PendingRecord {
+    fn finish(self) -> RecentRequest {
+        let latency_ms = self.started.elapsed().as_millis() as u64;
+        match self.meta {
+            Some(meta) => RecentRequest {
+                at: self.at,
+                app_type: meta.app_type,
+                path: self.path,
+                provider_id: Some(meta.provider_id),
+                provider_name: Some(meta.provider_name),
+                model: Some(meta.model),
+                status: self.status,
+                latency_ms,
+                startup_test: meta.startup_test,
+            },
+            None => RecentRequest {
+                at: self.at,
+                app_type: app_type_from_path(&self.path).to_string(),
+                path: self.path,
+                provider_id: None,
+                provider_name: None,
+                model: None,
+                status: self.status,
+                latency_ms,
+                startup_test: false,
+            },
+        }
+    }
+}
+
+/// 进行中请求追踪器
+pub struct InflightTracker {
+    active: AtomicUsize,
+    idle: Notify,
+    abort_tx: watch::Sender<bool>,
+    recent: Mutex<VecDeque<RecentRequest>>,
+    /// 最近一次真实代理请求（或服务器启动）的时间，供连接保温判断空闲
+    last_traffic: Mutex<Instant>,
+}
+
+impl Default for InflightTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InflightTracker {
+    pub fn new() -> Self {
+        let (abort_tx, _) = watch::channel(false);
+        Self {
+            active: AtomicUsize::new(0),
+            idle: Notify::new(),
+            abort_tx,
+            recent: Mutex::new(VecDeque::with_capacity(RECENT_REQUESTS_CAPACITY)),
+            last_traffic: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// 当前进行中请求数
+    pub fn active(&self) -> usize {
+        self.active.load(Ordering::SeqCst)
+    }
+
+    /// 登记一个请求，返回的守卫在释放（含 panic/错误路径）时自动注销
+    pub fn begin(self: &Arc<Self>) -> InflightGuard {
+        self.active.fetch_add(1, Ordering::SeqCst);
+        InflightGuard {
+            tracker: self.clone(),
+            record: None,
+        }
+    }
+
+    /// 最近请求（最新在前）
+    pub fn recent_requests(&self) -> Vec<RecentRequest> {
+        let recent = self.recent.lock().unwrap_or_else(|e| e.into_inner());
+        recent.iter().rev().cloned().collect()
+    }
+
+    fn push_recent(&self, entry: RecentRequest) {
+        let mut recent = self.recent.lock().unwrap_or_else(|e| e.into_inner());
+        if recent.len() >= RECENT_REQUESTS_CAPACITY {
+            recent.pop_front();
+        }
+        recent.push_back(entry);
+    }
+
+    /// 距最近一次真实代理请求（无请求时为服务器启动）的时长
+    pub fn idle_for(&self) -> Duration {
+        self.last_traffic
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .elapsed()
+    }
+
+    fn touch(&self) {
+        *self.last_traffic.lock().unwrap_or_else(|e| e.into_inner()) = Instant::now();
+    }
+
+    /// 服务器（重新）启动时清除中止信号，并从启动时刻开始计算空闲
+    pub fn reset(&self) {
+        self.abort_tx.send_replace(false);
+        self.touch();
+    }
+
+    /// 中止信号：`drain` 超时后触发
+    fn abort_signal(&self) -> AbortSignal {
+        let mut rx = self.abort_tx.subscribe();
+        Box::pin(async move {
+            if rx.wait_for(|aborted| *aborted).await.is_err() {
+                std::future::pending::<()>().await;
+            }
+        })
+    }
+
+    async fn wait_idle(&self) {
+        loop {
+            let notified = self.idle.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            if self.active() == 0 {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// 等待进行中请求结束；超时后中止剩余请求
+    pub async fn drain(&self, timeout: Duration) -> DrainOutcome {
+        let started = Instant::now();
+        let in_flight = self.active();
+        log::info!(
+            "[Drain] 开始排空: {in_flight} 个进行中请求（超时 {}s）",
+            timeout.as_secs()
+        );
+
+        let aborted = if tokio::time::timeout(timeout, self.wait_idle())
+            .await
+            .is_ok()
+        {
+            0
+        } else {
+            let remaining = self.active();
+            self.abort_tx.send_replace(true);
+            remaining
+        };
+
+        let outcome = DrainOutcome {
+            in_flight,
+            aborted,
+            elapsed_ms: started.elapsed().as_millis() as u64,
+        };
+        if aborted > 0 {
+            log::warn!(
+                "[Drain] 排空超时，已中止 {aborted} 个请求（耗时 {}ms）",
+                outcome.elapsed_ms
+            );
+        } else {
+            log::info!("[Drain] 排空完成（耗时 {}ms）", outcome.elapsed_ms);
+        }
+        outcome
+    }
+}
+
+/// 进行中请求守卫
+pub struct InflightGuard {
+    tracker: Arc<InflightTracker>,
+    record: Option<PendingRecord>,
+}
+
+impl Drop for InflightGuard {
+    fn drop(&mut self) {
+        if let Some(record) = self.record.take() {
+            self.tracker.push_recent(record.finish());
+        }
+        if self.tracker.active.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.tracker.idle.notify_waiters();
+        }
+    }
+}
+
+/// 路由中间件：登记请求，直到响应体发送完毕
+pub async fn track_inflight(
+    State(tracker): State<Arc<InflightTracker>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let path = req.uri().path();
+    if UNTRACKED_PATHS.contains(&path) || UNTRACKED_PREFIXES.iter().any(|p| path.starts_with(p)) {
+        return next.run(req).await;
+    }
+
+    let path = path.to_string();
+    let started = Instant::now();
+    let at = chrono::Utc::now().to_rfc3339();
+    // 仅记录代理 API 请求（内部管理接口只计入进行中请求数）
+    let recordable = app_type_from_path(&path) != "-";
+    if recordable {
+        tracker.touch();
+    }
+
+    let mut guard = tracker.begin();
+    let mut aborted = tracker.abort_signal();
+
+    let response = tokio::select! {
+        response = next.run(req) => response,
+        _ = &mut aborted => {
+            let response =
+                (StatusCode::SERVICE_UNAVAILABLE, "代理正在关闭，请求已中止").into_response();
+            if recordable {
+                guard.record = Some(PendingRecord {
+                    started,
+                    at,
+                    path,
+                    status: response.status().as_u16(),
+                    meta: None,
+                });
+            }
+            return response;
+        }
+    };
+
+    let meta = response.extensions().get::<RequestMeta>().cloned();
+    if recordable || meta.is_some() {
+        guard.record = Some(PendingRecord {
+            started,
+            at,
+            path,
+            status: response.status().as_u16(),
+            meta,
+        });
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = TrackedBody {
+        inner: body,
+        aborted,
+        _guard: guard,
+    };
+    Response::from_parts(parts, Body::new(body))
+}
+
+/// 由请求路径推断应用类型（handler 未写入元信息时使用，例如请求在选路前失败）
+pub(crate) fn app_type_from_path(path: &str) -> &'static str {
+    if path.contains("/messages") {
+        "claude"
+    } else if path.contains("/v1beta/") {
+        "gemini"
+    } else if path.contains("chat/completions") || path.contains("responses") {
+        "codex"
+    } else {
+        "-"
+    }
+}
+
+/// 持有守卫的响应体：流结束（或被丢弃）时注销请求，收到中止信号时立即断开
+struct TrackedBody {
+    inner: Body,
+    aborted: AbortSignal,
+    _guard: InflightGuard,
+}
+
+impl HttpBody for TrackedBody {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        if self.aborted.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(Some(Err(axum::Error::new(std::io::Error::new(
+                std::io::ErrorKind::Interrupted,
+                "代理正在关闭，响应已中止",
+            )))));
+        }
+        Pin::new(&mut self.inner).poll_frame(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderMap;
+    use axum::routing::{get, post};
+    use axum::Router;
+    use tokio::sync::oneshot;
+
+    /// 模拟 Claude handler：按请求头写入供应商元信息
+    async fn mock_messages(headers: HeaderMap) -> Response {
+        let provider = headers
+            .get("x-provider")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("p1")
+            .to_string();
+        let mut response = "ok".into_response();
+        response.extensions_mut().insert(RequestMeta {
+            app_type: "claude".to_string(),
+            provider_name: format!("name-{provider}"),
+            startup_test: provider == "startup",
+            provider_id: provider,
+            model: "claude-sonnet".to_string(),
+        });
+        response
+    }
+
+    /// 启动带追踪中间件的服务：`/slow` 模拟慢速上游，`/health` 不计数，
+    /// `/v1/messages` 写入元信息，`/v1/chat/completions` 模拟选路前失败
+    async fn spawn_server(
+        tracker: Arc<InflightTracker>,
+        delay: Duration,
+    ) -> (String, oneshot::Sender<()>, tokio::task::JoinHandle<()>) {
+        let app = Router::new()
+            .route(
+                "/slow",
+                get(move || async move {
+                    tokio::time::sleep(delay).await;
+                    "done"
+                }),
+            )
+            .route("/health", get(|| async { "ok" }))
+            .route("/v1/messages", post(mock_messages))
+            .route(
+                "/v1/chat/completions",
+                post(|| async { (StatusCode::BAD_GATEWAY, "no provider") }),
+            )
+            .layer(axum::middleware::from_fn_with_state(
+                tracker,
+                track_inflight,
+            ));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let (tx, rx) = oneshot::channel::<()>();
+        let handle = tokio::spawn(async move {
+            axum::serve(listener, app)
+                .with_graceful_shutdown(async {
+                    rx.await.ok();
+                })
+                .await
+                .ok();
+        });
+        (base, tx, handle)
+    }
+
+    async fn wait_for_active(tracker: &InflightTracker, n: usize) {
+        for _ in 0..100 {
+            if tracker.active() == n {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("active count never reached {n}");
+    }
+
+    #[tokio::test]
+    async fn test_in_flight_request_completes_before_shutdown() {
+        let tracker = Arc::new(InflightTracker::new());
+        let (base, shutdown, server) =
+            spawn_server(tracker.clone(), Duration::from_millis(300)).await;
+
+        let client = reqwest::Client::new();
+        let request = tokio::spawn({
+            let client = client.clone();
+            let url = format!("{base}/slow");
+            async move { client.get(url).send().await?.text().await }
+        });
+        wait_for_active(&tracker, 1).await;
+
+        shutdown.send(()).unwrap();
+        let outcome = tracker.drain(Duration::from_secs(5)).await;
+
+        // 未被中止：请求在排空期间完整返回
+        assert_eq!(request.await.unwrap().unwrap(), "done");
+        assert_eq!(outcome.in_flight, 1);
+        assert_eq!(outcome.aborted, 0);
+        assert_eq!(tracker.active(), 0);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_requests_aborted_after_drain_timeout() {
+        let tracker = Arc::new(InflightTracker::new());
+        let (base, shutdown, server) = spawn_server(tracker.clone(), Duration::from_secs(30)).await;
+
+        let request = tokio::spawn({
+            let url = format!("{base}/slow");
+            async move { reqwest::get(url).await.map(|r| r.status()) }
+        });
+        wait_for_active(&tracker, 1).await;
+
+        shutdown.send(()).unwrap();
+        let outcome = tracker.drain(Duration::from_millis(100)).await;
+        assert_eq!(outcome.aborted, 1);
+
+        let status = request.await.unwrap().unwrap();
+        assert_eq!(status, reqwest::StatusCode::SERVICE_UNAVAILABLE);
+        wait_for_active(&tracker, 0).await;
+        tokio::time::timeout(Duration::from_secs(5), server)
+            .await
+            .expect("server stops after abort")
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_status_paths_are_not_counted() {
+        let tracker = Arc::new(InflightTracker::new());
+        let (base, _shutdown, _server) =
+            spawn_server(tracker.clone(), Duration::from_millis(0)).await;
+
+        let body = reqwest::get(format!("{base}/health"))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert_eq!(body, "ok");
+        assert_eq!(tracker.active(), 0);
+
+        let outcome = tracker.drain(Duration::from_millis(10)).await;
+        assert_eq!(outcome.in_flight, 0);
+        assert_eq!(outcome.aborted, 0);
+    }
+
+    #[tokio::test]
+    async fn test_recent_requests_ring_buffer() {
+        let tracker = Arc::new(InflightTracker::new());
+        let (base, _shutdown, _server) =
+            spawn_server(tracker.clone(), Duration::from_millis(0)).await;
+        let client = reqwest::Client::new();
+
+        for provider in ["p1", "startup", "p2"] {
+            let resp = client
+                .post(format!("{base}/v1/messages"))
+                .header("x-provider", provider)
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(resp.text().await.unwrap(), "ok");
+        }
+        let resp = client
+            .post(format!("{base}/v1/chat/completions"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::BAD_GATEWAY);
+        client.get(format!("{base}/health")).send().await.unwrap();
+
+        wait_for_active(&tracker, 0).await;
+        let recent = tracker.recent_requests();
+        assert_eq!(recent.len(), 4);
+
+        // 最新在前；未写入元信息的请求按路径推断应用
+        assert_eq!(recent[0].app_type, "codex");
+        assert_eq!(recent[0].status, 502);
+        assert!(recent[0].provider_id.is_none());
+        assert_eq!(recent[1].provider_id.as_deref(), Some("p2"));
+        assert_eq!(recent[2].provider_id.as_deref(), Some("startup"));
+        assert!(recent[2].startup_test);
+        assert_eq!(recent[3].provider_name.as_deref(), Some("name-p1"));
+        assert_eq!(recent[3].model.as_deref(), Some("claude-sonnet"));
+        assert!(!recent[3].startup_test);
+    }
+
+    #[tokio::test]
+    async fn test_recent_requests_capacity() {
+        let tracker = Arc::new(InflightTracker::new());
+        let (base, _shutdown, _server) =
+            spawn_server(tracker.clone(), Duration::from_millis(0)).await;
+        let client = reqwest::Client::new();
+
+        for i in 0..RECENT_REQUESTS_CAPACITY + 5 {
+            client
+                .post(format!("{base}/v1/messages"))
+                .header("x-provider", format!("p{i}"))
+                .send()
+                .await
+                .unwrap()
+                .text()
+                .await
+                .unwrap();
+        }
+
+        wait_for_active(&tracker, 0).await;
+        let recent = tracker.recent_requests();
+        assert_eq!(recent.len(), RECENT_REQUESTS_CAPACITY);
+        let newest = format!("p{}", RECENT_REQUESTS_CAPACITY + 4);
+        assert_eq!(recent[0].provider_id.as_deref(), Some(newest.as_str()));
+        assert_eq!(recent.last().unwrap().provider_id.as_deref(), Some("p5"));
+    }
+}
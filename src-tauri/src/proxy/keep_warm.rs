@@ -0,0 +1,306 @@
+//! 连接保温
+//!
+//! 空闲几分钟后的第一个请求需要重新建立 TLS 连接（还可能经过冷的 CDN 路由），多出 300–800ms。
+//! 应用开启 `keep_warm` 后，按 `keep_warm_interval_secs`（默认 55 秒）对各供应商当前锁定的 URL
+//! 发送 HEAD，经由转发共用的连接池，让连接保持活跃：
+//! - 只发 HEAD，结果不计入熔断器、健康状态、疑似失效与请求日志
+//! - 代理超过 1 小时没有真实请求时自动暂停，有新请求后恢复
+//! - 随代理服务器启动/停止
+//!
+//! Claude 请求经 Python 代理转发，上游连接不在本进程的连接池中，保温只能维持 DNS/路由的热度。
+
+use super::attribution::redact_base_url;
+use super::forwarder::shared_client;
+use super::inflight::InflightTracker;
+use super::provider_router::ProviderRouter;
+use crate::database::Database;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{watch, Mutex};
+use tokio::task::JoinHandle;
+
+/// 调度循环的检查粒度
+const SCHEDULER_TICK: Duration = Duration::from_secs(5);
+/// 超过该时长没有真实请求时暂停保温
+const IDLE_PAUSE: Duration = Duration::from_secs(3600);
+/// 单次 HEAD 的超时
+const KEEP_WARM_TIMEOUT: Duration = Duration::from_secs(10);
+/// 停止时等待调度任务结束的最长时间
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+const APP_TYPES: [&str; 3] = ["claude", "codex", "gemini"];
+
+/// 后台连接保温
+pub struct KeepWarmer {
+    warmer: Warmer,
+    stop_tx: watch::Sender<bool>,
+    handle: Mutex<Option<JoinHandle<()>>>,
+    stop_timeout: Duration,
+}
+
+#[derive(Clone)]
+struct Warmer {
+    router: Arc<ProviderRouter>,
+    db: Arc<Database>,
+    inflight: Arc<InflightTracker>,
+    tick: Duration,
+    idle_pause: Duration,
+}
+
+impl KeepWarmer {
+    pub fn new(
+        router: Arc<ProviderRouter>,
+        db: Arc<Database>,
+        inflight: Arc<InflightTracker>,
+    ) -> Self {
+        let (stop_tx, _) = watch::channel(false);
+        Self {
+            warmer: Warmer {
+                router,
+                db,
+                inflight,
+                tick: SCHEDULER_TICK,
+                idle_pause: IDLE_PAUSE,
+            },
+            stop_tx,
+            handle: Mutex::new(None),
+            stop_timeout: STOP_TIMEOUT,
+        }
+    }
+
+    /// 启动调度循环（已在运行时忽略）
+    pub async fn start(&self) {
+        let mut handle = self.handle.lock().await;
+        if handle.as_ref().is_some_and(|h| !h.is_finished()) {
+            return;
+        }
+
+        self.stop_tx.send_replace(false);
+        let stop_rx = self.stop_tx.subscribe();
+        let warmer = self.warmer.clone();
+        *handle = Some(tokio::spawn(async move {
+            warmer.run(stop_rx).await;
+        }));
+    }
+
+    /// 停止调度循环
+    pub async fn stop(&self) {
+        self.stop_tx.send_replace(true);
+        if let Some(mut handle) = self.handle.lock().await.take() {
+            if tokio::time::timeout(self.stop_timeout, &mut handle)
+                .await
+                .is_err()
+            {
+                handle.abort();
+                log::warn!("[KeepWarm] 连接保温停止超时，已中止调度任务");
+            }
+        }
+    }
+}
+
+impl Warmer {
+    async fn run(self, mut stop_rx: watch::Receiver<bool>) {
+        // 首轮在开启一个间隔之后执行（刚有过真实请求的连接本就是热的）
+        let mut last_round: HashMap<&'static str, Instant> = HashMap::new();
+        let mut paused = false;
+
+        loop {
+            let idle = self.inflight.idle_for() > self.idle_pause;
+            if idle != paused {
+                paused = idle;
+                if idle {
+                    log::info!(
+                        "[KeepWarm] 超过 {} 分钟没有请求，暂停连接保温",
+                        self.idle_pause.as_secs() / 60
+                    );
+                } else {
+                    log::info!("[KeepWarm] 收到新请求，恢复连接保温");
+                }
+            }
+
+            if !paused {
+                for key in APP_TYPES {
+                    let config = match self.db.get_proxy_config_for_app(key).await {
+                        Ok(config) if config.keep_warm => config,
+                        _ => {
+                            last_round.remove(key);
+                            continue;
+                        }
+                    };
+
+                    let interval = Duration::from_secs(config.keep_warm_interval_secs.max(1));
+                    let last = *last_round.entry(key).or_insert_with(Instant::now);
+                    if last.elapsed() < interval {
+                        continue;
+                    }
+                    last_round.insert(key, Instant::now());
+
+                    let timeout = config.non_streaming_timeout as u64;
+                    tokio::select! {
+                        _ = self.warm_round(key, timeout) => {}
+                        _ = stop_rx.changed() => {}
+                    }
+                    if *stop_rx.borrow() {
+                        return;
+                    }
+                }
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(self.tick) => {}
+                _ = stop_rx.changed() => {}
+            }
+            if *stop_rx.borrow() {
+                return;
+            }
+        }
+    }
+
+    /// 对一个应用的全部保温目标发送 HEAD，返回收到响应的数量
+    ///
+    /// 使用与该应用转发相同超时配置的共享客户端，确保预热的是真实请求会复用的连接。
+    async fn warm_round(&self, app_type: &str, non_streaming_timeout: u64) -> usize {
+        let client = shared_client(non_streaming_timeout);
+        let mut warmed = 0;
+        for url in self.router.keep_warm_targets(app_type).await {
+            let shown = redact_base_url(&url);
+            match client.head(&url).timeout(KEEP_WARM_TIMEOUT).send().await {
+                Ok(response) => {
+                    log::debug!(
+                        "[KeepWarm] {app_type} {shown} -> {}",
+                        response.status().as_u16()
+                    );
+                    self.router.record_keep_warm(app_type, &url).await;
+                    warmed += 1;
+                }
+                Err(e) => log::debug!("[KeepWarm] {app_type} {shown} 保温失败: {e}"),
+            }
+        }
+        warmed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::Method, routing::any, Router};
+
+    /// 启动模拟上游：记录收到的请求方法
+    async fn spawn_upstream() -> (String, Arc<std::sync::Mutex<Vec<Method>>>) {
+        let methods = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = methods.clone();
+        let app = Router::new().fallback(any(move |method: Method| {
+            let seen = seen.clone();
+            async move {
+                seen.lock().unwrap().push(method);
+            }
+        }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+        (format!("http://{addr}/v1"), methods)
+    }
+
+    async fn warmer(url: &str, idle_pause: Duration) -> Warmer {
+        let db = Arc::new(Database::memory().unwrap());
+        let mut config = db.get_proxy_config_for_app("codex").await.unwrap();
+        config.keep_warm = true;
+        config.keep_warm_interval_secs = 1;
+        db.update_proxy_config_for_app(config).await.unwrap();
+
+        let router = Arc::new(ProviderRouter::new(db.clone()));
+        router
+            .set_supplier_current_url("codex", 1, "acme", url)
+            .await;
+        Warmer {
+            router,
+            db,
+            inflight: Arc::new(InflightTracker::new()),
+            tick: Duration::from_millis(50),
+            idle_pause,
+        }
+    }
+
+    /// 运行调度循环一段时间后停止
+    async fn run_for(warmer: &Warmer, duration: Duration) {
+        let (stop_tx, stop_rx) = watch::channel(false);
+        let handle = tokio::spawn(warmer.clone().run(stop_rx));
+        tokio::time::sleep(duration).await;
+        stop_tx.send_replace(true);
+        handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_round_sends_head_without_recording_health() {
+        let (url, methods) = spawn_upstream().await;
+        let warmer = warmer(&url, IDLE_PAUSE).await;
+
+        assert_eq!(warmer.warm_round("codex", 300).await, 1);
+        assert_eq!(*methods.lock().unwrap(), vec![Method::HEAD]);
+
+        let snapshot = warmer.router.routing_snapshot("codex").await;
+        assert_eq!(snapshot.suppliers[0].keep_warm_age_secs, Some(0));
+        // 不计入熔断器与健康状态
+        let breakers = warmer.router.get_all_circuit_breaker_stats("codex").await;
+        assert!(breakers.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_scheduler_fires_on_interval() {
+        let (url, methods) = spawn_upstream().await;
+        let warmer = warmer(&url, IDLE_PAUSE).await;
+
+        run_for(&warmer, Duration::from_millis(1300)).await;
+        let methods = methods.lock().unwrap();
+        assert!(!methods.is_empty());
+        assert!(methods.iter().all(|m| *m == Method::HEAD));
+    }
+
+    #[tokio::test]
+    async fn test_scheduler_pauses_when_idle() {
+        let (url, methods) = spawn_upstream().await;
+        let warmer = warmer(&url, Duration::ZERO).await;
+
+        run_for(&warmer, Duration::from_millis(1300)).await;
+        assert!(methods.lock().unwrap().is_empty());
+        let snapshot = warmer.router.routing_snapshot("codex").await;
+        assert_eq!(snapshot.suppliers[0].keep_warm_age_secs, None);
+    }
+
+    #[tokio::test]
+    async fn test_keep_warmer_start_stop() {
+        let db = Arc::new(Database::memory().unwrap());
+        let router = Arc::new(ProviderRouter::new(db.clone()));
+        let warmer = KeepWarmer::new(router, db, Arc::new(InflightTracker::new()));
+
+        warmer.start().await;
+        assert!(warmer.handle.lock().await.is_some());
+        warmer.stop().await;
+        assert!(warmer.handle.lock().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_stop_aborts_scheduler_after_timeout() {
+        let db = Arc::new(Database::memory().unwrap());
+        let router = Arc::new(ProviderRouter::new(db.clone()));
+        let mut warmer = KeepWarmer::new(router, db, Arc::new(InflightTracker::new()));
+        warmer.stop_timeout = Duration::from_millis(50);
+
+        // 模拟卡住、不响应停止信号的调度任务：任务被中止时持有的 sender 随之释放
+        let (alive_tx, alive_rx) = tokio::sync::oneshot::channel::<()>();
+        *warmer.handle.lock().await = Some(tokio::spawn(async move {
+            let _alive = alive_tx;
+            std::future::pending::<()>().await;
+        }));
+
+        warmer.stop().await;
+        assert!(warmer.handle.lock().await.is_none());
+        assert!(tokio::time::timeout(Duration::from_secs(1), alive_rx)
+            .await
+            .expect("scheduler task must be aborted")
+            .is_err());
+    }
+}
@@ -0,0 +1,142 @@
+//! 输出 token 上限钳制
+//!
+//! 部分网关对超出其模型上限的 `max_tokens` 直接返回 400，白白消耗一次故障转移。
+//! 供应商配置 `settings_config.maxTokensCap` 后，转发前将请求体中的输出上限字段压到该值（只降不升）。
+
+use crate::provider::Provider;
+use serde_json::Value;
+
+/// settings_config 中的上限字段
+pub const MAX_TOKENS_CAP_KEY: &str = "maxTokensCap";
+
+/// 一次钳制（字段路径、原值、钳制后的值）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaxTokensClamp {
+    pub field: &'static str,
+    pub from: u64,
+    pub to: u64,
+}
+
+/// 供应商配置的输出上限（未配置或非正整数返回 None）
+pub fn max_tokens_cap(provider: &Provider) -> Option<u64> {
+    provider
+        .settings_config
+        .get(MAX_TOKENS_CAP_KEY)
+        .and_then(|v| v.as_u64())
+        .filter(|cap| *cap > 0)
+}
+
+/// 各格式的输出上限字段（按适配器名称）
+fn cap_fields(adapter_name: &str) -> &'static [&'static str] {
+    match adapter_name {
+        "Claude" => &["max_tokens"],
+        "Codex" => &["max_output_tokens", "max_tokens"],
+        "Gemini" => &["generationConfig.maxOutputTokens"],
+        _ => &[],
+    }
+}
+
+/// 将请求体中的输出上限压到供应商上限
+///
+/// 未配置上限或无需钳制时返回 None（调用方继续使用原请求体）；返回的钳制记录为第一个被压低的字段。
+pub fn clamp_max_tokens(
+    body: &Value,
+    provider: &Provider,
+    adapter_name: &str,
+) -> Option<(Value, MaxTokensClamp)> {
+    let cap = max_tokens_cap(provider)?;
+    let mut clamped = body.clone();
+    let mut first = None;
+    for field in cap_fields(adapter_name) {
+        let Some(slot) = field
+            .split('.')
+            .try_fold(&mut clamped, |v, key| v.get_mut(key))
+        else {
+            continue;
+        };
+        let Some(from) = slot.as_u64() else {
+            continue;
+        };
+        if from > cap {
+            *slot = Value::from(cap);
+            first.get_or_insert(MaxTokensClamp {
+                field,
+                from,
+                to: cap,
+            });
+        }
+    }
+    first.map(|clamp| (clamped, clamp))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn provider(settings: Value) -> Provider {
+        Provider::with_id("p".to_string(), "p".to_string(), settings, None)
+    }
+
+    #[test]
+    fn clamps_output_limit_per_format() {
+        let capped = provider(json!({ "maxTokensCap": 8192 }));
+
+        let (body, clamp) =
+            clamp_max_tokens(&json!({ "max_tokens": 32000 }), &capped, "Claude").unwrap();
+        assert_eq!(body["max_tokens"], 8192);
+        assert_eq!(
+            clamp,
+            MaxTokensClamp {
+                field: "max_tokens",
+                from: 32000,
+                to: 8192
+            }
+        );
+
+        let (body, clamp) = clamp_max_tokens(
+            &json!({ "max_output_tokens": 16000, "max_tokens": 9000 }),
+            &capped,
+            "Codex",
+        )
+        .unwrap();
+        assert_eq!(body["max_output_tokens"], 8192);
+        assert_eq!(body["max_tokens"], 8192);
+        assert_eq!(clamp.field, "max_output_tokens");
+
+        let (body, clamp) = clamp_max_tokens(
+            &json!({ "contents": [], "generationConfig": { "maxOutputTokens": 65536 } }),
+            &capped,
+            "Gemini",
+        )
+        .unwrap();
+        assert_eq!(body["generationConfig"]["maxOutputTokens"], 8192);
+        assert_eq!(clamp.from, 65536);
+    }
+
+    #[test]
+    fn never_raises_and_ignores_absent_caps() {
+        let capped = provider(json!({ "maxTokensCap": 8192 }));
+
+        // 未超出上限、字段缺失、字段属于其它格式：不修改
+        assert!(clamp_max_tokens(&json!({ "max_tokens": 1024 }), &capped, "Claude").is_none());
+        assert!(clamp_max_tokens(&json!({ "messages": [] }), &capped, "Claude").is_none());
+        assert!(
+            clamp_max_tokens(&json!({ "max_output_tokens": 32000 }), &capped, "Claude").is_none()
+        );
+
+        // 未配置或配置非法：不修改
+        for settings in [
+            json!({}),
+            json!({ "maxTokensCap": 0 }),
+            json!({ "maxTokensCap": "8k" }),
+        ] {
+            assert!(clamp_max_tokens(
+                &json!({ "max_tokens": 32000 }),
+                &provider(settings),
+                "Claude"
+            )
+            .is_none());
+        }
+    }
+}
@@ -2,39 +2,79 @@
 //!
 //! 提供本地HTTP代理服务，支持多Provider故障转移和请求透传
 
+pub mod app_gate;
+pub mod attribution;
+pub mod bandwidth;
+pub mod body_transforms;
+pub mod capture;
 pub mod circuit_breaker;
+pub mod compare;
+pub mod dry_run;
+#[cfg(test)]
+mod e2e_tests;
+pub mod env_expand;
 pub mod error;
+pub mod error_body;
 pub mod error_mapper;
+pub mod error_schema;
+pub mod event_log;
 pub(crate) mod failover_switch;
+pub mod failure_kind;
+pub mod failure_report;
 mod forwarder;
 pub mod handler_config;
 pub mod handler_context;
 mod handlers;
 mod health;
+pub mod inflight;
+pub mod keep_warm;
+pub mod max_tokens;
+pub(crate) mod model_fallbacks;
 pub mod model_mapper;
 pub(crate) mod model_catalog;
+pub(crate) mod model_list;
 pub(crate) mod model_sanitizer;
+pub(crate) mod model_trace;
+pub mod model_warmup;
 pub(crate) mod model_resolver;
+pub mod notifications;
 pub(crate) mod openai_model_resolver;
-pub(crate) mod python_proxy;
+pub mod python_proxy;
 pub mod provider_router;
 pub mod providers;
+pub mod replay;
+pub mod request_trace;
 pub mod response_handler;
 pub mod response_processor;
+pub mod selection_policy;
 pub mod server;
 pub mod session;
+pub mod shadow;
+pub mod sse_filter;
+pub mod system_prompt;
+#[cfg(test)]
+pub(crate) mod testkit;
+pub mod thinking_capability;
 pub(crate) mod types;
+pub mod update_check;
 pub mod usage;
 
 // 公开导出给外部使用（commands, services等模块需要）
 #[allow(unused_imports)]
 pub use circuit_breaker::{
     CircuitBreaker, CircuitBreakerConfig, CircuitBreakerStats, CircuitState,
+    ProviderCircuitBreakerStats,
 };
 #[allow(unused_imports)]
 pub use error::ProxyError;
 #[allow(unused_imports)]
-pub use provider_router::ProviderRouter;
+pub use event_log::EventRecorder;
+#[allow(unused_imports)]
+pub use inflight::RecentRequest;
+#[allow(unused_imports)]
+pub use model_catalog::ModelFamily;
+#[allow(unused_imports)]
+pub use provider_router::{ProviderRouter, ProviderVerification};
 #[allow(unused_imports)]
 pub use response_handler::{NonStreamHandler, ResponseType, StreamHandler};
 #[allow(unused_imports)]
@@ -42,7 +82,11 @@ pub use server::ProxyServer;
 #[allow(unused_imports)]
 pub use session::{ClientFormat, ProxySession};
 #[allow(unused_imports)]
-pub use types::{ProxyConfig, ProxyServerInfo, ProxyStatus};
+pub use types::{
+    ActiveTarget, AdminStatus, AppRoutingSnapshot, AppStatusSummary, AppTakeoverDetail,
+    FailoverPause, LiveGuardAction, LiveGuardEvent, LiveSnapshot, ProviderHealth, ProxyConfig,
+    ProxyServerInfo, ProxyStatus, RouterMemoryStats, ShadowConfig, SupplierRoutingState,
+};
 
 // 内部模块间共享（供子模块使用）
 // 注意：这个导出用于模块内部，编译器可能警告未使用但实际被子模块使用
@@ -3,8 +3,14 @@
 //! 说明：
 //! - 该模块不做联网；家族关键词来源于主流模型命名习惯（并参考 artificialanalysis.ai/models 的常见族群）。
 //! - 目标是“保守识别”：能识别则严格同家族，否则返回 Other（不做限制）。
+//! - 设置中的 `model_family_overrides`（模式 → 家族名）先于内置规则生效，
+//!   可把自研模型归入已有家族，也可声明新的家族（`csc family add`）。
+//! - 供应商可通过 `settingsConfig.familyGuard` 放宽锚定（见 [`FamilyGuard`]）。
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ModelFamily {
     Claude,
     OpenAi, // gpt-* / o* / chatgpt 等
@@ -21,19 +27,130 @@ pub enum ModelFamily {
     Yi,
     Command, // Cohere Command
     Jamba,
+    /// 用户自定义家族（家族名不属于内置家族）
+    Custom(String),
     Other,
 }
 
+/// 内置家族名（自定义规则中的家族名按此归入已有家族）
+const BUILTIN_FAMILIES: &[(&str, ModelFamily)] = &[
+    ("claude", ModelFamily::Claude),
+    ("openai", ModelFamily::OpenAi),
+    ("gemini", ModelFamily::Gemini),
+    ("llama", ModelFamily::Llama),
+    ("qwen", ModelFamily::Qwen),
+    ("mistral", ModelFamily::Mistral),
+    ("deepseek", ModelFamily::DeepSeek),
+    ("grok", ModelFamily::Grok),
+    ("phi", ModelFamily::Phi),
+    ("gemma", ModelFamily::Gemma),
+    ("glm", ModelFamily::Glm),
+    ("kimi", ModelFamily::Kimi),
+    ("yi", ModelFamily::Yi),
+    ("command", ModelFamily::Command),
+    ("jamba", ModelFamily::Jamba),
+    ("other", ModelFamily::Other),
+];
+
+impl ModelFamily {
+    /// 按家族名解析：内置家族名（不区分大小写）归入对应家族，其余视为自定义家族
+    pub fn from_name(name: &str) -> ModelFamily {
+        let name = normalize(name);
+        BUILTIN_FAMILIES
+            .iter()
+            .find(|(builtin, _)| *builtin == name)
+            .map(|(_, family)| family.clone())
+            .unwrap_or(ModelFamily::Custom(name))
+    }
+
+    /// 家族名（与 `from_name` 互逆）
+    pub fn name(&self) -> &str {
+        if let ModelFamily::Custom(name) = self {
+            return name;
+        }
+        BUILTIN_FAMILIES
+            .iter()
+            .find(|(_, family)| family == self)
+            .map(|(name, _)| *name)
+            .unwrap_or("other")
+    }
+
+    /// 全部内置家族名
+    pub fn builtin_names() -> impl Iterator<Item = &'static str> {
+        BUILTIN_FAMILIES.iter().map(|(name, _)| *name)
+    }
+
+    /// 是否为内置家族名
+    pub fn is_builtin_name(name: &str) -> bool {
+        let name = normalize(name);
+        BUILTIN_FAMILIES.iter().any(|(builtin, _)| *builtin == name)
+    }
+}
+
+/// 家族锚定的严格程度（供应商 `settingsConfig.familyGuard`）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FamilyGuard {
+    /// 显式映射与智能解析都不跨家族（默认）
+    #[default]
+    Strict,
+    /// 显式映射（ANTHROPIC_* 映射、Codex 别名）可跨家族，智能解析仍只在同家族内选择
+    Prefer,
+    /// 不做家族限制
+    Off,
+}
+
+impl FamilyGuard {
+    /// 按名称解析（不区分大小写），无法识别时返回 None
+    pub fn from_name(name: &str) -> Option<FamilyGuard> {
+        match normalize(name).as_str() {
+            "strict" => Some(FamilyGuard::Strict),
+            "prefer" => Some(FamilyGuard::Prefer),
+            "off" => Some(FamilyGuard::Off),
+            _ => None,
+        }
+    }
+
+    /// 显式映射是否必须同家族
+    pub fn anchors_mapping(self) -> bool {
+        self == FamilyGuard::Strict
+    }
+
+    /// 智能解析是否只在同家族内选择
+    pub fn anchors_resolution(self) -> bool {
+        self != FamilyGuard::Off
+    }
+}
+
 fn normalize(s: &str) -> String {
     s.trim().to_lowercase()
 }
 
+/// 识别模型家族（先查设置中的自定义规则，再走内置规则）
 pub fn detect_model_family(model_id: &str) -> ModelFamily {
+    detect_model_family_with(model_id, &crate::settings::get_model_family_overrides())
+}
+
+/// 按给定的自定义规则识别模型家族
+///
+/// 规则的模式按子串匹配（不区分大小写，含 provider 前缀），多条命中时取最长的模式。
+pub fn detect_model_family_with(
+    model_id: &str,
+    overrides: &BTreeMap<String, String>,
+) -> ModelFamily {
     let s = normalize(model_id);
     if s.is_empty() {
         return ModelFamily::Other;
     }
 
+    if let Some((_, family)) = overrides
+        .iter()
+        .filter(|(pattern, _)| !pattern.is_empty() && s.contains(&normalize(pattern)))
+        .max_by_key(|(pattern, _)| pattern.len())
+    {
+        return ModelFamily::from_name(family);
+    }
+
     // provider 前缀常见：anthropic/claude-*, openai/gpt-*, google/gemini-*
     let s = s.split('/').last().unwrap_or(s.as_str()).to_string();
 
@@ -122,8 +239,21 @@ pub fn detect_model_family(model_id: &str) -> ModelFamily {
 }
 
 pub fn is_same_family(request_model: &str, candidate_model: &str) -> bool {
-    let a = detect_model_family(request_model);
-    let b = detect_model_family(candidate_model);
+    is_same_family_with(
+        request_model,
+        candidate_model,
+        &crate::settings::get_model_family_overrides(),
+    )
+}
+
+/// 按给定的自定义规则判断是否同家族
+pub fn is_same_family_with(
+    request_model: &str,
+    candidate_model: &str,
+    overrides: &BTreeMap<String, String>,
+) -> bool {
+    let a = detect_model_family_with(request_model, overrides);
+    let b = detect_model_family_with(candidate_model, overrides);
     // 保守：只有当请求能识别家族时才强制；否则一律放行
     if a == ModelFamily::Other {
         return true;
@@ -163,5 +293,51 @@ mod tests {
         assert!(!is_same_family("claude-sonnet-4-5", "glm-4.5"));
         assert!(!is_same_family("gpt-5.2", "deepseek-r1"));
     }
+
+    fn overrides(rules: &[(&str, &str)]) -> BTreeMap<String, String> {
+        rules
+            .iter()
+            .map(|(pattern, family)| (pattern.to_string(), family.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn override_maps_custom_pattern_into_claude_family() {
+        let rules = overrides(&[("acme-coder", "Claude")]);
+        let same = |a: &str, b: &str| is_same_family_with(a, b, &rules);
+        let family = detect_model_family_with("acme/ACME-Coder-2", &rules);
+        assert_eq!(family, ModelFamily::Claude);
+        assert!(same("claude-sonnet-4-5", "acme-coder-2"));
+        assert!(!same("gpt-5.2", "acme-coder-2"));
+        // 未配置规则时按内置规则：acme-coder-2 无法识别，Claude 请求不可映射到它
+        let rules = BTreeMap::new();
+        assert!(!is_same_family_with("claude-4", "acme-coder-2", &rules));
+    }
+
+    #[test]
+    fn override_declares_new_family_that_blocks_cross_matches() {
+        // 最长模式优先：acme-chat-pro 命中 acme-chat-pro 规则而非 acme 规则
+        let rules = overrides(&[("acme", "acme"), ("acme-chat-pro", "claude")]);
+        let same = |a: &str, b: &str| is_same_family_with(a, b, &rules);
+        let family = detect_model_family_with("acme-coder-2", &rules);
+        assert_eq!(family, ModelFamily::Custom("acme".to_string()));
+        assert_eq!(family.name(), "acme");
+        let family = detect_model_family_with("acme-chat-pro", &rules);
+        assert_eq!(family, ModelFamily::Claude);
+
+        assert!(same("acme-coder-2", "acme-reasoner"));
+        assert!(!same("acme-coder-2", "claude-sonnet-4-5"));
+        assert!(!same("acme-coder-2", "unknown-model"));
+        assert!(!same("claude-sonnet-4-5", "acme-coder-2"));
+    }
+
+    #[test]
+    fn family_names_round_trip() {
+        assert_eq!(ModelFamily::from_name(" OpenAI "), ModelFamily::OpenAi);
+        assert_eq!(ModelFamily::OpenAi.name(), "openai");
+        assert_eq!(ModelFamily::from_name("other"), ModelFamily::Other);
+        assert!(ModelFamily::is_builtin_name("Claude"));
+        assert!(!ModelFamily::is_builtin_name("acme"));
+    }
 }
 
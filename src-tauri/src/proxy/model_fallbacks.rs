@@ -0,0 +1,175 @@
+//! 供应商模型回退链
+//!
+//! 上游提示模型不可用（model_not_found / 无可用渠道）时，按供应商 `settings_config.modelFallbacks`
+//! 声明的顺序在同一供应商上依次改用其它模型重试，全部失败后才故障转移：
+//!
+//! ```json
+//! {
+//!   "modelFallbacks": {
+//!     "claude-sonnet-4-5": ["claude-sonnet-4-1", "claude-haiku-4-5"]
+//!   }
+//! }
+//! ```
+//!
+//! key 可以是客户端请求的模型，也可以是映射/解析后实际发送的模型（不区分大小写）。
+//! 智能解析同样参考回退链：回退链中出现在上游模型列表里的模型优先于打分选择。
+
+use crate::provider::Provider;
+use serde_json::Value;
+
+/// settings_config 中的回退链字段
+pub const MODEL_FALLBACKS_KEY: &str = "modelFallbacks";
+
+/// 同一供应商上因模型不可用而换模型重试的最大次数（含回退链耗尽后的一次智能解析重试）
+pub const MAX_MODEL_FALLBACK_RETRIES: usize = 3;
+
+fn normalize(model: &str) -> String {
+    model.trim().to_lowercase()
+}
+
+/// 某个模型配置的回退链（未配置时为空；值可以是数组或单个字符串）
+///
+/// 按首次出现的顺序去重（不区分大小写），并排除模型自身。
+pub fn model_fallbacks(provider: &Provider, model: &str) -> Vec<String> {
+    let Some(map) = provider
+        .settings_config
+        .get(MODEL_FALLBACKS_KEY)
+        .and_then(|v| v.as_object())
+    else {
+        return Vec::new();
+    };
+    let key = normalize(model);
+    let Some(value) = map
+        .iter()
+        .find(|(k, _)| normalize(k) == key)
+        .map(|(_, v)| v)
+    else {
+        return Vec::new();
+    };
+    let entries: Vec<&Value> = match value {
+        Value::Array(items) => items.iter().collect(),
+        other => vec![other],
+    };
+    let mut chain: Vec<String> = Vec::new();
+    for model in entries
+        .into_iter()
+        .filter_map(|v| v.as_str())
+        .map(str::trim)
+        .filter(|m| !m.is_empty())
+    {
+        let norm = normalize(model);
+        if norm == key || chain.iter().any(|c| normalize(c) == norm) {
+            continue;
+        }
+        chain.push(model.to_string());
+    }
+    chain
+}
+
+/// 请求的回退链：先取实际发送模型的回退链，再取客户端请求模型的；去重并排除 `avoid` 中的模型
+pub fn fallback_chain(
+    provider: &Provider,
+    request_model: &str,
+    sent_model: &str,
+    avoid: &[String],
+) -> Vec<String> {
+    let mut chain: Vec<String> = Vec::new();
+    for model in model_fallbacks(provider, sent_model)
+        .into_iter()
+        .chain(model_fallbacks(provider, request_model))
+    {
+        let norm = normalize(&model);
+        if avoid.iter().any(|a| normalize(a) == norm) || chain.iter().any(|c| normalize(c) == norm)
+        {
+            continue;
+        }
+        chain.push(model);
+    }
+    chain
+}
+
+/// 回退链中第一个出现在上游模型列表里的模型（返回列表中的写法），供智能解析优先采用
+pub fn preferred_in_list(
+    provider: &Provider,
+    request_model: &str,
+    sent_model: &str,
+    models: &[String],
+    avoid: &[String],
+) -> Option<String> {
+    fallback_chain(provider, request_model, sent_model, avoid)
+        .into_iter()
+        .find_map(|candidate| {
+            let norm = normalize(&candidate);
+            models.iter().find(|m| normalize(m) == norm).cloned()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn provider(fallbacks: Value) -> Provider {
+        Provider::with_id(
+            "p".to_string(),
+            "p".to_string(),
+            json!({ "modelFallbacks": fallbacks }),
+            None,
+        )
+    }
+
+    #[test]
+    fn chain_merges_sent_and_request_model_entries() {
+        let p = provider(json!({
+            "claude-sonnet-4-5": ["claude-sonnet-4-1", "claude-haiku-4-5"],
+            "Sonnet-CC": ["claude-haiku-4-5", "claude-3-7-sonnet"],
+            "gpt-5": "gpt-5-mini"
+        }));
+
+        assert_eq!(
+            fallback_chain(&p, "claude-sonnet-4-5", "sonnet-cc", &[]),
+            vec!["claude-haiku-4-5", "claude-3-7-sonnet", "claude-sonnet-4-1"]
+        );
+        assert_eq!(
+            fallback_chain(
+                &p,
+                "claude-sonnet-4-5",
+                "claude-sonnet-4-5",
+                &["claude-sonnet-4-1".to_string()]
+            ),
+            vec!["claude-haiku-4-5"]
+        );
+        assert_eq!(model_fallbacks(&p, "gpt-5"), vec!["gpt-5-mini"]);
+        assert!(model_fallbacks(&p, "gpt-4o").is_empty());
+    }
+
+    #[test]
+    fn fallbacks_are_deduplicated() {
+        let p = provider(json!({
+            "gpt-5": ["gpt-5-mini", " GPT-5-Mini ", "GPT-5", "gpt-5-nano", "gpt-5-mini"]
+        }));
+        // 重复项只保留第一次出现的写法，模型自身不作为回退
+        assert_eq!(
+            model_fallbacks(&p, "gpt-5"),
+            vec!["gpt-5-mini", "gpt-5-nano"]
+        );
+        assert_eq!(
+            fallback_chain(&p, "gpt-5", "gpt-5", &[]),
+            vec!["gpt-5-mini", "gpt-5-nano"]
+        );
+    }
+
+    #[test]
+    fn preferred_candidate_must_be_listed() {
+        let p = provider(json!({ "gpt-5": ["gpt-5-pro", "gpt-5-mini"] }));
+        let models = vec!["GPT-5-Mini".to_string(), "gpt-5-codex".to_string()];
+        assert_eq!(
+            preferred_in_list(&p, "gpt-5", "gpt-5", &models, &[]),
+            Some("GPT-5-Mini".to_string())
+        );
+        assert_eq!(
+            preferred_in_list(&p, "gpt-5", "gpt-5", &models, &["gpt-5-mini".to_string()]),
+            None
+        );
+    }
+}
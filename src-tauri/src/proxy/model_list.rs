@@ -0,0 +1,551 @@
+//! 上游模型列表（`/v1/models`）获取
+//!
+//! OpenAI 模型解析与低成本探测（`ProbeMode::Models`）共用：
+//! - 依次尝试 `/v1/models`、`/models`
+//! - 区分鉴权失败（401/403）、其他 HTTP 错误与网络错误，便于测速结果分类
+//! - 兼容 `{data:[{id}]}`、`{models:[...]}`、`{data:["id"]}` 三种返回格式
+//! - 模型解析时跟随分页（`Link: rel="next"` 或 `has_more` + `last_id`），并读取 `Cache-Control: max-age`
+
+use crate::proxy::error_body::read_error_body;
+use crate::proxy::providers::AuthScheme;
+use reqwest::header::{HeaderMap, CACHE_CONTROL, LINK};
+use reqwest::{Client, RequestBuilder};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fmt;
+use std::future::Future;
+use std::time::Duration;
+
+const MODELS_ENDPOINTS: [&str; 2] = ["/v1/models", "/models"];
+
+/// 分页上限：最多拉取的页数
+pub(crate) const MAX_MODEL_PAGES: usize = 5;
+/// 分页上限：累计模型数
+pub(crate) const MAX_MODEL_IDS: usize = 2000;
+/// 上游 `max-age` 最多把本地缓存延长到 24 小时
+const MAX_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// 请求模型列表时的鉴权头风格
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ModelListAuth {
+    /// `Authorization: Bearer <key>`（OpenAI 兼容）
+    Bearer,
+    /// `x-api-key` + `anthropic-version`；非 `sk-ant-` 的 key 同时附带 Bearer（兼容中转网关）
+    Anthropic,
+    /// Claude 供应商显式配置的认证头（`settings_config.auth`）+ `anthropic-version`
+    Scheme(AuthScheme),
+}
+
+/// 获取模型列表失败的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ModelListError {
+    /// 401/403：key 无效或无权限
+    Auth { status: u16, body: String },
+    /// 其他非 2xx（通常是未开放 models 端点）
+    Http { status: u16, body: String },
+    /// 网络错误或超时
+    Network(String),
+    /// 返回内容不是可识别的模型列表
+    Invalid(String),
+}
+
+impl ModelListError {
+    pub(crate) fn is_auth(&self) -> bool {
+        matches!(self, ModelListError::Auth { .. })
+    }
+}
+
+impl fmt::Display for ModelListError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModelListError::Auth { status, body } | ModelListError::Http { status, body } => {
+                if body.is_empty() {
+                    write!(f, "HTTP {status}")
+                } else {
+                    write!(f, "HTTP {status}: {body}")
+                }
+            }
+            ModelListError::Network(msg) => write!(f, "请求模型列表失败: {msg}"),
+            ModelListError::Invalid(msg) => write!(f, "模型列表不兼容: {msg}"),
+        }
+    }
+}
+
+/// 跟随分页后合并去重的模型列表
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ModelList {
+    pub ids: Vec<String>,
+    /// 首页响应的 `Cache-Control: max-age`（用于延长本地缓存）
+    pub max_age: Option<Duration>,
+}
+
+impl ModelList {
+    /// 本地缓存有效期：上游 `max-age` 比默认值更长时以其为准（只延长，不缩短），
+    /// 最多延长到 [`MAX_CACHE_TTL`]，避免超大 `max-age` 让列表长期不刷新
+    pub(crate) fn cache_ttl(&self, default: Duration) -> Duration {
+        self.max_age
+            .map_or(default, |age| age.min(MAX_CACHE_TTL).max(default))
+    }
+}
+
+/// 解析 `Cache-Control: max-age=N`（带 `no-store` / `no-cache` 时忽略）
+pub(crate) fn cache_max_age(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers
+        .get(CACHE_CONTROL)?
+        .to_str()
+        .ok()?
+        .to_ascii_lowercase();
+    let directives: Vec<&str> = value.split(',').map(str::trim).collect();
+    if directives
+        .iter()
+        .any(|d| *d == "no-store" || *d == "no-cache")
+    {
+        return None;
+    }
+    directives
+        .iter()
+        .find_map(|d| d.strip_prefix("max-age="))
+        .and_then(|secs| secs.trim_matches('"').parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
+/// 下一页 URL：优先 `Link: <...>; rel="next"`，其次 `has_more` + `last_id`（追加 `after_id`）
+///
+/// Link 指向其他源时只保留路径与查询参数，经 Python 代理拉取时下一页仍发往代理。
+pub(crate) fn next_page_url(
+    current_url: &str,
+    headers: &HeaderMap,
+    body: &Value,
+) -> Option<String> {
+    let current = url::Url::parse(current_url).ok()?;
+
+    let link_next = headers
+        .get_all(LINK)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .find_map(|link| {
+            let (target, params) = link.trim().split_once(';')?;
+            let is_next = params.split(';').any(|p| {
+                let p = p.trim().to_ascii_lowercase();
+                p == "rel=next" || p == "rel=\"next\""
+            });
+            is_next.then(|| target.trim().trim_start_matches('<').trim_end_matches('>'))
+        });
+    if let Some(target) = link_next {
+        let mut next = current.join(target).ok()?;
+        if next.origin() != current.origin() {
+            let mut rebased = current.clone();
+            rebased.set_path(next.path());
+            rebased.set_query(next.query());
+            next = rebased;
+        }
+        return Some(next.to_string());
+    }
+
+    if body.get("has_more").and_then(|v| v.as_bool()) != Some(true) {
+        return None;
+    }
+    let last_id = body
+        .get("last_id")
+        .and_then(|v| v.as_str())
+        .map(str::to_string)
+        .or_else(|| parse_model_ids(body).pop())?;
+    let mut next = current;
+    let query: Vec<(String, String)> = next
+        .query_pairs()
+        .filter(|(k, _)| k != "after_id")
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+    next.query_pairs_mut()
+        .clear()
+        .extend_pairs(query)
+        .append_pair("after_id", &last_id);
+    Some(next.to_string())
+}
+
+/// 从首页开始跟随分页，合并并去重（忽略大小写，保持顺序）
+///
+/// 达到页数/模型数上限、下一页失败或某页没有新模型时停止，已获取的部分照常返回。
+pub(crate) async fn collect_model_pages<F, Fut>(
+    first_url: &str,
+    first_headers: &HeaderMap,
+    first_body: &Value,
+    max_pages: usize,
+    mut fetch_page: F,
+) -> ModelList
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<(HeaderMap, Value), String>>,
+{
+    // 返回本页是否带来了新模型
+    fn add_page(seen: &mut HashSet<String>, ids: &mut Vec<String>, body: &Value) -> bool {
+        let before = ids.len();
+        for id in parse_model_ids(body) {
+            if seen.insert(id.to_lowercase()) {
+                ids.push(id);
+            }
+        }
+        ids.len() > before
+    }
+
+    let mut seen = HashSet::new();
+    let mut ids = Vec::new();
+    add_page(&mut seen, &mut ids, first_body);
+    let mut next = next_page_url(first_url, first_headers, first_body);
+    let mut pages = 1;
+    while let Some(url) = next.take() {
+        if pages >= max_pages || ids.len() >= MAX_MODEL_IDS {
+            log::debug!(
+                "[ModelList] 分页达到上限（{pages} 页 / {} 个模型），停止拉取",
+                ids.len()
+            );
+            break;
+        }
+        match fetch_page(url.clone()).await {
+            Ok((headers, body)) => {
+                pages += 1;
+                if add_page(&mut seen, &mut ids, &body) {
+                    next = next_page_url(&url, &headers, &body);
+                }
+            }
+            Err(e) => log::debug!("[ModelList] 拉取分页失败 url={url}: {e}"),
+        }
+    }
+
+    ids.truncate(MAX_MODEL_IDS);
+    ModelList {
+        ids,
+        max_age: cache_max_age(first_headers),
+    }
+}
+
+/// 拼接 base_url 与端点，避免出现 `/v1/v1`
+pub(crate) fn join_models_url(base_url: &str, endpoint: &str) -> String {
+    let base_trimmed = base_url.trim_end_matches('/');
+    let endpoint_trimmed = endpoint.trim_start_matches('/');
+    let mut url = format!("{base_trimmed}/{endpoint_trimmed}");
+    if url.contains("/v1/v1") {
+        url = url.replace("/v1/v1", "/v1");
+    }
+    url
+}
+
+/// 从模型列表响应中提取模型 ID（去除首尾空白，保持顺序，不去重）
+pub(crate) fn parse_model_ids(v: &Value) -> Vec<String> {
+    fn push(out: &mut Vec<String>, id: &str) {
+        let id = id.trim();
+        if !id.is_empty() {
+            out.push(id.to_string());
+        }
+    }
+
+    // OpenAI 兼容：{ data: [{ id: "..." }, ...] }
+    let mut out = Vec::new();
+    if let Some(arr) = v.get("data").and_then(|d| d.as_array()) {
+        for item in arr {
+            if let Some(id) = item.get("id").and_then(|x| x.as_str()) {
+                push(&mut out, id);
+            }
+        }
+    }
+
+    // 兜底：一些服务可能返回 { models: [...] } 或 { data: ["id", ...] }
+    if out.is_empty() {
+        if let Some(arr) = v.get("models").and_then(|d| d.as_array()) {
+            for item in arr {
+                if let Some(id) = item.get("id").and_then(|x| x.as_str()) {
+                    push(&mut out, id);
+                } else if let Some(s) = item.as_str() {
+                    push(&mut out, s);
+                }
+            }
+        }
+    }
+    if out.is_empty() {
+        if let Some(arr) = v.get("data").and_then(|d| d.as_array()) {
+            for item in arr {
+                if let Some(s) = item.as_str() {
+                    push(&mut out, s);
+                }
+            }
+        }
+    }
+
+    out
+}
+
+/// 构建带鉴权头的模型列表请求
+fn models_request(
+    client: &Client,
+    url: &str,
+    api_key: &str,
+    auth: &ModelListAuth,
+) -> RequestBuilder {
+    let req = client.get(url).header("accept", "application/json");
+    match auth {
+        ModelListAuth::Bearer => req.header("authorization", format!("Bearer {api_key}")),
+        ModelListAuth::Anthropic => {
+            let req = req
+                .header("x-api-key", api_key)
+                .header("anthropic-version", "2023-06-01");
+            if api_key.starts_with("sk-ant-") {
+                req
+            } else {
+                req.header("authorization", format!("Bearer {api_key}"))
+            }
+        }
+        ModelListAuth::Scheme(scheme) => {
+            let (name, value) = scheme.header(api_key);
+            req.header(name, value)
+                .header("anthropic-version", "2023-06-01")
+        }
+    }
+}
+
+/// 直连上游获取模型 ID 列表（仅首页，用于低成本探测）
+///
+/// 鉴权失败不会立即返回：`/models` 仍会尝试一次，但最终错误优先报告鉴权失败。
+pub(crate) async fn fetch_model_ids(
+    client: &Client,
+    base_url: &str,
+    api_key: &str,
+    auth: ModelListAuth,
+) -> Result<Vec<String>, ModelListError> {
+    fetch_model_pages(client, base_url, api_key, &auth, 1)
+        .await
+        .map(|list| list.ids)
+}
+
+/// 直连上游获取完整模型列表（跟随分页，最多 [`MAX_MODEL_PAGES`] 页）
+pub(crate) async fn fetch_model_list(
+    client: &Client,
+    base_url: &str,
+    api_key: &str,
+    auth: ModelListAuth,
+) -> Result<ModelList, ModelListError> {
+    fetch_model_pages(client, base_url, api_key, &auth, MAX_MODEL_PAGES).await
+}
+
+async fn fetch_model_pages(
+    client: &Client,
+    base_url: &str,
+    api_key: &str,
+    auth: &ModelListAuth,
+    max_pages: usize,
+) -> Result<ModelList, ModelListError> {
+    let api_key = api_key.trim();
+    let mut auth_err: Option<ModelListError> = None;
+    let mut last_err = ModelListError::Invalid("no models endpoint available".to_string());
+
+    for ep in MODELS_ENDPOINTS {
+        let url = join_models_url(base_url, ep);
+        let resp = models_request(client, &url, api_key, auth)
+            .send()
+            .await
+            .map_err(|e| ModelListError::Network(e.to_string()))?;
+
+        let status = resp.status().as_u16();
+        if !resp.status().is_success() {
+            let body = read_error_body(resp).await.unwrap_or_default();
+            let body: String = body.trim().chars().take(200).collect();
+            if status == 401 || status == 403 {
+                auth_err.get_or_insert(ModelListError::Auth { status, body });
+            } else {
+                last_err = ModelListError::Http { status, body };
+            }
+            continue;
+        }
+
+        let headers = resp.headers().clone();
+        match resp.json::<Value>().await {
+            Ok(v) => {
+                if parse_model_ids(&v).is_empty() {
+                    last_err = ModelListError::Invalid("返回为空".to_string());
+                    continue;
+                }
+                let fetch_page = |url: String| async move {
+                    let resp = models_request(client, &url, api_key, auth)
+                        .send()
+                        .await
+                        .map_err(|e| e.to_string())?;
+                    if !resp.status().is_success() {
+                        return Err(format!("HTTP {}", resp.status().as_u16()));
+                    }
+                    let headers = resp.headers().clone();
+                    let body = resp.json::<Value>().await.map_err(|e| e.to_string())?;
+                    Ok((headers, body))
+                };
+                return Ok(collect_model_pages(&url, &headers, &v, max_pages, fetch_page).await);
+            }
+            Err(e) => last_err = ModelListError::Invalid(e.to_string()),
+        }
+    }
+
+    Err(auth_err.unwrap_or(last_err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_model_ids_formats() {
+        let v = json!({"data": [{"id": " gpt-5 "}, {"id": ""}, {"id": "gpt-4o"}]});
+        assert_eq!(parse_model_ids(&v), vec!["gpt-5", "gpt-4o"]);
+
+        let v = json!({"models": [{"id": "a"}, "b"]});
+        assert_eq!(parse_model_ids(&v), vec!["a", "b"]);
+
+        let v = json!({"data": ["x", "y"]});
+        assert_eq!(parse_model_ids(&v), vec!["x", "y"]);
+
+        assert!(parse_model_ids(&json!({"object": "list"})).is_empty());
+    }
+
+    /// 分页模型列表：每页 2 个，`has_more` + `last_id`；偶数页带 Link 头时走 Link
+    async fn spawn_paginated_models(total: usize, use_link: bool) -> String {
+        use axum::{
+            extract::Query,
+            http::{header, HeaderMap as AxumHeaderMap},
+            routing::get,
+            Json, Router,
+        };
+        use std::collections::HashMap;
+
+        let handler = move |Query(q): Query<HashMap<String, String>>| async move {
+            let start = match q.get("after_id").or_else(|| q.get("page")) {
+                Some(after) => after.trim_start_matches("model-").parse::<usize>().unwrap() + 1,
+                None => 0,
+            };
+            let end = (start + 2).min(total);
+            // 第二页重复上一页的最后一个模型，验证跨页去重
+            let ids: Vec<String> = (start.saturating_sub(usize::from(start > 0))..end)
+                .map(|i| format!("model-{i}"))
+                .collect();
+            let mut headers = AxumHeaderMap::new();
+            headers.insert(
+                header::CACHE_CONTROL,
+                "public, max-age=86400".parse().unwrap(),
+            );
+            let has_more = end < total;
+            if use_link && has_more {
+                let link = format!("</v1/models?page=model-{}>; rel=\"next\"", end - 1);
+                headers.insert(header::LINK, link.parse().unwrap());
+            }
+            let data: Vec<Value> = ids.iter().map(|id| json!({ "id": id })).collect();
+            let body = if use_link {
+                json!({ "data": data })
+            } else {
+                json!({ "data": data, "has_more": has_more, "last_id": ids.last() })
+            };
+            (headers, Json(body))
+        };
+        let app = Router::new().route("/v1/models", get(handler));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+        format!("http://{addr}")
+    }
+
+    #[tokio::test]
+    async fn test_fetch_model_list_follows_pagination() {
+        let client = Client::new();
+        for use_link in [false, true] {
+            let base = spawn_paginated_models(7, use_link).await;
+            let list = fetch_model_list(&client, &base, "sk-1", ModelListAuth::Bearer)
+                .await
+                .unwrap();
+            let expected: Vec<String> = (0..7).map(|i| format!("model-{i}")).collect();
+            assert_eq!(list.ids, expected, "use_link={use_link}");
+            assert_eq!(list.max_age, Some(Duration::from_secs(86400)));
+            assert_eq!(
+                list.cache_ttl(Duration::from_secs(60)),
+                Duration::from_secs(86400)
+            );
+            let forever = ModelList {
+                max_age: Some(Duration::from_secs(365 * 86400)),
+                ..list.clone()
+            };
+            assert_eq!(
+                forever.cache_ttl(Duration::from_secs(60)),
+                Duration::from_secs(86400)
+            );
+
+            // 探测只取首页
+            let first = fetch_model_ids(&client, &base, "sk-1", ModelListAuth::Bearer)
+                .await
+                .unwrap();
+            assert_eq!(first, vec!["model-0", "model-1"]);
+        }
+
+        // 页数上限：5 页 × 2 个
+        let base = spawn_paginated_models(100, false).await;
+        let list = fetch_model_list(&client, &base, "sk-1", ModelListAuth::Bearer)
+            .await
+            .unwrap();
+        assert_eq!(list.ids.len(), 2 * MAX_MODEL_PAGES);
+    }
+
+    #[test]
+    fn test_cache_max_age_and_next_page_url() {
+        let headers = |pairs: &[(&'static str, &str)]| {
+            let mut map = HeaderMap::new();
+            for (name, value) in pairs {
+                map.append(*name, value.parse().unwrap());
+            }
+            map
+        };
+        assert_eq!(
+            cache_max_age(&headers(&[("cache-control", "public, max-age=600")])),
+            Some(Duration::from_secs(600))
+        );
+        assert_eq!(
+            cache_max_age(&headers(&[("cache-control", "no-store, max-age=600")])),
+            None
+        );
+        assert_eq!(cache_max_age(&HeaderMap::new()), None);
+
+        // Link 指向其他源：改写到当前源（Python 代理）
+        let link = headers(&[(
+            "link",
+            "<https://up.example.com/v1/models?cursor=abc>; rel=\"next\"",
+        )]);
+        assert_eq!(
+            next_page_url("http://127.0.0.1:8080/v1/models", &link, &json!({})).as_deref(),
+            Some("http://127.0.0.1:8080/v1/models?cursor=abc")
+        );
+        assert_eq!(
+            next_page_url(
+                "https://a.com/v1/models?limit=2&after_id=x",
+                &HeaderMap::new(),
+                &json!({ "data": [{ "id": "m1" }], "has_more": true, "last_id": "m1" })
+            )
+            .as_deref(),
+            Some("https://a.com/v1/models?limit=2&after_id=m1")
+        );
+        assert_eq!(
+            next_page_url(
+                "https://a.com/v1/models",
+                &HeaderMap::new(),
+                &json!({ "data": [{ "id": "m1" }], "has_more": false })
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_join_models_url() {
+        assert_eq!(
+            join_models_url("https://a.com/v1/", "/v1/models"),
+            "https://a.com/v1/models"
+        );
+        assert_eq!(
+            join_models_url("https://a.com", "/models"),
+            "https://a.com/models"
+        );
+    }
+}
@@ -3,16 +3,31 @@
 //! 在请求转发前，根据 Provider 配置替换请求中的模型名称
 
 use crate::provider::Provider;
-use crate::proxy::model_catalog::{detect_model_family, is_same_family, ModelFamily};
+use crate::proxy::model_catalog::{
+    detect_model_family_with, is_same_family_with, FamilyGuard, ModelFamily,
+};
+use crate::proxy::model_resolver;
+use once_cell::sync::Lazy;
 use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Mutex;
+
+/// 运行时已告警过的跨家族映射：(供应商, 环境变量, 配置值)
+type WarnedMapping = (String, &'static str, String);
+
+static WARNED_CROSS_FAMILY: Lazy<Mutex<HashSet<WarnedMapping>>> =
+    Lazy::new(|| Mutex::new(HashSet::new()));
 
 /// 模型映射配置
 pub struct ModelMapping {
+    pub provider_id: String,
     pub haiku_model: Option<String>,
     pub sonnet_model: Option<String>,
     pub opus_model: Option<String>,
     pub default_model: Option<String>,
     pub reasoning_model: Option<String>,
+    /// 家族锚定严格程度（prefer / off 时映射可跨家族）
+    pub family_guard: FamilyGuard,
 }
 
 impl ModelMapping {
@@ -21,6 +36,7 @@ impl ModelMapping {
         let env = provider.settings_config.get("env");
 
         Self {
+            provider_id: provider.id.clone(),
             haiku_model: env
                 .and_then(|e| e.get("ANTHROPIC_DEFAULT_HAIKU_MODEL"))
                 .and_then(|v| v.as_str())
@@ -46,6 +62,7 @@ impl ModelMapping {
                 .and_then(|v| v.as_str())
                 .filter(|s| !s.is_empty())
                 .map(String::from),
+            family_guard: provider.family_guard(),
         }
     }
 
@@ -61,48 +78,29 @@ impl ModelMapping {
     /// 根据原始模型名称获取映射后的模型
     pub fn map_model(&self, original_model: &str, has_thinking: bool) -> String {
         let model_lower = original_model.to_lowercase();
-
-        fn claude_family(lower: &str) -> Option<&'static str> {
-            if lower.contains("haiku") {
-                return Some("haiku");
-            }
-            if lower.contains("sonnet") {
-                return Some("sonnet");
-            }
-            if lower.contains("opus") {
-                return Some("opus");
-            }
-            None
-        }
-
-        // 约束：家族守护（Claude/GPT/Gemini/Llama 等）
-        // - 若原始模型能识别家族，则映射目标必须仍在同家族内（严禁跨到 GLM/GPT 等）。
-        // - Claude 额外要求：尽量保持 haiku/sonnet/opus 一致（避免“性能断崖”）。
-        let original_family = detect_model_family(original_model);
-        let original_claude_family = claude_family(&model_lower);
-
-        let is_acceptable_mapping = |mapped: &str| -> bool {
-            let mapped_lower = mapped.to_lowercase();
-            // 1) 家族锚定：必须同家族（保守：只有请求可识别时才强制）
-            if !is_same_family(original_model, mapped) {
-                return false;
-            }
-
-            // 2) Claude 的子家族守护：尽量保持 haiku/sonnet/opus 一致
-            if original_family == ModelFamily::Claude {
-                // 映射值本身缺少 haiku/sonnet/opus 关键词时放行（交给后续智能解析兜底）
-                if let Some(f) = original_claude_family {
-                    let mapped_family = claude_family(&mapped_lower);
-                    return mapped_family.is_none() || mapped_lower.contains(f);
+        // 自定义家族规则每次映射只读取一次
+        let overrides = crate::settings::get_model_family_overrides();
+
+        // 跨家族属于配置错误（子家族不一致只是不适用于当前请求），需要告警；
+        // 供应商声明 familyGuard=prefer/off 时跨家族映射是有意为之
+        let accept = |env_key: &'static str, mapped: &str| -> bool {
+            match self.family_guard {
+                FamilyGuard::Off => true,
+                FamilyGuard::Prefer => matches_claude_subfamily(original_model, mapped, &overrides),
+                FamilyGuard::Strict => {
+                    if !is_same_family_with(original_model, mapped, &overrides) {
+                        warn_cross_family_once(&self.provider_id, env_key, mapped, original_model);
+                        return false;
+                    }
+                    matches_claude_subfamily(original_model, mapped, &overrides)
                 }
             }
-            true
         };
 
         // 1. thinking 模式优先使用推理模型
         if has_thinking {
             if let Some(ref m) = self.reasoning_model {
-                if is_acceptable_mapping(m) {
+                if accept("ANTHROPIC_REASONING_MODEL", m) {
                     return m.clone();
                 }
             }
@@ -111,21 +109,21 @@ impl ModelMapping {
         // 2. 按模型类型匹配
         if model_lower.contains("haiku") {
             if let Some(ref m) = self.haiku_model {
-                if is_acceptable_mapping(m) {
+                if accept("ANTHROPIC_DEFAULT_HAIKU_MODEL", m) {
                     return m.clone();
                 }
             }
         }
         if model_lower.contains("opus") {
             if let Some(ref m) = self.opus_model {
-                if is_acceptable_mapping(m) {
+                if accept("ANTHROPIC_DEFAULT_OPUS_MODEL", m) {
                     return m.clone();
                 }
             }
         }
         if model_lower.contains("sonnet") {
             if let Some(ref m) = self.sonnet_model {
-                if is_acceptable_mapping(m) {
+                if accept("ANTHROPIC_DEFAULT_SONNET_MODEL", m) {
                     return m.clone();
                 }
             }
@@ -133,7 +131,7 @@ impl ModelMapping {
 
         // 3. 默认模型
         if let Some(ref m) = self.default_model {
-            if is_acceptable_mapping(m) {
+            if accept("ANTHROPIC_MODEL", m) {
                 return m.clone();
             }
         }
@@ -143,6 +141,111 @@ impl ModelMapping {
     }
 }
 
+fn claude_family(lower: &str) -> Option<&'static str> {
+    if lower.contains("haiku") {
+        return Some("haiku");
+    }
+    if lower.contains("sonnet") {
+        return Some("sonnet");
+    }
+    if lower.contains("opus") {
+        return Some("opus");
+    }
+    None
+}
+
+/// 约束：家族守护（Claude/GPT/Gemini/Llama 等）
+/// - 若原始模型能识别家族，则映射目标必须仍在同家族内（严禁跨到 GLM/GPT 等）。
+/// - Claude 额外要求：尽量保持 haiku/sonnet/opus 一致（避免“性能断崖”）。
+fn is_acceptable_mapping(
+    original_model: &str,
+    mapped: &str,
+    overrides: &BTreeMap<String, String>,
+) -> bool {
+    // 1) 家族锚定：必须同家族（保守：只有请求可识别时才强制）
+    // 2) Claude 的子家族守护：尽量保持 haiku/sonnet/opus 一致
+    is_same_family_with(original_model, mapped, overrides)
+        && matches_claude_subfamily(original_model, mapped, overrides)
+}
+
+/// Claude 请求的映射值尽量保持 haiku/sonnet/opus 一致（其他家族不限制）
+fn matches_claude_subfamily(
+    original_model: &str,
+    mapped: &str,
+    overrides: &BTreeMap<String, String>,
+) -> bool {
+    if detect_model_family_with(original_model, overrides) == ModelFamily::Claude {
+        // 映射值本身缺少 haiku/sonnet/opus 关键词时放行（交给后续智能解析兜底）
+        if let Some(f) = claude_family(&original_model.to_lowercase()) {
+            let mapped_lower = mapped.to_lowercase();
+            return claude_family(&mapped_lower).is_none() || mapped_lower.contains(f);
+        }
+    }
+    true
+}
+
+/// 映射环境变量及其代表性请求模型（离线判断映射值是否会被家族守护拒绝）
+const MAPPING_ENV_KEYS: [(&str, &str); 5] = [
+    ("ANTHROPIC_DEFAULT_HAIKU_MODEL", "claude-haiku-4-5"),
+    ("ANTHROPIC_DEFAULT_SONNET_MODEL", "claude-sonnet-4-5"),
+    ("ANTHROPIC_DEFAULT_OPUS_MODEL", "claude-opus-4-5"),
+    ("ANTHROPIC_MODEL", "claude-sonnet-4-5"),
+    ("ANTHROPIC_REASONING_MODEL", "claude-sonnet-4-5"),
+];
+
+/// 跨家族的映射配置：(环境变量, 配置值)
+///
+/// 这些值在请求时会被家族守护拒绝，请求保持原模型转发。
+/// `familyGuard` 为 prefer / off 的供应商有意跨家族映射，不报告。
+pub fn cross_family_mappings(provider: &Provider) -> Vec<(&'static str, String)> {
+    if !provider.family_guard().anchors_mapping() {
+        return Vec::new();
+    }
+    let Some(env) = provider.settings_config.get("env") else {
+        return Vec::new();
+    };
+    let overrides = crate::settings::get_model_family_overrides();
+    MAPPING_ENV_KEYS
+        .iter()
+        .filter_map(|(key, request_model)| {
+            let value = env.get(*key)?.as_str()?.trim();
+            (!value.is_empty() && !is_same_family_with(request_model, value, &overrides))
+                .then(|| (*key, value.to_string()))
+        })
+        .collect()
+}
+
+/// 基于供应商已缓存的模型列表，为映射环境变量建议一个同家族的值
+///
+/// 模型列表未缓存或列表中没有可被家族守护接受的模型时返回 None。
+pub fn suggest_mapping_value(provider: &Provider, env_key: &str) -> Option<String> {
+    let (_, request_model) = MAPPING_ENV_KEYS.iter().find(|(key, _)| *key == env_key)?;
+    let models = model_resolver::cached_model_list(provider)?;
+    let thinking = env_key == "ANTHROPIC_REASONING_MODEL";
+    let overrides = crate::settings::get_model_family_overrides();
+    model_resolver::choose_best_model(request_model, thinking, &models)
+        .filter(|m| is_acceptable_mapping(request_model, m, &overrides))
+}
+
+/// 请求时映射因跨家族被拒绝：同一供应商的同一配置只告警一次，返回本次是否输出了日志
+fn warn_cross_family_once(
+    provider_id: &str,
+    env_key: &'static str,
+    value: &str,
+    request_model: &str,
+) -> bool {
+    let Ok(mut warned) = WARNED_CROSS_FAMILY.lock() else {
+        return false;
+    };
+    if !warned.insert((provider_id.to_string(), env_key, value.to_string())) {
+        return false;
+    }
+    log::warn!(
+        "[ModelMapper] 供应商 {provider_id} 的 {env_key}={value} 与请求模型 {request_model} 不属于同一模型家族，已忽略该映射（运行 csc audit claude 查看修正建议）"
+    );
+    true
+}
+
 /// 检测请求是否启用了 thinking 模式
 pub fn has_thinking_enabled(body: &Value) -> bool {
     body.get("thinking")
@@ -152,6 +255,41 @@ pub fn has_thinking_enabled(body: &Value) -> bool {
         == Some("enabled")
 }
 
+/// 客户端是否未指定模型：缺失、空、"auto" 或 "unknown"（部分 IDE 集成如此发送）
+pub fn is_unspecified_model(model: Option<&str>) -> bool {
+    match model.map(str::trim) {
+        None | Some("") => true,
+        Some(m) => m.eq_ignore_ascii_case("auto") || m.eq_ignore_ascii_case("unknown"),
+    }
+}
+
+/// 客户端未指定模型时补上默认模型，返回替换后的请求体（无需替换时返回 None）
+///
+/// 供应商配置了 ANTHROPIC_MODEL 时优先使用，其次为应用级 `default_model`；
+/// 替换后再走正常的模型映射与智能解析。
+pub fn apply_default_model(
+    body: &Value,
+    provider: &Provider,
+    app_default: Option<&str>,
+) -> Option<Value> {
+    if !is_unspecified_model(body.get("model").and_then(|m| m.as_str())) {
+        return None;
+    }
+    let model = ModelMapping::from_provider(provider)
+        .default_model
+        .or_else(|| {
+            app_default
+                .map(str::trim)
+                .filter(|m| !m.is_empty())
+                .map(String::from)
+        })?;
+
+    let mut body = body.clone();
+    body.as_object_mut()?
+        .insert("model".to_string(), Value::String(model));
+    Some(body)
+}
+
 /// 对请求体应用模型映射
 ///
 /// 返回 (映射后的请求体, 原始模型名, 映射后模型名)
@@ -382,5 +520,176 @@ mod tests {
         assert_eq!(result["model"], "claude-haiku-4-5");
         assert_eq!(original, Some("claude-haiku-4-5".to_string()));
         assert!(mapped.is_none());
+
+        assert_eq!(
+            cross_family_mappings(&provider),
+            vec![(
+                "ANTHROPIC_DEFAULT_HAIKU_MODEL",
+                "zai-org/GLM-4.5".to_string()
+            )]
+        );
+        assert!(cross_family_mappings(&create_provider_with_mapping()).is_empty());
+        assert!(cross_family_mappings(&create_provider_without_mapping()).is_empty());
+    }
+
+    #[test]
+    fn test_family_guard_levels_for_mapping() {
+        let provider = |guard: &str| {
+            let mut provider = create_provider_without_mapping();
+            provider.settings_config = json!({
+                "familyGuard": guard,
+                "env": {
+                    "ANTHROPIC_DEFAULT_SONNET_MODEL": "glm-4.5",
+                    "ANTHROPIC_DEFAULT_HAIKU_MODEL": "claude-sonnet-4-5"
+                }
+            });
+            provider
+        };
+        let mapped = |guard: &str, model: &str| {
+            let (result, _, _) = apply_model_mapping(json!({ "model": model }), &provider(guard));
+            result["model"].as_str().unwrap().to_string()
+        };
+        let (sonnet, haiku) = ("claude-sonnet-4-5", "claude-haiku-4-5");
+
+        // strict（默认）：跨家族映射被忽略并计入审计
+        assert_eq!(mapped("strict", sonnet), sonnet);
+        assert_eq!(mapped("unknown", sonnet), sonnet);
+        assert_eq!(cross_family_mappings(&provider("strict")).len(), 1);
+
+        // prefer：显式映射可跨家族，但 Claude 子家族守护仍生效
+        assert_eq!(mapped("prefer", sonnet), "glm-4.5");
+        assert_eq!(mapped("prefer", haiku), haiku);
+        assert!(cross_family_mappings(&provider("prefer")).is_empty());
+
+        // off：不做任何家族限制
+        assert_eq!(mapped("off", sonnet), "glm-4.5");
+        assert_eq!(mapped("off", haiku), sonnet);
+        assert!(cross_family_mappings(&provider("off")).is_empty());
+    }
+
+    #[test]
+    fn test_cross_family_runtime_warning_is_deduped() {
+        let mut provider = create_provider_without_mapping();
+        provider.id = "warn-dedup".to_string();
+        provider.settings_config = json!({
+            "env": {"ANTHROPIC_DEFAULT_HAIKU_MODEL": "glm-4.5"}
+        });
+
+        // 首次请求已告警，之后同一配置不再告警
+        apply_model_mapping(json!({"model": "claude-haiku-4-5"}), &provider);
+        apply_model_mapping(json!({"model": "claude-haiku-4-5"}), &provider);
+        assert!(!warn_cross_family_once(
+            "warn-dedup",
+            "ANTHROPIC_DEFAULT_HAIKU_MODEL",
+            "glm-4.5",
+            "claude-haiku-4-5"
+        ));
+        // 配置值或供应商变化后重新告警
+        assert!(warn_cross_family_once(
+            "warn-dedup",
+            "ANTHROPIC_DEFAULT_HAIKU_MODEL",
+            "glm-4.6",
+            "claude-haiku-4-5"
+        ));
+        assert!(warn_cross_family_once(
+            "warn-dedup-other",
+            "ANTHROPIC_DEFAULT_HAIKU_MODEL",
+            "glm-4.5",
+            "claude-haiku-4-5"
+        ));
+
+        // 子家族不一致不是配置错误，不告警
+        let mapping = ModelMapping::from_provider(&create_provider_with_reasoning_only());
+        mapping.map_model("claude-haiku-4-5", true);
+        assert!(warn_cross_family_once(
+            "test",
+            "ANTHROPIC_REASONING_MODEL",
+            "claude-sonnet-4-5-thinking",
+            "claude-haiku-4-5"
+        ));
+    }
+
+    #[test]
+    fn test_suggest_mapping_value_from_cached_models() {
+        let mut provider = create_provider_without_mapping();
+        provider.id = "suggest".to_string();
+        provider.settings_config = json!({
+            "env": {
+                "ANTHROPIC_BASE_URL": "https://suggest.example.com/",
+                "ANTHROPIC_DEFAULT_HAIKU_MODEL": "glm-4.5"
+            }
+        });
+
+        // 未缓存模型列表时不给建议
+        assert!(suggest_mapping_value(&provider, "ANTHROPIC_DEFAULT_HAIKU_MODEL").is_none());
+
+        model_resolver::seed_model_list_cache(
+            &provider,
+            &[
+                "glm-4.5",
+                "claude-haiku-4-5-20251001",
+                "claude-sonnet-4-5-thinking",
+            ],
+        );
+        assert_eq!(
+            suggest_mapping_value(&provider, "ANTHROPIC_DEFAULT_HAIKU_MODEL").as_deref(),
+            Some("claude-haiku-4-5-20251001")
+        );
+        assert_eq!(
+            suggest_mapping_value(&provider, "ANTHROPIC_REASONING_MODEL").as_deref(),
+            Some("claude-sonnet-4-5-thinking")
+        );
+        // 列表中没有 opus 时不建议其他子家族（请求时同样会被拒绝）
+        assert!(suggest_mapping_value(&provider, "ANTHROPIC_DEFAULT_OPUS_MODEL").is_none());
+        assert!(suggest_mapping_value(&provider, "UNKNOWN_KEY").is_none());
+    }
+
+    #[test]
+    fn test_default_model_fills_missing_and_auto() {
+        let provider = create_provider_without_mapping();
+        let default = Some("claude-sonnet-4-5");
+
+        let body = apply_default_model(&json!({"max_tokens": 16}), &provider, default).unwrap();
+        assert_eq!(body["model"], "claude-sonnet-4-5");
+        assert_eq!(body["max_tokens"], 16);
+        for model in ["auto", "AUTO", "unknown", " "] {
+            let body = apply_default_model(&json!({"model": model}), &provider, default).unwrap();
+            assert_eq!(body["model"], "claude-sonnet-4-5");
+        }
+
+        // 客户端指定了模型、未配置默认模型：不替换
+        assert!(
+            apply_default_model(&json!({"model": "claude-haiku-4-5"}), &provider, default)
+                .is_none()
+        );
+        assert!(apply_default_model(&json!({"model": "auto"}), &provider, None).is_none());
+        assert!(apply_default_model(&json!({"model": "auto"}), &provider, Some("  ")).is_none());
+    }
+
+    #[test]
+    fn test_provider_anthropic_model_takes_precedence_over_default() {
+        let provider = create_provider_with_mapping();
+        let body = apply_default_model(
+            &json!({"model": "auto"}),
+            &provider,
+            Some("claude-opus-4-5"),
+        )
+        .unwrap();
+        assert_eq!(body["model"], "cursor2-claude-4.5-sonnet");
+    }
+
+    #[test]
+    fn test_default_model_goes_through_mapping() {
+        let provider = Provider::with_id(
+            "test".to_string(),
+            "Test".to_string(),
+            json!({"env": {"ANTHROPIC_DEFAULT_OPUS_MODEL": "claude-opus-4-5-2cc"}}),
+            None,
+        );
+        let body = apply_default_model(&json!({}), &provider, Some("claude-opus-4-5")).unwrap();
+        let (result, original, mapped) = apply_model_mapping(body, &provider);
+        assert_eq!(original.as_deref(), Some("claude-opus-4-5"));
+        assert_eq!(mapped.as_deref(), Some("claude-opus-4-5-2cc"));
+        assert_eq!(result["model"], "claude-opus-4-5-2cc");
     }
 }
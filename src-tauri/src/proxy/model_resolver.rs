@@ -4,8 +4,15 @@
 //! 并在首次成功后写回 Provider 配置，避免后续重复匹配。
 
 use crate::provider::Provider;
+use crate::proxy::env_expand::expand_env_str;
+use crate::proxy::error_body::read_error_body;
+use crate::proxy::model_catalog::FamilyGuard;
+use crate::proxy::model_fallbacks;
+use crate::proxy::model_list::{collect_model_pages, ModelList, MAX_MODEL_PAGES};
+use crate::proxy::providers::{python_proxy_auth_header, AuthScheme};
 use once_cell::sync::Lazy;
 use regex::Regex;
+use reqwest::header::HeaderMap;
 use reqwest::Client;
 use serde_json::Value;
 use std::collections::{HashMap, HashSet};
@@ -17,6 +24,8 @@ const MODELS_ENDPOINT: &str = "/v1/models";
 const MODEL_LIST_TTL: Duration = Duration::from_secs(6 * 60 * 60); // 6h
 const MODEL_LIST_FAILURE_COOLDOWN: Duration = Duration::from_secs(30 * 60); // 30m
 const MODELS_FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+/// 整个拉取过程（含 Bearer 回退与分页）的总超时
+const MODELS_FETCH_TOTAL_TIMEOUT: Duration = Duration::from_secs(10);
 
 #[derive(Debug, Clone)]
 pub struct ModelWriteback {
@@ -35,6 +44,8 @@ struct ModelListKey {
 #[derive(Debug, Clone)]
 struct CachedModelList {
     fetched_at: Instant,
+    /// 缓存有效期：默认 MODEL_LIST_TTL，上游 `Cache-Control: max-age` 更长时以其为准
+    ttl: Duration,
     models: Vec<String>,
 }
 
@@ -69,6 +80,7 @@ fn extract_anthropic_base_url(provider: &Provider) -> Option<String> {
         .get("env")
         .and_then(|env| env.get("ANTHROPIC_BASE_URL"))
         .and_then(|v| v.as_str())
+        .and_then(|s| expand_env_str(s, "env.ANTHROPIC_BASE_URL").ok())
         .map(|s| s.trim().trim_end_matches('/').to_string())
 }
 
@@ -178,11 +190,13 @@ fn choose_best_model_with_avoid(
     thinking_from_body: bool,
     candidates: &[String],
     avoid_norm: &HashSet<String>,
+    guard: FamilyGuard,
 ) -> Option<String> {
     let req_norm = normalize_token(request_model);
     let request = parse_features(request_model, thinking_from_body);
-    let request_is_claude =
-        crate::proxy::model_catalog::detect_model_family(request_model)
+    // familyGuard=off 的供应商不做家族锚定
+    let anchor_claude = guard.anchors_resolution()
+        && crate::proxy::model_catalog::detect_model_family(request_model)
             == crate::proxy::model_catalog::ModelFamily::Claude;
 
     // 若存在至少一个同 family 候选，则强制在同 family 内选择（“优先 family”，但允许无同 family 时降级）
@@ -191,7 +205,7 @@ fn choose_best_model_with_avoid(
             if avoid_norm.contains(&normalize_token(c)) {
                 return false;
             }
-            if request_is_claude && !normalize_token(c).contains("claude") {
+            if anchor_claude && !normalize_token(c).contains("claude") {
                 return false;
             }
             parse_features(c, false).family == request.family
@@ -204,7 +218,7 @@ fn choose_best_model_with_avoid(
             continue;
         }
         // 家族锚定：Claude 请求严禁映射到非 Claude（例如 GLM/GPT）
-        if request_is_claude && !cn.contains("claude") {
+        if anchor_claude && !cn.contains("claude") {
             continue;
         }
         if cn == req_norm {
@@ -221,7 +235,7 @@ fn choose_best_model_with_avoid(
             continue;
         }
         // 家族锚定：Claude 请求严禁映射到非 Claude（例如 GLM/GPT）
-        if request_is_claude && !cn.contains("claude") {
+        if anchor_claude && !cn.contains("claude") {
             continue;
         }
 
@@ -248,13 +262,18 @@ fn choose_best_model_with_avoid(
     best.map(|(_, m)| m)
 }
 
-#[cfg(test)]
-fn choose_best_model(
+pub(crate) fn choose_best_model(
     request_model: &str,
     thinking_from_body: bool,
     candidates: &[String],
 ) -> Option<String> {
-    choose_best_model_with_avoid(request_model, thinking_from_body, candidates, &HashSet::new())
+    choose_best_model_with_avoid(
+        request_model,
+        thinking_from_body,
+        candidates,
+        &HashSet::new(),
+        FamilyGuard::Strict,
+    )
 }
 
 fn determine_writeback_key(original_request_model: &str, thinking_from_body: bool) -> &'static str {
@@ -287,7 +306,8 @@ async fn fetch_models_via_python_proxy(
     client: &Client,
     base_url: &str,
     api_key: &str,
-) -> Result<Vec<String>, String> {
+    scheme: Option<&AuthScheme>,
+) -> Result<ModelList, String> {
     let url = format!(
         "{}{MODELS_ENDPOINT}",
         crate::proxy::python_proxy::python_proxy_base()
@@ -297,13 +317,14 @@ async fn fetch_models_via_python_proxy(
         client: &Client,
         url: &str,
         base_url: &str,
-        api_key_value: &str,
-    ) -> Result<Value, String> {
+        (auth_header, auth_value): &(String, String),
+    ) -> Result<(HeaderMap, Value), String> {
         let resp = client
             .get(url)
             .timeout(MODELS_FETCH_TIMEOUT)
-            // Python 代理会把 X-API-Key 注入为 x-api-key 或 authorization（取决于 value 前缀）
-            .header("X-API-Key", api_key_value)
+            // Python 代理会把 X-API-Key 注入为 x-api-key 或 authorization（取决于 value 前缀），
+            // 其它认证头原样透传
+            .header(auth_header, auth_value)
             .header("x-target-base-url", base_url)
             // 一些 Anthropic 兼容网关会要求该头存在；对于 OpenAI 风格网关一般会忽略
             .header("anthropic-version", "2023-06-01")
@@ -313,27 +334,33 @@ async fn fetch_models_via_python_proxy(
 
         if !resp.status().is_success() {
             let status = resp.status().as_u16();
-            let text = resp.text().await.unwrap_or_default();
+            let text = read_error_body(resp).await.unwrap_or_default();
             return Err(format!("请求 /v1/models 返回非 2xx: {status} body={}", text));
         }
 
+        let headers = resp.headers().clone();
         resp.json::<Value>()
             .await
+            .map(|v| (headers, v))
             .map_err(|e| format!("解析 /v1/models JSON 失败: {e}"))
     }
 
     // 兼容：部分 NewAPI/聚合服务对 /v1/messages 接受 x-api-key，但 /v1/models 只接受 Authorization: Bearer。
     // Python 代理的规则：当传入的 X-API-Key value 以 "Bearer " 开头时，会注入 authorization 头。
-    let v = match do_fetch(client, &url, base_url, api_key).await {
-        Ok(v) => v,
+    // 显式配置了认证方式时只按该方式请求，不做前缀推断与 Bearer 回退。
+    let mut auth_header = python_proxy_auth_header(scheme, api_key);
+    let (headers, v) = match do_fetch(client, &url, base_url, &auth_header).await {
+        Ok(page) => page,
+        Err(e1) if scheme.is_some() => return Err(e1),
         Err(e1) => {
             // 对 Anthropic 官方 key（sk-ant-*）不再尝试 Bearer；避免误用导致额外失败日志
             if api_key.trim_start().starts_with("sk-ant-") || api_key.trim_start().starts_with("Bearer ") {
                 return Err(e1);
             }
             let bearer = format!("Bearer {}", api_key.trim());
-            match do_fetch(client, &url, base_url, &bearer).await {
-                Ok(v) => v,
+            auth_header = python_proxy_auth_header(None, &bearer);
+            match do_fetch(client, &url, base_url, &auth_header).await {
+                Ok(page) => page,
                 Err(e2) => {
                     return Err(format!("{e1}; fallback_bearer={e2}"));
                 }
@@ -341,67 +368,61 @@ async fn fetch_models_via_python_proxy(
         }
     };
 
-    // OpenAI 兼容：{ data: [{ id: "..." }, ...] }
-    let mut out = Vec::new();
-    if let Some(arr) = v.get("data").and_then(|d| d.as_array()) {
-        for item in arr {
-            if let Some(id) = item.get("id").and_then(|x| x.as_str()) {
-                if !id.trim().is_empty() {
-                    out.push(id.trim().to_string());
-                }
-            }
-        }
-    }
+    // 跟随分页（沿用首页成功的认证头），合并去重（保持顺序）
+    let auth_header = &auth_header;
+    let fetch_page =
+        |page_url: String| async move { do_fetch(client, &page_url, base_url, auth_header).await };
+    let list = collect_model_pages(&url, &headers, &v, MAX_MODEL_PAGES, fetch_page).await;
 
-    // 兜底：一些服务可能返回 { models: [...] } 或 { data: ["id", ...] }
-    if out.is_empty() {
-        if let Some(arr) = v.get("models").and_then(|d| d.as_array()) {
-            for item in arr {
-                if let Some(id) = item.get("id").and_then(|x| x.as_str()) {
-                    if !id.trim().is_empty() {
-                        out.push(id.trim().to_string());
-                    }
-                } else if let Some(s) = item.as_str() {
-                    if !s.trim().is_empty() {
-                        out.push(s.trim().to_string());
-                    }
-                }
-            }
-        }
-    }
-    if out.is_empty() {
-        if let Some(arr) = v.get("data").and_then(|d| d.as_array()) {
-            for item in arr {
-                if let Some(s) = item.as_str() {
-                    if !s.trim().is_empty() {
-                        out.push(s.trim().to_string());
-                    }
-                }
-            }
-        }
+    if list.ids.is_empty() {
+        return Err("上游 /v1/models 返回为空或不兼容".to_string());
     }
 
-    // 去重（保持顺序）
-    let mut seen = HashSet::new();
-    out.retain(|m| seen.insert(normalize_token(m)));
+    Ok(list)
+}
 
-    if out.is_empty() {
-        return Err("上游 /v1/models 返回为空或不兼容".to_string());
-    }
+/// 读取供应商已缓存（TTL 内）的模型列表，不触发拉取
+///
+/// 缓存只存在于代理进程内；代理未运行或尚未处理过该供应商的请求时返回 None。
+pub fn cached_model_list(provider: &Provider) -> Option<Vec<String>> {
+    let key = ModelListKey {
+        provider_id: provider.id.clone(),
+        base_url: extract_anthropic_base_url(provider)?,
+    };
+    let cache = MODEL_LIST_CACHE.lock().ok()?;
+    cache
+        .get(&key)
+        .filter(|v| v.fetched_at.elapsed() <= v.ttl)
+        .map(|v| v.models.clone())
+}
 
-    Ok(out)
+#[cfg(test)]
+pub(crate) fn seed_model_list_cache(provider: &Provider, models: &[&str]) {
+    let key = ModelListKey {
+        provider_id: provider.id.clone(),
+        base_url: extract_anthropic_base_url(provider).expect("base_url"),
+    };
+    MODEL_LIST_CACHE.lock().unwrap().insert(
+        key,
+        CachedModelList {
+            fetched_at: Instant::now(),
+            ttl: MODEL_LIST_TTL,
+            models: models.iter().map(|m| m.to_string()).collect(),
+        },
+    );
 }
 
 async fn get_or_fetch_model_list(
     client: &Client,
     key: &ModelListKey,
     api_key: &str,
+    scheme: Option<&AuthScheme>,
 ) -> Option<Vec<String>> {
     // 1) TTL 缓存命中
     {
         let cache = MODEL_LIST_CACHE.lock().ok()?;
         if let Some(v) = cache.get(key) {
-            if v.fetched_at.elapsed() <= MODEL_LIST_TTL {
+            if v.fetched_at.elapsed() <= v.ttl {
                 return Some(v.models.clone());
             }
         }
@@ -418,37 +439,85 @@ async fn get_or_fetch_model_list(
     }
 
     // 3) 拉取
-    match fetch_models_via_python_proxy(client, &key.base_url, api_key).await {
-        Ok(models) => {
+    match fetch_and_cache_model_list(client, key, api_key, scheme).await {
+        Ok(models) => Some(models),
+        Err(e) => {
+            log::debug!(
+                "[ModelResolver] /v1/models 拉取失败 provider={} base_url={} err={}",
+                key.provider_id,
+                key.base_url,
+                e
+            );
+            None
+        }
+    }
+}
+
+/// 拉取模型列表并更新缓存；失败时记录失败时间（进入冷却）
+async fn fetch_and_cache_model_list(
+    client: &Client,
+    key: &ModelListKey,
+    api_key: &str,
+    scheme: Option<&AuthScheme>,
+) -> Result<Vec<String>, String> {
+    let fetched = tokio::time::timeout(
+        MODELS_FETCH_TOTAL_TIMEOUT,
+        fetch_models_via_python_proxy(client, &key.base_url, api_key, scheme),
+    )
+    .await
+    .unwrap_or_else(|_| {
+        Err(format!(
+            "拉取模型列表超时（{}s）",
+            MODELS_FETCH_TOTAL_TIMEOUT.as_secs()
+        ))
+    });
+    match fetched {
+        Ok(list) => {
+            let models = list.ids.clone();
             if let Ok(mut cache) = MODEL_LIST_CACHE.lock() {
                 cache.insert(
                     key.clone(),
                     CachedModelList {
                         fetched_at: Instant::now(),
-                        models: models.clone(),
+                        ttl: list.cache_ttl(MODEL_LIST_TTL),
+                        models: list.ids,
                     },
                 );
             }
             if let Ok(mut failures) = MODEL_LIST_FAILURES.lock() {
                 failures.remove(key);
             }
-            Some(models)
+            Ok(models)
         }
         Err(e) => {
-            log::debug!(
-                "[ModelResolver] /v1/models 拉取失败 provider={} base_url={} err={}",
-                key.provider_id,
-                key.base_url,
-                e
-            );
             if let Ok(mut failures) = MODEL_LIST_FAILURES.lock() {
                 failures.insert(key.clone(), Instant::now());
             }
-            None
+            Err(e)
         }
     }
 }
 
+/// 预热：忽略缓存与失败冷却重新拉取模型列表（保存供应商后调用），返回模型数
+///
+/// 修改过 key 的供应商不应继续沿用旧 key 的失败冷却。
+pub(crate) async fn warm_model_list(
+    client: &Client,
+    provider: &Provider,
+    api_key: &str,
+) -> Result<usize, String> {
+    let base_url = extract_anthropic_base_url(provider)
+        .ok_or_else(|| "缺少 ANTHROPIC_BASE_URL 配置".to_string())?;
+    let key = ModelListKey {
+        provider_id: provider.id.clone(),
+        base_url,
+    };
+    let scheme = AuthScheme::from_provider(provider);
+    fetch_and_cache_model_list(client, &key, api_key, scheme.as_ref())
+        .await
+        .map(|models| models.len())
+}
+
 /// Claude 模型名称智能解析（默认启用）
 ///
 /// - 优先使用 provider 当前配置的 model（若其本来就在 /v1/models 列表内）
@@ -477,19 +546,14 @@ pub async fn resolve_claude_model_in_body_with_avoid(
     provider: &Provider,
     api_key: &str,
     original_request_model: &str,
-    mut body: Value,
+    body: Value,
     avoid_models: &[&str],
 ) -> (Value, Option<ModelWriteback>) {
     // 仅对“看起来像 Claude 模型”的请求启用解析，避免误处理其它模型体系
-    let request_features = parse_features(original_request_model, false);
-    let is_claudeish = request_features.family.is_some()
-        || (original_request_model.to_lowercase().contains("claude")
-            && (request_features.major.is_some() || request_features.minor.is_some()));
-    if !is_claudeish {
+    if !is_claudeish_request(original_request_model) {
         return (body, None);
     }
 
-    let thinking_from_body = crate::proxy::model_mapper::has_thinking_enabled(&body);
     let avoid_norm: HashSet<String> = avoid_models.iter().map(|s| normalize_token(s)).collect();
 
     let Some(base_url) = extract_anthropic_base_url(provider) else {
@@ -500,10 +564,55 @@ pub async fn resolve_claude_model_in_body_with_avoid(
         base_url,
     };
 
-    let Some(models) = get_or_fetch_model_list(client, &key, api_key).await else {
+    let scheme = AuthScheme::from_provider(provider);
+    let Some(models) = get_or_fetch_model_list(client, &key, api_key, scheme.as_ref()).await else {
         return (body, None);
     };
 
+    resolve_claude_against_models(provider, original_request_model, body, &models, &avoid_norm)
+}
+
+/// 仅使用已缓存的模型列表做智能解析（dry-run 使用，不拉取 /v1/models）
+///
+/// 第二个返回值为解析依据：`model-list`（命中缓存）、`no-cache`（实际请求会先拉取模型列表）、
+/// `skipped`（非 Claude 模型，不解析）。
+pub(crate) fn resolve_claude_model_cached(
+    provider: &Provider,
+    original_request_model: &str,
+    body: Value,
+) -> (Value, &'static str) {
+    if !is_claudeish_request(original_request_model) {
+        return (body, "skipped");
+    }
+    let Some(models) = cached_model_list(provider) else {
+        return (body, "no-cache");
+    };
+    let (body, _) = resolve_claude_against_models(
+        provider,
+        original_request_model,
+        body,
+        &models,
+        &HashSet::new(),
+    );
+    (body, "model-list")
+}
+
+fn is_claudeish_request(original_request_model: &str) -> bool {
+    let request_features = parse_features(original_request_model, false);
+    request_features.family.is_some()
+        || (original_request_model.to_lowercase().contains("claude")
+            && (request_features.major.is_some() || request_features.minor.is_some()))
+}
+
+fn resolve_claude_against_models(
+    provider: &Provider,
+    original_request_model: &str,
+    mut body: Value,
+    models: &[String],
+    avoid_norm: &HashSet<String>,
+) -> (Value, Option<ModelWriteback>) {
+    let thinking_from_body = crate::proxy::model_mapper::has_thinking_enabled(&body);
+
     let current_model = body
         .get("model")
         .and_then(|m| m.as_str())
@@ -513,19 +622,30 @@ pub async fn resolve_claude_model_in_body_with_avoid(
     };
 
     // 如果当前 model 已在上游列表内，则直接使用（无需智能匹配/写回），除非显式要求避开该 model
-    if is_model_in_list(&current_model, &models)
+    if is_model_in_list(&current_model, models)
         && !avoid_norm.contains(&normalize_token(&current_model))
     {
         return (body, None);
     }
 
-    // 基于“原始请求模型”做智能匹配（保留 family/版本信息）
-    let chosen = choose_best_model_with_avoid(
+    // 回退链（modelFallbacks）中在列表内的模型优先；否则基于“原始请求模型”做智能匹配（保留 family/版本信息）
+    let avoid: Vec<String> = avoid_norm.iter().cloned().collect();
+    let chosen = model_fallbacks::preferred_in_list(
+        provider,
         original_request_model,
-        thinking_from_body,
-        &models,
-        &avoid_norm,
-    );
+        &current_model,
+        models,
+        &avoid,
+    )
+    .or_else(|| {
+        choose_best_model_with_avoid(
+            original_request_model,
+            thinking_from_body,
+            models,
+            avoid_norm,
+            provider.family_guard(),
+        )
+    });
     let Some(chosen) = chosen else {
         return (body, None);
     };
@@ -534,39 +654,52 @@ pub async fn resolve_claude_model_in_body_with_avoid(
         return (body, None);
     }
 
-    // 生成写回建议：仅当写回目标 key 不存在或与目标不同才写回
-    let env_key = determine_writeback_key(original_request_model, thinking_from_body);
-    let existing = read_env_model(provider, env_key);
-    let needs_writeback = existing
-        .as_deref()
-        .map(|v| normalize_token(v) != normalize_token(&chosen))
-        .unwrap_or(true);
-
+    let writeback = claude_writeback(
+        provider,
+        original_request_model,
+        thinking_from_body,
+        &current_model,
+        &chosen,
+    );
     log::debug!(
-        "[ModelResolver] provider={} model {} → {} (writeback_key={} {})",
+        "[ModelResolver] provider={} model {} → {} ({})",
         provider.id,
         current_model,
         chosen,
-        env_key,
-        if needs_writeback { "pending" } else { "skip" }
+        if writeback.is_some() {
+            "writeback pending"
+        } else {
+            "writeback skip"
+        }
     );
 
-    body["model"] = serde_json::json!(chosen.clone());
-
-    let writeback = if needs_writeback {
-        Some(ModelWriteback {
-            env_key,
-            value: chosen.clone(),
-            from_model: current_model,
-            to_model: chosen,
-        })
-    } else {
-        None
-    };
-
+    body["model"] = serde_json::json!(chosen);
     (body, writeback)
 }
 
+/// 生成写回建议：仅当写回目标 key 不存在或与目标不同才写回
+///
+/// 回退链重试成功后同样使用，记录实际可用的模型。
+pub(crate) fn claude_writeback(
+    provider: &Provider,
+    original_request_model: &str,
+    thinking_from_body: bool,
+    from_model: &str,
+    chosen: &str,
+) -> Option<ModelWriteback> {
+    let env_key = determine_writeback_key(original_request_model, thinking_from_body);
+    let needs_writeback = read_env_model(provider, env_key)
+        .as_deref()
+        .map(|v| normalize_token(v) != normalize_token(chosen))
+        .unwrap_or(true);
+    needs_writeback.then(|| ModelWriteback {
+        env_key,
+        value: chosen.to_string(),
+        from_model: from_model.to_string(),
+        to_model: chosen.to_string(),
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -588,6 +721,46 @@ mod tests {
         assert!(f.thinking);
     }
 
+    #[test]
+    fn claude_fallback_chain_is_preferred_over_scoring() {
+        let provider = Provider::with_id(
+            "p".to_string(),
+            "p".to_string(),
+            serde_json::json!({
+                "modelFallbacks": {
+                    "claude-sonnet-4-5": [
+                        "claude-sonnet-4-1",
+                        "claude-haiku-4-5",
+                        "claude-haiku-4-5"
+                    ]
+                }
+            }),
+            None,
+        );
+        let models = vec![
+            "claude-haiku-4-5".to_string(),
+            "cursor2-claude-4.5-sonnet".to_string(),
+        ];
+        let body = serde_json::json!({ "model": "claude-sonnet-4-5" });
+
+        // 回退链中第一个在列表内的模型优先于打分（打分会选同 family 的 sonnet）
+        let (resolved, writeback) = resolve_claude_against_models(
+            &provider,
+            "claude-sonnet-4-5",
+            body.clone(),
+            &models,
+            &HashSet::new(),
+        );
+        assert_eq!(resolved["model"], "claude-haiku-4-5");
+        assert!(writeback.is_some());
+
+        // 回退链模型被避开后退回打分选择
+        let avoid: HashSet<String> = [normalize_token("claude-haiku-4-5")].into();
+        let (resolved, _) =
+            resolve_claude_against_models(&provider, "claude-sonnet-4-5", body, &models, &avoid);
+        assert_eq!(resolved["model"], "cursor2-claude-4.5-sonnet");
+    }
+
     #[test]
     fn choose_best_prefers_family_then_version_then_thinking() {
         let candidates = vec![
@@ -604,4 +777,146 @@ mod tests {
         let chosen = choose_best_model("claude-sonnet-4-5-20250929", true, &candidates).unwrap();
         assert_eq!(chosen, "claude-sonnet-4-5-thinking");
     }
+
+    #[test]
+    fn family_guard_levels_for_claude_resolution() {
+        let models = vec!["glm-4.5".to_string(), "glm-4.5-air".to_string()];
+        for (guard, expected) in [
+            ("strict", "claude-sonnet-4-5"),
+            ("prefer", "claude-sonnet-4-5"),
+            ("off", "glm-4.5"),
+        ] {
+            let provider = Provider::with_id(
+                "p1".to_string(),
+                "P1".to_string(),
+                serde_json::json!({ "familyGuard": guard }),
+                None,
+            );
+            let body = serde_json::json!({ "model": "claude-sonnet-4-5" });
+            let (body, _) = resolve_claude_against_models(
+                &provider,
+                "claude-sonnet-4-5",
+                body,
+                &models,
+                &HashSet::new(),
+            );
+            assert_eq!(body["model"], expected, "{guard}");
+        }
+    }
+
+    #[tokio::test]
+    #[serial_test::serial]
+    async fn fetch_models_via_python_proxy_sends_explicit_auth_header() {
+        use axum::{http::HeaderMap, routing::get, Json, Router};
+
+        // 充当 Python 代理（原样透传请求头）：把收到的认证头回显为模型 ID
+        let app = Router::new().route(
+            "/v1/models",
+            get(|headers: HeaderMap| async move {
+                let ids: Vec<Value> = ["x-api-key", "authorization", "api-key"]
+                    .into_iter()
+                    .filter_map(|name| {
+                        let value = headers.get(name)?.to_str().ok()?;
+                        Some(serde_json::json!({ "id": format!("{name}={value}") }))
+                    })
+                    .collect();
+                assert_eq!(
+                    headers
+                        .get("x-target-base-url")
+                        .and_then(|v| v.to_str().ok()),
+                    Some("https://gw.example.com")
+                );
+                Json(serde_json::json!({ "data": ids }))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+        std::env::set_var("CC_SWITCH_PYTHON_PROXY_BASE", format!("http://{addr}"));
+
+        let client = Client::new();
+        let fetch = |scheme: Option<AuthScheme>| {
+            let client = client.clone();
+            async move {
+                fetch_models_via_python_proxy(
+                    &client,
+                    "https://gw.example.com",
+                    "sk-gw",
+                    scheme.as_ref(),
+                )
+                .await
+            }
+        };
+
+        let x_api_key = fetch(Some(AuthScheme::XApiKey)).await;
+        let bearer = fetch(Some(AuthScheme::Bearer)).await;
+        let header = fetch(Some(AuthScheme::Header("api-key".to_string()))).await;
+        let default = fetch(None).await;
+        std::env::remove_var("CC_SWITCH_PYTHON_PROXY_BASE");
+
+        assert_eq!(x_api_key.unwrap().ids, vec!["x-api-key=sk-gw"]);
+        assert_eq!(bearer.unwrap().ids, vec!["authorization=Bearer sk-gw"]);
+        assert_eq!(header.unwrap().ids, vec!["api-key=sk-gw"]);
+        // 未配置：沿用 X-API-Key，由 Python 代理按前缀注入
+        assert_eq!(default.unwrap().ids, vec!["x-api-key=sk-gw"]);
+    }
+
+    #[tokio::test]
+    #[serial_test::serial]
+    async fn fetch_models_via_python_proxy_follows_pagination() {
+        use axum::{extract::Query, http::HeaderMap, routing::get, Json, Router};
+
+        // 两页（after_id 翻页），每页都要求带上首页成功的认证头
+        let app = Router::new().route(
+            "/v1/models",
+            get(
+                |headers: HeaderMap, Query(q): Query<HashMap<String, String>>| async move {
+                    assert_eq!(
+                        headers.get("x-api-key").and_then(|v| v.to_str().ok()),
+                        Some("sk-gw")
+                    );
+                    let body = match q.get("after_id").map(String::as_str) {
+                        None => serde_json::json!({
+                            "data": [{ "id": "claude-haiku-4-5" }, { "id": "claude-sonnet-4-5" }],
+                            "has_more": true,
+                            "last_id": "claude-sonnet-4-5"
+                        }),
+                        Some("claude-sonnet-4-5") => serde_json::json!({
+                            "data": [{ "id": "claude-sonnet-4-5" }, { "id": "claude-opus-4-5" }],
+                            "has_more": false
+                        }),
+                        Some(other) => panic!("unexpected after_id {other}"),
+                    };
+                    ([("cache-control", "max-age=604800")], Json(body))
+                },
+            ),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+        std::env::set_var("CC_SWITCH_PYTHON_PROXY_BASE", format!("http://{addr}"));
+
+        let list = fetch_models_via_python_proxy(
+            &Client::new(),
+            "https://gw.example.com",
+            "sk-gw",
+            Some(&AuthScheme::XApiKey),
+        )
+        .await;
+        std::env::remove_var("CC_SWITCH_PYTHON_PROXY_BASE");
+
+        let list = list.unwrap();
+        assert_eq!(
+            list.ids,
+            vec!["claude-haiku-4-5", "claude-sonnet-4-5", "claude-opus-4-5"]
+        );
+        assert_eq!(
+            list.cache_ttl(MODEL_LIST_TTL),
+            Duration::from_secs(7 * 24 * 60 * 60)
+        );
+    }
 }
@@ -1,6 +1,8 @@
 //! 模型名称清洗（用于避免“伪模型名”污染请求/日志/缓存）
 //!
 //! 当前重点：OpenAI/Codex 的 `gpt-*` 模型不允许携带日期后缀（例如 `gpt-5.2-2025-12-11`）。
+//! 已知供应商模型列表时使用 [`sanitize_gpt_model_name_against`]：仅在截断后的名称确实存在时才截断，
+//! 避免误伤 `gpt-5.2-1120-preview` 这类真实存在的版本号后缀。
 
 use serde_json::Value;
 
@@ -73,6 +75,34 @@ pub fn sanitize_gpt_model_name(model: &str) -> String {
     trimmed.to_string()
 }
 
+/// 以供应商模型列表为准的清洗（先验证再截断）：
+/// - 列表为空（未知）：退回 [`sanitize_gpt_model_name`] 的启发式截断
+/// - 原名在列表中（忽略大小写）：原样保留
+/// - 截断后的名称在列表中：返回列表中的写法
+/// - 都不在：保留原名，交由后续的智能匹配处理
+pub fn sanitize_gpt_model_name_against(model: &str, models: &[String]) -> String {
+    let trimmed = model.trim();
+    if models.is_empty() {
+        return sanitize_gpt_model_name(trimmed);
+    }
+    if models
+        .iter()
+        .any(|m| m.trim().eq_ignore_ascii_case(trimmed))
+    {
+        return trimmed.to_string();
+    }
+    let sanitized = sanitize_gpt_model_name(trimmed);
+    if sanitized != trimmed {
+        if let Some(hit) = models
+            .iter()
+            .find(|m| m.trim().eq_ignore_ascii_case(&sanitized))
+        {
+            return hit.trim().to_string();
+        }
+    }
+    trimmed.to_string()
+}
+
 /// 若 body 中存在 `model` 字段且需要清洗，则原地替换并返回 (from,to)
 pub fn sanitize_openai_model_in_body(body: &mut Value) -> Option<(String, String)> {
     let m = body.get("model")?.as_str()?.to_string();
@@ -126,6 +156,40 @@ mod tests {
         );
     }
 
+    #[test]
+    fn sanitize_against_strips_only_when_stripped_form_is_listed() {
+        let models = vec!["gpt-4".to_string(), "GPT-4o".to_string()];
+        assert_eq!(
+            sanitize_gpt_model_name_against("gpt-4-0613", &models),
+            "gpt-4"
+        );
+        assert_eq!(
+            sanitize_gpt_model_name_against("gpt-4o-2024-08-06", &models),
+            "GPT-4o"
+        );
+        // 列表未知：保持启发式截断
+        assert_eq!(sanitize_gpt_model_name_against("gpt-4-0613", &[]), "gpt-4");
+        assert_eq!(
+            sanitize_gpt_model_name_against("gpt-5.2-2025-12-11", &[]),
+            "gpt-5.2"
+        );
+    }
+
+    #[test]
+    fn sanitize_against_preserves_listed_version_suffix() {
+        let models = vec!["gpt-5.2".to_string(), "gpt-5.2-1120-preview".to_string()];
+        assert_eq!(
+            sanitize_gpt_model_name_against("gpt-5.2-1120-preview", &models),
+            "gpt-5.2-1120-preview"
+        );
+        // 截断后的名称也不在列表中：保留原名，交由智能匹配
+        let models = vec!["gpt-5.1".to_string()];
+        assert_eq!(
+            sanitize_gpt_model_name_against("gpt-5.2-1120-preview", &models),
+            "gpt-5.2-1120-preview"
+        );
+    }
+
     #[test]
     fn sanitize_body_rewrites_model() {
         let mut body = json!({"model":"gpt-5.2-2025-12-11"});
@@ -0,0 +1,188 @@
+//! 模型解析追踪
+//!
+//! 请求模型依次经过 默认模型 → 显式映射 → 名称清洗 / 别名 / 模型列表解析 → 请求体变换 → 不可用重试，
+//! 每一步实际改写模型时追加一条记录（步骤、改写前、改写后、依据），用于还原“上游为什么看到这个模型”。
+//! 追踪只做记录，不影响解析结果；紧凑格式用于 debug 日志与 `x-cc-switch-trace` 响应头：
+//!
+//! `default:->claude-sonnet-4-5(unspecified)|mapping:claude-sonnet-4-5>sonnet-cc(env)|retry:sonnet-cc>sonnet-b(fallback-chain)`
+
+use super::model_fallbacks::fallback_chain;
+use super::model_sanitizer::sanitize_gpt_model_name;
+use super::openai_model_resolver::alias_target;
+use crate::provider::Provider;
+use serde::Serialize;
+
+/// 客户端未指定模型，补上默认模型
+pub const STEP_DEFAULT: &str = "default";
+/// 显式映射（ANTHROPIC_DEFAULT_* / ANTHROPIC_MODEL / ANTHROPIC_REASONING_MODEL）
+pub const STEP_MAPPING: &str = "mapping";
+/// 截断版本/日期后缀
+pub const STEP_SANITIZE: &str = "sanitize";
+/// 命中已写回/显式别名
+pub const STEP_ALIAS: &str = "alias";
+/// 智能解析（回退链优先，其次模型列表打分）
+pub const STEP_RESOLVER: &str = "resolver";
+/// Bedrock 模型 ID 换算
+pub const STEP_BEDROCK: &str = "bedrock";
+/// 供应商请求体变换（bodyTransforms）改写了模型
+pub const STEP_TRANSFORM: &str = "transform";
+/// 上游提示模型不可用后的同供应商重试
+pub const STEP_RETRY: &str = "retry";
+/// 供应商锁定模型（modelLock），原样转发
+pub const STEP_LOCKED: &str = "locked";
+
+/// 追踪中的一步
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelResolutionStep {
+    pub step: &'static str,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub reason: &'static str,
+}
+
+/// 单个供应商上的模型解析追踪（按发生顺序）
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct ModelResolutionTrace {
+    steps: Vec<ModelResolutionStep>,
+}
+
+impl ModelResolutionTrace {
+    /// 无条件追加一步（重试、锁定等即使模型未变也需要记录）
+    pub fn record(
+        &mut self,
+        step: &'static str,
+        from: Option<&str>,
+        to: Option<&str>,
+        reason: &'static str,
+    ) {
+        self.steps.push(ModelResolutionStep {
+            step,
+            from: from.map(str::to_string),
+            to: to.map(str::to_string),
+            reason,
+        });
+    }
+
+    /// 仅当该步实际改写了模型时追加
+    pub fn record_change(
+        &mut self,
+        step: &'static str,
+        from: Option<&str>,
+        to: Option<&str>,
+        reason: &'static str,
+    ) {
+        if from != to {
+            self.record(step, from, to, reason);
+        }
+    }
+
+    pub fn steps(&self) -> &[ModelResolutionStep] {
+        &self.steps
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// 紧凑格式：`步骤:改写前>改写后(依据)`，以 `|` 分隔；缺失的模型记为 `-`
+    pub fn render(&self) -> String {
+        self.steps
+            .iter()
+            .map(|s| {
+                format!(
+                    "{}:{}>{}({})",
+                    s.step,
+                    s.from.as_deref().unwrap_or("-"),
+                    s.to.as_deref().unwrap_or("-"),
+                    s.reason
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("|")
+    }
+}
+
+/// 智能解析一步的归因（不重新解析，只根据解析前后的模型判断走了哪条分支）
+///
+/// 返回 (步骤, 依据)：Codex 的别名命中与名称清洗单独成步；其余为回退链或模型列表打分。
+pub fn classify_resolution(
+    provider: &Provider,
+    is_claude: bool,
+    request_model: &str,
+    from: &str,
+    to: &str,
+) -> (&'static str, &'static str) {
+    if !is_claude
+        && alias_target(provider, request_model).is_some_and(|m| m.eq_ignore_ascii_case(to))
+    {
+        return (STEP_ALIAS, "alias-map");
+    }
+    if fallback_chain(provider, request_model, from, &[])
+        .iter()
+        .any(|m| m.eq_ignore_ascii_case(to))
+    {
+        return (STEP_RESOLVER, "fallback-chain");
+    }
+    if !is_claude && sanitize_gpt_model_name(from).eq_ignore_ascii_case(to) {
+        return (STEP_SANITIZE, "date-suffix");
+    }
+    (STEP_RESOLVER, "model-list")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn render_and_classify() {
+        let provider = Provider::with_id(
+            "p".to_string(),
+            "p".to_string(),
+            json!({
+                "env": { "CC_SWITCH_CODEX_MODEL_ALIASES": json!({ "gpt-5.2": "gpt-5.2-codex" }).to_string() },
+                "modelFallbacks": { "gpt-5": ["gpt-5-mini"] }
+            }),
+            None,
+        );
+        assert_eq!(
+            classify_resolution(&provider, false, "gpt-5.2", "gpt-5.2", "gpt-5.2-codex"),
+            (STEP_ALIAS, "alias-map")
+        );
+        assert_eq!(
+            classify_resolution(&provider, false, "gpt-5", "gpt-5", "gpt-5-mini"),
+            (STEP_RESOLVER, "fallback-chain")
+        );
+        assert_eq!(
+            classify_resolution(
+                &provider,
+                false,
+                "gpt-4o-2024-08-06",
+                "gpt-4o-2024-08-06",
+                "gpt-4o"
+            ),
+            (STEP_SANITIZE, "date-suffix")
+        );
+
+        let mut trace = ModelResolutionTrace::default();
+        trace.record_change(STEP_MAPPING, Some("a"), Some("a"), "env");
+        assert!(trace.is_empty());
+        trace.record(STEP_DEFAULT, None, Some("claude-sonnet-4-5"), "unspecified");
+        trace.record_change(
+            STEP_MAPPING,
+            Some("claude-sonnet-4-5"),
+            Some("sonnet-cc"),
+            "env",
+        );
+        assert_eq!(
+            trace.render(),
+            "default:->claude-sonnet-4-5(unspecified)|mapping:claude-sonnet-4-5>sonnet-cc(env)"
+        );
+        assert_eq!(
+            serde_json::to_value(&trace).unwrap()[1],
+            json!({ "step": "mapping", "from": "claude-sonnet-4-5", "to": "sonnet-cc", "reason": "env" })
+        );
+    }
+}
@@ -0,0 +1,304 @@
+//! 保存供应商后的模型列表预热
+//!
+//! 新增或修改供应商后，第一个真实请求需要先拉取 `/v1/models`，多出一次往返；key 填错时拉取失败还会
+//! 进入失败冷却，要等真实请求出错才会发现问题。保存成功后在后台预热：
+//! - 按应用走与请求相同的解析路径拉取模型列表并写入缓存（忽略旧的失败冷却）
+//! - 拉取失败说明 key 或 base_url 可能有误：记录 `model_warmup_failed` 事件（事件列表与 `csc doctor` 可见）
+//! - 尽力而为：不影响保存结果；同一供应商 60 秒内只成功预热一次（失败后修正配置再保存会重新预热）；
+//!   只读模式下跳过
+//!
+//! Gemini 暂无模型列表解析，不预热。
+
+use super::event_log::EventRecorder;
+use super::server::ProxyState;
+use super::{model_resolver, openai_model_resolver, providers::get_adapter};
+use crate::app_config::AppType;
+use crate::database::{Database, EventKind};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 同一供应商两次预热的最小间隔
+const MIN_WARMUP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// (app_type, provider_id) -> 上次预热时间
+static LAST_WARMUP: Lazy<Mutex<HashMap<(String, String), Instant>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 预热结果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum WarmupOutcome {
+    /// 已拉取并缓存模型列表
+    Warmed { models: usize },
+    /// 未预热（只读模式、频率限制、应用不支持等）
+    Skipped { reason: String },
+    /// 拉取失败（已记录事件）
+    Failed { error: String },
+}
+
+impl WarmupOutcome {
+    fn skipped(reason: &str) -> Self {
+        Self::Skipped {
+            reason: reason.to_string(),
+        }
+    }
+}
+
+/// 模型列表预热器
+#[derive(Clone)]
+pub struct ModelWarmup {
+    db: Arc<Database>,
+    events: EventRecorder,
+    read_only: bool,
+}
+
+impl ModelWarmup {
+    pub fn new(db: Arc<Database>, events: EventRecorder, read_only: bool) -> Self {
+        Self {
+            db,
+            events,
+            read_only,
+        }
+    }
+
+    /// 复用运行中代理的事件记录器与只读设置（预热结果写入代理进程内的模型列表缓存）
+    pub(crate) fn for_proxy(state: &ProxyState) -> Self {
+        Self::new(
+            state.db.clone(),
+            state.provider_router.event_recorder().clone(),
+            state.provider_router.is_read_only(),
+        )
+    }
+
+    /// 代理未运行时使用：只读设置取自全局代理配置，事件单独写入
+    pub async fn standalone(db: Arc<Database>) -> Self {
+        let read_only = db
+            .get_global_proxy_config()
+            .await
+            .map(|c| c.read_only)
+            .unwrap_or(false);
+        let events = if read_only {
+            EventRecorder::disabled()
+        } else {
+            EventRecorder::spawn(db.clone())
+        };
+        Self::new(db, events, read_only)
+    }
+
+    /// 预热一个供应商的模型列表；失败只记录事件，不返回错误
+    pub async fn run(&self, app_type: &str, provider_id: &str) -> WarmupOutcome {
+        if self.read_only {
+            return WarmupOutcome::skipped("只读模式");
+        }
+        let Ok(app) = AppType::from_str(app_type) else {
+            return WarmupOutcome::skipped("未知应用");
+        };
+        if matches!(app, AppType::Gemini) {
+            return WarmupOutcome::skipped("Gemini 暂不支持模型列表预热");
+        }
+        let provider = match self.db.get_provider_by_id(provider_id, app_type) {
+            Ok(Some(provider)) => provider,
+            Ok(None) => return WarmupOutcome::skipped("供应商不存在"),
+            Err(e) => {
+                log::debug!("[ModelWarmup] 读取供应商 {provider_id} 失败: {e}");
+                return WarmupOutcome::skipped("读取供应商失败");
+            }
+        };
+        if !try_acquire_slot(app_type, provider_id) {
+            return WarmupOutcome::skipped("60 秒内已预热");
+        }
+
+        let api_key = get_adapter(&app)
+            .extract_auth(&provider)
+            .map(|auth| auth.api_key)
+            .unwrap_or_default();
+        let result = if api_key.trim().is_empty() {
+            Err("缺少 API Key".to_string())
+        } else if matches!(app, AppType::Claude) {
+            model_resolver::warm_model_list(&reqwest::Client::new(), &provider, &api_key).await
+        } else {
+            openai_model_resolver::warm_model_list(&provider, &api_key).await
+        };
+
+        match result {
+            Ok(models) => {
+                log::info!(
+                    "[{app_type}] 供应商 {} 模型列表已预热（{models} 个模型）",
+                    provider.name
+                );
+                WarmupOutcome::Warmed { models }
+            }
+            Err(error) => {
+                log::warn!(
+                    "[{app_type}] 供应商 {} 模型列表预热失败（key 或 base_url 可能有误）: {error}",
+                    provider.name
+                );
+                self.events.record(
+                    app_type,
+                    EventKind::ModelWarmupFailed,
+                    Some(provider_id),
+                    None,
+                    serde_json::json!({ "name": provider.name, "error": error }),
+                );
+                // 失败时释放频率限制，修正 key 或 base_url 后再次保存可立即重新预热
+                release_slot(app_type, provider_id);
+                // 独立运行（CLI）时进程可能随即退出，等待事件写入
+                self.events.flush().await;
+                WarmupOutcome::Failed { error }
+            }
+        }
+    }
+}
+
+/// 频率限制：距上次预热不足最小间隔时返回 false，否则登记本次预热
+fn try_acquire_slot(app_type: &str, provider_id: &str) -> bool {
+    let Ok(mut last) = LAST_WARMUP.lock() else {
+        return true;
+    };
+    let key = (app_type.to_string(), provider_id.to_string());
+    let now = Instant::now();
+    if let Some(prev) = last.get(&key) {
+        if now.duration_since(*prev) < MIN_WARMUP_INTERVAL {
+            return false;
+        }
+    }
+    last.insert(key, now);
+    true
+}
+
+/// 撤销本次登记（预热失败时调用）
+fn release_slot(app_type: &str, provider_id: &str) {
+    if let Ok(mut last) = LAST_WARMUP.lock() {
+        last.remove(&(app_type.to_string(), provider_id.to_string()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::testkit::{codex_provider, MockResponse, MockUpstream};
+    use serde_json::json;
+
+    async fn warmup_with_provider(id: &str, base_url: &str) -> (Arc<Database>, ModelWarmup) {
+        let db = Arc::new(Database::memory().unwrap());
+        db.save_provider("codex", &codex_provider(id, base_url))
+            .unwrap();
+        let warmup = ModelWarmup::new(db.clone(), EventRecorder::spawn(db.clone()), false);
+        (db, warmup)
+    }
+
+    #[tokio::test]
+    async fn saved_provider_model_list_is_cached() {
+        let upstream = MockUpstream::start().await;
+        upstream.route(
+            "/w1/v1/models",
+            [MockResponse::json(
+                200,
+                json!({ "data": [{ "id": "gpt-5" }, { "id": "gpt-5-mini" }] }),
+            )],
+        );
+        let base_url = upstream.url("/w1/v1");
+        let (_db, warmup) = warmup_with_provider("warm-ok", &base_url).await;
+
+        assert_eq!(
+            warmup.run("codex", "warm-ok").await,
+            WarmupOutcome::Warmed { models: 2 }
+        );
+        let cached =
+            openai_model_resolver::cached_model_list(&codex_provider("warm-ok", &base_url));
+        assert_eq!(
+            cached,
+            Some(vec!["gpt-5".to_string(), "gpt-5-mini".to_string()])
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_warmup_records_event() {
+        let upstream = MockUpstream::start().await;
+        upstream.route(
+            "/w2/v1/models",
+            [MockResponse::json(401, json!({ "error": "invalid key" }))],
+        );
+        let (db, warmup) = warmup_with_provider("warm-bad", &upstream.url("/w2/v1")).await;
+
+        let outcome = warmup.run("codex", "warm-bad").await;
+        assert!(matches!(outcome, WarmupOutcome::Failed { .. }));
+
+        let events = db
+            .query_events("codex", Some(EventKind::ModelWarmupFailed), 0, 10)
+            .unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].provider_id.as_deref(), Some("warm-bad"));
+        // 供应商本身保持已保存状态
+        assert!(db
+            .get_provider_by_id("warm-bad", "codex")
+            .unwrap()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn repeated_warmup_is_rate_limited() {
+        let upstream = MockUpstream::start().await;
+        upstream.route(
+            "/w3/v1/models",
+            [MockResponse::json(
+                200,
+                json!({ "data": [{ "id": "gpt-5" }] }),
+            )],
+        );
+        let (_db, warmup) = warmup_with_provider("warm-twice", &upstream.url("/w3/v1")).await;
+
+        assert!(matches!(
+            warmup.run("codex", "warm-twice").await,
+            WarmupOutcome::Warmed { .. }
+        ));
+        assert!(matches!(
+            warmup.run("codex", "warm-twice").await,
+            WarmupOutcome::Skipped { .. }
+        ));
+        assert_eq!(upstream.hits("/w3/v1/models"), 1);
+    }
+
+    #[tokio::test]
+    async fn failed_warmup_does_not_hold_slot() {
+        let upstream = MockUpstream::start().await;
+        upstream.route(
+            "/w4/v1/models",
+            [MockResponse::json(401, json!({ "error": "invalid key" }))],
+        );
+        let (_db, warmup) = warmup_with_provider("warm-retry", &upstream.url("/w4/v1")).await;
+
+        assert!(matches!(
+            warmup.run("codex", "warm-retry").await,
+            WarmupOutcome::Failed { .. }
+        ));
+
+        // 修正后再次保存：不受 60 秒频率限制
+        upstream.route(
+            "/w4/v1/models",
+            [MockResponse::json(
+                200,
+                json!({ "data": [{ "id": "gpt-5" }] }),
+            )],
+        );
+        assert_eq!(
+            warmup.run("codex", "warm-retry").await,
+            WarmupOutcome::Warmed { models: 1 }
+        );
+    }
+
+    #[tokio::test]
+    async fn read_only_mode_skips_warmup() {
+        let (db, _) = warmup_with_provider("warm-ro", "http://127.0.0.1:9/v1").await;
+        let warmup = ModelWarmup::new(db.clone(), EventRecorder::disabled(), true);
+
+        assert_eq!(
+            warmup.run("codex", "warm-ro").await,
+            WarmupOutcome::skipped("只读模式")
+        );
+    }
+}
@@ -0,0 +1,324 @@
+//! 故障通知
+//!
+//! 在故障转移、熔断打开/恢复、全部供应商失败、接管中的 live 配置被外部改写、数据库暂不可用、
+//! 供应商配置缺失时，按应用配置发送通知：
+//! - Webhook：POST JSON（事件类型、应用、供应商、错误、时间戳），失败重试一次
+//! - 桌面通知：通过 Tauri 通知插件弹出系统通知（仅 GUI 进程）
+//!
+//! 发送全部在后台任务中进行，永远不会阻塞或影响请求路径。
+
+use crate::app_config::AppType;
+use crate::settings::NotificationSettings;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// 单次 Webhook 请求超时
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+/// 首次失败后的重试间隔
+const WEBHOOK_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+static APP_HANDLE: OnceLock<tauri::AppHandle> = OnceLock::new();
+static HTTP_CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+/// 通知事件类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationEvent {
+    /// 故障转移切换了当前供应商
+    FailoverSwitch,
+    /// 熔断器打开
+    CircuitOpened,
+    /// 熔断器恢复（闭合）
+    CircuitRecovered,
+    /// 所有供应商均失败
+    AllProvidersFailed,
+    /// 接管中的 live 配置被外部改写
+    LiveConfigModified,
+    /// 数据库暂不可用（锁定/损坏），代理使用最近一次的供应商列表
+    DatabaseDegraded,
+    /// 未配置任何供应商，或故障转移关闭时当前供应商缺失（每次缺失只通知一次）
+    ProviderSetupMissing,
+}
+
+impl NotificationEvent {
+    fn title(&self) -> &'static str {
+        match self {
+            NotificationEvent::FailoverSwitch => "故障转移",
+            NotificationEvent::CircuitOpened => "供应商熔断",
+            NotificationEvent::CircuitRecovered => "供应商恢复",
+            NotificationEvent::AllProvidersFailed => "所有供应商失败",
+            NotificationEvent::LiveConfigModified => "Live 配置被改写",
+            NotificationEvent::DatabaseDegraded => "数据库不可用",
+            NotificationEvent::ProviderSetupMissing => "供应商配置缺失",
+        }
+    }
+}
+
+/// Webhook 负载
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NotificationPayload {
+    pub event: NotificationEvent,
+    pub app_type: String,
+    pub provider_id: Option<String>,
+    pub provider_name: Option<String>,
+    pub error: Option<String>,
+    /// RFC 3339
+    pub timestamp: String,
+}
+
+impl NotificationPayload {
+    pub fn new(event: NotificationEvent, app_type: &str) -> Self {
+        Self {
+            event,
+            app_type: app_type.to_string(),
+            provider_id: None,
+            provider_name: None,
+            error: None,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+
+    pub fn with_provider(mut self, id: &str, name: &str) -> Self {
+        self.provider_id = Some(id.to_string());
+        self.provider_name = Some(name.to_string());
+        self
+    }
+
+    pub fn with_error(mut self, error: impl Into<String>) -> Self {
+        self.error = Some(error.into());
+        self
+    }
+
+    /// 桌面通知正文
+    fn body(&self) -> String {
+        let provider = self
+            .provider_name
+            .as_deref()
+            .or(self.provider_id.as_deref())
+            .unwrap_or("-");
+        let mut body = match self.event {
+            NotificationEvent::FailoverSwitch => {
+                format!("[{}] 已切换到 {}", self.app_type, provider)
+            }
+            NotificationEvent::CircuitOpened => {
+                format!("[{}] {} 熔断器已打开", self.app_type, provider)
+            }
+            NotificationEvent::CircuitRecovered => {
+                format!("[{}] {} 已恢复", self.app_type, provider)
+            }
+            NotificationEvent::AllProvidersFailed => {
+                format!("[{}] 所有供应商均失败", self.app_type)
+            }
+            NotificationEvent::LiveConfigModified => {
+                format!("[{}] 接管中的 live 配置被外部改写", self.app_type)
+            }
+            NotificationEvent::DatabaseDegraded => {
+                format!(
+                    "[{}] 数据库暂不可用，代理使用最近一次的供应商列表",
+                    self.app_type
+                )
+            }
+            NotificationEvent::ProviderSetupMissing => {
+                format!("[{}] 没有可用的供应商配置，请求无法转发", self.app_type)
+            }
+        };
+        if let Some(err) = &self.error {
+            body.push_str(&format!("：{err}"));
+        }
+        body
+    }
+}
+
+/// 注册 GUI 进程的 AppHandle（用于桌面通知）
+pub fn init(app_handle: tauri::AppHandle) {
+    let _ = APP_HANDLE.set(app_handle);
+}
+
+fn http_client() -> &'static reqwest::Client {
+    HTTP_CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(WEBHOOK_TIMEOUT)
+            .build()
+            .unwrap_or_default()
+    })
+}
+
+/// 发送通知（后台执行，立即返回）
+///
+/// 未配置通知或当前不在 tokio 运行时内时直接忽略。
+pub fn notify(payload: NotificationPayload) {
+    let Some(config) = AppType::from_str(&payload.app_type)
+        .ok()
+        .and_then(|t| crate::settings::get_notification_settings(&t))
+    else {
+        return;
+    };
+    if config.webhooks.is_empty() && !config.desktop {
+        return;
+    }
+
+    let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+        log::debug!("[Notify] 不在异步运行时内，跳过通知: {:?}", payload.event);
+        return;
+    };
+
+    runtime.spawn(async move {
+        dispatch(&config, &payload).await;
+    });
+}
+
+async fn dispatch(config: &NotificationSettings, payload: &NotificationPayload) {
+    if config.desktop {
+        show_desktop(payload);
+    }
+    for url in &config.webhooks {
+        if let Err(e) = post_webhook(http_client(), url, payload).await {
+            log::warn!("[Notify] Webhook 发送失败 {url}: {e}");
+        }
+    }
+}
+
+fn show_desktop(payload: &NotificationPayload) {
+    use tauri_plugin_notification::NotificationExt;
+
+    let Some(app) = APP_HANDLE.get() else {
+        return;
+    };
+    if let Err(e) = app
+        .notification()
+        .builder()
+        .title(format!("CC Switch · {}", payload.event.title()))
+        .body(payload.body())
+        .show()
+    {
+        log::warn!("[Notify] 桌面通知发送失败: {e}");
+    }
+}
+
+/// POST 负载到 Webhook，失败（网络错误或非 2xx）时重试一次
+pub async fn post_webhook(
+    client: &reqwest::Client,
+    url: &str,
+    payload: &NotificationPayload,
+) -> Result<(), String> {
+    match post_once(client, url, payload).await {
+        Ok(()) => Ok(()),
+        Err(first) => {
+            log::debug!("[Notify] Webhook 首次发送失败，将重试: {first}");
+            tokio::time::sleep(WEBHOOK_RETRY_DELAY).await;
+            post_once(client, url, payload).await
+        }
+    }
+}
+
+async fn post_once(
+    client: &reqwest::Client,
+    url: &str,
+    payload: &NotificationPayload,
+) -> Result<(), String> {
+    let resp = client
+        .post(url)
+        .json(payload)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if resp.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("HTTP {}", resp.status().as_u16()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+    use serde_json::Value;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct Mock {
+        received: Arc<Mutex<Vec<Value>>>,
+        /// 前 N 次请求返回 500
+        fail_first: usize,
+    }
+
+    async fn hook(State(mock): State<Mock>, Json(body): Json<Value>) -> StatusCode {
+        let mut received = mock.received.lock().unwrap();
+        received.push(body);
+        if received.len() <= mock.fail_first {
+            StatusCode::INTERNAL_SERVER_ERROR
+        } else {
+            StatusCode::OK
+        }
+    }
+
+    async fn spawn_mock(fail_first: usize) -> (String, Arc<Mutex<Vec<Value>>>) {
+        let mock = Mock {
+            fail_first,
+            ..Mock::default()
+        };
+        let received = mock.received.clone();
+        let app = Router::new().route("/hook", post(hook)).with_state(mock);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+        (format!("http://{addr}/hook"), received)
+    }
+
+    #[tokio::test]
+    async fn test_webhook_payload_shape() {
+        let (url, received) = spawn_mock(0).await;
+        let payload = NotificationPayload::new(NotificationEvent::CircuitOpened, "claude")
+            .with_provider("p1", "anyrouter")
+            .with_error("HTTP 502");
+
+        post_webhook(&reqwest::Client::new(), &url, &payload)
+            .await
+            .unwrap();
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        let body = &received[0];
+        assert_eq!(body["event"], "circuit_opened");
+        assert_eq!(body["app_type"], "claude");
+        assert_eq!(body["provider_id"], "p1");
+        assert_eq!(body["provider_name"], "anyrouter");
+        assert_eq!(body["error"], "HTTP 502");
+        assert!(chrono::DateTime::parse_from_rfc3339(body["timestamp"].as_str().unwrap()).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_webhook_retries_once() {
+        let (url, received) = spawn_mock(1).await;
+        let payload = NotificationPayload::new(NotificationEvent::FailoverSwitch, "codex");
+
+        post_webhook(&reqwest::Client::new(), &url, &payload)
+            .await
+            .unwrap();
+        assert_eq!(received.lock().unwrap().len(), 2);
+
+        // 持续失败：最多发送两次
+        let (url, received) = spawn_mock(usize::MAX).await;
+        let err = post_webhook(&reqwest::Client::new(), &url, &payload)
+            .await
+            .unwrap_err();
+        assert_eq!(err, "HTTP 500");
+        assert_eq!(received.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_desktop_body() {
+        let payload = NotificationPayload::new(NotificationEvent::AllProvidersFailed, "gemini")
+            .with_error("timeout");
+        assert_eq!(payload.body(), "[gemini] 所有供应商均失败：timeout");
+
+        let payload = NotificationPayload::new(NotificationEvent::FailoverSwitch, "claude")
+            .with_provider("p2", "backup");
+        assert_eq!(payload.body(), "[claude] 已切换到 backup");
+    }
+}
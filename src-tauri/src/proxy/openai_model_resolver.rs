@@ -5,17 +5,30 @@
 //! - 在首次成功后写回 Provider 配置（避免后续重复匹配）
 //!
 //! 约束：
-//! - 不进行“跨家族”映射（例如 gpt-* 不会映射到 deepseek/qwen 等）
+//! - 不进行“跨家族”映射（例如 gpt-* 不会映射到 deepseek/qwen 等）；
+//!   本地后端（`localBackend: true`，如 Ollama）例外：用户手写的跨家族别名会被采用，自动匹配仍不跨家族
 //! - 仅当能确认候选存在（/v1/models）或已命中历史写回映射时才改写；否则保持原样，保证可用性
+//! - Azure OpenAI 供应商以 `azure.deploymentMap` 的 key 作为候选，不请求 /v1/models
+//! - gpt-* 的日期/版本后缀先对照模型列表再截断（`gpt-5.2-1120-preview` 真实存在时保留）；
+//!   拿不到模型列表时退回启发式截断
 
 use crate::provider::Provider;
-use crate::proxy::model_catalog::{detect_model_family, is_same_family, ModelFamily};
+use crate::proxy::env_expand::expand_env_str;
+use crate::proxy::model_catalog::{
+    detect_model_family, detect_model_family_with, is_same_family, is_same_family_with,
+    FamilyGuard, ModelFamily,
+};
+use crate::proxy::model_fallbacks;
+use crate::proxy::model_list::{fetch_model_list, ModelListAuth};
 use crate::proxy::model_resolver::ModelWriteback;
-use crate::proxy::model_sanitizer::sanitize_gpt_model_name;
+use crate::proxy::model_sanitizer::{
+    sanitize_gpt_model_name, sanitize_gpt_model_name_against, sanitize_openai_model_in_body,
+};
+use crate::proxy::providers::AzureConfig;
 use once_cell::sync::Lazy;
 use reqwest::Client;
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Mutex;
 use std::time::{Duration, Instant};
 
@@ -34,6 +47,8 @@ struct ModelListKey {
 #[derive(Debug, Clone)]
 struct CachedModelList {
     fetched_at: Instant,
+    /// 缓存有效期：默认 MODEL_LIST_TTL，上游 `Cache-Control: max-age` 更长时以其为准
+    ttl: Duration,
     models: Vec<String>,
 }
 
@@ -59,6 +74,14 @@ fn sanitize_openai_model_name(model: &str) -> String {
 }
 
 fn extract_openai_base_url(provider: &Provider) -> Option<String> {
+    // `${ENV_VAR}` 占位符在读取时展开；变量未设置时视为没有 base_url（不拉取模型列表）
+    let raw = raw_openai_base_url(provider)?;
+    expand_env_str(&raw, "base_url")
+        .ok()
+        .map(|url| url.into_owned())
+}
+
+fn raw_openai_base_url(provider: &Provider) -> Option<String> {
     // 与 CodexAdapter.extract_base_url 保持一致：尽可能从多种字段提取 base_url
     // 1) base_url
     if let Some(url) = provider
@@ -103,65 +126,98 @@ fn extract_openai_base_url(provider: &Provider) -> Option<String> {
     None
 }
 
-async fn fetch_models(base_url: &str, api_key: &str) -> Result<Vec<String>, String> {
-    fn build_url(base_url: &str, endpoint: &str) -> String {
-        let base_trimmed = base_url.trim_end_matches('/');
-        let endpoint_trimmed = endpoint.trim_start_matches('/');
-        let mut url = format!("{base_trimmed}/{endpoint_trimmed}");
-        if url.contains("/v1/v1") {
-            url = url.replace("/v1/v1", "/v1");
-        }
-        url
-    }
-
+/// 拉取（分页合并后的）模型列表；超时作用于整个分页拉取过程
+async fn fetch_models(base_url: &str, api_key: &str) -> Result<CachedModelList, String> {
     let client = Client::builder()
         .timeout(MODELS_FETCH_TIMEOUT)
         .build()
         .map_err(|e| e.to_string())?;
 
-    let endpoints = ["/v1/models", "/models"];
-    for ep in endpoints.iter() {
-        let url = build_url(base_url, ep);
-        let resp = client
-            .get(url)
-            .header("accept", "application/json")
-            .header("authorization", format!("Bearer {}", api_key))
-            .send()
-            .await
-            .map_err(|e| e.to_string())?;
-
-        if !resp.status().is_success() {
+    let list = tokio::time::timeout(
+        MODELS_FETCH_TIMEOUT,
+        fetch_model_list(&client, base_url, api_key, ModelListAuth::Bearer),
+    )
+    .await
+    .map_err(|_| format!("拉取模型列表超时（{}s）", MODELS_FETCH_TIMEOUT.as_secs()))?
+    .map_err(|e| e.to_string())?;
+
+    let mut out: Vec<String> = Vec::new();
+    for id in list.ids.iter() {
+        // 保留上游真实 id（如 gpt-5.2-1120-preview），不做日期截断；截断与否由请求侧对照列表决定
+        let id = id.trim().to_string();
+        if id.is_empty() {
             continue;
         }
-        let v = resp
-            .json::<serde_json::Value>()
-            .await
-            .map_err(|e| e.to_string())?;
-        let data = v
-            .get("data")
-            .and_then(|x| x.as_array())
-            .ok_or_else(|| "missing data[]".to_string())?;
-
-        let mut out: Vec<String> = Vec::new();
-        for item in data.iter() {
-            let Some(id) = item.get("id").and_then(|x| x.as_str()) else {
-                continue;
-            };
-            let id = sanitize_openai_model_name(id);
-            if id.trim().is_empty() {
-                continue;
+        if out.iter().any(|x| x.eq_ignore_ascii_case(&id)) {
+            continue;
+        }
+        out.push(id);
+    }
+    if out.is_empty() {
+        return Err("no models endpoint available".to_string());
+    }
+    Ok(CachedModelList {
+        fetched_at: Instant::now(),
+        ttl: list.cache_ttl(MODEL_LIST_TTL),
+        models: out,
+    })
+}
+
+/// 拉取模型列表并更新缓存；失败时记录失败时间（进入冷却）
+async fn fetch_and_cache_model_list(
+    key: &ModelListKey,
+    api_key: &str,
+) -> Result<Vec<String>, String> {
+    match fetch_models(&key.base_url, api_key).await {
+        Ok(fetched) => {
+            let list = fetched.models.clone();
+            if let Ok(mut cache) = MODEL_LIST_CACHE.lock() {
+                cache.insert(key.clone(), fetched);
             }
-            if out.iter().any(|x| x.eq_ignore_ascii_case(&id)) {
-                continue;
+            if let Ok(mut failures) = MODEL_LIST_FAILURES.lock() {
+                failures.remove(key);
             }
-            out.push(id);
+            Ok(list)
         }
-        if !out.is_empty() {
-            return Ok(out);
+        Err(e) => {
+            if let Ok(mut failures) = MODEL_LIST_FAILURES.lock() {
+                failures.insert(key.clone(), Instant::now());
+            }
+            Err(e)
         }
     }
+}
 
-    Err("no models endpoint available".to_string())
+/// 预热：忽略缓存与失败冷却重新拉取模型列表（保存供应商后调用），返回模型数
+///
+/// Azure OpenAI 的候选模型来自部署映射，不需要拉取。
+pub(crate) async fn warm_model_list(provider: &Provider, api_key: &str) -> Result<usize, String> {
+    if let Some(azure) = AzureConfig::from_provider(provider) {
+        return Ok(azure.models().len());
+    }
+    let base_url =
+        extract_openai_base_url(provider).ok_or_else(|| "缺少 base_url 配置".to_string())?;
+    let key = ModelListKey {
+        provider_id: provider.id.clone(),
+        base_url,
+    };
+    fetch_and_cache_model_list(&key, api_key)
+        .await
+        .map(|models| models.len())
+}
+
+/// 读取供应商已缓存（TTL 内）的模型列表，不触发拉取
+#[cfg(test)]
+pub(crate) fn cached_model_list(provider: &Provider) -> Option<Vec<String>> {
+    let key = ModelListKey {
+        provider_id: provider.id.clone(),
+        base_url: extract_openai_base_url(provider)?,
+    };
+    let cache = MODEL_LIST_CACHE.lock().ok()?;
+    cache
+        .get(&key)
+        .filter(|v| v.fetched_at.elapsed() <= v.ttl)
+        .map(|v| v.models.clone())
 }
 
 fn read_alias_map(provider: &Provider) -> HashMap<String, String> {
@@ -233,14 +289,14 @@ fn extract_major_minor_gpt(s: &str) -> (Option<u32>, Option<u32>) {
     (major, minor)
 }
 
-fn score_candidate(request: &str, candidate: &str) -> i32 {
+fn score_candidate(request: &str, candidate: &str, overrides: &BTreeMap<String, String>) -> i32 {
     let req = normalize_token(request);
     let cand = normalize_token(candidate);
     if req == cand {
         return 10_000;
     }
 
-    if !is_same_family(request, candidate) {
+    if !is_same_family_with(request, candidate, overrides) {
         return -100_000;
     }
 
@@ -293,16 +349,34 @@ fn score_candidate(request: &str, candidate: &str) -> i32 {
     score
 }
 
-fn choose_best_model(request_model: &str, candidates: &[String]) -> Option<String> {
-    let req = sanitize_openai_model_name(request_model);
-    let req_family = detect_model_family(&req);
+/// 别名是否可用：默认要求同家族；本地后端或 familyGuard 为 prefer / off 的供应商
+/// 允许显式配置的跨家族别名（自动写回的别名总是同家族，除非 familyGuard=off）
+fn alias_allowed(provider: &Provider, request_model: &str, mapped: &str) -> bool {
+    is_same_family(request_model, mapped)
+        || provider.is_local_backend()
+        || !provider.family_guard().anchors_mapping()
+}
+
+fn choose_best_model(
+    request_model: &str,
+    candidates: &[String],
+    guard: FamilyGuard,
+) -> Option<String> {
+    // 候选中存在原名时不截断，避免把 gpt-5.2-1120-preview 当作 gpt-5.2 打分
+    let req = sanitize_gpt_model_name_against(request_model, candidates);
+    // 自定义家族规则每次解析只读取一次，避免逐个候选克隆设置
+    let overrides = crate::settings::get_model_family_overrides();
+    let req_family = detect_model_family_with(&req, &overrides);
 
     let mut best: Option<(i32, String)> = None;
     for c in candidates.iter() {
-        if req_family != ModelFamily::Other && detect_model_family(c) != req_family {
+        if guard.anchors_resolution()
+            && req_family != ModelFamily::Other
+            && detect_model_family_with(c, &overrides) != req_family
+        {
             continue;
         }
-        let s = score_candidate(&req, c);
+        let s = score_candidate(&req, c, &overrides);
         match &best {
             None => best = Some((s, c.clone())),
             Some((best_s, best_c)) => {
@@ -335,17 +409,30 @@ pub async fn resolve_openai_model_in_body(
         return (body, None);
     }
 
-    // 0) 已写回别名优先（无网络）
+    // 0) 已写回别名优先（无网络）；带版本/日期后缀的请求需先对照模型列表决定是否截断，别名稍后再查
     let aliases = read_alias_map(provider);
-    let request_key = normalize_token(&request_model);
-    if let Some(mapped) = aliases.get(&request_key) {
-        if is_same_family(&request_model, mapped) && normalize_token(mapped) != request_key {
+    if request_model == original_request_model.trim() {
+        if let Some(mapped) = lookup_alias(provider, &aliases, &request_model) {
             body["model"] = serde_json::json!(mapped);
             return (body, None);
         }
     }
 
+    // Azure OpenAI：候选模型即部署映射的 key，不请求 /v1/models
+    if let Some(azure) = AzureConfig::from_provider(provider) {
+        // 部署按请求体中的模型查找：对照映射后命中时统一写成映射中的 key（如去掉日期后缀）
+        return resolve_against_model_list(
+            provider,
+            original_request_model,
+            &azure.models(),
+            aliases,
+            body,
+        );
+    }
+
     let Some(base_url) = extract_openai_base_url(provider) else {
+        // 无模型列表可对照：退回启发式截断
+        let _ = sanitize_openai_model_in_body(&mut body);
         return (body, None);
     };
     let key = ModelListKey {
@@ -356,9 +443,15 @@ pub async fn resolve_openai_model_in_body(
     // 1) 缓存命中
     if let Ok(cache) = MODEL_LIST_CACHE.lock() {
         if let Some(v) = cache.get(&key) {
-            if v.fetched_at.elapsed() <= MODEL_LIST_TTL {
+            if v.fetched_at.elapsed() <= v.ttl {
                 let models = &v.models;
-                return resolve_from_model_list(&request_model, models, aliases, body);
+                return resolve_against_model_list(
+                    provider,
+                    original_request_model,
+                    models,
+                    aliases,
+                    body,
+                );
             }
         }
     }
@@ -367,43 +460,96 @@ pub async fn resolve_openai_model_in_body(
     if let Ok(failures) = MODEL_LIST_FAILURES.lock() {
         if let Some(t) = failures.get(&key) {
             if t.elapsed() <= MODEL_LIST_FAILURE_COOLDOWN {
+                let _ = sanitize_openai_model_in_body(&mut body);
                 return (body, None);
             }
         }
     }
 
     // 3) 拉取
-    match fetch_models(&base_url, api_key).await {
+    match fetch_and_cache_model_list(&key, api_key).await {
         Ok(list) => {
-            if let Ok(mut cache) = MODEL_LIST_CACHE.lock() {
-                cache.insert(
-                    key.clone(),
-                    CachedModelList {
-                        fetched_at: Instant::now(),
-                        models: list.clone(),
-                    },
-                );
-            }
-            if let Ok(mut failures) = MODEL_LIST_FAILURES.lock() {
-                failures.remove(&key);
-            }
-            resolve_from_model_list(&request_model, &list, aliases, body)
+            resolve_against_model_list(provider, original_request_model, &list, aliases, body)
         }
         Err(e) => {
-            if let Ok(mut failures) = MODEL_LIST_FAILURES.lock() {
-                failures.insert(key.clone(), Instant::now());
-            }
             log::debug!(
                 "[OpenAIModelResolver] /v1/models 拉取失败 provider={} base_url={} err={}",
                 provider.id,
                 base_url,
                 e
             );
+            let _ = sanitize_openai_model_in_body(&mut body);
             (body, None)
         }
     }
 }
 
+/// 仅使用已缓存的模型列表做解析（dry-run 使用，不拉取 /v1/models）
+///
+/// 已写回别名与 Azure 部署映射不需要网络，照常生效。第二个返回值为解析依据：
+/// `alias`、`azure-deployments`、`model-list`（命中缓存）、`no-cache`（实际请求会先拉取模型列表）、
+/// `skipped`（非 OpenAI 模型，不解析）。
+pub(crate) fn resolve_openai_model_cached(
+    provider: &Provider,
+    original_request_model: &str,
+    mut body: Value,
+) -> (Value, &'static str) {
+    let request_model = sanitize_openai_model_name(original_request_model);
+    if request_model.trim().is_empty()
+        || request_model == "unknown"
+        || detect_model_family(&request_model) != ModelFamily::OpenAi
+    {
+        return (body, "skipped");
+    }
+
+    let aliases = read_alias_map(provider);
+    if request_model == original_request_model.trim() {
+        if let Some(mapped) = lookup_alias(provider, &aliases, &request_model) {
+            body["model"] = serde_json::json!(mapped);
+            return (body, "alias");
+        }
+    }
+
+    if let Some(azure) = AzureConfig::from_provider(provider) {
+        let (body, _) = resolve_against_model_list(
+            provider,
+            original_request_model,
+            &azure.models(),
+            aliases,
+            body,
+        );
+        return (body, "azure-deployments");
+    }
+
+    let cached = extract_openai_base_url(provider).and_then(|base_url| {
+        let key = ModelListKey {
+            provider_id: provider.id.clone(),
+            base_url,
+        };
+        let cache = MODEL_LIST_CACHE.lock().ok()?;
+        cache
+            .get(&key)
+            .filter(|v| v.fetched_at.elapsed() <= v.ttl)
+            .map(|v| v.models.clone())
+    });
+    match cached {
+        Some(models) => {
+            let (body, _) = resolve_against_model_list(
+                provider,
+                original_request_model,
+                &models,
+                aliases,
+                body,
+            );
+            (body, "model-list")
+        }
+        None => {
+            let _ = sanitize_openai_model_in_body(&mut body);
+            (body, "no-cache")
+        }
+    }
+}
+
 pub async fn resolve_openai_model_in_body_with_avoid(
     _client: &Client,
     provider: &Provider,
@@ -423,13 +569,28 @@ pub async fn resolve_openai_model_in_body_with_avoid(
 
     let aliases = read_alias_map(provider);
 
+    // avoid 来自实际发送的模型名，按原样比较（不截断，避免误伤同前缀的真实模型）
     let avoid_norm: Vec<String> = avoid_models
         .iter()
-        .map(|m| normalize_token(&sanitize_openai_model_name(m)))
+        .map(|m| normalize_token(m))
         .filter(|m| !m.trim().is_empty())
         .collect();
 
+    if let Some(azure) = AzureConfig::from_provider(provider) {
+        let models = azure.models();
+        let request_model = sanitize_gpt_model_name_against(original_request_model, &models);
+        return resolve_from_model_list_with_avoid(
+            provider,
+            &request_model,
+            &models,
+            aliases,
+            body,
+            &avoid_norm,
+        );
+    }
+
     let Some(base_url) = extract_openai_base_url(provider) else {
+        let _ = sanitize_openai_model_in_body(&mut body);
         return (body, None);
     };
     let key = ModelListKey {
@@ -440,9 +601,11 @@ pub async fn resolve_openai_model_in_body_with_avoid(
     // 1) 缓存命中
     if let Ok(cache) = MODEL_LIST_CACHE.lock() {
         if let Some(v) = cache.get(&key) {
-            if v.fetched_at.elapsed() <= MODEL_LIST_TTL {
+            if v.fetched_at.elapsed() <= v.ttl {
                 let models = &v.models;
+                let request_model = sanitize_gpt_model_name_against(original_request_model, models);
                 return resolve_from_model_list_with_avoid(
+                    provider,
                     &request_model,
                     models,
                     aliases,
@@ -457,44 +620,114 @@ pub async fn resolve_openai_model_in_body_with_avoid(
     if let Ok(failures) = MODEL_LIST_FAILURES.lock() {
         if let Some(t) = failures.get(&key) {
             if t.elapsed() <= MODEL_LIST_FAILURE_COOLDOWN {
+                let _ = sanitize_openai_model_in_body(&mut body);
                 return (body, None);
             }
         }
     }
 
     // 3) 拉取
-    match fetch_models(&base_url, api_key).await {
+    match fetch_and_cache_model_list(&key, api_key).await {
         Ok(list) => {
-            if let Ok(mut cache) = MODEL_LIST_CACHE.lock() {
-                cache.insert(
-                    key.clone(),
-                    CachedModelList {
-                        fetched_at: Instant::now(),
-                        models: list.clone(),
-                    },
-                );
-            }
-            if let Ok(mut failures) = MODEL_LIST_FAILURES.lock() {
-                failures.remove(&key);
-            }
-            resolve_from_model_list_with_avoid(&request_model, &list, aliases, body, &avoid_norm)
+            let request_model = sanitize_gpt_model_name_against(original_request_model, &list);
+            resolve_from_model_list_with_avoid(
+                provider,
+                &request_model,
+                &list,
+                aliases,
+                body,
+                &avoid_norm,
+            )
         }
         Err(e) => {
-            if let Ok(mut failures) = MODEL_LIST_FAILURES.lock() {
-                failures.insert(key.clone(), Instant::now());
-            }
             log::debug!(
                 "[OpenAIModelResolver] /v1/models 拉取失败 provider={} base_url={} err={}",
                 provider.id,
                 base_url,
                 e
             );
+            let _ = sanitize_openai_model_in_body(&mut body);
             (body, None)
         }
     }
 }
 
+/// 回退链重试成功后的写回：记录 `请求模型 → 实际可用模型` 别名（已是该别名或跨家族时不写回）
+pub(crate) fn openai_writeback(
+    provider: &Provider,
+    original_request_model: &str,
+    chosen: &str,
+) -> Option<ModelWriteback> {
+    let request_model = sanitize_openai_model_name(original_request_model);
+    if request_model.trim().is_empty()
+        || normalize_token(&request_model) == normalize_token(chosen)
+        || !alias_allowed(provider, &request_model, chosen)
+    {
+        return None;
+    }
+    let aliases = read_alias_map(provider);
+    if aliases
+        .get(&normalize_token(&request_model))
+        .map(|m| normalize_token(m))
+        == Some(normalize_token(chosen))
+    {
+        return None;
+    }
+    Some(ModelWriteback {
+        env_key: CODEX_ALIASES_ENV_KEY,
+        value: merge_alias_map(aliases, &request_model, chosen),
+        from_model: request_model,
+        to_model: chosen.to_string(),
+    })
+}
+
+/// 请求模型可命中的别名目标（原样或截断版本/日期后缀后查找；不请求网络，供模型解析追踪归因）
+pub(crate) fn alias_target(provider: &Provider, original_request_model: &str) -> Option<String> {
+    let aliases = read_alias_map(provider);
+    lookup_alias(provider, &aliases, original_request_model.trim()).or_else(|| {
+        lookup_alias(
+            provider,
+            &aliases,
+            &sanitize_openai_model_name(original_request_model),
+        )
+    })
+}
+
+/// 查找可用的已写回/显式别名（与请求不同且满足家族约束时返回映射目标）
+fn lookup_alias(
+    provider: &Provider,
+    aliases: &HashMap<String, String>,
+    request_model: &str,
+) -> Option<String> {
+    let request_key = normalize_token(request_model);
+    let mapped = aliases.get(&request_key)?;
+    if alias_allowed(provider, request_model, mapped) && normalize_token(mapped) != request_key {
+        return Some(mapped.clone());
+    }
+    None
+}
+
+/// 已拿到模型列表：先对照列表决定是否截断版本/日期后缀，再走别名与智能匹配
+fn resolve_against_model_list(
+    provider: &Provider,
+    original_request_model: &str,
+    models: &[String],
+    aliases: HashMap<String, String>,
+    mut body: Value,
+) -> (Value, Option<ModelWriteback>) {
+    let request_model = sanitize_gpt_model_name_against(original_request_model, models);
+    if let Some(mapped) = lookup_alias(provider, &aliases, &request_model) {
+        body["model"] = serde_json::json!(mapped);
+        return (body, None);
+    }
+    if body.get("model").and_then(|m| m.as_str()) != Some(request_model.as_str()) {
+        body["model"] = serde_json::json!(request_model.clone());
+    }
+    resolve_from_model_list(provider, &request_model, models, aliases, body)
+}
+
 fn resolve_from_model_list(
+    provider: &Provider,
     request_model: &str,
     models: &[String],
     aliases: HashMap<String, String>,
@@ -508,7 +741,10 @@ fn resolve_from_model_list(
         return (body, None);
     }
 
-    let chosen = choose_best_model(request_model, models);
+    // 回退链（modelFallbacks）中在列表内的模型优先
+    let chosen =
+        model_fallbacks::preferred_in_list(provider, request_model, request_model, models, &[])
+            .or_else(|| choose_best_model(request_model, models, provider.family_guard()));
     let Some(chosen) = chosen else {
         return (body, None);
     };
@@ -529,6 +765,7 @@ fn resolve_from_model_list(
 }
 
 fn resolve_from_model_list_with_avoid(
+    provider: &Provider,
     request_model: &str,
     models: &[String],
     aliases: HashMap<String, String>,
@@ -552,8 +789,21 @@ fn resolve_from_model_list_with_avoid(
     }
 
     // 允许在“上游提示模型不可用”场景下，即便 models[] 含 request_model 也尝试选择次优模型；
-    // 因此这里不做“request_model 存在则不改”的短路。
-    let chosen = choose_best_model(request_model, &candidates);
+    // 因此这里不做“request_model 存在则不改”的短路。回退链中在列表内的模型优先。
+    let chosen = avoid_norm
+        .iter()
+        .map(String::as_str)
+        .chain([request_model])
+        .find_map(|sent| {
+            model_fallbacks::preferred_in_list(
+                provider,
+                request_model,
+                sent,
+                &candidates,
+                avoid_norm,
+            )
+        })
+        .or_else(|| choose_best_model(request_model, &candidates, provider.family_guard()));
     let Some(chosen) = chosen else {
         return (body, None);
     };
@@ -612,10 +862,37 @@ mod tests {
             "gpt-5.2-codex".to_string(),
             "gpt-4o".to_string(),
         ];
-        let best = choose_best_model("gpt-5.2", &cands).unwrap();
+        let best = choose_best_model("gpt-5.2", &cands, FamilyGuard::Strict).unwrap();
         assert_eq!(best, "gpt-5.2-codex");
     }
 
+    #[test]
+    fn family_guard_levels_for_openai_resolution() {
+        let models = vec!["glm-4.5".to_string()];
+        let resolve = |guard: &str, alias: Option<&str>| {
+            let mut p = provider_with_base("https://example.com");
+            p.settings_config["familyGuard"] = json!(guard);
+            if let Some(alias) = alias {
+                p.settings_config["env"][CODEX_ALIASES_ENV_KEY] = json!(alias);
+            }
+            let body = json!({"model": "gpt-5.2"});
+            let (body, _) =
+                resolve_against_model_list(&p, "gpt-5.2", &models, read_alias_map(&p), body);
+            body["model"].as_str().unwrap().to_string()
+        };
+
+        // 智能解析：strict / prefer 只在同家族内选择，off 才会跨家族
+        assert_eq!(resolve("strict", None), "gpt-5.2");
+        assert_eq!(resolve("prefer", None), "gpt-5.2");
+        assert_eq!(resolve("off", None), "glm-4.5");
+
+        // 显式别名：prefer / off 允许跨家族
+        let alias = r#"{"gpt-5.2":"glm-4.5-air"}"#;
+        assert_eq!(resolve("strict", Some(alias)), "gpt-5.2");
+        assert_eq!(resolve("prefer", Some(alias)), "glm-4.5-air");
+        assert_eq!(resolve("off", Some(alias)), "glm-4.5-air");
+    }
+
     #[test]
     fn alias_map_merge_is_bounded() {
         let p = provider_with_base("https://example.com");
@@ -663,4 +940,186 @@ mod tests {
             Some("https://example.com/v1")
         );
     }
+
+    #[tokio::test]
+    async fn azure_resolves_against_deployment_map_without_models_endpoint() {
+        // base_url 不可达：若请求了 /v1/models 则无法得到映射结果
+        let p = Provider {
+            settings_config: json!({
+                "base_url": "http://127.0.0.1:1",
+                "azure": {
+                    "deploymentMap": { "gpt-4o": "prod-4o", "gpt-4o-mini": "prod-mini" },
+                    "apiVersion": "2024-10-21"
+                }
+            }),
+            ..provider_with_base("http://127.0.0.1:1")
+        };
+        let client = Client::new();
+
+        let (body, wb) = resolve_openai_model_in_body(
+            &client,
+            &p,
+            "sk-test",
+            "gpt-4o-2024-08-06",
+            json!({"model": "gpt-4o-2024-08-06"}),
+        )
+        .await;
+        assert_eq!(body["model"], "gpt-4o");
+        assert!(wb.is_none());
+
+        let (body, wb) = resolve_openai_model_in_body(
+            &client,
+            &p,
+            "sk-test",
+            "gpt-4o-latest",
+            json!({"model": "gpt-4o-latest"}),
+        )
+        .await;
+        assert_eq!(body["model"], "gpt-4o");
+        assert_eq!(wb.unwrap().to_model, "gpt-4o");
+
+        let (body, _) = resolve_openai_model_in_body_with_avoid(
+            &client,
+            &p,
+            "sk-test",
+            "gpt-4o",
+            json!({"model": "gpt-4o"}),
+            &["gpt-4o"],
+        )
+        .await;
+        assert_eq!(body["model"], "gpt-4o-mini");
+    }
+
+    #[tokio::test]
+    async fn fallback_chain_is_preferred_over_scoring() {
+        let p = Provider {
+            settings_config: json!({
+                "base_url": "http://127.0.0.1:1",
+                "azure": {
+                    "deploymentMap": { "gpt-5.2": "prod-52", "gpt-5-mini": "prod-mini" },
+                    "apiVersion": "2024-10-21"
+                },
+                "modelFallbacks": { "gpt-5.1": ["gpt-5-nano", "gpt-5-mini"] }
+            }),
+            ..provider_with_base("http://127.0.0.1:1")
+        };
+        let client = Client::new();
+
+        // 不在列表内：回退链中第一个在列表内的模型优先于打分（打分会选 gpt-5.2）
+        let (body, wb) = resolve_openai_model_in_body(
+            &client,
+            &p,
+            "sk-test",
+            "gpt-5.1",
+            json!({"model": "gpt-5.1"}),
+        )
+        .await;
+        assert_eq!(body["model"], "gpt-5-mini");
+        assert_eq!(wb.unwrap().to_model, "gpt-5-mini");
+
+        // 回退链模型被避开后退回打分选择
+        let (body, _) = resolve_openai_model_in_body_with_avoid(
+            &client,
+            &p,
+            "sk-test",
+            "gpt-5.1",
+            json!({"model": "gpt-5-mini"}),
+            &["gpt-5-mini"],
+        )
+        .await;
+        assert_eq!(body["model"], "gpt-5.2");
+    }
+
+    #[tokio::test]
+    async fn version_suffix_is_kept_when_listed_and_stripped_otherwise() {
+        let p = Provider {
+            settings_config: json!({
+                "base_url": "http://127.0.0.1:1",
+                "azure": {
+                    "deploymentMap": { "gpt-5.2": "prod-52", "gpt-5.2-1120-preview": "prod-preview" },
+                    "apiVersion": "2024-10-21"
+                }
+            }),
+            ..provider_with_base("http://127.0.0.1:1")
+        };
+        let client = Client::new();
+
+        // 列表中真实存在：不截断
+        let (body, wb) = resolve_openai_model_in_body(
+            &client,
+            &p,
+            "sk-test",
+            "gpt-5.2-1120-preview",
+            json!({"model": "gpt-5.2-1120-preview"}),
+        )
+        .await;
+        assert_eq!(body["model"], "gpt-5.2-1120-preview");
+        assert!(wb.is_none());
+
+        // 截断后的名称在列表中：照旧截断
+        let (body, wb) = resolve_openai_model_in_body(
+            &client,
+            &p,
+            "sk-test",
+            "gpt-5.2-2025-12-11",
+            json!({"model": "gpt-5.2-2025-12-11"}),
+        )
+        .await;
+        assert_eq!(body["model"], "gpt-5.2");
+        assert!(wb.is_none());
+
+        // 拿不到模型列表（base_url 不可达）：退回启发式截断
+        let remote = provider_with_base("http://127.0.0.1:1");
+        let (body, _) = resolve_openai_model_in_body(
+            &client,
+            &remote,
+            "sk-test",
+            "gpt-4-0613",
+            json!({"model": "gpt-4-0613"}),
+        )
+        .await;
+        assert_eq!(body["model"], "gpt-4");
+    }
+
+    #[tokio::test]
+    async fn local_backend_honors_explicit_cross_family_alias() {
+        // base_url 不可达：结果只能来自别名
+        let aliases = json!({ "gpt-5.2": "qwen2.5-coder:7b" }).to_string();
+        let remote = Provider {
+            settings_config: json!({
+                "base_url": "http://127.0.0.1:1",
+                "env": { "OPENAI_API_KEY": "sk-test", CODEX_ALIASES_ENV_KEY: aliases }
+            }),
+            ..provider_with_base("http://127.0.0.1:1")
+        };
+        let mut local = remote.clone();
+        local.settings_config["localBackend"] = json!(true);
+        let client = Client::new();
+
+        let (body, _) = resolve_openai_model_in_body(
+            &client,
+            &remote,
+            "sk-test",
+            "gpt-5.2",
+            json!({"model": "gpt-5.2"}),
+        )
+        .await;
+        assert_eq!(body["model"], "gpt-5.2");
+
+        let (body, wb) = resolve_openai_model_in_body(
+            &client,
+            &local,
+            "sk-test",
+            "gpt-5.2",
+            json!({"model": "gpt-5.2"}),
+        )
+        .await;
+        assert_eq!(body["model"], "qwen2.5-coder:7b");
+        assert!(wb.is_none());
+
+        // 没有显式别名时，自动匹配仍不跨家族
+        let cands = vec!["qwen2.5-coder:7b".to_string(), "llama3.1:8b".to_string()];
+        let strict = FamilyGuard::Strict;
+        assert_eq!(choose_best_model("gpt-5.2", &cands, strict), None);
+    }
 }
@@ -2,17 +2,67 @@
 //!
 //! 负责选择和管理代理目标供应商，实现智能故障转移
 
-use crate::database::Database;
+use crate::database::{Database, EventKind};
 use crate::error::AppError;
+use crate::i18n::{tr, Locale, Msg};
 use crate::provider::Provider;
-use crate::proxy::circuit_breaker::{AllowResult, CircuitBreaker, CircuitBreakerConfig};
+use crate::proxy::body_transforms::apply_body_transforms;
+use crate::proxy::circuit_breaker::{
+    AllowResult, CircuitBreaker, CircuitBreakerConfig, CircuitState,
+};
+use crate::proxy::env_expand::{expand_env_str, expand_provider, MissingEnvVar};
+use crate::proxy::error_body::read_error_body;
+use crate::proxy::event_log::EventRecorder;
+use crate::proxy::failure_kind::{
+    is_depleted_text, is_overloaded_text, upstream_error_message, FailureKind,
+};
+use crate::proxy::handler_context::RequestScope;
+use crate::proxy::model_list::{fetch_model_ids, ModelListAuth, ModelListError};
+use crate::proxy::notifications::{self, NotificationEvent, NotificationPayload};
+use crate::proxy::providers::{
+    python_proxy_auth_header, to_bedrock_body, AuthScheme, AzureConfig, BedrockConfig, VertexConfig,
+};
+use crate::proxy::selection_policy::{
+    PriorityLevel, SelectionInput, SelectionPolicy, SelectionPolicyKind, SelectionState,
+    SupplierCandidates,
+};
+use crate::proxy::thinking_capability::prefer_thinking_capable;
+use crate::proxy::types::{
+    last_request_summary_setting_key, AppProxyConfig, AppRoutingSnapshot, FailoverPause,
+    LastRequestSummary, RouterMemoryStats, SupplierRoutingState,
+};
+use crate::settings::ProbeMode;
+use futures::StreamExt;
 use serde_json::Value;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::Mutex;
 use tokio::sync::RwLock;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio_util::sync::CancellationToken;
+
+/// URL 疑似失效标记
+#[derive(Debug, Clone, Copy)]
+struct SuspectUrl {
+    /// 解除时间
+    until: std::time::Instant,
+    /// 连续被标记疑似失效的次数（经该 URL 的请求成功后清零）
+    strikes: u32,
+}
+
+/// 疑似失效的 URL（演练报告展示）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SuspectUrlEntry {
+    pub supplier: String,
+    pub url: String,
+    /// 解除时间（Unix 秒）
+    pub until: i64,
+    /// 连续被标记疑似失效的次数
+    pub strikes: u32,
+}
 
 /// URL延迟测试结果
 #[derive(Debug, Clone)]
@@ -31,7 +81,12 @@ pub struct UrlProbeDetail {
 
 #[derive(Debug, Clone)]
 pub enum UrlProbeKind {
+    /// 全链路问答请求成功（或近期有真实成功请求）
     FullOk { latency_ms: u64 },
+    /// 仅 `/v1/models` 探测成功：key 能列出模型，但未验证能否完成问答
+    ModelsOk {
+        latency_ms: u64,
+    },
     Overloaded { latency_ms: u64, message: String },
     FallbackOk {
         connect_ms: u64,
@@ -41,6 +96,52 @@ pub enum UrlProbeKind {
     Failed { reason: String },
 }
 
+/// 切换供应商前的单次校验结果（沿用 benchmark 的 OK/MO/OV/FB/FAIL 分类）
+#[derive(Debug, Clone)]
+pub struct ProviderVerification {
+    pub mode: ProbeMode,
+    pub detail: UrlProbeDetail,
+}
+
+impl ProviderVerification {
+    /// 除 FAIL 外均视为通过（满载说明 key 与 URL 可用；FB 说明 URL 可达）
+    pub fn passed(&self) -> bool {
+        !matches!(self.detail.kind, UrlProbeKind::Failed { .. })
+    }
+
+    pub fn describe(&self) -> String {
+        let url = &self.detail.url;
+        match &self.detail.kind {
+            UrlProbeKind::FullOk { latency_ms } => format!("OK {url} ({latency_ms}ms)"),
+            UrlProbeKind::ModelsOk { latency_ms } => {
+                format!("MO {url} ({latency_ms}ms, 仅确认可列出模型)")
+            }
+            UrlProbeKind::Overloaded {
+                latency_ms,
+                message,
+            } => format!("OV {url} ({latency_ms}ms, 上游满载: {message})"),
+            UrlProbeKind::FallbackOk {
+                connect_ms, reason, ..
+            } => format!("FB {url} (仅确认可连通 {connect_ms}ms: {reason})"),
+            UrlProbeKind::Failed { reason } => format!("FAIL {url}: {reason}"),
+        }
+    }
+
+    /// 通过返回 `Ok(None)`；未通过时 `force` 返回警告文本，否则返回错误
+    pub fn check(&self, force: bool) -> Result<Option<String>, AppError> {
+        if self.passed() {
+            Ok(None)
+        } else if force {
+            Ok(Some(format!("校验未通过，仍强制切换: {}", self.describe())))
+        } else {
+            Err(AppError::Message(format!(
+                "校验未通过，已取消切换: {}",
+                self.describe()
+            )))
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 struct UrlProbeError {
     latency_ms: u64,
@@ -50,14 +151,91 @@ struct UrlProbeError {
 #[derive(Debug, Clone)]
 enum UrlProbeErrorKind {
     Overloaded { message: String },
+    /// key 余额/额度耗尽（HTTP 402 或 insufficient balance 等提示；URL 本身可达）
+    Depleted {
+        message: String,
+    },
     Http { status: u16, body: Option<String> },
+    /// 仅 models 探测：401/403，key 无效（URL 本身可达）
+    Auth { status: u16, body: Option<String> },
     Network { message: String },
 }
 
+/// 测速判定“所有 key 均不可用”（鉴权失败或余额耗尽）时 FAIL 原因的前缀
+const ALL_KEYS_FAILED_PREFIX: &str = "所有 key 均不可用: ";
+
+/// 问答探测的提示词：短英文、温度 0，只为拿到一次可计时的完整响应
+const PROBE_PROMPT: &str = "ping";
+/// 问答探测的默认输出上限（Responses API 要求 max_output_tokens >= 16）
+const PROBE_MAX_TOKENS: u32 = 16;
+
+/// 故障转移暂停的最长时长（7 天），HTTP 接口与 Tauri 命令据此校验
+pub const MAX_FAILOVER_PAUSE_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// 供应商进入冷静期的原因（决定冷静期时长，并展示在追踪与测速结果中）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CooldownReason {
+    /// URL 可达但上游满载/限流：短暂冷却后即可重试
+    Overloaded,
+    /// 没有可用 URL（全部失败或疑似失效）
+    NoUsableUrl,
+    /// URL 可达但所有 key 均鉴权失败或余额耗尽
+    AllKeysFailed,
+}
+
+impl CooldownReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CooldownReason::Overloaded => "overloaded",
+            CooldownReason::NoUsableUrl => "no-usable-url",
+            CooldownReason::AllKeysFailed => "all-keys-failed",
+        }
+    }
+
+    /// 由 [`as_str`](Self::as_str) 标签解析（读取测速结果中的 `cooldown_reason`）
+    pub fn from_label(label: &str) -> Option<Self> {
+        [
+            CooldownReason::Overloaded,
+            CooldownReason::NoUsableUrl,
+            CooldownReason::AllKeysFailed,
+        ]
+        .into_iter()
+        .find(|r| r.as_str() == label)
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            CooldownReason::Overloaded => "上游满载",
+            CooldownReason::NoUsableUrl => "无可用 URL",
+            CooldownReason::AllKeysFailed => "所有 key 均不可用",
+        }
+    }
+
+    /// 由测速明细推断供应商无可用 URL 的原因：
+    /// 任一 URL 满载视为满载；全部 URL 均因 key 不可用而失败视为 key 全部失败；其余为无可用 URL
+    fn from_details(details: &[UrlProbeDetail]) -> Self {
+        if details
+            .iter()
+            .any(|d| matches!(d.kind, UrlProbeKind::Overloaded { .. }))
+        {
+            return CooldownReason::Overloaded;
+        }
+        let all_keys_failed = !details.is_empty()
+            && details.iter().all(|d| {
+                matches!(&d.kind, UrlProbeKind::Failed { reason } if reason.starts_with(ALL_KEYS_FAILED_PREFIX))
+            });
+        if all_keys_failed {
+            CooldownReason::AllKeysFailed
+        } else {
+            CooldownReason::NoUsableUrl
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BenchmarkUrlResult {
     pub url: String,
-    /// OK / OV / FB / FAIL
+    /// OK / MO（仅模型列表探测成功） / OV / FB / FAIL
     pub kind: String,
     pub latency_ms: Option<u64>,
     pub penalty_ms: Option<u64>,
@@ -78,6 +256,62 @@ pub struct BenchmarkSupplierResult {
     pub chosen_kind: String,
     pub metric_ms: Option<u64>,
     pub urls: Vec<BenchmarkUrlResult>,
+    /// COOLDOWN 时的冷却原因（overloaded / no-usable-url / all-keys-failed）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cooldown_reason: Option<String>,
+}
+
+/// 测速进度事件（`csc bench` 进度条与管理端流式输出）
+///
+/// `index` 为 supplier 在本次测速中的序号（按层级、supplier 排序），`total` 为 supplier 总数。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum BenchmarkProgress {
+    /// 开始测速某个 supplier
+    SupplierStarted {
+        index: usize,
+        total: usize,
+        priority: usize,
+        supplier: String,
+    },
+    /// 某个 supplier 测速完成
+    SupplierFinished {
+        index: usize,
+        total: usize,
+        result: BenchmarkSupplierResult,
+    },
+}
+
+/// 只读选路预览中的一个候选（dry-run 使用）
+#[derive(Debug, Clone)]
+pub struct SelectionPreview {
+    pub provider: Provider,
+    /// 层级（故障转移关闭时为 None）
+    pub priority: Option<usize>,
+    pub supplier: String,
+    /// 选中的 base_url
+    pub base_url: Option<String>,
+    /// URL 来源：only / current / cached-latency / untested（实际请求会先测速）/ current-provider
+    pub url_source: &'static str,
+}
+
+/// 一次选路的结果
+#[derive(Debug, Default)]
+struct Selection {
+    /// 是否按故障转移队列选路（关闭、暂停或固定供应商时为 false）
+    failover_enabled: bool,
+    chain: Vec<Provider>,
+    /// provider id -> URL 来源（仅预览时记录）
+    url_sources: HashMap<String, &'static str>,
+}
+
+impl Selection {
+    fn single(provider: Provider) -> Self {
+        Self {
+            chain: vec![provider],
+            ..Default::default()
+        }
+    }
 }
 
 /// 供应商路由器
@@ -94,26 +328,57 @@ pub struct ProviderRouter {
     priority_level_tested: Arc<RwLock<HashMap<String, bool>>>,
     /// URL延迟缓存 - key 格式: "app_type:priority:supplier:base_url", value: 延迟测试结果
     url_latencies: Arc<RwLock<HashMap<String, UrlLatency>>>,
-    /// 供应商冷静期 - key 格式: "app_type:priority:supplier", value: 冷静期结束时间
-    supplier_cooldowns: Arc<RwLock<HashMap<String, std::time::Instant>>>,
-    /// URL 疑似失效标记 - key 格式: "app_type:supplier:base_url", value: 解除时间
-    suspect_urls: Arc<RwLock<HashMap<String, std::time::Instant>>>,
+    /// 供应商冷静期 - key 格式: "app_type:priority:supplier", value: (冷静期结束时间, 冷却原因)
+    supplier_cooldowns: Arc<RwLock<HashMap<String, (std::time::Instant, CooldownReason)>>>,
+    /// URL 疑似失效标记 - key 格式: "app_type:supplier:base_url", value: 解除时间与连续标记次数
+    suspect_urls: Arc<RwLock<HashMap<String, SuspectUrl>>>,
     /// URL 疑似失效后，下一次选路完成时输出“切换结果”（避免只看到“疑似失效”看不到切换到哪）
     /// key 格式: "app_type:priority:supplier"
     supplier_pending_url_switch: Arc<RwLock<HashMap<String, PendingUrlSwitch>>>,
     /// 每个供应商当前选中的 URL（同一时刻只使用一个“最快 URL”）
     /// key 格式: "app_type:priority:supplier", value: base_url
     supplier_current_url: Arc<RwLock<HashMap<String, String>>>,
+    /// 各 URL 最近一次连接保温时间 - key 格式: "app_type:base_url"
+    keep_warm_at: Arc<RwLock<HashMap<String, std::time::Instant>>>,
     /// 供应商需触发“URL失效后的重新测速”（只触发一次，避免刷屏）
     /// key 格式: "app_type:priority:supplier", value: 触发有效期
     supplier_retest_once: Arc<RwLock<HashMap<String, std::time::Instant>>>,
     /// 供应商测速锁（避免并发请求触发重复测速）
     /// key 格式: "app_type:priority:supplier"
     supplier_benchmark_locks: Arc<RwLock<HashMap<String, Arc<Mutex<()>>>>>,
+    /// 探测并发上限（按需测速、选路测速与后台健康检查共用）
+    probe_semaphore: Arc<Semaphore>,
+    /// 各供应商最近一次（或已排定的下一次）探测时间，用于同一供应商的探测间隔
+    /// key 格式: "app_type:supplier"
+    supplier_last_probe: Arc<RwLock<HashMap<String, std::time::Instant>>>,
     /// 启动即测速（保底）模式下的测试覆盖：用于将下一次（或短时间内）请求强制路由到指定 supplier
     test_override: Arc<RwLock<Option<TestOverride>>>,
+    /// 按请求固定供应商（`csc replay` / `csc compare`）：请求 ID -> 固定目标
+    ///
+    /// 只对携带该请求 ID 的请求生效，同时进行的正常请求照常选路；请求结束时由
+    /// [`ProviderPinGuard`] 在析构中清理（同步上下文，因此使用 std 锁）
+    provider_pins: Arc<std::sync::Mutex<HashMap<String, ProviderPin>>>,
     /// 测试结果（run_id -> result），供 CLI 轮询读取
     test_results: Arc<RwLock<HashMap<String, BenchmarkSupplierResult>>>,
+    /// 测速中探测成功的 key（provider id），测速后的下一次选路排在层级最前
+    /// key 格式: "app_type:priority"
+    benchmark_winning_keys: Arc<RwLock<HashMap<String, HashSet<String>>>>,
+    /// 路由事件记录（熔断切换、冷却、疑似失效、余额耗尽）
+    events: EventRecorder,
+    /// 数据库暂不可用（锁定/损坏）时的兜底：各应用最近一次成功加载的请求快照
+    last_good_scopes: Arc<RwLock<HashMap<String, RequestScope>>>,
+    /// 数据库暂不可用时的兜底：各应用最近一次成功选出的候选链
+    last_good_providers: Arc<RwLock<HashMap<String, Vec<Provider>>>>,
+    /// 正在使用兜底数据的应用（按应用独立进入/退出降级）
+    db_degraded_apps: Arc<std::sync::RwLock<HashSet<String>>>,
+    /// 已提示过供应商配置缺失的应用（每次缺失只通知一次，选路恢复后清除）
+    setup_missing_apps: Arc<RwLock<HashSet<String>>>,
+    /// 临时暂停故障转移：app_type -> 截止时刻（不持久化，代理重启即失效）
+    ///
+    /// 托盘菜单在同步上下文中读取，因此使用 std 锁
+    failover_pauses: Arc<std::sync::RwLock<HashMap<String, std::time::Instant>>>,
+    /// 只读模式：熔断器等内存状态照常更新，但不写数据库
+    read_only: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -126,20 +391,111 @@ struct TestOverride {
     expires_at: std::time::Instant,
 }
 
+/// 等待请求认领的供应商固定
+#[derive(Debug, Clone)]
+struct ProviderPin {
+    app_type: String,
+    provider_id: String,
+    expires_at: std::time::Instant,
+}
+
+/// 请求认领的供应商固定：析构时移除，请求无论从哪条路径结束都会清理
+pub struct ProviderPinGuard {
+    pins: Arc<std::sync::Mutex<HashMap<String, ProviderPin>>>,
+    request_id: String,
+    provider_id: String,
+}
+
+impl ProviderPinGuard {
+    pub fn provider_id(&self) -> &str {
+        &self.provider_id
+    }
+}
+
+impl Drop for ProviderPinGuard {
+    fn drop(&mut self) {
+        if let Ok(mut pins) = self.pins.lock() {
+            pins.remove(&self.request_id);
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 struct PendingUrlSwitch {
     from_url: String,
     expires_at: std::time::Instant,
 }
 
+/// 按应用配置的连通性惩罚与探测超时
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ProbeTuning {
+    /// 仅连通性可用（满载/回退）时追加的排序惩罚
+    penalty_ms: u64,
+    /// 连通性探测（HEAD base_url）超时
+    connectivity_timeout: Duration,
+    /// 全链路/模型列表探测超时
+    probe_timeout: Duration,
+    /// 同一供应商相邻两次探测的最小间隔
+    min_probe_interval: Duration,
+    /// 近期有真实成功请求（该时长内）的 URL 跳过探测；0 表示不跳过
+    skip_recent_success_secs: u64,
+    /// 测速时同时测试的 supplier 数
+    benchmark_concurrency: usize,
+}
+
+impl Default for ProbeTuning {
+    fn default() -> Self {
+        Self {
+            penalty_ms: ProviderRouter::DEFAULT_CONNECTIVITY_PENALTY_MS,
+            connectivity_timeout: ProviderRouter::DEFAULT_CONNECTIVITY_TIMEOUT,
+            probe_timeout: ProviderRouter::DEFAULT_PROBE_TIMEOUT,
+            min_probe_interval: ProviderRouter::DEFAULT_MIN_PROBE_INTERVAL,
+            skip_recent_success_secs: ProviderRouter::DEFAULT_SKIP_RECENT_SUCCESS_SECS,
+            benchmark_concurrency: ProviderRouter::DEFAULT_BENCHMARK_CONCURRENCY,
+        }
+    }
+}
+
 impl ProviderRouter {
-    const CONNECTIVITY_TIMEOUT: Duration = Duration::from_secs(5);
-    const CONNECTIVITY_PENALTY_MS: u64 = 30_000;
+    /// 连通性探测默认超时（可按应用配置 connectivity_timeout_secs）
+    const DEFAULT_CONNECTIVITY_TIMEOUT: Duration = Duration::from_secs(5);
+    /// 仅连通性可用 URL 的默认排序惩罚（可按应用配置 connectivity_penalty_ms）
+    const DEFAULT_CONNECTIVITY_PENALTY_MS: u64 = 30_000;
+    /// 全链路/模型列表探测默认超时（可按应用配置 probe_timeout_secs）
+    const DEFAULT_PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+    /// 同一供应商探测的默认最小间隔（可按应用配置 probe_min_interval_ms）
+    const DEFAULT_MIN_PROBE_INTERVAL: Duration = Duration::from_millis(500);
+    /// 近期成功请求跳过探测的默认时长（可按应用配置 probe_skip_recent_success_secs）
+    const DEFAULT_SKIP_RECENT_SUCCESS_SECS: u64 = 120;
+    /// 测速时默认同时测试的 supplier 数（可按应用配置 benchmark_concurrency）
+    const DEFAULT_BENCHMARK_CONCURRENCY: usize = 3;
+    /// 同时进行中的探测数上限（所有应用共用）
+    const MAX_CONCURRENT_PROBES: usize = 4;
+    /// 估算近期成功延迟时读取的最近成功请求条数
+    const RECENT_SUCCESS_SAMPLE_ROWS: usize = 20;
     const DEFAULT_BENCHMARK_SUMMARY_INFO_ENV: &'static str = "CC_SWITCH_BENCHMARK_SUMMARY";
     /// 熔断器 Open -> HalfOpen 的最小冷静期（秒）：避免频繁 HalfOpen 探测拖慢正常服务
     const MIN_CIRCUIT_OPEN_TIMEOUT_SECS: u64 = 600;
     /// 标记 URL 疑似失效前至少连续失败轮数（默认对齐 3 轮）
     const MIN_NETWORK_FAILS_BEFORE_SUSPECT: u32 = 3;
+    /// URL 连续被标记疑似失效时的时长（秒）：按次数指数增长，超出后保持上限
+    const SUSPECT_BACKOFF_SECS: [u64; 5] = [60, 120, 300, 900, 3600];
+    /// 测速时同一 URL 默认最多尝试的 key 数（可按应用配置 benchmark_max_keys_per_url）
+    const DEFAULT_BENCHMARK_MAX_KEYS_PER_URL: usize = 2;
+    /// 余额耗尽后默认跳过的时长（可按应用配置 depleted_recheck_secs）
+    const DEFAULT_DEPLETED_RECHECK_SECS: u64 = 6 * 3600;
+    /// 供应商无可用 URL（非满载原因）时的冷静期（秒）
+    const HARD_FAILURE_COOLDOWN_SECS: u64 = 20;
+    /// 供应商仅因满载无可用 URL 时的默认冷静期（可按应用配置 overload_cooldown_secs）
+    const DEFAULT_OVERLOAD_COOLDOWN_SECS: u64 = 5;
+    /// 后台清理过期内存条目的间隔
+    const JANITOR_INTERVAL: Duration = Duration::from_secs(10 * 60);
+    /// URL 延迟缓存的最长保留时间（超过后清理，下次选路按需重新测速）
+    const URL_LATENCY_MAX_AGE: Duration = Duration::from_secs(24 * 3600);
+    /// 疑似失效标记到期后继续保留连续标记次数的时长（与最长标记时长一致）
+    const SUSPECT_STRIKE_RETENTION: Duration = Duration::from_secs(3600);
+    /// 探测时间记录的保留时长（远大于探测间隔，过期即可清理）
+    const PROBE_PACING_RETENTION: Duration = Duration::from_secs(60);
 
     /// 创建新的供应商路由器
     pub fn new(db: Arc<Database>) -> Self {
@@ -154,16 +510,107 @@ impl ProviderRouter {
             suspect_urls: Arc::new(RwLock::new(HashMap::new())),
             supplier_pending_url_switch: Arc::new(RwLock::new(HashMap::new())),
             supplier_current_url: Arc::new(RwLock::new(HashMap::new())),
+            keep_warm_at: Arc::new(RwLock::new(HashMap::new())),
             supplier_retest_once: Arc::new(RwLock::new(HashMap::new())),
             supplier_benchmark_locks: Arc::new(RwLock::new(HashMap::new())),
+            probe_semaphore: Arc::new(Semaphore::new(Self::MAX_CONCURRENT_PROBES)),
+            supplier_last_probe: Arc::new(RwLock::new(HashMap::new())),
             test_override: Arc::new(RwLock::new(None)),
+            provider_pins: Arc::new(std::sync::Mutex::new(HashMap::new())),
             test_results: Arc::new(RwLock::new(HashMap::new())),
+            benchmark_winning_keys: Arc::new(RwLock::new(HashMap::new())),
+            events: EventRecorder::disabled(),
+            last_good_scopes: Arc::new(RwLock::new(HashMap::new())),
+            last_good_providers: Arc::new(RwLock::new(HashMap::new())),
+            db_degraded_apps: Arc::new(std::sync::RwLock::new(HashSet::new())),
+            setup_missing_apps: Arc::new(RwLock::new(HashSet::new())),
+            failover_pauses: Arc::new(std::sync::RwLock::new(HashMap::new())),
+            read_only: false,
         }
     }
 
-    #[inline]
-    fn normalize_base_url(url: &str) -> String {
-        url.trim().trim_end_matches('/').to_string()
+    /// 只读模式（代理服务启动时按全局配置设置）
+    pub fn with_read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// 是否为只读模式：请求日志、写回、健康状态等数据库写入均跳过
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// 设置路由事件记录器（代理服务启动时注入）
+    pub fn with_event_recorder(mut self, events: EventRecorder) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// 路由事件记录器（转发器记录“全部失败”等事件时复用）
+    pub fn event_recorder(&self) -> &EventRecorder {
+        &self.events
+    }
+
+    /// 熔断状态发生变化时记录事件
+    fn record_breaker_transition(
+        &self,
+        app_type: &str,
+        provider_id: &str,
+        before: CircuitState,
+        after: CircuitState,
+        detail: Value,
+    ) {
+        let kind = match after {
+            _ if before == after => return,
+            CircuitState::Open => EventKind::BreakerOpen,
+            CircuitState::HalfOpen => EventKind::BreakerHalfOpen,
+            CircuitState::Closed => EventKind::BreakerClose,
+        };
+        self.events
+            .record(app_type, kind, Some(provider_id), None, detail);
+    }
+
+    /// 规范化 base_url：去掉首尾空白与末尾 `/`，scheme 与主机名转小写，省略默认端口，路径原样保留
+    ///
+    /// 同一 URL 的不同写法（`https://X.com:443/v1/` 与 `https://x.com/v1`）归为同一个 URL 分组，
+    /// 测速缓存、疑似失效标记与 URL 优先级匹配都以规范化后的值为键。主机名按 `url` 解析，
+    /// IPv6 字面量（`http://[::1]:8080`）与 IP + 端口同样适用；无法解析时只做去空白与末尾 `/`。
+    pub(crate) fn normalize_base_url(url: &str) -> String {
+        let url = url.trim().trim_end_matches('/');
+        let Some(parsed) = url::Url::parse(url).ok().filter(|u| u.has_host()) else {
+            return url.to_string();
+        };
+        // 路径与查询取原文：`url` 会对路径做百分号编码等改写
+        let rest = url.split_once("://").map_or("", |(_, rest)| rest);
+        let path = rest.find(['/', '?', '#']).map_or("", |i| &rest[i..]);
+
+        let mut out = format!("{}://", parsed.scheme());
+        if !parsed.username().is_empty() {
+            out.push_str(parsed.username());
+            if let Some(password) = parsed.password() {
+                out.push(':');
+                out.push_str(password);
+            }
+            out.push('@');
+        }
+        out.push_str(parsed.host_str().unwrap_or_default());
+        if let Some(port) = parsed.port() {
+            out.push_str(&format!(":{port}"));
+        }
+        out.push_str(path);
+        out
+    }
+
+    /// 校验 base_url：必须是带主机名的 http(s) 地址（支持 IPv6 字面量与自定义端口）
+    pub fn validate_base_url(url: &str) -> Result<(), String> {
+        let parsed = url::Url::parse(url.trim()).map_err(|e| format!("{url}: {e}"))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(format!("{url}: 仅支持 http/https"));
+        }
+        if !parsed.has_host() {
+            return Err(format!("{url}: 缺少主机名"));
+        }
+        Ok(())
     }
 
     #[inline]
@@ -173,7 +620,10 @@ impl ProviderRouter {
 
     #[inline]
     fn url_latency_key(app_type: &str, priority: usize, supplier: &str, url: &str) -> String {
-        format!("{app_type}:{priority}:{supplier}:{url}")
+        format!(
+            "{app_type}:{priority}:{supplier}:{}",
+            Self::normalize_base_url(url)
+        )
     }
 
     async fn get_supplier_current_url(
@@ -187,7 +637,7 @@ impl ProviderRouter {
         map.get(&key).cloned()
     }
 
-    async fn set_supplier_current_url(
+    pub(crate) async fn set_supplier_current_url(
         &self,
         app_type: &str,
         priority: usize,
@@ -238,6 +688,58 @@ impl ProviderRouter {
         map.remove(&key);
     }
 
+    /// 连接保温目标：该应用各供应商当前锁定的 URL（去重、排序）
+    pub(crate) async fn keep_warm_targets(&self, app_type: &str) -> Vec<String> {
+        let prefix = format!("{app_type}:");
+        let map = self.supplier_current_url.read().await;
+        let mut targets: Vec<String> = map
+            .iter()
+            .filter(|(key, _)| key.starts_with(&prefix))
+            .map(|(_, url)| url.clone())
+            .collect();
+        targets.sort();
+        targets.dedup();
+        targets
+    }
+
+    /// 记录一次连接保温（只用于路由快照展示，不影响选路）
+    pub(crate) async fn record_keep_warm(&self, app_type: &str, url: &str) {
+        let key = format!("{app_type}:{}", Self::normalize_base_url(url));
+        self.keep_warm_at
+            .write()
+            .await
+            .insert(key, std::time::Instant::now());
+    }
+
+    /// 队列层级调整后：清除这些 supplier 在所有层级下的“已测试”与 current_url 状态，
+    /// 使新顺序在下一次请求时立即生效。返回清除的条目数。
+    pub async fn invalidate_suppliers(&self, app_type: &str, suppliers: &[String]) -> usize {
+        // key 形如 `{app_type}:{priority}:{supplier}`
+        let affected = |key: &str| {
+            let mut parts = key.splitn(3, ':');
+            parts.next() == Some(app_type)
+                && parts.next().is_some()
+                && parts
+                    .next()
+                    .is_some_and(|s| suppliers.iter().any(|x| x == s))
+        };
+
+        let mut removed = 0;
+        {
+            let mut tested = self.priority_level_tested.write().await;
+            let before = tested.len();
+            tested.retain(|k, _| !affected(k));
+            removed += before - tested.len();
+        }
+        {
+            let mut current = self.supplier_current_url.write().await;
+            let before = current.len();
+            current.retain(|k, _| !affected(k));
+            removed += before - current.len();
+        }
+        removed
+    }
+
     async fn get_active_test_override(&self, app_type: &str) -> Option<TestOverride> {
         let mut guard = self.test_override.write().await;
         if let Some(o) = guard.as_ref() {
@@ -270,29 +772,11 @@ impl ProviderRouter {
         env_key: &str,
         env_value: &str,
     ) -> Result<(), AppError> {
-        let db = self.db.clone();
-        let app_type = app_type.to_string();
-        let provider_id = provider_id.to_string();
         let env_key = env_key.to_string();
         let env_value = env_value.to_string();
 
-        tokio::task::spawn_blocking(move || -> Result<(), AppError> {
-            let Some(mut provider) = db.get_provider_by_id(&provider_id, &app_type)? else {
-                return Ok(());
-            };
-
-            // 确保 settings_config.env 存在且为 object
-            if provider.settings_config.is_null() {
-                provider.settings_config = serde_json::json!({});
-            }
-            if !provider.settings_config.is_object() {
-                provider.settings_config = serde_json::json!({});
-            }
-
-            let env = provider
-                .settings_config
-                .as_object_mut()
-                .expect("settings_config should be object")
+        self.writeback_provider_settings(app_type, provider_id, move |settings| {
+            let env = settings
                 .entry("env")
                 .or_insert_with(|| serde_json::json!({}));
 
@@ -303,12 +787,64 @@ impl ProviderRouter {
             env.as_object_mut()
                 .expect("env should be object")
                 .insert(env_key, Value::String(env_value));
+        })
+        .await
+    }
+
+    /// 写回 settings_config 顶层字段（如 `supportsThinking`）
+    pub async fn writeback_provider_setting(
+        &self,
+        app_type: &str,
+        provider_id: &str,
+        key: &str,
+        value: Value,
+    ) -> Result<(), AppError> {
+        let key = key.to_string();
+        self.writeback_provider_settings(app_type, provider_id, move |settings| {
+            settings.insert(key, value);
+        })
+        .await
+    }
+
+    async fn writeback_provider_settings<F>(
+        &self,
+        app_type: &str,
+        provider_id: &str,
+        update: F,
+    ) -> Result<(), AppError>
+    where
+        F: FnOnce(&mut serde_json::Map<String, Value>) + Send + 'static,
+    {
+        if self.read_only {
+            log::debug!("[{app_type}] 只读模式，跳过写回供应商 {provider_id} 的配置");
+            return Ok(());
+        }
+        let db = self.db.clone();
+        let app_type = app_type.to_string();
+        let provider_id = provider_id.to_string();
+
+        tokio::task::spawn_blocking(move || -> Result<(), AppError> {
+            let Some(mut provider) = db.get_provider_by_id(&provider_id, &app_type)? else {
+                return Ok(());
+            };
+
+            // 确保 settings_config 为 object
+            if !provider.settings_config.is_object() {
+                provider.settings_config = serde_json::json!({});
+            }
+
+            update(
+                provider
+                    .settings_config
+                    .as_object_mut()
+                    .expect("settings_config should be object"),
+            );
 
             db.save_provider(&app_type, &provider)?;
             Ok(())
         })
         .await
-        .map_err(|e| AppError::Message(format!("写回模型配置任务失败: {e}")))?
+        .map_err(|e| AppError::Message(format!("写回供应商配置任务失败: {e}")))?
     }
 
     async fn mark_supplier_retest_once(
@@ -374,6 +910,47 @@ impl ProviderRouter {
         *self.test_override.write().await = Some(override_state);
     }
 
+    /// 将请求 ID 为 `request_id` 的请求固定到指定供应商（不论故障转移开关与队列、冷却、熔断状态）
+    ///
+    /// 只匹配该请求，不影响其它请求；`ttl_secs` 内未被请求认领则失效。
+    pub fn pin_provider_for_request(
+        &self,
+        app_type: &str,
+        provider_id: &str,
+        request_id: &str,
+        ttl_secs: u64,
+    ) {
+        let now = std::time::Instant::now();
+        let Some(expires_at) = now.checked_add(std::time::Duration::from_secs(ttl_secs)) else {
+            return;
+        };
+        if let Ok(mut pins) = self.provider_pins.lock() {
+            pins.retain(|_, pin| pin.expires_at > now);
+            pins.insert(
+                request_id.to_string(),
+                ProviderPin {
+                    app_type: app_type.to_string(),
+                    provider_id: provider_id.to_string(),
+                    expires_at,
+                },
+            );
+        }
+    }
+
+    /// 请求开始时认领针对它的供应商固定；返回的 guard 析构时移除固定
+    pub fn claim_provider_pin(&self, app_type: &str, request_id: &str) -> Option<ProviderPinGuard> {
+        let pins = self.provider_pins.lock().ok()?;
+        let pin = pins.get(request_id)?;
+        if pin.app_type != app_type || pin.expires_at <= std::time::Instant::now() {
+            return None;
+        }
+        Some(ProviderPinGuard {
+            pins: self.provider_pins.clone(),
+            request_id: request_id.to_string(),
+            provider_id: pin.provider_id.clone(),
+        })
+    }
+
     /// 供 forwarder 使用：如果当前存在生效的 startup 测试覆盖，并且 provider 与其匹配，
     /// 则将这一次“真实请求”的结果写入 test_results，并清理覆盖状态。
     pub async fn maybe_record_startup_test_from_forwarder(
@@ -394,7 +971,7 @@ impl ProviderRouter {
         if o.supplier != supplier {
             return;
         }
-        let priority = provider.sort_index.unwrap_or(999999) as usize;
+        let priority = provider.priority();
         if o.priority != priority {
             return;
         }
@@ -403,7 +980,7 @@ impl ProviderRouter {
             return;
         };
         if let Some(pinned) = o.base_url.as_deref() {
-            if pinned.trim_end_matches('/') != base_url.trim_end_matches('/') {
+            if pinned != base_url {
                 return;
             }
         }
@@ -416,6 +993,7 @@ impl ProviderRouter {
             }
         }
 
+        let penalty_ms = self.probe_tuning(app_type).await.penalty_ms;
         let (kind, metric_ms, message, reason) = match (status, error_text.as_deref()) {
             (Some(code), _) if (200..300).contains(&code) => (
                 "OK".to_string(),
@@ -428,7 +1006,7 @@ impl ProviderRouter {
                 if Self::is_overloaded_error_text(&msg) {
                     (
                         "OV".to_string(),
-                        Some(latency_ms.saturating_add(Self::CONNECTIVITY_PENALTY_MS)),
+                        Some(latency_ms.saturating_add(penalty_ms)),
                         Some(msg),
                         None,
                     )
@@ -465,11 +1043,7 @@ impl ProviderRouter {
             url: base_url.clone(),
             kind: kind.clone(),
             latency_ms: Some(latency_ms),
-            penalty_ms: if kind == "OV" {
-                Some(Self::CONNECTIVITY_PENALTY_MS)
-            } else {
-                None
-            },
+            penalty_ms: if kind == "OV" { Some(penalty_ms) } else { None },
             message,
             reason,
         };
@@ -483,7 +1057,9 @@ impl ProviderRouter {
             chosen_kind: kind.clone(),
             metric_ms,
             urls: vec![url_result],
+            cooldown_reason: None,
         };
+        self.record_benchmark_history(app_type, std::slice::from_ref(&result));
 
         {
             let mut map = self.test_results.write().await;
@@ -516,7 +1092,10 @@ impl ProviderRouter {
         map.get(run_id).cloned()
     }
 
-    fn details_to_benchmark_url_results(details: &[UrlProbeDetail]) -> Vec<BenchmarkUrlResult> {
+    fn details_to_benchmark_url_results(
+        details: &[UrlProbeDetail],
+        overload_penalty_ms: u64,
+    ) -> Vec<BenchmarkUrlResult> {
         details
             .iter()
             .map(|d| {
@@ -528,10 +1107,13 @@ impl ProviderRouter {
                         None,
                         None,
                     ),
+                    UrlProbeKind::ModelsOk { latency_ms } => {
+                        ("MO".to_string(), Some(*latency_ms), None, None, None)
+                    }
                     UrlProbeKind::Overloaded { latency_ms, message } => (
                         "OV".to_string(),
                         Some(*latency_ms),
-                        Some(Self::CONNECTIVITY_PENALTY_MS),
+                        Some(overload_penalty_ms),
                         Some(message.clone()),
                         None,
                     ),
@@ -586,28 +1168,26 @@ impl ProviderRouter {
         map.entry(key).or_insert_with(|| Arc::new(Mutex::new(()))).clone()
     }
 
-    fn is_likely_network_error(err: &str) -> bool {
-        err.contains("超时")
-            || err.contains("连接失败")
-            || err.contains("Connection refused")
-            || err.contains("connection refused")
-            || err.contains("dns")
-            || err.contains("DNS")
-            || err.contains("timed out")
-            || err.contains("error sending request")
-            || err.contains("connection closed")
-            || err.contains("Upstream request failed")
-            || err.contains("请求转发失败: error")
-            || err.contains("请求转发失败: timed out")
-            || err.contains("请求转发失败: Connection refused")
-    }
-
-    fn default_url_priority_for_supplier(supplier: &str) -> Vec<&'static str> {
-        match supplier.to_lowercase().as_str() {
-            // 支持为特定 supplier 内置首选 URL（用于更快命中稳定入口）
-            "anyrouter" => vec!["https://anyrouter.top"],
-            _ => Vec::new(),
+    /// supplier 的首选 URL 列表（已去重，保留顺序）
+    ///
+    /// 供应商自身的 baseUrlPriority / BASE_URL_PRIORITY 优先，其次是数据库中按 supplier 配置的列表
+    /// （`csc url-priority set`）。
+    fn preferred_urls_for_supplier(
+        &self,
+        supplier: &str,
+        provider: Option<&Provider>,
+    ) -> Vec<String> {
+        let mut preferred: Vec<String> = provider
+            .map(Self::parse_url_priority_from_provider)
+            .unwrap_or_default();
+        match self.db.get_supplier_url_priority(supplier) {
+            Ok(urls) => preferred.extend(urls.iter().map(|u| Self::normalize_base_url(u))),
+            Err(e) => log::warn!("读取 supplier={supplier} 的 URL 优先级失败: {e}"),
         }
+
+        let mut seen = std::collections::HashSet::<String>::new();
+        preferred.retain(|u| seen.insert(u.to_string()));
+        preferred
     }
 
     fn parse_url_priority_from_provider(provider: &Provider) -> Vec<String> {
@@ -625,17 +1205,17 @@ impl ProviderRouter {
             if let Some(arr) = v.as_array() {
                 for item in arr {
                     if let Some(s) = item.as_str() {
-                        let s = s.trim();
+                        let s = Self::normalize_base_url(s);
                         if !s.is_empty() {
-                            out.push(s.to_string());
+                            out.push(s);
                         }
                     }
                 }
             } else if let Some(s) = v.as_str() {
                 for part in s.split(',') {
-                    let p = part.trim();
+                    let p = Self::normalize_base_url(part);
                     if !p.is_empty() {
-                        out.push(p.to_string());
+                        out.push(p);
                     }
                 }
             }
@@ -648,9 +1228,9 @@ impl ProviderRouter {
             .and_then(|v| v.as_str())
         {
             for part in s.split(',') {
-                let p = part.trim();
+                let p = Self::normalize_base_url(part);
                 if !p.is_empty() {
-                    out.push(p.to_string());
+                    out.push(p);
                 }
             }
         }
@@ -667,7 +1247,8 @@ impl ProviderRouter {
         }
         let mut picked = Vec::with_capacity(urls.len());
         for p in priority {
-            if let Some(pos) = urls.iter().position(|u| u == p) {
+            let p = Self::normalize_base_url(p);
+            if let Some(pos) = urls.iter().position(|u| Self::normalize_base_url(u) == p) {
                 picked.push(urls.remove(pos));
             }
         }
@@ -698,71 +1279,111 @@ impl ProviderRouter {
     }
 
     fn is_overloaded_error_text(text: &str) -> bool {
-        // 常见“可达但不可用”的提示（满载/限流/暂不可用）
-        text.contains("负载已经达到上限")
-            || text.contains("满载")
-            || text.contains("rate limit")
-            || text.contains("Rate limit")
-            || text.contains("Too Many Requests")
-            || text.contains("temporarily unavailable")
+        is_overloaded_text(text)
     }
 
     fn extract_error_message_from_body(body: &str) -> Option<String> {
-        let Ok(v) = serde_json::from_str::<serde_json::Value>(body) else {
+        upstream_error_message(body)
+    }
+
+    /// 探测响应为余额耗尽（HTTP 402 或响应体含余额不足提示）时返回 `Depleted`
+    fn depleted_probe_error(status: u16, body: Option<&str>) -> Option<UrlProbeErrorKind> {
+        let body = body.unwrap_or_default();
+        if status != 402 && !is_depleted_text(body) {
             return None;
+        }
+        let message =
+            Self::extract_error_message_from_body(body).unwrap_or_else(|| body.trim().to_string());
+        let message = if message.is_empty() {
+            format!("HTTP {status}")
+        } else {
+            Self::shorten_for_log(&message, 200)
         };
+        Some(UrlProbeErrorKind::Depleted { message })
+    }
 
-        // 兼容多种错误结构
-        if let Some(msg) = v
-            .get("error")
-            .and_then(|e| e.get("message"))
-            .and_then(|m| m.as_str())
-        {
-            return Some(msg.to_string());
+    /// 探测的非 2xx 响应分类：余额耗尽 / 鉴权失败（401/403）/ 其它 HTTP 错误
+    fn http_probe_error(status: u16, body: Option<String>) -> UrlProbeErrorKind {
+        if let Some(kind) = Self::depleted_probe_error(status, body.as_deref()) {
+            return kind;
         }
-
-        if let Some(msg) = v.get("message").and_then(|m| m.as_str()) {
-            return Some(msg.to_string());
+        match status {
+            401 | 403 => UrlProbeErrorKind::Auth { status, body },
+            _ => UrlProbeErrorKind::Http { status, body },
         }
-
-        None
     }
 
 
     fn supplier_name(provider: &Provider) -> String {
-        provider
-            .name
-            .split('-')
-            .next()
-            .unwrap_or(&provider.name)
-            .to_string()
+        provider.supplier()
     }
 
-    fn extract_base_url(provider: &Provider, app_type: &str) -> Option<String> {
-        match app_type {
+    /// 展开 `${ENV_VAR}` 占位符；变量未设置时视为未配置（转发时会给出明确错误）
+    fn expand_setting(provider: &Provider, value: String) -> Option<String> {
+        match expand_env_str(&value, "settings_config") {
+            Ok(expanded) => Some(expanded.into_owned()),
+            Err(e) => {
+                log::debug!("[{}] {}", provider.id, e);
+                None
+            }
+        }
+    }
+
+    /// 提取并展开 base_url；引用了未设置的环境变量时记录警告并返回 `None`
+    /// （审计通过 [`Self::try_extract_base_url`] 报告具体变量）
+    pub fn extract_base_url(provider: &Provider, app_type: &str) -> Option<String> {
+        match Self::try_extract_base_url(provider, app_type) {
+            Ok(url) => url,
+            Err(e) => {
+                log::warn!("[{}] base_url 无法展开: {}", provider.id, e);
+                None
+            }
+        }
+    }
+
+    /// 提取并展开 base_url；未配置时为 `Ok(None)`，引用了未设置的环境变量时返回错误
+    pub fn try_extract_base_url(
+        provider: &Provider,
+        app_type: &str,
+    ) -> Result<Option<String>, MissingEnvVar> {
+        let raw = match app_type {
             "claude" => provider
                 .settings_config
                 .get("env")
                 .and_then(|env| env.get("ANTHROPIC_BASE_URL"))
                 .and_then(|v| v.as_str())
-                .map(|s| s.to_string()),
+                .map(|s| s.to_string())
+                // Bedrock 供应商没有 ANTHROPIC_BASE_URL：按 Bedrock 端点分组/测速
+                .or_else(|| BedrockConfig::from_provider(provider).map(|b| b.base_url())),
             "gemini" => provider
                 .settings_config
                 .get("env")
                 .and_then(|env| env.get("GOOGLE_GEMINI_BASE_URL"))
                 .and_then(|v| v.as_str())
-                .map(|s| s.to_string()),
+                .map(|s| s.to_string())
+                // Vertex AI 供应商按 Vertex 端点分组/测速
+                .or_else(|| VertexConfig::from_provider(provider).map(|v| v.base_url())),
             "codex" => provider
                 .settings_config
                 .get("base_url")
                 .and_then(|v| v.as_str())
                 .map(|s| s.to_string()),
             _ => None,
-        }
+        };
+        let Some(raw) = raw else {
+            return Ok(None);
+        };
+        let path = match app_type {
+            "claude" => "env.ANTHROPIC_BASE_URL",
+            "gemini" => "env.GOOGLE_GEMINI_BASE_URL",
+            _ => "base_url",
+        };
+        let expanded = expand_env_str(&raw, path)?;
+        Ok(Some(Self::normalize_base_url(&expanded)))
     }
 
-    fn extract_api_key_value(provider: &Provider, app_type: &str) -> Option<String> {
-        match app_type {
+    pub(crate) fn extract_api_key_value(provider: &Provider, app_type: &str) -> Option<String> {
+        let raw = match app_type {
             "claude" => provider
                 .settings_config
                 .get("env")
@@ -785,50 +1406,371 @@ impl ProviderRouter {
                 .and_then(|v| v.as_str())
                 .map(|s| s.to_string()),
             _ => None,
-        }
+        };
+        raw.and_then(|key| Self::expand_setting(provider, key))
     }
 
     async fn is_url_suspect(&self, app_type: &str, supplier: &str, url: &str) -> bool {
         let now = std::time::Instant::now();
         let key = format!("{app_type}:{supplier}:{}", Self::normalize_base_url(url));
-        let mut map = self.suspect_urls.write().await;
+        let map = self.suspect_urls.read().await;
+        // 过期条目保留连续标记次数，直到经该 URL 的请求成功
+        map.get(&key).is_some_and(|s| s.until > now)
+    }
 
-        match map.get(&key).copied() {
-            Some(until) if until > now => true,
-            Some(_) => {
-                map.remove(&key);
-                false
-            }
-            None => false,
-        }
+    /// 第 `strikes` 次标记疑似失效的时长：60s → 2m → 5m → 15m → 1h，叠加 ±10% 抖动避免多个 URL 同时恢复
+    fn suspect_secs(strikes: u32) -> u64 {
+        let idx = (strikes.max(1) as usize - 1).min(Self::SUSPECT_BACKOFF_SECS.len() - 1);
+        let base = Self::SUSPECT_BACKOFF_SECS[idx];
+        let spread = base / 10;
+        let jitter = (uuid::Uuid::new_v4().as_u128() % (2 * spread as u128 + 1)) as u64;
+        let cap = Self::SUSPECT_BACKOFF_SECS[Self::SUSPECT_BACKOFF_SECS.len() - 1];
+        (base - spread + jitter).min(cap)
     }
 
-    async fn set_url_suspect(&self, app_type: &str, supplier: &str, url: &str, seconds: u64) {
+    /// 标记 URL 疑似失效，返回 (连续标记次数, 时长秒)
+    async fn strike_url_suspect(&self, app_type: &str, supplier: &str, url: &str) -> (u32, u64) {
         let key = format!("{app_type}:{supplier}:{}", Self::normalize_base_url(url));
-        let until = std::time::Instant::now() + std::time::Duration::from_secs(seconds);
         let mut map = self.suspect_urls.write().await;
-        map.insert(key, until);
+        let strikes = map.get(&key).map_or(0, |s| s.strikes) + 1;
+        let seconds = Self::suspect_secs(strikes);
+        let until = std::time::Instant::now() + std::time::Duration::from_secs(seconds);
+        map.insert(key, SuspectUrl { until, strikes });
+        (strikes, seconds)
     }
 
-    async fn is_supplier_in_cooldown(&self, app_type: &str, priority: usize, supplier: &str) -> bool {
+    /// 当前疑似失效的 URL（按 supplier、URL 排序）
+    pub async fn suspect_url_entries(&self, app_type: &str) -> Vec<SuspectUrlEntry> {
         let now = std::time::Instant::now();
-        let key = format!("{app_type}:{priority}:{supplier}");
-        let mut map = self.supplier_cooldowns.write().await;
-        match map.get(&key).copied() {
-            Some(until) if until > now => true,
-            Some(_) => {
-                map.remove(&key);
-                false
+        let now_unix = chrono::Utc::now().timestamp();
+        let prefix = format!("{app_type}:");
+        let map = self.suspect_urls.read().await;
+        let mut out: Vec<SuspectUrlEntry> = map
+            .iter()
+            .filter(|(_, s)| s.until > now)
+            .filter_map(|(key, s)| {
+                let (supplier, url) = key.strip_prefix(&prefix)?.split_once(':')?;
+                Some(SuspectUrlEntry {
+                    supplier: supplier.to_string(),
+                    url: url.to_string(),
+                    until: now_unix + s.until.duration_since(now).as_secs() as i64,
+                    strikes: s.strikes,
+                })
+            })
+            .collect();
+        out.sort_by(|a, b| (&a.supplier, &a.url).cmp(&(&b.supplier, &b.url)));
+        out
+    }
+
+    async fn is_supplier_in_cooldown(&self, app_type: &str, priority: usize, supplier: &str) -> bool {
+        self.supplier_cooldown_reason(app_type, priority, supplier)
+            .await
+            .is_some()
+    }
+
+    /// 供应商处于冷静期时返回冷却原因（已过期的条目顺带清理）
+    async fn supplier_cooldown_reason(
+        &self,
+        app_type: &str,
+        priority: usize,
+        supplier: &str,
+    ) -> Option<CooldownReason> {
+        let now = std::time::Instant::now();
+        let key = format!("{app_type}:{priority}:{supplier}");
+        let mut map = self.supplier_cooldowns.write().await;
+        match map.get(&key).copied() {
+            Some((until, reason)) if until > now => Some(reason),
+            Some(_) => {
+                map.remove(&key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// 当前处于冷却期的供应商名称及冷却原因（用于单请求追踪展示）
+    pub async fn suppliers_in_cooldown(&self, app_type: &str) -> Vec<(String, CooldownReason)> {
+        let now = std::time::Instant::now();
+        let prefix = format!("{app_type}:");
+        let map = self.supplier_cooldowns.read().await;
+        let mut out: Vec<(String, CooldownReason)> = map
+            .iter()
+            .filter(|(_, (until, _))| *until > now)
+            .filter_map(|(key, (_, reason))| key.strip_prefix(&prefix).map(|rest| (rest, *reason)))
+            .filter_map(|(rest, reason)| {
+                rest.split_once(':')
+                    .map(|(_, supplier)| (supplier.to_string(), reason))
+            })
+            .collect();
+        out.sort_by(|a, b| a.0.cmp(&b.0));
+        out.dedup_by(|a, b| a.0 == b.0);
+        out
+    }
+
+    /// 冷静期时长：满载按应用配置（默认 5 秒），其它原因固定 20 秒
+    async fn cooldown_secs(&self, app_type: &str, reason: CooldownReason) -> u64 {
+        match reason {
+            CooldownReason::Overloaded => match self.app_config(app_type).await {
+                Ok(config) => config.overload_cooldown_secs,
+                Err(_) => Self::DEFAULT_OVERLOAD_COOLDOWN_SECS,
+            },
+            CooldownReason::NoUsableUrl | CooldownReason::AllKeysFailed => {
+                Self::HARD_FAILURE_COOLDOWN_SECS
             }
-            None => false,
         }
     }
 
-    async fn set_supplier_cooldown(&self, app_type: &str, priority: usize, supplier: &str, seconds: u64) {
+    async fn set_supplier_cooldown(
+        &self,
+        app_type: &str,
+        priority: usize,
+        supplier: &str,
+        reason: CooldownReason,
+        seconds: u64,
+    ) {
         let key = format!("{app_type}:{priority}:{supplier}");
         let until = std::time::Instant::now() + std::time::Duration::from_secs(seconds);
         let mut map = self.supplier_cooldowns.write().await;
-        map.insert(key, until);
+        map.insert(key, (until, reason));
+        self.events.record(
+            app_type,
+            EventKind::Cooldown,
+            None,
+            Some(supplier),
+            serde_json::json!({
+                "priority": priority,
+                "reason": reason.as_str(),
+                "secs": seconds,
+            }),
+        );
+    }
+
+    /// 各内存表的条目数（`/admin/status` 展示）
+    pub async fn memory_stats(&self) -> RouterMemoryStats {
+        RouterMemoryStats {
+            circuit_breakers: self.circuit_breakers.read().await.len(),
+            round_robin_counters: self.round_robin_counters.read().await.len(),
+            supplier_cooldowns: self.supplier_cooldowns.read().await.len(),
+            suspect_urls: self.suspect_urls.read().await.len(),
+            url_latencies: self.url_latencies.read().await.len(),
+            supplier_benchmark_locks: self.supplier_benchmark_locks.read().await.len(),
+        }
+    }
+
+    /// 该应用的只读路由快照：激活层级，以及各供应商锁定的 URL、缓存延迟、冷却与疑似失效状态
+    ///
+    /// 只取读锁且按需生成，不在选路路径上维护额外状态。
+    pub async fn routing_snapshot(&self, app_type: &str) -> AppRoutingSnapshot {
+        let now = std::time::Instant::now();
+        let prefix = format!("{app_type}:");
+        // "priority:supplier" 形式的 key 拆分为 (priority, supplier)
+        let split_level = |rest: &str| -> Option<(usize, String)> {
+            let (priority, supplier) = rest.split_once(':')?;
+            Some((priority.parse().ok()?, supplier.to_string()))
+        };
+
+        fn state_of(
+            suppliers: &mut BTreeMap<(usize, String), SupplierRoutingState>,
+            priority: usize,
+            supplier: String,
+        ) -> &mut SupplierRoutingState {
+            suppliers
+                .entry((priority, supplier.clone()))
+                .or_insert_with(|| SupplierRoutingState {
+                    priority,
+                    supplier,
+                    ..Default::default()
+                })
+        }
+
+        let mut suppliers: BTreeMap<(usize, String), SupplierRoutingState> = BTreeMap::new();
+        for (key, url) in self.supplier_current_url.read().await.iter() {
+            let Some((priority, supplier)) = key.strip_prefix(&prefix).and_then(split_level) else {
+                continue;
+            };
+            state_of(&mut suppliers, priority, supplier).current_url = Some(url.clone());
+        }
+
+        for (key, (until, reason)) in self.supplier_cooldowns.read().await.iter() {
+            if *until <= now {
+                continue;
+            }
+            let Some((priority, supplier)) = key.strip_prefix(&prefix).and_then(split_level) else {
+                continue;
+            };
+            let state = state_of(&mut suppliers, priority, supplier);
+            state.cooldown_reason = Some(reason.as_str().to_string());
+            state.cooldown_remaining_secs = Some(until.duration_since(now).as_secs());
+        }
+
+        {
+            let latencies = self.url_latencies.read().await;
+            for state in suppliers.values_mut() {
+                let Some(url) = state.current_url.as_deref() else {
+                    continue;
+                };
+                let key = Self::url_latency_key(app_type, state.priority, &state.supplier, url);
+                if let Some(latency) = latencies.get(&key) {
+                    state.latency_ms = Some(latency.latency_ms);
+                    state.latency_age_secs = Some(now.duration_since(latency.tested_at).as_secs());
+                }
+            }
+        }
+
+        {
+            let warmed = self.keep_warm_at.read().await;
+            for state in suppliers.values_mut() {
+                let Some(url) = state.current_url.as_deref() else {
+                    continue;
+                };
+                if let Some(at) = warmed.get(&format!("{app_type}:{url}")) {
+                    state.keep_warm_age_secs = Some(now.duration_since(*at).as_secs());
+                }
+            }
+        }
+
+        for suspect in self.suspect_url_entries(app_type).await {
+            for state in suppliers
+                .values_mut()
+                .filter(|s| s.supplier == suspect.supplier)
+            {
+                state.suspect_urls.push(suspect.url.clone());
+            }
+        }
+
+        AppRoutingSnapshot {
+            app_type: app_type.to_string(),
+            active_priority_level: self
+                .active_priority_level
+                .read()
+                .await
+                .get(app_type)
+                .copied(),
+            suppliers: suppliers.into_values().collect(),
+        }
+    }
+
+    /// 启动后台清理任务：每 10 分钟清理一次过期条目与数据库中已到期的维护禁用，路由器释放后自动退出
+    pub fn spawn_janitor(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let router = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Self::JANITOR_INTERVAL);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            // 首个 tick 立即触发：启动时无需清理
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                let Some(router) = router.upgrade() else {
+                    return;
+                };
+                let removed = router.prune_stale_entries().await;
+                if removed > 0 {
+                    log::debug!(
+                        "[Router] 已清理 {removed} 条过期内存条目，当前: {:?}",
+                        router.memory_stats().await
+                    );
+                }
+                let cleared = router.clear_expired_disables(chrono::Utc::now().timestamp());
+                if cleared > 0 {
+                    log::debug!("[Router] 已清除 {cleared} 条到期的维护禁用");
+                }
+            }
+        })
+    }
+
+    /// 清除数据库中 `now` 时刻已到期的维护禁用，返回清除条数（只读模式不写库）
+    ///
+    /// 选路只按时间过滤，不在请求路径上写库；清除由后台清理任务执行。
+    pub fn clear_expired_disables(&self, now: i64) -> usize {
+        if self.read_only {
+            return 0;
+        }
+        self.db.clear_expired_disables(now).unwrap_or_else(|e| {
+            log::warn!("[Router] 清除已到期的维护禁用失败: {e}");
+            0
+        })
+    }
+
+    /// 清理过期内存条目，返回清理数量：
+    /// - 已到期的供应商冷静期
+    /// - 到期超过保留时长的疑似失效标记（保留期内仍累计连续标记次数）
+    /// - 超过最长保留时间的 URL 延迟缓存
+    /// - 数据库中已不存在的层级对应的轮询计数
+    /// - 无人持有的测速锁
+    /// - 已过保留时长的探测时间记录
+    /// - 超过最长保留时间的连接保温记录
+    pub async fn prune_stale_entries(&self) -> usize {
+        self.prune_stale_entries_at(std::time::Instant::now()).await
+    }
+
+    async fn prune_stale_entries_at(&self, now: std::time::Instant) -> usize {
+        let mut removed = 0;
+
+        {
+            let mut map = self.supplier_cooldowns.write().await;
+            let before = map.len();
+            map.retain(|_, (until, _)| *until > now);
+            removed += before - map.len();
+        }
+        {
+            let mut map = self.suspect_urls.write().await;
+            let before = map.len();
+            map.retain(|_, s| s.until + Self::SUSPECT_STRIKE_RETENTION > now);
+            removed += before - map.len();
+        }
+        {
+            let mut map = self.url_latencies.write().await;
+            let before = map.len();
+            map.retain(|_, l| {
+                now.saturating_duration_since(l.tested_at) <= Self::URL_LATENCY_MAX_AGE
+            });
+            removed += before - map.len();
+        }
+        {
+            // key 格式: "app_type:priority:{priority}:key-rr"；读取失败的应用保留计数
+            let mut priorities: HashMap<String, Option<HashSet<usize>>> = HashMap::new();
+            let mut map = self.round_robin_counters.write().await;
+            let before = map.len();
+            map.retain(|key, _| {
+                let mut parts = key.split(':');
+                let (Some(app_type), Some("priority"), Some(priority)) =
+                    (parts.next(), parts.next(), parts.next())
+                else {
+                    return true;
+                };
+                let Ok(priority) = priority.parse::<usize>() else {
+                    return true;
+                };
+                let live = priorities.entry(app_type.to_string()).or_insert_with(|| {
+                    self.db
+                        .get_failover_providers(app_type)
+                        .ok()
+                        .map(|providers| providers.iter().map(|p| p.priority()).collect())
+                });
+                live.as_ref().is_none_or(|set| set.contains(&priority))
+            });
+            removed += before - map.len();
+        }
+        {
+            let mut map = self.supplier_benchmark_locks.write().await;
+            let before = map.len();
+            map.retain(|_, lock| Arc::strong_count(lock) > 1);
+            removed += before - map.len();
+        }
+        {
+            let mut map = self.supplier_last_probe.write().await;
+            let before = map.len();
+            map.retain(|_, at| *at + Self::PROBE_PACING_RETENTION > now);
+            removed += before - map.len();
+        }
+        {
+            let mut map = self.keep_warm_at.write().await;
+            let before = map.len();
+            map.retain(|_, at| now.saturating_duration_since(*at) <= Self::URL_LATENCY_MAX_AGE);
+            removed += before - map.len();
+        }
+
+        removed
     }
 
     /// 选择可用的供应商（支持故障转移）
@@ -836,43 +1778,240 @@ impl ProviderRouter {
     /// 返回按优先级排序的可用供应商列表：
     /// - 故障转移关闭时：仅返回当前供应商
     /// - 故障转移开启时：完全按照故障转移队列顺序返回，忽略当前供应商设置
+    ///
+    /// 同一层级内按供应商名字典序、其次 URL 字典序遍历（测速与冷却判定顺序固定），
+    /// 层级内候选按 provider id 排序后再按轮询计数旋转，因此结果不受运行次数影响。
+    ///
+    /// `wants_thinking` 为 true（请求开启了 extended thinking）时，不支持 thinking 的供应商
+    /// 移到链尾（见 [`thinking_capability`](super::thinking_capability)）。
     pub fn select_providers<'a>(
         &'a self,
         app_type: &'a str,
         request_model: Option<&'a str>,
+        wants_thinking: bool,
     ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Vec<Provider>, AppError>> + 'a + Send>>
     {
         Box::pin(async move {
-            self.select_providers_impl(app_type, request_model).await
+            let selection = self
+                .select_providers_impl(app_type, request_model, false)
+                .await
+                .map(|selection| selection.chain);
+            let providers = match selection {
+                Ok(providers) => {
+                    // 按请求固定的结果不作为兜底候选链
+                    if RequestScope::pinned_provider_id(app_type).is_none() {
+                        self.last_good_providers
+                            .write()
+                            .await
+                            .insert(app_type.to_string(), providers.clone());
+                    }
+                    self.mark_db_available(app_type);
+                    self.clear_setup_missing(app_type).await;
+                    providers
+                }
+                Err(e) if e.is_database_unavailable() => {
+                    let cached = self.last_good_providers.read().await.get(app_type).cloned();
+                    match cached {
+                        Some(providers) => {
+                            self.mark_db_degraded(app_type, &e);
+                            providers
+                        }
+                        None => return Err(e),
+                    }
+                }
+                Err(e) if e.is_provider_setup_missing() => {
+                    self.mark_setup_missing(app_type, &e).await;
+                    return Err(e);
+                }
+                Err(e) => return Err(e),
+            };
+            if !wants_thinking {
+                return Ok(providers);
+            }
+            let (providers, moved) = prefer_thinking_capable(providers);
+            if moved > 0 {
+                log::info!(
+                    "[{app_type}] thinking 请求：{moved} 个不支持 thinking 的供应商移到链尾"
+                );
+            }
+            Ok(providers)
         })
     }
 
+    /// 加载单请求配置快照；数据库暂不可用时使用最近一次成功加载的快照
+    pub async fn load_request_scope(&self, app_type: &str) -> Result<RequestScope, AppError> {
+        match RequestScope::load(&self.db, app_type).await {
+            Ok(scope) => {
+                self.last_good_scopes
+                    .write()
+                    .await
+                    .insert(app_type.to_string(), scope.clone());
+                Ok(scope)
+            }
+            Err(e) if e.is_database_unavailable() => {
+                let cached = self.last_good_scopes.read().await.get(app_type).cloned();
+                match cached {
+                    Some(scope) => {
+                        self.mark_db_degraded(app_type, &e);
+                        Ok(scope)
+                    }
+                    None => Err(e),
+                }
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// 是否有应用正在使用兜底数据（数据库锁定/损坏）
+    pub fn is_db_degraded(&self) -> bool {
+        self.db_degraded_apps
+            .read()
+            .map(|apps| !apps.is_empty())
+            .unwrap_or(false)
+    }
+
+    /// 正在使用兜底数据的应用（按名称排序）
+    pub fn db_degraded_apps(&self) -> Vec<String> {
+        let mut apps: Vec<String> = self
+            .db_degraded_apps
+            .read()
+            .map(|apps| apps.iter().cloned().collect())
+            .unwrap_or_default();
+        apps.sort();
+        apps
+    }
+
+    /// 进入降级：每个应用只在状态切换时告警并通知，避免每个请求刷屏
+    fn mark_db_degraded(&self, app_type: &str, error: &AppError) {
+        let newly_degraded = self
+            .db_degraded_apps
+            .write()
+            .map(|mut apps| apps.insert(app_type.to_string()))
+            .unwrap_or(false);
+        if !newly_degraded {
+            log::debug!("[{app_type}] 数据库仍不可用，继续使用最近一次的供应商列表: {error}");
+            return;
+        }
+        log::warn!("[{app_type}] 数据库暂不可用，使用最近一次成功读取的供应商列表: {error}");
+        notifications::notify(
+            NotificationPayload::new(NotificationEvent::DatabaseDegraded, app_type)
+                .with_error(error.to_string()),
+        );
+    }
+
+    /// 退出降级：只清除本次选路成功的应用，其他应用在各自下一次成功读取时恢复
+    fn mark_db_available(&self, app_type: &str) {
+        let recovered = self
+            .db_degraded_apps
+            .write()
+            .map(|mut apps| apps.remove(app_type))
+            .unwrap_or(false);
+        if recovered {
+            log::info!("[{app_type}] 数据库已恢复，供应商列表重新从数据库读取");
+        }
+    }
+
+    /// 供应商配置缺失：每个应用只告警并通知一次，之后的请求只记 debug，直到选路重新成功
+    async fn mark_setup_missing(&self, app_type: &str, error: &AppError) {
+        if !self
+            .setup_missing_apps
+            .write()
+            .await
+            .insert(app_type.to_string())
+        {
+            log::debug!("[{app_type}] 供应商配置仍缺失: {error}");
+            return;
+        }
+        log::warn!("[{app_type}] {error}");
+        notifications::notify(
+            NotificationPayload::new(NotificationEvent::ProviderSetupMissing, app_type)
+                .with_error(error.to_string()),
+        );
+    }
+
+    async fn clear_setup_missing(&self, app_type: &str) {
+        if !self.setup_missing_apps.read().await.contains(app_type) {
+            return;
+        }
+        if self.setup_missing_apps.write().await.remove(app_type) {
+            log::info!("[{app_type}] 供应商配置已就绪，恢复正常选路");
+        }
+    }
+
+    /// 找不到可用供应商时区分原因：该应用完全没有配置供应商时返回 `NoProvidersConfigured`，
+    /// 否则返回 `otherwise`
+    fn no_providers_or(&self, app_type: &str, otherwise: AppError) -> AppError {
+        match self.db.get_all_providers(app_type) {
+            Ok(all) if all.is_empty() => AppError::NoProvidersConfigured {
+                app_type: app_type.to_string(),
+            },
+            _ => otherwise,
+        }
+    }
+
+    /// 选路实现；`dry_run` 为 true 时只读（见 [`Self::preview_providers`]）
     async fn select_providers_impl(
         &self,
         app_type: &str,
         request_model: Option<&str>,
-    ) -> Result<Vec<Provider>, AppError> {
+        dry_run: bool,
+    ) -> Result<Selection, AppError> {
         let request_model = request_model.unwrap_or("unknown");
 
+        // 按请求固定供应商（`csc replay` / `csc compare`）：直接返回该供应商
+        if let Some(provider_id) = RequestScope::pinned_provider_id(app_type) {
+            return match self.db.get_provider_by_id(&provider_id, app_type)? {
+                Some(provider) => {
+                    log::info!(
+                        "[{app_type}] 请求固定供应商: {} ({})",
+                        provider.name,
+                        provider.id
+                    );
+                    Ok(Selection::single(provider))
+                }
+                None => Err(AppError::Message(format!(
+                    "固定的供应商不存在: {provider_id}"
+                ))),
+            };
+        }
+
         // 检查该应用的自动故障转移开关是否开启（从 proxy_config 表读取）
-        let auto_failover_enabled = match self.db.get_proxy_config_for_app(app_type).await {
+        let (mut auto_failover_enabled, policy_kind) = match self.app_config(app_type).await {
             Ok(config) => {
                 let enabled = config.auto_failover_enabled;
                 log::debug!("[{app_type}] Failover enabled from proxy_config: {enabled}");
-                enabled
+                (enabled, config.selection_policy)
             }
             Err(e) => {
                 log::error!(
                     "[{app_type}] Failed to read proxy_config for auto_failover_enabled: {e}, defaulting to disabled"
                 );
-                false
+                (false, SelectionPolicyKind::default())
             }
         };
 
+        if auto_failover_enabled && self.failover_pause_remaining(app_type).is_some() {
+            log::debug!("[{app_type}] 故障转移已暂停，仅使用当前供应商");
+            auto_failover_enabled = false;
+        }
+
         if auto_failover_enabled {
+            let tuning = self.probe_tuning(app_type).await;
             // 故障转移开启：按层级生成候选链（由转发器按“层级内轮询重试 -> 进入下一层级”执行）
             // 轮询单位为“不同的 key 值”（相同 key 不重复计权），且每个供应商同一时刻仅使用其“当前最快 URL”。
             let failover_providers = self.db.get_failover_providers(app_type)?;
+            // 维护禁用（csc dis <app> <id> --for 4h）：查询时过滤已到期的禁用，
+            // 选路只读库，到期记录由后台清理任务清除
+            let now = chrono::Utc::now().timestamp();
+            let disabled_providers = self
+                .db
+                .get_disabled_providers(app_type, now)
+                .unwrap_or_else(|e| {
+                    log::warn!("[{app_type}] 读取供应商维护禁用状态失败: {e}");
+                    HashMap::new()
+                });
+            // 余额耗尽：重新检查间隔内跳过（到期后重新参与选路，再次耗尽会重新计时）
+            let depleted_providers = self.depleted_providers(app_type).await;
 
             log::debug!(
                 "[{}] Failover enabled, {} providers in queue",
@@ -880,21 +2019,34 @@ impl ProviderRouter {
                 failover_providers.len()
             );
 
+            let current_supplier = self
+                .current_provider_id(app_type)
+                .ok()
+                .flatten()
+                .and_then(|id| failover_providers.iter().find(|p| p.id == id))
+                .map(Self::supplier_name);
+
             // 按层级分组（sort_index 作为层级）
             let mut priority_groups: std::collections::BTreeMap<usize, Vec<Provider>> =
                 std::collections::BTreeMap::new();
             for provider in failover_providers {
-                let priority = provider.sort_index.unwrap_or(999999);
+                let priority = provider.priority();
                 priority_groups
                     .entry(priority)
                     .or_insert_with(Vec::new)
                     .push(provider);
             }
 
-            let mut first_priority: Option<usize> = None;
-            let mut selected_chain: Vec<Provider> = Vec::new();
+            // 解析各层级候选（测速、冷却、熔断器判定），排序交给选路策略
+            let mut levels: Vec<PriorityLevel> = Vec::new();
 
-            let test_override = self.get_active_test_override(app_type).await;
+            // 测试覆盖不参与预览
+            let test_override = if dry_run {
+                None
+            } else {
+                self.get_active_test_override(app_type).await
+            };
+            let mut url_sources: HashMap<String, &'static str> = HashMap::new();
 
             for (priority, providers_in_level) in priority_groups.iter() {
                 if let Some(o) = test_override.as_ref() {
@@ -902,9 +2054,10 @@ impl ProviderRouter {
                         continue;
                     }
                 }
-                // 在当前层级内按供应商 -> URL -> providers 分组
-                let mut supplier_urls: HashMap<String, HashMap<String, Vec<Provider>>> =
-                    HashMap::new();
+                // 在当前层级内按供应商 -> URL -> providers 分组；
+                // 使用 BTreeMap 保证遍历顺序固定（供应商名字典序，其次 URL 字典序），测速/选择可复现
+                let mut supplier_urls: BTreeMap<String, BTreeMap<String, Vec<Provider>>> =
+                    BTreeMap::new();
 
                 for provider in providers_in_level {
                     let supplier = Self::supplier_name(provider);
@@ -913,13 +2066,37 @@ impl ProviderRouter {
                             continue;
                         }
                     }
+                    if test_override.is_none() {
+                        if let Some(until) = disabled_providers.get(&provider.id) {
+                            log::debug!(
+                                "[{}:{}] 供应商维护禁用中，跳过: {} ({}) 剩余 {}s",
+                                app_type,
+                                priority,
+                                provider.name,
+                                provider.id,
+                                until - now
+                            );
+                            continue;
+                        }
+                        if let Some(recheck_at) = depleted_providers.get(&provider.id) {
+                            log::debug!(
+                                "[{}:{}] 供应商余额耗尽，跳过: {} ({}) {}s 后重新检查",
+                                app_type,
+                                priority,
+                                provider.name,
+                                provider.id,
+                                recheck_at - now
+                            );
+                            continue;
+                        }
+                    }
                     let Some(base_url) = Self::extract_base_url(provider, app_type) else {
                         continue;
                     };
                     let base_url = Self::normalize_base_url(&base_url);
                     supplier_urls
                         .entry(supplier)
-                        .or_insert_with(HashMap::new)
+                        .or_insert_with(BTreeMap::new)
                         .entry(base_url)
                         .or_insert_with(Vec::new)
                         .push(provider.clone());
@@ -929,15 +2106,24 @@ impl ProviderRouter {
                     continue;
                 }
 
-                let mut candidates: Vec<Provider> = Vec::new();
+                let mut level = PriorityLevel {
+                    priority: *priority,
+                    suppliers: Vec::new(),
+                };
 
                 for (supplier, url_map) in supplier_urls.iter() {
-                    if test_override.is_none()
-                        && self
-                            .is_supplier_in_cooldown(app_type, *priority, supplier)
+                    if test_override.is_none() {
+                        if let Some(reason) = self
+                            .supplier_cooldown_reason(app_type, *priority, supplier)
                             .await
-                    {
-                        continue;
+                        {
+                            level.suppliers.push(SupplierCandidates {
+                                supplier: supplier.clone(),
+                                cooldown: Some(reason),
+                                ..Default::default()
+                            });
+                            continue;
+                        }
                     }
 
                     let matching_override = test_override.as_ref().filter(|o| {
@@ -956,6 +2142,20 @@ impl ProviderRouter {
                     // - 启动时为每个 supplier 选一次最快 URL；
                     // - 仅当该 URL 被标记 suspect（链路失效）时，才清空并重新测速/切换。
                     let mut selected_url: Option<String> = None;
+                    // 最终无可用 URL 时的冷却原因（本轮测速后按测速明细更新）
+                    let mut cooldown_reason = CooldownReason::NoUsableUrl;
+
+                    // 预览只读缓存状态，不测速、不写入当前 URL
+                    let mut url_source = "only";
+                    if dry_run {
+                        if let Some((url, source)) = self
+                            .preview_supplier_url(app_type, *priority, supplier, url_map)
+                            .await
+                        {
+                            selected_url = Some(url);
+                            url_source = source;
+                        }
+                    }
 
                     if let Some(pin) = pinned_url.as_ref() {
                         if url_map.contains_key(pin) {
@@ -979,6 +2179,7 @@ impl ProviderRouter {
                                     message: None,
                                     reason: Some("覆盖URL不在该supplier的URL列表中".to_string()),
                                 }],
+                                cooldown_reason: None,
                             };
                             {
                                 let mut map = self.test_results.write().await;
@@ -996,7 +2197,7 @@ impl ProviderRouter {
                         }
                     }
 
-                    if selected_url.is_none() {
+                    if !dry_run && selected_url.is_none() {
                         if !force_benchmark && url_map.len() == 1 {
                             if let Some(url) = url_map.keys().next() {
                                 if !self.is_url_suspect(app_type, supplier, url).await {
@@ -1023,7 +2224,7 @@ impl ProviderRouter {
                         }
                     }
 
-                    if selected_url.is_none() {
+                    if !dry_run && selected_url.is_none() {
                         // 使用锁避免并发请求导致重复测速
                         let lock = self
                             .get_supplier_benchmark_lock(app_type, *priority, supplier)
@@ -1051,21 +2252,12 @@ impl ProviderRouter {
                                 self.take_supplier_retest_once(app_type, *priority, supplier).await;
 
                             // URL 优先级：当指定 URL 可用时优先使用（例如首选域名）
-                            // 优先级来源：默认规则 + provider.settingsConfig/baseUrlPriority + env.BASE_URL_PRIORITY
+                            // 优先级来源：provider.settingsConfig/baseUrlPriority + env.BASE_URL_PRIORITY + supplier_url_priority 表
                             if !force_retest {
-                                let mut preferred: Vec<String> =
-                                    Self::default_url_priority_for_supplier(supplier)
-                                        .into_iter()
-                                        .map(|s| s.to_string())
-                                        .collect();
-                                if let Some(p) = url_map.values().flat_map(|v| v.first()).next() {
-                                    preferred.extend(Self::parse_url_priority_from_provider(p));
-                                }
-                                // 去重（保留顺序）
-                                {
-                                    let mut seen = std::collections::HashMap::<String, ()>::new();
-                                    preferred.retain(|u| seen.insert(u.to_string(), ()).is_none());
-                                }
+                                let preferred = self.preferred_urls_for_supplier(
+                                    supplier,
+                                    url_map.values().flat_map(|v| v.first()).next(),
+                                );
 
                                 for purl in preferred.iter() {
                                     if !url_map.contains_key(purl) {
@@ -1087,7 +2279,7 @@ impl ProviderRouter {
 
                                     if let Some(l) = cached_latency {
                                         // 仅当“明显不是回退结果（penalty）”时，才认为可直接命中优先 URL
-                                        if l != u64::MAX && l < Self::CONNECTIVITY_PENALTY_MS {
+                                        if l != u64::MAX && l < tuning.penalty_ms {
                                             selected_url = Some(purl.clone());
                                             self.set_supplier_current_url(
                                                 app_type,
@@ -1106,11 +2298,14 @@ impl ProviderRouter {
                                             );
                                             break;
                                         }
-                                    } else if let Ok(connect_ms) = self.connectivity_latency(purl).await
+                                    } else if let Ok(connect_ms) = Self::connectivity_latency(
+                                        purl,
+                                        tuning.connectivity_timeout,
+                                    )
+                                    .await
                                     {
                                         // 仅用于缓存（避免重复探测刷屏），不作为“优先级直接命中”的依据
-                                        let latency = connect_ms
-                                            .saturating_add(Self::CONNECTIVITY_PENALTY_MS);
+                                        let latency = connect_ms.saturating_add(tuning.penalty_ms);
                                         let mut latencies = self.url_latencies.write().await;
                                         latencies.insert(
                                             cache_key,
@@ -1176,7 +2371,7 @@ impl ProviderRouter {
                             }
 
                             if should_benchmark || filtered_urls.is_empty() {
-                                let benchmark_results = self
+                                let (benchmark_results, reason) = self
                                     .benchmark_urls_with_log_mode(
                                         app_type,
                                         *priority,
@@ -1186,6 +2381,7 @@ impl ProviderRouter {
                                         force_retest,
                                     )
                                     .await;
+                                cooldown_reason = reason;
 
                                 {
                                     let mut tested_map = self.priority_level_tested.write().await;
@@ -1208,15 +2404,10 @@ impl ProviderRouter {
                             // 若存在 URL 优先级配置，则优先挑选“全链路 OK”的优先 URL；
                             // 若不存在“全链路 OK”，仍按原有策略仅做顺序调整（FB 结果不会强制锁定优先 URL）。
                             if filtered_urls.len() > 1 {
-                                let mut preferred: Vec<String> = Self::default_url_priority_for_supplier(supplier)
-                                    .into_iter()
-                                    .map(|s| s.to_string())
-                                    .collect();
-                                if let Some(p) = url_map.values().flat_map(|v| v.first()).next() {
-                                    preferred.extend(Self::parse_url_priority_from_provider(p));
-                                }
-                                let mut seen = std::collections::HashMap::<String, ()>::new();
-                                preferred.retain(|u| seen.insert(u.to_string(), ()).is_none());
+                                let preferred = self.preferred_urls_for_supplier(
+                                    supplier,
+                                    url_map.values().flat_map(|v| v.first()).next(),
+                                );
 
                                 // 先尝试命中“优先 URL 且全链路 OK”
                                 for purl in preferred.iter() {
@@ -1233,7 +2424,7 @@ impl ProviderRouter {
                                         latencies.get(&cache_key).map(|l| l.latency_ms)
                                     };
                                     if let Some(l) = cached_latency {
-                                        if l != u64::MAX && l < Self::CONNECTIVITY_PENALTY_MS {
+                                        if l != u64::MAX && l < tuning.penalty_ms {
                                             selected_url = Some(purl.clone());
                                             self.set_supplier_current_url(
                                                 app_type,
@@ -1264,17 +2455,52 @@ impl ProviderRouter {
                     }
 
                     let Some(selected_url) = selected_url else {
-                        // 该供应商当前无可用 URL：进入短暂冷静期
-                        self.set_supplier_cooldown(app_type, *priority, supplier, 20).await;
+                        // 该供应商当前无可用 URL：进入短暂冷静期（满载时更短）
+                        if !dry_run {
+                            let seconds = self.cooldown_secs(app_type, cooldown_reason).await;
+                            log::debug!(
+                                "[{}:{}] 供应商 {} 无可用 URL，冷却 {}s（{}）",
+                                app_type,
+                                priority,
+                                supplier,
+                                seconds,
+                                cooldown_reason.describe()
+                            );
+                            self.set_supplier_cooldown(
+                                app_type,
+                                *priority,
+                                supplier,
+                                cooldown_reason,
+                                seconds,
+                            )
+                            .await;
+                        }
+                        level.suppliers.push(SupplierCandidates {
+                            supplier: supplier.clone(),
+                            cooldown: Some(cooldown_reason),
+                            ..Default::default()
+                        });
                         continue;
                     };
 
                     let Some(providers_at_url) = url_map.get(&selected_url) else {
                         continue;
                     };
+                    let latency_ms = {
+                        let cache_key =
+                            Self::url_latency_key(app_type, *priority, supplier, &selected_url);
+                        let latencies = self.url_latencies.read().await;
+                        latencies.get(&cache_key).map(|l| l.latency_ms)
+                    };
+                    let mut entry = SupplierCandidates {
+                        supplier: supplier.clone(),
+                        url: Some(selected_url.clone()),
+                        latency_ms,
+                        ..Default::default()
+                    };
 
                     // 在该 URL 上按“不同 key 值”去重，保证轮询均分
-                    let mut unique_by_key: HashMap<String, Provider> = HashMap::new();
+                    let mut unique_by_key: BTreeMap<String, Provider> = BTreeMap::new();
                     for provider in providers_at_url {
                         let Some(key_value) = Self::extract_api_key_value(provider, app_type) else {
                             continue;
@@ -1284,80 +2510,364 @@ impl ProviderRouter {
 
                     // 熔断器过滤：只保留当前可用的 key
                     for provider in unique_by_key.values() {
+                        if dry_run {
+                            // 预览只读取状态，不触发 Open → HalfOpen
+                            url_sources.insert(provider.id.clone(), url_source);
+                            let open = self
+                                .get_circuit_breaker_stats(&provider.id, app_type)
+                                .await
+                                .is_some_and(|stats| stats.state == CircuitState::Open);
+                            if open {
+                                entry.breaker_blocked.push(provider.clone());
+                            } else {
+                                entry.available.push(provider.clone());
+                            }
+                            continue;
+                        }
                         let circuit_key = format!("{}:{}", app_type, provider.id);
                         let breaker = self.get_or_create_circuit_breaker(&circuit_key).await;
-                        if bypass_circuit_breaker || breaker.is_available().await {
-                            candidates.push(provider.clone());
+                        if bypass_circuit_breaker {
+                            entry.available.push(provider.clone());
+                            continue;
+                        }
+                        let before = breaker.get_state().await;
+                        let available = breaker.is_available().await;
+                        let after = breaker.get_state().await;
+                        self.record_breaker_transition(
+                            app_type,
+                            &provider.id,
+                            before,
+                            after,
+                            Value::Null,
+                        );
+                        if available {
+                            entry.available.push(provider.clone());
+                        } else {
+                            entry.breaker_blocked.push(provider.clone());
                         }
                     }
+                    level.suppliers.push(entry);
                 }
 
-                if candidates.is_empty() {
-                    continue;
-                }
-
-                // 层级命中：应用“key均分”轮询（在所有 key 上 round-robin）
-                candidates.sort_by(|a, b| a.id.cmp(&b.id));
-
-                let counter_key = format!("{app_type}:priority:{priority}:key-rr");
-                let rotate_count = {
-                    let mut counters = self.round_robin_counters.write().await;
-                    let counter = counters.entry(counter_key.clone()).or_insert(0);
-                    let count = *counter % candidates.len();
-                    *counter = (*counter + 1) % candidates.len();
-                    count
-                };
-                candidates.rotate_left(rotate_count);
-
-                if first_priority.is_none() {
-                    first_priority = Some(*priority);
-                }
-                // 追加该层级的候选 key；后续层级继续追加，由 forwarder 在失败后推进到下一层级
-                selected_chain.extend(candidates);
+                levels.push(level);
             }
 
-            let Some(target_priority) = first_priority else {
-                return Err(AppError::Config(format!(
-                    "No available providers for {app_type} (all priorities unavailable)"
-                )));
+            let input = SelectionInput {
+                app_type: app_type.to_string(),
+                request_model: request_model.to_string(),
+                current_supplier,
+                levels,
+            };
+            // 测试覆盖只保留了被测 supplier，按默认策略排序
+            let policy = if test_override.is_some() {
+                SelectionPolicyKind::Default.policy()
+            } else {
+                policy_kind.policy()
+            };
+            let selected_chain = self.order_with_policy(policy, &input, !dry_run).await;
+
+            let Some(target_priority) = selected_chain.first().map(Provider::priority) else {
+                return Err(self.no_providers_or(
+                    app_type,
+                    AppError::Config(format!(
+                        "No available providers for {app_type} (all priorities unavailable)"
+                    )),
+                ));
             };
 
             // 记录当前激活层级
-            {
+            if !dry_run {
                 let mut active_levels = self.active_priority_level.write().await;
                 active_levels.insert(app_type.to_string(), target_priority);
             }
 
             log::debug!(
-                "[{}] Selected priority {} with {} key(s) across priorities (model={})",
+                "[{}] Selected priority {} with {} key(s) across priorities (model={}, policy={})",
                 app_type,
                 target_priority,
                 selected_chain.len(),
-                request_model
+                request_model,
+                policy.name()
             );
 
-            return Ok(selected_chain);
+            Ok(Selection {
+                failover_enabled: true,
+                chain: selected_chain,
+                url_sources,
+            })
         } else {
             // 故障转移关闭：仅使用当前供应商，跳过熔断器检查
             // 原因：单 Provider 场景下，熔断器打开会导致所有请求失败，用户体验差
             log::info!("[{app_type}] Failover disabled, using current provider only (circuit breaker bypassed)");
 
-            if let Some(current_id) = self.db.get_current_provider(app_type)? {
-                if let Some(current) = self.db.get_provider_by_id(&current_id, app_type)? {
+            let current_id = self.current_provider_id(app_type)?;
+            if let Some(current_id) = &current_id {
+                if let Some(current) = self.db.get_provider_by_id(current_id, app_type)? {
+                    let now = chrono::Utc::now().timestamp();
+                    if let Ok(disabled) = self.db.get_disabled_providers(app_type, now) {
+                        if disabled.contains_key(&current.id) {
+                            // 仅有一个候选，禁用后无处可切：照常使用并提示
+                            log::warn!(
+                                "[{}] 当前供应商处于维护禁用中，故障转移关闭时仍使用: {} ({})",
+                                app_type,
+                                current.name,
+                                current.id
+                            );
+                        }
+                    }
                     log::debug!(
                         "[{}] Current provider: {} ({})",
                         app_type,
                         current.name,
                         current.id
                     );
-                    return Ok(vec![current]);
+                    return Ok(Selection::single(current));
+                }
+            }
+            Err(self.no_providers_or(
+                app_type,
+                AppError::CurrentProviderMissing {
+                    app_type: app_type.to_string(),
+                    provider_id: current_id,
+                },
+            ))
+        }
+    }
+
+    /// 加载轮询计数与测速胜出 key，交给策略排序；`commit` 为 true 时写回推进后的状态（预览不写回）
+    async fn order_with_policy(
+        &self,
+        policy: &dyn SelectionPolicy,
+        input: &SelectionInput,
+        commit: bool,
+    ) -> Vec<Provider> {
+        let app_type = input.app_type.as_str();
+        let counter_key = |priority: usize| format!("{app_type}:priority:{priority}:key-rr");
+        let winning_key = |priority: usize| format!("{app_type}:{priority}");
+
+        // 持有写锁直到写回，保证并发请求的轮询计数依次推进
+        let mut counters = self.round_robin_counters.write().await;
+        let mut winners = self.benchmark_winning_keys.write().await;
+        let mut state = SelectionState::new(
+            input
+                .levels
+                .iter()
+                .filter_map(|l| Some((l.priority, *counters.get(&counter_key(l.priority))?)))
+                .collect(),
+            input
+                .levels
+                .iter()
+                .filter_map(|l| Some((l.priority, winners.get(&winning_key(l.priority))?.clone())))
+                .collect(),
+        );
+        let chain = policy.order(input, &mut state);
+        if !commit {
+            return chain;
+        }
+
+        for &(priority, len) in state.rotated() {
+            let counter = counters.entry(counter_key(priority)).or_insert(0);
+            *counter = (*counter + 1) % len;
+        }
+        for &priority in state.taken() {
+            winners.remove(&winning_key(priority));
+        }
+        chain
+    }
+
+    /// 应用级代理配置：请求内使用 [`RequestScope`] 快照，快照之外读库
+    async fn app_config(&self, app_type: &str) -> Result<AppProxyConfig, AppError> {
+        match RequestScope::app_config(app_type) {
+            Some(config) => Ok(config),
+            None => self.db.get_proxy_config_for_app(app_type).await,
+        }
+    }
+
+    /// 当前供应商：请求内使用 [`RequestScope`] 快照，快照之外读库
+    fn current_provider_id(&self, app_type: &str) -> Result<Option<String>, AppError> {
+        match RequestScope::current_provider_id(app_type) {
+            Some(current) => Ok(current),
+            None => self.db.get_current_provider(app_type),
+        }
+    }
+
+    /// 连通性惩罚与探测超时：读取应用配置，失败时使用默认值
+    async fn probe_tuning(&self, app_type: &str) -> ProbeTuning {
+        match self.app_config(app_type).await {
+            Ok(config) => ProbeTuning {
+                penalty_ms: config.connectivity_penalty_ms,
+                connectivity_timeout: Duration::from_secs(config.connectivity_timeout_secs.max(1)),
+                probe_timeout: Duration::from_secs(config.probe_timeout_secs.max(1)),
+                min_probe_interval: Duration::from_millis(config.probe_min_interval_ms),
+                skip_recent_success_secs: config.probe_skip_recent_success_secs,
+                benchmark_concurrency: config.benchmark_concurrency.max(1) as usize,
+            },
+            Err(_) => ProbeTuning::default(),
+        }
+    }
+
+    /// 探测节流：同一供应商相邻两次探测至少间隔 `min_interval`，且同时进行的探测不超过
+    /// `MAX_CONCURRENT_PROBES`；返回的许可需持有到本次探测结束
+    ///
+    /// 先排定本次探测时间再等待，并发探测同一供应商时依次顺延，不会同时放行。
+    async fn pace_probe(
+        &self,
+        app_type: &str,
+        supplier: &str,
+        min_interval: Duration,
+    ) -> Option<OwnedSemaphorePermit> {
+        let key = format!("{app_type}:{supplier}");
+        let now = std::time::Instant::now();
+        let scheduled = {
+            let mut map = self.supplier_last_probe.write().await;
+            let at = map
+                .get(&key)
+                .map_or(now, |last| (*last + min_interval).max(now));
+            map.insert(key, at);
+            at
+        };
+        let wait = scheduled.saturating_duration_since(now);
+        if !wait.is_zero() {
+            log::debug!(
+                "[{app_type}] 供应商 {supplier} 探测间隔未到，等待 {}ms",
+                wait.as_millis()
+            );
+            tokio::time::sleep(wait).await;
+        }
+        self.probe_semaphore.clone().acquire_owned().await.ok()
+    }
+
+    /// 近期真实成功请求的中位延迟：`max_age_secs` 内该 URL 的供应商有 2xx 请求时返回
+    fn recent_success_latency(
+        &self,
+        app_type: &str,
+        providers: &[Provider],
+        max_age_secs: u64,
+    ) -> Option<u64> {
+        if max_age_secs == 0 {
+            return None;
+        }
+        let provider_ids: Vec<String> = providers.iter().map(|p| p.id.clone()).collect();
+        match self.db.get_recent_success_stats(
+            &provider_ids,
+            app_type,
+            Self::RECENT_SUCCESS_SAMPLE_ROWS,
+            Some(max_age_secs as i64),
+        ) {
+            Ok(stats) => stats.map(|s| s.median_latency_ms),
+            Err(e) => {
+                log::debug!("[{app_type}] 读取近期成功请求失败: {e}");
+                None
+            }
+        }
+    }
+
+    /// 处于余额耗尽跳过期内的供应商（provider_id -> 重新检查时间，Unix 秒）
+    async fn depleted_providers(&self, app_type: &str) -> HashMap<String, i64> {
+        let recheck_secs = match self.app_config(app_type).await {
+            Ok(config) => config.depleted_recheck_secs,
+            Err(_) => Self::DEFAULT_DEPLETED_RECHECK_SECS,
+        };
+        self.db
+            .get_depleted_providers(app_type, recheck_secs)
+            .await
+            .unwrap_or_else(|e| {
+                log::warn!("[{app_type}] 读取供应商余额耗尽状态失败: {e}");
+                HashMap::new()
+            })
+    }
+
+    /// 只读预览选路结果（dry-run 使用）
+    ///
+    /// 与 `select_providers` 走同一套选路逻辑（暂停、分组、维护禁用、冷却、熔断与选路策略），
+    /// 但不测速、不写入当前 URL 与冷却、不推进轮询计数，熔断器只读取状态（不触发 Open → HalfOpen）。
+    /// 测试覆盖不参与预览。返回值第一个元素为是否开启了故障转移。
+    pub async fn preview_providers(
+        &self,
+        app_type: &str,
+    ) -> Result<(bool, Vec<SelectionPreview>), AppError> {
+        let selection = self.select_providers_impl(app_type, None, true).await?;
+        let failover_enabled = selection.failover_enabled;
+        let url_sources = selection.url_sources;
+        let chain = selection
+            .chain
+            .into_iter()
+            .map(|provider| {
+                if !failover_enabled {
+                    return SelectionPreview {
+                        base_url: Self::extract_base_url(&provider, app_type),
+                        supplier: Self::supplier_name(&provider),
+                        priority: None,
+                        url_source: "current-provider",
+                        provider,
+                    };
                 }
+                SelectionPreview {
+                    base_url: Self::extract_base_url(&provider, app_type)
+                        .map(|url| Self::normalize_base_url(&url)),
+                    supplier: Self::supplier_name(&provider),
+                    priority: Some(provider.priority()),
+                    url_source: url_sources.get(&provider.id).copied().unwrap_or("only"),
+                    provider,
+                }
+            })
+            .collect();
+        Ok((failover_enabled, chain))
+    }
+
+    /// 预览某个 supplier 会使用的 URL（只读缓存状态，不测速）
+    async fn preview_supplier_url(
+        &self,
+        app_type: &str,
+        priority: usize,
+        supplier: &str,
+        url_map: &BTreeMap<String, Vec<Provider>>,
+    ) -> Option<(String, &'static str)> {
+        let mut usable = Vec::new();
+        for url in url_map.keys() {
+            if !self.is_url_suspect(app_type, supplier, url).await {
+                usable.push(url.clone());
+            }
+        }
+        if usable.is_empty() {
+            return None;
+        }
+        if url_map.len() == 1 {
+            return usable.pop().map(|url| (url, "only"));
+        }
+
+        if let Some(current) = self
+            .get_supplier_current_url(app_type, priority, supplier)
+            .await
+        {
+            if usable.contains(&current) {
+                return Some((current, "current"));
             }
         }
 
-        Err(AppError::Config(format!(
-            "No available provider for {app_type} (failover disabled but current provider missing)"
-        )))
+        let fastest = {
+            let latencies = self.url_latencies.read().await;
+            usable
+                .iter()
+                .filter_map(|url| {
+                    let key = Self::url_latency_key(app_type, priority, supplier, url);
+                    latencies
+                        .get(&key)
+                        .filter(|l| l.latency_ms != u64::MAX)
+                        .map(|l| (l.latency_ms, url.clone()))
+                })
+                .min()
+        };
+        if let Some((_, url)) = fastest {
+            return Some((url, "cached-latency"));
+        }
+
+        let preferred = self
+            .preferred_urls_for_supplier(supplier, url_map.values().flat_map(|v| v.first()).next());
+        Self::apply_url_priority(usable, &preferred)
+            .into_iter()
+            .next()
+            .map(|url| (url, "untested"))
     }
 
     /// 请求执行前获取熔断器“放行许可”
@@ -1371,10 +2881,17 @@ impl ProviderRouter {
     pub async fn allow_provider_request(&self, provider_id: &str, app_type: &str) -> AllowResult {
         let circuit_key = format!("{app_type}:{provider_id}");
         let breaker = self.get_or_create_circuit_breaker(&circuit_key).await;
-        breaker.allow_request().await
+        let before = breaker.get_state().await;
+        let result = breaker.allow_request().await;
+        let after = breaker.get_state().await;
+        self.record_breaker_transition(app_type, provider_id, before, after, Value::Null);
+        result
     }
 
     /// 记录供应商请求结果
+    ///
+    /// `failure_kind` 为转发器从 `ProxyError` 得出的失败类型，决定是否标记 URL suspect；
+    /// 为 None 时才回退到按 `error_msg` 文本识别。
     pub async fn record_result(
         &self,
         provider_id: &str,
@@ -1382,9 +2899,57 @@ impl ProviderRouter {
         used_half_open_permit: bool,
         success: bool,
         error_msg: Option<String>,
+        failure_kind: Option<FailureKind>,
+    ) -> Result<(), AppError> {
+        self.record_result_inner(
+            provider_id,
+            app_type,
+            used_half_open_permit,
+            success,
+            error_msg,
+            failure_kind,
+            false,
+        )
+        .await
+    }
+
+    /// 记录后台健康检查的探测结果
+    ///
+    /// 与 `record_result` 一样更新熔断器与 provider_health，
+    /// 但不触发 URL suspect/重新测速（探测不是真实请求，也不计入代理请求统计）。
+    pub async fn record_probe_result(
+        &self,
+        provider_id: &str,
+        app_type: &str,
+        used_half_open_permit: bool,
+        success: bool,
+        error_msg: Option<String>,
+    ) -> Result<(), AppError> {
+        self.record_result_inner(
+            provider_id,
+            app_type,
+            used_half_open_permit,
+            success,
+            error_msg,
+            None,
+            true,
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn record_result_inner(
+        &self,
+        provider_id: &str,
+        app_type: &str,
+        used_half_open_permit: bool,
+        success: bool,
+        error_msg: Option<String>,
+        failure_kind: Option<FailureKind>,
+        is_probe: bool,
     ) -> Result<(), AppError> {
         // 1. 按应用独立获取熔断器配置（用于更新健康状态和判断是否禁用）
-        let (failure_threshold, max_retries) = match self.db.get_proxy_config_for_app(app_type).await {
+        let (failure_threshold, max_retries) = match self.app_config(app_type).await {
             Ok(app_config) => (app_config.circuit_failure_threshold, app_config.max_retries),
             Err(e) => {
                 log::warn!(
@@ -1397,6 +2962,7 @@ impl ProviderRouter {
         // 2. 更新熔断器状态
         let circuit_key = format!("{app_type}:{provider_id}");
         let breaker = self.get_or_create_circuit_breaker(&circuit_key).await;
+        let state_before = breaker.get_state().await;
 
         if success {
             breaker.record_success(used_half_open_permit).await;
@@ -1410,17 +2976,55 @@ impl ProviderRouter {
             );
         }
 
+        // 2.1 熔断状态变化时记录事件并发送通知（后台执行，不影响请求路径）
+        let state_after = breaker.get_state().await;
+        self.record_breaker_transition(
+            app_type,
+            provider_id,
+            state_before,
+            state_after,
+            serde_json::json!({ "error": error_msg.as_deref(), "probe": is_probe }),
+        );
+        let event = match (state_before, state_after) {
+            (before, CircuitState::Open) if before != CircuitState::Open => {
+                Some(NotificationEvent::CircuitOpened)
+            }
+            (before, CircuitState::Closed) if before != CircuitState::Closed => {
+                Some(NotificationEvent::CircuitRecovered)
+            }
+            _ => None,
+        };
+        if let Some(event) = event {
+            let provider_name = self
+                .db
+                .get_provider_by_id(provider_id, app_type)
+                .ok()
+                .flatten()
+                .map(|p| p.name)
+                .unwrap_or_else(|| provider_id.to_string());
+            let mut payload =
+                NotificationPayload::new(event, app_type).with_provider(provider_id, &provider_name);
+            if let Some(err) = error_msg.as_deref() {
+                payload = payload.with_error(err);
+            }
+            notifications::notify(payload);
+        }
+
         // 2.5 失败时：只有在“明显链路错误”时才标记 URL suspect（避免因上游满载/策略/5xx 误判导致反复测速刷屏）
         // 促使下次选择时在同供应商内切换到其它 URL 并重新测速。
-        if !success {
-            if let Some(err) = error_msg.as_deref() {
-                if Self::is_likely_network_error(err) {
-                    let seconds = 60;
+        if is_probe {
+            // 健康检查探测：只更新熔断器与健康状态
+        } else if !success {
+            // 优先使用转发器给出的结构化失败类型；仅有错误文本时才回退到文本识别
+            let kind =
+                failure_kind.or_else(|| error_msg.as_deref().map(FailureKind::from_error_text));
+            if let Some(kind) = kind {
+                if kind.is_link_failure() {
                     if let Some(provider) = self.db.get_provider_by_id(provider_id, app_type)? {
                         let supplier = Self::supplier_name(&provider);
                         if let Some(url) = Self::extract_base_url(&provider, app_type) {
                             let url = Self::normalize_base_url(&url);
-                            let priority = provider.sort_index.unwrap_or(999999) as usize;
+                            let priority = provider.priority();
 
                             let min_fails = std::cmp::max(
                                 Self::MIN_NETWORK_FAILS_BEFORE_SUSPECT,
@@ -1430,7 +3034,20 @@ impl ProviderRouter {
                             if consecutive_failures >= min_fails
                                 && !self.is_url_suspect(app_type, &supplier, &url).await
                             {
-                                self.set_url_suspect(app_type, &supplier, &url, seconds).await;
+                                let (strikes, seconds) =
+                                    self.strike_url_suspect(app_type, &supplier, &url).await;
+                                self.events.record(
+                                    app_type,
+                                    EventKind::Suspect,
+                                    Some(provider_id),
+                                    Some(&supplier),
+                                    serde_json::json!({
+                                        "url": url,
+                                        "strikes": strikes,
+                                        "secs": seconds,
+                                        "kind": format!("{kind:?}"),
+                                    }),
+                                );
 
                                 // 记录一次“待展示切换结果”：下一次选路结束时输出 from->to
                                 {
@@ -1463,12 +3080,15 @@ impl ProviderRouter {
                                     tested_map.remove(&tested_key);
                                 }
                                 log::info!(
-                                    "[{}:{}] URL疑似失效 supplier={} url={} 将触发本层级重新测速并在同supplier内切换URL (err={})",
+                                    "[{}:{}] URL疑似失效 supplier={} url={} 将触发本层级重新测速并在同supplier内切换URL (第{}次, {}s, kind={:?}, err={})",
                                     app_type,
                                     priority,
                                     supplier,
                                     url,
-                                    Self::shorten_for_log(err, 160)
+                                    strikes,
+                                    seconds,
+                                    kind,
+                                    Self::shorten_for_log(error_msg.as_deref().unwrap_or("-"), 160)
                                 );
                             }
                         }
@@ -1476,17 +3096,22 @@ impl ProviderRouter {
                 }
             }
         } else {
-            // 成功时尝试移除 suspect（如果有的话）
+            // 成功时尝试移除 suspect（如果有的话），连续标记次数一并清零
             if let Some(provider) = self.db.get_provider_by_id(provider_id, app_type)? {
                 let supplier = Self::supplier_name(&provider);
                 if let Some(url) = Self::extract_base_url(&provider, app_type) {
-                    let key = format!("{app_type}:{supplier}:{url}");
+                    let key = format!("{app_type}:{supplier}:{}", Self::normalize_base_url(&url));
                     let mut map = self.suspect_urls.write().await;
                     map.remove(&key);
                 }
             }
         }
 
+        // 只读模式：熔断器与 suspect 等内存状态已更新，不写健康状态与余额耗尽标记
+        if self.read_only {
+            return Ok(());
+        }
+
         // 3. 更新数据库健康状态（使用配置的阈值）
         self.db
             .update_provider_health_with_threshold(
@@ -1498,6 +3123,31 @@ impl ProviderRouter {
             )
             .await?;
 
+        // 4. 余额耗尽：记录到 provider_health，重新检查间隔内选路跳过该供应商
+        if !success {
+            let kind =
+                failure_kind.or_else(|| error_msg.as_deref().map(FailureKind::from_error_text));
+            if kind == Some(FailureKind::Depleted) {
+                // 只保存上游提示（去掉 `depleted_summary` 的前缀）
+                let message = error_msg.as_deref().unwrap_or_default();
+                let message = message.strip_prefix("余额不足: ").unwrap_or(message);
+                self.db
+                    .mark_provider_depleted(provider_id, app_type, message)
+                    .await?;
+                self.events.record(
+                    app_type,
+                    EventKind::QuotaExclusion,
+                    Some(provider_id),
+                    None,
+                    serde_json::json!({ "message": message }),
+                );
+                log::warn!(
+                    "[{app_type}] 供应商 {provider_id} 余额耗尽，暂停选路: {}",
+                    Self::shorten_for_log(message, 160)
+                );
+            }
+        }
+
         Ok(())
     }
 
@@ -1514,6 +3164,104 @@ impl ProviderRouter {
     pub async fn reset_provider_breaker(&self, provider_id: &str, app_type: &str) {
         let circuit_key = format!("{app_type}:{provider_id}");
         self.reset_circuit_breaker(&circuit_key).await;
+        self.events.record(
+            app_type,
+            EventKind::BreakerClose,
+            Some(provider_id),
+            None,
+            serde_json::json!({ "manual": true }),
+        );
+    }
+
+    /// 临时暂停故障转移 `duration`：期间选路只返回当前供应商（与关闭故障转移相同），
+    /// 不修改持久化的 `auto_failover_enabled`，到期自动恢复。返回截止时间（Unix 秒）
+    ///
+    /// 时长超过 [`MAX_FAILOVER_PAUSE_SECS`] 时按上限处理。
+    pub fn pause_failover(&self, app_type: &str, duration: Duration) -> i64 {
+        let duration = duration.min(Duration::from_secs(MAX_FAILOVER_PAUSE_SECS));
+        // 在持有写锁之前计算截止时间，避免溢出 panic 使锁中毒
+        let Some(until) = std::time::Instant::now().checked_add(duration) else {
+            log::warn!("[{app_type}] 暂停时长超出范围，忽略本次暂停");
+            return chrono::Utc::now().timestamp();
+        };
+        if let Ok(mut pauses) = self.failover_pauses.write() {
+            pauses.insert(app_type.to_string(), until);
+        }
+        log::warn!(
+            "[{app_type}] 已暂停故障转移 {} 秒，期间只使用当前供应商",
+            duration.as_secs()
+        );
+        let secs = i64::try_from(duration.as_secs()).unwrap_or(i64::MAX);
+        chrono::Utc::now().timestamp().saturating_add(secs)
+    }
+
+    /// 提前恢复故障转移；返回此前是否处于暂停中
+    pub fn resume_failover(&self, app_type: &str) -> bool {
+        let was_paused = self.failover_pause_remaining(app_type).is_some();
+        if let Ok(mut pauses) = self.failover_pauses.write() {
+            pauses.remove(app_type);
+        }
+        if was_paused {
+            log::info!("[{app_type}] 已恢复故障转移");
+        }
+        was_paused
+    }
+
+    /// 故障转移暂停的剩余时长（未暂停或已到期时为 None，到期条目在读取时清除）
+    pub fn failover_pause_remaining(&self, app_type: &str) -> Option<Duration> {
+        let until = *self.failover_pauses.read().ok()?.get(app_type)?;
+        let remaining = until.saturating_duration_since(std::time::Instant::now());
+        if remaining.is_zero() {
+            if let Ok(mut pauses) = self.failover_pauses.write() {
+                if pauses.get(app_type) == Some(&until) {
+                    pauses.remove(app_type);
+                    log::info!("[{app_type}] 故障转移暂停已到期，恢复正常故障转移");
+                }
+            }
+            return None;
+        }
+        Some(remaining)
+    }
+
+    /// 所有暂停中的故障转移（按应用排序，用于状态与托盘显示）
+    pub fn failover_pauses(&self) -> Vec<FailoverPause> {
+        let apps: Vec<String> = match self.failover_pauses.read() {
+            Ok(pauses) => pauses.keys().cloned().collect(),
+            Err(_) => return Vec::new(),
+        };
+        let now = chrono::Utc::now().timestamp();
+        let mut result: Vec<FailoverPause> = apps
+            .into_iter()
+            .filter_map(|app_type| {
+                let remaining = self.failover_pause_remaining(&app_type)?;
+                // 向上取整，避免显示“剩余 0 秒”
+                let remaining_secs = remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0);
+                Some(FailoverPause {
+                    until: now + remaining_secs as i64,
+                    remaining_secs,
+                    app_type,
+                })
+            })
+            .collect();
+        result.sort_by(|a, b| a.app_type.cmp(&b.app_type));
+        result
+    }
+
+    /// 手动强制打开指定供应商的熔断器 `secs` 秒（已知上游故障时预先下线，不修改供应商配置）
+    ///
+    /// 期间选路与请求都会跳过该供应商；到期后进入 HalfOpen 探测，`reset_provider_breaker` 可提前解除
+    pub async fn force_open_provider(&self, app_type: &str, provider_id: &str, secs: u64) {
+        let circuit_key = format!("{app_type}:{provider_id}");
+        let breaker = self.get_or_create_circuit_breaker(&circuit_key).await;
+        breaker.force_open(Duration::from_secs(secs)).await;
+        self.events.record(
+            app_type,
+            EventKind::BreakerForced,
+            Some(provider_id),
+            None,
+            serde_json::json!({ "secs": secs }),
+        );
+        log::warn!("[{app_type}] 已手动强制熔断供应商 {provider_id}，持续 {secs} 秒");
     }
 
     /// 更新所有熔断器的配置（热更新）
@@ -1531,8 +3279,55 @@ impl ProviderRouter {
         log::info!("已更新 {count} 个熔断器的配置");
     }
 
+    /// 按应用级代理配置计算熔断器配置
+    ///
+    /// - 冷静期：0 表示“立即进入 HalfOpen”（主要用于测试/诊断）；其余情况钳制到最小值
+    /// - 失败阈值：进入“熔断冷静期”前至少覆盖一次完整重试轮数（默认 3 轮）
+    fn breaker_config_for_app(app_config: &AppProxyConfig) -> CircuitBreakerConfig {
+        let configured_timeout = app_config.circuit_timeout_seconds as u64;
+        let timeout_seconds = if configured_timeout == 0 {
+            0
+        } else {
+            configured_timeout.max(Self::MIN_CIRCUIT_OPEN_TIMEOUT_SECS)
+        };
+
+        let min_failure_threshold = std::cmp::max(
+            Self::MIN_NETWORK_FAILS_BEFORE_SUSPECT,
+            std::cmp::max(1, app_config.max_retries),
+        );
+        let failure_threshold =
+            std::cmp::max(app_config.circuit_failure_threshold, min_failure_threshold);
+
+        CircuitBreakerConfig {
+            failure_threshold,
+            success_threshold: app_config.circuit_success_threshold,
+            timeout_seconds,
+            error_rate_threshold: app_config.circuit_error_rate_threshold,
+            min_requests: app_config.circuit_min_requests,
+            half_open_max_probes: app_config.circuit_half_open_max_probes.max(1),
+        }
+    }
+
+    /// 从数据库重新加载指定应用的熔断器配置并应用到已创建的熔断器，返回更新数量
+    pub async fn reload_app_breaker_configs(&self, app_type: &str) -> Result<usize, AppError> {
+        let app_config = self.db.get_proxy_config_for_app(app_type).await?;
+        let config = Self::breaker_config_for_app(&app_config);
+        let prefix = format!("{app_type}:");
+
+        let breakers = self.circuit_breakers.read().await;
+        let mut count = 0;
+        for (key, breaker) in breakers.iter() {
+            if key.starts_with(&prefix) {
+                breaker.update_config(config.clone()).await;
+                count += 1;
+            }
+        }
+
+        log::info!("[{app_type}] 已重新加载 {count} 个熔断器的配置");
+        Ok(count)
+    }
+
     /// 获取熔断器状态
-    #[allow(dead_code)]
     pub async fn get_circuit_breaker_stats(
         &self,
         provider_id: &str,
@@ -1548,6 +3343,23 @@ impl ProviderRouter {
         }
     }
 
+    /// 该应用下所有已创建熔断器的状态（按 provider_id 排序；未经过请求的供应商没有熔断器）
+    pub async fn get_all_circuit_breaker_stats(
+        &self,
+        app_type: &str,
+    ) -> Vec<(String, crate::proxy::circuit_breaker::CircuitBreakerStats)> {
+        let prefix = format!("{app_type}:");
+        let breakers = self.circuit_breakers.read().await;
+        let mut out = Vec::new();
+        for (key, breaker) in breakers.iter() {
+            if let Some(provider_id) = key.strip_prefix(&prefix) {
+                out.push((provider_id.to_string(), breaker.get_stats().await));
+            }
+        }
+        out.sort_by(|a, b| a.0.cmp(&b.0));
+        out
+    }
+
     /// 获取或创建熔断器
     async fn get_or_create_circuit_breaker(&self, key: &str) -> Arc<CircuitBreaker> {
         // 先尝试读锁获取
@@ -1570,45 +3382,24 @@ impl ProviderRouter {
         let app_type = key.split(':').next().unwrap_or("claude");
 
         // 按应用独立读取熔断器配置
-        let config = match self.db.get_proxy_config_for_app(app_type).await {
+        let config = match self.app_config(app_type).await {
             Ok(app_config) => {
-                let configured_timeout = app_config.circuit_timeout_seconds as u64;
-                // 0 表示“立即进入 HalfOpen”（主要用于测试/诊断）；其余情况才做最小值钳制
-                let timeout_seconds = if configured_timeout == 0 {
-                    0
-                } else {
-                    configured_timeout.max(Self::MIN_CIRCUIT_OPEN_TIMEOUT_SECS)
-                };
-                if configured_timeout != 0 && timeout_seconds != configured_timeout {
+                let config = Self::breaker_config_for_app(&app_config);
+                if app_config.circuit_timeout_seconds as u64 != config.timeout_seconds {
                     log::debug!(
                         "Circuit breaker timeout clamped for {key} (app={app_type}): {}s -> {}s",
-                        configured_timeout,
-                        timeout_seconds
+                        app_config.circuit_timeout_seconds,
+                        config.timeout_seconds
                     );
                 }
-
-                // 进入“熔断冷静期”前至少覆盖一次完整重试轮数（默认 3 轮）
-                let min_failure_threshold = std::cmp::max(
-                    Self::MIN_NETWORK_FAILS_BEFORE_SUSPECT,
-                    std::cmp::max(1, app_config.max_retries),
-                );
-                let failure_threshold =
-                    std::cmp::max(app_config.circuit_failure_threshold, min_failure_threshold);
-
                 log::debug!(
                     "Loading circuit breaker config for {key} (app={app_type}): \
                     failure_threshold={}, success_threshold={}, timeout={}s",
-                    failure_threshold,
-                    app_config.circuit_success_threshold,
-                    timeout_seconds
+                    config.failure_threshold,
+                    config.success_threshold,
+                    config.timeout_seconds
                 );
-                crate::proxy::circuit_breaker::CircuitBreakerConfig {
-                    failure_threshold,
-                    success_threshold: app_config.circuit_success_threshold,
-                    timeout_seconds,
-                    error_rate_threshold: app_config.circuit_error_rate_threshold,
-                    min_requests: app_config.circuit_min_requests,
-                }
+                config
             }
             Err(e) => {
                 log::warn!(
@@ -1636,12 +3427,45 @@ impl ProviderRouter {
         provider: &Provider,
         app_type: &str,
         request_model: &str,
+    ) -> Result<u64, UrlProbeError> {
+        self.test_url_latency_with_budget(provider, app_type, request_model, None)
+            .await
+    }
+
+    /// 同 `test_url_latency`，`max_tokens` 可覆盖探测请求的输出上限（健康检查用 1 以降低成本）
+    async fn test_url_latency_with_budget(
+        &self,
+        provider: &Provider,
+        app_type: &str,
+        request_model: &str,
+        max_tokens: Option<u32>,
     ) -> Result<u64, UrlProbeError> {
         let config_err = |message: String| UrlProbeError {
             latency_ms: 0,
             kind: UrlProbeErrorKind::Network { message },
         };
 
+        // `${ENV_VAR}` 占位符只展开到本次探测使用的副本
+        let expanded = expand_provider(provider).map_err(|e| config_err(e.to_string()))?;
+        let provider: &Provider = &expanded;
+        let timeout = self.probe_tuning(app_type).await.probe_timeout;
+        let request_model = &self
+            .resolve_probe_model(provider, app_type, request_model)
+            .await;
+
+        if app_type == "claude" {
+            if let Some(bedrock) = BedrockConfig::from_provider(provider) {
+                return Self::bedrock_probe_latency(&bedrock, request_model, max_tokens, timeout)
+                    .await;
+            }
+        }
+        if app_type == "gemini" {
+            if let Some(vertex) = VertexConfig::from_provider(provider) {
+                return Self::vertex_probe_latency(&vertex, request_model, max_tokens, timeout)
+                    .await;
+            }
+        }
+
         // 根据app_type提取base_url
         let base_url = match app_type {
             "claude" => provider
@@ -1668,6 +3492,8 @@ impl ProviderRouter {
                 return Err(config_err(format!("不支持的app_type: {}", app_type)));
             }
         };
+        let base_url = Self::normalize_base_url(base_url);
+        let base_url = base_url.as_str();
 
         // 根据app_type提取API key
         let api_key = match app_type {
@@ -1698,7 +3524,7 @@ impl ProviderRouter {
         };
 
         let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(10))
+            .timeout(timeout)
             .build()
             .map_err(|e| UrlProbeError {
                 latency_ms: 0,
@@ -1707,18 +3533,59 @@ impl ProviderRouter {
                 },
             })?;
 
+        // Azure OpenAI：与转发一致按部署构建 URL
+        let azure = if app_type == "codex" {
+            AzureConfig::from_provider(provider)
+        } else {
+            None
+        };
+
         let start = std::time::Instant::now();
 
-        let response = if app_type == "codex" {
+        let response = if let Some(azure) = &azure {
+            // 探测模型不在部署映射中时使用首个部署
+            let deployment = azure
+                .deployment_for(request_model)
+                .or_else(|| azure.first_deployment())
+                .unwrap_or_default();
+            let payload_chat = serde_json::json!({
+                "max_tokens": max_tokens.unwrap_or(PROBE_MAX_TOKENS),
+                "temperature": 0,
+                "stream": false,
+                "messages": [{
+                    "role": "user",
+                    "content": PROBE_PROMPT
+                }]
+            });
+            client
+                .post(azure.build_url(base_url, "/v1/chat/completions", deployment))
+                .header("Content-Type", "application/json")
+                .header("Accept", "application/json")
+                .header(
+                    "x-request-id",
+                    format!("cc-switch-probe-{}", uuid::Uuid::new_v4()),
+                )
+                .header("api-key", api_key)
+                .json(&apply_body_transforms(provider, payload_chat))
+                .send()
+                .await
+                .map_err(|e| UrlProbeError {
+                    latency_ms: start.elapsed().as_millis() as u64,
+                    kind: UrlProbeErrorKind::Network {
+                        message: format!("请求失败: {e}"),
+                    },
+                })?
+        } else if app_type == "codex" {
             // Codex: 真实客户端主要走 Responses API（/v1/responses），否则会出现“真实可用但测速不可用”的误判
             let test_payload = serde_json::json!({
                 "model": request_model,
-                "max_output_tokens": 64,
+                // Responses API 要求 max_output_tokens >= 16；推理模型不接受 temperature，这里不发送
+                "max_output_tokens": max_tokens.unwrap_or(PROBE_MAX_TOKENS).max(16),
                 "stream": false
                 ,
                 "input": [{
                     "role": "user",
-                    "content": [{"type":"input_text","text":"ping"}]
+                    "content": [{"type":"input_text","text":PROBE_PROMPT}]
                 }]
             });
 
@@ -1748,7 +3615,7 @@ impl ProviderRouter {
                 .header("x-stainless-runtime-version", env!("CARGO_PKG_VERSION"))
                 .header("x-stainless-package-version", env!("CARGO_PKG_VERSION"))
                 .header("Authorization", format!("Bearer {}", api_key))
-                .json(&test_payload)
+                .json(&apply_body_transforms(provider, test_payload))
                 .send()
                 .await
                 .map_err(|e| UrlProbeError {
@@ -1762,7 +3629,7 @@ impl ProviderRouter {
             // 若 responses 端点不兼容（常见：400/500 + bad_response_status_code/openai_error/Format mismatch），回退用 chat/completions 探测
             if !resp.status().is_success() {
                 let status = resp.status().as_u16();
-                let body = resp.text().await.ok();
+                let body = read_error_body(resp).await;
                 let text = body.as_deref().unwrap_or_default().to_lowercase();
                 let looks_incompatible = text.contains("bad_response_status_code")
                     || text.contains("openai_error")
@@ -1774,12 +3641,12 @@ impl ProviderRouter {
                 if looks_incompatible {
                     let payload_chat = serde_json::json!({
                         "model": request_model,
-                        "max_tokens": 64,
-                        "temperature": 0.7,
+                        "max_tokens": max_tokens.unwrap_or(PROBE_MAX_TOKENS),
+                        "temperature": 0,
                         "stream": false,
                         "messages": [{
                             "role": "user",
-                            "content": "ping"
+                            "content": PROBE_PROMPT
                         }]
                     });
                     let target_chat = join(base_url, "/v1/chat/completions");
@@ -1799,7 +3666,7 @@ impl ProviderRouter {
                         .header("x-stainless-runtime-version", env!("CARGO_PKG_VERSION"))
                         .header("x-stainless-package-version", env!("CARGO_PKG_VERSION"))
                         .header("Authorization", format!("Bearer {}", api_key))
-                        .json(&payload_chat)
+                        .json(&apply_body_transforms(provider, payload_chat))
                         .send()
                         .await
                         .map_err(|e| UrlProbeError {
@@ -1809,12 +3676,11 @@ impl ProviderRouter {
                             },
                         })?
                 } else {
+                    let body = body.map(|t| Self::shorten_for_log(&t, 200));
                     return Err(UrlProbeError {
                         latency_ms: start.elapsed().as_millis() as u64,
-                        kind: UrlProbeErrorKind::Http {
-                            status,
-                            body: body.map(|t| Self::shorten_for_log(&t, 200)),
-                        },
+                        kind: Self::depleted_probe_error(status, body.as_deref())
+                            .unwrap_or(UrlProbeErrorKind::Http { status, body }),
                     });
                 }
             } else {
@@ -1825,14 +3691,17 @@ impl ProviderRouter {
             // 关键：测试请求必须尽量贴近真实 CLI 环境，否则会出现“测速不可用但真实可用”的误判。
             let test_payload = serde_json::json!({
                 "model": request_model,
-                "max_tokens": 100,
-                "temperature": 1.0,
+                "max_tokens": max_tokens.unwrap_or(PROBE_MAX_TOKENS),
+                "temperature": 0,
                 "stream": false,
                 "messages": [{
                     "role": "user",
-                    "content": "请用一句话简短介绍你自己。"
+                    "content": PROBE_PROMPT
                 }]
             });
+            // 与真实转发一致：显式配置了 settings_config.auth 时直接发送目标认证头
+            let (auth_header, auth_value) =
+                python_proxy_auth_header(AuthScheme::from_provider(provider).as_ref(), api_key);
 
             client
                 .post(format!(
@@ -1849,9 +3718,9 @@ impl ProviderRouter {
                 .header("x-stainless-runtime", "cc-switch")
                 .header("x-stainless-runtime-version", env!("CARGO_PKG_VERSION"))
                 .header("x-stainless-package-version", env!("CARGO_PKG_VERSION"))
-                .header("X-API-Key", api_key)
+                .header(auth_header, auth_value)
                 .header("x-target-base-url", base_url)
-                .json(&test_payload)
+                .json(&apply_body_transforms(provider, test_payload))
                 .send()
                 .await
                 .map_err(|e| UrlProbeError {
@@ -1876,23 +3745,50 @@ impl ProviderRouter {
 
         let status = response.status();
         let latency = start.elapsed().as_millis() as u64;
+        // 与真实转发一致：Claude 经 Python 代理时拆分本地一跳与真实上游耗时
+        let upstream_ms = (app_type == "claude")
+            .then(|| crate::proxy::python_proxy::upstream_elapsed_ms(response.headers()))
+            .flatten();
 
         // 简化测试：仅检查HTTP状态码和连通性
         if status.is_success() {
             // HTTP 200-299，连接成功
-            log::debug!("探测正常 {} - {}", status.as_u16(), provider.name);
-            Ok(latency)
+            match upstream_ms {
+                Some(upstream_ms) => {
+                    log::debug!(
+                        "探测正常 {} - {} ({})",
+                        status.as_u16(),
+                        provider.name,
+                        crate::proxy::python_proxy::format_hop_split(
+                            Locale::current(),
+                            latency,
+                            upstream_ms
+                        )
+                    );
+                    // 测速只计真实上游耗时，与直连上游的 Codex/Gemini 可比；缺少响应头时回退为总耗时
+                    Ok(upstream_ms.min(latency))
+                }
+                None => {
+                    log::debug!("探测正常 {} - {}", status.as_u16(), provider.name);
+                    Ok(latency)
+                }
+            }
         } else {
             // 非200状态码，记录详细错误
             log::debug!("探测失败 {} - {} - 详情: {}", status.as_u16(), provider.name, status);
             let status_code = status.as_u16();
-            let body_text = response.text().await.ok();
+            let body_text = read_error_body(response).await;
             let msg = body_text
                 .as_deref()
                 .and_then(Self::extract_error_message_from_body)
                 .unwrap_or_default();
 
-            if !msg.is_empty() && Self::is_overloaded_error_text(&msg) {
+            if let Some(kind) = Self::depleted_probe_error(status_code, body_text.as_deref()) {
+                Err(UrlProbeError {
+                    latency_ms: latency,
+                    kind,
+                })
+            } else if !msg.is_empty() && Self::is_overloaded_error_text(&msg) {
                 Err(UrlProbeError {
                     latency_ms: latency,
                     kind: UrlProbeErrorKind::Overloaded { message: msg },
@@ -1909,17 +3805,472 @@ impl ProviderRouter {
         }
     }
 
-    async fn connectivity_latency(&self, base_url: &str) -> Result<u64, String> {
-        let url = format!("{}/", base_url.trim_end_matches('/'));
-        let client = reqwest::Client::builder()
-            .timeout(Self::CONNECTIVITY_TIMEOUT)
-            .redirect(reqwest::redirect::Policy::none())
-            .build()
+    /// 后台健康检查探测（不经过真实请求）
+    ///
+    /// 按应用的探测方式执行：
+    /// - full：`test_url_latency` 发送 max_tokens=1 的问答请求；上游满载视为可用
+    /// - models：仅请求 `/v1/models`
+    /// - connectivity（或健康检查开启零成本模式）：仅做连通性探测（HEAD base_url）
+    pub(crate) async fn probe_provider_health(
+        &self,
+        provider: &Provider,
+        app_type: &str,
+        zero_cost: bool,
+    ) -> Result<u64, String> {
+        let tuning = self.probe_tuning(app_type).await;
+        let _permit = self
+            .pace_probe(
+                app_type,
+                &Self::supplier_name(provider),
+                tuning.min_probe_interval,
+            )
+            .await;
+        let mode = Self::probe_mode(app_type);
+        if zero_cost || mode == ProbeMode::Connectivity {
+            let base_url = Self::extract_base_url(provider, app_type)
+                .ok_or_else(|| "Provider缺少base_url配置".to_string())?;
+            return Self::connectivity_latency(&base_url, tuning.connectivity_timeout).await;
+        }
+
+        let result = if mode == ProbeMode::Models {
+            self.models_probe_latency(provider, app_type).await
+        } else {
+            let model = self.app_probe_model(app_type).await;
+            self.test_url_latency_with_budget(provider, app_type, &model, Some(1))
+                .await
+        };
+        match result {
+            Ok(latency) => Ok(latency),
+            Err(e) => match e.kind {
+                UrlProbeErrorKind::Overloaded { .. } => Ok(e.latency_ms),
+                UrlProbeErrorKind::Depleted { message } => Err(format!("余额不足: {message}")),
+                UrlProbeErrorKind::Http { status, body }
+                | UrlProbeErrorKind::Auth { status, body } => Err(match body {
+                    Some(body) => format!("HTTP {status}: {body}"),
+                    None => format!("HTTP {status}"),
+                }),
+                UrlProbeErrorKind::Network { message } => Err(message),
+            },
+        }
+    }
+
+    /// 切换前校验：默认只做不产生费用的探测，`full` 为 true 时才发送问答请求
+    ///
+    /// - 默认：请求 `/v1/models`；鉴权失败判为失败，其余 HTTP 错误回退连通性探测
+    ///   （应用探测方式为 connectivity 时仅 HEAD base_url）
+    /// - full：max_tokens=1 的问答请求，任何 HTTP 错误都判为失败
+    pub async fn verify_provider(
+        &self,
+        provider: &Provider,
+        app_type: &str,
+        full: bool,
+    ) -> ProviderVerification {
+        let mode = Self::verify_mode(Self::probe_mode(app_type), full);
+        self.verify_provider_with_mode(provider, app_type, mode)
+            .await
+    }
+
+    /// 切换前校验使用的探测方式：问答探测需显式开启
+    fn verify_mode(app_mode: ProbeMode, full: bool) -> ProbeMode {
+        match (full, app_mode) {
+            (true, _) => ProbeMode::Full,
+            (false, ProbeMode::Connectivity) => ProbeMode::Connectivity,
+            (false, _) => ProbeMode::Models,
+        }
+    }
+
+    async fn verify_provider_with_mode(
+        &self,
+        provider: &Provider,
+        app_type: &str,
+        mode: ProbeMode,
+    ) -> ProviderVerification {
+        let Some(url) = Self::extract_base_url(provider, app_type) else {
+            return ProviderVerification {
+                mode,
+                detail: UrlProbeDetail {
+                    url: "-".to_string(),
+                    kind: UrlProbeKind::Failed {
+                        reason: "Provider缺少base_url配置".to_string(),
+                    },
+                },
+            };
+        };
+
+        let tuning = self.probe_tuning(app_type).await;
+        let kind = match mode {
+            ProbeMode::Connectivity => {
+                match Self::connectivity_latency(&url, tuning.connectivity_timeout).await {
+                    Ok(connect_ms) => UrlProbeKind::FallbackOk {
+                        connect_ms,
+                        penalty_ms: 0,
+                        reason: "仅连通性探测".to_string(),
+                    },
+                    Err(reason) => UrlProbeKind::Failed { reason },
+                }
+            }
+            ProbeMode::Models | ProbeMode::Full => {
+                let result = if mode == ProbeMode::Models {
+                    self.models_probe_latency(provider, app_type).await
+                } else {
+                    let model = self.app_probe_model(app_type).await;
+                    self.test_url_latency_with_budget(provider, app_type, &model, Some(1))
+                        .await
+                };
+                match result {
+                    Ok(latency_ms) if mode == ProbeMode::Models => {
+                        UrlProbeKind::ModelsOk { latency_ms }
+                    }
+                    Ok(latency_ms) => UrlProbeKind::FullOk { latency_ms },
+                    Err(e) => match e.kind {
+                        UrlProbeErrorKind::Overloaded { message } => UrlProbeKind::Overloaded {
+                            latency_ms: e.latency_ms,
+                            message: Self::shorten_for_log(&message, 120),
+                        },
+                        UrlProbeErrorKind::Http { status, body } if mode == ProbeMode::Models => {
+                            // 上游可能未开放 models 端点：确认 URL 可达即可
+                            let reason = match body {
+                                Some(b) => format!("HTTP {status}: {b}"),
+                                None => format!("HTTP {status}"),
+                            };
+                            match Self::connectivity_latency(&url, tuning.connectivity_timeout)
+                                .await
+                            {
+                                Ok(connect_ms) => UrlProbeKind::FallbackOk {
+                                    connect_ms,
+                                    penalty_ms: tuning.penalty_ms,
+                                    reason: Self::shorten_for_log(&reason, 120),
+                                },
+                                Err(connect_err) => UrlProbeKind::Failed {
+                                    reason: format!("{reason}; {connect_err}"),
+                                },
+                            }
+                        }
+                        UrlProbeErrorKind::Http { status, body } => UrlProbeKind::Failed {
+                            reason: match body {
+                                Some(b) => format!("HTTP {status}: {b}"),
+                                None => format!("HTTP {status}"),
+                            },
+                        },
+                        UrlProbeErrorKind::Auth { status, body } => UrlProbeKind::Failed {
+                            reason: match body {
+                                Some(b) => format!("鉴权失败 HTTP {status}: {b}"),
+                                None => format!("鉴权失败 HTTP {status}"),
+                            },
+                        },
+                        UrlProbeErrorKind::Depleted { message } => UrlProbeKind::Failed {
+                            reason: format!("余额不足: {message}"),
+                        },
+                        UrlProbeErrorKind::Network { message } => UrlProbeKind::Failed {
+                            reason: Self::shorten_for_log(&message, 120),
+                        },
+                    },
+                }
+            }
+        };
+
+        ProviderVerification {
+            mode,
+            detail: UrlProbeDetail { url, kind },
+        }
+    }
+
+    /// 应用当前的探测方式
+    fn probe_mode(app_type: &str) -> ProbeMode {
+        app_type
+            .parse::<crate::app_config::AppType>()
+            .map(|t| crate::settings::get_probe_mode(&t))
+            .unwrap_or_default()
+    }
+
+    /// Bedrock 探测：签名后发送一次 InvokeModel（默认只生成 1 个 token）
+    async fn bedrock_probe_latency(
+        bedrock: &BedrockConfig,
+        request_model: &str,
+        max_tokens: Option<u32>,
+        timeout: Duration,
+    ) -> Result<u64, UrlProbeError> {
+        let network_err = |latency_ms: u64, message: String| UrlProbeError {
+            latency_ms,
+            kind: UrlProbeErrorKind::Network { message },
+        };
+
+        let (_, body) = to_bedrock_body(&serde_json::json!({
+            "max_tokens": max_tokens.unwrap_or(1),
+            "temperature": 0,
+            "messages": [{ "role": "user", "content": PROBE_PROMPT }]
+        }));
+        let url = bedrock.invoke_url(&bedrock.model_id(request_model), false);
+        let payload = serde_json::to_vec(&body).map_err(|e| network_err(0, e.to_string()))?;
+        let signed = bedrock
+            .sign(&url, &payload, chrono::Utc::now())
+            .map_err(|e| network_err(0, e))?;
+
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(|e| network_err(0, format!("创建HTTP客户端失败: {e}")))?;
+        let mut request = client
+            .post(&url)
+            .header("Content-Type", "application/json")
+            .header("Accept", "application/json");
+        for (name, value) in signed {
+            request = request.header(name, value);
+        }
+
+        let start = std::time::Instant::now();
+        let response = request.body(payload).send().await.map_err(|e| {
+            network_err(start.elapsed().as_millis() as u64, format!("请求失败: {e}"))
+        })?;
+        let latency_ms = start.elapsed().as_millis() as u64;
+
+        let status = response.status().as_u16();
+        if response.status().is_success() {
+            return Ok(latency_ms);
+        }
+        let body = read_error_body(response)
+            .await
+            .map(|t| Self::shorten_for_log(&t, 200));
+        Err(UrlProbeError {
+            latency_ms,
+            kind: Self::http_probe_error(status, body),
+        })
+    }
+
+    /// Vertex AI 探测：签发（或复用缓存的）access token 后发送 1 token 的 generateContent
+    ///
+    /// 令牌签发失败按网络错误返回（附带签发失败原因）；上游 401/403 归类为鉴权失败。
+    async fn vertex_probe_latency(
+        vertex: &VertexConfig,
+        request_model: &str,
+        max_tokens: Option<u32>,
+        timeout: Duration,
+    ) -> Result<u64, UrlProbeError> {
+        let network_err = |latency_ms: u64, message: String| UrlProbeError {
+            latency_ms,
+            kind: UrlProbeErrorKind::Network { message },
+        };
+
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(|e| network_err(0, format!("创建HTTP客户端失败: {e}")))?;
+        let token = vertex
+            .access_token(&client)
+            .await
+            .map_err(|e| network_err(0, e.to_string()))?;
+
+        let model = match request_model.trim() {
+            "" => Self::default_probe_model("gemini"),
+            model => model,
+        };
+        let url = vertex.model_url(&format!("{model}:generateContent"));
+        let payload = serde_json::json!({
+            "contents": [{ "role": "user", "parts": [{ "text": PROBE_PROMPT }] }],
+            "generationConfig": { "maxOutputTokens": max_tokens.unwrap_or(1), "temperature": 0 }
+        });
+
+        let start = std::time::Instant::now();
+        let response = client
+            .post(&url)
+            .bearer_auth(token)
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|e| {
+                network_err(start.elapsed().as_millis() as u64, format!("请求失败: {e}"))
+            })?;
+        let latency_ms = start.elapsed().as_millis() as u64;
+
+        let status = response.status().as_u16();
+        if response.status().is_success() {
+            return Ok(latency_ms);
+        }
+        if status == 401 {
+            vertex.invalidate_token().await;
+        }
+        let body = read_error_body(response)
+            .await
+            .map(|t| Self::shorten_for_log(&t, 200));
+        Err(UrlProbeError {
+            latency_ms,
+            kind: Self::http_probe_error(status, body),
+        })
+    }
+
+    /// 低成本探测：请求上游 `/v1/models`，不发送问答负载
+    ///
+    /// 401/403 归类为鉴权失败，其余非 2xx 视为 HTTP 错误（可能未开放 models 端点）。
+    async fn models_probe_latency(
+        &self,
+        provider: &Provider,
+        app_type: &str,
+    ) -> Result<u64, UrlProbeError> {
+        let config_err = |message: &str| UrlProbeError {
+            latency_ms: 0,
+            kind: UrlProbeErrorKind::Network {
+                message: message.to_string(),
+            },
+        };
+        // Azure OpenAI / Bedrock / Vertex AI 没有 /v1/models：发送最小问答
+        let no_models_endpoint = match app_type {
+            "codex" => AzureConfig::from_provider(provider).is_some(),
+            "claude" => BedrockConfig::from_provider(provider).is_some(),
+            "gemini" => VertexConfig::from_provider(provider).is_some(),
+            _ => false,
+        };
+        if no_models_endpoint {
+            let model = self.app_probe_model(app_type).await;
+            return self
+                .test_url_latency_with_budget(provider, app_type, &model, Some(1))
+                .await;
+        }
+
+        let base_url = Self::extract_base_url(provider, app_type)
+            .ok_or_else(|| config_err("Provider缺少base_url配置"))?;
+        let api_key = Self::extract_api_key_value(provider, app_type)
+            .ok_or_else(|| config_err("Provider缺少API key配置"))?;
+        let auth = if app_type == "claude" {
+            AuthScheme::from_provider(provider)
+                .map(ModelListAuth::Scheme)
+                .unwrap_or(ModelListAuth::Anthropic)
+        } else {
+            ModelListAuth::Bearer
+        };
+
+        let client = reqwest::Client::builder()
+            .timeout(self.probe_tuning(app_type).await.probe_timeout)
+            .build()
+            .map_err(|e| config_err(&format!("创建HTTP客户端失败: {e}")))?;
+
+        let start = std::time::Instant::now();
+        let result = fetch_model_ids(&client, &base_url, &api_key, auth).await;
+        let latency_ms = start.elapsed().as_millis() as u64;
+
+        match result {
+            Ok(_) => Ok(latency_ms),
+            Err(e) => {
+                let kind = match e {
+                    ModelListError::Auth { status, body } => UrlProbeErrorKind::Auth {
+                        status,
+                        body: Some(body).filter(|b| !b.is_empty()),
+                    },
+                    ModelListError::Http { status, body } => {
+                        Self::http_probe_error(status, Some(body).filter(|b| !b.is_empty()))
+                    }
+                    other => UrlProbeErrorKind::Network {
+                        message: other.to_string(),
+                    },
+                };
+                Err(UrlProbeError { latency_ms, kind })
+            }
+        }
+    }
+
+    /// 单个供应商探测实际使用的模型
+    ///
+    /// 优先级：供应商 `settingsConfig.probeModel` > 本地后端（Ollama 等）的 Codex 默认模型
+    /// （请求模型在本地通常不存在）> 应用级 `probeModel` > 调用方指定的模型（通常为最近的请求模型）
+    /// > [`Self::app_probe_model`]。
+    async fn resolve_probe_model(
+        &self,
+        provider: &Provider,
+        app_type: &str,
+        request_model: &str,
+    ) -> String {
+        if let Some(model) = provider.probe_model() {
+            return model;
+        }
+        if app_type == "codex" && provider.is_local_backend() {
+            if let Some(model) = provider.codex_default_model() {
+                return model;
+            }
+        }
+        // 显式配置的探测模型说明供应商只支持特定模型，优先于请求模型
+        if let Some(model) = self.configured_probe_model(app_type).await {
+            return model;
+        }
+        match request_model.trim() {
+            "" | "unknown" => self.app_probe_model(app_type).await,
+            model => model.to_string(),
+        }
+    }
+
+    /// 应用级探测模型：`AppProxyConfig.probe_model` > 最近一次真实请求的模型 > 内置默认模型
+    pub async fn app_probe_model(&self, app_type: &str) -> String {
+        self.configured_probe_model(app_type)
+            .await
+            .or_else(|| self.last_request_model(app_type))
+            .unwrap_or_else(|| Self::default_probe_model(app_type).to_string())
+    }
+
+    /// 应用级配置的探测模型（`AppProxyConfig.probe_model`，空白视为未配置）
+    async fn configured_probe_model(&self, app_type: &str) -> Option<String> {
+        self.app_config(app_type)
+            .await
+            .ok()
+            .and_then(|config| config.probe_model)
+            .map(|m| m.trim().to_string())
+            .filter(|m| !m.is_empty())
+    }
+
+    /// 最近一次真实请求的模型（持久化的请求指纹），缺失或为 "unknown" 时返回 None
+    fn last_request_model(&self, app_type: &str) -> Option<String> {
+        let json = self
+            .db
+            .get_setting(&last_request_summary_setting_key(app_type))
+            .ok()??;
+        let summary: LastRequestSummary = serde_json::from_str(&json).ok()?;
+        let model = summary.model.trim();
+        (!model.is_empty() && model != "unknown").then(|| model.to_string())
+    }
+
+    /// 探测使用的内置默认模型（未配置探测模型且没有请求记录时使用）
+    fn default_probe_model(app_type: &str) -> &'static str {
+        match app_type {
+            "claude" => "claude-sonnet-4-5-20250929",
+            "codex" => "gpt-5.2",
+            "gemini" => "gemini-2.0-flash",
+            _ => "unknown",
+        }
+    }
+
+    /// 供应商所在层级是否处于冷却期
+    pub(crate) async fn is_provider_in_cooldown(
+        &self,
+        provider: &Provider,
+        app_type: &str,
+    ) -> bool {
+        let priority = provider.priority();
+        let supplier = Self::supplier_name(provider);
+        self.is_supplier_in_cooldown(app_type, priority, &supplier).await
+    }
+
+    /// URL 是否指向本机（localhost / 127.0.0.0/8 / ::1）
+    fn is_loopback_url(url: &str) -> bool {
+        match url::Url::parse(url).ok().as_ref().and_then(|u| u.host()) {
+            Some(url::Host::Domain(host)) => host.eq_ignore_ascii_case("localhost"),
+            Some(url::Host::Ipv4(ip)) => ip.is_loopback(),
+            Some(url::Host::Ipv6(ip)) => ip.is_loopback(),
+            None => false,
+        }
+    }
+
+    /// 连通性探测：对 base_url 发 HTTP HEAD（走 HTTP(S)_PROXY 与 TLS），拿到任意响应即视为可连通
+    pub async fn connectivity_latency(base_url: &str, timeout: Duration) -> Result<u64, String> {
+        // 带路径前缀（如 `/anthropic`、`/v1`）的 base_url 原样探测，不追加 `/`；
+        // 按 `url` 解析，IPv6 字面量与 IP + 端口不会拼出无效地址
+        let url = url::Url::parse(&Self::normalize_base_url(base_url))
+            .map_err(|e| format!("无效的 base_url {base_url}: {e}"))?;
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
             .map_err(|e| format!("创建HTTP客户端失败: {e}"))?;
 
         let start = std::time::Instant::now();
         let resp = client
-            .head(&url)
+            .head(url)
             .send()
             .await
             .map_err(|e| format!("连通性探测失败: {e}"))?;
@@ -1938,7 +4289,7 @@ impl ProviderRouter {
         priority: usize,
         request_model: &str,
         supplier: &str,
-        url_groups: &HashMap<String, Vec<Provider>>,
+        url_groups: &BTreeMap<String, Vec<Provider>>,
     ) -> Vec<(String, u64)> {
         self.benchmark_urls_with_log_mode(
             app_type,
@@ -1949,6 +4300,7 @@ impl ProviderRouter {
             false,
         )
         .await
+        .0
     }
 
     async fn benchmark_urls_with_log_mode(
@@ -1957,9 +4309,9 @@ impl ProviderRouter {
         priority: usize,
         request_model: &str,
         supplier: &str,
-        url_groups: &HashMap<String, Vec<Provider>>,
+        url_groups: &BTreeMap<String, Vec<Provider>>,
         force_summary_info: bool,
-    ) -> Vec<(String, u64)> {
+    ) -> (Vec<(String, u64)>, CooldownReason) {
         let details = self
             .benchmark_urls_detailed_impl(
                 app_type,
@@ -1968,15 +4320,19 @@ impl ProviderRouter {
                 supplier,
                 url_groups,
                 force_summary_info,
+                Self::probe_mode(app_type),
             )
             .await;
 
+        let penalty_ms = self.probe_tuning(app_type).await.penalty_ms;
         let mut results: Vec<(String, u64)> = Vec::with_capacity(details.len());
         for d in details.iter() {
             let latency = match &d.kind {
-                UrlProbeKind::FullOk { latency_ms } => *latency_ms,
+                UrlProbeKind::FullOk { latency_ms } | UrlProbeKind::ModelsOk { latency_ms } => {
+                    *latency_ms
+                }
                 UrlProbeKind::Overloaded { latency_ms, .. } => {
-                    latency_ms.saturating_add(Self::CONNECTIVITY_PENALTY_MS)
+                    latency_ms.saturating_add(penalty_ms)
                 }
                 UrlProbeKind::FallbackOk {
                     connect_ms,
@@ -1988,7 +4344,7 @@ impl ProviderRouter {
             results.push((d.url.clone(), latency));
         }
 
-        results
+        (results, CooldownReason::from_details(&details))
     }
 
     /// 详细测速：与真实启动探测同构，但保留“满载/限流”等可达状态，
@@ -1999,7 +4355,7 @@ impl ProviderRouter {
         priority: usize,
         request_model: &str,
         supplier: &str,
-        url_groups: &HashMap<String, Vec<Provider>>,
+        url_groups: &BTreeMap<String, Vec<Provider>>,
     ) -> Vec<UrlProbeDetail> {
         self.benchmark_urls_detailed_impl(
             app_type,
@@ -2008,26 +4364,30 @@ impl ProviderRouter {
             supplier,
             url_groups,
             false,
+            Self::probe_mode(app_type),
         )
         .await
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn benchmark_urls_detailed_impl(
         &self,
         app_type: &str,
         priority: usize,
         request_model: &str,
         supplier: &str,
-        url_groups: &HashMap<String, Vec<Provider>>,
+        url_groups: &BTreeMap<String, Vec<Provider>>,
         force_summary_info: bool,
+        mode: ProbeMode,
     ) -> Vec<UrlProbeDetail> {
         log::debug!(
-            "[{}:{}] 开始URL延迟测试，共{}个URL (supplier={}, model={})",
+            "[{}:{}] 开始URL延迟测试，共{}个URL (supplier={}, model={}, mode={:?})",
             app_type,
             priority,
             url_groups.len(),
             supplier,
-            request_model
+            request_model,
+            mode
         );
 
         let mut details: Vec<UrlProbeDetail> = Vec::new();
@@ -2037,11 +4397,15 @@ impl ProviderRouter {
         let mut fallback_ok_count: usize = 0;
         let mut fail_count: usize = 0;
 
-        for (url, providers) in url_groups {
-            // 同一 URL 下按 key 去重并尝试少量 key，避免“只测第一个 key 就判死”
-            const MAX_KEYS_PER_URL: usize = 2;
+        // 同一 URL 下按 key 去重并尝试少量 key，避免“只测第一个 key 就判死”
+        let max_keys_per_url = match self.app_config(app_type).await {
+            Ok(config) => (config.benchmark_max_keys_per_url as usize).max(1),
+            Err(_) => Self::DEFAULT_BENCHMARK_MAX_KEYS_PER_URL,
+        };
+        let tuning = self.probe_tuning(app_type).await;
 
-            let mut unique_by_key: HashMap<String, Provider> = HashMap::new();
+        for (url, providers) in url_groups {
+            let mut unique_by_key: BTreeMap<String, Provider> = BTreeMap::new();
             for p in providers {
                 let Some(key_value) = Self::extract_api_key_value(p, app_type) else {
                     continue;
@@ -2049,14 +4413,60 @@ impl ProviderRouter {
                 unique_by_key.entry(key_value).or_insert_with(|| p.clone());
             }
 
+            // 本地后端的 localhost URL：连通性回退不加惩罚（本地延迟没有比较意义）
+            let local_loopback =
+                Self::is_loopback_url(url) && providers.iter().any(|p| p.is_local_backend());
+
             let mut tested_providers: Vec<Provider> = unique_by_key.into_values().collect();
-            tested_providers.truncate(MAX_KEYS_PER_URL);
+            // 连通性模式：不发送任何请求负载，直接走下方的连通性探测
+            if mode == ProbeMode::Connectivity {
+                tested_providers.clear();
+            }
+
+            // 近期有真实成功请求：直接采用请求日志中的中位延迟，不再花 token 探测
+            // （URL 失效后的重新测速除外）
+            let recent_ok = if mode == ProbeMode::Connectivity || force_summary_info {
+                None
+            } else {
+                self.recent_success_latency(app_type, providers, tuning.skip_recent_success_secs)
+            };
+            if let Some(latency) = recent_ok {
+                log::debug!(
+                    "[{}:{}] {} 近 {} 秒内有成功请求，跳过探测（中位延迟 {}ms）",
+                    app_type,
+                    priority,
+                    url,
+                    tuning.skip_recent_success_secs,
+                    latency
+                );
+                tested_providers.clear();
+            }
 
-            let mut full_ok: Option<u64> = None;
+            let mut full_ok: Option<u64> = recent_ok;
             let mut overloaded: Option<(u64, String)> = None;
             let mut err_summaries: Vec<String> = Vec::new();
+            let mut auth_failures: usize = 0;
+            let mut depleted_keys: usize = 0;
+            let mut attempted: usize = 0;
 
             for provider in tested_providers.iter() {
+                // 达到上限后：已测 key 全部满载（或余额耗尽）时再额外尝试一个未测 key，避免前几个 key 额度耗尽被误判为 OV
+                if attempted >= max_keys_per_url {
+                    let all_overloaded = (overloaded.is_some() || depleted_keys > 0)
+                        && err_summaries.len() == depleted_keys;
+                    if attempted > max_keys_per_url || !all_overloaded {
+                        break;
+                    }
+                    log::debug!(
+                        "[{}:{}] {} 已测 {} 个 key 均满载，额外尝试 {}",
+                        app_type,
+                        priority,
+                        url,
+                        attempted,
+                        provider.name
+                    );
+                }
+                attempted += 1;
                 log::debug!(
                     "[{}:{}] 测试URL: {} (使用provider: {})",
                     app_type,
@@ -2065,9 +4475,26 @@ impl ProviderRouter {
                     provider.name
                 );
 
-                match self.test_url_latency(provider, app_type, request_model).await {
+                let permit = self
+                    .pace_probe(app_type, supplier, tuning.min_probe_interval)
+                    .await;
+                let result = if mode == ProbeMode::Models {
+                    self.models_probe_latency(provider, app_type).await
+                } else {
+                    self.test_url_latency(provider, app_type, request_model)
+                        .await
+                };
+                drop(permit);
+                match result {
                     Ok(latency) => {
                         full_ok = Some(latency);
+                        // 记录探测成功的 key：测速后的下一次选路优先使用
+                        self.benchmark_winning_keys
+                            .write()
+                            .await
+                            .entry(format!("{app_type}:{priority}"))
+                            .or_default()
+                            .insert(provider.id.clone());
                         break;
                     }
                     Err(e) => match e.kind {
@@ -2076,6 +4503,24 @@ impl ProviderRouter {
                             // Overloaded 可能与 key 相关，继续尝试下一个 key
                             continue;
                         }
+                        UrlProbeErrorKind::Depleted { message } => {
+                            // 余额耗尽只与 key 相关：记录状态后继续尝试下一个 key
+                            depleted_keys += 1;
+                            if self.read_only {
+                                // 只读模式不记录余额耗尽
+                            } else if let Err(e) = self
+                                .db
+                                .mark_provider_depleted(&provider.id, app_type, &message)
+                                .await
+                            {
+                                log::warn!(
+                                    "[{app_type}] 记录供应商 {} 余额耗尽失败: {e}",
+                                    provider.id
+                                );
+                            }
+                            let summary = format!("余额不足: {message}");
+                            err_summaries.push(Self::shorten_for_log(&summary, 120));
+                        }
                         UrlProbeErrorKind::Http { status, body } => {
                             let b = body.unwrap_or_default();
                             let reason = if b.is_empty() {
@@ -2085,6 +4530,14 @@ impl ProviderRouter {
                             };
                             err_summaries.push(Self::shorten_for_log(&reason, 120));
                         }
+                        UrlProbeErrorKind::Auth { status, body } => {
+                            auth_failures += 1;
+                            let reason = match body.filter(|b| !b.is_empty()) {
+                                Some(b) => format!("鉴权失败 HTTP {status}: {b}"),
+                                None => format!("鉴权失败 HTTP {status}"),
+                            };
+                            err_summaries.push(Self::shorten_for_log(&reason, 120));
+                        }
                         UrlProbeErrorKind::Network { message } => {
                             err_summaries.push(Self::shorten_for_log(&message, 120));
                         }
@@ -2106,9 +4559,19 @@ impl ProviderRouter {
                     },
                 );
 
+                // models 探测只说明 key 能列出模型；近期真实成功请求仍视为全链路 OK
+                let kind = if mode == ProbeMode::Models && recent_ok.is_none() {
+                    UrlProbeKind::ModelsOk {
+                        latency_ms: latency,
+                    }
+                } else {
+                    UrlProbeKind::FullOk {
+                        latency_ms: latency,
+                    }
+                };
                 details.push(UrlProbeDetail {
                     url: url.clone(),
-                    kind: UrlProbeKind::FullOk { latency_ms: latency },
+                    kind,
                 });
                 continue;
             }
@@ -2125,18 +4588,43 @@ impl ProviderRouter {
                 continue;
             }
 
-            let err_short = if err_summaries.is_empty() {
+            let err_short = if mode == ProbeMode::Connectivity {
+                "仅连通性探测".to_string()
+            } else if err_summaries.is_empty() {
                 "未知错误".to_string()
             } else {
                 err_summaries.join("; ")
             };
 
+            // 鉴权失败（models 探测）或余额耗尽说明 URL 可达但 key 全部不可用：不再回退连通性测试
+            let key_failures = auth_failures + depleted_keys;
+            if key_failures > 0 && key_failures == attempted {
+                fail_count += 1;
+                details.push(UrlProbeDetail {
+                    url: url.clone(),
+                    kind: UrlProbeKind::Failed {
+                        reason: format!("{ALL_KEYS_FAILED_PREFIX}{err_short}"),
+                    },
+                });
+                continue;
+            }
+
             // 回退到简单连通性测试（仅作为“可达性”保底）
-            match self.connectivity_latency(url).await {
+            let permit = self
+                .pace_probe(app_type, supplier, tuning.min_probe_interval)
+                .await;
+            let connectivity = Self::connectivity_latency(url, tuning.connectivity_timeout).await;
+            drop(permit);
+            match connectivity {
                 Ok(connect_ms) => {
                     fallback_ok_count += 1;
 
-                    let penalty_ms = Self::CONNECTIVITY_PENALTY_MS;
+                    // 连通性模式下所有 URL 同等对待，无需惩罚
+                    let penalty_ms = if mode == ProbeMode::Connectivity || local_loopback {
+                        0
+                    } else {
+                        tuning.penalty_ms
+                    };
                     let total_ms = connect_ms.saturating_add(penalty_ms);
 
                     // 缓存回退结果（避免重复测速刷屏）
@@ -2175,31 +4663,31 @@ impl ProviderRouter {
             }
         }
 
-        // 排序：OK 最优，其次 OVERLOADED，再次 FB，最后 FAIL
+        // 排序：OK 最优，其次 MO、OVERLOADED，再次 FB，最后 FAIL
         details.sort_by_key(|d| match &d.kind {
             UrlProbeKind::FullOk { latency_ms } => (0u8, *latency_ms),
-            UrlProbeKind::Overloaded { latency_ms, .. } => (1u8, latency_ms.saturating_add(30_000)),
-            UrlProbeKind::FallbackOk { connect_ms, penalty_ms, .. } => (2u8, connect_ms.saturating_add(*penalty_ms)),
-            UrlProbeKind::Failed { .. } => (3u8, u64::MAX),
+            UrlProbeKind::ModelsOk { latency_ms } => (1u8, *latency_ms),
+            UrlProbeKind::Overloaded { latency_ms, .. } => {
+                (2u8, latency_ms.saturating_add(tuning.penalty_ms))
+            }
+            UrlProbeKind::FallbackOk { connect_ms, penalty_ms, .. } => (3u8, connect_ms.saturating_add(*penalty_ms)),
+            UrlProbeKind::Failed { .. } => (4u8, u64::MAX),
         });
 
-        // 选用策略：对齐真实路由（优先 URL 且全链路 OK > OK 最快 > OV > FB）
-        let mut preferred: Vec<String> = Self::default_url_priority_for_supplier(supplier)
-            .into_iter()
-            .map(|s| s.to_string())
-            .collect();
-        if let Some(p) = url_groups.values().flat_map(|v| v.first()).next() {
-            preferred.extend(Self::parse_url_priority_from_provider(p));
-        }
-        {
-            let mut seen = std::collections::HashMap::<String, ()>::new();
-            preferred.retain(|u| seen.insert(u.to_string(), ()).is_none());
-        }
+        // 选用策略：对齐真实路由（优先 URL 且 OK/MO > OK 最快 > MO 最快 > OV > FB）
+        let preferred = self.preferred_urls_for_supplier(
+            supplier,
+            url_groups.values().flat_map(|v| v.first()).next(),
+        );
 
         let preferred_ok = preferred.iter().find_map(|u| {
-            details
-                .iter()
-                .find(|d| d.url == *u && matches!(d.kind, UrlProbeKind::FullOk { .. }))
+            details.iter().find(|d| {
+                d.url == *u
+                    && matches!(
+                        d.kind,
+                        UrlProbeKind::FullOk { .. } | UrlProbeKind::ModelsOk { .. }
+                    )
+            })
         });
 
         let selected = preferred_ok
@@ -2208,6 +4696,11 @@ impl ProviderRouter {
                     .iter()
                     .find(|d| matches!(d.kind, UrlProbeKind::FullOk { .. }))
             })
+            .or_else(|| {
+                details
+                    .iter()
+                    .find(|d| matches!(d.kind, UrlProbeKind::ModelsOk { .. }))
+            })
             .or_else(|| {
                 details
                     .iter()
@@ -2221,6 +4714,7 @@ impl ProviderRouter {
         let selected_text = selected
             .map(|d| match &d.kind {
                 UrlProbeKind::FullOk { latency_ms } => format!("{} (OK {}ms)", d.url, latency_ms),
+                UrlProbeKind::ModelsOk { latency_ms } => format!("{} (MO {}ms)", d.url, latency_ms),
                 UrlProbeKind::Overloaded { latency_ms, .. } => {
                     format!("{} (OV {}ms)", d.url, latency_ms)
                 }
@@ -2240,6 +4734,7 @@ impl ProviderRouter {
             .iter()
             .map(|d| match &d.kind {
                 UrlProbeKind::FullOk { latency_ms } => format!("{}=OK({}ms)", d.url, latency_ms),
+                UrlProbeKind::ModelsOk { latency_ms } => format!("{}=MO({}ms)", d.url, latency_ms),
                 UrlProbeKind::Overloaded { latency_ms, message } => {
                     format!("{}=OV({}ms, {})", d.url, latency_ms, message)
                 }
@@ -2256,45 +4751,65 @@ impl ProviderRouter {
         if full_ok_count == 0 && overloaded_count == 0 && fallback_ok_count == 0 {
             // 全失败时始终 WARN（便于排障）
             log::warn!(
-                "[{}:{}] 测速结束 supplier={} model={} 结果: 全失败(ok=0 ov=0 fb=0 fail={}) 选用={} 详情: {}",
-                app_type,
-                priority,
-                supplier,
-                request_model,
-                fail_count,
-                selected_text,
-                detail_text
+                "{}",
+                tr(
+                    Msg::BenchmarkAllFailed,
+                    &[
+                        &app_type,
+                        &priority,
+                        &supplier,
+                        &request_model,
+                        &fail_count,
+                        &selected_text,
+                        &detail_text,
+                    ],
+                )
             );
         } else {
             // 默认不刷屏：摘要降为 DEBUG；需要时可通过 CC_SWITCH_BENCHMARK_SUMMARY=1 提升到 INFO
             if summary_info {
+                let reason = if force_summary_info {
+                    "retest"
+                } else {
+                    "normal"
+                };
                 log::info!(
-                    "[{}:{}] 测速结束 supplier={} model={} reason={} 结果: ok={} ov={} fb={} fail={} 选用={} 详情: {}",
-                    app_type,
-                    priority,
-                    supplier,
-                    request_model,
-                    if force_summary_info { "retest" } else { "normal" },
-                    full_ok_count,
-                    overloaded_count,
-                    fallback_ok_count,
-                    fail_count,
-                    selected_text,
-                    detail_text
+                    "{}",
+                    tr(
+                        Msg::BenchmarkSummaryWithReason,
+                        &[
+                            &app_type,
+                            &priority,
+                            &supplier,
+                            &request_model,
+                            &reason,
+                            &full_ok_count,
+                            &overloaded_count,
+                            &fallback_ok_count,
+                            &fail_count,
+                            &selected_text,
+                            &detail_text,
+                        ],
+                    )
                 );
             } else {
                 log::debug!(
-                    "[{}:{}] 测速结束 supplier={} model={} 结果: ok={} ov={} fb={} fail={} 选用={} 详情: {}",
-                    app_type,
-                    priority,
-                    supplier,
-                    request_model,
-                    full_ok_count,
-                    overloaded_count,
-                    fallback_ok_count,
-                    fail_count,
-                    selected_text,
-                    detail_text
+                    "{}",
+                    tr(
+                        Msg::BenchmarkSummary,
+                        &[
+                            &app_type,
+                            &priority,
+                            &supplier,
+                            &request_model,
+                            &full_ok_count,
+                            &overloaded_count,
+                            &fallback_ok_count,
+                            &fail_count,
+                            &selected_text,
+                            &detail_text,
+                        ],
+                    )
                 );
             }
         }
@@ -2302,22 +4817,32 @@ impl ProviderRouter {
         details
     }
 
+    /// 测速全部（或指定层级/supplier 的）供应商
+    ///
+    /// 不同 supplier 之间按 `benchmark_concurrency` 并发测速，单个 supplier 内部行为不变；
+    /// 结果始终按 (层级, supplier) 排序，与完成先后无关。
+    ///
+    /// `cancel` 被取消后不再开始新的 supplier，进行中的探测立即中止（不写入 current_url），
+    /// 只返回已完成的 supplier。`on_progress` 在每个 supplier 开始与完成时同步调用。
     pub async fn benchmark_all_suppliers(
         &self,
         app_type: &str,
         request_model: &str,
         only_priority: Option<usize>,
         only_supplier: Option<&str>,
+        cancel: &CancellationToken,
+        on_progress: &(dyn Fn(BenchmarkProgress) + Send + Sync),
     ) -> Result<Vec<BenchmarkSupplierResult>, AppError> {
         let providers = self.db.get_failover_providers(app_type)?;
         if providers.is_empty() {
             return Ok(Vec::new());
         }
+        let tuning = self.probe_tuning(app_type).await;
 
         let mut priority_groups: std::collections::BTreeMap<usize, Vec<Provider>> =
             std::collections::BTreeMap::new();
         for provider in providers {
-            let priority = provider.sort_index.unwrap_or(999999);
+            let priority = provider.priority();
             if let Some(p) = only_priority {
                 if priority != p {
                     continue;
@@ -2326,10 +4851,12 @@ impl ProviderRouter {
             priority_groups.entry(priority).or_default().push(provider);
         }
 
-        let mut out: Vec<BenchmarkSupplierResult> = Vec::new();
-
+        // 按 (层级, supplier) 排好的测速任务
+        let mut jobs: Vec<(usize, String, BTreeMap<String, Vec<Provider>>)> = Vec::new();
         for (priority, providers_in_level) in priority_groups.into_iter() {
-            let mut supplier_urls: HashMap<String, HashMap<String, Vec<Provider>>> = HashMap::new();
+            // 与 select_providers 相同的固定顺序：供应商名字典序，其次 URL 字典序
+            let mut supplier_urls: BTreeMap<String, BTreeMap<String, Vec<Provider>>> =
+                BTreeMap::new();
 
             for provider in providers_in_level {
                 let supplier = Self::supplier_name(&provider);
@@ -2349,153 +4876,242 @@ impl ProviderRouter {
                     .push(provider);
             }
 
-            for (supplier, url_groups) in supplier_urls.into_iter() {
-                if self.is_supplier_in_cooldown(app_type, priority, &supplier).await {
-                    out.push(BenchmarkSupplierResult {
+            jobs.extend(
+                supplier_urls
+                    .into_iter()
+                    .map(|(supplier, url_groups)| (priority, supplier, url_groups)),
+            );
+        }
+
+        // 无序并发：先完成的 supplier 立即让出名额，结果再按任务序号排回
+        let total = jobs.len();
+        let mut indexed: Vec<(usize, BenchmarkSupplierResult)> =
+            futures::stream::iter(jobs.into_iter().enumerate())
+                .map(|(index, (priority, supplier, url_groups))| async move {
+                    if cancel.is_cancelled() {
+                        return None;
+                    }
+                    on_progress(BenchmarkProgress::SupplierStarted {
+                        index,
+                        total,
                         priority,
-                        supplier,
-                        request_model: Some(request_model.to_string()),
-                        effective_model: None,
-                        chosen_url: None,
-                        chosen_kind: "COOLDOWN".to_string(),
-                        metric_ms: None,
-                        urls: Vec::new(),
+                        supplier: supplier.clone(),
                     });
-                    continue;
-                }
+                    let result = tokio::select! {
+                        result = self.benchmark_supplier(
+                            app_type,
+                            request_model,
+                            priority,
+                            supplier,
+                            url_groups,
+                            tuning,
+                        ) => result,
+                        _ = cancel.cancelled() => return None,
+                    };
+                    on_progress(BenchmarkProgress::SupplierFinished {
+                        index,
+                        total,
+                        result: result.clone(),
+                    });
+                    Some((index, result))
+                })
+                .buffer_unordered(tuning.benchmark_concurrency.max(1))
+                .filter_map(futures::future::ready)
+                .collect()
+                .await;
+        if cancel.is_cancelled() {
+            log::info!(
+                "[{app_type}] 测速已取消：完成 {}/{total} 个 supplier",
+                indexed.len()
+            );
+        }
+        indexed.sort_by_key(|(index, _)| *index);
+        let out: Vec<BenchmarkSupplierResult> =
+            indexed.into_iter().map(|(_, result)| result).collect();
 
-                let details = self
-                    .benchmark_urls_detailed(app_type, priority, request_model, &supplier, &url_groups)
-                    .await;
+        self.record_benchmark_history(app_type, &out);
+        Ok(out)
+    }
 
-                let mut urls: Vec<BenchmarkUrlResult> = Vec::with_capacity(details.len());
-                for d in details.iter() {
-                    let (kind, latency_ms, penalty_ms, message, reason) = match &d.kind {
-                        UrlProbeKind::FullOk { latency_ms } => (
-                            "OK".to_string(),
-                            Some(*latency_ms),
-                            None,
-                            None,
-                            None,
-                        ),
-                        UrlProbeKind::Overloaded { latency_ms, message } => (
-                            "OV".to_string(),
-                            Some(*latency_ms),
-                            Some(Self::CONNECTIVITY_PENALTY_MS),
-                            Some(message.clone()),
-                            None,
-                        ),
-                        UrlProbeKind::FallbackOk {
-                            connect_ms,
-                            penalty_ms,
-                            reason,
-                        } => (
-                            "FB".to_string(),
-                            Some(*connect_ms),
-                            Some(*penalty_ms),
-                            None,
-                            Some(reason.clone()),
-                        ),
-                        UrlProbeKind::Failed { reason } => (
-                            "FAIL".to_string(),
-                            None,
-                            None,
-                            None,
-                            Some(reason.clone()),
-                        ),
-                    };
+    /// 测速单个 supplier：冷却中直接返回 COOLDOWN，否则逐 URL 探测并写入 current_url
+    async fn benchmark_supplier(
+        &self,
+        app_type: &str,
+        request_model: &str,
+        priority: usize,
+        supplier: String,
+        url_groups: BTreeMap<String, Vec<Provider>>,
+        tuning: ProbeTuning,
+    ) -> BenchmarkSupplierResult {
+        if let Some(reason) = self
+            .supplier_cooldown_reason(app_type, priority, &supplier)
+            .await
+        {
+            return BenchmarkSupplierResult {
+                priority,
+                supplier,
+                request_model: Some(request_model.to_string()),
+                effective_model: None,
+                chosen_url: None,
+                chosen_kind: "COOLDOWN".to_string(),
+                metric_ms: None,
+                urls: Vec::new(),
+                cooldown_reason: Some(reason.as_str().to_string()),
+            };
+        }
 
-                    urls.push(BenchmarkUrlResult {
-                        url: d.url.clone(),
-                        kind,
-                        latency_ms,
-                        penalty_ms,
-                        message,
-                        reason,
-                    });
-                }
+        let details = self
+            .benchmark_urls_detailed(app_type, priority, request_model, &supplier, &url_groups)
+            .await;
 
-                let mut preferred: Vec<String> = Self::default_url_priority_for_supplier(&supplier)
-                    .into_iter()
-                    .map(|s| s.to_string())
-                    .collect();
-                if let Some(p) = url_groups.values().flat_map(|v| v.first()).next() {
-                    preferred.extend(Self::parse_url_priority_from_provider(p));
-                }
-                {
-                    let mut seen = std::collections::HashMap::<String, ()>::new();
-                    preferred.retain(|u| seen.insert(u.to_string(), ()).is_none());
-                }
+        let mut urls: Vec<BenchmarkUrlResult> = Vec::with_capacity(details.len());
+        for d in details.iter() {
+            let (kind, latency_ms, penalty_ms, message, reason) = match &d.kind {
+                UrlProbeKind::FullOk { latency_ms } => (
+                    "OK".to_string(),
+                    Some(*latency_ms),
+                    None,
+                    None,
+                    None,
+                ),
+                UrlProbeKind::ModelsOk { latency_ms } => (
+                    "MO".to_string(),
+                    Some(*latency_ms),
+                    None,
+                    None,
+                    None,
+                ),
+                UrlProbeKind::Overloaded { latency_ms, message } => (
+                    "OV".to_string(),
+                    Some(*latency_ms),
+                    Some(tuning.penalty_ms),
+                    Some(message.clone()),
+                    None,
+                ),
+                UrlProbeKind::FallbackOk {
+                    connect_ms,
+                    penalty_ms,
+                    reason,
+                } => (
+                    "FB".to_string(),
+                    Some(*connect_ms),
+                    Some(*penalty_ms),
+                    None,
+                    Some(reason.clone()),
+                ),
+                UrlProbeKind::Failed { reason } => (
+                    "FAIL".to_string(),
+                    None,
+                    None,
+                    None,
+                    Some(reason.clone()),
+                ),
+            };
 
-                let preferred_ok = preferred.iter().find_map(|u| {
-                    details
-                        .iter()
-                        .find(|d| d.url == *u && matches!(d.kind, UrlProbeKind::FullOk { .. }))
-                });
+            urls.push(BenchmarkUrlResult {
+                url: d.url.clone(),
+                kind,
+                latency_ms,
+                penalty_ms,
+                message,
+                reason,
+            });
+        }
 
-                let pick = preferred_ok
-                    .or_else(|| {
-                        details
-                            .iter()
-                            .find(|d| matches!(d.kind, UrlProbeKind::FullOk { .. }))
-                    })
-                    .or_else(|| {
-                        details
-                            .iter()
-                            .find(|d| matches!(d.kind, UrlProbeKind::Overloaded { .. }))
-                    })
-                    .or_else(|| {
-                        details
-                            .iter()
-                            .find(|d| matches!(d.kind, UrlProbeKind::FallbackOk { .. }))
-                    });
+        let preferred = self.preferred_urls_for_supplier(
+            &supplier,
+            url_groups.values().flat_map(|v| v.first()).next(),
+        );
 
-                let (chosen_url, chosen_kind, metric_ms) = if let Some(p) = pick {
-                    match &p.kind {
-                        UrlProbeKind::FullOk { latency_ms } => {
-                            (Some(p.url.clone()), "OK".to_string(), Some(*latency_ms))
-                        }
-                        UrlProbeKind::Overloaded { latency_ms, .. } => (
-                            Some(p.url.clone()),
-                            "OV".to_string(),
-                            Some(latency_ms.saturating_add(Self::CONNECTIVITY_PENALTY_MS)),
-                        ),
-                        UrlProbeKind::FallbackOk {
-                            connect_ms,
-                            penalty_ms,
-                            ..
-                        } => (
-                            Some(p.url.clone()),
-                            "FB".to_string(),
-                            Some(connect_ms.saturating_add(*penalty_ms)),
-                        ),
-                        UrlProbeKind::Failed { .. } => (None, "FAIL".to_string(), None),
-                    }
-                } else {
-                    (None, "FAIL".to_string(), None)
-                };
+        let preferred_ok = preferred.iter().find_map(|u| {
+            details.iter().find(|d| {
+                d.url == *u
+                    && matches!(
+                        d.kind,
+                        UrlProbeKind::FullOk { .. } | UrlProbeKind::ModelsOk { .. }
+                    )
+            })
+        });
 
-                if let Some(url) = chosen_url.as_deref() {
-                    self.set_supplier_current_url(app_type, priority, &supplier, url)
-                        .await;
-                    let mut tested_map = self.priority_level_tested.write().await;
-                    tested_map.insert(Self::supplier_key(app_type, priority, &supplier), true);
-                }
+        let pick = preferred_ok
+            .or_else(|| {
+                details
+                    .iter()
+                    .find(|d| matches!(d.kind, UrlProbeKind::FullOk { .. }))
+            })
+            .or_else(|| {
+                details
+                    .iter()
+                    .find(|d| matches!(d.kind, UrlProbeKind::ModelsOk { .. }))
+            })
+            .or_else(|| {
+                details
+                    .iter()
+                    .find(|d| matches!(d.kind, UrlProbeKind::Overloaded { .. }))
+            })
+            .or_else(|| {
+                details
+                    .iter()
+                    .find(|d| matches!(d.kind, UrlProbeKind::FallbackOk { .. }))
+            });
 
-                out.push(BenchmarkSupplierResult {
-                    priority,
-                    supplier,
-                    request_model: Some(request_model.to_string()),
-                    effective_model: None,
-                    chosen_url,
-                    chosen_kind,
-                    metric_ms,
-                    urls,
-                });
+        let (chosen_url, chosen_kind, metric_ms) = if let Some(p) = pick {
+            match &p.kind {
+                UrlProbeKind::FullOk { latency_ms } => {
+                    (Some(p.url.clone()), "OK".to_string(), Some(*latency_ms))
+                }
+                UrlProbeKind::ModelsOk { latency_ms } => {
+                    (Some(p.url.clone()), "MO".to_string(), Some(*latency_ms))
+                }
+                UrlProbeKind::Overloaded { latency_ms, .. } => (
+                    Some(p.url.clone()),
+                    "OV".to_string(),
+                    Some(latency_ms.saturating_add(tuning.penalty_ms)),
+                ),
+                UrlProbeKind::FallbackOk {
+                    connect_ms,
+                    penalty_ms,
+                    ..
+                } => (
+                    Some(p.url.clone()),
+                    "FB".to_string(),
+                    Some(connect_ms.saturating_add(*penalty_ms)),
+                ),
+                UrlProbeKind::Failed { .. } => (None, "FAIL".to_string(), None),
             }
+        } else {
+            (None, "FAIL".to_string(), None)
+        };
+
+        if let Some(url) = chosen_url.as_deref() {
+            self.set_supplier_current_url(app_type, priority, &supplier, url)
+                .await;
+            let mut tested_map = self.priority_level_tested.write().await;
+            tested_map.insert(Self::supplier_key(app_type, priority, &supplier), true);
         }
 
-        Ok(out)
+        BenchmarkSupplierResult {
+            priority,
+            supplier,
+            request_model: Some(request_model.to_string()),
+            effective_model: None,
+            chosen_url,
+            chosen_kind,
+            metric_ms,
+            urls,
+            cooldown_reason: None,
+        }
+    }
+
+    /// 写入测速历史（失败只记日志，不影响测速结果；只读模式不写入）
+    fn record_benchmark_history(&self, app_type: &str, results: &[BenchmarkSupplierResult]) {
+        if self.read_only {
+            return;
+        }
+        let now = chrono::Utc::now().timestamp();
+        if let Err(e) = self.db.save_benchmark_results(app_type, results, now) {
+            log::warn!("[{app_type}] 写入测速历史失败: {e}");
+        }
     }
 }
 
@@ -2514,6 +5130,72 @@ mod tests {
         assert!(breaker.allow_request().await.allowed);
     }
 
+    #[tokio::test]
+    async fn test_all_circuit_breaker_stats_reports_each_state() {
+        use crate::proxy::circuit_breaker::{CircuitBreakerConfig, CircuitState};
+
+        let router = ProviderRouter::new(Arc::new(Database::memory().unwrap()));
+        let config = CircuitBreakerConfig {
+            failure_threshold: 2,
+            timeout_seconds: 0,
+            min_requests: 100,
+            ..Default::default()
+        };
+        let [closed, open, half, other] =
+            [(); 4].map(|_| Arc::new(CircuitBreaker::new(config.clone())));
+        {
+            let mut breakers = router.circuit_breakers.write().await;
+            for (key, breaker) in [
+                ("codex:closed", &closed),
+                ("codex:open", &open),
+                ("codex:half", &half),
+                ("claude:other", &other),
+            ] {
+                breakers.insert(key.to_string(), breaker.clone());
+            }
+        }
+
+        closed.record_success(false).await;
+        closed.record_failure(false).await;
+        open.record_failure(false).await;
+        open.record_failure(false).await;
+        // 超时为 0：打开后下一次可用性检查即进入 HalfOpen
+        half.record_failure(false).await;
+        half.record_failure(false).await;
+        assert!(half.is_available().await);
+
+        let stats = router.get_all_circuit_breaker_stats("codex").await;
+        let ids: Vec<_> = stats.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, vec!["closed", "half", "open"]);
+
+        let (_, closed) = &stats[0];
+        assert_eq!(closed.state, CircuitState::Closed);
+        assert_eq!(closed.consecutive_failures, 1);
+        assert_eq!((closed.total_requests, closed.failed_requests), (2, 1));
+        assert_eq!(closed.error_rate, 0.5);
+        assert_eq!(closed.last_transition_at, None);
+
+        let (_, half) = &stats[1];
+        assert_eq!(half.state, CircuitState::HalfOpen);
+        assert_eq!(half.consecutive_successes, 0);
+        assert!(half.last_transition_at.is_some());
+
+        let (_, open) = &stats[2];
+        assert_eq!(open.state, CircuitState::Open);
+        assert_eq!(open.consecutive_failures, 0);
+        assert_eq!(open.error_rate, 1.0);
+        assert!(open.last_transition_at.unwrap() >= chrono::Utc::now().timestamp() - 5);
+
+        assert_eq!(
+            router.get_all_circuit_breaker_stats("claude").await.len(),
+            1
+        );
+        assert!(router
+            .get_all_circuit_breaker_stats("gemini")
+            .await
+            .is_empty());
+    }
+
     #[tokio::test]
     async fn test_failover_disabled_uses_current_provider() {
         let db = Arc::new(Database::memory().unwrap());
@@ -2534,12 +5216,70 @@ mod tests {
             let mut tested = router.priority_level_tested.write().await;
             tested.insert("claude:1:anyrouter".to_string(), true);
         }
-        let providers = router.select_providers("claude", None).await.unwrap();
+        let providers = router.select_providers("claude", None, false).await.unwrap();
 
         assert_eq!(providers.len(), 1);
         assert_eq!(providers[0].id, "a");
     }
 
+    #[tokio::test]
+    async fn test_missing_provider_setup_errors_are_distinct() {
+        let db = Arc::new(Database::memory().unwrap());
+        let router = ProviderRouter::new(db.clone());
+
+        // 没有任何供应商：故障转移开关与否都提示添加
+        let err = router
+            .select_providers("codex", None, false)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, AppError::NoProvidersConfigured { .. }),
+            "{err}"
+        );
+        assert!(err.to_string().contains("csc add codex"), "{err}");
+        let mut config = db.get_proxy_config_for_app("codex").await.unwrap();
+        config.auto_failover_enabled = true;
+        db.update_proxy_config_for_app(config).await.unwrap();
+        let err = router
+            .select_providers("codex", None, false)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, AppError::NoProvidersConfigured { .. }),
+            "{err}"
+        );
+
+        // 有供应商但故障转移关闭且没有当前供应商：提示重新选择
+        let provider =
+            Provider::with_id("a".to_string(), "Provider A".to_string(), json!({}), None);
+        db.save_provider("claude", &provider).unwrap();
+        let err = router
+            .select_providers("claude", None, false)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(
+                &err,
+                AppError::CurrentProviderMissing {
+                    provider_id: None,
+                    ..
+                }
+            ),
+            "{err}"
+        );
+        assert!(err.to_string().contains("csc enable claude"), "{err}");
+
+        // 每个应用只通知一次，选路恢复后清除
+        assert!(router.setup_missing_apps.read().await.contains("claude"));
+        db.set_current_provider("claude", "a").unwrap();
+        router
+            .select_providers("claude", None, false)
+            .await
+            .unwrap();
+        assert!(!router.setup_missing_apps.read().await.contains("claude"));
+        assert!(router.setup_missing_apps.read().await.contains("codex"));
+    }
+
     #[tokio::test]
     async fn test_failover_enabled_uses_queue_order() {
         let db = Arc::new(Database::memory().unwrap());
@@ -2590,7 +5330,7 @@ mod tests {
             let mut tested = router.priority_level_tested.write().await;
             tested.insert("claude:1:anyrouter".to_string(), true);
         }
-        let providers = router.select_providers("claude", None).await.unwrap();
+        let providers = router.select_providers("claude", None, false).await.unwrap();
 
         // 返回“多层级候选链”：先给出 priority=1，再追加 priority=2（由 forwarder 在失败后推进到下一层级）
         assert_eq!(providers.len(), 2);
@@ -2599,68 +5339,2854 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_select_providers_does_not_consume_half_open_permit() {
+    async fn test_failover_pause_limits_selection_until_expiry() {
         let db = Arc::new(Database::memory().unwrap());
 
-        db.update_circuit_breaker_config(&CircuitBreakerConfig {
-            failure_threshold: 1,
-            timeout_seconds: 0,
-            ..Default::default()
-        })
-        .await
-        .unwrap();
-
-        let provider_a =
-            Provider::with_id(
-                "a".to_string(),
-                "anyrouter-key-a".to_string(),
-                json!({
-                    "env": {
-                        "ANTHROPIC_API_KEY": "sk-a",
-                        "ANTHROPIC_BASE_URL": "https://example.com"
-                    }
-                }),
-                None,
-            );
-        let provider_b =
-            Provider::with_id(
-                "b".to_string(),
-                "anyrouter-key-b".to_string(),
+        for (id, sort_index) in [("a", 2), ("b", 1)] {
+            let mut provider = Provider::with_id(
+                id.to_string(),
+                format!("anyrouter-key-{id}"),
                 json!({
                     "env": {
-                        "ANTHROPIC_API_KEY": "sk-b",
+                        "ANTHROPIC_API_KEY": format!("sk-{id}"),
                         "ANTHROPIC_BASE_URL": "https://example.com"
                     }
                 }),
                 None,
             );
+            provider.sort_index = Some(sort_index);
+            db.save_provider("claude", &provider).unwrap();
+        }
+        db.set_current_provider("claude", "a").unwrap();
+        db.add_to_failover_queue("claude", "b").unwrap();
+        db.add_to_failover_queue("claude", "a").unwrap();
+
+        let mut config = db.get_proxy_config_for_app("claude").await.unwrap();
+        config.auto_failover_enabled = true;
+        db.update_proxy_config_for_app(config).await.unwrap();
+
+        let router = ProviderRouter::new(db.clone());
+        {
+            let mut tested = router.priority_level_tested.write().await;
+            tested.insert("claude:1:anyrouter".to_string(), true);
+        }
+
+        // 暂停期间只使用当前供应商
+        router.pause_failover("claude", Duration::from_millis(200));
+        let pauses = router.failover_pauses();
+        assert_eq!(pauses.len(), 1);
+        assert_eq!(pauses[0].app_type, "claude");
+        assert!(router.failover_pause_remaining("codex").is_none());
+
+        let providers = router
+            .select_providers("claude", None, false)
+            .await
+            .unwrap();
+        assert_eq!(providers.len(), 1);
+        assert_eq!(providers[0].id, "a");
+
+        // 到期后自动恢复正常故障转移链
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert!(router.failover_pauses().is_empty());
+        let providers = router
+            .select_providers("claude", None, false)
+            .await
+            .unwrap();
+        assert_eq!(providers.len(), 2);
+        assert_eq!(providers[0].id, "b");
+
+        // 手动恢复：仅在处于暂停状态时返回 true
+        router.pause_failover("claude", Duration::from_secs(60));
+        assert!(router.resume_failover("claude"));
+        assert!(!router.resume_failover("claude"));
+        let providers = router
+            .select_providers("claude", None, false)
+            .await
+            .unwrap();
+        assert_eq!(providers.len(), 2);
+
+        // 超长时长按上限处理，不会溢出 panic
+        let until = router.pause_failover("claude", Duration::MAX);
+        let remaining = router.failover_pause_remaining("claude").unwrap();
+        assert!(remaining <= Duration::from_secs(MAX_FAILOVER_PAUSE_SECS));
+        assert!(until <= chrono::Utc::now().timestamp() + MAX_FAILOVER_PAUSE_SECS as i64);
+        assert!(router.resume_failover("claude"));
+    }
+
+    #[tokio::test]
+    async fn test_thinking_request_moves_incapable_providers_to_the_end() {
+        let db = Arc::new(Database::memory().unwrap());
+
+        // b 排在前面但已标记不支持 thinking
+        let mut provider_a = Provider::with_id(
+            "a".to_string(),
+            "anyrouter-key-a".to_string(),
+            json!({
+                "env": {
+                    "ANTHROPIC_API_KEY": "sk-a",
+                    "ANTHROPIC_BASE_URL": "https://example.com"
+                }
+            }),
+            None,
+        );
+        provider_a.sort_index = Some(2);
+        let mut provider_b = Provider::with_id(
+            "b".to_string(),
+            "anyrouter-key-b".to_string(),
+            json!({
+                "env": {
+                    "ANTHROPIC_API_KEY": "sk-b",
+                    "ANTHROPIC_BASE_URL": "https://example.com"
+                },
+                "supportsThinking": false
+            }),
+            None,
+        );
+        provider_b.sort_index = Some(1);
 
         db.save_provider("claude", &provider_a).unwrap();
         db.save_provider("claude", &provider_b).unwrap();
-
-        db.add_to_failover_queue("claude", "a").unwrap();
+        db.set_current_provider("claude", "a").unwrap();
         db.add_to_failover_queue("claude", "b").unwrap();
+        db.add_to_failover_queue("claude", "a").unwrap();
 
-        // 启用自动故障转移（使用新的 proxy_config API）
         let mut config = db.get_proxy_config_for_app("claude").await.unwrap();
         config.auto_failover_enabled = true;
         db.update_proxy_config_for_app(config).await.unwrap();
 
         let router = ProviderRouter::new(db.clone());
+        {
+            let mut tested = router.priority_level_tested.write().await;
+            tested.insert("claude:1:anyrouter".to_string(), true);
+        }
 
-        router
-            .record_result("b", "claude", false, false, Some("fail".to_string()))
+        let plain = router
+            .select_providers("claude", None, false)
             .await
             .unwrap();
+        let ids: Vec<_> = plain.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, vec!["b", "a"]);
 
-        // 单元测试不跑真实网络测速：手动标记该供应商已测试过URL
-        {
-            let mut tested = router.priority_level_tested.write().await;
-            tested.insert("claude:999999:anyrouter".to_string(), true);
+        let thinking = router.select_providers("claude", None, true).await.unwrap();
+        let ids: Vec<_> = thinking.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn test_request_scope_reads_config_once_per_request() {
+        let db = Arc::new(Database::memory().unwrap());
+        for id in ["a", "b"] {
+            let mut provider = Provider::with_id(
+                id.to_string(),
+                format!("anyrouter-key-{id}"),
+                json!({
+                    "env": {
+                        "ANTHROPIC_API_KEY": format!("sk-{id}"),
+                        "ANTHROPIC_BASE_URL": "https://example.com"
+                    }
+                }),
+                None,
+            );
+            provider.sort_index = Some(1);
+            db.save_provider("claude", &provider).unwrap();
+            db.add_to_failover_queue("claude", id).unwrap();
         }
-        let providers = router.select_providers("claude", None).await.unwrap();
-        assert_eq!(providers.len(), 2);
+        db.set_current_provider("claude", "a").unwrap();
+        let mut config = db.get_proxy_config_for_app("claude").await.unwrap();
+        config.auto_failover_enabled = true;
+        db.update_proxy_config_for_app(config).await.unwrap();
+
+        let router = ProviderRouter::new(db.clone());
+        {
+            let mut tested = router.priority_level_tested.write().await;
+            tested.insert("claude:1:anyrouter".to_string(), true);
+        }
+
+        // 一次请求：选路 → 首个供应商失败 → 第二个成功（熔断器均为首次创建）
+        async fn request(router: &ProviderRouter) {
+            let providers = router
+                .select_providers("claude", None, false)
+                .await
+                .unwrap();
+            assert_eq!(providers.len(), 2);
+            for (i, provider) in providers.iter().enumerate() {
+                router.allow_provider_request(&provider.id, "claude").await;
+                router
+                    .record_result(&provider.id, "claude", false, i == 1, None, None)
+                    .await
+                    .unwrap();
+            }
+        }
+
+        let before = db.dao_reads("get_proxy_config_for_app");
+        let scope = RequestScope::load(&db, "claude").await.unwrap();
+        scope.run(request(&router)).await;
+        assert_eq!(db.dao_reads("get_proxy_config_for_app") - before, 1);
+
+        // 快照之外（测速、CLI）照常读库
+        let before = db.dao_reads("get_proxy_config_for_app");
+        request(&router).await;
+        assert!(db.dao_reads("get_proxy_config_for_app") - before > 1);
+
+        // 请求之间的配置变更在下一个请求的快照中生效
+        let mut config = db.get_proxy_config_for_app("claude").await.unwrap();
+        config.auto_failover_enabled = false;
+        db.update_proxy_config_for_app(config).await.unwrap();
+        let scope = RequestScope::load(&db, "claude").await.unwrap();
+        let before = db.dao_reads("get_current_provider");
+        let providers = scope
+            .run(router.select_providers("claude", None, false))
+            .await
+            .unwrap();
+        assert_eq!(providers.len(), 1);
+        assert_eq!(providers[0].id, "a");
+        assert_eq!(db.dao_reads("get_current_provider"), before);
+    }
+
+    #[tokio::test]
+    async fn test_database_locked_serves_last_good_providers() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("cc-switch.db");
+        let db = Arc::new(Database::open(&path).unwrap());
+        db.set_busy_timeout(Duration::from_millis(50));
+        let provider = Provider::with_id(
+            "a".to_string(),
+            "a".to_string(),
+            json!({
+                "env": {
+                    "ANTHROPIC_API_KEY": "sk-a",
+                    "ANTHROPIC_BASE_URL": "https://example.com"
+                }
+            }),
+            None,
+        );
+        db.save_provider("claude", &provider).unwrap();
+        db.set_current_provider("claude", "a").unwrap();
+        let codex = Provider::with_id(
+            "c".to_string(),
+            "c".to_string(),
+            json!({ "auth": { "OPENAI_API_KEY": "sk-c" } }),
+            None,
+        );
+        db.save_provider("codex", &codex).unwrap();
+        db.set_current_provider("codex", "c").unwrap();
+
+        let router = ProviderRouter::new(db.clone());
+        async fn request_app(
+            router: &ProviderRouter,
+            app_type: &str,
+        ) -> Result<Vec<Provider>, AppError> {
+            let scope = router.load_request_scope(app_type).await?;
+            scope
+                .run(router.select_providers(app_type, None, false))
+                .await
+        }
+        async fn request(router: &ProviderRouter) -> Result<Vec<Provider>, AppError> {
+            request_app(router, "claude").await
+        }
+
+        // 数据库可用且尚无缓存时，锁定直接报错
+        let locker = rusqlite::Connection::open(&path).unwrap();
+        locker.execute_batch("BEGIN EXCLUSIVE").unwrap();
+        let err = request(&router).await.unwrap_err();
+        assert!(err.is_database_unavailable(), "{err}");
+        assert!(!router.is_db_degraded());
+        locker.execute_batch("ROLLBACK").unwrap();
+
+        let providers = request(&router).await.unwrap();
+        assert_eq!(providers[0].id, "a");
+        assert!(!router.is_db_degraded());
+
+        // 另一个连接持有排他事务：请求沿用最近一次的供应商列表
+        locker.execute_batch("BEGIN EXCLUSIVE").unwrap();
+        let providers = request(&router).await.unwrap();
+        assert_eq!(providers.len(), 1);
+        assert_eq!(providers[0].id, "a");
+        assert!(router.is_db_degraded());
+        assert!(router.select_providers("claude", None, false).await.is_ok());
+
+        assert_eq!(router.db_degraded_apps(), vec!["claude"]);
+
+        // 锁释放后恢复正常读取
+        locker.execute_batch("COMMIT").unwrap();
+        request(&router).await.unwrap();
+        assert!(!router.is_db_degraded());
+
+        // 降级按应用独立：各应用在自己的下一次成功选路后才恢复
+        request_app(&router, "codex").await.unwrap();
+        locker.execute_batch("BEGIN EXCLUSIVE").unwrap();
+        request(&router).await.unwrap();
+        request_app(&router, "codex").await.unwrap();
+        assert_eq!(router.db_degraded_apps(), vec!["claude", "codex"]);
+        locker.execute_batch("COMMIT").unwrap();
+        request(&router).await.unwrap();
+        assert_eq!(router.db_degraded_apps(), vec!["codex"]);
+        assert!(router.is_db_degraded());
+        request_app(&router, "codex").await.unwrap();
+        assert!(!router.is_db_degraded());
+    }
+
+    #[tokio::test]
+    async fn test_select_providers_does_not_consume_half_open_permit() {
+        let db = Arc::new(Database::memory().unwrap());
+
+        db.update_circuit_breaker_config(&CircuitBreakerConfig {
+            failure_threshold: 1,
+            timeout_seconds: 0,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let provider_a =
+            Provider::with_id(
+                "a".to_string(),
+                "anyrouter-key-a".to_string(),
+                json!({
+                    "env": {
+                        "ANTHROPIC_API_KEY": "sk-a",
+                        "ANTHROPIC_BASE_URL": "https://example.com"
+                    }
+                }),
+                None,
+            );
+        let provider_b =
+            Provider::with_id(
+                "b".to_string(),
+                "anyrouter-key-b".to_string(),
+                json!({
+                    "env": {
+                        "ANTHROPIC_API_KEY": "sk-b",
+                        "ANTHROPIC_BASE_URL": "https://example.com"
+                    }
+                }),
+                None,
+            );
+
+        db.save_provider("claude", &provider_a).unwrap();
+        db.save_provider("claude", &provider_b).unwrap();
+
+        db.add_to_failover_queue("claude", "a").unwrap();
+        db.add_to_failover_queue("claude", "b").unwrap();
+
+        // 启用自动故障转移（使用新的 proxy_config API）
+        let mut config = db.get_proxy_config_for_app("claude").await.unwrap();
+        config.auto_failover_enabled = true;
+        db.update_proxy_config_for_app(config).await.unwrap();
+
+        let router = ProviderRouter::new(db.clone());
+
+        router
+            .record_result("b", "claude", false, false, Some("fail".to_string()), None)
+            .await
+            .unwrap();
+
+        // 单元测试不跑真实网络测速：手动标记该供应商已测试过URL
+        {
+            let mut tested = router.priority_level_tested.write().await;
+            tested.insert(
+                format!("claude:{}:anyrouter", crate::provider::DEFAULT_PRIORITY),
+                true,
+            );
+        }
+        let providers = router.select_providers("claude", None, false).await.unwrap();
+        assert_eq!(providers.len(), 2);
 
         assert!(router.allow_provider_request("b", "claude").await.allowed);
     }
+
+    #[tokio::test]
+    async fn test_breaker_transitions_recorded_as_events() {
+        use crate::database::EventKind;
+
+        let db = Arc::new(Database::memory().unwrap());
+        let provider = Provider::with_id(
+            "a".to_string(),
+            "anyrouter-key-a".to_string(),
+            json!({ "env": { "ANTHROPIC_API_KEY": "sk-a" } }),
+            None,
+        );
+        db.save_provider("claude", &provider).unwrap();
+
+        let router =
+            ProviderRouter::new(db.clone()).with_event_recorder(EventRecorder::spawn(db.clone()));
+        for _ in 0..20 {
+            router
+                .record_result(
+                    "a",
+                    "claude",
+                    false,
+                    false,
+                    Some("HTTP 500: boom".to_string()),
+                    None,
+                )
+                .await
+                .unwrap();
+        }
+        router.force_open_provider("claude", "a", 60).await;
+        router.reset_provider_breaker("a", "claude").await;
+        router.event_recorder().flush().await;
+
+        let events = db.query_events("claude", None, 0, 100).unwrap();
+        let kinds: Vec<_> = events.iter().rev().map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                EventKind::BreakerOpen,
+                EventKind::BreakerForced,
+                EventKind::BreakerClose
+            ]
+        );
+        let opened = events.last().unwrap();
+        assert_eq!(opened.provider_id.as_deref(), Some("a"));
+        assert_eq!(opened.detail["error"], json!("HTTP 500: boom"));
+        assert_eq!(events[1].detail["secs"], json!(60));
+        assert_eq!(events[0].detail["manual"], json!(true));
+
+        let only_open = db
+            .query_events("claude", Some(EventKind::BreakerOpen), 0, 100)
+            .unwrap();
+        assert_eq!(only_open.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_force_open_provider_skips_until_closed() {
+        let db = Arc::new(Database::memory().unwrap());
+        for id in ["a", "b"] {
+            let provider = Provider::with_id(
+                id.to_string(),
+                format!("anyrouter-key-{id}"),
+                json!({
+                    "env": {
+                        "ANTHROPIC_API_KEY": format!("sk-{id}"),
+                        "ANTHROPIC_BASE_URL": "https://example.com"
+                    }
+                }),
+                None,
+            );
+            db.save_provider("claude", &provider).unwrap();
+            db.add_to_failover_queue("claude", id).unwrap();
+        }
+        let mut config = db.get_proxy_config_for_app("claude").await.unwrap();
+        config.auto_failover_enabled = true;
+        db.update_proxy_config_for_app(config).await.unwrap();
+
+        let router = ProviderRouter::new(db.clone());
+        {
+            let mut tested = router.priority_level_tested.write().await;
+            tested.insert(
+                format!("claude:{}:anyrouter", crate::provider::DEFAULT_PRIORITY),
+                true,
+            );
+        }
+
+        router.force_open_provider("claude", "a", 1800).await;
+
+        // 强制熔断期间：选路跳过 a，直接请求也被拒绝，成功结果不会使其恢复
+        let providers = router
+            .select_providers("claude", None, false)
+            .await
+            .unwrap();
+        let ids: Vec<_> = providers.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, vec!["b"]);
+        router
+            .record_result("a", "claude", false, true, None, None)
+            .await
+            .unwrap();
+        let permit = router.allow_provider_request("a", "claude").await;
+        assert!(!permit.allowed);
+        assert_eq!(
+            permit.rejection,
+            Some(crate::proxy::circuit_breaker::BreakerRejection::Open)
+        );
+
+        let stats = router
+            .get_circuit_breaker_stats("a", "claude")
+            .await
+            .unwrap();
+        assert!(stats.forced);
+        assert!(stats.forced_until.unwrap() > chrono::Utc::now().timestamp() + 1700);
+
+        // 手动解除后恢复参与选路
+        router.reset_provider_breaker("a", "claude").await;
+        let providers = router
+            .select_providers("claude", None, false)
+            .await
+            .unwrap();
+        assert_eq!(providers.len(), 2);
+        let stats = router
+            .get_circuit_breaker_stats("a", "claude")
+            .await
+            .unwrap();
+        assert!(!stats.forced);
+        assert_eq!(stats.forced_until, None);
+    }
+
+    #[tokio::test]
+    async fn test_strict_primary_policy_pins_current_supplier() {
+        let db = Arc::new(Database::memory().unwrap());
+        for (id, supplier) in [("a", "alpha"), ("b", "beta")] {
+            let provider = Provider::with_id(
+                id.to_string(),
+                format!("{supplier}-key-{id}"),
+                json!({
+                    "env": {
+                        "ANTHROPIC_API_KEY": format!("sk-{id}"),
+                        "ANTHROPIC_BASE_URL": format!("https://{supplier}.example.com")
+                    }
+                }),
+                None,
+            );
+            db.save_provider("claude", &provider).unwrap();
+            db.add_to_failover_queue("claude", id).unwrap();
+        }
+        db.set_current_provider("claude", "a").unwrap();
+        let mut config = db.get_proxy_config_for_app("claude").await.unwrap();
+        config.auto_failover_enabled = true;
+        config.selection_policy = SelectionPolicyKind::StrictPrimary;
+        db.update_proxy_config_for_app(config).await.unwrap();
+
+        let router = ProviderRouter::new(db.clone());
+        {
+            let mut tested = router.priority_level_tested.write().await;
+            for supplier in ["alpha", "beta"] {
+                tested.insert(
+                    format!("claude:{}:{supplier}", crate::provider::DEFAULT_PRIORITY),
+                    true,
+                );
+            }
+        }
+
+        // 主供应商可用时只返回主供应商，预览与实际选路一致
+        let ids = |providers: &[Provider]| -> Vec<String> {
+            providers.iter().map(|p| p.id.clone()).collect()
+        };
+        let providers = router
+            .select_providers("claude", None, false)
+            .await
+            .unwrap();
+        assert_eq!(ids(&providers), vec!["a"]);
+        let (_, preview) = router.preview_providers("claude").await.unwrap();
+        let preview_ids: Vec<_> = preview.iter().map(|p| p.provider.id.as_str()).collect();
+        assert_eq!(preview_ids, vec!["a"]);
+
+        // 主供应商熔断后才转移到其他供应商
+        router.force_open_provider("claude", "a", 1800).await;
+        let providers = router
+            .select_providers("claude", None, false)
+            .await
+            .unwrap();
+        assert_eq!(ids(&providers), vec!["b"]);
+    }
+
+    #[tokio::test]
+    async fn test_preview_follows_selection_without_side_effects() {
+        let db = Arc::new(Database::memory().unwrap());
+        for id in ["a", "b"] {
+            let provider = Provider::with_id(
+                id.to_string(),
+                format!("alpha-key-{id}"),
+                json!({
+                    "env": {
+                        "ANTHROPIC_API_KEY": format!("sk-{id}"),
+                        "ANTHROPIC_BASE_URL": "https://alpha.example.com"
+                    }
+                }),
+                None,
+            );
+            db.save_provider("claude", &provider).unwrap();
+            db.add_to_failover_queue("claude", id).unwrap();
+        }
+        db.set_current_provider("claude", "a").unwrap();
+        let mut config = db.get_proxy_config_for_app("claude").await.unwrap();
+        config.auto_failover_enabled = true;
+        db.update_proxy_config_for_app(config).await.unwrap();
+
+        let router = ProviderRouter::new(db.clone());
+        let ids = |providers: &[Provider]| -> Vec<String> {
+            providers.iter().map(|p| p.id.clone()).collect()
+        };
+        let preview_ids = |preview: &[SelectionPreview]| -> Vec<String> {
+            preview.iter().map(|p| p.provider.id.clone()).collect()
+        };
+
+        // 重复预览结果不变（不推进轮询），且与随后的实际选路一致
+        let (enabled, first) = router.preview_providers("claude").await.unwrap();
+        assert!(enabled);
+        assert_eq!(first[0].url_source, "only");
+        let (_, second) = router.preview_providers("claude").await.unwrap();
+        assert_eq!(preview_ids(&first), preview_ids(&second));
+        let chain = router
+            .select_providers("claude", None, false)
+            .await
+            .unwrap();
+        assert_eq!(ids(&chain), preview_ids(&first));
+
+        // 实际选路推进轮询后，预览跟随新的顺序
+        let (_, rotated) = router.preview_providers("claude").await.unwrap();
+        assert_ne!(preview_ids(&rotated), preview_ids(&first));
+
+        // 暂停故障转移期间只预览当前供应商
+        router.pause_failover("claude", Duration::from_secs(60));
+        let (enabled, paused) = router.preview_providers("claude").await.unwrap();
+        assert!(!enabled);
+        assert_eq!(preview_ids(&paused), vec!["a"]);
+        assert_eq!(paused[0].url_source, "current-provider");
+    }
+
+    /// 模拟 Codex 上游：记录 POST 次数；`/v1/models` 按 key 返回 200 或 401
+    async fn spawn_models_upstream() -> (String, Arc<std::sync::atomic::AtomicUsize>) {
+        use axum::{
+            http::{HeaderMap, StatusCode},
+            routing::{get, post},
+            Json, Router,
+        };
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let posts = Arc::new(AtomicUsize::new(0));
+        let counter = posts.clone();
+        let app = Router::new()
+            .route(
+                "/v1/models",
+                get(|headers: HeaderMap| async move {
+                    let auth = headers
+                        .get("authorization")
+                        .and_then(|v| v.to_str().ok())
+                        .unwrap_or_default();
+                    if auth == "Bearer sk-good" {
+                        Ok(Json(json!({"data": [{"id": "gpt-5.2"}]})))
+                    } else {
+                        Err(StatusCode::UNAUTHORIZED)
+                    }
+                }),
+            )
+            .route("/models", get(|| async { StatusCode::UNAUTHORIZED }))
+            .route(
+                "/v1/responses",
+                post(move || async move {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    StatusCode::OK
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+        (format!("http://{addr}"), posts)
+    }
+
+    fn codex_provider(id: &str, base_url: &str, key: &str) -> Provider {
+        Provider::with_id(
+            id.to_string(),
+            id.to_string(),
+            json!({
+                "base_url": base_url,
+                "env": { "OPENAI_API_KEY": key }
+            }),
+            None,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_models_probe_mode_skips_completion() {
+        use std::sync::atomic::Ordering;
+
+        let (url, posts) = spawn_models_upstream().await;
+        let router = ProviderRouter::new(Arc::new(Database::memory().unwrap()));
+
+        let mut groups = BTreeMap::new();
+        groups.insert(url.clone(), vec![codex_provider("p1", &url, "sk-good")]);
+        let details = router
+            .benchmark_urls_detailed_impl(
+                "codex",
+                1,
+                "gpt-5.2",
+                "p1",
+                &groups,
+                false,
+                ProbeMode::Models,
+            )
+            .await;
+        assert_eq!(details.len(), 1);
+        // 只确认能列出模型，不算全链路 OK
+        assert!(matches!(details[0].kind, UrlProbeKind::ModelsOk { .. }));
+
+        // 鉴权失败：直接判定失败，不回退连通性
+        let mut groups = BTreeMap::new();
+        groups.insert(url.clone(), vec![codex_provider("p2", &url, "sk-bad")]);
+        let details = router
+            .benchmark_urls_detailed_impl(
+                "codex",
+                1,
+                "gpt-5.2",
+                "p2",
+                &groups,
+                false,
+                ProbeMode::Models,
+            )
+            .await;
+        match &details[0].kind {
+            UrlProbeKind::Failed { reason } => assert!(reason.contains("鉴权失败 HTTP 401")),
+            other => panic!("unexpected probe result: {other:?}"),
+        }
+
+        assert_eq!(posts.load(Ordering::SeqCst), 0);
+
+        // full 模式仍然发送问答请求
+        let mut groups = BTreeMap::new();
+        groups.insert(url.clone(), vec![codex_provider("p3", &url, "sk-good")]);
+        router
+            .benchmark_urls_detailed_impl(
+                "codex",
+                1,
+                "gpt-5.2",
+                "p3",
+                &groups,
+                false,
+                ProbeMode::Full,
+            )
+            .await;
+        assert_eq!(posts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_recent_success_skips_probe() {
+        use std::sync::atomic::Ordering;
+
+        let (url, posts) = spawn_models_upstream().await;
+        let db = Arc::new(Database::memory().unwrap());
+        db.conn
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT INTO proxy_request_logs (request_id, provider_id, app_type, model,
+                 latency_ms, status_code, created_at)
+                 VALUES ('r1', 'p1', 'codex', 'gpt-5.2', 420, 200, ?1)",
+                [chrono::Utc::now().timestamp() - 30],
+            )
+            .unwrap();
+        let router = ProviderRouter::new(db.clone());
+
+        let mut groups = BTreeMap::new();
+        groups.insert(url.clone(), vec![codex_provider("p1", &url, "sk-good")]);
+        let probe = |force| {
+            router.benchmark_urls_detailed_impl(
+                "codex",
+                1,
+                "gpt-5.2",
+                "p1",
+                &groups,
+                force,
+                ProbeMode::Full,
+            )
+        };
+
+        // 30 秒前有成功请求：直接采用日志中的延迟，不发送探测
+        let details = probe(false).await;
+        assert!(matches!(
+            details[0].kind,
+            UrlProbeKind::FullOk { latency_ms: 420 }
+        ));
+        assert_eq!(posts.load(Ordering::SeqCst), 0);
+
+        // URL 失效后的重新测速不跳过
+        probe(true).await;
+        assert_eq!(posts.load(Ordering::SeqCst), 1);
+
+        // 阈值短于最近一次成功请求：照常探测
+        let mut config = db.get_proxy_config_for_app("codex").await.unwrap();
+        config.probe_skip_recent_success_secs = 10;
+        db.update_proxy_config_for_app(config).await.unwrap();
+        probe(false).await;
+        assert_eq!(posts.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_probe_pacing_spaces_same_supplier() {
+        let router = ProviderRouter::new(Arc::new(Database::memory().unwrap()));
+        let interval = Duration::from_millis(150);
+
+        let started = std::time::Instant::now();
+        for _ in 0..3 {
+            router.pace_probe("codex", "a", interval).await;
+        }
+        assert!(started.elapsed() >= interval * 2);
+
+        // 其它供应商、其它应用不受影响
+        let started = std::time::Instant::now();
+        router.pace_probe("codex", "b", interval).await;
+        router.pace_probe("claude", "a", interval).await;
+        assert!(started.elapsed() < interval);
+
+        // 并发探测同一供应商依次顺延
+        let started = std::time::Instant::now();
+        let mut offsets = futures::future::join_all((0..3).map(|_| async {
+            router.pace_probe("gemini", "a", interval).await;
+            started.elapsed()
+        }))
+        .await;
+        offsets.sort();
+        assert!(
+            offsets[1] >= interval && offsets[2] >= interval * 2,
+            "{offsets:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_probe_concurrency_is_capped() {
+        let router = ProviderRouter::new(Arc::new(Database::memory().unwrap()));
+        let mut permits = Vec::new();
+        for i in 0..ProviderRouter::MAX_CONCURRENT_PROBES {
+            permits.push(
+                router
+                    .pace_probe("codex", &format!("s{i}"), Duration::ZERO)
+                    .await,
+            );
+        }
+        let blocked = tokio::time::timeout(
+            Duration::from_millis(100),
+            router.pace_probe("codex", "extra", Duration::ZERO),
+        )
+        .await;
+        assert!(blocked.is_err());
+
+        permits.pop();
+        let permit = tokio::time::timeout(
+            Duration::from_millis(100),
+            router.pace_probe("codex", "extra", Duration::ZERO),
+        )
+        .await
+        .unwrap();
+        assert!(permit.is_some());
+    }
+
+    /// 模拟 Azure OpenAI：记录收到的（部署, api-version, api-key）
+    async fn spawn_azure_upstream() -> (String, Arc<std::sync::Mutex<Vec<(String, String, String)>>>)
+    {
+        use axum::{
+            extract::{Path, Query},
+            http::HeaderMap,
+            routing::post,
+            Json, Router,
+        };
+
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let record = seen.clone();
+        let app = Router::new().route(
+            "/openai/deployments/:deployment/chat/completions",
+            post(
+                move |Path(deployment): Path<String>,
+                      Query(query): Query<HashMap<String, String>>,
+                      headers: HeaderMap| async move {
+                    let api_key = headers
+                        .get("api-key")
+                        .and_then(|v| v.to_str().ok())
+                        .unwrap_or_default()
+                        .to_string();
+                    let api_version = query.get("api-version").cloned().unwrap_or_default();
+                    record
+                        .lock()
+                        .unwrap()
+                        .push((deployment, api_version, api_key));
+                    Json(json!({"choices": [{"message": {"content": "pong"}}]}))
+                },
+            ),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+        (format!("http://{addr}"), seen)
+    }
+
+    #[tokio::test]
+    async fn test_azure_probe_uses_deployment_url() {
+        let (url, seen) = spawn_azure_upstream().await;
+        let router = ProviderRouter::new(Arc::new(Database::memory().unwrap()));
+        let provider = Provider::with_id(
+            "az".to_string(),
+            "az".to_string(),
+            json!({
+                "base_url": url,
+                "env": { "OPENAI_API_KEY": "az-key" },
+                "azure": {
+                    "deploymentMap": { "gpt-4o": "prod-4o", "gpt-4o-mini": "prod-mini" },
+                    "apiVersion": "2024-10-21"
+                }
+            }),
+            None,
+        );
+
+        router
+            .test_url_latency(&provider, "codex", "gpt-4o-mini")
+            .await
+            .expect("azure probe");
+        // 探测模型不在映射中：使用首个部署
+        router
+            .test_url_latency(&provider, "codex", "gpt-5.2")
+            .await
+            .expect("azure probe fallback");
+        // models 模式：Azure 没有 /v1/models，同样走部署
+        let result = router
+            .verify_provider_with_mode(&provider, "codex", ProbeMode::Models)
+            .await;
+        assert!(matches!(result.detail.kind, UrlProbeKind::ModelsOk { .. }));
+
+        let seen = seen.lock().unwrap();
+        let expected = |deployment: &str| {
+            (
+                deployment.to_string(),
+                "2024-10-21".to_string(),
+                "az-key".to_string(),
+            )
+        };
+        assert_eq!(
+            *seen,
+            vec![
+                expected("prod-mini"),
+                expected("prod-4o"),
+                expected("prod-4o")
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bedrock_probe_sends_signed_one_token_invoke() {
+        use axum::{extract::Path, http::HeaderMap, routing::post, Json, Router};
+
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let record = seen.clone();
+        let handler = move |Path(model_id): Path<String>,
+                            headers: HeaderMap,
+                            Json(body): Json<Value>| async move {
+            let signed = headers
+                .get("authorization")
+                .and_then(|v| v.to_str().ok())
+                .is_some_and(|v| v.starts_with("AWS4-HMAC-SHA256 Credential=AKID/"));
+            record.lock().unwrap().push((model_id, signed, body));
+            Json(json!({"type": "message", "content": []}))
+        };
+        let app = Router::new().route("/model/:model_id/invoke", post(handler));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+
+        let endpoint = format!("http://{addr}");
+        let provider = Provider::with_id(
+            "br".to_string(),
+            "br".to_string(),
+            json!({
+                "bedrock": {
+                    "region": "us-east-1",
+                    "accessKeyId": "AKID",
+                    "secretAccessKey": "secret",
+                    "endpoint": endpoint
+                }
+            }),
+            None,
+        );
+        assert_eq!(
+            ProviderRouter::extract_base_url(&provider, "claude"),
+            Some(endpoint)
+        );
+
+        let router = ProviderRouter::new(Arc::new(Database::memory().unwrap()));
+        router
+            .test_url_latency(&provider, "claude", "claude-3-5-haiku-20241022")
+            .await
+            .expect("bedrock probe");
+
+        let seen = seen.lock().unwrap();
+        let (model_id, signed, body) = &seen[0];
+        assert_eq!(model_id, "anthropic.claude-3-5-haiku-20241022-v1:0");
+        assert!(signed);
+        assert_eq!(body["max_tokens"], 1);
+        assert_eq!(body["anthropic_version"], "bedrock-2023-05-31");
+        assert!(body.get("model").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_vertex_probe_sends_bearer_one_token_request() {
+        use crate::proxy::providers::vertex_test_support::{
+            spawn_token_endpoint, write_service_account,
+        };
+        use axum::{extract::Path, http::HeaderMap, routing::post, Json, Router};
+
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let record = seen.clone();
+        let handler = move |Path(model): Path<String>,
+                            headers: HeaderMap,
+                            Json(body): Json<Value>| async move {
+            let authorization = headers
+                .get("authorization")
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string);
+            record.lock().unwrap().push((model, authorization, body));
+            Json(json!({"candidates": []}))
+        };
+        let app = Router::new().route(
+            "/v1/projects/test-project/locations/us-central1/publishers/google/models/:model",
+            post(handler),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let (token_uri, _) = spawn_token_endpoint(3600).await;
+        let endpoint = format!("http://{addr}");
+        let provider = Provider::with_id(
+            "vx".to_string(),
+            "vx".to_string(),
+            json!({
+                "vertex": {
+                    "serviceAccountJsonPath": write_service_account(dir.path(), &token_uri),
+                    "project": "test-project",
+                    "location": "us-central1",
+                    "endpoint": endpoint
+                }
+            }),
+            None,
+        );
+        assert_eq!(
+            ProviderRouter::extract_base_url(&provider, "gemini"),
+            Some(endpoint)
+        );
+
+        let router = ProviderRouter::new(Arc::new(Database::memory().unwrap()));
+        router
+            .test_url_latency(&provider, "gemini", "gemini-2.5-flash")
+            .await
+            .expect("vertex probe");
+
+        let seen = seen.lock().unwrap();
+        let (model, authorization, body) = &seen[0];
+        assert_eq!(model, "gemini-2.5-flash:generateContent");
+        assert_eq!(authorization.as_deref(), Some("Bearer ya29.token-1"));
+        assert_eq!(body["generationConfig"]["maxOutputTokens"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_verify_provider_before_switch() {
+        use std::sync::atomic::Ordering;
+
+        let (url, posts) = spawn_models_upstream().await;
+        let router = ProviderRouter::new(Arc::new(Database::memory().unwrap()));
+
+        // 校验通过
+        let provider = codex_provider("p1", &url, "sk-good");
+        let ok = router
+            .verify_provider_with_mode(&provider, "codex", ProbeMode::Full)
+            .await;
+        assert!(matches!(ok.detail.kind, UrlProbeKind::FullOk { .. }));
+        assert!(ok.check(false).unwrap().is_none());
+        assert_eq!(posts.load(Ordering::SeqCst), 1);
+
+        // models 校验通过：单独归类为 MO，不发问答请求
+        let models_ok = router
+            .verify_provider_with_mode(&provider, "codex", ProbeMode::Models)
+            .await;
+        assert!(matches!(
+            models_ok.detail.kind,
+            UrlProbeKind::ModelsOk { .. }
+        ));
+        assert!(models_ok.describe().starts_with("MO "));
+        assert!(models_ok.check(false).unwrap().is_none());
+        assert_eq!(posts.load(Ordering::SeqCst), 1);
+
+        // 校验失败：拒绝切换
+        let provider = codex_provider("p2", &url, "sk-bad");
+        let bad = router
+            .verify_provider_with_mode(&provider, "codex", ProbeMode::Models)
+            .await;
+        assert!(!bad.passed());
+        let err = bad.check(false).unwrap_err().to_string();
+        assert!(err.contains("FAIL"), "{err}");
+        assert!(err.contains("鉴权失败 HTTP 401"), "{err}");
+
+        // --force：仅警告
+        let warning = bad.check(true).unwrap().expect("warning");
+        assert!(warning.contains("鉴权失败 HTTP 401"));
+
+        // 无法连接的 base_url
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let dead = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let provider = codex_provider("p3", &dead, "sk-good");
+        let unreachable = router
+            .verify_provider_with_mode(&provider, "codex", ProbeMode::Connectivity)
+            .await;
+        assert!(unreachable.check(false).is_err());
+
+        // 默认不发问答请求，问答探测需显式开启
+        assert_eq!(
+            ProviderRouter::verify_mode(ProbeMode::Full, false),
+            ProbeMode::Models
+        );
+        assert_eq!(
+            ProviderRouter::verify_mode(ProbeMode::Models, false),
+            ProbeMode::Models
+        );
+        assert_eq!(
+            ProviderRouter::verify_mode(ProbeMode::Connectivity, false),
+            ProbeMode::Connectivity
+        );
+        assert_eq!(
+            ProviderRouter::verify_mode(ProbeMode::Connectivity, true),
+            ProbeMode::Full
+        );
+    }
+
+    /// 本地后端形态的上游：记录 /v1/responses 请求的模型并返回 500
+    async fn spawn_local_backend_upstream() -> (String, Arc<std::sync::Mutex<Vec<String>>>) {
+        use axum::{http::StatusCode, routing::post, Json, Router};
+
+        let models = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let record = models.clone();
+        let app = Router::new().route(
+            "/v1/responses",
+            post(move |Json(body): Json<Value>| async move {
+                let model = body["model"].as_str().unwrap_or_default().to_string();
+                record.lock().unwrap().push(model);
+                (StatusCode::INTERNAL_SERVER_ERROR, "model not loaded")
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+        (format!("http://{addr}"), models)
+    }
+
+    fn local_backend_provider(id: &str, base_url: &str) -> Provider {
+        let mut provider = codex_provider(id, base_url, "ollama");
+        provider.settings_config["localBackend"] = json!(true);
+        provider.settings_config["config"] = json!("model = \"qwen2.5-coder:7b\"\n");
+        provider
+    }
+
+    #[tokio::test]
+    async fn test_local_backend_probes_with_configured_model() {
+        let (url, models) = spawn_local_backend_upstream().await;
+        let router = ProviderRouter::new(Arc::new(Database::memory().unwrap()));
+
+        let mut remote = local_backend_provider("p1", &url);
+        remote.settings_config["localBackend"] = json!(false);
+        for provider in [local_backend_provider("p1", &url), remote] {
+            assert!(router
+                .test_url_latency(&provider, "codex", "gpt-5.2")
+                .await
+                .is_err());
+        }
+        assert_eq!(*models.lock().unwrap(), vec!["qwen2.5-coder:7b", "gpt-5.2"]);
+    }
+
+    #[tokio::test]
+    async fn test_chat_fallback_probe_is_minimal_and_deterministic() {
+        use axum::{http::StatusCode, routing::post, Json, Router};
+
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let record = seen.clone();
+        let app = Router::new()
+            .route(
+                "/v1/responses",
+                post(|| async { (StatusCode::BAD_REQUEST, "openai_error: format mismatch") }),
+            )
+            .route(
+                "/v1/chat/completions",
+                post(move |Json(body): Json<Value>| async move {
+                    record.lock().unwrap().push(body);
+                    Json(json!({"choices": []}))
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+
+        let router = ProviderRouter::new(Arc::new(Database::memory().unwrap()));
+        let provider = codex_provider("p1", &format!("http://{addr}"), "sk-a");
+        router
+            .test_url_latency(&provider, "codex", "gpt-5.2")
+            .await
+            .expect("chat fallback probe");
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 1);
+        assert_eq!(seen[0]["temperature"], 0);
+        assert_eq!(seen[0]["max_tokens"], PROBE_MAX_TOKENS);
+        assert_eq!(seen[0]["messages"][0]["content"], PROBE_PROMPT);
+    }
+
+    #[tokio::test]
+    async fn test_probe_model_precedence() {
+        let db = Arc::new(Database::memory().unwrap());
+        let router = ProviderRouter::new(db.clone());
+        let mut provider = codex_provider("p1", "https://a.example.com", "sk-a");
+
+        // 无任何配置：内置默认模型；调用方指定的模型优先于应用级回退
+        assert_eq!(router.app_probe_model("codex").await, "gpt-5.2");
+        assert_eq!(
+            router.resolve_probe_model(&provider, "codex", "o3").await,
+            "o3"
+        );
+
+        // 最近一次真实请求的模型（"unknown" 视为无记录）
+        let key = last_request_summary_setting_key("codex");
+        for (model, expected) in [("unknown", "gpt-5.2"), ("gpt-5-codex", "gpt-5-codex")] {
+            let summary = LastRequestSummary {
+                model: model.to_string(),
+                ..Default::default()
+            };
+            db.set_setting(&key, &serde_json::to_string(&summary).unwrap())
+                .unwrap();
+            assert_eq!(router.app_probe_model("codex").await, expected);
+        }
+
+        // 应用级配置优先于请求记录
+        let mut config = db.get_proxy_config_for_app("codex").await.unwrap();
+        config.probe_model = Some("gpt-5-mini".to_string());
+        db.update_proxy_config_for_app(config).await.unwrap();
+        assert_eq!(router.app_probe_model("codex").await, "gpt-5-mini");
+        assert_eq!(
+            router.resolve_probe_model(&provider, "codex", "").await,
+            "gpt-5-mini"
+        );
+        // 应用级配置也优先于调用方传入的请求模型
+        assert_eq!(
+            router.resolve_probe_model(&provider, "codex", "o3").await,
+            "gpt-5-mini"
+        );
+        // 其它应用不受影响
+        assert_eq!(router.app_probe_model("gemini").await, "gemini-2.0-flash");
+
+        // 供应商级配置优先于一切
+        provider.settings_config["probeModel"] = json!(" gpt-5-nano ");
+        assert_eq!(
+            router.resolve_probe_model(&provider, "codex", "o3").await,
+            "gpt-5-nano"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_probe_payload_uses_configured_model() {
+        let (url, models) = spawn_local_backend_upstream().await;
+        let db = Arc::new(Database::memory().unwrap());
+        let router = ProviderRouter::new(db.clone());
+        let mut config = db.get_proxy_config_for_app("codex").await.unwrap();
+        config.probe_model = Some("gpt-5-mini".to_string());
+        db.update_proxy_config_for_app(config).await.unwrap();
+
+        // 健康检查（全链路）使用应用级探测模型
+        router
+            .verify_provider_with_mode(
+                &codex_provider("p1", &url, "sk-a"),
+                "codex",
+                ProbeMode::Full,
+            )
+            .await;
+        // 测速传入的模型被供应商级探测模型覆盖
+        let mut provider = codex_provider("p2", &url, "sk-b");
+        provider.settings_config["probeModel"] = json!("gpt-5-nano");
+        let mut groups = BTreeMap::new();
+        groups.insert(url.clone(), vec![provider]);
+        router
+            .benchmark_urls_detailed("codex", 1, "gpt-5.2", "p2", &groups)
+            .await;
+
+        assert_eq!(*models.lock().unwrap(), vec!["gpt-5-mini", "gpt-5-nano"]);
+    }
+
+    #[tokio::test]
+    #[serial_test::serial]
+    async fn test_claude_probe_latency_excludes_python_hop() {
+        use axum::{response::IntoResponse, routing::post, Router};
+
+        // 模拟 Python 代理：回传 40ms 的真实上游耗时，本地一跳额外耗时 80ms
+        let app = Router::new().route(
+            "/v1/messages",
+            post(|| async {
+                tokio::time::sleep(Duration::from_millis(80)).await;
+                (
+                    [(crate::proxy::python_proxy::UPSTREAM_ELAPSED_HEADER, "40")],
+                    "{}",
+                )
+                    .into_response()
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+        std::env::set_var("CC_SWITCH_PYTHON_PROXY_BASE", format!("http://{addr}"));
+
+        let router = ProviderRouter::new(Arc::new(Database::memory().unwrap()));
+        let provider = Provider::with_id(
+            "c1".to_string(),
+            "c1".to_string(),
+            json!({
+                "env": {
+                    "ANTHROPIC_BASE_URL": "https://relay.example.com",
+                    "ANTHROPIC_AUTH_TOKEN": "sk-c"
+                }
+            }),
+            None,
+        );
+        let latency = router
+            .test_url_latency(&provider, "claude", "claude-haiku-4-5")
+            .await;
+        std::env::remove_var("CC_SWITCH_PYTHON_PROXY_BASE");
+
+        assert_eq!(latency.unwrap(), 40);
+    }
+
+    #[tokio::test]
+    async fn test_local_backend_loopback_has_no_connectivity_penalty() {
+        let (url, _) = spawn_local_backend_upstream().await;
+        let router = ProviderRouter::new(Arc::new(Database::memory().unwrap()));
+
+        let mut remote = local_backend_provider("p1", &url);
+        remote.settings_config["localBackend"] = json!(false);
+        for (provider, expected) in [
+            (local_backend_provider("p1", &url), 0),
+            (remote, ProviderRouter::DEFAULT_CONNECTIVITY_PENALTY_MS),
+        ] {
+            let mut groups = BTreeMap::new();
+            groups.insert(url.clone(), vec![provider]);
+            let details = router
+                .benchmark_urls_detailed_impl(
+                    "codex",
+                    1,
+                    "gpt-5.2",
+                    "p1",
+                    &groups,
+                    false,
+                    ProbeMode::Full,
+                )
+                .await;
+            match &details[0].kind {
+                UrlProbeKind::FallbackOk { penalty_ms, .. } => assert_eq!(*penalty_ms, expected),
+                other => panic!("unexpected probe result: {other:?}"),
+            }
+        }
+
+        assert!(ProviderRouter::is_loopback_url("http://localhost:11434/v1"));
+        assert!(ProviderRouter::is_loopback_url("http://[::1]:11434"));
+        assert!(!ProviderRouter::is_loopback_url("https://api.example.com"));
+    }
+
+    #[tokio::test]
+    async fn test_connectivity_probe_mode_has_no_penalty() {
+        use std::sync::atomic::Ordering;
+
+        let (url, posts) = spawn_models_upstream().await;
+        let router = ProviderRouter::new(Arc::new(Database::memory().unwrap()));
+
+        let mut groups = BTreeMap::new();
+        groups.insert(url.clone(), vec![codex_provider("p1", &url, "sk-bad")]);
+        let details = router
+            .benchmark_urls_detailed_impl(
+                "codex",
+                1,
+                "gpt-5.2",
+                "p1",
+                &groups,
+                false,
+                ProbeMode::Connectivity,
+            )
+            .await;
+        match &details[0].kind {
+            UrlProbeKind::FallbackOk { penalty_ms, .. } => assert_eq!(*penalty_ms, 0),
+            other => panic!("unexpected probe result: {other:?}"),
+        }
+        assert_eq!(posts.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_benchmark_tries_more_keys_when_first_ones_are_overloaded() {
+        use axum::{
+            http::{HeaderMap, StatusCode},
+            routing::post,
+            Json, Router,
+        };
+
+        // 前三个 key 额度耗尽（满载），第四个可用；网关只支持 chat/completions
+        let keys = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let record = keys.clone();
+        let app = Router::new()
+            .route(
+                "/v1/responses",
+                post(|| async {
+                    (
+                        StatusCode::BAD_REQUEST,
+                        Json(json!({ "error": { "message": "openai_error" } })),
+                    )
+                }),
+            )
+            .route(
+                "/v1/chat/completions",
+                post(move |headers: HeaderMap| async move {
+                    let key = headers["authorization"]
+                        .to_str()
+                        .unwrap()
+                        .trim_start_matches("Bearer ")
+                        .to_string();
+                    record.lock().unwrap().push(key.clone());
+                    if key == "sk-4" {
+                        (StatusCode::OK, Json(json!({ "output": [] })))
+                    } else {
+                        (
+                            StatusCode::TOO_MANY_REQUESTS,
+                            Json(json!({ "error": { "message": "Rate limit exceeded" } })),
+                        )
+                    }
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+        let url = format!("http://{addr}");
+
+        let db = Arc::new(Database::memory().unwrap());
+        let mut providers = Vec::new();
+        for i in 1..=4 {
+            let mut provider = codex_provider(&format!("k{i}"), &url, &format!("sk-{i}"));
+            provider.name = format!("acme-k{i}");
+            provider.sort_index = Some(1);
+            db.save_provider("codex", &provider).unwrap();
+            db.add_to_failover_queue("codex", &provider.id).unwrap();
+            providers.push(provider);
+        }
+        let mut groups = BTreeMap::new();
+        groups.insert(url.clone(), providers);
+        let router = ProviderRouter::new(db.clone());
+        let benchmark = || {
+            router.benchmark_urls_detailed_impl(
+                "codex",
+                1,
+                "gpt-5",
+                "acme",
+                &groups,
+                false,
+                ProbeMode::Full,
+            )
+        };
+
+        // 默认上限 2：两个 key 满载后额外尝试一个，仍满载判为 OV
+        let details = benchmark().await;
+        assert!(matches!(details[0].kind, UrlProbeKind::Overloaded { .. }));
+        assert_eq!(*keys.lock().unwrap(), vec!["sk-1", "sk-2", "sk-3"]);
+
+        // 提高上限到 3：第四个 key 在额外尝试中探测成功
+        keys.lock().unwrap().clear();
+        let mut config = db.get_proxy_config_for_app("codex").await.unwrap();
+        config.auto_failover_enabled = true;
+        config.benchmark_max_keys_per_url = 3;
+        db.update_proxy_config_for_app(config).await.unwrap();
+        let details = benchmark().await;
+        assert!(matches!(details[0].kind, UrlProbeKind::FullOk { .. }));
+        assert_eq!(*keys.lock().unwrap(), vec!["sk-1", "sk-2", "sk-3", "sk-4"]);
+
+        // 测速后的首次选路优先使用探测成功的 key，之后恢复轮询
+        router
+            .priority_level_tested
+            .write()
+            .await
+            .insert("codex:1:acme".to_string(), true);
+        let first = router.select_providers("codex", None, false).await.unwrap();
+        assert_eq!(first[0].id, "k4");
+        let second = router.select_providers("codex", None, false).await.unwrap();
+        assert_eq!(second[0].id, "k2");
+    }
+
+    #[tokio::test]
+    async fn test_probe_marks_depleted_keys_from_status_and_body() {
+        use axum::{
+            http::{HeaderMap, StatusCode},
+            routing::post,
+            Json, Router,
+        };
+
+        // sk-1 返回 402；sk-2 以 400 + 余额不足提示返回；sk-3 可用
+        let app = Router::new().route(
+            "/v1/responses",
+            post(|headers: HeaderMap| async move {
+                match headers["authorization"].to_str().unwrap() {
+                    "Bearer sk-1" => (
+                        StatusCode::PAYMENT_REQUIRED,
+                        Json(json!({ "error": { "message": "Insufficient Balance" } })),
+                    ),
+                    "Bearer sk-2" => (
+                        StatusCode::BAD_REQUEST,
+                        Json(json!({ "error": { "message": "账户余额不足，请充值" } })),
+                    ),
+                    _ => (StatusCode::OK, Json(json!({ "output": [] }))),
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+        let url = format!("http://{addr}");
+
+        let db = Arc::new(Database::memory().unwrap());
+        let mut providers = Vec::new();
+        for i in 1..=3 {
+            let mut provider = codex_provider(&format!("k{i}"), &url, &format!("sk-{i}"));
+            provider.name = format!("acme-k{i}");
+            db.save_provider("codex", &provider).unwrap();
+            providers.push(provider);
+        }
+        let mut groups = BTreeMap::new();
+        groups.insert(url.clone(), providers.clone());
+        let router = ProviderRouter::new(db.clone());
+
+        // 两个 key 均余额耗尽：额外尝试第三个 key
+        let details = router
+            .benchmark_urls_detailed_impl(
+                "codex",
+                1,
+                "gpt-5",
+                "acme",
+                &groups,
+                false,
+                ProbeMode::Full,
+            )
+            .await;
+        assert!(matches!(details[0].kind, UrlProbeKind::FullOk { .. }));
+        for (id, message) in [
+            ("k1", "Insufficient Balance"),
+            ("k2", "账户余额不足，请充值"),
+        ] {
+            let health = db.get_provider_health(id, "codex").await.unwrap();
+            assert!(health.depleted_at.is_some(), "{id}");
+            assert_eq!(health.depleted_message.as_deref(), Some(message));
+        }
+        assert!(db
+            .get_provider_health("k3", "codex")
+            .await
+            .unwrap()
+            .depleted_at
+            .is_none());
+
+        let verification = router
+            .verify_provider_with_mode(&providers[0], "codex", ProbeMode::Full)
+            .await;
+        assert!(!verification.passed());
+        assert!(verification
+            .describe()
+            .contains("余额不足: Insufficient Balance"));
+    }
+
+    #[tokio::test]
+    async fn test_select_providers_honors_db_url_priority() {
+        let db = Arc::new(Database::memory().unwrap());
+        for (id, url) in [
+            ("p1", "https://a.example.com"),
+            ("p2", "https://b.example.com"),
+        ] {
+            let mut provider = codex_provider(id, url, &format!("sk-{id}"));
+            provider.name = format!("acme-{id}");
+            provider.sort_index = Some(1);
+            db.save_provider("codex", &provider).unwrap();
+            db.add_to_failover_queue("codex", id).unwrap();
+        }
+        let mut config = db.get_proxy_config_for_app("codex").await.unwrap();
+        config.auto_failover_enabled = true;
+        db.update_proxy_config_for_app(config).await.unwrap();
+
+        db.set_supplier_url_priority("ACME", &["https://b.example.com/".to_string()])
+            .unwrap();
+
+        let router = ProviderRouter::new(db.clone());
+        // 两个 URL 都有全链路缓存，a 更快；优先级应命中 b
+        {
+            let mut latencies = router.url_latencies.write().await;
+            for (url, latency_ms) in [
+                ("https://a.example.com", 100),
+                ("https://b.example.com", 500),
+            ] {
+                latencies.insert(
+                    ProviderRouter::url_latency_key("codex", 1, "acme", url),
+                    UrlLatency {
+                        latency_ms,
+                        tested_at: std::time::Instant::now(),
+                    },
+                );
+            }
+        }
+
+        let providers = router.select_providers("codex", None, false).await.unwrap();
+        assert_eq!(providers.len(), 1);
+        assert_eq!(providers[0].id, "p2");
+    }
+
+    #[test]
+    fn test_normalize_base_url() {
+        for (raw, expected) in [
+            ("https://x.example.com", "https://x.example.com"),
+            (" https://x.example.com/ ", "https://x.example.com"),
+            ("HTTPS://X.Example.COM/v1/", "https://x.example.com/v1"),
+            ("https://x.com/Anthropic/", "https://x.com/Anthropic"),
+            ("https://X.com:8443/api", "https://x.com:8443/api"),
+            ("https://User:Pw@X.com/v1", "https://User:Pw@x.com/v1"),
+            ("http://LOCALHOST:11434?x=1", "http://localhost:11434?x=1"),
+            ("not a url/", "not a url"),
+            ("https://x.com:443/v1", "https://x.com/v1"),
+            ("http://[::1]:8080/", "http://[::1]:8080"),
+            ("HTTP://[0:0:0:0:0:0:0:1]:8080/v1", "http://[::1]:8080/v1"),
+            ("http://[FE80::1]/v1", "http://[fe80::1]/v1"),
+            ("http://192.168.1.10:3000/", "http://192.168.1.10:3000"),
+        ] {
+            assert_eq!(ProviderRouter::normalize_base_url(raw), expected, "{raw}");
+        }
+    }
+
+    /// 在指定地址启动上游：HEAD 任意路径与 POST /v1/responses 均返回 200
+    async fn spawn_literal_address_upstream(bind: &str) -> Option<String> {
+        use axum::{routing::post, Json, Router};
+
+        let app = Router::new()
+            .route(
+                "/v1/responses",
+                post(|| async { Json(json!({"id": "resp", "output": []})) }),
+            )
+            .fallback(|| async { "ok" });
+        // 环境不支持 IPv6 时跳过
+        let listener = tokio::net::TcpListener::bind(bind).await.ok()?;
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+        Some(format!("http://{addr}"))
+    }
+
+    #[tokio::test]
+    async fn test_probes_support_literal_addresses() {
+        let router = ProviderRouter::new(Arc::new(Database::memory().unwrap()));
+        let timeout = Duration::from_secs(5);
+        for bind in ["127.0.0.1:0", "[::1]:0"] {
+            let Some(url) = spawn_literal_address_upstream(bind).await else {
+                continue;
+            };
+            assert!(url.starts_with("http://[::1]:") || url.starts_with("http://127.0.0.1:"));
+            for base_url in [url.clone(), format!("{url}/"), format!("{url}/v1")] {
+                ProviderRouter::connectivity_latency(&base_url, timeout)
+                    .await
+                    .unwrap_or_else(|e| panic!("{base_url}: {e}"));
+            }
+            let provider = codex_provider("p1", &format!("{url}/"), "sk-a");
+            router
+                .test_url_latency(&provider, "codex", "gpt-5.2")
+                .await
+                .unwrap_or_else(|e| panic!("{url}: {e:?}"));
+        }
+        // 主机名 + 非默认端口：端口不可达时报连接失败而不是 URL 无效
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let dead = format!("http://localhost:{}", listener.local_addr().unwrap().port());
+        drop(listener);
+        let err = ProviderRouter::connectivity_latency(&dead, timeout)
+            .await
+            .unwrap_err();
+        assert!(err.contains("连通性探测失败"), "{err}");
+        let err = ProviderRouter::connectivity_latency("127.0.0.1:8080", timeout)
+            .await
+            .unwrap_err();
+        assert!(err.contains("无效的 base_url"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_url_priority_matches_literal_addresses() {
+        let db = Arc::new(Database::memory().unwrap());
+        for (id, url) in [
+            ("p1", "http://192.168.1.10:3000/"),
+            ("p2", "http://[::1]:8080"),
+            ("p3", "https://gw.example.com:8443/v1"),
+        ] {
+            let mut provider = codex_provider(id, url, &format!("sk-{id}"));
+            provider.name = format!("acme-{id}");
+            provider.sort_index = Some(1);
+            db.save_provider("codex", &provider).unwrap();
+            db.add_to_failover_queue("codex", id).unwrap();
+        }
+        let mut config = db.get_proxy_config_for_app("codex").await.unwrap();
+        config.auto_failover_enabled = true;
+        db.update_proxy_config_for_app(config).await.unwrap();
+
+        for (preferred, expected) in [
+            ("HTTP://[0:0:0:0:0:0:0:1]:8080/", "p2"),
+            ("http://192.168.1.10:3000", "p1"),
+            ("https://GW.example.com:8443/v1/", "p3"),
+        ] {
+            db.set_supplier_url_priority("acme", &[preferred.to_string()])
+                .unwrap();
+            let router = ProviderRouter::new(db.clone());
+            {
+                let mut latencies = router.url_latencies.write().await;
+                for (url, latency_ms) in [
+                    ("http://192.168.1.10:3000", 300),
+                    ("http://[::1]:8080/", 200),
+                    ("https://gw.example.com:8443/v1", 100),
+                ] {
+                    latencies.insert(
+                        ProviderRouter::url_latency_key("codex", 1, "acme", url),
+                        UrlLatency {
+                            latency_ms,
+                            tested_at: std::time::Instant::now(),
+                        },
+                    );
+                }
+            }
+            let providers = router.select_providers("codex", None, false).await.unwrap();
+            assert_eq!(providers.len(), 1, "{preferred}");
+            assert_eq!(providers[0].id, expected, "{preferred}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_equivalent_base_url_spellings_share_one_group() {
+        let db = Arc::new(Database::memory().unwrap());
+        for (id, url) in [
+            ("p1", "https://X.example.com/v1/"),
+            ("p2", "https://x.example.com/v1"),
+            ("p3", "https://x.example.com/anthropic"),
+        ] {
+            let mut provider = codex_provider(id, url, &format!("sk-{id}"));
+            provider.name = format!("acme-{id}");
+            provider.sort_index = Some(1);
+            db.save_provider("codex", &provider).unwrap();
+            db.add_to_failover_queue("codex", id).unwrap();
+        }
+        let mut config = db.get_proxy_config_for_app("codex").await.unwrap();
+        config.auto_failover_enabled = true;
+        db.update_proxy_config_for_app(config).await.unwrap();
+
+        let seed = |router: &ProviderRouter| {
+            let latencies = router.url_latencies.clone();
+            let tested = router.priority_level_tested.clone();
+            async move {
+                tested
+                    .write()
+                    .await
+                    .insert(ProviderRouter::supplier_key("codex", 1, "acme"), true);
+                let mut latencies = latencies.write().await;
+                for (url, latency_ms) in [
+                    ("https://x.example.com/v1/", 100),
+                    ("HTTPS://x.example.com/anthropic", 500),
+                ] {
+                    latencies.insert(
+                        ProviderRouter::url_latency_key("codex", 1, "acme", url),
+                        UrlLatency {
+                            latency_ms,
+                            tested_at: std::time::Instant::now(),
+                        },
+                    );
+                }
+            }
+        };
+
+        // 两种 /v1 写法归为同一个 URL：测速缓存命中，两个 key 都在该 URL 下
+        let router = ProviderRouter::new(db.clone());
+        seed(&router).await;
+        let mut ids: Vec<String> = router
+            .select_providers("codex", None, false)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|p| p.id)
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec!["p1", "p2"]);
+
+        // 路径前缀是独立的 URL；优先级写法不同也能匹配
+        db.set_supplier_url_priority("acme", &["HTTPS://X.EXAMPLE.COM/anthropic/".to_string()])
+            .unwrap();
+        assert_eq!(
+            db.get_supplier_url_priority("acme").unwrap(),
+            vec!["https://x.example.com/anthropic"]
+        );
+        let router = ProviderRouter::new(db.clone());
+        seed(&router).await;
+        let providers = router.select_providers("codex", None, false).await.unwrap();
+        assert_eq!(providers.len(), 1);
+        assert_eq!(providers[0].id, "p3");
+    }
+
+    #[tokio::test]
+    async fn test_failure_kind_drives_url_suspect() {
+        let db = Arc::new(Database::memory().unwrap());
+        for (id, url) in [
+            ("net", "https://net.example.com"),
+            ("http", "https://http.example.com"),
+        ] {
+            let mut provider = codex_provider(id, url, &format!("sk-{id}"));
+            provider.name = format!("acme-{id}");
+            provider.sort_index = Some(1);
+            db.save_provider("codex", &provider).unwrap();
+            db.add_to_failover_queue("codex", id).unwrap();
+        }
+
+        let router = ProviderRouter::new(db.clone());
+        for _ in 0..ProviderRouter::MIN_NETWORK_FAILS_BEFORE_SUSPECT {
+            router
+                .record_result(
+                    "net",
+                    "codex",
+                    false,
+                    false,
+                    Some("fail".to_string()),
+                    Some(FailureKind::Network),
+                )
+                .await
+                .unwrap();
+            // 文本看起来像链路错误，但结构化类型为 5xx：不应标记 suspect
+            router
+                .record_result(
+                    "http",
+                    "codex",
+                    false,
+                    false,
+                    Some("请求转发失败: error sending request".to_string()),
+                    Some(FailureKind::UpstreamHttp(500)),
+                )
+                .await
+                .unwrap();
+        }
+
+        assert!(
+            router
+                .is_url_suspect("codex", "acme", "https://net.example.com")
+                .await
+        );
+        assert!(
+            !router
+                .is_url_suspect("codex", "acme", "https://http.example.com")
+                .await
+        );
+    }
+
+    #[tokio::test]
+    async fn test_repeated_suspect_markings_back_off_and_reset_on_success() {
+        let db = Arc::new(Database::memory().unwrap());
+        let mut provider = codex_provider("net", "https://net.example.com", "sk-net");
+        provider.name = "acme-net".to_string();
+        provider.sort_index = Some(1);
+        db.save_provider("codex", &provider).unwrap();
+        db.add_to_failover_queue("codex", "net").unwrap();
+
+        let router = ProviderRouter::new(db.clone());
+        async fn fail(router: &ProviderRouter) {
+            for _ in 0..ProviderRouter::MIN_NETWORK_FAILS_BEFORE_SUSPECT {
+                router
+                    .record_result(
+                        "net",
+                        "codex",
+                        false,
+                        false,
+                        Some("fail".to_string()),
+                        Some(FailureKind::Network),
+                    )
+                    .await
+                    .unwrap();
+            }
+        }
+        let key = "codex:acme:https://net.example.com";
+
+        // 60s → 2m → 5m → 15m → 1h → 1h（±10% 抖动）
+        for (strikes, base) in [(1, 60), (2, 120), (3, 300), (4, 900), (5, 3600), (6, 3600)] {
+            fail(&router).await;
+            let entries = router.suspect_url_entries("codex").await;
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0].supplier, "acme");
+            assert_eq!(entries[0].url, "https://net.example.com");
+            assert_eq!(entries[0].strikes, strikes);
+
+            let remaining = router.suspect_urls.read().await[key]
+                .until
+                .duration_since(std::time::Instant::now())
+                .as_secs();
+            assert!(
+                remaining + 1 >= base * 9 / 10,
+                "strikes={strikes} remaining={remaining}"
+            );
+            assert!(
+                remaining <= (base * 11 / 10).min(3600),
+                "strikes={strikes} remaining={remaining}"
+            );
+
+            // 到期后不再视为疑似失效，但保留连续标记次数
+            if let Some(entry) = router.suspect_urls.write().await.get_mut(key) {
+                entry.until = std::time::Instant::now();
+            }
+            assert!(
+                !router
+                    .is_url_suspect("codex", "acme", "https://net.example.com")
+                    .await
+            );
+            assert!(router.suspect_url_entries("codex").await.is_empty());
+        }
+
+        // 经该 URL 的请求成功：连续标记次数清零，下一次重新从 60s 开始
+        router
+            .record_result("net", "codex", false, true, None, None)
+            .await
+            .unwrap();
+        assert!(router.suspect_urls.read().await.get(key).is_none());
+        fail(&router).await;
+        let entries = router.suspect_url_entries("codex").await;
+        assert_eq!(entries[0].strikes, 1);
+        let remaining = entries[0].until - chrono::Utc::now().timestamp();
+        assert!((53..=66).contains(&remaining), "remaining={remaining}");
+    }
+
+    #[tokio::test]
+    async fn test_routing_snapshot_reports_router_state() {
+        let router = ProviderRouter::new(Arc::new(Database::memory().unwrap()));
+        let empty = router.routing_snapshot("codex").await;
+        assert_eq!(empty.active_priority_level, None);
+        assert!(empty.suppliers.is_empty());
+
+        router
+            .active_priority_level
+            .write()
+            .await
+            .insert("codex".to_string(), 1);
+        router
+            .set_supplier_current_url("codex", 1, "acme", "https://a.example.com/")
+            .await;
+        router.url_latencies.write().await.insert(
+            ProviderRouter::url_latency_key("codex", 1, "acme", "https://a.example.com"),
+            UrlLatency {
+                latency_ms: 180,
+                tested_at: std::time::Instant::now(),
+            },
+        );
+        router
+            .strike_url_suspect("codex", "acme", "https://b.example.com")
+            .await;
+        router
+            .set_supplier_cooldown("codex", 2, "busy", CooldownReason::Overloaded, 30)
+            .await;
+        // 其他应用的条目不出现在快照中
+        router
+            .set_supplier_current_url("claude", 1, "other", "https://c.example.com")
+            .await;
+
+        let snapshot = router.routing_snapshot("codex").await;
+        assert_eq!(snapshot.app_type, "codex");
+        assert_eq!(snapshot.active_priority_level, Some(1));
+        assert_eq!(snapshot.suppliers.len(), 2);
+
+        let acme = &snapshot.suppliers[0];
+        assert_eq!((acme.priority, acme.supplier.as_str()), (1, "acme"));
+        assert_eq!(acme.current_url.as_deref(), Some("https://a.example.com"));
+        assert_eq!(acme.latency_ms, Some(180));
+        assert_eq!(acme.latency_age_secs, Some(0));
+        assert_eq!(acme.cooldown_reason, None);
+        assert_eq!(acme.suspect_urls, vec!["https://b.example.com".to_string()]);
+
+        let busy = &snapshot.suppliers[1];
+        assert_eq!((busy.priority, busy.supplier.as_str()), (2, "busy"));
+        assert_eq!(busy.current_url, None);
+        assert_eq!(busy.latency_ms, None);
+        assert_eq!(busy.cooldown_reason.as_deref(), Some("overloaded"));
+        let remaining = busy.cooldown_remaining_secs.unwrap();
+        assert!((29..=30).contains(&remaining), "remaining={remaining}");
+    }
+
+    #[tokio::test]
+    async fn test_janitor_prunes_stale_entries_and_keeps_live_ones() {
+        let db = Arc::new(Database::memory().unwrap());
+        let mut provider = codex_provider("p1", "https://a.example.com", "sk-1");
+        provider.name = "acme-p1".to_string();
+        provider.sort_index = Some(1);
+        db.save_provider("codex", &provider).unwrap();
+        db.add_to_failover_queue("codex", "p1").unwrap();
+        let router = ProviderRouter::new(db);
+
+        // 以 25 小时后为清理时刻
+        let start = std::time::Instant::now();
+        let at = |hours: u64, secs: u64| start + Duration::from_secs(hours * 3600 + secs);
+        let now = at(25, 0);
+        {
+            let mut map = router.round_robin_counters.write().await;
+            map.insert("codex:priority:1:key-rr".to_string(), 3);
+            map.insert("codex:priority:7:key-rr".to_string(), 1);
+        }
+        {
+            let mut map = router.supplier_cooldowns.write().await;
+            map.insert(
+                "codex:1:live".to_string(),
+                (at(30, 0), CooldownReason::NoUsableUrl),
+            );
+            map.insert(
+                "codex:1:gone".to_string(),
+                (at(0, 60), CooldownReason::Overloaded),
+            );
+        }
+        {
+            let mut map = router.suspect_urls.write().await;
+            let mut insert = |url: &str, until, strikes| {
+                map.insert(format!("codex:acme:{url}"), SuspectUrl { until, strikes });
+            };
+            insert("https://live.example.com", at(26, 0), 2);
+            // 刚到期：保留期内继续累计连续标记次数
+            insert("https://recent.example.com", at(24, 1800), 3);
+            insert("https://old.example.com", at(0, 60), 1);
+        }
+        {
+            let mut map = router.url_latencies.write().await;
+            for (url, tested_at) in [
+                ("https://a.example.com", at(24, 0)),
+                ("https://old.example.com", start),
+            ] {
+                map.insert(
+                    ProviderRouter::url_latency_key("codex", 1, "acme", url),
+                    UrlLatency {
+                        latency_ms: 100,
+                        tested_at,
+                    },
+                );
+            }
+        }
+        let held = router.get_supplier_benchmark_lock("codex", 1, "acme").await;
+        router.get_supplier_benchmark_lock("codex", 1, "gone").await;
+
+        let before = router.memory_stats().await;
+        assert_eq!(
+            before,
+            RouterMemoryStats {
+                circuit_breakers: 0,
+                round_robin_counters: 2,
+                supplier_cooldowns: 2,
+                suspect_urls: 3,
+                url_latencies: 2,
+                supplier_benchmark_locks: 2,
+            }
+        );
+
+        assert_eq!(router.prune_stale_entries_at(now).await, 5);
+        assert!(router
+            .round_robin_counters
+            .read()
+            .await
+            .contains_key("codex:priority:1:key-rr"));
+        assert!(router
+            .supplier_cooldowns
+            .read()
+            .await
+            .contains_key("codex:1:live"));
+        {
+            let map = router.suspect_urls.read().await;
+            let mut keys: Vec<&str> = map.keys().map(|k| k.as_str()).collect();
+            keys.sort();
+            assert_eq!(
+                keys,
+                vec![
+                    "codex:acme:https://live.example.com",
+                    "codex:acme:https://recent.example.com"
+                ]
+            );
+        }
+        assert!(router
+            .url_latencies
+            .read()
+            .await
+            .contains_key("codex:1:acme:https://a.example.com"));
+        assert!(router
+            .supplier_benchmark_locks
+            .read()
+            .await
+            .contains_key(&ProviderRouter::supplier_key("codex", 1, "acme")));
+        drop(held);
+
+        // 测速锁释放后下一轮清理
+        assert_eq!(router.prune_stale_entries_at(now).await, 1);
+        assert_eq!(router.memory_stats().await.supplier_benchmark_locks, 0);
+    }
+
+    #[tokio::test]
+    async fn test_explicit_supplier_groups_cooldown() {
+        let router = ProviderRouter::new(Arc::new(Database::memory().unwrap()));
+
+        // 名称推断："my-provider-key1" 与 "mycorp" 不同组，"my-other" 却与前者同组
+        let mut p1 = codex_provider("p1", "https://a.example.com", "sk-1");
+        p1.name = "my-provider-key1".to_string();
+        let mut p2 = codex_provider("p2", "https://b.example.com", "sk-2");
+        p2.name = "mycorp".to_string();
+        let mut p3 = codex_provider("p3", "https://c.example.com", "sk-3");
+        p3.name = "my-other".to_string();
+        assert_eq!(ProviderRouter::supplier_name(&p1), "my");
+        assert_eq!(ProviderRouter::supplier_name(&p3), "my");
+
+        // 显式 supplier：p1 与 p2 同组，p3 仍按名称推断
+        p1.set_supplier(Some("mycorp"));
+        p2.set_supplier(Some(" mycorp "));
+        for p in [&mut p1, &mut p2, &mut p3] {
+            p.sort_index = Some(1);
+        }
+        router
+            .set_supplier_cooldown("codex", 1, "mycorp", CooldownReason::NoUsableUrl, 60)
+            .await;
+        assert!(router.is_provider_in_cooldown(&p1, "codex").await);
+        assert!(router.is_provider_in_cooldown(&p2, "codex").await);
+        assert!(!router.is_provider_in_cooldown(&p3, "codex").await);
+
+        // 设置显式 supplier 后改名不影响分组
+        p1.name = "renamed-key1".to_string();
+        assert!(router.is_provider_in_cooldown(&p1, "codex").await);
+    }
+
+    #[tokio::test]
+    async fn test_disabled_provider_skipped_until_expiry() {
+        let db = Arc::new(Database::memory().unwrap());
+        for (id, url) in [
+            ("p1", "https://a.example.com"),
+            ("p2", "https://b.example.com"),
+        ] {
+            let mut provider = codex_provider(id, url, &format!("sk-{id}"));
+            provider.name = format!("{id}-key");
+            provider.sort_index = Some(1);
+            db.save_provider("codex", &provider).unwrap();
+            db.add_to_failover_queue("codex", id).unwrap();
+        }
+        let mut config = db.get_proxy_config_for_app("codex").await.unwrap();
+        config.auto_failover_enabled = true;
+        db.update_proxy_config_for_app(config).await.unwrap();
+        let router = ProviderRouter::new(db.clone());
+
+        let ids = |providers: Vec<Provider>| {
+            let mut ids: Vec<String> = providers.into_iter().map(|p| p.id).collect();
+            ids.sort();
+            ids
+        };
+
+        let now = chrono::Utc::now().timestamp();
+        db.set_provider_disabled_until("codex", "p1", Some(now + 4 * 3600))
+            .unwrap();
+        let selected = router.select_providers("codex", None, false).await.unwrap();
+        assert_eq!(ids(selected), vec!["p2"]);
+
+        // 时间越过截止点：查询时过滤，记录本身保留到清除
+        let disabled = db.get_disabled_providers("codex", now + 5 * 3600).unwrap();
+        assert!(disabled.is_empty());
+        assert!(db
+            .get_disabled_providers("codex", now)
+            .unwrap()
+            .contains_key("p1"));
+        // 选路不写库，由后台清理任务清除
+        assert_eq!(router.clear_expired_disables(now + 5 * 3600), 1);
+        assert!(db.get_disabled_providers("codex", now).unwrap().is_empty());
+
+        let selected = router.select_providers("codex", None, false).await.unwrap();
+        assert_eq!(ids(selected), vec!["p1", "p2"]);
+    }
+
+    #[tokio::test]
+    async fn test_depleted_provider_skipped_until_recheck() {
+        let db = Arc::new(Database::memory().unwrap());
+        for (id, url) in [
+            ("p1", "https://a.example.com"),
+            ("p2", "https://b.example.com"),
+        ] {
+            let mut provider = codex_provider(id, url, &format!("sk-{id}"));
+            provider.name = format!("{id}-key");
+            provider.sort_index = Some(1);
+            db.save_provider("codex", &provider).unwrap();
+            db.add_to_failover_queue("codex", id).unwrap();
+        }
+        let mut config = db.get_proxy_config_for_app("codex").await.unwrap();
+        config.auto_failover_enabled = true;
+        db.update_proxy_config_for_app(config.clone())
+            .await
+            .unwrap();
+        let router = ProviderRouter::new(db.clone());
+
+        let ids = |providers: Vec<Provider>| {
+            let mut ids: Vec<String> = providers.into_iter().map(|p| p.id).collect();
+            ids.sort();
+            ids
+        };
+
+        router
+            .record_result(
+                "p1",
+                "codex",
+                false,
+                false,
+                Some("余额不足: Insufficient Balance".to_string()),
+                Some(FailureKind::Depleted),
+            )
+            .await
+            .unwrap();
+        let health = db.get_provider_health("p1", "codex").await.unwrap();
+        assert!(health.depleted_at.is_some());
+        assert_eq!(
+            health.depleted_message.as_deref(),
+            Some("Insufficient Balance")
+        );
+        let selected = router.select_providers("codex", None, false).await.unwrap();
+        assert_eq!(ids(selected), vec!["p2"]);
+
+        // 普通失败不清除耗尽状态
+        router
+            .record_result("p1", "codex", false, false, Some("HTTP 500".into()), None)
+            .await
+            .unwrap();
+        let selected = router.select_providers("codex", None, false).await.unwrap();
+        assert_eq!(ids(selected), vec!["p2"]);
+
+        // 超过重新检查间隔：重新参与选路（状态保留到请求成功）
+        config.depleted_recheck_secs = 0;
+        db.update_proxy_config_for_app(config.clone())
+            .await
+            .unwrap();
+        let selected = router.select_providers("codex", None, false).await.unwrap();
+        assert_eq!(ids(selected), vec!["p1", "p2"]);
+        config.depleted_recheck_secs = 3600;
+        db.update_proxy_config_for_app(config).await.unwrap();
+        let selected = router.select_providers("codex", None, false).await.unwrap();
+        assert_eq!(ids(selected), vec!["p2"]);
+
+        // 请求成功后清除
+        router
+            .record_result("p1", "codex", false, true, None, None)
+            .await
+            .unwrap();
+        let health = db.get_provider_health("p1", "codex").await.unwrap();
+        assert!(health.depleted_at.is_none() && health.depleted_message.is_none());
+        let selected = router.select_providers("codex", None, false).await.unwrap();
+        assert_eq!(ids(selected), vec!["p1", "p2"]);
+    }
+
+    async fn multi_supplier_db() -> Arc<Database> {
+        let db = Arc::new(Database::memory().unwrap());
+        for (id, name, url) in [
+            ("d1", "delta-1", "https://d.example.com"),
+            ("a1", "alpha-1", "https://a.example.com"),
+            ("c1", "charlie-1", "https://c.example.com"),
+            ("b1", "bravo-1", "https://b.example.com"),
+            ("a2", "alpha-2", "https://a2.example.com"),
+        ] {
+            let mut provider = codex_provider(id, url, &format!("sk-{id}"));
+            provider.name = name.to_string();
+            provider.sort_index = Some(1);
+            db.save_provider("codex", &provider).unwrap();
+            db.add_to_failover_queue("codex", id).unwrap();
+        }
+        let mut config = db.get_proxy_config_for_app("codex").await.unwrap();
+        config.auto_failover_enabled = true;
+        db.update_proxy_config_for_app(config).await.unwrap();
+        db
+    }
+
+    #[tokio::test]
+    async fn test_select_providers_order_is_deterministic() {
+        let db = multi_supplier_db().await;
+
+        // 每次都用新的 router（新的 HashMap 随机种子）；alpha 有两个 URL，预先写入 current_url 避免测速
+        let mut chains: Vec<Vec<String>> = Vec::new();
+        for _ in 0..8 {
+            let router = ProviderRouter::new(db.clone());
+            router
+                .set_supplier_current_url("codex", 1, "alpha", "https://a.example.com")
+                .await;
+            let providers = router.select_providers("codex", None, false).await.unwrap();
+            chains.push(providers.into_iter().map(|p| p.id).collect());
+        }
+
+        assert_eq!(chains[0], vec!["a1", "b1", "c1", "d1"]);
+        assert!(chains.iter().all(|c| c == &chains[0]));
+    }
+
+    #[tokio::test]
+    async fn test_benchmark_all_suppliers_order_is_deterministic() {
+        let db = multi_supplier_db().await;
+
+        for _ in 0..8 {
+            let router = ProviderRouter::new(db.clone());
+            // 冷却中的供应商直接产出 COOLDOWN 结果，不触发网络测速
+            for supplier in ["alpha", "bravo", "charlie", "delta"] {
+                router
+                    .set_supplier_cooldown("codex", 1, supplier, CooldownReason::NoUsableUrl, 60)
+                    .await;
+            }
+            let results = router
+                .benchmark_all_suppliers(
+                    "codex",
+                    "gpt-5",
+                    None,
+                    None,
+                    &CancellationToken::new(),
+                    &|_| {},
+                )
+                .await
+                .unwrap();
+            let suppliers: Vec<&str> = results.iter().map(|r| r.supplier.as_str()).collect();
+            assert_eq!(suppliers, vec!["alpha", "bravo", "charlie", "delta"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cooldown_duration_and_reason_follow_failure_cause() {
+        use axum::{
+            http::{HeaderMap, StatusCode},
+            routing::post,
+            Json, Router,
+        };
+
+        // responses 端点不兼容时回退 chat/completions：busy 的 key 被限流；dead 的 key 余额耗尽
+        let app = Router::new()
+            .route(
+                "/v1/responses",
+                post(|| async {
+                    (
+                        StatusCode::BAD_REQUEST,
+                        Json(json!({ "error": { "message": "openai_error" } })),
+                    )
+                }),
+            )
+            .route(
+                "/v1/chat/completions",
+                post(|headers: HeaderMap| async move {
+                    match headers["authorization"].to_str().unwrap() {
+                        "Bearer sk-busy" => (
+                            StatusCode::TOO_MANY_REQUESTS,
+                            Json(json!({ "error": { "message": "Rate limit exceeded" } })),
+                        ),
+                        _ => (
+                            StatusCode::PAYMENT_REQUIRED,
+                            Json(json!({ "error": { "message": "Insufficient Balance" } })),
+                        ),
+                    }
+                }),
+            );
+        let mut urls = Vec::new();
+        for _ in 0..2 {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let app = app.clone();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.ok();
+            });
+            urls.push(format!("http://{addr}"));
+        }
+        let (busy_url, dead_url) = (urls[0].clone(), urls[1].clone());
+        // 无人监听的端口：连通性回退同样失败
+        let gone_url = "http://127.0.0.1:1".to_string();
+
+        let db = Arc::new(Database::memory().unwrap());
+        for (supplier, url) in [
+            ("busy", &busy_url),
+            ("dead", &dead_url),
+            ("gone", &gone_url),
+        ] {
+            let mut provider = codex_provider(supplier, url, &format!("sk-{supplier}"));
+            provider.name = format!("{supplier}-k1");
+            provider.sort_index = Some(1);
+            db.save_provider("codex", &provider).unwrap();
+            db.add_to_failover_queue("codex", supplier).unwrap();
+        }
+        let mut config = db.get_proxy_config_for_app("codex").await.unwrap();
+        config.auto_failover_enabled = true;
+        config.overload_cooldown_secs = 3;
+        db.update_proxy_config_for_app(config).await.unwrap();
+
+        let router = ProviderRouter::new(db.clone());
+        // 唯一 URL 均被标记疑似失效：重新测速后仍无可用 URL
+        for (supplier, url) in [
+            ("busy", &busy_url),
+            ("dead", &dead_url),
+            ("gone", &gone_url),
+        ] {
+            router.strike_url_suspect("codex", supplier, url).await;
+        }
+        let _ = router.select_providers("codex", None, false).await;
+
+        let remaining =
+            |until: std::time::Instant| until.saturating_duration_since(std::time::Instant::now());
+        {
+            let map = router.supplier_cooldowns.read().await;
+            let (until, reason) = map["codex:1:busy"];
+            assert_eq!(reason, CooldownReason::Overloaded);
+            assert!(remaining(until) <= Duration::from_secs(3));
+            for supplier in ["dead", "gone"] {
+                let (until, _) = map[&format!("codex:1:{supplier}")];
+                assert!(remaining(until) > Duration::from_secs(15), "{supplier}");
+                assert!(remaining(until) <= Duration::from_secs(20), "{supplier}");
+            }
+        }
+
+        let expected = vec![
+            ("busy".to_string(), CooldownReason::Overloaded),
+            ("dead".to_string(), CooldownReason::AllKeysFailed),
+            ("gone".to_string(), CooldownReason::NoUsableUrl),
+        ];
+        assert_eq!(router.suppliers_in_cooldown("codex").await, expected);
+
+        let results = router
+            .benchmark_all_suppliers(
+                "codex",
+                "gpt-5",
+                None,
+                None,
+                &CancellationToken::new(),
+                &|_| {},
+            )
+            .await
+            .unwrap();
+        let rows: Vec<(&str, &str, Option<&str>)> = results
+            .iter()
+            .map(|r| {
+                (
+                    r.supplier.as_str(),
+                    r.chosen_kind.as_str(),
+                    r.cooldown_reason.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                ("busy", "COOLDOWN", Some("overloaded")),
+                ("dead", "COOLDOWN", Some("all-keys-failed")),
+                ("gone", "COOLDOWN", Some("no-usable-url")),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_benchmark_honors_db_url_priority() {
+        async fn spawn_responses(delay_ms: u64) -> String {
+            use axum::{http::StatusCode, routing::post, Router};
+
+            let app = Router::new().route(
+                "/v1/responses",
+                post(move || async move {
+                    tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+                    StatusCode::OK
+                }),
+            );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.ok();
+            });
+            format!("http://{addr}")
+        }
+
+        let fast = spawn_responses(0).await;
+        let slow = spawn_responses(300).await;
+
+        let db = Arc::new(Database::memory().unwrap());
+        for (id, url) in [("p1", &fast), ("p2", &slow)] {
+            let mut provider = codex_provider(id, url, &format!("sk-{id}"));
+            provider.name = format!("acme-{id}");
+            provider.sort_index = Some(1);
+            db.save_provider("codex", &provider).unwrap();
+            db.add_to_failover_queue("codex", id).unwrap();
+        }
+        let router = ProviderRouter::new(db.clone());
+
+        let results = router
+            .benchmark_all_suppliers(
+                "codex",
+                "gpt-5.2",
+                None,
+                None,
+                &CancellationToken::new(),
+                &|_| {},
+            )
+            .await
+            .unwrap();
+        assert_eq!(results[0].chosen_url.as_deref(), Some(fast.as_str()));
+
+        db.set_supplier_url_priority("acme", std::slice::from_ref(&slow))
+            .unwrap();
+        let results = router
+            .benchmark_all_suppliers(
+                "codex",
+                "gpt-5.2",
+                None,
+                None,
+                &CancellationToken::new(),
+                &|_| {},
+            )
+            .await
+            .unwrap();
+        assert_eq!(results[0].chosen_url.as_deref(), Some(slow.as_str()));
+    }
+
+    #[tokio::test]
+    async fn test_benchmark_runs_suppliers_concurrently_in_order() {
+        use axum::{extract::Path, http::StatusCode, routing::post, Router};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        // 每个 supplier 一个路径前缀；越靠前的 supplier 响应越慢，完成顺序与结果顺序相反
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        // 探测到达后等待同时在途的探测数达到该值（最多 2s），并发执行时必然出现重叠
+        let rendezvous = Arc::new(AtomicUsize::new(1));
+        let (in_flight_c, peak_c, rendezvous_c) =
+            (in_flight.clone(), peak.clone(), rendezvous.clone());
+        let app = Router::new().route(
+            "/:supplier/v1/responses",
+            post(move |Path(supplier): Path<String>| {
+                let (in_flight, peak, rendezvous) =
+                    (in_flight_c.clone(), peak_c.clone(), rendezvous_c.clone());
+                async move {
+                    let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    let deadline = std::time::Instant::now() + Duration::from_secs(2);
+                    while in_flight.load(Ordering::SeqCst) < rendezvous.load(Ordering::SeqCst)
+                        && std::time::Instant::now() < deadline
+                    {
+                        tokio::time::sleep(Duration::from_millis(10)).await;
+                    }
+                    let index: u64 = supplier.trim_start_matches('s').parse().unwrap();
+                    tokio::time::sleep(Duration::from_millis(300 - 40 * index)).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                    StatusCode::OK
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+
+        let db = Arc::new(Database::memory().unwrap());
+        for index in 0..6 {
+            let id = format!("s{index}");
+            let mut provider = codex_provider(&id, &format!("{base}/{id}"), &format!("sk-{id}"));
+            provider.sort_index = Some(1 + index / 3);
+            db.save_provider("codex", &provider).unwrap();
+            db.add_to_failover_queue("codex", &id).unwrap();
+        }
+
+        for concurrency in [1, 3] {
+            let mut config = db.get_proxy_config_for_app("codex").await.unwrap();
+            config.benchmark_concurrency = concurrency;
+            db.update_proxy_config_for_app(config).await.unwrap();
+            peak.store(0, Ordering::SeqCst);
+            rendezvous.store(concurrency.min(2) as usize, Ordering::SeqCst);
+
+            let router = ProviderRouter::new(db.clone());
+            let results = router
+                .benchmark_all_suppliers(
+                    "codex",
+                    "gpt-5.2",
+                    None,
+                    None,
+                    &CancellationToken::new(),
+                    &|_| {},
+                )
+                .await
+                .unwrap();
+            // 在途探测不超过并发上限；并发开启时确实有探测重叠
+            let observed = peak.load(Ordering::SeqCst);
+            assert!(observed <= concurrency as usize, "peak={observed}");
+            if concurrency > 1 {
+                assert!(observed >= 2, "peak={observed}");
+            }
+
+            // 结果按 (层级, supplier) 排序；每个 supplier 的 current_url 与已测速标记都已写入
+            let order: Vec<(usize, &str)> = results
+                .iter()
+                .map(|r| (r.priority, r.supplier.as_str()))
+                .collect();
+            assert_eq!(
+                order,
+                vec![(1, "s0"), (1, "s1"), (1, "s2"), (2, "s3"), (2, "s4"), (2, "s5")]
+            );
+            let tested = router.priority_level_tested.read().await;
+            for result in &results {
+                let url = format!("{base}/{}", result.supplier);
+                assert_eq!(result.chosen_kind, "OK");
+                assert_eq!(result.chosen_url.as_deref(), Some(url.as_str()));
+                let current = router
+                    .get_supplier_current_url("codex", result.priority, &result.supplier)
+                    .await;
+                assert_eq!(current.as_deref(), Some(url.as_str()));
+                let key = ProviderRouter::supplier_key("codex", result.priority, &result.supplier);
+                assert_eq!(tested.get(&key), Some(&true));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_benchmark_cancellation_stops_further_probes() {
+        use axum::{extract::Path, http::StatusCode, routing::post, Router};
+
+        // 每个 supplier 的探测次数；s9 响应很慢，用于验证取消会中止进行中的探测
+        let hits: Arc<std::sync::Mutex<HashMap<String, usize>>> = Arc::default();
+        let hits_c = hits.clone();
+        let app = Router::new().route(
+            "/:supplier/v1/responses",
+            post(move |Path(supplier): Path<String>| {
+                let hits = hits_c.clone();
+                async move {
+                    *hits.lock().unwrap().entry(supplier.clone()).or_default() += 1;
+                    if supplier == "s9" {
+                        tokio::time::sleep(Duration::from_secs(30)).await;
+                    }
+                    StatusCode::OK
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+
+        let db = Arc::new(Database::memory().unwrap());
+        for id in ["s0", "s1", "s2", "s9"] {
+            let mut provider = codex_provider(id, &format!("{base}/{id}"), &format!("sk-{id}"));
+            provider.sort_index = Some(if id == "s9" { 2 } else { 1 });
+            db.save_provider("codex", &provider).unwrap();
+            db.add_to_failover_queue("codex", id).unwrap();
+        }
+        let mut config = db.get_proxy_config_for_app("codex").await.unwrap();
+        config.benchmark_concurrency = 1;
+        db.update_proxy_config_for_app(config).await.unwrap();
+        let router = ProviderRouter::new(db.clone());
+
+        // 第一个 supplier 完成时取消：只返回它的结果，后续 supplier 不再发出任何探测
+        let cancel = CancellationToken::new();
+        let events = std::sync::Mutex::new(Vec::new());
+        let results = router
+            .benchmark_all_suppliers("codex", "gpt-5.2", Some(1), None, &cancel, &|event| {
+                let label = match &event {
+                    BenchmarkProgress::SupplierStarted { index, total, .. } => {
+                        format!("start {index}/{total}")
+                    }
+                    BenchmarkProgress::SupplierFinished { index, total, .. } => {
+                        cancel.cancel();
+                        format!("finish {index}/{total}")
+                    }
+                };
+                events.lock().unwrap().push(label);
+            })
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].supplier, "s0");
+        assert_eq!(results[0].chosen_kind, "OK");
+        assert_eq!(*events.lock().unwrap(), vec!["start 0/3", "finish 0/3"]);
+        let counts = hits.lock().unwrap().clone();
+        assert!(counts.get("s0").copied().unwrap_or(0) > 0, "{counts:?}");
+        assert!(
+            !counts.contains_key("s1") && !counts.contains_key("s2"),
+            "{counts:?}"
+        );
+
+        // 取消进行中的测速：探测立即中止，不写入 current_url
+        let cancel = CancellationToken::new();
+        let trigger = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            trigger.cancel();
+        });
+        let start = std::time::Instant::now();
+        let results = router
+            .benchmark_all_suppliers("codex", "gpt-5.2", Some(2), None, &cancel, &|_| {})
+            .await
+            .unwrap();
+        assert!(results.is_empty());
+        let elapsed = start.elapsed();
+        assert!(elapsed < Duration::from_secs(5), "{elapsed:?}");
+        assert_eq!(hits.lock().unwrap().get("s9"), Some(&1));
+        let current = router.get_supplier_current_url("codex", 2, "s9").await;
+        assert!(current.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_custom_connectivity_penalty_changes_cached_url_order() {
+        use axum::{http::StatusCode, routing::post, Router};
+
+        // slow：全链路可用但较慢；reach：无任何业务端点，只能通过连通性回退
+        let slow = Router::new().route(
+            "/v1/responses",
+            post(|| async {
+                tokio::time::sleep(Duration::from_millis(500)).await;
+                StatusCode::OK
+            }),
+        );
+        let mut urls = Vec::new();
+        for app in [slow, Router::new()] {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.ok();
+            });
+            urls.push(format!("http://{addr}"));
+        }
+
+        let db = Arc::new(Database::memory().unwrap());
+        for (id, url) in [("slow", &urls[0]), ("reach", &urls[1])] {
+            let mut provider = codex_provider(id, url, &format!("sk-{id}"));
+            provider.name = format!("acme-{id}");
+            provider.sort_index = Some(1);
+            db.save_provider("codex", &provider).unwrap();
+            db.add_to_failover_queue("codex", id).unwrap();
+        }
+
+        // 首次选路测速（全链路 OK 优先）；清除当前 URL 后按缓存延迟（含惩罚）重新排序
+        async fn select_twice(db: &Arc<Database>, penalty_ms: u64) -> (String, String) {
+            let mut config = db.get_proxy_config_for_app("codex").await.unwrap();
+            config.auto_failover_enabled = true;
+            config.connectivity_penalty_ms = penalty_ms;
+            db.update_proxy_config_for_app(config).await.unwrap();
+
+            let router = ProviderRouter::new(db.clone());
+            let first = router.select_providers("codex", None, false).await.unwrap();
+            router.clear_supplier_current_url("codex", 1, "acme").await;
+            let second = router.select_providers("codex", None, false).await.unwrap();
+            (first[0].id.clone(), second[0].id.clone())
+        }
+
+        assert_eq!(
+            select_twice(&db, ProviderRouter::DEFAULT_CONNECTIVITY_PENALTY_MS).await,
+            ("slow".to_string(), "slow".to_string())
+        );
+        // 惩罚低于全链路延迟时，仅连通性可用的 URL 在缓存排序中领先
+        assert_eq!(
+            select_twice(&db, 50).await,
+            ("slow".to_string(), "reach".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_custom_connectivity_penalty_gates_preferred_url() {
+        let db = Arc::new(Database::memory().unwrap());
+        for (id, url) in [("p1", "https://a.invalid"), ("p2", "https://b.invalid")] {
+            let mut provider = codex_provider(id, url, &format!("sk-{id}"));
+            provider.name = format!("acme-{id}");
+            provider.sort_index = Some(1);
+            db.save_provider("codex", &provider).unwrap();
+            db.add_to_failover_queue("codex", id).unwrap();
+        }
+        db.set_supplier_url_priority("ACME", &["https://b.invalid/".to_string()])
+            .unwrap();
+
+        // 优先 URL 只有 5 秒的缓存延迟；未命中时会触发测速（域名不可解析，测速必然失败）
+        async fn select_with_cache(
+            db: &Arc<Database>,
+            penalty_ms: u64,
+        ) -> (Result<Vec<Provider>, AppError>, bool) {
+            let mut config = db.get_proxy_config_for_app("codex").await.unwrap();
+            config.auto_failover_enabled = true;
+            config.connectivity_penalty_ms = penalty_ms;
+            config.connectivity_timeout_secs = 1;
+            config.probe_timeout_secs = 1;
+            db.update_proxy_config_for_app(config).await.unwrap();
+
+            let router = ProviderRouter::new(db.clone());
+            {
+                let mut latencies = router.url_latencies.write().await;
+                latencies.insert(
+                    ProviderRouter::url_latency_key("codex", 1, "acme", "https://b.invalid"),
+                    UrlLatency {
+                        latency_ms: 5_000,
+                        tested_at: std::time::Instant::now(),
+                    },
+                );
+            }
+            let result = router.select_providers("codex", None, false).await;
+            let benchmarked = router
+                .priority_level_tested
+                .read()
+                .await
+                .contains_key(&ProviderRouter::supplier_key("codex", 1, "acme"));
+            (result, benchmarked)
+        }
+
+        // 默认惩罚 30 秒：5 秒视为全链路结果，直接命中优先 URL
+        let (result, benchmarked) =
+            select_with_cache(&db, ProviderRouter::DEFAULT_CONNECTIVITY_PENALTY_MS).await;
+        assert_eq!(result.unwrap()[0].id, "p2");
+        assert!(!benchmarked);
+
+        // 惩罚调低到 1 秒：同样的缓存被视为回退结果，不再锁定优先 URL 而是重新测速
+        let (result, benchmarked) = select_with_cache(&db, 1_000).await;
+        assert!(result.is_err());
+        assert!(benchmarked);
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_suppliers_clears_tested_and_current_url() {
+        let db = Arc::new(Database::memory().unwrap());
+        let router = ProviderRouter::new(db);
+        {
+            let mut tested = router.priority_level_tested.write().await;
+            tested.insert("codex:1:acme".to_string(), true);
+            tested.insert("codex:3:acme".to_string(), true);
+            tested.insert("codex:1:other".to_string(), true);
+            tested.insert("claude:1:acme".to_string(), true);
+        }
+        router
+            .set_supplier_current_url("codex", 1, "acme", "https://a.example.com")
+            .await;
+        router
+            .set_supplier_current_url("codex", 1, "other", "https://o.example.com")
+            .await;
+
+        let cleared = router
+            .invalidate_suppliers("codex", &["acme".to_string()])
+            .await;
+        assert_eq!(cleared, 3);
+
+        let tested = router.priority_level_tested.read().await;
+        let mut keys: Vec<_> = tested.keys().cloned().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["claude:1:acme", "codex:1:other"]);
+        drop(tested);
+        assert!(router
+            .get_supplier_current_url("codex", 1, "acme")
+            .await
+            .is_none());
+        assert_eq!(
+            router
+                .get_supplier_current_url("codex", 1, "other")
+                .await
+                .as_deref(),
+            Some("https://o.example.com")
+        );
+    }
+
+    #[test]
+    fn test_extract_helpers_expand_env_placeholders() {
+        std::env::set_var("CC_SWITCH_TEST_ROUTER_KEY", "sk-router");
+        let provider = codex_provider(
+            "p1",
+            "https://${CC_SWITCH_TEST_ROUTER_UNSET}.example.com",
+            "${CC_SWITCH_TEST_ROUTER_KEY}",
+        );
+        assert_eq!(
+            ProviderRouter::extract_api_key_value(&provider, "codex").as_deref(),
+            Some("sk-router")
+        );
+        // 变量未设置：视为未配置
+        assert_eq!(ProviderRouter::extract_base_url(&provider, "codex"), None);
+        // 原配置不变
+        assert_eq!(
+            provider.settings_config["env"]["OPENAI_API_KEY"],
+            "${CC_SWITCH_TEST_ROUTER_KEY}"
+        );
+    }
 }
@@ -2,6 +2,11 @@
 //!
 //! 定义认证信息和认证策略，支持多种上游供应商的认证方式。
 
+use crate::provider::Provider;
+
+/// settings_config 中的认证方案字段
+pub const AUTH_KEY: &str = "auth";
+
 /// 认证信息
 ///
 /// 包含 API Key 和对应的认证策略
@@ -13,6 +18,8 @@ pub struct AuthInfo {
     pub strategy: AuthStrategy,
     /// OAuth access_token（用于 GoogleOAuth 策略）
     pub access_token: Option<String>,
+    /// 供应商显式配置的认证方式（存在时只发送该认证头）
+    pub scheme: Option<AuthScheme>,
 }
 
 impl AuthInfo {
@@ -22,15 +29,23 @@ impl AuthInfo {
             api_key,
             strategy,
             access_token: None,
+            scheme: None,
         }
     }
 
+    /// 附加显式配置的认证方式
+    pub fn with_scheme(mut self, scheme: Option<AuthScheme>) -> Self {
+        self.scheme = scheme;
+        self
+    }
+
     /// 创建带有 access_token 的认证信息（用于 OAuth）
     pub fn with_access_token(api_key: String, access_token: String) -> Self {
         Self {
             api_key,
             strategy: AuthStrategy::GoogleOAuth,
             access_token: Some(access_token),
+            scheme: None,
         }
     }
 
@@ -96,6 +111,84 @@ pub enum AuthStrategy {
     ///
     /// 用于 Gemini CLI 等需要 OAuth 的场景
     GoogleOAuth,
+
+    /// Azure OpenAI 认证方式
+    ///
+    /// - Header: `api-key: <api_key>`
+    AzureApiKey,
+}
+
+/// 供应商显式配置的认证方式（`settings_config.auth`）
+///
+/// ```json
+/// { "auth": { "scheme": "header", "headerName": "api-key" } }
+/// ```
+///
+/// 未配置时沿用各适配器的默认推断。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthScheme {
+    /// `x-api-key: <api_key>`
+    XApiKey,
+    /// `Authorization: Bearer <api_key>`
+    Bearer,
+    /// `<headerName>: <api_key>`
+    Header(String),
+}
+
+impl AuthScheme {
+    /// 读取 `settings_config.auth`（配置无效时记录警告并忽略）
+    pub fn from_provider(provider: &Provider) -> Option<Self> {
+        let auth = provider
+            .settings_config
+            .get(AUTH_KEY)
+            .filter(|v| !v.is_null())?;
+        let scheme = auth
+            .get("scheme")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+
+        let parsed = match scheme.as_str() {
+            "x-api-key" => Some(Self::XApiKey),
+            "bearer" => Some(Self::Bearer),
+            "header" => auth
+                .get("headerName")
+                .and_then(|v| v.as_str())
+                .map(str::trim)
+                .filter(|name| reqwest::header::HeaderName::from_bytes(name.as_bytes()).is_ok())
+                .map(|name| Self::Header(name.to_string())),
+            _ => None,
+        };
+        if parsed.is_none() {
+            log::warn!(
+                "[Auth] Provider {} 的 auth 配置无效，已忽略: {auth}",
+                provider.id
+            );
+        }
+        parsed
+    }
+
+    /// 认证头（名称, 值）
+    pub fn header(&self, api_key: &str) -> (String, String) {
+        match self {
+            Self::XApiKey => ("x-api-key".to_string(), api_key.to_string()),
+            Self::Bearer => ("Authorization".to_string(), format!("Bearer {api_key}")),
+            Self::Header(name) => (name.clone(), api_key.to_string()),
+        }
+    }
+}
+
+/// Claude 经 Python 代理转发时使用的认证头（名称, 值）
+///
+/// Python 代理会原样透传请求头，并把 `X-API-Key` 按值的前缀注入为 `x-api-key` 或
+/// `authorization`。显式配置了认证方式时直接发送目标认证头，不再经过前缀推断；
+/// 否则沿用 `X-API-Key: <api_key>`。
+pub fn python_proxy_auth_header(scheme: Option<&AuthScheme>, api_key: &str) -> (String, String) {
+    match scheme {
+        Some(scheme) => scheme.header(api_key),
+        None => ("X-API-Key".to_string(), api_key.to_string()),
+    }
 }
 
 #[cfg(test)]
@@ -187,6 +280,66 @@ mod tests {
         assert_ne!(auth.strategy, AuthStrategy::Google);
     }
 
+    fn provider_with_auth(auth: serde_json::Value) -> Provider {
+        Provider::with_id(
+            "p1".to_string(),
+            "p1".to_string(),
+            serde_json::json!({ "auth": auth }),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_auth_scheme_from_provider() {
+        let scheme = |auth| AuthScheme::from_provider(&provider_with_auth(auth));
+
+        assert_eq!(
+            scheme(serde_json::json!({"scheme": "x-api-key"})),
+            Some(AuthScheme::XApiKey)
+        );
+        assert_eq!(
+            scheme(serde_json::json!({"scheme": "Bearer"})),
+            Some(AuthScheme::Bearer)
+        );
+        assert_eq!(
+            scheme(serde_json::json!({"scheme": "header", "headerName": " api-key "})),
+            Some(AuthScheme::Header("api-key".to_string()))
+        );
+
+        // 缺少或非法的 headerName、未知 scheme 均忽略
+        assert_eq!(scheme(serde_json::json!({"scheme": "header"})), None);
+        assert_eq!(
+            scheme(serde_json::json!({"scheme": "header", "headerName": "bad header"})),
+            None
+        );
+        assert_eq!(scheme(serde_json::json!({"scheme": "basic"})), None);
+        assert_eq!(
+            AuthScheme::from_provider(&Provider::with_id(
+                "p2".to_string(),
+                "p2".to_string(),
+                serde_json::json!({}),
+                None,
+            )),
+            None
+        );
+    }
+
+    #[test]
+    fn test_python_proxy_auth_header() {
+        assert_eq!(
+            python_proxy_auth_header(None, "sk-1"),
+            ("X-API-Key".to_string(), "sk-1".to_string())
+        );
+        assert_eq!(
+            python_proxy_auth_header(Some(&AuthScheme::Bearer), "sk-1"),
+            ("Authorization".to_string(), "Bearer sk-1".to_string())
+        );
+        assert_eq!(
+            python_proxy_auth_header(Some(&AuthScheme::Header("api-key".to_string())), "sk-1"),
+            ("api-key".to_string(), "sk-1".to_string())
+        );
+    }
+
     #[test]
     fn test_all_strategies_are_distinct() {
         let strategies = [
@@ -195,6 +348,7 @@ mod tests {
             AuthStrategy::Bearer,
             AuthStrategy::Google,
             AuthStrategy::GoogleOAuth,
+            AuthStrategy::AzureApiKey,
         ];
 
         for (i, s1) in strategies.iter().enumerate() {
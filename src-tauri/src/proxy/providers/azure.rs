@@ -0,0 +1,197 @@
+//! Azure OpenAI 端点
+//!
+//! Azure OpenAI 按部署（deployment）而不是请求体中的 model 路由：
+//! `{base}/openai/deployments/{deployment}/chat/completions?api-version=...`，认证头为 `api-key`。
+//!
+//! Codex 供应商在 `settings_config.azure` 中配置模型 → 部署映射与 API 版本：
+//!
+//! ```json
+//! { "azure": { "deploymentMap": { "gpt-4o": "prod-gpt4o" }, "apiVersion": "2024-10-21" } }
+//! ```
+//!
+//! 配置存在时转发、测速与模型解析都走 Azure 路径；模型列表直接取映射的 key，不请求 `/v1/models`。
+
+use crate::provider::Provider;
+use crate::proxy::error::ProxyError;
+use std::collections::BTreeMap;
+
+/// Azure OpenAI 配置（`settings_config.azure`）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AzureConfig {
+    /// 模型 → 部署名
+    pub deployment_map: BTreeMap<String, String>,
+    /// `api-version` 查询参数
+    pub api_version: String,
+}
+
+/// 部署名与 api-version 只允许出现在 URL 中不需要转义的字符
+fn is_url_safe(s: &str) -> bool {
+    !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+impl AzureConfig {
+    /// 读取 `settings_config.azure`（配置无效时记录警告并忽略）
+    pub fn from_provider(provider: &Provider) -> Option<Self> {
+        let azure = provider
+            .settings_config
+            .get("azure")
+            .filter(|v| !v.is_null())?;
+
+        let api_version = azure
+            .get("apiVersion")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|v| is_url_safe(v));
+        let deployment_map: BTreeMap<String, String> = azure
+            .get("deploymentMap")
+            .and_then(|v| v.as_object())
+            .map(|map| {
+                map.iter()
+                    .filter_map(|(model, deployment)| {
+                        let model = model.trim();
+                        let deployment = deployment.as_str()?.trim();
+                        (!model.is_empty() && is_url_safe(deployment))
+                            .then(|| (model.to_string(), deployment.to_string()))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        match api_version {
+            Some(api_version) if !deployment_map.is_empty() => Some(Self {
+                deployment_map,
+                api_version: api_version.to_string(),
+            }),
+            _ => {
+                log::warn!(
+                    "[Azure] Provider {} 的 azure 配置无效（需要 apiVersion 与非空 deploymentMap），已忽略: {azure}",
+                    provider.id
+                );
+                None
+            }
+        }
+    }
+
+    /// 可用模型（映射的 key）
+    pub fn models(&self) -> Vec<String> {
+        self.deployment_map.keys().cloned().collect()
+    }
+
+    /// 按模型查找部署（忽略大小写）
+    pub fn deployment_for(&self, model: &str) -> Option<&str> {
+        let model = model.trim();
+        self.deployment_map
+            .iter()
+            .find(|(m, _)| m.eq_ignore_ascii_case(model))
+            .map(|(_, deployment)| deployment.as_str())
+    }
+
+    /// 首个部署（探测请求的模型不在映射中时使用）
+    pub fn first_deployment(&self) -> Option<&str> {
+        self.deployment_map.values().next().map(String::as_str)
+    }
+
+    /// 构建部署 URL：`/v1/chat/completions` → `{base}/openai/deployments/{deployment}/chat/completions`
+    ///
+    /// base_url 可以带或不带 `/openai` 后缀。
+    pub fn build_url(&self, base_url: &str, endpoint: &str, deployment: &str) -> String {
+        let base = base_url.trim_end_matches('/');
+        let base = base.strip_suffix("/openai").unwrap_or(base);
+        let endpoint = endpoint.trim_start_matches('/');
+        let endpoint = endpoint.strip_prefix("v1/").unwrap_or(endpoint);
+        format!(
+            "{base}/openai/deployments/{deployment}/{endpoint}?api-version={}",
+            self.api_version
+        )
+    }
+
+    /// 按请求体中的模型构建 URL；映射中没有该模型时返回配置错误
+    pub fn request_url(
+        &self,
+        base_url: &str,
+        endpoint: &str,
+        model: Option<&str>,
+    ) -> Result<String, ProxyError> {
+        let model = model.unwrap_or_default();
+        let deployment = self.deployment_for(model).ok_or_else(|| {
+            ProxyError::ConfigError(format!(
+                "Azure deploymentMap 中没有模型 {model} 对应的部署（可用: {}）",
+                self.models().join(", ")
+            ))
+        })?;
+        Ok(self.build_url(base_url, endpoint, deployment))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn provider(azure: serde_json::Value) -> Provider {
+        Provider::with_id(
+            "az".to_string(),
+            "az".to_string(),
+            json!({ "base_url": "https://res.openai.azure.com", "azure": azure }),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_from_provider() {
+        let config = AzureConfig::from_provider(&provider(json!({
+            "deploymentMap": { "gpt-4o": "prod-gpt4o", "gpt-4o-mini": " mini ", "bad": "a/b" },
+            "apiVersion": "2024-10-21"
+        })))
+        .unwrap();
+        assert_eq!(config.api_version, "2024-10-21");
+        assert_eq!(config.models(), vec!["gpt-4o", "gpt-4o-mini"]);
+        assert_eq!(config.deployment_for("GPT-4o-mini"), Some("mini"));
+        assert_eq!(config.deployment_for("gpt-5"), None);
+
+        assert!(AzureConfig::from_provider(&provider(json!({
+            "deploymentMap": { "gpt-4o": "prod-gpt4o" }
+        })))
+        .is_none());
+        assert!(AzureConfig::from_provider(&provider(json!({
+            "deploymentMap": {},
+            "apiVersion": "2024-10-21"
+        })))
+        .is_none());
+        assert!(AzureConfig::from_provider(&provider(serde_json::Value::Null)).is_none());
+    }
+
+    #[test]
+    fn test_build_url() {
+        let config = AzureConfig {
+            deployment_map: BTreeMap::from([("gpt-4o".to_string(), "prod-gpt4o".to_string())]),
+            api_version: "2024-10-21".to_string(),
+        };
+        let expected = "https://res.openai.azure.com/openai/deployments/prod-gpt4o/chat/completions?api-version=2024-10-21";
+        for base in [
+            "https://res.openai.azure.com",
+            "https://res.openai.azure.com/",
+            "https://res.openai.azure.com/openai",
+        ] {
+            assert_eq!(
+                config
+                    .request_url(base, "/v1/chat/completions", Some("gpt-4o"))
+                    .unwrap(),
+                expected
+            );
+        }
+        assert_eq!(
+            config.build_url("https://res.openai.azure.com", "/v1/responses", "prod-gpt4o"),
+            "https://res.openai.azure.com/openai/deployments/prod-gpt4o/responses?api-version=2024-10-21"
+        );
+        assert!(config
+            .request_url(
+                "https://res.openai.azure.com",
+                "/v1/chat/completions",
+                Some("gpt-5")
+            )
+            .is_err());
+    }
+}
@@ -0,0 +1,709 @@
+//! AWS Bedrock（Claude）
+//!
+//! Claude 供应商配置了 `settings_config.bedrock` 时，请求不经过 Python 代理，而是直接发往
+//! `bedrock-runtime.{region}.amazonaws.com`：
+//! - 请求体：去掉 `model` / `stream`，补充 `anthropic_version`，模型 ID 放在 URL 路径中
+//! - 认证：SigV4 签名（service = `bedrock`）
+//! - 响应：非流式响应体即 Anthropic Messages 格式，直接透传；流式响应为 AWS event-stream
+//!   二进制帧，逐帧解出 Anthropic 事件后转换为 SSE
+//!
+//! ```json
+//! {
+//!   "bedrock": {
+//!     "region": "us-east-1",
+//!     "accessKeyId": "AKIA...",
+//!     "secretAccessKey": "...",
+//!     "sessionToken": "（可选）",
+//!     "modelMap": { "claude-sonnet-4-5-20250929": "us.anthropic.claude-sonnet-4-5-20250929-v1:0" }
+//!   }
+//! }
+//! ```
+
+use crate::provider::Provider;
+use base64::Engine;
+use bytes::{Buf, Bytes, BytesMut};
+use chrono::{DateTime, Utc};
+use futures::{Stream, StreamExt};
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+/// settings_config 中的 Bedrock 配置字段
+pub const BEDROCK_KEY: &str = "bedrock";
+/// Bedrock 上 Anthropic 模型要求的 `anthropic_version`
+pub const BEDROCK_ANTHROPIC_VERSION: &str = "bedrock-2023-05-31";
+/// SigV4 签名使用的服务名
+const SIGNING_SERVICE: &str = "bedrock";
+
+/// Bedrock 配置（`settings_config.bedrock`）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BedrockConfig {
+    pub region: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    /// 临时凭证的 session token
+    pub session_token: Option<String>,
+    /// 自定义端点（VPC 端点等），未配置时使用区域默认端点
+    pub endpoint: Option<String>,
+    /// Anthropic 模型名 → Bedrock 模型 ID（或推理配置文件 ID）
+    pub model_map: BTreeMap<String, String>,
+}
+
+impl BedrockConfig {
+    /// 读取 `settings_config.bedrock`（配置无效时记录警告并忽略）
+    pub fn from_provider(provider: &Provider) -> Option<Self> {
+        let bedrock = provider
+            .settings_config
+            .get(BEDROCK_KEY)
+            .filter(|v| !v.is_null())?;
+        let field = |name: &str| {
+            bedrock
+                .get(name)
+                .and_then(|v| v.as_str())
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_string)
+        };
+
+        let (Some(region), Some(access_key_id), Some(secret_access_key)) = (
+            field("region"),
+            field("accessKeyId"),
+            field("secretAccessKey"),
+        ) else {
+            log::warn!(
+                "[Bedrock] Provider {} 的 bedrock 配置无效（需要 region、accessKeyId、secretAccessKey），已忽略",
+                provider.id
+            );
+            return None;
+        };
+
+        let model_map = bedrock
+            .get("modelMap")
+            .and_then(|v| v.as_object())
+            .map(|map| {
+                map.iter()
+                    .filter_map(|(model, id)| {
+                        let id = id.as_str()?.trim();
+                        (!id.is_empty()).then(|| (model.trim().to_string(), id.to_string()))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Some(Self {
+            region,
+            access_key_id,
+            secret_access_key,
+            session_token: field("sessionToken"),
+            endpoint: field("endpoint").map(|e| e.trim_end_matches('/').to_string()),
+            model_map,
+        })
+    }
+
+    /// 端点基础 URL
+    pub fn base_url(&self) -> String {
+        self.endpoint
+            .clone()
+            .unwrap_or_else(|| format!("https://bedrock-runtime.{}.amazonaws.com", self.region))
+    }
+
+    /// Anthropic 模型名 → Bedrock 模型 ID
+    ///
+    /// 优先查 `modelMap`（忽略大小写）；已经是 Bedrock ID（含 `anthropic.`）时原样使用；
+    /// 否则按 `anthropic.{model}-v1:0` 拼接。
+    pub fn model_id(&self, model: &str) -> String {
+        let model = model.trim();
+        if let Some((_, id)) = self
+            .model_map
+            .iter()
+            .find(|(m, _)| m.eq_ignore_ascii_case(model))
+        {
+            return id.clone();
+        }
+        if model.contains("anthropic.") {
+            return model.to_string();
+        }
+        format!("anthropic.{model}-v1:0")
+    }
+
+    /// InvokeModel / InvokeModelWithResponseStream 的 URL
+    pub fn invoke_url(&self, model_id: &str, stream: bool) -> String {
+        let action = if stream {
+            "invoke-with-response-stream"
+        } else {
+            "invoke"
+        };
+        format!(
+            "{}/model/{}/{action}",
+            self.base_url(),
+            uri_encode(model_id)
+        )
+    }
+
+    /// 对 POST 请求做 SigV4 签名，返回需要附加的请求头
+    pub fn sign(
+        &self,
+        url: &str,
+        payload: &[u8],
+        now: DateTime<Utc>,
+    ) -> Result<Vec<(String, String)>, String> {
+        sign_v4(
+            &SigningParams {
+                access_key_id: &self.access_key_id,
+                secret_access_key: &self.secret_access_key,
+                session_token: self.session_token.as_deref(),
+                region: &self.region,
+                service: SIGNING_SERVICE,
+            },
+            "POST",
+            url,
+            payload,
+            now,
+        )
+    }
+}
+
+/// Anthropic Messages 请求体 → Bedrock InvokeModel 请求体
+///
+/// 返回（请求中的模型名, Bedrock 请求体）。
+pub fn to_bedrock_body(body: &Value) -> (String, Value) {
+    let mut body = body.clone();
+    let model = body
+        .get("model")
+        .and_then(|m| m.as_str())
+        .unwrap_or_default()
+        .to_string();
+    if let Some(obj) = body.as_object_mut() {
+        obj.remove("model");
+        obj.remove("stream");
+        obj.insert(
+            "anthropic_version".to_string(),
+            json!(BEDROCK_ANTHROPIC_VERSION),
+        );
+    }
+    (model, body)
+}
+
+// ============================================================================
+// SigV4
+// ============================================================================
+
+pub struct SigningParams<'a> {
+    pub access_key_id: &'a str,
+    pub secret_access_key: &'a str,
+    pub session_token: Option<&'a str>,
+    pub region: &'a str,
+    pub service: &'a str,
+}
+
+/// AWS URI 编码：保留 `A-Za-z0-9-_.~`，其余按 `%XX` 编码
+fn uri_encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b'~') {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{b:02X}"));
+        }
+    }
+    out
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC 接受任意长度的 key");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// SigV4 签名（签名头：host、x-amz-date，以及存在时的 x-amz-security-token）
+///
+/// 返回需要附加到请求上的头（不含 host，由 HTTP 客户端按 URL 生成）。
+pub fn sign_v4(
+    params: &SigningParams<'_>,
+    method: &str,
+    url: &str,
+    payload: &[u8],
+    now: DateTime<Utc>,
+) -> Result<Vec<(String, String)>, String> {
+    let url = url::Url::parse(url).map_err(|e| format!("无效的 URL {url}: {e}"))?;
+    let host = match (url.host_str(), url.port()) {
+        (Some(host), Some(port)) => format!("{host}:{port}"),
+        (Some(host), None) => host.to_string(),
+        _ => return Err(format!("URL 缺少主机名: {url}")),
+    };
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+
+    // 非 S3 服务：路径中每个分段再编码一次
+    let canonical_uri = url
+        .path()
+        .split('/')
+        .map(uri_encode)
+        .collect::<Vec<_>>()
+        .join("/");
+    let mut query: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(k, v)| (uri_encode(&k), uri_encode(&v)))
+        .collect();
+    query.sort();
+    let canonical_query = query
+        .iter()
+        .map(|(k, v)| format!("{k}={v}"))
+        .collect::<Vec<_>>()
+        .join("&");
+
+    let mut headers = vec![
+        ("host".to_string(), host),
+        ("x-amz-date".to_string(), amz_date.clone()),
+    ];
+    if let Some(token) = params.session_token {
+        headers.push(("x-amz-security-token".to_string(), token.to_string()));
+    }
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(k, v)| format!("{k}:{}\n", v.trim()))
+        .collect();
+    let signed_headers = headers
+        .iter()
+        .map(|(k, _)| k.as_str())
+        .collect::<Vec<_>>()
+        .join(";");
+
+    let canonical_request = format!(
+        "{method}\n{canonical_uri}\n{canonical_query}\n{canonical_headers}\n{signed_headers}\n{}",
+        hex(&Sha256::digest(payload))
+    );
+    let scope = format!("{date}/{}/{}/aws4_request", params.region, params.service);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+        hex(&Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let key = hmac_sha256(
+        format!("AWS4{}", params.secret_access_key).as_bytes(),
+        date.as_bytes(),
+    );
+    let key = hmac_sha256(&key, params.region.as_bytes());
+    let key = hmac_sha256(&key, params.service.as_bytes());
+    let key = hmac_sha256(&key, b"aws4_request");
+    let signature = hex(&hmac_sha256(&key, string_to_sign.as_bytes()));
+
+    let mut out: Vec<(String, String)> = headers.into_iter().skip(1).collect();
+    out.push((
+        "authorization".to_string(),
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+            params.access_key_id
+        ),
+    ));
+    Ok(out)
+}
+
+// ============================================================================
+// event-stream → SSE
+// ============================================================================
+
+/// event-stream 帧
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventStreamFrame {
+    /// 字符串类型的头（`:message-type`、`:event-type`、`:exception-type` 等）
+    pub headers: BTreeMap<String, String>,
+    pub payload: Bytes,
+}
+
+/// AWS event-stream 增量解码器
+///
+/// 帧格式：总长度(u32) | 头长度(u32) | 前导 CRC(u32) | 头 | 负载 | 帧 CRC(u32)，均为大端。
+#[derive(Default)]
+pub struct EventStreamDecoder {
+    buf: BytesMut,
+}
+
+impl EventStreamDecoder {
+    /// 追加数据并返回其中完整的帧
+    pub fn push(&mut self, data: &[u8]) -> Result<Vec<EventStreamFrame>, String> {
+        self.buf.extend_from_slice(data);
+        let mut frames = Vec::new();
+        while self.buf.len() >= 12 {
+            let total = u32::from_be_bytes(self.buf[0..4].try_into().unwrap()) as usize;
+            let headers_len = u32::from_be_bytes(self.buf[4..8].try_into().unwrap()) as usize;
+            if total < 16 + headers_len {
+                return Err(format!("event-stream 帧长度无效: {total}"));
+            }
+            let prelude_crc = u32::from_be_bytes(self.buf[8..12].try_into().unwrap());
+            if crc32fast::hash(&self.buf[0..8]) != prelude_crc {
+                return Err("event-stream 前导 CRC 校验失败".to_string());
+            }
+            if self.buf.len() < total {
+                break;
+            }
+
+            let frame = self.buf.split_to(total).freeze();
+            let message_crc = u32::from_be_bytes(frame[total - 4..].try_into().unwrap());
+            if crc32fast::hash(&frame[..total - 4]) != message_crc {
+                return Err("event-stream 帧 CRC 校验失败".to_string());
+            }
+            let headers = parse_headers(&frame[12..12 + headers_len])?;
+            frames.push(EventStreamFrame {
+                headers,
+                payload: frame.slice(12 + headers_len..total - 4),
+            });
+        }
+        Ok(frames)
+    }
+}
+
+fn parse_headers(mut raw: &[u8]) -> Result<BTreeMap<String, String>, String> {
+    let invalid = || "event-stream 头格式无效".to_string();
+    let mut headers = BTreeMap::new();
+    while raw.has_remaining() {
+        let name_len = raw.get_u8() as usize;
+        if raw.remaining() < name_len + 1 {
+            return Err(invalid());
+        }
+        let name = String::from_utf8_lossy(&raw[..name_len]).to_string();
+        raw.advance(name_len);
+        // 只保留字符串类型（7），其余按类型长度跳过
+        let value_len = match raw.get_u8() {
+            0 | 1 => 0,
+            2 => 1,
+            3 => 2,
+            4 => 4,
+            5 | 8 => 8,
+            9 => 16,
+            6 | 7 => {
+                if raw.remaining() < 2 {
+                    return Err(invalid());
+                }
+                raw.get_u16() as usize
+            }
+            _ => return Err(invalid()),
+        };
+        if raw.remaining() < value_len {
+            return Err(invalid());
+        }
+        headers.insert(name, String::from_utf8_lossy(&raw[..value_len]).to_string());
+        raw.advance(value_len);
+    }
+    Ok(headers)
+}
+
+/// 单帧 → SSE 文本（不需要转发的帧返回 None）
+///
+/// - `chunk` 事件：负载为 `{"bytes": "<base64 Anthropic 事件>"}`
+/// - 异常帧：转换为 Anthropic `error` 事件
+pub fn frame_to_sse(frame: &EventStreamFrame) -> Option<String> {
+    let payload: Value = serde_json::from_slice(&frame.payload).ok()?;
+    match frame.headers.get(":message-type").map(String::as_str) {
+        Some("event") => {
+            let encoded = payload.get("bytes").and_then(|b| b.as_str())?;
+            let decoded = base64::engine::general_purpose::STANDARD
+                .decode(encoded)
+                .ok()?;
+            let event: Value = serde_json::from_slice(&decoded).ok()?;
+            let event_type = event.get("type").and_then(|t| t.as_str())?;
+            Some(format!("event: {event_type}\ndata: {event}\n\n"))
+        }
+        Some("exception") => {
+            let error_type = frame
+                .headers
+                .get(":exception-type")
+                .cloned()
+                .unwrap_or_else(|| "api_error".to_string());
+            let message = payload
+                .get("message")
+                .or_else(|| payload.get("Message"))
+                .and_then(|m| m.as_str())
+                .unwrap_or_default();
+            let event = json!({
+                "type": "error",
+                "error": { "type": error_type, "message": message }
+            });
+            Some(format!("event: error\ndata: {event}\n\n"))
+        }
+        _ => None,
+    }
+}
+
+/// Bedrock 流式响应（event-stream）→ Anthropic SSE
+pub fn create_bedrock_sse_stream(
+    stream: impl Stream<Item = Result<Bytes, reqwest::Error>> + Send + 'static,
+) -> impl Stream<Item = Result<Bytes, std::io::Error>> + Send {
+    async_stream::stream! {
+        let mut decoder = EventStreamDecoder::default();
+        tokio::pin!(stream);
+
+        while let Some(chunk) = stream.next().await {
+            let bytes = match chunk {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    yield Err(std::io::Error::other(e));
+                    return;
+                }
+            };
+            match decoder.push(&bytes) {
+                Ok(frames) => {
+                    for frame in frames {
+                        if let Some(sse) = frame_to_sse(&frame) {
+                            yield Ok(Bytes::from(sse));
+                        }
+                    }
+                }
+                Err(e) => {
+                    log::error!("[Bedrock] 解析 event-stream 失败: {e}");
+                    yield Err(std::io::Error::new(std::io::ErrorKind::InvalidData, e));
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    /// 按 event-stream 格式编码一帧（仅字符串头）
+    pub(crate) fn encode_frame(headers: &[(&str, &str)], payload: &[u8]) -> Vec<u8> {
+        let mut raw_headers = Vec::new();
+        for (name, value) in headers {
+            raw_headers.push(name.len() as u8);
+            raw_headers.extend_from_slice(name.as_bytes());
+            raw_headers.push(7);
+            raw_headers.extend_from_slice(&(value.len() as u16).to_be_bytes());
+            raw_headers.extend_from_slice(value.as_bytes());
+        }
+        let total = 16 + raw_headers.len() + payload.len();
+        let mut frame = Vec::with_capacity(total);
+        frame.extend_from_slice(&(total as u32).to_be_bytes());
+        frame.extend_from_slice(&(raw_headers.len() as u32).to_be_bytes());
+        let prelude_crc = crc32fast::hash(&frame);
+        frame.extend_from_slice(&prelude_crc.to_be_bytes());
+        frame.extend_from_slice(&raw_headers);
+        frame.extend_from_slice(payload);
+        let message_crc = crc32fast::hash(&frame);
+        frame.extend_from_slice(&message_crc.to_be_bytes());
+        frame
+    }
+
+    /// Bedrock `chunk` 事件帧
+    pub(crate) fn chunk_frame(event: &Value) -> Vec<u8> {
+        let encoded = base64::engine::general_purpose::STANDARD.encode(event.to_string());
+        encode_frame(
+            &[
+                (":event-type", "chunk"),
+                (":content-type", "application/json"),
+                (":message-type", "event"),
+            ],
+            json!({ "bytes": encoded }).to_string().as_bytes(),
+        )
+    }
+
+    fn example_params() -> SigningParams<'static> {
+        SigningParams {
+            access_key_id: "AKIDEXAMPLE",
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            session_token: None,
+            region: "us-east-1",
+            service: "service",
+        }
+    }
+
+    #[test]
+    fn test_sigv4_matches_aws_test_suite() {
+        // aws-sig-v4-test-suite: get-vanilla / post-vanilla
+        let now = Utc.with_ymd_and_hms(2015, 8, 30, 12, 36, 0).unwrap();
+        let params = example_params();
+
+        let headers = sign_v4(&params, "GET", "https://example.amazonaws.com/", b"", now).unwrap();
+        assert_eq!(
+            headers[0],
+            ("x-amz-date".to_string(), "20150830T123600Z".to_string())
+        );
+        assert_eq!(
+            headers[1].1,
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, SignedHeaders=host;x-amz-date, Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+
+        let headers = sign_v4(&params, "POST", "https://example.amazonaws.com/", b"", now).unwrap();
+        assert!(headers[1].1.ends_with(
+            "Signature=5da7c1a2acd57cee7505fc6676e4e544621c30862966e37dddb68e92efbe5d6b"
+        ));
+    }
+
+    #[test]
+    fn test_bedrock_invoke_url_and_signature() {
+        let config = BedrockConfig {
+            region: "us-east-1".to_string(),
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            session_token: Some("session".to_string()),
+            endpoint: None,
+            model_map: BTreeMap::new(),
+        };
+        let url = config.invoke_url("anthropic.claude-3-5-haiku-20241022-v1:0", false);
+        assert_eq!(
+            url,
+            "https://bedrock-runtime.us-east-1.amazonaws.com/model/anthropic.claude-3-5-haiku-20241022-v1%3A0/invoke"
+        );
+
+        let now = Utc.with_ymd_and_hms(2025, 1, 2, 3, 4, 5).unwrap();
+        let headers = config.sign(&url, br#"{"max_tokens":1}"#, now).unwrap();
+        let names: Vec<&str> = headers.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(
+            names,
+            ["x-amz-date", "x-amz-security-token", "authorization"]
+        );
+        assert_eq!(
+            headers[2].1,
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20250102/us-east-1/bedrock/aws4_request, SignedHeaders=host;x-amz-date;x-amz-security-token, Signature=7b93b1d2d26fad4c02e783b4672e57a05a6500e24b6ec8344a79903778c3cd6e"
+        );
+    }
+
+    #[test]
+    fn test_model_id_and_body() {
+        let provider = Provider::with_id(
+            "br".to_string(),
+            "br".to_string(),
+            json!({
+                "bedrock": {
+                    "region": "us-west-2",
+                    "accessKeyId": "AKID",
+                    "secretAccessKey": "secret",
+                    "modelMap": { "claude-sonnet-4-5-20250929": "us.anthropic.claude-sonnet-4-5-20250929-v1:0" }
+                }
+            }),
+            None,
+        );
+        let config = BedrockConfig::from_provider(&provider).unwrap();
+        assert_eq!(
+            config.base_url(),
+            "https://bedrock-runtime.us-west-2.amazonaws.com"
+        );
+        assert_eq!(
+            config.model_id("claude-sonnet-4-5-20250929"),
+            "us.anthropic.claude-sonnet-4-5-20250929-v1:0"
+        );
+        assert_eq!(
+            config.model_id("anthropic.claude-3-haiku-20240307-v1:0"),
+            "anthropic.claude-3-haiku-20240307-v1:0"
+        );
+        assert_eq!(
+            config.model_id("claude-3-5-haiku-20241022"),
+            "anthropic.claude-3-5-haiku-20241022-v1:0"
+        );
+
+        let (model, body) = to_bedrock_body(&json!({
+            "model": "claude-3-5-haiku-20241022",
+            "stream": true,
+            "max_tokens": 16,
+            "messages": [{"role": "user", "content": "hi"}]
+        }));
+        assert_eq!(model, "claude-3-5-haiku-20241022");
+        assert_eq!(
+            body,
+            json!({
+                "anthropic_version": BEDROCK_ANTHROPIC_VERSION,
+                "max_tokens": 16,
+                "messages": [{"role": "user", "content": "hi"}]
+            })
+        );
+
+        // 缺少凭证：忽略
+        let incomplete = Provider::with_id(
+            "br2".to_string(),
+            "br2".to_string(),
+            json!({ "bedrock": { "region": "us-east-1" } }),
+            None,
+        );
+        assert!(BedrockConfig::from_provider(&incomplete).is_none());
+    }
+
+    #[test]
+    fn test_event_stream_decoder_handles_split_frames() {
+        let start = json!({"type": "message_start", "message": {"id": "msg_1"}});
+        let delta =
+            json!({"type": "content_block_delta", "delta": {"type": "text_delta", "text": "hi"}});
+        let mut data = chunk_frame(&start);
+        data.extend(chunk_frame(&delta));
+        data.extend(encode_frame(
+            &[
+                (":exception-type", "throttlingException"),
+                (":message-type", "exception"),
+            ],
+            br#"{"message":"Too many requests"}"#,
+        ));
+
+        // 按 7 字节切分喂入，帧跨越多个分片
+        let mut decoder = EventStreamDecoder::default();
+        let mut sse = String::new();
+        for piece in data.chunks(7) {
+            for frame in decoder.push(piece).unwrap() {
+                sse.push_str(&frame_to_sse(&frame).unwrap());
+            }
+        }
+        assert_eq!(
+            sse,
+            format!(
+                "event: message_start\ndata: {start}\n\nevent: content_block_delta\ndata: {delta}\n\nevent: error\ndata: {}\n\n",
+                json!({"type": "error", "error": {"type": "throttlingException", "message": "Too many requests"}})
+            )
+        );
+
+        // CRC 损坏
+        let mut corrupted = chunk_frame(&start);
+        let last = corrupted.len() - 1;
+        corrupted[last] ^= 0xff;
+        assert!(EventStreamDecoder::default().push(&corrupted).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_bedrock_sse_stream_from_canned_response() {
+        use axum::{routing::post, Router};
+
+        let events = [
+            json!({"type": "message_start", "message": {"id": "msg_1", "usage": {"input_tokens": 5}}}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "pong"}}),
+            json!({"type": "message_delta", "delta": {"stop_reason": "end_turn"}, "usage": {"output_tokens": 1}}),
+            json!({"type": "message_stop"}),
+        ];
+        let body: Vec<u8> = events.iter().flat_map(chunk_frame).collect();
+        let app = Router::new().route(
+            "/model/:model_id/invoke-with-response-stream",
+            post(move || async move { body }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+
+        let response = reqwest::Client::new()
+            .post(format!(
+                "http://{addr}/model/anthropic.claude-3-5-haiku-20241022-v1%3A0/invoke-with-response-stream"
+            ))
+            .send()
+            .await
+            .unwrap();
+        let chunks: Vec<Bytes> = create_bedrock_sse_stream(response.bytes_stream())
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        let sse: String = chunks
+            .iter()
+            .map(|c| String::from_utf8_lossy(c).to_string())
+            .collect();
+
+        let expected: String = events
+            .iter()
+            .map(|e| format!("event: {}\ndata: {e}\n\n", e["type"].as_str().unwrap()))
+            .collect();
+        assert_eq!(sse, expected);
+    }
+}
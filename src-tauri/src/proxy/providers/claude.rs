@@ -6,8 +6,11 @@
 //! - **Claude**: Anthropic 官方 API (x-api-key + anthropic-version)
 //! - **ClaudeAuth**: 中转服务 (仅 Bearer 认证，无 x-api-key)
 //! - **OpenRouter**: 已支持 Claude Code 兼容接口，默认透传（保留旧转换逻辑备用）
+//!
+//! `settings_config.auth` 显式配置了认证方式（x-api-key / bearer / 自定义头）时，
+//! 只发送该认证头，优先于以上推断。
 
-use super::{AuthInfo, AuthStrategy, ProviderAdapter, ProviderType};
+use super::{AuthInfo, AuthScheme, AuthStrategy, ProviderAdapter, ProviderType};
 use crate::provider::Provider;
 use crate::proxy::error::ProxyError;
 use reqwest::RequestBuilder;
@@ -205,8 +208,9 @@ impl ProviderAdapter for ClaudeAdapter {
             _ => AuthStrategy::Anthropic,
         };
 
-        self.extract_key(provider)
-            .map(|key| AuthInfo::new(key, strategy))
+        self.extract_key(provider).map(|key| {
+            AuthInfo::new(key, strategy).with_scheme(AuthScheme::from_provider(provider))
+        })
     }
 
     fn build_url(&self, base_url: &str, endpoint: &str) -> String {
@@ -225,6 +229,14 @@ impl ProviderAdapter for ClaudeAdapter {
     }
 
     fn add_auth_headers(&self, request: RequestBuilder, auth: &AuthInfo) -> RequestBuilder {
+        // 显式配置的认证方式：只发送该认证头
+        if let Some(scheme) = &auth.scheme {
+            let (name, value) = scheme.header(&auth.api_key);
+            return request
+                .header(name, value)
+                .header("anthropic-version", "2023-06-01");
+        }
+
         match auth.strategy {
             // Anthropic 官方: Authorization Bearer + x-api-key + anthropic-version
             AuthStrategy::Anthropic => request
@@ -453,4 +465,79 @@ mod tests {
         }));
         assert!(!adapter.needs_transform(&openrouter_disabled));
     }
+
+    /// 回显收到的请求头（小写名称 → 值）
+    async fn spawn_header_echo() -> String {
+        use axum::{http::HeaderMap, routing::post, Json, Router};
+
+        let app = Router::new().route(
+            "/v1/messages",
+            post(|headers: HeaderMap| async move {
+                let echoed: serde_json::Map<String, serde_json::Value> = headers
+                    .iter()
+                    .map(|(k, v)| {
+                        (
+                            k.as_str().to_string(),
+                            json!(v.to_str().unwrap_or_default()),
+                        )
+                    })
+                    .collect();
+                Json(serde_json::Value::Object(echoed))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+        format!("http://{addr}")
+    }
+
+    #[tokio::test]
+    async fn test_explicit_auth_scheme_reaches_upstream() {
+        let adapter = ClaudeAdapter::new();
+        let url = spawn_header_echo().await;
+        let client = reqwest::Client::new();
+
+        let send = |auth: serde_json::Value| {
+            let provider = create_provider(json!({
+                "env": {
+                    "ANTHROPIC_BASE_URL": url,
+                    "ANTHROPIC_AUTH_TOKEN": "sk-gw-key"
+                },
+                "auth": auth
+            }));
+            let auth = adapter.extract_auth(&provider).expect("auth");
+            let request = adapter
+                .add_auth_headers(client.post(adapter.build_url(&url, "/v1/messages")), &auth);
+            async move {
+                request
+                    .send()
+                    .await
+                    .expect("send")
+                    .json::<serde_json::Value>()
+                    .await
+                    .expect("json")
+            }
+        };
+
+        let headers = send(json!({"scheme": "x-api-key"})).await;
+        assert_eq!(headers["x-api-key"], "sk-gw-key");
+        assert!(headers.get("authorization").is_none());
+        assert_eq!(headers["anthropic-version"], "2023-06-01");
+
+        let headers = send(json!({"scheme": "bearer"})).await;
+        assert_eq!(headers["authorization"], "Bearer sk-gw-key");
+        assert!(headers.get("x-api-key").is_none());
+
+        let headers = send(json!({"scheme": "header", "headerName": "api-key"})).await;
+        assert_eq!(headers["api-key"], "sk-gw-key");
+        assert!(headers.get("x-api-key").is_none());
+        assert!(headers.get("authorization").is_none());
+
+        // 未配置：沿用 Anthropic 默认（Bearer + x-api-key）
+        let headers = send(serde_json::Value::Null).await;
+        assert_eq!(headers["x-api-key"], "sk-gw-key");
+        assert_eq!(headers["authorization"], "Bearer sk-gw-key");
+    }
 }
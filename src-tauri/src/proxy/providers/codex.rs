@@ -4,8 +4,11 @@
 //!
 //! ## 客户端检测
 //! 支持检测官方 Codex 客户端 (codex_vscode, codex_cli_rs)
+//!
+//! ## Azure OpenAI
+//! 配置了 `settings_config.azure` 时使用 `api-key` 认证头，URL 按部署构建（见 [`AzureConfig`]）
 
-use super::{AuthInfo, AuthStrategy, ProviderAdapter};
+use super::{AuthInfo, AuthStrategy, AzureConfig, ProviderAdapter};
 use crate::provider::Provider;
 use crate::proxy::error::ProxyError;
 use regex::Regex;
@@ -133,8 +136,13 @@ impl ProviderAdapter for CodexAdapter {
     }
 
     fn extract_auth(&self, provider: &Provider) -> Option<AuthInfo> {
+        let strategy = if AzureConfig::from_provider(provider).is_some() {
+            AuthStrategy::AzureApiKey
+        } else {
+            AuthStrategy::Bearer
+        };
         self.extract_key(provider)
-            .map(|key| AuthInfo::new(key, AuthStrategy::Bearer))
+            .map(|key| AuthInfo::new(key, strategy))
     }
 
     fn build_url(&self, base_url: &str, endpoint: &str) -> String {
@@ -152,7 +160,11 @@ impl ProviderAdapter for CodexAdapter {
     }
 
     fn add_auth_headers(&self, request: RequestBuilder, auth: &AuthInfo) -> RequestBuilder {
-        request.header("Authorization", format!("Bearer {}", auth.api_key))
+        match auth.strategy {
+            // Azure OpenAI: api-key
+            AuthStrategy::AzureApiKey => request.header("api-key", &auth.api_key),
+            _ => request.header("Authorization", format!("Bearer {}", auth.api_key)),
+        }
     }
 }
 
@@ -231,6 +243,80 @@ mod tests {
         assert_eq!(url, "https://www.packyapi.com/v1/responses");
     }
 
+    #[tokio::test]
+    async fn test_azure_request_reaches_deployment() {
+        use axum::{extract::Path, http::HeaderMap, routing::post, Json, Router};
+
+        // Azure 形态的上游：回显部署名、查询串与认证头
+        let app = Router::new().route(
+            "/openai/deployments/:deployment/chat/completions",
+            post(
+                |Path(deployment): Path<String>,
+                 uri: axum::http::Uri,
+                 headers: HeaderMap| async move {
+                    let header = |name: &str| {
+                        headers
+                            .get(name)
+                            .and_then(|v| v.to_str().ok())
+                            .map(str::to_string)
+                    };
+                    Json(json!({
+                        "deployment": deployment,
+                        "query": uri.query(),
+                        "api_key": header("api-key"),
+                        "authorization": header("authorization"),
+                    }))
+                },
+            ),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+        let base_url = format!("http://{addr}");
+
+        let adapter = CodexAdapter::new();
+        let provider = create_provider(json!({
+            "base_url": base_url,
+            "env": { "OPENAI_API_KEY": "az-key" },
+            "azure": {
+                "deploymentMap": { "gpt-4o": "prod-4o", "gpt-4o-mini": "prod-mini" },
+                "apiVersion": "2024-10-21"
+            }
+        }));
+        let auth = adapter.extract_auth(&provider).unwrap();
+        assert_eq!(auth.strategy, AuthStrategy::AzureApiKey);
+
+        let azure = AzureConfig::from_provider(&provider).unwrap();
+        let url = azure
+            .request_url(
+                &adapter.extract_base_url(&provider).unwrap(),
+                "/v1/chat/completions",
+                Some("gpt-4o-mini"),
+            )
+            .unwrap();
+        let echoed: serde_json::Value = adapter
+            .add_auth_headers(reqwest::Client::new().post(url), &auth)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(echoed["deployment"], "prod-mini");
+        assert_eq!(echoed["query"], "api-version=2024-10-21");
+        assert_eq!(echoed["api_key"], "az-key");
+        assert!(echoed["authorization"].is_null());
+
+        // 未配置 azure：仍为 Bearer
+        let plain = create_provider(json!({ "env": { "OPENAI_API_KEY": "sk-1" } }));
+        assert_eq!(
+            adapter.extract_auth(&plain).unwrap().strategy,
+            AuthStrategy::Bearer
+        );
+    }
+
     // 官方客户端检测测试
     #[test]
     fn test_is_official_client_vscode() {
@@ -5,20 +5,26 @@
 //! ## 模块结构
 //! - `adapter`: 定义 `ProviderAdapter` trait
 //! - `auth`: 认证类型和策略
+//! - `azure`: Azure OpenAI 部署路由（Codex）
+//! - `bedrock`: AWS Bedrock（Claude，SigV4 + event-stream）
 //! - `claude`: Claude (Anthropic) 适配器
 //! - `codex`: Codex (OpenAI) 适配器
 //! - `gemini`: Gemini (Google) 适配器
 //! - `models`: API 数据模型
 //! - `transform`: 格式转换
+//! - `vertex`: Google Vertex AI（Gemini，服务账号 OAuth）
 
 mod adapter;
 mod auth;
+mod azure;
+mod bedrock;
 mod claude;
 mod codex;
 mod gemini;
 pub mod models;
 pub mod streaming;
 pub mod transform;
+mod vertex;
 
 use crate::app_config::AppType;
 use crate::provider::Provider;
@@ -26,10 +32,15 @@ use serde::{Deserialize, Serialize};
 
 // 公开导出
 pub use adapter::ProviderAdapter;
-pub use auth::{AuthInfo, AuthStrategy};
+pub use auth::{python_proxy_auth_header, AuthInfo, AuthScheme, AuthStrategy, AUTH_KEY};
+pub use azure::AzureConfig;
+pub use bedrock::{create_bedrock_sse_stream, to_bedrock_body, BedrockConfig, BEDROCK_KEY};
 pub use claude::ClaudeAdapter;
 pub use codex::CodexAdapter;
 pub use gemini::GeminiAdapter;
+#[cfg(test)]
+pub(crate) use vertex::tests as vertex_test_support;
+pub use vertex::VertexConfig;
 
 /// 供应商类型枚举
 ///
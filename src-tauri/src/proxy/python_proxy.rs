@@ -1,9 +1,12 @@
-use once_cell::sync::Lazy;
-use regex::Regex;
+use crate::i18n::{Locale, Msg};
+use reqwest::header::HeaderMap;
 
 const DEFAULT_PYTHON_PROXY_BASE: &str = "http://127.0.0.1:15722";
 
-pub(crate) fn python_proxy_base() -> String {
+/// Python 代理回传的真实上游耗时（毫秒，不含本地这一跳）
+pub const UPSTREAM_ELAPSED_HEADER: &str = "x-upstream-elapsed-ms";
+
+pub fn python_proxy_base() -> String {
     std::env::var("CC_SWITCH_PYTHON_PROXY_BASE")
         .ok()
         .map(|s| s.trim().trim_end_matches('/').to_string())
@@ -19,19 +22,82 @@ pub(crate) fn python_proxy_label() -> String {
     }
 }
 
-fn port_from_base(base: &str) -> Option<String> {
-    static RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)://[^/]+:(\d+)$").expect("regex"));
+/// 读取 Python 代理回传的上游耗时；响应头缺失或无法解析时返回 `None`（仅记录总耗时）
+pub(crate) fn upstream_elapsed_ms(headers: &HeaderMap) -> Option<u64> {
+    let value = headers.get(UPSTREAM_ELAPSED_HEADER)?.to_str().ok()?.trim();
+    let ms = value.parse::<f64>().ok()?;
+    (ms.is_finite() && ms >= 0.0).then(|| ms.round() as u64)
+}
 
+/// 耗时拆分：`0.040s 本地 + 2.700s 上游`
+///
+/// 上游耗时可能略大于总耗时（两端计时起点不同），此时本地部分记为 0。
+pub(crate) fn format_hop_split(locale: Locale, total_ms: u64, upstream_ms: u64) -> String {
+    let local = total_ms.saturating_sub(upstream_ms) as f64 / 1000.0;
+    let upstream = upstream_ms as f64 / 1000.0;
+    format!(
+        "{local:.3}s {} + {upstream:.3}s {}",
+        Msg::ForwardLocal.template(locale),
+        Msg::ForwardUpstream.template(locale),
+    )
+}
+
+fn port_from_base(base: &str) -> Option<String> {
     let base = base.trim().trim_end_matches('/');
-    if let Some(c) = RE.captures(base) {
-        return c.get(1).map(|m| m.as_str().to_string());
+    // 兜底：无 scheme 的情况（如 `127.0.0.1:15722`、`[::1]:15722`）按 http 解析
+    let parsed = if base.contains("://") {
+        url::Url::parse(base)
+    } else {
+        url::Url::parse(&format!("http://{base}"))
+    };
+    parsed.ok()?.port().map(|port| port.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn port_from_base_handles_ipv6_and_bare_hosts() {
+        for (base, expected) in [
+            ("http://127.0.0.1:15722", Some("15722")),
+            ("http://127.0.0.1:15722/", Some("15722")),
+            ("http://[::1]:8080", Some("8080")),
+            ("http://[::1]", None),
+            ("127.0.0.1:15722", Some("15722")),
+            ("[::1]:15722", Some("15722")),
+            ("http://python.local:9000/proxy", Some("9000")),
+            ("https://python.local", None),
+        ] {
+            assert_eq!(port_from_base(base).as_deref(), expected, "{base}");
+        }
     }
-    // 兜底：无 scheme 的情况
-    if let Some((_, port)) = base.rsplit_once(':') {
-        if port.chars().all(|c| c.is_ascii_digit()) {
-            return Some(port.to_string());
+
+    #[test]
+    fn upstream_elapsed_header_is_optional_and_lenient() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(upstream_elapsed_ms(&headers), None);
+        for (value, expected) in [
+            ("2700", Some(2700)),
+            (" 2700.4 ", Some(2700)),
+            ("-1", None),
+            ("NaN", None),
+            ("fast", None),
+        ] {
+            headers.insert(UPSTREAM_ELAPSED_HEADER, value.parse().unwrap());
+            assert_eq!(upstream_elapsed_ms(&headers), expected, "{value}");
         }
     }
-    None
-}
 
+    #[test]
+    fn hop_split_clamps_local_part() {
+        assert_eq!(
+            format_hop_split(Locale::En, 2740, 2700),
+            "0.040s local + 2.700s upstream"
+        );
+        assert_eq!(
+            format_hop_split(Locale::Zh, 2700, 2750),
+            "0.000s 本地 + 2.750s 上游"
+        );
+    }
+}
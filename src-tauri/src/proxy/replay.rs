@@ -0,0 +1,316 @@
+//! 请求重放（`csc replay`）
+//!
+//! 从请求捕获或 JSON 文件还原请求，按请求 ID 固定到指定供应商后发往运行中的代理，
+//! 与真实请求走同一条转发链路（模型映射、认证均一致）；固定只作用于这一个请求，不影响正常流量。
+
+use crate::app_config::AppType;
+use crate::database::RequestCapture;
+use crate::error::AppError;
+use crate::proxy::attribution::REQUEST_ID_HEADER;
+use serde_json::Value;
+use std::time::Instant;
+
+/// 供应商固定等待重放请求认领的时限（秒）；认领后随请求结束清理
+const REPLAY_PIN_TTL_SECS: u64 = 120;
+
+/// 一次重放的结果
+#[derive(Debug, Clone)]
+pub struct ReplayOutcome {
+    pub status: u16,
+    /// 发出请求到收到响应头的耗时
+    pub latency_ms: u64,
+    /// 响应体的前 N 字节（按 UTF-8 有损解码）
+    pub preview: String,
+    /// 响应体是否超过 N 字节（超出部分未读取）
+    pub preview_truncated: bool,
+}
+
+/// 从 JSON 还原请求：既接受请求捕获记录（含 endpoint/body），也接受原始请求体
+pub fn reconstruct(app_type: &AppType, raw: Value) -> Result<RequestCapture, AppError> {
+    let is_capture = raw.get("endpoint").is_some_and(Value::is_string) && raw.get("body").is_some();
+    if is_capture {
+        let mut capture: RequestCapture = serde_json::from_value(raw)
+            .map_err(|e| AppError::Message(format!("解析请求捕获失败: {e}")))?;
+        if capture.app_type.is_empty() {
+            capture.app_type = app_type.as_str().to_string();
+        } else if capture.app_type != app_type.as_str() {
+            return Err(AppError::Message(format!(
+                "请求捕获属于 {}，与指定的应用 {} 不一致",
+                capture.app_type,
+                app_type.as_str()
+            )));
+        }
+        return Ok(capture);
+    }
+
+    if !raw.is_object() {
+        return Err(AppError::Message("请求文件必须是 JSON 对象".to_string()));
+    }
+    Ok(RequestCapture {
+        request_id: String::new(),
+        app_type: app_type.as_str().to_string(),
+        endpoint: default_endpoint(app_type, &raw)?,
+        headers: Default::default(),
+        body: raw,
+        truncated: false,
+        created_at: 0,
+    })
+}
+
+/// 原始请求体对应的默认端点
+fn default_endpoint(app_type: &AppType, body: &Value) -> Result<String, AppError> {
+    match app_type {
+        AppType::Claude => Ok("/v1/messages".to_string()),
+        AppType::Codex => {
+            if body.get("input").is_none() && body.get("messages").is_some() {
+                Ok("/v1/chat/completions".to_string())
+            } else {
+                Ok("/v1/responses".to_string())
+            }
+        }
+        // Gemini 的模型在 URI 中，原始请求体无法还原端点
+        AppType::Gemini => Err(AppError::Message(
+            "Gemini 请求需使用包含 endpoint 的请求捕获文件（csc replay --from-log 获取）"
+                .to_string(),
+        )),
+    }
+}
+
+/// 将请求固定到指定供应商，经运行中的代理（`base`）重放一次
+pub async fn replay(
+    client: &reqwest::Client,
+    base: &str,
+    app_type: &AppType,
+    provider_id: &str,
+    capture: &RequestCapture,
+    max_bytes: usize,
+) -> Result<ReplayOutcome, AppError> {
+    let run_id = format!("replay-{}", uuid::Uuid::new_v4());
+    let pin = client
+        .post(format!("{base}/__cc_switch/test_override/start"))
+        .json(&serde_json::json!({
+            "app_type": app_type.as_str(),
+            "provider_id": provider_id,
+            "run_id": run_id,
+            "ttl_secs": REPLAY_PIN_TTL_SECS,
+        }))
+        .send()
+        .await
+        .map_err(|e| AppError::Message(format!("连接代理失败: {e}")))?;
+    if !pin.status().is_success() {
+        let text = pin.text().await.unwrap_or_default();
+        return Err(AppError::Message(format!("固定供应商失败: {text}")));
+    }
+
+    let mut request = client
+        .post(format!("{base}{}", capture.endpoint))
+        .json(&capture.body);
+    for (name, value) in &capture.headers {
+        if name.eq_ignore_ascii_case(REQUEST_ID_HEADER) || name.eq_ignore_ascii_case("x-request-id")
+        {
+            continue;
+        }
+        request = request.header(name.as_str(), value.as_str());
+    }
+    // 代理按请求 ID 匹配固定，只有这一个请求会被路由到指定供应商
+    request = request.header(REQUEST_ID_HEADER, &run_id);
+
+    let started = Instant::now();
+    let mut response = request
+        .send()
+        .await
+        .map_err(|e| AppError::Message(format!("重放请求失败: {e}")))?;
+    let latency_ms = started.elapsed().as_millis() as u64;
+    let status = response.status().as_u16();
+
+    // 只读取前 max_bytes 字节：流式响应不必等到结束
+    let mut buf: Vec<u8> = Vec::new();
+    let mut preview_truncated = false;
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| AppError::Message(format!("读取响应失败: {e}")))?
+    {
+        buf.extend_from_slice(&chunk);
+        if buf.len() > max_bytes {
+            buf.truncate(max_bytes);
+            preview_truncated = true;
+            break;
+        }
+    }
+
+    Ok(ReplayOutcome {
+        status,
+        latency_ms,
+        preview: String::from_utf8_lossy(&buf).into_owned(),
+        preview_truncated,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+    use crate::provider::Provider;
+    use crate::proxy::server::ProxyServer;
+    use crate::proxy::types::ProxyConfig;
+    use axum::{http::HeaderMap, routing::post, Json, Router};
+    use serde_json::json;
+    use std::sync::Arc;
+
+    fn codex_provider(id: &str, base_url: &str, key: &str, sort_index: usize) -> Provider {
+        let mut provider = Provider::with_id(
+            id.to_string(),
+            format!("acme-{id}"),
+            json!({ "base_url": base_url, "env": { "OPENAI_API_KEY": key } }),
+            None,
+        );
+        provider.sort_index = Some(sort_index);
+        provider
+    }
+
+    #[test]
+    fn reconstruct_accepts_capture_files_and_raw_bodies() {
+        let capture = reconstruct(
+            &AppType::Codex,
+            json!({
+                "requestId": "req-1",
+                "appType": "codex",
+                "endpoint": "/v1/responses",
+                "headers": { "user-agent": "codex_cli_rs/0.50" },
+                "body": { "model": "gpt-5", "input": "hi" }
+            }),
+        )
+        .unwrap();
+        assert_eq!(capture.request_id, "req-1");
+        assert_eq!(capture.endpoint, "/v1/responses");
+        assert_eq!(capture.headers["user-agent"], "codex_cli_rs/0.50");
+
+        let err = reconstruct(
+            &AppType::Claude,
+            json!({ "appType": "codex", "endpoint": "/v1/responses", "body": {} }),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("不一致"));
+
+        let chat = reconstruct(
+            &AppType::Codex,
+            json!({ "model": "gpt-4o", "messages": [] }),
+        )
+        .unwrap();
+        assert_eq!(chat.endpoint, "/v1/chat/completions");
+        assert_eq!(chat.app_type, "codex");
+        assert_eq!(chat.body["model"], "gpt-4o");
+        let claude =
+            reconstruct(&AppType::Claude, json!({ "model": "claude-sonnet-4-5" })).unwrap();
+        assert_eq!(claude.endpoint, "/v1/messages");
+
+        assert!(reconstruct(&AppType::Gemini, json!({ "contents": [] })).is_err());
+        assert!(reconstruct(&AppType::Codex, json!(["not", "an", "object"])).is_err());
+    }
+
+    #[tokio::test]
+    async fn replay_is_pinned_to_the_requested_provider() {
+        // 上游回显认证头与请求体，用于确认实际命中的供应商
+        let app = Router::new().route(
+            "/v1/responses",
+            post(|headers: HeaderMap, Json(body): Json<Value>| async move {
+                Json(json!({
+                    "authorization": headers
+                        .get("authorization")
+                        .and_then(|v| v.to_str().ok()),
+                    "user_agent": headers.get("user-agent").and_then(|v| v.to_str().ok()),
+                    "model": body["model"],
+                }))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let upstream = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+
+        let db = Arc::new(Database::memory().unwrap());
+        db.save_provider("codex", &codex_provider("p1", &upstream, "sk-first", 0))
+            .unwrap();
+        db.save_provider("codex", &codex_provider("p2", &upstream, "sk-second", 1))
+            .unwrap();
+        db.set_current_provider("codex", "p1").unwrap();
+
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let server = ProxyServer::new(
+            ProxyConfig {
+                listen_port: port,
+                ..Default::default()
+            },
+            db,
+            None,
+        );
+        server.start().await.unwrap();
+        let base = format!("http://127.0.0.1:{port}");
+
+        let mut capture = reconstruct(
+            &AppType::Codex,
+            json!({ "model": "gpt-5", "input": "hi", "stream": false }),
+        )
+        .unwrap();
+        capture
+            .headers
+            .insert("user-agent".to_string(), "codex_cli_rs/0.50".to_string());
+
+        let client = reqwest::Client::new();
+        let live_request = || {
+            client
+                .post(format!("{base}/v1/responses"))
+                .json(&json!({ "model": "gpt-5", "input": "live", "stream": false }))
+                .send()
+        };
+
+        // 固定只匹配重放请求的 ID：等待认领期间的正常请求仍走当前供应商
+        let pin = client
+            .post(format!("{base}/__cc_switch/test_override/start"))
+            .json(&json!({
+                "app_type": "codex",
+                "provider_id": "p2",
+                "run_id": "replay-pending",
+                "ttl_secs": 120,
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert!(pin.status().is_success());
+        let live: Value = live_request().await.unwrap().json().await.unwrap();
+        assert_eq!(live["authorization"], "Bearer sk-first");
+
+        let outcome = replay(&client, &base, &AppType::Codex, "p2", &capture, 4096)
+            .await
+            .unwrap();
+        assert_eq!(outcome.status, 200, "{}", outcome.preview);
+        assert!(!outcome.preview_truncated);
+        let echoed: Value = serde_json::from_str(&outcome.preview).unwrap();
+        assert_eq!(echoed["authorization"], "Bearer sk-second");
+        assert_eq!(echoed["user_agent"], "codex_cli_rs/0.50");
+        assert_eq!(echoed["model"], "gpt-5");
+
+        // 重放结束后固定已清理，正常请求不受影响
+        let live: Value = live_request().await.unwrap().json().await.unwrap();
+        assert_eq!(live["authorization"], "Bearer sk-first");
+
+        let outcome = replay(&client, &base, &AppType::Codex, "p2", &capture, 8)
+            .await
+            .unwrap();
+        assert!(outcome.preview_truncated);
+        assert_eq!(outcome.preview.len(), 8);
+
+        let err = replay(&client, &base, &AppType::Codex, "missing", &capture, 8)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("固定供应商失败"));
+
+        server.stop().await.unwrap();
+    }
+}
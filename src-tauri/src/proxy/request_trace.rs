@@ -0,0 +1,321 @@
+//! 单请求尝试追踪
+//!
+//! 当请求携带 `x-cc-switch-debug: 1` 时，记录本次请求的完整尝试序列
+//! （供应商 id、跳过原因、每次耗时、最终胜出者），并通过响应头 `x-cc-switch-trace` 返回，
+//! 便于定位“某一个具体请求到底试过哪些供应商”，而不必翻日志。
+//!
+//! 编码格式（逗号分隔，`;` 后为胜出者，以及胜出者上的模型解析追踪，见 [`ModelResolutionTrace::render`]）：
+//! `dropped=2,p1=502@120ms,p2=skip:breaker:open,anyrouter=skip:cooldown:overloaded,p3=clamp:32000>8192,p3=200@80ms;winner=p3;model=mapping:claude-sonnet-4-5>sonnet-cc(env)`
+
+use super::circuit_breaker::BreakerRejection;
+use super::model_trace::ModelResolutionTrace;
+use super::provider_router::CooldownReason;
+use super::ProxyError;
+use axum::http::{HeaderMap, HeaderValue};
+use axum::response::Response;
+
+/// 开启追踪的请求头
+pub const DEBUG_HEADER: &str = "x-cc-switch-debug";
+
+/// 追踪结果响应头
+pub const TRACE_HEADER: &str = "x-cc-switch-trace";
+
+/// 追踪头最大字节数（超出时丢弃最早的尝试）
+const MAX_TRACE_HEADER_BYTES: usize = 1024;
+
+/// 跳过原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    /// 熔断器拒绝（附拒绝原因：打开 / 半开探测名额已满）
+    Breaker(BreakerRejection),
+    /// 供应商处于冷却期（附冷却原因）
+    Cooldown(CooldownReason),
+}
+
+impl SkipReason {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SkipReason::Breaker(BreakerRejection::Open) => "breaker:open",
+            SkipReason::Breaker(BreakerRejection::HalfOpenExhausted) => "breaker:half-open",
+            SkipReason::Cooldown(CooldownReason::Overloaded) => "cooldown:overloaded",
+            SkipReason::Cooldown(CooldownReason::NoUsableUrl) => "cooldown:no-usable-url",
+            SkipReason::Cooldown(CooldownReason::AllKeysFailed) => "cooldown:all-keys-failed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TraceOutcome {
+    Skipped(SkipReason),
+    /// 转发前将输出 token 上限从 from 压到 to
+    Clamped {
+        from: u64,
+        to: u64,
+    },
+    /// 实际发起请求：状态码（网络错误/超时为 None）与耗时
+    Attempted {
+        status: Option<u16>,
+        latency_ms: u64,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TraceEntry {
+    target: String,
+    outcome: TraceOutcome,
+}
+
+/// 单请求追踪（未开启时所有记录操作均为空操作）
+#[derive(Debug, Clone, Default)]
+pub struct RequestTrace {
+    enabled: bool,
+    entries: Vec<TraceEntry>,
+    winner: Option<String>,
+    model: Option<String>,
+}
+
+impl RequestTrace {
+    /// 根据请求头决定是否开启追踪
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let enabled = headers
+            .get(DEBUG_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| matches!(v.trim(), "1" | "true"))
+            .unwrap_or(false);
+        Self {
+            enabled,
+            ..Self::default()
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn record_skip(&mut self, target: &str, reason: SkipReason) {
+        if !self.enabled {
+            return;
+        }
+        self.entries.push(TraceEntry {
+            target: target.to_string(),
+            outcome: TraceOutcome::Skipped(reason),
+        });
+    }
+
+    pub fn record_attempt(&mut self, provider_id: &str, status: Option<u16>, latency_ms: u64) {
+        if !self.enabled {
+            return;
+        }
+        self.entries.push(TraceEntry {
+            target: provider_id.to_string(),
+            outcome: TraceOutcome::Attempted { status, latency_ms },
+        });
+    }
+
+    pub fn record_clamp(&mut self, provider_id: &str, from: u64, to: u64) {
+        if !self.enabled {
+            return;
+        }
+        self.entries.push(TraceEntry {
+            target: provider_id.to_string(),
+            outcome: TraceOutcome::Clamped { from, to },
+        });
+    }
+
+    /// 记录一次失败尝试（上游 HTTP 错误保留状态码）
+    pub fn record_error(&mut self, provider_id: &str, error: &ProxyError, latency_ms: u64) {
+        let status = match error {
+            ProxyError::UpstreamError { status, .. } => Some(*status),
+            _ => None,
+        };
+        self.record_attempt(provider_id, status, latency_ms);
+    }
+
+    pub fn set_winner(&mut self, provider_id: &str) {
+        if !self.enabled {
+            return;
+        }
+        self.winner = Some(provider_id.to_string());
+    }
+
+    /// 胜出者上的模型解析追踪（没有改写模型时不输出）
+    pub fn set_model_trace(&mut self, model_trace: &ModelResolutionTrace) {
+        if !self.enabled || model_trace.is_empty() {
+            return;
+        }
+        self.model = Some(model_trace.render());
+    }
+
+    /// 紧凑编码（超出上限时从最早的尝试开始丢弃）
+    pub fn encode(&self) -> String {
+        let parts: Vec<String> = self.entries.iter().map(Self::encode_entry).collect();
+        let winner = self
+            .winner
+            .as_deref()
+            .map(sanitize_target)
+            .unwrap_or_else(|| "-".to_string());
+        let mut tail = format!(";winner={winner}");
+        if let Some(model) = &self.model {
+            tail.push_str(&format!(";model={}", sanitize_target(model)));
+        }
+
+        let mut dropped = 0usize;
+        loop {
+            let mut out = String::new();
+            if dropped > 0 {
+                out.push_str(&format!("dropped={dropped}"));
+                if dropped < parts.len() {
+                    out.push(',');
+                }
+            }
+            out.push_str(&parts[dropped..].join(","));
+            out.push_str(&tail);
+            if out.len() <= MAX_TRACE_HEADER_BYTES || dropped >= parts.len() {
+                return out;
+            }
+            dropped += 1;
+        }
+    }
+
+    /// 将追踪写入响应头（未开启时原样返回）
+    pub fn attach(&self, mut response: Response) -> Response {
+        if !self.enabled {
+            return response;
+        }
+        if let Ok(value) = HeaderValue::from_str(&self.encode()) {
+            response.headers_mut().insert(TRACE_HEADER, value);
+        }
+        response
+    }
+
+    fn encode_entry(entry: &TraceEntry) -> String {
+        let target = sanitize_target(&entry.target);
+        match &entry.outcome {
+            TraceOutcome::Skipped(reason) => format!("{target}=skip:{}", reason.as_str()),
+            TraceOutcome::Clamped { from, to } => format!("{target}=clamp:{from}>{to}"),
+            TraceOutcome::Attempted { status, latency_ms } => match status {
+                Some(code) => format!("{target}={code}@{latency_ms}ms"),
+                None => format!("{target}=err@{latency_ms}ms"),
+            },
+        }
+    }
+}
+
+/// 响应头只允许可见 ASCII，且需避开编码中使用的分隔符
+fn sanitize_target(s: &str) -> String {
+    s.chars()
+        .map(|c| {
+            if c.is_ascii_graphic() && !matches!(c, ',' | ';' | '=') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enabled_trace() -> RequestTrace {
+        let mut headers = HeaderMap::new();
+        headers.insert(DEBUG_HEADER, HeaderValue::from_static("1"));
+        RequestTrace::from_headers(&headers)
+    }
+
+    #[test]
+    fn test_disabled_without_debug_header() {
+        let mut trace = RequestTrace::from_headers(&HeaderMap::new());
+        trace.record_attempt("p1", Some(200), 10);
+        trace.set_winner("p1");
+        assert!(!trace.is_enabled());
+
+        let response = trace.attach(Response::new(axum::body::Body::empty()));
+        assert!(response.headers().get(TRACE_HEADER).is_none());
+    }
+
+    #[test]
+    fn test_multi_attempt_failover_trace() {
+        let mut trace = enabled_trace();
+        trace.record_skip(
+            "anyrouter",
+            SkipReason::Cooldown(CooldownReason::Overloaded),
+        );
+        trace.record_attempt("p1", Some(502), 120);
+        trace.record_skip("p2", SkipReason::Breaker(BreakerRejection::Open));
+        trace.record_error("p3", &ProxyError::Timeout("5s".to_string()), 5000);
+        trace.record_attempt("p4", Some(200), 80);
+        trace.set_winner("p4");
+
+        assert_eq!(
+            trace.encode(),
+            "anyrouter=skip:cooldown:overloaded,p1=502@120ms,p2=skip:breaker:open,p3=err@5000ms,p4=200@80ms;winner=p4"
+        );
+
+        let response = trace.attach(Response::new(axum::body::Body::empty()));
+        let header = response.headers().get(TRACE_HEADER).unwrap();
+        assert!(header.to_str().unwrap().ends_with(";winner=p4"));
+    }
+
+    #[test]
+    fn test_no_winner_when_all_failed() {
+        let mut trace = enabled_trace();
+        trace.record_attempt("p1", Some(429), 30);
+        assert_eq!(trace.encode(), "p1=429@30ms;winner=-");
+    }
+
+    #[test]
+    fn test_clamp_is_recorded_before_attempt() {
+        let mut trace = enabled_trace();
+        trace.record_clamp("p1", 32000, 8192);
+        trace.record_attempt("p1", Some(200), 40);
+        trace.set_winner("p1");
+        assert_eq!(trace.encode(), "p1=clamp:32000>8192,p1=200@40ms;winner=p1");
+    }
+
+    #[test]
+    fn test_model_trace_follows_winner() {
+        let mut trace = enabled_trace();
+        trace.record_attempt("p1", Some(200), 40);
+        trace.set_winner("p1");
+        trace.set_model_trace(&ModelResolutionTrace::default());
+        assert_eq!(trace.encode(), "p1=200@40ms;winner=p1");
+
+        let mut model_trace = ModelResolutionTrace::default();
+        model_trace.record(
+            crate::proxy::model_trace::STEP_MAPPING,
+            Some("claude-sonnet-4-5"),
+            Some("sonnet-cc"),
+            "env",
+        );
+        trace.set_model_trace(&model_trace);
+        assert_eq!(
+            trace.encode(),
+            "p1=200@40ms;winner=p1;model=mapping:claude-sonnet-4-5>sonnet-cc(env)"
+        );
+    }
+
+    #[test]
+    fn test_header_is_capped_by_dropping_oldest() {
+        let mut trace = enabled_trace();
+        for i in 0..200 {
+            trace.record_attempt(&format!("provider-{i}"), Some(500), 100);
+        }
+        trace.set_winner("provider-199");
+
+        let encoded = trace.encode();
+        assert!(encoded.len() <= MAX_TRACE_HEADER_BYTES);
+        assert!(encoded.starts_with("dropped="));
+        assert!(!encoded.contains("provider-0="));
+        assert!(encoded.contains("provider-199=500@100ms"));
+        assert!(encoded.ends_with(";winner=provider-199"));
+    }
+
+    #[test]
+    fn test_target_is_sanitized() {
+        let mut trace = enabled_trace();
+        trace.record_attempt("供应商,a", Some(200), 1);
+        assert_eq!(trace.encode(), "____a=200@1ms;winner=-");
+    }
+}
@@ -3,10 +3,11 @@
 //! 统一处理流式和非流式 API 响应
 
 use super::{
+    bandwidth::{self, ByteCounter},
     handler_config::UsageParserConfig,
     handler_context::{RequestContext, StreamingTimeoutConfig},
     server::ProxyState,
-    usage::parser::TokenUsage,
+    usage::{parser::TokenUsage, ModelMapping, TransferBytes},
     ProxyError,
 };
 use axum::response::Response;
@@ -67,13 +68,14 @@ pub async fn handle_streaming(
         builder = builder.header(key, value);
     }
 
-    // 创建字节流
-    let stream = response
-        .bytes_stream()
+    // 创建字节流（逐块累计转发的字节数，不缓冲）
+    let response_bytes = ByteCounter::default();
+    let stream = bandwidth::count_stream(response.bytes_stream(), response_bytes.clone())
         .map(|chunk| chunk.map_err(|e| std::io::Error::other(e.to_string())));
 
     // 创建使用量收集器
-    let usage_collector = create_usage_collector(ctx, state, status.as_u16(), parser_config);
+    let usage_collector =
+        create_usage_collector(ctx, state, status.as_u16(), parser_config, response_bytes);
 
     // 获取流式超时配置
     let timeout_config = ctx.streaming_timeout_config();
@@ -134,7 +136,15 @@ pub async fn handle_non_streaming(
                 sanitize_gpt_model_name(&ctx.request_model)
             };
 
-            spawn_log_usage(state, ctx, usage, &model, status.as_u16(), false);
+            spawn_log_usage(
+                state,
+                ctx,
+                usage,
+                &model,
+                status.as_u16(),
+                false,
+                body_bytes.len() as u64,
+            );
         } else {
             // 即使未能解析usage，也要记录默认值（保持原始逻辑）
             let model = json_value
@@ -150,6 +160,7 @@ pub async fn handle_non_streaming(
                 &model,
                 status.as_u16(),
                 false,
+                body_bytes.len() as u64,
             );
             log::debug!(
                 "[{}] 未能解析 usage 信息，使用默认值记录",
@@ -170,6 +181,7 @@ pub async fn handle_non_streaming(
             &sanitize_gpt_model_name(&ctx.request_model),
             status.as_u16(),
             false,
+            body_bytes.len() as u64,
         );
     }
 
@@ -282,13 +294,18 @@ fn create_usage_collector(
     state: &ProxyState,
     status_code: u16,
     parser_config: &UsageParserConfig,
+    response_bytes: ByteCounter,
 ) -> SseUsageCollector {
     let state = state.clone();
+    let request_id = ctx.request_id.clone();
     let provider_id = ctx.provider.id.clone();
     let request_model = ctx.request_model.clone();
+    let mapping = ctx.model_mapping();
     let app_type_str = parser_config.app_type_str;
     let tag = ctx.tag;
     let start_time = ctx.start_time;
+    let upstream_ms = ctx.upstream_ms;
+    let request_bytes = ctx.request_bytes;
     let stream_parser = parser_config.stream_parser;
     let model_extractor = parser_config.model_extractor;
 
@@ -296,21 +313,32 @@ fn create_usage_collector(
         if let Some(usage) = stream_parser(&events) {
             let model = model_extractor(&events, &request_model);
             let latency_ms = start_time.elapsed().as_millis() as u64;
+            // 回调在流结束时触发，此时已转发全部分块
+            let bytes = TransferBytes {
+                request: request_bytes,
+                response: response_bytes.get(),
+            };
 
             let state = state.clone();
+            let request_id = request_id.clone();
             let provider_id = provider_id.clone();
+            let mapping = mapping.clone();
 
             tokio::spawn(async move {
                 log_usage_internal(
                     &state,
+                    request_id,
                     &provider_id,
                     app_type_str,
                     &model,
                     usage,
                     latency_ms,
                     first_token_ms,
+                    upstream_ms,
+                    bytes,
                     true, // is_streaming
                     status_code,
+                    mapping,
                 )
                 .await;
             });
@@ -328,26 +356,38 @@ fn spawn_log_usage(
     model: &str,
     status_code: u16,
     is_streaming: bool,
+    response_bytes: u64,
 ) {
     use super::model_sanitizer::sanitize_gpt_model_name;
 
     let state = state.clone();
+    let request_id = ctx.request_id.clone();
     let provider_id = ctx.provider.id.clone();
     let app_type_str = ctx.app_type_str.to_string();
     let model = sanitize_gpt_model_name(model);
     let latency_ms = ctx.latency_ms();
+    let upstream_ms = ctx.upstream_ms;
+    let bytes = TransferBytes {
+        request: ctx.request_bytes,
+        response: response_bytes,
+    };
+    let mapping = ctx.model_mapping();
 
     tokio::spawn(async move {
         log_usage_internal(
             &state,
+            request_id,
             &provider_id,
             &app_type_str,
             &model,
             usage,
             latency_ms,
             None,
+            upstream_ms,
+            bytes,
             is_streaming,
             status_code,
+            mapping,
         )
         .await;
     });
@@ -357,36 +397,39 @@ fn spawn_log_usage(
 #[allow(clippy::too_many_arguments)]
 async fn log_usage_internal(
     state: &ProxyState,
+    request_id: String,
     provider_id: &str,
     app_type: &str,
     model: &str,
     usage: TokenUsage,
     latency_ms: u64,
     first_token_ms: Option<u64>,
+    upstream_ms: Option<u64>,
+    bytes: TransferBytes,
     is_streaming: bool,
     status_code: u16,
+    mapping: ModelMapping,
 ) {
     use super::usage::logger::UsageLogger;
 
+    // 只读模式不写请求日志
+    if state.provider_router.is_read_only() {
+        return;
+    }
     let logger = UsageLogger::new(&state.db);
 
     // 获取 provider 的 cost_multiplier
-    let multiplier = match state.db.get_provider_by_id(provider_id, app_type) {
-        Ok(Some(p)) => {
-            if let Some(meta) = p.meta {
-                if let Some(cm) = meta.cost_multiplier {
-                    Decimal::from_str(&cm).unwrap_or(Decimal::from(1))
-                } else {
-                    Decimal::from(1)
-                }
-            } else {
-                Decimal::from(1)
-            }
-        }
-        _ => Decimal::from(1),
-    };
-
-    let request_id = uuid::Uuid::new_v4().to_string();
+    let provider = state
+        .db
+        .get_provider_by_id(provider_id, app_type)
+        .ok()
+        .flatten();
+    let multiplier = provider
+        .as_ref()
+        .and_then(|p| p.meta.as_ref())
+        .and_then(|meta| meta.cost_multiplier.as_deref())
+        .map(|cm| Decimal::from_str(cm).unwrap_or(Decimal::from(1)))
+        .unwrap_or(Decimal::from(1));
 
     if let Err(e) = logger.log_with_calculation(
         request_id,
@@ -397,12 +440,26 @@ async fn log_usage_internal(
         multiplier,
         latency_ms,
         first_token_ms,
+        upstream_ms,
+        bytes,
         status_code,
         None,
         None, // provider_type
         is_streaming,
+        mapping,
     ) {
         log::warn!("记录使用量失败: {e}");
+        return;
+    }
+    if let Some(provider) = &provider {
+        bandwidth::check_daily_alert(
+            &state.db,
+            state.provider_router.event_recorder(),
+            app_type,
+            provider,
+            bytes,
+            chrono::Utc::now().timestamp(),
+        );
     }
 }
 
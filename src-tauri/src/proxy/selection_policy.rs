@@ -0,0 +1,375 @@
+//! 选路策略
+//!
+//! 选路分两步：路由器先按层级、supplier 解析出每个 supplier 的当前 URL、冷却状态与
+//! 熔断器放行的 key（含测速、冷却、疑似失效等副作用）；再由选路策略把这些候选排成
+//! 转发器使用的候选链。策略只做排序，不读写数据库、不测速。
+//!
+//! 策略按应用配置 `AppProxyConfig.selection_policy` 选择：
+//! - `default`：按层级依次追加，层级内按 key 轮询，测速胜出的 key 优先一次
+//! - `strict-primary`：只使用主 supplier（当前供应商所属 supplier，未指定时为最高层级的第一个），
+//!   仅当其 key 全部被熔断器拦截时才按默认策略故障转移
+
+use super::provider_router::CooldownReason;
+use crate::provider::Provider;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+/// 选路策略的输入：已解析的各层级候选
+#[derive(Debug, Clone, Default)]
+pub struct SelectionInput {
+    pub app_type: String,
+    pub request_model: String,
+    /// 当前供应商所属 supplier（未指定或不在故障转移队列中时为 None）
+    pub current_supplier: Option<String>,
+    /// 按层级升序；不含没有任何可用配置（缺 URL、维护禁用、余额耗尽）的层级
+    pub levels: Vec<PriorityLevel>,
+}
+
+/// 单个层级的候选
+#[derive(Debug, Clone, Default)]
+pub struct PriorityLevel {
+    pub priority: usize,
+    /// 按 supplier 名字典序
+    pub suppliers: Vec<SupplierCandidates>,
+}
+
+/// 单个 supplier 在某层级上的候选
+#[derive(Debug, Clone, Default)]
+pub struct SupplierCandidates {
+    pub supplier: String,
+    /// 选中的 URL（冷却中或无可用 URL 时为 None）
+    pub url: Option<String>,
+    /// 选中 URL 的缓存延迟（毫秒）
+    pub latency_ms: Option<u64>,
+    /// 冷却原因（冷却中或本次无可用 URL 而进入冷却）
+    pub cooldown: Option<CooldownReason>,
+    /// 选中 URL 上熔断器放行的 key（按 key 值去重）
+    pub available: Vec<Provider>,
+    /// 选中 URL 上被熔断器拦截的 key
+    pub breaker_blocked: Vec<Provider>,
+}
+
+/// 策略可用的路由器状态：层级内轮询计数与测速胜出的 key
+///
+/// 由路由器加载；正常选路时在持有写锁期间提交，预览时直接丢弃。
+#[derive(Debug, Default)]
+pub struct SelectionState {
+    counters: BTreeMap<usize, usize>,
+    winners: BTreeMap<usize, HashSet<String>>,
+    rotated: Vec<(usize, usize)>,
+    taken: Vec<usize>,
+}
+
+impl SelectionState {
+    pub(crate) fn new(
+        counters: BTreeMap<usize, usize>,
+        winners: BTreeMap<usize, HashSet<String>>,
+    ) -> Self {
+        Self {
+            counters,
+            winners,
+            ..Default::default()
+        }
+    }
+
+    /// 该层级本次的轮询偏移（`len` 为候选数，须大于 0），并记录需推进的计数
+    pub fn rotation(&mut self, priority: usize, len: usize) -> usize {
+        self.rotated.push((priority, len));
+        self.counters.get(&priority).copied().unwrap_or(0) % len
+    }
+
+    /// 取出该层级测速胜出的 key（只生效一次）
+    pub fn take_benchmark_winners(&mut self, priority: usize) -> Option<HashSet<String>> {
+        let winners = self.winners.remove(&priority)?;
+        self.taken.push(priority);
+        Some(winners)
+    }
+
+    /// 本次使用过轮询的层级及其候选数
+    pub(crate) fn rotated(&self) -> &[(usize, usize)] {
+        &self.rotated
+    }
+
+    /// 本次取出测速胜出 key 的层级
+    pub(crate) fn taken(&self) -> &[usize] {
+        &self.taken
+    }
+}
+
+/// 选路策略：把已解析的候选排成转发器使用的候选链
+pub trait SelectionPolicy: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// 返回有序候选链；为空时请求以 “No available providers” 失败
+    fn order(&self, input: &SelectionInput, state: &mut SelectionState) -> Vec<Provider>;
+}
+
+/// 应用配置中的策略名
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SelectionPolicyKind {
+    #[default]
+    Default,
+    StrictPrimary,
+}
+
+impl SelectionPolicyKind {
+    /// 按名称解析（不区分大小写），无法识别时返回 None
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "default" => Some(Self::Default),
+            "strict-primary" => Some(Self::StrictPrimary),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Default => "default",
+            Self::StrictPrimary => "strict-primary",
+        }
+    }
+
+    pub fn policy(&self) -> &'static dyn SelectionPolicy {
+        match self {
+            Self::Default => &DefaultPolicy,
+            Self::StrictPrimary => &StrictPrimaryPolicy,
+        }
+    }
+}
+
+/// 默认策略：按层级依次追加候选，层级内按 key 均分轮询
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultPolicy;
+
+impl DefaultPolicy {
+    /// 单个层级的候选顺序：按 provider id 排序后按轮询计数旋转，
+    /// 测速后的首次选路把探测成功的 key 排在最前
+    fn order_level(
+        priority: usize,
+        mut candidates: Vec<Provider>,
+        state: &mut SelectionState,
+    ) -> Vec<Provider> {
+        if candidates.is_empty() {
+            return candidates;
+        }
+        candidates.sort_by(|a, b| a.id.cmp(&b.id));
+        let rotate_count = state.rotation(priority, candidates.len());
+        candidates.rotate_left(rotate_count);
+        if let Some(winners) = state.take_benchmark_winners(priority) {
+            candidates.sort_by_key(|p| !winners.contains(&p.id));
+        }
+        candidates
+    }
+
+    fn order_matching(
+        input: &SelectionInput,
+        state: &mut SelectionState,
+        keep: impl Fn(&SupplierCandidates) -> bool,
+    ) -> Vec<Provider> {
+        let mut chain = Vec::new();
+        for level in &input.levels {
+            let candidates: Vec<Provider> = level
+                .suppliers
+                .iter()
+                .filter(|s| keep(s))
+                .flat_map(|s| s.available.iter().cloned())
+                .collect();
+            chain.extend(Self::order_level(level.priority, candidates, state));
+        }
+        chain
+    }
+}
+
+impl SelectionPolicy for DefaultPolicy {
+    fn name(&self) -> &'static str {
+        "default"
+    }
+
+    fn order(&self, input: &SelectionInput, state: &mut SelectionState) -> Vec<Provider> {
+        Self::order_matching(input, state, |_| true)
+    }
+}
+
+/// 严格主供应商：不因慢、满载或单次失败切走，只有主 supplier 的 key 全部被熔断器拦截时才故障转移
+///
+/// 主 supplier 冷却中或没有可用 URL 时返回空链（请求直接失败，不切换到其它 supplier）。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StrictPrimaryPolicy;
+
+impl StrictPrimaryPolicy {
+    /// 主 supplier：当前供应商所属 supplier，否则为最高层级的第一个 supplier
+    fn primary(input: &SelectionInput) -> Option<&str> {
+        input.current_supplier.as_deref().or_else(|| {
+            input
+                .levels
+                .first()
+                .and_then(|level| level.suppliers.first())
+                .map(|s| s.supplier.as_str())
+        })
+    }
+}
+
+impl SelectionPolicy for StrictPrimaryPolicy {
+    fn name(&self) -> &'static str {
+        "strict-primary"
+    }
+
+    fn order(&self, input: &SelectionInput, state: &mut SelectionState) -> Vec<Provider> {
+        let Some(primary) = Self::primary(input) else {
+            return Vec::new();
+        };
+        let entries: Vec<&SupplierCandidates> = input
+            .levels
+            .iter()
+            .flat_map(|level| &level.suppliers)
+            .filter(|s| s.supplier == primary)
+            .collect();
+
+        if entries.iter().any(|s| !s.available.is_empty()) {
+            return DefaultPolicy::order_matching(input, state, |s| s.supplier == primary);
+        }
+        if entries.iter().any(|s| !s.breaker_blocked.is_empty()) {
+            log::info!(
+                "[{}] 主供应商 {primary} 已熔断，按默认策略故障转移",
+                input.app_type
+            );
+            return DefaultPolicy.order(input, state);
+        }
+        log::debug!(
+            "[{}] 主供应商 {primary} 暂无可用 URL 或处于冷却期，严格模式不切换",
+            input.app_type
+        );
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn key(id: &str) -> Provider {
+        Provider::with_id(id.to_string(), id.to_string(), json!({}), None)
+    }
+
+    fn supplier(name: &str, available: &[&str], blocked: &[&str]) -> SupplierCandidates {
+        SupplierCandidates {
+            supplier: name.to_string(),
+            url: Some(format!("https://{name}.example.com")),
+            available: available.iter().map(|id| key(id)).collect(),
+            breaker_blocked: blocked.iter().map(|id| key(id)).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn input(levels: Vec<(usize, Vec<SupplierCandidates>)>) -> SelectionInput {
+        SelectionInput {
+            app_type: "codex".to_string(),
+            levels: levels
+                .into_iter()
+                .map(|(priority, suppliers)| PriorityLevel {
+                    priority,
+                    suppliers,
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn ids(chain: &[Provider]) -> Vec<&str> {
+        chain.iter().map(|p| p.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_default_policy_rotates_within_level_and_appends_levels() {
+        let input = input(vec![
+            (
+                0,
+                vec![
+                    supplier("a", &["a2", "a1"], &[]),
+                    supplier("b", &["b1"], &[]),
+                ],
+            ),
+            (1, vec![supplier("c", &["c1"], &["c2"])]),
+        ]);
+        let mut state = SelectionState::new(BTreeMap::from([(0, 1)]), BTreeMap::new());
+        let chain = DefaultPolicy.order(&input, &mut state);
+        assert_eq!(ids(&chain), ["a2", "b1", "a1", "c1"]);
+        assert_eq!(state.rotated(), [(0, 3), (1, 1)]);
+    }
+
+    #[test]
+    fn test_default_policy_puts_benchmark_winners_first_once() {
+        let input = input(vec![(0, vec![supplier("a", &["a1", "a2", "a3"], &[])])]);
+        let winners = HashSet::from(["a3".to_string()]);
+        let mut state = SelectionState::new(BTreeMap::new(), BTreeMap::from([(0, winners)]));
+        assert_eq!(
+            ids(&DefaultPolicy.order(&input, &mut state)),
+            ["a3", "a1", "a2"]
+        );
+        assert_eq!(state.taken(), [0]);
+        assert_eq!(
+            ids(&DefaultPolicy.order(&input, &mut state)),
+            ["a1", "a2", "a3"]
+        );
+    }
+
+    #[test]
+    fn test_strict_primary_keeps_current_supplier() {
+        let mut input = input(vec![
+            (0, vec![supplier("fast", &["f1"], &[])]),
+            (1, vec![supplier("main", &["m1"], &[])]),
+        ]);
+        input.current_supplier = Some("main".to_string());
+        let chain = StrictPrimaryPolicy.order(&input, &mut SelectionState::default());
+        assert_eq!(ids(&chain), ["m1"]);
+
+        // 未指定当前供应商：最高层级的第一个 supplier 为主
+        input.current_supplier = None;
+        let chain = StrictPrimaryPolicy.order(&input, &mut SelectionState::default());
+        assert_eq!(ids(&chain), ["f1"]);
+    }
+
+    #[test]
+    fn test_strict_primary_fails_over_only_when_breaker_opens() {
+        let mut input = input(vec![
+            (
+                0,
+                vec![
+                    supplier("main", &[], &["m1"]),
+                    supplier("other", &["o1"], &[]),
+                ],
+            ),
+            (1, vec![supplier("backup", &["b1"], &[])]),
+        ]);
+        input.current_supplier = Some("main".to_string());
+        let chain = StrictPrimaryPolicy.order(&input, &mut SelectionState::default());
+        assert_eq!(ids(&chain), ["o1", "b1"]);
+
+        // 冷却中（无 URL、无熔断）：不切换
+        input.levels[0].suppliers[0] = SupplierCandidates {
+            supplier: "main".to_string(),
+            cooldown: Some(CooldownReason::Overloaded),
+            ..Default::default()
+        };
+        let chain = StrictPrimaryPolicy.order(&input, &mut SelectionState::default());
+        assert!(chain.is_empty());
+    }
+
+    #[test]
+    fn test_policy_kind_names() {
+        for kind in [
+            SelectionPolicyKind::Default,
+            SelectionPolicyKind::StrictPrimary,
+        ] {
+            assert_eq!(SelectionPolicyKind::from_name(kind.as_str()), Some(kind));
+            assert_eq!(kind.policy().name(), kind.as_str());
+        }
+        assert_eq!(
+            SelectionPolicyKind::from_name(" Strict-Primary "),
+            Some(SelectionPolicyKind::StrictPrimary)
+        );
+        assert_eq!(SelectionPolicyKind::from_name("random"), None);
+    }
+}
@@ -3,11 +3,23 @@
 //! 基于Axum的HTTP服务器，处理代理请求
 
 use super::{
-    failover_switch::FailoverSwitchManager, handlers, provider_router::ProviderRouter, types::*,
+    app_gate::AppGate,
+    circuit_breaker::{CircuitBreakerStats, ProviderCircuitBreakerStats},
+    event_log::EventRecorder,
+    failover_switch::FailoverSwitchManager,
+    handlers,
+    health::HealthChecker,
+    inflight::{InflightTracker, RecentRequest},
+    keep_warm::KeepWarmer,
+    model_warmup::ModelWarmup,
+    provider_router::ProviderRouter,
+    types::*,
+    update_check::UpdateChecker,
     ProxyError,
 };
 use crate::database::Database;
 use axum::{
+    middleware,
     routing::{get, post},
     Router,
 };
@@ -32,6 +44,43 @@ pub struct ProxyState {
     pub app_handle: Option<tauri::AppHandle>,
     /// 故障转移切换管理器
     pub failover_manager: Arc<FailoverSwitchManager>,
+    /// 进行中请求追踪（优雅停机排空 + active_connections）
+    pub inflight: Arc<InflightTracker>,
+    /// 按应用的代理开关（热更新）
+    pub app_gate: Arc<AppGate>,
+    /// 每日新版本检测（默认关闭）
+    pub update_checker: Arc<UpdateChecker>,
+}
+
+impl ProxyState {
+    /// 当前状态快照（补齐运行时间与各应用当前目标）
+    pub async fn snapshot_status(&self) -> ProxyStatus {
+        let mut status = self.status.read().await.clone();
+        status.active_connections = self.inflight.active();
+
+        // 计算运行时间
+        if let Some(start) = *self.start_time.read().await {
+            status.uptime_seconds = start.elapsed().as_secs();
+        }
+
+        // 从 current_providers HashMap 获取每个应用类型当前正在使用的 provider
+        let current_providers = self.current_providers.read().await;
+        status.active_targets = current_providers
+            .iter()
+            .map(|(app_type, (provider_id, provider_name))| ActiveTarget {
+                app_type: app_type.clone(),
+                provider_id: provider_id.clone(),
+                provider_name: provider_name.clone(),
+            })
+            .collect();
+        status.db_degraded_apps = self.provider_router.db_degraded_apps();
+        status.db_degraded = !status.db_degraded_apps.is_empty();
+        status.read_only = self.provider_router.is_read_only();
+        status.failover_paused = self.provider_router.failover_pauses();
+        status.update_check = self.update_checker.latest();
+
+        status
+    }
 }
 
 /// 代理HTTP服务器
@@ -41,6 +90,12 @@ pub struct ProxyServer {
     shutdown_tx: Arc<RwLock<Option<oneshot::Sender<()>>>>,
     /// 服务器任务句柄，用于等待服务器实际关闭
     server_handle: Arc<RwLock<Option<JoinHandle<()>>>>,
+    /// 后台健康检查（按应用设置开启，默认关闭）
+    health_checker: HealthChecker,
+    /// 连接保温（按应用配置开启，默认关闭）
+    keep_warmer: KeepWarmer,
+    /// 路由器过期内存条目的后台清理任务
+    janitor_handle: Arc<RwLock<Option<JoinHandle<()>>>>,
 }
 
 impl ProxyServer {
@@ -49,10 +104,34 @@ impl ProxyServer {
         db: Arc<Database>,
         app_handle: Option<tauri::AppHandle>,
     ) -> Self {
+        // 路由事件日志（后台批量写入 events 表；只读模式不记录）
+        let events = if config.read_only {
+            EventRecorder::disabled()
+        } else {
+            EventRecorder::spawn(db.clone())
+        };
         // 创建共享的 ProviderRouter（熔断器状态将跨所有请求保持）
-        let provider_router = Arc::new(ProviderRouter::new(db.clone()));
+        let provider_router = Arc::new(
+            ProviderRouter::new(db.clone())
+                .with_event_recorder(events.clone())
+                .with_read_only(config.read_only),
+        );
         // 创建故障转移切换管理器
-        let failover_manager = Arc::new(FailoverSwitchManager::new(db.clone()));
+        let failover_manager = Arc::new(
+            FailoverSwitchManager::new(db.clone())
+                .with_event_recorder(events)
+                .with_read_only(config.read_only),
+        );
+
+        let health_checker = HealthChecker::new(
+            provider_router.clone(),
+            db.clone(),
+            failover_manager.clone(),
+            app_handle.clone(),
+        );
+        let inflight = Arc::new(InflightTracker::new());
+        let keep_warmer = KeepWarmer::new(provider_router.clone(), db.clone(), inflight.clone());
+        let update_checker = Arc::new(UpdateChecker::new(db.clone(), config.read_only));
 
         let state = ProxyState {
             db,
@@ -63,6 +142,9 @@ impl ProxyServer {
             provider_router,
             app_handle,
             failover_manager,
+            inflight,
+            app_gate: Arc::new(AppGate::new()),
+            update_checker,
         };
 
         Self {
@@ -70,6 +152,9 @@ impl ProxyServer {
             state,
             shutdown_tx: Arc::new(RwLock::new(None)),
             server_handle: Arc::new(RwLock::new(None)),
+            health_checker,
+            keep_warmer,
+            janitor_handle: Arc::new(RwLock::new(None)),
         }
     }
 
@@ -96,6 +181,9 @@ impl ProxyServer {
             .map_err(|e| ProxyError::BindFailed(e.to_string()))?;
 
         log::info!("代理服务器启动于 {addr}");
+        if self.config.read_only {
+            log::info!("代理处于只读模式：不写入请求日志、健康状态、写回与故障转移切换");
+        }
 
         // 保存关闭句柄
         *self.shutdown_tx.write().await = Some(shutdown_tx);
@@ -105,13 +193,20 @@ impl ProxyServer {
         status.running = true;
         status.address = self.config.listen_address.clone();
         status.port = self.config.listen_port;
+        status.drain_timeout_secs = self.config.drain_timeout;
         drop(status);
 
         // 恢复最近一次真实请求指纹（供重启后 `csc t` 复用真实请求形态）
         self.restore_last_request_summaries().await;
 
+        self.refresh_app_gate().await;
+        self.warn_duplicate_keys();
+        self.warn_missing_sort_index();
+        self.check_consistency().await;
+
         // 记录启动时间
         *self.state.start_time.write().await = Some(std::time::Instant::now());
+        self.state.inflight.reset();
 
         // 启动服务器
         let state = self.state.clone();
@@ -131,6 +226,16 @@ impl ProxyServer {
         // 保存服务器任务句柄
         *self.server_handle.write().await = Some(handle);
 
+        // 启动后台健康检查（未配置间隔的应用不会探测）
+        self.health_checker.start().await;
+        // 启动连接保温（未开启 keep_warm 的应用不会发送请求）
+        self.keep_warmer.start().await;
+
+        // 启动路由器内存条目的定期清理
+        *self.janitor_handle.write().await = Some(self.state.provider_router.spawn_janitor());
+        // 启动每日新版本检测（未开启时不查询）
+        self.state.update_checker.start().await;
+
         Ok(ProxyServerInfo {
             address: self.config.listen_address.clone(),
             port: self.config.listen_port,
@@ -138,6 +243,15 @@ impl ProxyServer {
         })
     }
 
+    /// 写回请求指纹设置（只读模式跳过）
+    async fn persist_summary_setting(&self, key: String, json: String) {
+        if self.config.read_only {
+            return;
+        }
+        let db = self.state.db.clone();
+        let _ = tokio::task::spawn_blocking(move || db.set_setting(&key, &json)).await;
+    }
+
     async fn restore_last_request_summaries(&self) {
         let db = self.state.db.clone();
         let app_types = ["claude", "codex", "gemini"];
@@ -164,10 +278,7 @@ impl ProxyServer {
                         Err(_) => continue,
                     };
 
-                    let db = self.state.db.clone();
-                    let key_for_write = key.clone();
-                    let _ = tokio::task::spawn_blocking(move || db.set_setting(&key_for_write, &json))
-                        .await;
+                    self.persist_summary_setting(key.clone(), json).await;
 
                     restored.push((app_type.to_string(), summary));
                     continue;
@@ -196,10 +307,7 @@ impl ProxyServer {
                     let Ok(json) = serde_json::to_string(&summary) else {
                         continue;
                     };
-                    let db = self.state.db.clone();
-                    let key_for_write = key.clone();
-                    let _ = tokio::task::spawn_blocking(move || db.set_setting(&key_for_write, &json))
-                        .await;
+                    self.persist_summary_setting(key.clone(), json).await;
 
                     restored.push((app_type.to_string(), summary));
                     continue;
@@ -222,19 +330,34 @@ impl ProxyServer {
     }
 
     pub async fn stop(&self) -> Result<(), ProxyError> {
-        // 1. 发送关闭信号
+        // 1. 发送关闭信号（停止接受新连接，已建立的连接处理完当前请求后关闭）
         if let Some(tx) = self.shutdown_tx.write().await.take() {
             let _ = tx.send(());
         } else {
             return Err(ProxyError::NotRunning);
         }
 
-        // 2. 等待服务器任务结束（带 5 秒超时保护）
-        if let Some(handle) = self.server_handle.write().await.take() {
-            match tokio::time::timeout(std::time::Duration::from_secs(5), handle).await {
+        // 停止后台健康检查，避免关闭过程中继续探测
+        self.health_checker.stop().await;
+        self.keep_warmer.stop().await;
+        self.state.update_checker.stop().await;
+        if let Some(handle) = self.janitor_handle.write().await.take() {
+            handle.abort();
+        }
+
+        // 2. 排空进行中的请求（含流式响应），超时后中止剩余请求
+        let drain_timeout = std::time::Duration::from_secs(self.config.drain_timeout);
+        self.state.inflight.drain(drain_timeout).await;
+
+        // 3. 等待服务器任务结束（带 5 秒超时保护，超时后中止任务）
+        if let Some(mut handle) = self.server_handle.write().await.take() {
+            match tokio::time::timeout(std::time::Duration::from_secs(5), &mut handle).await {
                 Ok(Ok(())) => log::info!("代理服务器已完全停止"),
                 Ok(Err(e)) => log::warn!("代理服务器任务异常终止: {e}"),
-                Err(_) => log::warn!("代理服务器停止超时（5秒），强制继续"),
+                Err(_) => {
+                    handle.abort();
+                    log::warn!("代理服务器停止超时（5秒），已中止服务器任务");
+                }
             }
         }
 
@@ -242,25 +365,12 @@ impl ProxyServer {
     }
 
     pub async fn get_status(&self) -> ProxyStatus {
-        let mut status = self.state.status.read().await.clone();
-
-        // 计算运行时间
-        if let Some(start) = *self.state.start_time.read().await {
-            status.uptime_seconds = start.elapsed().as_secs();
-        }
-
-        // 从 current_providers HashMap 获取每个应用类型当前正在使用的 provider
-        let current_providers = self.state.current_providers.read().await;
-        status.active_targets = current_providers
-            .iter()
-            .map(|(app_type, (provider_id, provider_name))| ActiveTarget {
-                app_type: app_type.clone(),
-                provider_id: provider_id.clone(),
-                provider_name: provider_name.clone(),
-            })
-            .collect();
+        self.state.snapshot_status().await
+    }
 
-        status
+    /// 最近请求（最新在前）
+    pub fn get_recent_requests(&self) -> Vec<RecentRequest> {
+        self.state.inflight.recent_requests()
     }
 
     fn build_router(&self) -> Router {
@@ -272,7 +382,17 @@ impl ProxyServer {
         Router::new()
             // 健康检查
             .route("/health", get(handlers::health_check))
+            .route("/version", get(handlers::version))
             .route("/status", get(handlers::get_status))
+            .route("/admin/status", get(handlers::admin_status))
+            .route("/admin/recent-requests", get(handlers::recent_requests))
+            .route("/admin/audit/:app_type", get(handlers::admin_audit))
+            .route(
+                "/admin/circuit-breakers/:app_type",
+                get(handlers::admin_circuit_breakers),
+            )
+            .route("/admin/events/:app_type", get(handlers::admin_events))
+            .route("/metrics", get(handlers::metrics))
             // 内部测速 API（供 CLI 复用同一条选路/测速链路；不依赖启动 Claude）
             .route("/__cc_switch/benchmark", post(handlers::benchmark_all_suppliers))
             // 启动即测速：测试覆盖（强制下一次请求走指定 supplier），供 CLI 编排多次启动测试
@@ -284,6 +404,30 @@ impl ProxyServer {
                 "/__cc_switch/test_override/result/:run_id",
                 get(handlers::get_test_result),
             )
+            // 手动重置熔断器（供 CLI 监控面板使用）
+            .route("/__cc_switch/breaker/reset", post(handlers::reset_breaker))
+            .route(
+                "/__cc_switch/breaker/force-open",
+                post(handlers::force_open_breaker),
+            )
+            // 临时暂停/恢复故障转移（供 CLI `failover pause/resume` 使用）
+            .route(
+                "/__cc_switch/failover/pause",
+                post(handlers::pause_failover),
+            )
+            .route(
+                "/__cc_switch/failover/resume",
+                post(handlers::resume_failover),
+            )
+            // 导入配置后热更新（供 CLI 使用）
+            .route("/__cc_switch/config/reload", post(handlers::reload_config))
+            // 队列层级调整后清除选路缓存（供 CLI `queue reorder` 使用）
+            .route(
+                "/__cc_switch/queue/invalidate",
+                post(handlers::invalidate_queue),
+            )
+            // 保存供应商后预热模型列表（供 CLI `add` 使用）
+            .route("/__cc_switch/models/warmup", post(handlers::warm_up_models))
             // Claude API (支持带前缀和不带前缀两种格式)
             .route("/v1/messages", post(handlers::handle_messages))
             .route("/claude/v1/messages", post(handlers::handle_messages))
@@ -309,6 +453,19 @@ impl ProxyServer {
             // Gemini API (支持带前缀和不带前缀)
             .route("/v1beta/*path", post(handlers::handle_gemini))
             .route("/gemini/v1beta/*path", post(handlers::handle_gemini))
+            .layer(middleware::from_fn_with_state(
+                self.state.inflight.clone(),
+                super::inflight::track_inflight,
+            ))
+            // 位于进行中请求追踪之外：被拒绝的请求不计入统计与最近请求
+            .layer(middleware::from_fn_with_state(
+                self.state.app_gate.clone(),
+                super::app_gate::gate_disabled_apps,
+            ))
+            // 错误响应（含被拦截的请求）统一改写为客户端协议的错误结构
+            .layer(middleware::from_fn(super::error_schema::translate_errors))
+            // 最外层：被拦截的请求同样携带请求 ID
+            .layer(middleware::from_fn(super::attribution::assign_request_id))
             .layer(cors)
             .with_state(self.state.clone())
     }
@@ -328,6 +485,138 @@ impl ProxyServer {
         self.state.provider_router.update_all_configs(config).await;
     }
 
+    /// 从数据库刷新各应用的代理开关
+    pub async fn refresh_app_gate(&self) {
+        if let Err(e) = self.state.app_gate.reload(&self.state.db).await {
+            log::warn!("刷新应用代理开关失败: {e}");
+        }
+    }
+
+    /// 同一故障转移层级内共享 API Key 的供应商：相互之间的故障转移不会生效（`csc audit` 查看详情）
+    fn warn_duplicate_keys(&self) {
+        for app_type in ["claude", "codex", "gemini"] {
+            let groups = match self.state.db.find_duplicate_keys(app_type) {
+                Ok(groups) => groups,
+                Err(e) => {
+                    log::warn!("[{app_type}] 检查重复 API Key 失败: {e}");
+                    continue;
+                }
+            };
+            for group in &groups {
+                for (priority, holders) in group.same_priority_sets() {
+                    let names: Vec<&str> = holders.iter().map(|h| h.name.as_str()).collect();
+                    log::warn!(
+                        "[{app_type}:{priority}] {} 个供应商共享同一 API Key（指纹 {}），相互之间的故障转移不会生效: {}",
+                        holders.len(),
+                        group.fingerprint,
+                        names.join(", ")
+                    );
+                }
+            }
+        }
+    }
+
+    /// 队列中未设置 sort_index 的供应商会落入默认层级，容易被误以为“从未使用”
+    fn warn_missing_sort_index(&self) {
+        for app_type in ["claude", "codex", "gemini"] {
+            let providers = match self.state.db.find_missing_sort_index(app_type) {
+                Ok(providers) => providers,
+                Err(e) => {
+                    log::warn!("[{app_type}] 检查未设置优先级层级的供应商失败: {e}");
+                    continue;
+                }
+            };
+            if providers.is_empty() {
+                continue;
+            }
+            let names: Vec<&str> = providers.iter().map(|p| p.name.as_str()).collect();
+            log::warn!(
+                "[{app_type}] {} 个供应商未设置优先级层级，按默认层级 {} 参与路由（csc normalize-priorities {app_type} 可写入）: {}",
+                providers.len(),
+                crate::provider::DEFAULT_PRIORITY,
+                names.join(", ")
+            );
+        }
+    }
+
+    /// 启动一致性检查：记录告警，按设置自动修复简单问题，并通知界面显示提示
+    async fn check_consistency(&self) {
+        let auto_fix = crate::settings::get_auto_fix_consistency() && !self.config.read_only;
+        for app_type in ["claude", "codex", "gemini"] {
+            let report = match self.state.db.check_consistency(app_type, auto_fix).await {
+                Ok(report) => report,
+                Err(e) => {
+                    log::warn!("[{app_type}] 一致性检查失败: {e}");
+                    continue;
+                }
+            };
+            if report.findings.is_empty() {
+                continue;
+            }
+            for finding in report.unresolved() {
+                log::warn!(
+                    "[{app_type}] 一致性检查: {}（建议: {}）",
+                    finding.message,
+                    finding.suggestion
+                );
+            }
+            if let Some(app) = &self.state.app_handle {
+                use tauri::Emitter;
+                if let Err(e) = app.emit("proxy-consistency", &report) {
+                    log::error!("[{app_type}] 发射一致性检查事件失败: {e}");
+                }
+            }
+        }
+    }
+
+    /// 各应用的路由快照（激活层级、各供应商锁定的 URL 与冷却状态）
+    pub async fn get_routing_snapshot(&self) -> Vec<AppRoutingSnapshot> {
+        let mut snapshots = Vec::new();
+        for app_type in ["claude", "codex", "gemini"] {
+            snapshots.push(self.state.provider_router.routing_snapshot(app_type).await);
+        }
+        snapshots
+    }
+
+    /// 该应用下所有供应商的熔断器状态
+    pub async fn get_all_circuit_breaker_stats(
+        &self,
+        app_type: &str,
+    ) -> Vec<ProviderCircuitBreakerStats> {
+        self.state
+            .provider_router
+            .get_all_circuit_breaker_stats(app_type)
+            .await
+            .into_iter()
+            .map(|(provider_id, stats)| ProviderCircuitBreakerStats { provider_id, stats })
+            .collect()
+    }
+
+    /// 单个供应商的熔断器状态
+    pub async fn get_circuit_breaker_stats(
+        &self,
+        provider_id: &str,
+        app_type: &str,
+    ) -> Option<CircuitBreakerStats> {
+        self.state
+            .provider_router
+            .get_circuit_breaker_stats(provider_id, app_type)
+            .await
+    }
+
+    /// 队列成员或层级调整后清除这些 supplier 的选路缓存，返回清除的条目数
+    pub async fn invalidate_suppliers(&self, app_type: &str, suppliers: &[String]) -> usize {
+        self.state
+            .provider_router
+            .invalidate_suppliers(app_type, suppliers)
+            .await
+    }
+
+    /// 模型列表预热器（共用代理的事件记录器与只读设置）
+    pub fn model_warmup(&self) -> ModelWarmup {
+        ModelWarmup::for_proxy(&self.state)
+    }
+
     /// 重置指定 Provider 的熔断器
     pub async fn reset_provider_circuit_breaker(&self, provider_id: &str, app_type: &str) {
         self.state
@@ -335,4 +624,200 @@ impl ProxyServer {
             .reset_provider_breaker(provider_id, app_type)
             .await;
     }
+
+    /// 强制打开指定 Provider 的熔断器
+    pub async fn force_open_provider_circuit_breaker(
+        &self,
+        app_type: &str,
+        provider_id: &str,
+        secs: u64,
+    ) {
+        self.state
+            .provider_router
+            .force_open_provider(app_type, provider_id, secs)
+            .await;
+    }
+
+    /// 临时暂停故障转移，返回暂停状态
+    pub fn pause_failover(&self, app_type: &str, secs: u64) -> FailoverPause {
+        let until = self
+            .state
+            .provider_router
+            .pause_failover(app_type, std::time::Duration::from_secs(secs));
+        FailoverPause {
+            app_type: app_type.to_string(),
+            until,
+            remaining_secs: secs,
+        }
+    }
+
+    /// 提前恢复故障转移；返回此前是否处于暂停中
+    pub fn resume_failover(&self, app_type: &str) -> bool {
+        self.state.provider_router.resume_failover(app_type)
+    }
+
+    /// 暂停中的故障转移（同步读取，供托盘菜单使用）
+    pub fn failover_pauses(&self) -> Vec<FailoverPause> {
+        self.state.provider_router.failover_pauses()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::Provider;
+    use crate::proxy::testkit::{MockResponse, MockUpstream, TestProxy};
+    use axum::Json;
+    use serde_json::json;
+
+    fn total_changes(db: &Database) -> i64 {
+        db.conn
+            .lock()
+            .unwrap()
+            .query_row("SELECT total_changes()", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn read_only_mode_forwards_without_db_writes() {
+        // a 失败触发故障转移，b 正常返回 usage
+        let upstream = MockUpstream::start().await;
+        upstream.route("/a/v1/responses", [MockResponse::text(500, "boom")]);
+        upstream.route(
+            "/b/v1/responses",
+            [MockResponse::json(
+                200,
+                json!({
+                    "model": "gpt-5",
+                    "usage": { "input_tokens": 7, "output_tokens": 3, "total_tokens": 10 }
+                }),
+            )],
+        );
+
+        let proxy = TestProxy::builder()
+            .codex_provider("a", &upstream.url("/a/v1"))
+            .codex_provider("b", &upstream.url("/b/v1"))
+            .app_config("codex", |c| c.auto_failover_enabled = true)
+            .proxy_config(|c| c.read_only = true)
+            .start()
+            .await;
+        assert!(proxy.server.get_status().await.read_only);
+        // b 的维护禁用已到期：照常参与选路，但只读模式下不清除该记录
+        let now = chrono::Utc::now().timestamp();
+        proxy
+            .db
+            .set_provider_disabled_until("codex", "b", Some(now - 60))
+            .unwrap();
+
+        let before = total_changes(&proxy.db);
+        let response = proxy
+            .post_json(
+                "/v1/responses",
+                &json!({ "model": "gpt-5", "input": "hi", "stream": false }),
+            )
+            .await;
+        assert_eq!(response.status(), 200);
+        // 等待后台日志/写回任务（若有）完成
+        tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+        assert_eq!(total_changes(&proxy.db), before);
+        assert_eq!(proxy.server.get_status().await.success_requests, 1);
+        assert_eq!(
+            proxy.db.get_disabled_providers("codex", now - 120).unwrap()["b"],
+            now - 60
+        );
+
+        proxy.stop().await;
+    }
+
+    #[tokio::test]
+    async fn responses_carry_request_id_and_provider_attribution() {
+        use crate::proxy::attribution::{BASE_URL_HEADER, PROVIDER_ID_HEADER, REQUEST_ID_HEADER};
+
+        let app = Router::new().route(
+            "/b/v1/responses",
+            post(|| async {
+                Json(json!({
+                    "model": "gpt-5",
+                    "usage": { "input_tokens": 7, "output_tokens": 3, "total_tokens": 10 }
+                }))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+
+        let db = Arc::new(Database::memory().unwrap());
+        let provider = Provider::with_id(
+            "b".to_string(),
+            "b".to_string(),
+            json!({
+                "env": { "OPENAI_API_KEY": "sk-b" },
+                "base_url": format!("http://user:secret@{addr}/b/v1")
+            }),
+            None,
+        );
+        db.save_provider("codex", &provider).unwrap();
+        db.set_current_provider("codex", "b").unwrap();
+
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let server = ProxyServer::new(
+            ProxyConfig {
+                listen_port: port,
+                ..Default::default()
+            },
+            db.clone(),
+            None,
+        );
+        server.start().await.unwrap();
+
+        let client = reqwest::Client::new();
+        let response = client
+            .post(format!("http://127.0.0.1:{port}/v1/responses"))
+            .header("x-request-id", "client-req-1")
+            .json(&json!({ "model": "gpt-5", "input": "hi", "stream": false }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        let header = |name: &str| response.headers()[name].to_str().unwrap().to_string();
+        assert_eq!(header(REQUEST_ID_HEADER), "client-req-1");
+        assert_eq!(header(PROVIDER_ID_HEADER), "b");
+        assert_eq!(header(BASE_URL_HEADER), format!("http://{addr}/b/v1"));
+
+        // 请求日志行与响应头可按 request_id 关联
+        tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+        let provider_id: String = db
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT provider_id FROM proxy_request_logs WHERE request_id = ?1",
+                ["client-req-1"],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(provider_id, "b");
+
+        // 代理自身生成的错误（未配置 Gemini 供应商）同样带生成的请求 ID
+        let response = client
+            .post(format!(
+                "http://127.0.0.1:{port}/v1beta/models/gemini-pro:generateContent"
+            ))
+            .json(&json!({ "contents": [] }))
+            .send()
+            .await
+            .unwrap();
+        assert!(!response.status().is_success());
+        let request_id = response.headers()[REQUEST_ID_HEADER].to_str().unwrap();
+        assert!(uuid::Uuid::parse_str(request_id).is_ok());
+        assert!(response.headers().get(PROVIDER_ID_HEADER).is_none());
+
+        server.stop().await.unwrap();
+    }
 }
@@ -0,0 +1,234 @@
+//! 影子流量：按比例把非流式请求镜像到评估中的供应商
+//!
+//! 镜像请求在后台任务中发出，结果（状态码、延迟、usage）只写入请求日志并标记
+//! `shadow = 1`；不影响客户端响应，不记录熔断器，也不参与故障转移。
+//! 流式请求不镜像。
+
+use super::usage::calculator::CostCalculator;
+use super::usage::logger::{ModelMapping, RequestLog, TransferBytes, UsageLogger};
+use super::usage::parser::TokenUsage;
+use crate::app_config::AppType;
+use crate::database::Database;
+use crate::provider::Provider;
+use rust_decimal::Decimal;
+use serde_json::Value;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+/// 同时进行中的影子请求上限（影子上游挂起时避免后台任务无限堆积）
+pub const MAX_SHADOW_IN_FLIGHT: usize = 16;
+
+static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+
+static CLAUDE_SAMPLER: ShadowSampler = ShadowSampler::new();
+static CODEX_SAMPLER: ShadowSampler = ShadowSampler::new();
+static GEMINI_SAMPLER: ShadowSampler = ShadowSampler::new();
+
+/// 确定性采样器：第 n 个请求在 `⌊(n+1)·p/100⌋ > ⌊n·p/100⌋` 时镜像
+///
+/// 任意连续 100 个请求恰好镜像 `p` 个，且均匀分布（不依赖随机数）。
+#[derive(Debug, Default)]
+pub struct ShadowSampler {
+    counter: AtomicU64,
+}
+
+impl ShadowSampler {
+    pub const fn new() -> Self {
+        Self {
+            counter: AtomicU64::new(0),
+        }
+    }
+
+    /// 本次请求是否镜像（`percent` 超过 100 按 100 处理）
+    pub fn should_mirror(&self, percent: u8) -> bool {
+        let percent = u64::from(percent.min(100));
+        if percent == 0 {
+            return false;
+        }
+        let n = self.counter.fetch_add(1, Ordering::Relaxed);
+        (n + 1) * percent / 100 > n * percent / 100
+    }
+}
+
+/// 各应用独立的采样器
+pub fn sampler_for(app_type: &AppType) -> &'static ShadowSampler {
+    match app_type {
+        AppType::Claude => &CLAUDE_SAMPLER,
+        AppType::Codex => &CODEX_SAMPLER,
+        AppType::Gemini => &GEMINI_SAMPLER,
+    }
+}
+
+/// 流式请求（`stream: true` 或 Gemini 的 streamGenerateContent）不镜像
+pub fn is_streaming_request(body: &Value, endpoint: &str) -> bool {
+    body.get("stream").and_then(Value::as_bool).unwrap_or(false)
+        || endpoint.contains("streamGenerateContent")
+}
+
+/// 影子请求名额：持有期间计入进行中数量，释放时归还
+#[derive(Debug)]
+pub struct InFlightSlot(());
+
+impl InFlightSlot {
+    /// 已达 `MAX_SHADOW_IN_FLIGHT` 时返回 None（本次不镜像）
+    pub fn try_acquire() -> Option<Self> {
+        IN_FLIGHT
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                (n < MAX_SHADOW_IN_FLIGHT).then_some(n + 1)
+            })
+            .ok()
+            .map(|_| Self(()))
+    }
+}
+
+impl Drop for InFlightSlot {
+    fn drop(&mut self) {
+        IN_FLIGHT.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// 当前进行中的影子请求数
+pub fn in_flight() -> usize {
+    IN_FLIGHT.load(Ordering::Acquire)
+}
+
+/// 一次影子请求的结果
+#[derive(Debug, Clone)]
+pub struct ShadowOutcome {
+    pub status_code: u16,
+    pub latency_ms: u64,
+    pub usage: Option<TokenUsage>,
+    pub error_message: Option<String>,
+    pub requested_model: Option<String>,
+    pub effective_model: Option<String>,
+}
+
+/// 把影子请求结果写入请求日志（`shadow = 1`，使用新的 request_id）
+pub fn record_outcome(
+    db: &Database,
+    app_type: &AppType,
+    provider: &Provider,
+    outcome: ShadowOutcome,
+) -> Result<(), crate::error::AppError> {
+    let logger = UsageLogger::new(db);
+    let usage = outcome.usage.unwrap_or_default();
+    let model = usage
+        .model
+        .clone()
+        .or_else(|| outcome.effective_model.clone())
+        .or_else(|| outcome.requested_model.clone())
+        .unwrap_or_else(|| "unknown".to_string());
+    let model = super::model_sanitizer::sanitize_gpt_model_name(&model);
+
+    let multiplier = provider
+        .meta
+        .as_ref()
+        .and_then(|meta| meta.cost_multiplier.as_deref())
+        .and_then(|cm| Decimal::from_str(cm).ok())
+        .unwrap_or(Decimal::from(1));
+    let pricing = logger.get_model_pricing(&model)?;
+    let cost = CostCalculator::try_calculate(&usage, pricing.as_ref(), multiplier);
+
+    logger.log_request(&RequestLog {
+        request_id: uuid::Uuid::new_v4().to_string(),
+        provider_id: provider.id.clone(),
+        app_type: app_type.as_str().to_string(),
+        model,
+        usage,
+        cost,
+        latency_ms: outcome.latency_ms,
+        first_token_ms: None,
+        upstream_ms: None,
+        bytes: TransferBytes::default(),
+        status_code: outcome.status_code,
+        error_message: outcome.error_message,
+        session_id: None,
+        provider_type: None,
+        is_streaming: false,
+        cost_multiplier: multiplier.to_string(),
+        mapping: ModelMapping {
+            requested: outcome.requested_model,
+            effective: outcome.effective_model,
+        },
+        shadow: true,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn sampler_mirrors_exact_percentage() {
+        for percent in [0u8, 1, 10, 25, 33, 50, 99, 100] {
+            let sampler = ShadowSampler::new();
+            let mirrored = (0..1000).filter(|_| sampler.should_mirror(percent)).count();
+            assert_eq!(mirrored, percent as usize * 10, "percent={percent}");
+        }
+
+        // 超过 100 按 100 处理
+        let sampler = ShadowSampler::new();
+        assert!((0..10).all(|_| sampler.should_mirror(200)));
+    }
+
+    #[test]
+    fn sampler_spreads_mirrors_evenly() {
+        let sampler = ShadowSampler::new();
+        let picks: Vec<bool> = (0..20).map(|_| sampler.should_mirror(25)).collect();
+        for window in picks.chunks(4) {
+            assert_eq!(window.iter().filter(|p| **p).count(), 1, "{picks:?}");
+        }
+    }
+
+    #[test]
+    fn streaming_requests_are_not_mirrored() {
+        assert!(is_streaming_request(
+            &json!({"stream": true}),
+            "/v1/messages"
+        ));
+        assert!(is_streaming_request(
+            &json!({}),
+            "/v1beta/models/gemini-2.5-pro:streamGenerateContent"
+        ));
+        assert!(!is_streaming_request(
+            &json!({"stream": false}),
+            "/v1/messages"
+        ));
+        assert!(!is_streaming_request(&json!({}), "/v1/responses"));
+    }
+
+    #[test]
+    fn record_outcome_flags_log_row_as_shadow() {
+        let db = Database::memory().unwrap();
+        let provider = Provider::with_id("cheap".to_string(), "cheap".to_string(), json!({}), None);
+        let outcome = ShadowOutcome {
+            status_code: 200,
+            latency_ms: 321,
+            usage: TokenUsage::from_app_response(
+                &AppType::Claude,
+                &json!({
+                    "model": "claude-sonnet-4-5",
+                    "usage": {"input_tokens": 10, "output_tokens": 5}
+                }),
+            ),
+            error_message: None,
+            requested_model: Some("claude-sonnet-4-5".to_string()),
+            effective_model: None,
+        };
+        record_outcome(&db, &AppType::Claude, &provider, outcome).unwrap();
+
+        let conn = db.conn.lock().unwrap();
+        let (provider_id, shadow, latency, input): (String, i64, i64, i64) = conn
+            .query_row(
+                "SELECT provider_id, shadow, latency_ms, input_tokens FROM proxy_request_logs",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+            )
+            .unwrap();
+        assert_eq!(provider_id, "cheap");
+        assert_eq!(shadow, 1);
+        assert_eq!(latency, 321);
+        assert_eq!(input, 10);
+    }
+}
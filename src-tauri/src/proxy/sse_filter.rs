@@ -0,0 +1,296 @@
+//! 流式响应过滤：丢弃供应商注入的自定义 SSE 事件
+//!
+//! 部分聚合商会在流中插入自定义事件（如 `event: billing_info`），导致客户端解析器崩溃。
+//! 供应商配置 `settings_config.sseFilter` 后，转发器按 SSE 事件边界（空行）切分上游流，
+//! 丢弃匹配的事件，其余内容逐字节原样转发（包括多行 data 字段与 `\r\n` 换行）：
+//!
+//! ```json
+//! { "sseFilter": { "dropEvents": ["billing_info"], "dropDataContaining": ["advertisement"] } }
+//! ```
+
+use super::ProxyError;
+use crate::provider::Provider;
+use bytes::{Bytes, BytesMut};
+use futures::stream::{Stream, StreamExt};
+use reqwest::Response;
+use serde::Deserialize;
+
+/// settings_config 中的过滤配置字段
+pub const SSE_FILTER_KEY: &str = "sseFilter";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SseFilter {
+    /// 按 `event:` 名称丢弃
+    pub drop_events: Vec<String>,
+    /// 任一 `data:` 行包含其中文本时丢弃
+    pub drop_data_containing: Vec<String>,
+}
+
+impl SseFilter {
+    /// 读取供应商配置（未配置、格式错误或规则为空时返回 None）
+    pub fn from_provider(provider: &Provider) -> Option<Self> {
+        let raw = provider.settings_config.get(SSE_FILTER_KEY)?;
+        match serde_json::from_value::<Self>(raw.clone()) {
+            Ok(filter)
+                if !filter.drop_events.is_empty() || !filter.drop_data_containing.is_empty() =>
+            {
+                Some(filter)
+            }
+            Ok(_) => None,
+            Err(e) => {
+                log::warn!("[{}] {SSE_FILTER_KEY} 配置无效，已忽略: {e}", provider.id);
+                None
+            }
+        }
+    }
+
+    /// 单个事件（含结尾空行）是否应丢弃
+    fn should_drop(&self, event: &[u8]) -> bool {
+        let text = String::from_utf8_lossy(event);
+        text.split(['\r', '\n']).any(|line| {
+            if let Some(name) = field_value(line, "event") {
+                return self.drop_events.iter().any(|e| e == name.trim_end());
+            }
+            if let Some(data) = field_value(line, "data") {
+                return self
+                    .drop_data_containing
+                    .iter()
+                    .any(|pat| !pat.is_empty() && data.contains(pat.as_str()));
+            }
+            false
+        })
+    }
+}
+
+/// SSE 字段值（冒号后的单个空格按规范去掉）
+fn field_value<'a>(line: &'a str, field: &str) -> Option<&'a str> {
+    let value = line.strip_prefix(field)?.strip_prefix(':')?;
+    Some(value.strip_prefix(' ').unwrap_or(value))
+}
+
+/// 找到首个完整事件的结束位置（含结尾空行）；事件不完整时返回 None
+///
+/// 行结束符可以是 `\n`、`\r\n` 或 `\r`；缓冲区以 `\r` 结尾时无法判断后面是否跟 `\n`，等待下一块。
+fn event_end(buf: &[u8]) -> Option<usize> {
+    let mut line_start = 0;
+    let mut i = 0;
+    while i < buf.len() {
+        let terminator = match buf[i] {
+            b'\n' => 1,
+            b'\r' => match buf.get(i + 1) {
+                Some(b'\n') => 2,
+                Some(_) => 1,
+                None => return None,
+            },
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        let end = i + terminator;
+        if i == line_start {
+            return Some(end);
+        }
+        line_start = end;
+        i = end;
+    }
+    None
+}
+
+/// 按事件边界切分并过滤（跨块事件会缓存到完整后再判断）
+#[derive(Debug, Default)]
+struct SseEventFilter {
+    filter: SseFilter,
+    buffer: BytesMut,
+    dropped: usize,
+}
+
+impl SseEventFilter {
+    fn new(filter: SseFilter) -> Self {
+        Self {
+            filter,
+            ..Self::default()
+        }
+    }
+
+    /// 输入一块数据，返回可以立即转发的字节（可能为空）
+    fn push(&mut self, chunk: &[u8]) -> Bytes {
+        self.buffer.extend_from_slice(chunk);
+        let mut out = BytesMut::new();
+        while let Some(end) = event_end(&self.buffer) {
+            let event = self.buffer.split_to(end);
+            if self.filter.should_drop(&event) {
+                self.dropped += 1;
+            } else {
+                out.extend_from_slice(&event);
+            }
+        }
+        out.freeze()
+    }
+
+    /// 流结束：剩余的不完整事件同样按规则判断
+    fn finish(&mut self) -> Bytes {
+        let rest = self.buffer.split();
+        if rest.is_empty() || self.filter.should_drop(&rest) {
+            if !rest.is_empty() {
+                self.dropped += 1;
+            }
+            return Bytes::new();
+        }
+        rest.freeze()
+    }
+}
+
+/// 过滤 SSE 字节流（保留的内容逐字节透传）
+pub fn filter_sse_stream(
+    stream: impl Stream<Item = Result<Bytes, reqwest::Error>> + Send + 'static,
+    filter: SseFilter,
+    tag: String,
+) -> impl Stream<Item = Result<Bytes, std::io::Error>> + Send {
+    async_stream::stream! {
+        let mut events = SseEventFilter::new(filter);
+        tokio::pin!(stream);
+
+        while let Some(chunk) = stream.next().await {
+            match chunk {
+                Ok(bytes) => {
+                    let out = events.push(&bytes);
+                    if !out.is_empty() {
+                        yield Ok(out);
+                    }
+                }
+                Err(e) => {
+                    yield Err(std::io::Error::other(e));
+                    return;
+                }
+            }
+        }
+        let rest = events.finish();
+        if !rest.is_empty() {
+            yield Ok(rest);
+        }
+        if events.dropped > 0 {
+            log::debug!("[{tag}] sseFilter 丢弃 {} 个事件", events.dropped);
+        }
+    }
+}
+
+/// 供应商配置了 sseFilter 且响应为 SSE 时，包装响应体为过滤流；否则原样返回
+pub fn filter_sse_response(
+    provider: &Provider,
+    response: Response,
+) -> Result<Response, ProxyError> {
+    let Some(filter) = SseFilter::from_provider(provider) else {
+        return Ok(response);
+    };
+    let is_sse = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.contains("text/event-stream"))
+        .unwrap_or(false);
+    if !is_sse {
+        return Ok(response);
+    }
+
+    let mut builder = axum::http::Response::builder().status(response.status());
+    for (key, value) in response.headers() {
+        // 过滤后长度会变化
+        if key != reqwest::header::CONTENT_LENGTH {
+            builder = builder.header(key, value);
+        }
+    }
+    let stream = filter_sse_stream(response.bytes_stream(), filter, provider.id.clone());
+    let filtered = builder
+        .body(reqwest::Body::wrap_stream(stream))
+        .map_err(|e| ProxyError::Internal(format!("构造 SSE 过滤响应失败: {e}")))?;
+    Ok(Response::from(filtered))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter() -> SseFilter {
+        SseFilter {
+            drop_events: vec!["billing_info".to_string()],
+            drop_data_containing: vec!["advertisement".to_string()],
+        }
+    }
+
+    /// 以固定块大小切分输入，逐块送入过滤器
+    fn run_chunked(input: &str, chunk_size: usize) -> String {
+        let mut events = SseEventFilter::new(filter());
+        let mut out = Vec::new();
+        for chunk in input.as_bytes().chunks(chunk_size) {
+            out.extend_from_slice(&events.push(chunk));
+        }
+        out.extend_from_slice(&events.finish());
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn drops_injected_events_across_chunk_boundaries() {
+        let kept_1 = "event: message_start\ndata: {\"type\":\"message_start\"}\n\n";
+        let billing = "event: billing_info\ndata: {\"cost\":0.01}\n\n";
+        let kept_2 = "event: content_block_delta\ndata: line one\ndata: line two\n\n";
+        let ad = "data: {\"advertisement\":\"buy now\"}\n\n";
+        let kept_3 = ": keep-alive comment\n\nevent: message_stop\ndata: {}\n\n";
+        let input = format!("{kept_1}{billing}{kept_2}{ad}{kept_3}");
+        let expected = format!("{kept_1}{kept_2}{kept_3}");
+
+        for chunk_size in [1, 2, 3, 7, 16, input.len()] {
+            assert_eq!(
+                run_chunked(&input, chunk_size),
+                expected,
+                "chunk={chunk_size}"
+            );
+        }
+    }
+
+    #[test]
+    fn preserves_crlf_bytes_and_trailing_partial_event() {
+        let kept = "event: ping\r\ndata: {}\r\n\r\n";
+        let billing = "event:billing_info\r\ndata: x\r\n\r\n";
+        let tail = "data: unterminated";
+        let input = format!("{kept}{billing}{tail}");
+        for chunk_size in [1, 5, input.len()] {
+            assert_eq!(run_chunked(&input, chunk_size), format!("{kept}{tail}"));
+        }
+
+        // 未终止的注入事件在流结束时同样丢弃
+        assert_eq!(
+            run_chunked("data: ok\n\nevent: billing_info\n", 4),
+            "data: ok\n\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn filter_stream_passes_through_untouched_when_nothing_matches() {
+        let input = "event: a\ndata: 1\n\nevent: b\ndata: 2\n\n";
+        let chunks: Vec<Result<Bytes, reqwest::Error>> = input
+            .as_bytes()
+            .chunks(5)
+            .map(|c| Ok(Bytes::copy_from_slice(c)))
+            .collect();
+        let out: Vec<u8> = filter_sse_stream(futures::stream::iter(chunks), filter(), "t".into())
+            .map(|chunk| chunk.unwrap().to_vec())
+            .concat()
+            .await;
+        assert_eq!(out, input.as_bytes());
+    }
+
+    #[test]
+    fn absent_or_empty_config_is_ignored() {
+        let provider = |settings| Provider::with_id("p".into(), "p".into(), settings, None);
+        assert!(SseFilter::from_provider(&provider(serde_json::json!({}))).is_none());
+        assert!(
+            SseFilter::from_provider(&provider(serde_json::json!({ "sseFilter": {} }))).is_none()
+        );
+        assert!(SseFilter::from_provider(&provider(
+            serde_json::json!({ "sseFilter": { "dropEvents": ["billing_info"] } })
+        ))
+        .is_some());
+    }
+}
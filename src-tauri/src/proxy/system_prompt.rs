@@ -0,0 +1,265 @@
+//! 供应商 system prompt 注入
+//!
+//! 部分转售端点要求固定的厂商前导语才能解锁特定模型。供应商配置
+//! `settings_config.systemPrompt: {"prepend": "...", "append": "..."}` 后，转发前注入：
+//! - Claude：`system`（字符串或 content block 数组）
+//! - Codex Responses：`instructions`；Chat Completions：首条 system 消息（没有时插入一条）
+//!
+//! 注入是幂等的：内容已以 prepend 开头 / 以 append 结尾时不再重复添加，
+//! 同一供应商上的重试（如模型不可用重试）不会叠加。
+
+use crate::provider::Provider;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// settings_config 中的注入字段
+pub const SYSTEM_PROMPT_KEY: &str = "systemPrompt";
+
+/// 前后文本之间的分隔
+const SEPARATOR: &str = "\n\n";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SystemPromptInjection {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prepend: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub append: Option<String>,
+}
+
+impl SystemPromptInjection {
+    /// 读取供应商配置（未配置、格式错误或前后均为空时返回 None）
+    pub fn from_provider(provider: &Provider) -> Option<Self> {
+        let raw = provider.settings_config.get(SYSTEM_PROMPT_KEY)?;
+        let parsed: Self = match serde_json::from_value(raw.clone()) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                log::warn!(
+                    "[{}] {SYSTEM_PROMPT_KEY} 配置无效，已忽略: {e}",
+                    provider.id
+                );
+                return None;
+            }
+        };
+        let non_empty = |s: Option<String>| s.filter(|s| !s.trim().is_empty());
+        let injection = Self {
+            prepend: non_empty(parsed.prepend),
+            append: non_empty(parsed.append),
+        };
+        (injection.prepend.is_some() || injection.append.is_some()).then_some(injection)
+    }
+
+    /// 拼接字符串形式的 prompt（已包含时不重复添加）
+    fn wrap_text(&self, text: &str) -> String {
+        let mut out = text.to_string();
+        if let Some(prepend) = &self.prepend {
+            if !out.starts_with(prepend.as_str()) {
+                out = if out.is_empty() {
+                    prepend.clone()
+                } else {
+                    format!("{prepend}{SEPARATOR}{out}")
+                };
+            }
+        }
+        if let Some(append) = &self.append {
+            if !out.ends_with(append.as_str()) {
+                out = if out.is_empty() {
+                    append.clone()
+                } else {
+                    format!("{out}{SEPARATOR}{append}")
+                };
+            }
+        }
+        out
+    }
+
+    /// content block 数组：首尾各插入一个文本块（已存在时不重复插入）
+    fn wrap_blocks(&self, blocks: &mut Vec<Value>) {
+        let block_text = |b: Option<&Value>| {
+            b.and_then(|b| b.get("text"))
+                .and_then(|t| t.as_str())
+                .map(|t| t.to_string())
+        };
+        if let Some(prepend) = &self.prepend {
+            if block_text(blocks.first()).as_deref() != Some(prepend.as_str()) {
+                blocks.insert(0, json!({ "type": "text", "text": prepend }));
+            }
+        }
+        if let Some(append) = &self.append {
+            if block_text(blocks.last()).as_deref() != Some(append.as_str()) {
+                blocks.push(json!({ "type": "text", "text": append }));
+            }
+        }
+    }
+
+    /// 注入到字符串或 block 数组形式的字段（字段缺失时以字符串形式写入）
+    fn wrap_field(&self, slot: Option<&mut Value>) -> Option<Value> {
+        match slot {
+            Some(Value::Array(blocks)) => {
+                self.wrap_blocks(blocks);
+                None
+            }
+            Some(Value::String(text)) => {
+                *text = self.wrap_text(text);
+                None
+            }
+            Some(Value::Null) | None => Some(Value::String(self.wrap_text(""))),
+            // 其它类型无法安全注入，保持原样
+            Some(_) => None,
+        }
+    }
+
+    /// 按请求格式注入（`adapter_name` 为适配器名称）
+    pub fn apply(&self, body: &mut Value, adapter_name: &str) {
+        let Some(obj) = body.as_object_mut() else {
+            return;
+        };
+        match adapter_name {
+            "Claude" => {
+                if let Some(value) = self.wrap_field(obj.get_mut("system")) {
+                    obj.insert("system".to_string(), value);
+                }
+            }
+            "Codex" => {
+                if let Some(Value::Array(messages)) = obj.get_mut("messages") {
+                    let system = messages
+                        .iter_mut()
+                        .find(|m| m.get("role").and_then(|r| r.as_str()) == Some("system"));
+                    match system.and_then(|m| m.as_object_mut()) {
+                        Some(message) => {
+                            if let Some(value) = self.wrap_field(message.get_mut("content")) {
+                                message.insert("content".to_string(), value);
+                            }
+                        }
+                        None => messages.insert(
+                            0,
+                            json!({ "role": "system", "content": self.wrap_text("") }),
+                        ),
+                    }
+                } else if let Some(value) = self.wrap_field(obj.get_mut("instructions")) {
+                    obj.insert("instructions".to_string(), value);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// 应用供应商配置的 system prompt 注入（未配置或格式不支持时返回 None，调用方继续使用原请求体）
+pub fn apply_system_prompt(body: &Value, provider: &Provider, adapter_name: &str) -> Option<Value> {
+    let injection = SystemPromptInjection::from_provider(provider)?;
+    let mut injected = body.clone();
+    injection.apply(&mut injected, adapter_name);
+    (injected != *body).then_some(injected)
+}
+
+/// 注入后的 prompt 字段（演练报告用）：`system` / `instructions` / 首条 system 消息
+pub fn injected_prompt(body: &Value) -> Option<Value> {
+    body.get("system")
+        .or_else(|| body.get("instructions"))
+        .or_else(|| {
+            body.get("messages")?
+                .as_array()?
+                .iter()
+                .find(|m| m.get("role").and_then(|r| r.as_str()) == Some("system"))?
+                .get("content")
+        })
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider(prompt: Value) -> Provider {
+        Provider::with_id(
+            "p".to_string(),
+            "p".to_string(),
+            json!({ "systemPrompt": prompt }),
+            None,
+        )
+    }
+
+    #[test]
+    fn claude_string_and_block_forms() {
+        let p = provider(json!({ "prepend": "VENDOR", "append": "END" }));
+
+        let body = apply_system_prompt(&json!({ "system": "be brief" }), &p, "Claude").unwrap();
+        assert_eq!(body["system"], "VENDOR\n\nbe brief\n\nEND");
+
+        let body = apply_system_prompt(&json!({ "messages": [] }), &p, "Claude").unwrap();
+        assert_eq!(body["system"], "VENDOR\n\nEND");
+
+        let blocks = json!({
+            "system": [{ "type": "text", "text": "be brief", "cache_control": { "type": "ephemeral" } }]
+        });
+        let body = apply_system_prompt(&blocks, &p, "Claude").unwrap();
+        let texts: Vec<_> = body["system"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|b| b["text"].as_str().unwrap())
+            .collect();
+        assert_eq!(texts, vec!["VENDOR", "be brief", "END"]);
+        assert_eq!(body["system"][1]["cache_control"]["type"], "ephemeral");
+    }
+
+    #[test]
+    fn openai_instructions_and_system_messages() {
+        let p = provider(json!({ "prepend": "VENDOR" }));
+
+        let body = apply_system_prompt(&json!({ "instructions": "hi" }), &p, "Codex").unwrap();
+        assert_eq!(body["instructions"], "VENDOR\n\nhi");
+
+        let chat = json!({ "messages": [
+            { "role": "system", "content": "sys" },
+            { "role": "user", "content": "hi" }
+        ] });
+        let body = apply_system_prompt(&chat, &p, "Codex").unwrap();
+        assert_eq!(body["messages"][0]["content"], "VENDOR\n\nsys");
+        assert_eq!(body["messages"].as_array().unwrap().len(), 2);
+
+        let chat = json!({ "messages": [{ "role": "user", "content": "hi" }] });
+        let body = apply_system_prompt(&chat, &p, "Codex").unwrap();
+        assert_eq!(
+            body["messages"][0],
+            json!({ "role": "system", "content": "VENDOR" })
+        );
+    }
+
+    #[test]
+    fn injection_is_idempotent_across_retries() {
+        let p = provider(json!({ "prepend": "VENDOR", "append": "END" }));
+        for (body, adapter) in [
+            (json!({ "system": "be brief" }), "Claude"),
+            (
+                json!({ "system": [{ "type": "text", "text": "x" }] }),
+                "Claude",
+            ),
+            (json!({ "instructions": "hi" }), "Codex"),
+            (
+                json!({ "messages": [{ "role": "user", "content": "hi" }] }),
+                "Codex",
+            ),
+        ] {
+            let once = apply_system_prompt(&body, &p, adapter).unwrap();
+            // 重试路径使用已注入的请求体：不再变化
+            assert!(apply_system_prompt(&once, &p, adapter).is_none());
+        }
+    }
+
+    #[test]
+    fn absent_config_leaves_body_untouched() {
+        let body = json!({ "system": "be brief" });
+        let none = Provider::with_id("p".to_string(), "p".to_string(), json!({}), None);
+        assert!(apply_system_prompt(&body, &none, "Claude").is_none());
+        assert!(
+            apply_system_prompt(&body, &provider(json!({ "prepend": " " })), "Claude").is_none()
+        );
+        assert!(apply_system_prompt(&body, &provider(json!("x")), "Claude").is_none());
+        // Gemini 不处理
+        assert!(
+            apply_system_prompt(&body, &provider(json!({ "prepend": "V" })), "Gemini").is_none()
+        );
+    }
+}
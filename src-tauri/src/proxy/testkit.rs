@@ -0,0 +1,470 @@
+//! 端到端测试工具（仅测试编译）
+//!
+//! 单元测试覆盖不到 handler → 路由 → 转发 → 上游 这条完整链路，而请求头、重试、流式转发的回归
+//! 多发生在这里。本模块提供：
+//! - [`MockUpstream`]：可编程的模拟上游，按路由返回预设的响应序列（状态码、延迟、SSE 流、失败序列），
+//!   并记录收到的每个请求
+//! - [`TestProxy`]：绑定临时端口、使用内存数据库的进程内代理实例
+//!
+//! ```ignore
+//! let upstream = MockUpstream::start().await;
+//! upstream.route("/a/v1/responses", [MockResponse::status(500), MockResponse::json(200, body)]);
+//! let proxy = TestProxy::builder().codex_provider("a", &upstream.url("/a/v1")).start().await;
+//! let response = proxy.post_json("/v1/responses", &json!({ "model": "gpt-5" })).await;
+//! ```
+
+use super::server::ProxyServer;
+use super::types::{AppProxyConfig, ProxyConfig};
+use crate::database::Database;
+use crate::provider::Provider;
+use axum::body::{Body, Bytes};
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, Method, StatusCode};
+use axum::response::Response;
+use axum::Router;
+use futures::StreamExt;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// 模拟上游的响应体
+#[derive(Debug, Clone)]
+pub enum MockBody {
+    /// 一次性返回的完整响应体
+    Full(Bytes),
+    /// SSE 流：每个事件之间间隔 `interval`
+    Sse {
+        events: Vec<String>,
+        interval: Duration,
+    },
+}
+
+/// 一条预设响应
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: MockBody,
+    /// 返回响应头之前的等待时间
+    pub delay: Duration,
+}
+
+impl MockResponse {
+    /// JSON 响应
+    pub fn json(status: u16, body: Value) -> Self {
+        Self {
+            status,
+            headers: vec![("content-type".to_string(), "application/json".to_string())],
+            body: MockBody::Full(Bytes::from(body.to_string())),
+            delay: Duration::ZERO,
+        }
+    }
+
+    /// 纯文本响应
+    pub fn text(status: u16, body: &str) -> Self {
+        Self {
+            status,
+            headers: vec![("content-type".to_string(), "text/plain".to_string())],
+            body: MockBody::Full(Bytes::from(body.to_string())),
+            delay: Duration::ZERO,
+        }
+    }
+
+    /// 只有状态码的错误响应
+    pub fn status(status: u16) -> Self {
+        Self::text(status, "mock upstream error")
+    }
+
+    /// SSE 流响应：`events` 为完整的事件文本（不含结尾空行）
+    pub fn sse<I, S>(events: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            status: 200,
+            headers: vec![("content-type".to_string(), "text/event-stream".to_string())],
+            body: MockBody::Sse {
+                events: events.into_iter().map(Into::into).collect(),
+                interval: Duration::ZERO,
+            },
+            delay: Duration::ZERO,
+        }
+    }
+
+    /// 返回响应前等待
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// SSE 事件之间的间隔（非 SSE 响应忽略）
+    pub fn with_event_interval(mut self, interval: Duration) -> Self {
+        if let MockBody::Sse { interval: i, .. } = &mut self.body {
+            *i = interval;
+        }
+        self
+    }
+
+    /// 追加响应头
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    fn into_response(self) -> Response {
+        let mut builder = Response::builder().status(self.status);
+        for (name, value) in &self.headers {
+            builder = builder.header(name, value);
+        }
+        let body = match self.body {
+            MockBody::Full(bytes) => Body::from(bytes),
+            MockBody::Sse { events, interval } => {
+                let stream = futures::stream::iter(events.into_iter().enumerate()).then(
+                    move |(i, event)| async move {
+                        if i > 0 && !interval.is_zero() {
+                            tokio::time::sleep(interval).await;
+                        }
+                        Ok::<_, Infallible>(Bytes::from(format!("{event}\n\n")))
+                    },
+                );
+                Body::from_stream(stream)
+            }
+        };
+        builder.body(body).expect("mock response")
+    }
+}
+
+/// 模拟上游收到的请求
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: Method,
+    pub path: String,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+impl RecordedRequest {
+    /// 以 JSON 解析请求体（非 JSON 时为 Null）
+    pub fn json(&self) -> Value {
+        serde_json::from_slice(&self.body).unwrap_or(Value::Null)
+    }
+}
+
+#[derive(Default)]
+struct MockState {
+    /// 路径 -> 剩余的响应序列（最后一条重复使用）
+    routes: HashMap<String, Vec<MockResponse>>,
+    requests: Vec<RecordedRequest>,
+}
+
+/// 可编程的模拟上游
+///
+/// 按请求路径匹配（不区分方法）。每条路由按顺序消费预设响应，最后一条会一直重复；
+/// 未配置的路径返回 404。
+pub struct MockUpstream {
+    addr: SocketAddr,
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockUpstream {
+    /// 在临时端口上启动
+    pub async fn start() -> Self {
+        let state = Arc::new(Mutex::new(MockState::default()));
+        let app = Router::new()
+            .fallback(Self::handle)
+            .with_state(state.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+        Self { addr, state }
+    }
+
+    async fn handle(State(state): State<Arc<Mutex<MockState>>>, request: Request) -> Response {
+        let (parts, body) = request.into_parts();
+        let body = axum::body::to_bytes(body, usize::MAX)
+            .await
+            .unwrap_or_default();
+        let path = parts.uri.path().to_string();
+
+        let response = {
+            let mut state = state.lock().unwrap();
+            state.requests.push(RecordedRequest {
+                method: parts.method,
+                path: path.clone(),
+                headers: parts.headers,
+                body,
+            });
+            state.routes.get_mut(&path).map(|queue| {
+                if queue.len() > 1 {
+                    queue.remove(0)
+                } else {
+                    queue[0].clone()
+                }
+            })
+        };
+
+        match response {
+            Some(response) => {
+                if !response.delay.is_zero() {
+                    tokio::time::sleep(response.delay).await;
+                }
+                response.into_response()
+            }
+            None => Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(Body::from(format!("no mock route for {path}")))
+                .unwrap(),
+        }
+    }
+
+    /// 设置（覆盖）某个路径的响应序列
+    pub fn route<I>(&self, path: &str, responses: I) -> &Self
+    where
+        I: IntoIterator<Item = MockResponse>,
+    {
+        let responses: Vec<_> = responses.into_iter().collect();
+        assert!(!responses.is_empty(), "mock route {path} 至少需要一条响应");
+        self.state
+            .lock()
+            .unwrap()
+            .routes
+            .insert(path.to_string(), responses);
+        self
+    }
+
+    /// 上游地址 + 路径前缀，例如 `url("/a/v1")` -> `http://127.0.0.1:port/a/v1`
+    pub fn url(&self, prefix: &str) -> String {
+        format!("http://{}{prefix}", self.addr)
+    }
+
+    /// 收到的全部请求（按到达顺序）
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// 某个路径收到的请求数
+    pub fn hits(&self, path: &str) -> usize {
+        self.state
+            .lock()
+            .unwrap()
+            .requests
+            .iter()
+            .filter(|r| r.path == path)
+            .count()
+    }
+}
+
+/// Codex 供应商（OpenAI 兼容）：`base_url` 指向模拟上游
+pub fn codex_provider(id: &str, base_url: &str) -> Provider {
+    Provider::with_id(
+        id.to_string(),
+        id.to_string(),
+        json!({
+            "env": { "OPENAI_API_KEY": format!("sk-{id}") },
+            "base_url": base_url
+        }),
+        None,
+    )
+}
+
+/// Claude 供应商：请求经 Python 代理转发，`base_url` 只作为 `x-target-base-url` 传递
+pub fn claude_provider(id: &str, base_url: &str) -> Provider {
+    Provider::with_id(
+        id.to_string(),
+        id.to_string(),
+        json!({
+            "env": {
+                "ANTHROPIC_AUTH_TOKEN": format!("sk-{id}"),
+                "ANTHROPIC_BASE_URL": base_url
+            }
+        }),
+        None,
+    )
+}
+
+type AppConfigUpdate = Box<dyn FnOnce(&mut AppProxyConfig) + Send>;
+
+/// [`TestProxy`] 构建器：启动前写入供应商与应用配置
+pub struct TestProxyBuilder {
+    db: Arc<Database>,
+    config: ProxyConfig,
+    /// (app_type, provider)，按添加顺序写入 sort_index 与故障转移队列
+    providers: Vec<(String, Provider)>,
+    app_configs: Vec<(String, AppConfigUpdate)>,
+}
+
+impl TestProxyBuilder {
+    /// 添加供应商（第一个添加的供应商成为当前供应商）
+    pub fn provider(mut self, app_type: &str, provider: Provider) -> Self {
+        self.providers.push((app_type.to_string(), provider));
+        self
+    }
+
+    /// 添加 Codex 供应商
+    pub fn codex_provider(self, id: &str, base_url: &str) -> Self {
+        self.provider("codex", codex_provider(id, base_url))
+    }
+
+    /// 添加 Claude 供应商
+    pub fn claude_provider(self, id: &str, base_url: &str) -> Self {
+        self.provider("claude", claude_provider(id, base_url))
+    }
+
+    /// 修改应用级代理配置
+    pub fn app_config<F>(mut self, app_type: &str, update: F) -> Self
+    where
+        F: FnOnce(&mut AppProxyConfig) + Send + 'static,
+    {
+        self.app_configs
+            .push((app_type.to_string(), Box::new(update)));
+        self
+    }
+
+    /// 修改全局代理配置（监听端口由构建器分配）
+    pub fn proxy_config<F>(mut self, update: F) -> Self
+    where
+        F: FnOnce(&mut ProxyConfig),
+    {
+        update(&mut self.config);
+        self
+    }
+
+    /// 写入配置并启动代理
+    pub async fn start(self) -> TestProxy {
+        let db = self.db;
+        let mut current: HashMap<String, String> = HashMap::new();
+        for (index, (app_type, mut provider)) in self.providers.into_iter().enumerate() {
+            provider.sort_index.get_or_insert(index + 1);
+            db.save_provider(&app_type, &provider).unwrap();
+            db.add_to_failover_queue(&app_type, &provider.id).unwrap();
+            current.entry(app_type).or_insert(provider.id);
+        }
+        for (app_type, id) in &current {
+            db.set_current_provider(app_type, id).unwrap();
+        }
+        for (app_type, update) in self.app_configs {
+            let mut config = db.get_proxy_config_for_app(&app_type).await.unwrap();
+            update(&mut config);
+            db.update_proxy_config_for_app(config).await.unwrap();
+        }
+
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let server = ProxyServer::new(
+            ProxyConfig {
+                listen_port: port,
+                ..self.config
+            },
+            db.clone(),
+            None,
+        );
+        server.start().await.unwrap();
+
+        TestProxy {
+            db,
+            server,
+            port,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+/// 进程内代理实例（内存数据库 + 临时端口）
+pub struct TestProxy {
+    pub db: Arc<Database>,
+    pub server: ProxyServer,
+    pub port: u16,
+    client: reqwest::Client,
+}
+
+impl TestProxy {
+    pub fn builder() -> TestProxyBuilder {
+        TestProxyBuilder {
+            db: Arc::new(Database::memory().unwrap()),
+            config: ProxyConfig::default(),
+            providers: Vec::new(),
+            app_configs: Vec::new(),
+        }
+    }
+
+    /// 代理地址 + 路径
+    pub fn url(&self, path: &str) -> String {
+        format!("http://127.0.0.1:{}{path}", self.port)
+    }
+
+    /// 发送 JSON POST 请求
+    pub async fn post_json(&self, path: &str, body: &Value) -> reqwest::Response {
+        self.client
+            .post(self.url(path))
+            .json(body)
+            .send()
+            .await
+            .unwrap()
+    }
+
+    /// 发送带额外请求头的 JSON POST 请求
+    pub async fn post_json_with_headers(
+        &self,
+        path: &str,
+        body: &Value,
+        headers: &[(&str, &str)],
+    ) -> reqwest::Response {
+        let mut request = self.client.post(self.url(path)).json(body);
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        request.send().await.unwrap()
+    }
+
+    /// 停止代理
+    pub async fn stop(self) {
+        self.server.stop().await.unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn mock_route_consumes_sequence_and_repeats_last() {
+        let upstream = MockUpstream::start().await;
+        upstream.route(
+            "/seq",
+            [
+                MockResponse::status(503).with_header("retry-after", "1"),
+                MockResponse::json(200, json!({ "ok": true }))
+                    .with_delay(Duration::from_millis(30)),
+            ],
+        );
+        let client = reqwest::Client::new();
+        let get = |path: &str| client.post(upstream.url(path)).body("x").send();
+
+        let first = get("/seq").await.unwrap();
+        assert_eq!(first.status(), 503);
+        assert_eq!(first.headers()["retry-after"], "1");
+        for _ in 0..2 {
+            let started = std::time::Instant::now();
+            let response = get("/seq").await.unwrap();
+            assert_eq!(response.status(), 200);
+            assert!(started.elapsed() >= Duration::from_millis(30));
+        }
+        assert_eq!(get("/missing").await.unwrap().status(), 404);
+
+        assert_eq!(upstream.hits("/seq"), 3);
+        let requests = upstream.requests();
+        assert_eq!(requests.len(), 4);
+        assert_eq!(requests[0].method, Method::POST);
+        assert_eq!(requests[0].body, Bytes::from("x"));
+        assert_eq!(requests[0].json(), Value::Null);
+    }
+}
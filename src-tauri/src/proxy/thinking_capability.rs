@@ -0,0 +1,144 @@
+//! 扩展思考（thinking）能力
+//!
+//! 供应商 `settings_config.supportsThinking` 标记上游模型是否支持 `thinking.type == "enabled"`，
+//! 未配置时视为支持。未配置的供应商按需推断：thinking 请求首次被上游以“不支持
+//! thinking/reasoning”拒绝后写回 `false`，之后的 thinking 请求在选路时排到链尾。
+
+use super::error::ProxyError;
+use crate::provider::Provider;
+
+/// settings_config 中的能力字段
+pub const SUPPORTS_THINKING_KEY: &str = "supportsThinking";
+
+/// 供应商显式配置的 thinking 能力（未配置返回 None）
+pub fn configured_support(provider: &Provider) -> Option<bool> {
+    provider
+        .settings_config
+        .get(SUPPORTS_THINKING_KEY)
+        .and_then(|v| v.as_bool())
+}
+
+/// 供应商是否支持 thinking（未配置视为支持）
+pub fn supports_thinking(provider: &Provider) -> bool {
+    configured_support(provider).unwrap_or(true)
+}
+
+/// 上游是否因不支持 thinking 而拒绝了请求（仅 4xx 且错误文本明确提及 thinking/reasoning）
+pub fn is_thinking_unsupported_error(err: &ProxyError) -> bool {
+    match err {
+        ProxyError::UpstreamError {
+            status,
+            body: Some(body),
+        } if (400..500).contains(status) && *status != 429 => is_thinking_unsupported_text(body),
+        _ => false,
+    }
+}
+
+fn is_thinking_unsupported_text(text: &str) -> bool {
+    let lower = text.to_lowercase();
+    let mentions_thinking = lower.contains("thinking") || lower.contains("reasoning");
+    let unsupported = [
+        "not support",
+        "unsupported",
+        "not available",
+        "not allowed",
+        "not permitted",
+        "unknown field",
+        "unrecognized",
+        "不支持",
+    ]
+    .iter()
+    .any(|pat| lower.contains(pat));
+    mentions_thinking && unsupported
+}
+
+/// thinking 请求：不支持的供应商移到链尾（其余顺序不变），返回调整后的链与移动的数量
+///
+/// 只调整顺序不剔除：全部不支持时仍按原顺序尝试，由上游给出最终错误。
+pub fn prefer_thinking_capable(providers: Vec<Provider>) -> (Vec<Provider>, usize) {
+    let (capable, incapable): (Vec<_>, Vec<_>) = providers.into_iter().partition(supports_thinking);
+    let moved = if capable.is_empty() {
+        0
+    } else {
+        incapable.len()
+    };
+    let mut chain = capable;
+    chain.extend(incapable);
+    (chain, moved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn provider(id: &str, settings: serde_json::Value) -> Provider {
+        Provider::with_id(id.to_string(), id.to_string(), settings, None)
+    }
+
+    #[test]
+    fn detects_thinking_rejections_only() {
+        let upstream = |status: u16, body: &str| ProxyError::UpstreamError {
+            status,
+            body: Some(body.to_string()),
+        };
+        assert!(is_thinking_unsupported_error(&upstream(
+            400,
+            r#"{"error":{"message":"thinking is not supported for this model"}}"#
+        )));
+        assert!(is_thinking_unsupported_error(&upstream(
+            422,
+            "Unknown field: thinking"
+        )));
+        assert!(is_thinking_unsupported_error(&upstream(
+            400,
+            "该模型不支持 reasoning 参数"
+        )));
+
+        // 与 thinking 无关、限流、5xx、参数取值错误均不视为能力缺失
+        assert!(!is_thinking_unsupported_error(&upstream(
+            400,
+            "model not supported"
+        )));
+        assert!(!is_thinking_unsupported_error(&upstream(
+            429,
+            "thinking not available: rate limited"
+        )));
+        assert!(!is_thinking_unsupported_error(&upstream(
+            503,
+            "thinking unsupported"
+        )));
+        assert!(!is_thinking_unsupported_error(&upstream(
+            400,
+            "thinking.budget_tokens must be >= 1024"
+        )));
+        assert!(!is_thinking_unsupported_error(&ProxyError::Timeout(
+            "thinking unsupported".to_string()
+        )));
+    }
+
+    #[test]
+    fn incapable_providers_move_to_the_end() {
+        let chain = vec![
+            provider("a", json!({ "supportsThinking": false })),
+            provider("b", json!({})),
+            provider("c", json!({ "supportsThinking": true })),
+        ];
+        assert_eq!(configured_support(&chain[0]), Some(false));
+        assert_eq!(configured_support(&chain[1]), None);
+        assert!(supports_thinking(&chain[1]));
+
+        let (reordered, moved) = prefer_thinking_capable(chain);
+        let ids: Vec<_> = reordered.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, vec!["b", "c", "a"]);
+        assert_eq!(moved, 1);
+
+        // 全部不支持：保持原顺序
+        let (reordered, moved) = prefer_thinking_capable(vec![
+            provider("x", json!({ "supportsThinking": false })),
+            provider("y", json!({ "supportsThinking": false })),
+        ]);
+        assert_eq!(reordered[0].id, "x");
+        assert_eq!(moved, 0);
+    }
+}
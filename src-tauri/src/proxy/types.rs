@@ -1,5 +1,9 @@
+use super::selection_policy::SelectionPolicyKind;
+use super::update_check::UpdateCheckResult;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::LazyLock;
 
 pub(crate) const LAST_REQUEST_SUMMARY_SETTING_KEY_PREFIX: &str = "last_request_summary_";
 const BUILTIN_LAST_REQUEST_SUMMARIES_JSON: &str =
@@ -22,13 +26,12 @@ pub(crate) fn last_request_summary_setting_key(app_type: &str) -> String {
     format!("{LAST_REQUEST_SUMMARY_SETTING_KEY_PREFIX}{sanitized}")
 }
 
+/// 内置的最近请求摘要，首次需要时才解析（只读 CLI 命令不会触发）
+static BUILTIN_LAST_REQUEST_SUMMARIES: LazyLock<HashMap<String, LastRequestSummary>> =
+    LazyLock::new(|| serde_json::from_str(BUILTIN_LAST_REQUEST_SUMMARIES_JSON).unwrap_or_default());
+
 pub(crate) fn builtin_last_request_summary(app_type: &str) -> Option<LastRequestSummary> {
-    let Ok(map) = serde_json::from_str::<HashMap<String, LastRequestSummary>>(
-        BUILTIN_LAST_REQUEST_SUMMARIES_JSON,
-    ) else {
-        return None;
-    };
-    map.get(app_type).cloned()
+    BUILTIN_LAST_REQUEST_SUMMARIES.get(app_type).cloned()
 }
 
 /// 代理服务器配置
@@ -56,6 +59,12 @@ pub struct ProxyConfig {
     /// 非流式总超时（秒）- 非流式请求的总超时时间
     #[serde(default = "default_non_streaming_timeout")]
     pub non_streaming_timeout: u64,
+    /// 停止时的排空超时（秒）- 等待进行中请求完成的最长时间，超时后中止
+    #[serde(default = "default_drain_timeout")]
+    pub drain_timeout: u64,
+    /// 只读模式：照常转发，但不写数据库（请求日志、健康状态、写回、故障转移切换等）
+    #[serde(default)]
+    pub read_only: bool,
 }
 
 fn default_streaming_first_byte_timeout() -> u64 {
@@ -70,6 +79,10 @@ fn default_non_streaming_timeout() -> u64 {
     600
 }
 
+fn default_drain_timeout() -> u64 {
+    super::inflight::DEFAULT_DRAIN_TIMEOUT_SECS
+}
+
 impl Default for ProxyConfig {
     fn default() -> Self {
         Self {
@@ -82,6 +95,8 @@ impl Default for ProxyConfig {
             streaming_first_byte_timeout: 30,
             streaming_idle_timeout: 60,
             non_streaming_timeout: 600,
+            drain_timeout: default_drain_timeout(),
+            read_only: false,
         }
     }
 }
@@ -123,6 +138,64 @@ pub struct ProxyStatus {
     /// 各 app 最近一次真实请求摘要（用于 `csc t` 的 model=auto）
     #[serde(default)]
     pub last_requests: HashMap<String, LastRequestSummary>,
+    /// 接管中的 live 配置被外部改写后重新接管的次数
+    #[serde(default)]
+    pub live_reassert_count: u64,
+    /// 最近一次 live 配置守护事件
+    #[serde(default)]
+    pub last_live_guard_event: Option<LiveGuardEvent>,
+    /// 数据库暂不可用（锁定/损坏），至少一个应用正在使用最近一次成功读取的供应商列表
+    #[serde(default)]
+    pub db_degraded: bool,
+    /// 正在使用兜底供应商列表的应用
+    #[serde(default)]
+    pub db_degraded_apps: Vec<String>,
+    /// 只读模式（不写数据库）
+    #[serde(default)]
+    pub read_only: bool,
+    /// 停止时等待进行中请求完成的最长时间（秒，`--drain-timeout`）
+    #[serde(default)]
+    pub drain_timeout_secs: u64,
+    /// 临时暂停故障转移的应用（`csc failover pause`）
+    #[serde(default)]
+    pub failover_paused: Vec<FailoverPause>,
+    /// 最近一次新版本检测结果（开启每日检测或执行过 `csc version --check` 后才有）
+    #[serde(default)]
+    pub update_check: Option<UpdateCheckResult>,
+}
+
+/// 暂停中的故障转移：期间只使用当前供应商，到期自动恢复
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FailoverPause {
+    pub app_type: String,
+    /// 截止时间（Unix 秒）
+    pub until: i64,
+    /// 剩余秒数
+    pub remaining_secs: u64,
+}
+
+/// live 配置守护的处理结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LiveGuardAction {
+    /// 已重新写入接管配置
+    Reasserted,
+    /// 仅告警
+    Alerted,
+    /// 重新接管失败
+    Failed,
+}
+
+/// 接管中的 live 配置被外部改写（不再指向本地代理）时的处理记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LiveGuardEvent {
+    pub app_type: String,
+    pub action: LiveGuardAction,
+    /// RFC 3339
+    pub at: String,
+    /// 失败原因
+    #[serde(default)]
+    pub error: Option<String>,
 }
 
 /// 最近一次请求摘要（用于诊断与测速对齐真实环境）
@@ -193,6 +266,85 @@ pub struct ActiveTarget {
     pub provider_id: String,
 }
 
+/// 运行中代理的管理状态（`GET /admin/status`，供 `csc proxy status` 查询）
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct AdminStatus {
+    #[serde(flatten)]
+    pub status: ProxyStatus,
+    /// 代理进程 PID
+    pub pid: u32,
+    /// 代理版本
+    pub version: String,
+    /// 各 app 自启动以来的请求统计
+    #[serde(default)]
+    pub apps: Vec<AppStatusSummary>,
+    /// 路由器内存表大小（排查内存增长）
+    #[serde(default)]
+    pub router: RouterMemoryStats,
+}
+
+/// 路由器各内存表的条目数
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct RouterMemoryStats {
+    pub circuit_breakers: usize,
+    pub round_robin_counters: usize,
+    pub supplier_cooldowns: usize,
+    pub suspect_urls: usize,
+    pub url_latencies: usize,
+    pub supplier_benchmark_locks: usize,
+}
+
+/// 路由快照中的单个供应商（按层级区分，同一供应商在不同层级各一条）
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct SupplierRoutingState {
+    pub priority: usize,
+    pub supplier: String,
+    /// 路由当前锁定的 base_url（尚未选定时为 None）
+    pub current_url: Option<String>,
+    /// 当前 URL 的缓存延迟（毫秒）
+    pub latency_ms: Option<u64>,
+    /// 缓存延迟的测得时间距今（秒）
+    pub latency_age_secs: Option<u64>,
+    /// 冷静期原因（overloaded / no-usable-url / all-keys-failed）
+    pub cooldown_reason: Option<String>,
+    /// 冷静期剩余秒数
+    pub cooldown_remaining_secs: Option<u64>,
+    /// 该供应商下疑似失效的 URL
+    #[serde(default)]
+    pub suspect_urls: Vec<String>,
+    /// 当前 URL 最近一次连接保温距今（秒），未保温过时为 None
+    #[serde(default)]
+    pub keep_warm_age_secs: Option<u64>,
+}
+
+/// 单个应用的路由快照（查询时从路由器内存表读取，不随每次变更更新）
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct AppRoutingSnapshot {
+    pub app_type: String,
+    /// 最近一次选路使用的优先级层级（尚未选路时为 None）
+    pub active_priority_level: Option<usize>,
+    /// 按层级、供应商名排序
+    pub suppliers: Vec<SupplierRoutingState>,
+}
+
+/// 单个 app 的请求统计
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct AppStatusSummary {
+    pub app_type: String,
+    pub total_requests: u64,
+    pub success_requests: u64,
+    /// 成功率 (0-100)
+    pub success_rate: f32,
+    pub current_provider_id: Option<String>,
+    pub current_provider_name: Option<String>,
+    /// 该应用的代理已关闭（请求直接返回 503）
+    #[serde(default)]
+    pub disabled: bool,
+    /// 因应用关闭而被拒绝的请求数
+    #[serde(default)]
+    pub rejected_disabled: u64,
+}
+
 /// 代理服务器信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProxyServerInfo {
@@ -209,6 +361,23 @@ pub struct ProxyTakeoverStatus {
     pub gemini: bool,
 }
 
+/// 单个应用的接管详情（`csc takeover status`）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppTakeoverDetail {
+    pub app_type: String,
+    /// 接管开关（proxy_config.enabled）
+    pub enabled: bool,
+    /// 是否保存了接管前的 Live 备份（关闭接管时据此恢复）
+    pub has_backup: bool,
+    /// 接管时写入 live 配置的代理地址
+    pub proxy_url: String,
+    /// live 配置是否指向代理（配置文件不存在或无法读取时为 None）
+    pub points_to_proxy: Option<bool>,
+    /// 该应用的 live 配置文件
+    pub files: Vec<PathBuf>,
+}
+
 /// API 格式类型（预留，当前不需要格式转换）
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[allow(dead_code)]
@@ -229,6 +398,12 @@ pub struct ProviderHealth {
     pub last_failure_at: Option<String>,
     pub last_error: Option<String>,
     pub updated_at: String,
+    /// 余额耗尽（HTTP 402 / insufficient balance）的记录时间；成功请求或手动重置后清除
+    #[serde(default)]
+    pub depleted_at: Option<String>,
+    /// 余额耗尽时的上游提示
+    #[serde(default)]
+    pub depleted_message: Option<String>,
 }
 
 /// Live 配置备份记录
@@ -242,6 +417,19 @@ pub struct LiveBackup {
     pub backed_up_at: String,
 }
 
+/// Live 配置快照（每次接管写入前保存，用于 diff 与回滚）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LiveSnapshot {
+    /// 快照 ID（自增，越大越新）
+    pub id: i64,
+    /// 应用类型 (claude/codex/gemini)
+    pub app_type: String,
+    /// 写入前的 Live 配置 JSON（与 LiveBackup.original_config 格式一致）
+    pub config: String,
+    /// 快照时间
+    pub created_at: String,
+}
+
 /// 全局代理配置（统一字段，三行镜像）
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -254,6 +442,9 @@ pub struct GlobalProxyConfig {
     pub listen_port: u16,
     /// 是否启用日志
     pub enable_logging: bool,
+    /// 只读模式：代理照常转发，但不写数据库（演示、共享的只读环境）
+    #[serde(default)]
+    pub read_only: bool,
 }
 
 /// 应用级代理配置（每个 app 独立）
@@ -284,4 +475,112 @@ pub struct AppProxyConfig {
     pub circuit_error_rate_threshold: f64,
     /// 计算错误率的最小请求数
     pub circuit_min_requests: u32,
+    /// 客户端未指定模型（缺失、空、"auto"、"unknown"）时使用的默认模型
+    ///
+    /// 供应商配置了 ANTHROPIC_MODEL 时以供应商配置为准。
+    #[serde(default)]
+    pub default_model: Option<String>,
+    /// 测速时同一 URL 最多尝试的 key 数（全部满载时再额外尝试一个未测 key）
+    #[serde(default = "default_benchmark_max_keys_per_url")]
+    pub benchmark_max_keys_per_url: u32,
+    /// 供应商余额耗尽（HTTP 402 等）后跳过的时长（秒），到期后重新参与选路
+    #[serde(default = "default_depleted_recheck_secs")]
+    pub depleted_recheck_secs: u64,
+    /// 供应商仅因满载/限流而无可用 URL 时的冷却时长（秒）；其它原因固定冷却 20 秒
+    #[serde(default = "default_overload_cooldown_secs")]
+    pub overload_cooldown_secs: u64,
+    /// 仅连通性可用（满载/回退）URL 在测速排序中追加的惩罚（毫秒）；
+    /// 选路时缓存延迟低于该值才视为完整可用
+    #[serde(default = "default_connectivity_penalty_ms")]
+    pub connectivity_penalty_ms: u64,
+    /// 连通性探测（TCP/HTTP 可达）超时（秒）
+    #[serde(default = "default_connectivity_timeout_secs")]
+    pub connectivity_timeout_secs: u64,
+    /// 全链路/模型列表探测超时（秒）
+    #[serde(default = "default_probe_timeout_secs")]
+    pub probe_timeout_secs: u64,
+    /// 熔断器半开状态下允许同时进行的探测请求数
+    #[serde(default = "default_circuit_half_open_max_probes")]
+    pub circuit_half_open_max_probes: u32,
+    /// 影子流量：按比例把非流式请求异步镜像到指定供应商，仅用于评估
+    #[serde(default)]
+    pub shadow: Option<ShadowConfig>,
+    /// 同一供应商相邻两次探测的最小间隔（毫秒），避免测速突发触发上游限流
+    #[serde(default = "default_probe_min_interval_ms")]
+    pub probe_min_interval_ms: u64,
+    /// 供应商最近一次真实成功请求早于该时长（秒）内时跳过探测，直接采用请求日志中的延迟；0 表示不跳过
+    #[serde(default = "default_probe_skip_recent_success_secs")]
+    pub probe_skip_recent_success_secs: u64,
+    /// 测速/探测请求使用的模型；供应商 `settings_config.probeModel` 优先，
+    /// 未配置时依次回退到最近一次请求的模型与内置默认模型
+    #[serde(default)]
+    pub probe_model: Option<String>,
+    /// 测速时同时测试的 supplier 数（同一 supplier 内部仍按 URL/key 依次探测）
+    #[serde(default = "default_benchmark_concurrency")]
+    pub benchmark_concurrency: u32,
+    /// 连接保温：定期对各供应商当前 URL 发送 HEAD，保持连接池中的连接不被回收
+    #[serde(default)]
+    pub keep_warm: bool,
+    /// 连接保温间隔（秒）
+    #[serde(default = "default_keep_warm_interval_secs")]
+    pub keep_warm_interval_secs: u64,
+    /// 故障转移开启时的选路策略（default / strict-primary）
+    #[serde(default)]
+    pub selection_policy: SelectionPolicyKind,
+}
+
+/// 影子流量配置
+///
+/// 镜像请求的结果只写入请求日志（`shadow = 1`），不影响客户端响应、熔断器与故障转移。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShadowConfig {
+    /// 影子供应商 ID（同一 app 下）
+    pub provider_id: String,
+    /// 采样比例（0-100）
+    pub sample_percent: u8,
+}
+
+fn default_benchmark_max_keys_per_url() -> u32 {
+    2
+}
+
+fn default_depleted_recheck_secs() -> u64 {
+    6 * 3600
+}
+
+fn default_overload_cooldown_secs() -> u64 {
+    5
+}
+
+fn default_connectivity_penalty_ms() -> u64 {
+    30_000
+}
+
+fn default_connectivity_timeout_secs() -> u64 {
+    5
+}
+
+fn default_probe_timeout_secs() -> u64 {
+    10
+}
+
+fn default_circuit_half_open_max_probes() -> u32 {
+    1
+}
+
+fn default_probe_min_interval_ms() -> u64 {
+    500
+}
+
+fn default_probe_skip_recent_success_secs() -> u64 {
+    120
+}
+
+fn default_benchmark_concurrency() -> u32 {
+    3
+}
+
+fn default_keep_warm_interval_secs() -> u64 {
+    55
 }
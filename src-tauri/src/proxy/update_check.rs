@@ -0,0 +1,425 @@
+//! 版本信息与新版本检测
+//!
+//! - `GET /version` 报告当前版本、构建时嵌入的 git hash 与数据库 schema 版本，便于确认各实例的版本
+//! - 开启 `update_check_enabled`（默认关闭）后，代理在后台每天查询一次 GitHub Releases 的最新版本，
+//!   结果写入 settings（`update_check_result`），在 `ProxyStatus.update_check` 与 `csc version` 中展示
+//! - 只检测与提示，不自动下载；检测失败只记录日志，不影响代理
+//!
+//! 版本比较按数字段逐段进行（忽略前缀 `v` 与 `+` 之后的构建信息），本仓库的 `3.9.0-2` 这类
+//! 修订号视为在 `3.9.0` 之后。
+
+use crate::database::Database;
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::{watch, Mutex};
+use tokio::task::JoinHandle;
+
+/// GitHub Releases 最新版本接口
+pub const RELEASES_API_URL: &str = "https://api.github.com/repos/Aaroen/cc-switch/releases/latest";
+/// 当前版本
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// 构建时嵌入的 git 短哈希（非 git 环境构建时为 None）
+pub const GIT_HASH: Option<&str> = option_env!("CC_SWITCH_GIT_HASH");
+
+/// 两次检测的最小间隔
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(24 * 3600);
+/// 调度循环的检查粒度（开关在下一轮生效）
+const SCHEDULER_TICK: Duration = Duration::from_secs(3600);
+/// 查询 Releases 接口的超时
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+/// 停止时等待调度任务结束的最长时间
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 版本信息（`GET /version`）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionInfo {
+    pub version: String,
+    pub git_hash: Option<String>,
+    pub schema_version: i32,
+}
+
+impl VersionInfo {
+    pub fn current() -> Self {
+        Self {
+            version: CURRENT_VERSION.to_string(),
+            git_hash: GIT_HASH.map(str::to_string),
+            schema_version: crate::database::SCHEMA_VERSION,
+        }
+    }
+}
+
+/// 最近一次新版本检测结果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpdateCheckResult {
+    /// 检测时运行的版本
+    pub current_version: String,
+    /// Releases 上的最新版本（tag 原样保留）
+    pub latest_version: String,
+    pub update_available: bool,
+    pub release_url: Option<String>,
+    /// 检测时间（Unix 秒）
+    pub checked_at: i64,
+}
+
+impl UpdateCheckResult {
+    fn new(latest_version: &str, release_url: Option<String>) -> Self {
+        Self {
+            current_version: CURRENT_VERSION.to_string(),
+            latest_version: latest_version.to_string(),
+            update_available: is_newer_version(latest_version, CURRENT_VERSION),
+            release_url,
+            checked_at: chrono::Utc::now().timestamp(),
+        }
+    }
+
+    /// 按当前运行的版本重新比较（升级后旧的检测结果不再提示）
+    fn for_running_version(mut self) -> Self {
+        if self.current_version != CURRENT_VERSION {
+            self.current_version = CURRENT_VERSION.to_string();
+            self.update_available = is_newer_version(&self.latest_version, CURRENT_VERSION);
+        }
+        self
+    }
+}
+
+/// 版本号拆为数字段：`v3.9.0-2+abc` -> `[3, 9, 0, 2]`（非数字段记为 0）
+fn version_key(version: &str) -> Vec<u64> {
+    let version = version.trim();
+    let version = version.strip_prefix(['v', 'V']).unwrap_or(version);
+    let version = version.split('+').next().unwrap_or_default();
+    version
+        .split(['.', '-'])
+        .map(|part| {
+            let digits: String = part.chars().take_while(char::is_ascii_digit).collect();
+            digits.parse().unwrap_or(0)
+        })
+        .collect()
+}
+
+/// 逐段比较版本号，缺少的段按 0 处理
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let (a, b) = (version_key(a), version_key(b));
+    (0..a.len().max(b.len()))
+        .map(|i| {
+            let x = a.get(i).copied().unwrap_or(0);
+            let y = b.get(i).copied().unwrap_or(0);
+            x.cmp(&y)
+        })
+        .find(|o| o.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+/// `latest` 是否比 `current` 新
+pub fn is_newer_version(latest: &str, current: &str) -> bool {
+    compare_versions(latest, current) == Ordering::Greater
+}
+
+#[derive(Deserialize)]
+struct LatestRelease {
+    tag_name: String,
+    #[serde(default)]
+    html_url: Option<String>,
+}
+
+/// 查询 Releases 接口的最新版本（不写入数据库）
+async fn fetch_latest(api_url: &str) -> Result<UpdateCheckResult, AppError> {
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .user_agent(format!("cc-switch/{CURRENT_VERSION}"))
+        .build()
+        .map_err(|e| AppError::Message(format!("创建HTTP客户端失败: {e}")))?;
+    let resp = client
+        .get(api_url)
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .await
+        .map_err(|e| AppError::Message(format!("查询最新版本失败: {e}")))?;
+    if !resp.status().is_success() {
+        return Err(AppError::Message(format!(
+            "查询最新版本失败: HTTP {}",
+            resp.status()
+        )));
+    }
+    let release: LatestRelease = resp
+        .json()
+        .await
+        .map_err(|e| AppError::Message(format!("解析最新版本失败: {e}")))?;
+    Ok(UpdateCheckResult::new(&release.tag_name, release.html_url))
+}
+
+/// 立即检测并写入 settings（`csc version --check`，不受每日检测开关影响）
+pub async fn check_now(db: &Database, api_url: &str) -> Result<UpdateCheckResult, AppError> {
+    let result = fetch_latest(api_url).await?;
+    db.save_update_check_result(&result)?;
+    Ok(result)
+}
+
+/// 读取最近一次检测结果（已按当前运行的版本重新比较）
+pub fn load_stored(db: &Database) -> Option<UpdateCheckResult> {
+    match db.get_update_check_result() {
+        Ok(result) => result.map(UpdateCheckResult::for_running_version),
+        Err(e) => {
+            log::debug!("[UpdateCheck] 读取检测结果失败: {e}");
+            None
+        }
+    }
+}
+
+/// 后台每日新版本检测（随代理服务器启动/停止）
+pub struct UpdateChecker {
+    checker: Checker,
+    stop_tx: watch::Sender<bool>,
+    handle: Mutex<Option<JoinHandle<()>>>,
+}
+
+#[derive(Clone)]
+struct Checker {
+    db: Arc<Database>,
+    api_url: String,
+    read_only: bool,
+    latest: Arc<RwLock<Option<UpdateCheckResult>>>,
+}
+
+impl UpdateChecker {
+    pub fn new(db: Arc<Database>, read_only: bool) -> Self {
+        Self::with_api_url(db, RELEASES_API_URL, read_only)
+    }
+
+    fn with_api_url(db: Arc<Database>, api_url: &str, read_only: bool) -> Self {
+        let latest = load_stored(&db);
+        let (stop_tx, _) = watch::channel(false);
+        Self {
+            checker: Checker {
+                db,
+                api_url: api_url.to_string(),
+                read_only,
+                latest: Arc::new(RwLock::new(latest)),
+            },
+            stop_tx,
+            handle: Mutex::new(None),
+        }
+    }
+
+    /// 最近一次检测结果（供 `ProxyStatus` 展示）
+    pub fn latest(&self) -> Option<UpdateCheckResult> {
+        self.checker.latest()
+    }
+
+    /// 启动调度循环（已在运行时忽略）
+    pub async fn start(&self) {
+        let mut handle = self.handle.lock().await;
+        if handle.as_ref().is_some_and(|h| !h.is_finished()) {
+            return;
+        }
+
+        self.stop_tx.send_replace(false);
+        let stop_rx = self.stop_tx.subscribe();
+        let checker = self.checker.clone();
+        *handle = Some(tokio::spawn(async move {
+            checker.run(stop_rx).await;
+        }));
+    }
+
+    /// 停止调度循环
+    pub async fn stop(&self) {
+        self.stop_tx.send_replace(true);
+        if let Some(handle) = self.handle.lock().await.take() {
+            if tokio::time::timeout(STOP_TIMEOUT, handle).await.is_err() {
+                log::warn!("[UpdateCheck] 新版本检测停止超时，强制继续");
+            }
+        }
+    }
+}
+
+impl Checker {
+    fn latest(&self) -> Option<UpdateCheckResult> {
+        self.latest.read().ok().and_then(|latest| latest.clone())
+    }
+
+    async fn run(self, mut stop_rx: watch::Receiver<bool>) {
+        loop {
+            tokio::select! {
+                _ = self.check_if_due() => {}
+                _ = stop_rx.changed() => {}
+            }
+            if *stop_rx.borrow() {
+                return;
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(SCHEDULER_TICK) => {}
+                _ = stop_rx.changed() => {}
+            }
+            if *stop_rx.borrow() {
+                return;
+            }
+        }
+    }
+
+    /// 已开启检测且距上次检测满 24 小时时查询一次；返回本次检测结果
+    async fn check_if_due(&self) -> Option<UpdateCheckResult> {
+        if !self.db.get_update_check_enabled().unwrap_or(false) {
+            return None;
+        }
+        let previous = self.latest();
+        let now = chrono::Utc::now().timestamp();
+        if let Some(prev) = &previous {
+            if now - prev.checked_at < CHECK_INTERVAL.as_secs() as i64 {
+                return None;
+            }
+        }
+
+        let result = match fetch_latest(&self.api_url).await {
+            Ok(result) => result,
+            Err(e) => {
+                log::warn!("[UpdateCheck] {e}");
+                return None;
+            }
+        };
+
+        let already_announced = previous.as_ref().is_some_and(|prev| {
+            prev.update_available && prev.latest_version == result.latest_version
+        });
+        if result.update_available && !already_announced {
+            log::warn!(
+                "[UpdateCheck] 发现新版本 {}（当前 {}）{}",
+                result.latest_version,
+                result.current_version,
+                result.release_url.as_deref().unwrap_or_default()
+            );
+        } else if !result.update_available {
+            log::info!("[UpdateCheck] 已是最新版本 {}", result.current_version);
+        }
+
+        // 只读模式不写数据库，结果只保存在内存中
+        if !self.read_only {
+            if let Err(e) = self.db.save_update_check_result(&result) {
+                log::warn!("[UpdateCheck] 保存检测结果失败: {e}");
+            }
+        }
+        if let Ok(mut latest) = self.latest.write() {
+            *latest = Some(result.clone());
+        }
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::testkit::{MockResponse, MockUpstream};
+    use serde_json::json;
+
+    #[test]
+    fn version_comparison() {
+        assert!(is_newer_version("v3.10.0", "3.9.0"));
+        assert!(is_newer_version("3.9.1", "3.9.0-2"));
+        // 修订号在同版本之后
+        assert!(is_newer_version("v3.9.0-3", "3.9.0-2"));
+        assert!(is_newer_version("3.9.0-2", "3.9.0"));
+        assert!(!is_newer_version("3.9.0", "3.9.0-2"));
+        assert!(!is_newer_version("v3.9.0-2", "3.9.0-2"));
+        assert!(!is_newer_version("3.8.9", "3.9.0"));
+        assert_eq!(compare_versions("3.9", "3.9.0"), Ordering::Equal);
+        assert_eq!(compare_versions("v3.9.0+build.7", "3.9.0"), Ordering::Equal);
+    }
+
+    async fn releases_mock(tag: &str) -> MockUpstream {
+        let upstream = MockUpstream::start().await;
+        upstream.route(
+            "/repos/releases/latest",
+            [MockResponse::json(
+                200,
+                json!({ "tag_name": tag, "html_url": format!("https://example.com/{tag}") }),
+            )],
+        );
+        upstream
+    }
+
+    #[tokio::test]
+    async fn daily_check_stores_result_and_respects_interval() {
+        let upstream = releases_mock("v999.0.0").await;
+        let db = Arc::new(Database::memory().unwrap());
+        let checker =
+            UpdateChecker::with_api_url(db.clone(), &upstream.url("/repos/releases/latest"), false);
+
+        // 默认关闭：不查询
+        assert!(checker.checker.check_if_due().await.is_none());
+        assert_eq!(upstream.hits("/repos/releases/latest"), 0);
+
+        db.set_update_check_enabled(true).unwrap();
+        let result = checker.checker.check_if_due().await.expect("checked");
+        assert!(result.update_available);
+        assert_eq!(result.latest_version, "v999.0.0");
+        assert_eq!(result.current_version, CURRENT_VERSION);
+        assert_eq!(
+            result.release_url.as_deref(),
+            Some("https://example.com/v999.0.0")
+        );
+
+        // 结果写入 settings，并在状态中可见
+        assert_eq!(db.get_update_check_result().unwrap(), Some(result.clone()));
+        assert_eq!(checker.latest(), Some(result));
+
+        // 24 小时内不重复查询
+        assert!(checker.checker.check_if_due().await.is_none());
+        assert_eq!(upstream.hits("/repos/releases/latest"), 1);
+
+        let request = &upstream.requests()[0];
+        assert!(request
+            .headers
+            .get("user-agent")
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|ua| ua.starts_with("cc-switch/")));
+    }
+
+    #[tokio::test]
+    async fn check_now_reports_up_to_date_and_failures() {
+        let upstream = releases_mock(&format!("v{CURRENT_VERSION}")).await;
+        let db = Database::memory().unwrap();
+
+        let result = check_now(&db, &upstream.url("/repos/releases/latest"))
+            .await
+            .unwrap();
+        assert!(!result.update_available);
+        assert_eq!(load_stored(&db), Some(result));
+
+        upstream.route("/repos/releases/latest", [MockResponse::status(403)]);
+        let err = check_now(&db, &upstream.url("/repos/releases/latest"))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("403"));
+    }
+
+    #[tokio::test]
+    async fn read_only_checker_keeps_result_in_memory() {
+        let upstream = releases_mock("v999.0.0").await;
+        let db = Arc::new(Database::memory().unwrap());
+        db.set_update_check_enabled(true).unwrap();
+        let checker =
+            UpdateChecker::with_api_url(db.clone(), &upstream.url("/repos/releases/latest"), true);
+
+        assert!(checker.checker.check_if_due().await.is_some());
+        assert!(checker.latest().is_some());
+        assert_eq!(db.get_update_check_result().unwrap(), None);
+    }
+
+    #[test]
+    fn stored_result_is_recompared_after_upgrade() {
+        let db = Database::memory().unwrap();
+        let stale = UpdateCheckResult {
+            current_version: "0.0.1".to_string(),
+            latest_version: format!("v{CURRENT_VERSION}"),
+            update_available: true,
+            release_url: None,
+            checked_at: 1_700_000_000,
+        };
+        db.save_update_check_result(&stale).unwrap();
+
+        let loaded = load_stored(&db).unwrap();
+        assert_eq!(loaded.current_version, CURRENT_VERSION);
+        assert!(!loaded.update_available);
+    }
+}
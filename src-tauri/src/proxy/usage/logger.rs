@@ -8,6 +8,26 @@ use crate::services::usage_stats::find_model_pricing_row;
 use rust_decimal::Decimal;
 use std::time::SystemTime;
 
+/// 模型映射：CLI 请求的模型 → 实际发往供应商的模型（模型映射/智能解析之后）
+#[derive(Debug, Clone, Default)]
+pub struct ModelMapping {
+    pub requested: Option<String>,
+    pub effective: Option<String>,
+}
+
+/// 请求/响应体字节数（流量计量；流式响应为转发分块的字节数之和）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TransferBytes {
+    pub request: u64,
+    pub response: u64,
+}
+
+impl TransferBytes {
+    pub fn total(&self) -> u64 {
+        self.request + self.response
+    }
+}
+
 /// 请求日志
 #[derive(Debug, Clone)]
 pub struct RequestLog {
@@ -19,6 +39,10 @@ pub struct RequestLog {
     pub cost: Option<CostBreakdown>,
     pub latency_ms: u64,
     pub first_token_ms: Option<u64>,
+    /// Python 代理回传的真实上游耗时（本地一跳 = latency_ms - upstream_ms）
+    pub upstream_ms: Option<u64>,
+    /// 请求/响应体字节数
+    pub bytes: TransferBytes,
     pub status_code: u16,
     pub error_message: Option<String>,
     pub session_id: Option<String>,
@@ -28,6 +52,10 @@ pub struct RequestLog {
     pub is_streaming: bool,
     /// 成本倍数
     pub cost_multiplier: String,
+    /// 请求模型 → 实际模型
+    pub mapping: ModelMapping,
+    /// 是否为影子流量（镜像请求，不计入常规统计）
+    pub shadow: bool,
 }
 
 /// 使用量记录器
@@ -74,8 +102,10 @@ impl<'a> UsageLogger<'a> {
                 input_tokens, output_tokens, cache_read_tokens, cache_creation_tokens,
                 input_cost_usd, output_cost_usd, cache_read_cost_usd, cache_creation_cost_usd, total_cost_usd,
                 latency_ms, first_token_ms, status_code, error_message, session_id,
-                provider_type, is_streaming, cost_multiplier, created_at
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22)",
+                provider_type, is_streaming, cost_multiplier, created_at,
+                requested_model, effective_model, shadow, upstream_ms,
+                request_bytes, response_bytes
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28)",
             rusqlite::params![
                 log.request_id,
                 log.provider_id,
@@ -99,6 +129,12 @@ impl<'a> UsageLogger<'a> {
                 log.is_streaming as i64,
                 log.cost_multiplier,
                 created_at,
+                log.mapping.requested,
+                log.mapping.effective,
+                log.shadow as i64,
+                log.upstream_ms.map(|v| v as i64),
+                log.bytes.request as i64,
+                log.bytes.response as i64,
             ],
         )
         .map_err(|e| AppError::Database(format!("记录请求日志失败: {e}")))?;
@@ -129,12 +165,16 @@ impl<'a> UsageLogger<'a> {
             cost: None,
             latency_ms,
             first_token_ms: None,
+            upstream_ms: None,
+            bytes: TransferBytes::default(),
             status_code,
             error_message: Some(error_message),
             session_id: None,
             provider_type: None,
             is_streaming: false,
             cost_multiplier: "1.0".to_string(),
+            mapping: ModelMapping::default(),
+            shadow: false,
         };
 
         self.log_request(&log)
@@ -156,6 +196,7 @@ impl<'a> UsageLogger<'a> {
         is_streaming: bool,
         session_id: Option<String>,
         provider_type: Option<String>,
+        mapping: ModelMapping,
     ) -> Result<(), AppError> {
         let log = RequestLog {
             request_id,
@@ -166,12 +207,16 @@ impl<'a> UsageLogger<'a> {
             cost: None,
             latency_ms,
             first_token_ms: None,
+            upstream_ms: None,
+            bytes: TransferBytes::default(),
             status_code,
             error_message: Some(error_message),
             session_id,
             provider_type,
             is_streaming,
             cost_multiplier: "1.0".to_string(),
+            mapping,
+            shadow: false,
         };
 
         self.log_request(&log)
@@ -203,10 +248,13 @@ impl<'a> UsageLogger<'a> {
         cost_multiplier: Decimal,
         latency_ms: u64,
         first_token_ms: Option<u64>,
+        upstream_ms: Option<u64>,
+        bytes: TransferBytes,
         status_code: u16,
         session_id: Option<String>,
         provider_type: Option<String>,
         is_streaming: bool,
+        mapping: ModelMapping,
     ) -> Result<(), AppError> {
         let pricing = self.get_model_pricing(&model)?;
 
@@ -223,12 +271,16 @@ impl<'a> UsageLogger<'a> {
             cost,
             latency_ms,
             first_token_ms,
+            upstream_ms,
+            bytes,
             status_code,
             error_message: None,
             session_id,
             provider_type,
             is_streaming,
             cost_multiplier: cost_multiplier.to_string(),
+            mapping,
+            shadow: false,
         };
 
         self.log_request(&log)
@@ -273,22 +325,51 @@ mod tests {
             Decimal::from(1),
             100,
             None,
+            Some(60),
+            TransferBytes {
+                request: 120,
+                response: 2048,
+            },
             200,
             None,
             Some("claude".to_string()),
             false,
+            ModelMapping {
+                requested: Some("claude-sonnet-4-5".to_string()),
+                effective: Some("test-model".to_string()),
+            },
         )?;
 
         // 验证记录已插入
         let conn = crate::database::lock_conn!(db.conn);
-        let count: i64 = conn
+        let (count, requested, effective, upstream_ms, bytes): (
+            i64,
+            Option<String>,
+            Option<String>,
+            Option<i64>,
+            (i64, i64),
+        ) = conn
             .query_row(
-                "SELECT COUNT(*) FROM proxy_request_logs WHERE request_id = 'req-123'",
+                "SELECT COUNT(*), MAX(requested_model), MAX(effective_model), MAX(upstream_ms),
+                        MAX(request_bytes), MAX(response_bytes)
+                 FROM proxy_request_logs WHERE request_id = 'req-123'",
                 [],
-                |row| row.get(0),
+                |row| {
+                    Ok((
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                        (row.get(4)?, row.get(5)?),
+                    ))
+                },
             )
             .unwrap();
         assert_eq!(count, 1);
+        assert_eq!(requested.as_deref(), Some("claude-sonnet-4-5"));
+        assert_eq!(effective.as_deref(), Some("test-model"));
+        assert_eq!(upstream_ms, Some(60));
+        assert_eq!(bytes, (120, 2048));
         Ok(())
     }
 
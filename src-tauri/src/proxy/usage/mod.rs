@@ -10,6 +10,6 @@ pub mod parser;
 #[allow(unused_imports)]
 pub use calculator::{CostBreakdown, CostCalculator, ModelPricing};
 #[allow(unused_imports)]
-pub use logger::{RequestLog, UsageLogger};
+pub use logger::{ModelMapping, RequestLog, TransferBytes, UsageLogger};
 #[allow(unused_imports)]
 pub use parser::{ApiType, TokenUsage};
@@ -6,6 +6,7 @@
 //! - Codex API (非流式和流式)
 //! - Gemini API (非流式和流式)
 
+use crate::app_config::AppType;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -31,6 +32,17 @@ pub enum ApiType {
 }
 
 impl TokenUsage {
+    /// 按应用类型解析非流式响应（Codex 同时兼容 Responses 与 Chat Completions 格式）
+    pub fn from_app_response(app_type: &AppType, body: &Value) -> Option<Self> {
+        match app_type {
+            AppType::Claude => Self::from_claude_response(body),
+            AppType::Codex => {
+                Self::from_codex_response(body).or_else(|| Self::from_openai_response(body))
+            }
+            AppType::Gemini => Self::from_gemini_response(body),
+        }
+    }
+
     /// 从 Claude API 非流式响应解析
     pub fn from_claude_response(body: &Value) -> Option<Self> {
         let usage = body.get("usage")?;
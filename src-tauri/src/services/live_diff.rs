@@ -0,0 +1,216 @@
+//! Live 配置快照与当前文件之间的统一格式（unified）diff
+//!
+//! 快照与 Live 读取结果都是归一化后的 JSON（Claude 为 settings.json，Codex 为
+//! `{"auth", "config"}`，Gemini 为 `{"env"}`），这里先按文件还原成文本再逐行比较，
+//! 因此 JSON 的缩进/键顺序差异不会出现在结果中。
+
+use crate::app_config::AppType;
+use crate::gemini_config::{json_to_env, serialize_env_file};
+use serde_json::Value;
+
+/// 每个 hunk 前后保留的上下文行数
+const CONTEXT_LINES: usize = 3;
+
+/// 将 Live 配置还原为各文件的文本（文件名, 内容）
+pub fn render_live_files(app_type: &AppType, config: &Value) -> Vec<(&'static str, String)> {
+    let pretty = |value: &Value| serde_json::to_string_pretty(value).unwrap_or_default();
+
+    match app_type {
+        AppType::Claude => vec![("settings.json", pretty(config))],
+        AppType::Codex => vec![
+            (
+                "auth.json",
+                config.get("auth").map(pretty).unwrap_or_default(),
+            ),
+            (
+                "config.toml",
+                config
+                    .get("config")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+            ),
+        ],
+        AppType::Gemini => {
+            let text = json_to_env(config)
+                .map(|env| serialize_env_file(&env))
+                .unwrap_or_else(|_| pretty(config));
+            vec![(".env", text)]
+        }
+    }
+}
+
+/// 比较两份 Live 配置，返回各文件的 unified diff（无差异时为空字符串）
+pub fn diff_live_configs(
+    app_type: &AppType,
+    old: &Value,
+    new: &Value,
+    old_label: &str,
+    new_label: &str,
+) -> String {
+    render_live_files(app_type, old)
+        .into_iter()
+        .zip(render_live_files(app_type, new))
+        .map(|((name, old_text), (_, new_text))| {
+            unified_diff(
+                &old_text,
+                &new_text,
+                &format!("{name} ({old_label})"),
+                &format!("{name} ({new_label})"),
+            )
+        })
+        .collect()
+}
+
+enum DiffOp<'a> {
+    Equal(&'a str),
+    Delete(&'a str),
+    Insert(&'a str),
+}
+
+/// 基于最长公共子序列的逐行比较（配置文件行数很少，O(n·m) 足够）
+fn diff_ops<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<DiffOp<'a>> {
+    let (n, m) = (old.len(), new.len());
+    // lcs[i][j]：old[i..] 与 new[j..] 的最长公共子序列长度
+    let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops = Vec::with_capacity(n + m);
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if old[i] == new[j] {
+            ops.push(DiffOp::Equal(old[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            ops.push(DiffOp::Delete(old[i]));
+            i += 1;
+        } else {
+            ops.push(DiffOp::Insert(new[j]));
+            j += 1;
+        }
+    }
+    ops.extend(old[i..].iter().map(|line| DiffOp::Delete(line)));
+    ops.extend(new[j..].iter().map(|line| DiffOp::Insert(line)));
+    ops
+}
+
+fn hunk_range(pos: usize, count: usize) -> String {
+    // 空范围按惯例指向前一行
+    let start = if count == 0 { pos } else { pos + 1 };
+    format!("{start},{count}")
+}
+
+/// 生成 unified diff（无差异时为空字符串）
+pub fn unified_diff(old: &str, new: &str, old_label: &str, new_label: &str) -> String {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let ops = diff_ops(&old_lines, &new_lines);
+
+    let changes: Vec<usize> = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, op)| !matches!(op, DiffOp::Equal(_)))
+        .map(|(idx, _)| idx)
+        .collect();
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    // old_pos[k] / new_pos[k]：第 k 个操作之前已消耗的旧/新行数
+    let mut old_pos = vec![0usize; ops.len() + 1];
+    let mut new_pos = vec![0usize; ops.len() + 1];
+    for (k, op) in ops.iter().enumerate() {
+        old_pos[k + 1] = old_pos[k] + usize::from(!matches!(op, DiffOp::Insert(_)));
+        new_pos[k + 1] = new_pos[k] + usize::from(!matches!(op, DiffOp::Delete(_)));
+    }
+
+    let mut out = format!("--- {old_label}\n+++ {new_label}\n");
+    let mut first = 0;
+    while first < changes.len() {
+        // 相邻改动之间的相同行不超过两倍上下文时合并为同一个 hunk
+        let mut last = first;
+        while last + 1 < changes.len() && changes[last + 1] - changes[last] <= 2 * CONTEXT_LINES + 1
+        {
+            last += 1;
+        }
+
+        let start = changes[first].saturating_sub(CONTEXT_LINES);
+        let end = (changes[last] + CONTEXT_LINES + 1).min(ops.len());
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_pos[start], old_pos[end] - old_pos[start]),
+            hunk_range(new_pos[start], new_pos[end] - new_pos[start])
+        ));
+        for op in &ops[start..end] {
+            let (prefix, line) = match op {
+                DiffOp::Equal(line) => (' ', line),
+                DiffOp::Delete(line) => ('-', line),
+                DiffOp::Insert(line) => ('+', line),
+            };
+            out.push(prefix);
+            out.push_str(line);
+            out.push('\n');
+        }
+
+        first = last + 1;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_unified_diff_groups_nearby_changes_into_hunks() {
+        let old: String = (1..=20).map(|i| format!("line{i}\n")).collect();
+        let new = old
+            .replace("line2\n", "line2-changed\n")
+            .replace("line4\n", "")
+            .replace("line18\n", "line18\nextra\n");
+
+        let diff = unified_diff(&old, &new, "a", "b");
+        assert_eq!(
+            diff,
+            "--- a\n+++ b\n\
+             @@ -1,7 +1,6 @@\n line1\n-line2\n+line2-changed\n line3\n-line4\n line5\n line6\n line7\n\
+             @@ -16,5 +15,6 @@\n line16\n line17\n line18\n+extra\n line19\n line20\n"
+        );
+        assert!(unified_diff(&old, &old, "a", "b").is_empty());
+        assert_eq!(
+            unified_diff("", "x\n", "a", "b"),
+            "--- a\n+++ b\n@@ -0,0 +1,1 @@\n+x\n"
+        );
+    }
+
+    #[test]
+    fn test_diff_live_configs_renders_codex_files_separately() {
+        let old = json!({
+            "auth": {"OPENAI_API_KEY": "sk-real"},
+            "config": "model_provider = \"any\"\nbase_url = \"https://relay.example.com/v1\"\n"
+        });
+        let new = json!({
+            "auth": {"OPENAI_API_KEY": "PROXY_MANAGED"},
+            "config": "model_provider = \"any\"\nbase_url = \"http://127.0.0.1:15721/v1\"\n"
+        });
+
+        let diff = diff_live_configs(&AppType::Codex, &old, &new, "快照 #1", "当前");
+        assert!(diff.contains("--- auth.json (快照 #1)\n+++ auth.json (当前)\n"));
+        assert!(diff.contains(
+            "-  \"OPENAI_API_KEY\": \"sk-real\"\n+  \"OPENAI_API_KEY\": \"PROXY_MANAGED\"\n"
+        ));
+        assert!(diff.contains("--- config.toml (快照 #1)\n"));
+        assert!(diff.contains(" model_provider = \"any\"\n-base_url = \"https://relay.example.com/v1\"\n+base_url = \"http://127.0.0.1:15721/v1\"\n"));
+        assert!(diff_live_configs(&AppType::Codex, &old, &old, "a", "b").is_empty());
+    }
+}
@@ -0,0 +1,214 @@
+//! 接管中 live 配置的文件守护
+//!
+//! 监听各应用 live 配置所在目录（非递归；原子写入会替换文件，因此不直接监听文件本身），
+//! 把属于被守护应用的文件事件转交给处理函数。同一批事件在 [`LIVE_GUARD_DEBOUNCE`]
+//! 内没有新事件时才处理一次，编辑器连续保存不会反复触发。
+//!
+//! 是否真的需要重新接管由处理函数读取文件后判断，自身写入触发的事件也会走到这里，
+//! 但此时文件仍指向代理，不会形成循环。
+
+use crate::app_config::AppType;
+use crate::codex_config::{get_codex_auth_path, get_codex_config_path};
+use crate::config::get_claude_settings_path;
+use crate::gemini_config::get_gemini_env_path;
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// 事件合并窗口：最后一个事件之后静默这么久才开始处理
+pub const LIVE_GUARD_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// 被守护的文件（规范化路径）→ 所属应用
+type WatchedFiles = Arc<Mutex<HashMap<PathBuf, AppType>>>;
+
+/// 各应用的 live 配置文件
+pub fn live_config_paths(app_type: &AppType) -> Vec<PathBuf> {
+    match app_type {
+        AppType::Claude => vec![get_claude_settings_path()],
+        AppType::Codex => vec![get_codex_auth_path(), get_codex_config_path()],
+        AppType::Gemini => vec![get_gemini_env_path()],
+    }
+}
+
+/// 目录规范化后再拼回文件名（文件可能已被删除，不能直接 canonicalize 文件本身）
+fn normalize(path: &Path) -> PathBuf {
+    match (path.parent(), path.file_name()) {
+        (Some(dir), Some(name)) => dir
+            .canonicalize()
+            .unwrap_or_else(|_| dir.to_path_buf())
+            .join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+pub struct LiveGuard {
+    watcher: RecommendedWatcher,
+    files: WatchedFiles,
+    watched_dirs: Vec<PathBuf>,
+    task: JoinHandle<()>,
+}
+
+impl LiveGuard {
+    /// 启动守护（此时尚未监听任何文件），`handler` 在防抖后按应用调用
+    pub fn start<F, Fut>(handler: F) -> Result<Self, String>
+    where
+        F: Fn(AppType) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let files: WatchedFiles = Arc::default();
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        let callback_files = files.clone();
+        let watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+            let Ok(event) = res else {
+                return;
+            };
+            if event.kind.is_access() {
+                return;
+            }
+            let Ok(files) = callback_files.lock() else {
+                return;
+            };
+            for path in &event.paths {
+                if let Some(app_type) = files.get(&normalize(path)) {
+                    let _ = tx.send(app_type.clone());
+                }
+            }
+        })
+        .map_err(|e| format!("创建文件监听失败: {e}"))?;
+
+        let task = tokio::spawn(Self::run(rx, files.clone(), handler));
+        Ok(Self {
+            watcher,
+            files,
+            watched_dirs: Vec::new(),
+            task,
+        })
+    }
+
+    async fn run<F, Fut>(mut rx: mpsc::UnboundedReceiver<AppType>, files: WatchedFiles, handler: F)
+    where
+        F: Fn(AppType) -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        while let Some(first) = rx.recv().await {
+            let mut pending = vec![first];
+            while let Ok(Some(app_type)) =
+                tokio::time::timeout(LIVE_GUARD_DEBOUNCE, rx.recv()).await
+            {
+                if !pending.contains(&app_type) {
+                    pending.push(app_type);
+                }
+            }
+
+            for app_type in pending {
+                // 防抖期间可能已取消守护（例如正在恢复 live 配置）
+                let still_watched = files
+                    .lock()
+                    .map(|files| files.values().any(|a| *a == app_type))
+                    .unwrap_or(false);
+                if still_watched {
+                    handler(app_type).await;
+                }
+            }
+        }
+    }
+
+    /// 开始守护指定应用的 live 配置文件（所在目录不存在的文件跳过）
+    pub fn watch(&mut self, app_type: &AppType, paths: &[PathBuf]) -> Result<(), String> {
+        for path in paths {
+            let Some(dir) = path.parent().filter(|d| d.is_dir()) else {
+                continue;
+            };
+            let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+            if !self.watched_dirs.contains(&dir) {
+                self.watcher
+                    .watch(&dir, RecursiveMode::NonRecursive)
+                    .map_err(|e| format!("监听 {} 失败: {e}", dir.display()))?;
+                self.watched_dirs.push(dir);
+            }
+            if let Ok(mut files) = self.files.lock() {
+                files.insert(normalize(path), app_type.clone());
+            }
+        }
+        Ok(())
+    }
+
+    /// 取消守护指定应用
+    pub fn unwatch(&mut self, app_type: &AppType) {
+        if let Ok(mut files) = self.files.lock() {
+            files.retain(|_, a| a != app_type);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.lock().map(|f| f.is_empty()).unwrap_or(true)
+    }
+}
+
+impl Drop for LiveGuard {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_guard_debounces_events_for_watched_files_only() {
+        let dir = TempDir::new().expect("temp dir");
+        let watched = dir.path().join("settings.json");
+        let other = dir.path().join("other.json");
+        std::fs::write(&watched, "{}").expect("write fixture");
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut guard = LiveGuard::start(move |app_type: AppType| {
+            let tx = tx.clone();
+            async move {
+                let _ = tx.send(app_type);
+            }
+        })
+        .expect("start guard");
+        guard
+            .watch(&AppType::Claude, std::slice::from_ref(&watched))
+            .expect("watch");
+
+        // 连续保存只触发一次
+        for i in 0..5 {
+            std::fs::write(&watched, format!("{{\"v\":{i}}}")).expect("write");
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let first = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .expect("handler should run");
+        assert_eq!(first, Some(AppType::Claude));
+        assert!(
+            tokio::time::timeout(LIVE_GUARD_DEBOUNCE * 3, rx.recv())
+                .await
+                .is_err(),
+            "save storm should be handled once"
+        );
+
+        // 同目录下的其他文件不触发
+        std::fs::write(&other, "{}").expect("write other");
+        assert!(tokio::time::timeout(LIVE_GUARD_DEBOUNCE * 3, rx.recv())
+            .await
+            .is_err());
+
+        // 取消守护后不再触发
+        guard.unwatch(&AppType::Claude);
+        assert!(guard.is_empty());
+        std::fs::write(&watched, "{\"v\":9}").expect("write");
+        assert!(tokio::time::timeout(LIVE_GUARD_DEBOUNCE * 3, rx.recv())
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,547 @@
+//! 从 CLI 现有的 live 配置导入供应商
+//!
+//! 读取 `~/.claude/settings.json`、Codex 的 `auth.json` / `config.toml` 与 Gemini 的 `.env`，
+//! 提取 base_url、key 与模型环境变量后写入供应商表。live 文件只读不写。
+//!
+//! 供应商 ID 由 base_url + key 的哈希生成（`live-<hash>`），重复运行时更新已导入的供应商，
+//! 不会产生重复条目。
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use crate::app_config::AppType;
+use crate::codex_config::{get_codex_auth_path, get_codex_config_path};
+use crate::config::get_claude_settings_path;
+use crate::database::Database;
+use crate::error::AppError;
+use crate::gemini_config::{get_gemini_env_path, parse_env_file};
+use crate::provider::{Provider, CLAUDE_PROXY_ONLY_KEYS};
+use crate::services::proxy::PROXY_TOKEN_PLACEHOLDER;
+
+/// Claude 的 key 字段（按优先级）
+const CLAUDE_KEY_ENV: [&str; 2] = ["ANTHROPIC_AUTH_TOKEN", "ANTHROPIC_API_KEY"];
+
+const CLAUDE_DEFAULT_BASE_URL: &str = "https://api.anthropic.com";
+const CODEX_DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+const GEMINI_DEFAULT_BASE_URL: &str = "https://generativelanguage.googleapis.com";
+
+/// live 配置文件位置
+#[derive(Debug, Clone)]
+pub struct LivePaths {
+    pub claude_settings: PathBuf,
+    pub codex_auth: PathBuf,
+    pub codex_config: PathBuf,
+    pub gemini_env: PathBuf,
+}
+
+impl LivePaths {
+    /// 当前用户的 live 配置位置（遵循设置中的目录覆盖）
+    pub fn current() -> Self {
+        Self {
+            claude_settings: get_claude_settings_path(),
+            codex_auth: get_codex_auth_path(),
+            codex_config: get_codex_config_path(),
+            gemini_env: get_gemini_env_path(),
+        }
+    }
+}
+
+/// 单个应用的导入结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LiveImportAction {
+    Created,
+    Updated,
+    Unchanged,
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LiveImportItem {
+    pub app_type: String,
+    pub action: LiveImportAction,
+    /// 读取的 live 文件
+    pub source: String,
+    pub provider_id: Option<String>,
+    pub name: Option<String>,
+    pub base_url: Option<String>,
+    /// 跳过原因
+    pub reason: Option<String>,
+}
+
+/// 从 live 文件提取出的供应商
+struct LiveCandidate {
+    base_url: String,
+    api_key: String,
+    settings_config: Value,
+}
+
+/// 从 live 配置导入供应商
+pub struct LiveImportService;
+
+impl LiveImportService {
+    /// 依次导入各应用的 live 配置；单个应用无法导入时记为跳过，不影响其他应用
+    pub fn import(
+        db: &Database,
+        app_types: &[AppType],
+        paths: &LivePaths,
+    ) -> Result<Vec<LiveImportItem>, AppError> {
+        let mut items = Vec::new();
+        for app_type in app_types {
+            let (source, candidate) = match app_type {
+                AppType::Claude => (&paths.claude_settings, read_claude(&paths.claude_settings)),
+                AppType::Codex => (
+                    &paths.codex_auth,
+                    read_codex(&paths.codex_auth, &paths.codex_config),
+                ),
+                AppType::Gemini => (&paths.gemini_env, read_gemini(&paths.gemini_env)),
+            };
+            let mut item = LiveImportItem {
+                app_type: app_type.as_str().to_string(),
+                action: LiveImportAction::Skipped,
+                source: source.display().to_string(),
+                provider_id: None,
+                name: None,
+                base_url: None,
+                reason: None,
+            };
+
+            match candidate {
+                Ok(candidate) => {
+                    item.base_url = Some(candidate.base_url.clone());
+                    let (provider, action) = Self::upsert(db, app_type.as_str(), candidate)?;
+                    item.action = action;
+                    item.provider_id = Some(provider.id);
+                    item.name = Some(provider.name);
+                }
+                Err(reason) => item.reason = Some(reason),
+            }
+            items.push(item);
+        }
+        Ok(items)
+    }
+
+    fn upsert(
+        db: &Database,
+        app_type: &str,
+        candidate: LiveCandidate,
+    ) -> Result<(Provider, LiveImportAction), AppError> {
+        let id = live_provider_id(&candidate.base_url, &candidate.api_key);
+
+        match db.get_provider_by_id(&id, app_type)? {
+            Some(mut existing) => {
+                if !merge_settings(
+                    app_type,
+                    &mut existing.settings_config,
+                    &candidate.settings_config,
+                ) {
+                    return Ok((existing, LiveImportAction::Unchanged));
+                }
+                db.save_provider(app_type, &existing)?;
+                Ok((existing, LiveImportAction::Updated))
+            }
+            None => {
+                let mut provider = Provider::with_id(
+                    id,
+                    provider_name(&candidate.base_url),
+                    candidate.settings_config,
+                    None,
+                );
+                provider.category = Some("custom".to_string());
+                db.save_provider(app_type, &provider)?;
+                Ok((provider, LiveImportAction::Created))
+            }
+        }
+    }
+}
+
+/// `live-` + base_url 与 key 的 FNV-1a 哈希前 12 位（跨版本稳定，用于重复导入时定位）
+fn live_provider_id(base_url: &str, api_key: &str) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in base_url.bytes().chain([b'\n']).chain(api_key.bytes()) {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("live-{}", &format!("{hash:016x}")[..12])
+}
+
+/// 以 base_url 的主机名作为供应商名称
+fn provider_name(base_url: &str) -> String {
+    url::Url::parse(base_url)
+        .ok()
+        .and_then(|u| u.host_str().map(str::to_string))
+        .unwrap_or_else(|| base_url.to_string())
+}
+
+/// 导入接管的 settingsConfig 根字段：Claude 为 settings.json 的全部内容（代理专用字段除外），
+/// Codex 为 `auth` / `config`，Gemini 为 `env`
+fn owns_root_key(app_type: &str, key: &str) -> bool {
+    match app_type {
+        "claude" => !CLAUDE_PROXY_ONLY_KEYS.contains(&key),
+        "codex" => key == "auth" || key == "config",
+        "gemini" => key == "env",
+        _ => false,
+    }
+}
+
+/// 把 live 中读到的 `source` 合并进已导入的 `target`，返回是否有变化
+///
+/// 导入接管的根字段整体以 live 为准，live 中已删除的键同样删除；
+/// 其余根字段（在 cc-switch 中另行配置的代理专用字段等）保持不变
+fn merge_settings(app_type: &str, target: &mut Value, source: &Value) -> bool {
+    let before = target.clone();
+    match (target.as_object_mut(), source.as_object()) {
+        (Some(target), Some(source)) => {
+            target.retain(|key, _| !owns_root_key(app_type, key) || source.contains_key(key));
+            for (key, value) in source {
+                target.insert(key.clone(), value.clone());
+            }
+        }
+        _ => *target = source.clone(),
+    }
+    *target != before
+}
+
+fn normalize_base_url(url: &str) -> String {
+    url.trim().trim_end_matches('/').to_string()
+}
+
+/// 非空且不是代理接管占位符的 key
+fn usable_key(key: Option<&str>) -> Result<String, String> {
+    match key.map(str::trim) {
+        Some(PROXY_TOKEN_PLACEHOLDER) => {
+            Err("live 配置已被代理接管（key 为占位符），请先停止代理".to_string())
+        }
+        Some(key) if !key.is_empty() => Ok(key.to_string()),
+        _ => Err("未配置 API Key".to_string()),
+    }
+}
+
+fn read_text(path: &Path) -> Result<String, String> {
+    if !path.exists() {
+        return Err(format!("未找到 {}", path.display()));
+    }
+    std::fs::read_to_string(path).map_err(|e| format!("读取 {} 失败: {e}", path.display()))
+}
+
+fn read_json(path: &Path) -> Result<Value, String> {
+    let text = read_text(path)?;
+    serde_json::from_str(&text).map_err(|e| format!("解析 {} 失败: {e}", path.display()))
+}
+
+fn read_claude(path: &Path) -> Result<LiveCandidate, String> {
+    let mut settings = read_json(path)?;
+    let env = settings
+        .get("env")
+        .and_then(|v| v.as_object())
+        .ok_or_else(|| "settings.json 中没有 env 配置".to_string())?;
+    let env_str = |key: &str| env.get(key).and_then(|v| v.as_str());
+
+    let api_key = CLAUDE_KEY_ENV
+        .iter()
+        .find_map(|name| env_str(name))
+        .ok_or_else(|| "未配置 API Key".to_string())?;
+    let api_key = usable_key(Some(api_key))?;
+    let base_url = env_str("ANTHROPIC_BASE_URL")
+        .map(normalize_base_url)
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| CLAUDE_DEFAULT_BASE_URL.to_string());
+
+    // 完整保留 settings.json（permissions 等非 env 配置同样属于供应商配置），仅规范化 base_url
+    settings["env"]["ANTHROPIC_BASE_URL"] = json!(base_url);
+
+    Ok(LiveCandidate {
+        base_url,
+        api_key,
+        settings_config: settings,
+    })
+}
+
+fn read_codex(auth_path: &Path, config_path: &Path) -> Result<LiveCandidate, String> {
+    let auth = read_json(auth_path)?;
+    let api_key = usable_key(auth.get("OPENAI_API_KEY").and_then(|v| v.as_str()))
+        .map_err(|e| format!("{e}（ChatGPT 账号登录方式无法导入）"))?;
+
+    // config.toml 可选：当前 model_provider 的 base_url 优先，其次顶层 base_url
+    let config_text = if config_path.exists() {
+        Some(read_text(config_path)?)
+    } else {
+        None
+    };
+    let config: Option<toml::Table> = match config_text.as_deref() {
+        Some(text) => Some(
+            toml::from_str(text)
+                .map_err(|e| format!("解析 {} 失败: {e}", config_path.display()))?,
+        ),
+        None => None,
+    };
+    let base_url = config
+        .as_ref()
+        .and_then(|cfg| {
+            let provider = cfg.get("model_provider")?.as_str()?;
+            cfg.get("model_providers")?
+                .get(provider)?
+                .get("base_url")?
+                .as_str()
+        })
+        .or_else(|| config.as_ref()?.get("base_url")?.as_str())
+        .map(normalize_base_url)
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| CODEX_DEFAULT_BASE_URL.to_string());
+
+    // 与 Codex 供应商结构一致：auth 为 auth.json 原样内容，config 为 config.toml 原文
+    Ok(LiveCandidate {
+        base_url,
+        api_key,
+        settings_config: json!({
+            "auth": auth,
+            "config": config_text.unwrap_or_default(),
+        }),
+    })
+}
+
+fn read_gemini(path: &Path) -> Result<LiveCandidate, String> {
+    let env = parse_env_file(&read_text(path)?);
+    let api_key = usable_key(env.get("GEMINI_API_KEY").map(String::as_str))?;
+    let base_url = env
+        .get("GOOGLE_GEMINI_BASE_URL")
+        .map(|s| normalize_base_url(s))
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| GEMINI_DEFAULT_BASE_URL.to_string());
+
+    let mut out = Map::new();
+    out.insert("GOOGLE_GEMINI_BASE_URL".to_string(), json!(base_url));
+    out.insert("GEMINI_API_KEY".to_string(), json!(api_key));
+    if let Some(model) = env.get("GEMINI_MODEL").filter(|s| !s.trim().is_empty()) {
+        out.insert("GEMINI_MODEL".to_string(), json!(model.trim()));
+    }
+
+    Ok(LiveCandidate {
+        base_url,
+        api_key,
+        settings_config: json!({ "env": out }),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const ALL: [AppType; 3] = [AppType::Claude, AppType::Codex, AppType::Gemini];
+
+    fn fixture_paths(dir: &TempDir) -> LivePaths {
+        LivePaths {
+            claude_settings: dir.path().join(".claude/settings.json"),
+            codex_auth: dir.path().join(".codex/auth.json"),
+            codex_config: dir.path().join(".codex/config.toml"),
+            gemini_env: dir.path().join(".gemini/.env"),
+        }
+    }
+
+    fn write(path: &Path, content: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    fn write_fixtures(paths: &LivePaths) {
+        write(
+            &paths.claude_settings,
+            r#"{
+  "env": {
+    "ANTHROPIC_BASE_URL": "https://relay.example.com/",
+    "ANTHROPIC_AUTH_TOKEN": "sk-claude-live",
+    "ANTHROPIC_DEFAULT_HAIKU_MODEL": "claude-haiku-4-5",
+    "DISABLE_TELEMETRY": "1"
+  },
+  "permissions": { "allow": [] }
+}"#,
+        );
+        write(
+            &paths.codex_auth,
+            r#"{ "OPENAI_API_KEY": "sk-codex-live" }"#,
+        );
+        write(
+            &paths.codex_config,
+            r#"model_provider = "relay"
+model = "gpt-5"
+
+[model_providers.relay]
+name = "relay"
+base_url = "https://codex.example.com/v1"
+wire_api = "responses"
+"#,
+        );
+        write(
+            &paths.gemini_env,
+            "GEMINI_API_KEY=gm-live\nGEMINI_MODEL=gemini-2.5-pro\n",
+        );
+    }
+
+    fn snapshot(paths: &LivePaths) -> Vec<String> {
+        [
+            &paths.claude_settings,
+            &paths.codex_auth,
+            &paths.codex_config,
+            &paths.gemini_env,
+        ]
+        .iter()
+        .map(|p| std::fs::read_to_string(p).unwrap())
+        .collect()
+    }
+
+    #[test]
+    fn import_reads_all_apps_and_is_idempotent() {
+        let dir = TempDir::new().unwrap();
+        let paths = fixture_paths(&dir);
+        write_fixtures(&paths);
+        let before = snapshot(&paths);
+        let db = Database::memory().unwrap();
+
+        let items = LiveImportService::import(&db, &ALL, &paths).unwrap();
+        assert!(items.iter().all(|i| i.action == LiveImportAction::Created));
+        assert_eq!(items[0].name.as_deref(), Some("relay.example.com"));
+        assert_eq!(
+            items[1].base_url.as_deref(),
+            Some("https://codex.example.com/v1")
+        );
+        assert_eq!(
+            items[2].base_url.as_deref(),
+            Some("https://generativelanguage.googleapis.com")
+        );
+
+        let claude = db
+            .get_provider_by_id(items[0].provider_id.as_ref().unwrap(), "claude")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            claude.settings_config,
+            json!({
+                "env": {
+                    "ANTHROPIC_BASE_URL": "https://relay.example.com",
+                    "ANTHROPIC_AUTH_TOKEN": "sk-claude-live",
+                    "ANTHROPIC_DEFAULT_HAIKU_MODEL": "claude-haiku-4-5",
+                    "DISABLE_TELEMETRY": "1"
+                },
+                "permissions": { "allow": [] }
+            })
+        );
+        let codex = db
+            .get_provider_by_id(items[1].provider_id.as_ref().unwrap(), "codex")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            codex.settings_config["auth"],
+            json!({ "OPENAI_API_KEY": "sk-codex-live" })
+        );
+        assert_eq!(codex.settings_config["config"], json!(before[2]));
+        assert!(codex.settings_config.get("env").is_none());
+        let gemini = db
+            .get_provider_by_id(items[2].provider_id.as_ref().unwrap(), "gemini")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            gemini.settings_config["env"]["GEMINI_MODEL"],
+            "gemini-2.5-pro"
+        );
+
+        // 再次导入：不新建、不改动
+        let again = LiveImportService::import(&db, &ALL, &paths).unwrap();
+        assert!(again
+            .iter()
+            .all(|i| i.action == LiveImportAction::Unchanged));
+        assert_eq!(again[0].provider_id, items[0].provider_id);
+        assert_eq!(db.get_all_providers("claude").unwrap().len(), 1);
+
+        // 模型变化后更新同一供应商，保留用户在 cc-switch 中改过的名称
+        let mut renamed = claude.clone();
+        renamed.name = "我的中转".to_string();
+        db.save_provider("claude", &renamed).unwrap();
+        write(
+            &paths.claude_settings,
+            &before[0].replace("claude-haiku-4-5", "claude-haiku-4-5-20251001"),
+        );
+        let updated = LiveImportService::import(&db, &[AppType::Claude], &paths).unwrap();
+        assert_eq!(updated[0].action, LiveImportAction::Updated);
+        assert_eq!(updated[0].name.as_deref(), Some("我的中转"));
+        let claude = db
+            .get_provider_by_id(&claude.id, "claude")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            claude.settings_config["env"]["ANTHROPIC_DEFAULT_HAIKU_MODEL"],
+            "claude-haiku-4-5-20251001"
+        );
+
+        // live 中删除的键同步删除；cc-switch 中另行配置的代理专用字段保留
+        let mut tuned = claude.clone();
+        tuned.settings_config[crate::provider::PROBE_MODEL_KEY] = json!("claude-haiku-4-5");
+        db.save_provider("claude", &tuned).unwrap();
+        let pruned = r#"{
+  "env": {
+    "ANTHROPIC_BASE_URL": "https://relay.example.com/",
+    "ANTHROPIC_AUTH_TOKEN": "sk-claude-live",
+    "ANTHROPIC_DEFAULT_HAIKU_MODEL": "claude-haiku-4-5-20251001"
+  }
+}"#;
+        write(&paths.claude_settings, pruned);
+        let updated = LiveImportService::import(&db, &[AppType::Claude], &paths).unwrap();
+        assert_eq!(updated[0].action, LiveImportAction::Updated);
+        let claude = db
+            .get_provider_by_id(&claude.id, "claude")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            claude.settings_config,
+            json!({
+                "env": {
+                    "ANTHROPIC_BASE_URL": "https://relay.example.com",
+                    "ANTHROPIC_AUTH_TOKEN": "sk-claude-live",
+                    "ANTHROPIC_DEFAULT_HAIKU_MODEL": "claude-haiku-4-5-20251001"
+                },
+                "probeModel": "claude-haiku-4-5"
+            })
+        );
+
+        // live 文件从未被修改
+        let mut expected = before;
+        expected[0] = pruned.to_string();
+        assert_eq!(snapshot(&paths), expected);
+    }
+
+    #[test]
+    fn import_skips_missing_and_taken_over_configs() {
+        let dir = TempDir::new().unwrap();
+        let paths = fixture_paths(&dir);
+        write(
+            &paths.claude_settings,
+            r#"{"env": {"ANTHROPIC_BASE_URL": "http://127.0.0.1:15721", "ANTHROPIC_AUTH_TOKEN": "PROXY_MANAGED"}}"#,
+        );
+        // ChatGPT 登录方式：auth.json 中只有 tokens
+        write(&paths.codex_auth, r#"{"tokens": {"access_token": "x"}}"#);
+        let db = Database::memory().unwrap();
+
+        let items = LiveImportService::import(&db, &ALL, &paths).unwrap();
+        assert!(items.iter().all(|i| i.action == LiveImportAction::Skipped));
+        assert!(items[0].reason.as_deref().unwrap().contains("已被代理接管"));
+        assert!(items[1]
+            .reason
+            .as_deref()
+            .unwrap()
+            .contains("ChatGPT 账号登录"));
+        assert!(items[2].reason.as_deref().unwrap().starts_with("未找到"));
+        assert!(db.get_all_providers("claude").unwrap().is_empty());
+    }
+
+    #[test]
+    fn live_provider_id_is_stable_and_keyed_by_url_and_key() {
+        let id = live_provider_id("https://a.example.com", "sk-1");
+        assert_eq!(id, live_provider_id("https://a.example.com", "sk-1"));
+        assert_eq!(id.len(), "live-".len() + 12);
+        assert_ne!(id, live_provider_id("https://a.example.com", "sk-2"));
+        assert_ne!(id, live_provider_id("https://b.example.com", "sk-1"));
+    }
+}
@@ -1,6 +1,9 @@
 pub mod config;
 pub mod env_checker;
 pub mod env_manager;
+pub mod live_diff;
+pub mod live_guard;
+pub mod live_import;
 pub mod mcp;
 pub mod prompt;
 pub mod provider;
@@ -11,6 +14,7 @@ pub mod stream_check;
 pub mod usage_stats;
 
 pub use config::ConfigService;
+pub use live_import::{LiveImportAction, LiveImportItem, LiveImportService, LivePaths};
 pub use mcp::McpService;
 pub use prompt::PromptService;
 pub use provider::{ProviderService, ProviderSortUpdate};
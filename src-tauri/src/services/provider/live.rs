@@ -10,7 +10,7 @@ use crate::app_config::AppType;
 use crate::codex_config::{get_codex_auth_path, get_codex_config_path};
 use crate::config::{delete_file, get_claude_settings_path, read_json_file, write_json_file};
 use crate::error::AppError;
-use crate::provider::Provider;
+use crate::provider::{Provider, CLAUDE_PROXY_ONLY_KEYS};
 use crate::services::mcp::McpService;
 use crate::store::AppState;
 
@@ -19,6 +19,36 @@ use super::gemini_auth::{
 };
 use super::normalize_claude_models_in_value;
 
+/// 写入 Claude live 配置前去掉代理专用字段，其余字段（env 与 Claude Code 自身配置）原样保留
+pub(crate) fn claude_live_settings(settings: &Value) -> Value {
+    let mut live = settings.clone();
+    if let Some(obj) = live.as_object_mut() {
+        for key in CLAUDE_PROXY_ONLY_KEYS {
+            obj.remove(*key);
+        }
+    }
+    live
+}
+
+/// 将 live 配置回填到供应商时补回代理专用字段（live 文件中不含这些字段）
+pub(crate) fn backfill_from_live(
+    app_type: &AppType,
+    provider_settings: &Value,
+    mut live: Value,
+) -> Value {
+    if !matches!(app_type, AppType::Claude) {
+        return live;
+    }
+    if let (Some(obj), Some(current)) = (live.as_object_mut(), provider_settings.as_object()) {
+        for key in CLAUDE_PROXY_ONLY_KEYS {
+            if let Some(value) = current.get(*key) {
+                obj.insert(key.to_string(), value.clone());
+            }
+        }
+    }
+    live
+}
+
 /// Live configuration snapshot for backup/restore
 #[derive(Clone)]
 #[allow(dead_code)]
@@ -97,7 +127,7 @@ pub(crate) fn write_live_snapshot(app_type: &AppType, provider: &Provider) -> Re
     match app_type {
         AppType::Claude => {
             let path = get_claude_settings_path();
-            write_json_file(&path, &provider.settings_config)?;
+            write_json_file(&path, &claude_live_settings(&provider.settings_config))?;
         }
         AppType::Codex => {
             let obj = provider
@@ -390,3 +420,69 @@ pub(crate) fn write_gemini_live(provider: &Provider) -> Result<(), AppError> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn claude_live_settings_drops_proxy_only_keys() {
+        let provider = Provider::with_id(
+            "relay".to_string(),
+            "relay".to_string(),
+            json!({
+                "env": {
+                    "ANTHROPIC_BASE_URL": "https://relay.example.com",
+                    "ANTHROPIC_AUTH_TOKEN": "sk-relay"
+                },
+                "permissions": { "allow": ["Bash(ls:*)"] },
+                "model": "opus",
+                "supplier": "relay",
+                "auth": { "type": "bearer" },
+                "bedrock": { "region": "us-east-1", "secretAccessKey": "aws-secret" },
+                "supportsThinking": false,
+                "maxTokensCap": 8192,
+                "bodyTransforms": [{ "op": "remove", "path": "/metadata" }],
+                "systemPrompt": { "mode": "prepend", "text": "hi" },
+                "sseFilter": { "dropEvents": ["ping"] },
+                "localBackend": false,
+                "modelLock": "claude-sonnet-4-5",
+                "modelFallbacks": ["claude-haiku-4-5"],
+                "probeModel": "claude-haiku-4-5",
+                "familyGuard": true
+            }),
+            None,
+        );
+
+        // 登记表中的每个字段都出现在样例中
+        for key in CLAUDE_PROXY_ONLY_KEYS {
+            assert!(provider.settings_config.get(*key).is_some(), "{key}");
+        }
+
+        let live = claude_live_settings(&provider.settings_config);
+        assert_eq!(
+            live,
+            json!({
+                "env": {
+                    "ANTHROPIC_BASE_URL": "https://relay.example.com",
+                    "ANTHROPIC_AUTH_TOKEN": "sk-relay"
+                },
+                "permissions": { "allow": ["Bash(ls:*)"] },
+                "model": "opus"
+            })
+        );
+        // 供应商本身的配置不受影响
+        assert_eq!(provider.settings_config["bedrock"]["region"], "us-east-1");
+
+        // 切换时从 live 回填：live 中的修改生效，代理专用字段补回
+        let mut edited = live;
+        edited["model"] = json!("sonnet");
+        let backfilled = backfill_from_live(&AppType::Claude, &provider.settings_config, edited);
+        assert_eq!(backfilled["model"], "sonnet");
+        assert_eq!(backfilled["bedrock"], provider.settings_config["bedrock"]);
+        assert_eq!(
+            backfilled.as_object().unwrap().len(),
+            provider.settings_config.as_object().unwrap().len()
+        );
+    }
+}
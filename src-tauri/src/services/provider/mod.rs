@@ -15,6 +15,7 @@ use serde_json::Value;
 use crate::app_config::AppType;
 use crate::error::AppError;
 use crate::provider::{Provider, UsageResult};
+use crate::proxy::provider_router::ProviderRouter;
 use crate::services::mcp::McpService;
 use crate::settings::CustomEndpoint;
 use crate::store::AppState;
@@ -23,10 +24,10 @@ use crate::store::AppState;
 pub use live::{import_default_config, read_live_settings, sync_current_to_live};
 
 // Internal re-exports (pub(crate))
-pub(crate) use live::write_live_snapshot;
+pub(crate) use live::{claude_live_settings, write_live_snapshot};
 
 // Internal re-exports
-use live::write_gemini_live;
+use live::{backfill_from_live, write_gemini_live};
 use usage::validate_usage_script;
 
 /// Provider business logic service
@@ -53,6 +54,42 @@ mod tests {
         );
     }
 
+    #[test]
+    fn validate_provider_settings_checks_base_url() {
+        for (base_url, ok) in [
+            ("http://[::1]:8080/v1", true),
+            ("http://192.168.1.10:3000", true),
+            ("https://gateway.example.com:8443/anthropic", true),
+            ("gateway.example.com", false),
+            ("ftp://gateway.example.com", false),
+        ] {
+            let provider = Provider::with_id(
+                "claude".into(),
+                "Claude".into(),
+                json!({ "env": { "ANTHROPIC_BASE_URL": base_url, "ANTHROPIC_API_KEY": "sk" } }),
+                None,
+            );
+            let result = ProviderService::validate_provider_settings(&AppType::Claude, &provider);
+            assert_eq!(result.is_ok(), ok, "{base_url}: {result:?}");
+        }
+    }
+
+    #[test]
+    fn template_based_providers_pass_validation() {
+        let templates = crate::provider_templates::builtin_provider_templates().unwrap();
+        for template in templates.values() {
+            for app in template.apps.keys() {
+                let app_type: AppType = app.parse().unwrap();
+                let provider = template
+                    .build_provider(app, "tpl", None, "sk-test", None)
+                    .unwrap();
+                ProviderService::validate_provider_settings(&app_type, &provider).unwrap_or_else(
+                    |e| panic!("template {}/{} failed validation: {e}", template.id, app),
+                );
+            }
+        }
+    }
+
     #[test]
     fn extract_credentials_returns_expected_values() {
         let provider = Provider::with_id(
@@ -208,6 +245,9 @@ impl ProviderService {
             .get(id)
             .ok_or_else(|| AppError::Message(format!("供应商 {id} 不存在")))?;
 
+        // Manual switch expresses user intent: cancel any pending auto-restore
+        crate::settings::set_restore_primary(&app_type, None)?;
+
         // Check if proxy takeover mode is active AND proxy server is actually running
         // Both conditions must be true to use hot-switch mode
         // Use blocking wait since this is a sync function
@@ -277,7 +317,11 @@ impl ProviderService {
                 // Only backfill when switching to a different provider
                 if let Ok(live_config) = read_live_settings(app_type.clone()) {
                     if let Some(mut current_provider) = providers.get(&current_id).cloned() {
-                        current_provider.settings_config = live_config;
+                        current_provider.settings_config = backfill_from_live(
+                            &app_type,
+                            &current_provider.settings_config,
+                            live_config,
+                        );
                         // Ignore backfill failure, don't affect switch flow
                         let _ = state.db.save_provider(app_type.as_str(), &current_provider);
                     }
@@ -471,6 +515,17 @@ impl ProviderService {
             }
         }
 
+        // base_url 须为带主机名的 http(s) 地址；`${ENV_VAR}` 未设置时跳过（转发时再报错）
+        if let Some(base_url) = ProviderRouter::extract_base_url(provider, app_type.as_str()) {
+            ProviderRouter::validate_base_url(&base_url).map_err(|e| {
+                AppError::localized(
+                    "provider.base_url.invalid",
+                    format!("供应商 {} 的 base_url 无效: {e}", provider.id),
+                    format!("Provider {} has an invalid base_url: {e}", provider.id),
+                )
+            })?;
+        }
+
         // Validate and clean UsageScript configuration (common for all app types)
         if let Some(meta) = &provider.meta {
             if let Some(usage_script) = &meta.usage_script {
@@ -6,16 +6,29 @@ use crate::app_config::AppType;
 use crate::config::{get_claude_settings_path, read_json_file, write_json_file};
 use crate::database::Database;
 use crate::provider::Provider;
+use crate::proxy::model_warmup::{ModelWarmup, WarmupOutcome};
+use crate::proxy::notifications::{self, NotificationEvent, NotificationPayload};
 use crate::proxy::server::ProxyServer;
+use crate::proxy::RecentRequest;
 use crate::proxy::types::*;
-use crate::services::provider::write_live_snapshot;
+use crate::services::live_diff::diff_live_configs;
+use crate::services::live_guard::{live_config_paths, LiveGuard};
+use crate::services::provider::{claude_live_settings, write_live_snapshot};
+use crate::settings::LiveGuardMode;
 use serde_json::{json, Value};
 use std::str::FromStr;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
 
 /// 用于接管 Live 配置时的占位符（避免客户端提示缺少 key，同时不泄露真实 Token）
-const PROXY_TOKEN_PLACEHOLDER: &str = "PROXY_MANAGED";
+pub(crate) const PROXY_TOKEN_PLACEHOLDER: &str = "PROXY_MANAGED";
+
+/// Live 配置守护统计（进程内，重启后清零）
+#[derive(Debug, Default)]
+struct LiveGuardStats {
+    reassert_count: u64,
+    last_event: Option<LiveGuardEvent>,
+}
 
 #[derive(Clone)]
 pub struct ProxyService {
@@ -23,6 +36,9 @@ pub struct ProxyService {
     server: Arc<RwLock<Option<ProxyServer>>>,
     /// AppHandle，用于传递给 ProxyServer 以支持故障转移时的 UI 更新
     app_handle: Arc<RwLock<Option<tauri::AppHandle>>>,
+    /// 接管中 live 配置的文件守护（没有被守护的应用时为 None）
+    live_guard: Arc<Mutex<Option<LiveGuard>>>,
+    live_guard_stats: Arc<RwLock<LiveGuardStats>>,
 }
 
 impl ProxyService {
@@ -31,6 +47,8 @@ impl ProxyService {
             db,
             server: Arc::new(RwLock::new(None)),
             app_handle: Arc::new(RwLock::new(None)),
+            live_guard: Arc::new(Mutex::new(None)),
+            live_guard_stats: Arc::new(RwLock::new(LiveGuardStats::default())),
         }
     }
 
@@ -50,7 +68,8 @@ impl ProxyService {
             .await
             .map_err(|e| format!("获取全局代理配置失败: {e}"))?;
 
-        if !global_config.proxy_enabled {
+        // 只读模式不写数据库（包括总开关）
+        if !global_config.proxy_enabled && !global_config.read_only {
             global_config.proxy_enabled = true;
             self.db
                 .update_global_proxy_config(global_config.clone())
@@ -132,7 +151,15 @@ impl ProxyService {
 
         // 5. 启动代理服务器
         match self.start().await {
-            Ok(info) => Ok(info),
+            Ok(info) => {
+                // 6. 守护已接管的 live 配置
+                for app_type in [AppType::Claude, AppType::Codex, AppType::Gemini] {
+                    if self.detect_takeover_in_live_config_for_app(&app_type) {
+                        self.watch_live_config(&app_type).await;
+                    }
+                }
+                Ok(info)
+            }
             Err(e) => {
                 // 启动失败，恢复原始配置
                 log::error!("代理启动失败，尝试恢复原始配置: {e}");
@@ -150,6 +177,13 @@ impl ProxyService {
         }
     }
 
+    /// 将各应用的接管开关推送到运行中的代理（未运行时无需处理）
+    pub async fn refresh_app_gate(&self) {
+        if let Some(server) = self.server.read().await.as_ref() {
+            server.refresh_app_gate().await;
+        }
+    }
+
     /// 获取各应用的接管状态（是否改写该应用的 Live 配置指向本地代理）
     pub async fn get_takeover_status(&self) -> Result<ProxyTakeoverStatus, String> {
         // 从 proxy_config.enabled 读取（优先），兼容旧的 live_backup 备份检测
@@ -185,81 +219,117 @@ impl ProxyService {
     /// - 关闭：仅恢复当前 app 的 Live 配置；若无其它接管，则自动停止代理服务
     pub async fn set_takeover_for_app(&self, app_type: &str, enabled: bool) -> Result<(), String> {
         let app = AppType::from_str(app_type).map_err(|e| format!("无效的应用类型: {e}"))?;
-        let app_type_str = app.as_str();
 
         if enabled {
-            // 1) 代理服务未运行则自动启动
+            // 代理服务未运行则自动启动
             if !self.is_running().await {
                 self.start().await?;
             }
 
-            // 2) 已接管则直接返回（幂等）
-            let current_config = self
-                .db
-                .get_proxy_config_for_app(app_type_str)
-                .await
-                .map_err(|e| format!("获取 {app_type_str} 配置失败: {e}"))?;
-
-            if current_config.enabled {
-                return Ok(());
+            let applied = self.apply_takeover_for_app(&app).await?;
+            // 守护该应用的 live 配置，被外部改写时重新接管或告警
+            if applied || self.detect_takeover_in_live_config_for_app(&app) {
+                self.watch_live_config(&app).await;
             }
+            return Ok(());
+        }
 
-            // 3) 备份 Live 配置（严格：目标 app 不存在则报错）
-            self.backup_live_config_strict(&app).await?;
+        if !self.release_takeover_for_app(&app).await? {
+            return Ok(()); // 未接管，幂等返回
+        }
 
-            // 4) 同步 Live Token 到数据库（仅当前 app）
-            if let Err(e) = self.sync_live_to_provider(&app).await {
-                let _ = self.db.delete_live_backup(app_type_str).await;
-                return Err(e);
-            }
+        // 若无其它接管，停止代理服务
+        let any_enabled = self
+            .db
+            .is_live_takeover_active()
+            .await
+            .map_err(|e| format!("检查接管状态失败: {e}"))?;
+        if !any_enabled && self.is_running().await {
+            // 此时没有任何 app 处于接管状态，停止服务即可
+            let _ = self.stop().await;
+        }
 
-            // 5) 写入接管配置（仅当前 app）
-            if let Err(e) = self.takeover_live_config_strict(&app).await {
-                log::error!("{app_type_str} 接管 Live 配置失败，尝试恢复: {e}");
-                match self.restore_live_config_for_app(&app).await {
-                    Ok(()) => {
-                        // 恢复成功才清理备份，避免失败场景下丢失唯一可回滚来源
-                        let _ = self.db.delete_live_backup(app_type_str).await;
-                    }
-                    Err(restore_err) => {
-                        log::error!(
-                            "{app_type_str} 恢复 Live 配置失败，将保留备份以便下次启动恢复: {restore_err}"
-                        );
-                    }
+        Ok(())
+    }
+
+    /// 接管指定应用的 Live 配置：备份 → 同步 Token → 写入代理地址 → 设置 enabled
+    ///
+    /// 不启动代理、不守护 live 配置（CLI 对独立运行的代理进程开启接管时使用）；
+    /// 已接管时返回 false（幂等）。
+    pub async fn apply_takeover_for_app(&self, app: &AppType) -> Result<bool, String> {
+        let app_type_str = app.as_str();
+
+        // 1) 已接管则直接返回（幂等）
+        let current_config = self
+            .db
+            .get_proxy_config_for_app(app_type_str)
+            .await
+            .map_err(|e| format!("获取 {app_type_str} 配置失败: {e}"))?;
+        if current_config.enabled {
+            return Ok(false);
+        }
+
+        // 2) 备份 Live 配置（严格：目标 app 不存在则报错）
+        self.backup_live_config_strict(app).await?;
+
+        // 3) 同步 Live Token 到数据库（仅当前 app）
+        if let Err(e) = self.sync_live_to_provider(app).await {
+            let _ = self.db.delete_live_backup(app_type_str).await;
+            return Err(e);
+        }
+
+        // 4) 写入接管配置（仅当前 app）
+        if let Err(e) = self.takeover_live_config_strict(app).await {
+            log::error!("{app_type_str} 接管 Live 配置失败，尝试恢复: {e}");
+            match self.restore_live_config_for_app(app).await {
+                Ok(()) => {
+                    // 恢复成功才清理备份，避免失败场景下丢失唯一可回滚来源
+                    let _ = self.db.delete_live_backup(app_type_str).await;
+                }
+                Err(restore_err) => {
+                    log::error!(
+                        "{app_type_str} 恢复 Live 配置失败，将保留备份以便下次启动恢复: {restore_err}"
+                    );
                 }
-                return Err(e);
             }
+            return Err(e);
+        }
 
-            // 6) 设置 proxy_config.enabled = true
-            let mut updated_config = self
-                .db
-                .get_proxy_config_for_app(app_type_str)
-                .await
-                .map_err(|e| format!("获取 {app_type_str} 配置失败: {e}"))?;
-            updated_config.enabled = true;
-            self.db
-                .update_proxy_config_for_app(updated_config)
-                .await
-                .map_err(|e| format!("设置 {app_type_str} enabled 状态失败: {e}"))?;
+        // 5) 设置 proxy_config.enabled = true
+        let mut updated_config = self
+            .db
+            .get_proxy_config_for_app(app_type_str)
+            .await
+            .map_err(|e| format!("获取 {app_type_str} 配置失败: {e}"))?;
+        updated_config.enabled = true;
+        self.db
+            .update_proxy_config_for_app(updated_config)
+            .await
+            .map_err(|e| format!("设置 {app_type_str} enabled 状态失败: {e}"))?;
+        self.refresh_app_gate().await;
 
-            // 7) 兼容旧逻辑：写入 any-of 标志（失败不影响功能）
-            let _ = self.db.set_live_takeover_active(true).await;
-            return Ok(());
-        }
+        // 6) 兼容旧逻辑：写入 any-of 标志（失败不影响功能）
+        let _ = self.db.set_live_takeover_active(true).await;
+        Ok(true)
+    }
+
+    /// 关闭指定应用的接管：从 Live 备份恢复、删除备份、清除 enabled 与健康状态
+    ///
+    /// 不停止代理；未接管时返回 false（幂等）。
+    pub async fn release_takeover_for_app(&self, app: &AppType) -> Result<bool, String> {
+        let app_type_str = app.as_str();
 
-        // 关闭接管：检查 enabled 状态
         let current_config = self
             .db
             .get_proxy_config_for_app(app_type_str)
             .await
             .map_err(|e| format!("获取 {app_type_str} 配置失败: {e}"))?;
-
         if !current_config.enabled {
-            return Ok(()); // 未接管，幂等返回
+            return Ok(false);
         }
 
         // 1) 恢复 Live 配置
-        self.restore_live_config_for_app(&app).await?;
+        self.restore_live_config_for_app(app).await?;
 
         // 2) 删除该 app 的备份（避免长期存储敏感 Token）
         self.db
@@ -278,6 +348,7 @@ impl ProxyService {
             .update_proxy_config_for_app(updated_config)
             .await
             .map_err(|e| format!("清除 {app_type_str} enabled 状态失败: {e}"))?;
+        self.refresh_app_gate().await;
 
         // 4) 清除该应用的健康状态（关闭代理时重置队列状态）
         self.db
@@ -285,24 +356,51 @@ impl ProxyService {
             .await
             .map_err(|e| format!("清除 {app_type_str} 健康状态失败: {e}"))?;
 
-        // 5) 若无其它接管，更新旧标志，并停止代理服务
-        // 检查是否还有其它 app 的 enabled = true
+        // 5) 若无其它接管，更新旧标志
         let any_enabled = self
             .db
             .is_live_takeover_active()
             .await
             .map_err(|e| format!("检查接管状态失败: {e}"))?;
-
         if !any_enabled {
             let _ = self.db.set_live_takeover_active(false).await;
-
-            if self.is_running().await {
-                // 此时没有任何 app 处于接管状态，停止服务即可
-                let _ = self.stop().await;
-            }
         }
 
-        Ok(())
+        Ok(true)
+    }
+
+    /// 指定应用的接管详情：开关、备份、live 配置是否仍指向代理
+    pub async fn get_takeover_detail(&self, app: &AppType) -> Result<AppTakeoverDetail, String> {
+        let app_type_str = app.as_str();
+        let enabled = self
+            .db
+            .get_proxy_config_for_app(app_type_str)
+            .await
+            .map_err(|e| format!("获取 {app_type_str} 配置失败: {e}"))?
+            .enabled;
+        let has_backup = self
+            .db
+            .get_live_backup(app_type_str)
+            .await
+            .map_err(|e| format!("获取 {app_type_str} Live 备份失败: {e}"))?
+            .is_some();
+
+        let (proxy_url, proxy_codex_base_url) = self.build_proxy_urls().await?;
+        let points_to_proxy = self.read_live_config_for_app(app).ok().map(|config| {
+            Self::live_points_to_proxy(app, &config, &proxy_url, &proxy_codex_base_url)
+        });
+
+        Ok(AppTakeoverDetail {
+            app_type: app_type_str.to_string(),
+            enabled,
+            has_backup,
+            proxy_url: match app {
+                AppType::Codex => proxy_codex_base_url,
+                _ => proxy_url,
+            },
+            points_to_proxy,
+            files: live_config_paths(app),
+        })
     }
 
     /// 同步 Live 配置中的 Token 到数据库
@@ -532,7 +630,7 @@ impl ProxyService {
                 .await
                 .map_err(|e| format!("获取全局代理配置失败: {e}"))?;
 
-            if global_config.proxy_enabled {
+            if global_config.proxy_enabled && !global_config.read_only {
                 global_config.proxy_enabled = false;
                 if let Err(e) = self.db.update_global_proxy_config(global_config).await {
                     log::warn!("更新代理总开关失败: {e}");
@@ -723,6 +821,9 @@ impl ProxyService {
 
         // Claude: 修改 ANTHROPIC_BASE_URL，使用占位符替代真实 Token（代理会注入真实 Token）
         if let Ok(mut live_config) = self.read_claude_live() {
+            self.snapshot_live_config(&AppType::Claude, &live_config)
+                .await;
+
             if let Some(env) = live_config.get_mut("env").and_then(|v| v.as_object_mut()) {
                 env.insert("ANTHROPIC_BASE_URL".to_string(), json!(&proxy_url));
                 // 仅覆盖已存在的 Token 字段，避免新增字段导致用户困惑；
@@ -760,6 +861,9 @@ impl ProxyService {
 
         // Codex: 修改 config.toml 的 base_url，auth.json 的 OPENAI_API_KEY（代理会注入真实 Token）
         if let Ok(mut live_config) = self.read_codex_live() {
+            self.snapshot_live_config(&AppType::Codex, &live_config)
+                .await;
+
             // 1. 修改 auth.json 中的 OPENAI_API_KEY（使用占位符）
             if let Some(auth) = live_config.get_mut("auth").and_then(|v| v.as_object_mut()) {
                 auth.insert("OPENAI_API_KEY".to_string(), json!(PROXY_TOKEN_PLACEHOLDER));
@@ -779,6 +883,9 @@ impl ProxyService {
 
         // Gemini: 修改 GOOGLE_GEMINI_BASE_URL，使用占位符替代真实 Token（代理会注入真实 Token）
         if let Ok(mut live_config) = self.read_gemini_live() {
+            self.snapshot_live_config(&AppType::Gemini, &live_config)
+                .await;
+
             if let Some(env) = live_config.get_mut("env").and_then(|v| v.as_object_mut()) {
                 env.insert("GOOGLE_GEMINI_BASE_URL".to_string(), json!(&proxy_url));
                 // 使用占位符，避免显示缺少 key 的警告
@@ -803,6 +910,8 @@ impl ProxyService {
         match app_type {
             AppType::Claude => {
                 let mut live_config = self.read_claude_live()?;
+                self.snapshot_live_config(app_type, &live_config).await;
+
                 if let Some(env) = live_config.get_mut("env").and_then(|v| v.as_object_mut()) {
                     env.insert("ANTHROPIC_BASE_URL".to_string(), json!(&proxy_url));
 
@@ -839,6 +948,7 @@ impl ProxyService {
             }
             AppType::Codex => {
                 let mut live_config = self.read_codex_live()?;
+                self.snapshot_live_config(app_type, &live_config).await;
 
                 if let Some(auth) = live_config.get_mut("auth").and_then(|v| v.as_object_mut()) {
                     auth.insert("OPENAI_API_KEY".to_string(), json!(PROXY_TOKEN_PLACEHOLDER));
@@ -856,6 +966,7 @@ impl ProxyService {
             }
             AppType::Gemini => {
                 let mut live_config = self.read_gemini_live()?;
+                self.snapshot_live_config(app_type, &live_config).await;
 
                 if let Some(env) = live_config.get_mut("env").and_then(|v| v.as_object_mut()) {
                     env.insert("GOOGLE_GEMINI_BASE_URL".to_string(), json!(&proxy_url));
@@ -882,6 +993,8 @@ impl ProxyService {
         match app_type {
             AppType::Claude => {
                 if let Ok(mut live_config) = self.read_claude_live() {
+                    self.snapshot_live_config(app_type, &live_config).await;
+
                     if let Some(env) = live_config.get_mut("env").and_then(|v| v.as_object_mut()) {
                         env.insert("ANTHROPIC_BASE_URL".to_string(), json!(&proxy_url));
 
@@ -918,6 +1031,8 @@ impl ProxyService {
             }
             AppType::Codex => {
                 if let Ok(mut live_config) = self.read_codex_live() {
+                    self.snapshot_live_config(app_type, &live_config).await;
+
                     if let Some(auth) = live_config.get_mut("auth").and_then(|v| v.as_object_mut())
                     {
                         auth.insert("OPENAI_API_KEY".to_string(), json!(PROXY_TOKEN_PLACEHOLDER));
@@ -936,6 +1051,8 @@ impl ProxyService {
             }
             AppType::Gemini => {
                 if let Ok(mut live_config) = self.read_gemini_live() {
+                    self.snapshot_live_config(app_type, &live_config).await;
+
                     if let Some(env) = live_config.get_mut("env").and_then(|v| v.as_object_mut()) {
                         env.insert("GOOGLE_GEMINI_BASE_URL".to_string(), json!(&proxy_url));
                         env.insert("GEMINI_API_KEY".to_string(), json!(PROXY_TOKEN_PLACEHOLDER));
@@ -956,6 +1073,9 @@ impl ProxyService {
 
     /// 恢复指定应用的 Live 配置（若无备份则不做任何操作）
     async fn restore_live_config_for_app(&self, app_type: &AppType) -> Result<(), String> {
+        // 先停止守护，避免恢复写入被当作外部改写而重新接管
+        self.unwatch_live_config(app_type).await;
+
         match app_type {
             AppType::Claude => {
                 if let Ok(Some(backup)) = self.db.get_live_backup("claude").await {
@@ -991,6 +1111,7 @@ impl ProxyService {
         let mut errors = Vec::new();
 
         for app_type in [AppType::Claude, AppType::Codex, AppType::Gemini] {
+            self.unwatch_live_config(&app_type).await;
             if let Err(e) = self
                 .restore_live_config_for_app_with_fallback(&app_type)
                 .await
@@ -1055,6 +1176,14 @@ impl ProxyService {
         Ok(())
     }
 
+    fn read_live_config_for_app(&self, app_type: &AppType) -> Result<Value, String> {
+        match app_type {
+            AppType::Claude => self.read_claude_live(),
+            AppType::Codex => self.read_codex_live(),
+            AppType::Gemini => self.read_gemini_live(),
+        }
+    }
+
     fn write_live_config_for_app(&self, app_type: &AppType, config: &Value) -> Result<(), String> {
         match app_type {
             AppType::Claude => self.write_claude_live(config),
@@ -1063,20 +1192,273 @@ impl ProxyService {
         }
     }
 
+    // ==================== Live 配置快照 ====================
+
+    /// 接管写入前保存当前 Live 配置快照（失败仅记录警告，不阻断接管）
+    async fn snapshot_live_config(&self, app_type: &AppType, config: &Value) {
+        let app_type_str = app_type.as_str();
+        let result = match serde_json::to_string(config) {
+            Ok(json_str) => self
+                .db
+                .save_live_snapshot(app_type_str, &json_str)
+                .await
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        if let Err(e) = result {
+            log::warn!("保存 {app_type_str} Live 配置快照失败: {e}");
+        }
+    }
+
+    /// 列出指定应用的 Live 配置快照（最新在前）
+    pub async fn list_live_snapshots(&self, app_type: &str) -> Result<Vec<LiveSnapshot>, String> {
+        let app = AppType::from_str(app_type).map_err(|e| format!("无效的应用类型: {e}"))?;
+        self.db
+            .list_live_snapshots(app.as_str())
+            .await
+            .map_err(|e| format!("读取 {app_type} Live 快照失败: {e}"))
+    }
+
+    async fn require_live_snapshot(
+        &self,
+        app: &AppType,
+        snapshot_id: Option<i64>,
+    ) -> Result<(LiveSnapshot, Value), String> {
+        let app_type_str = app.as_str();
+        let snapshot = self
+            .db
+            .get_live_snapshot(app_type_str, snapshot_id)
+            .await
+            .map_err(|e| format!("读取 {app_type_str} Live 快照失败: {e}"))?
+            .ok_or_else(|| match snapshot_id {
+                Some(id) => format!("{app_type_str} 不存在快照 #{id}"),
+                None => format!("{app_type_str} 暂无 Live 快照（接管后才会生成）"),
+            })?;
+        let config: Value = serde_json::from_str(&snapshot.config)
+            .map_err(|e| format!("解析 {app_type_str} 快照 #{} 失败: {e}", snapshot.id))?;
+        Ok((snapshot, config))
+    }
+
+    /// 快照与当前 Live 配置之间的 unified diff（`snapshot_id` 为 None 时取最新快照）
+    ///
+    /// 返回所用快照与 diff 文本（无差异时为空字符串）。
+    pub async fn diff_live_snapshot(
+        &self,
+        app_type: &str,
+        snapshot_id: Option<i64>,
+    ) -> Result<(LiveSnapshot, String), String> {
+        let app = AppType::from_str(app_type).map_err(|e| format!("无效的应用类型: {e}"))?;
+        let (snapshot, before) = self.require_live_snapshot(&app, snapshot_id).await?;
+        let current = self.read_live_config_for_app(&app)?;
+
+        let diff = diff_live_configs(
+            &app,
+            &before,
+            &current,
+            &format!("快照 #{}", snapshot.id),
+            "当前",
+        );
+        Ok((snapshot, diff))
+    }
+
+    /// 将 Live 配置回滚到指定快照（`snapshot_id` 为 None 时取最新快照）
+    ///
+    /// 回滚前先为当前 Live 保存一份快照，回滚本身也可以再撤销。
+    pub async fn restore_live_snapshot(
+        &self,
+        app_type: &str,
+        snapshot_id: Option<i64>,
+    ) -> Result<LiveSnapshot, String> {
+        let app = AppType::from_str(app_type).map_err(|e| format!("无效的应用类型: {e}"))?;
+        let (snapshot, config) = self.require_live_snapshot(&app, snapshot_id).await?;
+
+        if let Ok(current) = self.read_live_config_for_app(&app) {
+            self.snapshot_live_config(&app, &current).await;
+        }
+        self.write_live_config_for_app(&app, &config)?;
+
+        log::info!("{} Live 配置已回滚到快照 #{}", app.as_str(), snapshot.id);
+        Ok(snapshot)
+    }
+
+    // ==================== Live 配置守护 ====================
+
+    /// 开始守护指定应用的 live 配置（守护方式为 off 时跳过，失败仅记录警告）
+    async fn watch_live_config(&self, app_type: &AppType) {
+        if crate::settings::get_live_guard_mode(app_type) == LiveGuardMode::Off {
+            return;
+        }
+
+        let mut guard = self.live_guard.lock().await;
+        if guard.is_none() {
+            let service = self.clone();
+            let started = LiveGuard::start(move |app_type| {
+                let service = service.clone();
+                async move {
+                    service.handle_external_live_change(&app_type).await;
+                }
+            });
+            match started {
+                Ok(live_guard) => *guard = Some(live_guard),
+                Err(e) => {
+                    log::warn!("启动 Live 配置守护失败: {e}");
+                    return;
+                }
+            }
+        }
+
+        if let Some(live_guard) = guard.as_mut() {
+            match live_guard.watch(app_type, &live_config_paths(app_type)) {
+                Ok(()) => log::info!("已开始守护 {} Live 配置", app_type.as_str()),
+                Err(e) => log::warn!("守护 {} Live 配置失败: {e}", app_type.as_str()),
+            }
+        }
+    }
+
+    /// 停止守护指定应用的 live 配置（没有其它被守护的应用时释放监听）
+    async fn unwatch_live_config(&self, app_type: &AppType) {
+        let mut guard = self.live_guard.lock().await;
+        if let Some(live_guard) = guard.as_mut() {
+            live_guard.unwatch(app_type);
+            if live_guard.is_empty() {
+                *guard = None;
+            }
+        }
+    }
+
+    /// 处理接管中 live 配置的文件改动
+    ///
+    /// 仍指向本地代理时忽略（包括接管自身的写入）；否则按守护方式重新接管或仅告警。
+    pub(crate) async fn handle_external_live_change(
+        &self,
+        app_type: &AppType,
+    ) -> Option<LiveGuardEvent> {
+        let mode = crate::settings::get_live_guard_mode(app_type);
+        if mode == LiveGuardMode::Off {
+            return None;
+        }
+
+        let (proxy_url, proxy_codex_base_url) = self.build_proxy_urls().await.ok()?;
+        if let Ok(config) = self.read_live_config_for_app(app_type) {
+            if Self::live_points_to_proxy(app_type, &config, &proxy_url, &proxy_codex_base_url) {
+                return None;
+            }
+        }
+
+        let (action, error) = match mode {
+            LiveGuardMode::Reassert => match self.takeover_live_config_strict(app_type).await {
+                Ok(()) => (LiveGuardAction::Reasserted, None),
+                Err(e) => (LiveGuardAction::Failed, Some(e)),
+            },
+            LiveGuardMode::Alert | LiveGuardMode::Off => (LiveGuardAction::Alerted, None),
+        };
+        let event = LiveGuardEvent {
+            app_type: app_type.as_str().to_string(),
+            action,
+            at: chrono::Utc::now().to_rfc3339(),
+            error,
+        };
+        self.record_live_guard_event(&event).await;
+        Some(event)
+    }
+
+    /// live 配置中的请求地址是否仍指向本地代理
+    fn live_points_to_proxy(
+        app_type: &AppType,
+        config: &Value,
+        proxy_url: &str,
+        proxy_codex_base_url: &str,
+    ) -> bool {
+        let env_value = |key: &str| {
+            config
+                .get("env")
+                .and_then(|env| env.get(key))
+                .and_then(|v| v.as_str())
+                .map(|v| v.trim_end_matches('/').to_string())
+        };
+
+        match app_type {
+            AppType::Claude => env_value("ANTHROPIC_BASE_URL").as_deref() == Some(proxy_url),
+            AppType::Gemini => env_value("GOOGLE_GEMINI_BASE_URL").as_deref() == Some(proxy_url),
+            AppType::Codex => {
+                let Some(doc) = config
+                    .get("config")
+                    .and_then(|v| v.as_str())
+                    .and_then(|s| s.parse::<toml::Table>().ok())
+                else {
+                    return false;
+                };
+                // 与 update_toml_base_url 的写入位置保持一致
+                let base_url = doc
+                    .get("model_provider")
+                    .and_then(|v| v.as_str())
+                    .and_then(|key| doc.get("model_providers")?.get(key)?.get("base_url"))
+                    .or_else(|| doc.get("base_url"))
+                    .and_then(|v| v.as_str());
+                base_url.map(|url| url.trim_end_matches('/')) == Some(proxy_codex_base_url)
+            }
+        }
+    }
+
+    /// 记录守护事件：日志、状态统计、前端事件与通知
+    async fn record_live_guard_event(&self, event: &LiveGuardEvent) {
+        let app_type_str = event.app_type.as_str();
+        let detail = match event.action {
+            LiveGuardAction::Reasserted => {
+                log::warn!("{app_type_str} Live 配置被外部改写，已重新接管");
+                "已重新接管".to_string()
+            }
+            LiveGuardAction::Alerted => {
+                log::warn!("{app_type_str} Live 配置被外部改写（仅告警，未改动文件）");
+                "未改动文件".to_string()
+            }
+            LiveGuardAction::Failed => {
+                let err = event.error.as_deref().unwrap_or_default();
+                log::error!("{app_type_str} Live 配置被外部改写，重新接管失败: {err}");
+                format!("重新接管失败: {err}")
+            }
+        };
+
+        {
+            let mut stats = self.live_guard_stats.write().await;
+            if event.action == LiveGuardAction::Reasserted {
+                stats.reassert_count += 1;
+            }
+            stats.last_event = Some(event.clone());
+        }
+
+        if let Some(app) = self.app_handle.read().await.as_ref() {
+            use tauri::Emitter;
+            if let Err(e) = app.emit("live-config-modified", event) {
+                log::error!("发射 Live 配置改写事件失败: {e}");
+            }
+        }
+
+        notifications::notify(
+            NotificationPayload::new(NotificationEvent::LiveConfigModified, app_type_str)
+                .with_error(detail),
+        );
+    }
+
     fn detect_takeover_in_live_config_for_app(&self, app_type: &AppType) -> bool {
+        self.live_config_taken_over(app_type).unwrap_or(false)
+    }
+
+    /// live 配置是否写成了代理占位符（配置文件不存在或读取失败时为 None）
+    pub fn live_config_taken_over(&self, app_type: &AppType) -> Option<bool> {
         match app_type {
-            AppType::Claude => match self.read_claude_live() {
-                Ok(config) => Self::is_claude_live_taken_over(&config),
-                Err(_) => false,
-            },
-            AppType::Codex => match self.read_codex_live() {
-                Ok(config) => Self::is_codex_live_taken_over(&config),
-                Err(_) => false,
-            },
-            AppType::Gemini => match self.read_gemini_live() {
-                Ok(config) => Self::is_gemini_live_taken_over(&config),
-                Err(_) => false,
-            },
+            AppType::Claude => self
+                .read_claude_live()
+                .ok()
+                .map(|config| Self::is_claude_live_taken_over(&config)),
+            AppType::Codex => self
+                .read_codex_live()
+                .ok()
+                .map(|config| Self::is_codex_live_taken_over(&config)),
+            AppType::Gemini => self
+                .read_gemini_live()
+                .ok()
+                .map(|config| Self::is_gemini_live_taken_over(&config)),
         }
     }
 
@@ -1406,6 +1788,10 @@ impl ProxyService {
             .set_current_provider(app_type_enum.as_str(), provider_id)
             .map_err(|e| format!("更新当前供应商失败: {e}"))?;
 
+        // 手动切换：取消待切回的首选供应商，避免覆盖用户意图
+        crate::settings::set_restore_primary(&app_type_enum, None)
+            .map_err(|e| format!("清除待切回供应商失败: {e}"))?;
+
         log::info!("代理模式：已切换 {app_type} 的目标供应商为 {provider_id}");
         Ok(())
     }
@@ -1467,7 +1853,8 @@ impl ProxyService {
 
     fn write_claude_live(&self, config: &Value) -> Result<(), String> {
         let path = get_claude_settings_path();
-        write_json_file(&path, config).map_err(|e| format!("写入 Claude 配置失败: {e}"))
+        write_json_file(&path, &claude_live_settings(config))
+            .map_err(|e| format!("写入 Claude 配置失败: {e}"))
     }
 
     fn read_codex_live(&self) -> Result<Value, String> {
@@ -1546,14 +1933,27 @@ impl ProxyService {
 
     /// 获取服务器状态
     pub async fn get_status(&self) -> Result<ProxyStatus, String> {
-        if let Some(server) = self.server.read().await.as_ref() {
-            Ok(server.get_status().await)
+        let mut status = if let Some(server) = self.server.read().await.as_ref() {
+            server.get_status().await
         } else {
             // 服务器未运行时返回默认状态
-            Ok(ProxyStatus {
+            ProxyStatus {
                 running: false,
                 ..Default::default()
-            })
+            }
+        };
+
+        let stats = self.live_guard_stats.read().await;
+        status.live_reassert_count = stats.reassert_count;
+        status.last_live_guard_event = stats.last_event.clone();
+        Ok(status)
+    }
+
+    /// 获取最近请求（服务器未运行时为空）
+    pub async fn get_recent_requests(&self) -> Vec<RecentRequest> {
+        match self.server.read().await.as_ref() {
+            Some(server) => server.get_recent_requests(),
+            None => Vec::new(),
         }
     }
 
@@ -1667,6 +2067,58 @@ impl ProxyService {
         Ok(())
     }
 
+    /// 单个供应商的熔断器状态（代理未运行或尚未创建熔断器时为 None）
+    pub async fn get_circuit_breaker_stats(
+        &self,
+        provider_id: &str,
+        app_type: &str,
+    ) -> Option<crate::proxy::CircuitBreakerStats> {
+        match self.server.read().await.as_ref() {
+            Some(server) => {
+                server
+                    .get_circuit_breaker_stats(provider_id, app_type)
+                    .await
+            }
+            None => None,
+        }
+    }
+
+    /// 该应用下所有供应商的熔断器状态（代理未运行时为空）
+    pub async fn get_all_circuit_breaker_stats(
+        &self,
+        app_type: &str,
+    ) -> Vec<crate::proxy::ProviderCircuitBreakerStats> {
+        match self.server.read().await.as_ref() {
+            Some(server) => server.get_all_circuit_breaker_stats(app_type).await,
+            None => Vec::new(),
+        }
+    }
+
+    /// 各应用的路由快照（代理未运行时为空）
+    pub async fn get_routing_snapshot(&self) -> Vec<crate::proxy::AppRoutingSnapshot> {
+        match self.server.read().await.as_ref() {
+            Some(server) => server.get_routing_snapshot().await,
+            None => Vec::new(),
+        }
+    }
+
+    /// 队列成员或层级调整后清除受影响 supplier 的选路缓存（代理未运行时无需处理）
+    pub async fn invalidate_suppliers(&self, app_type: &str, suppliers: &[String]) -> usize {
+        match self.server.read().await.as_ref() {
+            Some(server) => server.invalidate_suppliers(app_type, suppliers).await,
+            None => 0,
+        }
+    }
+
+    /// 保存供应商后预热模型列表（代理未运行时同样预热，缓存对本进程稍后启动的代理生效）
+    pub async fn warm_up_models(&self, app_type: &str, provider_id: &str) -> WarmupOutcome {
+        let warmup = match self.server.read().await.as_ref() {
+            Some(server) => server.model_warmup(),
+            None => ModelWarmup::standalone(self.db.clone()).await,
+        };
+        warmup.run(app_type, provider_id).await
+    }
+
     /// 重置指定 Provider 的熔断器
     ///
     /// 如果代理服务器正在运行，立即重置内存中的熔断器状态
@@ -1683,6 +2135,51 @@ impl ProxyService {
         }
         Ok(())
     }
+
+    /// 强制打开指定 Provider 的熔断器 `secs` 秒（熔断器仅存在于运行中的代理）
+    pub async fn force_open_provider_circuit_breaker(
+        &self,
+        app_type: &str,
+        provider_id: &str,
+        secs: u64,
+    ) -> Result<(), String> {
+        match self.server.read().await.as_ref() {
+            Some(server) => {
+                server
+                    .force_open_provider_circuit_breaker(app_type, provider_id, secs)
+                    .await;
+                Ok(())
+            }
+            None => Err("代理服务器未运行，无法强制熔断".to_string()),
+        }
+    }
+
+    /// 临时暂停故障转移 `secs` 秒（暂停状态仅存在于运行中的代理）
+    pub async fn pause_failover(&self, app_type: &str, secs: u64) -> Result<FailoverPause, String> {
+        match self.server.read().await.as_ref() {
+            Some(server) => Ok(server.pause_failover(app_type, secs)),
+            None => Err("代理服务器未运行，无法暂停故障转移".to_string()),
+        }
+    }
+
+    /// 提前恢复故障转移；返回此前是否处于暂停中（代理未运行时为 false）
+    pub async fn resume_failover(&self, app_type: &str) -> bool {
+        match self.server.read().await.as_ref() {
+            Some(server) => server.resume_failover(app_type),
+            None => false,
+        }
+    }
+
+    /// 暂停中的故障转移（同步读取：代理状态被占用写锁时返回空，供托盘菜单使用）
+    pub fn failover_pauses(&self) -> Vec<FailoverPause> {
+        match self.server.try_read() {
+            Ok(guard) => guard
+                .as_ref()
+                .map(ProxyServer::failover_pauses)
+                .unwrap_or_default(),
+            Err(_) => Vec::new(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -1903,4 +2400,280 @@ model = "gpt-5.1-codex"
             "should not add ANTHROPIC_AUTH_TOKEN when absent"
         );
     }
+
+    #[tokio::test]
+    #[serial]
+    async fn takeover_snapshot_supports_diff_and_restore() {
+        let _home = TempHome::new();
+        crate::settings::reload_settings().expect("reload settings");
+
+        let db = Arc::new(Database::memory().expect("init db"));
+        let service = ProxyService::new(db.clone());
+
+        let original = json!({
+            "env": {
+                "ANTHROPIC_BASE_URL": "https://relay.example.com",
+                "ANTHROPIC_AUTH_TOKEN": "sk-real"
+            },
+            "permissions": {"allow": ["Bash(ls:*)"]}
+        });
+        write_json_file(&get_claude_settings_path(), &original).expect("write fixture");
+
+        service
+            .takeover_live_config_strict(&AppType::Claude)
+            .await
+            .expect("takeover");
+
+        let snapshots = service.list_live_snapshots("claude").await.expect("list");
+        assert_eq!(snapshots.len(), 1, "takeover should snapshot the live file");
+        let takeover_snapshot = snapshots[0].id;
+
+        let (used, diff) = service
+            .diff_live_snapshot("claude", None)
+            .await
+            .expect("diff");
+        assert_eq!(used.id, takeover_snapshot);
+        assert!(diff.starts_with(&format!(
+            "--- settings.json (快照 #{takeover_snapshot})\n+++ settings.json (当前)\n"
+        )));
+        assert!(diff.contains("-    \"ANTHROPIC_AUTH_TOKEN\": \"sk-real\""));
+        assert!(diff.contains(&format!(
+            "+    \"ANTHROPIC_AUTH_TOKEN\": \"{PROXY_TOKEN_PLACEHOLDER}\""
+        )));
+        assert!(!diff.contains("-    \"permissions\""));
+
+        // 接管后 live 文件又被其他工具改动
+        let mut mutated = service.read_claude_live().expect("read live");
+        mutated["model"] = json!("opus");
+        write_json_file(&get_claude_settings_path(), &mutated).expect("mutate live");
+        let (_, diff) = service
+            .diff_live_snapshot("claude", Some(takeover_snapshot))
+            .await
+            .expect("diff after mutation");
+        assert!(diff.contains("+  \"model\": \"opus\""));
+
+        let restored = service
+            .restore_live_snapshot("claude", Some(takeover_snapshot))
+            .await
+            .expect("restore");
+        assert_eq!(restored.id, takeover_snapshot);
+        assert_eq!(service.read_claude_live().expect("read live"), original);
+
+        // 回滚前的状态也留有快照，可以再撤销
+        let snapshots = service.list_live_snapshots("claude").await.expect("list");
+        assert_eq!(snapshots.len(), 2);
+        service
+            .restore_live_snapshot("claude", Some(snapshots[0].id))
+            .await
+            .expect("undo restore");
+        assert_eq!(service.read_claude_live().expect("read live"), mutated);
+
+        let err = service
+            .diff_live_snapshot("codex", None)
+            .await
+            .expect_err("codex has no snapshot");
+        assert!(err.contains("暂无 Live 快照"), "{err}");
+    }
+
+    #[test]
+    fn live_points_to_proxy_checks_active_codex_provider() {
+        let proxy = "http://127.0.0.1:15721";
+        let codex_proxy = "http://127.0.0.1:15721/v1";
+        let codex = |toml: &str| json!({"auth": {}, "config": toml});
+
+        let taken_over = codex(
+            "model_provider = \"any\"\n\n[model_providers.any]\nbase_url = \"http://127.0.0.1:15721/v1\"\n",
+        );
+        assert!(ProxyService::live_points_to_proxy(
+            &AppType::Codex,
+            &taken_over,
+            proxy,
+            codex_proxy
+        ));
+
+        // 活动 provider 已切走，其他 provider 仍指向代理也不算
+        let switched = codex(
+            "model_provider = \"relay\"\n\n[model_providers.any]\nbase_url = \"http://127.0.0.1:15721/v1\"\n\n[model_providers.relay]\nbase_url = \"https://relay.example.com/v1\"\n",
+        );
+        assert!(!ProxyService::live_points_to_proxy(
+            &AppType::Codex,
+            &switched,
+            proxy,
+            codex_proxy
+        ));
+
+        assert!(ProxyService::live_points_to_proxy(
+            &AppType::Gemini,
+            &json!({"env": {"GOOGLE_GEMINI_BASE_URL": "http://127.0.0.1:15721/"}}),
+            proxy,
+            codex_proxy
+        ));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn apply_and_release_takeover_for_all_apps() {
+        use crate::codex_config::{get_codex_auth_path, get_codex_config_path};
+        use crate::gemini_config::get_gemini_env_path;
+
+        let _home = TempHome::new();
+        crate::settings::reload_settings().expect("reload settings");
+
+        let db = Arc::new(Database::memory().expect("init db"));
+        let service = ProxyService::new(db.clone());
+
+        write_json_file(
+            &get_claude_settings_path(),
+            &json!({"env": {"ANTHROPIC_BASE_URL": "https://api.example.com", "ANTHROPIC_AUTH_TOKEN": "sk-claude"}}),
+        )
+        .expect("write claude");
+        write_json_file(
+            &get_codex_auth_path(),
+            &json!({"OPENAI_API_KEY": "sk-codex"}),
+        )
+        .expect("write codex auth");
+        std::fs::write(
+            get_codex_config_path(),
+            "model_provider = \"any\"\n\n[model_providers.any]\nbase_url = \"https://relay.example.com/v1\"\n",
+        )
+        .expect("write codex config");
+        std::fs::create_dir_all(get_gemini_env_path().parent().unwrap()).expect("gemini dir");
+        std::fs::write(
+            get_gemini_env_path(),
+            "GEMINI_API_KEY=sk-gemini\nGOOGLE_GEMINI_BASE_URL=https://gemini.example.com\n",
+        )
+        .expect("write gemini");
+
+        for app in [AppType::Claude, AppType::Codex, AppType::Gemini] {
+            let original = service.read_live_config_for_app(&app).expect("read live");
+            let detail = service.get_takeover_detail(&app).await.expect("detail");
+            assert!(!detail.enabled && !detail.has_backup);
+            assert_eq!(detail.points_to_proxy, Some(false), "{app:?}");
+
+            assert!(service.apply_takeover_for_app(&app).await.expect("apply"));
+            assert!(!service
+                .apply_takeover_for_app(&app)
+                .await
+                .expect("idempotent"));
+            let detail = service.get_takeover_detail(&app).await.expect("detail");
+            assert!(detail.enabled && detail.has_backup);
+            assert_eq!(detail.points_to_proxy, Some(true), "{app:?}");
+            assert_eq!(service.live_config_taken_over(&app), Some(true));
+
+            assert!(service
+                .release_takeover_for_app(&app)
+                .await
+                .expect("release"));
+            assert!(!service
+                .release_takeover_for_app(&app)
+                .await
+                .expect("idempotent"));
+            assert_eq!(
+                service.read_live_config_for_app(&app).expect("read live"),
+                original,
+                "{app:?}"
+            );
+            let detail = service.get_takeover_detail(&app).await.expect("detail");
+            assert!(!detail.enabled && !detail.has_backup);
+        }
+        assert!(!db.is_live_takeover_active().await.expect("flag"));
+
+        // 接管后 live 配置被其它工具改写：开关仍开启，但不再指向代理
+        service
+            .apply_takeover_for_app(&AppType::Gemini)
+            .await
+            .expect("apply");
+        std::fs::write(
+            get_gemini_env_path(),
+            "GEMINI_API_KEY=sk-other\nGOOGLE_GEMINI_BASE_URL=https://other.example.com\n",
+        )
+        .expect("drift");
+        let detail = service
+            .get_takeover_detail(&AppType::Gemini)
+            .await
+            .expect("detail");
+        assert!(detail.enabled);
+        assert_eq!(detail.points_to_proxy, Some(false));
+        assert_eq!(detail.files, vec![get_gemini_env_path()]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[serial]
+    async fn live_guard_reasserts_external_changes_or_alerts() {
+        let _home = TempHome::new();
+        crate::settings::reload_settings().expect("reload settings");
+
+        let db = Arc::new(Database::memory().expect("init db"));
+        let service = ProxyService::new(db.clone());
+        let (proxy_url, _) = service.build_proxy_urls().await.expect("proxy urls");
+
+        write_json_file(
+            &get_claude_settings_path(),
+            &json!({"env": {"ANTHROPIC_BASE_URL": "https://relay.example.com"}}),
+        )
+        .expect("write fixture");
+        service
+            .takeover_live_config_strict(&AppType::Claude)
+            .await
+            .expect("takeover");
+        service.watch_live_config(&AppType::Claude).await;
+
+        // 其他工具把请求地址改回了中转站
+        let external = json!({
+            "env": {
+                "ANTHROPIC_BASE_URL": "https://other.example.com",
+                "ANTHROPIC_AUTH_TOKEN": "sk-other"
+            }
+        });
+        write_json_file(&get_claude_settings_path(), &external).expect("external write");
+
+        let mut reasserted = false;
+        for _ in 0..50 {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            let live = service.read_claude_live().expect("read live");
+            if live["env"]["ANTHROPIC_BASE_URL"] == json!(proxy_url) {
+                assert_eq!(
+                    live["env"]["ANTHROPIC_AUTH_TOKEN"],
+                    json!(PROXY_TOKEN_PLACEHOLDER)
+                );
+                reasserted = true;
+                break;
+            }
+        }
+        assert!(reasserted, "guard should re-apply the takeover");
+
+        let status = service.get_status().await.expect("status");
+        assert_eq!(status.live_reassert_count, 1);
+        let event = status.last_live_guard_event.expect("guard event");
+        assert_eq!(event.app_type, "claude");
+        assert_eq!(event.action, LiveGuardAction::Reasserted);
+
+        // 被覆盖的外部改动留有快照
+        let snapshots = service.list_live_snapshots("claude").await.expect("list");
+        let latest: Value = serde_json::from_str(&snapshots[0].config).expect("snapshot json");
+        assert_eq!(latest, external);
+
+        // 停止守护后不再处理
+        service.unwatch_live_config(&AppType::Claude).await;
+        assert!(service.live_guard.lock().await.is_none());
+
+        // 仅告警：不改动文件
+        let mut settings = crate::settings::get_settings();
+        settings.live_guard_claude = Some(LiveGuardMode::Alert);
+        crate::settings::update_settings(settings).expect("update settings");
+        write_json_file(&get_claude_settings_path(), &external).expect("external write");
+
+        let event = service
+            .handle_external_live_change(&AppType::Claude)
+            .await
+            .expect("alert event");
+        assert_eq!(event.action, LiveGuardAction::Alerted);
+        assert_eq!(service.read_claude_live().expect("read live"), external);
+        let status = service.get_status().await.expect("status");
+        assert_eq!(status.live_reassert_count, 1);
+        assert_eq!(
+            status.last_live_guard_event.map(|e| e.action),
+            Some(LiveGuardAction::Alerted)
+        );
+    }
 }
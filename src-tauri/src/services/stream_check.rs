@@ -12,7 +12,9 @@ use std::time::{Duration, Instant};
 use crate::app_config::AppType;
 use crate::error::AppError;
 use crate::provider::Provider;
-use crate::proxy::providers::{get_adapter, AuthInfo};
+use crate::proxy::body_transforms::BodyTransforms;
+use crate::proxy::env_expand::expand_provider;
+use crate::proxy::providers::{get_adapter, AuthInfo, AuthStrategy, AzureConfig};
 
 /// 健康状态枚举
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -127,6 +129,8 @@ impl StreamCheckService {
     ) -> Result<StreamCheckResult, AppError> {
         let start = Instant::now();
         let adapter = get_adapter(app_type);
+        let expanded = expand_provider(provider).map_err(|e| AppError::Message(e.to_string()))?;
+        let provider: &Provider = &expanded;
 
         let base_url = adapter
             .extract_base_url(provider)
@@ -144,15 +148,29 @@ impl StreamCheckService {
 
         let model_to_test = Self::resolve_test_model(app_type, provider, config);
 
+        // 与转发一致应用供应商的 bodyTransforms
+        let transforms = BodyTransforms::from_provider(provider).unwrap_or_default();
+
         let result = match app_type {
             AppType::Claude => {
-                Self::check_claude_stream(&client, &base_url, &auth, &model_to_test).await
+                Self::check_claude_stream(&client, &base_url, &auth, &model_to_test, &transforms)
+                    .await
             }
             AppType::Codex => {
-                Self::check_codex_stream(&client, &base_url, &auth, &model_to_test).await
+                let azure = AzureConfig::from_provider(provider);
+                Self::check_codex_stream(
+                    &client,
+                    &base_url,
+                    &auth,
+                    &model_to_test,
+                    azure.as_ref(),
+                    &transforms,
+                )
+                .await
             }
             AppType::Gemini => {
-                Self::check_gemini_stream(&client, &base_url, &auth, &model_to_test).await
+                Self::check_gemini_stream(&client, &base_url, &auth, &model_to_test, &transforms)
+                    .await
             }
         };
 
@@ -193,6 +211,7 @@ impl StreamCheckService {
         base_url: &str,
         auth: &AuthInfo,
         model: &str,
+        transforms: &BodyTransforms,
     ) -> Result<(u16, String), AppError> {
         let base = base_url.trim_end_matches('/');
         let url = if base.ends_with("/v1") {
@@ -201,16 +220,24 @@ impl StreamCheckService {
             format!("{base}/v1/messages")
         };
 
-        let body = json!({
+        let mut body = json!({
             "model": model,
             "max_tokens": 1,
             "messages": [{ "role": "user", "content": "hi" }],
             "stream": true
         });
+        transforms.apply(&mut body);
+
+        // 显式配置了 settings_config.auth 时按配置发送认证头
+        let (auth_header, auth_value) = auth
+            .scheme
+            .as_ref()
+            .map(|scheme| scheme.header(&auth.api_key))
+            .unwrap_or_else(|| ("x-api-key".to_string(), auth.api_key.clone()));
 
         let response = client
             .post(&url)
-            .header("x-api-key", &auth.api_key)
+            .header(auth_header, auth_value)
             .header("anthropic-version", "2023-06-01")
             .header("Content-Type", "application/json")
             .json(&body)
@@ -243,17 +270,26 @@ impl StreamCheckService {
         base_url: &str,
         auth: &AuthInfo,
         model: &str,
+        azure: Option<&AzureConfig>,
+        transforms: &BodyTransforms,
     ) -> Result<(u16, String), AppError> {
+        // 解析模型名和推理等级 (支持 model@level 或 model#level 格式)
+        let (actual_model, reasoning_effort) = Self::parse_model_with_effort(model);
+
         let base = base_url.trim_end_matches('/');
-        let url = if base.ends_with("/v1") {
+        let url = if let Some(azure) = azure {
+            // Azure OpenAI：与转发一致按部署构建 URL，模型不在映射中时使用首个部署
+            let deployment = azure
+                .deployment_for(&actual_model)
+                .or_else(|| azure.first_deployment())
+                .unwrap_or_default();
+            azure.build_url(base, "/v1/chat/completions", deployment)
+        } else if base.ends_with("/v1") {
             format!("{base}/chat/completions")
         } else {
             format!("{base}/v1/chat/completions")
         };
 
-        // 解析模型名和推理等级 (支持 model@level 或 model#level 格式)
-        let (actual_model, reasoning_effort) = Self::parse_model_with_effort(model);
-
         let mut body = json!({
             "model": actual_model,
             "messages": [
@@ -270,10 +306,15 @@ impl StreamCheckService {
         if let Some(effort) = reasoning_effort {
             body["reasoning_effort"] = json!(effort);
         }
+        transforms.apply(&mut body);
 
-        let response = client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", auth.api_key))
+        let request = match auth.strategy {
+            AuthStrategy::AzureApiKey => client.post(&url).header("api-key", &auth.api_key),
+            _ => client
+                .post(&url)
+                .header("Authorization", format!("Bearer {}", auth.api_key)),
+        };
+        let response = request
             .header("Content-Type", "application/json")
             .json(&body)
             .send()
@@ -304,17 +345,19 @@ impl StreamCheckService {
         base_url: &str,
         auth: &AuthInfo,
         model: &str,
+        transforms: &BodyTransforms,
     ) -> Result<(u16, String), AppError> {
         let base = base_url.trim_end_matches('/');
         let url = format!("{base}/v1/chat/completions");
 
-        let body = json!({
+        let mut body = json!({
             "model": model,
             "messages": [{ "role": "user", "content": "hi" }],
             "max_tokens": 1,
             "temperature": 0,
             "stream": true
         });
+        transforms.apply(&mut body);
 
         let response = client
             .post(&url)
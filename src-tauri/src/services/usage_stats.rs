@@ -49,6 +49,9 @@ pub struct ProviderStats {
     pub total_cost: String,
     pub success_rate: f32,
     pub avg_latency_ms: u64,
+    /// 请求体 / 响应体字节数（流量计量）
+    pub request_bytes: u64,
+    pub response_bytes: u64,
 }
 
 /// 模型统计
@@ -107,6 +110,11 @@ pub struct RequestLogDetail {
     pub latency_ms: u64,
     pub first_token_ms: Option<u64>,
     pub duration_ms: Option<u64>,
+    /// Python 代理回传的真实上游耗时（仅 Claude；本地一跳 = latency_ms - upstream_ms）
+    pub upstream_ms: Option<u64>,
+    /// 请求体 / 响应体字节数（流式响应为转发分块之和）
+    pub request_bytes: u64,
+    pub response_bytes: u64,
     pub status_code: u16,
     pub error_message: Option<String>,
     pub created_at: i64,
@@ -121,23 +129,18 @@ impl Database {
     ) -> Result<UsageSummary, AppError> {
         let conn = lock_conn!(self.conn);
 
-        let (where_clause, params_vec) = if start_date.is_some() || end_date.is_some() {
-            let mut conditions = Vec::new();
-            let mut params = Vec::new();
-
-            if let Some(start) = start_date {
-                conditions.push("created_at >= ?");
-                params.push(start);
-            }
-            if let Some(end) = end_date {
-                conditions.push("created_at <= ?");
-                params.push(end);
-            }
-
-            (format!("WHERE {}", conditions.join(" AND ")), params)
-        } else {
-            (String::new(), Vec::new())
-        };
+        // 影子流量只用于评估，不计入使用量统计
+        let mut conditions = vec!["shadow = 0"];
+        let mut params_vec = Vec::new();
+        if let Some(start) = start_date {
+            conditions.push("created_at >= ?");
+            params_vec.push(start);
+        }
+        if let Some(end) = end_date {
+            conditions.push("created_at <= ?");
+            params_vec.push(end);
+        }
+        let where_clause = format!("WHERE {}", conditions.join(" AND "));
 
         let sql = format!(
             "SELECT 
@@ -196,7 +199,7 @@ impl Database {
                     COALESCE(SUM(cache_creation_tokens), 0) as total_cache_creation_tokens,
                     COALESCE(SUM(cache_read_tokens), 0) as total_cache_read_tokens
                  FROM proxy_request_logs
-                 WHERE created_at >= strftime('%s', 'now', '-1 day')
+                 WHERE created_at >= strftime('%s', 'now', '-1 day') AND shadow = 0
                  GROUP BY bucket
                  ORDER BY bucket ASC";
 
@@ -256,7 +259,7 @@ impl Database {
                     COALESCE(SUM(cache_creation_tokens), 0) as total_cache_creation_tokens,
                     COALESCE(SUM(cache_read_tokens), 0) as total_cache_read_tokens
                  FROM proxy_request_logs
-                 WHERE created_at >= strftime('%s', 'now', ?)
+                 WHERE created_at >= strftime('%s', 'now', ?) AND shadow = 0
                  GROUP BY bucket
                  ORDER BY bucket ASC";
 
@@ -317,9 +320,12 @@ impl Database {
                 COALESCE(SUM(l.input_tokens + l.output_tokens), 0) as total_tokens,
                 COALESCE(SUM(CAST(l.total_cost_usd AS REAL)), 0) as total_cost,
                 COALESCE(SUM(CASE WHEN l.status_code >= 200 AND l.status_code < 300 THEN 1 ELSE 0 END), 0) as success_count,
-                COALESCE(AVG(l.latency_ms), 0) as avg_latency
+                COALESCE(AVG(l.latency_ms), 0) as avg_latency,
+                COALESCE(SUM(l.request_bytes), 0) as request_bytes,
+                COALESCE(SUM(l.response_bytes), 0) as response_bytes
              FROM proxy_request_logs l
              LEFT JOIN providers p ON l.provider_id = p.id AND l.app_type = p.app_type
+             WHERE l.shadow = 0
              GROUP BY l.provider_id, l.app_type
              ORDER BY total_cost DESC";
 
@@ -343,6 +349,8 @@ impl Database {
                 total_cost: format!("{:.6}", row.get::<_, f64>(4)?),
                 success_rate,
                 avg_latency_ms: row.get::<_, f64>(6)? as u64,
+                request_bytes: row.get::<_, i64>(7)?.max(0) as u64,
+                response_bytes: row.get::<_, i64>(8)?.max(0) as u64,
             })
         })?;
 
@@ -364,6 +372,7 @@ impl Database {
                 COALESCE(SUM(input_tokens + output_tokens), 0) as total_tokens,
                 COALESCE(SUM(CAST(total_cost_usd AS REAL)), 0) as total_cost
              FROM proxy_request_logs
+             WHERE shadow = 0
              GROUP BY model
              ORDER BY total_cost DESC";
 
@@ -403,7 +412,7 @@ impl Database {
     ) -> Result<PaginatedLogs, AppError> {
         let conn = lock_conn!(self.conn);
 
-        let mut conditions = Vec::new();
+        let mut conditions = vec!["l.shadow = 0"];
         let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
 
         if let Some(ref app_type) = filters.app_type {
@@ -431,11 +440,7 @@ impl Database {
             params.push(Box::new(end));
         }
 
-        let where_clause = if conditions.is_empty() {
-            String::new()
-        } else {
-            format!("WHERE {}", conditions.join(" AND "))
-        };
+        let where_clause = format!("WHERE {}", conditions.join(" AND "));
 
         // 获取总数
         let count_sql = format!(
@@ -458,7 +463,8 @@ impl Database {
                     l.input_tokens, l.output_tokens, l.cache_read_tokens, l.cache_creation_tokens,
                     l.input_cost_usd, l.output_cost_usd, l.cache_read_cost_usd, l.cache_creation_cost_usd, l.total_cost_usd,
                     l.is_streaming, l.latency_ms, l.first_token_ms, l.duration_ms,
-                    l.status_code, l.error_message, l.created_at
+                    l.status_code, l.error_message, l.created_at, l.upstream_ms,
+                    l.request_bytes, l.response_bytes
              FROM proxy_request_logs l
              LEFT JOIN providers p ON l.provider_id = p.id AND l.app_type = p.app_type
              {where_clause}
@@ -488,6 +494,9 @@ impl Database {
                 latency_ms: row.get::<_, i64>(15)? as u64,
                 first_token_ms: row.get::<_, Option<i64>>(16)?.map(|v| v as u64),
                 duration_ms: row.get::<_, Option<i64>>(17)?.map(|v| v as u64),
+                upstream_ms: row.get::<_, Option<i64>>(21)?.map(|v| v as u64),
+                request_bytes: row.get::<_, i64>(22)?.max(0) as u64,
+                response_bytes: row.get::<_, i64>(23)?.max(0) as u64,
                 status_code: row.get::<_, i64>(18)? as u16,
                 error_message: row.get(19)?,
                 created_at: row.get(20)?,
@@ -529,7 +538,8 @@ impl Database {
                     input_tokens, output_tokens, cache_read_tokens, cache_creation_tokens,
                     input_cost_usd, output_cost_usd, cache_read_cost_usd, cache_creation_cost_usd, total_cost_usd,
                     is_streaming, latency_ms, first_token_ms, duration_ms,
-                    status_code, error_message, created_at
+                    status_code, error_message, created_at, upstream_ms,
+                    request_bytes, response_bytes
              FROM proxy_request_logs l
              LEFT JOIN providers p ON l.provider_id = p.id AND l.app_type = p.app_type
              WHERE l.request_id = ?",
@@ -554,6 +564,9 @@ impl Database {
                     latency_ms: row.get::<_, i64>(15)? as u64,
                     first_token_ms: row.get::<_, Option<i64>>(16)?.map(|v| v as u64),
                     duration_ms: row.get::<_, Option<i64>>(17)?.map(|v| v as u64),
+                    upstream_ms: row.get::<_, Option<i64>>(21)?.map(|v| v as u64),
+                    request_bytes: row.get::<_, i64>(22)?.max(0) as u64,
+                    response_bytes: row.get::<_, i64>(23)?.max(0) as u64,
                     status_code: row.get::<_, i64>(18)? as u16,
                     error_message: row.get(19)?,
                     created_at: row.get(20)?,
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::PathBuf;
 use std::sync::{OnceLock, RwLock};
@@ -39,6 +40,9 @@ pub struct AppSettings {
     pub launch_on_startup: bool,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub language: Option<String>,
+    /// 运行日志与 CLI 输出语言（"zh" / "en"，可被 CC_SWITCH_LANG 覆盖）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_language: Option<String>,
 
     // ===== 设备级目录覆盖 =====
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -58,6 +62,159 @@ pub struct AppSettings {
     /// 当前 Gemini 供应商 ID（本地存储，优先于数据库 is_current）
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub current_provider_gemini: Option<String>,
+
+    // ===== 故障转移切换防抖（设备级）=====
+    /// 同一供应商连续胜出多少次后才持久化切换（默认 3）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failover_switch_min_wins: Option<u32>,
+    /// 两次持久化切换之间的最短驻留时间，单位秒（默认 60）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failover_switch_dwell_secs: Option<u64>,
+
+    // ===== 故障恢复后自动切回首选供应商（设备级）=====
+    /// Claude：首选供应商恢复后自动切回
+    #[serde(default)]
+    pub auto_restore_primary_claude: bool,
+    /// Codex：首选供应商恢复后自动切回
+    #[serde(default)]
+    pub auto_restore_primary_codex: bool,
+    /// Gemini：首选供应商恢复后自动切回
+    #[serde(default)]
+    pub auto_restore_primary_gemini: bool,
+    /// Claude：首次自动切换前的当前供应商（待切回）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restore_primary_claude: Option<String>,
+    /// Codex：首次自动切换前的当前供应商（待切回）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restore_primary_codex: Option<String>,
+    /// Gemini：首次自动切换前的当前供应商（待切回）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restore_primary_gemini: Option<String>,
+
+    // ===== 故障通知（设备级）=====
+    /// Claude：故障转移/熔断事件通知
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notifications_claude: Option<NotificationSettings>,
+    /// Codex：故障转移/熔断事件通知
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notifications_codex: Option<NotificationSettings>,
+    /// Gemini：故障转移/熔断事件通知
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notifications_gemini: Option<NotificationSettings>,
+
+    // ===== 后台健康检查（设备级）=====
+    /// Claude：故障转移队列后台健康检查
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health_check_claude: Option<HealthCheckSettings>,
+    /// Codex：故障转移队列后台健康检查
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health_check_codex: Option<HealthCheckSettings>,
+    /// Gemini：故障转移队列后台健康检查
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health_check_gemini: Option<HealthCheckSettings>,
+
+    // ===== 测速/探测方式（设备级）=====
+    /// Claude：URL 测速与健康检查的探测方式
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub probe_mode_claude: Option<ProbeMode>,
+    /// Codex：URL 测速与健康检查的探测方式
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub probe_mode_codex: Option<ProbeMode>,
+    /// Gemini：URL 测速与健康检查的探测方式
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub probe_mode_gemini: Option<ProbeMode>,
+
+    // ===== Live 配置守护（设备级）=====
+    /// Claude：接管中的 live 配置被外部改写时的处理方式
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub live_guard_claude: Option<LiveGuardMode>,
+    /// Codex：接管中的 live 配置被外部改写时的处理方式
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub live_guard_codex: Option<LiveGuardMode>,
+    /// Gemini：接管中的 live 配置被外部改写时的处理方式
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub live_guard_gemini: Option<LiveGuardMode>,
+
+    // ===== 请求捕获（设备级，默认关闭）=====
+    /// Claude：保存脱敏/截断后的请求体，供 `csc replay` 重放
+    #[serde(default)]
+    pub capture_bodies_claude: bool,
+    /// Codex：保存脱敏/截断后的请求体，供 `csc replay` 重放
+    #[serde(default)]
+    pub capture_bodies_codex: bool,
+    /// Gemini：保存脱敏/截断后的请求体，供 `csc replay` 重放
+    #[serde(default)]
+    pub capture_bodies_gemini: bool,
+
+    // ===== 数据库维护（设备级）=====
+    /// 请求日志保留天数（`csc db maintain` 清理更早的日志，默认 30）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_log_retention_days: Option<u32>,
+
+    // ===== 上游错误响应（设备级）=====
+    /// 上游错误响应体最多读取的字节数（超出部分截断并注明总大小，默认 64KB）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_body_max_bytes: Option<usize>,
+
+    // ===== 启动一致性检查（设备级，默认关闭）=====
+    /// 代理启动时自动修复简单的不一致（如把当前供应商加入故障转移队列）
+    #[serde(default)]
+    pub auto_fix_consistency: bool,
+
+    // ===== 模型家族（设备级）=====
+    /// 自定义家族规则：模型名模式（子串，不区分大小写）→ 家族名，先于内置规则生效
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub model_family_overrides: BTreeMap<String, String>,
+}
+
+/// URL 探测方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProbeMode {
+    /// 发送一次真实问答请求（全链路，默认）
+    #[default]
+    Full,
+    /// 仅请求 `/v1/models`（校验 key，不产生调用费用）
+    Models,
+    /// 仅做连通性探测（HEAD base_url）
+    Connectivity,
+}
+
+/// 接管中的 live 配置被外部改写时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LiveGuardMode {
+    /// 重新写入接管配置（默认）
+    #[default]
+    Reassert,
+    /// 仅告警（日志、事件与通知），不改动文件
+    Alert,
+    /// 不监听
+    Off,
+}
+
+/// 单个应用的后台健康检查设置
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthCheckSettings {
+    /// 检查间隔（秒），0 表示关闭
+    #[serde(default)]
+    pub interval_secs: u64,
+    /// 零成本模式：仅做连通性探测，不发送问答请求
+    #[serde(default)]
+    pub zero_cost: bool,
+}
+
+/// 单个应用的故障通知设置
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationSettings {
+    /// Webhook 地址列表（POST JSON）
+    #[serde(default)]
+    pub webhooks: Vec<String>,
+    /// 是否发送系统桌面通知
+    #[serde(default)]
+    pub desktop: bool,
 }
 
 fn default_show_in_tray() -> bool {
@@ -81,12 +238,40 @@ impl Default for AppSettings {
             skip_claude_onboarding: true,
             launch_on_startup: false,
             language: None,
+            log_language: None,
             claude_config_dir: None,
             codex_config_dir: None,
             gemini_config_dir: None,
             current_provider_claude: None,
             current_provider_codex: None,
             current_provider_gemini: None,
+            failover_switch_min_wins: None,
+            failover_switch_dwell_secs: None,
+            auto_restore_primary_claude: false,
+            auto_restore_primary_codex: false,
+            auto_restore_primary_gemini: false,
+            restore_primary_claude: None,
+            restore_primary_codex: None,
+            restore_primary_gemini: None,
+            notifications_claude: None,
+            notifications_codex: None,
+            notifications_gemini: None,
+            health_check_claude: None,
+            health_check_codex: None,
+            health_check_gemini: None,
+            probe_mode_claude: None,
+            probe_mode_codex: None,
+            probe_mode_gemini: None,
+            live_guard_claude: None,
+            live_guard_codex: None,
+            live_guard_gemini: None,
+            capture_bodies_claude: false,
+            capture_bodies_codex: false,
+            capture_bodies_gemini: false,
+            request_log_retention_days: None,
+            error_body_max_bytes: None,
+            auto_fix_consistency: false,
+            model_family_overrides: BTreeMap::new(),
         }
     }
 }
@@ -128,6 +313,23 @@ impl AppSettings {
             .map(|s| s.trim())
             .filter(|s| matches!(*s, "en" | "zh" | "ja"))
             .map(|s| s.to_string());
+
+        self.log_language = self
+            .log_language
+            .as_ref()
+            .map(|s| s.trim())
+            .filter(|s| matches!(*s, "en" | "zh"))
+            .map(|s| s.to_string());
+
+        // 0 天会清空全部日志，视为未配置
+        self.request_log_retention_days = self.request_log_retention_days.filter(|d| *d > 0);
+        self.error_body_max_bytes = self.error_body_max_bytes.filter(|n| *n > 0);
+
+        self.model_family_overrides = std::mem::take(&mut self.model_family_overrides)
+            .into_iter()
+            .map(|(pattern, family)| (pattern.trim().to_lowercase(), family.trim().to_lowercase()))
+            .filter(|(pattern, family)| !pattern.is_empty() && !family.is_empty())
+            .collect();
     }
 
     fn load_from_file() -> Self {
@@ -238,6 +440,11 @@ pub fn get_gemini_override_dir() -> Option<PathBuf> {
         .map(|p| resolve_override_path(p))
 }
 
+/// 获取日志/CLI 输出语言设置（未设置时为 None）
+pub fn get_log_language() -> Option<String> {
+    settings_store().read().ok()?.log_language.clone()
+}
+
 // ===== 当前供应商管理函数 =====
 
 /// 获取指定应用类型的当前供应商 ID（从本地 settings 读取）
@@ -269,6 +476,163 @@ pub fn set_current_provider(app_type: &AppType, id: Option<&str>) -> Result<(),
     update_settings(settings)
 }
 
+/// 指定应用是否开启"首选供应商恢复后自动切回"
+pub fn get_auto_restore_primary(app_type: &AppType) -> bool {
+    let Ok(settings) = settings_store().read() else {
+        return false;
+    };
+    match app_type {
+        AppType::Claude => settings.auto_restore_primary_claude,
+        AppType::Codex => settings.auto_restore_primary_codex,
+        AppType::Gemini => settings.auto_restore_primary_gemini,
+    }
+}
+
+/// 获取待切回的首选供应商 ID（首次自动切换前的当前供应商）
+pub fn get_restore_primary(app_type: &AppType) -> Option<String> {
+    let settings = settings_store().read().ok()?;
+    match app_type {
+        AppType::Claude => settings.restore_primary_claude.clone(),
+        AppType::Codex => settings.restore_primary_codex.clone(),
+        AppType::Gemini => settings.restore_primary_gemini.clone(),
+    }
+}
+
+/// 设置待切回的首选供应商 ID（`None` 表示清除）
+pub fn set_restore_primary(app_type: &AppType, id: Option<&str>) -> Result<(), AppError> {
+    if get_restore_primary(app_type).as_deref() == id {
+        return Ok(());
+    }
+
+    let mut settings = get_settings();
+
+    match app_type {
+        AppType::Claude => settings.restore_primary_claude = id.map(|s| s.to_string()),
+        AppType::Codex => settings.restore_primary_codex = id.map(|s| s.to_string()),
+        AppType::Gemini => settings.restore_primary_gemini = id.map(|s| s.to_string()),
+    }
+
+    update_settings(settings)
+}
+
+/// 获取指定应用的故障通知设置（未配置时返回 None）
+pub fn get_notification_settings(app_type: &AppType) -> Option<NotificationSettings> {
+    let settings = settings_store().read().ok()?;
+    match app_type {
+        AppType::Claude => settings.notifications_claude.clone(),
+        AppType::Codex => settings.notifications_codex.clone(),
+        AppType::Gemini => settings.notifications_gemini.clone(),
+    }
+}
+
+/// 获取指定应用的后台健康检查设置（未配置时返回 None）
+pub fn get_health_check_settings(app_type: &AppType) -> Option<HealthCheckSettings> {
+    let settings = settings_store().read().ok()?;
+    match app_type {
+        AppType::Claude => settings.health_check_claude.clone(),
+        AppType::Codex => settings.health_check_codex.clone(),
+        AppType::Gemini => settings.health_check_gemini.clone(),
+    }
+}
+
+/// 获取指定应用的探测方式（未配置时为 `ProbeMode::Full`）
+pub fn get_probe_mode(app_type: &AppType) -> ProbeMode {
+    let Ok(settings) = settings_store().read() else {
+        return ProbeMode::default();
+    };
+    match app_type {
+        AppType::Claude => settings.probe_mode_claude,
+        AppType::Codex => settings.probe_mode_codex,
+        AppType::Gemini => settings.probe_mode_gemini,
+    }
+    .unwrap_or_default()
+}
+
+/// 获取指定应用的 live 配置守护方式（未配置时为 `LiveGuardMode::Reassert`）
+pub fn get_live_guard_mode(app_type: &AppType) -> LiveGuardMode {
+    let Ok(settings) = settings_store().read() else {
+        return LiveGuardMode::default();
+    };
+    match app_type {
+        AppType::Claude => settings.live_guard_claude,
+        AppType::Codex => settings.live_guard_codex,
+        AppType::Gemini => settings.live_guard_gemini,
+    }
+    .unwrap_or_default()
+}
+
+/// 指定应用是否开启请求捕获（未配置时关闭）
+pub fn get_capture_bodies(app_type: &AppType) -> bool {
+    let Ok(settings) = settings_store().read() else {
+        return false;
+    };
+    match app_type {
+        AppType::Claude => settings.capture_bodies_claude,
+        AppType::Codex => settings.capture_bodies_codex,
+        AppType::Gemini => settings.capture_bodies_gemini,
+    }
+}
+
+/// 请求日志默认保留天数
+pub const DEFAULT_REQUEST_LOG_RETENTION_DAYS: u32 = 30;
+
+/// 获取请求日志保留天数（未配置时为 30 天）
+pub fn get_request_log_retention_days() -> u32 {
+    settings_store()
+        .read()
+        .ok()
+        .and_then(|s| s.request_log_retention_days)
+        .unwrap_or(DEFAULT_REQUEST_LOG_RETENTION_DAYS)
+}
+
+/// 上游错误响应体默认最多读取的字节数
+pub const DEFAULT_ERROR_BODY_MAX_BYTES: usize = 64 * 1024;
+
+/// 获取上游错误响应体最多读取的字节数（未配置时为 64KB）
+pub fn get_error_body_max_bytes() -> usize {
+    settings_store()
+        .read()
+        .ok()
+        .and_then(|s| s.error_body_max_bytes)
+        .unwrap_or(DEFAULT_ERROR_BODY_MAX_BYTES)
+}
+
+/// 代理启动时是否自动修复简单的不一致（未配置时关闭）
+pub fn get_auto_fix_consistency() -> bool {
+    settings_store()
+        .read()
+        .map(|s| s.auto_fix_consistency)
+        .unwrap_or(false)
+}
+
+/// 自定义模型家族规则（模式 → 家族名）
+pub fn get_model_family_overrides() -> BTreeMap<String, String> {
+    settings_store()
+        .read()
+        .map(|s| s.model_family_overrides.clone())
+        .unwrap_or_default()
+}
+
+/// 添加或覆盖一条自定义模型家族规则
+pub fn set_model_family_override(pattern: &str, family: &str) -> Result<(), AppError> {
+    let mut settings = get_settings();
+    settings
+        .model_family_overrides
+        .insert(pattern.to_string(), family.to_string());
+    update_settings(settings)
+}
+
+/// 删除一条自定义模型家族规则，返回规则是否存在
+pub fn remove_model_family_override(pattern: &str) -> Result<bool, AppError> {
+    let mut settings = get_settings();
+    let pattern = pattern.trim().to_lowercase();
+    if settings.model_family_overrides.remove(&pattern).is_none() {
+        return Ok(false);
+    }
+    update_settings(settings)?;
+    Ok(true)
+}
+
 /// 获取有效的当前供应商 ID（验证存在性）
 ///
 /// 逻辑：
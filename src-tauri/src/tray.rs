@@ -14,6 +14,8 @@ use crate::store::AppState;
 pub struct TrayTexts {
     pub show_main: &'static str,
     pub no_provider_hint: &'static str,
+    /// 故障转移暂停提示（`{minutes}` 替换为剩余分钟数）
+    pub failover_paused: &'static str,
     pub quit: &'static str,
 }
 
@@ -23,17 +25,20 @@ impl TrayTexts {
             "en" => Self {
                 show_main: "Open main window",
                 no_provider_hint: "  (No providers yet, please add them from the main window)",
+                failover_paused: "  ⏸ Failover paused ({minutes} min left)",
                 quit: "Quit",
             },
             "ja" => Self {
                 show_main: "メインウィンドウを開く",
                 no_provider_hint:
                     "  (プロバイダーがまだありません。メイン画面から追加してください)",
+                failover_paused: "  ⏸ フェイルオーバー一時停止中（残り {minutes} 分）",
                 quit: "終了",
             },
             _ => Self {
                 show_main: "打开主界面",
                 no_provider_hint: "  (无供应商，请在主界面添加)",
+                failover_paused: "  ⏸ 故障转移已暂停（剩余 {minutes} 分钟）",
                 quit: "退出",
             },
         }
@@ -182,6 +187,9 @@ pub fn create_tray_menu(
             .map_err(|e| AppError::Message(format!("创建打开主界面菜单失败: {e}")))?;
     menu_builder = menu_builder.item(&show_main_item).separator();
 
+    // 临时暂停的故障转移（代理未运行时为空）
+    let failover_pauses = app_state.proxy_service.failover_pauses();
+
     // 直接添加所有供应商到主菜单（扁平化结构，更简单可靠）
     for section in TRAY_SECTIONS.iter() {
         let app_type_str = section.app_type.as_str();
@@ -199,6 +207,19 @@ pub fn create_tray_menu(
 
         menu_builder =
             append_provider_section(app, menu_builder, Some(&manager), section, &tray_texts)?;
+
+        if let Some(pause) = failover_pauses.iter().find(|p| p.app_type == app_type_str) {
+            let minutes = pause.remaining_secs.div_ceil(60).to_string();
+            let paused_item = MenuItem::with_id(
+                app,
+                format!("failover_paused_{app_type_str}"),
+                tray_texts.failover_paused.replace("{minutes}", &minutes),
+                false,
+                None::<&str>,
+            )
+            .map_err(|e| AppError::Message(format!("创建{}暂停提示失败: {e}", section.log_name)))?;
+            menu_builder = menu_builder.item(&paused_item);
+        }
     }
 
     // 分隔符和退出菜单
@@ -264,6 +285,53 @@ pub fn handle_tray_menu_event(app: &tauri::AppHandle, event_id: &str) {
     }
 }
 
+/// 重建托盘菜单（代理状态变化后调用）
+pub fn refresh_tray_menu(app: &tauri::AppHandle) {
+    let Some(app_state) = app.try_state::<AppState>() else {
+        return;
+    };
+    match create_tray_menu(app, app_state.inner()) {
+        Ok(new_menu) => {
+            if let Some(tray) = app.tray_by_id("main") {
+                if let Err(e) = tray.set_menu(Some(new_menu)) {
+                    log::error!("更新托盘菜单失败: {e}");
+                }
+            }
+        }
+        Err(e) => log::error!("创建托盘菜单失败: {e}"),
+    }
+}
+
+/// 托盘暂停提示的轮询间隔
+const FAILOVER_PAUSE_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15);
+
+/// 定期检查故障转移暂停状态，变化时刷新托盘菜单
+///
+/// 暂停可能来自 CLI / HTTP 接口，也会自动到期，不一定经过 Tauri 命令；
+/// 剩余分钟数变化时同样刷新，保持提示准确。
+pub fn spawn_failover_pause_watcher(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut last: Vec<(String, u64)> = Vec::new();
+        let mut interval = tokio::time::interval(FAILOVER_PAUSE_POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            let Some(app_state) = app.try_state::<AppState>() else {
+                continue;
+            };
+            let current: Vec<(String, u64)> = app_state
+                .proxy_service
+                .failover_pauses()
+                .into_iter()
+                .map(|p| (p.app_type, p.remaining_secs.div_ceil(60)))
+                .collect();
+            if current != last {
+                refresh_tray_menu(&app);
+                last = current;
+            }
+        }
+    });
+}
+
 /// 内部切换供应商函数
 pub fn switch_provider_internal(
     app: &tauri::AppHandle,
@@ -275,8 +343,7 @@ pub fn switch_provider_internal(
         let app_type_str = app_type.as_str().to_string();
         let provider_id_clone = provider_id.clone();
 
-        crate::commands::switch_provider(app_state.clone(), app_type_str.clone(), provider_id)
-            .map_err(AppError::Message)?;
+        crate::services::ProviderService::switch(app_state.inner(), app_type, &provider_id)?;
 
         // 切换成功后重新创建托盘菜单
         if let Ok(new_menu) = create_tray_menu(app, app_state.inner()) {
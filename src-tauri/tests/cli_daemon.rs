@@ -0,0 +1,91 @@
+#![cfg(unix)]
+
+use std::net::TcpListener;
+use std::path::Path;
+use std::process::{Command, Output};
+use std::time::{Duration, Instant};
+
+use nix::sys::signal::kill;
+use nix::unistd::Pid;
+
+fn csc(home: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_csc"))
+        .args(args)
+        .env("HOME", home)
+        .env_remove("CC_SWITCH_LISTEN_ADDRESS")
+        .env_remove("CC_SWITCH_LISTEN_PORT")
+        .output()
+        .expect("run csc")
+}
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .and_then(|l| l.local_addr())
+        .map(|addr| addr.port())
+        .expect("bind ephemeral port")
+}
+
+fn is_alive(pid: i32) -> bool {
+    kill(Pid::from_raw(pid), None).is_ok()
+}
+
+#[test]
+fn daemon_start_status_stop() {
+    let home = tempfile::tempdir().expect("temp home");
+    let port = free_port().to_string();
+
+    let start = csc(
+        home.path(),
+        &[
+            "proxy",
+            "start",
+            "--daemon",
+            "--address",
+            "127.0.0.1",
+            "--port",
+            &port,
+        ],
+    );
+    assert!(
+        start.status.success(),
+        "start failed: {}",
+        String::from_utf8_lossy(&start.stderr)
+    );
+
+    let pid_file = home.path().join(".cc-switch/proxy.pid");
+    let pid: i32 = std::fs::read_to_string(&pid_file)
+        .expect("pid file")
+        .trim()
+        .parse()
+        .expect("pid");
+    assert!(is_alive(pid));
+    assert!(home.path().join(".cc-switch/logs/rust_proxy.log").exists());
+
+    // 端口覆盖生效：守护进程在指定端口监听
+    assert!(std::net::TcpStream::connect(format!("127.0.0.1:{port}")).is_ok());
+
+    let status = csc(home.path(), &["proxy", "status"]);
+    let stdout = String::from_utf8_lossy(&status.stdout);
+    assert!(stdout.contains("运行中"), "unexpected status: {stdout}");
+
+    // 重复启动应被拒绝
+    let again = csc(home.path(), &["proxy", "start", "--daemon"]);
+    assert!(!again.status.success());
+
+    let stop = csc(home.path(), &["proxy", "stop"]);
+    assert!(
+        stop.status.success(),
+        "stop failed: {}",
+        String::from_utf8_lossy(&stop.stderr)
+    );
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while is_alive(pid) && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    assert!(!is_alive(pid), "daemon still running after stop");
+    assert!(!pid_file.exists());
+
+    let status = csc(home.path(), &["proxy", "status"]);
+    assert!(String::from_utf8_lossy(&status.stdout).contains("未运行"));
+}
@@ -67,6 +67,17 @@ export function AutoFailoverConfigPanel({
         circuitTimeoutSeconds: formData.circuitTimeoutSeconds,
         circuitErrorRateThreshold: formData.circuitErrorRateThreshold,
         circuitMinRequests: formData.circuitMinRequests,
+        defaultModel: config.defaultModel,
+        benchmarkMaxKeysPerUrl: config.benchmarkMaxKeysPerUrl,
+        depletedRecheckSecs: config.depletedRecheckSecs,
+        overloadCooldownSecs: config.overloadCooldownSecs,
+        connectivityPenaltyMs: config.connectivityPenaltyMs,
+        connectivityTimeoutSecs: config.connectivityTimeoutSecs,
+        probeTimeoutSecs: config.probeTimeoutSecs,
+        circuitHalfOpenMaxProbes: config.circuitHalfOpenMaxProbes,
+        shadow: config.shadow,
+        probeMinIntervalMs: config.probeMinIntervalMs,
+        probeSkipRecentSuccessSecs: config.probeSkipRecentSuccessSecs,
       });
       toast.success(
         t("proxy.autoFailover.configSaved", "自动故障转移配置已保存"),
@@ -9,6 +9,18 @@ import {
 } from "@/components/ui/table";
 import { useProviderStats } from "@/lib/query/usage";
 
+function formatBytes(bytes: number): string {
+  const units = ["KB", "MB", "GB", "TB"];
+  if (bytes < 1024) return `${bytes}B`;
+  let value = bytes / 1024;
+  let unit = 0;
+  while (value >= 1024 && unit < units.length - 1) {
+    value /= 1024;
+    unit += 1;
+  }
+  return `${value.toFixed(1)}${units[unit]}`;
+}
+
 export function ProviderStatsTable() {
   const { t } = useTranslation();
   const { data: stats, isLoading } = useProviderStats();
@@ -38,13 +50,16 @@ export function ProviderStatsTable() {
             <TableHead className="text-right">
               {t("usage.avgLatency", "平均延迟")}
             </TableHead>
+            <TableHead className="text-right">
+              {t("usage.bandwidth", "流量")}
+            </TableHead>
           </TableRow>
         </TableHeader>
         <TableBody>
           {stats?.length === 0 ? (
             <TableRow>
               <TableCell
-                colSpan={6}
+                colSpan={7}
                 className="text-center text-muted-foreground"
               >
                 {t("usage.noData", "暂无数据")}
@@ -71,6 +86,9 @@ export function ProviderStatsTable() {
                 <TableCell className="text-right">
                   {stat.avgLatencyMs}ms
                 </TableCell>
+                <TableCell className="text-right">
+                  {formatBytes(stat.requestBytes + stat.responseBytes)}
+                </TableCell>
               </TableRow>
             ))
           )}
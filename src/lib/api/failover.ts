@@ -3,7 +3,13 @@ import type {
   ProviderHealth,
   CircuitBreakerConfig,
   CircuitBreakerStats,
+  ProviderCircuitBreakerStats,
   FailoverQueueItem,
+  FailoverQueueTier,
+  FailoverPause,
+  SupplierUrlPriority,
+  RoutingEvent,
+  RoutingEventKind,
 } from "@/types/proxy";
 
 export interface Provider {
@@ -39,6 +45,23 @@ export const failoverApi = {
     return invoke("reset_circuit_breaker", { providerId, appType });
   },
 
+  // 强制打开熔断器（持续 secs 秒，需代理运行中）
+  async forceOpenCircuitBreaker(
+    appType: string,
+    providerId: string,
+    secs: number,
+  ): Promise<void> {
+    return invoke("force_open_circuit_breaker", { appType, providerId, secs });
+  },
+
+  // 查询路由事件日志（熔断切换、冷却、故障转移切换等，按时间倒序）
+  async getRoutingEvents(
+    appType: string,
+    options: { kind?: RoutingEventKind; since?: number; limit?: number } = {},
+  ): Promise<RoutingEvent[]> {
+    return invoke("get_routing_events", { appType, ...options });
+  },
+
   // 获取熔断器配置
   async getCircuitBreakerConfig(): Promise<CircuitBreakerConfig> {
     return invoke("get_circuit_breaker_config");
@@ -59,6 +82,13 @@ export const failoverApi = {
     return invoke("get_circuit_breaker_stats", { providerId, appType });
   },
 
+  // 获取该应用下所有供应商的熔断器统计信息
+  async getAllCircuitBreakerStats(
+    appType: string,
+  ): Promise<ProviderCircuitBreakerStats[]> {
+    return invoke("get_all_circuit_breaker_stats", { appType });
+  },
+
   // ========== 故障转移队列 API（新） ==========
 
   // 获取故障转移队列
@@ -66,6 +96,33 @@ export const failoverApi = {
     return invoke("get_failover_queue", { appType });
   },
 
+  // 按优先级层级分组获取故障转移队列
+  async getFailoverQueueGrouped(appType: string): Promise<FailoverQueueTier[]> {
+    return invoke("get_failover_queue_grouped", { appType });
+  },
+
+  // 设置供应商是否在队列中（幂等），返回是否发生变化
+  async setFailoverQueueMembership(
+    appType: string,
+    providerId: string,
+    inQueue: boolean,
+  ): Promise<boolean> {
+    return invoke("set_failover_queue_membership", {
+      appType,
+      providerId,
+      inQueue,
+    });
+  },
+
+  // 把供应商移到指定优先级层级，返回是否发生变化
+  async moveProviderPriority(
+    appType: string,
+    providerId: string,
+    priority: number,
+  ): Promise<boolean> {
+    return invoke("move_provider_priority", { appType, providerId, priority });
+  },
+
   // 获取可添加到队列的供应商（不在队列中的）
   async getAvailableProvidersForFailover(appType: string): Promise<Provider[]> {
     return invoke("get_available_providers_for_failover", { appType });
@@ -96,4 +153,27 @@ export const failoverApi = {
   ): Promise<void> {
     return invoke("set_auto_failover_enabled", { appType, enabled });
   },
+
+  // 临时暂停故障转移 secs 秒（只使用当前供应商，到期自动恢复，需代理运行中）
+  async pauseFailover(appType: string, secs: number): Promise<FailoverPause> {
+    return invoke("pause_failover", { appType, secs });
+  },
+
+  // 提前恢复故障转移，返回此前是否处于暂停中
+  async resumeFailover(appType: string): Promise<boolean> {
+    return invoke("resume_failover", { appType });
+  },
+
+  // 获取所有 supplier 的首选 URL 列表
+  async getSupplierUrlPriorities(): Promise<SupplierUrlPriority[]> {
+    return invoke("get_supplier_url_priorities");
+  },
+
+  // 设置 supplier 的首选 URL 列表（空数组表示清除）
+  async setSupplierUrlPriority(
+    supplier: string,
+    urls: string[],
+  ): Promise<void> {
+    return invoke("set_supplier_url_priority", { supplier, urls });
+  },
 };
@@ -17,9 +17,60 @@ export interface ProviderSwitchEvent {
   providerId: string;
 }
 
+export type ProviderIssue =
+  | { kind: "missingBaseUrl"; providerId: string; providerName: string }
+  | {
+      kind: "unresolvedBaseUrl";
+      providerId: string;
+      providerName: string;
+      error: string;
+    }
+  | { kind: "missingApiKey"; providerId: string; providerName: string }
+  | { kind: "missingSortIndex"; providerId: string; providerName: string }
+  | {
+      kind: "crossFamilyMapping";
+      providerId: string;
+      providerName: string;
+      envKey: string;
+      value: string;
+      suggestion?: string | null;
+    };
+
+export interface KeyHolder {
+  id: string;
+  name: string;
+  sortIndex?: number | null;
+  inFailoverQueue: boolean;
+  baseUrl?: string | null;
+}
+
+export interface ProviderAuditReport {
+  appType: AppId;
+  duplicateKeys: { fingerprint: string; providers: KeyHolder[] }[];
+  issues: ProviderIssue[];
+}
+
+export interface ProviderTemplateApp {
+  baseUrls: string[];
+  authStyle: "authToken" | "apiKey";
+  models: Record<string, string>;
+  probeModel?: string;
+}
+
+export interface ProviderTemplate {
+  id: string;
+  name: string;
+  websiteUrl?: string;
+  apps: Partial<Record<AppId, ProviderTemplateApp>>;
+  builtin: boolean;
+}
+
 export const providersApi = {
-  async getAll(appId: AppId): Promise<Record<string, Provider>> {
-    return await invoke("get_providers", { app: appId });
+  async getAll(
+    appId: AppId,
+    windowSecs?: number,
+  ): Promise<Record<string, Provider>> {
+    return await invoke("get_providers", { app: appId, windowSecs });
   },
 
   async getCurrent(appId: AppId): Promise<string> {
@@ -38,8 +89,18 @@ export const providersApi = {
     return await invoke("delete_provider", { id, app: appId });
   },
 
-  async switch(id: string, appId: AppId): Promise<boolean> {
-    return await invoke("switch_provider", { id, app: appId });
+  async switch(
+    id: string,
+    appId: AppId,
+    options?: { verify?: boolean; full?: boolean; force?: boolean },
+  ): Promise<boolean> {
+    return await invoke("switch_provider", {
+      id,
+      app: appId,
+      verify: options?.verify,
+      full: options?.full,
+      force: options?.force,
+    });
   },
 
   async importDefault(appId: AppId): Promise<boolean> {
@@ -57,6 +118,14 @@ export const providersApi = {
     return await invoke("update_providers_sort_order", { updates, app: appId });
   },
 
+  async audit(appId: AppId): Promise<ProviderAuditReport> {
+    return await invoke("audit_providers", { app: appId });
+  },
+
+  async listTemplates(appId?: AppId): Promise<ProviderTemplate[]> {
+    return await invoke("list_provider_templates", { app: appId });
+  },
+
   async onSwitched(
     handler: (event: ProviderSwitchEvent) => void,
   ): Promise<UnlistenFn> {
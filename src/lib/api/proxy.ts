@@ -1,11 +1,15 @@
 import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
 import type {
   ProxyConfig,
   ProxyStatus,
   ProxyServerInfo,
+  RecentRequest,
   ProxyTakeoverStatus,
   GlobalProxyConfig,
   AppProxyConfig,
+  ConsistencyReport,
+  AppRoutingSnapshot,
 } from "@/types/proxy";
 
 export const proxyApi = {
@@ -26,6 +30,16 @@ export const proxyApi = {
     return invoke("get_proxy_status");
   },
 
+  // 获取最近的代理请求（最新在前）
+  async getRecentProxyRequests(): Promise<RecentRequest[]> {
+    return invoke("get_recent_proxy_requests");
+  },
+
+  // 获取各应用的路由快照：激活层级、各供应商锁定的 URL、缓存延迟与冷却状态（代理未运行时为空）
+  async getRoutingSnapshot(): Promise<AppRoutingSnapshot[]> {
+    return invoke("get_routing_snapshot");
+  },
+
   // 检查代理服务器是否正在运行
   async isProxyRunning(): Promise<boolean> {
     return invoke("is_proxy_running");
@@ -92,4 +106,13 @@ export const proxyApi = {
   async updateProxyConfigForApp(config: AppProxyConfig): Promise<void> {
     return invoke("update_proxy_config_for_app", { config });
   },
+
+  // 代理启动时的一致性检查结果（有问题的应用各一条，用于显示提示）
+  async onConsistencyReport(
+    handler: (report: ConsistencyReport) => void,
+  ): Promise<UnlistenFn> {
+    return await listen("proxy-consistency", (event) => {
+      handler(event.payload as ConsistencyReport);
+    });
+  },
 };
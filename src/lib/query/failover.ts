@@ -86,6 +86,18 @@ export function useCircuitBreakerStats(providerId: string, appType: string) {
   });
 }
 
+/**
+ * 获取该应用下所有供应商的熔断器统计信息
+ */
+export function useAllCircuitBreakerStats(appType: string) {
+  return useQuery({
+    queryKey: ["circuitBreakerStats", "all", appType],
+    queryFn: () => failoverApi.getAllCircuitBreakerStats(appType),
+    enabled: !!appType,
+    refetchInterval: 5000,
+  });
+}
+
 // ========== 故障转移队列 Hooks（新） ==========
 
 /**
@@ -7,6 +7,20 @@ const directorySchema = z
   .optional()
   .or(z.literal(""));
 
+const notificationSchema = z.object({
+  webhooks: z.array(z.string().url()),
+  desktop: z.boolean(),
+});
+
+const probeModeSchema = z.enum(["full", "models", "connectivity"]);
+
+const liveGuardModeSchema = z.enum(["reassert", "alert", "off"]);
+
+const healthCheckSchema = z.object({
+  intervalSecs: z.number().int().min(0),
+  zeroCost: z.boolean(),
+});
+
 export const settingsSchema = z.object({
   // 设备级 UI 设置
   showInTray: z.boolean(),
@@ -15,6 +29,7 @@ export const settingsSchema = z.object({
   skipClaudeOnboarding: z.boolean().optional(),
   launchOnStartup: z.boolean().optional(),
   language: z.enum(["en", "zh", "ja"]).optional(),
+  logLanguage: z.enum(["en", "zh"]).optional(),
 
   // 设备级目录覆盖
   claudeConfigDir: directorySchema.nullable().optional(),
@@ -25,6 +40,49 @@ export const settingsSchema = z.object({
   currentProviderClaude: z.string().optional(),
   currentProviderCodex: z.string().optional(),
   currentProviderGemini: z.string().optional(),
+
+  // 故障转移切换防抖（设备级）
+  failoverSwitchMinWins: z.number().int().min(1).optional(),
+  failoverSwitchDwellSecs: z.number().int().min(0).optional(),
+
+  // 故障恢复后自动切回首选供应商（设备级）
+  autoRestorePrimaryClaude: z.boolean().optional(),
+  autoRestorePrimaryCodex: z.boolean().optional(),
+  autoRestorePrimaryGemini: z.boolean().optional(),
+  restorePrimaryClaude: z.string().optional(),
+  restorePrimaryCodex: z.string().optional(),
+  restorePrimaryGemini: z.string().optional(),
+
+  // 故障通知（设备级）
+  notificationsClaude: notificationSchema.optional(),
+  notificationsCodex: notificationSchema.optional(),
+  notificationsGemini: notificationSchema.optional(),
+
+  // 后台健康检查（设备级）
+  healthCheckClaude: healthCheckSchema.optional(),
+  healthCheckCodex: healthCheckSchema.optional(),
+  healthCheckGemini: healthCheckSchema.optional(),
+
+  // 测速/探测方式（设备级）
+  probeModeClaude: probeModeSchema.optional(),
+  probeModeCodex: probeModeSchema.optional(),
+  probeModeGemini: probeModeSchema.optional(),
+
+  // Live 配置守护（设备级）
+  liveGuardClaude: liveGuardModeSchema.optional(),
+  liveGuardCodex: liveGuardModeSchema.optional(),
+  liveGuardGemini: liveGuardModeSchema.optional(),
+
+  // 请求捕获（设备级）
+  captureBodiesClaude: z.boolean().optional(),
+  captureBodiesCodex: z.boolean().optional(),
+  captureBodiesGemini: z.boolean().optional(),
+
+  // 数据库维护（设备级）
+  requestLogRetentionDays: z.number().int().min(1).optional(),
+
+  // 启动一致性检查（设备级）
+  autoFixConsistency: z.boolean().optional(),
 });
 
 export type SettingsFormData = z.infer<typeof settingsSchema>;
@@ -1,3 +1,5 @@
+import type { CircuitBreakerStats } from "@/types/proxy";
+
 export type ProviderCategory =
   | "official" // 官方
   | "cn_official" // 开源官方（原"国产官方"）
@@ -25,6 +27,19 @@ export interface Provider {
   iconColor?: string; // 图标颜色（Hex 格式，如 "#00A67E"）
   // 是否加入故障转移队列
   inFailoverQueue?: boolean;
+  // 代理运行中时的熔断器状态（仅列表接口返回）
+  circuitBreaker?: CircuitBreakerStats;
+  // 统计窗口内的请求数与成功率（仅列表接口返回，无流量时省略）
+  successStats?: ProviderSuccessRate;
+}
+
+// 供应商在统计窗口内的成功率
+export interface ProviderSuccessRate {
+  requests: number;
+  success: number;
+  // 0~1；无请求时为 null
+  successRate: number | null;
+  avgLatencyMs: number | null;
 }
 
 export interface AppConfig {
@@ -114,6 +129,8 @@ export interface Settings {
   launchOnStartup?: boolean;
   // 首选语言（可选，默认中文）
   language?: "en" | "zh" | "ja";
+  // 运行日志与 CLI 输出语言（可选，默认中文；环境变量 CC_SWITCH_LANG 优先）
+  logLanguage?: "en" | "zh";
 
   // ===== 设备级目录覆盖 =====
   // 覆盖 Claude Code 配置目录（可选）
@@ -130,6 +147,85 @@ export interface Settings {
   currentProviderCodex?: string;
   // 当前 Gemini 供应商 ID（优先于数据库 is_current）
   currentProviderGemini?: string;
+
+  // ===== 故障转移切换防抖（设备级）=====
+  // 同一供应商连续胜出多少次后才持久化切换（默认 3）
+  failoverSwitchMinWins?: number;
+  // 两次持久化切换之间的最短驻留时间（秒，默认 60）
+  failoverSwitchDwellSecs?: number;
+
+  // ===== 故障恢复后自动切回首选供应商（设备级）=====
+  autoRestorePrimaryClaude?: boolean;
+  autoRestorePrimaryCodex?: boolean;
+  autoRestorePrimaryGemini?: boolean;
+  // 首次自动切换前的当前供应商（待切回，由后端维护）
+  restorePrimaryClaude?: string;
+  restorePrimaryCodex?: string;
+  restorePrimaryGemini?: string;
+
+  // ===== 故障通知（设备级）=====
+  notificationsClaude?: NotificationSettings;
+  notificationsCodex?: NotificationSettings;
+  notificationsGemini?: NotificationSettings;
+
+  // ===== 后台健康检查（设备级）=====
+  healthCheckClaude?: HealthCheckSettings;
+  healthCheckCodex?: HealthCheckSettings;
+  healthCheckGemini?: HealthCheckSettings;
+
+  // ===== 测速/探测方式（设备级）=====
+  probeModeClaude?: ProbeMode;
+  probeModeCodex?: ProbeMode;
+  probeModeGemini?: ProbeMode;
+
+  // ===== Live 配置守护（设备级）=====
+  liveGuardClaude?: LiveGuardMode;
+  liveGuardCodex?: LiveGuardMode;
+  liveGuardGemini?: LiveGuardMode;
+
+  // ===== 请求捕获（设备级，默认关闭）=====
+  // 保存脱敏/截断后的请求体，供 csc replay 重放
+  captureBodiesClaude?: boolean;
+  captureBodiesCodex?: boolean;
+  captureBodiesGemini?: boolean;
+
+  // ===== 数据库维护（设备级）=====
+  // 请求日志保留天数（csc db maintain 清理更早的日志，默认 30）
+  requestLogRetentionDays?: number;
+
+  // ===== 上游错误响应（设备级）=====
+  // 上游错误响应体最多读取的字节数（超出部分截断并注明总大小，默认 64KB）
+  errorBodyMaxBytes?: number;
+
+  // ===== 启动一致性检查（设备级，默认关闭）=====
+  // 代理启动时自动修复简单的不一致（如把当前供应商加入故障转移队列）
+  autoFixConsistency?: boolean;
+
+  // ===== 模型家族（设备级）=====
+  // 自定义家族规则：模型名模式（子串，不区分大小写）→ 家族名，先于内置规则生效
+  modelFamilyOverrides?: Record<string, string>;
+}
+
+// URL 探测方式：full=真实问答请求，models=仅请求 /v1/models，connectivity=仅连通性
+export type ProbeMode = "full" | "models" | "connectivity";
+
+// 接管中的 live 配置被外部改写时：reassert=重新写入接管配置（默认），alert=仅告警，off=不监听
+export type LiveGuardMode = "reassert" | "alert" | "off";
+
+// 单个应用的后台健康检查设置
+export interface HealthCheckSettings {
+  // 检查间隔（秒），0 表示关闭
+  intervalSecs: number;
+  // 零成本模式：仅做连通性探测，不发送问答请求
+  zeroCost: boolean;
+}
+
+// 单个应用的故障通知设置
+export interface NotificationSettings {
+  // Webhook 地址列表（POST JSON）
+  webhooks: string[];
+  // 是否发送系统桌面通知
+  desktop: boolean;
 }
 
 // MCP 服务器连接参数（宽松：允许扩展字段）
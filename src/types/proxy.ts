@@ -27,6 +27,49 @@ export interface ProxyStatus {
   last_error: string | null;
   failover_count: number;
   active_targets?: ActiveTarget[];
+  live_reassert_count?: number;
+  last_live_guard_event?: LiveGuardEvent | null;
+  db_degraded?: boolean; // 数据库锁定/损坏，使用最近一次的供应商列表
+  db_degraded_apps?: string[]; // 正在使用兜底供应商列表的应用
+  read_only?: boolean; // 只读模式，不写数据库
+  failover_paused?: FailoverPause[]; // 临时暂停故障转移的应用
+  update_check?: UpdateCheckResult | null; // 最近一次新版本检测结果
+}
+
+// 暂停中的故障转移：期间只使用当前供应商，到期自动恢复
+export interface FailoverPause {
+  app_type: string;
+  until: number; // Unix 秒
+  remaining_secs: number;
+}
+
+// 新版本检测结果（只检测，不自动下载）
+export interface UpdateCheckResult {
+  current_version: string;
+  latest_version: string;
+  update_available: boolean;
+  release_url?: string | null;
+  checked_at: number; // Unix 秒
+}
+
+// 接管中的 live 配置被外部改写时的处理记录（事件名：live-config-modified）
+export interface LiveGuardEvent {
+  app_type: string;
+  action: "reasserted" | "alerted" | "failed";
+  at: string;
+  error?: string | null;
+}
+
+export interface RecentRequest {
+  at: string;
+  app_type: string;
+  path: string;
+  provider_id: string | null;
+  provider_name: string | null;
+  model: string | null;
+  status: number;
+  latency_ms: number;
+  startup_test: boolean;
 }
 
 export interface ActiveTarget {
@@ -35,6 +78,26 @@ export interface ActiveTarget {
   provider_id: string;
 }
 
+// 路由快照中的单个供应商（同一供应商在不同层级各一条）
+export interface SupplierRoutingState {
+  priority: number;
+  supplier: string;
+  current_url: string | null; // 路由当前锁定的 base_url
+  latency_ms: number | null; // 当前 URL 的缓存延迟
+  latency_age_secs: number | null;
+  cooldown_reason: string | null; // overloaded / no-usable-url / all-keys-failed
+  cooldown_remaining_secs: number | null;
+  suspect_urls: string[]; // 疑似失效的 URL
+  keep_warm_age_secs?: number | null; // 当前 URL 最近一次连接保温距今（秒）
+}
+
+// 单个应用的路由快照（查询时生成）
+export interface AppRoutingSnapshot {
+  app_type: string;
+  active_priority_level: number | null;
+  suppliers: SupplierRoutingState[];
+}
+
 export interface ProxyServerInfo {
   address: string;
   port: number;
@@ -56,6 +119,9 @@ export interface ProviderHealth {
   last_failure_at: string | null;
   last_error: string | null;
   updated_at: string;
+  // 余额耗尽（HTTP 402 等）的记录时间与上游提示
+  depleted_at?: string | null;
+  depleted_message?: string | null;
 }
 
 // 熔断器相关类型
@@ -65,6 +131,8 @@ export interface CircuitBreakerConfig {
   timeoutSeconds: number;
   errorRateThreshold: number;
   minRequests: number;
+  // 半开状态下的并发探测数（默认 1）
+  halfOpenMaxProbes?: number;
 }
 
 export type CircuitState = "closed" | "open" | "half_open";
@@ -75,6 +143,48 @@ export interface CircuitBreakerStats {
   consecutiveSuccesses: number;
   totalRequests: number;
   failedRequests: number;
+  // 自上次关闭以来的错误率（0-1）
+  errorRate: number;
+  // 最近 60 秒滑动窗口内的请求数与错误率（0-1）
+  windowRequests: number;
+  windowErrorRate: number;
+  // 因熔断打开 / 半开探测名额用尽而拒绝的请求数（累计）
+  rejectedOpen: number;
+  rejectedHalfOpen: number;
+  // 是否处于手动强制熔断状态及截止时间（Unix 秒）
+  forced?: boolean;
+  forcedUntil?: number | null;
+  // 最近一次状态切换时间（Unix 秒）
+  lastTransitionAt?: number | null;
+}
+
+export interface ProviderCircuitBreakerStats extends CircuitBreakerStats {
+  providerId: string;
+}
+
+// 路由事件类型
+export type RoutingEventKind =
+  | "breaker_open"
+  | "breaker_half_open"
+  | "breaker_close"
+  | "breaker_forced"
+  | "cooldown"
+  | "suspect"
+  | "failover_switch"
+  | "quota_exclusion"
+  | "all_failed"
+  | "model_warmup_failed"
+  | "bandwidth_alert";
+
+// 路由事件日志（熔断切换、冷却、故障转移切换等）
+export interface RoutingEvent {
+  id: number;
+  createdAt: number; // Unix 秒
+  appType: string;
+  kind: RoutingEventKind;
+  providerId: string | null;
+  supplier: string | null;
+  detail: Record<string, unknown> | null;
 }
 
 // 供应商健康状态枚举
@@ -110,12 +220,26 @@ export interface FailoverQueueItem {
   sortIndex?: number;
 }
 
+// 故障转移队列的一个优先级层级（sortIndex 从小到大）
+export interface FailoverQueueTier {
+  priority: number;
+  providers: FailoverQueueItem[];
+}
+
+// supplier 首选 URL 列表（测速选 URL 时优先命中）
+export interface SupplierUrlPriority {
+  supplier: string;
+  urls: string[];
+}
+
 // 全局代理配置（统一字段，三行镜像）
 export interface GlobalProxyConfig {
   proxyEnabled: boolean;
   listenAddress: string;
   listenPort: number;
   enableLogging: boolean;
+  // 只读模式：代理运行期间不写数据库（重启代理后生效）
+  readOnly?: boolean;
 }
 
 // 应用级代理配置（每个 app 独立）
@@ -132,4 +256,65 @@ export interface AppProxyConfig {
   circuitTimeoutSeconds: number;
   circuitErrorRateThreshold: number;
   circuitMinRequests: number;
+  // 客户端未指定模型（缺失或 "auto"）时使用的默认模型
+  defaultModel?: string | null;
+  // 测速时每个 URL 最多尝试的 key 数
+  benchmarkMaxKeysPerUrl?: number;
+  // 余额耗尽后跳过的时长（秒）
+  depletedRecheckSecs?: number;
+  // 仅因满载/限流无可用 URL 时的冷却时长（秒）
+  overloadCooldownSecs?: number;
+  // 仅连通性可用 URL 的排序惩罚（毫秒）
+  connectivityPenaltyMs?: number;
+  // 连通性探测超时（秒）
+  connectivityTimeoutSecs?: number;
+  // 全链路/模型列表探测超时（秒）
+  probeTimeoutSecs?: number;
+  // 熔断器半开状态下的并发探测数
+  circuitHalfOpenMaxProbes?: number;
+  // 影子流量：按比例把非流式请求镜像到指定供应商（仅评估，不影响响应）
+  shadow?: ShadowConfig | null;
+  // 同一供应商相邻两次探测的最小间隔（毫秒）
+  probeMinIntervalMs?: number;
+  // 近期（秒）有真实成功请求的供应商跳过探测；0 表示不跳过
+  probeSkipRecentSuccessSecs?: number;
+  // 测速/探测请求使用的模型；供应商 settingsConfig.probeModel 优先
+  probeModel?: string | null;
+  // 测速时同时测试的 supplier 数
+  benchmarkConcurrency?: number;
+  // 连接保温：定期 HEAD 各供应商当前 URL，保持连接活跃
+  keepWarm?: boolean;
+  // 连接保温间隔（秒），默认 55
+  keepWarmIntervalSecs?: number;
+  // 选路策略：default 按层级轮询/测速结果；strict-primary 只用主供应商，熔断后才转移
+  selectionPolicy?: "default" | "strict-primary";
+}
+
+export interface ShadowConfig {
+  providerId: string;
+  // 采样比例（0-100）
+  samplePercent: number;
+}
+
+// 启动一致性检查（proxy-consistency 事件，每个有问题的应用一条）
+export type ConsistencySeverity = "warning" | "error";
+
+export interface ConsistencyFinding {
+  kind:
+    | "currentNotInQueue"
+    | "emptyQueue"
+    | "queuedProviderIncomplete"
+    | "noUsableQueuedProvider"
+    | "noCurrentProvider"
+    | "currentProviderIncomplete";
+  severity: ConsistencySeverity;
+  message: string;
+  suggestion: string; // 修复建议
+  providerId?: string;
+  fixed: boolean; // 已自动修复
+}
+
+export interface ConsistencyReport {
+  appType: string;
+  findings: ConsistencyFinding[];
 }
@@ -26,6 +26,9 @@ export interface RequestLog {
   latencyMs: number;
   firstTokenMs?: number;
   durationMs?: number;
+  upstreamMs?: number;
+  requestBytes: number;
+  responseBytes: number;
   statusCode: number;
   errorMessage?: string;
   createdAt: number;
@@ -76,6 +79,8 @@ export interface ProviderStats {
   totalCost: string;
   successRate: number;
   avgLatencyMs: number;
+  requestBytes: number;
+  responseBytes: number;
 }
 
 export interface ModelStats {
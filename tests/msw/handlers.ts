@@ -279,6 +279,11 @@ export const handlers = [
   ),
   http.post(`${TAURI_ENDPOINT}/add_to_failover_queue`, () => success(true)),
   http.post(`${TAURI_ENDPOINT}/remove_from_failover_queue`, () => success(true)),
+  http.post(`${TAURI_ENDPOINT}/get_failover_queue_grouped`, () => success([])),
+  http.post(`${TAURI_ENDPOINT}/set_failover_queue_membership`, () =>
+    success(true),
+  ),
+  http.post(`${TAURI_ENDPOINT}/move_provider_priority`, () => success(true)),
   http.post(`${TAURI_ENDPOINT}/reorder_failover_queue`, () => success(true)),
   http.post(`${TAURI_ENDPOINT}/set_failover_item_enabled`, () => success(true)),
 